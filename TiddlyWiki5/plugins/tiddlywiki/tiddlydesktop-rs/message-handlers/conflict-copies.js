@@ -0,0 +1,216 @@
+/*\
+title: $:/plugins/tiddlywiki/tiddlydesktop-rs/message-handlers/conflict-copies.js
+type: application/javascript
+module-type: startup
+
+Message handler for reviewing and merging sync tool conflict copies of a wiki
+
+\*/
+(function(){
+
+/*jslint node: true, browser: true */
+/*global $tw: false */
+"use strict";
+
+exports.name = "tiddlydesktop-conflict-copies-handler";
+exports.after = ["startup"];
+exports.synchronous = true;
+
+exports.startup = function() {
+    // Only run in browser with Tauri
+    if (typeof window === "undefined" || !window.__TAURI__) {
+        return;
+    }
+
+    var invoke = window.__TAURI__.core.invoke;
+
+    function lingo(key) {
+        return $tw.wiki.renderText("text/plain", "text/vnd.tiddlywiki", "<<td-lingo " + key + ">>");
+    }
+
+    function formatModified(modified) {
+        var date = modified && $tw.utils.parseDate(modified);
+        return date ? date.toLocaleString() : "-";
+    }
+
+    // Update the conflict badge of a wiki in the list
+    function updateConflictCount(path) {
+        invoke("get_conflict_copies").then(function(conflicts) {
+            var found = conflicts.filter(function(c) { return c.wikiPath === path; })[0];
+            $tw.wiki.filterTiddlers("[prefix[$:/temp/tiddlydesktop-rs/wikis/]]").forEach(function(title) {
+                var tiddler = $tw.wiki.getTiddler(title);
+                if (tiddler && tiddler.fields.path === path) {
+                    $tw.wiki.setText(title, "conflict_count", null, found ? String(found.copies.length) : "0");
+                }
+            });
+        }).catch(function(err) {
+            console.error("[TiddlyDesktop] Failed to look for conflict copies:", err);
+        });
+    }
+
+    function merge(path, copy, titles, retireCopy) {
+        return invoke("merge_conflict_copy", {
+            wikiPath: path,
+            copyPath: copy.path,
+            titles: titles,
+            retireCopy: retireCopy
+        }).then(function(count) {
+            console.log("[TiddlyDesktop] Merged", count, "tiddlers from", copy.path);
+            updateConflictCount(path);
+            if (titles.length > 0) {
+                window.__TAURI__.dialog.message(count + " " + lingo("Conflicts/Merged"), { title: lingo("Conflicts/Title"), kind: "info" });
+            }
+        }).catch(function(err) {
+            console.error("[TiddlyDesktop] Merge failed:", err);
+            window.__TAURI__.dialog.message("Merge failed: " + err, { title: "Error", kind: "error" });
+        });
+    }
+
+    function renderDiff(hunks) {
+        var pre = document.createElement("pre");
+        pre.className = "td-conflict-diff";
+        hunks.forEach(function(hunk, index) {
+            if (index > 0) {
+                pre.appendChild(document.createTextNode("…\n"));
+            }
+            hunk.lines.forEach(function(line) {
+                var span = document.createElement("span");
+                var prefix = line.op === "insert" ? "+ " : (line.op === "delete" ? "- " : "  ");
+                if (line.op !== "equal") {
+                    span.className = "td-diff-" + line.op;
+                }
+                span.textContent = prefix + line.text + (/\n$/.test(line.text) ? "" : "\n");
+                pre.appendChild(span);
+            });
+        });
+        return pre;
+    }
+
+    // Dialog listing the differing tiddlers; checked ones are taken from the copy
+    function showMergeDialog(path, filename, copy, comparison) {
+        var overlay = document.createElement("div");
+        overlay.className = "td-conflict-overlay";
+        var dialog = document.createElement("div");
+        dialog.className = "td-conflict-dialog";
+
+        var heading = document.createElement("h2");
+        heading.textContent = lingo("Conflicts/Title") + ": " + copy.filename;
+        var hint = document.createElement("p");
+        hint.className = "td-conflict-hint";
+        hint.textContent = filename + " (" + copy.tool + ", " + new Date(copy.modified).toLocaleString() + "). " + lingo("Conflicts/Hint");
+        dialog.appendChild(heading);
+        dialog.appendChild(hint);
+
+        var list = document.createElement("div");
+        list.className = "td-conflict-list";
+        var checkboxes = [];
+        comparison.conflicts.forEach(function(conflict) {
+            var item = document.createElement("details");
+            item.className = "td-conflict-item";
+            var summary = document.createElement("summary");
+            var checkbox = document.createElement("input");
+            checkbox.type = "checkbox";
+            // Tiddlers only in the wiki can't be taken from the copy
+            checkbox.disabled = conflict.kind === "removed";
+            checkbox.checked = conflict.kind !== "removed" && conflict.copyIsNewer;
+            checkbox.addEventListener("click", function(e) { e.stopPropagation(); });
+            if (!checkbox.disabled) {
+                checkboxes.push({ checkbox: checkbox, title: conflict.title });
+            }
+            var label = document.createElement("span");
+            label.textContent = " " + conflict.title;
+            var kind = document.createElement("span");
+            kind.className = "td-conflict-kind";
+            kind.textContent = lingo("Conflicts/" + conflict.kind.charAt(0).toUpperCase() + conflict.kind.slice(1));
+            summary.appendChild(checkbox);
+            summary.appendChild(label);
+            summary.appendChild(kind);
+            item.appendChild(summary);
+
+            var meta = document.createElement("div");
+            meta.className = "td-conflict-meta";
+            meta.textContent = filename + ": " + formatModified(conflict.wikiModified) + " / " + copy.filename + ": " + formatModified(conflict.copyModified) +
+                (conflict.changedFields.length > 0 ? " - " + lingo("Conflicts/Fields") + " " + conflict.changedFields.join(", ") : "");
+            item.appendChild(meta);
+            if (conflict.diff.length > 0) {
+                item.appendChild(renderDiff(conflict.diff));
+            }
+            list.appendChild(item);
+        });
+        dialog.appendChild(list);
+
+        var footer = document.createElement("div");
+        footer.className = "td-conflict-footer";
+        var retireLabel = document.createElement("label");
+        var retireCheckbox = document.createElement("input");
+        retireCheckbox.type = "checkbox";
+        retireCheckbox.checked = true;
+        retireLabel.appendChild(retireCheckbox);
+        retireLabel.appendChild(document.createTextNode(" " + lingo("Conflicts/RetireCopy")));
+        var buttons = document.createElement("div");
+        buttons.className = "td-conflict-buttons";
+        var cancelBtn = document.createElement("button");
+        cancelBtn.className = "td-button";
+        cancelBtn.textContent = lingo("Buttons/Cancel") || "Cancel";
+        var mergeBtn = document.createElement("button");
+        mergeBtn.className = "td-button td-button-open";
+        mergeBtn.textContent = lingo("Buttons/Merge") || "Merge";
+        buttons.appendChild(cancelBtn);
+        buttons.appendChild(mergeBtn);
+        footer.appendChild(retireLabel);
+        footer.appendChild(buttons);
+        dialog.appendChild(footer);
+
+        overlay.appendChild(dialog);
+        document.body.appendChild(overlay);
+
+        function cleanup() { if (overlay.parentNode) overlay.parentNode.removeChild(overlay); }
+        cancelBtn.addEventListener("click", cleanup);
+        overlay.addEventListener("click", function(e) { if (e.target === overlay) cleanup(); });
+        mergeBtn.addEventListener("click", function() {
+            var titles = checkboxes.filter(function(c) { return c.checkbox.checked; }).map(function(c) { return c.title; });
+            cleanup();
+            merge(path, copy, titles, retireCheckbox.checked);
+        });
+        mergeBtn.focus();
+    }
+
+    $tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-resolve-conflicts", function(event) {
+        var path = event.paramObject && event.paramObject.path;
+        var filename = (event.paramObject && event.paramObject.filename) || path;
+        if (!path) {
+            return;
+        }
+
+        invoke("is_wiki_open", { path: path }).then(function(isOpen) {
+            if (isOpen) {
+                window.__TAURI__.dialog.message(lingo("Conflicts/CloseWiki"), { title: lingo("Conflicts/Title"), kind: "warning" });
+                return;
+            }
+            return invoke("get_conflict_copies").then(function(conflicts) {
+                var found = conflicts.filter(function(c) { return c.wikiPath === path; })[0];
+                if (!found) {
+                    updateConflictCount(path);
+                    return;
+                }
+                // Newest copy first; the badge shows how many remain afterwards
+                var copy = found.copies[0];
+                return invoke("compare_conflict_copy", { wikiPath: path, copyPath: copy.path }).then(function(comparison) {
+                    if (comparison.identical) {
+                        return window.__TAURI__.dialog.confirm(lingo("Conflicts/Identical"), { title: lingo("Conflicts/Title"), kind: "info" }).then(function(confirmed) {
+                            if (confirmed) {
+                                merge(path, copy, [], true);
+                            }
+                        });
+                    }
+                    showMergeDialog(path, filename, copy, comparison);
+                });
+            });
+        }).catch(function(err) {
+            console.error("[TiddlyDesktop] Failed to compare conflict copy:", err);
+            window.__TAURI__.dialog.message("Failed to compare conflict copy: " + err, { title: "Error", kind: "error" });
+        });
+    });
+};
+
+})();
@@ -34,6 +34,53 @@ exports.startup = function() {
         var toFolder = !isFolder; // If it's a folder, convert to file; if it's a file, convert to folder
         var isAndroid = $tw.wiki.getTiddlerText("$:/temp/tiddlydesktop-rs/is-android") === "yes";
 
+        // Human-readable summary of a pre-flight report
+        function describeReport(report) {
+            var lines = [
+                (toFolder ? "Convert to a folder wiki:" : "Convert to a single-file wiki:"),
+                report.destination,
+                "",
+                report.tiddlerCount + " tiddlers (" + report.systemTiddlerCount + " system), " + report.plugins.length + " plugins"
+            ];
+            if (report.attachments.external > 0 || report.attachments.embeddedBinary > 0) {
+                lines.push("Attachments: " + report.attachments.external + " external (" + report.attachments.relative + " relative), " +
+                    report.attachments.embeddedBinary + " embedded");
+            }
+            if (report.pluginIssues.length > 0) {
+                lines.push("", "Plugins that won't survive the conversion:");
+                report.pluginIssues.forEach(function(issue) {
+                    lines.push("- " + issue.title + ": " + issue.reason);
+                });
+            }
+            if (report.warnings.length > 0) {
+                lines.push("");
+                report.warnings.forEach(function(warning) {
+                    lines.push("Warning: " + warning);
+                });
+            }
+            return lines.join("\n");
+        }
+
+        // Explain why a converted wiki failed verification
+        function describeVerification(report) {
+            var verification = report.verification;
+            var lines = ["The converted wiki could not be verified. Keep using the original wiki.", ""];
+            if (!verification.opened) {
+                lines.push("TiddlyWiki could not load the converted wiki.");
+            }
+            lines.push("Tiddlers: " + verification.convertedTiddlers + " converted, " + verification.expectedTiddlers + " expected");
+            if (verification.missingTitles.length > 0) {
+                lines.push("", "Missing:");
+                verification.missingTitles.forEach(function(title) {
+                    lines.push("- " + title);
+                });
+            }
+            report.warnings.forEach(function(warning) {
+                lines.push("Warning: " + warning);
+            });
+            return lines.join("\n");
+        }
+
         // Function to perform the conversion
         function doConversion(destPath) {
             if (!destPath) {
@@ -52,12 +99,18 @@ exports.startup = function() {
                 sourcePath: sourcePath,
                 destPath: destPath,
                 toFolder: toFolder
-            }).then(function() {
-                console.log("[TiddlyDesktop] Conversion successful");
+            }).then(function(report) {
                 // Remove converting indicator
                 $tw.wiki.deleteTiddler("$:/temp/tiddlydesktop-rs/converting");
+                // Desktop verifies the converted wiki; only open it if verification passed
+                if (report && report.verification && !report.verification.passed) {
+                    console.warn("[TiddlyDesktop] Conversion failed verification:", report.verification);
+                    window.__TAURI__.dialog.message(describeVerification(report), { title: "Conversion not verified", kind: "warning" });
+                    return;
+                }
+                console.log("[TiddlyDesktop] Conversion successful");
                 // Auto-open the converted wiki (open-path auto-detects file vs folder)
-                $tw.rootWidget.dispatchEvent({type: "tm-tiddlydesktop-rs-open-path", param: destPath});
+                $tw.rootWidget.dispatchEvent({type: "tm-tiddlydesktop-rs-open-path", param: (report && report.destination) || destPath});
             }).catch(function(err) {
                 console.error("[TiddlyDesktop] Conversion failed:", err);
                 // Remove converting indicator
@@ -66,6 +119,37 @@ exports.startup = function() {
             });
         }
 
+        // Desktop: dry run first and let the user review the pre-flight report
+        function reviewConversion(destPath) {
+            if (!destPath) {
+                return; // User cancelled
+            }
+            $tw.wiki.addTiddler({
+                title: "$:/temp/tiddlydesktop-rs/converting",
+                text: "yes"
+            });
+            window.__TAURI__.core.invoke("convert_wiki", {
+                sourcePath: sourcePath,
+                destPath: destPath,
+                toFolder: toFolder,
+                dryRun: true
+            }).then(function(report) {
+                $tw.wiki.deleteTiddler("$:/temp/tiddlydesktop-rs/converting");
+                return window.__TAURI__.dialog.confirm(describeReport(report), {
+                    title: "Convert wiki",
+                    kind: (report.pluginIssues.length > 0 || report.warnings.length > 0) ? "warning" : "info"
+                }).then(function(confirmed) {
+                    if (confirmed) {
+                        doConversion(destPath);
+                    }
+                });
+            }).catch(function(err) {
+                console.error("[TiddlyDesktop] Conversion pre-flight failed:", err);
+                $tw.wiki.deleteTiddler("$:/temp/tiddlydesktop-rs/converting");
+                window.__TAURI__.dialog.message("Conversion failed: " + err, { title: "Error", kind: "error" });
+            });
+        }
+
         if (isAndroid) {
             // Android: Use SAF-specific pickers
             if (toFolder) {
@@ -134,7 +218,7 @@ exports.startup = function() {
                 title: toFolder ? "Save folder wiki as..." : "Save single-file wiki as...",
                 filters: toFolder ? [] : [{ name: "TiddlyWiki", extensions: ["html"] }]
             }).then(function(destPath) {
-                doConversion(destPath);
+                reviewConversion(destPath);
             }).catch(function(err) {
                 console.error("[TiddlyDesktop] Save dialog error:", err);
             });
@@ -0,0 +1,130 @@
+/*\
+title: $:/plugins/tiddlywiki/tiddlydesktop-rs/message-handlers/js-errors.js
+type: application/javascript
+module-type: startup
+
+Message handler for showing and clearing the JavaScript errors a wiki reported
+
+\*/
+(function(){
+
+/*jslint node: true, browser: true */
+/*global $tw: false */
+"use strict";
+
+exports.name = "tiddlydesktop-js-errors-handler";
+exports.after = ["startup"];
+exports.synchronous = true;
+
+exports.startup = function() {
+    // Only run in browser with Tauri
+    if (typeof window === "undefined" || !window.__TAURI__) {
+        return;
+    }
+
+    var invoke = window.__TAURI__.core.invoke;
+
+    function lingo(key) {
+        return $tw.wiki.renderText("text/plain", "text/vnd.tiddlywiki", "<<td-lingo " + key + ">>");
+    }
+
+    function location(error) {
+        if (!error.source) {
+            return "";
+        }
+        return error.source + (error.line ? ":" + error.line + (error.column ? ":" + error.column : "") : "");
+    }
+
+    // Dialog listing the errors; each expands to its source and stack
+    function showErrorsDialog(path, filename, errors) {
+        var overlay = document.createElement("div");
+        overlay.className = "td-conflict-overlay";
+        var dialog = document.createElement("div");
+        dialog.className = "td-conflict-dialog td-js-errors-dialog";
+
+        var heading = document.createElement("h2");
+        heading.textContent = lingo("JsErrors/Title") + ": " + filename;
+        var hint = document.createElement("p");
+        hint.className = "td-conflict-hint";
+        hint.textContent = lingo("JsErrors/Hint");
+        dialog.appendChild(heading);
+        dialog.appendChild(hint);
+
+        var list = document.createElement("div");
+        list.className = "td-conflict-list";
+        errors.forEach(function(error) {
+            var item = document.createElement("details");
+            item.className = "td-conflict-item td-js-error-item";
+            var summary = document.createElement("summary");
+            summary.textContent = error.message;
+            var kind = document.createElement("span");
+            kind.className = "td-conflict-kind";
+            kind.textContent = error.kind;
+            summary.appendChild(kind);
+            item.appendChild(summary);
+
+            var meta = document.createElement("div");
+            meta.className = "td-conflict-meta";
+            meta.textContent = new Date(error.time).toLocaleString() + " - " + error.window + (error.source ? " - " + location(error) : "");
+            item.appendChild(meta);
+            if (error.stack) {
+                var stack = document.createElement("pre");
+                stack.textContent = error.stack;
+                item.appendChild(stack);
+            }
+            list.appendChild(item);
+        });
+        dialog.appendChild(list);
+
+        var footer = document.createElement("div");
+        footer.className = "td-conflict-footer";
+        var buttons = document.createElement("div");
+        buttons.className = "td-conflict-buttons";
+        var closeBtn = document.createElement("button");
+        closeBtn.className = "td-button";
+        closeBtn.textContent = lingo("Buttons/Close") || "Close";
+        var clearBtn = document.createElement("button");
+        clearBtn.className = "td-button td-button-open";
+        clearBtn.textContent = lingo("Buttons/ClearErrors") || "Clear";
+        buttons.appendChild(closeBtn);
+        buttons.appendChild(clearBtn);
+        footer.appendChild(buttons);
+        dialog.appendChild(footer);
+
+        overlay.appendChild(dialog);
+        document.body.appendChild(overlay);
+
+        function cleanup() { if (overlay.parentNode) overlay.parentNode.removeChild(overlay); }
+        closeBtn.addEventListener("click", cleanup);
+        overlay.addEventListener("click", function(e) { if (e.target === overlay) cleanup(); });
+        clearBtn.addEventListener("click", function() {
+            cleanup();
+            invoke("clear_js_errors", { path: path }).then(function() {
+                $tw.wiki.filterTiddlers("[prefix[$:/temp/tiddlydesktop-rs/wikis/]]").forEach(function(title) {
+                    var tiddler = $tw.wiki.getTiddler(title);
+                    if (tiddler && tiddler.fields.path === path) {
+                        $tw.wiki.setText(title, "js_error_count", null, "0");
+                    }
+                });
+            }).catch(function(err) {
+                console.error("[TiddlyDesktop] Failed to clear the JS errors:", err);
+            });
+        });
+        closeBtn.focus();
+    }
+
+    $tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-show-js-errors", function(event) {
+        var path = event.paramObject && event.paramObject.path;
+        var filename = (event.paramObject && event.paramObject.filename) || path;
+        if (!path) {
+            return;
+        }
+        invoke("get_js_errors", { path: path, limit: 100 }).then(function(errors) {
+            showErrorsDialog(path, filename, errors || []);
+        }).catch(function(err) {
+            window.__TAURI__.dialog.message("Failed to read the JS errors: " + err, { title: "Error", kind: "error" });
+        });
+    });
+};
+
+})();
@@ -497,6 +497,11 @@ exports.startup = function(callback) {
 			if (existingEntry.relay_room && !entry.relay_room) {
 				entry.relay_room = existingEntry.relay_room;
 			}
+			entry.scheduled_backup = existingEntry.scheduled_backup;
+			// Preserve the custom icon, accent color and emoji
+			entry.custom_icon = existingEntry.custom_icon;
+			entry.accent_color = existingEntry.accent_color;
+			entry.emoji = existingEntry.emoji;
 		}
 		// Remove if already exists
 		entries = entries.filter(function(e) { return e.path !== entry.path; });
@@ -556,18 +561,30 @@ exports.startup = function(callback) {
 				path: entry.path,
 				display_path: entry.display_path || entry.path,
 				filename: entry.filename,
-				favicon: entry.favicon || "",
+				favicon: entry.custom_icon || entry.favicon || "",
+				accent_color: entry.accent_color || "",
+				emoji: entry.emoji || "",
 				is_folder: entry.is_folder ? "true" : "false",
 				backups_enabled: entry.backups_enabled ? "true" : "false",
 				backup_dir: entry.backup_dir || "",
 				backup_dir_display: entry.backup_dir ? getDisplayPath(entry.backup_dir) : "",
 				backup_count: entry.backup_count !== undefined ? String(entry.backup_count) : "",
+				scheduled_backup_interval: entry.scheduled_backup ? formatBackupInterval(entry.scheduled_backup.interval_minutes) : "",
+				scheduled_backup_keep: entry.scheduled_backup && entry.scheduled_backup.keep !== null && entry.scheduled_backup.keep !== undefined ? String(entry.scheduled_backup.keep) : "",
+				scheduled_backup_max_age: entry.scheduled_backup && entry.scheduled_backup.max_age_days ? entry.scheduled_backup.max_age_days + " d" : "",
 				group: entry.group || "",
 				sync_enabled: entry.sync_enabled ? "true" : "false",
 				sync_id: entry.sync_id || "",
 				sync_peers: entry.sync_peers ? JSON.stringify(entry.sync_peers) : "[]",
 				relay_room: entry.relay_room || "",
 				sync_mode: entry.sync_mode || "",
+				confirm_remote_deletions: entry.confirm_remote_deletions ? "true" : "false",
+				sync_schedule: formatSyncSchedule(entry.sync_schedule),
+				sync_priority_high: entry.sync_priority ? entry.sync_priority.high || "" : "",
+				sync_priority_bulk: entry.sync_priority ? entry.sync_priority.bulk || "" : "",
+				sync_priority_minutes: entry.sync_priority && entry.sync_priority.bulk_minutes ? String(entry.sync_priority.bulk_minutes) : "",
+				hotkey: entry.hotkey || "",
+				missing: entry.missing ? "true" : "false",
 				needs_reauth: "checking", // Will be updated by permission check on Android
 				text: ""
 			});
@@ -583,6 +600,18 @@ exports.startup = function(callback) {
 			entries.forEach(function(entry, index) {
 				$tw.wiki.setText("$:/temp/tiddlydesktop-rs/wikis/" + index, "needs_reauth", null, "no");
 			});
+			checkWikiStorage();
+			checkFolderSnapshots();
+			checkDownloadConfigs();
+			checkGeolocationPermissions();
+			checkSerialPermissions();
+			checkMqttPermissions();
+			checkExternalBrowser();
+			checkMcpAccess();
+			checkEnvVars();
+			checkTimeTracked();
+			checkConflictCopies();
+			checkJsErrors();
 		}
 
 		// Check which wikis are currently open (for disabling Plugins button etc.)
@@ -595,6 +624,286 @@ exports.startup = function(callback) {
 		});
 	}
 
+	// Apply storage status (removable drive / network mount, drive present) to the wiki list UI
+	function applyWikiStorageStatus(statuses) {
+		var entries = getWikiListEntries();
+		(statuses || []).forEach(function(status) {
+			for (var i = 0; i < entries.length; i++) {
+				if (entries[i].path === status.path) {
+					var tempTitle = "$:/temp/tiddlydesktop-rs/wikis/" + i;
+					$tw.wiki.setText(tempTitle, "storage_kind", null, status.kind);
+					$tw.wiki.setText(tempTitle, "storage_volume", null, status.volume || "");
+					$tw.wiki.setText(tempTitle, "available", null, status.available ? "yes" : "no");
+					break;
+				}
+			}
+		});
+	}
+
+	// Check where each wiki lives and whether it's reachable (desktop only).
+	// Rust keeps watching for drives being plugged in and emits wiki-storage-changed.
+	function checkWikiStorage() {
+		invoke("get_wiki_storage_status").then(applyWikiStorageStatus).catch(function(err) {
+			console.error("[TiddlyDesktop] Failed to check wiki storage:", err);
+		});
+	}
+
+	// Snapshot settings of folder wikis, keyed by path (desktop only)
+	var folderSnapshotConfigs = {};
+
+	function applyFolderSnapshots(configs) {
+		folderSnapshotConfigs = configs || {};
+		var entries = getWikiListEntries();
+		entries.forEach(function(entry, index) {
+			if (!entry.is_folder) return;
+			var config = folderSnapshotConfigs[entry.path] || {};
+			var tempTitle = "$:/temp/tiddlydesktop-rs/wikis/" + index;
+			$tw.wiki.setText(tempTitle, "snapshot_dir", null, config.enabled ? config.output_dir : "");
+			$tw.wiki.setText(tempTitle, "snapshot_interval", null, String(config.interval_hours || 0));
+			$tw.wiki.setText(tempTitle, "snapshot_on_shutdown", null, config.on_shutdown ? "yes" : "no");
+		});
+	}
+
+	function checkFolderSnapshots() {
+		invoke("get_folder_snapshot_configs").then(applyFolderSnapshots).catch(function(err) {
+			console.error("[TiddlyDesktop] Failed to load folder snapshot settings:", err);
+		});
+	}
+
+	// Download settings of wikis (auto-download folder, CSV delimiter), keyed by path (desktop only)
+	var downloadConfigs = {};
+
+	function applyDownloadConfigs(configs) {
+		downloadConfigs = configs || {};
+		var entries = getWikiListEntries();
+		entries.forEach(function(entry, index) {
+			var config = downloadConfigs[entry.path] || {};
+			var tempTitle = "$:/temp/tiddlydesktop-rs/wikis/" + index;
+			$tw.wiki.setText(tempTitle, "download_dir", null, config.auto_dir || "");
+			$tw.wiki.setText(tempTitle, "csv_delimiter", null, config.csv_delimiter || "");
+		});
+	}
+
+	function checkDownloadConfigs() {
+		invoke("get_download_configs").then(applyDownloadConfigs).catch(function(err) {
+			console.error("[TiddlyDesktop] Failed to load download settings:", err);
+		});
+	}
+
+	// Update part of a wiki's download settings
+	function updateDownloadConfig(path, changes) {
+		var config = $tw.utils.extend({
+			auto_dir: null,
+			csv_delimiter: null
+		}, downloadConfigs[path] || {}, changes);
+		return invoke("set_download_config", { wikiPath: path, config: config }).then(function(saved) {
+			downloadConfigs[path] = saved;
+			applyDownloadConfigs(downloadConfigs);
+		}).catch(function(err) {
+			console.error("Failed to update download settings:", err);
+			alert("Failed to update download settings: " + err);
+		});
+	}
+
+	// Save the QR code of text as a PNG image tiddler
+	function saveQrCode(text, title) {
+		return invoke("generate_qr_png", { text: text }).then(function(png) {
+			var bytes = new Uint8Array(png);
+			var binary = "";
+			for(var i = 0; i < bytes.length; i += 0x8000) {
+				binary += String.fromCharCode.apply(null, bytes.subarray(i, i + 0x8000));
+			}
+			$tw.wiki.addTiddler(new $tw.Tiddler({ title: title, type: "image/png", text: btoa(binary) }));
+		}).catch(function(err) {
+			console.error("[TiddlyDesktop] Failed to create QR code:", err);
+		});
+	}
+
+	// Show which wikis were allowed or denied the location (desktop only)
+	function checkGeolocationPermissions() {
+		invoke("get_geolocation_permissions").then(function(permissions) {
+			var entries = getWikiListEntries();
+			entries.forEach(function(entry, index) {
+				var allowed = (permissions || {})[entry.path];
+				$tw.wiki.setText("$:/temp/tiddlydesktop-rs/wikis/" + index, "geolocation", null,
+					allowed === true ? "allowed" : allowed === false ? "denied" : "");
+			});
+		}).catch(function(err) {
+			console.error("[TiddlyDesktop] Failed to load location permissions:", err);
+		});
+	}
+
+	// Show which wikis were allowed or denied serial port access (desktop only)
+	function checkSerialPermissions() {
+		invoke("get_serial_permissions").then(function(permissions) {
+			var entries = getWikiListEntries();
+			entries.forEach(function(entry, index) {
+				var allowed = (permissions || {})[entry.path];
+				$tw.wiki.setText("$:/temp/tiddlydesktop-rs/wikis/" + index, "serial", null,
+					allowed === true ? "allowed" : allowed === false ? "denied" : "");
+			});
+		}).catch(function(err) {
+			console.error("[TiddlyDesktop] Failed to load serial port permissions:", err);
+		});
+	}
+
+	// Show which wikis were allowed or denied MQTT access (desktop only)
+	function checkMqttPermissions() {
+		invoke("get_mqtt_permissions").then(function(permissions) {
+			var entries = getWikiListEntries();
+			entries.forEach(function(entry, index) {
+				var allowed = (permissions || {})[entry.path];
+				$tw.wiki.setText("$:/temp/tiddlydesktop-rs/wikis/" + index, "mqtt", null,
+					allowed === true ? "allowed" : allowed === false ? "denied" : "");
+			});
+		}).catch(function(err) {
+			console.error("[TiddlyDesktop] Failed to load MQTT permissions:", err);
+		});
+	}
+
+	// Show which wikis open in the system browser (desktop only)
+	function checkExternalBrowser() {
+		invoke("get_external_browser_wikis").then(function(wikis) {
+			var entries = getWikiListEntries();
+			entries.forEach(function(entry, index) {
+				$tw.wiki.setText("$:/temp/tiddlydesktop-rs/wikis/" + index, "external_browser", null,
+					(wikis || {})[entry.path] ? "yes" : "no");
+			});
+		}).catch(function(err) {
+			console.error("[TiddlyDesktop] Failed to load external browser settings:", err);
+		});
+	}
+
+	// Show which wikis are shared with AI assistants over MCP (desktop only)
+	function checkMcpAccess() {
+		invoke("get_mcp_access").then(function(access) {
+			var entries = getWikiListEntries();
+			entries.forEach(function(entry, index) {
+				$tw.wiki.setText("$:/temp/tiddlydesktop-rs/wikis/" + index, "mcp_access", null,
+					(access || {})[entry.path] || "none");
+			});
+		}).catch(function(err) {
+			console.error("[TiddlyDesktop] Failed to load MCP access settings:", err);
+		});
+	}
+
+	// Show the environment variables set for each wiki, and which are in the keychain (desktop only)
+	function checkEnvVars() {
+		invoke("get_wiki_env_vars").then(function(envVars) {
+			var entries = getWikiListEntries();
+			entries.forEach(function(entry, index) {
+				var vars = (envVars || {})[entry.path] || [];
+				$tw.wiki.setText("$:/temp/tiddlydesktop-rs/wikis/" + index, "env_vars", null,
+					$tw.utils.stringifyList(vars.map(function(v) { return v.name; })));
+				$tw.wiki.setText("$:/temp/tiddlydesktop-rs/wikis/" + index, "env_vars_keychain", null,
+					$tw.utils.stringifyList(vars.filter(function(v) { return v.keychain; }).map(function(v) { return v.name; })));
+			});
+		}).catch(function(err) {
+			console.error("[TiddlyDesktop] Failed to load environment variables:", err);
+		});
+	}
+
+	// Show the time tracked in each wiki over the last 7 days (desktop only)
+	function checkTimeTracked() {
+		var week = 7 * 24 * 60 * 60 * 1000;
+		invoke("get_time_report", { range: { from: Date.now() - week } }).then(function(report) {
+			var seconds = {};
+			(report || []).forEach(function(wiki) {
+				seconds[wiki.path] = wiki.seconds;
+			});
+			getWikiListEntries().forEach(function(entry, index) {
+				var minutes = Math.floor((seconds[entry.path] || 0) / 60);
+				$tw.wiki.setText("$:/temp/tiddlydesktop-rs/wikis/" + index, "time_tracked", null,
+					minutes > 0 ? Math.floor(minutes / 60) + "h " + (minutes % 60) + "m" : "");
+			});
+		}).catch(function(err) {
+			console.error("[TiddlyDesktop] Failed to load the time report:", err);
+		});
+	}
+
+	// Count sync tool conflict copies next to each single-file wiki (desktop only)
+	function checkConflictCopies() {
+		invoke("get_conflict_copies").then(function(conflicts) {
+			var entries = getWikiListEntries();
+			entries.forEach(function(entry, index) {
+				var found = (conflicts || []).filter(function(c) { return c.wikiPath === entry.path; })[0];
+				$tw.wiki.setText("$:/temp/tiddlydesktop-rs/wikis/" + index, "conflict_count", null, found ? String(found.copies.length) : "0");
+			});
+		}).catch(function(err) {
+			console.error("[TiddlyDesktop] Failed to look for conflict copies:", err);
+		});
+	}
+
+	// Count the JavaScript errors each wiki reported (desktop only)
+	function checkJsErrors() {
+		invoke("get_js_error_counts").then(function(counts) {
+			getWikiListEntries().forEach(function(entry, index) {
+				$tw.wiki.setText("$:/temp/tiddlydesktop-rs/wikis/" + index, "js_error_count", null, String((counts || {})[entry.path] || 0));
+			});
+		}).catch(function(err) {
+			console.error("[TiddlyDesktop] Failed to read the JS error log:", err);
+		});
+	}
+
+	// Update part of a folder wiki's snapshot settings
+	function updateFolderSnapshot(path, changes) {
+		var config = $tw.utils.extend({
+			enabled: false,
+			output_dir: "",
+			interval_hours: 0,
+			on_shutdown: false
+		}, folderSnapshotConfigs[path] || {}, changes);
+		return invoke("set_folder_snapshot_config", { wikiPath: path, config: config }).then(function(saved) {
+			folderSnapshotConfigs[path] = saved;
+			applyFolderSnapshots(folderSnapshotConfigs);
+		}).catch(function(err) {
+			console.error("Failed to update snapshot settings:", err);
+			alert("Failed to update snapshot settings: " + err);
+		});
+	}
+
+	// A wiki couldn't be opened because its drive or file is gone: offer to locate it.
+	// The entry keeps its settings (backups, group, sync) under the new path.
+	function offerToLocateWiki(path, isFolder, err) {
+		var prompt = $tw.wiki.renderText("text/plain", "text/vnd.tiddlywiki", "<<td-lingo Labels/LocateMissingWiki>>");
+		$tw.tiddlydesktoprs.confirm(prompt + "\n\n" + err).then(function(confirmed) {
+			if (!confirmed) return null;
+			return openDialog(isFolder ? {
+				directory: true,
+				multiple: false
+			} : {
+				multiple: false,
+				filters: [{
+					name: "TiddlyWiki",
+					extensions: ["html", "htm"]
+				}]
+			});
+		}).then(function(newPath) {
+			if (!newPath) return;
+			return invoke("relocate_wiki", { oldPath: path, newPath: newPath }).then(function(relocated) {
+				var entries = getWikiListEntries();
+				for (var i = 0; i < entries.length; i++) {
+					if (entries[i].path === path) {
+						entries[i].path = relocated.path;
+						entries[i].filename = relocated.filename;
+						entries[i].display_path = relocated.display_path;
+						break;
+					}
+				}
+				saveWikiList(entries);
+				refreshWikiList();
+				$tw.rootWidget.dispatchEvent({
+					type: "tm-tiddlydesktop-rs-open-path",
+					param: relocated.path,
+					paramObject: { path: relocated.path, isFolder: isFolder ? "true" : "false" }
+				});
+			});
+		}).catch(function(locateErr) {
+			console.error("Failed to locate wiki:", locateErr);
+			alert("Failed to locate wiki: " + locateErr);
+		});
+	}
+
 	// Check permissions for all wiki entries (Android only)
 	function checkWikiPermissions(entries) {
 		entries.forEach(function(entry, index) {
@@ -1032,6 +1341,97 @@ exports.startup = function(callback) {
 		$tw.wiki.setText("$:/temp/tiddlydesktop-rs/show-plugin-installer", "text", null, "no");
 		$tw.wiki.setText("$:/temp/tiddlydesktop-rs/plugin-install-loading", "text", null, "yes");
 
+		// Check the plugins against the bundled core first; a failed check doesn't stop the install
+		invoke("check_plugin_compatibility", { path: path, isFolder: isFolder, plugins: selectedPlugins }).then(function(report) {
+			if (!report.issues.length) {
+				installPlugins(path, isFolder, selectedPlugins);
+				return;
+			}
+			$tw.wiki.setText("$:/temp/tiddlydesktop-rs/plugin-install-loading", "text", null, "no");
+			confirmPluginIssues(report).then(function(install) {
+				if (install) {
+					$tw.wiki.setText("$:/temp/tiddlydesktop-rs/plugin-install-loading", "text", null, "yes");
+					installPlugins(path, isFolder, selectedPlugins);
+				} else {
+					// Back to the plugin selection
+					$tw.wiki.setText("$:/temp/tiddlydesktop-rs/show-plugin-installer", "text", null, "yes");
+				}
+			});
+		}).catch(function(err) {
+			console.warn("check_plugin_compatibility error:", err);
+			installPlugins(path, isFolder, selectedPlugins);
+		});
+	});
+
+	// Report plugins that may not work with the bundled core; resolves to true to install anyway
+	function confirmPluginIssues(report) {
+		function lingo(key) {
+			return $tw.wiki.renderText("text/plain", "text/vnd.tiddlywiki", "<<td-lingo PluginCompat/" + key + ">>");
+		}
+		return new Promise(function(resolve) {
+			var overlay = document.createElement("div");
+			overlay.className = "td-conflict-overlay";
+			var dialog = document.createElement("div");
+			dialog.className = "td-conflict-dialog";
+
+			var heading = document.createElement("h2");
+			heading.textContent = lingo("Title");
+			var hint = document.createElement("p");
+			hint.className = "td-conflict-hint";
+			hint.textContent = lingo("Hint") + " " + report.coreVersion;
+			dialog.appendChild(heading);
+			dialog.appendChild(hint);
+
+			var list = document.createElement("div");
+			list.className = "td-conflict-list";
+			report.issues.forEach(function(issue) {
+				var item = document.createElement("div");
+				item.className = "td-conflict-item";
+				var name = document.createElement("strong");
+				name.textContent = issue.title + (issue.version ? " " + issue.version : "");
+				var kind = document.createElement("span");
+				kind.className = "td-conflict-kind";
+				kind.textContent = issue.kind === "core-version" ? lingo("CoreVersion") : lingo("ModuleType");
+				var meta = document.createElement("div");
+				meta.className = "td-conflict-meta";
+				meta.textContent = issue.kind === "core-version"
+					? lingo("Requires") + " " + issue.coreVersion
+					: issue.module + " (module-type: " + issue.moduleType + ")";
+				item.appendChild(name);
+				item.appendChild(kind);
+				item.appendChild(meta);
+				list.appendChild(item);
+			});
+			dialog.appendChild(list);
+
+			var footer = document.createElement("div");
+			footer.className = "td-conflict-footer";
+			var buttons = document.createElement("div");
+			buttons.className = "td-conflict-buttons";
+			var cancelBtn = document.createElement("button");
+			cancelBtn.className = "td-button";
+			cancelBtn.textContent = $tw.wiki.renderText("text/plain", "text/vnd.tiddlywiki", "<<td-lingo Buttons/Cancel>>") || "Cancel";
+			var installBtn = document.createElement("button");
+			installBtn.className = "td-button td-button-open";
+			installBtn.textContent = lingo("InstallAnyway");
+			buttons.appendChild(cancelBtn);
+			buttons.appendChild(installBtn);
+			footer.appendChild(buttons);
+			dialog.appendChild(footer);
+
+			overlay.appendChild(dialog);
+			document.body.appendChild(overlay);
+			function finish(result) {
+				if (overlay.parentNode) overlay.parentNode.removeChild(overlay);
+				resolve(result);
+			}
+			cancelBtn.addEventListener("click", function() { finish(false); });
+			installBtn.addEventListener("click", function() { finish(true); });
+			cancelBtn.focus();
+		});
+	}
+
+	function installPlugins(path, isFolder, selectedPlugins) {
 		invoke("install_plugins_to_wiki", { path: path, isFolder: isFolder, plugins: selectedPlugins }).then(function() {
 			$tw.wiki.setText("$:/temp/tiddlydesktop-rs/plugin-install-loading", "text", null, "no");
 			// Clean up temp tiddlers
@@ -1046,7 +1446,7 @@ exports.startup = function(callback) {
 			$tw.wiki.setText("$:/temp/tiddlydesktop-rs/plugin-install-loading", "text", null, "no");
 			alert("Failed to update plugins: " + err);
 		});
-	});
+	}
 
 	// Message handler: cancel plugin installer
 	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-cancel-plugin-install", function(event) {
@@ -1060,6 +1460,43 @@ exports.startup = function(callback) {
 		$tw.wiki.deleteTiddler("$:/temp/tiddlydesktop-rs/plugin-install-is-folder");
 	});
 
+	// Message handler: search the tiddlers of all wikis in the list (desktop)
+	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-search-all-wikis", function(event) {
+		var query = ($tw.wiki.getTiddlerText("$:/temp/tiddlydesktop-rs/search") || "").trim();
+		if (!query) return;
+		invoke("search_all_wikis", { query: query, limit: 100 }).then(function(results) {
+			$tw.wiki.filterTiddlers("[prefix[$:/temp/tiddlydesktop-rs/wiki-search/]]").forEach(function(title) {
+				$tw.wiki.deleteTiddler(title);
+			});
+			results.hits.forEach(function(hit, i) {
+				$tw.wiki.addTiddler(new $tw.Tiddler({
+					title: "$:/temp/tiddlydesktop-rs/wiki-search/" + i,
+					order: String(i),
+					wiki_path: hit.wikiPath,
+					wiki_name: hit.wikiName,
+					tiddler: hit.title,
+					snippet: hit.snippet
+				}));
+			});
+			$tw.wiki.addTiddler(new $tw.Tiddler({
+				title: "$:/temp/tiddlydesktop-rs/wiki-search",
+				text: query,
+				indexing: results.indexing ? "yes" : "no"
+			}));
+		}).catch(function(err) {
+			console.error("search_all_wikis error:", err);
+		});
+	});
+
+	// Message handler: open the wiki of a search result at its tiddler
+	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-open-search-result", function(event) {
+		var hit = $tw.wiki.getTiddler(event.param);
+		if (!hit) return;
+		invoke("open_search_result", { wikiPath: hit.fields.wiki_path, title: hit.fields.tiddler }).catch(function(err) {
+			alert("Failed to open the wiki: " + err);
+		});
+	});
+
 	// Message handler: open a specific wiki path (auto-detect file vs folder)
 	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-open-path", function(event) {
 		var path = event.param || event.paramObject.path;
@@ -1089,7 +1526,24 @@ exports.startup = function(callback) {
 				refreshWikiList();
 			}).catch(function(err) {
 				console.error("Failed to open wiki:", err);
-				alert("Failed to open: " + err);
+				if (isAndroid || !entry) {
+					alert("Failed to open: " + err);
+					return;
+				}
+				// Unplugged drive or moved file: offer to locate instead of just failing
+				invoke("get_wiki_storage_status").then(function(statuses) {
+					applyWikiStorageStatus(statuses);
+					var missing = statuses.some(function(status) {
+						return status.path === path && !status.available;
+					});
+					if (missing) {
+						offerToLocateWiki(path, isFolder, err);
+					} else {
+						alert("Failed to open: " + err);
+					}
+				}).catch(function() {
+					alert("Failed to open: " + err);
+				});
 			});
 		}
 	});
@@ -1299,289 +1753,891 @@ exports.startup = function(callback) {
 		}
 	});
 
-	// Message handler: set backup count for a wiki (max backups to keep)
-	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-set-backup-count", function(event) {
+	// Message handler: choose the snapshot folder of a folder wiki (enables snapshots)
+	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-set-snapshot-dir", function(event) {
 		var path = event.paramObject && event.paramObject.path;
-		var countStr = event.paramObject && event.paramObject.count;
-		if (path) {
-			// Parse count: empty string or null = default (20), "0" = unlimited, number = that count
-			var count = countStr === "" || countStr === null || countStr === undefined ? null : parseInt(countStr, 10);
-			if (count !== null && isNaN(count)) count = null;
+		if (!path) return;
+		openDialog({
+			directory: true,
+			multiple: false
+		}).then(function(folder) {
+			if (folder) {
+				updateFolderSnapshot(path, { output_dir: folder });
+			}
+		}).catch(function(err) {
+			console.error("openDialog error:", err);
+		});
+	});
 
-			// Update the Rust backend
-			invoke("set_wiki_backup_count", { path: path, count: count }).then(function() {
-				// Update the local wiki list entry
-				var entries = getWikiListEntries();
-				for (var i = 0; i < entries.length; i++) {
-					if (entries[i].path === path) {
-						if (count === null) {
-							delete entries[i].backup_count;
-						} else {
-							entries[i].backup_count = count;
-						}
-						// Directly update the temp tiddler field for immediate UI update
-						var tempTitle = "$:/temp/tiddlydesktop-rs/wikis/" + i;
-						$tw.wiki.setText(tempTitle, "backup_count", null, count !== null ? String(count) : "");
-						break;
-					}
-				}
-				saveWikiList(entries);
-			}).catch(function(err) {
-				console.error("Failed to set backup count:", err);
-			});
+	// Message handler: stop taking snapshots of a folder wiki
+	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-clear-snapshot-dir", function(event) {
+		var path = event.paramObject && event.paramObject.path;
+		if (path) {
+			updateFolderSnapshot(path, { output_dir: "" });
 		}
 	});
 
-	// Message handler: set group for a wiki
-	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-set-group", function(event) {
+	// Message handler: choose a folder that downloads of a wiki are saved to without asking
+	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-set-download-dir", function(event) {
+		var path = event.paramObject && event.paramObject.path;
+		if (!path) return;
+		openDialog({
+			directory: true,
+			multiple: false
+		}).then(function(folder) {
+			if (folder) {
+				updateDownloadConfig(path, { auto_dir: folder });
+			}
+		}).catch(function(err) {
+			console.error("openDialog error:", err);
+		});
+	});
+
+	// Message handler: ask where to save downloads of a wiki again
+	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-clear-download-dir", function(event) {
 		var path = event.paramObject && event.paramObject.path;
-		var group = event.paramObject && event.paramObject.group;
 		if (path) {
-			// Treat empty string as null (ungrouped)
-			var groupValue = group && group.trim() ? group.trim() : null;
-			// Update the Rust backend
-			invoke("set_wiki_group", { path: path, group: groupValue }).then(function() {
-				// Update the local wiki list entry
-				var entries = getWikiListEntries();
-				for (var i = 0; i < entries.length; i++) {
-					if (entries[i].path === path) {
-						if (groupValue) {
-							entries[i].group = groupValue;
-						} else {
-							delete entries[i].group;
-						}
-						break;
-					}
-				}
-				saveWikiList(entries);
-				refreshWikiList();
-			}).catch(function(err) {
-				console.error("Failed to set wiki group:", err);
-			});
+			updateDownloadConfig(path, { auto_dir: null });
 		}
 	});
 
-	// Message handler: rename a group
-	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-rename-group", function(event) {
-		var oldName = event.paramObject && event.paramObject.oldName;
-		var newName = event.paramObject && event.paramObject.newName;
-		if (oldName && newName && newName.trim()) {
-			invoke("rename_wiki_group", { oldName: oldName, newName: newName.trim() }).then(function() {
-				// Update local entries
-				var entries = getWikiListEntries();
-				for (var i = 0; i < entries.length; i++) {
-					if (entries[i].group === oldName) {
-						entries[i].group = newName.trim();
-					}
-				}
-				// Transfer collapsed state to the new group name
-				var collapsed = getCollapsedGroups();
-				var idx = collapsed.indexOf(oldName);
-				if (idx !== -1) {
-					collapsed[idx] = newName.trim();
-					saveCollapsedGroups(collapsed);
-				}
-				saveWikiList(entries);
-				refreshWikiList();
-			}).catch(function(err) {
-				console.error("Failed to rename group:", err);
-			});
-		}
+	// Message handler: set the delimiter of CSV downloads of a wiki ("," ";" "|" or "tab")
+	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-set-csv-delimiter", function(event) {
+		var path = event.paramObject && event.paramObject.path;
+		if (!path) return;
+		var delimiter = event.paramObject.delimiter;
+		updateDownloadConfig(path, { csv_delimiter: delimiter && delimiter !== "," ? delimiter : null });
 	});
 
-	// Message handler: delete a group (moves wikis to ungrouped)
-	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-delete-group", function(event) {
-		var groupName = event.param || (event.paramObject && event.paramObject.group);
-		if (groupName) {
-			invoke("delete_wiki_group", { groupName: groupName }).then(function() {
-				// Update local entries
-				var entries = getWikiListEntries();
-				for (var i = 0; i < entries.length; i++) {
-					if (entries[i].group === groupName) {
-						delete entries[i].group;
-					}
-				}
-				// Remove from collapsed groups
-				var collapsed = getCollapsedGroups();
-				collapsed = collapsed.filter(function(g) { return g !== groupName; });
-				saveCollapsedGroups(collapsed);
-				saveWikiList(entries);
-				refreshWikiList();
-			}).catch(function(err) {
-				console.error("Failed to delete group:", err);
-			});
-		}
+	// Message handler: forget a wiki's location permission, so it asks again
+	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-reset-geolocation", function(event) {
+		var path = event.paramObject && event.paramObject.path;
+		if (!path) return;
+		invoke("set_geolocation_permission", { wikiPath: path, allowed: null }).then(function() {
+			checkGeolocationPermissions();
+		}).catch(function(err) {
+			console.error("Failed to reset location permission:", err);
+			alert("Failed to reset location permission: " + err);
+		});
 	});
 
-	// Message handler: toggle group collapse state (persistent)
-	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-toggle-group", function(event) {
-		var groupName = event.param || (event.paramObject && event.paramObject.group);
-		var key = groupName || "Ungrouped";
-		var stateTitle = "$:/state/tiddlydesktop-rs/group-collapsed/" + key;
-		var currentState = $tw.wiki.getTiddlerText(stateTitle);
-		var newState = currentState === "yes" ? "no" : "yes";
-		// Update ephemeral state for instant UI response
-		$tw.wiki.setText(stateTitle, "text", null, newState);
-		// Persist collapsed groups to a saved tiddler
-		var collapsed = getCollapsedGroups();
-		if (newState === "yes") {
-			if (collapsed.indexOf(key) === -1) collapsed.push(key);
-		} else {
-			collapsed = collapsed.filter(function(g) { return g !== key; });
-		}
-		saveCollapsedGroups(collapsed);
+	// Message handler: forget a wiki's serial port permission, so it asks again
+	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-reset-serial", function(event) {
+		var path = event.paramObject && event.paramObject.path;
+		if (!path) return;
+		invoke("set_serial_permission", { wikiPath: path, allowed: null }).then(function() {
+			checkSerialPermissions();
+		}).catch(function(err) {
+			console.error("Failed to reset serial port permission:", err);
+			alert("Failed to reset serial port permission: " + err);
+		});
 	});
 
-	// Message handler: show group manager modal
-	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-manage-groups", function(event) {
-		$tw.wiki.setText("$:/temp/tiddlydesktop-rs/show-group-manager", "text", null, "yes");
+	// Message handler: forget a wiki's MQTT permission, so it asks again
+	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-reset-mqtt", function(event) {
+		var path = event.paramObject && event.paramObject.path;
+		if (!path) return;
+		invoke("set_mqtt_permission", { wikiPath: path, allowed: null }).then(function() {
+			checkMqttPermissions();
+		}).catch(function(err) {
+			console.error("Failed to reset MQTT permission:", err);
+			alert("Failed to reset MQTT permission: " + err);
+		});
 	});
 
-	// Message handler: close group manager modal
-	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-close-group-manager", function(event) {
-		$tw.wiki.setText("$:/temp/tiddlydesktop-rs/show-group-manager", "text", null, "no");
+	// Message handler: share a wiki with AI assistants (read-only, read-write) or stop sharing it
+	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-set-mcp-access", function(event) {
+		var path = event.paramObject && event.paramObject.path;
+		var access = event.paramObject && event.paramObject.access;
+		if (!path) return;
+		invoke("set_mcp_access", { wikiPath: path, access: access === "none" ? null : access }).then(function() {
+			checkMcpAccess();
+		}).catch(function(err) {
+			console.error("Failed to change MCP access:", err);
+			alert("Failed to change MCP access: " + err);
+		});
 	});
 
-	// Set up drag-drop listeners
-	// Track recently processed drops to prevent duplicate opens if both events fire
-	var recentDrops = {};
-	var DROP_DEDUPE_MS = 500;
-
-	// Handle dropped files/folders by opening them as wikis
-	function handleDroppedPaths(paths) {
-		if (!paths || paths.length === 0) return;
-		var now = Date.now();
-		paths.forEach(function(path) {
-			// Deduplicate: skip if this path was processed recently
-			if (recentDrops[path] && (now - recentDrops[path]) < DROP_DEDUPE_MS) {
-				return;
-			}
-			recentDrops[path] = now;
-
-			// Check if it's an HTML file or potentially a folder
-			if (path.endsWith(".html") || path.endsWith(".htm")) {
-				invoke("open_wiki_window", { path: path }).then(function(entry) {
-					addToWikiList(entry);
-					refreshWikiList();
-				});
-			} else {
-				// Try to open as a folder - backend will verify if it's a valid wiki folder
-				invoke("check_is_wiki_folder", { path: path }).then(function(isFolder) {
-					if (isFolder) {
-						invoke("open_wiki_folder", { path: path }).then(function(entry) {
-							addToWikiList(entry);
-							refreshWikiList();
-						}).catch(function(err) {
-							console.error("Failed to open wiki folder:", err);
-						});
-					}
-				});
-			}
+	// Message handler: set an environment variable for a wiki's process, its value optionally
+	// kept in the OS keychain
+	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-set-wiki-env-var", function(event) {
+		var params = event.paramObject || {};
+		if (!params.path || !params.name) return;
+		invoke("set_wiki_env_var", {
+			wikiPath: params.path,
+			name: params.name,
+			value: params.value || "",
+			keychain: params.keychain === "yes"
+		}).then(function() {
+			checkEnvVars();
+		}).catch(function(err) {
+			console.error("Failed to set environment variable:", err);
+			alert("Failed to set environment variable: " + err);
 		});
-		// Clean up old entries periodically
-		setTimeout(function() {
-			var cutoff = Date.now() - DROP_DEDUPE_MS * 2;
-			for (var p in recentDrops) {
-				if (recentDrops[p] < cutoff) {
-					delete recentDrops[p];
-				}
-			}
-		}, DROP_DEDUPE_MS * 2);
-	}
-
-	// Listen for tauri://drag-drop (Windows, and Tauri's built-in drag-drop)
-	listen("tauri://drag-drop", function(event) {
-		$tw.wiki.setText("$:/temp/tiddlydesktop-rs/drag-over", "text", null, "no");
-		handleDroppedPaths(event.payload && event.payload.paths);
 	});
 
-	// Listen for td-file-drop (Linux/macOS custom GTK/Cocoa drag-drop)
-	listen("td-file-drop", function(event) {
-		$tw.wiki.setText("$:/temp/tiddlydesktop-rs/drag-over", "text", null, "no");
-		handleDroppedPaths(event.payload && event.payload.paths);
+	// Message handler: remove an environment variable of a wiki
+	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-remove-wiki-env-var", function(event) {
+		var path = event.paramObject && event.paramObject.path;
+		var name = event.paramObject && event.paramObject.name;
+		if (!path || !name) return;
+		invoke("remove_wiki_env_var", { wikiPath: path, name: name }).then(function() {
+			checkEnvVars();
+		}).catch(function(err) {
+			console.error("Failed to remove environment variable:", err);
+			alert("Failed to remove environment variable: " + err);
+		});
 	});
 
-	// Visual feedback for drag-over state (Tauri built-in events - Windows)
-	listen("tauri://drag-enter", function() {
-		$tw.wiki.setText("$:/temp/tiddlydesktop-rs/drag-over", "text", null, "yes");
+	// Message handler: open a wiki in the system browser instead of a window (or back)
+	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-set-external-browser", function(event) {
+		var path = event.paramObject && event.paramObject.path;
+		if (!path) return;
+		invoke("set_external_browser", { wikiPath: path, enabled: event.paramObject.enabled === "yes" }).then(function() {
+			checkExternalBrowser();
+		}).catch(function(err) {
+			console.error("Failed to change how the wiki opens:", err);
+			alert("Failed to change how the wiki opens: " + err);
+		});
 	});
 
-	listen("tauri://drag-leave", function() {
-		$tw.wiki.setText("$:/temp/tiddlydesktop-rs/drag-over", "text", null, "no");
+	// Message handler: bind a global hotkey that opens a wiki (an empty one removes it)
+	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-set-wiki-hotkey", function(event) {
+		var path = event.paramObject && event.paramObject.path;
+		if (!path) return;
+		var hotkey = (event.paramObject.hotkey || "").trim();
+		var command = hotkey ? invoke("set_wiki_hotkey", { path: path, hotkey: hotkey }) : invoke("clear_wiki_hotkey", { path: path });
+		command.then(function() {
+			var entries = getWikiListEntries();
+			for (var i = 0; i < entries.length; i++) {
+				if (entries[i].path === path) {
+					if (hotkey) {
+						entries[i].hotkey = hotkey;
+					} else {
+						delete entries[i].hotkey;
+					}
+					$tw.wiki.setText("$:/temp/tiddlydesktop-rs/wikis/" + i, "hotkey", null, hotkey);
+					break;
+				}
+			}
+			saveWikiList(entries);
+		}).catch(function(err) {
+			console.error("Failed to set the wiki hotkey:", err);
+			alert("Failed to set the wiki hotkey: " + err);
+		});
 	});
 
-	// Visual feedback for drag-over state (custom GTK/Cocoa events - Linux/macOS)
-	listen("td-drag-motion", function() {
-		$tw.wiki.setText("$:/temp/tiddlydesktop-rs/drag-over", "text", null, "yes");
+	// Message handler: check a wiki's external attachments against their recorded hashes
+	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-verify-attachments", function(event) {
+		var path = event.paramObject && event.paramObject.path;
+		if (!path) return;
+		function lingo(key) {
+			return $tw.wiki.renderText("text/plain", "text/vnd.tiddlywiki", "<<td-lingo Attachments/" + key + ">>");
+		}
+		invoke("verify_attachments", { wikiPath: path }).then(function(report) {
+			var total = report.ok + report["new"] + report.modified + report.corrupted + report.missing;
+			var summary = lingo("Summary")
+				.replace("$total$", total)
+				.replace("$ok$", report.ok + report["new"])
+				.replace("$modified$", report.modified)
+				.replace("$corrupted$", report.corrupted)
+				.replace("$missing$", report.missing);
+			if (report.complete) {
+				window.__TAURI__.dialog.message(summary, { title: "TiddlyDesktop", kind: "info" });
+				return;
+			}
+			var lines = report.problems.filter(function(p) { return p.status !== "new"; }).map(function(p) {
+				return lingo("Status/" + p.status) + ": " + p.uri;
+			});
+			$tw.tiddlydesktoprs.confirm(summary + "\n\n" + lines.join("\n") + "\n\n" + lingo("ConfirmAccept")).then(function(confirmed) {
+				if (!confirmed) return;
+				return invoke("accept_attachment_changes", { wikiPath: path });
+			}).catch(function(err) {
+				console.error("Failed to accept attachment changes:", err);
+				alert("Failed to accept attachment changes: " + err);
+			});
+		}).catch(function(err) {
+			console.error("Failed to verify attachments:", err);
+			alert("Failed to verify attachments: " + err);
+		});
 	});
 
-	listen("td-drag-leave", function() {
-		$tw.wiki.setText("$:/temp/tiddlydesktop-rs/drag-over", "text", null, "no");
+	// Message handler: export a wiki with its attachments as a zip archive
+	// (encrypted="yes": AES-256 with the password entered in the popup)
+	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-export-archive", function(event) {
+		var path = event.paramObject && event.paramObject.path;
+		if (!path) return;
+		function lingo(key) {
+			return $tw.wiki.renderText("text/plain", "text/vnd.tiddlywiki", "<<td-lingo Archive/" + key + ">>");
+		}
+		var password = null;
+		if (event.paramObject.encrypted === "yes") {
+			var passwordTiddler = $tw.wiki.getTiddler("$:/temp/tiddlydesktop-rs/archive-password");
+			var fields = passwordTiddler ? passwordTiddler.fields : {};
+			// The password is not kept around
+			$tw.wiki.deleteTiddler("$:/temp/tiddlydesktop-rs/archive-password");
+			if (!fields.password) {
+				alert(lingo("PasswordMissing"));
+				return;
+			}
+			if (fields.password !== fields.confirm) {
+				alert(lingo("PasswordMismatch"));
+				return;
+			}
+			password = fields.password;
+		}
+		var name = path.replace(/[\\/]+$/, "").split(/[\\/]/).pop().replace(/\.html?$/i, "");
+		window.__TAURI__.dialog.save({
+			filters: [{
+				name: "Zip archive",
+				extensions: ["zip"]
+			}],
+			defaultPath: name + ".zip"
+		}).then(function(target) {
+			if (!target) return;
+			return invoke("export_wiki_archive", { wikiPath: path, target: target, password: password }).then(function(archive) {
+				var message = lingo(archive.encrypted ? "SavedEncrypted" : "Saved").replace("$files$", archive.files) + " " + archive.path;
+				if (archive.missing.length > 0) {
+					message += "\n\n" + lingo("Missing") + "\n" + archive.missing.join("\n");
+				}
+				window.__TAURI__.dialog.message(message, { title: "TiddlyDesktop", kind: "info" });
+			});
+		}).catch(function(err) {
+			console.error("Failed to export archive:", err);
+			alert("Failed to export archive: " + err);
+		});
 	});
 
-	// Fix for stale tc-dragover class when dragging between droppable widgets
-	// The droppable widget's currentlyEntered tracking can get out of sync
-	// Ensure only one droppable has the highlight at a time
-	document.addEventListener("dragenter", function(event) {
-		var target = event.target.closest(".tc-droppable");
-		var elements = document.querySelectorAll(".tc-dragover");
-		for(var i = 0; i < elements.length; i++) {
-			if(elements[i] !== target) {
-				elements[i].classList.remove("tc-dragover");
-			}
+	// Message handler: export a sync-enabled wiki with its sync state as a .twsync bundle
+	// (encrypted, with the room's password, if a password was entered in the popup)
+	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-export-sync-bundle", function(event) {
+		var path = event.paramObject && event.paramObject.path;
+		if (!path) return;
+		function lingo(key) {
+			return $tw.wiki.renderText("text/plain", "text/vnd.tiddlywiki", "<<td-lingo " + key + ">>");
+		}
+		var passwordTiddler = $tw.wiki.getTiddler("$:/temp/tiddlydesktop-rs/bundle-password");
+		var fields = passwordTiddler ? passwordTiddler.fields : {};
+		// The password is not kept around
+		$tw.wiki.deleteTiddler("$:/temp/tiddlydesktop-rs/bundle-password");
+		if ((fields.password || fields.confirm) && fields.password !== fields.confirm) {
+			alert(lingo("Archive/PasswordMismatch"));
+			return;
 		}
+		var name = path.replace(/[\\/]+$/, "").split(/[\\/]/).pop().replace(/\.html?$/i, "");
+		window.__TAURI__.dialog.save({
+			filters: [{
+				name: "TiddlyDesktop sync bundle",
+				extensions: ["twsync"]
+			}],
+			defaultPath: name + ".twsync"
+		}).then(function(target) {
+			if (!target) return;
+			return invoke("lan_sync_export_bundle", { wikiPath: path, target: target, password: fields.password || null }).then(function(bundle) {
+				var message = lingo(bundle.includesRoom ? "SyncBundle/SavedWithRoom" : "SyncBundle/Saved").replace("$files$", bundle.files) + " " + bundle.path;
+				window.__TAURI__.dialog.message(message, { title: "TiddlyDesktop", kind: "info" });
+			});
+		}).catch(function(err) {
+			console.error("Failed to export sync bundle:", err);
+			alert("Failed to export sync bundle: " + err);
+		});
 	});
 
-	// Listen for wiki list changes (e.g., when a wiki is opened from file explorer)
-	listen("wiki-list-changed", function(event) {
-		if(event.payload) {
-			addToWikiList(event.payload);
+	// Message handler: import a .twsync bundle into a chosen folder
+	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-import-sync-bundle", function(event) {
+		function lingo(key) {
+			return $tw.wiki.renderText("text/plain", "text/vnd.tiddlywiki", "<<td-lingo SyncBundle/" + key + ">>");
 		}
-		refreshWikiList();
+		var passwordTiddler = $tw.wiki.getTiddler("$:/temp/tiddlydesktop-rs/bundle-import-password");
+		var password = (passwordTiddler && passwordTiddler.fields.password) || null;
+		openDialog({
+			multiple: false,
+			filters: [{
+				name: "TiddlyDesktop sync bundle",
+				extensions: ["twsync"]
+			}]
+		}).then(function(bundlePath) {
+			if (!bundlePath) return;
+			return openDialog({ directory: true, multiple: false }).then(function(targetDir) {
+				if (!targetDir) return;
+				return invoke("lan_sync_import_bundle", { bundlePath: bundlePath, targetDir: targetDir, password: password }).then(function(entry) {
+					$tw.wiki.deleteTiddler("$:/temp/tiddlydesktop-rs/bundle-import-password");
+					window.__TAURI__.dialog.message(lingo("Imported") + " " + entry.path, { title: "TiddlyDesktop", kind: "info" });
+				});
+			});
+		}).catch(function(err) {
+			console.error("Failed to import sync bundle:", err);
+			alert("Failed to import sync bundle: " + err);
+		});
 	});
 
-	// Listen for wiki process closed events to update open/closed state (desktop)
-	listen("wiki-process-closed", function() {
-		refreshWikiList();
+	// Message handler: set the snapshot interval (hours, 0 = manual) and/or snapshot on close
+	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-set-snapshot-schedule", function(event) {
+		var path = event.paramObject && event.paramObject.path;
+		if (!path) return;
+		var changes = {};
+		if (event.paramObject.hours !== undefined) {
+			changes.interval_hours = parseInt(event.paramObject.hours, 10) || 0;
+		}
+		if (event.paramObject.onShutdown !== undefined) {
+			changes.on_shutdown = event.paramObject.onShutdown === "yes";
+		}
+		updateFolderSnapshot(path, changes);
 	});
 
-	// Expose a global function for Android to call from Kotlin when a WikiActivity closes.
-	// MainActivity's BroadcastReceiver calls evaluateJavascript() with this.
-	window.__tdWikiClosed = function(wikiPath) {
-		var entries = getWikiListEntries();
-		entries.forEach(function(entry, index) {
-			if (entry.path === wikiPath) {
-				$tw.wiki.setText("$:/temp/tiddlydesktop-rs/wikis/" + index, "is_open", null, "no");
-			}
+	// Message handler: take a snapshot of a folder wiki now
+	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-snapshot-now", function(event) {
+		var path = event.paramObject && event.paramObject.path;
+		if (!path) return;
+		invoke("snapshot_folder_wiki_now", { wikiPath: path }).then(function(snapshotPath) {
+			var saved = $tw.wiki.renderText("text/plain", "text/vnd.tiddlywiki", "<<td-lingo Labels/SnapshotSaved>>");
+			window.__TAURI__.dialog.message(saved + " " + snapshotPath, { title: "TiddlyDesktop", kind: "info" });
+		}).catch(function(err) {
+			console.error("Failed to take snapshot:", err);
+			alert("Failed to take snapshot: " + err);
 		});
-	};
+	});
 
-	// Listen for favicon updates from wiki windows (updates in real-time without full refresh)
-	window.addEventListener("td-favicon-updated", function(event) {
-		var path = event.detail && event.detail.path;
-		var favicon = event.detail && event.detail.favicon;
+	// Message handler: list the media files of a folder in a tiddlywiki.files manifest of a
+	// folder wiki, or update the list (desktop only)
+	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-add-media-folder", function(event) {
+		var path = event.paramObject && event.paramObject.path;
 		if (!path) return;
-
-		// Update the persistent wiki list entry
-		var entries = getWikiListEntries();
-		var updated = false;
-		for (var i = 0; i < entries.length; i++) {
-			if (entries[i].path === path) {
-				entries[i].favicon = favicon;
-				// Also update the temp tiddler directly for immediate UI update
-				var tempTitle = "$:/temp/tiddlydesktop-rs/wikis/" + i;
-				$tw.wiki.setText(tempTitle, "favicon", null, favicon || "");
-				updated = true;
-				break;
-			}
-		}
-		if (updated) {
-			saveWikiList(entries);
-		}
+		openDialog({
+			directory: true,
+			multiple: false,
+			title: $tw.wiki.renderText("text/plain", "text/vnd.tiddlywiki", "<<td-lingo MediaFolder/Select>>")
+		}).then(function(mediaDir) {
+			if (!mediaDir) return;
+			return invoke("generate_media_manifest", { wikiPath: path, mediaDir: mediaDir }).then(function(result) {
+				var listed = $tw.wiki.renderText("text/plain", "text/vnd.tiddlywiki", "<<td-lingo MediaFolder/Listed>>");
+				var reopen = $tw.wiki.renderText("text/plain", "text/vnd.tiddlywiki", "<<td-lingo MediaFolder/Reopen>>");
+				window.__TAURI__.dialog.message(result.files + " " + listed + " " + result.manifest +
+					" (+" + result.added + " / -" + result.removed + ")\n\n" + reopen, { title: "TiddlyDesktop", kind: "info" });
+			});
+		}).catch(function(err) {
+			console.error("Failed to list media folder:", err);
+			alert("Failed to list media folder: " + err);
+		});
 	});
 
-	// Clean up stale $:/state/ and $:/temp/ tiddlers from previous sessions.
-	// These get persisted into the wiki HTML on save and cause UI glitches
+	// Message handler: open a wiki with its plugins turned off, to remove a broken one (desktop only)
+	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-open-safe-mode", function(event) {
+		var path = event.paramObject && event.paramObject.path;
+		if (!path) return;
+		invoke("open_wiki_safe_mode", { path: path }).then(function(resultEntry) {
+			addToWikiList(resultEntry);
+			refreshWikiList();
+		}).catch(function(err) {
+			console.error("Failed to open wiki in safe mode:", err);
+			alert("Failed to open wiki in safe mode: " + err);
+		});
+	});
+
+	// Message handler: open a folder wiki with a plugin from its source folder, reloading it
+	// whenever the plugin changes (desktop only)
+	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-open-plugin-dev", function(event) {
+		var path = event.paramObject && event.paramObject.path;
+		if (!path) return;
+		openDialog({
+			directory: true,
+			multiple: false,
+			title: $tw.wiki.renderText("text/plain", "text/vnd.tiddlywiki", "<<td-lingo Labels/SelectPluginFolder>>")
+		}).then(function(pluginDir) {
+			if (!pluginDir) return;
+			return invoke("open_wiki_plugin_dev", { path: path, pluginDir: pluginDir }).then(function(resultEntry) {
+				addToWikiList(resultEntry);
+				refreshWikiList();
+			});
+		}).catch(function(err) {
+			console.error("Failed to open wiki for plugin development:", err);
+			alert("Failed to open wiki for plugin development: " + err);
+		});
+	});
+
+	// Message handler: create a desktop shortcut that opens a wiki directly (desktop only)
+	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-create-shortcut", function(event) {
+		var path = event.param;
+		if (!path) return;
+		invoke("create_desktop_shortcut", { wikiPath: path }).then(function(shortcutPath) {
+			var created = $tw.wiki.renderText("text/plain", "text/vnd.tiddlywiki", "<<td-lingo Labels/ShortcutCreated>>");
+			window.__TAURI__.dialog.message(created + " " + shortcutPath, { title: "TiddlyDesktop", kind: "info" });
+		}).catch(function(err) {
+			console.error("Failed to create shortcut:", err);
+			alert("Failed to create shortcut: " + err);
+		});
+	});
+
+	// Interval of scheduled backups as shown in the wiki list
+	function formatBackupInterval(minutes) {
+		if(minutes % 1440 === 0) return (minutes / 1440) + " d";
+		if(minutes % 60 === 0) return (minutes / 60) + " h";
+		return minutes + " min";
+	}
+
+	// Sync schedule of a wiki as stored in the temp tiddlers: "" (real time),
+	// "interval:N" (every N minutes) or "manual"
+	function formatSyncSchedule(schedule) {
+		if(!schedule) return "";
+		if(schedule.mode === "interval") return "interval:" + schedule.minutes;
+		return schedule.mode || "";
+	}
+
+	function parseSyncSchedule(text) {
+		var match = /^interval:(\d+)$/.exec(text);
+		if(match && parseInt(match[1], 10) > 0) return { mode: "interval", minutes: parseInt(match[1], 10) };
+		if(text === "manual") return { mode: "manual" };
+		return null;
+	}
+
+	// Message handler: change the scheduled backups of a wiki (interval, keep or maxAge;
+	// an empty keep/maxAge means the default, an interval of 0 turns them off)
+	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-set-scheduled-backup", function(event) {
+		var params = event.paramObject || {};
+		var path = params.path;
+		if(!path) return;
+		var entries = getWikiListEntries();
+		var index = -1;
+		for(var i = 0; i < entries.length; i++) {
+			if(entries[i].path === path) {
+				index = i;
+				break;
+			}
+		}
+		if(index === -1) return;
+		var config = $tw.utils.extend({ interval_minutes: 60, keep: null, max_age_days: null }, entries[index].scheduled_backup || {});
+		function parseOptional(value) {
+			var n = parseInt(value, 10);
+			return isNaN(n) ? null : n;
+		}
+		if(params.interval !== undefined) config.interval_minutes = parseOptional(params.interval) || 0;
+		if(params.keep !== undefined) config.keep = parseOptional(params.keep);
+		if(params.maxAge !== undefined) config.max_age_days = parseOptional(params.maxAge);
+		var newConfig = config.interval_minutes > 0 ? config : null;
+		invoke("set_wiki_scheduled_backup", { path: path, config: newConfig }).then(function() {
+			if(newConfig) {
+				entries[index].scheduled_backup = newConfig;
+			} else {
+				delete entries[index].scheduled_backup;
+			}
+			var tempTitle = "$:/temp/tiddlydesktop-rs/wikis/" + index;
+			$tw.wiki.setText(tempTitle, "scheduled_backup_interval", null, newConfig ? formatBackupInterval(newConfig.interval_minutes) : "");
+			$tw.wiki.setText(tempTitle, "scheduled_backup_keep", null, newConfig && newConfig.keep !== null ? String(newConfig.keep) : "");
+			$tw.wiki.setText(tempTitle, "scheduled_backup_max_age", null, newConfig && newConfig.max_age_days ? newConfig.max_age_days + " d" : "");
+			saveWikiList(entries);
+		}).catch(function(err) {
+			console.error("Failed to set scheduled backups:", err);
+		});
+	});
+
+	// Message handler: set backup count for a wiki (max backups to keep)
+	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-set-backup-count", function(event) {
+		var path = event.paramObject && event.paramObject.path;
+		var countStr = event.paramObject && event.paramObject.count;
+		if (path) {
+			// Parse count: empty string or null = default (20), "0" = unlimited, number = that count
+			var count = countStr === "" || countStr === null || countStr === undefined ? null : parseInt(countStr, 10);
+			if (count !== null && isNaN(count)) count = null;
+
+			// Update the Rust backend
+			invoke("set_wiki_backup_count", { path: path, count: count }).then(function() {
+				// Update the local wiki list entry
+				var entries = getWikiListEntries();
+				for (var i = 0; i < entries.length; i++) {
+					if (entries[i].path === path) {
+						if (count === null) {
+							delete entries[i].backup_count;
+						} else {
+							entries[i].backup_count = count;
+						}
+						// Directly update the temp tiddler field for immediate UI update
+						var tempTitle = "$:/temp/tiddlydesktop-rs/wikis/" + i;
+						$tw.wiki.setText(tempTitle, "backup_count", null, count !== null ? String(count) : "");
+						break;
+					}
+				}
+				saveWikiList(entries);
+			}).catch(function(err) {
+				console.error("Failed to set backup count:", err);
+			});
+		}
+	});
+
+	// Message handler: set group for a wiki
+	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-set-group", function(event) {
+		var path = event.paramObject && event.paramObject.path;
+		var group = event.paramObject && event.paramObject.group;
+		if (path) {
+			// Treat empty string as null (ungrouped)
+			var groupValue = group && group.trim() ? group.trim() : null;
+			// Update the Rust backend
+			invoke("set_wiki_group", { path: path, group: groupValue }).then(function() {
+				// Update the local wiki list entry
+				var entries = getWikiListEntries();
+				for (var i = 0; i < entries.length; i++) {
+					if (entries[i].path === path) {
+						if (groupValue) {
+							entries[i].group = groupValue;
+						} else {
+							delete entries[i].group;
+						}
+						break;
+					}
+				}
+				saveWikiList(entries);
+				refreshWikiList();
+			}).catch(function(err) {
+				console.error("Failed to set wiki group:", err);
+			});
+		}
+	});
+
+	// Size of custom wiki icons (stored as PNG data URIs)
+	var CUSTOM_ICON_SIZE = 64;
+
+	// Render an emoji as an icon, on a circle in the accent color if there is one
+	function renderEmojiIcon(emoji, color) {
+		var canvas = document.createElement("canvas");
+		canvas.width = canvas.height = CUSTOM_ICON_SIZE;
+		var ctx = canvas.getContext("2d");
+		var half = CUSTOM_ICON_SIZE / 2;
+		if (color) {
+			ctx.fillStyle = color;
+			ctx.beginPath();
+			ctx.arc(half, half, half, 0, 2 * Math.PI);
+			ctx.fill();
+		}
+		ctx.font = Math.round(CUSTOM_ICON_SIZE * (color ? 0.6 : 0.85)) + "px sans-serif";
+		ctx.textAlign = "center";
+		ctx.textBaseline = "middle";
+		ctx.fillText(emoji, half, half + CUSTOM_ICON_SIZE * 0.05);
+		return canvas.toDataURL("image/png");
+	}
+
+	// Let the user pick an image and scale it down to a PNG icon
+	function pickIconImage(callback) {
+		var input = document.createElement("input");
+		input.type = "file";
+		input.accept = "image/*";
+		input.addEventListener("change", function() {
+			var file = input.files && input.files[0];
+			if (!file) return;
+			var url = URL.createObjectURL(file);
+			var img = new Image();
+			img.onload = function() {
+				var canvas = document.createElement("canvas");
+				canvas.width = canvas.height = CUSTOM_ICON_SIZE;
+				var scale = CUSTOM_ICON_SIZE / Math.max(img.width, img.height);
+				var w = img.width * scale, h = img.height * scale;
+				canvas.getContext("2d").drawImage(img, (CUSTOM_ICON_SIZE - w) / 2, (CUSTOM_ICON_SIZE - h) / 2, w, h);
+				URL.revokeObjectURL(url);
+				callback(canvas.toDataURL("image/png"));
+			};
+			img.onerror = function() {
+				URL.revokeObjectURL(url);
+				alert($tw.wiki.renderText("text/plain", "text/vnd.tiddlywiki", "<<td-lingo Appearance/InvalidImage>>"));
+			};
+			img.src = url;
+		});
+		input.click();
+	}
+
+	// Message handler: set the custom icon, emoji and accent color of a wiki.
+	// An emoji is rendered into the custom icon; a picked image replaces the emoji.
+	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-set-appearance", function(event) {
+		var params = event.paramObject || {};
+		var path = params.path;
+		if (!path) return;
+		var entry = null;
+		getWikiListEntries().forEach(function(e) {
+			if (e.path === path) entry = e;
+		});
+		if (!entry) return;
+
+		function apply(appearance) {
+			invoke("set_wiki_appearance", {
+				path: path,
+				customIcon: appearance.custom_icon,
+				accentColor: appearance.accent_color,
+				emoji: appearance.emoji
+			}).then(function() {
+				var entries = getWikiListEntries();
+				entries.forEach(function(e) {
+					if (e.path === path) {
+						["custom_icon", "accent_color", "emoji"].forEach(function(field) {
+							if (appearance[field]) {
+								e[field] = appearance[field];
+							} else {
+								delete e[field];
+							}
+						});
+					}
+				});
+				saveWikiList(entries);
+				refreshWikiList();
+			}).catch(function(err) {
+				console.error("Failed to set wiki appearance:", err);
+				alert(String(err));
+			});
+		}
+
+		if (params.action === "reset") {
+			apply({ custom_icon: null, accent_color: null, emoji: null });
+		} else if (params.action === "pick-icon") {
+			pickIconImage(function(icon) {
+				apply({ custom_icon: icon, accent_color: entry.accent_color || null, emoji: null });
+			});
+		} else {
+			var emoji = (params.emoji || "").trim() || null;
+			var color = params.color || null;
+			var icon;
+			if (emoji) {
+				icon = renderEmojiIcon(emoji, color);
+			} else {
+				// Keep a picked image, drop an icon rendered from a removed emoji
+				icon = entry.emoji ? null : (entry.custom_icon || null);
+			}
+			apply({ custom_icon: icon, accent_color: color, emoji: emoji });
+		}
+	});
+
+	// Message handler: rename a group
+	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-rename-group", function(event) {
+		var oldName = event.paramObject && event.paramObject.oldName;
+		var newName = event.paramObject && event.paramObject.newName;
+		if (oldName && newName && newName.trim()) {
+			invoke("rename_wiki_group", { oldName: oldName, newName: newName.trim() }).then(function() {
+				// Update local entries
+				var entries = getWikiListEntries();
+				for (var i = 0; i < entries.length; i++) {
+					if (entries[i].group === oldName) {
+						entries[i].group = newName.trim();
+					}
+				}
+				// Transfer collapsed state to the new group name
+				var collapsed = getCollapsedGroups();
+				var idx = collapsed.indexOf(oldName);
+				if (idx !== -1) {
+					collapsed[idx] = newName.trim();
+					saveCollapsedGroups(collapsed);
+				}
+				saveWikiList(entries);
+				refreshWikiList();
+			}).catch(function(err) {
+				console.error("Failed to rename group:", err);
+			});
+		}
+	});
+
+	// Message handler: delete a group (moves wikis to ungrouped)
+	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-delete-group", function(event) {
+		var groupName = event.param || (event.paramObject && event.paramObject.group);
+		if (groupName) {
+			invoke("delete_wiki_group", { groupName: groupName }).then(function() {
+				// Update local entries
+				var entries = getWikiListEntries();
+				for (var i = 0; i < entries.length; i++) {
+					if (entries[i].group === groupName) {
+						delete entries[i].group;
+					}
+				}
+				// Remove from collapsed groups
+				var collapsed = getCollapsedGroups();
+				collapsed = collapsed.filter(function(g) { return g !== groupName; });
+				saveCollapsedGroups(collapsed);
+				saveWikiList(entries);
+				refreshWikiList();
+			}).catch(function(err) {
+				console.error("Failed to delete group:", err);
+			});
+		}
+	});
+
+	// Message handler: toggle group collapse state (persistent)
+	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-toggle-group", function(event) {
+		var groupName = event.param || (event.paramObject && event.paramObject.group);
+		var key = groupName || "Ungrouped";
+		var stateTitle = "$:/state/tiddlydesktop-rs/group-collapsed/" + key;
+		var currentState = $tw.wiki.getTiddlerText(stateTitle);
+		var newState = currentState === "yes" ? "no" : "yes";
+		// Update ephemeral state for instant UI response
+		$tw.wiki.setText(stateTitle, "text", null, newState);
+		// Persist collapsed groups to a saved tiddler
+		var collapsed = getCollapsedGroups();
+		if (newState === "yes") {
+			if (collapsed.indexOf(key) === -1) collapsed.push(key);
+		} else {
+			collapsed = collapsed.filter(function(g) { return g !== key; });
+		}
+		saveCollapsedGroups(collapsed);
+	});
+
+	// Message handler: show group manager modal
+	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-manage-groups", function(event) {
+		$tw.wiki.setText("$:/temp/tiddlydesktop-rs/show-group-manager", "text", null, "yes");
+	});
+
+	// Message handler: close group manager modal
+	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-close-group-manager", function(event) {
+		$tw.wiki.setText("$:/temp/tiddlydesktop-rs/show-group-manager", "text", null, "no");
+	});
+
+	// Set up drag-drop listeners
+	// Track recently processed drops to prevent duplicate opens if both events fire
+	var recentDrops = {};
+	var DROP_DEDUPE_MS = 500;
+
+	// Handle dropped files/folders by opening them as wikis
+	function handleDroppedPaths(paths) {
+		if (!paths || paths.length === 0) return;
+		var now = Date.now();
+		paths.forEach(function(path) {
+			// Deduplicate: skip if this path was processed recently
+			if (recentDrops[path] && (now - recentDrops[path]) < DROP_DEDUPE_MS) {
+				return;
+			}
+			recentDrops[path] = now;
+
+			// Check if it's an HTML file or potentially a folder
+			if (path.endsWith(".html") || path.endsWith(".htm")) {
+				invoke("open_wiki_window", { path: path }).then(function(entry) {
+					addToWikiList(entry);
+					refreshWikiList();
+				});
+			} else {
+				// Try to open as a folder - backend will verify if it's a valid wiki folder
+				invoke("check_is_wiki_folder", { path: path }).then(function(isFolder) {
+					if (isFolder) {
+						invoke("open_wiki_folder", { path: path }).then(function(entry) {
+							addToWikiList(entry);
+							refreshWikiList();
+						}).catch(function(err) {
+							console.error("Failed to open wiki folder:", err);
+						});
+					}
+				});
+			}
+		});
+		// Clean up old entries periodically
+		setTimeout(function() {
+			var cutoff = Date.now() - DROP_DEDUPE_MS * 2;
+			for (var p in recentDrops) {
+				if (recentDrops[p] < cutoff) {
+					delete recentDrops[p];
+				}
+			}
+		}, DROP_DEDUPE_MS * 2);
+	}
+
+	// Listen for tauri://drag-drop (Windows, and Tauri's built-in drag-drop)
+	listen("tauri://drag-drop", function(event) {
+		$tw.wiki.setText("$:/temp/tiddlydesktop-rs/drag-over", "text", null, "no");
+		handleDroppedPaths(event.payload && event.payload.paths);
+	});
+
+	// Listen for td-file-drop (Linux/macOS custom GTK/Cocoa drag-drop)
+	listen("td-file-drop", function(event) {
+		$tw.wiki.setText("$:/temp/tiddlydesktop-rs/drag-over", "text", null, "no");
+		handleDroppedPaths(event.payload && event.payload.paths);
+	});
+
+	// Visual feedback for drag-over state (Tauri built-in events - Windows)
+	listen("tauri://drag-enter", function() {
+		$tw.wiki.setText("$:/temp/tiddlydesktop-rs/drag-over", "text", null, "yes");
+	});
+
+	listen("tauri://drag-leave", function() {
+		$tw.wiki.setText("$:/temp/tiddlydesktop-rs/drag-over", "text", null, "no");
+	});
+
+	// Visual feedback for drag-over state (custom GTK/Cocoa events - Linux/macOS)
+	listen("td-drag-motion", function() {
+		$tw.wiki.setText("$:/temp/tiddlydesktop-rs/drag-over", "text", null, "yes");
+	});
+
+	listen("td-drag-leave", function() {
+		$tw.wiki.setText("$:/temp/tiddlydesktop-rs/drag-over", "text", null, "no");
+	});
+
+	// Fix for stale tc-dragover class when dragging between droppable widgets
+	// The droppable widget's currentlyEntered tracking can get out of sync
+	// Ensure only one droppable has the highlight at a time
+	document.addEventListener("dragenter", function(event) {
+		var target = event.target.closest(".tc-droppable");
+		var elements = document.querySelectorAll(".tc-dragover");
+		for(var i = 0; i < elements.length; i++) {
+			if(elements[i] !== target) {
+				elements[i].classList.remove("tc-dragover");
+			}
+		}
+	});
+
+	// Listen for wiki list changes (e.g., when a wiki is opened from file explorer)
+	listen("wiki-list-changed", function(event) {
+		if(event.payload) {
+			addToWikiList(event.payload);
+		}
+		refreshWikiList();
+	});
+
+	// Listen for drives being plugged in or removed (desktop) to enable/disable entries
+	listen("wiki-storage-changed", function(event) {
+		applyWikiStorageStatus(event.payload);
+	});
+
+	// Listen for wiki process closed events to update open/closed state (desktop)
+	listen("wiki-process-closed", function() {
+		refreshWikiList();
+	});
+
+	// A wiki reported JavaScript errors, or they were cleared (desktop)
+	listen("wiki-js-errors-changed", function() {
+		checkJsErrors();
+	});
+
+	// Wikis that kept running after a previous landing page crashed are back under management
+	listen("wiki-process-adopted", function() {
+		refreshWikiList();
+	});
+
+	// Expose a global function for Android to call from Kotlin when a WikiActivity closes.
+	// MainActivity's BroadcastReceiver calls evaluateJavascript() with this.
+	window.__tdWikiClosed = function(wikiPath) {
+		var entries = getWikiListEntries();
+		entries.forEach(function(entry, index) {
+			if (entry.path === wikiPath) {
+				$tw.wiki.setText("$:/temp/tiddlydesktop-rs/wikis/" + index, "is_open", null, "no");
+			}
+		});
+	};
+
+	// Listen for favicon updates from wiki windows (updates in real-time without full refresh)
+	window.addEventListener("td-favicon-updated", function(event) {
+		var path = event.detail && event.detail.path;
+		var favicon = event.detail && event.detail.favicon;
+		if (!path) return;
+
+		// Update the persistent wiki list entry
+		var entries = getWikiListEntries();
+		var updated = false;
+		for (var i = 0; i < entries.length; i++) {
+			if (entries[i].path === path) {
+				entries[i].favicon = favicon;
+				// Also update the temp tiddler directly for immediate UI update
+				// (a custom icon stays in place of the favicon)
+				var tempTitle = "$:/temp/tiddlydesktop-rs/wikis/" + i;
+				$tw.wiki.setText(tempTitle, "favicon", null, entries[i].custom_icon || favicon || "");
+				updated = true;
+				break;
+			}
+		}
+		if (updated) {
+			saveWikiList(entries);
+		}
+	});
+
+	// Clean up stale $:/state/ and $:/temp/ tiddlers from previous sessions.
+	// These get persisted into the wiki HTML on save and cause UI glitches
 	// on next launch (e.g. popup dropdowns appearing open).
 	// NOTE: Do NOT delete all $:/temp/tiddlydesktop-rs/ — that would wipe
 	// is-mobile, is-android, relay-sync-url, etc. Only delete specific stale prefixes.
@@ -1590,6 +2646,7 @@ exports.startup = function(callback) {
 			title.indexOf("$:/state/relay-room-details/") === 0 ||
 			title.indexOf("$:/state/group-popup/") === 0 ||
 			title.indexOf("$:/state/backup-count-popup/") === 0 ||
+			title.indexOf("$:/state/scheduled-backup-popup/") === 0 ||
 			title.indexOf("$:/state/link-wiki-popup") === 0 ||
 			title.indexOf("$:/state/tiddlydesktop-rs/") === 0 ||
 			title.indexOf("$:/temp/tiddlydesktop-rs/wikis/") === 0 ||
@@ -1597,136 +2654,1094 @@ exports.startup = function(callback) {
 			title.indexOf("$:/temp/tiddlydesktop-rs/relay-room-details/") === 0 ||
 			title.indexOf("$:/temp/tiddlydesktop-rs/relay-room-password/") === 0 ||
 			title.indexOf("$:/temp/tiddlydesktop-rs/relay-room-name-edit/") === 0 ||
+			title.indexOf("$:/temp/tiddlydesktop-rs/relay-room-qr/") === 0 ||
 			title.indexOf("$:/temp/tiddlydesktop-rs/relay-server-room/") === 0 ||
 			title.indexOf("$:/temp/new-group-name/") === 0 ||
 			title.indexOf("$:/temp/backup-count-input/") === 0) {
 			$tw.wiki.deleteTiddler(title);
 		}
-	});
+	});
+
+	// Always load wiki list from Rust JSON config (source of truth).
+	// The JSON is updated atomically with backups on every save, making it
+	// more reliable than the HTML tiddler which can be affected by browser
+	// caching or partial saves. Fall back to the HTML tiddler only if JSON
+	// is empty (e.g. first launch or JSON corruption).
+	invoke("get_recent_files").then(function(jsonEntries) {
+		if (jsonEntries && jsonEntries.length > 0) {
+			// JSON is the source of truth — update the WikiList tiddler
+			$tw.wiki.addTiddler({
+				title: "$:/TiddlyDesktop/WikiList",
+				type: "application/json",
+				text: JSON.stringify(jsonEntries, null, 2)
+			});
+			refreshWikiList();
+		} else {
+			// JSON is empty — try the HTML tiddler as fallback
+			refreshWikiList();
+			var htmlEntries = getWikiListEntries();
+			if (htmlEntries.length > 0) {
+				console.log("[TiddlyDesktop] JSON config empty — using " +
+					htmlEntries.length + " entries from HTML tiddler, syncing to JSON");
+				// Sync HTML entries back to JSON so this doesn't happen again
+				invoke("save_full_wiki_list", { entries: htmlEntries }).catch(function(err) {
+					console.error("[TiddlyDesktop] Failed to sync HTML entries to JSON:", err);
+				});
+			}
+		}
+	}).catch(function(err) {
+		console.error("[TiddlyDesktop] Failed to load JSON config:", err);
+		// Fall back to HTML tiddler
+		refreshWikiList();
+	});
+
+	// On Android, merge favicons from disk files into wiki list entries.
+	// WikiActivity saves favicons to files in the :wiki process, but can't send
+	// Tauri events to the main process. So we load them via get_recent_files
+	// (which reads favicon files on Android) and merge into the wiki list tiddler.
+	function mergeFaviconsFromDisk() {
+		if (!isAndroid) return;
+		invoke("get_recent_files").then(function(rustEntries) {
+			if (!rustEntries || !rustEntries.length) return;
+			var faviconMap = {};
+			rustEntries.forEach(function(e) {
+				if (e.favicon) {
+					faviconMap[e.path] = e.favicon;
+				}
+			});
+			var entries = getWikiListEntries();
+			var updated = false;
+			entries.forEach(function(entry, index) {
+				if (faviconMap[entry.path] && entry.favicon !== faviconMap[entry.path]) {
+					entry.favicon = faviconMap[entry.path];
+					var tempTitle = "$:/temp/tiddlydesktop-rs/wikis/" + index;
+					$tw.wiki.setText(tempTitle, "favicon", null, entry.favicon);
+					updated = true;
+				}
+			});
+			if (updated) {
+				saveWikiList(entries);
+				console.log("[TiddlyDesktop] Merged favicons from disk files");
+			}
+		}).catch(function(err) {
+			console.log("[TiddlyDesktop] Failed to load favicons from Rust:", err);
+		});
+	}
+	mergeFaviconsFromDisk();
+
+	// Re-merge favicons when returning to the landing page (e.g., after closing a wiki).
+	// WikiActivity extracts favicons in the :wiki process and saves them to disk,
+	// so we need to re-check when the user comes back.
+	if (isAndroid) {
+		document.addEventListener("visibilitychange", function() {
+			if (document.visibilityState === "visible") {
+				mergeFaviconsFromDisk();
+				checkPendingWikiOpen();
+			}
+		});
+	}
+
+	// Check if a pending wiki open was requested (from widget or Quick Capture).
+	// MainActivity writes a pending file, and we consume it here.
+	function checkPendingWikiOpen() {
+		invoke("get_pending_widget_wiki").then(function(pendingWiki) {
+			if (pendingWiki && pendingWiki.path) {
+				console.log("[TiddlyDesktop] Opening pending wiki: " + pendingWiki.path);
+				var path = pendingWiki.path;
+				var isFolder = !!pendingWiki.is_folder;
+				var navigateToTiddler = pendingWiki.navigate_to_tiddler || null;
+
+				// Look up backup settings from the wiki list
+				var entries = getWikiListEntries();
+				var entry = null;
+				for (var i = 0; i < entries.length; i++) {
+					if (entries[i].path === path) {
+						entry = entries[i];
+						break;
+					}
+				}
+
+				var command = isFolder ? "open_wiki_folder" : "open_wiki_window";
+				var params = { path: path };
+				if (!isFolder && entry) {
+					params.backupsEnabled = entry.backups_enabled !== false;
+					params.backupCount = entry.backup_count !== undefined ? entry.backup_count : null;
+				}
+				if (navigateToTiddler) {
+					params.tiddlerTitle = navigateToTiddler;
+				}
+
+				invoke(command, params).then(function(resultEntry) {
+					addToWikiList(resultEntry);
+					refreshWikiList();
+				}).catch(function(err) {
+					console.error("[TiddlyDesktop] Failed to open pending wiki:", err);
+				});
+			}
+		}).catch(function(err) {
+			// No pending wiki — normal case
+		});
+	}
+	if (isAndroid) {
+		checkPendingWikiOpen();
+	}
+
+	// ========================================
+	// Scratch Directory Handlers (desktop only)
+	// ========================================
+	if (!isAndroid) {
+		function refreshScratchDir() {
+			invoke("get_scratch_dir").then(function(info) {
+				$tw.wiki.setText("$:/temp/tiddlydesktop-rs/scratch-dir", "text", null, info.path);
+				$tw.wiki.setText("$:/temp/tiddlydesktop-rs/scratch-dir-custom", "text", null, info.custom ? "yes" : "no");
+			}).catch(function(err) {
+				console.error("Failed to get scratch directory:", err);
+			});
+		}
+		refreshScratchDir();
+
+		// Message handler: choose a custom scratch directory
+		$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-set-scratch-dir", function(event) {
+			openDialog({
+				directory: true,
+				multiple: false
+			}).then(function(folder) {
+				if (folder) {
+					invoke("set_scratch_dir", { path: folder }).then(function() {
+						refreshScratchDir();
+					}).catch(function(err) {
+						console.error("Failed to set scratch directory:", err);
+						alert("Failed to set scratch folder: " + err);
+					});
+				}
+			}).catch(function(err) {
+				console.error("openDialog error:", err);
+			});
+		});
+
+		// Message handler: restore the default scratch directory
+		$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-clear-scratch-dir", function(event) {
+			invoke("set_scratch_dir", { path: "" }).then(function() {
+				refreshScratchDir();
+			}).catch(function(err) {
+				console.error("Failed to reset scratch directory:", err);
+			});
+		});
+
+		// ========================================
+		// Watched Folders (desktop only)
+		// ========================================
+		function showWatchedFolders(folders) {
+			$tw.wiki.filterTiddlers("[prefix[$:/temp/tiddlydesktop-rs/watched-folders/]]").forEach(function(title) {
+				$tw.wiki.deleteTiddler(title);
+			});
+			(folders || []).forEach(function(folder, index) {
+				$tw.wiki.addTiddler({ title: "$:/temp/tiddlydesktop-rs/watched-folders/" + index, text: folder });
+			});
+		}
+		invoke("get_watched_folders").then(showWatchedFolders).catch(function(err) {
+			console.error("Failed to get watched folders:", err);
+		});
+
+		// What the last scan of the watched folders changed in the list
+		function showWatchedFoldersSummary(summary) {
+			if (!summary) return;
+			$tw.wiki.setText("$:/temp/tiddlydesktop-rs/watched-folders-summary", "text", null,
+				$tw.wiki.renderText("text/plain", "text/vnd.tiddlywiki", "<<td-lingo CustomPaths/WatchedFoldersSummary>>")
+					.replace("$added$", summary.added.length)
+					.replace("$missing$", summary.missing.length)
+					.replace("$reappeared$", summary.reappeared.length));
+		}
+		invoke("get_watched_folders_summary").then(showWatchedFoldersSummary).catch(function(err) {
+			console.error("Failed to get watched folders summary:", err);
+		});
+		listen("watched-folders-reconciled", function(event) {
+			showWatchedFoldersSummary(event.payload);
+		});
+
+		// Reload the list when wikis were discovered in a watched folder,
+		// or went missing or came back. Rust has already updated the JSON
+		// config (source of truth).
+		listen("watched-folders-changed", function() {
+			invoke("get_recent_files").then(function(jsonEntries) {
+				$tw.wiki.addTiddler({
+					title: "$:/TiddlyDesktop/WikiList",
+					type: "application/json",
+					text: JSON.stringify(jsonEntries, null, 2)
+				});
+				$tw.rootWidget.dispatchEvent({type: "tm-auto-save-wiki"});
+				refreshWikiList();
+			}).catch(function(err) {
+				console.error("[TiddlyDesktop] Failed to reload wiki list:", err);
+			});
+		});
+
+		// Message handler: add a watched folder
+		$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-add-watched-folder", function(event) {
+			openDialog({
+				directory: true,
+				multiple: false
+			}).then(function(folder) {
+				if (folder) {
+					invoke("add_watched_folder", { path: folder }).then(showWatchedFolders).catch(function(err) {
+						console.error("Failed to add watched folder:", err);
+						alert("Failed to add watched folder: " + err);
+					});
+				}
+			}).catch(function(err) {
+				console.error("openDialog error:", err);
+			});
+		});
+
+		// Message handler: add several wiki files, or all wikis below a folder, in one go
+		$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-add-wikis", function(event) {
+			var folder = !!(event.paramObject && event.paramObject.folder === "yes");
+			invoke("pick_and_register_wikis", { folder: folder }).then(function(summary) {
+				if (summary.added.length > 0) {
+					return invoke("get_recent_files").then(function(jsonEntries) {
+						$tw.wiki.addTiddler({
+							title: "$:/TiddlyDesktop/WikiList",
+							type: "application/json",
+							text: JSON.stringify(jsonEntries, null, 2)
+						});
+						$tw.rootWidget.dispatchEvent({type: "tm-auto-save-wiki"});
+						refreshWikiList();
+						return summary;
+					});
+				}
+				return summary;
+			}).then(function(summary) {
+				if (summary.failed.length > 0) {
+					alert("Added " + summary.added.length + " wikis. These could not be added:\n\n" +
+						summary.failed.map(function(f) { return f.path + ": " + f.error; }).join("\n"));
+				}
+			}).catch(function(err) {
+				console.error("pick_and_register_wikis error:", err);
+				alert("Failed to add wikis: " + err);
+			});
+		});
+
+		// Message handler: import the wiki list of classic TiddlyDesktop with the wikis'
+		// favicons, window states and backup settings (pick="yes": pick its data folder)
+		$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-import-classic", function(event) {
+			var pick = event.paramObject && event.paramObject.pick === "yes";
+			var dir = pick ? openDialog({ directory: true, multiple: false }) : Promise.resolve(null);
+			dir.then(function(folder) {
+				if (pick && !folder) return;
+				return invoke("import_classic_wikis", { dir: folder || null }).then(function(report) {
+					var lines = [
+						"Wikis added: " + report.added.length + (report.alreadyListed ? " (" + report.alreadyListed + " already in the list)" : ""),
+						"Favicons: " + report.favicons,
+						"Window sizes and positions: " + report.windowStates,
+						"Backup settings: " + report.backupSettings
+					];
+					if (report.failed.length > 0) {
+						lines.push("", "These could not be added:", report.failed.map(function(f) { return f.path + ": " + f.error; }).join("\n"));
+					}
+					window.__TAURI__.dialog.message(lines.join("\n"), {title: "TiddlyDesktop", kind: "info"});
+					if (report.added.length > 0 || report.favicons > 0 || report.backupSettings > 0) {
+						return invoke("get_recent_files").then(function(jsonEntries) {
+							$tw.wiki.addTiddler({
+								title: "$:/TiddlyDesktop/WikiList",
+								type: "application/json",
+								text: JSON.stringify(jsonEntries, null, 2)
+							});
+							$tw.rootWidget.dispatchEvent({type: "tm-auto-save-wiki"});
+							refreshWikiList();
+						});
+					}
+				});
+			}).catch(function(err) {
+				console.error("import_classic_wikis error:", err);
+				alert("Failed to import from classic TiddlyDesktop: " + err);
+			});
+		});
+
+		// Message handler: stop watching a folder (discovered wikis stay in the list)
+		$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-remove-watched-folder", function(event) {
+			var path = event.paramObject && event.paramObject.path;
+			if (path) {
+				invoke("remove_watched_folder", { path: path }).then(showWatchedFolders).catch(function(err) {
+					console.error("Failed to remove watched folder:", err);
+				});
+			}
+		});
+	}
+
+	// ========================================
+	// First-Run Setup (desktop only)
+	// ========================================
+	if (!isAndroid) {
+		var FIRST_RUN = "$:/temp/tiddlydesktop-rs/first-run";
+
+		function setFirstRunField(field, value) {
+			$tw.wiki.setText(FIRST_RUN, field, null, value);
+		}
+
+		function reloadWikiListFromDisk() {
+			return invoke("get_recent_files").then(function(jsonEntries) {
+				$tw.wiki.addTiddler({
+					title: "$:/TiddlyDesktop/WikiList",
+					type: "application/json",
+					text: JSON.stringify(jsonEntries, null, 2)
+				});
+				$tw.rootWidget.dispatchEvent({type: "tm-auto-save-wiki"});
+				refreshWikiList();
+			});
+		}
+
+		// Show how an import went in the wizard
+		function showImportSummary(summary) {
+			var imported = parseInt($tw.wiki.getTiddler(FIRST_RUN).fields.imported || "0", 10) + summary.added.length;
+			setFirstRunField("imported", String(imported));
+			setFirstRunField("import-failed", summary.failed.map(function(f) { return f.path + ": " + f.error; }).join("\n"));
+			if (summary.added.length > 0) {
+				return reloadWikiListFromDisk();
+			}
+		}
+
+		invoke("get_first_run_state").then(function(state) {
+			if (!state.pending) return;
+			$tw.wiki.addTiddler({
+				title: FIRST_RUN,
+				step: "storage",
+				"data-dir": state.dataDir,
+				portable: state.portable ? "yes" : "no",
+				"portable-available": state.portableAvailable ? "yes" : "no",
+				"classic-dir": state.classicDir || "",
+				"starter-path": state.starterPath || "",
+				"create-starter": state.starterPath ? "yes" : "no",
+				backups: "yes",
+				"backup-count": "",
+				"lan-sync": "no",
+				imported: "0"
+			});
+		}).catch(function(err) {
+			console.error("Failed to get first-run state:", err);
+		});
+
+		// Message handler: keep the app data next to the executable (the app restarts)
+		$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-first-run-portable", function() {
+			invoke("use_portable_mode").catch(function(err) {
+				console.error("use_portable_mode error:", err);
+				alert("Failed to switch to portable mode: " + err);
+			});
+		});
+
+		// Message handler: import the wikis of classic TiddlyDesktop (pick="yes": pick its data folder)
+		$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-first-run-import-classic", function(event) {
+			var pick = event.paramObject && event.paramObject.pick === "yes";
+			var dir = pick ? openDialog({ directory: true, multiple: false }) : Promise.resolve(null);
+			dir.then(function(folder) {
+				if (pick && !folder) return;
+				return invoke("import_classic_wikis", { dir: folder || null }).then(showImportSummary);
+			}).catch(function(err) {
+				console.error("import_classic_wikis error:", err);
+				alert("Failed to import wikis: " + err);
+			});
+		});
+
+		// Message handler: add all wikis below a folder
+		$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-first-run-import-folder", function() {
+			invoke("pick_and_register_wikis", { folder: true }).then(showImportSummary).catch(function(err) {
+				console.error("pick_and_register_wikis error:", err);
+				alert("Failed to add wikis: " + err);
+			});
+		});
+
+		// Message handler: apply the choices (skip="yes": keep the defaults) and open the starter wiki
+		$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-first-run-finish", function(event) {
+			var fields = $tw.wiki.getTiddler(FIRST_RUN).fields;
+			var skip = event.paramObject && event.paramObject.skip === "yes";
+			var choices = skip ? { backups: true } : {
+				backups: fields.backups === "yes",
+				backupCount: parseInt(fields["backup-count"], 10) || null,
+				lanSync: fields["lan-sync"] === "yes",
+				starterPath: fields["create-starter"] === "yes" ? fields["starter-path"] || null : null
+			};
+			invoke("finish_first_run", { choices: choices }).then(function(starterPath) {
+				$tw.wiki.deleteTiddler(FIRST_RUN);
+				return reloadWikiListFromDisk().then(function() {
+					if (starterPath) {
+						return invoke("open_wiki_window", { path: starterPath });
+					}
+				});
+			}).catch(function(err) {
+				console.error("finish_first_run error:", err);
+				alert("Failed to finish the setup: " + err);
+			});
+		});
+	}
+
+	// ========================================
+	// App Lock (desktop only)
+	// ========================================
+	if (!isAndroid) {
+		function applyAppLockSettings(settings) {
+			$tw.wiki.setText("$:/temp/tiddlydesktop-rs/app-lock-enabled", "text", null, settings.enabled ? "yes" : "no");
+			$tw.wiki.setText("$:/temp/tiddlydesktop-rs/app-lock-minutes", "text", null, String(settings.autoLockMinutes));
+		}
+		invoke("get_app_lock_settings").then(applyAppLockSettings).catch(function(err) {
+			console.error("Failed to get app lock settings:", err);
+		});
+
+		// Ask for one or more secrets in a modal with password fields (never stored in tiddlers).
+		// Resolves to an array of the entered values, or null if cancelled.
+		function askSecrets(labels) {
+			return new Promise(function(resolve) {
+				var overlay = document.createElement("div");
+				overlay.className = "td-conflict-overlay";
+				var dialog = document.createElement("form");
+				dialog.className = "td-conflict-dialog td-app-lock-dialog";
+				var inputs = labels.map(function(label) {
+					var row = document.createElement("label");
+					row.className = "td-app-lock-field";
+					row.textContent = $tw.wiki.renderText("text/plain", "text/vnd.tiddlywiki", "<<td-lingo " + label + ">>");
+					var input = document.createElement("input");
+					input.type = "password";
+					input.autocomplete = "off";
+					row.appendChild(input);
+					dialog.appendChild(row);
+					return input;
+				});
+				var buttons = document.createElement("div");
+				buttons.className = "td-conflict-footer";
+				var cancelBtn = document.createElement("button");
+				cancelBtn.type = "button";
+				cancelBtn.className = "td-button";
+				cancelBtn.textContent = $tw.wiki.renderText("text/plain", "text/vnd.tiddlywiki", "<<td-lingo Buttons/Cancel>>") || "Cancel";
+				var okBtn = document.createElement("button");
+				okBtn.type = "submit";
+				okBtn.className = "td-button td-button-open";
+				okBtn.textContent = "OK";
+				buttons.appendChild(cancelBtn);
+				buttons.appendChild(okBtn);
+				dialog.appendChild(buttons);
+				overlay.appendChild(dialog);
+				document.body.appendChild(overlay);
+				function finish(result) {
+					if (overlay.parentNode) overlay.parentNode.removeChild(overlay);
+					resolve(result);
+				}
+				cancelBtn.addEventListener("click", function() { finish(null); });
+				dialog.addEventListener("submit", function(e) {
+					e.preventDefault();
+					finish(inputs.map(function(input) { return input.value; }));
+				});
+				inputs[0].focus();
+			});
+		}
+
+		// Message handler: set or change the PIN/password
+		$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-set-app-lock", function(event) {
+			var enabled = $tw.wiki.getTiddlerText("$:/temp/tiddlydesktop-rs/app-lock-enabled") === "yes";
+			var labels = enabled ? ["AppLock/Current", "AppLock/New", "AppLock/Repeat"] : ["AppLock/New", "AppLock/Repeat"];
+			askSecrets(labels).then(function(values) {
+				if (!values) return;
+				var current = enabled ? values.shift() : null;
+				if (values[0] !== values[1]) {
+					alert($tw.wiki.renderText("text/plain", "text/vnd.tiddlywiki", "<<td-lingo AppLock/Mismatch>>"));
+					return;
+				}
+				invoke("set_app_lock_secret", { currentSecret: current, newSecret: values[0] }).then(applyAppLockSettings).catch(function(err) {
+					console.error("Failed to set app lock:", err);
+					alert("Failed to set PIN/password: " + err);
+				});
+			});
+		});
+
+		// Message handler: remove the PIN/password
+		$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-remove-app-lock", function(event) {
+			askSecrets(["AppLock/Current"]).then(function(values) {
+				if (!values) return;
+				invoke("set_app_lock_secret", { currentSecret: values[0], newSecret: "" }).then(applyAppLockSettings).catch(function(err) {
+					console.error("Failed to remove app lock:", err);
+					alert("Failed to remove PIN/password: " + err);
+				});
+			});
+		});
+
+		// Message handler: set the inactivity period (minutes, 0 = never)
+		$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-set-app-lock-timeout", function(event) {
+			var minutes = parseInt(event.paramObject && event.paramObject.minutes, 10) || 0;
+			invoke("set_app_lock_timeout", { minutes: minutes }).then(applyAppLockSettings).catch(function(err) {
+				console.error("Failed to set auto-lock:", err);
+			});
+		});
+
+		// Message handler: lock the app now
+		$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-lock-app-now", function(event) {
+			invoke("lock_app_now").catch(function(err) {
+				console.error("Failed to lock app:", err);
+				alert("Failed to lock: " + err);
+			});
+		});
+	}
+
+	// ========================================
+	// Background Window Throttling (desktop only)
+	// ========================================
+	if (!isAndroid) {
+		function applyThrottleMinutes(minutes) {
+			$tw.wiki.setText("$:/temp/tiddlydesktop-rs/throttle-minutes", "text", null, String(minutes));
+		}
+		invoke("get_throttle_hidden_minutes").then(applyThrottleMinutes).catch(function(err) {
+			console.error("Failed to get throttle setting:", err);
+		});
+
+		// Message handler: throttle wiki windows after being minimized/hidden for N minutes (0 = never)
+		$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-set-throttle-minutes", function(event) {
+			var minutes = parseInt(event.paramObject && event.paramObject.minutes, 10) || 0;
+			invoke("set_throttle_hidden_minutes", { minutes: minutes }).then(function() {
+				applyThrottleMinutes(minutes);
+			}).catch(function(err) {
+				console.error("Failed to set throttle setting:", err);
+			});
+		});
+	}
+
+	// ========================================
+	// Session Restore (desktop only)
+	// ========================================
+	if (!isAndroid) {
+		var lastSessionTitle = "$:/temp/tiddlydesktop-rs/last-session";
+		function showLastSession(session) {
+			$tw.wiki.setText("$:/temp/tiddlydesktop-rs/session-restore-mode", "text", null, session.mode);
+			if (session.mode === "ask" && session.wikis.length > 0) {
+				$tw.wiki.addTiddler(new $tw.Tiddler({
+					title: lastSessionTitle,
+					count: String(session.wikis.length),
+					crashed: session.crashed ? "yes" : "no",
+					wikis: session.wikis.map(function(wiki) { return wiki.path; }).join("\n")
+				}));
+			} else {
+				$tw.wiki.deleteTiddler(lastSessionTitle);
+			}
+		}
+		invoke("get_last_session").then(showLastSession).catch(function(err) {
+			console.error("Failed to get last session:", err);
+		});
 
-	// Always load wiki list from Rust JSON config (source of truth).
-	// The JSON is updated atomically with backups on every save, making it
-	// more reliable than the HTML tiddler which can be affected by browser
-	// caching or partial saves. Fall back to the HTML tiddler only if JSON
-	// is empty (e.g. first launch or JSON corruption).
-	invoke("get_recent_files").then(function(jsonEntries) {
-		if (jsonEntries && jsonEntries.length > 0) {
-			// JSON is the source of truth — update the WikiList tiddler
-			$tw.wiki.addTiddler({
-				title: "$:/TiddlyDesktop/WikiList",
-				type: "application/json",
-				text: JSON.stringify(jsonEntries, null, 2)
+		// Message handler: reopen the wikis that were open when the app last quit
+		$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-restore-session", function(event) {
+			$tw.wiki.deleteTiddler(lastSessionTitle);
+			invoke("restore_last_session").catch(function(err) {
+				console.error("Failed to restore session:", err);
+				alert("Failed to restore session: " + err);
 			});
-			refreshWikiList();
-		} else {
-			// JSON is empty — try the HTML tiddler as fallback
-			refreshWikiList();
-			var htmlEntries = getWikiListEntries();
-			if (htmlEntries.length > 0) {
-				console.log("[TiddlyDesktop] JSON config empty — using " +
-					htmlEntries.length + " entries from HTML tiddler, syncing to JSON");
-				// Sync HTML entries back to JSON so this doesn't happen again
-				invoke("save_full_wiki_list", { entries: htmlEntries }).catch(function(err) {
-					console.error("[TiddlyDesktop] Failed to sync HTML entries to JSON:", err);
+		});
+
+		// Message handler: don't offer the last session's wikis again
+		$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-dismiss-session", function(event) {
+			$tw.wiki.deleteTiddler(lastSessionTitle);
+			invoke("dismiss_last_session").catch(function(err) {
+				console.error("Failed to dismiss session:", err);
+			});
+		});
+
+		// Message handler: reopen the last session's wikis on launch (ask / always / never)
+		$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-set-session-restore-mode", function(event) {
+			var mode = event.paramObject && event.paramObject.mode;
+			invoke("set_session_restore_mode", { mode: mode }).then(function() {
+				$tw.wiki.setText("$:/temp/tiddlydesktop-rs/session-restore-mode", "text", null, mode);
+			}).catch(function(err) {
+				console.error("Failed to set session restore mode:", err);
+				alert("Failed to set session restore mode: " + err);
+			});
+		});
+	}
+
+	// ========================================
+	// Tray Quick Actions (desktop only)
+	// ========================================
+	if (!isAndroid) {
+		var quickActions = [];
+		function showQuickActions(actions) {
+			quickActions = actions || [];
+			$tw.wiki.filterTiddlers("[prefix[$:/temp/tiddlydesktop-rs/quick-actions/]]").forEach(function(title) {
+				$tw.wiki.deleteTiddler(title);
+			});
+			var entries = getWikiListEntries();
+			quickActions.forEach(function(action, index) {
+				var entry = entries.filter(function(e) { return e.path === action.wikiPath; })[0];
+				$tw.wiki.addTiddler({
+					title: "$:/temp/tiddlydesktop-rs/quick-actions/" + index,
+					index: String(index),
+					kind: action.kind,
+					wiki: entry ? entry.filename : action.wikiPath,
+					hotkey: action.hotkey || ""
 				});
-			}
+			});
 		}
-	}).catch(function(err) {
-		console.error("[TiddlyDesktop] Failed to load JSON config:", err);
-		// Fall back to HTML tiddler
-		refreshWikiList();
-	});
+		function saveQuickActions(actions) {
+			return invoke("set_quick_actions", { actions: actions }).then(function() {
+				showQuickActions(actions);
+			});
+		}
+		invoke("get_quick_actions").then(showQuickActions).catch(function(err) {
+			console.error("Failed to get quick actions:", err);
+		});
 
-	// On Android, merge favicons from disk files into wiki list entries.
-	// WikiActivity saves favicons to files in the :wiki process, but can't send
-	// Tauri events to the main process. So we load them via get_recent_files
-	// (which reads favicon files on Android) and merge into the wiki list tiddler.
-	function mergeFaviconsFromDisk() {
-		if (!isAndroid) return;
-		invoke("get_recent_files").then(function(rustEntries) {
-			if (!rustEntries || !rustEntries.length) return;
-			var faviconMap = {};
-			rustEntries.forEach(function(e) {
-				if (e.favicon) {
-					faviconMap[e.path] = e.favicon;
-				}
+		// Message handler: add the quick action from the form
+		$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-add-quick-action", function() {
+			var form = $tw.wiki.getTiddler("$:/temp/tiddlydesktop-rs/new-quick-action");
+			var fields = form ? form.fields : {};
+			if (!fields.path) return;
+			var action = { kind: fields.kind || "new-tiddler", wikiPath: fields.path, hotkey: fields.hotkey || null };
+			saveQuickActions(quickActions.concat([action])).then(function() {
+				$tw.wiki.deleteTiddler("$:/temp/tiddlydesktop-rs/new-quick-action");
+			}).catch(function(err) {
+				console.error("Failed to add quick action:", err);
+				alert("Failed to add quick action: " + err);
+			});
+		});
+
+		// Message handler: remove a quick action
+		$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-remove-quick-action", function(event) {
+			var index = parseInt(event.paramObject && event.paramObject.index, 10);
+			saveQuickActions(quickActions.filter(function(action, i) { return i !== index; })).catch(function(err) {
+				console.error("Failed to remove quick action:", err);
+			});
+		});
+	}
+
+	// ========================================
+	// Allowed Commands (desktop only)
+	// ========================================
+	if (!isAndroid) {
+		var allowedCommands = [];
+		function showAllowedCommands(commands) {
+			allowedCommands = commands || [];
+			$tw.wiki.filterTiddlers("[prefix[$:/temp/tiddlydesktop-rs/allowed-commands/]]").forEach(function(title) {
+				$tw.wiki.deleteTiddler(title);
 			});
 			var entries = getWikiListEntries();
-			var updated = false;
-			entries.forEach(function(entry, index) {
-				if (faviconMap[entry.path] && entry.favicon !== faviconMap[entry.path]) {
-					entry.favicon = faviconMap[entry.path];
-					var tempTitle = "$:/temp/tiddlydesktop-rs/wikis/" + index;
-					$tw.wiki.setText(tempTitle, "favicon", null, entry.favicon);
-					updated = true;
-				}
+			allowedCommands.forEach(function(command, index) {
+				var entry = command.wikiPath && entries.filter(function(e) { return e.path === command.wikiPath; })[0];
+				$tw.wiki.addTiddler({
+					title: "$:/temp/tiddlydesktop-rs/allowed-commands/" + index,
+					index: String(index),
+					name: command.name,
+					commandline: [command.program].concat(command.args).join(" "),
+					wiki: command.wikiPath ? (entry ? entry.filename : command.wikiPath) : ""
+				});
 			});
-			if (updated) {
-				saveWikiList(entries);
-				console.log("[TiddlyDesktop] Merged favicons from disk files");
-			}
+		}
+		function saveAllowedCommands(commands) {
+			return invoke("set_allowed_commands", { commands: commands }).then(function() {
+				showAllowedCommands(commands);
+			});
+		}
+		invoke("get_allowed_commands").then(showAllowedCommands).catch(function(err) {
+			console.error("Failed to get allowed commands:", err);
+		});
+
+		// Message handler: add the allowed command from the form
+		$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-add-allowed-command", function() {
+			var form = $tw.wiki.getTiddler("$:/temp/tiddlydesktop-rs/new-allowed-command");
+			var fields = form ? form.fields : {};
+			if (!fields.name || !fields.program) return;
+			var command = {
+				name: fields.name.trim(),
+				program: fields.program.trim(),
+				args: $tw.utils.parseStringArray(fields.args || "") || [],
+				workingDir: fields["working-dir"] || null,
+				wikiPath: fields.path || null
+			};
+			saveAllowedCommands(allowedCommands.concat([command])).then(function() {
+				$tw.wiki.deleteTiddler("$:/temp/tiddlydesktop-rs/new-allowed-command");
+			}).catch(function(err) {
+				console.error("Failed to add allowed command:", err);
+				alert("Failed to add allowed command: " + err);
+			});
+		});
+
+		// Message handler: remove an allowed command
+		$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-remove-allowed-command", function(event) {
+			var index = parseInt(event.paramObject && event.paramObject.index, 10);
+			saveAllowedCommands(allowedCommands.filter(function(command, i) { return i !== index; })).catch(function(err) {
+				console.error("Failed to remove allowed command:", err);
+			});
+		});
+	}
+
+	// ========================================
+	// Custom CSS/JS snippets (desktop only)
+	// ========================================
+	if (!isAndroid) {
+		function showCustomSnippets(config) {
+			$tw.wiki.filterTiddlers("[prefix[$:/temp/tiddlydesktop-rs/custom-snippets/]]").forEach(function(title) {
+				$tw.wiki.deleteTiddler(title);
+			});
+			var entries = getWikiListEntries();
+			(config.snippets || []).forEach(function(snippet) {
+				var entry = entries.filter(function(e) { return e.path === snippet.wikiPath; })[0];
+				$tw.wiki.addTiddler({
+					title: "$:/temp/tiddlydesktop-rs/custom-snippets/" + snippet.id,
+					id: String(snippet.id),
+					name: snippet.name,
+					kind: snippet.kind,
+					wiki: entry ? entry.filename : snippet.wikiPath
+				});
+			});
+			$tw.wiki.setText("$:/temp/tiddlydesktop-rs/custom-snippets-disabled", "text", null, config.disabled ? "yes" : "no");
+		}
+		function refreshCustomSnippets() {
+			return invoke("list_custom_snippets", { wikiPath: null }).then(showCustomSnippets);
+		}
+		refreshCustomSnippets().catch(function(err) {
+			console.error("Failed to get custom snippets:", err);
+		});
+
+		// Message handler: attach the snippet from the form to its wiki
+		$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-add-custom-snippet", function() {
+			var form = $tw.wiki.getTiddler("$:/temp/tiddlydesktop-rs/new-custom-snippet");
+			var fields = form ? form.fields : {};
+			if (!fields.path || !fields.code) return;
+			invoke("add_custom_snippet", {
+				wikiPath: fields.path,
+				name: fields.name || "",
+				kind: fields.kind || "css",
+				code: fields.code
+			}).then(function() {
+				$tw.wiki.deleteTiddler("$:/temp/tiddlydesktop-rs/new-custom-snippet");
+				return refreshCustomSnippets();
+			}).catch(function(err) {
+				console.error("Failed to add custom snippet:", err);
+				alert("Failed to add custom snippet: " + err);
+			});
+		});
+
+		// Message handler: remove a custom snippet
+		$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-remove-custom-snippet", function(event) {
+			var id = parseInt(event.paramObject && event.paramObject.id, 10);
+			invoke("remove_custom_snippet", { id: id }).then(refreshCustomSnippets).catch(function(err) {
+				console.error("Failed to remove custom snippet:", err);
+			});
+		});
+
+		// Message handler: turn all snippet injections off ("yes") or back on
+		$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-set-custom-snippets-disabled", function(event) {
+			var disabled = (event.paramObject && event.paramObject.disabled) === "yes";
+			invoke("set_custom_snippets_disabled", { disabled: disabled }).then(refreshCustomSnippets).catch(function(err) {
+				alert("Failed to change custom snippets: " + err);
+			});
+		});
+	}
+
+	// ========================================
+	// Event Webhooks
+	// ========================================
+	var WEBHOOK_EVENTS = ["wiki-saved", "backup-completed", "sync-conflict"];
+	var webhooks = [];
+	function showWebhooks(hooks) {
+		webhooks = hooks || [];
+		$tw.wiki.filterTiddlers("[prefix[$:/temp/tiddlydesktop-rs/webhooks/]]").forEach(function(title) {
+			$tw.wiki.deleteTiddler(title);
+		});
+		webhooks.forEach(function(hook, index) {
+			$tw.wiki.addTiddler({
+				title: "$:/temp/tiddlydesktop-rs/webhooks/" + index,
+				index: String(index),
+				url: hook.url,
+				events: $tw.utils.stringifyList(hook.events),
+				signed: hook.secret ? "yes" : "no"
+			});
+		});
+	}
+	function saveWebhooks(hooks) {
+		return invoke("set_webhooks", { webhooks: hooks }).then(function() {
+			showWebhooks(hooks);
+		});
+	}
+	invoke("get_webhooks").then(showWebhooks).catch(function(err) {
+		console.error("Failed to get webhooks:", err);
+	});
+
+	// Message handler: add the webhook from the form
+	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-add-webhook", function() {
+		var form = $tw.wiki.getTiddler("$:/temp/tiddlydesktop-rs/new-webhook");
+		var fields = form ? form.fields : {};
+		var events = WEBHOOK_EVENTS.filter(function(event) { return fields[event] === "yes"; });
+		if (!fields.url || events.length === 0) return;
+		var hook = { url: fields.url, events: events, secret: fields.secret || null };
+		saveWebhooks(webhooks.concat([hook])).then(function() {
+			$tw.wiki.deleteTiddler("$:/temp/tiddlydesktop-rs/new-webhook");
 		}).catch(function(err) {
-			console.log("[TiddlyDesktop] Failed to load favicons from Rust:", err);
+			console.error("Failed to add webhook:", err);
+			alert("Failed to add webhook: " + err);
+		});
+	});
+
+	// Message handler: remove a webhook
+	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-remove-webhook", function(event) {
+		var index = parseInt(event.paramObject && event.paramObject.index, 10);
+		saveWebhooks(webhooks.filter(function(hook, i) { return i !== index; })).catch(function(err) {
+			console.error("Failed to remove webhook:", err);
+		});
+	});
+
+	// ========================================
+	// Memory Limit for Wiki Processes (desktop only)
+	// ========================================
+	if (!isAndroid) {
+		function applyMemoryLimit(settings) {
+			$tw.wiki.setText("$:/temp/tiddlydesktop-rs/memory-limit-mb", "text", null, String(settings.limitMb));
+			$tw.wiki.setText("$:/temp/tiddlydesktop-rs/memory-limit-auto-restart", "text", null, settings.autoRestart ? "yes" : "no");
+		}
+		invoke("get_memory_limit_settings").then(applyMemoryLimit).catch(function(err) {
+			console.error("Failed to get memory limit:", err);
+		});
+
+		// Message handler: set the soft memory limit per wiki process (0 = off)
+		$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-set-memory-limit", function(event) {
+			var params = event.paramObject || {};
+			var limitMb = parseInt(params.limitMb, 10) || 0;
+			var autoRestart = params.autoRestart === "yes";
+			invoke("set_memory_limit", { limitMb: limitMb, autoRestart: autoRestart }).then(applyMemoryLimit).catch(function(err) {
+				console.error("Failed to set memory limit:", err);
+			});
 		});
 	}
-	mergeFaviconsFromDisk();
 
-	// Re-merge favicons when returning to the landing page (e.g., after closing a wiki).
-	// WikiActivity extracts favicons in the :wiki process and saves them to disk,
-	// so we need to re-check when the user comes back.
-	if (isAndroid) {
-		document.addEventListener("visibilitychange", function() {
-			if (document.visibilityState === "visible") {
-				mergeFaviconsFromDisk();
-				checkPendingWikiOpen();
-			}
+	// ========================================
+	// Incremental Save (desktop only)
+	// ========================================
+	if (!isAndroid) {
+		function applyIncrementalSave(enabled) {
+			$tw.wiki.setText("$:/temp/tiddlydesktop-rs/incremental-save", "text", null, enabled ? "yes" : "no");
+		}
+		invoke("get_incremental_save").then(applyIncrementalSave).catch(function(err) {
+			console.error("Failed to get incremental save setting:", err);
+		});
+
+		// Message handler: save big single-file wikis by appending the changed tiddlers
+		$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-set-incremental-save", function(event) {
+			var params = event.paramObject || {};
+			invoke("set_incremental_save", { enabled: params.enabled === "yes" }).then(applyIncrementalSave).catch(function(err) {
+				console.error("Failed to set incremental save setting:", err);
+			});
 		});
 	}
 
-	// Check if a pending wiki open was requested (from widget or Quick Capture).
-	// MainActivity writes a pending file, and we consume it here.
-	function checkPendingWikiOpen() {
-		invoke("get_pending_widget_wiki").then(function(pendingWiki) {
-			if (pendingWiki && pendingWiki.path) {
-				console.log("[TiddlyDesktop] Opening pending wiki: " + pendingWiki.path);
-				var path = pendingWiki.path;
-				var isFolder = !!pendingWiki.is_folder;
-				var navigateToTiddler = pendingWiki.navigate_to_tiddler || null;
+	// ========================================
+	// Folder Wiki Server (desktop only)
+	// ========================================
+	if (!isAndroid) {
+		function applyNativeFolderServer(enabled) {
+			$tw.wiki.setText("$:/temp/tiddlydesktop-rs/native-folder-server", "text", null, enabled ? "yes" : "no");
+		}
+		invoke("get_native_folder_server").then(applyNativeFolderServer).catch(function(err) {
+			console.error("Failed to get folder server setting:", err);
+		});
+
+		// Message handler: serve folder wikis with the built-in server instead of Node.js
+		$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-set-native-folder-server", function(event) {
+			var params = event.paramObject || {};
+			invoke("set_native_folder_server", { enabled: params.enabled === "yes" }).then(applyNativeFolderServer).catch(function(err) {
+				console.error("Failed to set folder server setting:", err);
+			});
+		});
+
+		function applyFolderWebdav(enabled) {
+			$tw.wiki.setText("$:/temp/tiddlydesktop-rs/folder-webdav", "text", null, enabled ? "yes" : "no");
+		}
+		invoke("get_folder_webdav").then(applyFolderWebdav).catch(function(err) {
+			console.error("Failed to get WebDAV setting:", err);
+		});
+
+		// Message handler: also serve folder wikis' tiddler files over WebDAV
+		$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-set-folder-webdav", function(event) {
+			var params = event.paramObject || {};
+			invoke("set_folder_webdav", { enabled: params.enabled === "yes" }).then(applyFolderWebdav).catch(function(err) {
+				console.error("Failed to set WebDAV setting:", err);
+			});
+		});
+
+		function applyServerListen(listen) {
+			$tw.wiki.addTiddler(new $tw.Tiddler({
+				title: "$:/temp/tiddlydesktop-rs/server-listen",
+				text: listen.mode,
+				"interface": listen["interface"] || ""
+			}));
+		}
+		invoke("get_server_listen").then(applyServerListen).catch(function(err) {
+			console.error("Failed to get server listen setting:", err);
+		});
+
+		// Message handler: where the built-in servers listen (mode=local|all, or mode=interface with interface=<name>)
+		$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-set-server-listen", function(event) {
+			var params = event.paramObject || {};
+			var listen = params.mode === "interface" ? { mode: "interface", "interface": params["interface"] } : { mode: params.mode === "all" ? "all" : "local" };
+			invoke("set_server_listen", { listen: listen }).then(applyServerListen).catch(function(err) {
+				console.error("Failed to set server listen setting:", err);
+			});
+		});
+
+		function applyAutomationApi(info) {
+			$tw.wiki.addTiddler(new $tw.Tiddler({
+				title: "$:/temp/tiddlydesktop-rs/automation-api",
+				text: info.enabled ? "yes" : "no",
+				running: info.running ? "yes" : "no",
+				port: String(info.port),
+				token_file: info.tokenFile
+			}));
+		}
+		invoke("get_automation_api").then(applyAutomationApi).catch(function(err) {
+			console.error("Failed to get automation API setting:", err);
+		});
+
+		// Message handler: turn the local automation API on or off
+		$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-set-automation-api", function(event) {
+			var params = event.paramObject || {};
+			invoke("set_automation_api", { enabled: params.enabled === "yes" }).then(applyAutomationApi).catch(function(err) {
+				console.error("Failed to set automation API setting:", err);
+				window.__TAURI__.dialog.message("Failed to start the automation API: " + err, { title: "Error", kind: "error" });
+			});
+		});
+
+		// Message handler: store the tiddlers of a single-file wiki in a new SQLite wiki folder
+		$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-import-sqlite-wiki", function(event) {
+			var htmlPath;
+			openDialog({
+				multiple: false,
+				filters: [{
+					name: "TiddlyWiki",
+					extensions: ["html", "htm"]
+				}]
+			}).then(function(file) {
+				if (!file) return null;
+				htmlPath = file;
+				return openDialog({
+					directory: true,
+					multiple: false,
+					title: $tw.wiki.renderText("text/plain", "text/vnd.tiddlywiki", "<<td-lingo FolderServer/SqliteTarget>>")
+				});
+			}).then(function(folder) {
+				if (!folder) return;
+				return invoke("import_sqlite_wiki", { htmlPath: htmlPath, folderPath: folder }).then(function() {
+					return invoke("open_wiki_folder", { path: folder });
+				}).then(function(entry) {
+					addToWikiList(entry);
+					refreshWikiList();
+				});
+			}).catch(function(err) {
+				console.error("import_sqlite_wiki error:", err);
+				alert("Failed to import wiki: " + err);
+			});
+		});
+
+		// Message handler: write a SQLite wiki out as a single-file wiki
+		$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-export-sqlite-wiki", function(event) {
+			var folderPath;
+			openDialog({
+				directory: true,
+				multiple: false
+			}).then(function(folder) {
+				if (!folder) return null;
+				folderPath = folder;
+				return window.__TAURI__.dialog.save({
+					filters: [{
+						name: "TiddlyWiki",
+						extensions: ["html"]
+					}],
+					defaultPath: "wiki.html"
+				});
+			}).then(function(filePath) {
+				if (!filePath) return;
+				return invoke("export_sqlite_wiki", { folderPath: folderPath, htmlPath: filePath });
+			}).catch(function(err) {
+				console.error("export_sqlite_wiki error:", err);
+				alert("Failed to export wiki: " + err);
+			});
+		});
+	}
+
+	// ========================================
+	// Landing Page Snapshots
+	// ========================================
+	function showAppWikiSnapshots(snapshots) {
+		$tw.wiki.filterTiddlers("[prefix[$:/temp/tiddlydesktop-rs/snapshots/]]").forEach(function(title) {
+			$tw.wiki.deleteTiddler(title);
+		});
+		(snapshots || []).forEach(function(snapshot) {
+			$tw.wiki.addTiddler({
+				title: "$:/temp/tiddlydesktop-rs/snapshots/" + snapshot.timestamp,
+				path: snapshot.path,
+				timestamp: snapshot.timestamp
+			});
+		});
+	}
+	invoke("list_app_wiki_snapshots").then(showAppWikiSnapshots).catch(function(err) {
+		console.error("Failed to list landing page snapshots:", err);
+	});
+
+	// Message handler: put a snapshot of the landing page back (the page reloads afterwards)
+	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-restore-app-wiki", function(event) {
+		var params = event.paramObject || {};
+		if (!params.path || !confirm($tw.wiki.renderText("text/plain", "text/vnd.tiddlywiki", "<<td-lingo Snapshots/ConfirmRestore>>"))) {
+			return;
+		}
+		invoke("restore_app_wiki", { snapshotPath: params.path }).catch(function(err) {
+			console.error("restore_app_wiki error:", err);
+			alert("Failed to restore snapshot: " + err);
+		});
+	});
 
-				// Look up backup settings from the wiki list
-				var entries = getWikiListEntries();
-				var entry = null;
-				for (var i = 0; i < entries.length; i++) {
-					if (entries[i].path === path) {
-						entry = entries[i];
-						break;
-					}
+	// ========================================
+	// Shell Extensions (desktop only)
+	// ========================================
+	if (!isAndroid) {
+		function showExtensions(extensions) {
+			$tw.wiki.filterTiddlers("[prefix[$:/temp/tiddlydesktop-rs/extensions/]]").forEach(function(title) {
+				$tw.wiki.deleteTiddler(title);
+			});
+			(extensions || []).forEach(function(ext) {
+				var fields = {
+					title: "$:/temp/tiddlydesktop-rs/extensions/" + ext.id,
+					id: ext.id,
+					name: ext.name,
+					version: ext.version,
+					description: ext.description,
+					enabled: ext.enabled ? "yes" : "no"
+				};
+				if (ext.error) {
+					fields.error = ext.error;
 				}
+				$tw.wiki.addTiddler(fields);
+			});
+		}
+		function loadExtensions() {
+			invoke("list_extensions").then(showExtensions).catch(function(err) {
+				console.error("Failed to list extensions:", err);
+			});
+		}
+		loadExtensions();
 
-				var command = isFolder ? "open_wiki_folder" : "open_wiki_window";
-				var params = { path: path };
-				if (!isFolder && entry) {
-					params.backupsEnabled = entry.backups_enabled !== false;
-					params.backupCount = entry.backup_count !== undefined ? entry.backup_count : null;
-				}
-				if (navigateToTiddler) {
-					params.tiddlerTitle = navigateToTiddler;
-				}
+		$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-reload-extensions", function() {
+			loadExtensions();
+		});
 
-				invoke(command, params).then(function(resultEntry) {
-					addToWikiList(resultEntry);
-					refreshWikiList();
-				}).catch(function(err) {
-					console.error("[TiddlyDesktop] Failed to open pending wiki:", err);
-				});
+		$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-open-extensions-folder", function() {
+			invoke("open_extensions_folder").catch(function(err) {
+				console.error("Failed to open extensions folder:", err);
+				alert("Failed to open extensions folder: " + err);
+			});
+		});
+
+		// Message handler: enable/disable an extension (enabling loads native code, so ask first)
+		$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-set-extension-enabled", function(event) {
+			var params = event.paramObject || {};
+			var enabled = params.enabled === "yes";
+			if (!params.id) return;
+			if (enabled && !confirm($tw.wiki.renderText("text/plain", "text/vnd.tiddlywiki", "<<td-lingo Extensions/ConfirmEnable>>"))) {
+				return;
 			}
-		}).catch(function(err) {
-			// No pending wiki — normal case
+			invoke("set_extension_enabled", { id: params.id, enabled: enabled }).then(showExtensions).catch(function(err) {
+				console.error("Failed to change extension:", err);
+			});
 		});
 	}
-	if (isAndroid) {
-		checkPendingWikiOpen();
-	}
 
 	// ========================================
 	// Custom Plugin/Edition Path Handlers (Android only)
@@ -2024,23 +4039,63 @@ exports.startup = function(callback) {
 		});
 	}
 
-	// Check for application updates
-	invoke("check_for_updates").then(function(result) {
-		if (result.update_available && result.latest_version) {
-			$tw.wiki.setText("$:/temp/tiddlydesktop-rs/update-available", "text", null, "yes");
-			$tw.wiki.setText("$:/temp/tiddlydesktop-rs/latest-version", "text", null, result.latest_version);
-			$tw.wiki.setText("$:/temp/tiddlydesktop-rs/releases-url", "text", null, result.releases_url);
-			console.log("Update available: v" + result.latest_version + " (current: v" + result.current_version + ")");
-		} else {
-			$tw.wiki.setText("$:/temp/tiddlydesktop-rs/update-available", "text", null, "no");
-			console.log("TiddlyDesktop-RS is up to date (v" + result.current_version + ")");
+	// ========================================
+	// Metered Connections
+	// ========================================
+	// Set when the update check waits for an unmetered connection
+	var updateCheckDeferred = false;
+
+	function applyMeteredStatus(status) {
+		var connection = status.roaming ? "roaming" : (status.metered ? "metered" : "unmetered");
+		$tw.wiki.setText("$:/temp/tiddlydesktop-rs/metered-connection", "text", null, connection);
+		$tw.wiki.setText("$:/temp/tiddlydesktop-rs/metered-allow/relay-sync", "text", null, status.allowRelaySync ? "yes" : "no");
+		$tw.wiki.setText("$:/temp/tiddlydesktop-rs/metered-allow/plugin-library", "text", null, status.allowPluginLibrary ? "yes" : "no");
+		$tw.wiki.setText("$:/temp/tiddlydesktop-rs/metered-allow/updates", "text", null, status.allowUpdates ? "yes" : "no");
+		// A deferred update check runs once it may
+		if (updateCheckDeferred && (status.allowUpdates || connection === "unmetered")) {
+			checkForUpdates();
 		}
-	}).catch(function(err) {
-		console.warn("Failed to check for updates:", err);
-		// Don't show update button on error
-		$tw.wiki.setText("$:/temp/tiddlydesktop-rs/update-available", "text", null, "no");
+	}
+	invoke("get_metered_status").then(applyMeteredStatus).catch(function(err) {
+		console.error("Failed to get metered connection status:", err);
+	});
+
+	// Message handler: let a feature use metered connections, or make it wait
+	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-set-metered-allowed", function(event) {
+		var params = event.paramObject || {};
+		invoke("set_metered_allowed", { feature: params.feature, allowed: params.allowed === "yes" }).then(applyMeteredStatus).catch(function(err) {
+			console.error("Failed to set metered connection setting:", err);
+		});
 	});
 
+	if (listen) {
+		listen("metered-connection-changed", function(event) {
+			applyMeteredStatus(event.payload);
+		});
+	}
+
+	// Check for application updates
+	function checkForUpdates() {
+		updateCheckDeferred = false;
+		invoke("check_for_updates").then(function(result) {
+			if (result.update_available && result.latest_version) {
+				$tw.wiki.setText("$:/temp/tiddlydesktop-rs/update-available", "text", null, "yes");
+				$tw.wiki.setText("$:/temp/tiddlydesktop-rs/latest-version", "text", null, result.latest_version);
+				$tw.wiki.setText("$:/temp/tiddlydesktop-rs/releases-url", "text", null, result.releases_url);
+				console.log("Update available: v" + result.latest_version + " (current: v" + result.current_version + ")");
+			} else {
+				$tw.wiki.setText("$:/temp/tiddlydesktop-rs/update-available", "text", null, "no");
+				console.log("TiddlyDesktop-RS is up to date (v" + result.current_version + ")");
+			}
+		}).catch(function(err) {
+			console.warn("Failed to check for updates:", err);
+			updateCheckDeferred = String(err).indexOf("metered") !== -1;
+			// Don't show update button on error
+			$tw.wiki.setText("$:/temp/tiddlydesktop-rs/update-available", "text", null, "no");
+		});
+	}
+	checkForUpdates();
+
 	// ── Sync message handlers ─────────────────────────────────────────
 
 	// Request a wiki from a peer
@@ -2120,7 +4175,44 @@ exports.startup = function(callback) {
 			console.error("[LAN Sync] do-link-wiki: missing wikiId=" + wikiId + " or path=" + path);
 			return;
 		}
+		function lingo(key) {
+			return $tw.wiki.renderText("text/plain", "text/vnd.tiddlywiki", "<<td-lingo LanSync/Preview/" + key + ">>");
+		}
+
+		// Show what the first sync would do before linking (nothing is synced yet)
+		var preview = fromDeviceId ? invoke("preview_sync", { path: path, syncId: wikiId, deviceId: fromDeviceId }).then(function(report) {
+			var overwritten = report.conflicts.filter(function(c) { return c.keeps === "remote"; });
+			var lines = [
+				lingo("Summary")
+					.replace("$push$", report.push.length)
+					.replace("$pull$", report.pull.length)
+					.replace("$unchanged$", report.unchanged),
+				lingo("Conflicts")
+					.replace("$local$", overwritten.length)
+					.replace("$remote$", report.conflicts.length - overwritten.length),
+				lingo("Deletions")
+					.replace("$local$", report.delete_local.length)
+					.replace("$remote$", report.delete_remote.length)
+			];
+			var replaced = overwritten.map(function(c) { return c.title; }).concat(report.delete_local);
+			if (replaced.length > 0) {
+				lines.push("", lingo("Replaced"));
+				lines = lines.concat(replaced.slice(0, 15).map(function(title) { return "  " + title; }));
+				if (replaced.length > 15) lines.push("  …");
+			}
+			return $tw.tiddlydesktoprs.confirm(lines.join("\n") + "\n\n" + lingo("Confirm"));
+		}, function(err) {
+			console.error("[LAN Sync] Sync preview failed:", err);
+			return $tw.tiddlydesktoprs.confirm(lingo("Failed") + "\n\n" + err);
+		}) : Promise.resolve(true);
+
+		preview.then(function(confirmed) {
+			if (!confirmed) return;
+			linkWiki(wikiId, path, fromDeviceId, roomCode);
+		});
+	});
 
+	function linkWiki(wikiId, path, fromDeviceId, roomCode) {
 		invoke("lan_sync_link_wiki", { path: path, syncId: wikiId, fromDeviceId: fromDeviceId || null, roomCode: roomCode || null }).then(function(resolvedRoom) {
 			console.log("[LAN Sync] Linked wiki to sync ID " + wikiId + " (room: " + resolvedRoom + ")");
 			try {
@@ -2149,7 +4241,7 @@ exports.startup = function(callback) {
 			console.error("Failed to link wiki:", err);
 			alert("Failed to link wiki: " + err);
 		});
-	});
+	}
 
 	// ── Relay Sync message handlers ─────────────────────────────────────
 
@@ -2512,6 +4604,19 @@ exports.startup = function(callback) {
 		});
 	});
 
+	// Use the relay for a room only while its devices aren't reachable on the LAN
+	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-relay-set-room-relay-fallback", function(event) {
+		var p = event.paramObject || {};
+		var roomCode = p.roomCode;
+		var enabled = p.enabled === "true";
+		if (!roomCode) return;
+		invoke("relay_sync_set_room_relay_fallback", { roomCode: roomCode, enabled: enabled }).then(function() {
+			refreshSyncStatus();
+		}).catch(function(err) {
+			console.error("Failed to set room relay fallback:", err);
+		});
+	});
+
 	// Set Relay room password
 	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-relay-set-room-password", function(event) {
 		var p = event.paramObject || {};
@@ -2554,11 +4659,69 @@ exports.startup = function(callback) {
 			// Pre-fill edit fields
 			$tw.wiki.setText("$:/temp/tiddlydesktop-rs/relay-room-password/" + roomCode, "text", null, creds.password);
 			$tw.wiki.setText("$:/temp/tiddlydesktop-rs/relay-room-name-edit/" + roomCode, "text", null, creds.name);
+			// QR code of the room code, for pairing another device
+			saveQrCode(creds.room_code || roomCode, "$:/temp/tiddlydesktop-rs/relay-room-qr/" + roomCode);
 		}).catch(function(err) {
 			console.error("Failed to load room credentials:", err);
 		});
 	});
 
+	// Load the devices seen in each relay room
+	function loadRoomDevices() {
+		invoke("relay_sync_get_room_directory").then(function(rooms) {
+			rooms.forEach(function(room) {
+				var devices = (room.members || []).map(function(m) {
+					return {
+						device_id: m.device_id,
+						device_name: m.device_name,
+						online: m.online ? "yes" : "no",
+						revoked: m.revoked ? "yes" : "no",
+						last_seen: new Date(m.last_seen).toLocaleString(),
+						last_sync: m.last_sync ? new Date(m.last_sync).toLocaleString() : ""
+					};
+				});
+				var tiddler = "$:/temp/tiddlydesktop-rs/relay-room-devices/" + room.room_code;
+				$tw.wiki.setText(tiddler, "text", null, JSON.stringify(devices));
+				$tw.wiki.setText(tiddler, "type", null, "application/json");
+			});
+		}).catch(function(err) {
+			console.error("[Relay] Load room devices failed:", err);
+		});
+	}
+
+	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-relay-load-devices", function(event) {
+		loadRoomDevices();
+	});
+
+	// Revoke a device in a room (rotate="yes" also changes the room password)
+	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-relay-revoke-device", function(event) {
+		var p = event.paramObject || {};
+		if (!p.roomCode || !p.deviceId) return;
+		invoke("relay_sync_revoke_device", { roomCode: p.roomCode, deviceId: p.deviceId, rotatePassword: p.rotate === "yes" }).then(function(newPassword) {
+			if (newPassword) {
+				$tw.wiki.setText("$:/temp/tiddlydesktop-rs/relay-room-rotated/" + p.roomCode, "text", null, newPassword);
+				$tw.rootWidget.dispatchEvent({
+					type: "tm-tiddlydesktop-rs-relay-load-room-details",
+					paramObject: { roomCode: p.roomCode }
+				});
+			}
+			loadRoomDevices();
+			refreshSyncStatus();
+		}).catch(function(err) {
+			console.error("[Relay] Revoke device failed:", err);
+		});
+	});
+
+	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-relay-unrevoke-device", function(event) {
+		var p = event.paramObject || {};
+		if (!p.roomCode || !p.deviceId) return;
+		invoke("relay_sync_unrevoke_device", { roomCode: p.roomCode, deviceId: p.deviceId }).then(function() {
+			loadRoomDevices();
+		}).catch(function(err) {
+			console.error("[Relay] Allow device failed:", err);
+		});
+	});
+
 	// Set Relay server URL
 	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-relay-sync-set-url", function(event) {
 		var url = event.paramObject && event.paramObject.url;
@@ -2581,6 +4744,119 @@ exports.startup = function(callback) {
 		});
 	});
 
+	// ========================================
+	// Network Interfaces for LAN Sync
+	// ========================================
+	var NETWORK_INTERFACES_PREFIX = "$:/temp/tiddlydesktop-rs/network-interfaces/";
+	var networkInterfaces = [];
+
+	function applyNetworkInterfaces(interfaces) {
+		networkInterfaces = interfaces || [];
+		$tw.wiki.filterTiddlers("[prefix[" + NETWORK_INTERFACES_PREFIX + "]]").forEach(function(title) {
+			$tw.wiki.deleteTiddler(title);
+		});
+		networkInterfaces.forEach(function(iface) {
+			$tw.wiki.addTiddler(new $tw.Tiddler({
+				title: NETWORK_INTERFACES_PREFIX + iface.name,
+				name: iface.name,
+				addresses: iface.addresses.join(", "),
+				selected: iface.selected ? "yes" : "no"
+			}));
+		});
+		var all = networkInterfaces.every(function(iface) { return iface.selected; });
+		$tw.wiki.setText("$:/temp/tiddlydesktop-rs/network-interfaces-all", "text", null, all ? "yes" : "no");
+	}
+	invoke("lan_sync_get_interfaces").then(applyNetworkInterfaces).catch(function(err) {
+		console.error("Failed to get network interfaces:", err);
+	});
+
+	// Message handler: use all interfaces (all=yes) or toggle one (toggle=<name>)
+	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-set-network-interfaces", function(event) {
+		var params = event.paramObject || {};
+		var names = [];
+		if (params.toggle) {
+			var all = networkInterfaces.every(function(iface) { return iface.selected; });
+			networkInterfaces.forEach(function(iface) {
+				// From "all", a click leaves that interface out
+				var selected = all ? iface.name !== params.toggle : (iface.selected !== (iface.name === params.toggle));
+				if (selected) {
+					names.push(iface.name);
+				}
+			});
+			// Every interface (or none) means all of them
+			if (names.length === networkInterfaces.length) {
+				names = [];
+			}
+		}
+		invoke("lan_sync_set_interfaces", { names: names }).then(applyNetworkInterfaces).catch(function(err) {
+			console.error("Failed to set network interfaces:", err);
+		});
+	});
+
+	// ========================================
+	// Shared Clipboard (text sent between connected devices)
+	// ========================================
+	var CLIPBOARD_PEERS_PREFIX = "$:/temp/tiddlydesktop-rs/clipboard-peers/";
+
+	function applySharedClipboard(enabled) {
+		$tw.wiki.setText("$:/temp/tiddlydesktop-rs/shared-clipboard", "text", null, enabled ? "yes" : "no");
+	}
+	invoke("lan_sync_get_shared_clipboard").then(applySharedClipboard).catch(function(err) {
+		console.error("Failed to get shared clipboard setting:", err);
+	});
+
+	function refreshClipboardPeers() {
+		invoke("lan_sync_get_clipboard_peers").then(function(peers) {
+			$tw.wiki.filterTiddlers("[prefix[" + CLIPBOARD_PEERS_PREFIX + "]]").forEach(function(title) {
+				$tw.wiki.deleteTiddler(title);
+			});
+			(peers || []).forEach(function(peer) {
+				$tw.wiki.addTiddler(new $tw.Tiddler({
+					title: CLIPBOARD_PEERS_PREFIX + peer.device_id,
+					device_id: peer.device_id,
+					device_name: peer.user_name ? peer.user_name + " (" + peer.device_name + ")" : peer.device_name
+				}));
+			});
+		}).catch(function() {});
+	}
+
+	// Message handler: opt in to (or out of) the shared clipboard
+	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-set-shared-clipboard", function(event) {
+		var params = event.paramObject || {};
+		invoke("lan_sync_set_shared_clipboard", { enabled: params.enabled === "yes" }).then(function(enabled) {
+			applySharedClipboard(enabled);
+			refreshClipboardPeers();
+		}).catch(function(err) {
+			console.error("Failed to set shared clipboard setting:", err);
+		});
+	});
+
+	// Message handler: send the text typed in the sync panel to a device
+	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-share-clipboard", function(event) {
+		var params = event.paramObject || {};
+		var text = $tw.wiki.getTiddlerText("$:/temp/tiddlydesktop-rs/shared-clipboard-input", "");
+		if (!params.deviceId || !text) return;
+		var statusTitle = "$:/temp/tiddlydesktop-rs/shared-clipboard-status";
+		invoke("lan_sync_share_clipboard", { deviceId: params.deviceId, text: text }).then(function() {
+			$tw.wiki.deleteTiddler("$:/temp/tiddlydesktop-rs/shared-clipboard-input");
+			$tw.wiki.setText(statusTitle, "text", null, $tw.wiki.renderText("text/plain", "text/vnd.tiddlywiki", "<<td-lingo SharedClipboard/SentTo>>") + " " + (params.deviceName || ""));
+		}).catch(function(err) {
+			$tw.wiki.setText(statusTitle, "text", null, String(err));
+		});
+	});
+
+	if (listen) {
+		listen("lan-sync-clipboard-received", function(event) {
+			var data = event.payload || {};
+			if (!data.text) return;
+			$tw.wiki.addTiddler(new $tw.Tiddler({
+				title: "$:/temp/tiddlydesktop-rs/shared-clipboard-received",
+				from: data.from || "",
+				text: data.text
+			}));
+		});
+	}
+
 	// Prepare add room form with auto-generated credentials
 	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-relay-prepare-add-room", function(event) {
 		invoke("relay_sync_generate_credentials").then(function(creds) {
@@ -2648,6 +4924,248 @@ exports.startup = function(callback) {
 		});
 	});
 
+	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-set-confirm-remote-deletions", function(event) {
+		var p = event.paramObject || {};
+		var path = p.path;
+		var confirm = p.confirm === "true";
+		if (!path) return;
+		var entries = getWikiListEntries();
+		for (var i = 0; i < entries.length; i++) {
+			if (entries[i].path === path) {
+				entries[i].confirm_remote_deletions = confirm;
+				$tw.wiki.setText("$:/temp/tiddlydesktop-rs/wikis/" + i, "confirm_remote_deletions", null, confirm ? "true" : "false");
+				break;
+			}
+		}
+		saveWikiList(entries);
+		invoke("set_wiki_confirm_remote_deletions", { path: path, confirm: confirm }).catch(function(err) {
+			console.error("Failed to set deletion confirmation:", err);
+		});
+	});
+
+	// "interval:N" / "manual" in the wiki list ("" syncs in real time)
+	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-set-sync-schedule", function(event) {
+		var p = event.paramObject || {};
+		var path = p.path;
+		var schedule = parseSyncSchedule(p.schedule || "");
+		if (!path) return;
+		var entries = getWikiListEntries();
+		for (var i = 0; i < entries.length; i++) {
+			if (entries[i].path === path) {
+				if (schedule) {
+					entries[i].sync_schedule = schedule;
+				} else {
+					delete entries[i].sync_schedule;
+				}
+				$tw.wiki.setText("$:/temp/tiddlydesktop-rs/wikis/" + i, "sync_schedule", null, formatSyncSchedule(schedule));
+				break;
+			}
+		}
+		saveWikiList(entries);
+		invoke("set_wiki_sync_schedule", { path: path, schedule: schedule }).catch(function(err) {
+			console.error("Failed to set wiki sync schedule:", err);
+		});
+	});
+
+	// Filters of the tiddlers synced right away (high) and in batches every
+	// N minutes (bulk); two empty filters sync all tiddlers alike
+	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-set-sync-priority", function(event) {
+		var p = event.paramObject || {};
+		var path = p.path;
+		if (!path) return;
+		var high = (p.high || "").trim();
+		var bulk = (p.bulk || "").trim();
+		var minutes = parseInt(p.minutes, 10);
+		var priority = (high || bulk) ? { high: high, bulk: bulk, bulk_minutes: minutes > 0 ? minutes : 0 } : null;
+		invoke("set_wiki_sync_priority", { path: path, priority: priority }).then(function() {
+			var entries = getWikiListEntries();
+			for (var i = 0; i < entries.length; i++) {
+				if (entries[i].path === path) {
+					if (priority) {
+						entries[i].sync_priority = priority;
+					} else {
+						delete entries[i].sync_priority;
+					}
+					var tiddlerTitle = "$:/temp/tiddlydesktop-rs/wikis/" + i;
+					$tw.wiki.setText(tiddlerTitle, "sync_priority_high", null, high);
+					$tw.wiki.setText(tiddlerTitle, "sync_priority_bulk", null, bulk);
+					$tw.wiki.setText(tiddlerTitle, "sync_priority_minutes", null, priority && priority.bulk_minutes ? String(priority.bulk_minutes) : "");
+					break;
+				}
+			}
+			saveWikiList(entries);
+		}).catch(function(err) {
+			alert("Failed to set the sync priority: " + err);
+		});
+	});
+
+	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-sync-now", function(event) {
+		var p = event.paramObject || {};
+		if (!p.path) return;
+		invoke("sync_now", { path: p.path }).catch(function(err) {
+			alert("Failed to sync: " + err);
+		});
+	});
+
+	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-rollback-last-sync", function(event) {
+		var p = event.paramObject || {};
+		if (!p.path || !confirm($tw.wiki.renderText("text/plain", "text/vnd.tiddlywiki", "<<td-lingo SyncMode/ConfirmRollback>>"))) {
+			return;
+		}
+		invoke("rollback_last_sync", { path: p.path }).then(function(appliedNow) {
+			var key = appliedNow ? "SyncMode/RolledBack" : "SyncMode/RollbackOnOpen";
+			window.__TAURI__.dialog.message($tw.wiki.renderText("text/plain", "text/vnd.tiddlywiki", "<<td-lingo " + key + ">>"), { title: "TiddlyDesktop", kind: "info" });
+		}).catch(function(err) {
+			alert("Failed to roll back the last sync: " + err);
+		});
+	});
+
+	// ========================================
+	// App configuration export/import (desktop only)
+	// ========================================
+
+	// The password of the configuration archive (and its confirmation when
+	// exporting), not kept around. Returns false if they don't match.
+	function takeAppConfigPassword(confirm) {
+		var tiddler = $tw.wiki.getTiddler("$:/temp/tiddlydesktop-rs/app-config-password");
+		var fields = tiddler ? tiddler.fields : {};
+		$tw.wiki.deleteTiddler("$:/temp/tiddlydesktop-rs/app-config-password");
+		if (confirm && (fields.password || fields.confirm) && fields.password !== fields.confirm) {
+			return false;
+		}
+		return fields.password || null;
+	}
+
+	function appConfigLingo(key) {
+		return $tw.wiki.renderText("text/plain", "text/vnd.tiddlywiki", "<<td-lingo AppConfig/" + key + ">>");
+	}
+
+	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-export-app-config", function() {
+		var password = takeAppConfigPassword(true);
+		if (password === false) {
+			alert(appConfigLingo("PasswordMismatch"));
+			return;
+		}
+		window.__TAURI__.dialog.save({
+			filters: [{
+				name: "TiddlyDesktop configuration",
+				extensions: ["zip"]
+			}],
+			defaultPath: "tiddlydesktop-config.zip"
+		}).then(function(target) {
+			if (!target) return;
+			return invoke("export_app_config", { target: target, password: password }).then(function(exported) {
+				var message = appConfigLingo(exported.encrypted ? "SavedEncrypted" : "Saved")
+					.replace("$wikis$", exported.wikis)
+					.replace("$files$", exported.files) + " " + exported.path;
+				window.__TAURI__.dialog.message(message, { title: "TiddlyDesktop", kind: "info" });
+			});
+		}).catch(function(err) {
+			console.error("Failed to export the configuration:", err);
+			alert("Failed to export the configuration: " + err);
+		});
+	});
+
+	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-import-app-config", function() {
+		var password = takeAppConfigPassword(false);
+		openDialog({
+			multiple: false,
+			filters: [{
+				name: "TiddlyDesktop configuration",
+				extensions: ["zip"]
+			}]
+		}).then(function(archivePath) {
+			if (!archivePath || !confirm(appConfigLingo("ConfirmImport"))) return;
+			return invoke("import_app_config", { archivePath: archivePath, password: password }).then(function(report) {
+				var lines = [
+					appConfigLingo("Imported").replace("$wikis$", report.wikis).replace("$files$", report.files).replace("$rooms$", report.rooms)
+				];
+				if (report.roomsMissing.length > 0) {
+					lines.push("", appConfigLingo("RoomsMissing"), report.roomsMissing.join("\n"));
+				}
+				window.__TAURI__.dialog.message(lines.join("\n"), { title: "TiddlyDesktop", kind: "info" });
+				return invoke("get_recent_files").then(function(jsonEntries) {
+					$tw.wiki.addTiddler({
+						title: "$:/TiddlyDesktop/WikiList",
+						type: "application/json",
+						text: JSON.stringify(jsonEntries, null, 2)
+					});
+					$tw.rootWidget.dispatchEvent({type: "tm-auto-save-wiki"});
+					refreshWikiList();
+				});
+			});
+		}).catch(function(err) {
+			console.error("Failed to import the configuration:", err);
+			alert("Failed to import the configuration: " + err);
+		});
+	});
+
+	// ========================================
+	// Transfer statistics (sync peers, relay rooms, wikis, media server)
+	// ========================================
+	var TRANSFER_STATS_PREFIX = "$:/temp/tiddlydesktop-rs/transfer-stats/";
+
+	function formatTransferBytes(bytes) {
+		var units = ["B", "KB", "MB", "GB", "TB"];
+		var i = 0;
+		while(bytes >= 1024 && i < units.length - 1) {
+			bytes /= 1024;
+			i++;
+		}
+		return (i === 0 ? bytes : bytes.toFixed(1)) + " " + units[i];
+	}
+
+	function formatTransferDuration(ms) {
+		if(ms === null || ms === undefined) return "";
+		if(ms < 1000) return ms + " ms";
+		if(ms < 60000) return (ms / 1000).toFixed(1) + " s";
+		return Math.floor(ms / 60000) + " min " + Math.round((ms % 60000) / 1000) + " s";
+	}
+
+	function refreshTransferStats() {
+		invoke("get_transfer_stats").then(function(stats) {
+			$tw.wiki.filterTiddlers("[prefix[" + TRANSFER_STATS_PREFIX + "]]").forEach(function(title) {
+				$tw.wiki.deleteTiddler(title);
+			});
+			var groups = { peer: stats.peers, room: stats.rooms, wiki: stats.wikis };
+			Object.keys(groups).forEach(function(kind) {
+				(groups[kind] || []).forEach(function(counter, index) {
+					$tw.wiki.addTiddler(new $tw.Tiddler({
+						title: TRANSFER_STATS_PREFIX + kind + "/" + counter.id,
+						kind: kind,
+						order: String(index),
+						name: counter.name || counter.id,
+						sent: formatTransferBytes(counter.bytesSent),
+						received: formatTransferBytes(counter.bytesReceived),
+						duration: formatTransferDuration(counter.lastSyncDurationMs),
+						syncing: counter.syncing ? "yes" : "no"
+					}));
+				});
+			});
+			$tw.wiki.addTiddler(new $tw.Tiddler({
+				title: "$:/temp/tiddlydesktop-rs/transfer-stats-total",
+				sent: formatTransferBytes(stats.totalSent),
+				received: formatTransferBytes(stats.totalReceived),
+				since: new Date(stats.since).toLocaleString(),
+				media: stats.mediaRequests ? formatTransferBytes(stats.mediaBytesSent) : ""
+			}));
+		}).catch(function(err) {
+			console.error("get_transfer_stats failed:", err);
+		});
+	}
+	refreshTransferStats();
+	setInterval(refreshTransferStats, 10000);
+
+	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-refresh-transfer-stats", function() {
+		refreshTransferStats();
+	});
+
+	$tw.rootWidget.addEventListener("tm-tiddlydesktop-rs-reset-transfer-stats", function() {
+		invoke("reset_transfer_stats").then(refreshTransferStats).catch(function(err) {
+			console.error("reset_transfer_stats failed:", err);
+		});
+	});
+
 	// Track auth state transitions to fetch server rooms once on initial detection
 	var _lastKnownAuthState = false;
 
@@ -2658,6 +5176,8 @@ exports.startup = function(callback) {
 		_refreshSyncTimer = setTimeout(_doRefreshSyncStatus, 100);
 	}
 	function _doRefreshSyncStatus() {
+		refreshClipboardPeers();
+		refreshTransferStats();
 		invoke("lan_sync_get_status").then(function(status) {
 			$tw.wiki.setText("$:/temp/tiddlydesktop-rs/lan-sync-running", "text", null, status.running ? "yes" : "no");
 			$tw.wiki.setText("$:/temp/tiddlydesktop-rs/lan-sync-device-name", "text", null, status.device_name);
@@ -2760,6 +5280,7 @@ exports.startup = function(callback) {
 					room_name: room.name,
 					room_code: room.room_code,
 					auto_connect: room.auto_connect ? "yes" : "no",
+					relay_fallback: room.relay_fallback ? "yes" : "no",
 					connected: room.connected ? "yes" : "no",
 					peer_count: String(room.connected_peers ? room.connected_peers.length : 0),
 					peer_list: peerTitles.join(" ")
@@ -58,12 +58,12 @@ cat > "${BUILD_DIR}/share/applications/${NAME}.desktop" << EOF
 [Desktop Entry]
 Name=TiddlyDesktop
 Comment=TiddlyWiki desktop application
-Exec=${NAME}
+Exec=${NAME} %U
 Icon=${NAME}
 Terminal=false
 Type=Application
 Categories=Office;Utility;
-MimeType=text/html;
+MimeType=text/html;application/xhtml+xml;x-scheme-handler/tiddlydesktop;
 EOF
 
 # Create install script
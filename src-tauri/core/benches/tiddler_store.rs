@@ -0,0 +1,61 @@
+//! Reading tiddlers from a big single-file wiki: whole-file parsing with
+//! `tiddlywiki_html` against streaming with `tiddler_store`
+//!
+//! Run with `cargo bench -p tiddlydesktop-core`.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use std::path::PathBuf;
+use tiddlydesktop_core::{tiddler_store, tiddlywiki_html};
+
+const TIDDLERS: usize = 20_000;
+
+/// A wiki file with a plugin-sized tiddler and many ordinary ones
+fn write_wiki() -> PathBuf {
+    let mut tiddlers = vec![serde_json::json!({
+        "title": "$:/plugins/big",
+        "plugin-type": "plugin",
+        "text": "x".repeat(2_000_000),
+    })];
+    for i in 0..TIDDLERS {
+        tiddlers.push(serde_json::json!({
+            "title": format!("Tiddler {}", i),
+            "tags": "[[Some Tag]] other",
+            "text": format!("Text of tiddler {} with a {{{{transclusion}}}} and \"quotes\".\n", i).repeat(10),
+        }));
+    }
+    tiddlers.push(serde_json::json!({ "title": "$:/favicon.ico", "type": "image/png", "text": "iVBORw0KGgo".repeat(20) }));
+    let html = format!(
+        "<html><head></head><body><script class=\"tiddlywiki-tiddler-store\" type=\"application/json\">{}</script></body></html>",
+        serde_json::to_string(&tiddlers).unwrap().replace("},{", "},\n{")
+    );
+    let path = std::env::temp_dir().join(format!("tiddlydesktop-bench-{}.html", std::process::id()));
+    std::fs::write(&path, html).unwrap();
+    path
+}
+
+fn bench_store(c: &mut Criterion) {
+    let path = write_wiki();
+    let mut group = c.benchmark_group("tiddler_store");
+    group.sample_size(10);
+
+    group.bench_function("whole file: all tiddlers", |b| b.iter(|| {
+        let html = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(tiddlywiki_html::extract_all_tiddlers_from_html(&html).len(), TIDDLERS + 2);
+    }));
+    group.bench_function("streaming: all tiddlers", |b| b.iter(|| {
+        assert_eq!(tiddler_store::open(&path).unwrap().count(), TIDDLERS + 2);
+    }));
+    group.bench_function("whole file: favicon", |b| b.iter(|| {
+        let html = std::fs::read_to_string(&path).unwrap();
+        assert!(tiddlywiki_html::extract_favicon(&html).is_some());
+    }));
+    group.bench_function("streaming: favicon", |b| b.iter(|| {
+        assert!(tiddlywiki_html::extract_favicon_from_file(&path).is_some());
+    }));
+    group.finish();
+
+    let _ = std::fs::remove_file(&path);
+}
+
+criterion_group!(benches, bench_store);
+criterion_main!(benches);
@@ -0,0 +1,298 @@
+//! Backups of single-file wikis and tiddler history across them
+//!
+//! Backups are timestamped copies written before each save
+//! (`<stem>.backups/<stem>.YYYYMMDD-HHMMSS.html`, or a custom backup directory):
+//! - Backup creation and pruning of old backups
+//! - Backup discovery and timestamp parsing
+//! - Per-backup tiddler extraction (JSON stores, with legacy div fallback)
+//! - Line diffs between consecutive versions
+
+use std::path::{Path, PathBuf};
+
+use chrono::{Local, NaiveDateTime, TimeZone};
+
+use crate::text_diff::{self, DiffHunk};
+use crate::tiddlywiki_html;
+
+/// Timestamp format used in backup filenames (see `create_backup`)
+pub const BACKUP_TIMESTAMP_FORMAT: &str = "%Y%m%d-%H%M%S";
+
+/// A backup file belonging to a wiki, with the timestamp parsed from its name
+#[derive(Clone, Debug)]
+pub struct BackupFile {
+    pub path: PathBuf,
+    pub timestamp: NaiveDateTime,
+}
+
+/// One version of a tiddler as found in a backup (or the current wiki file)
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct TiddlerVersion {
+    /// File the version was extracted from
+    pub source: String,
+    /// True for the live wiki file, false for backups
+    pub is_current: bool,
+    /// RFC 3339 timestamp of the backup (or file modification time for the live wiki)
+    pub timestamp: String,
+    /// False if the tiddler did not exist at this point in time
+    pub exists: bool,
+    /// All tiddler fields (None for legacy div-format backups or missing tiddlers)
+    pub fields: Option<serde_json::Value>,
+    /// Tiddler text
+    pub text: Option<String>,
+    /// Line diff hunks of the text against the previous (older) version
+    pub diff: Vec<DiffHunk>,
+}
+
+/// Number of backups kept per wiki unless configured otherwise
+pub const DEFAULT_BACKUP_COUNT: u32 = 20;
+
+/// Create a backup of the wiki file before saving
+/// If custom_backup_dir is Some, backups go there; otherwise to .backups folder next to wiki
+/// backup_count: None = default 20, Some(0) = unlimited, Some(n) = keep n backups
+pub async fn create_backup(path: &Path, custom_backup_dir: Option<&str>, backup_count: Option<u32>) -> Result<(), String> {
+    if !path.exists() {
+        return Ok(()); // No backup needed for new files
+    }
+
+    let filename = path.file_stem().and_then(|s| s.to_str()).unwrap_or("wiki");
+    let backup_dir = backup_dir_for_wiki(path, custom_backup_dir).ok_or("No parent directory")?;
+
+    tokio::fs::create_dir_all(&backup_dir)
+        .await
+        .map_err(|e| format!("Failed to create backup dir: {}", e))?;
+
+    // Create timestamped backup filename
+    let timestamp = Local::now().format(BACKUP_TIMESTAMP_FORMAT);
+    let backup_name = format!("{}.{}.html", filename, timestamp);
+    let backup_path = backup_dir.join(backup_name);
+
+    // Copy current file to backup
+    tokio::fs::copy(path, &backup_path)
+        .await
+        .map_err(|e| format!("Failed to create backup: {}", e))?;
+
+    // Clean up old backups (0 = unlimited)
+    let keep = backup_count.unwrap_or(DEFAULT_BACKUP_COUNT);
+    if keep > 0 {
+        cleanup_old_backups(&backup_dir, keep as usize).await;
+    }
+
+    Ok(())
+}
+
+/// Remove old backups, keeping only the most recent ones
+pub async fn cleanup_old_backups(backup_dir: &Path, keep: usize) {
+    if keep == 0 {
+        return; // 0 means unlimited, don't delete anything
+    }
+
+    if let Ok(mut entries) = tokio::fs::read_dir(backup_dir).await {
+        let mut backups: Vec<PathBuf> = Vec::new();
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let path = entry.path();
+            if path.extension().map(|e| e == "html").unwrap_or(false) {
+                backups.push(path);
+            }
+        }
+
+        // Sort by name (which includes timestamp) descending
+        backups.sort();
+        backups.reverse();
+
+        // Remove old backups
+        for old_backup in backups.into_iter().skip(keep) {
+            let _ = tokio::fs::remove_file(old_backup).await;
+        }
+    }
+}
+
+/// Resolve the backup directory for a wiki, honouring a custom backup dir
+pub fn backup_dir_for_wiki(wiki_path: &Path, custom_backup_dir: Option<&str>) -> Option<PathBuf> {
+    if let Some(custom_dir) = custom_backup_dir {
+        return Some(PathBuf::from(custom_dir));
+    }
+    let parent = wiki_path.parent()?;
+    let stem = wiki_path.file_stem().and_then(|s| s.to_str()).unwrap_or("wiki");
+    Some(parent.join(format!("{}.backups", stem)))
+}
+
+/// List the backups of a wiki, oldest first.
+/// Only files named `<stem>.<timestamp>.html` are considered, so a shared custom
+/// backup directory holding backups of several wikis is handled correctly.
+pub fn list_wiki_backups(wiki_path: &Path, custom_backup_dir: Option<&str>) -> Vec<BackupFile> {
+    let stem = wiki_path.file_stem().and_then(|s| s.to_str()).unwrap_or("wiki");
+    let backup_dir = match backup_dir_for_wiki(wiki_path, custom_backup_dir) {
+        Some(dir) => dir,
+        None => return Vec::new(),
+    };
+
+    let entries = match std::fs::read_dir(&backup_dir) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    let prefix = format!("{}.", stem);
+    let mut backups: Vec<BackupFile> = entries
+        .filter_map(|e| e.ok())
+        .filter_map(|entry| {
+            let path = entry.path();
+            let name = path.file_name()?.to_str()?;
+            let ts = name.strip_prefix(&prefix)?.strip_suffix(".html")?;
+            let timestamp = NaiveDateTime::parse_from_str(ts, BACKUP_TIMESTAMP_FORMAT).ok()?;
+            Some(BackupFile { path, timestamp })
+        })
+        .collect();
+
+    backups.sort_by_key(|b| b.timestamp);
+    backups
+}
+
+/// Tiddler-level differences between two versions of a wiki (titles, sorted)
+#[derive(Clone, Debug, Default, PartialEq, serde::Serialize)]
+pub struct TiddlerChanges {
+    /// In the newer version only
+    pub added: Vec<String>,
+    /// In the older version only
+    pub removed: Vec<String>,
+    /// In both, with different fields
+    pub changed: Vec<String>,
+}
+
+/// Tiddlers of a wiki by title (JSON stores; the last occurrence wins, as in
+/// TiddlyWiki's load order)
+fn tiddlers_by_title(html: &str) -> std::collections::HashMap<String, serde_json::Value> {
+    tiddlywiki_html::extract_all_tiddlers_from_html(html)
+        .into_iter()
+        .filter_map(|tiddler| {
+            let title = tiddler.get("title")?.as_str()?.to_string();
+            Some((title, tiddler))
+        })
+        .collect()
+}
+
+/// Compare the tiddlers of two wiki files (`older`, e.g. a backup, and `newer`)
+pub fn diff_wiki_tiddlers(older_html: &str, newer_html: &str) -> TiddlerChanges {
+    let older = tiddlers_by_title(older_html);
+    let newer = tiddlers_by_title(newer_html);
+    let mut changes = TiddlerChanges::default();
+    for (title, tiddler) in &newer {
+        match older.get(title) {
+            None => changes.added.push(title.clone()),
+            Some(old) if old != tiddler => changes.changed.push(title.clone()),
+            Some(_) => {}
+        }
+    }
+    changes.removed = older.keys().filter(|title| !newer.contains_key(*title)).cloned().collect();
+    changes.added.sort();
+    changes.removed.sort();
+    changes.changed.sort();
+    changes
+}
+
+/// Find a tiddler in wiki HTML. Returns (fields, text).
+/// JSON stores are searched first (last occurrence wins, matching TiddlyWiki's
+/// load order); older div-format wikis fall back to text-only extraction.
+fn find_tiddler(html: &str, title: &str) -> Option<(Option<serde_json::Value>, Option<String>)> {
+    let found = tiddlywiki_html::extract_all_tiddlers_from_html(html)
+        .into_iter()
+        .rev()
+        .find(|t| t.get("title").and_then(|v| v.as_str()) == Some(title));
+
+    if let Some(tiddler) = found {
+        let text = tiddler.get("text").and_then(|v| v.as_str()).map(|s| s.to_string());
+        return Some((Some(tiddler), text));
+    }
+
+    tiddlywiki_html::extract_tiddler_from_html(html, title).map(|text| (None, Some(text)))
+}
+
+/// A backup timestamp as RFC 3339 in local time
+pub fn local_timestamp(naive: &NaiveDateTime) -> String {
+    match Local.from_local_datetime(naive).earliest() {
+        Some(dt) => dt.to_rfc3339(),
+        None => naive.format("%Y-%m-%dT%H:%M:%S").to_string(),
+    }
+}
+
+/// Collect the versions of a tiddler across all backups and the live wiki file.
+/// Consecutive identical versions are collapsed; the result is newest first.
+pub fn collect_tiddler_history(wiki_path: &Path, custom_backup_dir: Option<&str>, title: &str) -> Vec<TiddlerVersion> {
+    let mut sources: Vec<(PathBuf, String, bool)> = list_wiki_backups(wiki_path, custom_backup_dir)
+        .into_iter()
+        .map(|b| (b.path, local_timestamp(&b.timestamp), false))
+        .collect();
+
+    if wiki_path.exists() {
+        let modified = std::fs::metadata(wiki_path)
+            .and_then(|m| m.modified())
+            .map(|t| chrono::DateTime::<Local>::from(t).to_rfc3339())
+            .unwrap_or_default();
+        sources.push((wiki_path.to_path_buf(), modified, true));
+    }
+
+    let mut versions: Vec<TiddlerVersion> = Vec::new();
+    for (path, timestamp, is_current) in sources {
+        let html = match std::fs::read_to_string(&path) {
+            Ok(html) => html,
+            Err(e) => {
+                eprintln!("[TiddlyDesktop] History: failed to read {}: {}", path.display(), e);
+                continue;
+            }
+        };
+
+        let (exists, fields, text) = match find_tiddler(&html, title) {
+            Some((fields, text)) => (true, fields, text),
+            None => (false, None, None),
+        };
+
+        // Skip versions identical to the previous one
+        if let Some(prev) = versions.last() {
+            if prev.exists == exists && prev.fields == fields && prev.text == text {
+                continue;
+            }
+        }
+        // Leading "doesn't exist yet" entries carry no information
+        if versions.is_empty() && !exists {
+            continue;
+        }
+
+        let prev_text = versions.last().and_then(|v| v.text.clone()).unwrap_or_default();
+        let diff = text_diff::diff_lines(&prev_text, text.as_deref().unwrap_or(""));
+
+        versions.push(TiddlerVersion {
+            source: path.to_string_lossy().to_string(),
+            is_current,
+            timestamp,
+            exists,
+            fields,
+            text,
+            diff,
+        });
+    }
+
+    versions.reverse();
+    versions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wiki(tiddlers: &str) -> String {
+        format!(
+            r#"<html><body><script class="tiddlywiki-tiddler-store" type="application/json">{}</script></body></html>"#,
+            tiddlers
+        )
+    }
+
+    #[test]
+    fn test_diff_wiki_tiddlers() {
+        let older = wiki(r#"[{"title":"Kept","text":"a"},{"title":"Edited","text":"old"},{"title":"Gone","text":"x"}]"#);
+        let newer = wiki(r#"[{"title":"Kept","text":"a"},{"title":"Edited","text":"new"},{"title":"New","text":"y"}]"#);
+        let changes = diff_wiki_tiddlers(&older, &newer);
+        assert_eq!(changes.added, vec!["New"]);
+        assert_eq!(changes.removed, vec!["Gone"]);
+        assert_eq!(changes.changed, vec!["Edited"]);
+        assert_eq!(diff_wiki_tiddlers(&older, &older), TiddlerChanges::default());
+    }
+}
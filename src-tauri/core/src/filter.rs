@@ -0,0 +1,905 @@
+//! TiddlyWiki filter expressions, evaluated without booting TiddlyWiki
+//!
+//! A reimplementation of the commonly used part of TiddlyWiki's filter
+//! language, so features like selective sync, search indexing and scheduled
+//! journals can run filters against wikis that aren't open, without starting
+//! Node.js for every evaluation.
+//!
+//! Supported:
+//! - Runs with the `+ - ~ =` prefixes and the named prefixes `:or`, `:and`,
+//!   `:except`, `:else`, `:all`, `:intersection`, `:filter` and `:map`
+//! - Literal `[..]`, text reference `{..}`, variable `<..>` and regexp `/../`
+//!   operands, multiple operands separated by `,`, and `!` negation
+//! - The selection, string, list and sort operators handled in `apply_step`
+//!
+//! Operators that need the wikitext parser or widget tree (`links`,
+//! `backlinks`, `wikify`, ...) are rejected with an error instead of quietly
+//! returning nothing, so callers can fall back to the real TiddlyWiki. Other
+//! unknown operator names are field filters, like in TiddlyWiki.
+//!
+//! Shadow tiddlers are taken from the plugins stored in single-file wikis.
+//! Folder wikis only have their own tiddlers; plugins listed in
+//! `tiddlywiki.info` are not loaded.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+
+use chrono::{Local, NaiveDateTime, TimeZone, Utc};
+use regex::RegexBuilder;
+
+use crate::tiddlywiki_html;
+use crate::wiki_folder::{self, tiddler_from_json};
+
+/// A tiddler's fields, all as strings (the way TiddlyWiki stores them)
+pub type Tiddler = BTreeMap<String, String>;
+
+/// Operators of TiddlyWiki that can't be evaluated outside of TiddlyWiki itself
+const UNSUPPORTED_OPERATORS: &[&str] = &[
+    "links", "backlinks", "transcludes", "backtranscludes", "listed", "untagged",
+    "wikify", "getvariable", "variables", "function", "modules", "moduletypes",
+    "plugintiddlers", "shadowsource", "storyviews", "editions", "editiondescription",
+    "commands", "deserialize", "deserializers", "unusedtitle", "lookup", "sortsub",
+    "cascade", "jsonget", "jsonindexes", "jsontype", "jsonextract", "jsonset",
+    "format", "substitute", "charcode", "makedatauris", "parsedate", "sameday",
+    "eachday", "haschanged", "range", "sortan", "math", "add", "subtract", "multiply",
+    "divide", "remainder", "compare", "insertbefore", "insertafter", "move",
+    "putafter", "putbefore", "putfirst", "putlast", "replace", "append", "prepend",
+    "remove", "allafter", "allbefore", "after", "before", "next", "previous",
+    "cycle", "toggle", "contains", "pad", "slugify", "search-replace",
+    "encodebase64", "decodebase64", "encodeuri", "decodeuri", "encodeuricomponent",
+    "decodeuricomponent", "encodehtml", "decodehtml", "escaperegexp", "escapecss",
+    "stringify", "jsonstringify", "sum", "product", "average", "median", "variance",
+    "standard-deviation", "maxall", "minall", "fixed", "precision", "exponential",
+];
+
+/// The tiddlers of a wiki, indexed by title
+#[derive(Debug, Default, Clone)]
+pub struct Wiki {
+    tiddlers: BTreeMap<String, Tiddler>,
+    shadows: BTreeMap<String, Tiddler>,
+}
+
+impl Wiki {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load a single-file wiki (HTML) or a wiki folder
+    pub fn load(path: &Path) -> Result<Self, String> {
+        if path.is_dir() {
+            Self::from_folder(path)
+        } else {
+            let html = std::fs::read_to_string(path)
+                .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            Ok(Self::from_html(&html))
+        }
+    }
+
+    /// Tiddlers and plugin shadow tiddlers of a single-file wiki
+    pub fn from_html(html: &str) -> Self {
+        let mut wiki = Self::new();
+        for value in tiddlywiki_html::extract_all_tiddlers_from_html(html) {
+            if let Some(fields) = tiddler_from_json(&value) {
+                wiki.add_tiddler(fields);
+            }
+        }
+        wiki.unpack_plugins();
+        wiki
+    }
+
+    /// A wiki of the given tiddlers, with the shadow tiddlers of any plugins among them
+    pub fn from_tiddlers(tiddlers: impl IntoIterator<Item = Tiddler>) -> Self {
+        let mut wiki = Self::new();
+        for fields in tiddlers {
+            wiki.add_tiddler(fields);
+        }
+        wiki.unpack_plugins();
+        wiki
+    }
+
+    /// Tiddlers of a wiki folder, loaded from `tiddlers/` like TiddlyWiki does
+    /// (or from the database of a SQLite wiki)
+    pub fn from_folder(path: &Path) -> Result<Self, String> {
+        if crate::sqlite_wiki::is_sqlite_wiki(path) {
+            return Ok(Self::from_tiddlers(crate::sqlite_wiki::SqliteWiki::open(path)?.load_all()?));
+        }
+        let mut wiki = Self::new();
+        for file in wiki_folder::load_tiddlers_from_path(&path.join("tiddlers")) {
+            for fields in file.tiddlers {
+                wiki.add_tiddler(fields);
+            }
+        }
+        Ok(wiki)
+    }
+
+    /// Add (or replace) a tiddler. Tiddlers without a title are ignored.
+    pub fn add_tiddler(&mut self, fields: Tiddler) {
+        if let Some(title) = fields.get("title").filter(|t| !t.is_empty()).cloned() {
+            self.tiddlers.insert(title, fields);
+        }
+    }
+
+    /// A tiddler, or the shadow tiddler of that title
+    pub fn get_tiddler(&self, title: &str) -> Option<&Tiddler> {
+        self.tiddlers.get(title).or_else(|| self.shadows.get(title))
+    }
+
+    pub fn tiddler_exists(&self, title: &str) -> bool {
+        self.tiddlers.contains_key(title)
+    }
+
+    pub fn is_shadow(&self, title: &str) -> bool {
+        self.shadows.contains_key(title)
+    }
+
+    /// Titles of all (non-shadow) tiddlers, sorted
+    pub fn titles(&self) -> Vec<String> {
+        self.tiddlers.keys().cloned().collect()
+    }
+
+    /// Evaluate a filter expression
+    pub fn filter(&self, expr: &str) -> Result<Vec<String>, String> {
+        self.filter_with_variables(expr, &HashMap::new())
+    }
+
+    /// Evaluate a filter expression with variables (e.g. `currentTiddler`)
+    pub fn filter_with_variables(&self, expr: &str, variables: &HashMap<String, String>) -> Result<Vec<String>, String> {
+        parse_filter(expr)?.evaluate(self, variables)
+    }
+
+    /// Shadow tiddlers come from the `tiddlers` map in the text of plugin tiddlers
+    fn unpack_plugins(&mut self) {
+        for plugin in self.tiddlers.values().filter(|t| t.contains_key("plugin-type")) {
+            let Some(text) = plugin.get("text") else { continue };
+            let Ok(parsed) = serde_json::from_str::<serde_json::Value>(text) else { continue };
+            let Some(tiddlers) = parsed.get("tiddlers").and_then(|t| t.as_object()) else { continue };
+            for (title, value) in tiddlers {
+                if let Some(mut fields) = tiddler_from_json(value) {
+                    fields.insert("title".to_string(), title.clone());
+                    self.shadows.insert(title.clone(), fields);
+                }
+            }
+        }
+    }
+
+    fn field(&self, title: &str, field: &str) -> Option<String> {
+        if field == "title" {
+            return Some(title.to_string());
+        }
+        self.get_tiddler(title).and_then(|t| t.get(field).cloned())
+    }
+
+    fn text_reference(&self, reference: &str, variables: &HashMap<String, String>) -> String {
+        let current = || variables.get("currentTiddler").cloned().unwrap_or_default();
+        if let Some((title, field)) = reference.split_once("!!") {
+            let title = if title.is_empty() { current() } else { title.to_string() };
+            self.field(&title, field).unwrap_or_default()
+        } else if let Some((title, index)) = reference.split_once("##") {
+            let title = if title.is_empty() { current() } else { title.to_string() };
+            self.data_index(&title, index).unwrap_or_default()
+        } else {
+            let title = if reference.is_empty() { current() } else { reference.to_string() };
+            self.field(&title, "text").unwrap_or_default()
+        }
+    }
+
+    /// A value of a data tiddler (JSON object or `key: value` dictionary)
+    fn data_index(&self, title: &str, index: &str) -> Option<String> {
+        let tiddler = self.get_tiddler(title)?;
+        let text = tiddler.get("text")?;
+        if tiddler.get("type").map(String::as_str) == Some("application/json") {
+            let parsed: serde_json::Value = serde_json::from_str(text).ok()?;
+            return match parsed.get(index)? {
+                serde_json::Value::String(s) => Some(s.clone()),
+                other => Some(other.to_string()),
+            };
+        }
+        text.lines()
+            .filter_map(|line| line.split_once(':'))
+            .find(|(key, _)| key.trim() == index)
+            .map(|(_, value)| value.trim().to_string())
+    }
+}
+
+/// Parse the content of a `.tid` (or `.meta`) file: `name: value` header
+/// lines, a blank line, then the text
+pub fn parse_tid(content: &str) -> Tiddler {
+    let mut fields = Tiddler::new();
+    let content = content.replace("\r\n", "\n");
+    let (header, text) = match content.split_once("\n\n") {
+        Some((header, text)) => (header, Some(text)),
+        None => (content.as_str(), None),
+    };
+    for line in header.lines() {
+        if let Some((name, value)) = line.split_once(':') {
+            let name = name.trim();
+            if !name.is_empty() {
+                fields.insert(name.to_string(), value.trim().to_string());
+            }
+        }
+    }
+    if let Some(text) = text {
+        fields.insert("text".to_string(), text.to_string());
+    }
+    fields
+}
+
+/// Split a TiddlyWiki title list like `one [[two words]] three`
+pub fn parse_title_list(s: &str) -> Vec<String> {
+    let mut titles = Vec::new();
+    let mut rest = s.trim_start();
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix("[[") {
+            if let Some(end) = after.find("]]") {
+                titles.push(after[..end].to_string());
+                rest = after[end + 2..].trim_start();
+                continue;
+            }
+        }
+        let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        titles.push(rest[..end].to_string());
+        rest = rest[end..].trim_start();
+    }
+    titles
+}
+
+/// Join titles into a title list, bracketing titles that contain spaces
+pub fn stringify_title_list(titles: &[String]) -> String {
+    titles.iter()
+        .map(|t| if t.contains(char::is_whitespace) || t.is_empty() { format!("[[{}]]", t) } else { t.clone() })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Parse a TiddlyWiki date field (`YYYYMMDDHHMMSSmmm`, UTC)
+fn parse_tw_date(value: &str) -> Option<chrono::DateTime<Utc>> {
+    let digits = value.get(..14)?;
+    NaiveDateTime::parse_from_str(digits, "%Y%m%d%H%M%S").ok().map(|d| d.and_utc())
+}
+
+/// A parsed filter expression, reusable across wikis
+#[derive(Debug, Clone)]
+pub struct Filter {
+    runs: Vec<Run>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum RunPrefix {
+    Or,
+    And,
+    Except,
+    Else,
+    All,
+    Intersection,
+    Filter,
+    Map,
+}
+
+#[derive(Debug, Clone)]
+enum RunBody {
+    Title(String),
+    Steps(Vec<Step>),
+}
+
+#[derive(Debug, Clone)]
+struct Run {
+    prefix: RunPrefix,
+    body: RunBody,
+}
+
+#[derive(Debug, Clone)]
+struct Step {
+    operator: String,
+    suffix: Option<String>,
+    negate: bool,
+    operands: Vec<Operand>,
+}
+
+#[derive(Debug, Clone)]
+enum Operand {
+    Literal(String),
+    TextReference(String),
+    Variable(String),
+    Regexp { pattern: String, flags: String },
+}
+
+/// Parse a filter expression
+pub fn parse_filter(expr: &str) -> Result<Filter, String> {
+    let mut parser = Parser { s: expr, pos: 0 };
+    let mut runs = Vec::new();
+    loop {
+        parser.skip_whitespace();
+        if parser.at_end() {
+            break;
+        }
+        runs.push(parser.run()?);
+    }
+    Ok(Filter { runs })
+}
+
+struct Parser<'a> {
+    s: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn at_end(&self) -> bool {
+        self.pos >= self.s.len()
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.s[self.pos..].chars().next()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek() {
+            if !c.is_whitespace() {
+                break;
+            }
+            self.pos += c.len_utf8();
+        }
+    }
+
+    /// Everything up to `end`, consuming `end` as well
+    fn until(&mut self, end: &str, what: &str) -> Result<String, String> {
+        match self.s[self.pos..].find(end) {
+            Some(i) => {
+                let value = self.s[self.pos..self.pos + i].to_string();
+                self.pos += i + end.len();
+                Ok(value)
+            }
+            None => Err(format!("Missing {} in filter expression", what)),
+        }
+    }
+
+    fn run(&mut self) -> Result<Run, String> {
+        let prefix = match self.peek() {
+            Some('+') => { self.pos += 1; RunPrefix::And }
+            Some('-') => { self.pos += 1; RunPrefix::Except }
+            Some('~') => { self.pos += 1; RunPrefix::Else }
+            Some('=') => { self.pos += 1; RunPrefix::All }
+            Some(':') => {
+                self.pos += 1;
+                let end = self.s[self.pos..].find('[').ok_or("Missing [ after filter run prefix")?;
+                let name = &self.s[self.pos..self.pos + end];
+                let name = name.split(':').next().unwrap_or("");
+                self.pos += end;
+                match name {
+                    "or" => RunPrefix::Or,
+                    "and" => RunPrefix::And,
+                    "except" => RunPrefix::Except,
+                    "else" => RunPrefix::Else,
+                    "all" => RunPrefix::All,
+                    "intersection" => RunPrefix::Intersection,
+                    "filter" => RunPrefix::Filter,
+                    "map" => RunPrefix::Map,
+                    other => return Err(format!("Unsupported filter run prefix: :{}", other)),
+                }
+            }
+            _ => RunPrefix::Or,
+        };
+
+        let rest = &self.s[self.pos..];
+        // `[[Title]]` can't contain `]`, so `[[Title]tags[]]` is an operation
+        let bracketed_title = rest.strip_prefix("[[")
+            .and_then(|after| after.find(']').map(|end| after[end..].starts_with("]]")))
+            .unwrap_or(false);
+        let body = if bracketed_title {
+            self.pos += 2;
+            RunBody::Title(self.until("]]", "]]")?)
+        } else if rest.starts_with('[') {
+            self.pos += 1;
+            RunBody::Steps(self.steps()?)
+        } else if let Some(quote @ ('"' | '\'')) = self.peek() {
+            self.pos += 1;
+            RunBody::Title(self.until(&quote.to_string(), "closing quote")?)
+        } else {
+            let end = rest.find(|c: char| c.is_whitespace() || c == '[' || c == ']').unwrap_or(rest.len());
+            if end == 0 {
+                return Err(format!("Unexpected '{}' in filter expression", &rest[..1]));
+            }
+            self.pos += end;
+            RunBody::Title(rest[..end].to_string())
+        };
+        Ok(Run { prefix, body })
+    }
+
+    /// Steps of a run, after its opening `[`, up to and including the closing `]`
+    fn steps(&mut self) -> Result<Vec<Step>, String> {
+        let mut steps = Vec::new();
+        loop {
+            let negate = self.peek() == Some('!');
+            if negate {
+                self.pos += 1;
+            }
+            let rest = &self.s[self.pos..];
+            let end = rest.find(['[', '{', '<', '/', ']'])
+                .ok_or("Missing [ in filter expression")?;
+            if rest[end..].starts_with(']') {
+                return Err("Missing operand in filter expression".to_string());
+            }
+            let name = &rest[..end];
+            self.pos += end;
+            let (operator, suffix) = match name.split_once(':') {
+                Some((operator, suffix)) => (operator, Some(suffix.to_string())),
+                None => (name, None),
+            };
+            let operator = if operator.is_empty() { "title" } else { operator }.to_string();
+
+            let mut operands = Vec::new();
+            loop {
+                let operand = match self.peek() {
+                    Some('[') => { self.pos += 1; Operand::Literal(self.until("]", "]")?) }
+                    Some('{') => { self.pos += 1; Operand::TextReference(self.until("}", "}")?) }
+                    Some('<') => { self.pos += 1; Operand::Variable(self.until(">", ">")?) }
+                    Some('/') => {
+                        self.pos += 1;
+                        let pattern = self.regexp_body()?;
+                        let flags = if self.peek() == Some('(') {
+                            self.pos += 1;
+                            self.until(")", ")")?
+                        } else {
+                            String::new()
+                        };
+                        Operand::Regexp { pattern, flags }
+                    }
+                    _ => return Err("Missing operand in filter expression".to_string()),
+                };
+                operands.push(operand);
+                if self.peek() == Some(',') {
+                    self.pos += 1;
+                } else {
+                    break;
+                }
+            }
+            steps.push(Step { operator, suffix, negate, operands });
+
+            match self.peek() {
+                Some(']') => {
+                    self.pos += 1;
+                    return Ok(steps);
+                }
+                None => return Err("Missing ] in filter expression".to_string()),
+                _ => {}
+            }
+        }
+    }
+
+    /// A regexp operand up to its unescaped closing `/`
+    fn regexp_body(&mut self) -> Result<String, String> {
+        let bytes = self.s.as_bytes();
+        let start = self.pos;
+        let mut i = start;
+        while i < bytes.len() {
+            match bytes[i] {
+                b'\\' => i += 2,
+                b'/' => {
+                    self.pos = i + 1;
+                    return Ok(self.s[start..i].to_string());
+                }
+                _ => i += 1,
+            }
+        }
+        Err("Missing / in filter expression".to_string())
+    }
+}
+
+/// Union that moves titles already present to the end, like TiddlyWiki's `pushTop`
+fn push_top(results: &mut Vec<String>, titles: Vec<String>) {
+    for title in titles {
+        results.retain(|t| t != &title);
+        results.push(title);
+    }
+}
+
+fn dedupe(titles: Vec<String>) -> Vec<String> {
+    let mut seen = HashSet::new();
+    titles.into_iter().filter(|t| seen.insert(t.clone())).collect()
+}
+
+impl Filter {
+    /// Evaluate against a wiki with the given variables
+    pub fn evaluate(&self, wiki: &Wiki, variables: &HashMap<String, String>) -> Result<Vec<String>, String> {
+        self.evaluate_with_source(wiki, wiki.titles(), variables)
+    }
+
+    fn evaluate_with_source(&self, wiki: &Wiki, source: Vec<String>, variables: &HashMap<String, String>) -> Result<Vec<String>, String> {
+        let mut results: Vec<String> = Vec::new();
+        for run in &self.runs {
+            let eval = |input: Vec<String>, variables: &HashMap<String, String>| -> Result<Vec<String>, String> {
+                match &run.body {
+                    RunBody::Title(title) => Ok(vec![title.clone()]),
+                    RunBody::Steps(steps) => {
+                        let mut titles = input;
+                        for step in steps {
+                            titles = apply_step(wiki, titles, step, variables)?;
+                        }
+                        Ok(titles)
+                    }
+                }
+            };
+            match run.prefix {
+                RunPrefix::Or => push_top(&mut results, eval(source.clone(), variables)?),
+                RunPrefix::All => results.extend(eval(source.clone(), variables)?),
+                RunPrefix::And => results = eval(results, variables)?,
+                RunPrefix::Except => {
+                    let removed: HashSet<String> = eval(source.clone(), variables)?.into_iter().collect();
+                    results.retain(|t| !removed.contains(t));
+                }
+                RunPrefix::Else => {
+                    if results.is_empty() {
+                        results = eval(source.clone(), variables)?;
+                    }
+                }
+                RunPrefix::Intersection => {
+                    let other: HashSet<String> = eval(source.clone(), variables)?.into_iter().collect();
+                    results.retain(|t| other.contains(t));
+                }
+                RunPrefix::Filter | RunPrefix::Map => {
+                    let mut output = Vec::new();
+                    for title in &results {
+                        let mut scoped = variables.clone();
+                        if let Some(current) = variables.get("currentTiddler") {
+                            scoped.insert("..currentTiddler".to_string(), current.clone());
+                        }
+                        scoped.insert("currentTiddler".to_string(), title.clone());
+                        let matched = eval(vec![title.clone()], &scoped)?;
+                        if run.prefix == RunPrefix::Filter {
+                            if !matched.is_empty() {
+                                output.push(title.clone());
+                            }
+                        } else {
+                            output.push(matched.into_iter().next().unwrap_or_default());
+                        }
+                    }
+                    results = if run.prefix == RunPrefix::Map { output } else { dedupe(output) };
+                }
+            }
+        }
+        Ok(results)
+    }
+}
+
+fn resolve_operand(wiki: &Wiki, operand: &Operand, variables: &HashMap<String, String>) -> String {
+    match operand {
+        Operand::Literal(s) => s.clone(),
+        Operand::TextReference(r) => wiki.text_reference(r, variables),
+        Operand::Variable(name) => variables.get(name).cloned().unwrap_or_default(),
+        Operand::Regexp { pattern, .. } => pattern.clone(),
+    }
+}
+
+/// Keep the titles for which `keep` is true (or false, for a negated step)
+fn select(input: Vec<String>, negate: bool, keep: impl Fn(&str) -> bool) -> Vec<String> {
+    input.into_iter().filter(|t| keep(t) != negate).collect()
+}
+
+fn number(s: &str) -> Result<f64, String> {
+    s.trim().parse::<f64>().map_err(|_| format!("Not a number in filter operand: {}", s))
+}
+
+fn count_operand(s: &str, default: usize) -> usize {
+    s.trim().parse::<usize>().unwrap_or(default)
+}
+
+fn apply_step(wiki: &Wiki, input: Vec<String>, step: &Step, variables: &HashMap<String, String>) -> Result<Vec<String>, String> {
+    let operands: Vec<String> = step.operands.iter().map(|o| resolve_operand(wiki, o, variables)).collect();
+    let operand = operands.first().cloned().unwrap_or_default();
+    let suffix = step.suffix.as_deref().unwrap_or("");
+    let negate = step.negate;
+    let current = || variables.get("currentTiddler").cloned().unwrap_or_default();
+
+    let output = match step.operator.as_str() {
+        "title" if !negate => vec![operand],
+        "title" => select(input, true, |t| t == operand),
+        "field" => {
+            let field = if suffix.is_empty() { "title" } else { suffix };
+            select(input, negate, |t| wiki.field(t, field).unwrap_or_default() == operand)
+        }
+        "tag" => select(input, negate, |t| {
+            wiki.field(t, "tags").map(|tags| parse_title_list(&tags).contains(&operand)).unwrap_or(false)
+        }),
+        "tags" => dedupe(input.iter().flat_map(|t| parse_title_list(&wiki.field(t, "tags").unwrap_or_default())).collect()),
+        "tagging" => {
+            let tags: HashSet<&String> = input.iter().collect();
+            wiki.titles().into_iter()
+                .filter(|t| parse_title_list(&wiki.field(t, "tags").unwrap_or_default()).iter().any(|tag| tags.contains(tag)))
+                .collect()
+        }
+        "has" => select(input, negate, |t| match suffix {
+            "field" => wiki.get_tiddler(t).map(|tiddler| tiddler.contains_key(&operand)).unwrap_or(false),
+            _ => wiki.field(t, &operand).map(|v| !v.is_empty()).unwrap_or(false),
+        }),
+        "is" => {
+            let current = current();
+            let check = |t: &str| -> Result<bool, String> {
+                Ok(match operand.as_str() {
+                    "system" => t.starts_with("$:/"),
+                    "tiddler" => wiki.tiddler_exists(t),
+                    "shadow" => wiki.is_shadow(t),
+                    "missing" => !wiki.tiddler_exists(t) && !wiki.is_shadow(t),
+                    "current" => t == current,
+                    "blank" => t.is_empty(),
+                    "draft" => wiki.field(t, "draft.of").is_some(),
+                    "tag" => wiki.titles().iter().any(|other| {
+                        parse_title_list(&wiki.field(other, "tags").unwrap_or_default()).iter().any(|tag| tag == t)
+                    }),
+                    other => return Err(format!("Unsupported filter operand: is[{}]", other)),
+                })
+            };
+            let mut output = Vec::new();
+            for title in input {
+                if check(&title)? != negate {
+                    output.push(title);
+                }
+            }
+            output
+        }
+        "all" => {
+            let mut output = Vec::new();
+            for kind in operand.split('+').filter(|k| !k.is_empty()) {
+                match kind {
+                    "tiddlers" => output.extend(wiki.titles()),
+                    "shadows" => output.extend(wiki.shadows.keys().cloned()),
+                    "current" => output.push(current()),
+                    other => return Err(format!("Unsupported filter operand: all[{}]", other)),
+                }
+            }
+            dedupe(output)
+        }
+        "each" => {
+            let field = if operand.is_empty() { "title" } else { operand.as_str() };
+            let mut seen = HashSet::new();
+            input.into_iter()
+                .filter(|t| wiki.get_tiddler(t).is_some() || field == "title")
+                .filter(|t| seen.insert(wiki.field(t, field).unwrap_or_default()))
+                .collect()
+        }
+        "prefix" => select(input, negate, |t| t.starts_with(&operand)),
+        "suffix" => select(input, negate, |t| t.ends_with(&operand)),
+        "removeprefix" => input.into_iter().filter_map(|t| t.strip_prefix(&operand).map(str::to_string)).collect(),
+        "removesuffix" => input.into_iter().filter_map(|t| t.strip_suffix(&operand).map(str::to_string)).collect(),
+        "addprefix" => input.into_iter().map(|t| format!("{}{}", operand, t)).collect(),
+        "addsuffix" => input.into_iter().map(|t| format!("{}{}", t, operand)).collect(),
+        "lowercase" => input.into_iter().map(|t| t.to_lowercase()).collect(),
+        "uppercase" => input.into_iter().map(|t| t.to_uppercase()).collect(),
+        "trim" => input.into_iter().map(|t| t.trim().to_string()).collect(),
+        "length" => input.into_iter().map(|t| t.chars().count().to_string()).collect(),
+        "minlength" => {
+            let min = count_operand(&operand, 0);
+            input.into_iter().filter(|t| t.chars().count() >= min).collect()
+        }
+        "split" => input.into_iter().flat_map(|t| t.split(operand.as_str()).map(str::to_string).collect::<Vec<_>>()).collect(),
+        "join" => {
+            if input.is_empty() { Vec::new() } else { vec![input.join(&operand)] }
+        }
+        "match" => select(input, negate, |t| {
+            if suffix == "caseinsensitive" { t.to_lowercase() == operand.to_lowercase() } else { t == operand }
+        }),
+        "regexp" => {
+            let flags = match step.operands.first() {
+                Some(Operand::Regexp { flags, .. }) => flags.clone(),
+                _ => String::new(),
+            };
+            let regex = RegexBuilder::new(&operand)
+                .case_insensitive(flags.contains('i'))
+                .multi_line(flags.contains('m'))
+                .build()
+                .map_err(|e| format!("Invalid regexp in filter: {}", e))?;
+            let field = if suffix.is_empty() { "title" } else { suffix };
+            select(input, negate, |t| regex.is_match(&wiki.field(t, field).unwrap_or_default()))
+        }
+        "search" => {
+            let (fields, flags) = suffix.split_once(':').unwrap_or((suffix, ""));
+            let flags: Vec<&str> = flags.split(',').collect();
+            let case_sensitive = flags.contains(&"casesensitive");
+            let normalize = |s: &str| if case_sensitive { s.to_string() } else { s.to_lowercase() };
+            let terms: Vec<String> = if flags.contains(&"literal") {
+                vec![normalize(&operand)]
+            } else {
+                operand.split_whitespace().map(normalize).collect()
+            };
+            let some = flags.contains(&"some");
+            let fields: Vec<&str> = if fields.is_empty() { vec!["title", "tags", "text"] } else { fields.split(',').collect() };
+            select(input, negate, |t| {
+                let haystacks: Vec<String> = if fields == ["*"] {
+                    wiki.get_tiddler(t).map(|tiddler| tiddler.values().map(|v| normalize(v)).collect()).unwrap_or_default()
+                } else {
+                    fields.iter().filter_map(|f| wiki.field(t, f)).map(|v| normalize(&v)).collect()
+                };
+                let found = |term: &String| haystacks.iter().any(|h| h.contains(term.as_str()));
+                !terms.is_empty() && if some { terms.iter().any(found) } else { terms.iter().all(found) }
+            })
+        }
+        "days" => {
+            let field = if suffix.is_empty() { "modified" } else { suffix };
+            let days: i64 = operand.trim().parse().unwrap_or(0);
+            let midnight = Local::now().date_naive().and_hms_opt(0, 0, 0)
+                .and_then(|d| Local.from_local_datetime(&d).earliest())
+                .map(|d| d.with_timezone(&Utc))
+                .unwrap_or_else(Utc::now);
+            let target = midnight + chrono::Duration::days(days);
+            select(input, negate, |t| match wiki.field(t, field).as_deref().and_then(parse_tw_date) {
+                Some(date) if days <= 0 => date >= target,
+                Some(date) => date >= midnight && date < target + chrono::Duration::days(1),
+                None => false,
+            })
+        }
+        "get" => input.iter().filter_map(|t| wiki.field(t, &operand)).filter(|v| !v.is_empty()).collect(),
+        "getindex" => input.iter().filter_map(|t| wiki.data_index(t, &operand)).collect(),
+        "fields" => {
+            let mut names: Vec<String> = input.iter()
+                .filter_map(|t| wiki.get_tiddler(t))
+                .flat_map(|tiddler| tiddler.keys().cloned())
+                .collect();
+            names.sort();
+            dedupe(names)
+        }
+        "list" => {
+            let (title, field) = match operand.split_once("!!") {
+                Some((title, field)) => (title.to_string(), field.to_string()),
+                None => (operand.clone(), "list".to_string()),
+            };
+            let title = if title.is_empty() { current() } else { title };
+            let list = parse_title_list(&wiki.field(&title, &field).unwrap_or_default());
+            if negate {
+                input.into_iter().filter(|t| !list.contains(t)).collect()
+            } else {
+                list
+            }
+        }
+        "enlist" => {
+            let list = parse_title_list(&operand);
+            if suffix == "raw" { list } else { dedupe(list) }
+        }
+        "sort" | "nsort" | "sortcs" => {
+            let field = if operand.is_empty() { "title" } else { operand.as_str() };
+            let mut keyed: Vec<(String, String)> = input.into_iter()
+                .map(|t| (wiki.field(&t, field).unwrap_or_default(), t))
+                .collect();
+            match step.operator.as_str() {
+                "nsort" => keyed.sort_by(|a, b| {
+                    match (a.0.trim().parse::<f64>(), b.0.trim().parse::<f64>()) {
+                        (Ok(x), Ok(y)) => x.partial_cmp(&y).unwrap_or(std::cmp::Ordering::Equal),
+                        (Ok(_), Err(_)) => std::cmp::Ordering::Less,
+                        (Err(_), Ok(_)) => std::cmp::Ordering::Greater,
+                        (Err(_), Err(_)) => a.0.to_lowercase().cmp(&b.0.to_lowercase()),
+                    }
+                }),
+                "sortcs" => keyed.sort_by(|a, b| a.0.cmp(&b.0)),
+                _ => keyed.sort_by_key(|k| k.0.to_lowercase()),
+            }
+            let mut sorted: Vec<String> = keyed.into_iter().map(|(_, t)| t).collect();
+            if negate {
+                sorted.reverse();
+            }
+            sorted
+        }
+        "reverse" => input.into_iter().rev().collect(),
+        "unique" => dedupe(input),
+        "count" => vec![input.len().to_string()],
+        "first" => input.into_iter().take(count_operand(&operand, 1)).collect(),
+        "last" => {
+            let n = count_operand(&operand, 1);
+            let skip = input.len().saturating_sub(n);
+            input.into_iter().skip(skip).collect()
+        }
+        "rest" | "butfirst" | "bf" => input.into_iter().skip(count_operand(&operand, 1)).collect(),
+        "butlast" | "bl" => {
+            let n = count_operand(&operand, 1);
+            let keep = input.len().saturating_sub(n);
+            input.into_iter().take(keep).collect()
+        }
+        "nth" => {
+            let n = count_operand(&operand, 1).max(1);
+            input.into_iter().nth(n - 1).into_iter().collect()
+        }
+        "limit" => {
+            let n = number(&operand)? as usize;
+            if negate {
+                let skip = input.len().saturating_sub(n);
+                input.into_iter().skip(skip).collect()
+            } else {
+                input.into_iter().take(n).collect()
+            }
+        }
+        "then" => {
+            if input.is_empty() { input } else { vec![operand] }
+        }
+        "else" => {
+            if input.is_empty() { vec![operand] } else { input }
+        }
+        "subfilter" => {
+            let filter = parse_filter(&operand)?;
+            let output = filter.evaluate_with_source(wiki, input.clone(), variables)?;
+            if negate {
+                input.into_iter().filter(|t| !output.contains(t)).collect()
+            } else {
+                output
+            }
+        }
+        "filter" => {
+            let filters = operands.iter().map(|o| parse_filter(o)).collect::<Result<Vec<_>, _>>()?;
+            let mut output = Vec::new();
+            for title in input {
+                let mut scoped = variables.clone();
+                scoped.insert("currentTiddler".to_string(), title.clone());
+                let mut matched = true;
+                for filter in &filters {
+                    if filter.evaluate_with_source(wiki, vec![title.clone()], &scoped)?.is_empty() {
+                        matched = false;
+                        break;
+                    }
+                }
+                if matched != negate {
+                    output.push(title);
+                }
+            }
+            output
+        }
+        name if UNSUPPORTED_OPERATORS.contains(&name) => {
+            return Err(format!("Filter operator not supported outside TiddlyWiki: {}", name));
+        }
+        // Like TiddlyWiki: unknown operators match the field of that name
+        field => select(input, negate, |t| wiki.field(t, field).unwrap_or_default() == operand),
+    };
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wiki() -> Wiki {
+        let mut wiki = Wiki::new();
+        for (title, tags, modified) in [
+            ("Journal 1", "Journal [[Daily Notes]]", "20240101120000000"),
+            ("Journal 2", "Journal", "20240102120000000"),
+            ("Recipe", "Cooking", "20231224120000000"),
+            ("$:/config/Thing", "", "20240101000000000"),
+        ] {
+            let mut fields = Tiddler::new();
+            fields.insert("title".to_string(), title.to_string());
+            fields.insert("tags".to_string(), tags.to_string());
+            fields.insert("modified".to_string(), modified.to_string());
+            fields.insert("text".to_string(), format!("Text of {}", title));
+            wiki.add_tiddler(fields);
+        }
+        wiki
+    }
+
+    #[test]
+    fn test_evaluates_runs_and_operators() {
+        let wiki = wiki();
+        assert_eq!(wiki.filter("[tag[Journal]]").unwrap(), vec!["Journal 1", "Journal 2"]);
+        assert_eq!(wiki.filter("[tag[Daily Notes]] Recipe").unwrap(), vec!["Journal 1", "Recipe"]);
+        assert_eq!(wiki.filter("[!is[system]] -[tag[Journal]]").unwrap(), vec!["Recipe"]);
+        assert_eq!(wiki.filter("[all[tiddlers]!is[system]!sort[modified]first[]]").unwrap(), vec!["Journal 2"]);
+        assert_eq!(wiki.filter("[tag[Nope]] ~[[Fallback]]").unwrap(), vec!["Fallback"]);
+        assert_eq!(wiki.filter("[search[of recipe]]").unwrap(), vec!["Recipe"]);
+        assert_eq!(wiki.filter("[tag[Journal]] :filter[get[tags]match[Journal]]").unwrap(), vec!["Journal 2"]);
+        assert_eq!(wiki.filter("[[Journal 1]tags[]]").unwrap(), vec!["Journal", "Daily Notes"]);
+        assert_eq!(wiki.filter("[prefix[Journal]count[]]").unwrap(), vec!["2"]);
+        assert_eq!(wiki.filter("[regexp/^j.*2$/(i)]").unwrap(), vec!["Journal 2"]);
+        assert_eq!(wiki.filter("a b a").unwrap(), vec!["b", "a"]);
+        assert_eq!(wiki.filter("[{Recipe!!modified}]").unwrap(), vec!["20231224120000000"]);
+    }
+
+    #[test]
+    fn test_rejects_bad_and_unsupported_filters() {
+        let wiki = wiki();
+        assert!(wiki.filter("[tag[Journal]").is_err());
+        assert!(wiki.filter("[tag").is_err());
+        assert!(wiki.filter("[[Recipe]links[]]").is_err());
+        assert!(wiki.filter(":sort[title[x]]").is_err());
+    }
+
+    #[test]
+    fn test_parses_tid_files_and_title_lists() {
+        let tiddler = parse_tid("title: My Note\r\ntags: a [[b c]]\r\n\r\nLine one\r\n\r\nLine two");
+        assert_eq!(tiddler["title"], "My Note");
+        assert_eq!(tiddler["text"], "Line one\n\nLine two");
+        assert_eq!(parse_title_list(&tiddler["tags"]), vec!["a", "b c"]);
+        assert_eq!(stringify_title_list(&["a".to_string(), "b c".to_string()]), "a [[b c]]");
+    }
+}
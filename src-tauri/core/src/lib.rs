@@ -0,0 +1,45 @@
+//! Wiki management for TiddlyDesktop, without Tauri
+//!
+//! The parts of TiddlyDesktop that don't need a window, so other Rust projects
+//! (or a command-line tool) can manage the same wikis and data directory:
+//! - `storage`: wiki list, per-wiki configs and app settings of a data directory
+//! - `tiddlywiki_html`: reading and writing tiddlers in single-file wikis
+//! - `tiddler_store`: streaming the tiddlers of big single-file wikis
+//! - `backup`: timestamped backups before saving, and tiddler history across them
+//! - `text_diff`: structured line/word diffs
+//! - `filter`: TiddlyWiki filter expressions evaluated against closed wikis
+//! - `wiki_folder`: tiddler files, plugin folders and boot pages of wiki folders
+//! - `sqlite_wiki`: wiki folders storing their tiddlers in a SQLite database
+//! - `sync`: LAN/relay sync message types, encryption and conflict detection
+//! - `types`, `utils`, `path_identity`: shared data types and path helpers
+//!
+//! Errors are reported as `String`s, like everywhere in the app.
+//!
+//! ```no_run
+//! use tiddlydesktop_core::{backup, storage::DataStore, tiddlywiki_html};
+//!
+//! # async fn example() -> Result<(), String> {
+//! let store = DataStore::new("/home/me/.local/share/com.burningtreec.tiddlydesktop-rs");
+//! for wiki in store.load_recent_files().iter().filter(|w| !w.is_folder) {
+//!     let path = std::path::Path::new(&wiki.path);
+//!     let html = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+//!     let html = tiddlywiki_html::inject_tiddler_into_html(&html, "Visited", "text/vnd.tiddlywiki", "yes")?;
+//!     backup::create_backup(path, wiki.backup_dir.as_deref(), wiki.backup_count).await?;
+//!     std::fs::write(path, html).map_err(|e| e.to_string())?;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod backup;
+pub mod filter;
+pub mod path_identity;
+pub mod sqlite_wiki;
+pub mod storage;
+pub mod sync;
+pub mod text_diff;
+pub mod tiddler_store;
+pub mod tiddlywiki_html;
+pub mod types;
+pub mod utils;
+pub mod wiki_folder;
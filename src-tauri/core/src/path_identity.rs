@@ -0,0 +1,255 @@
+//! Path identity: deciding whether two paths refer to the same wiki
+//!
+//! The same wiki can be reached through different path strings:
+//! - Symlinks (Unix) and junctions (Windows) to the file or a parent folder
+//! - Hardlinks to the same file
+//! - Different letter case on case-insensitive filesystems (Windows, macOS)
+//! - `\\?\` prefixes, `..` components, trailing separators
+//!
+//! `PathIdentity` captures a canonical path plus the filesystem's file ID
+//! (device + inode on Unix, volume serial + file index on Windows).
+//! Existing files are compared by file ID; paths that don't exist (yet)
+//! fall back to comparing canonical paths.
+
+use std::path::{Component, Path, PathBuf};
+
+/// Filesystem-level identity of an existing file or directory
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FileId {
+    volume: u64,
+    index: u64,
+}
+
+impl FileId {
+    #[cfg(unix)]
+    fn of(path: &Path) -> Option<Self> {
+        use std::os::unix::fs::MetadataExt;
+        let meta = std::fs::metadata(path).ok()?;
+        Some(Self { volume: meta.dev(), index: meta.ino() })
+    }
+
+    #[cfg(target_os = "windows")]
+    fn of(path: &Path) -> Option<Self> {
+        use std::os::windows::fs::OpenOptionsExt;
+        use std::os::windows::io::AsRawHandle;
+        use windows::Win32::Foundation::HANDLE;
+        use windows::Win32::Storage::FileSystem::{GetFileInformationByHandle, BY_HANDLE_FILE_INFORMATION};
+
+        // FILE_FLAG_BACKUP_SEMANTICS is required to open directories
+        const FILE_FLAG_BACKUP_SEMANTICS: u32 = 0x0200_0000;
+        let file = std::fs::OpenOptions::new()
+            .read(true)
+            .custom_flags(FILE_FLAG_BACKUP_SEMANTICS)
+            .open(path)
+            .ok()?;
+        let mut info = BY_HANDLE_FILE_INFORMATION::default();
+        unsafe { GetFileInformationByHandle(HANDLE(file.as_raw_handle()), &mut info) }.ok()?;
+        Some(Self {
+            volume: info.dwVolumeSerialNumber as u64,
+            index: ((info.nFileIndexHigh as u64) << 32) | info.nFileIndexLow as u64,
+        })
+    }
+}
+
+/// Canonical path and file ID of a path, for identity comparisons
+#[derive(Clone, Debug)]
+pub struct PathIdentity {
+    canonical: PathBuf,
+    file_id: Option<FileId>,
+}
+
+impl PathIdentity {
+    /// Resolve a path's identity. Never fails: paths that can't be resolved
+    /// keep a lexically normalized form and no file ID.
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        Self {
+            canonical: canonicalize_lenient(path),
+            file_id: FileId::of(path),
+        }
+    }
+
+    /// The canonical path (symlinks/junctions resolved where possible)
+    pub fn canonical(&self) -> &Path {
+        &self.canonical
+    }
+
+    /// The filesystem file ID, if the path exists
+    pub fn file_id(&self) -> Option<FileId> {
+        self.file_id
+    }
+
+    /// Whether both identities refer to the same file or directory
+    pub fn same_as(&self, other: &PathIdentity) -> bool {
+        match (self.file_id, other.file_id) {
+            (Some(a), Some(b)) => a == b,
+            _ => canonical_paths_equal(&self.canonical, &other.canonical),
+        }
+    }
+}
+
+impl PartialEq for PathIdentity {
+    fn eq(&self, other: &Self) -> bool {
+        self.same_as(other)
+    }
+}
+
+/// Whether two path strings refer to the same file or directory
+pub fn same_path(path1: &str, path2: &str) -> bool {
+    if path1 == path2 {
+        return true;
+    }
+    PathIdentity::new(path1).same_as(&PathIdentity::new(path2))
+}
+
+/// Canonicalize, falling back to canonicalizing the nearest existing ancestor
+/// (so a not-yet-created file inside a symlinked folder still resolves) and
+/// finally to lexical normalization
+fn canonicalize_lenient(path: &Path) -> PathBuf {
+    if let Ok(canonical) = dunce::canonicalize(path) {
+        return canonical;
+    }
+    let normalized = normalize_lexically(path);
+    let mut ancestor = normalized.as_path();
+    let mut rest: Vec<&std::ffi::OsStr> = Vec::new();
+    while let (Some(parent), Some(name)) = (ancestor.parent(), ancestor.file_name()) {
+        rest.push(name);
+        ancestor = parent;
+        if let Ok(canonical) = dunce::canonicalize(ancestor) {
+            return rest.iter().rev().fold(canonical, |acc, name| acc.join(name));
+        }
+    }
+    normalized
+}
+
+/// Resolve `.` and `..` components without touching the filesystem
+fn normalize_lexically(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in dunce::simplified(path).components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other.as_os_str()),
+        }
+    }
+    normalized
+}
+
+/// Compare canonical paths, ignoring case where the filesystem usually does
+fn canonical_paths_equal(a: &Path, b: &Path) -> bool {
+    #[cfg(any(target_os = "windows", target_os = "macos"))]
+    {
+        a.to_string_lossy().to_lowercase() == b.to_string_lossy().to_lowercase()
+    }
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    {
+        a == b
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Scratch directory for a test, removed when dropped
+    struct TestDir(PathBuf);
+
+    impl TestDir {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("td-path-identity-{}-{}", name, std::process::id()));
+            let _ = std::fs::remove_dir_all(&dir);
+            std::fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+
+        fn wiki(&self) -> PathBuf {
+            let wiki = self.0.join("wiki.html");
+            std::fs::write(&wiki, "<html></html>").unwrap();
+            wiki
+        }
+    }
+
+    impl Drop for TestDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn test_same_file_through_dot_components() {
+        let dir = TestDir::new("dots");
+        let wiki = dir.wiki();
+        let indirect = dir.0.join("sub").join("..").join("wiki.html");
+        std::fs::create_dir_all(dir.0.join("sub")).unwrap();
+        assert!(same_path(&wiki.to_string_lossy(), &indirect.to_string_lossy()));
+        assert!(!same_path(&wiki.to_string_lossy(), &dir.0.join("other.html").to_string_lossy()));
+    }
+
+    #[test]
+    fn test_hardlinked_wikis_are_the_same() {
+        let dir = TestDir::new("hardlink");
+        let wiki = dir.wiki();
+        let link = dir.0.join("linked.html");
+        std::fs::hard_link(&wiki, &link).unwrap();
+        assert!(PathIdentity::new(&wiki).same_as(&PathIdentity::new(&link)));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinked_wikis_are_the_same() {
+        let dir = TestDir::new("symlink");
+        let wiki = dir.wiki();
+
+        // Symlink to the file itself
+        let file_link = dir.0.join("link.html");
+        std::os::unix::fs::symlink(&wiki, &file_link).unwrap();
+        assert!(PathIdentity::new(&wiki) == PathIdentity::new(&file_link));
+
+        // Symlinked folder, including a file that doesn't exist yet
+        let folder_link = dir.0.join("folder-link");
+        std::os::unix::fs::symlink(&dir.0, &folder_link).unwrap();
+        assert!(PathIdentity::new(&wiki) == PathIdentity::new(folder_link.join("wiki.html")));
+        let missing = PathIdentity::new(folder_link.join("new.html"));
+        assert!(missing.file_id().is_none());
+        assert!(missing == PathIdentity::new(dir.0.join("new.html")));
+    }
+
+    #[cfg(target_os = "windows")]
+    #[test]
+    fn test_junctioned_wikis_are_the_same() {
+        let dir = TestDir::new("junction");
+        let target = dir.0.join("target");
+        std::fs::create_dir_all(&target).unwrap();
+        std::fs::write(target.join("wiki.html"), "<html></html>").unwrap();
+        let junction = dir.0.join("junction");
+        let status = std::process::Command::new("cmd")
+            .args(["/C", "mklink", "/J"])
+            .arg(&junction)
+            .arg(&target)
+            .status()
+            .unwrap();
+        assert!(status.success());
+        assert!(PathIdentity::new(target.join("wiki.html")) == PathIdentity::new(junction.join("wiki.html")));
+    }
+
+    #[cfg(any(target_os = "windows", target_os = "macos"))]
+    #[test]
+    fn test_case_insensitive_filesystems() {
+        let dir = TestDir::new("case");
+        let wiki = dir.wiki();
+        let upper = dir.0.join("WIKI.HTML");
+        assert!(same_path(&wiki.to_string_lossy(), &upper.to_string_lossy()));
+        // Not-yet-existing files compare case-insensitively too
+        assert!(same_path(&dir.0.join("new.html").to_string_lossy(), &dir.0.join("NEW.html").to_string_lossy()));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_case_sensitive_filesystems() {
+        let dir = TestDir::new("case");
+        let wiki = dir.wiki();
+        assert!(!same_path(&wiki.to_string_lossy(), &dir.0.join("WIKI.HTML").to_string_lossy()));
+    }
+}
@@ -0,0 +1,239 @@
+//! Wiki folders whose tiddlers live in a SQLite database
+//!
+//! A SQLite wiki is a wiki folder with a `tiddlers.sqlite` database instead of
+//! a `tiddlers/` folder. Its `tiddlywiki.info` still names the plugins, themes
+//! and languages it boots with. Saving a tiddler writes one row, so saving
+//! stays fast no matter how many tiddlers the wiki has.
+//!
+//! Single-file wikis are imported with `import_html` and exported back to a
+//! standalone single-file wiki with `export_html`.
+
+use std::path::Path;
+
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::filter::Tiddler;
+use crate::tiddlywiki_html;
+use crate::wiki_folder::{self, TiddlyWikiInstall};
+
+/// The database file inside the wiki folder
+pub const DATABASE_FILE: &str = "tiddlers.sqlite";
+
+/// Plugins only needed while the wiki is served from its folder
+const SERVER_PLUGINS: &[&str] = &["tiddlywiki/tiddlyweb", "tiddlywiki/filesystem"];
+
+/// Whether `wiki_dir` is a wiki folder that stores its tiddlers in SQLite
+pub fn is_sqlite_wiki(wiki_dir: &Path) -> bool {
+    wiki_dir.join(DATABASE_FILE).is_file()
+}
+
+/// An open tiddler database
+pub struct SqliteWiki {
+    conn: Connection,
+    data_version: i64,
+}
+
+impl SqliteWiki {
+    /// Open the database of a wiki folder, creating it if needed
+    pub fn open(wiki_dir: &Path) -> Result<Self, String> {
+        let path = wiki_dir.join(DATABASE_FILE);
+        let conn = Connection::open(&path)
+            .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+        Self::init(conn)
+    }
+
+    /// A database that only lives in memory
+    pub fn open_in_memory() -> Result<Self, String> {
+        Self::init(Connection::open_in_memory().map_err(|e| e.to_string())?)
+    }
+
+    fn init(conn: Connection) -> Result<Self, String> {
+        conn.execute_batch(
+            "PRAGMA journal_mode = WAL;
+             PRAGMA synchronous = NORMAL;
+             CREATE TABLE IF NOT EXISTS tiddlers (
+                 title TEXT PRIMARY KEY NOT NULL,
+                 fields TEXT NOT NULL
+             );",
+        )
+        .map_err(|e| format!("Failed to set up tiddler database: {}", e))?;
+        let mut wiki = Self { conn, data_version: 0 };
+        wiki.data_version = wiki.query_data_version()?;
+        Ok(wiki)
+    }
+
+    fn query_data_version(&self) -> Result<i64, String> {
+        self.conn
+            .query_row("PRAGMA data_version", [], |row| row.get(0))
+            .map_err(|e| e.to_string())
+    }
+
+    /// Whether another connection (e.g. another program) changed the database
+    /// since the last call
+    pub fn changed_externally(&mut self) -> bool {
+        match self.query_data_version() {
+            Ok(version) if version != self.data_version => {
+                self.data_version = version;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn count(&self) -> Result<usize, String> {
+        self.conn
+            .query_row("SELECT COUNT(*) FROM tiddlers", [], |row| row.get::<_, i64>(0))
+            .map(|n| n as usize)
+            .map_err(|e| e.to_string())
+    }
+
+    pub fn load_all(&self) -> Result<Vec<Tiddler>, String> {
+        let mut stmt = self.conn
+            .prepare("SELECT fields FROM tiddlers ORDER BY title")
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([], |row| row.get::<_, String>(0))
+            .map_err(|e| e.to_string())?;
+        let mut tiddlers = Vec::new();
+        for fields in rows {
+            let fields = fields.map_err(|e| e.to_string())?;
+            match serde_json::from_str::<Tiddler>(&fields) {
+                Ok(tiddler) => tiddlers.push(tiddler),
+                Err(e) => eprintln!("[TiddlyDesktop] Skipping unreadable tiddler row: {}", e),
+            }
+        }
+        Ok(tiddlers)
+    }
+
+    pub fn get(&self, title: &str) -> Result<Option<Tiddler>, String> {
+        let fields: Option<String> = self.conn
+            .query_row("SELECT fields FROM tiddlers WHERE title = ?1", params![title], |row| row.get(0))
+            .optional()
+            .map_err(|e| e.to_string())?;
+        fields
+            .map(|f| serde_json::from_str(&f).map_err(|e| format!("Failed to read tiddler {}: {}", title, e)))
+            .transpose()
+    }
+
+    /// Insert or replace a tiddler (the `title` field is the key)
+    pub fn put(&mut self, tiddler: &Tiddler) -> Result<(), String> {
+        self.put_all(std::iter::once(tiddler.clone())).map(|_| ())
+    }
+
+    /// Insert or replace many tiddlers in one transaction
+    pub fn put_all(&mut self, tiddlers: impl IntoIterator<Item = Tiddler>) -> Result<usize, String> {
+        let tx = self.conn.transaction().map_err(|e| e.to_string())?;
+        let mut count = 0;
+        {
+            let mut stmt = tx
+                .prepare("INSERT OR REPLACE INTO tiddlers (title, fields) VALUES (?1, ?2)")
+                .map_err(|e| e.to_string())?;
+            for tiddler in tiddlers {
+                let Some(title) = tiddler.get("title") else { continue };
+                let fields = serde_json::to_string(&tiddler).map_err(|e| e.to_string())?;
+                stmt.execute(params![title, fields])
+                    .map_err(|e| format!("Failed to save tiddler {}: {}", title, e))?;
+                count += 1;
+            }
+        }
+        tx.commit().map_err(|e| format!("Failed to save tiddlers: {}", e))?;
+        // Our own writes aren't external changes
+        self.data_version = self.query_data_version()?;
+        Ok(count)
+    }
+
+    pub fn delete(&mut self, title: &str) -> Result<(), String> {
+        self.conn
+            .execute("DELETE FROM tiddlers WHERE title = ?1", params![title])
+            .map_err(|e| format!("Failed to delete tiddler {}: {}", title, e))?;
+        self.data_version = self.query_data_version()?;
+        Ok(())
+    }
+}
+
+/// Create a SQLite wiki in `wiki_dir` (which must not be a wiki folder yet)
+/// holding the tiddlers of a single-file wiki. The wiki keeps its own plugins
+/// as tiddlers; only the core comes from the TiddlyWiki installation serving
+/// it. Returns the number of imported tiddlers.
+pub fn import_html(html: &str, wiki_dir: &Path) -> Result<usize, String> {
+    if wiki_dir.join("tiddlywiki.info").exists() || is_sqlite_wiki(wiki_dir) {
+        return Err(format!("{} is already a wiki folder", wiki_dir.display()));
+    }
+    let tiddlers: Vec<Tiddler> = tiddlywiki_html::extract_all_tiddlers_from_html(html)
+        .iter()
+        .filter_map(wiki_folder::tiddler_from_json)
+        .filter(|t| t.get("title").map(String::as_str) != Some("$:/core"))
+        .collect();
+    if tiddlers.is_empty() {
+        return Err("No tiddlers found (not a TiddlyWiki 5.2 or later single-file wiki?)".to_string());
+    }
+
+    std::fs::create_dir_all(wiki_dir)
+        .map_err(|e| format!("Failed to create {}: {}", wiki_dir.display(), e))?;
+    let info = serde_json::json!({
+        "description": "SQLite wiki imported by TiddlyDesktop",
+        "plugins": SERVER_PLUGINS,
+        "themes": [],
+        "languages": []
+    });
+    let info = serde_json::to_string_pretty(&info).map_err(|e| e.to_string())?;
+    std::fs::write(wiki_dir.join("tiddlywiki.info"), info)
+        .map_err(|e| format!("Failed to write tiddlywiki.info: {}", e))?;
+
+    SqliteWiki::open(wiki_dir)?.put_all(tiddlers)
+}
+
+/// A standalone single-file wiki with the tiddlers of a SQLite wiki, the
+/// plugins from its `tiddlywiki.info` and the core of `install`
+pub fn export_html(wiki_dir: &Path, install: &TiddlyWikiInstall) -> Result<String, String> {
+    let server_plugins: Vec<String> = SERVER_PLUGINS.iter().map(|p| format!("$:/plugins/{}", p)).collect();
+    let mut all: std::collections::BTreeMap<String, Tiddler> = install.load_plugins(wiki_dir)?
+        .into_iter()
+        .filter_map(|p| p.get("title").cloned().map(|title| (title, p)))
+        .filter(|(title, _)| !server_plugins.contains(title))
+        .collect();
+    for tiddler in SqliteWiki::open(wiki_dir)?.load_all()? {
+        if let Some(title) = tiddler.get("title").cloned() {
+            all.insert(title, tiddler);
+        }
+    }
+    install.build_boot_html(&all.into_values().collect::<Vec<_>>())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tiddler(title: &str, text: &str) -> Tiddler {
+        [("title", title), ("text", text)].iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_put_get_delete() {
+        let mut wiki = SqliteWiki::open_in_memory().unwrap();
+        wiki.put_all([tiddler("B", "two"), tiddler("A", "one")]).unwrap();
+        wiki.put(&tiddler("A", "changed")).unwrap();
+        assert_eq!(wiki.count().unwrap(), 2);
+        assert_eq!(wiki.get("A").unwrap().unwrap()["text"], "changed");
+        let titles: Vec<String> = wiki.load_all().unwrap().iter().map(|t| t["title"].clone()).collect();
+        assert_eq!(titles, ["A", "B"]);
+        wiki.delete("A").unwrap();
+        assert!(wiki.get("A").unwrap().is_none());
+        assert!(!wiki.changed_externally());
+    }
+
+    #[test]
+    fn test_import_skips_core() {
+        let dir = std::env::temp_dir().join(format!("td-sqlite-import-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let html = r#"<script class="tiddlywiki-tiddler-store" type="application/json">[{"title":"$:/core","text":"{}"},{"title":"Hello","text":"World","tags":"[[Two Words]]"}]</script>"#;
+        assert_eq!(import_html(html, &dir).unwrap(), 1);
+        assert!(is_sqlite_wiki(&dir));
+        assert!(import_html(html, &dir).is_err());
+        let wiki = SqliteWiki::open(&dir).unwrap();
+        assert_eq!(wiki.get("Hello").unwrap().unwrap()["tags"], "[[Two Words]]");
+        assert!(wiki.get("$:/core").unwrap().is_none());
+        drop(wiki);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
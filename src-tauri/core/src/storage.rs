@@ -0,0 +1,366 @@
+//! Configuration files in a TiddlyDesktop data directory
+//!
+//! `DataStore` reads and writes the JSON files the app keeps in its data
+//! directory:
+//! - Wiki list (`recent_wikis.json`)
+//! - Per-wiki configurations (`wiki_configs.json`)
+//! - App settings (`app_settings.json`)
+//! - Share templates (`share_templates.json`)
+//!
+//! Writes are atomic and keep a `.bak` copy of the previous file, which is used
+//! when the file turns out to be empty or corrupt. In portable mode, wiki paths
+//! on the same drive as the portable base are stored relative to it.
+
+use std::path::{Path, PathBuf};
+
+use crate::types::{AppSettings, ShareTemplatesConfig, WikiConfigs, WikiEntry};
+use crate::utils;
+
+const RECENT_FILES: &str = "recent_wikis.json";
+const WIKI_CONFIGS: &str = "wiki_configs.json";
+const APP_SETTINGS: &str = "app_settings.json";
+const SHARE_TEMPLATES: &str = "share_templates.json";
+
+/// Atomic write with backup: keeps a .bak copy of the previous file, writes to
+/// a .tmp file first, then renames over the target. Prevents data loss if the
+/// process is killed mid-write (std::fs::write truncates first → empty file).
+pub fn atomic_write_with_backup(path: &Path, content: &str) -> Result<(), String> {
+    let backup_path = path.with_extension("json.bak");
+    if path.exists() {
+        let _ = std::fs::copy(path, &backup_path);
+    }
+    let tmp_path = path.with_extension("json.tmp");
+    std::fs::write(&tmp_path, content)
+        .map_err(|e| format!("Failed to write temp file {}: {}", tmp_path.display(), e))?;
+    std::fs::rename(&tmp_path, path).map_err(|e| {
+        let _ = std::fs::remove_file(&tmp_path);
+        format!("Failed to rename {} -> {}: {}", tmp_path.display(), path.display(), e)
+    })
+}
+
+/// Load a JSON config from a .bak backup file. Returns default on failure.
+pub fn load_json_from_backup<T: serde::de::DeserializeOwned + Default>(backup_path: &Path) -> Result<T, String> {
+    if !backup_path.exists() {
+        eprintln!("[WikiStorage] No backup at {} — using defaults", backup_path.display());
+        return Ok(T::default());
+    }
+    match std::fs::read_to_string(backup_path) {
+        Ok(s) if s.trim().is_empty() => {
+            eprintln!("[WikiStorage] Backup is also empty — using defaults");
+            Ok(T::default())
+        }
+        Ok(s) => match serde_json::from_str(&s) {
+            Ok(c) => {
+                eprintln!("[WikiStorage] Recovered from backup at {}", backup_path.display());
+                Ok(c)
+            }
+            Err(e) => {
+                eprintln!("[WikiStorage] Backup also corrupt: {} — using defaults", e);
+                Ok(T::default())
+            }
+        },
+        Err(e) => {
+            eprintln!("[WikiStorage] Failed to read backup: {} — using defaults", e);
+            Ok(T::default())
+        }
+    }
+}
+
+/// Load a JSON file, falling back to its .bak copy if it is empty or unreadable.
+/// A missing file yields the default value.
+fn load_json_with_recovery<T: serde::de::DeserializeOwned + Default>(path: &Path) -> Result<T, String> {
+    if !path.exists() {
+        return Ok(T::default());
+    }
+    let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let backup_path = path.with_extension("json.bak");
+    match std::fs::read_to_string(path) {
+        Ok(content) if content.trim().is_empty() => {
+            eprintln!("[WikiStorage] WARNING: {} is empty — trying backup", name);
+            load_json_from_backup::<T>(&backup_path)
+        }
+        Ok(content) => match serde_json::from_str(&content) {
+            Ok(c) => Ok(c),
+            Err(e) => {
+                eprintln!("[WikiStorage] WARNING: Failed to parse {}: {} — trying backup", name, e);
+                load_json_from_backup::<T>(&backup_path)
+            }
+        },
+        Err(e) => {
+            eprintln!("[WikiStorage] WARNING: Failed to read {}: {} — trying backup", name, e);
+            load_json_from_backup::<T>(&backup_path)
+        }
+    }
+}
+
+/// Create the parent directory of a config file and write it atomically
+fn write_config(path: &Path, content: &str) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create config directory: {}", e))?;
+    }
+    atomic_write_with_backup(path, content)
+}
+
+/// Portable mode: the form a wiki path is stored in. Paths on the same drive as the
+/// executable are stored relative to it, so the list survives drive letter or mount
+/// point changes; anything else (other drives, non-path keys) is kept as-is.
+pub fn portable_stored_path(path: &str, base: &Path) -> Option<String> {
+    if !on_same_volume(Path::new(path), base) {
+        return None;
+    }
+    utils::to_portable_relative(path, base)
+}
+
+/// Whether two paths are on the same filesystem. Windows drive letters are compared
+/// by `to_portable_relative`; on Unix the device IDs decide (the path must exist).
+#[cfg(unix)]
+fn on_same_volume(path: &Path, base: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (std::fs::metadata(path), std::fs::metadata(base)) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev(),
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn on_same_volume(_path: &Path, _base: &Path) -> bool {
+    true
+}
+
+/// Rewrite the path fields of wiki entries (`f` returns None to keep a value)
+fn map_entry_paths(entries: &mut [WikiEntry], f: impl Fn(&str) -> Option<String>) {
+    for entry in entries.iter_mut() {
+        if let Some(p) = f(&entry.path) {
+            entry.path = p;
+        }
+        if let Some(p) = entry.backup_dir.as_deref().and_then(&f) {
+            entry.backup_dir = Some(p);
+        }
+    }
+}
+
+/// Rewrite the wiki path keys of all per-wiki config maps (`f` returns None to keep a key)
+fn map_wiki_config_keys(configs: &mut WikiConfigs, f: impl Fn(&str) -> Option<String>) {
+    fn remap<V>(map: &mut std::collections::HashMap<String, V>, f: &impl Fn(&str) -> Option<String>) {
+        *map = std::mem::take(map)
+            .into_iter()
+            .map(|(k, v)| (f(&k).unwrap_or(k), v))
+            .collect();
+    }
+    remap(&mut configs.external_attachments, &f);
+    remap(&mut configs.session_auth, &f);
+    remap(&mut configs.window_states, &f);
+    remap(&mut configs.accelerators, &f);
+    remap(&mut configs.folder_snapshots, &f);
+    remap(&mut configs.mcp_access, &f);
+}
+
+/// The configuration files of one data directory
+#[derive(Clone, Debug)]
+pub struct DataStore {
+    data_dir: PathBuf,
+    portable_base: Option<PathBuf>,
+}
+
+impl DataStore {
+    /// Config files in `data_dir`, with wiki paths stored as given
+    pub fn new(data_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            data_dir: data_dir.into(),
+            portable_base: None,
+        }
+    }
+
+    /// Portable mode: store wiki paths relative to `base` where possible
+    pub fn with_portable_base(mut self, base: Option<PathBuf>) -> Self {
+        self.portable_base = base;
+        self
+    }
+
+    pub fn data_dir(&self) -> &Path {
+        &self.data_dir
+    }
+
+    pub fn portable_base(&self) -> Option<&Path> {
+        self.portable_base.as_deref()
+    }
+
+    pub fn recent_files_path(&self) -> PathBuf {
+        self.data_dir.join(RECENT_FILES)
+    }
+
+    pub fn wiki_configs_path(&self) -> PathBuf {
+        self.data_dir.join(WIKI_CONFIGS)
+    }
+
+    pub fn app_settings_path(&self) -> PathBuf {
+        self.data_dir.join(APP_SETTINGS)
+    }
+
+    pub fn share_templates_path(&self) -> PathBuf {
+        self.data_dir.join(SHARE_TEMPLATES)
+    }
+
+    /// Load app settings (defaults if there are none yet)
+    pub fn load_app_settings(&self) -> Result<AppSettings, String> {
+        let path = self.app_settings_path();
+        if path.exists() {
+            let content = std::fs::read_to_string(&path)
+                .map_err(|e| format!("Failed to read app settings: {}", e))?;
+            serde_json::from_str(&content)
+                .map_err(|e| format!("Failed to parse app settings: {}", e))
+        } else {
+            Ok(AppSettings::default())
+        }
+    }
+
+    /// Save app settings (atomic write with backup)
+    pub fn save_app_settings(&self, settings: &AppSettings) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(settings)
+            .map_err(|e| format!("Failed to serialize app settings: {}", e))?;
+        write_config(&self.app_settings_path(), &content)
+            .map_err(|e| format!("Failed to write app settings: {}", e))
+    }
+
+    /// Load the share templates config (defaults if there is none yet)
+    pub fn load_share_templates(&self) -> Result<ShareTemplatesConfig, String> {
+        let path = self.share_templates_path();
+        if !path.exists() {
+            return Ok(ShareTemplatesConfig::default());
+        }
+        let content = std::fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read share templates: {}", e))?;
+        if content.trim().is_empty() {
+            return Ok(ShareTemplatesConfig::default());
+        }
+        serde_json::from_str(&content)
+            .map_err(|e| format!("Failed to parse share templates: {}", e))
+    }
+
+    /// Save the share templates config (atomic write with backup)
+    pub fn save_share_templates(&self, config: &ShareTemplatesConfig) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(config)
+            .map_err(|e| format!("Failed to serialize share templates: {}", e))?;
+        write_config(&self.share_templates_path(), &content)
+            .map_err(|e| format!("Failed to write share templates: {}", e))
+    }
+
+    /// Load all per-wiki configs, keyed by absolute wiki path
+    pub fn load_wiki_configs(&self) -> Result<WikiConfigs, String> {
+        let mut configs = self.load_wiki_configs_raw()?;
+        if let Some(base) = &self.portable_base {
+            map_wiki_config_keys(&mut configs, |key| utils::from_portable_relative(key, base));
+        }
+        Ok(configs)
+    }
+
+    /// Load wiki configs exactly as stored (portable-relative keys unresolved)
+    pub fn load_wiki_configs_raw(&self) -> Result<WikiConfigs, String> {
+        load_json_with_recovery(&self.wiki_configs_path())
+    }
+
+    /// Save all per-wiki configs (atomic write with backup)
+    pub fn save_wiki_configs(&self, configs: &WikiConfigs) -> Result<(), String> {
+        let content = match &self.portable_base {
+            Some(base) => {
+                let mut stored = configs.clone();
+                map_wiki_config_keys(&mut stored, |key| portable_stored_path(key, base));
+                serde_json::to_string_pretty(&stored)
+            }
+            None => serde_json::to_string_pretty(configs),
+        }
+        .map_err(|e| format!("Failed to serialize wiki configs: {}", e))?;
+        write_config(&self.wiki_configs_path(), &content)
+            .map_err(|e| format!("Failed to write wiki configs: {}", e))
+    }
+
+    /// Load the wiki list with absolute paths (with backup recovery on corruption)
+    pub fn load_recent_files(&self) -> Vec<WikiEntry> {
+        let mut entries = self.load_recent_files_raw();
+        if let Some(base) = &self.portable_base {
+            map_entry_paths(&mut entries, |p| utils::from_portable_relative(p, base));
+        }
+        entries
+    }
+
+    /// Load the wiki list exactly as stored (portable-relative paths unresolved)
+    pub fn load_recent_files_raw(&self) -> Vec<WikiEntry> {
+        load_json_with_recovery(&self.recent_files_path()).unwrap_or_default()
+    }
+
+    /// Save the wiki list (atomic write with backup)
+    pub fn save_recent_files(&self, entries: &[WikiEntry]) -> Result<(), String> {
+        let json = match &self.portable_base {
+            Some(base) => {
+                let mut stored = entries.to_vec();
+                map_entry_paths(&mut stored, |p| portable_stored_path(p, base));
+                serde_json::to_string_pretty(&stored)
+            }
+            None => serde_json::to_string_pretty(entries),
+        }
+        .map_err(|e| e.to_string())?;
+        write_config(&self.recent_files_path(), &json)
+    }
+
+    /// Portable mode: rewrite absolute wiki paths stored by earlier versions into
+    /// portable-relative form. No-op outside portable mode.
+    pub fn migrate_portable_paths(&self) {
+        let Some(base) = &self.portable_base else {
+            return;
+        };
+
+        let raw_entries = self.load_recent_files_raw();
+        let needs_migration = raw_entries.iter().any(|e| {
+            portable_stored_path(&e.path, base).is_some()
+                || e.backup_dir.as_deref().and_then(|d| portable_stored_path(d, base)).is_some()
+        });
+        if needs_migration {
+            // Saving rewrites the paths in relative form
+            match self.save_recent_files(&self.load_recent_files()) {
+                Ok(()) => eprintln!("[WikiStorage] Migrated wiki list to portable-relative paths"),
+                Err(e) => eprintln!("[WikiStorage] Failed to migrate wiki list to relative paths: {}", e),
+            }
+        }
+
+        if let Ok(raw_configs) = self.load_wiki_configs_raw() {
+            let mut keys = raw_configs.external_attachments.keys()
+                .chain(raw_configs.session_auth.keys())
+                .chain(raw_configs.window_states.keys())
+                .chain(raw_configs.accelerators.keys())
+                .chain(raw_configs.folder_snapshots.keys());
+            if keys.any(|k| portable_stored_path(k, base).is_some()) {
+                match self.load_wiki_configs().and_then(|c| self.save_wiki_configs(&c)) {
+                    Ok(()) => eprintln!("[WikiStorage] Migrated wiki configs to portable-relative paths"),
+                    Err(e) => eprintln!("[WikiStorage] Failed to migrate wiki configs to relative paths: {}", e),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recovers_from_backup_when_file_is_empty() {
+        let dir = std::env::temp_dir().join(format!("td-core-storage-{}", std::process::id()));
+        let store = DataStore::new(&dir);
+        let settings = AppSettings {
+            language: Some("de-DE".to_string()),
+            ..Default::default()
+        };
+        store.save_app_settings(&settings).unwrap();
+
+        let entry: WikiEntry = serde_json::from_str(r#"{"path":"/w.html","filename":"w.html"}"#).unwrap();
+        store.save_recent_files(std::slice::from_ref(&entry)).unwrap();
+        store.save_recent_files(&[entry]).unwrap();
+        std::fs::write(store.recent_files_path(), "").unwrap();
+        let loaded = store.load_recent_files();
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded[0].path, "/w.html");
+        assert_eq!(store.load_app_settings().unwrap().language.as_deref(), Some("de-DE"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
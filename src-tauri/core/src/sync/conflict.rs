@@ -61,7 +61,7 @@ pub struct Tombstone {
 pub struct WikiSyncState {
     /// Vector clocks for each tiddler
     pub tiddler_clocks: HashMap<String, VectorClock>,
-    /// Deletion tombstones (pruned after 30 days)
+    /// Deletion tombstones (pruned after the retention window, 30 days by default)
     pub tombstones: Vec<Tombstone>,
 }
 
@@ -151,7 +151,7 @@ impl ConflictManager {
         let clock = state
             .tiddler_clocks
             .entry(title.to_string())
-            .or_insert_with(VectorClock::new);
+            .or_default();
         clock.increment(&self.device_id);
 
         let result = clock.clone();
@@ -167,7 +167,7 @@ impl ConflictManager {
         let clock = state
             .tiddler_clocks
             .entry(title.to_string())
-            .or_insert_with(VectorClock::new);
+            .or_default();
         clock.increment(&self.device_id);
         let result = clock.clone();
 
@@ -227,7 +227,7 @@ impl ConflictManager {
         let clock = state
             .tiddler_clocks
             .entry(title.to_string())
-            .or_insert_with(VectorClock::new);
+            .or_default();
         clock.merge(remote_clock);
 
         self.save_state_async(wiki_id, state);
@@ -246,7 +246,7 @@ impl ConflictManager {
         let clock = state
             .tiddler_clocks
             .entry(title.to_string())
-            .or_insert_with(VectorClock::new);
+            .or_default();
         clock.merge(remote_clock);
 
         // Add tombstone
@@ -318,18 +318,18 @@ impl ConflictManager {
         }
     }
 
-    /// Prune tombstones older than 30 days
-    pub fn prune_tombstones(&self) {
+    /// Prune tombstones older than `max_age_days`
+    pub fn prune_tombstones(&self, max_age_days: u32) {
         let now = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap_or_default()
             .as_secs();
-        let thirty_days = 30 * 24 * 60 * 60;
+        let max_age = max_age_days as u64 * 24 * 60 * 60;
 
         let mut states = self.states.lock().unwrap();
         for (wiki_id, state) in states.iter_mut() {
             let before = state.tombstones.len();
-            state.tombstones.retain(|t| now - t.deleted_at < thirty_days);
+            state.tombstones.retain(|t| now.saturating_sub(t.deleted_at) < max_age);
             let pruned = before - state.tombstones.len();
             if pruned > 0 {
                 eprintln!(
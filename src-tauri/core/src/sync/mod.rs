@@ -0,0 +1,11 @@
+//! Sync building blocks shared by LAN sync and relay sync
+//!
+//! - `protocol`: message types, room hashes, session encryption
+//! - `conflict`: vector clock-based conflict detection and per-wiki sync state
+//! - `wiki_info`: tiddlywiki.info merging and plugin availability for folder wikis
+//!
+//! Transport (discovery, WebSocket servers, relay connections) stays in the app.
+
+pub mod conflict;
+pub mod protocol;
+pub mod wiki_info;
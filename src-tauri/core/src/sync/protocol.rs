@@ -9,6 +9,7 @@ use chacha20poly1305::{
     ChaCha20Poly1305, Nonce,
 };
 use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
 use serde::{Deserialize, Serialize};
 use sha2::Sha256;
 
@@ -72,11 +73,25 @@ pub fn select_shared_room(our_rooms: &[String], peer_rooms: &[String]) -> Option
     shared.first().map(|s| (*s).clone())
 }
 
+/// Hash a room code for use in discovery beacons.
+/// Uses HMAC-SHA256 with a fixed label, truncated to first 8 bytes (16 hex chars).
+/// This prevents passive LAN observers from learning room codes while still
+/// allowing peers to match rooms by hashing their own codes and comparing.
+pub fn hash_room_code(room_code: &str) -> String {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(b"tiddlydesktop-discovery-room-hash")
+        .expect("HMAC can take key of any size");
+    mac.update(room_code.as_bytes());
+    let result = mac.finalize().into_bytes();
+    result[..8]
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
 /// Find a shared room by comparing our room code hashes against a peer's hashes.
 /// Hashes each of our room codes and checks if any match the peer's advertised hashes.
 /// Returns the first alphabetically-sorted matching room code (ours, not the hash).
 pub fn select_shared_room_by_hash(our_rooms: &[String], peer_room_hashes: &[String]) -> Option<String> {
-    use crate::lan_sync::discovery::hash_room_code;
     let mut shared: Vec<&String> = our_rooms.iter()
         .filter(|r| {
             let our_hash = hash_room_code(r);
@@ -90,7 +105,6 @@ pub fn select_shared_room_by_hash(our_rooms: &[String], peer_room_hashes: &[Stri
 /// Find ALL shared rooms by comparing our room code hashes against a peer's hashes.
 /// Same as `select_shared_room_by_hash` but returns all matches, not just the first.
 pub fn select_all_shared_rooms_by_hash(our_rooms: &[String], peer_room_hashes: &[String]) -> Vec<String> {
-    use crate::lan_sync::discovery::hash_room_code;
     let mut shared: Vec<String> = our_rooms.iter()
         .filter(|r| {
             let our_hash = hash_room_code(r);
@@ -226,6 +240,19 @@ pub enum SyncMessage {
     RequestFingerprints {
         wiki_id: String,
     },
+    /// Request a peer's fingerprints of a wiki for a sync preview (dry run).
+    /// Unlike RequestFingerprints nothing is synced: the peer only answers
+    /// with PreviewFingerprints.
+    RequestPreviewFingerprints {
+        wiki_id: String,
+    },
+    /// Answer to RequestPreviewFingerprints. `available` is false when the
+    /// peer doesn't share this wiki with us.
+    PreviewFingerprints {
+        wiki_id: String,
+        fingerprints: Vec<TiddlerFingerprint>,
+        available: bool,
+    },
     /// tiddlywiki.info content broadcast (folder wikis only).
     /// Sent on wiki open and peer connect for folder wikis.
     WikiInfoChanged {
@@ -316,6 +343,13 @@ pub enum SyncMessage {
         user_name: String,
     },
 
+    /// Text sent to this device's shared clipboard (only accepted when the
+    /// receiving device has the shared clipboard enabled)
+    ClipboardShare {
+        device_name: String,
+        text: String,
+    },
+
     /// Announce leaving a room — sent before disconnecting so peers
     /// can update their UI immediately (instead of waiting for ping timeout).
     RoomLeave {
@@ -328,6 +362,46 @@ pub enum SyncMessage {
 }
 
 impl SyncMessage {
+    /// The wiki a message belongs to (None for device-level messages)
+    pub fn wiki_id(&self) -> Option<&str> {
+        match self {
+            SyncMessage::TiddlerChanged { wiki_id, .. }
+            | SyncMessage::TiddlerDeleted { wiki_id, .. }
+            | SyncMessage::RequestFullSync { wiki_id, .. }
+            | SyncMessage::FullSyncBatch { wiki_id, .. }
+            | SyncMessage::AttachmentChanged { wiki_id, .. }
+            | SyncMessage::AttachmentChunk { wiki_id, .. }
+            | SyncMessage::AttachmentDeleted { wiki_id, .. }
+            | SyncMessage::ConflictNotification { wiki_id, .. }
+            | SyncMessage::RequestWikiFile { wiki_id, .. }
+            | SyncMessage::WikiFileChunk { wiki_id, .. }
+            | SyncMessage::WikiFileComplete { wiki_id, .. }
+            | SyncMessage::AttachmentManifest { wiki_id, .. }
+            | SyncMessage::RequestAttachments { wiki_id, .. }
+            | SyncMessage::TiddlerFingerprints { wiki_id, .. }
+            | SyncMessage::RequestFingerprints { wiki_id }
+            | SyncMessage::RequestPreviewFingerprints { wiki_id }
+            | SyncMessage::PreviewFingerprints { wiki_id, .. }
+            | SyncMessage::WikiInfoChanged { wiki_id, .. }
+            | SyncMessage::WikiInfoRequest { wiki_id }
+            | SyncMessage::PluginManifest { wiki_id, .. }
+            | SyncMessage::RequestPluginFiles { wiki_id, .. }
+            | SyncMessage::PluginFileChunk { wiki_id, .. }
+            | SyncMessage::PluginFilesComplete { wiki_id, .. }
+            | SyncMessage::EditingStarted { wiki_id, .. }
+            | SyncMessage::EditingStopped { wiki_id, .. }
+            | SyncMessage::CollabUpdate { wiki_id, .. }
+            | SyncMessage::CollabAwareness { wiki_id, .. }
+            | SyncMessage::PeerSaved { wiki_id, .. } => Some(wiki_id),
+            SyncMessage::WikiManifest { .. }
+            | SyncMessage::UserNameAnnounce { .. }
+            | SyncMessage::ClipboardShare { .. }
+            | SyncMessage::RoomLeave { .. }
+            | SyncMessage::Ping
+            | SyncMessage::Pong => None,
+        }
+    }
+
     /// Returns true for bulk-data messages (attachments, wiki file transfers)
     /// that should use the low-priority channel so they don't block tiddler sync.
     pub fn is_bulk_data(&self) -> bool {
@@ -563,8 +637,7 @@ const SPAKE2_CLIENT_CONFIRM_LABEL: &[u8] = b"tiddlydesktop-spake2-client-confirm
 /// Compute a key confirmation tag: HMAC-SHA256(shared_secret, label)[..16] as hex.
 /// Different labels for server vs client ensure both sides prove knowledge independently.
 pub fn spake2_key_confirm(shared_secret: &[u8], label: &[u8]) -> String {
-    use hmac::Mac;
-    type HmacSha256 = hmac::Hmac<Sha256>;
+    type HmacSha256 = Hmac<Sha256>;
     let mut mac = <HmacSha256 as Mac>::new_from_slice(shared_secret)
         .expect("HMAC can take key of any size");
     Mac::update(&mut mac, label);
@@ -0,0 +1,224 @@
+//! Text diff engine
+//!
+//! Computes structured diffs on the Rust side so wikis don't need to ship a JS
+//! diff library. Used by:
+//! - The app's `diff_texts` command (sync conflict UI, backup comparison in wikis)
+//! - Tiddler history (`backup`)
+//! - Merging sync tool conflict copies
+
+use std::time::Duration;
+
+use similar::{Algorithm, ChangeTag, DiffOp, TextDiff};
+
+/// Upper bound for a single diff computation; similar falls back to a coarser
+/// (but still correct) diff once the deadline is reached.
+const DIFF_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Options for a diff computation
+#[derive(Clone, Debug, Default, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffOptions {
+    /// "lines" (default), "words" or "chars"
+    #[serde(default)]
+    pub granularity: Option<String>,
+    /// Unchanged lines/tokens of context around each hunk (default 3)
+    #[serde(default)]
+    pub context: Option<usize>,
+    /// "myers" (default) or "patience"
+    #[serde(default)]
+    pub algorithm: Option<String>,
+}
+
+/// A highlighted segment within a changed line (inline word-level emphasis)
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct DiffSegment {
+    pub emphasized: bool,
+    pub text: String,
+}
+
+/// One line (or token, for word/char granularity) of a hunk
+#[derive(Clone, Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffLine {
+    /// "equal", "insert" or "delete"
+    pub op: &'static str,
+    /// 0-based index in the old text (None for inserts)
+    pub old_index: Option<usize>,
+    /// 0-based index in the new text (None for deletes)
+    pub new_index: Option<usize>,
+    pub text: String,
+    /// Inline segments for changed lines (line granularity only)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub segments: Vec<DiffSegment>,
+}
+
+/// A contiguous group of changes with surrounding context
+#[derive(Clone, Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffHunk {
+    pub old_start: usize,
+    pub old_len: usize,
+    pub new_start: usize,
+    pub new_len: usize,
+    pub lines: Vec<DiffLine>,
+}
+
+/// Result of a diff computation
+#[derive(Clone, Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TextDiffResult {
+    pub hunks: Vec<DiffHunk>,
+    pub insertions: usize,
+    pub deletions: usize,
+    /// Similarity ratio between 0.0 and 1.0
+    pub ratio: f32,
+    /// True if both texts are identical
+    pub identical: bool,
+}
+
+fn tag_name(tag: ChangeTag) -> &'static str {
+    match tag {
+        ChangeTag::Equal => "equal",
+        ChangeTag::Insert => "insert",
+        ChangeTag::Delete => "delete",
+    }
+}
+
+fn hunk_bounds(group: &[DiffOp]) -> (usize, usize, usize, usize) {
+    let first = &group[0];
+    let last = &group[group.len() - 1];
+    let old_start = first.old_range().start;
+    let new_start = first.new_range().start;
+    (
+        old_start,
+        last.old_range().end - old_start,
+        new_start,
+        last.new_range().end - new_start,
+    )
+}
+
+/// Compute a structured diff between two texts
+pub fn compute_diff(old: &str, new: &str, options: &DiffOptions) -> TextDiffResult {
+    let algorithm = match options.algorithm.as_deref() {
+        Some("patience") => Algorithm::Patience,
+        _ => Algorithm::Myers,
+    };
+    let context = options.context.unwrap_or(3);
+    let granularity = options.granularity.as_deref().unwrap_or("lines");
+
+    let mut config = TextDiff::configure();
+    config.algorithm(algorithm).timeout(DIFF_TIMEOUT);
+    let diff = match granularity {
+        "words" => config.diff_words(old, new),
+        "chars" => config.diff_chars(old, new),
+        _ => config.diff_lines(old, new),
+    };
+    let inline = !matches!(granularity, "words" | "chars");
+
+    let mut insertions = 0;
+    let mut deletions = 0;
+    let mut hunks = Vec::new();
+
+    for group in diff.grouped_ops(context) {
+        if group.is_empty() {
+            continue;
+        }
+        let (old_start, old_len, new_start, new_len) = hunk_bounds(&group);
+        let mut lines = Vec::new();
+
+        for op in &group {
+            if inline {
+                for change in diff.iter_inline_changes(op) {
+                    let segments = if change.tag() == ChangeTag::Equal {
+                        Vec::new()
+                    } else {
+                        change
+                            .iter_strings_lossy()
+                            .map(|(emphasized, text)| DiffSegment { emphasized, text: text.into_owned() })
+                            .collect()
+                    };
+                    let text: String = change.iter_strings_lossy().map(|(_, t)| t.into_owned()).collect();
+                    match change.tag() {
+                        ChangeTag::Insert => insertions += 1,
+                        ChangeTag::Delete => deletions += 1,
+                        ChangeTag::Equal => {}
+                    }
+                    lines.push(DiffLine {
+                        op: tag_name(change.tag()),
+                        old_index: change.old_index(),
+                        new_index: change.new_index(),
+                        text,
+                        segments,
+                    });
+                }
+            } else {
+                for change in diff.iter_changes(op) {
+                    match change.tag() {
+                        ChangeTag::Insert => insertions += 1,
+                        ChangeTag::Delete => deletions += 1,
+                        ChangeTag::Equal => {}
+                    }
+                    lines.push(DiffLine {
+                        op: tag_name(change.tag()),
+                        old_index: change.old_index(),
+                        new_index: change.new_index(),
+                        text: change.to_string_lossy().into_owned(),
+                        segments: Vec::new(),
+                    });
+                }
+            }
+        }
+
+        hunks.push(DiffHunk { old_start, old_len, new_start, new_len, lines });
+    }
+
+    TextDiffResult {
+        identical: hunks.is_empty(),
+        hunks,
+        insertions,
+        deletions,
+        ratio: diff.ratio(),
+    }
+}
+
+/// Line diff with default options (used for tiddler history)
+pub fn diff_lines(old: &str, new: &str) -> Vec<DiffHunk> {
+    compute_diff(old, new, &DiffOptions::default()).hunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identical_texts_have_no_hunks() {
+        let result = compute_diff("a\nb\nc\n", "a\nb\nc\n", &DiffOptions::default());
+        assert!(result.identical);
+        assert!(result.hunks.is_empty());
+        assert_eq!(result.insertions, 0);
+        assert_eq!(result.deletions, 0);
+    }
+
+    #[test]
+    fn test_line_change_produces_single_hunk() {
+        let old = "one\ntwo\nthree\nfour\n";
+        let new = "one\n2\nthree\nfour\n";
+        let result = compute_diff(old, new, &DiffOptions::default());
+        assert_eq!(result.hunks.len(), 1);
+        assert_eq!(result.insertions, 1);
+        assert_eq!(result.deletions, 1);
+        let hunk = &result.hunks[0];
+        assert_eq!(hunk.old_start, 0);
+        assert!(hunk.lines.iter().any(|l| l.op == "delete" && l.text == "two\n"));
+        assert!(hunk.lines.iter().any(|l| l.op == "insert" && l.text == "2\n"));
+    }
+
+    #[test]
+    fn test_word_granularity() {
+        let options = DiffOptions { granularity: Some("words".to_string()), ..Default::default() };
+        let result = compute_diff("the quick fox", "the slow fox", &options);
+        assert_eq!(result.deletions, 1);
+        assert_eq!(result.insertions, 1);
+        assert!(result.hunks[0].lines.iter().all(|l| l.segments.is_empty()));
+    }
+}
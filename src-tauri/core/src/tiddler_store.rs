@@ -0,0 +1,454 @@
+//! Streaming reader for the tiddler stores of single-file wikis
+//!
+//! Reads tiddlers one at a time from the JSON stores (TiddlyWiki 5.2+) and the
+//! legacy `storeArea` div store, so big wikis can be scanned without holding the
+//! whole file, or all of its tiddlers, in memory. Memory use is bounded by the
+//! largest single tiddler.
+//!
+//! ```no_run
+//! use tiddlydesktop_core::tiddler_store;
+//!
+//! # fn example() -> Result<(), String> {
+//! let path = std::path::Path::new("/home/me/wiki.html");
+//! for tiddler in tiddler_store::open(path)? {
+//!     let tiddler = tiddler?;
+//!     println!("{}", tiddler["title"]);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use crate::utils;
+
+const JSON_STORE_START: &[u8] = br#"<script class="tiddlywiki-tiddler-store" type="application/json">"#;
+const DIV_STORE_START: &[u8] = br#"<div id="storeArea""#;
+
+/// How much is read from the file at a time
+const CHUNK_SIZE: usize = 64 * 1024;
+
+enum State {
+    /// Between stores
+    Outside,
+    /// Inside a `<script class="tiddlywiki-tiddler-store">` JSON array
+    JsonStore,
+    /// Inside `<div id="storeArea">`
+    DivStore,
+    Done,
+}
+
+/// Iterator over the tiddlers of a single-file wiki, in file order (later
+/// tiddlers override earlier ones with the same title, like in TiddlyWiki)
+pub struct StoreReader<R> {
+    reader: R,
+    buf: Vec<u8>,
+    pos: usize,
+    eof: bool,
+    state: State,
+}
+
+/// Stream the tiddlers of the wiki file at `path`
+pub fn open(path: &Path) -> Result<StoreReader<File>, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    Ok(StoreReader::new(file))
+}
+
+impl<R: Read> StoreReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self { reader, buf: Vec::new(), pos: 0, eof: false, state: State::Outside }
+    }
+
+    fn fail(&mut self, error: String) -> String {
+        self.state = State::Done;
+        error
+    }
+
+    /// Buffer at least `n` unconsumed bytes if the input has that many.
+    /// Returns whether it had.
+    fn fill(&mut self, n: usize) -> Result<bool, String> {
+        while self.buf.len() - self.pos < n && !self.eof {
+            // Drop consumed bytes before growing the buffer
+            if self.pos >= CHUNK_SIZE {
+                self.buf.drain(..self.pos);
+                self.pos = 0;
+            }
+            let old_len = self.buf.len();
+            self.buf.resize(old_len + CHUNK_SIZE, 0);
+            let read = loop {
+                match self.reader.read(&mut self.buf[old_len..]) {
+                    Ok(read) => break read,
+                    Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                    Err(e) => {
+                        self.buf.truncate(old_len);
+                        return Err(self.fail(format!("Failed to read wiki: {}", e)));
+                    }
+                }
+            };
+            self.buf.truncate(old_len + read);
+            if read == 0 {
+                self.eof = true;
+            }
+        }
+        Ok(self.buf.len() - self.pos >= n)
+    }
+
+    fn available(&self) -> &[u8] {
+        &self.buf[self.pos..]
+    }
+
+    /// Consume input up to and including the first of `needles` (which all
+    /// start with the same byte). Returns which one was found.
+    fn skip_past(&mut self, needles: &[&[u8]]) -> Result<Option<usize>, String> {
+        let longest = needles.iter().map(|n| n.len()).max().unwrap_or(0);
+        let lead = needles[0][0];
+        loop {
+            self.fill(longest)?;
+            let available = self.available();
+            if available.is_empty() {
+                return Ok(None);
+            }
+            let Some(i) = available.iter().position(|&b| b == lead) else {
+                self.pos = self.buf.len();
+                continue;
+            };
+            let rest = &available[i..];
+            if rest.len() < longest && !self.eof {
+                self.pos += i;
+                continue;
+            }
+            if let Some(found) = needles.iter().position(|n| rest.starts_with(n)) {
+                self.pos += i + needles[found].len();
+                return Ok(Some(found));
+            }
+            self.pos += i + 1;
+        }
+    }
+
+    /// Consume and return the input before `needle`, and the needle itself
+    fn read_until(&mut self, needle: &[u8]) -> Result<Vec<u8>, String> {
+        let mut out = Vec::new();
+        loop {
+            if !self.fill(needle.len())? {
+                return Err(self.fail("Unexpected end of wiki file".to_string()));
+            }
+            let available = self.available();
+            match available.windows(needle.len()).position(|w| w == needle) {
+                Some(i) => {
+                    out.extend_from_slice(&available[..i]);
+                    self.pos += i + needle.len();
+                    return Ok(out);
+                }
+                None => {
+                    // Keep a possible partial match at the end
+                    let take = available.len() + 1 - needle.len();
+                    out.extend_from_slice(&available[..take]);
+                    self.pos += take;
+                }
+            }
+        }
+    }
+
+    fn skip_whitespace_and_commas(&mut self) -> Result<(), String> {
+        loop {
+            if !self.fill(1)? {
+                return Ok(());
+            }
+            let available = self.available();
+            let skip = available.iter().take_while(|b| b.is_ascii_whitespace() || **b == b',').count();
+            let done = skip < available.len();
+            self.pos += skip;
+            if done {
+                return Ok(());
+            }
+        }
+    }
+
+    /// The bytes of the JSON object starting at the current position
+    fn read_json_object(&mut self) -> Result<Vec<u8>, String> {
+        let mut out = Vec::new();
+        let (mut depth, mut in_string) = (0usize, false);
+        loop {
+            if !self.fill(2)? && self.available().is_empty() {
+                return Err(self.fail("Unterminated tiddler in tiddler store".to_string()));
+            }
+            let available = self.available();
+            let mut i = 0;
+            let mut end = None;
+            while i < available.len() {
+                if in_string {
+                    // Jump to the next quote or escape
+                    match memchr::memchr2(b'"', b'\\', &available[i..]) {
+                        Some(j) if available[i + j] == b'"' => {
+                            in_string = false;
+                            i += j + 1;
+                        }
+                        // An escape needs the escaped byte in the buffer too
+                        Some(j) if i + j + 1 < available.len() => i += j + 2,
+                        Some(j) => {
+                            i += j;
+                            break;
+                        }
+                        None => i = available.len(),
+                    }
+                    continue;
+                }
+                match memchr::memchr3(b'"', b'{', b'}', &available[i..]) {
+                    Some(j) => {
+                        i += j + 1;
+                        match available[i - 1] {
+                            b'"' => in_string = true,
+                            b'{' => depth += 1,
+                            _ => {
+                                depth -= 1;
+                                if depth == 0 {
+                                    end = Some(i);
+                                    break;
+                                }
+                            }
+                        }
+                    }
+                    None => i = available.len(),
+                }
+            }
+            let take = end.unwrap_or(i);
+            out.extend_from_slice(&available[..take]);
+            self.pos += take;
+            if end.is_some() {
+                return Ok(out);
+            }
+            if take == 0 && self.eof {
+                return Err(self.fail("Unterminated tiddler in tiddler store".to_string()));
+            }
+        }
+    }
+
+    /// The next tiddler in a JSON store, or `None` at the end of the store.
+    /// Tiddlers whose JSON doesn't contain `needle` are skipped unparsed.
+    fn next_json_tiddler(&mut self, needle: Option<&memchr::memmem::Finder>) -> Result<Option<serde_json::Value>, String> {
+        loop {
+            self.skip_whitespace_and_commas()?;
+            match self.available().first() {
+                Some(b'[') => self.pos += 1,
+                Some(b'{') => {
+                    let bytes = self.read_json_object()?;
+                    if needle.is_some_and(|n| n.find(&bytes).is_none()) {
+                        continue;
+                    }
+                    return serde_json::from_slice(&bytes)
+                        .map(Some)
+                        .map_err(|e| format!("Skipping unreadable tiddler: {}", e));
+                }
+                // `]` ends the store; anything else means it's broken
+                _ => {
+                    self.state = State::Outside;
+                    return Ok(None);
+                }
+            }
+        }
+    }
+
+    fn next_div_tiddler(&mut self) -> Result<Option<serde_json::Value>, String> {
+        loop {
+            self.skip_whitespace_and_commas()?;
+            self.fill(6)?;
+            let available = self.available();
+            if available.starts_with(b"</div") || available.is_empty() {
+                self.state = State::Outside;
+                return Ok(None);
+            }
+            if !available.starts_with(b"<div") {
+                self.pos += 1;
+                continue;
+            }
+            self.pos += b"<div".len();
+            let attributes = String::from_utf8_lossy(&self.read_until(b">")?).into_owned();
+            let body = String::from_utf8_lossy(&self.read_until(b"</div>")?).into_owned();
+            return Ok(Some(parse_div_tiddler(&attributes, &body)));
+        }
+    }
+}
+
+/// Fields from the attributes of a legacy `<div>` tiddler, and the text from
+/// its (optional) `<pre>` element
+fn parse_div_tiddler(attributes: &str, body: &str) -> serde_json::Value {
+    let mut fields = serde_json::Map::new();
+    let mut rest = attributes;
+    while let Some(eq) = rest.find("=\"") {
+        let name = rest[..eq].trim();
+        let after = &rest[eq + 2..];
+        let Some(end) = after.find('"') else { break };
+        if !name.is_empty() {
+            fields.insert(name.to_string(), utils::html_decode(&after[..end]).into());
+        }
+        rest = &after[end + 1..];
+    }
+    let body = body.trim();
+    let text = body.strip_prefix("<pre>")
+        .and_then(|b| b.rfind("</pre>").map(|end| &b[..end]))
+        .unwrap_or(body);
+    if !text.is_empty() {
+        fields.insert("text".to_string(), utils::html_decode(text).into());
+    }
+    serde_json::Value::Object(fields)
+}
+
+impl<R: Read> StoreReader<R> {
+    /// The next tiddler whose JSON contains `needle` (tiddlers of the legacy
+    /// div store are always returned). Much faster than parsing every tiddler.
+    fn next_matching(&mut self, needle: Option<&memchr::memmem::Finder>) -> Option<Result<serde_json::Value, String>> {
+        loop {
+            let result = match self.state {
+                State::Done => return None,
+                State::Outside => match self.skip_past(&[JSON_STORE_START, DIV_STORE_START]) {
+                    Ok(Some(0)) => {
+                        self.state = State::JsonStore;
+                        continue;
+                    }
+                    Ok(Some(_)) => match self.read_until(b">") {
+                        Ok(_) => {
+                            self.state = State::DivStore;
+                            continue;
+                        }
+                        Err(e) => Err(e),
+                    },
+                    Ok(None) => {
+                        self.state = State::Done;
+                        return None;
+                    }
+                    Err(e) => Err(e),
+                },
+                State::JsonStore => self.next_json_tiddler(needle),
+                State::DivStore => self.next_div_tiddler(),
+            };
+            match result {
+                Ok(Some(tiddler)) => return Some(Ok(tiddler)),
+                Ok(None) => continue,
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+impl<R: Read> Iterator for StoreReader<R> {
+    type Item = Result<serde_json::Value, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_matching(None)
+    }
+}
+
+/// The tiddler `title` from the wiki file at `path`: the last tiddler with that
+/// title, or else the shadow tiddler from the last plugin containing it
+pub fn find_tiddler(path: &Path, title: &str) -> Result<Option<serde_json::Value>, String> {
+    // Titles are stored as-is unless they contain characters JSON or the
+    // store escape; only then does every tiddler need parsing
+    let plain = !title.chars().any(|c| matches!(c, '"' | '\\' | '<') || c.is_control());
+    let finder = memchr::memmem::Finder::new(title.as_bytes());
+    let needle = plain.then_some(&finder);
+
+    let mut reader = open(path)?;
+    let mut found = None;
+    let mut shadow = None;
+    while let Some(tiddler) = reader.next_matching(needle) {
+        let Ok(tiddler) = tiddler else { continue };
+        if tiddler.get("title").and_then(|t| t.as_str()) == Some(title) {
+            found = Some(tiddler);
+        } else if let Some(inner) = shadow_tiddler(&tiddler, title) {
+            shadow = Some(inner);
+        }
+    }
+    Ok(found.or(shadow))
+}
+
+/// The shadow tiddler `title` if `tiddler` is a plugin containing it
+pub fn shadow_tiddler(tiddler: &serde_json::Value, title: &str) -> Option<serde_json::Value> {
+    tiddler.get("plugin-type")?;
+    let text = tiddler.get("text")?.as_str()?;
+    // Cheap check before parsing the whole plugin
+    if !text.contains(title) {
+        return None;
+    }
+    let mut plugin: serde_json::Value = serde_json::from_str(text).ok()?;
+    plugin.get_mut("tiddlers")?.get_mut(title).map(serde_json::Value::take)
+}
+
+/// The text of the tiddler `title` from the wiki file at `path`
+pub fn tiddler_text(path: &Path, title: &str) -> Option<String> {
+    find_tiddler(path, title).ok()??
+        .get("text")
+        .and_then(|t| t.as_str())
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Hands out a few bytes per read, to cross buffer boundaries everywhere
+    struct Trickle<'a>(&'a [u8]);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let n = buf.len().min(3).min(self.0.len());
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Ok(n)
+        }
+    }
+
+    fn titles(html: &str) -> Vec<String> {
+        StoreReader::new(Trickle(html.as_bytes()))
+            .map(|t| t.unwrap()["title"].as_str().unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_reads_json_and_div_stores() {
+        let html = concat!(
+            r#"<html><head><script>var s = "<div";</script></head><body>"#,
+            r#"<script class="tiddlywiki-tiddler-store" type="application/json">[{"title":"A","text":"} { \"quoted\" \\"},"#,
+            "\n",
+            r#"{"title":"$:/plugins/x","plugin-type":"plugin","text":"{\"tiddlers\":{\"S\":{\"title\":\"S\",\"text\":\"shadow\"}}}"}]</script>"#,
+            r#"<script class="tiddlywiki-tiddler-store" type="application/json">[]</script>"#,
+            r#"<div id="storeArea" style="display:none;"><div title="B &amp; C" tags="[[a b]]">"#,
+            "\n<pre>x &lt;y&gt;</pre>\n</div>\n<div title=\"D\"></div></div>",
+            r#"<script>"<div id=\"storeArea\">"</script></body></html>"#,
+        );
+        assert_eq!(titles(html), ["A", "$:/plugins/x", "B & C", "D"]);
+
+        let tiddlers: Vec<serde_json::Value> = StoreReader::new(html.as_bytes()).map(Result::unwrap).collect();
+        assert_eq!(tiddlers[0]["text"], "} { \"quoted\" \\");
+        assert_eq!(tiddlers[2]["text"], "x <y>");
+        assert_eq!(tiddlers[2]["tags"], "[[a b]]");
+        assert_eq!(shadow_tiddler(&tiddlers[1], "S").unwrap()["text"], "shadow");
+        assert!(shadow_tiddler(&tiddlers[0], "S").is_none());
+    }
+
+    #[test]
+    fn test_finds_tiddlers_and_shadows() {
+        let path = std::env::temp_dir().join(format!("td-tiddler-store-{}.html", std::process::id()));
+        let html = concat!(
+            r#"<script class="tiddlywiki-tiddler-store" type="application/json">[{"title":"A","text":"old"},"#,
+            r#"{"title":"$:/plugins/x","plugin-type":"plugin","text":"{\"tiddlers\":{\"$:/S\":{\"title\":\"$:/S\",\"text\":\"shadow\"}}}"},"#,
+            r#"{"title":"A \"q\"","text":"quoted"},{"title":"A","text":"new"}]</script>"#,
+        );
+        std::fs::write(&path, html).unwrap();
+        assert_eq!(tiddler_text(&path, "A").as_deref(), Some("new"));
+        assert_eq!(tiddler_text(&path, "A \"q\"").as_deref(), Some("quoted"));
+        assert_eq!(tiddler_text(&path, "$:/S").as_deref(), Some("shadow"));
+        assert_eq!(tiddler_text(&path, "B"), None);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_skips_broken_tiddlers() {
+        let html = r#"<script class="tiddlywiki-tiddler-store" type="application/json">[{"title":"A"},{"title":1 2},{"title":"B"}]</script>"#;
+        let results: Vec<_> = StoreReader::new(html.as_bytes()).collect();
+        assert_eq!(results.len(), 3);
+        assert!(results[1].is_err());
+        assert_eq!(results[2].as_ref().unwrap()["title"], "B");
+    }
+}
@@ -6,7 +6,7 @@
 //! - Favicon extraction from various TiddlyWiki formats
 //! - Favicon extraction from wiki folders
 
-use std::path::PathBuf;
+use std::path::Path;
 use crate::utils;
 
 /// Extract a tiddler's text content from TiddlyWiki HTML
@@ -239,19 +239,31 @@ fn is_bundled_system_tiddler(title: &str) -> bool {
 /// processing order). This avoids re-serializing large plugin blobs and prevents
 /// escaping issues (e.g. </script> inside JSON breaking the enclosing <script> tag).
 pub fn build_merged_html(old_html: &str, bundled_html: &str) -> Result<String, String> {
-    let old_tiddlers = extract_all_tiddlers_from_html(old_html);
+    merge_into_bundled(extract_all_tiddlers_from_html(old_html), bundled_html)
+}
 
-    if old_tiddlers.is_empty() {
-        eprintln!("[TiddlyDesktop] Migration: no tiddlers found in old wiki, using bundled as-is");
-        return Ok(bundled_html.to_string());
+/// Like `build_merged_html`, but streams the old wiki from `old_path`, keeping
+/// only its user-data tiddlers in memory
+pub fn build_merged_html_from_file(old_path: &Path, bundled_html: &str) -> Result<String, String> {
+    let mut old_count = 0;
+    let mut user_tiddlers = Vec::new();
+    for tiddler in crate::tiddler_store::open(old_path)? {
+        let tiddler = match tiddler {
+            Ok(tiddler) => tiddler,
+            Err(e) => {
+                eprintln!("[TiddlyDesktop] Warning: {}", e);
+                continue;
+            }
+        };
+        old_count += 1;
+        if tiddler.get("title").and_then(|v| v.as_str()).is_some_and(|title| !is_bundled_system_tiddler(title)) {
+            user_tiddlers.push(tiddler);
+        }
     }
+    merge_user_tiddlers(old_count, user_tiddlers.iter().collect(), bundled_html)
+}
 
-    // Collect titles from bundled HTML (only need titles, not full parse)
-    let bundled_tiddlers = extract_all_tiddlers_from_html(bundled_html);
-    let bundled_titles: std::collections::HashSet<String> = bundled_tiddlers.iter()
-        .filter_map(|t| t.get("title").and_then(|v| v.as_str()).map(|s| s.to_string()))
-        .collect();
-
+fn merge_into_bundled(old_tiddlers: Vec<serde_json::Value>, bundled_html: &str) -> Result<String, String> {
     // Keep only user-data tiddlers from old HTML (skip system/plugin tiddlers
     // which are already up-to-date in the bundled HTML)
     let user_tiddlers: Vec<&serde_json::Value> = old_tiddlers.iter()
@@ -264,8 +276,17 @@ pub fn build_merged_html(old_html: &str, bundled_html: &str) -> Result<String, S
         })
         .collect();
 
-    eprintln!("[TiddlyDesktop] Migration: {} tiddlers in old wiki ({} user data, {} system/plugin skipped), {} in bundled",
-        old_tiddlers.len(), user_tiddlers.len(), old_tiddlers.len() - user_tiddlers.len(), bundled_titles.len());
+    merge_user_tiddlers(old_tiddlers.len(), user_tiddlers, bundled_html)
+}
+
+fn merge_user_tiddlers(old_count: usize, user_tiddlers: Vec<&serde_json::Value>, bundled_html: &str) -> Result<String, String> {
+    if old_count == 0 {
+        eprintln!("[TiddlyDesktop] Migration: no tiddlers found in old wiki, using bundled as-is");
+        return Ok(bundled_html.to_string());
+    }
+
+    eprintln!("[TiddlyDesktop] Migration: {} tiddlers in old wiki ({} user data, {} system/plugin skipped)",
+        old_count, user_tiddlers.len(), old_count - user_tiddlers.len());
 
     if user_tiddlers.is_empty() {
         eprintln!("[TiddlyDesktop] Migration: no user data to preserve, using bundled as-is");
@@ -310,6 +331,20 @@ pub fn build_merged_html(old_html: &str, bundled_html: &str) -> Result<String, S
     Ok(result)
 }
 
+/// Check if content looks like base64 image data
+fn looks_like_base64_image(s: &str) -> bool {
+    if s.len() < 20 { return false; }
+    // PNG starts with iVBOR, GIF with R0lGO, JPEG with /9j/, ICO varies
+    // Valid base64 chars only
+    let first_chars: String = s.chars().take(20).collect();
+    first_chars.chars().all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '/' || c == '=')
+        && (s.starts_with("iVBOR")   // PNG
+            || s.starts_with("R0lGO") // GIF
+            || s.starts_with("/9j/")  // JPEG
+            || s.starts_with("AAAB")  // ICO often
+            || s.len() > 100)         // Or just long enough to be real data
+}
+
 /// Extract favicon from the $:/favicon.ico tiddler in TiddlyWiki HTML
 /// The tiddler contains base64-encoded image data with a type field
 pub fn extract_favicon_from_tiddler(html: &str) -> Option<String> {
@@ -403,20 +438,6 @@ pub fn extract_favicon_from_tiddler(html: &str) -> Option<String> {
         None
     }
 
-    // Helper to check if content looks like base64 image data
-    fn looks_like_base64_image(s: &str) -> bool {
-        if s.len() < 20 { return false; }
-        // PNG starts with iVBOR, GIF with R0lGO, JPEG with /9j/, ICO varies
-        // Valid base64 chars only
-        let first_chars: String = s.chars().take(20).collect();
-        first_chars.chars().all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '/' || c == '=')
-            && (s.starts_with("iVBOR")   // PNG
-                || s.starts_with("R0lGO") // GIF
-                || s.starts_with("/9j/")  // JPEG
-                || s.starts_with("AAAB")  // ICO often
-                || s.len() > 100)         // Or just long enough to be real data
-    }
-
     // Strategy 1: Look for tiddler in JSON array format (modern TiddlyWiki 5.2+)
     let title_patterns = [
         r#""title":"$:/favicon.ico""#,
@@ -616,6 +637,37 @@ pub fn extract_favicon_from_tiddler(html: &str) -> Option<String> {
 /// First tries the <link> tag in <head>, then falls back to $:/favicon.ico tiddler
 pub fn extract_favicon(content: &str) -> Option<String> {
     // First try: Look for favicon link with data URI in the head section
+    if let Some(favicon) = extract_favicon_link(content) {
+        return Some(favicon);
+    }
+
+    // Second try: Extract from $:/favicon.ico tiddler
+    extract_favicon_from_tiddler(content)
+}
+
+/// Like `extract_favicon`, but reads only the head and streams the tiddlers of
+/// the wiki file at `path` instead of loading all of it
+pub fn extract_favicon_from_file(path: &Path) -> Option<String> {
+    use std::io::Read;
+
+    let mut head = Vec::new();
+    std::fs::File::open(path).ok()?.take(500_000).read_to_end(&mut head).ok()?;
+    if let Some(favicon) = extract_favicon_link(&String::from_utf8_lossy(&head)) {
+        return Some(favicon);
+    }
+    drop(head);
+
+    let tiddler = crate::tiddler_store::find_tiddler(path, "$:/favicon.ico").ok()??;
+    let text: String = tiddler.get("text")?.as_str()?.chars().filter(|c| !c.is_whitespace()).collect();
+    if !looks_like_base64_image(&text) {
+        return None;
+    }
+    let mime_type = tiddler.get("type").and_then(|t| t.as_str()).unwrap_or("image/x-icon");
+    Some(format!("data:{};base64,{}", mime_type, text))
+}
+
+/// The data URI of a favicon `<link>` in the `<head>` of `content`
+fn extract_favicon_link(content: &str) -> Option<String> {
     let head_end = content.find("</head>")
         .or_else(|| content.find("</HEAD>"))
         .unwrap_or(content.len().min(500_000));
@@ -655,12 +707,11 @@ pub fn extract_favicon(content: &str) -> Option<String> {
         }
     }
 
-    // Second try: Extract from $:/favicon.ico tiddler
-    extract_favicon_from_tiddler(content)
+    None
 }
 
 /// Extract favicon from a wiki folder by reading the favicon file
-pub async fn extract_favicon_from_folder(wiki_path: &PathBuf) -> Option<String> {
+pub async fn extract_favicon_from_folder(wiki_path: &Path) -> Option<String> {
     use base64::{engine::general_purpose::STANDARD, Engine};
 
     let tiddlers_path = wiki_path.join("tiddlers");
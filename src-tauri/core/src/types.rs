@@ -0,0 +1,518 @@
+//! Core data types for TiddlyDesktop
+//!
+//! This module contains the fundamental data structures used throughout the application:
+//! - Wiki entry representation
+//! - Configuration structures for external attachments and authentication
+//! - Internal wiki configuration storage
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// A wiki entry in the recent files list
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WikiEntry {
+    pub path: String,
+    pub filename: String,
+    #[serde(default)]
+    pub display_path: Option<String>, // Human-readable path for UI (e.g., "Documents/MyWiki.html" on Android)
+    #[serde(default)]
+    pub favicon: Option<String>, // Data URI for favicon
+    #[serde(default)]
+    pub is_folder: bool, // true if this is a wiki folder
+    #[serde(default = "default_backups_enabled")]
+    pub backups_enabled: bool, // whether to create backups on save (single-file only)
+    #[serde(default)]
+    pub backup_dir: Option<String>, // custom backup directory (if None, uses .backups folder next to wiki)
+    #[serde(default)]
+    pub backup_count: Option<u32>, // max backups to keep (None = default 20, 0 = unlimited)
+    #[serde(default)]
+    pub group: Option<String>, // group name for organizing wikis (None = "Ungrouped")
+    #[serde(default)]
+    pub sync_enabled: bool, // whether LAN sync is enabled for this wiki
+    #[serde(default)]
+    pub sync_id: Option<String>, // unique sync ID (UUID) for matching across devices
+    #[serde(default)]
+    pub sync_peers: Vec<String>, // deprecated: kept for deserialization compat with old configs
+    #[serde(default)]
+    pub relay_room: Option<String>, // relay room code this wiki is assigned to (None = no relay sync)
+    #[serde(default)]
+    pub sync_mode: Option<String>, // sync direction: None/"bidirectional", "send-only", "receive-only"
+    #[serde(default)]
+    pub custom_icon: Option<String>, // PNG data URI shown instead of the favicon (picked image or rendered emoji)
+    #[serde(default)]
+    pub accent_color: Option<String>, // "#rrggbb" accent for the wiki in the landing page and tray
+    #[serde(default)]
+    pub emoji: Option<String>, // emoji the custom icon was rendered from
+    #[serde(default)]
+    pub scheduled_backup: Option<ScheduledBackupConfig>, // periodic backups independent of saves (single-file only)
+    #[serde(default)]
+    pub confirm_remote_deletions: bool, // hold deletions from peers until confirmed (LAN sync)
+    #[serde(default)]
+    pub sync_schedule: Option<SyncSchedule>, // None = changes sync in real time; else on an interval or manually
+    #[serde(default)]
+    pub hotkey: Option<String>, // global hotkey that opens or focuses the wiki (desktop), e.g. "CommandOrControl+Alt+1"
+    #[serde(default)]
+    pub sync_priority: Option<SyncPriority>, // filters of tiddlers synced right away / in batches (LAN sync, desktop)
+    #[serde(default)]
+    pub missing: bool, // the file was gone at the last scan of the watched folder it's in
+}
+
+fn default_backups_enabled() -> bool {
+    true
+}
+
+/// Configuration for external attachments per wiki
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ExternalAttachmentsConfig {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub use_absolute_for_descendents: bool,
+    #[serde(default)]
+    pub use_absolute_for_non_descendents: bool,
+    /// Convert imported HEIC/HEIF and WebP images to JPEG (PNG with transparency)
+    #[serde(default)]
+    pub convert_images: bool,
+    /// Downscale imported images wider or higher than this (pixels, 0 = keep size)
+    #[serde(default)]
+    pub max_image_dimension: u32,
+    /// Remove the GPS location from imported photos
+    #[serde(default)]
+    pub strip_image_location: bool,
+    /// Transcode imported videos to H.264/AAC MP4 with ffmpeg
+    #[serde(default)]
+    pub transcode_videos: bool,
+}
+
+impl Default for ExternalAttachmentsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true, // Enable by default
+            use_absolute_for_descendents: false,
+            use_absolute_for_non_descendents: false,
+            convert_images: false,
+            max_image_dimension: 0,
+            strip_image_location: false,
+            transcode_videos: false,
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// A single authentication URL entry
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AuthUrlEntry {
+    pub name: String,
+    pub url: String,
+}
+
+/// Configuration for session authentication URLs per wiki
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+pub struct SessionAuthConfig {
+    #[serde(default)]
+    pub auth_urls: Vec<AuthUrlEntry>,
+}
+
+/// Window state (size, position, monitor) for a wiki
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WindowState {
+    pub width: u32,
+    pub height: u32,
+    pub x: i32,
+    pub y: i32,
+    /// Monitor name (may not be unique if multiple identical monitors)
+    #[serde(default)]
+    pub monitor_name: Option<String>,
+    /// Monitor position for unique identification (top-left corner)
+    #[serde(default)]
+    pub monitor_x: i32,
+    #[serde(default)]
+    pub monitor_y: i32,
+    /// Whether the window was maximized
+    #[serde(default)]
+    pub maximized: bool,
+}
+
+impl Default for WindowState {
+    fn default() -> Self {
+        Self {
+            width: 1200,
+            height: 800,
+            x: 100,
+            y: 100,
+            monitor_name: None,
+            monitor_x: 0,
+            monitor_y: 0,
+            maximized: false,
+        }
+    }
+}
+
+/// Keyboard accelerators handled by the init script, keyed by action name.
+/// An action mapped to an empty list is disabled (the key passes through to the wiki).
+#[derive(Clone, Debug, Serialize, Deserialize, Default, PartialEq)]
+pub struct AcceleratorMap {
+    #[serde(default)]
+    pub bindings: HashMap<String, Vec<String>>,
+}
+
+impl AcceleratorMap {
+    /// Actions the init script knows how to handle
+    pub const ACTIONS: &'static [&'static str] = &[
+        "find",
+        "find-next",
+        "find-previous",
+        "find-close",
+        "zoom-reset",
+        "reopen-closed-wiki",
+        "share-selection",
+        "quick-switcher",
+    ];
+
+    /// Built-in shortcuts (previously hard-wired in the init script)
+    pub fn defaults() -> Self {
+        let mut bindings = HashMap::new();
+        bindings.insert("find".to_string(), vec!["CmdOrCtrl+F".to_string()]);
+        bindings.insert("find-next".to_string(), vec!["F3".to_string(), "CmdOrCtrl+G".to_string()]);
+        bindings.insert("find-previous".to_string(), vec!["Shift+F3".to_string(), "CmdOrCtrl+Shift+G".to_string()]);
+        bindings.insert("find-close".to_string(), vec!["Escape".to_string()]);
+        bindings.insert("zoom-reset".to_string(), vec!["CmdOrCtrl+0".to_string()]);
+        bindings.insert("reopen-closed-wiki".to_string(), vec!["CmdOrCtrl+Shift+T".to_string()]);
+        bindings.insert("share-selection".to_string(), vec!["CmdOrCtrl+Alt+C".to_string()]);
+        bindings.insert("quick-switcher".to_string(), vec!["CmdOrCtrl+K".to_string()]);
+        Self { bindings }
+    }
+
+    /// Defaults with this map's overrides applied on top
+    pub fn with_defaults(&self) -> Self {
+        let mut merged = Self::defaults();
+        for (action, accelerators) in &self.bindings {
+            merged.bindings.insert(action.clone(), accelerators.clone());
+        }
+        merged
+    }
+}
+
+/// Scheduled single-file snapshots of a folder wiki
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+pub struct FolderSnapshotConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Directory the snapshot HTML file is written to
+    #[serde(default)]
+    pub output_dir: String,
+    /// Hours between snapshots while the wiki is open (0 = no schedule)
+    #[serde(default)]
+    pub interval_hours: u32,
+    /// Also take a snapshot when the wiki's server shuts down
+    #[serde(default)]
+    pub on_shutdown: bool,
+    /// Unix time (seconds) of the last snapshot
+    #[serde(default)]
+    pub last_snapshot: Option<u64>,
+}
+
+/// When a wiki exchanges changes with its peers, if not in real time (desktop).
+/// Outside of a sync, local changes wait and changes from peers are held.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "mode", rename_all = "lowercase")]
+pub enum SyncSchedule {
+    /// Every `minutes` minutes
+    Interval { minutes: u32 },
+    /// Only when asked to (`sync_now`)
+    Manual,
+}
+
+/// Tiddlers of a synced wiki that skip the queue or wait for a batch (desktop).
+/// Everything else syncs as usual (in real time or per the sync schedule).
+#[derive(Clone, Debug, Serialize, Deserialize, Default, PartialEq)]
+pub struct SyncPriority {
+    /// Filter of tiddlers sent right away, even between scheduled syncs
+    #[serde(default)]
+    pub high: String,
+    /// Filter of tiddlers sent together every `bulk_minutes`
+    #[serde(default)]
+    pub bulk: String,
+    /// Minutes between batches of bulk tiddlers (0 = 10)
+    #[serde(default)]
+    pub bulk_minutes: u32,
+}
+
+/// Periodic backups of a single-file wiki, taken whether or not it is saved
+#[derive(Clone, Debug, Serialize, Deserialize, Default, PartialEq)]
+pub struct ScheduledBackupConfig {
+    /// Minutes between backups (0 = off)
+    #[serde(default)]
+    pub interval_minutes: u32,
+    /// Scheduled backups to keep (None = default 24, 0 = unlimited)
+    #[serde(default)]
+    pub keep: Option<u32>,
+    /// Delete scheduled backups older than this many days (None = keep by count only)
+    #[serde(default)]
+    pub max_age_days: Option<u32>,
+}
+
+/// Where a wiki's downloads (tm-download-file, exports) are saved
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+pub struct DownloadConfig {
+    /// Directory of the last download; the save dialog starts there
+    #[serde(default)]
+    pub last_dir: Option<String>,
+    /// Save downloads in this directory without showing the save dialog
+    #[serde(default)]
+    pub auto_dir: Option<String>,
+    /// Field delimiter of CSV exports (None = comma)
+    #[serde(default)]
+    pub csv_delimiter: Option<String>,
+}
+
+/// All wiki configs stored in a single file, keyed by wiki path
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+pub struct WikiConfigs {
+    #[serde(default)]
+    pub external_attachments: HashMap<String, ExternalAttachmentsConfig>,
+    #[serde(default)]
+    pub session_auth: HashMap<String, SessionAuthConfig>,
+    #[serde(default)]
+    pub window_states: HashMap<String, WindowState>,
+    /// Per-wiki accelerator overrides (only actions that differ from the defaults)
+    #[serde(default)]
+    pub accelerators: HashMap<String, AcceleratorMap>,
+    /// Per-folder-wiki snapshot schedules
+    #[serde(default)]
+    pub folder_snapshots: HashMap<String, FolderSnapshotConfig>,
+    /// Per-wiki download directories and export options
+    #[serde(default)]
+    pub downloads: HashMap<String, DownloadConfig>,
+    /// Per-wiki location access for `get_current_position` (absent = ask)
+    #[serde(default)]
+    pub geolocation: HashMap<String, bool>,
+    /// Per-wiki serial port access (absent = ask)
+    #[serde(default)]
+    pub serial: HashMap<String, bool>,
+    /// Per-wiki MQTT broker access (absent = ask)
+    #[serde(default)]
+    pub mqtt: HashMap<String, bool>,
+    /// Wikis served to the system browser instead of opening a window
+    #[serde(default)]
+    pub external_browser: HashMap<String, bool>,
+    /// What AI assistants may do with a wiki over MCP (absent = nothing)
+    #[serde(default)]
+    pub mcp_access: HashMap<String, McpAccess>,
+    /// Environment variables for the processes of folder wikis (read by Node.js server plugins)
+    #[serde(default)]
+    pub env_vars: HashMap<String, Vec<WikiEnvVar>>,
+}
+
+/// An environment variable of a wiki's process
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct WikiEnvVar {
+    pub name: String,
+    /// Empty for variables whose value is in the OS keychain
+    #[serde(default)]
+    pub value: String,
+    /// Account of the OS keychain entry holding the value, if it's kept there
+    #[serde(default)]
+    pub keychain: Option<String>,
+}
+
+/// Access of AI assistants to a wiki through the MCP server (`--mcp`)
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum McpAccess {
+    /// Searching and reading tiddlers
+    ReadOnly,
+    /// Also creating, changing and deleting tiddlers (of the open wiki)
+    ReadWrite,
+}
+
+/// Application-wide settings (language, etc.)
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+pub struct AppSettings {
+    /// UI language code (e.g., "en-GB", "de-DE"). None = auto-detect from OS
+    #[serde(default)]
+    pub language: Option<String>,
+    /// Color palette for the landing page (e.g., "$:/palettes/Vanilla"). None = default
+    #[serde(default)]
+    pub palette: Option<String>,
+    /// SAF content:// URI for a folder containing custom plugins (Android only)
+    #[serde(default)]
+    pub custom_plugin_path_uri: Option<String>,
+    /// SAF content:// URI for a folder containing custom editions (Android only)
+    #[serde(default)]
+    pub custom_edition_path_uri: Option<String>,
+    /// GTK input method module for webviews (e.g. "ibus", "fcitx", "xim"). None = system default (Linux only)
+    #[serde(default)]
+    pub ime_module: Option<String>,
+    /// Detach the IME from wiki windows (Windows only, for IMEs that swallow shortcuts)
+    #[serde(default)]
+    pub ime_disabled: bool,
+    /// Directory for temporary build/conversion files. None = "scratch" under the data dir
+    #[serde(default)]
+    pub scratch_dir: Option<String>,
+    /// Directories scanned and watched for wiki files and wiki folders
+    #[serde(default)]
+    pub watched_folders: Vec<String>,
+    /// Wikis already found in watched folders (only new ones are added to the list)
+    #[serde(default)]
+    pub discovered_wikis: Vec<String>,
+    /// Minutes a wiki window may stay minimized/hidden before it is throttled (0 = never)
+    #[serde(default)]
+    pub throttle_hidden_minutes: u32,
+    /// Soft memory limit per wiki process (with its webview processes) in MB (0 = off)
+    #[serde(default)]
+    pub memory_limit_mb: u32,
+    /// Save and restart wikis over the memory limit without asking
+    #[serde(default)]
+    pub memory_limit_auto_restart: bool,
+    /// Shell extensions the user allowed to load (ids, see `extensions`)
+    #[serde(default)]
+    pub enabled_extensions: Vec<String>,
+    /// Serve folder wikis with the built-in TiddlyWeb server instead of Node.js
+    #[serde(default)]
+    pub native_folder_server: bool,
+    /// Also serve folder wikis' tiddler files over WebDAV (built-in server only)
+    #[serde(default)]
+    pub folder_webdav: bool,
+    /// Where the built-in servers listen (see `server_address` in the app)
+    #[serde(default)]
+    pub server_listen: ServerListen,
+    /// Save big single-file wikis by appending the changed tiddlers (see `incremental_save` in the app)
+    #[serde(default)]
+    pub incremental_save: bool,
+    /// Send text selections to connected sync devices and receive theirs
+    #[serde(default)]
+    pub shared_clipboard: bool,
+    /// Background traffic allowed on metered or roaming connections
+    #[serde(default)]
+    pub metered_allow: MeteredAllow,
+    /// Network interfaces LAN sync uses, by name (empty = all)
+    #[serde(default)]
+    pub lan_sync_interfaces: Vec<String>,
+    /// Days deletion tombstones are kept (0 = 30 days)
+    #[serde(default)]
+    pub lan_sync_tombstone_days: u32,
+    /// Reopening the wikis that were open when the app last quit
+    #[serde(default)]
+    pub restore_session: SessionRestore,
+    /// Answer the local automation API (see `automation` in the app)
+    #[serde(default)]
+    pub automation_api: bool,
+}
+
+/// Where the built-in servers (folder wikis, wikis in the browser, media) listen
+#[derive(Clone, Debug, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(tag = "mode", content = "interface", rename_all = "lowercase")]
+pub enum ServerListen {
+    /// 127.0.0.1 only
+    #[default]
+    Local,
+    /// Every address, IPv6 and IPv4, so other devices can reach them
+    All,
+    /// 127.0.0.1 and the addresses of one network interface (by name)
+    Interface(String),
+}
+
+/// Whether the wikis open when the app quit (or crashed) are reopened on the next launch
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SessionRestore {
+    /// Offer them on the landing page
+    #[default]
+    Ask,
+    Always,
+    Never,
+}
+
+/// Background traffic that runs on metered or roaming connections anyway
+/// (deferred until the connection is unmetered otherwise)
+#[derive(Clone, Debug, Serialize, Deserialize, Default, PartialEq)]
+pub struct MeteredAllow {
+    /// Connecting relay rooms (LAN sync is never deferred)
+    #[serde(default)]
+    pub relay_sync: bool,
+    /// Fetching plugins from plugin libraries
+    #[serde(default)]
+    pub plugin_library: bool,
+    /// Checking for updates
+    #[serde(default)]
+    pub updates: bool,
+}
+
+/// A share template for customizing how shared content is imported
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ShareTemplate {
+    pub id: String,
+    pub label: String,
+    #[serde(default)]
+    pub tags: String,
+    #[serde(default)]
+    pub title_pattern: String,
+    #[serde(default)]
+    pub content_prefix: String,
+    #[serde(default)]
+    pub content_suffix: String,
+    #[serde(default = "default_content_mode")]
+    pub content_mode: String,
+    #[serde(default)]
+    pub tiddler_type: String,
+}
+
+fn default_content_mode() -> String {
+    "clip".to_string()
+}
+
+/// A domain rule that auto-selects a template based on URL domain
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DomainRule {
+    pub domain: String,
+    pub template_id: String,
+    #[serde(default)]
+    pub additional_tags: String,
+    #[serde(default)]
+    pub title_strip: String,
+}
+
+/// Configuration for share templates and domain rules
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+pub struct ShareTemplatesConfig {
+    #[serde(default)]
+    pub templates: Vec<ShareTemplate>,
+    #[serde(default)]
+    pub domain_rules: Vec<DomainRule>,
+    #[serde(default)]
+    pub default_template_id: Option<String>,
+}
+
+/// Information about a TiddlyWiki edition
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EditionInfo {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub is_user_edition: bool,
+}
+
+/// Information about a TiddlyWiki plugin
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PluginInfo {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub category: String,
+}
+
+/// Status of a folder for wiki creation
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FolderStatus {
+    pub is_wiki: bool,
+    pub is_empty: bool,
+    pub has_files: bool,
+    pub path: String,
+    pub name: String,
+}
+
+
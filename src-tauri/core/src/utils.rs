@@ -3,10 +3,10 @@
 //! This module contains common utility functions used throughout the application:
 //! - HTML encoding/decoding
 //! - MIME type detection
-//! - Path utilities
+//! - Path utilities (including portable-relative path storage)
 //! - Base64 encoding
 
-use std::path::PathBuf;
+use std::path::{Component, Path, PathBuf};
 
 /// Decode basic HTML entities
 pub fn html_decode(s: &str) -> String {
@@ -118,28 +118,10 @@ pub fn is_absolute_filesystem_path(path: &str) -> bool {
 }
 
 /// Compare two paths for equality
-/// Uses canonical path comparison when possible to handle symlinks and different representations
-/// Falls back to string comparison if canonicalization fails
+/// Resolves symlinks, junctions and hardlinks (see `PathIdentity`); paths that
+/// don't exist yet are compared by their canonicalized form
 pub fn paths_equal(path1: &str, path2: &str) -> bool {
-    // Try to canonicalize both paths for robust comparison
-    let canonical1 = dunce::canonicalize(path1);
-    let canonical2 = dunce::canonicalize(path2);
-
-    match (canonical1, canonical2) {
-        (Ok(c1), Ok(c2)) => c1 == c2,
-        _ => {
-            // Fall back to string comparison if canonicalization fails
-            // (e.g., for paths that don't exist yet)
-            #[cfg(target_os = "windows")]
-            {
-                path1.eq_ignore_ascii_case(path2)
-            }
-            #[cfg(not(target_os = "windows"))]
-            {
-                path1 == path2
-            }
-        }
-    }
+    crate::path_identity::same_path(path1, path2)
 }
 
 /// Normalize a path for cross-platform compatibility
@@ -165,6 +147,77 @@ pub fn normalize_path(path: PathBuf) -> PathBuf {
     normalized
 }
 
+/// Compare two path components, case-insensitively on Windows
+fn components_equal(a: &Component, b: &Component) -> bool {
+    #[cfg(target_os = "windows")]
+    {
+        a.as_os_str().to_string_lossy().eq_ignore_ascii_case(&b.as_os_str().to_string_lossy())
+    }
+    #[cfg(not(target_os = "windows"))]
+    {
+        a == b
+    }
+}
+
+/// Express an absolute path relative to `base` for portable storage.
+/// The result always starts with "./" or "../" (forward slashes on all platforms)
+/// so stored paths can't be confused with absolute paths or non-path keys.
+/// Returns None if the path isn't absolute or lives on a different drive.
+pub fn to_portable_relative(path: &str, base: &Path) -> Option<String> {
+    if !is_absolute_filesystem_path(path) || !base.is_absolute() {
+        return None;
+    }
+    let path_components: Vec<Component> = Path::new(path).components().collect();
+    let base_components: Vec<Component> = base.components().collect();
+
+    // Drive prefix (Windows) and root must match
+    let is_anchor = |c: &Component| matches!(c, Component::Prefix(_) | Component::RootDir);
+    let path_anchor: Vec<&Component> = path_components.iter().take_while(|c| is_anchor(c)).collect();
+    let base_anchor: Vec<&Component> = base_components.iter().take_while(|c| is_anchor(c)).collect();
+    if path_anchor.len() != base_anchor.len()
+        || !path_anchor.iter().zip(&base_anchor).all(|(a, b)| components_equal(a, b))
+    {
+        return None;
+    }
+
+    let path_rest = &path_components[path_anchor.len()..];
+    let base_rest = &base_components[base_anchor.len()..];
+    let common = path_rest.iter().zip(base_rest)
+        .take_while(|(a, b)| components_equal(a, b))
+        .count();
+
+    let mut parts: Vec<String> = Vec::new();
+    if common == base_rest.len() {
+        parts.push(".".to_string());
+    } else {
+        for _ in common..base_rest.len() {
+            parts.push("..".to_string());
+        }
+    }
+    parts.extend(path_rest[common..].iter().map(|c| c.as_os_str().to_string_lossy().into_owned()));
+    Some(parts.join("/"))
+}
+
+/// Resolve a path stored by `to_portable_relative` against `base`.
+/// Returns None if `stored` isn't a portable-relative path.
+pub fn from_portable_relative(stored: &str, base: &Path) -> Option<String> {
+    let is_relative = ["./", "../", ".\\", "..\\"].iter().any(|p| stored.starts_with(p));
+    if !is_relative {
+        return None;
+    }
+    let mut resolved = PathBuf::new();
+    for component in base.join(stored).components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            other => resolved.push(other.as_os_str()),
+        }
+    }
+    Some(normalize_path(resolved).to_string_lossy().into_owned())
+}
+
 /// Check if a path is a wiki folder (contains tiddlywiki.info)
 pub fn is_wiki_folder(path: &std::path::Path) -> bool {
     path.is_dir() && path.join("tiddlywiki.info").exists()
@@ -184,3 +237,41 @@ pub fn base64_url_decode(input: &str) -> Option<String> {
         .ok()
         .and_then(|bytes| String::from_utf8(bytes).ok())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn test_portable_relative_round_trip() {
+        let base = Path::new("/media/usb/TiddlyDesktop");
+        let inside = to_portable_relative("/media/usb/TiddlyDesktop/wikis/notes.html", base).unwrap();
+        assert_eq!(inside, "./wikis/notes.html");
+        let sibling = to_portable_relative("/media/usb/Wikis/notes.html", base).unwrap();
+        assert_eq!(sibling, "../Wikis/notes.html");
+
+        // Mount point changed: resolves against the new base
+        let moved = Path::new("/run/media/user/usb/TiddlyDesktop");
+        assert_eq!(from_portable_relative(&sibling, moved).unwrap(), "/run/media/user/usb/Wikis/notes.html");
+        assert_eq!(from_portable_relative(&inside, moved).unwrap(), "/run/media/user/usb/TiddlyDesktop/wikis/notes.html");
+    }
+
+    #[test]
+    fn test_non_relative_paths_are_not_resolved() {
+        let base = Path::new("/media/usb/TiddlyDesktop");
+        assert!(from_portable_relative("__LANDING_PAGE__", base).is_none());
+        assert!(from_portable_relative("/home/user/notes.html", base).is_none());
+        assert!(from_portable_relative("content://com.android/tree/x", base).is_none());
+        assert!(to_portable_relative("content://com.android/tree/x", base).is_none());
+    }
+
+    #[cfg(target_os = "windows")]
+    #[test]
+    fn test_portable_relative_windows_drives() {
+        let base = Path::new("E:\\TiddlyDesktop");
+        assert_eq!(to_portable_relative("e:\\Wikis\\notes.html", base).unwrap(), "../Wikis/notes.html");
+        assert!(to_portable_relative("C:\\Users\\me\\notes.html", base).is_none());
+        assert_eq!(from_portable_relative("../Wikis/notes.html", Path::new("F:\\TiddlyDesktop")).unwrap(), "F:\\Wikis\\notes.html");
+    }
+}
@@ -0,0 +1,786 @@
+//! Wiki folders the way TiddlyWiki on Node.js reads and writes them
+//!
+//! - Loading tiddler files: `.tid`, `.multids`, `.json`, JavaScript modules
+//!   with header comments, other files with `.meta` companions, and
+//!   `tiddlywiki.files` specifications
+//! - Packing plugin, theme and language folders into plugin tiddlers
+//! - Resolving the plugins of `tiddlywiki.info` against a TiddlyWiki installation
+//! - Building the boot page that the Node.js server would serve at `/`
+//! - Choosing file names and formats for saving tiddlers
+//!
+//! This is what a Node-free folder wiki server needs; the HTTP side lives in
+//! the app.
+
+use std::path::{Path, PathBuf};
+
+use base64::Engine;
+use regex::Regex;
+
+use crate::filter::{parse_tid, stringify_title_list, Tiddler};
+use crate::utils;
+
+/// Files TiddlyWiki never loads as tiddlers
+fn is_excluded_file(name: &str) -> bool {
+    name.ends_with(".meta")
+        || name == "plugin.info"
+        || name == "tiddlywiki.files"
+        || name == ".DS_Store"
+        || name.starts_with("._")
+        || (name.starts_with('.') && name.ends_with(".swp"))
+        || matches!(name, ".git" | ".github" | ".vscode" | ".hg" | ".svn" | "CVS" | "npm-debug.log")
+}
+
+/// Whether tiddlers of this type keep their text base64-encoded
+pub fn is_binary_type(content_type: &str) -> bool {
+    !(content_type.is_empty()
+        || content_type.starts_with("text/")
+        || content_type == "image/svg+xml"
+        || content_type == "application/json"
+        || content_type == "application/javascript"
+        || content_type == "application/xml"
+        || content_type == "application/x-tiddler"
+        || content_type == "application/x-tiddlers"
+        || content_type == "application/x-tiddler-dictionary")
+}
+
+/// File extension for saving a tiddler of this type as a native file with a `.meta` companion
+fn extension_for_type(content_type: &str) -> Option<&'static str> {
+    Some(match content_type {
+        "image/png" => ".png",
+        "image/jpeg" => ".jpg",
+        "image/gif" => ".gif",
+        "image/webp" => ".webp",
+        "image/x-icon" => ".ico",
+        "image/bmp" => ".bmp",
+        "image/tiff" => ".tiff",
+        "image/heic" => ".heic",
+        "application/pdf" => ".pdf",
+        "audio/mpeg" => ".mp3",
+        "audio/mp4" => ".m4a",
+        "audio/ogg" => ".ogg",
+        "audio/wav" => ".wav",
+        "video/mp4" => ".mp4",
+        "video/webm" => ".webm",
+        "font/woff" => ".woff",
+        "font/woff2" => ".woff2",
+        "font/ttf" => ".ttf",
+        "font/otf" => ".otf",
+        "application/zip" => ".zip",
+        _ => return None,
+    })
+}
+
+fn read_text_or_base64(path: &Path, binary: bool) -> Result<String, String> {
+    if binary {
+        std::fs::read(path)
+            .map(|bytes| base64::engine::general_purpose::STANDARD.encode(bytes))
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))
+    } else {
+        std::fs::read_to_string(path)
+            .map(|s| s.strip_prefix('\u{feff}').map(str::to_string).unwrap_or(s))
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default()
+}
+
+/// Fields from the header comment of a JavaScript module (`/*\ title: ... \*/`)
+fn parse_js_header(content: &str) -> Tiddler {
+    let mut fields = Tiddler::new();
+    let content = content.replace("\r\n", "\n");
+    if let Some(start) = content.find("/*\\\n") {
+        let header = &content[start + 4..];
+        if let Some(end) = header.find("\n\\*/") {
+            fields = parse_tid(&header[..end]);
+            fields.remove("text");
+        }
+    }
+    fields
+}
+
+/// Tiddlers of a `.multids` file: header fields, then one `title: text` line per tiddler
+fn parse_multids(content: &str) -> Vec<Tiddler> {
+    let content = content.replace("\r\n", "\n");
+    let (header, body) = content.split_once("\n\n").unwrap_or(("", content.as_str()));
+    let mut defaults = parse_tid(header);
+    defaults.remove("text");
+    let prefix = defaults.remove("title").unwrap_or_default();
+    body.lines()
+        .filter_map(|line| line.split_once(':'))
+        .filter(|(key, _)| !key.trim().is_empty())
+        .map(|(key, value)| {
+            let mut fields = defaults.clone();
+            fields.insert("title".to_string(), format!("{}{}", prefix, key.trim()));
+            fields.insert("text".to_string(), value.trim().to_string());
+            fields
+        })
+        .collect()
+}
+
+/// Fields of a JSON tiddler object, with arrays turned into title lists
+pub fn tiddler_from_json(value: &serde_json::Value) -> Option<Tiddler> {
+    let object = value.as_object()?;
+    let mut fields = Tiddler::new();
+    for (name, value) in object {
+        let value = match value {
+            serde_json::Value::String(s) => s.clone(),
+            serde_json::Value::Array(items) => stringify_title_list(
+                &items.iter().map(|i| i.as_str().map(str::to_string).unwrap_or_else(|| i.to_string())).collect::<Vec<_>>(),
+            ),
+            serde_json::Value::Null => continue,
+            other => other.to_string(),
+        };
+        fields.insert(name.clone(), value);
+    }
+    Some(fields)
+}
+
+/// Tiddlers from a JSON file: an array of tiddlers, a single tiddler, or
+/// otherwise one `application/json` tiddler holding the file's text
+fn parse_json_file(content: &str, fallback_title: &str) -> Vec<Tiddler> {
+    match serde_json::from_str::<serde_json::Value>(content) {
+        Ok(serde_json::Value::Array(values)) if values.iter().all(|v| v.get("title").is_some()) => {
+            values.iter().filter_map(tiddler_from_json).collect()
+        }
+        Ok(value) if value.get("title").and_then(|t| t.as_str()).is_some() => {
+            tiddler_from_json(&value).into_iter().collect()
+        }
+        _ => {
+            let mut fields = Tiddler::new();
+            fields.insert("title".to_string(), fallback_title.to_string());
+            fields.insert("type".to_string(), "application/json".to_string());
+            fields.insert("text".to_string(), content.to_string());
+            vec![fields]
+        }
+    }
+}
+
+/// Load the tiddlers of one file, using its `.meta` companion if there is one
+pub fn load_tiddler_file(path: &Path) -> Result<Vec<Tiddler>, String> {
+    let name = file_name(path);
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("").to_lowercase();
+    let meta_path = PathBuf::from(format!("{}.meta", path.to_string_lossy()));
+
+    if meta_path.is_file() {
+        let meta = std::fs::read_to_string(&meta_path)
+            .map_err(|e| format!("Failed to read {}: {}", meta_path.display(), e))?;
+        let mut fields = parse_tid(&meta);
+        fields.remove("text");
+        let content_type = fields.get("type").cloned().unwrap_or_else(|| utils::get_mime_type(path).to_string());
+        fields.insert("text".to_string(), read_text_or_base64(path, is_binary_type(&content_type))?);
+        fields.entry("title".to_string()).or_insert(name);
+        return Ok(vec![fields]);
+    }
+
+    let tiddlers = match extension.as_str() {
+        "tid" => {
+            let mut fields = parse_tid(&read_text_or_base64(path, false)?);
+            fields.entry("title".to_string()).or_insert_with(|| name.trim_end_matches(".tid").to_string());
+            vec![fields]
+        }
+        "multids" => parse_multids(&read_text_or_base64(path, false)?),
+        "json" => parse_json_file(&read_text_or_base64(path, false)?, &name),
+        "js" => {
+            let text = read_text_or_base64(path, false)?;
+            let mut fields = parse_js_header(&text);
+            fields.entry("title".to_string()).or_insert(name);
+            fields.entry("type".to_string()).or_insert_with(|| "application/javascript".to_string());
+            fields.insert("text".to_string(), text);
+            vec![fields]
+        }
+        _ => {
+            let content_type = utils::get_mime_type(path);
+            let mut fields = Tiddler::new();
+            fields.insert("title".to_string(), name);
+            fields.insert("type".to_string(), content_type.to_string());
+            fields.insert("text".to_string(), read_text_or_base64(path, is_binary_type(content_type))?);
+            vec![fields]
+        }
+    };
+    Ok(tiddlers)
+}
+
+/// A file and the tiddlers loaded from it
+#[derive(Debug, Clone)]
+pub struct TiddlerFile {
+    pub path: PathBuf,
+    pub tiddlers: Vec<Tiddler>,
+    /// Whether the file holds exactly one tiddler and nothing else, so the
+    /// tiddler can be saved back to it
+    pub single: bool,
+}
+
+/// Load all tiddler files below a directory (or a single file), following
+/// `tiddlywiki.files` where present
+pub fn load_tiddlers_from_path(path: &Path) -> Vec<TiddlerFile> {
+    let mut files = Vec::new();
+    if path.is_dir() {
+        let spec_path = path.join("tiddlywiki.files");
+        if spec_path.is_file() {
+            match std::fs::read_to_string(&spec_path)
+                .map_err(|e| e.to_string())
+                .and_then(|s| serde_json::from_str::<serde_json::Value>(&s).map_err(|e| e.to_string()))
+            {
+                Ok(spec) => files.extend(load_tiddlywiki_files(path, &spec)),
+                Err(e) => eprintln!("[TiddlyDesktop] Invalid {}: {}", spec_path.display(), e),
+            }
+            return files;
+        }
+        let mut entries: Vec<PathBuf> = match std::fs::read_dir(path) {
+            Ok(entries) => entries.flatten().map(|e| e.path()).collect(),
+            Err(_) => return files,
+        };
+        entries.sort();
+        for entry in entries {
+            if !is_excluded_file(&file_name(&entry)) {
+                files.extend(load_tiddlers_from_path(&entry));
+            }
+        }
+    } else if path.is_file() {
+        match load_tiddler_file(path) {
+            Ok(tiddlers) => {
+                let single = tiddlers.len() == 1 && path.extension().and_then(|e| e.to_str()) != Some("multids");
+                files.push(TiddlerFile { path: path.to_path_buf(), tiddlers, single });
+            }
+            Err(e) => eprintln!("[TiddlyDesktop] {}", e),
+        }
+    }
+    files
+}
+
+/// A field value from a `tiddlywiki.files` entry: a string, a list, or a
+/// `{"source": ..., "prefix": ..., "suffix": ...}` object
+fn spec_field_value(value: &serde_json::Value, path: &Path, root: &Path) -> Option<String> {
+    match value {
+        serde_json::Value::String(s) => Some(s.clone()),
+        serde_json::Value::Array(items) => Some(stringify_title_list(
+            &items.iter().filter_map(|i| i.as_str().map(str::to_string)).collect::<Vec<_>>(),
+        )),
+        serde_json::Value::Object(object) => {
+            let name = file_name(path);
+            let extension = path.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+            let basename = name.strip_suffix(&extension).unwrap_or(&name).to_string();
+            let value = match object.get("source").and_then(|s| s.as_str()).unwrap_or("") {
+                "filename" => name.clone(),
+                "filename-uri-decoded" => urlencoding_decode(&name),
+                "basename" => basename.clone(),
+                "basename-uri-decoded" => urlencoding_decode(&basename),
+                "extname" => extension,
+                "filepath" => path.strip_prefix(root).unwrap_or(path).to_string_lossy().replace('\\', "/"),
+                "subdirectories" => {
+                    let relative = path.parent().and_then(|p| p.strip_prefix(root).ok()).unwrap_or(Path::new(""));
+                    stringify_title_list(&relative.components().map(|c| c.as_os_str().to_string_lossy().to_string()).collect::<Vec<_>>())
+                }
+                _ => return None,
+            };
+            let prefix = object.get("prefix").and_then(|s| s.as_str()).unwrap_or("");
+            let suffix = object.get("suffix").and_then(|s| s.as_str()).unwrap_or("");
+            Some(format!("{}{}{}", prefix, value, suffix))
+        }
+        serde_json::Value::Null => None,
+        other => Some(other.to_string()),
+    }
+}
+
+/// Percent-decoding for `*-uri-decoded` sources
+fn urlencoding_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok().and_then(|h| u8::from_str_radix(h, 16).ok());
+            if let Some(byte) = hex {
+                out.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).to_string()
+}
+
+/// Load one file named by a `tiddlywiki.files` entry
+fn load_spec_file(file: &Path, root: &Path, spec: &serde_json::Value) -> Option<TiddlerFile> {
+    let is_tiddler_file = spec.get("isTiddlerFile").and_then(|v| v.as_bool()).unwrap_or(false);
+    let fields = spec.get("fields").and_then(|f| f.as_object());
+    let mut tiddlers = if is_tiddler_file {
+        match load_tiddler_file(file) {
+            Ok(tiddlers) => tiddlers,
+            Err(e) => {
+                eprintln!("[TiddlyDesktop] {}", e);
+                return None;
+            }
+        }
+    } else {
+        let content_type = fields
+            .and_then(|f| f.get("type"))
+            .and_then(|t| t.as_str())
+            .unwrap_or_else(|| utils::get_mime_type(file))
+            .to_string();
+        // Text given as a field (media with `_canonical_uri`) saves reading big files
+        let text_given = fields.is_some_and(|f| f.contains_key("text"));
+        let mut text = match read_text_or_base64(file, is_binary_type(&content_type)) {
+            _ if text_given => String::new(),
+            Ok(text) => text,
+            Err(e) => {
+                eprintln!("[TiddlyDesktop] {}", e);
+                return None;
+            }
+        };
+        if let Some(prefix) = spec.get("prefix").and_then(|p| p.as_str()) {
+            text = format!("{}{}", prefix, text);
+        }
+        if let Some(suffix) = spec.get("suffix").and_then(|s| s.as_str()) {
+            text.push_str(suffix);
+        }
+        let mut tiddler = Tiddler::new();
+        tiddler.insert("type".to_string(), content_type);
+        tiddler.insert("text".to_string(), text);
+        vec![tiddler]
+    };
+    if let Some(fields) = fields {
+        for tiddler in &mut tiddlers {
+            for (name, value) in fields {
+                if let Some(value) = spec_field_value(value, file, root) {
+                    tiddler.insert(name.clone(), value);
+                }
+            }
+        }
+    }
+    tiddlers.retain(|t| t.get("title").is_some_and(|title| !title.is_empty()));
+    Some(TiddlerFile { path: file.to_path_buf(), tiddlers, single: false })
+}
+
+/// Tiddlers described by a `tiddlywiki.files` specification
+fn load_tiddlywiki_files(dir: &Path, spec: &serde_json::Value) -> Vec<TiddlerFile> {
+    let mut files = Vec::new();
+    for entry in spec.get("tiddlers").and_then(|t| t.as_array()).into_iter().flatten() {
+        if let Some(file) = entry.get("file").and_then(|f| f.as_str()) {
+            files.extend(load_spec_file(&dir.join(file), dir, entry));
+        }
+    }
+    for entry in spec.get("directories").and_then(|d| d.as_array()).into_iter().flatten() {
+        // A plain string is a directory loaded the usual way
+        if let Some(path) = entry.as_str() {
+            files.extend(load_tiddlers_from_path(&dir.join(path)));
+            continue;
+        }
+        let Some(path) = entry.get("path").and_then(|p| p.as_str()) else { continue };
+        let root = dir.join(path);
+        let pattern = entry.get("filesRegExp").and_then(|r| r.as_str()).unwrap_or("^.*$");
+        let Ok(regex) = Regex::new(pattern) else { continue };
+        let recursive = entry.get("searchSubdirectories").and_then(|s| s.as_bool()).unwrap_or(false);
+        let mut stack = vec![root.clone()];
+        while let Some(current) = stack.pop() {
+            let Ok(entries) = std::fs::read_dir(&current) else { continue };
+            let mut paths: Vec<PathBuf> = entries.flatten().map(|e| e.path()).collect();
+            paths.sort();
+            for path in paths {
+                let name = file_name(&path);
+                if path.is_dir() {
+                    if recursive && !is_excluded_file(&name) {
+                        stack.push(path);
+                    }
+                } else if regex.is_match(&name) && !is_excluded_file(&name) {
+                    files.extend(load_spec_file(&path, &root, entry));
+                }
+            }
+        }
+    }
+    files
+}
+
+/// Pack a plugin, theme or language folder into its plugin tiddler
+pub fn load_plugin_folder(dir: &Path) -> Result<Tiddler, String> {
+    let info_path = dir.join("plugin.info");
+    let info = std::fs::read_to_string(&info_path)
+        .map_err(|e| format!("Failed to read {}: {}", info_path.display(), e))?;
+    let info: serde_json::Value = serde_json::from_str(&info)
+        .map_err(|e| format!("Invalid {}: {}", info_path.display(), e))?;
+    let mut plugin = tiddler_from_json(&info).ok_or_else(|| format!("Invalid {}", info_path.display()))?;
+
+    let mut shadows = serde_json::Map::new();
+    for file in load_tiddlers_from_path(dir) {
+        for tiddler in file.tiddlers {
+            if let Some(title) = tiddler.get("title").cloned() {
+                shadows.insert(title, serde_json::to_value(tiddler).unwrap_or_default());
+            }
+        }
+    }
+    plugin.insert("type".to_string(), "application/json".to_string());
+    plugin.entry("plugin-type".to_string()).or_insert_with(|| "plugin".to_string());
+    plugin.entry("dependents".to_string()).or_default();
+    plugin.insert("text".to_string(), serde_json::json!({ "tiddlers": shadows }).to_string());
+    Ok(plugin)
+}
+
+/// The `plugins`, `themes` and `languages` of a wiki's `tiddlywiki.info`
+#[derive(Debug, Default, Clone)]
+pub struct WikiInfo {
+    pub plugins: Vec<String>,
+    pub themes: Vec<String>,
+    pub languages: Vec<String>,
+}
+
+impl WikiInfo {
+    pub fn load(wiki_dir: &Path) -> Result<Self, String> {
+        let path = wiki_dir.join("tiddlywiki.info");
+        let content = std::fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let info: serde_json::Value = serde_json::from_str(&content)
+            .map_err(|e| format!("Invalid {}: {}", path.display(), e))?;
+        if info.get("includeWikis").and_then(|w| w.as_array()).is_some_and(|w| !w.is_empty()) {
+            eprintln!("[TiddlyDesktop] {}: includeWikis is not supported without Node.js", path.display());
+        }
+        let list = |key: &str| -> Vec<String> {
+            info.get(key)
+                .and_then(|v| v.as_array())
+                .map(|items| items.iter().filter_map(|i| i.as_str().map(str::to_string)).collect())
+                .unwrap_or_default()
+        };
+        Ok(Self { plugins: list("plugins"), themes: list("themes"), languages: list("languages") })
+    }
+}
+
+/// A TiddlyWiki installation (the folder containing `tiddlywiki.js`, `boot/`,
+/// `core/` and `plugins/`), plus the extra search paths TiddlyWiki reads from
+/// `TIDDLYWIKI_PLUGIN_PATH`, `TIDDLYWIKI_THEME_PATH` and `TIDDLYWIKI_LANGUAGE_PATH`
+#[derive(Debug, Clone)]
+pub struct TiddlyWikiInstall {
+    pub dir: PathBuf,
+    pub plugin_paths: Vec<PathBuf>,
+    pub theme_paths: Vec<PathBuf>,
+    pub language_paths: Vec<PathBuf>,
+}
+
+impl TiddlyWikiInstall {
+    /// An installation with search paths taken from the environment
+    pub fn new(dir: &Path) -> Self {
+        let env_paths = |var: &str| -> Vec<PathBuf> {
+            std::env::var_os(var).map(|v| std::env::split_paths(&v).collect()).unwrap_or_default()
+        };
+        Self {
+            dir: dir.to_path_buf(),
+            plugin_paths: env_paths("TIDDLYWIKI_PLUGIN_PATH"),
+            theme_paths: env_paths("TIDDLYWIKI_THEME_PATH"),
+            language_paths: env_paths("TIDDLYWIKI_LANGUAGE_PATH"),
+        }
+    }
+
+    pub fn version(&self) -> String {
+        std::fs::read_to_string(self.dir.join("package.json"))
+            .ok()
+            .and_then(|s| serde_json::from_str::<serde_json::Value>(&s).ok())
+            .and_then(|p| p.get("version").and_then(|v| v.as_str()).map(str::to_string))
+            .unwrap_or_default()
+    }
+
+    fn find(&self, subdir: &str, extra: &[PathBuf], name: &str) -> Option<PathBuf> {
+        std::iter::once(self.dir.join(subdir))
+            .chain(extra.iter().cloned())
+            .map(|base| base.join(name))
+            .find(|dir| dir.join("plugin.info").is_file())
+    }
+
+    /// The folder of a plugin named like in `tiddlywiki.info` (`tiddlywiki/markdown`)
+    pub fn plugin_folder(&self, name: &str) -> Option<PathBuf> {
+        self.find("plugins", &self.plugin_paths, name)
+    }
+
+    /// The plugin tiddlers a wiki folder boots with: the core, the plugins,
+    /// themes and languages named in `tiddlywiki.info`, and the wiki's own
+    /// `plugins/`, `themes/` and `languages/` folders
+    pub fn load_plugins(&self, wiki_dir: &Path) -> Result<Vec<Tiddler>, String> {
+        let info = WikiInfo::load(wiki_dir)?;
+        let mut folders = vec![self.dir.join("core")];
+        for (names, subdir, extra) in [
+            (&info.plugins, "plugins", &self.plugin_paths),
+            (&info.themes, "themes", &self.theme_paths),
+            (&info.languages, "languages", &self.language_paths),
+        ] {
+            for name in names {
+                match self.find(subdir, extra, name) {
+                    Some(dir) => folders.push(dir),
+                    None => eprintln!("[TiddlyDesktop] Cannot find {} {}", subdir.trim_end_matches('s'), name),
+                }
+            }
+        }
+        for subdir in ["plugins", "themes", "languages"] {
+            if let Ok(entries) = std::fs::read_dir(wiki_dir.join(subdir)) {
+                let mut dirs: Vec<PathBuf> = entries.flatten().map(|e| e.path()).filter(|p| p.join("plugin.info").is_file()).collect();
+                dirs.sort();
+                folders.extend(dirs);
+            }
+        }
+
+        let mut plugins = Vec::new();
+        for dir in folders {
+            match load_plugin_folder(&dir) {
+                Ok(plugin) => plugins.push(plugin),
+                Err(e) if dir == self.dir.join("core") => return Err(e),
+                Err(e) => eprintln!("[TiddlyDesktop] {}", e),
+            }
+        }
+        Ok(plugins)
+    }
+
+    /// Build the page the Node.js server serves at `/`: the boot kernel,
+    /// library modules and a tiddler store holding `tiddlers`
+    pub fn build_boot_html(&self, tiddlers: &[Tiddler]) -> Result<String, String> {
+        let boot_dir = self.dir.join("boot");
+        let read = |name: &str| read_text_or_base64(&boot_dir.join(name), false);
+        let boot_js = read("boot.js")?;
+        let bootprefix_js = read("bootprefix.js")?;
+        let boot_css = read("boot.css").unwrap_or_default();
+
+        let has_tag = |tiddler: &Tiddler, tag: &str| {
+            tiddler.get("tags").is_some_and(|tags| crate::filter::parse_title_list(tags).iter().any(|t| t == tag))
+        };
+        let raw_markup = |tag: &str| -> String {
+            tiddlers.iter().filter(|t| has_tag(t, tag)).filter_map(|t| t.get("text").cloned()).collect::<Vec<_>>().join("\n")
+        };
+        let is_library = |t: &Tiddler| {
+            t.get("library").map(String::as_str) == Some("yes")
+                && t.get("type").map(String::as_str) == Some("application/javascript")
+        };
+        let libraries: String = tiddlers.iter()
+            .filter(|t| is_library(t))
+            .map(|t| format!(
+                "<script data-tiddler-title=\"{}\" type=\"text/javascript\">{}</script>\n",
+                utils::html_encode(t.get("title").map(String::as_str).unwrap_or("")),
+                t.get("text").map(String::as_str).unwrap_or("")
+            ))
+            .collect();
+        let store: Vec<&Tiddler> = tiddlers.iter().filter(|t| !is_library(t)).collect();
+        let store_json = serde_json::to_string(&store)
+            .map_err(|e| format!("Failed to serialize tiddlers: {}", e))?
+            .replace('<', "\\u003C")
+            .replace("},{", "},\n{");
+        let title = tiddlers.iter()
+            .find(|t| t.get("title").map(String::as_str) == Some("$:/SiteTitle"))
+            .and_then(|t| t.get("text").cloned())
+            .unwrap_or_else(|| "TiddlyWiki".to_string());
+
+        Ok(format!(
+            r#"<!doctype html>
+<html>
+<head>
+{top_head}
+<meta http-equiv="Content-Type" content="text/html;charset=utf-8" />
+<meta name="application-name" content="TiddlyWiki" />
+<meta name="generator" content="TiddlyWiki" />
+<meta name="tiddlywiki-version" content="{version}" />
+<meta name="viewport" content="width=device-width, initial-scale=1.0" />
+<meta name="apple-mobile-web-app-capable" content="yes" />
+<meta name="apple-mobile-web-app-status-bar-style" content="black-translucent" />
+<meta name="mobile-web-app-capable" content="yes"/>
+<meta name="format-detection" content="telephone=no" />
+<link id="faviconLink" rel="shortcut icon" href="favicon.ico">
+<title>{title}</title>
+{raw_markup}
+<style data-tiddler-title="$:/boot/boot.css" type="text/css">{boot_css}</style>
+</head>
+<body class="tc-body">
+{top_body}
+<div id="styleArea">
+</div>
+<script class="tiddlywiki-tiddler-store" type="application/json">{store_json}</script>
+<div id="storeArea" style="display:none;">
+</div>
+<div id="libraryModules" style="display:none;">
+{libraries}</div>
+<div id="bootKernelPrefix" style="display:none;">
+<script data-tiddler-title="$:/boot/bootprefix.js" type="text/javascript">{bootprefix_js}</script>
+</div>
+<div id="bootKernel" style="display:none;">
+<script data-tiddler-title="$:/boot/boot.js" type="text/javascript">{boot_js}</script>
+</div>
+</body>
+</html>
+"#,
+            top_head = raw_markup("$:/tags/RawMarkupTopHead"),
+            version = utils::html_encode(&self.version()),
+            title = utils::html_encode(&title),
+            raw_markup = raw_markup("$:/tags/RawMarkup"),
+            top_body = raw_markup("$:/tags/RawMarkupTopBody"),
+        ))
+    }
+}
+
+/// How a tiddler is stored on disk
+#[derive(Debug, Clone, PartialEq)]
+pub enum TiddlerFormat {
+    /// `.tid`: header fields and text
+    Tid,
+    /// `.json`: a one-element array of the tiddler's fields
+    Json,
+    /// The decoded text in a native file (e.g. `.png`) with fields in a `.meta` file
+    Native { extension: &'static str },
+}
+
+fn is_valid_field_name(name: &str) -> bool {
+    !name.is_empty() && !name.contains(':') && !name.chars().any(char::is_whitespace)
+}
+
+/// The format TiddlyWiki would save a tiddler in
+pub fn tiddler_format(tiddler: &Tiddler) -> TiddlerFormat {
+    let content_type = tiddler.get("type").map(String::as_str).unwrap_or("");
+    if is_binary_type(content_type) {
+        if let Some(extension) = extension_for_type(content_type) {
+            return TiddlerFormat::Native { extension };
+        }
+    }
+    let tid_compatible = tiddler.iter()
+        .filter(|(name, _)| name.as_str() != "text")
+        .all(|(name, value)| is_valid_field_name(name) && !value.contains('\n') && value.trim() == value);
+    if tid_compatible { TiddlerFormat::Tid } else { TiddlerFormat::Json }
+}
+
+/// Header lines of a `.tid` or `.meta` file (fields sorted, without text)
+fn tid_header(tiddler: &Tiddler) -> String {
+    tiddler.iter()
+        .filter(|(name, _)| name.as_str() != "text")
+        .map(|(name, value)| format!("{}: {}\n", name, value))
+        .collect()
+}
+
+/// Write a tiddler to `path` (without extension) in the given format.
+/// Returns the files written: the tiddler file, and the `.meta` file if any.
+pub fn write_tiddler(base: &Path, tiddler: &Tiddler, format: &TiddlerFormat) -> Result<Vec<PathBuf>, String> {
+    let with_extension = |extension: &str| PathBuf::from(format!("{}{}", base.to_string_lossy(), extension));
+    let write = |path: &Path, bytes: &[u8]| {
+        std::fs::write(path, bytes).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    };
+    let text = tiddler.get("text").map(String::as_str).unwrap_or("");
+    match format {
+        TiddlerFormat::Tid => {
+            let path = with_extension(".tid");
+            write(&path, format!("{}\n{}", tid_header(tiddler), text).as_bytes())?;
+            Ok(vec![path])
+        }
+        TiddlerFormat::Json => {
+            let path = with_extension(".json");
+            let json = serde_json::to_string_pretty(&[tiddler])
+                .map_err(|e| format!("Failed to serialize tiddler: {}", e))?;
+            write(&path, json.as_bytes())?;
+            Ok(vec![path])
+        }
+        TiddlerFormat::Native { extension } => {
+            let path = with_extension(extension);
+            let bytes = base64::engine::general_purpose::STANDARD.decode(text.trim())
+                .map_err(|e| format!("Invalid base64 text: {}", e))?;
+            write(&path, &bytes)?;
+            let meta_path = with_extension(&format!("{}.meta", extension));
+            write(&meta_path, tid_header(tiddler).as_bytes())?;
+            Ok(vec![path, meta_path])
+        }
+    }
+}
+
+/// The file name (without extension) TiddlyWiki gives a tiddler,
+/// e.g. `$:/config/Thing` → `$__config_Thing`
+pub fn title_to_filename(title: &str) -> String {
+    let mut name: String = title.chars()
+        .map(|c| if matches!(c, '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*' | '^') || c.is_control() { '_' } else { c })
+        .collect();
+    name = name.trim_matches(|c: char| c == '.' || c.is_whitespace()).to_string();
+    if name.chars().count() > 200 {
+        name = name.chars().take(200).collect();
+    }
+    if name.is_empty() {
+        name = "_".to_string();
+    }
+    // Reserved device names on Windows
+    let stem = name.split('.').next().unwrap_or("").to_uppercase();
+    if matches!(stem.as_str(), "CON" | "PRN" | "AUX" | "NUL" | "COM1" | "COM2" | "COM3" | "COM4" | "LPT1" | "LPT2" | "LPT3") {
+        name.insert(0, '_');
+    }
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trips_tiddler_files() {
+        let dir = std::env::temp_dir().join(format!("td-core-wiki-folder-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+
+        let mut note = Tiddler::new();
+        note.insert("title".to_string(), "$:/config/Note".to_string());
+        note.insert("tags".to_string(), "a [[b c]]".to_string());
+        note.insert("text".to_string(), "Line one\n\nLine two".to_string());
+        assert_eq!(tiddler_format(&note), TiddlerFormat::Tid);
+        write_tiddler(&dir.join(title_to_filename("$:/config/Note")), &note, &TiddlerFormat::Tid).unwrap();
+        assert!(dir.join("$__config_Note.tid").is_file());
+
+        let mut multiline = note.clone();
+        multiline.insert("title".to_string(), "Multi".to_string());
+        multiline.insert("caption".to_string(), "two\nlines".to_string());
+        assert_eq!(tiddler_format(&multiline), TiddlerFormat::Json);
+        write_tiddler(&dir.join("sub").join("Multi"), &multiline, &TiddlerFormat::Json).unwrap();
+
+        std::fs::write(dir.join("sub").join("lingo.multids"), "title: $:/language/\n\nHello: Hello there\nBye: Bye\n").unwrap();
+        std::fs::write(dir.join("module.js"), "/*\\\ntitle: $:/mod.js\ntype: application/javascript\nmodule-type: startup\n\\*/\nexports.x = 1;\n").unwrap();
+
+        let files = load_tiddlers_from_path(&dir);
+        let all: Vec<&Tiddler> = files.iter().flat_map(|f| &f.tiddlers).collect();
+        let find = |title: &str| all.iter().find(|t| t["title"] == title).copied();
+        assert_eq!(find("$:/config/Note"), Some(&note));
+        assert_eq!(find("Multi"), Some(&multiline));
+        assert_eq!(find("$:/language/Hello").unwrap()["text"], "Hello there");
+        assert_eq!(find("$:/mod.js").unwrap()["module-type"], "startup");
+        assert!(files.iter().find(|f| f.path.ends_with("lingo.multids")).is_some_and(|f| !f.single));
+        assert!(files.iter().find(|f| f.path.ends_with("Multi.json")).is_some_and(|f| f.single));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_loads_tiddlywiki_files_specifications() {
+        let dir = std::env::temp_dir().join(format!("td-core-wiki-files-{}", std::process::id()));
+        let media = dir.join("media");
+        std::fs::create_dir_all(media.join("2020")).unwrap();
+        std::fs::write(media.join("2020").join("a.png"), [0x89, b'P', b'N', b'G']).unwrap();
+        std::fs::write(media.join("notes.txt"), "Some notes").unwrap();
+        let spec = serde_json::json!({
+            "tiddlers": [{ "file": "media/notes.txt", "fields": { "title": "Notes" } }],
+            "directories": [{
+                "path": "media",
+                "filesRegExp": "\\.png$",
+                "searchSubdirectories": true,
+                "fields": {
+                    "title": { "source": "filepath", "prefix": "Media/" },
+                    "_canonical_uri": { "source": "filename", "prefix": "files/" },
+                    "text": ""
+                }
+            }]
+        });
+        std::fs::write(dir.join("tiddlywiki.files"), spec.to_string()).unwrap();
+
+        let files = load_tiddlers_from_path(&dir);
+        let _ = std::fs::remove_dir_all(&dir);
+        let all: Vec<&Tiddler> = files.iter().flat_map(|f| &f.tiddlers).collect();
+        let find = |title: &str| all.iter().find(|t| t["title"] == title).copied();
+        assert_eq!(find("Notes").unwrap()["text"], "Some notes");
+        let image = find("Media/2020/a.png").unwrap();
+        assert_eq!(image["type"], "image/png");
+        assert_eq!(image["_canonical_uri"], "files/a.png");
+        assert_eq!(image["text"], "");
+        assert!(files.iter().all(|f| !f.single));
+    }
+
+    #[test]
+    fn test_generates_tiddlywiki_file_names() {
+        assert_eq!(title_to_filename("$:/StoryList"), "$__StoryList");
+        assert_eq!(title_to_filename("What? A <b>tag</b>"), "What_ A _b_tag__b_");
+        assert_eq!(title_to_filename("..."), "_");
+        assert_eq!(title_to_filename("con"), "_con");
+    }
+}
@@ -0,0 +1,155 @@
+//! OS accessibility preferences (high contrast, reduced motion)
+//!
+//! Webviews don't reliably expose these through CSS media queries
+//! (WebKitGTK has no `prefers-contrast`), so they are detected natively and
+//! pushed into wiki windows by the init script:
+//! - `get_accessibility_preferences` for the initial state
+//! - `accessibility-preferences-changed` event when the OS setting changes
+
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use tauri::Emitter;
+
+/// How often the OS settings are re-checked
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+static WATCHER_STARTED: OnceLock<()> = OnceLock::new();
+
+/// Accessibility preferences relevant to wiki rendering
+#[derive(Clone, Copy, Debug, Default, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessibilityPreferences {
+    pub high_contrast: bool,
+    pub reduced_motion: bool,
+}
+
+/// Detect the current OS accessibility preferences
+pub fn detect() -> AccessibilityPreferences {
+    detect_impl()
+}
+
+#[cfg(target_os = "linux")]
+fn gsettings_get(schema: &str, key: &str) -> Option<String> {
+    let output = std::process::Command::new("gsettings")
+        .args(["get", schema, key])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().trim_matches('\'').to_string())
+}
+
+#[cfg(target_os = "linux")]
+fn detect_impl() -> AccessibilityPreferences {
+    let theme_is_high_contrast = |theme: &str| {
+        let t = theme.to_lowercase();
+        t.contains("highcontrast") || t.contains("high-contrast")
+    };
+
+    let high_contrast = gsettings_get("org.gnome.desktop.a11y.interface", "high-contrast")
+        .map(|v| v == "true")
+        .unwrap_or(false)
+        || gsettings_get("org.gnome.desktop.interface", "gtk-theme")
+            .map(|t| theme_is_high_contrast(&t))
+            .unwrap_or(false)
+        || std::env::var("GTK_THEME").map(|t| theme_is_high_contrast(&t)).unwrap_or(false);
+
+    let reduced_motion = gsettings_get("org.gnome.desktop.interface", "enable-animations")
+        .map(|v| v == "false")
+        .unwrap_or(false);
+
+    AccessibilityPreferences { high_contrast, reduced_motion }
+}
+
+#[cfg(target_os = "windows")]
+fn detect_impl() -> AccessibilityPreferences {
+    use windows_core::BOOL;
+    use windows::Win32::UI::Accessibility::{HCF_HIGHCONTRASTON, HIGHCONTRASTW};
+    use windows::Win32::UI::WindowsAndMessaging::{
+        SystemParametersInfoW, SPI_GETCLIENTAREAANIMATION, SPI_GETHIGHCONTRAST, SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS,
+    };
+
+    let mut high_contrast = false;
+    let mut reduced_motion = false;
+    unsafe {
+        let mut hc = HIGHCONTRASTW {
+            cbSize: std::mem::size_of::<HIGHCONTRASTW>() as u32,
+            ..Default::default()
+        };
+        if SystemParametersInfoW(
+            SPI_GETHIGHCONTRAST,
+            hc.cbSize,
+            Some(&mut hc as *mut _ as *mut std::ffi::c_void),
+            SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS(0),
+        ).is_ok() {
+            high_contrast = (hc.dwFlags.0 & HCF_HIGHCONTRASTON.0) != 0;
+        }
+
+        // "Show animations in Windows" (Settings > Accessibility > Visual effects)
+        let mut animations = BOOL(1);
+        if SystemParametersInfoW(
+            SPI_GETCLIENTAREAANIMATION,
+            0,
+            Some(&mut animations as *mut _ as *mut std::ffi::c_void),
+            SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS(0),
+        ).is_ok() {
+            reduced_motion = !animations.as_bool();
+        }
+    }
+
+    AccessibilityPreferences { high_contrast, reduced_motion }
+}
+
+#[cfg(target_os = "macos")]
+fn detect_impl() -> AccessibilityPreferences {
+    fn universal_access_flag(key: &str) -> bool {
+        std::process::Command::new("defaults")
+            .args(["read", "com.apple.universalaccess", key])
+            .output()
+            .map(|o| String::from_utf8_lossy(&o.stdout).trim() == "1")
+            .unwrap_or(false)
+    }
+
+    AccessibilityPreferences {
+        high_contrast: universal_access_flag("increaseContrast"),
+        reduced_motion: universal_access_flag("reduceMotion"),
+    }
+}
+
+#[cfg(target_os = "android")]
+fn detect_impl() -> AccessibilityPreferences {
+    AccessibilityPreferences::default()
+}
+
+/// Start polling the OS settings (once per process) and emit
+/// `accessibility-preferences-changed` to all windows when they change
+fn ensure_watcher(app: &tauri::AppHandle, initial: AccessibilityPreferences) {
+    if WATCHER_STARTED.set(()).is_err() {
+        return;
+    }
+    let app = app.clone();
+    std::thread::spawn(move || {
+        let mut last = initial;
+        loop {
+            std::thread::sleep(POLL_INTERVAL);
+            let current = detect();
+            if current != last {
+                eprintln!("[TiddlyDesktop] Accessibility preferences changed: {:?}", current);
+                let _ = app.emit("accessibility-preferences-changed", current);
+                last = current;
+            }
+        }
+    });
+}
+
+/// Get the OS accessibility preferences (high contrast, reduced motion)
+#[tauri::command]
+pub async fn get_accessibility_preferences(app: tauri::AppHandle) -> Result<AccessibilityPreferences, String> {
+    let prefs = tokio::task::spawn_blocking(detect)
+        .await
+        .map_err(|e| format!("Failed to spawn blocking task: {}", e))?;
+    ensure_watcher(&app, prefs);
+    Ok(prefs)
+}
@@ -0,0 +1,274 @@
+//! Allowed commands: local programs wikis may run by name
+//!
+//! Wikis can't run arbitrary programs. The user sets up named commands on the
+//! landing page (`allowed_commands.json` in the data dir): a program, its
+//! arguments and an optional working directory, optionally limited to one
+//! wiki. A wiki runs a command by name (`run_allowed_command`) and may only
+//! fill in the `{placeholder}` arguments the command declares; each value
+//! becomes exactly one argument and no shell is involved. A value starting
+//! with `-` can't start an argument unless the template has a `--` before
+//! it, so it isn't taken as an option. `{wikiPath}` is always the calling
+//! wiki.
+//!
+//! Output is streamed to the window line by line as `allowed-command-output`
+//! (`{ runId, stream, line }`), followed by `allowed-command-finished`
+//! (`{ runId, code }`).
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read};
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
+
+use serde::{Deserialize, Serialize};
+use tauri::Emitter;
+
+static NEXT_RUN_ID: AtomicU32 = AtomicU32::new(1);
+
+/// Running commands of this process by run id
+static RUNNING: LazyLock<Mutex<HashMap<u32, Arc<Mutex<Child>>>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AllowedCommand {
+    name: String,
+    program: String,
+    /// Arguments; `{name}` placeholders are filled in from the wiki's parameters
+    #[serde(default)]
+    args: Vec<String>,
+    #[serde(default)]
+    working_dir: Option<String>,
+    /// Only this wiki may run the command (None: any wiki)
+    #[serde(default)]
+    wiki_path: Option<String>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct OutputLine {
+    run_id: u32,
+    /// "stdout" or "stderr"
+    stream: &'static str,
+    line: String,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Finished {
+    run_id: u32,
+    /// Exit code (None when killed by a signal or stopped)
+    code: Option<i32>,
+}
+
+fn config_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(crate::get_data_dir(app)?.join("allowed_commands.json"))
+}
+
+fn load_commands(app: &tauri::AppHandle) -> Vec<AllowedCommand> {
+    config_path(app)
+        .ok()
+        .and_then(|p| std::fs::read_to_string(p).ok())
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+/// Fill in the `{name}` placeholders of an argument template. Every
+/// placeholder must have a value; unknown parameters are an error too, so a
+/// wiki can't pass anything the command doesn't expect, nor options where
+/// the command expects values (before a `--` argument).
+fn expand_args(template: &[String], params: &HashMap<String, String>) -> Result<Vec<String>, String> {
+    let mut used = Vec::new();
+    let mut args = Vec::with_capacity(template.len());
+    let mut after_options = false;
+    for arg in template {
+        let mut expanded = String::new();
+        let mut rest = arg.as_str();
+        while let Some(start) = rest.find('{') {
+            let Some(len) = rest[start + 1..].find('}') else {
+                break;
+            };
+            let name = &rest[start + 1..start + 1 + len];
+            if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+                // Not a placeholder (e.g. JSON in an argument)
+                expanded.push_str(&rest[..start + 1]);
+                rest = &rest[start + 1..];
+                continue;
+            }
+            let value = params
+                .get(name)
+                .ok_or_else(|| format!("Missing parameter \"{}\"", name))?;
+            expanded.push_str(&rest[..start]);
+            if expanded.is_empty() && value.starts_with('-') && !after_options {
+                return Err(format!("Parameter \"{}\" can't start with \"-\"", name));
+            }
+            expanded.push_str(value);
+            used.push(name.to_string());
+            rest = &rest[start + len + 2..];
+        }
+        expanded.push_str(rest);
+        after_options |= arg == "--";
+        args.push(expanded);
+    }
+    if let Some(unknown) = params.keys().find(|k| !used.contains(k)) {
+        return Err(format!("Unknown parameter \"{}\"", unknown));
+    }
+    Ok(args)
+}
+
+fn stream_lines(app: tauri::AppHandle, run_id: u32, stream: &'static str, reader: impl Read + Send + 'static) {
+    std::thread::spawn(move || {
+        for line in BufReader::new(reader).lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            let _ = app.emit("allowed-command-output", OutputLine { run_id, stream, line });
+        }
+    });
+}
+
+/// Run an allowed command by name for the wiki of this process, returning
+/// the run id
+#[cfg(not(target_os = "android"))]
+#[tauri::command]
+pub fn run_allowed_command(
+    app: tauri::AppHandle,
+    state: tauri::State<crate::WikiModeState>,
+    name: String,
+    params: Option<HashMap<String, String>>,
+) -> Result<u32, String> {
+    let wiki_path = state.wiki_path.to_string_lossy().into_owned();
+    let command = load_commands(&app)
+        .into_iter()
+        .find(|c| c.name == name)
+        .ok_or_else(|| format!("\"{}\" is not an allowed command", name))?;
+    if let Some(only) = &command.wiki_path {
+        if !crate::utils::paths_equal(only, &wiki_path) {
+            return Err(format!("\"{}\" is not allowed for this wiki", name));
+        }
+    }
+    let mut params = params.unwrap_or_default();
+    let uses_wiki_path = command.args.iter().any(|a| a.contains("{wikiPath}"));
+    if uses_wiki_path {
+        params.insert("wikiPath".to_string(), wiki_path);
+    }
+    let args = expand_args(&command.args, &params)?;
+
+    let mut cmd = Command::new(&command.program);
+    cmd.args(&args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    if let Some(dir) = command.working_dir.as_deref().filter(|d| !d.is_empty()) {
+        cmd.current_dir(dir);
+    }
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt;
+        cmd.creation_flags(crate::CREATE_NO_WINDOW);
+    }
+    let mut child = cmd.spawn().map_err(|e| format!("Failed to run \"{}\": {}", name, e))?;
+
+    let run_id = NEXT_RUN_ID.fetch_add(1, Ordering::Relaxed);
+    if let Some(stdout) = child.stdout.take() {
+        stream_lines(app.clone(), run_id, "stdout", stdout);
+    }
+    if let Some(stderr) = child.stderr.take() {
+        stream_lines(app.clone(), run_id, "stderr", stderr);
+    }
+    let child = Arc::new(Mutex::new(child));
+    RUNNING.lock().unwrap().insert(run_id, child.clone());
+
+    // Wait without holding the lock, so stop_allowed_command can kill it
+    std::thread::spawn(move || {
+        let code = loop {
+            match child.lock().unwrap().try_wait() {
+                Ok(Some(status)) => break status.code(),
+                Ok(None) => {}
+                Err(_) => break None,
+            }
+            std::thread::sleep(std::time::Duration::from_millis(100));
+        };
+        RUNNING.lock().unwrap().remove(&run_id);
+        let _ = app.emit("allowed-command-finished", Finished { run_id, code });
+    });
+    Ok(run_id)
+}
+
+/// Stop a running command
+#[tauri::command]
+pub fn stop_allowed_command(run_id: u32) -> Result<(), String> {
+    let child = RUNNING.lock().unwrap().get(&run_id).cloned();
+    match child {
+        Some(child) => child.lock().unwrap().kill().map_err(|e| format!("Failed to stop command: {}", e)),
+        None => Ok(()),
+    }
+}
+
+#[tauri::command]
+pub fn get_allowed_commands(app: tauri::AppHandle) -> Vec<AllowedCommand> {
+    load_commands(&app)
+}
+
+/// Replace the allowed commands (landing page)
+#[tauri::command]
+pub fn set_allowed_commands(app: tauri::AppHandle, commands: Vec<AllowedCommand>) -> Result<(), String> {
+    let mut names = Vec::new();
+    for command in &commands {
+        if command.name.trim().is_empty() || command.program.trim().is_empty() {
+            return Err("Allowed commands need a name and a program".to_string());
+        }
+        if names.contains(&command.name) {
+            return Err(format!("There is already a command named \"{}\"", command.name));
+        }
+        names.push(command.name.clone());
+    }
+    let json = serde_json::to_string_pretty(&commands).map_err(|e| e.to_string())?;
+    std::fs::write(config_path(&app)?, json).map_err(|e| format!("Failed to save allowed commands: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    fn params(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_expand_args() {
+        let template = strings(&["build", "--target={target}", "{file}"]);
+        assert_eq!(
+            expand_args(&template, &params(&[("target", "web"), ("file", "my notes; rm -rf ~")])),
+            Ok(strings(&["build", "--target=web", "my notes; rm -rf ~"]))
+        );
+        assert!(expand_args(&template, &params(&[("target", "web")])).is_err());
+        assert!(expand_args(&template, &params(&[("target", "web"), ("file", "x"), ("extra", "y")])).is_err());
+    }
+
+    #[test]
+    fn test_expand_args_without_placeholders() {
+        let template = strings(&["-e", "{ \"a\": 1 }", "{}", "{unclosed"]);
+        assert_eq!(expand_args(&template, &HashMap::new()), Ok(template.clone()));
+    }
+
+    #[test]
+    fn test_expand_args_rejects_options() {
+        let template = strings(&["--title={title}", "{file}"]);
+        assert!(expand_args(&template, &params(&[("title", "-x"), ("file", "--output=/etc/passwd")])).is_err());
+        assert_eq!(
+            expand_args(&template, &params(&[("title", "-x"), ("file", "notes-1")])),
+            Ok(strings(&["--title=-x", "notes-1"]))
+        );
+        let template = strings(&["--", "{file}"]);
+        assert_eq!(
+            expand_args(&template, &params(&[("file", "-notes")])),
+            Ok(strings(&["--", "-notes"]))
+        );
+        assert!(expand_args(&strings(&["{file}", "--"]), &params(&[("file", "-notes")])).is_err());
+    }
+}
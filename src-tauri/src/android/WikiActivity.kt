@@ -161,6 +161,18 @@ class WikiActivity : AppCompatActivity() {
         @JvmStatic
         external fun pdfCharCount(handle: Long, pageNum: Int): Int
 
+        /** Native method: Decode a QR code or barcode in a photo. Returns the text, "" if none, or "ERROR:...". */
+        @JvmStatic
+        external fun decodeQrImage(path: String): String
+
+        /** Native method: Current position for a wiki as JSON, "" if the user wasn't asked yet, or "ERROR:...". */
+        @JvmStatic
+        external fun currentPosition(dataDir: String, wikiPath: String): String
+
+        /** Native method: Record whether a wiki may use the location. */
+        @JvmStatic
+        external fun recordGeolocationPermission(dataDir: String, wikiPath: String, allowed: Boolean)
+
         /**
          * Check if a wiki is already open by scanning running tasks.
          * Returns the task ID if open, or -1 if not.
@@ -463,6 +475,14 @@ class WikiActivity : AppCompatActivity() {
     private lateinit var permissionRequestLauncher: ActivityResultLauncher<Array<String>>
     private lateinit var geolocationPermissionLauncher: ActivityResultLauncher<Array<String>>
 
+    // QR code scanning (photo from the camera app, decoded natively)
+    private lateinit var qrCameraPermissionLauncher: ActivityResultLauncher<String>
+    private lateinit var qrScanLauncher: ActivityResultLauncher<Uri>
+    private var qrScanFile: File? = null
+
+    // Position from the OS location services (geolocation.rs), asked once per wiki
+    private lateinit var positionPermissionLauncher: ActivityResultLauncher<Array<String>>
+
     // Export/save file support
     private lateinit var createDocumentLauncher: ActivityResultLauncher<Intent>
     private var pendingExportContent: ByteArray? = null
@@ -735,6 +755,109 @@ class WikiActivity : AppCompatActivity() {
         }
     }
 
+    /**
+     * JavaScript interface for scanning QR codes and barcodes.
+     * Takes a photo with the camera app; the result is passed to window.__tdQrScanResult.
+     */
+    inner class QrScanInterface {
+        @JavascriptInterface
+        fun scan() {
+            runOnUiThread {
+                if (ContextCompat.checkSelfPermission(this@WikiActivity, Manifest.permission.CAMERA) == PackageManager.PERMISSION_GRANTED) {
+                    launchQrScan()
+                } else {
+                    qrCameraPermissionLauncher.launch(Manifest.permission.CAMERA)
+                }
+            }
+        }
+    }
+
+    private fun launchQrScan() {
+        try {
+            val scanDir = File(cacheDir, "shared")
+            scanDir.mkdirs()
+            val file = File(scanDir, "qr-scan.jpg")
+            file.delete()
+            qrScanFile = file
+            val uri = FileProvider.getUriForFile(this, "${packageName}.fileprovider", file)
+            qrScanLauncher.launch(uri)
+        } catch (e: Exception) {
+            Log.e(TAG, "Failed to start QR scan: ${e.message}")
+            qrScanFile = null
+            deliverQrScanResult(JSONObject().put("error", e.message ?: "Camera not available"))
+        }
+    }
+
+    private fun deliverQrScanResult(result: JSONObject) {
+        runOnUiThread {
+            webView.evaluateJavascript("window.__tdQrScanResult && window.__tdQrScanResult($result)", null)
+        }
+    }
+
+    /**
+     * JavaScript interface for the position from the OS location services.
+     * The wiki is the one this activity shows, not one named by the page;
+     * the result is passed to window.__tdPositionResult.
+     */
+    inner class GeolocationInterface {
+        @JavascriptInterface
+        fun getCurrentPosition() {
+            runOnUiThread {
+                val permissions = arrayOf(Manifest.permission.ACCESS_FINE_LOCATION, Manifest.permission.ACCESS_COARSE_LOCATION)
+                if (permissions.any { ContextCompat.checkSelfPermission(this@WikiActivity, it) == PackageManager.PERMISSION_GRANTED }) {
+                    requestPosition()
+                } else {
+                    positionPermissionLauncher.launch(permissions)
+                }
+            }
+        }
+    }
+
+    private fun requestPosition() {
+        val path = wikiPath
+        val dataDir = applicationContext.filesDir.parentFile?.absolutePath
+        if (path.isNullOrEmpty() || dataDir == null) {
+            deliverPositionResult(JSONObject().put("error", "Location services are not available"))
+            return
+        }
+        // Location providers may take a moment
+        Thread {
+            val result = currentPosition(dataDir, path)
+            when {
+                result.startsWith("ERROR:") -> deliverPositionResult(JSONObject().put("error", result.removePrefix("ERROR:")))
+                result.isEmpty() -> runOnUiThread { askPositionPermission(dataDir, path) }
+                else -> deliverPositionResult(JSONObject().put("position", JSONObject(result)))
+            }
+        }.start()
+    }
+
+    /** First request of a wiki: ask, record the answer, then answer the request */
+    private fun askPositionPermission(dataDir: String, path: String) {
+        val record = { allowed: Boolean ->
+            Thread {
+                recordGeolocationPermission(dataDir, path, allowed)
+                if (allowed) {
+                    requestPosition()
+                } else {
+                    deliverPositionResult(JSONObject().put("error", "Location access was denied for this wiki"))
+                }
+            }.start()
+        }
+        AlertDialog.Builder(this)
+            .setTitle(getString(R.string.location_title))
+            .setMessage(getString(R.string.location_prompt, wikiTitle))
+            .setPositiveButton(getString(R.string.btn_allow)) { _, _ -> record(true) }
+            .setNegativeButton(getString(R.string.btn_deny)) { _, _ -> record(false) }
+            .setOnCancelListener { deliverPositionResult(JSONObject().put("error", "Location access was not allowed")) }
+            .show()
+    }
+
+    private fun deliverPositionResult(result: JSONObject) {
+        runOnUiThread {
+            webView.evaluateJavascript("window.__tdPositionResult && window.__tdPositionResult($result)", null)
+        }
+    }
+
     /**
      * JavaScript interface for opening URLs in external browser/apps.
      * Handles tm-open-external-window message from TiddlyWiki.
@@ -2929,6 +3052,50 @@ class WikiActivity : AppCompatActivity() {
             Log.d(TAG, "Geolocation permission ${if (granted) "granted" else "denied"} for $origin")
         }
 
+        // Register the launcher for the location permission of getCurrentPosition
+        positionPermissionLauncher = registerForActivityResult(
+            ActivityResultContracts.RequestMultiplePermissions()
+        ) { grants ->
+            if (grants.values.any { it }) {
+                requestPosition()
+            } else {
+                deliverPositionResult(JSONObject().put("error", "Location access for TiddlyDesktop is turned off"))
+            }
+        }
+
+        // Register the launchers for QR code scanning
+        qrCameraPermissionLauncher = registerForActivityResult(
+            ActivityResultContracts.RequestPermission()
+        ) { granted ->
+            if (granted) {
+                launchQrScan()
+            } else {
+                deliverQrScanResult(JSONObject().put("error", "Camera permission denied"))
+            }
+        }
+        qrScanLauncher = registerForActivityResult(
+            ActivityResultContracts.TakePicture()
+        ) { saved ->
+            val file = qrScanFile
+            qrScanFile = null
+            if (!saved || file == null) {
+                file?.delete()
+                deliverQrScanResult(JSONObject().put("cancelled", true))
+                return@registerForActivityResult
+            }
+            // Decoding a full-size photo takes a moment
+            Thread {
+                val decoded = decodeQrImage(file.absolutePath)
+                file.delete()
+                val result = when {
+                    decoded.startsWith("ERROR:") -> JSONObject().put("error", decoded.removePrefix("ERROR:"))
+                    decoded.isEmpty() -> JSONObject().put("error", "No QR code or barcode found in the photo")
+                    else -> JSONObject().put("text", decoded)
+                }
+                deliverQrScanResult(result)
+            }.start()
+        }
+
         // Register the create document launcher for export/save functionality
         createDocumentLauncher = registerForActivityResult(
             ActivityResultContracts.StartActivityForResult()
@@ -3403,6 +3570,12 @@ class WikiActivity : AppCompatActivity() {
             // Add JavaScript interface for printing
             addJavascriptInterface(PrintInterface(), "TiddlyDesktopPrint")
 
+            // Add JavaScript interface for scanning QR codes and barcodes
+            addJavascriptInterface(QrScanInterface(), "TiddlyDesktopQr")
+
+            // Add JavaScript interface for the position from the OS location services
+            addJavascriptInterface(GeolocationInterface(), "TiddlyDesktopGeolocation")
+
             // Add JavaScript interface for opening URLs in external browser/apps
             addJavascriptInterface(ExternalWindowInterface(), "TiddlyDesktopExternal")
 
@@ -4924,6 +5097,97 @@ class WikiActivity : AppCompatActivity() {
             })();
         """.trimIndent()
 
+        // Script for scanning QR codes and barcodes (TiddlyDesktop.scanQrCode() and
+        // tm-tiddlydesktop-rs-scan-qr-code, as on desktop)
+        val qrScanScript = """
+            (function() {
+                if (typeof ${'$'}tw === 'undefined' || !${'$'}tw.rootWidget) {
+                    setTimeout(arguments.callee, 100);
+                    return;
+                }
+                var TD = window.TiddlyDesktop = window.TiddlyDesktop || {};
+                var pending = null;
+                TD.scanQrCode = function() {
+                    if (pending) return pending.promise;
+                    pending = {};
+                    pending.promise = new Promise(function(resolve, reject) {
+                        pending.resolve = resolve;
+                        pending.reject = reject;
+                    });
+                    var promise = pending.promise;
+                    window.TiddlyDesktopQr.scan();
+                    return promise;
+                };
+                window.__tdQrScanResult = function(result) {
+                    var current = pending;
+                    pending = null;
+                    if (!current) return;
+                    if (result.error) current.reject(new Error(result.error));
+                    else current.resolve(result.cancelled ? null : result.text);
+                };
+                ${'$'}tw.rootWidget.addEventListener('tm-tiddlydesktop-rs-scan-qr-code', function(event) {
+                    var params = event.paramObject || {};
+                    var title = params.tiddler || '${'$'}:/temp/TiddlyDesktopRS/ScannedCode';
+                    var field = params.field || 'text';
+                    TD.scanQrCode().then(function(text) {
+                        if (text === null) return;
+                        var fields = { title: title };
+                        fields[field] = text;
+                        ${'$'}tw.wiki.addTiddler(new ${'$'}tw.Tiddler(${'$'}tw.wiki.getTiddler(title), fields, ${'$'}tw.wiki.getModificationFields()));
+                    }).catch(function(err) {
+                        console.error('[TiddlyDesktop] QR scan failed:', err);
+                        alert('QR scan failed: ' + err.message);
+                    });
+                    return false;
+                });
+            })();
+        """.trimIndent()
+
+        // Script for the position from the OS location services (TiddlyDesktop.getCurrentPosition()
+        // and tm-tiddlydesktop-rs-geotag, as on desktop; navigator.geolocation is the WebView's own)
+        val geolocationScript = """
+            (function() {
+                if (typeof ${'$'}tw === 'undefined' || !${'$'}tw.rootWidget) {
+                    setTimeout(arguments.callee, 100);
+                    return;
+                }
+                var TD = window.TiddlyDesktop = window.TiddlyDesktop || {};
+                var waiting = [];
+                TD.getCurrentPosition = function() {
+                    return new Promise(function(resolve, reject) {
+                        waiting.push({ resolve: resolve, reject: reject });
+                        if (waiting.length === 1) window.TiddlyDesktopGeolocation.getCurrentPosition();
+                    });
+                };
+                window.__tdPositionResult = function(result) {
+                    var answered = waiting;
+                    waiting = [];
+                    answered.forEach(function(request) {
+                        if (result.error) request.reject(new Error(result.error));
+                        else request.resolve(result.position);
+                    });
+                };
+                var NOTIFICATION_TITLE = '${'$'}:/temp/TiddlyDesktopRS/GeotagFailed';
+                ${'$'}tw.rootWidget.addEventListener('tm-tiddlydesktop-rs-geotag', function(event) {
+                    var title = event.param || event.tiddlerTitle;
+                    if (!title) return false;
+                    TD.getCurrentPosition().then(function(position) {
+                        var fields = {
+                            title: title,
+                            lat: String(position.latitude),
+                            long: String(position.longitude),
+                            alt: position.altitude === null ? undefined : String(position.altitude)
+                        };
+                        ${'$'}tw.wiki.addTiddler(new ${'$'}tw.Tiddler(${'$'}tw.wiki.getTiddler(title), fields, ${'$'}tw.wiki.getModificationFields()));
+                    }).catch(function(err) {
+                        ${'$'}tw.wiki.addTiddler(new ${'$'}tw.Tiddler({ title: NOTIFICATION_TITLE, text: 'Geotagging failed: ' + err.message }));
+                        ${'$'}tw.notifier.display(NOTIFICATION_TITLE);
+                    });
+                    return false;
+                });
+            })();
+        """.trimIndent()
+
         // Script to handle tm-open-external-window message (open URLs in external browser)
         val externalWindowScript = """
             (function() {
@@ -6996,6 +7260,10 @@ class WikiActivity : AppCompatActivity() {
                     view.evaluateJavascript(fullscreenScript, null)
                     // Inject the print handler
                     view.evaluateJavascript(printScript, null)
+                    // Inject the QR code scanner
+                    view.evaluateJavascript(qrScanScript, null)
+                    // Inject the position from the OS location services
+                    view.evaluateJavascript(geolocationScript, null)
                     // Inject the external window handler (open URLs in external browser)
                     view.evaluateJavascript(externalWindowScript, null)
                     // Inject the open window handler (open tiddler in new window)
@@ -0,0 +1,405 @@
+//! Export and import of the whole app configuration, to move a setup between
+//! machines or into and out of portable mode.
+//!
+//! The archive is a zip file with `tdconfig.json` (format version and the relay
+//! rooms), the config files of the data directory under `config/` and the user
+//! editions and plugins under `editions/` and `plugins/`. The wiki list and the
+//! per-wiki configs are stored with absolute paths; saving them on import makes
+//! them portable-relative again when the importing app runs in portable mode.
+//!
+//! Room passwords are only included in archives encrypted with a password
+//! (AES-256, like wiki archives and `.twsync` bundles).
+//!
+//! Importing merges: imported wikis and per-wiki configs replace those with the
+//! same path, other wikis are kept. The remaining config files, editions and
+//! plugins are replaced by the imported ones, and rooms this device isn't in
+//! yet are added. Device identity and encryption keys are never exported.
+
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use zip::write::{FileOptions, ZipWriter};
+use zip::{AesMode, CompressionMethod, ZipArchive};
+
+use crate::types::{WikiConfigs, WikiEntry};
+use crate::utils;
+use crate::wiki_archive::{add_file, collect_files, entry_name};
+
+const MANIFEST: &str = "tdconfig.json";
+const FORMAT_VERSION: u32 = 1;
+
+/// Largest config file read from an archive
+const MAX_CONFIG_SIZE: u64 = 64 * 1024 * 1024;
+
+const WIKI_LIST: &str = "config/wiki_list.json";
+const WIKI_CONFIGS: &str = "config/wiki_configs.json";
+
+/// Config files copied as they are
+const CONFIG_FILES: &[&str] = &[
+    "app_settings.json",
+    "share_templates.json",
+    "custom_snippets.json",
+    "quick_actions.json",
+    "webhooks.json",
+    "allowed_commands.json",
+];
+
+/// Folders of the data directory copied with their contents
+const DATA_FOLDERS: &[&str] = &["editions", "plugins"];
+
+/// A relay room in the archive
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ConfigRoom {
+    name: String,
+    room_code: String,
+    /// Only in encrypted archives
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    password: Option<String>,
+    #[serde(default)]
+    auto_connect: bool,
+}
+
+/// `tdconfig.json`
+#[derive(Debug, Serialize, Deserialize)]
+struct Manifest {
+    version: u32,
+    #[serde(default)]
+    rooms: Vec<ConfigRoom>,
+}
+
+/// A written configuration archive
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportedConfig {
+    pub path: String,
+    pub wikis: usize,
+    pub files: usize,
+    pub encrypted: bool,
+    /// Rooms with their passwords in the archive
+    pub rooms: usize,
+}
+
+/// What importing a configuration archive changed
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportedConfig {
+    pub wikis: usize,
+    pub files: usize,
+    pub rooms: usize,
+    /// Rooms that couldn't be added (no password in the archive, or sync isn't running)
+    pub rooms_missing: Vec<String>,
+}
+
+/// Where an archive entry goes below the data directory (None: not a file of
+/// the editions or plugins folders)
+fn data_entry_path(name: &Path) -> Option<PathBuf> {
+    let mut components = name.components();
+    let folder = components.next()?.as_os_str().to_str()?;
+    let relative = components.as_path();
+    (DATA_FOLDERS.contains(&folder) && !relative.as_os_str().is_empty()).then(|| name.to_path_buf())
+}
+
+/// Imported wikis replace those with the same path, the others are kept
+fn merge_wiki_list(existing: Vec<WikiEntry>, imported: Vec<WikiEntry>) -> Vec<WikiEntry> {
+    let mut merged: Vec<WikiEntry> = existing
+        .into_iter()
+        .filter(|entry| !imported.iter().any(|other| utils::paths_equal(&other.path, &entry.path)))
+        .collect();
+    merged.extend(imported);
+    merged
+}
+
+/// Imported per-wiki configs replace those of the same wiki (each kind of
+/// config is a map keyed by wiki path)
+fn merge_wiki_configs(existing: serde_json::Value, imported: serde_json::Value) -> serde_json::Value {
+    let mut merged = existing;
+    let (Some(target), serde_json::Value::Object(imported)) = (merged.as_object_mut(), imported) else {
+        return merged;
+    };
+    for (kind, configs) in imported {
+        match (target.get_mut(&kind).and_then(|v| v.as_object_mut()), configs) {
+            (Some(existing), serde_json::Value::Object(configs)) => existing.extend(configs),
+            (_, configs) => {
+                target.insert(kind, configs);
+            }
+        }
+    }
+    merged
+}
+
+fn write_archive(
+    data_dir: &Path,
+    manifest: &Manifest,
+    wiki_list: &[WikiEntry],
+    wiki_configs: &WikiConfigs,
+    target: &Path,
+    password: Option<&str>,
+) -> Result<usize, String> {
+    let mut options: FileOptions<()> = FileOptions::default()
+        .compression_method(CompressionMethod::Deflated)
+        .large_file(true);
+    if let Some(password) = password {
+        options = options.with_aes_encryption(AesMode::Aes256, password);
+    }
+
+    let mut entries: Vec<(String, Vec<u8>)> = vec![
+        (MANIFEST.to_string(), serde_json::to_vec(manifest).map_err(|e| e.to_string())?),
+        (WIKI_LIST.to_string(), serde_json::to_vec_pretty(wiki_list).map_err(|e| e.to_string())?),
+        (WIKI_CONFIGS.to_string(), serde_json::to_vec_pretty(wiki_configs).map_err(|e| e.to_string())?),
+    ];
+    for name in CONFIG_FILES {
+        if let Ok(content) = std::fs::read(data_dir.join(name)) {
+            entries.push((format!("config/{}", name), content));
+        }
+    }
+    let mut files = Vec::new();
+    for folder in DATA_FOLDERS {
+        collect_files(&data_dir.join(folder), &mut files);
+    }
+
+    let partial = target.with_extension("tdconfig.part");
+    let file = File::create(&partial).map_err(|e| format!("Failed to create {}: {}", partial.display(), e))?;
+    let mut zip = ZipWriter::new(file);
+    let result = entries
+        .iter()
+        .try_for_each(|(name, content)| {
+            zip.start_file(name.as_str(), options)
+                .map_err(|e| e.to_string())
+                .and_then(|_| zip.write_all(content).map_err(|e| e.to_string()))
+                .map_err(|e| format!("Failed to add {}: {}", name, e))
+        })
+        .and_then(|_| {
+            files.iter().try_for_each(|path| match entry_name(data_dir, "", path) {
+                Some(name) => add_file(&mut zip, &name, path, options),
+                None => Ok(()),
+            })
+        })
+        .and_then(|_| {
+            let mut file = zip.finish().map_err(|e| format!("Failed to write the archive: {}", e))?;
+            file.flush().map_err(|e| format!("Failed to write the archive: {}", e))
+        });
+    if let Err(e) = result {
+        let _ = std::fs::remove_file(&partial);
+        return Err(e);
+    }
+    std::fs::rename(&partial, target).map_err(|e| format!("Failed to save {}: {}", target.display(), e))?;
+    Ok(entries.len() - 1 + files.len())
+}
+
+/// Write the app configuration to a zip archive at `target`, AES-256 encrypted
+/// (with the room passwords) if a password is given
+#[tauri::command]
+pub async fn export_app_config(
+    app: tauri::AppHandle,
+    target: String,
+    password: Option<String>,
+) -> Result<ExportedConfig, String> {
+    let password = password.filter(|p| !p.is_empty());
+    let data_dir = crate::get_data_dir(&app)?;
+    let wiki_list = crate::wiki_storage::load_recent_files_from_disk(&app);
+    let wiki_configs = crate::wiki_storage::load_wiki_configs(&app)?;
+    let rooms: Vec<ConfigRoom> = match crate::lan_sync::relay_sync_get_status().await {
+        Ok(status) => status
+            .rooms
+            .into_iter()
+            .map(|room| ConfigRoom {
+                name: room.name,
+                room_code: room.room_code,
+                password: password.as_ref().map(|_| room.password),
+                auto_connect: room.auto_connect,
+            })
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    let manifest = Manifest { version: FORMAT_VERSION, rooms };
+
+    let wikis = wiki_list.len();
+    let encrypted = password.is_some();
+    let rooms = manifest.rooms.iter().filter(|r| r.password.is_some()).count();
+    let target_path = PathBuf::from(&target);
+    let files = tokio::task::spawn_blocking(move || {
+        write_archive(&data_dir, &manifest, &wiki_list, &wiki_configs, &target_path, password.as_deref())
+    })
+    .await
+    .map_err(|e| format!("Export failed: {}", e))??;
+    Ok(ExportedConfig { path: target, wikis, files, encrypted, rooms })
+}
+
+/// The contents of a configuration archive
+struct ArchiveContents {
+    manifest: Manifest,
+    wiki_list: Vec<WikiEntry>,
+    wiki_configs: serde_json::Value,
+    /// Name in the data directory → content
+    config_files: Vec<(String, Vec<u8>)>,
+}
+
+fn read_entry(archive: &mut ZipArchive<File>, index: usize, password: Option<&str>) -> Result<Vec<u8>, String> {
+    let entry = match password {
+        Some(password) => archive.by_index_decrypt(index, password.as_bytes()),
+        None => archive.by_index(index),
+    };
+    let mut content = Vec::new();
+    entry
+        .map_err(|e| format!("Failed to read the archive (wrong password?): {}", e))?
+        .take(MAX_CONFIG_SIZE)
+        .read_to_end(&mut content)
+        .map_err(|e| format!("Failed to read the archive (wrong password?): {}", e))?;
+    Ok(content)
+}
+
+/// Read the config files of the archive and extract its editions and plugins
+/// into the data directory
+fn read_archive(archive_path: &Path, data_dir: &Path, password: Option<&str>) -> Result<(ArchiveContents, usize), String> {
+    let file = File::open(archive_path).map_err(|e| format!("Failed to open {}: {}", archive_path.display(), e))?;
+    let mut archive = ZipArchive::new(file).map_err(|e| format!("Not a configuration archive: {}", e))?;
+
+    let index = archive.index_for_name(MANIFEST).ok_or("Not a configuration archive: tdconfig.json is missing")?;
+    let encrypted = archive.by_index_raw(index).map(|f| f.encrypted()).unwrap_or(false);
+    if encrypted && password.is_none() {
+        return Err("Password required: the archive is encrypted".to_string());
+    }
+    let manifest: Manifest = serde_json::from_slice(&read_entry(&mut archive, index, password)?)
+        .map_err(|e| format!("Invalid tdconfig.json: {}", e))?;
+    if manifest.version > FORMAT_VERSION {
+        return Err("The archive was made by a newer version of TiddlyDesktop".to_string());
+    }
+    let mut contents = ArchiveContents {
+        manifest,
+        wiki_list: Vec::new(),
+        wiki_configs: serde_json::Value::Null,
+        config_files: Vec::new(),
+    };
+
+    let mut extracted = 0;
+    for i in 0..archive.len() {
+        let Some(name) = archive.by_index_raw(i).ok().and_then(|f| f.enclosed_name()) else {
+            continue;
+        };
+        let name_str = name.to_string_lossy().replace('\\', "/");
+        if name_str == WIKI_LIST {
+            contents.wiki_list = serde_json::from_slice(&read_entry(&mut archive, i, password)?)
+                .map_err(|e| format!("Invalid wiki list: {}", e))?;
+        } else if name_str == WIKI_CONFIGS {
+            contents.wiki_configs = serde_json::from_slice(&read_entry(&mut archive, i, password)?)
+                .map_err(|e| format!("Invalid wiki configs: {}", e))?;
+        } else if let Some(file_name) = name_str.strip_prefix("config/").filter(|n| CONFIG_FILES.contains(n)) {
+            contents.config_files.push((file_name.to_string(), read_entry(&mut archive, i, password)?));
+        } else if let Some(relative) = data_entry_path(&name) {
+            let entry = match password {
+                Some(password) => archive.by_index_decrypt(i, password.as_bytes()),
+                None => archive.by_index(i),
+            };
+            let mut entry = entry.map_err(|e| format!("Failed to read the archive: {}", e))?;
+            if entry.is_dir() {
+                continue;
+            }
+            let dest = data_dir.join(&relative);
+            if let Some(parent) = dest.parent() {
+                std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+            }
+            let mut out = File::create(&dest).map_err(|e| format!("Failed to create {}: {}", dest.display(), e))?;
+            std::io::copy(&mut entry, &mut out).map_err(|e| format!("Failed to extract {}: {}", dest.display(), e))?;
+            extracted += 1;
+        }
+    }
+    Ok((contents, extracted))
+}
+
+/// Import a configuration archive written by `export_app_config` (see the
+/// module docs for what is merged and what is replaced). Settings other than
+/// the wiki list take effect after a restart.
+#[tauri::command]
+pub async fn import_app_config(
+    app: tauri::AppHandle,
+    archive_path: String,
+    password: Option<String>,
+) -> Result<ImportedConfig, String> {
+    let password = password.filter(|p| !p.is_empty());
+    let data_dir = crate::get_data_dir(&app)?;
+    let (contents, extracted) = {
+        let data_dir = data_dir.clone();
+        tokio::task::spawn_blocking(move || read_archive(Path::new(&archive_path), &data_dir, password.as_deref()))
+            .await
+            .map_err(|e| format!("Import failed: {}", e))??
+    };
+    let mut report = ImportedConfig { wikis: contents.wiki_list.len(), files: extracted, ..Default::default() };
+
+    let existing = crate::wiki_storage::load_recent_files_from_disk(&app);
+    crate::wiki_storage::save_recent_files_to_disk(&app, &merge_wiki_list(existing, contents.wiki_list))?;
+
+    if !contents.wiki_configs.is_null() {
+        let existing = crate::wiki_storage::load_wiki_configs(&app)?;
+        let existing = serde_json::to_value(existing).map_err(|e| e.to_string())?;
+        let merged: WikiConfigs = serde_json::from_value(merge_wiki_configs(existing, contents.wiki_configs))
+            .map_err(|e| format!("Invalid wiki configs: {}", e))?;
+        crate::wiki_storage::save_wiki_configs(&app, &merged)?;
+    }
+
+    for (name, content) in &contents.config_files {
+        tiddlydesktop_core::storage::atomic_write_with_backup(&data_dir.join(name), &String::from_utf8_lossy(content))?;
+        report.files += 1;
+    }
+
+    let known: Vec<String> = match crate::lan_sync::relay_sync_get_status().await {
+        Ok(status) => status.rooms.into_iter().map(|room| room.room_code).collect(),
+        Err(_) => Vec::new(),
+    };
+    for room in contents.manifest.rooms {
+        if known.contains(&room.room_code) {
+            continue;
+        }
+        let added = match room.password {
+            Some(password) => {
+                crate::lan_sync::relay_sync_add_room(room.name.clone(), room.room_code, password, room.auto_connect).await
+            }
+            None => Err("No password in the archive".to_string()),
+        };
+        match added {
+            Ok(()) => report.rooms += 1,
+            Err(e) => {
+                eprintln!("[AppConfig] Room {} not added: {}", room.name, e);
+                report.rooms_missing.push(room.name);
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_data_entry_path() {
+        assert_eq!(
+            data_entry_path(Path::new("editions/notes/tiddlywiki.info")),
+            Some(PathBuf::from("editions/notes/tiddlywiki.info"))
+        );
+        assert!(data_entry_path(Path::new("plugins/my/plugin.info")).is_some());
+        assert!(data_entry_path(Path::new("plugins")).is_none());
+        assert!(data_entry_path(Path::new("config/app_settings.json")).is_none());
+        assert!(data_entry_path(Path::new("device_identity.json")).is_none());
+    }
+
+    #[test]
+    fn test_merge_wiki_configs() {
+        let existing = serde_json::json!({
+            "window_states": { "/a.html": { "width": 800 }, "/b.html": { "width": 600 } },
+            "serial": { "/a.html": true }
+        });
+        let imported = serde_json::json!({
+            "window_states": { "/b.html": { "width": 1000 }, "/c.html": { "width": 400 } },
+            "geolocation": { "/c.html": false }
+        });
+        let merged = merge_wiki_configs(existing, imported);
+        assert_eq!(merged["window_states"]["/a.html"]["width"], 800);
+        assert_eq!(merged["window_states"]["/b.html"]["width"], 1000);
+        assert_eq!(merged["window_states"]["/c.html"]["width"], 400);
+        assert_eq!(merged["serial"]["/a.html"], true);
+        assert_eq!(merged["geolocation"]["/c.html"], false);
+    }
+}
@@ -0,0 +1,415 @@
+//! App-wide PIN/password lock (desktop)
+//!
+//! The PIN/password is stored as an argon2 hash in `app_lock.json` in the data
+//! dir. While locked, every process (main and wiki processes) hides its windows,
+//! so wiki content is not just covered but off screen, and the main process
+//! shows a small lock window. The lock state is a marker file in the data dir
+//! that each process polls, which also covers wiki processes started from
+//! desktop shortcuts.
+//!
+//! Only the main process unlocks: wiki processes run wiki code that could
+//! guess the PIN/password. Their own lock window (without the main process)
+//! asks to start TiddlyDesktop. Guesses are checked one at a time, and after
+//! `FREE_ATTEMPTS` wrong ones unlocking is refused for `LOCKOUT`, doubled with
+//! every further wrong one.
+//!
+//! The app locks:
+//! - At startup, when a PIN/password is set
+//! - After the configured inactivity period (activity is reported by the init script)
+//! - After resuming from sleep
+//! - On request (`lock_app_now`)
+
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use tauri::Manager;
+
+/// Label of the lock window
+pub const LOCK_WINDOW_LABEL: &str = "app-lock";
+
+/// How often the lock state is polled
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A wall-clock jump beyond the poll interval this large means the system slept
+const SLEEP_THRESHOLD: Duration = Duration::from_secs(30);
+
+/// Activity is written to disk at most this often
+const ACTIVITY_WRITE_INTERVAL: u64 = 15;
+
+/// Delay after a wrong PIN/password, to slow down guessing
+const FAILED_ATTEMPT_DELAY: Duration = Duration::from_secs(1);
+
+/// Wrong PINs/passwords before unlocking is refused for a while
+const FREE_ATTEMPTS: u32 = 5;
+
+/// First refusal after the free attempts, and the longest
+const LOCKOUT: Duration = Duration::from_secs(30);
+const MAX_LOCKOUT: Duration = Duration::from_secs(3600);
+
+static WATCHER_STARTED: OnceLock<()> = OnceLock::new();
+static LAST_ACTIVITY_WRITE: AtomicU64 = AtomicU64::new(0);
+
+/// Wrong PINs/passwords since the last right one; held while one is checked
+static ATTEMPTS: Mutex<Attempts> = Mutex::new(Attempts { failures: 0, refused_until: None });
+
+struct Attempts {
+    failures: u32,
+    refused_until: Option<Instant>,
+}
+
+/// Stored lock configuration
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
+struct LockConfig {
+    /// argon2 PHC string of the PIN/password
+    #[serde(default)]
+    hash: String,
+    /// Lock after this many minutes without activity (0 = never)
+    #[serde(default)]
+    auto_lock_minutes: u32,
+}
+
+/// Lock settings as shown in the landing page
+#[derive(Clone, Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppLockSettings {
+    pub enabled: bool,
+    pub auto_lock_minutes: u32,
+    pub locked: bool,
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn config_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(crate::get_data_dir(app)?.join("app_lock.json"))
+}
+
+fn locked_marker(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(crate::get_data_dir(app)?.join("app_lock.locked"))
+}
+
+fn activity_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(crate::get_data_dir(app)?.join("app_lock.activity"))
+}
+
+fn load_config(app: &tauri::AppHandle) -> LockConfig {
+    config_path(app)
+        .ok()
+        .and_then(|p| std::fs::read_to_string(p).ok())
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn save_config(app: &tauri::AppHandle, config: &LockConfig) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(config).map_err(|e| format!("Failed to serialize lock settings: {}", e))?;
+    std::fs::write(config_path(app)?, json).map_err(|e| format!("Failed to save lock settings: {}", e))
+}
+
+fn hash_secret(secret: &str) -> Result<String, String> {
+    use argon2::password_hash::{rand_core::OsRng, PasswordHasher, SaltString};
+
+    let salt = SaltString::generate(&mut OsRng);
+    argon2::Argon2::default()
+        .hash_password(secret.as_bytes(), &salt)
+        .map(|h| h.to_string())
+        .map_err(|e| format!("Failed to hash PIN/password: {}", e))
+}
+
+fn verify_secret(hash: &str, secret: &str) -> bool {
+    use argon2::password_hash::{PasswordHash, PasswordVerifier};
+
+    PasswordHash::new(hash)
+        .map(|parsed| argon2::Argon2::default().verify_password(secret.as_bytes(), &parsed).is_ok())
+        .unwrap_or(false)
+}
+
+/// How long unlocking is refused after `failures` wrong PINs/passwords in a row
+fn lockout(failures: u32) -> Option<Duration> {
+    let doublings = failures.checked_sub(FREE_ATTEMPTS)?;
+    Some(LOCKOUT.saturating_mul(2u32.saturating_pow(doublings)).min(MAX_LOCKOUT))
+}
+
+/// Check a PIN/password against the stored hash, one at a time across calls
+/// and refused while locked out (blocking: argon2 and the delay)
+fn check_secret(hash: &str, secret: &str) -> Result<(), String> {
+    let mut attempts = ATTEMPTS.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(until) = attempts.refused_until {
+        let remaining = until.saturating_duration_since(Instant::now());
+        if !remaining.is_zero() {
+            return Err(format!("Too many wrong attempts, try again in {} seconds", remaining.as_secs().max(1)));
+        }
+    }
+    if hash.is_empty() || verify_secret(hash, secret) {
+        *attempts = Attempts { failures: 0, refused_until: None };
+        return Ok(());
+    }
+    attempts.failures += 1;
+    attempts.refused_until = lockout(attempts.failures).map(|duration| Instant::now() + duration);
+    eprintln!("[TiddlyDesktop] Wrong PIN or password ({} in a row)", attempts.failures);
+    std::thread::sleep(FAILED_ATTEMPT_DELAY);
+    Err("Wrong PIN or password".to_string())
+}
+
+/// Whether the wall clock advanced much further than the poll interval,
+/// i.e. the system was suspended in between
+fn resumed_from_sleep(previous: SystemTime, now: SystemTime) -> bool {
+    now.duration_since(previous)
+        .map(|elapsed| elapsed > POLL_INTERVAL + SLEEP_THRESHOLD)
+        .unwrap_or(false)
+}
+
+/// Whether the app has been idle for longer than the auto-lock period
+fn is_idle(last_activity: u64, now: u64, auto_lock_minutes: u32) -> bool {
+    auto_lock_minutes > 0 && now.saturating_sub(last_activity) >= auto_lock_minutes as u64 * 60
+}
+
+fn is_locked(app: &tauri::AppHandle) -> bool {
+    locked_marker(app).map(|p| p.exists()).unwrap_or(false)
+}
+
+fn last_activity(app: &tauri::AppHandle) -> u64 {
+    activity_path(app)
+        .ok()
+        .and_then(|p| std::fs::read_to_string(p).ok())
+        .and_then(|s| s.trim().parse().ok())
+        .unwrap_or(0)
+}
+
+fn write_activity(app: &tauri::AppHandle) {
+    let now = now_secs();
+    LAST_ACTIVITY_WRITE.store(now, Ordering::Relaxed);
+    if let Ok(path) = activity_path(app) {
+        let _ = std::fs::write(path, now.to_string());
+    }
+}
+
+/// Lock the app (no-op when no PIN/password is set)
+fn lock(app: &tauri::AppHandle) {
+    if load_config(app).hash.is_empty() || is_locked(app) {
+        return;
+    }
+    if let Ok(marker) = locked_marker(app) {
+        match std::fs::write(&marker, now_secs().to_string()) {
+            Ok(()) => eprintln!("[TiddlyDesktop] App locked"),
+            Err(e) => eprintln!("[TiddlyDesktop] Failed to lock app: {}", e),
+        }
+    }
+}
+
+/// Show the lock window; `can_unlock` is set in the main process, the others
+/// ask to start it
+fn show_lock_window(app: &tauri::AppHandle, can_unlock: bool) {
+    if let Some(window) = app.get_webview_window(LOCK_WINDOW_LABEL) {
+        let _ = window.show();
+        let _ = window.set_focus();
+        return;
+    }
+    let page = if can_unlock { "lock.html" } else { "lock.html?elsewhere" };
+    let built = tauri::WebviewWindowBuilder::new(app, LOCK_WINDOW_LABEL, tauri::WebviewUrl::App(page.into()))
+        .title("TiddlyDesktop")
+        .inner_size(360.0, 240.0)
+        .resizable(false)
+        .center()
+        .always_on_top(true)
+        .focused(true)
+        .build();
+    if let Err(e) = built {
+        eprintln!("[TiddlyDesktop] Failed to open lock window: {}", e);
+    }
+}
+
+/// Start enforcing the lock in this process (once per process).
+/// `owns_lock_window` is set for the main process and for wiki processes
+/// running without it; these also show the lock window and detect inactivity
+/// and sleep. `can_unlock` is set for the main process only.
+pub fn start(app: &tauri::AppHandle, owns_lock_window: bool, can_unlock: bool) {
+    if WATCHER_STARTED.set(()).is_err() {
+        return;
+    }
+    if owns_lock_window {
+        lock(app);
+    }
+    let app = app.clone();
+    std::thread::spawn(move || {
+        let mut hidden: HashSet<String> = HashSet::new();
+        let mut last_tick = SystemTime::now();
+        let mut first = true;
+        loop {
+            // First pass runs right away so a locked app never shows its windows
+            if !first {
+                std::thread::sleep(POLL_INTERVAL);
+            }
+            first = false;
+            let now = SystemTime::now();
+            if owns_lock_window {
+                let config = load_config(&app);
+                let idle = is_idle(last_activity(&app).max(LAST_ACTIVITY_WRITE.load(Ordering::Relaxed)), now_secs(), config.auto_lock_minutes);
+                if !config.hash.is_empty() && (idle || resumed_from_sleep(last_tick, now)) {
+                    lock(&app);
+                }
+            }
+            last_tick = now;
+
+            let locked = is_locked(&app);
+            if !locked && hidden.is_empty() && app.get_webview_window(LOCK_WINDOW_LABEL).is_none() {
+                continue;
+            }
+            let handle = app.clone();
+            let to_restore: Vec<String> = if locked { Vec::new() } else { hidden.drain().collect() };
+            // Hide everything that became visible since the last tick (new windows, tray "show")
+            if locked {
+                for (label, window) in app.webview_windows() {
+                    if label != LOCK_WINDOW_LABEL && window.is_visible().unwrap_or(false) {
+                        hidden.insert(label);
+                    }
+                }
+            }
+            let to_hide: Vec<String> = if locked { hidden.iter().cloned().collect() } else { Vec::new() };
+            let _ = app.run_on_main_thread(move || {
+                for label in &to_hide {
+                    if let Some(window) = handle.get_webview_window(label) {
+                        let _ = window.hide();
+                    }
+                }
+                for label in &to_restore {
+                    if let Some(window) = handle.get_webview_window(label) {
+                        let _ = window.show();
+                    }
+                }
+                if locked && owns_lock_window {
+                    show_lock_window(&handle, can_unlock);
+                } else if !locked {
+                    if let Some(window) = handle.get_webview_window(LOCK_WINDOW_LABEL) {
+                        let _ = window.close();
+                    }
+                }
+            });
+        }
+    });
+}
+
+/// Report user activity (called by the init script, throttled there and here)
+#[tauri::command]
+pub fn app_lock_activity(app: tauri::AppHandle) {
+    let now = now_secs();
+    if now.saturating_sub(LAST_ACTIVITY_WRITE.load(Ordering::Relaxed)) >= ACTIVITY_WRITE_INTERVAL && !is_locked(&app) {
+        write_activity(&app);
+    }
+}
+
+/// Unlock the app with the PIN/password (main process only)
+#[tauri::command]
+pub async fn unlock_app(app: tauri::AppHandle, secret: String) -> Result<(), String> {
+    let config = load_config(&app);
+    tokio::task::spawn_blocking(move || check_secret(&config.hash, &secret))
+        .await
+        .map_err(|e| format!("Failed to spawn blocking task: {}", e))??;
+    // Fresh activity first, so the auto-lock doesn't fire again right away
+    write_activity(&app);
+    match std::fs::remove_file(locked_marker(&app)?) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(format!("Failed to unlock: {}", e)),
+    }
+    eprintln!("[TiddlyDesktop] App unlocked");
+    Ok(())
+}
+
+/// Get the lock settings
+#[tauri::command]
+pub fn get_app_lock_settings(app: tauri::AppHandle) -> AppLockSettings {
+    let config = load_config(&app);
+    AppLockSettings {
+        enabled: !config.hash.is_empty(),
+        auto_lock_minutes: config.auto_lock_minutes,
+        locked: is_locked(&app),
+    }
+}
+
+/// Set, change or remove (empty `new_secret`) the PIN/password.
+/// Changing or removing an existing one requires `current_secret`.
+#[tauri::command]
+pub async fn set_app_lock_secret(app: tauri::AppHandle, current_secret: Option<String>, new_secret: String) -> Result<AppLockSettings, String> {
+    let mut config = load_config(&app);
+    let new_hash = tokio::task::spawn_blocking(move || -> Result<Option<String>, String> {
+        check_secret(&config.hash, current_secret.as_deref().unwrap_or(""))?;
+        if new_secret.is_empty() {
+            Ok(None)
+        } else if new_secret.chars().count() < 4 {
+            Err("The PIN/password must have at least 4 characters".to_string())
+        } else {
+            hash_secret(&new_secret).map(Some)
+        }
+    })
+    .await
+    .map_err(|e| format!("Failed to spawn blocking task: {}", e))??;
+
+    config.hash = new_hash.unwrap_or_default();
+    save_config(&app, &config)?;
+    write_activity(&app);
+    Ok(get_app_lock_settings(app))
+}
+
+/// Set the inactivity period after which the app locks (0 = never)
+#[tauri::command]
+pub fn set_app_lock_timeout(app: tauri::AppHandle, minutes: u32) -> Result<AppLockSettings, String> {
+    let mut config = load_config(&app);
+    config.auto_lock_minutes = minutes;
+    save_config(&app, &config)?;
+    write_activity(&app);
+    Ok(get_app_lock_settings(app))
+}
+
+/// Lock the app now
+#[tauri::command]
+pub fn lock_app_now(app: tauri::AppHandle) -> Result<(), String> {
+    if load_config(&app).hash.is_empty() {
+        return Err("Set a PIN or password first".to_string());
+    }
+    lock(&app);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_secret() {
+        let hash = hash_secret("1234").unwrap();
+        assert!(hash.starts_with("$argon2"));
+        assert!(verify_secret(&hash, "1234"));
+        assert!(!verify_secret(&hash, "4321"));
+        assert!(!verify_secret("not a hash", "1234"));
+    }
+
+    #[test]
+    fn test_lockout() {
+        assert_eq!(lockout(0), None);
+        assert_eq!(lockout(FREE_ATTEMPTS - 1), None);
+        assert_eq!(lockout(FREE_ATTEMPTS), Some(LOCKOUT));
+        assert_eq!(lockout(FREE_ATTEMPTS + 1), Some(LOCKOUT * 2));
+        assert_eq!(lockout(FREE_ATTEMPTS + 3), Some(LOCKOUT * 8));
+        assert_eq!(lockout(FREE_ATTEMPTS + 40), Some(MAX_LOCKOUT));
+    }
+
+    #[test]
+    fn test_is_idle_and_resumed_from_sleep() {
+        assert!(!is_idle(1000, 1000 + 4 * 60, 5));
+        assert!(is_idle(1000, 1000 + 5 * 60, 5));
+        assert!(!is_idle(0, 1_000_000, 0));
+
+        let start = UNIX_EPOCH + Duration::from_secs(1_000_000);
+        assert!(!resumed_from_sleep(start, start + Duration::from_secs(2)));
+        assert!(resumed_from_sleep(start, start + Duration::from_secs(600)));
+        // Clock set backwards is not a resume
+        assert!(!resumed_from_sleep(start, start - Duration::from_secs(600)));
+    }
+}
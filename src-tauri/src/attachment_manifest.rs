@@ -0,0 +1,291 @@
+//! Integrity manifest of external attachments
+//!
+//! For each wiki, the SHA-256 hash, size and modification time of every file
+//! its `_canonical_uri` tiddlers point to are recorded in
+//! `<data_dir>/attachment_manifests/<md5 of wiki path>.json`. Verifying the
+//! attachments compares the files with the manifest:
+//! - modified: the content changed along with the modification time
+//! - corrupted: the content changed but size and modification time didn't
+//!   (bitrot, typically on external drives)
+//! - missing: the file is gone
+//! - new: not in the manifest yet (recorded by the verification)
+//!
+//! Changed files stay flagged until the current state is accepted.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tiddlydesktop_core::{filter, tiddler_store};
+
+/// Hash, size and modification time of an attachment file
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub sha256: String,
+    pub size: u64,
+    /// Unix time (seconds)
+    pub modified: Option<u64>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Manifest {
+    /// Entries by `_canonical_uri`
+    #[serde(default)]
+    files: BTreeMap<String, ManifestEntry>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AttachmentStatus {
+    Ok,
+    New,
+    Modified,
+    Corrupted,
+    Missing,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AttachmentCheck {
+    pub uri: String,
+    pub path: String,
+    pub status: AttachmentStatus,
+}
+
+/// Result of verifying the attachments of a wiki
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AttachmentReport {
+    pub ok: usize,
+    pub new: usize,
+    pub modified: usize,
+    pub corrupted: usize,
+    pub missing: usize,
+    /// Every attachment is there with the recorded content
+    pub complete: bool,
+    /// Attachments that aren't ok
+    pub problems: Vec<AttachmentCheck>,
+}
+
+/// Compare the recorded and current state of an attachment
+fn classify(recorded: Option<&ManifestEntry>, current: Option<&ManifestEntry>) -> AttachmentStatus {
+    match (recorded, current) {
+        (_, None) => AttachmentStatus::Missing,
+        (None, Some(_)) => AttachmentStatus::New,
+        (Some(recorded), Some(current)) if recorded.sha256 == current.sha256 => AttachmentStatus::Ok,
+        (Some(recorded), Some(current)) if recorded.size == current.size && recorded.modified == current.modified => {
+            AttachmentStatus::Corrupted
+        }
+        _ => AttachmentStatus::Modified,
+    }
+}
+
+/// Whether a `_canonical_uri` points to a local file
+pub(crate) fn is_local_uri(uri: &str) -> bool {
+    let lower = uri.to_ascii_lowercase();
+    !uri.is_empty()
+        && !["http:", "https:", "data:", "blob:", "tdasset:", "file:"]
+            .iter()
+            .any(|scheme| lower.starts_with(scheme))
+}
+
+/// The file a local `_canonical_uri` points to. Relative URIs are relative to
+/// the directory of a single-file wiki, or to a folder wiki itself.
+pub(crate) fn resolve_uri(wiki_path: &Path, uri: &str) -> PathBuf {
+    let decoded = urlencoding::decode(uri).map(|d| d.into_owned()).unwrap_or_else(|_| uri.to_string());
+    let path = Path::new(&decoded);
+    if path.is_absolute() {
+        return path.to_path_buf();
+    }
+    let base = if wiki_path.is_dir() { wiki_path } else { wiki_path.parent().unwrap_or(wiki_path) };
+    base.join(decoded.strip_prefix("./").unwrap_or(&decoded))
+}
+
+/// `.tid` and `.meta` files of a folder wiki
+fn collect_tiddler_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for path in entries.flatten().map(|e| e.path()) {
+        if path.is_dir() {
+            collect_tiddler_files(&path, files);
+        } else if path.extension().is_some_and(|e| e == "tid" || e == "meta") {
+            files.push(path);
+        }
+    }
+}
+
+/// Local `_canonical_uri`s of a wiki's tiddlers
+pub(crate) fn canonical_uris(wiki_path: &Path) -> Result<BTreeSet<String>, String> {
+    let mut uris = BTreeSet::new();
+    if wiki_path.is_dir() {
+        let mut files = Vec::new();
+        collect_tiddler_files(&wiki_path.join("tiddlers"), &mut files);
+        for file in files {
+            let Ok(content) = std::fs::read_to_string(&file) else {
+                continue;
+            };
+            if let Some(uri) = filter::parse_tid(&content).get("_canonical_uri") {
+                uris.insert(uri.to_string());
+            }
+        }
+    } else {
+        for tiddler in tiddler_store::open(wiki_path)? {
+            if let Some(uri) = tiddler?.get("_canonical_uri").and_then(|u| u.as_str()) {
+                uris.insert(uri.to_string());
+            }
+        }
+    }
+    uris.retain(|uri| is_local_uri(uri));
+    Ok(uris)
+}
+
+/// Current hash, size and modification time of a file (None if it's missing)
+fn hash_file(path: &Path) -> Option<ManifestEntry> {
+    let metadata = std::fs::metadata(path).ok().filter(|m| m.is_file())?;
+    let mut file = std::fs::File::open(path).ok()?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 256 * 1024];
+    loop {
+        let n = file.read(&mut buf).ok()?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Some(ManifestEntry {
+        sha256: hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect(),
+        size: metadata.len(),
+        modified: metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_secs()),
+    })
+}
+
+fn manifest_path(app: &tauri::AppHandle, wiki_path: &str) -> Result<PathBuf, String> {
+    Ok(crate::get_data_dir(app)?
+        .join("attachment_manifests")
+        .join(format!("{:x}.json", md5::compute(wiki_path.as_bytes()))))
+}
+
+fn load_manifest(path: &Path) -> Manifest {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_manifest(path: &Path, manifest: &Manifest) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    let json = serde_json::to_string_pretty(manifest).map_err(|e| format!("Failed to serialize manifest: {}", e))?;
+    std::fs::write(path, json).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Verify the attachments of a wiki against its manifest. New attachments are
+/// recorded; with `accept`, changed and missing ones are updated too.
+pub fn verify(app: &tauri::AppHandle, wiki_path: &str, accept: bool) -> Result<AttachmentReport, String> {
+    let wiki = Path::new(wiki_path);
+    let path = manifest_path(app, wiki_path)?;
+    let mut manifest = load_manifest(&path);
+    let mut report = AttachmentReport::default();
+    let mut files = BTreeMap::new();
+
+    for uri in canonical_uris(wiki)? {
+        let file = resolve_uri(wiki, &uri);
+        let current = hash_file(&file);
+        let recorded = manifest.files.get(&uri);
+        let status = classify(recorded, current.as_ref());
+        match status {
+            AttachmentStatus::Ok => report.ok += 1,
+            AttachmentStatus::New => report.new += 1,
+            AttachmentStatus::Modified => report.modified += 1,
+            AttachmentStatus::Corrupted => report.corrupted += 1,
+            AttachmentStatus::Missing => report.missing += 1,
+        }
+        // Flagged files keep their recorded state until it's accepted
+        let flagged = matches!(status, AttachmentStatus::Modified | AttachmentStatus::Corrupted | AttachmentStatus::Missing);
+        if let Some(entry) = if flagged && !accept { recorded.cloned() } else { current } {
+            files.insert(uri.clone(), entry);
+        }
+        if status != AttachmentStatus::Ok {
+            report.problems.push(AttachmentCheck {
+                uri,
+                path: file.to_string_lossy().into_owned(),
+                status,
+            });
+        }
+    }
+
+    // Attachments no longer referenced by the wiki are dropped
+    manifest.files = files;
+    save_manifest(&path, &manifest)?;
+    report.complete = report.modified + report.corrupted + report.missing == 0;
+    Ok(report)
+}
+
+/// Remove the manifest of a wiki that was removed from the list
+pub fn remove_manifest(app: &tauri::AppHandle, wiki_path: &str) {
+    if let Ok(path) = manifest_path(app, wiki_path) {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+/// Keep the manifest of a relocated wiki
+pub fn move_manifest(app: &tauri::AppHandle, old_path: &str, new_path: &str) {
+    if let (Ok(old), Ok(new)) = (manifest_path(app, old_path), manifest_path(app, new_path)) {
+        if old.exists() {
+            let _ = std::fs::rename(old, new);
+        }
+    }
+}
+
+/// Check the attachments of a wiki for modified, corrupted and missing files
+#[tauri::command]
+pub async fn verify_attachments(app: tauri::AppHandle, wiki_path: String) -> Result<AttachmentReport, String> {
+    tokio::task::spawn_blocking(move || verify(&app, &wiki_path, false))
+        .await
+        .map_err(|e| format!("Verification failed: {}", e))?
+}
+
+/// Record the current state of a wiki's attachments as the reference
+#[tauri::command]
+pub async fn accept_attachment_changes(app: tauri::AppHandle, wiki_path: String) -> Result<AttachmentReport, String> {
+    tokio::task::spawn_blocking(move || verify(&app, &wiki_path, true))
+        .await
+        .map_err(|e| format!("Verification failed: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(sha256: &str, size: u64, modified: u64) -> ManifestEntry {
+        ManifestEntry { sha256: sha256.to_string(), size, modified: Some(modified) }
+    }
+
+    #[test]
+    fn test_classify() {
+        let recorded = entry("aa", 10, 100);
+        assert_eq!(classify(Some(&recorded), Some(&entry("aa", 10, 200))), AttachmentStatus::Ok);
+        assert_eq!(classify(Some(&recorded), Some(&entry("bb", 10, 100))), AttachmentStatus::Corrupted);
+        assert_eq!(classify(Some(&recorded), Some(&entry("bb", 12, 200))), AttachmentStatus::Modified);
+        assert_eq!(classify(Some(&recorded), None), AttachmentStatus::Missing);
+        assert_eq!(classify(None, Some(&recorded)), AttachmentStatus::New);
+    }
+
+    #[test]
+    fn test_resolve_uri() {
+        assert!(is_local_uri("./files/a.png"));
+        assert!(!is_local_uri("https://example.com/a.png"));
+        let wiki = Path::new("/home/me/wikis/notes.html");
+        assert_eq!(resolve_uri(wiki, "./files/my%20photo.jpg"), Path::new("/home/me/wikis/files/my photo.jpg"));
+        assert_eq!(resolve_uri(wiki, "/media/usb/a.pdf"), Path::new("/media/usb/a.pdf"));
+    }
+}
@@ -0,0 +1,110 @@
+//! Moving embedded attachments out of a wiki, and small external ones back in
+//!
+//! The wiki window does the tiddler side (attachment_migration.js): it finds
+//! base64 tiddlers above a size threshold, saves their content to the wiki's
+//! `files` folder and replaces the text with a `_canonical_uri`, or inlines
+//! local `_canonical_uri` files below a threshold. These commands write, measure
+//! and read the attachment files. Inlined files are left on disk.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+use crate::attachment_manifest::{is_local_uri, resolve_uri};
+
+/// Largest attachment that is inlined (larger files make the wiki slow to load)
+const MAX_INLINE_SIZE: u64 = 10 * 1024 * 1024;
+
+/// File name for the content of a tiddler: the title without characters that
+/// aren't allowed in file names, with the extension of its content type
+fn attachment_filename(title: &str, extension: &str) -> String {
+    let mut name: String = title
+        .chars()
+        .map(|c| if c.is_control() || "/\\:*?\"<>|".contains(c) { '_' } else { c })
+        .collect();
+    name = name.trim().trim_matches('.').chars().take(100).collect();
+    if name.is_empty() {
+        name = "attachment".to_string();
+    }
+    let extension = extension.trim_start_matches('.');
+    if !extension.is_empty() && !name.to_lowercase().ends_with(&format!(".{}", extension.to_lowercase())) {
+        name = format!("{}.{}", name, extension);
+    }
+    name
+}
+
+/// Save the decoded content of an embedded tiddler to the wiki's `files`
+/// folder, under a name that doesn't overwrite an existing file. Returns the
+/// path of the saved file.
+#[tauri::command]
+pub async fn save_embedded_attachment(
+    wiki_path: String,
+    title: String,
+    extension: String,
+    data_base64: String,
+) -> Result<String, String> {
+    let data = STANDARD
+        .decode(data_base64.trim())
+        .map_err(|e| format!("Invalid base64 content of {}: {}", title, e))?;
+    let dir = crate::image_import::attachments_dir(&wiki_path);
+    tokio::fs::create_dir_all(&dir)
+        .await
+        .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let target = crate::downloads::unique_path(&dir, &attachment_filename(&title, &extension));
+    tokio::fs::write(&target, data)
+        .await
+        .map_err(|e| format!("Failed to write {}: {}", target.display(), e))?;
+    Ok(target.to_string_lossy().into_owned())
+}
+
+/// Sizes of the files local `_canonical_uri`s point to (None if missing or remote)
+#[tauri::command]
+pub fn get_attachment_sizes(wiki_path: String, uris: Vec<String>) -> HashMap<String, Option<u64>> {
+    let wiki = Path::new(&wiki_path);
+    uris.into_iter()
+        .map(|uri| {
+            let size = is_local_uri(&uri)
+                .then(|| std::fs::metadata(resolve_uri(wiki, &uri)).ok())
+                .flatten()
+                .filter(|m| m.is_file())
+                .map(|m| m.len());
+            (uri, size)
+        })
+        .collect()
+}
+
+/// Base64 content of the file a local `_canonical_uri` points to, for inlining
+#[tauri::command]
+pub async fn read_external_attachment(wiki_path: String, uri: String) -> Result<String, String> {
+    if !is_local_uri(&uri) {
+        return Err(format!("{} is not a local file", uri));
+    }
+    let path = resolve_uri(Path::new(&wiki_path), &uri);
+    let path = crate::drag_drop::sanitize::validate_user_file_path(&path.to_string_lossy())?;
+    let size = tokio::fs::metadata(&path)
+        .await
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?
+        .len();
+    if size > MAX_INLINE_SIZE {
+        return Err(format!("{} is too large to embed", path.display()));
+    }
+    let data = tokio::fs::read(&path)
+        .await
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    Ok(STANDARD.encode(data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_attachment_filename() {
+        assert_eq!(attachment_filename("Holiday photo", "jpg"), "Holiday photo.jpg");
+        assert_eq!(attachment_filename("scan.PNG", ".png"), "scan.PNG");
+        assert_eq!(attachment_filename("a/b: c?", "pdf"), "a_b_ c_.pdf");
+        assert_eq!(attachment_filename("..", "mp3"), "attachment.mp3");
+        assert_eq!(attachment_filename("notes", ""), "notes");
+    }
+}
@@ -0,0 +1,159 @@
+//! Per-window audio mute and volume
+//!
+//! Muting uses the webview's own mute, which also silences Web Audio:
+//! - Linux: WebKitGTK `is-muted`
+//! - Windows: WebView2 `IsMuted`
+//! - macOS: WKWebView `_setPageMuted:`
+//!
+//! No webview has a volume setting, so the volume is applied to the page's
+//! audio and video elements by `init_script/audio.js`.
+//!
+//! The tray menu has a mute toggle for every open wiki. The main process
+//! remembers which wikis are muted and tells their processes over IPC
+//! (`SetMuted`), including windows the wiki opens later.
+
+use std::collections::HashSet;
+use std::sync::{LazyLock, Mutex};
+
+use tauri::{AppHandle, Manager, WebviewWindow};
+
+/// Wikis muted from the tray (main process)
+static MUTED_WIKIS: LazyLock<Mutex<HashSet<String>>> = LazyLock::new(|| Mutex::new(HashSet::new()));
+
+fn validate_volume(volume: f64) -> Result<f64, String> {
+    if volume.is_finite() && (0.0..=1.0).contains(&volume) {
+        Ok(volume)
+    } else {
+        Err(format!("Volume must be between 0 and 1, got {}", volume))
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn apply_muted(window: &WebviewWindow, muted: bool) {
+    use webkit2gtk::WebViewExt;
+
+    let _ = window.with_webview(move |webview| webview.inner().set_is_muted(muted));
+}
+
+#[cfg(target_os = "windows")]
+fn apply_muted(window: &WebviewWindow, muted: bool) {
+    use webview2_com::Microsoft::Web::WebView2::Win32::ICoreWebView2_8;
+    use windows_core::Interface;
+
+    let _ = window.with_webview(move |webview| unsafe {
+        match webview.controller().CoreWebView2().and_then(|c| c.cast::<ICoreWebView2_8>()) {
+            Ok(core) => {
+                if let Err(e) = core.SetIsMuted(muted) {
+                    eprintln!("[TiddlyDesktop] WebView2 SetIsMuted failed: {:?}", e);
+                }
+            }
+            Err(e) => eprintln!("[TiddlyDesktop] WebView2 mute is not supported: {:?}", e),
+        }
+    });
+}
+
+#[cfg(target_os = "macos")]
+fn apply_muted(window: &WebviewWindow, muted: bool) {
+    use objc2::runtime::{AnyObject, Bool};
+    use objc2::{msg_send, sel};
+
+    /// `_WKMediaAudioMuted`
+    const AUDIO_MUTED: usize = 1;
+
+    let _ = window.with_webview(move |webview| unsafe {
+        let Some(wk_webview) = (webview.inner() as *mut AnyObject).as_ref() else {
+            return;
+        };
+        let supported: Bool = msg_send![wk_webview, respondsToSelector: sel!(_setPageMuted:)];
+        if supported.as_bool() {
+            let _: () = msg_send![wk_webview, _setPageMuted: if muted { AUDIO_MUTED } else { 0 }];
+        } else {
+            eprintln!("[TiddlyDesktop] WKWebView mute is not supported");
+        }
+    });
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows", target_os = "macos")))]
+fn apply_muted(_window: &WebviewWindow, _muted: bool) {}
+
+/// Mute or unmute every window of this process (IPC `SetMuted` in wiki processes)
+pub fn set_all_muted(app: &AppHandle, muted: bool) {
+    let handle = app.clone();
+    let _ = app.run_on_main_thread(move || {
+        for window in handle.webview_windows().values() {
+            apply_muted(window, muted);
+        }
+    });
+}
+
+/// Whether a wiki was muted from the tray
+pub fn is_wiki_muted(wiki_path: &str) -> bool {
+    MUTED_WIKIS.lock().unwrap().contains(wiki_path)
+}
+
+/// Mute or unmute a wiki from the tray
+pub fn toggle_wiki_muted(app: &AppHandle, wiki_path: &str) {
+    let muted = {
+        let mut muted_wikis = MUTED_WIKIS.lock().unwrap();
+        if !muted_wikis.remove(wiki_path) {
+            muted_wikis.insert(wiki_path.to_string());
+        }
+        muted_wikis.contains(wiki_path)
+    };
+    if let Some(server) = crate::GLOBAL_IPC_SERVER.get() {
+        let _ = server.send_muted(wiki_path, muted);
+    }
+    crate::refresh_tray_menu(app);
+}
+
+/// A process of a wiki connected to the main process: mute its windows if the
+/// wiki is muted, and list it in the tray
+pub fn wiki_registered(app: &AppHandle, wiki_path: &str) {
+    if is_wiki_muted(wiki_path) {
+        if let Some(server) = crate::GLOBAL_IPC_SERVER.get() {
+            let _ = server.send_muted(wiki_path, true);
+        }
+    }
+    crate::refresh_tray_menu(app);
+}
+
+/// A wiki was closed: it opens unmuted next time
+pub fn wiki_closed(app: &AppHandle, wiki_path: &str) {
+    MUTED_WIKIS.lock().unwrap().remove(wiki_path);
+    crate::refresh_tray_menu(app);
+}
+
+/// Mute or unmute a window of this process
+#[tauri::command]
+pub fn set_window_muted(app: AppHandle, label: String, muted: bool) -> Result<(), String> {
+    let window = app
+        .get_webview_window(&label)
+        .ok_or_else(|| format!("Window '{}' not found", label))?;
+    apply_muted(&window, muted);
+    Ok(())
+}
+
+/// Set the volume (0 to 1) of the audio and video in a window of this process
+#[tauri::command]
+pub fn set_window_volume(app: AppHandle, label: String, volume: f64) -> Result<(), String> {
+    let volume = validate_volume(volume)?;
+    let window = app
+        .get_webview_window(&label)
+        .ok_or_else(|| format!("Window '{}' not found", label))?;
+    window
+        .eval(&format!("window.__tdSetVolume && window.__tdSetVolume({})", volume))
+        .map_err(|e| format!("Failed to set volume: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_volume() {
+        assert_eq!(validate_volume(0.5), Ok(0.5));
+        assert_eq!(validate_volume(0.0), Ok(0.0));
+        assert!(validate_volume(1.5).is_err());
+        assert!(validate_volume(f64::NAN).is_err());
+    }
+}
@@ -0,0 +1,309 @@
+//! Local HTTP API for automation (desktop)
+//!
+//! With the app setting `automation_api` on (and always with `--serve-all`),
+//! the control API on port `--control-port` (default 8079, where
+//! `server_listen` says, see `serve_all`) lets launchers and scripts (Alfred, AutoHotkey, shell scripts)
+//! drive the app. Requests need `Authorization: Bearer <token>`, with the token
+//! from the `control-token` file in the data directory:
+//! - `GET /wikis`: the wiki list, with the wikis that are open
+//! - `POST /wikis/open?wiki=<path>`: open a wiki (or focus it when it's open)
+//! - `POST /wikis/close?wiki=<path>`: save an open wiki and close it
+//! - `GET /tiddlers?wiki=<path>&filter=<filter>`: titles of the tiddlers a
+//!   filter selects (default: the non-system tiddlers)
+//! - `GET /tiddler?wiki=<path>&title=<title>`: the fields of a tiddler
+//! - `PUT /tiddler?wiki=<path>&title=<title>`: create or replace a tiddler
+//!   from a JSON object of fields
+//! - `DELETE /tiddler?wiki=<path>&title=<title>`: delete a tiddler
+//! - `POST /backup?wiki=<path>`: back a wiki up into its backup folder
+//! - `POST /sync?wiki=<path>`: sync an open wiki with its LAN sync peers now
+//!
+//! Tiddler requests need the wiki to be open: they go to its window over IPC
+//! (`AutomationRequest`), `init_script/automation.js` carries them out and
+//! answers with `automation_respond`. Changes are saved like edits in the
+//! window.
+
+use std::collections::HashMap;
+use std::io::Read;
+#[cfg(not(target_os = "android"))]
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, LazyLock, Mutex};
+use std::time::Duration;
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use tauri::{Emitter, Manager};
+use tiny_http::{Method, Request};
+
+use crate::serve_all;
+use crate::utils;
+
+/// How long a wiki window gets to answer
+const WIKI_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Closing waits for the wiki to finish saving
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(75);
+
+/// Largest request body accepted (a tiddler's fields)
+const MAX_BODY_BYTES: u64 = 16 * 1024 * 1024;
+
+/// Tiddlers listed without a `filter`
+const DEFAULT_FILTER: &str = "[all[tiddlers]!is[system]sort[title]]";
+
+static NEXT_REQUEST_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Requests waiting for a wiki window's answer, by id
+static PENDING: LazyLock<Mutex<HashMap<u64, mpsc::Sender<Result<Value, String>>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// A response body, or the HTTP status and message of an error
+type ApiResult = Result<Value, (u16, String)>;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ApiWiki {
+    path: String,
+    name: String,
+    is_folder: bool,
+    open: bool,
+}
+
+/// The automation API setting and whether the API is listening
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AutomationApiInfo {
+    pub enabled: bool,
+    pub running: bool,
+    pub port: u16,
+    pub token_file: String,
+}
+
+/// A query parameter of a request URL (decoded; None when empty)
+fn query_param(url: &str, name: &str) -> Option<String> {
+    let url = tauri::Url::parse(&format!("http://127.0.0.1{}", url)).ok()?;
+    url.query_pairs()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.into_owned())
+        .filter(|value| !value.is_empty())
+}
+
+fn required(url: &str, name: &str) -> Result<String, (u16, String)> {
+    query_param(url, name).ok_or_else(|| (400, format!("Missing parameter: {}", name)))
+}
+
+fn port() -> u16 {
+    serve_all::control_port(&std::env::args().collect::<Vec<_>>())
+}
+
+/// The path an open wiki is tracked under, if `wiki` is open
+fn open_wiki_path(app: &tauri::AppHandle, wiki: &str) -> Option<String> {
+    app.state::<crate::AppState>()
+        .wiki_processes
+        .lock()
+        .unwrap()
+        .keys()
+        .find(|path| utils::paths_equal(path, wiki))
+        .cloned()
+}
+
+fn list_wikis(app: &tauri::AppHandle) -> Vec<ApiWiki> {
+    crate::wiki_storage::load_recent_files_from_disk(app)
+        .into_iter()
+        .map(|entry| ApiWiki {
+            open: open_wiki_path(app, &entry.path).is_some(),
+            path: entry.path,
+            name: entry.filename,
+            is_folder: entry.is_folder,
+        })
+        .collect()
+}
+
+#[cfg(not(target_os = "android"))]
+fn open(app: &tauri::AppHandle, wiki: String) -> ApiResult {
+    let result = tauri::async_runtime::block_on(async {
+        if Path::new(&wiki).is_dir() {
+            crate::open_wiki_folder(app.clone(), wiki.clone(), None).await
+        } else {
+            crate::open_wiki_window(app.clone(), wiki.clone(), None, None, None).await
+        }
+    });
+    let entry = result.map_err(|e| (400, e))?;
+    // Refresh the wiki list in the main window
+    let _ = app.emit("wiki-list-changed", &entry);
+    serde_json::to_value(&entry).map_err(|e| (500, e.to_string()))
+}
+
+/// Have the window of an open wiki carry out `action` and wait for its answer
+fn ask_wiki(app: &tauri::AppHandle, wiki: &str, action: &str, params: Value, timeout: Duration) -> ApiResult {
+    let wiki_path = open_wiki_path(app, wiki).ok_or_else(|| (409, format!("Wiki is not open: {}", wiki)))?;
+    let server = crate::GLOBAL_IPC_SERVER
+        .get()
+        .ok_or_else(|| (503, "IPC server not running".to_string()))?;
+    let request_id = NEXT_REQUEST_ID.fetch_add(1, Ordering::Relaxed);
+    let (tx, rx) = mpsc::channel();
+    PENDING.lock().unwrap().insert(request_id, tx);
+    let result = match server.send_automation_request(&wiki_path, request_id, action, &params.to_string()) {
+        Ok(()) => rx
+            .recv_timeout(timeout)
+            .unwrap_or_else(|_| Err("The wiki did not answer".to_string()))
+            .map_err(|e| (400, e)),
+        Err(e) => Err((502, format!("Failed to reach the wiki: {}", e))),
+    };
+    PENDING.lock().unwrap().remove(&request_id);
+    result
+}
+
+/// Carry out `action` in an open wiki for a local tool (IPC `WikiQuery`):
+/// the result as JSON, or None when the wiki isn't open
+pub fn query_open_wiki(app: &tauri::AppHandle, wiki: &str, action: &str, params_json: &str) -> Option<Result<String, String>> {
+    let params = serde_json::from_str(params_json).unwrap_or(Value::Null);
+    match ask_wiki(app, wiki, action, params, WIKI_TIMEOUT) {
+        Ok(value) => Some(Ok(value.to_string())),
+        Err((409, _)) => None,
+        Err((_, error)) => Some(Err(error)),
+    }
+}
+
+/// Answer of a wiki window to an `AutomationRequest` (IPC, main process)
+pub fn deliver_response(request_id: u64, result_json: Option<String>, error: Option<String>) {
+    let result = match error {
+        Some(error) => Err(error),
+        None => Ok(result_json
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or(Value::Null)),
+    };
+    if let Some(tx) = PENDING.lock().unwrap().get(&request_id) {
+        let _ = tx.send(result);
+    }
+}
+
+/// The tiddler fields in a request body
+fn read_fields(request: &mut Request) -> ApiResult {
+    let mut body = String::new();
+    request
+        .as_reader()
+        .take(MAX_BODY_BYTES)
+        .read_to_string(&mut body)
+        .map_err(|e| (400, format!("Failed to read the request: {}", e)))?;
+    match serde_json::from_str::<Value>(&body) {
+        Ok(fields) if fields.is_object() => Ok(fields),
+        _ => Err((400, "The body must be a JSON object of tiddler fields".to_string())),
+    }
+}
+
+fn route(app: &tauri::AppHandle, request: &mut Request) -> ApiResult {
+    let method = request.method().clone();
+    let url = request.url().to_string();
+    let wiki = || required(&url, "wiki");
+    let title = || required(&url, "title");
+    match (method, url.split('?').next().unwrap_or("")) {
+        (Method::Get, "/wikis") => serde_json::to_value(list_wikis(app)).map_err(|e| (500, e.to_string())),
+        #[cfg(not(target_os = "android"))]
+        (Method::Post, "/wikis/open") => open(app, wiki()?),
+        (Method::Post, "/wikis/close") => ask_wiki(app, &wiki()?, "close", Value::Null, CLOSE_TIMEOUT),
+        (Method::Get, "/tiddlers") => {
+            let filter = query_param(&url, "filter").unwrap_or_else(|| DEFAULT_FILTER.to_string());
+            ask_wiki(app, &wiki()?, "filter", json!({ "filter": filter }), WIKI_TIMEOUT)
+        }
+        (Method::Get, "/tiddler") => match ask_wiki(app, &wiki()?, "get", json!({ "title": title()? }), WIKI_TIMEOUT)? {
+            Value::Null => Err((404, "Tiddler not found".to_string())),
+            fields => Ok(fields),
+        },
+        (Method::Put, "/tiddler") => {
+            let (wiki, title) = (wiki()?, title()?);
+            let fields = read_fields(request)?;
+            ask_wiki(app, &wiki, "put", json!({ "title": title, "fields": fields }), WIKI_TIMEOUT)
+        }
+        (Method::Delete, "/tiddler") => ask_wiki(app, &wiki()?, "delete", json!({ "title": title()? }), WIKI_TIMEOUT),
+        #[cfg(not(target_os = "android"))]
+        (Method::Post, "/backup") => crate::headless::backup(app, Path::new(&wiki()?), None)
+            .map(|message| json!({ "message": message }))
+            .map_err(|e| (500, e)),
+        (Method::Post, "/sync") => crate::lan_sync::sync_now(app.clone(), wiki()?)
+            .map(|()| json!({ "message": "Sync started" }))
+            .map_err(|e| (409, e)),
+        _ => Err((404, "Not Found".to_string())),
+    }
+}
+
+/// Answer an authorized control API request (see `serve_all`)
+pub fn handle_request(app: &tauri::AppHandle, mut request: Request) -> Result<(), String> {
+    match route(app, &mut request) {
+        Ok(value) => serve_all::respond_json(request, &value),
+        Err((status, message)) => serve_all::respond_status(request, status, &message),
+    }
+}
+
+fn info(app: &tauri::AppHandle) -> AutomationApiInfo {
+    AutomationApiInfo {
+        enabled: crate::wiki_storage::load_app_settings(app).map(|s| s.automation_api).unwrap_or(false),
+        running: serve_all::control_api_running(),
+        port: port(),
+        token_file: serve_all::token_path(app)
+            .map(|path| path.to_string_lossy().to_string())
+            .unwrap_or_default(),
+    }
+}
+
+/// Start the API if it's turned on (windowed app; `--serve-all` starts it itself)
+pub fn start(app: &tauri::AppHandle) {
+    if info(app).enabled {
+        if let Err(e) = serve_all::start_control_api(app, port()) {
+            eprintln!("[TiddlyDesktop] Automation API not available: {}", e);
+        }
+    }
+}
+
+/// The automation API setting and state
+#[tauri::command]
+pub fn get_automation_api(app: tauri::AppHandle) -> AutomationApiInfo {
+    info(&app)
+}
+
+/// Turn the automation API on or off (takes effect right away)
+#[tauri::command]
+pub fn set_automation_api(app: tauri::AppHandle, enabled: bool) -> Result<AutomationApiInfo, String> {
+    let mut settings = crate::wiki_storage::load_app_settings(&app)?;
+    settings.automation_api = enabled;
+    crate::wiki_storage::save_app_settings(&app, &settings)?;
+    if !serve_all::is_active() {
+        if !enabled {
+            serve_all::stop_control_api();
+        } else if !serve_all::control_api_running() {
+            serve_all::start_control_api(&app, port())?;
+        }
+    }
+    Ok(info(&app))
+}
+
+/// Answer an automation request (called by `init_script/automation.js` in
+/// wiki processes)
+#[cfg(not(target_os = "android"))]
+#[tauri::command]
+pub fn automation_respond(
+    state: tauri::State<crate::WikiModeState>,
+    request_id: u64,
+    result: Option<Value>,
+    error: Option<String>,
+) -> Result<(), String> {
+    let mut client = state.ipc_client.lock().unwrap();
+    let client = client.as_mut().ok_or("Not connected to the main process")?;
+    client
+        .send_automation_response(request_id, result.map(|r| r.to_string()), error)
+        .map_err(|e| format!("Failed to send the answer: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query_param() {
+        let url = "/tiddler?wiki=%2Fhome%2Fme%2Fnotes.html&title=Getting+Started&filter=";
+        assert_eq!(query_param(url, "wiki").as_deref(), Some("/home/me/notes.html"));
+        assert_eq!(query_param(url, "title").as_deref(), Some("Getting Started"));
+        assert_eq!(query_param(url, "filter"), None);
+        assert_eq!(query_param("/wikis", "wiki"), None);
+        assert_eq!(required("/tiddler?title=A", "wiki"), Err((400, "Missing parameter: wiki".to_string())));
+    }
+}
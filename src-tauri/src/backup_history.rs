@@ -0,0 +1,126 @@
+//! Tiddler history and the backup browser
+//!
+//! The history and the tiddler comparison are done by
+//! `tiddlydesktop_core::backup`; this module exposes them to the landing page
+//! and wiki windows:
+//! - the history of one tiddler across all backups of a wiki
+//! - the backups of a wiki (save-time and scheduled ones, see `scheduled_backups`),
+//!   which tiddlers differ between a backup and the current file, and restoring
+//!   a backup (after backing up the current file, like a save)
+
+use std::path::{Path, PathBuf};
+
+use tiddlydesktop_core::backup::{self, BackupFile, TiddlerChanges, TiddlerVersion};
+
+/// Get the history of one tiddler across all backups of a wiki.
+/// Returns versions newest first, each with a diff against the version before it.
+#[tauri::command]
+pub async fn get_tiddler_history(app: tauri::AppHandle, wiki_path: String, title: String) -> Result<Vec<TiddlerVersion>, String> {
+    let validated_path = crate::drag_drop::sanitize::validate_wiki_path(&wiki_path)?;
+    let custom_backup_dir = crate::get_wiki_backup_dir(&app, &wiki_path);
+
+    tokio::task::spawn_blocking(move || {
+        backup::collect_tiddler_history(&validated_path, custom_backup_dir.as_deref(), &title)
+    }).await
+        .map_err(|e| format!("Failed to spawn blocking task: {}", e))
+}
+
+/// A backup of a wiki, as listed in the backup browser
+#[derive(Clone, Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupInfo {
+    path: String,
+    filename: String,
+    /// RFC 3339 time the backup was taken (from its name)
+    timestamp: String,
+    size: u64,
+    /// Taken by the backup schedule rather than before a save
+    scheduled: bool,
+}
+
+/// Save-time and scheduled backups of a wiki, oldest first within each kind
+fn wiki_backups(wiki_path: &Path, custom_backup_dir: Option<&str>) -> Vec<(BackupFile, bool)> {
+    let mut backups: Vec<(BackupFile, bool)> = backup::list_wiki_backups(wiki_path, custom_backup_dir)
+        .into_iter()
+        .map(|b| (b, false))
+        .collect();
+    if let Some(dir) = crate::scheduled_backups::scheduled_dir(wiki_path, custom_backup_dir) {
+        backups.extend(
+            backup::list_wiki_backups(wiki_path, Some(&dir.to_string_lossy()))
+                .into_iter()
+                .map(|b| (b, true)),
+        );
+    }
+    backups
+}
+
+/// The backup at `backup_path`, if it is one of the wiki's backups (so these
+/// commands can't be pointed at other files)
+fn find_backup(wiki_path: &Path, custom_backup_dir: Option<&str>, backup_path: &str) -> Result<PathBuf, String> {
+    wiki_backups(wiki_path, custom_backup_dir)
+        .into_iter()
+        .map(|(b, _)| b.path)
+        .find(|path| crate::utils::paths_equal(&path.to_string_lossy(), backup_path))
+        .ok_or_else(|| "Not a backup of this wiki".to_string())
+}
+
+/// List the backups of a wiki, newest first
+#[tauri::command]
+pub async fn list_backups(app: tauri::AppHandle, wiki_path: String) -> Result<Vec<BackupInfo>, String> {
+    let validated_path = crate::drag_drop::sanitize::validate_wiki_path(&wiki_path)?;
+    let custom_backup_dir = crate::get_wiki_backup_dir(&app, &wiki_path);
+
+    tokio::task::spawn_blocking(move || {
+        let mut backups = wiki_backups(&validated_path, custom_backup_dir.as_deref());
+        backups.sort_by(|(a, _), (b, _)| b.timestamp.cmp(&a.timestamp));
+        backups
+            .into_iter()
+            .map(|(b, scheduled)| BackupInfo {
+                filename: b.path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default(),
+                timestamp: backup::local_timestamp(&b.timestamp),
+                size: std::fs::metadata(&b.path).map(|m| m.len()).unwrap_or(0),
+                path: b.path.to_string_lossy().into_owned(),
+                scheduled,
+            })
+            .collect::<Vec<_>>()
+    }).await
+        .map_err(|e| format!("Failed to spawn blocking task: {}", e))
+}
+
+/// Tiddlers that differ between a backup and the current wiki file: `added`
+/// are only in the current file, `removed` only in the backup (restoring the
+/// backup undoes both), `changed` differ in any field
+#[tauri::command]
+pub async fn diff_backup(app: tauri::AppHandle, wiki_path: String, backup_path: String) -> Result<TiddlerChanges, String> {
+    let validated_path = crate::drag_drop::sanitize::validate_wiki_path(&wiki_path)?;
+    let custom_backup_dir = crate::get_wiki_backup_dir(&app, &wiki_path);
+
+    tokio::task::spawn_blocking(move || {
+        let backup_file = find_backup(&validated_path, custom_backup_dir.as_deref(), &backup_path)?;
+        let backup_html = std::fs::read_to_string(&backup_file)
+            .map_err(|e| format!("Failed to read backup: {}", e))?;
+        let current_html = std::fs::read_to_string(&validated_path)
+            .map_err(|e| format!("Failed to read wiki: {}", e))?;
+        Ok(backup::diff_wiki_tiddlers(&backup_html, &current_html))
+    }).await
+        .map_err(|e| format!("Failed to spawn blocking task: {}", e))?
+}
+
+/// Replace the wiki file with one of its backups. The wiki must not be open
+/// (its window would save over the restored file). The current file is backed
+/// up first and replaced atomically, as on a save.
+#[tauri::command]
+pub async fn restore_backup(app: tauri::AppHandle, wiki_path: String, backup_path: String) -> Result<(), String> {
+    let validated_path = crate::drag_drop::sanitize::validate_wiki_path(&wiki_path)?;
+    if crate::is_wiki_open(app.clone(), wiki_path.clone())
+        || crate::browser_mode::served_wikis().iter().any(|path| crate::utils::paths_equal(path, &wiki_path))
+    {
+        return Err("Close the wiki before restoring a backup".to_string());
+    }
+    let custom_backup_dir = crate::get_wiki_backup_dir(&app, &wiki_path);
+    let backup_file = find_backup(&validated_path, custom_backup_dir.as_deref(), &backup_path)?;
+    let content = tokio::fs::read_to_string(&backup_file)
+        .await
+        .map_err(|e| format!("Failed to read backup: {}", e))?;
+    crate::save_wiki(app, wiki_path, content).await
+}
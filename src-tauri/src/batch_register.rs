@@ -0,0 +1,147 @@
+//! Adding many wikis to the wiki list at once (desktop)
+//!
+//! The user picks several wiki files, or a parent folder that is searched the
+//! same way as watched folders. Each file is validated on its own blocking
+//! task, so a folder with many large wikis is checked in parallel. Valid wikis
+//! are appended to the wiki list; the rest are reported back with the reason.
+
+use std::path::{Path, PathBuf};
+
+use tauri_plugin_dialog::DialogExt;
+
+use crate::types::WikiEntry;
+use crate::utils;
+
+/// A wiki that could not be added
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct RegisterFailure {
+    pub path: String,
+    pub error: String,
+}
+
+/// Result of `pick_and_register_wikis`
+#[derive(Clone, Debug, Default, serde::Serialize)]
+pub struct RegisterSummary {
+    /// Newly added wikis
+    pub added: Vec<WikiEntry>,
+    /// Wikis that were already in the list
+    pub already_listed: usize,
+    pub failed: Vec<RegisterFailure>,
+}
+
+/// Validate a picked wiki and build its wiki list entry
+fn check_wiki(app: &tauri::AppHandle, path: &Path, is_folder: bool) -> Result<WikiEntry, String> {
+    if is_folder {
+        if !utils::is_wiki_folder(path) {
+            return Err("Not a wiki folder".to_string());
+        }
+    } else {
+        crate::validate_tiddlywiki_file(path)?;
+    }
+    let path_str = path.to_string_lossy().into_owned();
+    Ok(WikiEntry {
+        path: path_str.clone(),
+        filename: path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_else(|| path_str.clone()),
+        display_path: Some(path_str),
+        favicon: if is_folder { None } else { crate::favicon_cache::favicon_for_file(app, path) },
+        is_folder,
+        backups_enabled: !is_folder,
+        backup_dir: None,
+        backup_count: None,
+        group: None,
+        sync_enabled: false,
+        sync_id: None,
+        sync_peers: vec![],
+        relay_room: None,
+        sync_mode: None,
+        custom_icon: None,
+        accent_color: None,
+        emoji: None,
+        scheduled_backup: None,
+        confirm_remote_deletions: false,
+        sync_schedule: None,
+        hotkey: None,
+        sync_priority: None,
+        missing: false,
+    })
+}
+
+#[cfg(not(target_os = "android"))]
+async fn pick_folder(app: &tauri::AppHandle) -> Result<Vec<(PathBuf, bool)>, String> {
+    let Some(dir) = app.dialog().file().set_title("Add Wikis From Folder").blocking_pick_folder() else {
+        return Ok(Vec::new());
+    };
+    let dir = dir.into_path().map_err(|e| e.to_string())?;
+    tokio::task::spawn_blocking(move || crate::watched_folders::scan_folder(&dir))
+        .await
+        .map_err(|e| format!("Failed to spawn blocking task: {}", e))
+}
+
+#[cfg(target_os = "android")]
+async fn pick_folder(_app: &tauri::AppHandle) -> Result<Vec<(PathBuf, bool)>, String> {
+    Err("Adding wikis from a folder is not supported on Android".to_string())
+}
+
+/// Let the user pick wiki files (or, with `folder`, a folder to search for
+/// wiki files and wiki folders) and add the valid ones to the wiki list
+#[tauri::command]
+pub async fn pick_and_register_wikis(app: tauri::AppHandle, folder: bool) -> Result<RegisterSummary, String> {
+    let picked: Vec<(PathBuf, bool)> = if folder {
+        pick_folder(&app).await?
+    } else {
+        app.dialog()
+            .file()
+            .set_title("Add Wikis")
+            .add_filter("TiddlyWiki", &["html", "htm"])
+            .blocking_pick_files()
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|p| p.into_path().ok())
+            .map(|p| (p, false))
+            .collect()
+    };
+
+    register_wikis(&app, picked).await
+}
+
+/// Validate wiki files and wiki folders (`(path, is_folder)`) in parallel and
+/// append the valid ones to the wiki list
+pub(crate) async fn register_wikis(app: &tauri::AppHandle, picked: Vec<(PathBuf, bool)>) -> Result<RegisterSummary, String> {
+    let mut summary = RegisterSummary::default();
+    if picked.is_empty() {
+        return Ok(summary);
+    }
+
+    let mut entries = crate::wiki_storage::load_recent_files_from_disk(app);
+    let mut checks = Vec::new();
+    for (path, is_folder) in picked {
+        if entries.iter().any(|e| utils::paths_equal(&e.path, &path.to_string_lossy())) {
+            summary.already_listed += 1;
+            continue;
+        }
+        let app = app.clone();
+        checks.push(tokio::task::spawn_blocking(move || {
+            let result = check_wiki(&app, &path, is_folder);
+            (path, result)
+        }));
+    }
+
+    for (path, result) in futures_util::future::join_all(checks).await.into_iter().flatten() {
+        match result {
+            Ok(entry) => summary.added.push(entry),
+            Err(error) => summary.failed.push(RegisterFailure {
+                path: path.to_string_lossy().into_owned(),
+                error,
+            }),
+        }
+    }
+
+    if !summary.added.is_empty() {
+        // Appended like watched-folder discoveries, so a big batch doesn't push
+        // recently opened wikis out of the list
+        entries.extend(summary.added.iter().cloned());
+        crate::wiki_storage::save_recent_files_to_disk(app, &entries)?;
+        eprintln!("[TiddlyDesktop] Added {} wikis ({} failed)", summary.added.len(), summary.failed.len());
+    }
+    Ok(summary)
+}
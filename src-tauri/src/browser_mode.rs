@@ -0,0 +1,327 @@
+//! External browser mode: wikis served to the system browser (desktop)
+//!
+//! Wikis switched to this mode on the landing page (`external_browser` in the
+//! wiki configs) don't get a window. Opening one serves it (on the addresses
+//! of `server_address`) and opens its 127.0.0.1 URL in the default browser
+//! instead:
+//! - single-file wikis are served under a random path and saved with
+//!   TiddlyWiki's PUT saver, through `save_wiki`, so backups and webhooks work
+//!   as they do in a window (the ETag keeps two tabs from overwriting each
+//!   other); saves from other devices need the password of `server_address`
+//! - folder wikis use the built-in folder server (`folder_server`)
+//!
+//! The servers run in the main process and keep it running like open wiki
+//! processes, until they are stopped from the tray or the app quits. When
+//! they listen beyond this computer, the tray shows a QR code of the URL for
+//! phones and tablets (see `server_discovery`).
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+use std::time::SystemTime;
+
+use tauri::{AppHandle, Manager};
+use tiny_http::{Header, Method, Request, Response, StatusCode};
+
+use crate::server_address::{self, Binding, Servers};
+use crate::wiki_storage::{load_wiki_configs, save_wiki_configs};
+
+struct Session {
+    url: String,
+    server: Servers,
+}
+
+/// Wikis being served, by path
+static SESSIONS: LazyLock<Mutex<BTreeMap<String, Session>>> = LazyLock::new(|| Mutex::new(BTreeMap::new()));
+
+/// Scratch directory of the QR code shown last, removed when quitting
+static QR_DIR: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// Whether a wiki opens in the system browser
+pub fn is_enabled(app: &AppHandle, path: &str) -> bool {
+    load_wiki_configs(app)
+        .map(|configs| configs.external_browser.get(path).copied().unwrap_or(false))
+        .unwrap_or(false)
+}
+
+/// Whether any wiki is being served to the browser
+pub fn has_sessions() -> bool {
+    !SESSIONS.lock().unwrap().is_empty()
+}
+
+/// Paths of the wikis being served
+pub fn served_wikis() -> Vec<String> {
+    SESSIONS.lock().unwrap().keys().cloned().collect()
+}
+
+/// Serve a wiki (unless it already is) and open it in the system browser
+#[cfg(not(target_os = "android"))]
+pub fn open(app: &AppHandle, path: &str, is_folder: bool) -> Result<(), String> {
+    let url = {
+        let mut sessions = SESSIONS.lock().unwrap();
+        match sessions.get(path) {
+            Some(session) => session.url.clone(),
+            None => {
+                let port = crate::allocate_port(&app.state::<crate::AppState>());
+                let session = if is_folder {
+                    start_folder(app, path, port)?
+                } else {
+                    start_file(app, path, port)?
+                };
+                eprintln!("[BrowserMode] Serving {} at {}", path, session.url);
+                let url = session.url.clone();
+                sessions.insert(path.to_string(), session);
+                url
+            }
+        }
+    };
+    crate::refresh_tray_menu(app);
+    open_url(app, &url)
+}
+
+#[cfg(not(target_os = "android"))]
+fn open_url(app: &AppHandle, url: &str) -> Result<(), String> {
+    use tauri_plugin_opener::OpenerExt;
+    app.opener()
+        .open_url(url, None::<&str>)
+        .map_err(|e| format!("Failed to open the browser: {}", e))
+}
+
+/// Stop serving a wiki; exits the app when nothing else is left open
+#[cfg(not(target_os = "android"))]
+pub fn stop(app: &AppHandle, path: &str) {
+    let Some(session) = SESSIONS.lock().unwrap().remove(path) else {
+        return;
+    };
+    session.server.unblock();
+    eprintln!("[BrowserMode] Stopped serving {}", path);
+    crate::refresh_tray_menu(app);
+
+    let wiki_count = app.state::<crate::AppState>().wiki_processes.lock().unwrap().len();
+    if wiki_count == 0 && !has_sessions() && app.webview_windows().is_empty() {
+        eprintln!("[TiddlyDesktop] No more wikis or windows, exiting");
+        app.exit(0);
+    }
+}
+
+/// Stop serving all wikis (quitting)
+pub fn stop_all() {
+    let sessions = std::mem::take(&mut *SESSIONS.lock().unwrap());
+    for session in sessions.values() {
+        session.server.unblock();
+    }
+    if let Some(dir) = QR_DIR.lock().unwrap().take() {
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
+
+/// Show the QR code of the URL other devices open a served wiki with
+#[cfg(not(target_os = "android"))]
+fn show_qr_code(app: &AppHandle, path: &str) -> Result<(), String> {
+    use tauri_plugin_opener::OpenerExt;
+    let png = crate::qr_code::qr_png(&crate::server_discovery::first_url(path)?, None)?;
+    // Emptied for each code; a crash leaves it to the cleanup on a later start
+    let dir = crate::scratch_dir::create_build_dir(app, "qr")?;
+    let file = dir.join("qr-code.png");
+    *QR_DIR.lock().unwrap() = Some(dir);
+    std::fs::write(&file, png).map_err(|e| format!("Failed to write the QR code: {}", e))?;
+    app.opener()
+        .open_path(file.to_string_lossy(), None::<&str>)
+        .map_err(|e| format!("Failed to show the QR code: {}", e))
+}
+
+/// Handle a tray menu event of this module (`browser_open:<path>`,
+/// `browser_qr:<path>`, `browser_stop:<path>`)
+#[cfg(not(target_os = "android"))]
+pub fn handle_tray_event(app: &AppHandle, id: &str) {
+    if let Some(path) = id.strip_prefix("browser_open:") {
+        let url = SESSIONS.lock().unwrap().get(path).map(|session| session.url.clone());
+        if let Some(url) = url {
+            if let Err(e) = open_url(app, &url) {
+                eprintln!("[BrowserMode] {}", e);
+            }
+        }
+    } else if let Some(path) = id.strip_prefix("browser_qr:") {
+        if let Err(e) = show_qr_code(app, path) {
+            eprintln!("[BrowserMode] {}", e);
+        }
+    } else if let Some(path) = id.strip_prefix("browser_stop:") {
+        stop(app, path);
+    }
+}
+
+fn start_folder(app: &AppHandle, path: &str, port: u16) -> Result<Session, String> {
+    let tw_path = crate::get_tiddlywiki_path(app)?;
+    let tw_dir = tw_path.parent().map(Path::to_path_buf).unwrap_or_default();
+    let webdav = crate::wiki_storage::load_app_settings(app).map(|s| s.folder_webdav).unwrap_or(false);
+    let binding = Binding::current(app);
+    let server = crate::folder_server::start(Path::new(path), &tw_dir, port, webdav, None, &binding)?;
+    Ok(Session { url: server_address::local_url(port), server })
+}
+
+fn start_file(app: &AppHandle, path: &str, port: u16) -> Result<Session, String> {
+    let binding = Binding::current(app);
+    let server = binding.http(port)?;
+    let filename = Path::new(path)
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "wiki.html".to_string());
+    // Other programs and devices can't guess where the wiki is
+    let route = wiki_route(&format!("{:016x}", rand::random::<u64>()), &filename);
+    let url = format!("http://127.0.0.1:{}{}", port, route);
+    for lan_url in binding.lan_urls(port) {
+        eprintln!("[BrowserMode] Also at {}{}", lan_url.trim_end_matches('/'), route);
+    }
+
+    let announcement = crate::server_discovery::announce(&binding, path, port, &route, false);
+    let app = app.clone();
+    let path = path.to_string();
+    // One request at a time, also across addresses, so saves don't overlap
+    let one_at_a_time = Mutex::new(());
+    server.serve(move |request| {
+        let _announcement = &announcement;
+        let _one = one_at_a_time.lock().unwrap();
+        if let Err(e) = handle_file_request(&app, &path, &route, &binding, request) {
+            eprintln!("[BrowserMode] {}", e);
+        }
+    });
+    Ok(Session { url, server })
+}
+
+/// URL path of a served single-file wiki
+fn wiki_route(token: &str, filename: &str) -> String {
+    format!("/{}/{}", token, urlencoding::encode(filename))
+}
+
+/// ETag of the wiki file as last written
+fn etag(len: u64, modified: SystemTime) -> String {
+    let nanos = modified
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    format!("\"{:x}-{:x}\"", len, nanos)
+}
+
+fn file_etag(path: &str) -> Option<String> {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some(etag(metadata.len(), metadata.modified().ok()?))
+}
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name.as_bytes(), value.as_bytes()).unwrap()
+}
+
+fn respond<R: Read>(request: Request, response: Response<R>) -> Result<(), String> {
+    request.respond(response).map_err(|e| format!("Failed to send response: {}", e))
+}
+
+fn respond_status(request: Request, status: u16, message: &str) -> Result<(), String> {
+    respond(request, Response::from_string(message).with_status_code(StatusCode(status)))
+}
+
+fn handle_file_request(app: &AppHandle, path: &str, route: &str, binding: &Binding, mut request: Request) -> Result<(), String> {
+    if !server_address::allows_host(&request) {
+        return respond_status(request, 403, "Unknown host");
+    }
+    // Saves from other devices
+    if !binding.allows_write(&request) {
+        return server_address::refuse_write(request);
+    }
+    // Browsers don't escape all the characters `wiki_route` does
+    let url = request.url().split(['?', '#']).next().unwrap_or("").to_string();
+    if urlencoding::decode(&url).ok() != urlencoding::decode(route).ok() {
+        return respond_status(request, 404, "Not Found");
+    }
+    let method = request.method().clone();
+    match method {
+        // The `dav` header tells TiddlyWiki to save with PUT
+        Method::Options => respond(request, Response::empty(StatusCode(200))
+            .with_header(header("Allow", "GET, HEAD, PUT, OPTIONS"))
+            .with_header(header("dav", "1"))),
+        Method::Get | Method::Head => {
+            let Ok(file) = std::fs::File::open(path) else {
+                return respond_status(request, 404, "Not Found");
+            };
+            let mut response = Response::from_file(file)
+                .with_header(header("Content-Type", "text/html;charset=utf-8"))
+                .with_header(header("Cache-Control", "no-store"));
+            if let Some(etag) = file_etag(path) {
+                response = response.with_header(header("ETag", &etag));
+            }
+            respond(request, response)
+        }
+        Method::Put => {
+            // Changed since this tab loaded or saved it (another tab, another program)
+            let if_match = request.headers().iter()
+                .find(|h| h.field.equiv("If-Match"))
+                .map(|h| h.value.as_str().to_string());
+            if let Some(if_match) = if_match {
+                if file_etag(path).as_deref() != Some(if_match.as_str()) {
+                    return respond_status(request, 412, "The wiki file has changed since it was loaded");
+                }
+            }
+            let mut content = String::new();
+            if let Err(e) = request.as_reader().read_to_string(&mut content) {
+                return respond_status(request, 400, &format!("Failed to read the wiki: {}", e));
+            }
+            match tauri::async_runtime::block_on(crate::save_wiki(app.clone(), path.to_string(), content)) {
+                Ok(()) => {
+                    let mut response = Response::empty(StatusCode(204));
+                    if let Some(etag) = file_etag(path) {
+                        response = response.with_header(header("ETag", &etag));
+                    }
+                    respond(request, response)
+                }
+                Err(e) => {
+                    eprintln!("[BrowserMode] Failed to save {}: {}", path, e);
+                    respond_status(request, 500, &e)
+                }
+            }
+        }
+        _ => respond_status(request, 405, "Method Not Allowed"),
+    }
+}
+
+/// Wikis that open in the system browser, keyed by wiki path
+#[tauri::command]
+pub fn get_external_browser_wikis(app: AppHandle) -> Result<HashMap<String, bool>, String> {
+    Ok(load_wiki_configs(&app)?.external_browser)
+}
+
+/// Open a wiki in the system browser from now on, or in a window again
+/// (which also stops serving it)
+#[tauri::command]
+pub fn set_external_browser(app: AppHandle, wiki_path: String, enabled: bool) -> Result<(), String> {
+    let mut configs = load_wiki_configs(&app)?;
+    if enabled {
+        configs.external_browser.insert(wiki_path, true);
+    } else {
+        configs.external_browser.remove(&wiki_path);
+        #[cfg(not(target_os = "android"))]
+        stop(&app, &wiki_path);
+    }
+    save_wiki_configs(&app, &configs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_wiki_route() {
+        assert_eq!(wiki_route("abc", "My Wiki.html"), "/abc/My%20Wiki.html");
+        assert_eq!(wiki_route("abc", "notes#1.html"), "/abc/notes%231.html");
+    }
+
+    #[test]
+    fn test_etag_changes_with_file() {
+        let time = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let tag = etag(1000, time);
+        assert!(tag.starts_with('"') && tag.ends_with('"'));
+        assert_eq!(tag, etag(1000, time));
+        assert_ne!(tag, etag(1001, time));
+        assert_ne!(tag, etag(1000, time + Duration::from_millis(1)));
+    }
+}
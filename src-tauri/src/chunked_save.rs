@@ -0,0 +1,231 @@
+//! Saving big wikis in chunks
+//!
+//! `save_wiki` gets the whole wiki as one IPC argument, which for a wiki of
+//! a few hundred MB is copied into the IPC message, deserialized into a
+//! `String` and only then written. Wiki windows save big wikis through here
+//! instead: `save_wiki_begin` opens a temp file next to the wiki,
+//! `save_wiki_chunk` appends the content piece by piece, and
+//! `save_wiki_commit` replaces the wiki with it, after the same checks and
+//! backups as `save_wiki`. No more than a chunk is held at a time.
+//!
+//! Saves that get no chunk for `ABANDONED_AFTER` (the window was closed or
+//! reloaded midway) are dropped with their temp file, and temp files of a
+//! wiki left behind by a crash are removed when its next save begins.
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+/// A save that got no chunk for this long is given up
+const ABANDONED_AFTER: Duration = Duration::from_secs(10 * 60);
+
+/// A save in progress
+struct PendingSave {
+    /// The wiki as the window knows it (for settings, backups and webhooks)
+    path: String,
+    /// The validated wiki file
+    target: PathBuf,
+    temp: PathBuf,
+    /// None once the save is committed or given up
+    file: Option<std::fs::File>,
+    written: u64,
+    last_chunk: Instant,
+}
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Saves in progress, each with its own lock so writing a chunk of one
+/// doesn't hold up the others
+static SAVES: LazyLock<Mutex<HashMap<u64, Arc<Mutex<PendingSave>>>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+fn get(id: u64) -> Result<Arc<Mutex<PendingSave>>, String> {
+    SAVES
+        .lock()
+        .unwrap()
+        .get(&id)
+        .cloned()
+        .ok_or_else(|| format!("No save {} in progress", id))
+}
+
+fn take(id: u64) -> Result<Arc<Mutex<PendingSave>>, String> {
+    SAVES
+        .lock()
+        .unwrap()
+        .remove(&id)
+        .ok_or_else(|| format!("No save {} in progress", id))
+}
+
+/// Drop the saves that got no chunk for `ABANDONED_AFTER`, with their temp files
+fn sweep_abandoned() {
+    let abandoned: Vec<Arc<Mutex<PendingSave>>> = {
+        let mut saves = SAVES.lock().unwrap();
+        let ids: Vec<u64> = saves
+            .iter()
+            // A save that is busy writing a chunk isn't abandoned
+            .filter(|(_, save)| save.try_lock().is_ok_and(|save| save.last_chunk.elapsed() >= ABANDONED_AFTER))
+            .map(|(id, _)| *id)
+            .collect();
+        ids.iter().filter_map(|id| saves.remove(id)).collect()
+    };
+    for save in abandoned {
+        let mut save = save.lock().unwrap();
+        save.file = None;
+        eprintln!("[TiddlyDesktop] Gave up the unfinished save of {}", save.path);
+        let _ = std::fs::remove_file(&save.temp);
+    }
+}
+
+/// The save id, if `name` is the name of a temp file of a chunked save of
+/// `wiki_name` (`<wiki_name>.<id>.tmp`)
+fn temp_id(wiki_name: &str, name: &str) -> Option<u64> {
+    let id = name.strip_prefix(wiki_name)?.strip_prefix('.')?.strip_suffix(".tmp")?;
+    if id.is_empty() || !id.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    id.parse().ok()
+}
+
+/// Remove the temp files next to the wiki file `target` that no save in
+/// progress is writing (left behind when the app crashed during a save)
+fn remove_stale_temps(target: &Path) {
+    let (Some(dir), Some(wiki_name)) = (target.parent(), target.file_name().and_then(|n| n.to_str())) else {
+        return;
+    };
+    let in_progress: Vec<u64> = SAVES.lock().unwrap().keys().copied().collect();
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let stale = entry
+            .file_name()
+            .to_str()
+            .and_then(|name| temp_id(wiki_name, name))
+            .is_some_and(|id| !in_progress.contains(&id));
+        let path = entry.path();
+        if stale && std::fs::remove_file(&path).is_ok() {
+            eprintln!("[TiddlyDesktop] Removed the leftover temp file {}", path.display());
+        }
+    }
+}
+
+/// Start saving the wiki at `path` in chunks. Returns the id of the save.
+/// (Not for Android's content:// documents, which `save_wiki` writes.)
+#[tauri::command]
+pub fn save_wiki_begin(path: String) -> Result<u64, String> {
+    if path.starts_with("content://") || path.starts_with('{') {
+        return Err("Documents can't be saved in chunks".to_string());
+    }
+    let target = crate::drag_drop::sanitize::validate_wiki_path_for_write(&path)?;
+    sweep_abandoned();
+    remove_stale_temps(&target);
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    let mut name = target.as_os_str().to_owned();
+    name.push(format!(".{}.tmp", id));
+    let temp = PathBuf::from(name);
+    let file = std::fs::File::create(&temp).map_err(|e| format!("Failed to write temp file: {}", e))?;
+    let save = PendingSave { path, target, temp, file: Some(file), written: 0, last_chunk: Instant::now() };
+    SAVES.lock().unwrap().insert(id, Arc::new(Mutex::new(save)));
+    Ok(id)
+}
+
+/// Append the next piece of the wiki to save `id`
+#[tauri::command]
+pub fn save_wiki_chunk(id: u64, chunk: String) -> Result<(), String> {
+    let save = get(id)?;
+    let mut save = save.lock().unwrap();
+    let Some(file) = save.file.as_mut() else {
+        return Err(format!("No save {} in progress", id));
+    };
+    if let Err(e) = file.write_all(chunk.as_bytes()) {
+        save.file = None;
+        let _ = std::fs::remove_file(&save.temp);
+        SAVES.lock().unwrap().remove(&id);
+        return Err(format!("Failed to write temp file: {}", e));
+    }
+    save.written += chunk.len() as u64;
+    save.last_chunk = Instant::now();
+    Ok(())
+}
+
+/// Replace the wiki with everything sent for save `id`
+#[tauri::command]
+pub async fn save_wiki_commit(app: tauri::AppHandle, id: u64) -> Result<(), String> {
+    let (path, target, temp, written, file) = {
+        let save = take(id)?;
+        let mut save = save.lock().unwrap();
+        let file = save.file.take().ok_or_else(|| format!("No save {} in progress", id))?;
+        (save.path.clone(), save.target.clone(), save.temp.clone(), save.written, file)
+    };
+    let synced = file.sync_all();
+    // Closed first, or it couldn't be renamed on Windows
+    drop(file);
+    let result = match synced {
+        Ok(()) => commit(&app, &path, &target, &temp, written).await,
+        Err(e) => Err(format!("Failed to write temp file: {}", e)),
+    };
+    if result.is_err() {
+        let _ = tokio::fs::remove_file(&temp).await;
+    }
+    result
+}
+
+async fn commit(app: &tauri::AppHandle, path: &str, target: &Path, temp: &Path, size: u64) -> Result<(), String> {
+    crate::incremental_save::recover(target);
+    crate::prepare_wiki_write(app, path, target, size).await?;
+
+    // Incremental saving needs the new content to compare, which is read
+    // back only if it's on and the wiki big enough
+    let incremental = {
+        let app = app.clone();
+        let target = target.to_path_buf();
+        let temp = temp.to_path_buf();
+        tokio::task::spawn_blocking(move || {
+            crate::incremental_save::wants(&app, &target)
+                && std::fs::read(&temp).is_ok_and(|content| crate::incremental_save::save(&app, &target, &content))
+        })
+        .await
+        .unwrap_or(false)
+    };
+    if incremental {
+        let _ = tokio::fs::remove_file(temp).await;
+    } else if tokio::fs::rename(temp, target).await.is_err() {
+        // Rename fails on Windows if the file is locked
+        tokio::fs::copy(temp, target)
+            .await
+            .map_err(|e| format!("Failed to save file: {}", e))?;
+        let _ = tokio::fs::remove_file(temp).await;
+    }
+
+    crate::webhooks::dispatch(app, crate::webhooks::WebhookEvent::WikiSaved, serde_json::json!({ "wikiPath": path }));
+    crate::favicon_cache::refresh_after_save(app, target);
+    Ok(())
+}
+
+/// Give up save `id` (the wiki stays as it was)
+#[tauri::command]
+pub fn save_wiki_abort(id: u64) {
+    if let Ok(save) = take(id) {
+        let mut save = save.lock().unwrap();
+        save.file = None;
+        let _ = std::fs::remove_file(&save.temp);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_temp_id() {
+        assert_eq!(temp_id("wiki.html", "wiki.html.1.tmp"), Some(1));
+        assert_eq!(temp_id("wiki.html", "wiki.html.42.tmp"), Some(42));
+        assert_eq!(temp_id("wiki.html", "wiki.html.tmp"), None);
+        assert_eq!(temp_id("wiki.html", "wiki.html..tmp"), None);
+        assert_eq!(temp_id("wiki.html", "wiki.html.+1.tmp"), None);
+        assert_eq!(temp_id("wiki.html", "other.html.1.tmp"), None);
+        assert_eq!(temp_id("wiki.html", "wiki.html.1.tmp.bak"), None);
+    }
+}
@@ -0,0 +1,385 @@
+//! Importing from classic TiddlyDesktop (NW.js)
+//!
+//! Classic TiddlyDesktop keeps its configuration as tiddlers in the
+//! `user-config-tiddlers` folder of its data dir (`.tid` and `.json` files).
+//! Its wiki list holds the wikis as `file://` URLs; the other tiddlers that
+//! belong to a wiki have its URL in their title:
+//! - favicons: image tiddlers (base64 text)
+//! - window states: tiddlers with `x`/`y` (or `left`/`top`) and `width`/`height` fields
+//!
+//! and `$:/TiddlyDesktop/BackupPath` holds the backup setting (empty: no
+//! backups). Only what's recognized is taken over, so config files of other
+//! versions import as much as they can.
+//!
+//! The wikis are added to the wiki list like picked ones (`batch_register`).
+//! Classic favicons fill in the wikis without one, window states the wikis
+//! without one, and the backup setting applies to the newly added wikis, so
+//! importing twice changes nothing set up here in the meantime.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::types::WindowState;
+use crate::utils;
+
+/// Classic TiddlyDesktop config files bigger than this are skipped
+const MAX_CLASSIC_FILE_BYTES: u64 = 8 * 1024 * 1024;
+
+/// Title of the classic backup setting
+const BACKUP_PATH_TITLE: &str = "$:/TiddlyDesktop/BackupPath";
+
+/// Path of a `file://` URL (percent-decoded, without query and fragment)
+fn file_url_to_path(url: &str) -> Option<PathBuf> {
+    let rest = url.strip_prefix("file://")?;
+    let rest = rest.strip_prefix("localhost").unwrap_or(rest);
+    let rest = rest.split(['?', '#']).next().unwrap_or(rest);
+    let decoded = urlencoding::decode(rest).ok()?;
+    // file:///C:/Users/... on Windows
+    let bytes = decoded.as_bytes();
+    let path = if bytes.len() >= 3 && bytes[0] == b'/' && bytes[1].is_ascii_alphabetic() && bytes[2] == b':' {
+        &decoded[1..]
+    } else {
+        &decoded[..]
+    };
+    (!path.is_empty()).then(|| PathBuf::from(path))
+}
+
+/// Paths of all `file://` URLs in a config file, in order and without duplicates
+fn file_urls_in(text: &str) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("file://") {
+        let url = &rest[start..];
+        let end = url
+            .find(|c: char| c.is_whitespace() || matches!(c, '"' | '\'' | '<' | '>' | ']' | ','))
+            .unwrap_or(url.len());
+        if let Some(path) = file_url_to_path(&url[..end]) {
+            if !paths.contains(&path) {
+                paths.push(path);
+            }
+        }
+        rest = &url[end..];
+    }
+    paths
+}
+
+/// Data dir of classic TiddlyDesktop, if it is installed
+pub fn find_classic_dir() -> Option<PathBuf> {
+    let candidates = if cfg!(target_os = "windows") {
+        vec![dirs::data_local_dir()?.join("TiddlyDesktop").join("User Data").join("Default")]
+    } else {
+        let config = dirs::config_dir()?;
+        vec![config.join("TiddlyDesktop").join("Default"), config.join("TiddlyDesktop")]
+    };
+    candidates.into_iter().find(|dir| dir.join("user-config-tiddlers").is_dir())
+}
+
+/// A tiddler of the classic config (all fields as strings, `text` included)
+type Tiddler = HashMap<String, String>;
+
+/// The tiddler of a `.tid` file: `name: value` lines, a blank line, the text
+fn parse_tid(content: &str) -> Tiddler {
+    let mut tiddler = Tiddler::new();
+    let (header, text) = content.split_once("\n\n").unwrap_or((content, ""));
+    for line in header.lines() {
+        if let Some((name, value)) = line.split_once(':') {
+            tiddler.insert(name.trim().to_string(), value.trim().to_string());
+        }
+    }
+    tiddler.insert("text".to_string(), text.trim_end_matches('\n').to_string());
+    tiddler
+}
+
+/// The tiddlers of a `.json` file (an array of tiddlers, or a single one)
+fn parse_json(content: &str) -> Vec<Tiddler> {
+    let Ok(value) = serde_json::from_str::<serde_json::Value>(content) else {
+        return Vec::new();
+    };
+    let items = match value {
+        serde_json::Value::Array(items) => items,
+        item => vec![item],
+    };
+    items
+        .into_iter()
+        .filter_map(|item| {
+            let fields = item.as_object()?;
+            Some(
+                fields
+                    .iter()
+                    .map(|(name, value)| {
+                        let value = value.as_str().map(str::to_string).unwrap_or_else(|| value.to_string());
+                        (name.clone(), value)
+                    })
+                    .collect(),
+            )
+        })
+        .collect()
+}
+
+/// The backup setting of classic TiddlyDesktop
+#[derive(Debug, PartialEq)]
+enum ClassicBackups {
+    Off,
+    /// A fixed folder (patterns with `$filename$` etc. keep the default folder)
+    Folder(PathBuf),
+}
+
+/// What was found in a classic TiddlyDesktop config
+#[derive(Debug, Default)]
+struct ClassicConfig {
+    /// `(path, is_folder)` of the listed wikis that still exist
+    wikis: Vec<(PathBuf, bool)>,
+    /// Favicon data URIs by wiki path
+    favicons: HashMap<PathBuf, String>,
+    window_states: HashMap<PathBuf, WindowState>,
+    backups: Option<ClassicBackups>,
+}
+
+fn number(tiddler: &Tiddler, names: &[&str]) -> Option<f64> {
+    names.iter().find_map(|name| tiddler.get(*name)?.trim().parse::<f64>().ok())
+}
+
+fn window_state(tiddler: &Tiddler) -> Option<WindowState> {
+    let width = number(tiddler, &["width"])?;
+    let height = number(tiddler, &["height"])?;
+    if width < 1.0 || height < 1.0 {
+        return None;
+    }
+    let maximized = ["maximized", "state"]
+        .iter()
+        .any(|name| tiddler.get(*name).is_some_and(|v| v == "yes" || v == "true" || v == "maximized"));
+    Some(WindowState {
+        width: width as u32,
+        height: height as u32,
+        x: number(tiddler, &["x", "left"]).unwrap_or(0.0) as i32,
+        y: number(tiddler, &["y", "top"]).unwrap_or(0.0) as i32,
+        monitor_name: None,
+        monitor_x: 0,
+        monitor_y: 0,
+        maximized,
+    })
+}
+
+fn favicon(tiddler: &Tiddler) -> Option<String> {
+    let content_type = tiddler.get("type").filter(|t| t.starts_with("image/"))?;
+    let text = tiddler.get("text").map(|t| t.trim()).filter(|t| !t.is_empty())?;
+    Some(format!("data:{};base64,{}", content_type, text))
+}
+
+fn backups(tiddler: &Tiddler) -> ClassicBackups {
+    let text = tiddler.get("text").map(|t| t.trim()).unwrap_or("");
+    if text.is_empty() {
+        ClassicBackups::Off
+    } else {
+        ClassicBackups::Folder(PathBuf::from(text))
+    }
+}
+
+/// Take over what a tiddler holds
+fn read_tiddler(config: &mut ClassicConfig, tiddler: &Tiddler) {
+    let Some(title) = tiddler.get("title") else {
+        return;
+    };
+    if title == BACKUP_PATH_TITLE {
+        config.backups = Some(backups(tiddler));
+        return;
+    }
+    let Some(wiki) = file_urls_in(title).into_iter().next() else {
+        return;
+    };
+    if let Some(favicon) = favicon(tiddler) {
+        config.favicons.insert(wiki, favicon);
+    } else if let Some(state) = window_state(tiddler) {
+        config.window_states.insert(wiki, state);
+    }
+}
+
+/// Read a classic TiddlyDesktop data dir (or any folder of config files)
+fn read_classic_config(dir: &Path) -> ClassicConfig {
+    let config_dir = dir.join("user-config-tiddlers");
+    let config_dir = if config_dir.is_dir() { config_dir } else { dir.to_path_buf() };
+    let mut config = ClassicConfig::default();
+    let mut seen = HashSet::new();
+    let mut pending = vec![config_dir];
+    while let Some(dir) = pending.pop() {
+        let Ok(read_dir) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in read_dir.flatten() {
+            let path = entry.path();
+            let Ok(meta) = entry.metadata() else {
+                continue;
+            };
+            if meta.is_dir() {
+                pending.push(path);
+                continue;
+            }
+            if meta.len() > MAX_CLASSIC_FILE_BYTES {
+                continue;
+            }
+            let Ok(bytes) = std::fs::read(&path) else {
+                continue;
+            };
+            let content = String::from_utf8_lossy(&bytes);
+            match path.extension().and_then(|e| e.to_str()) {
+                Some("tid") => read_tiddler(&mut config, &parse_tid(&content)),
+                Some("json") => parse_json(&content).iter().for_each(|t| read_tiddler(&mut config, t)),
+                _ => {}
+            }
+            for wiki in file_urls_in(&content) {
+                let key = wiki.to_string_lossy().to_lowercase();
+                if seen.contains(&key) {
+                    continue;
+                }
+                let is_html = wiki
+                    .extension()
+                    .map(|e| e.eq_ignore_ascii_case("html") || e.eq_ignore_ascii_case("htm"))
+                    .unwrap_or(false);
+                if is_html && wiki.is_file() {
+                    seen.insert(key);
+                    config.wikis.push((wiki, false));
+                } else if wiki.is_dir() && utils::is_wiki_folder(&wiki) {
+                    seen.insert(key);
+                    config.wikis.push((wiki, true));
+                }
+            }
+        }
+    }
+    config
+}
+
+/// What an import of classic TiddlyDesktop did
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClassicImportReport {
+    /// The wikis added to the wiki list (and those already in it or failed)
+    #[serde(flatten)]
+    pub wikis: crate::batch_register::RegisterSummary,
+    /// Favicons taken over
+    pub favicons: usize,
+    /// Window sizes and positions taken over
+    pub window_states: usize,
+    /// Wikis given the classic backup setting
+    pub backup_settings: usize,
+}
+
+/// Import the wiki list of classic TiddlyDesktop (its data dir, or `dir` when
+/// the user picked one) with the wikis' favicons, window states and backup
+/// settings
+#[tauri::command]
+pub async fn import_classic_wikis(app: tauri::AppHandle, dir: Option<String>) -> Result<ClassicImportReport, String> {
+    let dir = match dir {
+        Some(dir) => PathBuf::from(dir),
+        None => find_classic_dir().ok_or_else(|| "Classic TiddlyDesktop was not found".to_string())?,
+    };
+    let config = tokio::task::spawn_blocking(move || read_classic_config(&dir))
+        .await
+        .map_err(|e| format!("Failed to spawn blocking task: {}", e))?;
+    let classic_paths: Vec<PathBuf> = config.wikis.iter().map(|(path, _)| path.clone()).collect();
+    let summary = crate::batch_register::register_wikis(&app, config.wikis).await?;
+    let find = |map_path: &str| classic_paths.iter().find(|p| utils::paths_equal(&p.to_string_lossy(), map_path));
+
+    let mut report = ClassicImportReport { wikis: summary, favicons: 0, window_states: 0, backup_settings: 0 };
+    let mut entries = crate::wiki_storage::load_recent_files_from_disk(&app);
+    for entry in entries.iter_mut() {
+        let Some(classic_path) = find(&entry.path) else {
+            continue;
+        };
+        if entry.favicon.is_none() {
+            if let Some(favicon) = config.favicons.get(classic_path) {
+                entry.favicon = Some(favicon.clone());
+                report.favicons += 1;
+            }
+        }
+        let newly_added = report.wikis.added.iter().any(|added| utils::paths_equal(&added.path, &entry.path));
+        match &config.backups {
+            Some(ClassicBackups::Off) if newly_added && entry.backups_enabled => {
+                entry.backups_enabled = false;
+                report.backup_settings += 1;
+            }
+            Some(ClassicBackups::Folder(folder)) if newly_added && !entry.is_folder && folder.is_dir() => {
+                entry.backup_dir = Some(folder.to_string_lossy().into_owned());
+                report.backup_settings += 1;
+            }
+            _ => {}
+        }
+    }
+    if report.favicons > 0 || report.backup_settings > 0 {
+        crate::wiki_storage::save_recent_files_to_disk(&app, &entries)?;
+    }
+
+    if !config.window_states.is_empty() {
+        let mut configs = crate::wiki_storage::load_wiki_configs(&app)?;
+        for entry in &entries {
+            let Some(state) = find(&entry.path).and_then(|p| config.window_states.get(p)) else {
+                continue;
+            };
+            if !configs.window_states.contains_key(&entry.path) {
+                configs.window_states.insert(entry.path.clone(), state.clone());
+                report.window_states += 1;
+            }
+        }
+        if report.window_states > 0 {
+            crate::wiki_storage::save_wiki_configs(&app, &configs)?;
+        }
+    }
+
+    eprintln!(
+        "[TiddlyDesktop] Classic import: {} wikis added, {} favicons, {} window states, {} backup settings",
+        report.wikis.added.len(),
+        report.favicons,
+        report.window_states,
+        report.backup_settings
+    );
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_url_to_path() {
+        assert_eq!(
+            file_url_to_path("file:///home/me/My%20Notes.html"),
+            Some(PathBuf::from("/home/me/My Notes.html"))
+        );
+        assert_eq!(
+            file_url_to_path("file:///C:/Users/me/wiki.html#Tiddler"),
+            Some(PathBuf::from("C:/Users/me/wiki.html"))
+        );
+        assert_eq!(file_url_to_path("file://localhost/tmp/w.html"), Some(PathBuf::from("/tmp/w.html")));
+        assert_eq!(file_url_to_path("https://example.com/w.html"), None);
+        assert_eq!(file_url_to_path("file://"), None);
+    }
+
+    #[test]
+    fn test_file_urls_in() {
+        let text = r#"[{"url":"file:///a/one.html","title":"One"},{"url":"file:///a/two.html"}]
+url: file:///a/one.html
+"#;
+        assert_eq!(
+            file_urls_in(text),
+            vec![PathBuf::from("/a/one.html"), PathBuf::from("/a/two.html")]
+        );
+        assert!(file_urls_in("no wikis here").is_empty());
+    }
+
+    #[test]
+    fn test_read_tiddlers() {
+        let mut config = ClassicConfig::default();
+        let window = parse_tid("title: $:/TiddlyDesktop/WindowState/file:///a/one.html\nleft: 10\ntop: 20\nwidth: 800\nheight: 600\n\n");
+        read_tiddler(&mut config, &window);
+        let icon = parse_json(r#"[{"title":"$:/favicon/file:///a/one.html","type":"image/png","text":"iVBORw0KGgo="}]"#);
+        read_tiddler(&mut config, &icon[0]);
+        read_tiddler(&mut config, &parse_tid("title: $:/TiddlyDesktop/BackupPath\n\n"));
+
+        let wiki = PathBuf::from("/a/one.html");
+        let state = &config.window_states[&wiki];
+        assert_eq!((state.x, state.y, state.width, state.height), (10, 20, 800, 600));
+        assert_eq!(config.favicons[&wiki], "data:image/png;base64,iVBORw0KGgo=");
+        assert_eq!(config.backups, Some(ClassicBackups::Off));
+    }
+}
@@ -0,0 +1,216 @@
+//! Wiki maintenance from scripts (desktop)
+//!
+//! For cron jobs and shell scripts looking after many wikis:
+//! - `--list-wikis`: the wiki list as JSON (path, name, whether it's a folder
+//!   wiki and whether it's open)
+//! - `--backup <wiki|all>`: back up a wiki, or every wiki of the list, into
+//!   its backup folder (prints where each backup went)
+//! - `--sync-now <wiki>`: sync an open wiki with its LAN sync peers now
+//!
+//! They go to the running instance through the control API (see `automation`)
+//! when it's listening, with the token from the data directory. Otherwise
+//! listing and backing up run on their own like `--headless` commands;
+//! syncing needs the instance, with the wiki open.
+//!
+//! Exit status: 0 on success, 1 when the command failed, 2 for usage errors.
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use serde_json::Value;
+
+use crate::headless;
+
+pub const USAGE: &str = "Usage:
+  tiddlydesktop-rs --list-wikis
+  tiddlydesktop-rs --backup <wiki|all>
+  tiddlydesktop-rs --sync-now <wiki>";
+
+/// Backups of big folder wikis are rendered first
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(600);
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    ListWikis,
+    /// None: every wiki of the list
+    Backup(Option<PathBuf>),
+    SyncNow(PathBuf),
+}
+
+/// What's left to do after trying the running instance
+pub enum Outcome {
+    Exit(i32),
+    /// No instance answered: run this without one
+    Standalone(headless::Command),
+}
+
+/// Why the running instance didn't do it
+enum RemoteError {
+    /// Nothing listening on the control port (or no token yet)
+    Unreachable,
+    Failed(String),
+}
+
+/// The command, if one of the flags is given
+pub fn parse_args(args: &[String]) -> Option<Result<Command, String>> {
+    let value = |flag: &str| {
+        let i = args.iter().position(|arg| arg == flag)?;
+        Some(
+            args.get(i + 1)
+                .filter(|value| !value.starts_with("--"))
+                .cloned()
+                .ok_or_else(|| format!("{} needs a value", flag)),
+        )
+    };
+    if args.iter().any(|arg| arg == "--list-wikis") {
+        return Some(Ok(Command::ListWikis));
+    }
+    if let Some(wiki) = value("--backup") {
+        return Some(wiki.map(|wiki| Command::Backup((wiki != "all").then(|| PathBuf::from(wiki)))));
+    }
+    value("--sync-now").map(|wiki| wiki.map(|wiki| Command::SyncNow(PathBuf::from(wiki))))
+}
+
+/// The control API of the running instance
+struct ControlApi {
+    port: u16,
+    token: String,
+    client: reqwest::Client,
+}
+
+impl ControlApi {
+    /// None when no instance ever started the API (there's no token)
+    fn find(args: &[String]) -> Option<Self> {
+        let data_dir = crate::portable_data_dir().or_else(crate::instance::system_data_dir)?;
+        let token = std::fs::read_to_string(data_dir.join(crate::serve_all::TOKEN_FILE)).ok()?;
+        let client = reqwest::Client::builder()
+            .connect_timeout(CONNECT_TIMEOUT)
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .ok()?;
+        Some(Self {
+            port: crate::serve_all::control_port(args),
+            token: token.trim().to_string(),
+            client,
+        })
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str, wiki: Option<&str>) -> Result<Value, RemoteError> {
+        let mut request = self
+            .client
+            .request(method, format!("http://127.0.0.1:{}{}", self.port, path))
+            .bearer_auth(&self.token);
+        if let Some(wiki) = wiki {
+            request = request.query(&[("wiki", wiki)]);
+        }
+        tauri::async_runtime::block_on(async {
+            let response = request.send().await.map_err(|e| {
+                if e.is_connect() {
+                    RemoteError::Unreachable
+                } else {
+                    RemoteError::Failed(e.to_string())
+                }
+            })?;
+            let status = response.status();
+            let body = response.text().await.map_err(|e| RemoteError::Failed(e.to_string()))?;
+            if !status.is_success() {
+                return Err(RemoteError::Failed(body));
+            }
+            serde_json::from_str(&body).map_err(|e| RemoteError::Failed(e.to_string()))
+        })
+    }
+
+    fn backup(&self, wiki: &str) -> Result<String, RemoteError> {
+        let result = self.request(reqwest::Method::POST, "/backup", Some(wiki))?;
+        Ok(result["message"].as_str().unwrap_or_default().to_string())
+    }
+
+    fn run(&self, command: &Command) -> Result<String, RemoteError> {
+        match command {
+            Command::ListWikis => {
+                let wikis = self.request(reqwest::Method::GET, "/wikis", None)?;
+                serde_json::to_string_pretty(&wikis).map_err(|e| RemoteError::Failed(e.to_string()))
+            }
+            Command::Backup(Some(wiki)) => self.backup(&headless::absolute(wiki).to_string_lossy()),
+            Command::Backup(None) => {
+                let wikis: Vec<String> = self
+                    .request(reqwest::Method::GET, "/wikis", None)?
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|wiki| Some(wiki["path"].as_str()?.to_string()))
+                    .collect();
+                headless::backup_each(&wikis, |wiki| {
+                    self.backup(wiki).map_err(|e| match e {
+                        RemoteError::Unreachable => "TiddlyDesktop stopped answering".to_string(),
+                        RemoteError::Failed(e) => e,
+                    })
+                })
+                .map_err(RemoteError::Failed)
+            }
+            Command::SyncNow(wiki) => {
+                let wiki = headless::absolute(wiki).to_string_lossy().into_owned();
+                self.request(reqwest::Method::POST, "/sync", Some(&wiki))?;
+                Ok(format!("Syncing {}", wiki))
+            }
+        }
+    }
+}
+
+/// Have the running instance carry out the command and print the result
+pub fn run(command: Command) -> Outcome {
+    let args: Vec<String> = std::env::args().collect();
+    let result = match ControlApi::find(&args) {
+        Some(api) => api.run(&command),
+        None => Err(RemoteError::Unreachable),
+    };
+    match result {
+        Ok(message) => {
+            println!("{}", message);
+            Outcome::Exit(0)
+        }
+        Err(RemoteError::Failed(e)) => {
+            eprintln!("{}", e);
+            Outcome::Exit(1)
+        }
+        Err(RemoteError::Unreachable) => match command {
+            Command::ListWikis => Outcome::Standalone(headless::Command::ListWikis),
+            Command::Backup(Some(wiki)) => Outcome::Standalone(headless::Command::Backup { wiki, output: None }),
+            Command::Backup(None) => Outcome::Standalone(headless::Command::BackupAll),
+            Command::SyncNow(_) => {
+                eprintln!(
+                    "Syncing needs TiddlyDesktop running with the automation API turned on and the wiki open"
+                );
+                Outcome::Exit(1)
+            }
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(parse_args(&args(&["app", "--headless", "backup", "w.html"])), None);
+        assert_eq!(parse_args(&args(&["app", "--list-wikis"])), Some(Ok(Command::ListWikis)));
+        assert_eq!(parse_args(&args(&["app", "--backup", "all"])), Some(Ok(Command::Backup(None))));
+        assert_eq!(
+            parse_args(&args(&["app", "--backup", "w.html", "--control-port", "9000"])),
+            Some(Ok(Command::Backup(Some(PathBuf::from("w.html")))))
+        );
+        assert_eq!(
+            parse_args(&args(&["app", "--sync-now", "w"])),
+            Some(Ok(Command::SyncNow(PathBuf::from("w"))))
+        );
+        assert!(parse_args(&args(&["app", "--backup"])).unwrap().is_err());
+        assert!(parse_args(&args(&["app", "--sync-now", "--control-port", "9000"])).unwrap().is_err());
+    }
+}
@@ -0,0 +1,389 @@
+//! Sync tool conflict copies of single-file wikis
+//!
+//! Syncthing, Dropbox, Nextcloud and friends keep both versions when a wiki was
+//! changed on two machines, e.g. `wiki (conflicted copy 2024-05-01).html` or
+//! `wiki.sync-conflict-20240501-101500-ABCDEFG.html`. This module:
+//! - Finds such siblings next to a registered wiki
+//! - Compares a copy with the wiki tiddler by tiddler (using `text_diff`)
+//! - Merges chosen tiddlers from the copy into the wiki and retires the copy
+
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+use crate::text_diff::{self, DiffHunk};
+use crate::tiddlywiki_html;
+use crate::utils;
+
+/// Tiddlers that only record UI state and are never worth merging
+const IGNORED_PREFIXES: &[&str] = &["$:/StoryList", "$:/HistoryList", "$:/temp/", "$:/state/"];
+
+const STORE_START: &str = r#"<script class="tiddlywiki-tiddler-store" type="application/json">"#;
+
+/// A conflict copy found next to a wiki
+#[derive(Clone, Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConflictCopy {
+    pub path: String,
+    pub filename: String,
+    /// "syncthing", "dropbox" or "other"
+    pub tool: &'static str,
+    /// RFC 3339 modification time
+    pub modified: String,
+    pub size: u64,
+}
+
+/// Conflict copies of one wiki in the wiki list
+#[derive(Clone, Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WikiConflicts {
+    pub wiki_path: String,
+    pub copies: Vec<ConflictCopy>,
+}
+
+/// A tiddler that differs between the wiki and the conflict copy
+#[derive(Clone, Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TiddlerConflict {
+    pub title: String,
+    /// "added" (only in the copy), "changed", or "removed" (only in the wiki)
+    pub kind: &'static str,
+    /// Fields whose values differ (excluding text)
+    pub changed_fields: Vec<String>,
+    /// TiddlyWiki `modified` timestamps of both versions
+    pub wiki_modified: Option<String>,
+    pub copy_modified: Option<String>,
+    /// Whether the copy's version is the more recently modified one
+    pub copy_is_newer: bool,
+    /// Line diff of the text, wiki version → copy version
+    pub diff: Vec<DiffHunk>,
+}
+
+/// Tiddler-level comparison of a wiki and one of its conflict copies
+#[derive(Clone, Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConflictComparison {
+    pub wiki_path: String,
+    pub copy_path: String,
+    pub identical: bool,
+    pub conflicts: Vec<TiddlerConflict>,
+}
+
+/// Recognise a conflict copy of the wiki file `wiki_name` by its file name.
+/// Returns the sync tool that (most likely) created it.
+fn conflict_tool(wiki_name: &str, candidate: &str) -> Option<&'static str> {
+    let (stem, _) = wiki_name.rsplit_once('.')?;
+    let (candidate_stem, ext) = candidate.rsplit_once('.')?;
+    if !(ext.eq_ignore_ascii_case("html") || ext.eq_ignore_ascii_case("htm")) || candidate == wiki_name {
+        return None;
+    }
+    let rest = candidate_stem.strip_prefix(stem)?;
+    // Syncthing: wiki.sync-conflict-20240501-101500-ABCDEFG.html
+    if rest.starts_with(".sync-conflict-") {
+        return Some("syncthing");
+    }
+    // Dropbox: "wiki (conflicted copy 2024-05-01).html" / "wiki (Name's conflicted copy 2024-05-01).html"
+    let rest = rest.trim_start();
+    if rest.starts_with('(') && rest.ends_with(')') {
+        let inner = rest.to_lowercase();
+        if inner.contains("conflicted copy") {
+            return Some("dropbox");
+        }
+        // Nextcloud/ownCloud and others: "wiki (conflict 2024-05-01-101500).html"
+        if inner.contains("conflict") {
+            return Some("other");
+        }
+    }
+    None
+}
+
+/// Find conflict copies next to a single-file wiki, newest first
+pub fn find_conflict_copies(wiki_path: &Path) -> Vec<ConflictCopy> {
+    let (dir, wiki_name) = match (wiki_path.parent(), wiki_path.file_name().and_then(|n| n.to_str())) {
+        (Some(d), Some(n)) => (d, n),
+        _ => return Vec::new(),
+    };
+    let entries = match std::fs::read_dir(dir) {
+        Ok(e) => e,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut copies: Vec<(std::time::SystemTime, ConflictCopy)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let filename = entry.file_name().to_string_lossy().into_owned();
+            let tool = conflict_tool(wiki_name, &filename)?;
+            let meta = entry.metadata().ok().filter(|m| m.is_file())?;
+            let modified = meta.modified().ok()?;
+            Some((modified, ConflictCopy {
+                path: entry.path().to_string_lossy().into_owned(),
+                filename,
+                tool,
+                modified: chrono::DateTime::<chrono::Local>::from(modified).to_rfc3339(),
+                size: meta.len(),
+            }))
+        })
+        .collect();
+    copies.sort_by(|a, b| b.0.cmp(&a.0));
+    copies.into_iter().map(|(_, c)| c).collect()
+}
+
+fn is_ignored(title: &str) -> bool {
+    IGNORED_PREFIXES.iter().any(|p| title.starts_with(p))
+}
+
+/// Tiddlers of a wiki keyed by title (later stores override earlier ones, as in TiddlyWiki)
+fn read_tiddlers(path: &Path) -> Result<HashMap<String, Value>, String> {
+    let html = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let tiddlers = tiddlywiki_html::extract_all_tiddlers_from_html(&html);
+    if tiddlers.is_empty() {
+        return Err(format!(
+            "No tiddlers found in {} (encrypted and pre-5.2 wikis can't be compared)",
+            path.display()
+        ));
+    }
+    Ok(tiddlers
+        .into_iter()
+        .filter_map(|t| {
+            let title = t.get("title")?.as_str()?.to_string();
+            Some((title, t))
+        })
+        .filter(|(title, _)| !is_ignored(title))
+        .collect())
+}
+
+fn field_str(tiddler: &Value, field: &str) -> Option<String> {
+    tiddler.get(field).and_then(|v| v.as_str()).map(|s| s.to_string())
+}
+
+/// Compare the tiddlers of a wiki with those of a conflict copy
+fn compare_tiddlers(wiki: &HashMap<String, Value>, copy: &HashMap<String, Value>) -> Vec<TiddlerConflict> {
+    let titles: BTreeSet<&String> = wiki.keys().chain(copy.keys()).collect();
+    let mut conflicts = Vec::new();
+
+    for title in titles {
+        let (kind, wiki_tiddler, copy_tiddler) = match (wiki.get(title), copy.get(title)) {
+            (Some(w), Some(c)) if w == c => continue,
+            (Some(w), Some(c)) => ("changed", Some(w), Some(c)),
+            (None, Some(c)) => ("added", None, Some(c)),
+            (Some(w), None) => ("removed", Some(w), None),
+            (None, None) => continue,
+        };
+
+        let mut changed_fields: BTreeSet<String> = BTreeSet::new();
+        if let (Some(Value::Object(w)), Some(Value::Object(c))) = (wiki_tiddler, copy_tiddler) {
+            for key in w.keys().chain(c.keys()) {
+                if key != "text" && w.get(key) != c.get(key) {
+                    changed_fields.insert(key.clone());
+                }
+            }
+        }
+
+        let wiki_modified = wiki_tiddler.and_then(|t| field_str(t, "modified"));
+        let copy_modified = copy_tiddler.and_then(|t| field_str(t, "modified"));
+        // TiddlyWiki timestamps (YYYYMMDDHHMMSSmmm) sort lexicographically
+        let copy_is_newer = match (&wiki_modified, &copy_modified) {
+            (Some(w), Some(c)) => c > w,
+            (None, Some(_)) => true,
+            _ => kind == "added",
+        };
+        let wiki_text = wiki_tiddler.and_then(|t| field_str(t, "text")).unwrap_or_default();
+        let copy_text = copy_tiddler.and_then(|t| field_str(t, "text")).unwrap_or_default();
+
+        conflicts.push(TiddlerConflict {
+            title: title.clone(),
+            kind,
+            changed_fields: changed_fields.into_iter().collect(),
+            wiki_modified,
+            copy_modified,
+            copy_is_newer,
+            diff: text_diff::diff_lines(&wiki_text, &copy_text),
+        });
+    }
+    conflicts
+}
+
+/// Append a tiddler store with `tiddlers` after the wiki's last store.
+/// TiddlyWiki loads stores in order, so these override the originals; the next
+/// save from TiddlyWiki folds them into a single store again.
+fn append_tiddler_store(html: &str, tiddlers: &[Value]) -> Result<String, String> {
+    let start = html.rfind(STORE_START).ok_or("The wiki has no tiddler store")?;
+    let end = html[start..]
+        .find("</script>")
+        .map(|i| start + i + "</script>".len())
+        .ok_or("The wiki's tiddler store is not terminated")?;
+    let json = serde_json::to_string(tiddlers)
+        .map_err(|e| format!("Failed to serialize tiddlers: {}", e))?
+        // Same escaping TiddlyWiki uses, so tiddler text can't close the script tag
+        .replace('<', "\\u003C");
+
+    let mut merged = String::with_capacity(html.len() + json.len() + 128);
+    merged.push_str(&html[..end]);
+    merged.push('\n');
+    merged.push_str(STORE_START);
+    merged.push_str(&json);
+    merged.push_str("</script>");
+    merged.push_str(&html[end..]);
+    Ok(merged)
+}
+
+/// Validate that `copy_path` is a conflict copy of `wiki_path`
+fn validate_copy(wiki_path: &Path, copy_path: &str) -> Result<PathBuf, String> {
+    let copy = crate::drag_drop::sanitize::validate_wiki_path(copy_path)?;
+    let is_copy = find_conflict_copies(wiki_path)
+        .iter()
+        .any(|c| utils::paths_equal(&c.path, copy_path));
+    if !is_copy {
+        return Err("Not a conflict copy of this wiki".to_string());
+    }
+    Ok(copy)
+}
+
+/// Find conflict copies of every single-file wiki in the wiki list
+#[tauri::command]
+pub async fn get_conflict_copies(app: tauri::AppHandle) -> Result<Vec<WikiConflicts>, String> {
+    let entries = crate::wiki_storage::load_recent_files_from_disk(&app);
+    tokio::task::spawn_blocking(move || {
+        entries
+            .into_iter()
+            .filter(|e| !e.is_folder && !e.path.starts_with("content://") && !e.path.starts_with('{'))
+            .filter_map(|e| {
+                let copies = find_conflict_copies(Path::new(&e.path));
+                (!copies.is_empty()).then_some(WikiConflicts { wiki_path: e.path, copies })
+            })
+            .collect()
+    })
+    .await
+    .map_err(|e| format!("Failed to spawn blocking task: {}", e))
+}
+
+/// Compare a wiki with one of its conflict copies, tiddler by tiddler
+#[tauri::command]
+pub async fn compare_conflict_copy(wiki_path: String, copy_path: String) -> Result<ConflictComparison, String> {
+    let wiki = crate::drag_drop::sanitize::validate_wiki_path(&wiki_path)?;
+    let copy = validate_copy(&wiki, &copy_path)?;
+    tokio::task::spawn_blocking(move || {
+        let conflicts = compare_tiddlers(&read_tiddlers(&wiki)?, &read_tiddlers(&copy)?);
+        Ok(ConflictComparison {
+            wiki_path,
+            copy_path,
+            identical: conflicts.is_empty(),
+            conflicts,
+        })
+    })
+    .await
+    .map_err(|e| format!("Failed to spawn blocking task: {}", e))?
+}
+
+/// Take the chosen tiddlers from a conflict copy into the wiki.
+/// The wiki is backed up first and must not be open. With `retire_copy`, the
+/// copy is moved into the wiki's backup directory afterwards.
+#[tauri::command]
+pub async fn merge_conflict_copy(
+    app: tauri::AppHandle,
+    wiki_path: String,
+    copy_path: String,
+    titles: Vec<String>,
+    retire_copy: bool,
+) -> Result<usize, String> {
+    let wiki = crate::drag_drop::sanitize::validate_wiki_path(&wiki_path)?;
+    let copy = validate_copy(&wiki, &copy_path)?;
+    if crate::is_wiki_open(app.clone(), wiki_path.clone()) {
+        return Err("Close the wiki before merging its conflict copy".to_string());
+    }
+
+    let custom_backup_dir = crate::get_wiki_backup_dir(&app, &wiki_path);
+    let backup_count = crate::wiki_storage::get_wiki_backup_count(&app, &wiki_path);
+
+    let taken = if titles.is_empty() {
+        0
+    } else {
+        let copy_tiddlers = read_tiddlers(&copy)?;
+        let chosen: Vec<Value> = titles
+            .iter()
+            .filter_map(|t| copy_tiddlers.get(t).cloned())
+            .collect();
+        let html = std::fs::read_to_string(&wiki)
+            .map_err(|e| format!("Failed to read wiki: {}", e))?;
+        let merged = append_tiddler_store(&html, &chosen)?;
+
+        crate::create_backup(&wiki, custom_backup_dir.as_deref(), backup_count).await?;
+        let temp = wiki.with_extension("html.merging");
+        std::fs::write(&temp, merged).map_err(|e| format!("Failed to write merged wiki: {}", e))?;
+        std::fs::rename(&temp, &wiki).map_err(|e| {
+            let _ = std::fs::remove_file(&temp);
+            format!("Failed to replace wiki: {}", e)
+        })?;
+        chosen.len()
+    };
+
+    if retire_copy {
+        let backup_dir = tiddlydesktop_core::backup::backup_dir_for_wiki(&wiki, custom_backup_dir.as_deref())
+            .ok_or("No backup directory for this wiki")?;
+        std::fs::create_dir_all(&backup_dir)
+            .map_err(|e| format!("Failed to create backup directory: {}", e))?;
+        let name = copy.file_name().map(|n| n.to_os_string()).unwrap_or_default();
+        std::fs::rename(&copy, backup_dir.join(name))
+            .map_err(|e| format!("Failed to move conflict copy to backups: {}", e))?;
+    }
+
+    eprintln!("[TiddlyDesktop] Merged {} tiddlers from conflict copy {}", taken, copy_path);
+    Ok(taken)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_recognises_conflict_copy_names() {
+        assert_eq!(conflict_tool("wiki.html", "wiki.sync-conflict-20240501-101500-ABCDEFG.html"), Some("syncthing"));
+        assert_eq!(conflict_tool("wiki.html", "wiki (conflicted copy 2024-05-01).html"), Some("dropbox"));
+        assert_eq!(conflict_tool("wiki.html", "wiki (Jane's conflicted copy 2024-05-01).html"), Some("dropbox"));
+        assert_eq!(conflict_tool("wiki.html", "wiki (conflict 2024-05-01-101500).html"), Some("other"));
+        assert_eq!(conflict_tool("wiki.html", "wiki.html"), None);
+        assert_eq!(conflict_tool("wiki.html", "wiki (1).html"), None);
+        assert_eq!(conflict_tool("wiki.html", "wiki.sync-conflict-20240501-101500-ABCDEFG.txt"), None);
+        assert_eq!(conflict_tool("wiki.html", "otherwiki (conflicted copy 2024-05-01).html"), None);
+    }
+
+    #[test]
+    fn test_compares_tiddlers() {
+        let wiki: HashMap<String, Value> = [
+            ("Same", json!({"title": "Same", "text": "a"})),
+            ("Edited", json!({"title": "Edited", "text": "old\n", "modified": "20240501100000000"})),
+            ("OnlyWiki", json!({"title": "OnlyWiki"})),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v))
+        .collect();
+        let copy: HashMap<String, Value> = [
+            ("Same", json!({"title": "Same", "text": "a"})),
+            ("Edited", json!({"title": "Edited", "text": "new\n", "modified": "20240502100000000", "tags": "x"})),
+            ("OnlyCopy", json!({"title": "OnlyCopy"})),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v))
+        .collect();
+
+        let conflicts = compare_tiddlers(&wiki, &copy);
+        let kinds: Vec<(&str, &str)> = conflicts.iter().map(|c| (c.title.as_str(), c.kind)).collect();
+        assert_eq!(kinds, vec![("Edited", "changed"), ("OnlyCopy", "added"), ("OnlyWiki", "removed")]);
+        assert!(conflicts[0].copy_is_newer);
+        assert_eq!(conflicts[0].changed_fields, vec!["modified".to_string(), "tags".to_string()]);
+        assert!(!conflicts[0].diff.is_empty());
+    }
+
+    #[test]
+    fn test_appended_store_overrides_earlier_tiddlers() {
+        let html = format!("<html>{}[{{\"title\":\"A\",\"text\":\"old\"}}]</script><div></div></html>", STORE_START);
+        let merged = append_tiddler_store(&html, &[json!({"title": "A", "text": "</script> new"})]).unwrap();
+        let tiddlers = tiddlywiki_html::extract_all_tiddlers_from_html(&merged);
+        assert_eq!(tiddlers.len(), 2);
+        assert_eq!(tiddlers[1]["text"], "</script> new");
+        assert!(merged.ends_with("<div></div></html>"));
+    }
+}
@@ -0,0 +1,167 @@
+//! Custom CSS/JS snippets injected into wiki windows
+//!
+//! The user attaches snippets to a wiki on the landing page; they are stored in
+//! `custom_snippets.json` in the data dir. When a wiki window is created, the
+//! wiki's snippets are passed to its initialization script
+//! (`window.__TD_CUSTOM_SNIPPETS__`, see `init_script/custom_snippets.js`):
+//! CSS is added as style elements, JS runs once the wiki has loaded. Changes
+//! apply the next time the wiki is opened.
+//!
+//! A snippet that keeps a wiki from booting can't be fixed from inside that
+//! wiki, so `disabled` turns all injections off until it is switched back on.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::utils;
+
+const CONFIG_FILE: &str = "custom_snippets.json";
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SnippetKind {
+    Css,
+    Js,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Snippet {
+    id: u32,
+    wiki_path: String,
+    name: String,
+    kind: SnippetKind,
+    code: String,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnippetsConfig {
+    /// Inject no snippets into any wiki (safety switch)
+    #[serde(default)]
+    disabled: bool,
+    #[serde(default)]
+    snippets: Vec<Snippet>,
+}
+
+fn config_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(crate::get_data_dir(app)?.join(CONFIG_FILE))
+}
+
+fn load_from(path: &Path) -> SnippetsConfig {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn load(app: &tauri::AppHandle) -> SnippetsConfig {
+    config_path(app).map(|path| load_from(&path)).unwrap_or_default()
+}
+
+fn save(app: &tauri::AppHandle, config: &SnippetsConfig) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(config).map_err(|e| e.to_string())?;
+    std::fs::write(config_path(app)?, json).map_err(|e| format!("Failed to save custom snippets: {}", e))
+}
+
+/// The snippets injected into a wiki (none while injections are disabled)
+fn injected<'a>(config: &'a SnippetsConfig, wiki_path: &'a str) -> impl Iterator<Item = &'a Snippet> {
+    config
+        .snippets
+        .iter()
+        .filter(move |snippet| !config.disabled && utils::paths_equal(&snippet.wiki_path, wiki_path))
+}
+
+/// The line of a wiki's initialization script that hands it its snippets
+/// (empty if it has none). Reads the config from the data dir, so it also works
+/// in wiki processes.
+pub fn init_script(wiki_path: &str) -> String {
+    let Some(data_dir) = crate::DATA_DIR.get() else {
+        return String::new();
+    };
+    let config = load_from(&data_dir.join(CONFIG_FILE));
+    let snippets: Vec<_> = injected(&config, wiki_path)
+        .map(|snippet| serde_json::json!({ "name": snippet.name, "kind": snippet.kind, "code": snippet.code }))
+        .collect();
+    if snippets.is_empty() {
+        return String::new();
+    }
+    // serde_json escapes the code into string literals
+    let json = serde_json::to_string(&snippets).unwrap_or_else(|_| "[]".to_string());
+    format!("window.__TD_CUSTOM_SNIPPETS__ = {};\n", json)
+}
+
+/// The custom snippets, all or those of one wiki, and whether they are disabled
+#[tauri::command]
+pub fn list_custom_snippets(app: tauri::AppHandle, wiki_path: Option<String>) -> SnippetsConfig {
+    let mut config = load(&app);
+    if let Some(wiki_path) = wiki_path {
+        config.snippets.retain(|snippet| utils::paths_equal(&snippet.wiki_path, &wiki_path));
+    }
+    config
+}
+
+/// Attach a CSS or JS snippet to a wiki
+#[tauri::command]
+pub fn add_custom_snippet(
+    app: tauri::AppHandle,
+    wiki_path: String,
+    name: String,
+    kind: SnippetKind,
+    code: String,
+) -> Result<Snippet, String> {
+    if code.trim().is_empty() {
+        return Err("The snippet is empty".to_string());
+    }
+    let mut config = load(&app);
+    let id = config.snippets.iter().map(|s| s.id).max().unwrap_or(0) + 1;
+    let name = match name.trim() {
+        "" => format!("Snippet {}", id),
+        name => name.to_string(),
+    };
+    let snippet = Snippet { id, wiki_path, name, kind, code };
+    config.snippets.push(snippet.clone());
+    save(&app, &config)?;
+    Ok(snippet)
+}
+
+/// Remove a custom snippet
+#[tauri::command]
+pub fn remove_custom_snippet(app: tauri::AppHandle, id: u32) -> Result<(), String> {
+    let mut config = load(&app);
+    let count = config.snippets.len();
+    config.snippets.retain(|snippet| snippet.id != id);
+    if config.snippets.len() == count {
+        return Err("No such snippet".to_string());
+    }
+    save(&app, &config)
+}
+
+/// Turn all custom snippet injections off (or back on)
+#[tauri::command]
+pub fn set_custom_snippets_disabled(app: tauri::AppHandle, disabled: bool) -> Result<(), String> {
+    let mut config = load(&app);
+    config.disabled = disabled;
+    save(&app, &config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snippet(id: u32, wiki_path: &str) -> Snippet {
+        Snippet { id, wiki_path: wiki_path.to_string(), name: String::new(), kind: SnippetKind::Css, code: "body{}".to_string() }
+    }
+
+    #[test]
+    fn test_injected() {
+        let mut config = SnippetsConfig { disabled: false, snippets: vec![snippet(1, "/a.html"), snippet(2, "/b.html"), snippet(3, "/a.html")] };
+        let ids: Vec<u32> = injected(&config, "/a.html").map(|s| s.id).collect();
+        assert_eq!(ids, [1, 3]);
+
+        // The safety switch turns them all off
+        config.disabled = true;
+        assert_eq!(injected(&config, "/a.html").count(), 0);
+    }
+}
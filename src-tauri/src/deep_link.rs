@@ -0,0 +1,92 @@
+//! `tiddlydesktop://` deep links (desktop)
+//!
+//! `tiddlydesktop://open?path=<wiki>&tiddler=<title>` opens a wiki of the wiki
+//! list and navigates to a tiddler, from links in emails, notes or other apps.
+//! The OS passes the link like a wiki file (see `file_open`): on the command
+//! line on Windows and Linux, handed over to the running instance if there is
+//! one, and as an Apple Event on macOS.
+//!
+//! Only wikis already in the wiki list open this way; a link can't make the
+//! app run an unknown HTML file. An open wiki is focused instead of opened
+//! twice (`open_wiki_window`). The tiddler is queued like a tray quick action
+//! (`quick_actions`), so the wiki navigates to it whether it was open or not.
+
+use crate::utils;
+
+/// The URL scheme, registered by the NSIS installer, the MSI
+/// (`plugins.deep-link` in tauri.conf.json), Info.plist and the Linux
+/// .desktop file
+pub const SCHEME: &str = "tiddlydesktop";
+
+#[derive(Debug, PartialEq)]
+pub struct DeepLink {
+    pub path: String,
+    pub tiddler: Option<String>,
+}
+
+/// Whether a command-line argument or opened URL is a deep link
+pub fn is_deep_link(arg: &str) -> bool {
+    arg.get(..SCHEME.len() + 1)
+        .is_some_and(|prefix| prefix.eq_ignore_ascii_case(&format!("{}:", SCHEME)))
+}
+
+/// The wiki and tiddler of a `tiddlydesktop://open?...` link
+pub fn parse(url: &str) -> Result<DeepLink, String> {
+    let url = tauri::Url::parse(url).map_err(|e| format!("Invalid link {}: {}", url, e))?;
+    if url.scheme() != SCHEME {
+        return Err(format!("Not a {} link: {}", SCHEME, url));
+    }
+    // `tiddlydesktop:open?...` has the action as path instead of host
+    let action = url.host_str().unwrap_or_else(|| url.path()).trim_matches('/');
+    if action != "open" {
+        return Err(format!("Unknown link action: {}", action));
+    }
+    let param = |name: &str| {
+        url.query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    };
+    let path = param("path").ok_or("The link names no wiki (path=...)")?;
+    Ok(DeepLink { path, tiddler: param("tiddler") })
+}
+
+/// Open the wiki of a deep link and navigate to its tiddler
+pub fn open(app: &tauri::AppHandle, url: &str) -> Result<(), String> {
+    let link = parse(url)?;
+    let known = crate::wiki_storage::load_recent_files_from_disk(app)
+        .into_iter()
+        .find(|entry| utils::paths_equal(&entry.path, &link.path))
+        .ok_or_else(|| format!("Not in the wiki list: {}", link.path))?;
+    if let Some(title) = link.tiddler {
+        crate::quick_actions::queue_open_tiddler(app, &known.path, title)?;
+    }
+    crate::file_open::open_wikis(app, vec![known.path]);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            parse("tiddlydesktop://open?path=%2Fhome%2Fme%2Fnotes.html&tiddler=Getting%20Started"),
+            Ok(DeepLink {
+                path: "/home/me/notes.html".to_string(),
+                tiddler: Some("Getting Started".to_string()),
+            })
+        );
+        assert_eq!(
+            parse("tiddlydesktop:open?path=C%3A%5CWikis%5Cwork.html&tiddler="),
+            Ok(DeepLink { path: "C:\\Wikis\\work.html".to_string(), tiddler: None })
+        );
+        assert!(parse("tiddlydesktop://open?tiddler=Foo").is_err());
+        assert!(parse("tiddlydesktop://delete?path=%2Fa.html").is_err());
+        assert!(parse("https://open?path=%2Fa.html").is_err());
+
+        assert!(is_deep_link("TiddlyDesktop://open?path=x"));
+        assert!(!is_deep_link("/home/me/tiddlydesktop.html"));
+    }
+}
@@ -0,0 +1,337 @@
+//! Desktop shortcuts that open a single wiki
+//!
+//! A shortcut launches TiddlyDesktop straight into wiki mode (`--wiki <path>`,
+//! or `--wiki-folder <path>` for folder wikis), named after the wiki's site
+//! title and using its favicon as icon, so a wiki feels like a standalone app.
+//! - Linux: a `.desktop` launcher
+//! - Windows: a `.lnk` shell link
+//! - macOS: a small `.app` bundle that runs the app with the wiki's arguments
+
+use std::path::{Path, PathBuf};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+/// Decode a `data:<mime>;base64,...` favicon into its MIME type and bytes
+fn decode_favicon(data_uri: &str) -> Option<(String, Vec<u8>)> {
+    let rest = data_uri.strip_prefix("data:")?;
+    let (header, data) = rest.split_once(',')?;
+    let mime = header.strip_suffix(";base64")?;
+    let bytes = STANDARD.decode(data.trim()).ok()?;
+    Some((mime.to_lowercase(), bytes))
+}
+
+/// Width and height of a PNG image (from its IHDR chunk)
+fn png_size(png: &[u8]) -> Option<(u32, u32)> {
+    if png.len() < 24 || &png[..8] != b"\x89PNG\r\n\x1a\n" || &png[12..16] != b"IHDR" {
+        return None;
+    }
+    let width = u32::from_be_bytes(png[16..20].try_into().ok()?);
+    let height = u32::from_be_bytes(png[20..24].try_into().ok()?);
+    Some((width, height))
+}
+
+/// Wrap a PNG in an ICO container (Windows Vista and later read PNG entries)
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+fn png_to_ico(png: &[u8]) -> Option<Vec<u8>> {
+    let (width, height) = png_size(png)?;
+    // 0 means 256 (or larger) in ICO directory entries
+    let dim = |d: u32| if d >= 256 { 0 } else { d as u8 };
+    let mut ico = Vec::with_capacity(png.len() + 22);
+    ico.extend_from_slice(&[0, 0, 1, 0, 1, 0]);
+    ico.extend_from_slice(&[dim(width), dim(height), 0, 0]);
+    ico.extend_from_slice(&1u16.to_le_bytes()); // colour planes
+    ico.extend_from_slice(&32u16.to_le_bytes()); // bits per pixel
+    ico.extend_from_slice(&(png.len() as u32).to_le_bytes());
+    ico.extend_from_slice(&22u32.to_le_bytes()); // image data offset
+    ico.extend_from_slice(png);
+    Some(ico)
+}
+
+/// Wrap a PNG in an ICNS container
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+fn png_to_icns(png: &[u8]) -> Option<Vec<u8>> {
+    let (width, _) = png_size(png)?;
+    let icon_type: &[u8; 4] = match width {
+        0..=16 => b"icp4",
+        17..=32 => b"icp5",
+        33..=64 => b"icp6",
+        65..=128 => b"ic07",
+        129..=256 => b"ic08",
+        257..=512 => b"ic09",
+        _ => b"ic10",
+    };
+    let entry_len = (png.len() + 8) as u32;
+    let mut icns = Vec::with_capacity(png.len() + 16);
+    icns.extend_from_slice(b"icns");
+    icns.extend_from_slice(&(entry_len + 8).to_be_bytes());
+    icns.extend_from_slice(icon_type);
+    icns.extend_from_slice(&entry_len.to_be_bytes());
+    icns.extend_from_slice(png);
+    Some(icns)
+}
+
+/// Make a shortcut name safe to use as a file name on every platform
+fn safe_file_name(name: &str) -> String {
+    let cleaned: String = name
+        .chars()
+        .map(|c| if matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|') || c.is_control() { '_' } else { c })
+        .collect();
+    let cleaned = cleaned.trim().trim_matches('.').trim();
+    if cleaned.is_empty() {
+        "Wiki".to_string()
+    } else {
+        cleaned.chars().take(100).collect()
+    }
+}
+
+/// Quote an argument for the `Exec` key of a `.desktop` file
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn desktop_exec_arg(arg: &str) -> String {
+    let mut quoted = String::with_capacity(arg.len() + 2);
+    quoted.push('"');
+    for c in arg.chars() {
+        match c {
+            '"' | '`' | '$' | '\\' => {
+                // Escaped once for the Exec quoting and once for the string value
+                quoted.push_str("\\\\");
+                quoted.push(c);
+            }
+            '%' => quoted.push_str("%%"),
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Site title of a wiki, falling back to its file or folder name
+fn wiki_title(path: &Path, is_folder: bool) -> String {
+    let site_title = if is_folder {
+        std::fs::read_to_string(path.join("tiddlers").join("$__SiteTitle.tid"))
+            .ok()
+            .and_then(|tid| tid.split_once("\n\n").map(|(_, text)| text.trim().to_string()))
+    } else {
+        std::fs::read_to_string(path).ok().and_then(|html| {
+            crate::tiddlywiki_html::extract_all_tiddlers_from_html(&html)
+                .into_iter()
+                .rev()
+                .find(|t| t.get("title").and_then(|v| v.as_str()) == Some("$:/SiteTitle"))
+                .and_then(|t| t.get("text").and_then(|v| v.as_str()).map(|s| s.trim().to_string()))
+        })
+    };
+    site_title.filter(|t| !t.is_empty()).unwrap_or_else(|| {
+        let name = if is_folder { path.file_name() } else { path.file_stem() };
+        name.map(|n| n.to_string_lossy().into_owned()).unwrap_or_default()
+    })
+}
+
+/// Write the favicon in the format the platform's shortcuts use.
+/// Returns None when the wiki has no usable favicon (the app icon is used then).
+fn write_icon(app: &tauri::AppHandle, wiki_path: &str, favicon: Option<&str>) -> Option<PathBuf> {
+    let (mime, bytes) = decode_favicon(favicon?)?;
+    #[cfg(target_os = "windows")]
+    let (ext, data) = match mime.as_str() {
+        "image/x-icon" | "image/vnd.microsoft.icon" => ("ico", bytes),
+        "image/png" => ("ico", png_to_ico(&bytes)?),
+        _ => return None,
+    };
+    #[cfg(target_os = "macos")]
+    let (ext, data) = match mime.as_str() {
+        "image/png" => ("icns", png_to_icns(&bytes)?),
+        _ => return None,
+    };
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let (ext, data) = match mime.as_str() {
+        "image/png" => ("png", bytes),
+        "image/svg+xml" => ("svg", bytes),
+        _ => return None,
+    };
+
+    let dir = crate::get_data_dir(app).ok()?.join("shortcut-icons");
+    std::fs::create_dir_all(&dir).ok()?;
+    let icon = dir.join(format!("{:x}.{}", md5::compute(wiki_path.as_bytes()), ext));
+    std::fs::write(&icon, data).ok()?;
+    Some(icon)
+}
+
+#[cfg(target_os = "linux")]
+fn write_shortcut(dir: &Path, name: &str, exe: &Path, args: &[&str], icon: Option<&Path>) -> Result<PathBuf, String> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let exec = std::iter::once(exe.to_string_lossy().as_ref())
+        .chain(args.iter().copied())
+        .map(desktop_exec_arg)
+        .collect::<Vec<_>>()
+        .join(" ");
+    let icon = icon.map(|i| i.to_string_lossy().into_owned()).unwrap_or_else(|| "tiddlydesktop-rs".to_string());
+    let contents = format!(
+        "[Desktop Entry]\nType=Application\nName={}\nComment=TiddlyWiki\nExec={}\nIcon={}\nTerminal=false\nCategories=Office;\n",
+        name.replace('\n', " "),
+        exec,
+        icon
+    );
+    let path = dir.join(format!("{}.desktop", safe_file_name(name)));
+    std::fs::write(&path, contents).map_err(|e| format!("Failed to write shortcut: {}", e))?;
+    // Desktop environments only launch executable .desktop files from the desktop
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))
+        .map_err(|e| format!("Failed to make shortcut executable: {}", e))?;
+    Ok(path)
+}
+
+#[cfg(target_os = "windows")]
+fn write_shortcut(dir: &Path, name: &str, exe: &Path, args: &[&str], icon: Option<&Path>) -> Result<PathBuf, String> {
+    use windows::core::{Interface, HSTRING};
+    use windows::Win32::System::Com::{CoCreateInstance, CoInitializeEx, IPersistFile, CLSCTX_INPROC_SERVER, COINIT_APARTMENTTHREADED};
+    use windows::Win32::UI::Shell::{IShellLinkW, ShellLink};
+
+    let path = dir.join(format!("{}.lnk", safe_file_name(name)));
+    // Windows paths can't contain quotes, so quoting each argument is enough
+    let arguments = args.iter().map(|a| format!("\"{}\"", a)).collect::<Vec<_>>().join(" ");
+    let icon = icon.unwrap_or(exe);
+    unsafe {
+        // Already initialised on this thread is fine
+        let _ = CoInitializeEx(None, COINIT_APARTMENTTHREADED);
+        let link: IShellLinkW = CoCreateInstance(&ShellLink, None, CLSCTX_INPROC_SERVER)
+            .map_err(|e| format!("Failed to create shell link: {}", e))?;
+        link.SetPath(&HSTRING::from(exe.as_os_str()))
+            .and_then(|_| link.SetArguments(&HSTRING::from(arguments)))
+            .and_then(|_| link.SetIconLocation(&HSTRING::from(icon.as_os_str()), 0))
+            .and_then(|_| link.SetDescription(&HSTRING::from(name)))
+            .map_err(|e| format!("Failed to set up shell link: {}", e))?;
+        let file: IPersistFile = link.cast().map_err(|e| format!("Failed to save shell link: {}", e))?;
+        file.Save(&HSTRING::from(path.as_os_str()), true)
+            .map_err(|e| format!("Failed to save shell link: {}", e))?;
+    }
+    Ok(path)
+}
+
+#[cfg(target_os = "macos")]
+fn write_shortcut(dir: &Path, name: &str, exe: &Path, args: &[&str], icon: Option<&Path>) -> Result<PathBuf, String> {
+    use std::os::unix::fs::PermissionsExt;
+
+    fn shell_quote(s: &str) -> String {
+        format!("'{}'", s.replace('\'', r"'\''"))
+    }
+    fn xml_escape(s: &str) -> String {
+        s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+    }
+
+    let bundle = dir.join(format!("{}.app", safe_file_name(name)));
+    let contents = bundle.join("Contents");
+    let macos = contents.join("MacOS");
+    let resources = contents.join("Resources");
+    std::fs::create_dir_all(&macos)
+        .and_then(|_| std::fs::create_dir_all(&resources))
+        .map_err(|e| format!("Failed to create shortcut app: {}", e))?;
+
+    let command = std::iter::once(exe.to_string_lossy().as_ref())
+        .chain(args.iter().copied())
+        .map(shell_quote)
+        .collect::<Vec<_>>()
+        .join(" ");
+    let launcher = macos.join("launcher");
+    std::fs::write(&launcher, format!("#!/bin/sh\nexec {}\n", command))
+        .and_then(|_| std::fs::set_permissions(&launcher, std::fs::Permissions::from_mode(0o755)))
+        .map_err(|e| format!("Failed to write shortcut launcher: {}", e))?;
+
+    let icon_key = match icon {
+        Some(icon) => {
+            std::fs::copy(icon, resources.join("wiki.icns")).map_err(|e| format!("Failed to copy icon: {}", e))?;
+            "    <key>CFBundleIconFile</key>\n    <string>wiki.icns</string>\n"
+        }
+        None => "",
+    };
+    let identifier = format!("com.burningtreec.tiddlydesktop-rs.wiki-{:x}", md5::compute(args.join(" ").as_bytes()));
+    let plist = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+<!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n\
+<plist version=\"1.0\">\n<dict>\n\
+    <key>CFBundleExecutable</key>\n    <string>launcher</string>\n\
+    <key>CFBundleIdentifier</key>\n    <string>{}</string>\n\
+    <key>CFBundleName</key>\n    <string>{}</string>\n\
+    <key>CFBundlePackageType</key>\n    <string>APPL</string>\n\
+{}</dict>\n</plist>\n",
+        identifier,
+        xml_escape(name),
+        icon_key
+    );
+    std::fs::write(contents.join("Info.plist"), plist).map_err(|e| format!("Failed to write shortcut app: {}", e))?;
+    Ok(bundle)
+}
+
+/// Create a desktop shortcut that opens a wiki directly. Returns the shortcut path.
+#[tauri::command]
+pub async fn create_desktop_shortcut(app: tauri::AppHandle, wiki_path: String) -> Result<String, String> {
+    #[cfg(not(target_os = "android"))]
+    {
+        let path = PathBuf::from(&wiki_path);
+        let is_folder = crate::utils::is_wiki_folder(&path);
+        if !is_folder && !path.is_file() {
+            return Err(format!("Wiki not found: {}", wiki_path));
+        }
+        let favicon = crate::wiki_storage::load_recent_files_from_disk(&app)
+            .into_iter()
+            .find(|e| crate::utils::paths_equal(&e.path, &wiki_path))
+            .and_then(|e| e.favicon);
+        let exe = std::env::current_exe().map_err(|e| format!("Failed to get executable path: {}", e))?;
+        let desktop = dirs::desktop_dir()
+            .or_else(dirs::home_dir)
+            .ok_or("Could not find the desktop folder")?;
+
+        tokio::task::spawn_blocking(move || {
+            let name = wiki_title(&path, is_folder);
+            let icon = write_icon(&app, &wiki_path, favicon.as_deref());
+            let flag = if is_folder { "--wiki-folder" } else { "--wiki" };
+            let shortcut = write_shortcut(&desktop, &name, &exe, &[flag, &wiki_path], icon.as_deref())?;
+            eprintln!("[TiddlyDesktop] Created desktop shortcut {:?}", shortcut);
+            Ok(shortcut.to_string_lossy().into_owned())
+        })
+        .await
+        .map_err(|e| format!("Failed to spawn blocking task: {}", e))?
+    }
+    #[cfg(target_os = "android")]
+    {
+        let _ = (app, wiki_path);
+        Err("Desktop shortcuts are not supported on Android".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 1x1 PNG
+    const PNG: &[u8] = &[
+        0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A, 0x00, 0x00, 0x00, 0x0D, 0x49, 0x48, 0x44, 0x52,
+        0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x08, 0x06, 0x00, 0x00, 0x00, 0x1F, 0x15, 0xC4,
+        0x89,
+    ];
+
+    #[test]
+    fn test_decodes_favicon_data_uri() {
+        let uri = format!("data:image/png;base64,{}", STANDARD.encode(PNG));
+        let (mime, bytes) = decode_favicon(&uri).unwrap();
+        assert_eq!(mime, "image/png");
+        assert_eq!(png_size(&bytes), Some((1, 1)));
+        assert!(decode_favicon("https://example.com/favicon.ico").is_none());
+    }
+
+    #[test]
+    fn test_wraps_png_in_icon_containers() {
+        let ico = png_to_ico(PNG).unwrap();
+        assert_eq!(&ico[..6], &[0, 0, 1, 0, 1, 0]);
+        assert_eq!(&ico[22..], PNG);
+        let icns = png_to_icns(PNG).unwrap();
+        assert_eq!(&icns[..4], b"icns");
+        assert_eq!(u32::from_be_bytes(icns[4..8].try_into().unwrap()) as usize, icns.len());
+        assert_eq!(&icns[8..12], b"icp4");
+    }
+
+    #[test]
+    fn test_quotes_desktop_exec_arguments() {
+        assert_eq!(desktop_exec_arg("/home/me/My Wiki.html"), "\"/home/me/My Wiki.html\"");
+        assert_eq!(desktop_exec_arg("100% $HOME"), "\"100%% \\\\$HOME\"");
+        assert_eq!(safe_file_name("Notes: 2024/05"), "Notes_ 2024_05");
+        assert_eq!(safe_file_name(" .. "), "Wiki");
+    }
+}
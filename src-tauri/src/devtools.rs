@@ -0,0 +1,189 @@
+//! Developer tools and remote debugging of wiki windows (desktop)
+//!
+//! Release builds create their webviews without developer tools. For plugin
+//! authors, remote debugging can be turned on per wiki from the main process
+//! (until the app quits): the wiki's processes started afterwards get
+//! developer tools, and the webview's remote inspector listens on a local port:
+//! - Linux: WebKitGTK inspector server (`WEBKIT_INSPECTOR_SERVER`), to connect
+//!   to from Epiphany or the WebKit MiniBrowser at `inspector://127.0.0.1:<port>`
+//! - Windows: WebView2 `--remote-debugging-port`, targets at
+//!   `http://127.0.0.1:<port>/json` (edge://inspect, chrome://inspect)
+//! - macOS: the webviews become inspectable from Safari's Develop menu
+//!
+//! `open_devtools` opens the developer tools of a window of the calling
+//! process, or, from the main process, of an open wiki's windows (over IPC).
+
+use std::collections::HashMap;
+use std::process::Command;
+use std::sync::{LazyLock, Mutex};
+
+use serde::Serialize;
+use tauri::Manager;
+
+/// Set for wiki processes that may open developer tools
+const DEVTOOLS_ENV_VAR: &str = "TIDDLYDESKTOP_DEVTOOLS";
+
+/// Wiki path → remote debugging port (main process)
+static REMOTE_DEBUGGING: LazyLock<Mutex<HashMap<String, u16>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// A wiki with remote debugging on
+#[derive(Debug, Serialize)]
+pub struct RemoteDebugging {
+    pub path: String,
+    pub port: u16,
+    /// Where to connect an inspector to (None on macOS: Safari's Develop menu)
+    pub endpoint: Option<String>,
+    /// The wiki is open and has to be reopened for it to take effect
+    pub restart_needed: bool,
+}
+
+/// Something developer tools can be opened for
+#[derive(Debug, Serialize)]
+pub struct DebugTarget {
+    /// "window" (of the main process) or "wiki" (a wiki process)
+    pub kind: &'static str,
+    /// Window label or wiki path
+    pub id: String,
+    pub pid: Option<u32>,
+    /// `open_devtools` works for it
+    pub devtools: bool,
+    pub remote_port: Option<u16>,
+    pub endpoint: Option<String>,
+}
+
+/// Whether webviews of this process get developer tools
+pub fn enabled() -> bool {
+    cfg!(debug_assertions) || std::env::var_os(DEVTOOLS_ENV_VAR).is_some()
+}
+
+fn endpoint(port: u16) -> Option<String> {
+    if cfg!(target_os = "linux") {
+        Some(format!("inspector://127.0.0.1:{}", port))
+    } else if cfg!(target_os = "windows") {
+        Some(format!("http://127.0.0.1:{}/json", port))
+    } else {
+        None
+    }
+}
+
+fn remote_port(wiki_path: &str) -> Option<u16> {
+    REMOTE_DEBUGGING.lock().unwrap().get(wiki_path).copied()
+}
+
+/// Give a wiki process about to be spawned developer tools and its remote
+/// debugging port, if remote debugging is on for the wiki
+pub fn configure_process(cmd: &mut Command, wiki_path: &str) {
+    let Some(port) = remote_port(wiki_path) else {
+        return;
+    };
+    cmd.env(DEVTOOLS_ENV_VAR, "1");
+    #[cfg(target_os = "linux")]
+    cmd.env("WEBKIT_INSPECTOR_SERVER", format!("127.0.0.1:{}", port));
+    #[cfg(target_os = "windows")]
+    {
+        // Replaces the arguments Tauri passes to WebView2, so keep its defaults
+        let mut args = std::env::var("WEBVIEW2_ADDITIONAL_BROWSER_ARGUMENTS")
+            .unwrap_or_else(|_| "--disable-features=msWebOOUI,msPdfOOUI,msSmartScreenProtection".to_string());
+        args.push_str(&format!(" --remote-debugging-port={}", port));
+        cmd.env("WEBVIEW2_ADDITIONAL_BROWSER_ARGUMENTS", args);
+    }
+    let _ = port; // Unused on macOS
+}
+
+/// A free local port for the remote inspector
+fn free_port() -> Result<u16, String> {
+    std::net::TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .map(|addr| addr.port())
+        .map_err(|e| format!("Failed to find a free port: {}", e))
+}
+
+/// Turn on remote debugging for a wiki (main process), on `port` or a free
+/// one. Applies to the wiki's processes started from now on.
+#[tauri::command]
+pub fn enable_remote_debugging(app: tauri::AppHandle, path: String, port: Option<u16>) -> Result<RemoteDebugging, String> {
+    let port = match port.or_else(|| remote_port(&path)) {
+        Some(port) => port,
+        None => free_port()?,
+    };
+    let was_enabled = REMOTE_DEBUGGING.lock().unwrap().insert(path.clone(), port) == Some(port);
+    let restart_needed = !was_enabled && app.state::<crate::AppState>().wiki_processes.lock().unwrap().contains_key(&path);
+    Ok(RemoteDebugging { path, port, endpoint: endpoint(port), restart_needed })
+}
+
+/// Turn off remote debugging for a wiki (takes effect when it's reopened)
+#[tauri::command]
+pub fn disable_remote_debugging(path: String) {
+    REMOTE_DEBUGGING.lock().unwrap().remove(&path);
+}
+
+/// The windows of the main process and the open wikis, with their debugging state
+#[tauri::command]
+pub fn list_debug_targets(app: tauri::AppHandle) -> Vec<DebugTarget> {
+    let mut targets: Vec<DebugTarget> = app
+        .webview_windows()
+        .into_keys()
+        .map(|label| DebugTarget {
+            kind: "window",
+            id: label,
+            pid: Some(std::process::id()),
+            devtools: enabled(),
+            remote_port: None,
+            endpoint: None,
+        })
+        .collect();
+    let mut wikis: Vec<(String, u32)> = app
+        .state::<crate::AppState>()
+        .wiki_processes
+        .lock()
+        .unwrap()
+        .values()
+        .map(|process| (process.path.clone(), process.pid))
+        .collect();
+    wikis.sort();
+    for (path, pid) in wikis {
+        let port = remote_port(&path);
+        targets.push(DebugTarget {
+            kind: "wiki",
+            id: path,
+            pid: Some(pid),
+            devtools: cfg!(debug_assertions) || port.is_some(),
+            remote_port: port,
+            endpoint: port.and_then(endpoint),
+        });
+    }
+    targets
+}
+
+/// Open the developer tools of every window of this process (IPC
+/// `OpenDevtools` in wiki processes)
+pub fn open_all(app: &tauri::AppHandle) {
+    if !enabled() {
+        return;
+    }
+    for window in app.webview_windows().values() {
+        window.open_devtools();
+    }
+}
+
+/// Open the developer tools of the window labelled `window` in this process,
+/// or, from the main process, of the windows of the open wiki at that path
+#[tauri::command]
+pub fn open_devtools(app: tauri::AppHandle, window: String) -> Result<(), String> {
+    if let Some(webview) = app.get_webview_window(&window) {
+        if !enabled() {
+            return Err("Developer tools are only available in debug builds, or in wikis opened with remote debugging on".to_string());
+        }
+        webview.open_devtools();
+        return Ok(());
+    }
+    let is_open_wiki = app.state::<crate::AppState>().wiki_processes.lock().unwrap().contains_key(&window);
+    if !is_open_wiki {
+        return Err(format!("No window or open wiki named '{}'", window));
+    }
+    if !cfg!(debug_assertions) && remote_port(&window).is_none() {
+        return Err("Turn on remote debugging for the wiki and reopen it first".to_string());
+    }
+    let server = crate::GLOBAL_IPC_SERVER.get().ok_or("IPC server not running")?;
+    server.send_open_devtools(&window).map_err(|e| format!("Failed to reach the wiki: {}", e))
+}
@@ -0,0 +1,248 @@
+//! Where downloads of wiki windows are saved (tm-download-file, `<a download>`)
+//!
+//! `download_file` shows a save dialog whose file type filter is picked from
+//! the file extension (or the content type when the extension is unknown), so
+//! exports like `.tid`, `.md` or `.jsonl` keep their type. Per wiki
+//! (`DownloadConfig` in the wiki configs):
+//! - the dialog starts in the directory of the last download
+//! - with an auto-download directory, files are saved there without a dialog,
+//!   under a numbered name if the file exists
+//! - CSV exports can use another delimiter than the comma
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::types::DownloadConfig;
+
+/// Save dialog filter: name, extensions and the content types that imply it
+struct FileType {
+    name: &'static str,
+    extensions: &'static [&'static str],
+    content_types: &'static [&'static str],
+}
+
+const FILE_TYPES: &[FileType] = &[
+    FileType { name: "HTML files", extensions: &["html", "htm"], content_types: &["text/html"] },
+    FileType { name: "JSON files", extensions: &["json"], content_types: &["application/json"] },
+    FileType {
+        name: "JSON Lines files",
+        extensions: &["jsonl", "ndjson"],
+        content_types: &["application/jsonl", "application/x-ndjson", "application/x-jsonlines"],
+    },
+    FileType { name: "CSV files", extensions: &["csv"], content_types: &["text/csv"] },
+    FileType { name: "TSV files", extensions: &["tsv"], content_types: &["text/tab-separated-values"] },
+    FileType { name: "Markdown files", extensions: &["md", "markdown"], content_types: &["text/markdown", "text/x-markdown"] },
+    FileType { name: "Tiddler files", extensions: &["tid"], content_types: &["application/x-tiddler"] },
+    FileType { name: "Tiddler dictionaries", extensions: &["multids"], content_types: &["application/x-tiddler-dictionary"] },
+    FileType { name: "SVG images", extensions: &["svg"], content_types: &["image/svg+xml"] },
+    FileType { name: "Text files", extensions: &["txt"], content_types: &["text/plain"] },
+];
+
+fn extension_of(filename: &str) -> String {
+    Path::new(filename)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase()
+}
+
+/// Save dialog filter for a download. The extension wins over the content
+/// type, which wikis often leave at `text/plain`.
+pub fn file_filter(filename: &str, content_type: Option<&str>) -> (&'static str, Vec<String>) {
+    let extension = extension_of(filename);
+    let content_type = content_type.map(|t| t.split(';').next().unwrap_or("").trim().to_lowercase());
+    let by_extension = FILE_TYPES.iter().find(|t| t.extensions.contains(&extension.as_str()));
+    let by_type = || {
+        let content_type = content_type.as_deref()?;
+        FILE_TYPES.iter().find(|t| t.content_types.contains(&content_type))
+    };
+    match by_extension.or_else(|| if extension.is_empty() { by_type() } else { None }) {
+        Some(file_type) => (file_type.name, file_type.extensions.iter().map(|e| e.to_string()).collect()),
+        None if extension.is_empty() => ("All files", vec!["*".to_string()]),
+        None => ("All files", vec![extension]),
+    }
+}
+
+/// Whether a download is a CSV file
+pub fn is_csv(filename: &str, content_type: Option<&str>) -> bool {
+    let (name, _) = file_filter(filename, content_type);
+    name == "CSV files"
+}
+
+/// Delimiter from a config value (",", ";", "|", "tab" or a tab character).
+/// None for the comma, which needs no conversion, and for unknown values.
+pub fn parse_delimiter(value: &str) -> Option<char> {
+    match value {
+        ";" => Some(';'),
+        "|" => Some('|'),
+        "\t" | "tab" => Some('\t'),
+        _ => None,
+    }
+}
+
+/// Rewrite comma-separated CSV (as TiddlyWiki's CSV exporter writes it) with
+/// another delimiter, quoting the fields that need it
+pub fn redelimit_csv(csv: &str, delimiter: char) -> String {
+    let mut out = String::with_capacity(csv.len());
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = csv.chars().peekable();
+
+    fn push_field(out: &mut String, field: &mut String, delimiter: char) {
+        if field.contains([delimiter, '"', '\n', '\r']) {
+            out.push('"');
+            out.push_str(&field.replace('"', "\"\""));
+            out.push('"');
+        } else {
+            out.push_str(field);
+        }
+        field.clear();
+    }
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            if c == '"' {
+                if chars.peek() == Some(&'"') {
+                    chars.next();
+                    field.push('"');
+                } else {
+                    in_quotes = false;
+                }
+            } else {
+                field.push(c);
+            }
+            continue;
+        }
+        match c {
+            '"' => in_quotes = true,
+            ',' => {
+                push_field(&mut out, &mut field, delimiter);
+                out.push(delimiter);
+            }
+            '\r' | '\n' => {
+                push_field(&mut out, &mut field, delimiter);
+                out.push(c);
+            }
+            _ => field.push(c),
+        }
+    }
+    push_field(&mut out, &mut field, delimiter);
+    out
+}
+
+/// `name.ext`, `name (1).ext`, `name (2).ext`, ...
+fn numbered_name(filename: &str, n: u32) -> String {
+    if n == 0 {
+        return filename.to_string();
+    }
+    let path = Path::new(filename);
+    let stem = path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    match path.extension() {
+        Some(ext) => format!("{} ({}).{}", stem, n, ext.to_string_lossy()),
+        None => format!("{} ({})", stem, n),
+    }
+}
+
+/// Path in `dir` for a download that doesn't overwrite an existing file.
+/// Only the file name of `filename` is used.
+#[cfg_attr(target_os = "android", allow(dead_code))]
+pub fn unique_path(dir: &Path, filename: &str) -> PathBuf {
+    let name = Path::new(filename)
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .filter(|n| !n.is_empty())
+        .unwrap_or_else(|| "download".to_string());
+    (0..)
+        .map(|n| dir.join(numbered_name(&name, n)))
+        .find(|p| !p.exists())
+        .unwrap_or_else(|| dir.join(name))
+}
+
+pub fn load_config(app: &tauri::AppHandle, wiki_path: &str) -> DownloadConfig {
+    crate::wiki_storage::load_wiki_configs(app)
+        .ok()
+        .and_then(|c| c.downloads.get(wiki_path).cloned())
+        .unwrap_or_default()
+}
+
+/// Remember the directory a wiki's download was saved to
+#[cfg_attr(target_os = "android", allow(dead_code))]
+pub fn remember_dir(app: &tauri::AppHandle, wiki_path: &str, saved_file: &str) {
+    let Some(dir) = Path::new(saved_file).parent() else {
+        return;
+    };
+    let Ok(mut configs) = crate::wiki_storage::load_wiki_configs(app) else {
+        return;
+    };
+    let config = configs.downloads.entry(wiki_path.to_string()).or_default();
+    let dir = dir.to_string_lossy().into_owned();
+    if config.last_dir.as_deref() == Some(dir.as_str()) {
+        return;
+    }
+    config.last_dir = Some(dir);
+    if let Err(e) = crate::wiki_storage::save_wiki_configs(app, &configs) {
+        eprintln!("[TiddlyDesktop] Failed to remember download directory: {}", e);
+    }
+}
+
+/// Get the download settings of all wikis, keyed by wiki path
+#[tauri::command]
+pub fn get_download_configs(app: tauri::AppHandle) -> Result<HashMap<String, DownloadConfig>, String> {
+    Ok(crate::wiki_storage::load_wiki_configs(&app)?.downloads)
+}
+
+/// Set the auto-download directory (empty = always ask) and CSV delimiter of a wiki
+#[tauri::command]
+pub fn set_download_config(app: tauri::AppHandle, wiki_path: String, config: DownloadConfig) -> Result<DownloadConfig, String> {
+    let mut config = config;
+    config.auto_dir = match config.auto_dir.as_deref() {
+        Some(dir) if !dir.is_empty() => Some(
+            crate::drag_drop::sanitize::validate_user_directory_path(dir)?
+                .to_string_lossy()
+                .into_owned(),
+        ),
+        _ => None,
+    };
+    config.csv_delimiter = config
+        .csv_delimiter
+        .filter(|d| parse_delimiter(d).is_some());
+
+    let mut configs = crate::wiki_storage::load_wiki_configs(&app)?;
+    // Only downloads change the last directory
+    config.last_dir = configs.downloads.get(&wiki_path).and_then(|c| c.last_dir.clone());
+    configs.downloads.insert(wiki_path, config.clone());
+    crate::wiki_storage::save_wiki_configs(&app, &configs)?;
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_filter() {
+        assert_eq!(file_filter("notes.tid", Some("text/plain")), ("Tiddler files", vec!["tid".to_string()]));
+        assert_eq!(file_filter("log.JSONL", None).0, "JSON Lines files");
+        assert_eq!(file_filter("export", Some("text/markdown; charset=utf-8")).0, "Markdown files");
+        assert_eq!(file_filter("data.xyz", Some("text/csv")), ("All files", vec!["xyz".to_string()]));
+        assert_eq!(file_filter("download", None), ("All files", vec!["*".to_string()]));
+    }
+
+    #[test]
+    fn test_redelimit_csv() {
+        let csv = "title,text\r\n\"a, b\",\"say \"\"hi\"\"\"\r\nc;d,e\r\n";
+        assert_eq!(
+            redelimit_csv(csv, ';'),
+            "title;text\r\na, b;\"say \"\"hi\"\"\"\r\n\"c;d\";e\r\n"
+        );
+        assert_eq!(parse_delimiter(","), None);
+        assert_eq!(parse_delimiter("tab"), Some('\t'));
+    }
+
+    #[test]
+    fn test_numbered_name() {
+        assert_eq!(numbered_name("export.csv", 0), "export.csv");
+        assert_eq!(numbered_name("export.csv", 2), "export (2).csv");
+        assert_eq!(numbered_name("README", 1), "README (1)");
+    }
+}
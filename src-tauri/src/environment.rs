@@ -0,0 +1,120 @@
+//! Environment info for feature detection in wikis
+//!
+//! `get_environment` tells plugins written for TiddlyDesktop-RS what they run
+//! on (platform, app and webview versions, display server) and which optional
+//! features are there, so they can adapt instead of guessing. Wiki windows
+//! also get it as the data tiddler `$:/temp/TiddlyDesktopRS/Environment`
+//! (`init_script/environment.js`).
+
+use serde::Serialize;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Environment {
+    /// "linux", "windows", "macos" or "android"
+    platform: &'static str,
+    arch: &'static str,
+    app_version: String,
+    /// "webkitgtk", "webview2", "wkwebview" or "android-webview"
+    webview_engine: &'static str,
+    webview_version: Option<String>,
+    /// "wayland" or "x11" (Linux only)
+    display_server: Option<&'static str>,
+    features: Features,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Features {
+    /// ffmpeg was found (video thumbnails and conversion)
+    ffmpeg: bool,
+    /// HEIC/HEIF photos can be converted on import (build feature)
+    heic: bool,
+    /// MQTT client (build feature)
+    mqtt: bool,
+    /// Relay sync is set up
+    relay_configured: bool,
+    /// Several wikis in their own processes, tray, global hotkeys, serial ports
+    desktop: bool,
+}
+
+fn webview_engine() -> &'static str {
+    if cfg!(target_os = "linux") {
+        "webkitgtk"
+    } else if cfg!(target_os = "windows") {
+        "webview2"
+    } else if cfg!(target_os = "android") {
+        "android-webview"
+    } else {
+        "wkwebview"
+    }
+}
+
+/// Display server from the session variables (Wayland wins when both are set,
+/// as X11 is then usually XWayland)
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn display_server(wayland_display: bool, x11_display: bool) -> Option<&'static str> {
+    if wayland_display {
+        Some("wayland")
+    } else if x11_display {
+        Some("x11")
+    } else {
+        None
+    }
+}
+
+/// Whether ffmpeg is installed (looked up once per process)
+fn has_ffmpeg() -> bool {
+    #[cfg(not(target_os = "android"))]
+    {
+        static FOUND: std::sync::OnceLock<bool> = std::sync::OnceLock::new();
+        *FOUND.get_or_init(|| crate::find_ffmpeg().is_some())
+    }
+    #[cfg(target_os = "android")]
+    false
+}
+
+#[tauri::command]
+pub async fn get_environment(app: tauri::AppHandle) -> Environment {
+    #[cfg(target_os = "linux")]
+    let display = display_server(
+        std::env::var_os("WAYLAND_DISPLAY").is_some(),
+        std::env::var_os("DISPLAY").is_some(),
+    );
+    #[cfg(not(target_os = "linux"))]
+    let display = None;
+
+    let relay_configured = crate::get_data_dir(&app)
+        .map(|dir| crate::relay_sync::is_configured(&dir))
+        .unwrap_or(false);
+    // ffmpeg is looked up by running it
+    let ffmpeg = tokio::task::spawn_blocking(has_ffmpeg).await.unwrap_or(false);
+
+    Environment {
+        platform: std::env::consts::OS,
+        arch: std::env::consts::ARCH,
+        app_version: app.package_info().version.to_string(),
+        webview_engine: webview_engine(),
+        webview_version: tauri::webview_version().ok(),
+        display_server: display,
+        features: Features {
+            ffmpeg,
+            heic: cfg!(feature = "heic"),
+            mqtt: cfg!(feature = "mqtt"),
+            relay_configured,
+            desktop: cfg!(not(target_os = "android")),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_server() {
+        assert_eq!(display_server(true, true), Some("wayland"));
+        assert_eq!(display_server(false, true), Some("x11"));
+        assert_eq!(display_server(false, false), None);
+    }
+}
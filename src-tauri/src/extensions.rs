@@ -0,0 +1,453 @@
+//! Shell extensions
+//!
+//! Extensions add capabilities to the desktop shell (importers, converters,
+//! integrations) without forking the app. Each one is a directory in
+//! `<data_dir>/extensions/<id>/` with an `extension.json` manifest and a native
+//! library. An extension can provide:
+//! - Commands, callable from the landing page and wikis via `extension_invoke`
+//! - Tray menu items that run one of its commands
+//! - A protocol handler serving `tdext://localhost/<id>/...` to webviews
+//!
+//! Extensions run native code with the app's permissions, so they are only
+//! loaded after the user enabled them (app setting `enabled_extensions`).
+//!
+//! Library interface (C ABI):
+//! ```c
+//! uint32_t td_extension_abi_version(void);                          // returns 1
+//! char *td_extension_call(const char *command, const char *args_json);
+//! void td_extension_free(char *result);                            // frees call results
+//! ```
+//! `td_extension_call` returns `{"ok": <any JSON>}` or `{"error": "<message>"}`.
+//! Protocol requests call the manifest's `protocol` command with
+//! `{"method", "path", "query", "bodyBase64"}` and expect
+//! `{"ok": {"status", "mime", "bodyBase64"}}`.
+
+use std::collections::HashMap;
+use std::ffi::{c_char, CStr, CString};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use tauri::http::{Request, Response};
+use tauri::AppHandle;
+
+const EXTENSIONS_DIR: &str = "extensions";
+const MANIFEST_FILE: &str = "extension.json";
+const ABI_VERSION: u32 = 1;
+
+type AbiVersionFn = unsafe extern "C" fn() -> u32;
+type CallFn = unsafe extern "C" fn(*const c_char, *const c_char) -> *mut c_char;
+type FreeFn = unsafe extern "C" fn(*mut c_char);
+
+/// Libraries stay loaded for the lifetime of the process (unloading while a
+/// call is in flight would crash)
+static LOADED: Mutex<Option<HashMap<String, Arc<libloading::Library>>>> = Mutex::new(None);
+
+/// Library file per platform, relative to the extension directory
+#[derive(Clone, Debug, Default, serde::Deserialize)]
+struct LibraryPaths {
+    #[serde(default)]
+    linux: Option<String>,
+    #[serde(default)]
+    windows: Option<String>,
+    #[serde(default)]
+    macos: Option<String>,
+}
+
+impl LibraryPaths {
+    fn current(&self) -> Option<&str> {
+        if cfg!(target_os = "windows") {
+            self.windows.as_deref()
+        } else if cfg!(target_os = "macos") {
+            self.macos.as_deref()
+        } else {
+            self.linux.as_deref()
+        }
+    }
+}
+
+/// A tray menu item running an extension command
+#[derive(Clone, Debug, serde::Deserialize)]
+struct TrayItem {
+    id: String,
+    label: String,
+    command: String,
+}
+
+/// Contents of `extension.json`
+#[derive(Clone, Debug, serde::Deserialize)]
+struct Manifest {
+    id: String,
+    name: String,
+    #[serde(default)]
+    version: String,
+    #[serde(default)]
+    description: String,
+    library: LibraryPaths,
+    #[serde(default)]
+    commands: Vec<String>,
+    #[serde(default)]
+    tray: Vec<TrayItem>,
+    /// Command serving `tdext://localhost/<id>/...`
+    #[serde(default)]
+    protocol: Option<String>,
+}
+
+impl Manifest {
+    /// Commands the extension declared (anything else is refused)
+    fn allows(&self, command: &str) -> bool {
+        self.commands.iter().any(|c| c == command)
+            || self.tray.iter().any(|t| t.command == command)
+            || self.protocol.as_deref() == Some(command)
+    }
+}
+
+/// An installed extension, as shown on the landing page
+#[derive(Clone, Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtensionInfo {
+    pub id: String,
+    pub name: String,
+    pub version: String,
+    pub description: String,
+    pub enabled: bool,
+    pub commands: Vec<String>,
+    pub tray_items: Vec<String>,
+    pub has_protocol: bool,
+    /// Why the extension can't be used (bad manifest, no library for this platform)
+    pub error: Option<String>,
+}
+
+/// Extension ids name directories and appear in URLs, so keep them simple
+fn is_valid_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= 64
+        && id.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+}
+
+/// A path inside the extension directory (no absolute paths or `..`)
+fn is_contained(relative: &str) -> bool {
+    let path = Path::new(relative);
+    !relative.is_empty() && path.components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+}
+
+fn extensions_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    Ok(crate::get_data_dir(app)?.join(EXTENSIONS_DIR))
+}
+
+fn read_manifest(dir: &Path) -> Result<Manifest, String> {
+    let content = std::fs::read_to_string(dir.join(MANIFEST_FILE))
+        .map_err(|e| format!("Failed to read {}: {}", MANIFEST_FILE, e))?;
+    let manifest: Manifest = serde_json::from_str(&content)
+        .map_err(|e| format!("Invalid {}: {}", MANIFEST_FILE, e))?;
+    let dir_name = dir.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+    if !is_valid_id(&manifest.id) || manifest.id != dir_name {
+        return Err(format!("Extension id '{}' must match its directory name and use a-z, 0-9, - or _", manifest.id));
+    }
+    match manifest.library.current() {
+        Some(lib) if is_contained(lib) => Ok(manifest),
+        Some(lib) => Err(format!("Library path '{}' must be inside the extension directory", lib)),
+        None => Err("No library for this platform".to_string()),
+    }
+}
+
+/// All extension directories with their manifests (or why they can't be read)
+fn scan(app: &AppHandle) -> Vec<(String, PathBuf, Result<Manifest, String>)> {
+    let Ok(dir) = extensions_dir(app) else {
+        return Vec::new();
+    };
+    let Ok(entries) = std::fs::read_dir(&dir) else {
+        return Vec::new();
+    };
+    let mut found: Vec<_> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.join(MANIFEST_FILE).is_file())
+        .map(|p| {
+            let name = p.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+            let manifest = read_manifest(&p);
+            (name, p, manifest)
+        })
+        .collect();
+    found.sort_by(|a, b| a.0.cmp(&b.0));
+    found
+}
+
+fn enabled_ids(app: &AppHandle) -> Vec<String> {
+    crate::wiki_storage::load_app_settings(app)
+        .map(|s| s.enabled_extensions)
+        .unwrap_or_default()
+}
+
+/// An enabled extension with a valid manifest
+fn find_enabled(app: &AppHandle, id: &str) -> Result<(PathBuf, Manifest), String> {
+    if !enabled_ids(app).iter().any(|e| e == id) {
+        return Err(format!("Extension '{}' is not enabled", id));
+    }
+    if !is_valid_id(id) {
+        return Err(format!("Invalid extension id '{}'", id));
+    }
+    let dir = extensions_dir(app)?.join(id);
+    let manifest = read_manifest(&dir)?;
+    Ok((dir, manifest))
+}
+
+fn load_library(dir: &Path, manifest: &Manifest) -> Result<Arc<libloading::Library>, String> {
+    let mut loaded = LOADED.lock().unwrap();
+    let loaded = loaded.get_or_insert_with(HashMap::new);
+    if let Some(lib) = loaded.get(&manifest.id) {
+        return Ok(lib.clone());
+    }
+
+    let path = dir.join(manifest.library.current().unwrap_or_default());
+    // SAFETY: the user enabled this extension and thereby trusts its code
+    let lib = unsafe { libloading::Library::new(&path) }
+        .map_err(|e| format!("Failed to load {}: {}", path.display(), e))?;
+    let version = unsafe {
+        let abi_version: libloading::Symbol<AbiVersionFn> = lib
+            .get(b"td_extension_abi_version\0")
+            .map_err(|e| format!("Not a TiddlyDesktop extension: {}", e))?;
+        abi_version()
+    };
+    if version != ABI_VERSION {
+        return Err(format!("Extension uses interface version {}, expected {}", version, ABI_VERSION));
+    }
+
+    eprintln!("[TiddlyDesktop] Loaded extension '{}' from {}", manifest.id, path.display());
+    let lib = Arc::new(lib);
+    loaded.insert(manifest.id.clone(), lib.clone());
+    Ok(lib)
+}
+
+/// Turn `{"ok": ...}` / `{"error": ...}` into a Result
+fn parse_call_result(raw: &str) -> Result<serde_json::Value, String> {
+    let mut value: serde_json::Value = serde_json::from_str(raw)
+        .map_err(|e| format!("Invalid result from extension: {}", e))?;
+    if let Some(error) = value.get("error") {
+        return Err(error.as_str().map(str::to_string).unwrap_or_else(|| error.to_string()));
+    }
+    value
+        .get_mut("ok")
+        .map(serde_json::Value::take)
+        .ok_or_else(|| "Extension result has neither \"ok\" nor \"error\"".to_string())
+}
+
+fn call_library(lib: &libloading::Library, command: &str, args: &serde_json::Value) -> Result<serde_json::Value, String> {
+    let command = CString::new(command).map_err(|e| e.to_string())?;
+    let args = CString::new(args.to_string()).map_err(|e| e.to_string())?;
+    // SAFETY: symbols follow the documented interface (checked via the ABI version)
+    let raw = unsafe {
+        let call: libloading::Symbol<CallFn> = lib.get(b"td_extension_call\0").map_err(|e| e.to_string())?;
+        let free: libloading::Symbol<FreeFn> = lib.get(b"td_extension_free\0").map_err(|e| e.to_string())?;
+        let result = call(command.as_ptr(), args.as_ptr());
+        if result.is_null() {
+            return Err("Extension returned no result".to_string());
+        }
+        let raw = CStr::from_ptr(result).to_string_lossy().into_owned();
+        free(result);
+        raw
+    };
+    parse_call_result(&raw)
+}
+
+/// Run a command of an enabled extension (blocking)
+pub fn call(app: &AppHandle, id: &str, command: &str, args: serde_json::Value) -> Result<serde_json::Value, String> {
+    let (dir, manifest) = find_enabled(app, id)?;
+    if !manifest.allows(command) {
+        return Err(format!("Extension '{}' has no command '{}'", id, command));
+    }
+    let lib = load_library(&dir, &manifest)?;
+    call_library(&lib, command, &args)
+}
+
+/// Tray menu items of enabled extensions as (menu id, label)
+pub fn tray_items(app: &AppHandle) -> Vec<(String, String)> {
+    let enabled = enabled_ids(app);
+    scan(app)
+        .into_iter()
+        .filter_map(|(_, _, manifest)| manifest.ok())
+        .filter(|m| enabled.contains(&m.id))
+        .flat_map(|m| {
+            m.tray
+                .iter()
+                .map(|item| (format!("ext:{}:{}", m.id, item.id), item.label.clone()))
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Run the command behind a tray menu item (`ext:<extension>:<item>`)
+pub fn handle_tray_event(app: &AppHandle, menu_id: &str) {
+    let Some((id, item_id)) = menu_id.strip_prefix("ext:").and_then(|rest| rest.split_once(':')) else {
+        return;
+    };
+    let (app, id, item_id) = (app.clone(), id.to_string(), item_id.to_string());
+    std::thread::spawn(move || {
+        let command = find_enabled(&app, &id)
+            .ok()
+            .and_then(|(_, m)| m.tray.into_iter().find(|t| t.id == item_id))
+            .map(|t| t.command);
+        let Some(command) = command else {
+            return;
+        };
+        if let Err(e) = call(&app, &id, &command, serde_json::json!({})) {
+            eprintln!("[TiddlyDesktop] Extension '{}' tray command '{}' failed: {}", id, command, e);
+        }
+    });
+}
+
+/// Split `/<extension>/<rest>` into the extension id and `/<rest>`
+fn split_protocol_path(path: &str) -> (String, String) {
+    let trimmed = path.trim_start_matches('/');
+    match trimmed.split_once('/') {
+        Some((id, rest)) => (id.to_string(), format!("/{}", rest)),
+        None => (trimmed.to_string(), "/".to_string()),
+    }
+}
+
+fn protocol_response(status: u16, mime: &str, body: Vec<u8>) -> Response<Vec<u8>> {
+    Response::builder()
+        .status(status)
+        .header("Content-Type", mime)
+        .header("Access-Control-Allow-Origin", "*")
+        .body(body)
+        .unwrap()
+}
+
+/// Serve `tdext://localhost/<extension>/<path>` from the extension's protocol
+/// command (the id is in the path: Windows rewrites custom scheme hosts).
+/// Called on a background thread.
+pub fn protocol_handler(app: &AppHandle, request: Request<Vec<u8>>) -> Response<Vec<u8>> {
+    let uri = request.uri();
+    let (id, path) = split_protocol_path(uri.path());
+    let command = match find_enabled(app, &id) {
+        Ok((_, manifest)) => manifest.protocol,
+        Err(e) => return protocol_response(404, "text/plain", e.into_bytes()),
+    };
+    let Some(command) = command else {
+        return protocol_response(404, "text/plain", format!("Extension '{}' has no protocol handler", id).into_bytes());
+    };
+
+    let args = serde_json::json!({
+        "method": request.method().as_str(),
+        "path": path,
+        "query": uri.query().unwrap_or_default(),
+        "bodyBase64": STANDARD.encode(request.body()),
+    });
+    match call(app, &id, &command, args) {
+        Ok(result) => {
+            let status = result.get("status").and_then(|s| s.as_u64()).unwrap_or(200) as u16;
+            let mime = result.get("mime").and_then(|m| m.as_str()).unwrap_or("application/octet-stream");
+            let body = result
+                .get("bodyBase64")
+                .and_then(|b| b.as_str())
+                .and_then(|b| STANDARD.decode(b).ok())
+                .unwrap_or_default();
+            protocol_response(status, mime, body)
+        }
+        Err(e) => protocol_response(500, "text/plain", e.into_bytes()),
+    }
+}
+
+/// List installed extensions
+#[tauri::command]
+pub fn list_extensions(app: AppHandle) -> Vec<ExtensionInfo> {
+    let enabled = enabled_ids(&app);
+    scan(&app)
+        .into_iter()
+        .map(|(name, _, manifest)| match manifest {
+            Ok(m) => ExtensionInfo {
+                enabled: enabled.contains(&m.id),
+                id: m.id,
+                name: m.name,
+                version: m.version,
+                description: m.description,
+                commands: m.commands,
+                tray_items: m.tray.into_iter().map(|t| t.label).collect(),
+                has_protocol: m.protocol.is_some(),
+                error: None,
+            },
+            Err(e) => ExtensionInfo {
+                enabled: enabled.contains(&name),
+                id: name.clone(),
+                name,
+                version: String::new(),
+                description: String::new(),
+                commands: Vec::new(),
+                tray_items: Vec::new(),
+                has_protocol: false,
+                error: Some(e),
+            },
+        })
+        .collect()
+}
+
+/// Enable or disable an extension. Loaded libraries stay loaded until restart.
+#[tauri::command]
+pub fn set_extension_enabled(app: AppHandle, id: String, enabled: bool) -> Result<Vec<ExtensionInfo>, String> {
+    let mut settings = crate::wiki_storage::load_app_settings(&app)?;
+    settings.enabled_extensions.retain(|e| e != &id);
+    if enabled {
+        settings.enabled_extensions.push(id);
+    }
+    crate::wiki_storage::save_app_settings(&app, &settings)?;
+    Ok(list_extensions(app))
+}
+
+/// Open the extensions directory in the file manager (created if missing)
+#[tauri::command]
+pub fn open_extensions_folder(app: AppHandle) -> Result<(), String> {
+    let dir = extensions_dir(&app)?;
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create extensions folder: {}", e))?;
+    #[cfg(not(target_os = "android"))]
+    {
+        use tauri_plugin_opener::OpenerExt;
+        app.opener()
+            .open_path(dir.to_string_lossy(), None::<&str>)
+            .map_err(|e| format!("Failed to open extensions folder: {}", e))
+    }
+    #[cfg(target_os = "android")]
+    Err("Extensions are not supported on Android".to_string())
+}
+
+/// Run an extension command from the landing page or a wiki
+#[tauri::command]
+pub async fn extension_invoke(
+    app: AppHandle,
+    extension: String,
+    command: String,
+    args: Option<serde_json::Value>,
+) -> Result<serde_json::Value, String> {
+    let args = args.unwrap_or_else(|| serde_json::json!({}));
+    tokio::task::spawn_blocking(move || call(&app, &extension, &command, args))
+        .await
+        .map_err(|e| format!("Failed to spawn blocking task: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validates_ids_and_library_paths() {
+        assert!(is_valid_id("csv-import_2"));
+        assert!(!is_valid_id("CSV"));
+        assert!(!is_valid_id("../x"));
+        assert!(is_contained("lib/libcsv.so"));
+        assert!(!is_contained("../libcsv.so"));
+        assert!(!is_contained("/usr/lib/libcsv.so"));
+    }
+
+    #[test]
+    fn test_parses_call_results() {
+        assert_eq!(parse_call_result(r#"{"ok":{"n":1}}"#).unwrap(), serde_json::json!({"n": 1}));
+        assert_eq!(parse_call_result(r#"{"error":"bad input"}"#).unwrap_err(), "bad input");
+        assert!(parse_call_result(r#"{}"#).is_err());
+    }
+
+    #[test]
+    fn test_splits_protocol_paths() {
+        assert_eq!(split_protocol_path("/csv/import/a.csv"), ("csv".to_string(), "/import/a.csv".to_string()));
+        assert_eq!(split_protocol_path("/csv"), ("csv".to_string(), "/".to_string()));
+    }
+}
@@ -0,0 +1,170 @@
+//! Favicons of single-file wikis, cached by path and modification time
+//!
+//! Finding a wiki's favicon can mean reading the whole file, when it's only
+//! in the `$:/favicon.ico` tiddler. The favicons found are kept in
+//! `favicon-cache.json` in the data directory with the modification time and
+//! size of the wiki, so adding or opening a wiki again only reads it when it
+//! changed since.
+//!
+//! After a save, `refresh_after_save` looks at the favicon again in the
+//! background. When it changed, the wiki list is updated and
+//! `wiki-favicon-updated` is emitted, for the landing page; the tray menu is
+//! rebuilt (see `wiki_storage::update_wiki_favicon`). Wiki processes hand the
+//! change to the main process over IPC, like their windows' favicon sync.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tiddlydesktop_core::tiddlywiki_html;
+
+const CACHE_FILE: &str = "favicon-cache.json";
+
+/// How long after a save the favicon is looked at (saves often come in bursts)
+const REFRESH_DELAY: Duration = Duration::from_secs(3);
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct Cached {
+    /// Modification time of the wiki (ms since the epoch)
+    modified: u64,
+    size: u64,
+    favicon: Option<String>,
+}
+
+/// Load-modify-store of the cache file, one at a time
+static CACHE_LOCK: Mutex<()> = Mutex::new(());
+
+/// Wikis with a refresh waiting to run
+static PENDING: LazyLock<Mutex<HashSet<PathBuf>>> = LazyLock::new(|| Mutex::new(HashSet::new()));
+
+/// Modification time and size of a file
+fn stamp(path: &Path) -> Option<(u64, u64)> {
+    let metadata = std::fs::metadata(path).ok()?;
+    let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?.as_millis() as u64;
+    Some((modified, metadata.len()))
+}
+
+/// The cached favicon of a wiki, if the wiki is unchanged since
+fn lookup(cache: &HashMap<String, Cached>, path: &Path, (modified, size): (u64, u64)) -> Option<Option<String>> {
+    cache
+        .get(path.to_string_lossy().as_ref())
+        .filter(|cached| cached.modified == modified && cached.size == size)
+        .map(|cached| cached.favicon.clone())
+}
+
+fn cache_path(app: &tauri::AppHandle) -> Option<PathBuf> {
+    crate::get_data_dir(app).ok().map(|dir| dir.join(CACHE_FILE))
+}
+
+fn load(cache_path: &Path) -> HashMap<String, Cached> {
+    std::fs::read_to_string(cache_path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn store(cache_path: &Path, cache: &HashMap<String, Cached>) {
+    let Ok(content) = serde_json::to_string(cache) else {
+        return;
+    };
+    // Other processes read it too: never leave it half-written
+    let temp = cache_path.with_extension("json.tmp");
+    if std::fs::write(&temp, content).and_then(|_| std::fs::rename(&temp, cache_path)).is_err() {
+        let _ = std::fs::remove_file(&temp);
+    }
+}
+
+/// The favicon of the single-file wiki at `path`, from the cache if the wiki
+/// is unchanged since it was last read
+pub fn favicon_for_file(app: &tauri::AppHandle, path: &Path) -> Option<String> {
+    let (Some(cache_path), Some(stamp)) = (cache_path(app), stamp(path)) else {
+        return tiddlywiki_html::extract_favicon_from_file(path);
+    };
+    {
+        let _lock = CACHE_LOCK.lock().unwrap();
+        if let Some(favicon) = lookup(&load(&cache_path), path, stamp) {
+            return favicon;
+        }
+    }
+
+    let favicon = tiddlywiki_html::extract_favicon_from_file(path);
+    let _lock = CACHE_LOCK.lock().unwrap();
+    let mut cache = load(&cache_path);
+    // Wikis that are gone don't need their favicons any more
+    cache.retain(|cached_path, _| Path::new(cached_path).exists());
+    cache.insert(
+        path.to_string_lossy().into_owned(),
+        Cached { modified: stamp.0, size: stamp.1, favicon: favicon.clone() },
+    );
+    store(&cache_path, &cache);
+    favicon
+}
+
+/// Show a wiki's new favicon in the wiki list, the landing page and the tray
+fn announce(app: &tauri::AppHandle, path: &str, favicon: Option<String>) {
+    #[cfg(not(target_os = "android"))]
+    {
+        use tauri::Manager;
+
+        if let Some(state) = app.try_state::<crate::WikiModeState>() {
+            if let Some(client) = state.ipc_client.lock().unwrap().as_mut() {
+                if let Err(e) = client.send_update_favicon(path, favicon) {
+                    eprintln!("[TiddlyDesktop] Failed to send favicon: {}", e);
+                }
+            }
+            return;
+        }
+    }
+    if let Err(e) = crate::wiki_storage::update_wiki_favicon(app.clone(), path.to_string(), favicon) {
+        eprintln!("[TiddlyDesktop] Failed to update favicon: {}", e);
+    }
+}
+
+/// Look at the favicon of the single-file wiki at `path` again shortly after
+/// it was saved, and announce it if it changed
+pub fn refresh_after_save(app: &tauri::AppHandle, path: &Path) {
+    if !PENDING.lock().unwrap().insert(path.to_path_buf()) {
+        return;
+    }
+    let app = app.clone();
+    let path = path.to_path_buf();
+    std::thread::spawn(move || {
+        std::thread::sleep(REFRESH_DELAY);
+        PENDING.lock().unwrap().remove(&path);
+        let path_str = path.to_string_lossy().into_owned();
+        let Some(listed) = crate::wiki_storage::load_recent_files_from_disk(&app)
+            .into_iter()
+            .find(|entry| crate::utils::paths_equal(&entry.path, &path_str))
+        else {
+            return;
+        };
+        let favicon = favicon_for_file(&app, &path);
+        if favicon != listed.favicon {
+            eprintln!("[TiddlyDesktop] Favicon of {} changed", path_str);
+            announce(&app, &listed.path, favicon);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup() {
+        let path = Path::new("/wikis/notes.html");
+        let cached = Cached { modified: 1000, size: 42, favicon: Some("data:image/png;base64,AA==".to_string()) };
+        let cache = HashMap::from([(path.to_string_lossy().into_owned(), cached.clone())]);
+        assert_eq!(lookup(&cache, path, (1000, 42)), Some(cached.favicon));
+        // Changed since: read again
+        assert_eq!(lookup(&cache, path, (2000, 42)), None);
+        assert_eq!(lookup(&cache, path, (1000, 43)), None);
+        assert_eq!(lookup(&cache, Path::new("/wikis/other.html"), (1000, 42)), None);
+        // A wiki without a favicon is cached too
+        let none = Cached { modified: 1000, size: 42, favicon: None };
+        let cache = HashMap::from([(path.to_string_lossy().into_owned(), none)]);
+        assert_eq!(lookup(&cache, path, (1000, 42)), Some(None));
+    }
+}
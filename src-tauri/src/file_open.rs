@@ -0,0 +1,126 @@
+//! Opening wikis handed to the app by the OS (desktop)
+//!
+//! Double-clicking a TiddlyWiki file (file associations), "Open With" and
+//! dropping files on the app icon pass wikis to the app:
+//! - Windows and Linux: as command-line arguments (the .desktop file's `%F`).
+//!   When the app is already running, the new instance hands them over to it
+//!   (see `instance`).
+//! - macOS: as Apple Events (`RunEvent::Opened`), also while running
+//!
+//! HTML files open as single-file wikis, a wiki folder or its
+//! `tiddlywiki.info` as a folder wiki. Each opens in its own wiki process and
+//! is added to the wiki list.
+//!
+//! `tiddlydesktop://` links come in the same ways and are kept as they are
+//! (see `deep_link`).
+
+use std::path::{Path, PathBuf};
+
+use tauri::Emitter;
+
+use crate::{deep_link, utils};
+
+/// The wiki `path` stands for, as an absolute path (the running instance may
+/// have another working directory): an HTML file, a wiki folder, or the
+/// `tiddlywiki.info` of one (its folder)
+pub fn resolve_wiki(path: &Path) -> Option<String> {
+    let wiki = if path.is_file() && path.file_name().is_some_and(|name| name == "tiddlywiki.info") {
+        path.parent()?.to_path_buf()
+    } else {
+        path.to_path_buf()
+    };
+    let is_html = wiki.is_file()
+        && wiki
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("html") || e.eq_ignore_ascii_case("htm"));
+    if !is_html && !utils::is_wiki_folder(&wiki) {
+        return None;
+    }
+    let wiki = std::fs::canonicalize(wiki).ok()?;
+    Some(utils::normalize_path(wiki).to_string_lossy().to_string())
+}
+
+/// Wikis and deep links given on the command line. Launchers may pass files
+/// as `file://` URLs (the .desktop file's `%U`).
+pub fn command_line_wikis() -> Vec<String> {
+    std::env::args()
+        .skip(1)
+        .filter_map(|arg| {
+            if deep_link::is_deep_link(&arg) {
+                return Some(arg);
+            }
+            let path = match tauri::Url::parse(&arg) {
+                Ok(url) if url.scheme() == "file" => url.to_file_path().ok()?,
+                _ => PathBuf::from(arg),
+            };
+            resolve_wiki(&path)
+        })
+        .collect()
+}
+
+/// Open wikis passed by the OS or another instance, each in its own process,
+/// and follow deep links among them
+pub fn open_wikis(app: &tauri::AppHandle, paths: Vec<String>) {
+    let (links, paths): (Vec<String>, Vec<String>) = paths.into_iter().partition(|p| deep_link::is_deep_link(p));
+    for link in links {
+        if let Err(e) = deep_link::open(app, &link) {
+            eprintln!("[TiddlyDesktop] Failed to open link {}: {}", link, e);
+        }
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        for path in paths {
+            let result = if Path::new(&path).is_dir() {
+                crate::open_wiki_folder(app.clone(), path.clone(), None).await
+            } else {
+                crate::open_wiki_window(app.clone(), path.clone(), None, None, None).await
+            };
+            match result {
+                // Refresh the wiki list in the main window
+                Ok(entry) => {
+                    let _ = app.emit("wiki-list-changed", entry);
+                }
+                Err(e) => eprintln!("[TiddlyDesktop] Failed to open {}: {}", path, e),
+            }
+        }
+    });
+}
+
+/// Wikis among files the OS opened the app with, and deep links (macOS
+/// `RunEvent::Opened`)
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+pub fn wikis_from_urls(urls: &[tauri::Url]) -> Vec<String> {
+    urls.iter()
+        .filter_map(|url| {
+            if url.scheme() == deep_link::SCHEME {
+                return Some(url.to_string());
+            }
+            resolve_wiki(&url.to_file_path().ok()?)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolves_wiki_files_and_folders() {
+        let dir = std::env::temp_dir().join(format!("td-file-open-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("Notes")).unwrap();
+        std::fs::write(dir.join("wiki.HTML"), "").unwrap();
+        std::fs::write(dir.join("notes.txt"), "").unwrap();
+        std::fs::write(dir.join("Notes").join("tiddlywiki.info"), "{}").unwrap();
+
+        let folder = resolve_wiki(&dir.join("Notes")).unwrap();
+        assert!(folder.ends_with("Notes"));
+        assert_eq!(resolve_wiki(&dir.join("Notes").join("tiddlywiki.info")), Some(folder));
+        assert!(resolve_wiki(&dir.join("wiki.HTML")).unwrap().ends_with("wiki.HTML"));
+        assert_eq!(resolve_wiki(&dir.join("notes.txt")), None);
+        assert_eq!(resolve_wiki(&dir), None);
+        assert_eq!(resolve_wiki(&dir.join("missing.html")), None);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,175 @@
+//! First-run setup wizard
+//!
+//! When the landing page is created for the first time, `mark_pending` leaves
+//! a `first-run` marker in the data dir. While it is there the landing page
+//! shows the setup wizard (`get_first_run_state`) instead of an empty wiki
+//! list: where to keep the app data (system data dir or portable, next to the
+//! executable), importing wikis from classic TiddlyDesktop or from a folder,
+//! backups and LAN sync for the listed wikis, and a starter wiki.
+//! `finish_first_run` applies the choices and removes the marker, so quitting
+//! half-way shows the wizard again on the next start. Importing from classic
+//! TiddlyDesktop is done by `classic_import`.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+const MARKER: &str = "first-run";
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FirstRunState {
+    /// The wizard should be shown
+    pending: bool,
+    data_dir: String,
+    portable: bool,
+    /// The executable's directory can hold the data (offered as portable mode)
+    portable_available: bool,
+    /// Data dir of a classic TiddlyDesktop installation, when found
+    classic_dir: Option<String>,
+    /// Suggested path of the starter wiki
+    starter_path: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FirstRunChoices {
+    backups: bool,
+    /// Backups to keep per wiki (None: default)
+    #[serde(default)]
+    backup_count: Option<u32>,
+    /// Enable LAN sync for the listed wikis
+    #[serde(default)]
+    lan_sync: bool,
+    /// Create an empty starter wiki here
+    #[serde(default)]
+    starter_path: Option<String>,
+}
+
+/// Called when the landing page was just created
+pub fn mark_pending(data_dir: &Path) {
+    if let Err(e) = std::fs::write(data_dir.join(MARKER), "") {
+        eprintln!("[TiddlyDesktop] Failed to write first-run marker: {}", e);
+    }
+}
+
+fn exe_dir() -> Result<PathBuf, String> {
+    std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(Path::to_path_buf))
+        .ok_or_else(|| "Failed to find the program's folder".to_string())
+}
+
+/// Whether the data can be kept next to the executable (not inside an app
+/// bundle or a read-only install location)
+fn portable_available(exe_dir: &Path) -> bool {
+    if exe_dir.to_string_lossy().contains(".app/Contents/") {
+        return false;
+    }
+    let probe = exe_dir.join(".tiddlydesktop-write-test");
+    let writable = std::fs::write(&probe, "").is_ok();
+    let _ = std::fs::remove_file(&probe);
+    writable
+}
+
+/// A wiki file name in `dir` that isn't taken yet
+fn starter_path(dir: &Path) -> PathBuf {
+    let mut path = dir.join("My Wiki.html");
+    let mut n = 2;
+    while path.exists() {
+        path = dir.join(format!("My Wiki {}.html", n));
+        n += 1;
+    }
+    path
+}
+
+#[tauri::command]
+pub fn get_first_run_state(app: tauri::AppHandle) -> Result<FirstRunState, String> {
+    let data_dir = crate::get_data_dir(&app)?;
+    let portable = crate::get_portable_dir(&app).is_some();
+    let pending = cfg!(not(target_os = "android")) && data_dir.join(MARKER).exists();
+    let (portable_available, classic_dir, starter) = if pending {
+        (
+            !portable && exe_dir().map(|dir| portable_available(&dir)).unwrap_or(false),
+            crate::classic_import::find_classic_dir(),
+            dirs::document_dir().or_else(dirs::home_dir).map(|dir| starter_path(&dir)),
+        )
+    } else {
+        (false, None, None)
+    };
+    Ok(FirstRunState {
+        pending,
+        data_dir: data_dir.to_string_lossy().into_owned(),
+        portable,
+        portable_available,
+        classic_dir: classic_dir.map(|dir| dir.to_string_lossy().into_owned()),
+        starter_path: starter.map(|path| path.to_string_lossy().into_owned()),
+    })
+}
+
+/// Switch to portable mode during the first run: the data created so far is
+/// copied next to the executable, which gets a `portable` marker, and the app
+/// restarts from there
+#[tauri::command]
+pub fn use_portable_mode(app: tauri::AppHandle) -> Result<(), String> {
+    let data_dir = crate::get_data_dir(&app)?;
+    if !data_dir.join(MARKER).exists() {
+        return Err("Portable mode can only be chosen during the first-run setup".to_string());
+    }
+    let exe_dir = exe_dir()?;
+    if !portable_available(&exe_dir) {
+        return Err(format!("Can't keep the data in {}", exe_dir.display()));
+    }
+    for entry in std::fs::read_dir(&data_dir).map_err(|e| format!("Failed to read data dir: {}", e))?.flatten() {
+        let target = exe_dir.join(entry.file_name());
+        if entry.file_type().map(|t| t.is_file()).unwrap_or(false) && !target.exists() {
+            std::fs::copy(entry.path(), &target)
+                .map_err(|e| format!("Failed to copy {}: {}", entry.path().display(), e))?;
+        }
+    }
+    std::fs::write(exe_dir.join("portable"), "").map_err(|e| format!("Failed to write portable marker: {}", e))?;
+    // The copies in the system data dir are left alone; without the marker
+    // there they are just an unused landing page
+    let _ = std::fs::remove_file(data_dir.join(MARKER));
+    eprintln!("[TiddlyDesktop] Switched to portable mode, restarting");
+    app.restart()
+}
+
+/// Apply the wizard's choices to the wiki list and end the first run.
+/// Returns the path of the starter wiki, if one was created.
+#[tauri::command]
+pub async fn finish_first_run(app: tauri::AppHandle, choices: FirstRunChoices) -> Result<Option<String>, String> {
+    let mut starter = None;
+    if let Some(path) = choices.starter_path.filter(|p| !p.trim().is_empty()) {
+        crate::create_wiki_file(app.clone(), path.clone(), "empty".to_string(), Vec::new()).await?;
+        // create_wiki_file adds the extension when it is missing
+        let path = PathBuf::from(path);
+        let path = if path.extension().map(|e| e == "html" || e == "htm").unwrap_or(false) {
+            path
+        } else {
+            path.with_extension("html")
+        };
+        crate::batch_register::register_wikis(&app, vec![(path.clone(), false)]).await?;
+        starter = Some(path.to_string_lossy().into_owned());
+    }
+
+    let mut entries = crate::wiki_storage::load_recent_files_from_disk(&app);
+    for entry in entries.iter_mut().filter(|e| !e.is_folder) {
+        entry.backups_enabled = choices.backups;
+        entry.backup_count = choices.backup_count;
+    }
+    crate::wiki_storage::save_recent_files_to_disk(&app, &entries)?;
+    if choices.lan_sync {
+        for entry in &entries {
+            crate::wiki_storage::set_wiki_sync(app.clone(), entry.path.clone(), true)?;
+        }
+    }
+
+    let marker = crate::get_data_dir(&app)?.join(MARKER);
+    if let Err(e) = std::fs::remove_file(&marker) {
+        if e.kind() != std::io::ErrorKind::NotFound {
+            return Err(format!("Failed to finish the first-run setup: {}", e));
+        }
+    }
+    Ok(starter)
+}
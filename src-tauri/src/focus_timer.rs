@@ -0,0 +1,313 @@
+//! Focus timer (pomodoro)
+//!
+//! One timer for the whole app: focus sessions alternate with short breaks,
+//! and every few sessions with a long break. The timer is just its start time
+//! and settings in `focus_timer.json` in the data dir, so every process (main
+//! and wiki processes) derives the same phase from the clock without talking
+//! to the others.
+//!
+//! Each process polls the file and emits `focus-timer-changed` (the status) to
+//! its windows when the timer starts, stops or changes phase; wiki windows show
+//! a small badge (`init_script/focus_timer.js`). The main process (or a wiki
+//! process running without it) shows a native notification at phase changes.
+
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tauri::Emitter;
+
+/// How often the timer state is polled
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+const MAX_MINUTES: u32 = 240;
+const MAX_SESSIONS_BEFORE_LONG_BREAK: u32 = 12;
+
+static WATCHER_STARTED: OnceLock<()> = OnceLock::new();
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Phase {
+    Focus,
+    ShortBreak,
+    LongBreak,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TimerSettings {
+    focus_minutes: u32,
+    short_break_minutes: u32,
+    long_break_minutes: u32,
+    sessions_before_long_break: u32,
+}
+
+impl Default for TimerSettings {
+    fn default() -> Self {
+        Self {
+            focus_minutes: 25,
+            short_break_minutes: 5,
+            long_break_minutes: 15,
+            sessions_before_long_break: 4,
+        }
+    }
+}
+
+/// Stored timer state
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct TimerState {
+    /// Milliseconds since the Unix epoch when the timer was started (None: stopped)
+    #[serde(default)]
+    started: Option<u64>,
+    #[serde(default)]
+    settings: TimerSettings,
+}
+
+/// Timer status as seen by the windows
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FocusTimerStatus {
+    running: bool,
+    phase: Option<Phase>,
+    remaining_seconds: u64,
+    /// Focus sessions finished since the timer was started
+    completed_sessions: u32,
+    #[serde(flatten)]
+    settings: TimerSettings,
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn state_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(crate::get_data_dir(app)?.join("focus_timer.json"))
+}
+
+fn load_state(app: &tauri::AppHandle) -> TimerState {
+    state_path(app)
+        .ok()
+        .and_then(|p| std::fs::read_to_string(p).ok())
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn save_state(app: &tauri::AppHandle, state: &TimerState) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(state).map_err(|e| format!("Failed to serialize focus timer: {}", e))?;
+    std::fs::write(state_path(app)?, json).map_err(|e| format!("Failed to save focus timer: {}", e))
+}
+
+/// Phase, finished focus sessions and milliseconds left in the phase, after
+/// the timer has run for `elapsed` milliseconds
+fn phase_at(settings: &TimerSettings, elapsed: u64) -> (Phase, u32, u64) {
+    let minute = 60_000u64;
+    let focus = settings.focus_minutes.max(1) as u64 * minute;
+    let short_break = settings.short_break_minutes.max(1) as u64 * minute;
+    let long_break = settings.long_break_minutes.max(1) as u64 * minute;
+    let sessions = settings.sessions_before_long_break.max(1);
+    let cycle = sessions as u64 * focus + (sessions as u64 - 1) * short_break + long_break;
+
+    let cycles = (elapsed / cycle) as u32;
+    let mut offset = elapsed % cycle;
+    for session in 0..sessions {
+        let completed = cycles * sessions + session;
+        if offset < focus {
+            return (Phase::Focus, completed, focus - offset);
+        }
+        offset -= focus;
+        let (phase, length) = if session + 1 < sessions {
+            (Phase::ShortBreak, short_break)
+        } else {
+            (Phase::LongBreak, long_break)
+        };
+        if offset < length {
+            return (phase, completed + 1, length - offset);
+        }
+        offset -= length;
+    }
+    unreachable!("offset is within the cycle")
+}
+
+fn status_at(state: &TimerState, now: u64) -> FocusTimerStatus {
+    match state.started {
+        Some(started) => {
+            let (phase, completed_sessions, remaining) = phase_at(&state.settings, now.saturating_sub(started));
+            FocusTimerStatus {
+                running: true,
+                phase: Some(phase),
+                remaining_seconds: remaining.div_ceil(1000),
+                completed_sessions,
+                settings: state.settings,
+            }
+        }
+        None => FocusTimerStatus {
+            running: false,
+            phase: None,
+            remaining_seconds: 0,
+            completed_sessions: 0,
+            settings: state.settings,
+        },
+    }
+}
+
+#[cfg(not(target_os = "android"))]
+fn notify_phase(app: &tauri::AppHandle, status: &FocusTimerStatus) {
+    use tauri_plugin_notification::NotificationExt;
+
+    let (title, body) = match status.phase {
+        Some(Phase::Focus) => ("Time to focus", format!("Focus for {} minutes", status.settings.focus_minutes)),
+        Some(Phase::ShortBreak) => (
+            "Focus session done",
+            format!("Take a {} minute break", status.settings.short_break_minutes),
+        ),
+        Some(Phase::LongBreak) => (
+            "Focus sessions done",
+            format!(
+                "{} sessions finished. Take a {} minute break",
+                status.completed_sessions, status.settings.long_break_minutes
+            ),
+        ),
+        None => return,
+    };
+    if let Err(e) = app.notification().builder().title(title).body(body).show() {
+        eprintln!("[TiddlyDesktop] Failed to show focus timer notification: {}", e);
+    }
+}
+
+/// Watch the timer in this process (once per process). `notify` is set for
+/// the main process and for wiki processes running without it.
+#[cfg(not(target_os = "android"))]
+pub fn start(app: &tauri::AppHandle, notify: bool) {
+    if WATCHER_STARTED.set(()).is_err() {
+        return;
+    }
+    let app = app.clone();
+    std::thread::spawn(move || {
+        let state = load_state(&app);
+        let mut last_started = state.started;
+        let mut last = status_at(&state, now_ms());
+        loop {
+            std::thread::sleep(POLL_INTERVAL);
+            let state = load_state(&app);
+            let status = status_at(&state, now_ms());
+            // A restart while running keeps the phase but moves the end
+            let changed = state.started != last_started
+                || status.phase != last.phase
+                || status.completed_sessions != last.completed_sessions
+                || status.settings != last.settings;
+            if !changed {
+                continue;
+            }
+            if notify && status.running && last.running && status.phase != last.phase {
+                notify_phase(&app, &status);
+            }
+            // The tray item toggles between start and stop (main process only)
+            if status.running != last.running {
+                crate::refresh_tray_menu(&app);
+            }
+            let _ = app.emit("focus-timer-changed", &status);
+            last_started = state.started;
+            last = status;
+        }
+    });
+}
+
+/// Start (or restart) the focus timer. Settings left out keep their
+/// previous values.
+#[tauri::command]
+pub fn start_focus_timer(
+    app: tauri::AppHandle,
+    focus_minutes: Option<u32>,
+    short_break_minutes: Option<u32>,
+    long_break_minutes: Option<u32>,
+    sessions_before_long_break: Option<u32>,
+) -> Result<FocusTimerStatus, String> {
+    let mut state = load_state(&app);
+    let settings = &mut state.settings;
+    let minutes = |value: Option<u32>, current: u32| value.unwrap_or(current).clamp(1, MAX_MINUTES);
+    settings.focus_minutes = minutes(focus_minutes, settings.focus_minutes);
+    settings.short_break_minutes = minutes(short_break_minutes, settings.short_break_minutes);
+    settings.long_break_minutes = minutes(long_break_minutes, settings.long_break_minutes);
+    settings.sessions_before_long_break = sessions_before_long_break
+        .unwrap_or(settings.sessions_before_long_break)
+        .clamp(1, MAX_SESSIONS_BEFORE_LONG_BREAK);
+    let now = now_ms();
+    state.started = Some(now);
+    save_state(&app, &state)?;
+    Ok(status_at(&state, now))
+}
+
+/// Whether the focus timer is running
+pub fn is_running(app: &tauri::AppHandle) -> bool {
+    load_state(app).started.is_some()
+}
+
+/// Stop the timer, or start it with the previous settings (tray menu)
+#[cfg(not(target_os = "android"))]
+pub fn toggle(app: &tauri::AppHandle) {
+    let result = if is_running(app) {
+        stop_focus_timer(app.clone())
+    } else {
+        start_focus_timer(app.clone(), None, None, None, None).map(|_| ())
+    };
+    if let Err(e) = result {
+        eprintln!("[TiddlyDesktop] Failed to toggle focus timer: {}", e);
+    }
+}
+
+#[tauri::command]
+pub fn stop_focus_timer(app: tauri::AppHandle) -> Result<(), String> {
+    let mut state = load_state(&app);
+    if state.started.take().is_some() {
+        save_state(&app, &state)?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_focus_timer_status(app: tauri::AppHandle) -> FocusTimerStatus {
+    status_at(&load_state(&app), now_ms())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE: u64 = 60_000;
+
+    #[test]
+    fn test_phase_at() {
+        let settings = TimerSettings::default();
+        assert_eq!(phase_at(&settings, 0), (Phase::Focus, 0, 25 * MINUTE));
+        assert_eq!(phase_at(&settings, 25 * MINUTE), (Phase::ShortBreak, 1, 5 * MINUTE));
+        assert_eq!(phase_at(&settings, 31 * MINUTE), (Phase::Focus, 1, 24 * MINUTE));
+        // 4 focus sessions and 3 short breaks, then the long break
+        let long_break = 4 * 25 * MINUTE + 3 * 5 * MINUTE;
+        assert_eq!(phase_at(&settings, long_break), (Phase::LongBreak, 4, 15 * MINUTE));
+        // The next cycle starts over with a focus session
+        assert_eq!(phase_at(&settings, long_break + 15 * MINUTE), (Phase::Focus, 4, 25 * MINUTE));
+    }
+
+    #[test]
+    fn test_single_session_cycle() {
+        let settings = TimerSettings {
+            focus_minutes: 50,
+            short_break_minutes: 5,
+            long_break_minutes: 10,
+            sessions_before_long_break: 1,
+        };
+        assert_eq!(phase_at(&settings, 50 * MINUTE), (Phase::LongBreak, 1, 10 * MINUTE));
+        assert_eq!(phase_at(&settings, 60 * MINUTE), (Phase::Focus, 1, 50 * MINUTE));
+    }
+
+    #[test]
+    fn test_stopped_status() {
+        let status = status_at(&TimerState::default(), 1000);
+        assert!(!status.running);
+        assert_eq!(status.phase, None);
+    }
+}
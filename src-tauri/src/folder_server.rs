@@ -0,0 +1,647 @@
+//! Node-free server for folder wikis
+//!
+//! Serves a wiki folder over the TiddlyWeb protocol like `tiddlywiki --listen`
+//! does, without starting Node.js:
+//! - `GET /`: the boot page with the core, the plugins from `tiddlywiki.info`
+//!   and all tiddlers
+//! - `GET /status`, `GET /favicon.ico` and files below `files/`
+//! - `GET /recipes/default/tiddlers.json`: skinny tiddlers for the syncer
+//! - `GET`/`PUT /recipes/default/tiddlers/<title>`, `DELETE /bags/default/tiddlers/<title>`
+//! - the tiddler files below `/dav/` over WebDAV, when enabled (see `webdav`)
+//!
+//! It listens where the settings say (see `server_address`), and is advertised
+//! to other devices when they can reach it (see `server_discovery`). Their
+//! writes need the password of `server_address`.
+//!
+//! Used instead of Node.js when enabled in the settings, or when Node.js isn't
+//! available, and always for SQLite wikis (see `tiddlydesktop_core::sqlite_wiki`).
+//! Tiddler files are read and written with `tiddlydesktop_core::wiki_folder`;
+//! changes made to them or to the database by other programs are picked up
+//! when the wiki polls for changes.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use base64::Engine;
+use tiddlydesktop_core::filter::{self, Tiddler};
+use tiddlydesktop_core::sqlite_wiki::{self, SqliteWiki};
+use tiddlydesktop_core::wiki_folder::{self, TiddlerFormat, TiddlyWikiInstall};
+use tiny_http::{Header, Method, Request, Response, StatusCode};
+
+use crate::server_address::{self, Binding, Servers};
+use crate::webdav::{self, DavShare};
+
+/// Filter used by `tiddlers.json` when the request doesn't name one
+const DEFAULT_FILTER: &str = "[all[tiddlers]!is[system]sort[title]]";
+
+/// Fields the TiddlyWeb format keeps at the top level; others go into `fields`
+const KNOWN_FIELDS: &[&str] = &[
+    "bag", "created", "creator", "modified", "modifier", "permissions", "recipe", "revision", "tags", "text", "title", "type", "uri",
+];
+
+/// Don't rescan the tiddler files more often than this
+const RESCAN_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Where a wiki's own tiddlers are kept
+trait TiddlerStorage: Send {
+    /// All tiddlers, or `None` if they can't have changed since the last load
+    fn load(&mut self) -> Option<Vec<Tiddler>>;
+    fn save(&mut self, title: &str, tiddler: &Tiddler) -> Result<(), String>;
+    fn delete(&mut self, title: &str) -> Result<(), String>;
+}
+
+/// Tiddler files in `tiddlers/`, like `tiddlywiki --listen` uses them
+struct FolderStorage {
+    tiddlers_dir: PathBuf,
+    /// File of each tiddler stored alone in a file (not from a multi-tiddler file)
+    files: HashMap<String, PathBuf>,
+    /// Tiddlers stored together with others, which can't be deleted alone
+    shared: HashSet<String>,
+}
+
+impl FolderStorage {
+    fn open(wiki_dir: &Path) -> Result<Self, String> {
+        let tiddlers_dir = wiki_dir.join("tiddlers");
+        std::fs::create_dir_all(&tiddlers_dir)
+            .map_err(|e| format!("Failed to create {}: {}", tiddlers_dir.display(), e))?;
+        Ok(Self { tiddlers_dir, files: HashMap::new(), shared: HashSet::new() })
+    }
+
+    /// A file path (without extension) for a new tiddler file that doesn't
+    /// collide with another tiddler's file
+    fn new_file_base(&self, title: &str, format: &TiddlerFormat) -> PathBuf {
+        let extension = match format {
+            TiddlerFormat::Tid => ".tid",
+            TiddlerFormat::Json => ".json",
+            TiddlerFormat::Native { extension } => extension,
+        };
+        let name = wiki_folder::title_to_filename(title);
+        let mut base = self.tiddlers_dir.join(&name);
+        let mut counter = 1;
+        while [".tid", ".json", extension].iter().any(|ext| {
+            let candidate = PathBuf::from(format!("{}{}", base.to_string_lossy(), ext));
+            candidate.exists() && self.files.get(title) != Some(&candidate)
+        }) {
+            base = self.tiddlers_dir.join(format!("{} {}", name, counter));
+            counter += 1;
+        }
+        base
+    }
+}
+
+impl TiddlerStorage for FolderStorage {
+    fn load(&mut self) -> Option<Vec<Tiddler>> {
+        let mut tiddlers = Vec::new();
+        self.files.clear();
+        self.shared.clear();
+        for file in wiki_folder::load_tiddlers_from_path(&self.tiddlers_dir) {
+            for tiddler in file.tiddlers {
+                let Some(title) = tiddler.get("title").cloned() else { continue };
+                if file.single {
+                    self.shared.remove(&title);
+                    self.files.insert(title, file.path.clone());
+                } else {
+                    self.files.remove(&title);
+                    self.shared.insert(title);
+                }
+                tiddlers.push(tiddler);
+            }
+        }
+        Some(tiddlers)
+    }
+
+    fn save(&mut self, title: &str, tiddler: &Tiddler) -> Result<(), String> {
+        let format = wiki_folder::tiddler_format(tiddler);
+
+        // Save back to the tiddler's own file if it has the right format
+        let existing = self.files.get(title).cloned();
+        let reusable = existing.as_ref().and_then(|path| {
+            let path_str = path.to_string_lossy();
+            let has_meta = PathBuf::from(format!("{}.meta", path_str)).is_file();
+            let extension = match &format {
+                TiddlerFormat::Tid if !has_meta => ".tid",
+                TiddlerFormat::Json if !has_meta => ".json",
+                TiddlerFormat::Native { extension } if has_meta => extension,
+                _ => return None,
+            };
+            path_str.strip_suffix(extension).map(PathBuf::from)
+        });
+        let base = reusable.unwrap_or_else(|| self.new_file_base(title, &format));
+        let written = wiki_folder::write_tiddler(&base, tiddler, &format)?;
+
+        if let Some(old) = existing {
+            if old != written[0] {
+                remove_tiddler_file(&old);
+            }
+        }
+        self.shared.remove(title);
+        self.files.insert(title.to_string(), written[0].clone());
+        Ok(())
+    }
+
+    fn delete(&mut self, title: &str) -> Result<(), String> {
+        match self.files.remove(title) {
+            Some(path) => remove_tiddler_file(&path),
+            None if self.shared.contains(title) => {
+                eprintln!("[FolderServer] {} is stored with other tiddlers and stays on disk", title);
+            }
+            None => {}
+        }
+        Ok(())
+    }
+}
+
+fn remove_tiddler_file(path: &Path) {
+    let _ = std::fs::remove_file(path);
+    let _ = std::fs::remove_file(format!("{}.meta", path.to_string_lossy()));
+}
+
+/// The `tiddlers.sqlite` database of a SQLite wiki
+struct DatabaseStorage {
+    db: SqliteWiki,
+    loaded: bool,
+}
+
+impl TiddlerStorage for DatabaseStorage {
+    fn load(&mut self) -> Option<Vec<Tiddler>> {
+        if self.loaded && !self.db.changed_externally() {
+            return None;
+        }
+        self.loaded = true;
+        match self.db.load_all() {
+            Ok(tiddlers) => Some(tiddlers),
+            Err(e) => {
+                eprintln!("[FolderServer] Failed to load tiddlers: {}", e);
+                None
+            }
+        }
+    }
+
+    fn save(&mut self, _title: &str, tiddler: &Tiddler) -> Result<(), String> {
+        self.db.put(tiddler)
+    }
+
+    fn delete(&mut self, title: &str) -> Result<(), String> {
+        self.db.delete(title)
+    }
+}
+
+/// The tiddlers of a wiki folder, with revisions for the syncer
+struct Store {
+    install: TiddlyWikiInstall,
+    wiki_dir: PathBuf,
+    storage: Box<dyn TiddlerStorage>,
+    /// Core, plugins, themes and languages, packed once at startup
+    plugins: BTreeMap<String, Tiddler>,
+    /// Source folder of a plugin in development, packed on every page load (plugin_dev.rs)
+    dev_plugin: Option<PathBuf>,
+    tiddlers: BTreeMap<String, Tiddler>,
+    revisions: HashMap<String, u64>,
+    next_revision: u64,
+    last_scan: Instant,
+}
+
+impl Store {
+    fn open(wiki_dir: &Path, install: TiddlyWikiInstall, dev_plugin: Option<PathBuf>) -> Result<Self, String> {
+        let plugins = install.load_plugins(wiki_dir)?
+            .into_iter()
+            .filter_map(|p| p.get("title").cloned().map(|title| (title, p)))
+            .collect();
+        let storage: Box<dyn TiddlerStorage> = if sqlite_wiki::is_sqlite_wiki(wiki_dir) {
+            Box::new(DatabaseStorage { db: SqliteWiki::open(wiki_dir)?, loaded: false })
+        } else {
+            Box::new(FolderStorage::open(wiki_dir)?)
+        };
+        let mut store = Self {
+            install,
+            wiki_dir: wiki_dir.to_path_buf(),
+            storage,
+            plugins,
+            dev_plugin,
+            tiddlers: BTreeMap::new(),
+            revisions: HashMap::new(),
+            next_revision: 1,
+            last_scan: Instant::now(),
+        };
+        store.pack_dev_plugin();
+        store.scan();
+        Ok(store)
+    }
+
+    /// Pack the plugin in development again, keeping the last working one if that fails
+    fn pack_dev_plugin(&mut self) {
+        let Some(dir) = &self.dev_plugin else { return };
+        match wiki_folder::load_plugin_folder(dir) {
+            Ok(plugin) => {
+                if let Some(title) = plugin.get("title").cloned() {
+                    self.plugins.insert(title, plugin);
+                }
+            }
+            Err(e) => eprintln!("[FolderServer] {}", e),
+        }
+    }
+
+    fn bump(&mut self, title: &str) -> u64 {
+        let revision = self.next_revision;
+        self.next_revision += 1;
+        self.revisions.insert(title.to_string(), revision);
+        revision
+    }
+
+    /// Reload the tiddlers, giving changed tiddlers a new revision
+    fn scan(&mut self) {
+        self.last_scan = Instant::now();
+        let Some(loaded) = self.storage.load() else { return };
+        let tiddlers: BTreeMap<String, Tiddler> = loaded.into_iter()
+            .filter_map(|t| t.get("title").cloned().map(|title| (title, t)))
+            .collect();
+        let changed: Vec<String> = tiddlers.iter()
+            .filter(|(title, tiddler)| self.tiddlers.get(*title) != Some(*tiddler))
+            .map(|(title, _)| title.clone())
+            .collect();
+        for title in changed {
+            self.bump(&title);
+        }
+        self.revisions.retain(|title, _| tiddlers.contains_key(title));
+        self.tiddlers = tiddlers;
+    }
+
+    fn rescan_if_stale(&mut self) {
+        if self.last_scan.elapsed() >= RESCAN_INTERVAL {
+            self.scan();
+        }
+    }
+
+    fn get(&self, title: &str) -> Option<&Tiddler> {
+        self.tiddlers.get(title).or_else(|| self.plugins.get(title))
+    }
+
+    fn revision(&self, title: &str) -> u64 {
+        self.revisions.get(title).copied().unwrap_or(0)
+    }
+
+    /// Plugins and tiddlers as one wiki (tiddlers override plugins of the same title)
+    fn wiki(&self) -> filter::Wiki {
+        let mut all = self.plugins.clone();
+        all.extend(self.tiddlers.iter().map(|(title, tiddler)| (title.clone(), tiddler.clone())));
+        filter::Wiki::from_tiddlers(all.into_values())
+    }
+
+    fn boot_html(&self) -> Result<String, String> {
+        let mut all = self.plugins.clone();
+        all.extend(self.tiddlers.iter().map(|(title, tiddler)| (title.clone(), tiddler.clone())));
+        self.install.build_boot_html(&all.into_values().collect::<Vec<_>>())
+    }
+
+    fn save(&mut self, title: &str, mut tiddler: Tiddler) -> Result<u64, String> {
+        tiddler.insert("title".to_string(), title.to_string());
+        self.storage.save(title, &tiddler)?;
+        self.tiddlers.insert(title.to_string(), tiddler);
+        Ok(self.bump(title))
+    }
+
+    fn delete(&mut self, title: &str) {
+        if let Err(e) = self.storage.delete(title) {
+            eprintln!("[FolderServer] {}", e);
+        }
+        self.tiddlers.remove(title);
+        self.revisions.remove(title);
+    }
+}
+
+/// Start serving `wiki_dir` on `port` at the addresses of `binding`, with the
+/// core and plugins of the TiddlyWiki installation in `tw_dir` (and the plugin
+/// in the source folder `dev_plugin`), and its tiddler files over WebDAV if
+/// `webdav` is set (not for SQLite wikis). Returns once the port is bound,
+/// with the servers so they can be stopped (`Servers::unblock`).
+pub fn start(
+    wiki_dir: &Path,
+    tw_dir: &Path,
+    port: u16,
+    webdav: bool,
+    dev_plugin: Option<PathBuf>,
+    binding: &Binding,
+) -> Result<Servers, String> {
+    let store = Store::open(wiki_dir, TiddlyWikiInstall::new(tw_dir), dev_plugin)?;
+    let servers = binding.http(port)?;
+    eprintln!(
+        "[FolderServer] Serving {} ({} tiddlers, {} plugins) at {}",
+        wiki_dir.display(), store.tiddlers.len(), store.plugins.len(), server_address::local_url(port)
+    );
+    for url in binding.lan_urls(port) {
+        eprintln!("[FolderServer] Reachable at {}", url);
+    }
+
+    let dav = (webdav && !sqlite_wiki::is_sqlite_wiki(wiki_dir))
+        .then(|| Arc::new(DavShare::new(wiki_dir.join("tiddlers"))));
+    if dav.is_some() {
+        eprintln!("[FolderServer] WebDAV at {}{}/", server_address::local_url(port).trim_end_matches('/'), webdav::DAV_PREFIX);
+    }
+    let announcement = crate::server_discovery::announce(binding, &wiki_dir.to_string_lossy(), port, "/", dav.is_some());
+
+    let store = Arc::new(Mutex::new(store));
+    let binding = Arc::new(binding.clone());
+    servers.serve(move |request| {
+        // Advertised until the servers stop and drop this
+        let _announcement = &announcement;
+        let store = store.clone();
+        let dav = dav.clone();
+        let binding = binding.clone();
+        std::thread::spawn(move || {
+            if let Err(e) = handle_request(request, &store, dav.as_deref(), &binding) {
+                eprintln!("[FolderServer] {}", e);
+            }
+        });
+    });
+    Ok(servers)
+}
+
+/// Whether folder wikis use this server instead of Node.js
+#[tauri::command]
+pub fn get_native_folder_server(app: tauri::AppHandle) -> bool {
+    crate::wiki_storage::load_app_settings(&app).map(|s| s.native_folder_server).unwrap_or(false)
+}
+
+/// Use this server instead of Node.js for folder wikis opened from now on
+#[tauri::command]
+pub fn set_native_folder_server(app: tauri::AppHandle, enabled: bool) -> Result<bool, String> {
+    let mut settings = crate::wiki_storage::load_app_settings(&app)?;
+    settings.native_folder_server = enabled;
+    crate::wiki_storage::save_app_settings(&app, &settings)?;
+    Ok(enabled)
+}
+
+/// Whether this server also serves the tiddler files over WebDAV
+#[tauri::command]
+pub fn get_folder_webdav(app: tauri::AppHandle) -> bool {
+    crate::wiki_storage::load_app_settings(&app).map(|s| s.folder_webdav).unwrap_or(false)
+}
+
+/// Serve the tiddler files over WebDAV for folder wikis opened from now on
+#[tauri::command]
+pub fn set_folder_webdav(app: tauri::AppHandle, enabled: bool) -> Result<bool, String> {
+    let mut settings = crate::wiki_storage::load_app_settings(&app)?;
+    settings.folder_webdav = enabled;
+    crate::wiki_storage::save_app_settings(&app, &settings)?;
+    Ok(enabled)
+}
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name.as_bytes(), value.as_bytes()).unwrap()
+}
+
+fn respond<R: Read>(request: Request, response: Response<R>) -> Result<(), String> {
+    request.respond(response).map_err(|e| format!("Failed to send response: {}", e))
+}
+
+fn respond_status(request: Request, status: u16, message: &str) -> Result<(), String> {
+    respond(request, Response::from_string(message).with_status_code(StatusCode(status)))
+}
+
+fn respond_json(request: Request, value: &serde_json::Value) -> Result<(), String> {
+    respond(request, Response::from_string(value.to_string())
+        .with_header(header("Content-Type", "application/json")))
+}
+
+fn decode(s: &str) -> String {
+    urlencoding::decode(s).map(|s| s.into_owned()).unwrap_or_else(|_| s.to_string())
+}
+
+fn parse_query(query: &str) -> HashMap<String, String> {
+    query.split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (decode(&key.replace('+', " ")), decode(&value.replace('+', " ")))
+        })
+        .collect()
+}
+
+/// TiddlyWiki's server rejects writes without this header, so other web pages
+/// can't change the wiki through the browser
+fn has_csrf_header(request: &Request) -> bool {
+    request.headers().iter().any(|h| {
+        h.field.equiv("X-Requested-With") && h.value.as_str() == "TiddlyWiki"
+    })
+}
+
+fn handle_request(mut request: Request, store: &Arc<Mutex<Store>>, dav: Option<&DavShare>, binding: &Binding) -> Result<(), String> {
+    // Web pages that rebind their own host name to this computer
+    if !server_address::allows_host(&request) {
+        return respond_status(request, 403, "Unknown host");
+    }
+    // Other devices on the network
+    if !binding.allows_write(&request) {
+        return server_address::refuse_write(request);
+    }
+    let url = request.url().to_string();
+    let (path, query) = url.split_once('?').unwrap_or((url.as_str(), ""));
+
+    // WebDAV clients don't send the CSRF header; other web pages can't make
+    // these requests without a CORS preflight, which isn't answered
+    if let Some(dav) = dav.filter(|_| webdav::is_dav_path(path)) {
+        // Uploads are read first, so a slow client doesn't hold up the wiki
+        let Some(body) = webdav::read_body(&mut request)? else {
+            return respond_status(request, 413, "Request too large");
+        };
+        // Tiddler files change with the store held, then the wiki sees them
+        let mut store = store.lock().unwrap();
+        if dav.handle(request, body)? {
+            store.scan();
+        }
+        return Ok(());
+    }
+    let query = parse_query(query);
+    let method = request.method().clone();
+
+    if matches!(method, Method::Put | Method::Delete) && !has_csrf_header(&request) {
+        return respond_status(request, 403, "Missing X-Requested-With header");
+    }
+
+    const TIDDLER_ROUTE: &str = "/recipes/default/tiddlers/";
+    const BAG_ROUTE: &str = "/bags/default/tiddlers/";
+    match (&method, path) {
+        (Method::Get, "/") | (Method::Get, "/index.html") => {
+            let html = {
+                let mut store = store.lock().unwrap();
+                store.rescan_if_stale();
+                store.pack_dev_plugin();
+                store.boot_html()
+            };
+            match html {
+                Ok(html) => respond(request, Response::from_string(html)
+                    .with_header(header("Content-Type", "text/html; charset=utf-8"))),
+                Err(e) => respond_status(request, 500, &e),
+            }
+        }
+        (Method::Get, "/status") => {
+            let version = store.lock().unwrap().install.version();
+            respond_json(request, &serde_json::json!({
+                "username": "GUEST",
+                "anonymous": true,
+                "read_only": false,
+                "logout_is_available": false,
+                "space": { "recipe": "default" },
+                "tiddlywiki_version": version,
+            }))
+        }
+        (Method::Get, "/favicon.ico") => {
+            let favicon = store.lock().unwrap().get("$:/favicon.ico").cloned();
+            match favicon {
+                Some(tiddler) => {
+                    let content_type = tiddler.get("type").cloned().unwrap_or_else(|| "image/x-icon".to_string());
+                    let text = tiddler.get("text").cloned().unwrap_or_default();
+                    let body = if wiki_folder::is_binary_type(&content_type) {
+                        base64::engine::general_purpose::STANDARD.decode(text.trim()).unwrap_or_default()
+                    } else {
+                        text.into_bytes()
+                    };
+                    respond(request, Response::from_data(body).with_header(header("Content-Type", &content_type)))
+                }
+                None => respond_status(request, 404, "Not Found"),
+            }
+        }
+        (Method::Get, "/recipes/default/tiddlers.json") => serve_skinny_tiddlers(request, store, &query),
+        (Method::Get, p) if p.starts_with(TIDDLER_ROUTE) => {
+            let title = decode(&p[TIDDLER_ROUTE.len()..]);
+            serve_tiddler(request, store, &title)
+        }
+        (Method::Put, p) if p.starts_with(TIDDLER_ROUTE) => {
+            let title = decode(&p[TIDDLER_ROUTE.len()..]);
+            save_tiddler(request, store, &title)
+        }
+        (Method::Delete, p) if p.starts_with(BAG_ROUTE) || p.starts_with(TIDDLER_ROUTE) => {
+            let prefix = if p.starts_with(BAG_ROUTE) { BAG_ROUTE } else { TIDDLER_ROUTE };
+            let title = decode(&p[prefix.len()..]);
+            store.lock().unwrap().delete(&title);
+            respond_status(request, 204, "")
+        }
+        (Method::Get, p) if p.starts_with("/files/") => {
+            let files_dir = store.lock().unwrap().wiki_dir.join("files");
+            serve_file(request, &files_dir, &decode(&p["/files/".len()..]))
+        }
+        _ => respond_status(request, 404, "Not Found"),
+    }
+}
+
+fn serve_skinny_tiddlers(request: Request, store: &Arc<Mutex<Store>>, query: &HashMap<String, String>) -> Result<(), String> {
+    let filter = query.get("filter").map(String::as_str).unwrap_or(DEFAULT_FILTER);
+    let exclude: Vec<&str> = query.get("exclude").map(|e| e.split(',').collect()).unwrap_or_else(|| vec!["text"]);
+
+    let mut store = store.lock().unwrap();
+    store.rescan_if_stale();
+    let wiki = store.wiki();
+
+    // Like TiddlyWiki's server, only run filters the wiki allows
+    let allowed = |title: &str| wiki.get_tiddler(title).and_then(|t| t.get("text")).map(|t| t.trim() == "yes").unwrap_or(false);
+    if !allowed("$:/config/Server/AllowAllExternalFilters") && !allowed(&format!("$:/config/Server/ExternalFilters/{}", filter)) {
+        drop(store);
+        return respond_status(request, 403, "Filter not allowed");
+    }
+
+    let titles = match wiki.filter(filter) {
+        Ok(titles) => titles,
+        Err(e) => {
+            drop(store);
+            return respond_status(request, 400, &e);
+        }
+    };
+    let tiddlers: Vec<serde_json::Value> = titles.iter()
+        .filter_map(|title| wiki.get_tiddler(title).map(|t| (title, t)))
+        .map(|(title, tiddler)| {
+            let mut fields: serde_json::Map<String, serde_json::Value> = tiddler.iter()
+                .filter(|(name, _)| !exclude.contains(&name.as_str()))
+                .map(|(name, value)| (name.clone(), serde_json::Value::String(value.clone())))
+                .collect();
+            fields.insert("revision".to_string(), store.revision(title).into());
+            fields.entry("type").or_insert_with(|| "text/vnd.tiddlywiki".into());
+            serde_json::Value::Object(fields)
+        })
+        .collect();
+    drop(store);
+    respond_json(request, &serde_json::Value::Array(tiddlers))
+}
+
+fn serve_tiddler(request: Request, store: &Arc<Mutex<Store>>, title: &str) -> Result<(), String> {
+    let store = store.lock().unwrap();
+    let Some(tiddler) = store.get(title) else {
+        drop(store);
+        return respond_status(request, 404, "Not Found");
+    };
+
+    let mut result = serde_json::Map::new();
+    let mut extra = serde_json::Map::new();
+    for (name, value) in tiddler {
+        let value = serde_json::Value::String(value.clone());
+        if KNOWN_FIELDS.contains(&name.as_str()) {
+            result.insert(name.clone(), value);
+        } else {
+            extra.insert(name.clone(), value);
+        }
+    }
+    if !extra.is_empty() {
+        result.insert("fields".to_string(), serde_json::Value::Object(extra));
+    }
+    result.insert("revision".to_string(), store.revision(title).into());
+    result.insert("bag".to_string(), "default".into());
+    result.entry("type").or_insert_with(|| "text/vnd.tiddlywiki".into());
+    drop(store);
+    respond_json(request, &serde_json::Value::Object(result))
+}
+
+fn save_tiddler(mut request: Request, store: &Arc<Mutex<Store>>, title: &str) -> Result<(), String> {
+    let Some(body) = webdav::read_body(&mut request)? else {
+        return respond_status(request, 413, "Tiddler too large");
+    };
+    let value: serde_json::Value = match serde_json::from_slice(&body) {
+        Ok(value) => value,
+        Err(e) => return respond_status(request, 400, &format!("Invalid tiddler JSON: {}", e)),
+    };
+
+    // Flatten the TiddlyWeb format: extra fields live in a `fields` object
+    let mut object = value.as_object().cloned().unwrap_or_default();
+    if let Some(serde_json::Value::Object(extra)) = object.remove("fields") {
+        object.extend(extra);
+    }
+    for name in ["revision", "bag", "_is_skinny"] {
+        object.remove(name);
+    }
+    let Some(tiddler) = wiki_folder::tiddler_from_json(&serde_json::Value::Object(object)) else {
+        return respond_status(request, 400, "Invalid tiddler");
+    };
+
+    let saved = store.lock().unwrap().save(title, tiddler);
+    match saved {
+        Ok(revision) => {
+            let etag = format!("\"default/{}/{}:\"", urlencoding::encode(title), revision);
+            respond(request, Response::from_string("")
+                .with_status_code(StatusCode(204))
+                .with_header(header("Etag", &etag)))
+        }
+        Err(e) => {
+            eprintln!("[FolderServer] Failed to save {}: {}", title, e);
+            respond_status(request, 500, &e)
+        }
+    }
+}
+
+fn serve_file(request: Request, files_dir: &Path, relative: &str) -> Result<(), String> {
+    let path = files_dir.join(relative);
+    let contained = match (dunce::canonicalize(&path), dunce::canonicalize(files_dir)) {
+        (Ok(path), Ok(dir)) => path.starts_with(dir) && path.is_file(),
+        _ => false,
+    };
+    if !contained {
+        return respond_status(request, 404, "Not Found");
+    }
+    match std::fs::File::open(&path) {
+        Ok(file) => {
+            let mime = crate::utils::get_mime_type(&path);
+            respond(request, Response::from_file(file).with_header(header("Content-Type", mime)))
+        }
+        Err(_) => respond_status(request, 404, "Not Found"),
+    }
+}
@@ -0,0 +1,199 @@
+//! Scheduled single-file snapshots of folder wikis
+//!
+//! A folder wiki can be configured to render itself to a standalone HTML file
+//! (`--render $:/core/save/all`) in a chosen directory, on a schedule while the
+//! wiki is open and/or when its Node.js server shuts down. The snapshot is a
+//! portable copy that can be emailed or opened without TiddlyDesktop.
+//!
+//! The wiki folder process runs the schedule; the landing page edits the
+//! per-wiki configuration and can trigger a snapshot on demand.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::types::FolderSnapshotConfig;
+
+/// How often the wiki folder process checks whether a snapshot is due
+#[cfg(not(target_os = "android"))]
+const CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Whether a scheduled snapshot is due at `now` (Unix seconds)
+fn is_due(config: &FolderSnapshotConfig, now: u64) -> bool {
+    if !config.enabled || config.interval_hours == 0 || config.output_dir.is_empty() {
+        return false;
+    }
+    let interval = config.interval_hours as u64 * 60 * 60;
+    config.last_snapshot.is_none_or(|last| now.saturating_sub(last) >= interval)
+}
+
+/// Snapshot file for a folder wiki: `<output dir>/<folder name>.html`
+fn snapshot_file(folder: &Path, config: &FolderSnapshotConfig) -> PathBuf {
+    let name = folder
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "wiki".to_string());
+    Path::new(&config.output_dir).join(format!("{}.html", name))
+}
+
+fn load_config(app: &tauri::AppHandle, wiki_path: &str) -> Option<FolderSnapshotConfig> {
+    crate::wiki_storage::load_wiki_configs(app)
+        .ok()
+        .and_then(|c| c.folder_snapshots.get(wiki_path).cloned())
+}
+
+/// Render a snapshot of `folder` and record the time it was taken
+#[cfg(not(target_os = "android"))]
+fn take_snapshot(app: &tauri::AppHandle, node_path: &Path, tw_path: &Path, folder: &Path) -> Result<PathBuf, String> {
+    let wiki_path = folder.to_string_lossy().into_owned();
+    let config = load_config(app, &wiki_path)
+        .filter(|c| !c.output_dir.is_empty())
+        .ok_or_else(|| "No snapshot folder configured for this wiki".to_string())?;
+    let output_dir = PathBuf::from(&config.output_dir);
+    std::fs::create_dir_all(&output_dir)
+        .map_err(|e| format!("Failed to create snapshot folder: {}", e))?;
+
+    let temp = crate::scratch_dir::create_build_dir(app, "snapshot")?;
+    let result = crate::wiki_conversion::render_folder_with(node_path, tw_path, folder, &temp, "snapshot.html", true)
+        .and_then(|built| {
+            // Copy next to the target first so a half-written snapshot never replaces the previous one
+            let dest = snapshot_file(folder, &config);
+            let partial = dest.with_extension("html.partial");
+            std::fs::copy(&built, &partial)
+                .and_then(|_| std::fs::rename(&partial, &dest))
+                .map_err(|e| {
+                    let _ = std::fs::remove_file(&partial);
+                    format!("Failed to write snapshot: {}", e)
+                })?;
+            Ok(dest)
+        });
+    let _ = std::fs::remove_dir_all(&temp);
+    let dest = result?;
+
+    if let Ok(mut configs) = crate::wiki_storage::load_wiki_configs(app) {
+        if let Some(c) = configs.folder_snapshots.get_mut(&wiki_path) {
+            c.last_snapshot = Some(now_secs());
+            let _ = crate::wiki_storage::save_wiki_configs(app, &configs);
+        }
+    }
+    eprintln!("[TiddlyDesktop] Folder wiki snapshot written to {:?}", dest);
+    Ok(dest)
+}
+
+/// Start the snapshot schedule for a folder wiki (called by the wiki folder process).
+/// The configuration is re-read on every check, so changes made in the landing
+/// page apply without reopening the wiki.
+#[cfg(not(target_os = "android"))]
+pub fn start_schedule(app: &tauri::AppHandle, node_path: PathBuf, tw_path: PathBuf, folder: PathBuf) {
+    let app = app.clone();
+    std::thread::spawn(move || {
+        let wiki_path = folder.to_string_lossy().into_owned();
+        loop {
+            let due = load_config(&app, &wiki_path).is_some_and(|c| is_due(&c, now_secs()));
+            if due {
+                if let Err(e) = take_snapshot(&app, &node_path, &tw_path, &folder) {
+                    eprintln!("[TiddlyDesktop] Scheduled folder wiki snapshot failed: {}", e);
+                }
+            }
+            std::thread::sleep(CHECK_INTERVAL);
+        }
+    });
+}
+
+/// Take the shutdown snapshot if configured (called when the wiki's server stops)
+#[cfg(not(target_os = "android"))]
+pub fn snapshot_on_shutdown(app: &tauri::AppHandle, node_path: &Path, tw_path: &Path, folder: &Path) {
+    let wanted = load_config(app, &folder.to_string_lossy())
+        .is_some_and(|c| c.enabled && c.on_shutdown && !c.output_dir.is_empty());
+    if wanted {
+        if let Err(e) = take_snapshot(app, node_path, tw_path, folder) {
+            eprintln!("[TiddlyDesktop] Shutdown folder wiki snapshot failed: {}", e);
+        }
+    }
+}
+
+/// Get the snapshot configuration of every folder wiki that has one
+#[tauri::command]
+pub fn get_folder_snapshot_configs(app: tauri::AppHandle) -> Result<HashMap<String, FolderSnapshotConfig>, String> {
+    Ok(crate::wiki_storage::load_wiki_configs(&app)?.folder_snapshots)
+}
+
+/// Set the snapshot configuration of a folder wiki. An empty output directory
+/// disables snapshots.
+#[tauri::command]
+pub fn set_folder_snapshot_config(app: tauri::AppHandle, wiki_path: String, config: FolderSnapshotConfig) -> Result<FolderSnapshotConfig, String> {
+    let mut config = config;
+    if !config.output_dir.is_empty() {
+        let dir = crate::drag_drop::sanitize::validate_user_directory_path(&config.output_dir)?;
+        config.output_dir = dir.to_string_lossy().into_owned();
+    }
+    config.enabled = !config.output_dir.is_empty();
+
+    let mut configs = crate::wiki_storage::load_wiki_configs(&app)?;
+    // The schedule continues from the last snapshot, whatever the UI sent
+    config.last_snapshot = configs.folder_snapshots.get(&wiki_path).and_then(|c| c.last_snapshot);
+    configs.folder_snapshots.insert(wiki_path, config.clone());
+    crate::wiki_storage::save_wiki_configs(&app, &configs)?;
+    Ok(config)
+}
+
+/// Take a snapshot of a folder wiki now. Returns the snapshot file path.
+#[tauri::command]
+pub async fn snapshot_folder_wiki_now(app: tauri::AppHandle, wiki_path: String) -> Result<String, String> {
+    #[cfg(not(target_os = "android"))]
+    {
+        let folder = PathBuf::from(&wiki_path);
+        if !crate::utils::is_wiki_folder(&folder) {
+            return Err("Not a wiki folder".to_string());
+        }
+        let node_path = crate::get_node_path(&app)?;
+        let tw_path = crate::get_tiddlywiki_path(&app)?;
+        tokio::task::spawn_blocking(move || take_snapshot(&app, &node_path, &tw_path, &folder))
+            .await
+            .map_err(|e| format!("Failed to spawn blocking task: {}", e))?
+            .map(|p| p.to_string_lossy().into_owned())
+    }
+    #[cfg(target_os = "android")]
+    {
+        let _ = (app, wiki_path);
+        Err("Folder wiki snapshots are not supported on Android".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(interval_hours: u32, last_snapshot: Option<u64>) -> FolderSnapshotConfig {
+        FolderSnapshotConfig {
+            enabled: true,
+            output_dir: "/tmp/snapshots".to_string(),
+            interval_hours,
+            on_shutdown: false,
+            last_snapshot,
+        }
+    }
+
+    #[test]
+    fn test_schedule_is_due_after_interval() {
+        let hour = 60 * 60;
+        assert!(is_due(&config(1, None), 10 * hour));
+        assert!(!is_due(&config(1, Some(10 * hour)), 10 * hour + 59 * 60));
+        assert!(is_due(&config(1, Some(10 * hour)), 11 * hour));
+        // Shutdown-only and disabled configs never run on a schedule
+        assert!(!is_due(&config(0, None), 10 * hour));
+        assert!(!is_due(&FolderSnapshotConfig { enabled: false, ..config(1, None) }, 10 * hour));
+    }
+
+    #[test]
+    fn test_snapshot_is_named_after_the_folder() {
+        let file = snapshot_file(Path::new("/home/user/Notes"), &config(1, None));
+        assert_eq!(file, Path::new("/tmp/snapshots").join("Notes.html"));
+    }
+}
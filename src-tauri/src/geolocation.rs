@@ -0,0 +1,485 @@
+//! Current position from the OS location services
+//!
+//! Webview geolocation is unreliable (WebKitGTK only has it when GeoClue is
+//! wired up, WebView2 asks on every start), so wikis get their position from
+//! `get_current_position` instead:
+//! - Linux: GeoClue2 over D-Bus
+//! - Windows: Windows.Devices.Geolocation
+//! - macOS: CoreLocation
+//! - Android: last known location of the fused, GPS and network providers,
+//!   for wikis in a wiki activity through JNI (`android_position`)
+//!
+//! The first request of a wiki asks the user (on Android the wiki activity
+//! does, and records the answer with `record_android_permission`). The answer
+//! is recorded per wiki (`geolocation` in the wiki configs) and can be reset
+//! on the landing page.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::Serialize;
+use tauri::AppHandle;
+
+use crate::wiki_storage::{load_wiki_configs, save_wiki_configs};
+
+/// How long to wait for a fix
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Only one permission dialog at a time
+static ASKING: Mutex<()> = Mutex::new(());
+
+/// A position fix
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Position {
+    pub latitude: f64,
+    pub longitude: f64,
+    /// Horizontal accuracy in metres
+    pub accuracy: Option<f64>,
+    /// Metres above sea level
+    pub altitude: Option<f64>,
+    /// Milliseconds since the Unix epoch
+    pub timestamp: u64,
+}
+
+/// A reading the provider marks as unknown (NaN, negative accuracy or
+/// GeoClue's -DBL_MAX altitude) as None
+fn known(value: f64, min: f64) -> Option<f64> {
+    (value.is_finite() && value >= min).then_some(value)
+}
+
+fn now_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Name of a wiki for the permission dialog
+pub(crate) fn wiki_name(wiki_path: &str) -> String {
+    let trimmed = wiki_path.trim_end_matches(['/', '\\']);
+    Path::new(trimmed)
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| wiki_path.to_string())
+}
+
+/// Run a blocking provider call, giving up after `TIMEOUT`
+#[cfg(any(target_os = "linux", target_os = "windows"))]
+fn with_timeout(f: impl FnOnce() -> Result<Position, String> + Send + 'static) -> Result<Position, String> {
+    let (tx, rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let _ = tx.send(f());
+    });
+    rx.recv_timeout(TIMEOUT)
+        .map_err(|_| "Timed out waiting for a location".to_string())?
+}
+
+#[cfg(target_os = "linux")]
+fn os_position(_app: &AppHandle) -> Result<Position, String> {
+    use zbus::blocking::{Connection, Proxy};
+    use zbus::zvariant::OwnedObjectPath;
+
+    /// GCLUE_ACCURACY_LEVEL_EXACT
+    const ACCURACY_EXACT: u32 = 8;
+
+    fn geoclue_err(e: impl std::fmt::Display) -> String {
+        format!("GeoClue: {}", e)
+    }
+
+    with_timeout(|| {
+        let conn = Connection::system().map_err(geoclue_err)?;
+        let manager = Proxy::new(
+            &conn,
+            "org.freedesktop.GeoClue2",
+            "/org/freedesktop/GeoClue2/Manager",
+            "org.freedesktop.GeoClue2.Manager",
+        )
+        .map_err(geoclue_err)?;
+        let client_path: OwnedObjectPath = manager.call("GetClient", &()).map_err(geoclue_err)?;
+        let client = Proxy::new(
+            &conn,
+            "org.freedesktop.GeoClue2",
+            client_path.as_str(),
+            "org.freedesktop.GeoClue2.Client",
+        )
+        .map_err(geoclue_err)?;
+        // GeoClue's agent asks the user about apps by their desktop file
+        client.set_property("DesktopId", "tiddlydesktop-rs").map_err(geoclue_err)?;
+        client
+            .set_property("RequestedAccuracyLevel", ACCURACY_EXACT)
+            .map_err(geoclue_err)?;
+
+        let mut updates = client.receive_signal("LocationUpdated").map_err(geoclue_err)?;
+        client.call::<_, _, ()>("Start", &()).map_err(geoclue_err)?;
+        let result = updates
+            .next()
+            .ok_or_else(|| "GeoClue stopped without a location".to_string())
+            .and_then(|signal| {
+                let (_old, new): (OwnedObjectPath, OwnedObjectPath) =
+                    signal.body().deserialize().map_err(geoclue_err)?;
+                let location = Proxy::new(
+                    &conn,
+                    "org.freedesktop.GeoClue2",
+                    new.as_str(),
+                    "org.freedesktop.GeoClue2.Location",
+                )
+                .map_err(geoclue_err)?;
+                let read = |name: &str| location.get_property::<f64>(name).map_err(geoclue_err);
+                Ok(Position {
+                    latitude: read("Latitude")?,
+                    longitude: read("Longitude")?,
+                    accuracy: read("Accuracy").ok().and_then(|a| known(a, 0.0)),
+                    altitude: read("Altitude").ok().and_then(|a| known(a, -1.0e9)),
+                    timestamp: now_ms(),
+                })
+            });
+        let _ = client.call::<_, _, ()>("Stop", &());
+        result
+    })
+}
+
+#[cfg(target_os = "windows")]
+fn os_position(_app: &AppHandle) -> Result<Position, String> {
+    use windows::Devices::Geolocation::{Geolocator, PositionAccuracy};
+
+    fn location_err(e: windows::core::Error) -> String {
+        format!("Location services: {}", e)
+    }
+
+    with_timeout(|| {
+        let locator = Geolocator::new().map_err(location_err)?;
+        let _ = locator.SetDesiredAccuracy(PositionAccuracy::High);
+        let position = locator
+            .GetGeopositionAsync()
+            .and_then(|operation| operation.get())
+            .map_err(location_err)?;
+        let coordinate = position.Coordinate().map_err(location_err)?;
+        let point = coordinate
+            .Point()
+            .and_then(|p| p.Position())
+            .map_err(location_err)?;
+        Ok(Position {
+            latitude: point.Latitude,
+            longitude: point.Longitude,
+            accuracy: coordinate.Accuracy().ok().and_then(|a| known(a, 0.0)),
+            altitude: known(point.Altitude, -1.0e9),
+            timestamp: now_ms(),
+        })
+    })
+}
+
+#[cfg(target_os = "macos")]
+mod core_location {
+    //! CLLocationManager, driven from the main thread (its run loop delivers
+    //! the updates)
+
+    use std::cell::RefCell;
+
+    use objc2::encode::{Encode, Encoding};
+    use objc2::rc::Retained;
+    use objc2::runtime::AnyObject;
+    use objc2::{class, msg_send};
+
+    use super::{known, Position};
+
+    #[link(name = "CoreLocation", kind = "framework")]
+    extern "C" {}
+
+    /// `kCLAuthorizationStatusRestricted` and `kCLAuthorizationStatusDenied`
+    const STATUS_RESTRICTED: i32 = 1;
+    const STATUS_DENIED: i32 = 2;
+
+    #[repr(C)]
+    struct Coordinate {
+        latitude: f64,
+        longitude: f64,
+    }
+
+    unsafe impl Encode for Coordinate {
+        const ENCODING: Encoding = Encoding::Struct("CLLocationCoordinate2D", &[f64::ENCODING, f64::ENCODING]);
+    }
+
+    thread_local! {
+        static MANAGER: RefCell<Option<Retained<AnyObject>>> = const { RefCell::new(None) };
+    }
+
+    /// Start location updates
+    pub fn start() {
+        let manager: Retained<AnyObject> = unsafe { msg_send![class!(CLLocationManager), new] };
+        unsafe {
+            let _: () = msg_send![&*manager, requestWhenInUseAuthorization];
+            let _: () = msg_send![&*manager, startUpdatingLocation];
+        }
+        MANAGER.with(|m| *m.borrow_mut() = Some(manager));
+    }
+
+    /// The location once there is one
+    pub fn current() -> Result<Option<Position>, String> {
+        let status: i32 = unsafe { msg_send![class!(CLLocationManager), authorizationStatus] };
+        if status == STATUS_RESTRICTED || status == STATUS_DENIED {
+            return Err("Location access for TiddlyDesktop is turned off in System Settings".to_string());
+        }
+        MANAGER.with(|m| {
+            let manager = m.borrow();
+            let Some(manager) = manager.as_ref() else {
+                return Ok(None);
+            };
+            unsafe {
+                let location: *mut AnyObject = msg_send![&**manager, location];
+                let Some(location) = location.as_ref() else {
+                    return Ok(None);
+                };
+                // A negative accuracy marks an invalid location
+                let accuracy: f64 = msg_send![location, horizontalAccuracy];
+                if accuracy < 0.0 {
+                    return Ok(None);
+                }
+                let coordinate: Coordinate = msg_send![location, coordinate];
+                let altitude: f64 = msg_send![location, altitude];
+                let vertical_accuracy: f64 = msg_send![location, verticalAccuracy];
+                let date: *mut AnyObject = msg_send![location, timestamp];
+                let seconds: f64 = match date.as_ref() {
+                    Some(date) => msg_send![date, timeIntervalSince1970],
+                    None => 0.0,
+                };
+                Ok(Some(Position {
+                    latitude: coordinate.latitude,
+                    longitude: coordinate.longitude,
+                    accuracy: Some(accuracy),
+                    altitude: known(vertical_accuracy, 0.0).map(|_| altitude),
+                    timestamp: (seconds * 1000.0) as u64,
+                }))
+            }
+        })
+    }
+
+    /// Stop location updates
+    pub fn stop() {
+        if let Some(manager) = MANAGER.with(|m| m.borrow_mut().take()) {
+            unsafe {
+                let _: () = msg_send![&*manager, stopUpdatingLocation];
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn on_main_thread<T: Send + 'static>(app: &AppHandle, f: fn() -> T) -> Result<T, String> {
+    let (tx, rx) = std::sync::mpsc::channel();
+    app.run_on_main_thread(move || {
+        let _ = tx.send(f());
+    })
+    .map_err(|e| format!("Location services: {}", e))?;
+    rx.recv().map_err(|_| "Location services: main thread is gone".to_string())
+}
+
+#[cfg(target_os = "macos")]
+fn os_position(app: &AppHandle) -> Result<Position, String> {
+    const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+    on_main_thread(app, core_location::start)?;
+    let deadline = std::time::Instant::now() + TIMEOUT;
+    let result = loop {
+        match on_main_thread(app, core_location::current) {
+            Ok(Ok(Some(position))) => break Ok(position),
+            Ok(Ok(None)) => {}
+            Ok(Err(e)) | Err(e) => break Err(e),
+        }
+        if std::time::Instant::now() >= deadline {
+            break Err("Timed out waiting for a location".to_string());
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    };
+    let _ = on_main_thread(app, core_location::stop);
+    result
+}
+
+#[cfg(target_os = "android")]
+fn os_position() -> Result<Position, String> {
+    use jni::objects::{JObject, JValue};
+    use jni::JNIEnv;
+
+    /// Providers in order of preference ("fused" needs Android 12)
+    const PROVIDERS: &[&str] = &["fused", "gps", "network"];
+
+    fn jni_err(e: jni::errors::Error) -> String {
+        format!("Location services: {}", e)
+    }
+
+    fn read_location(env: &mut JNIEnv, location: &JObject) -> Result<Position, String> {
+        let mut double = |name: &str| env.call_method(location, name, "()D", &[]).and_then(|v| v.d()).map_err(jni_err);
+        let latitude = double("getLatitude")?;
+        let longitude = double("getLongitude")?;
+        let altitude = double("getAltitude")?;
+        let has = |env: &mut JNIEnv, name: &str| env.call_method(location, name, "()Z", &[]).and_then(|v| v.z()).unwrap_or(false);
+        let accuracy = if has(env, "hasAccuracy") {
+            env.call_method(location, "getAccuracy", "()F", &[])
+                .and_then(|v| v.f())
+                .ok()
+                .map(f64::from)
+        } else {
+            None
+        };
+        let has_altitude = has(env, "hasAltitude");
+        let time = env.call_method(location, "getTime", "()J", &[]).and_then(|v| v.j()).map_err(jni_err)?;
+        Ok(Position {
+            latitude,
+            longitude,
+            accuracy,
+            altitude: has_altitude.then_some(altitude),
+            timestamp: time.max(0) as u64,
+        })
+    }
+
+    let vm = crate::android::wiki_activity::get_java_vm()?;
+    let mut env = vm.attach_current_thread().map_err(jni_err)?;
+    let activity_thread = env.find_class("android/app/ActivityThread").map_err(jni_err)?;
+    let context = env
+        .call_static_method(&activity_thread, "currentApplication", "()Landroid/app/Application;", &[])
+        .and_then(|v| v.l())
+        .map_err(jni_err)?;
+    let service = env.new_string("location").map_err(jni_err)?;
+    let manager = env
+        .call_method(&context, "getSystemService", "(Ljava/lang/String;)Ljava/lang/Object;", &[JValue::Object(&service)])
+        .and_then(|v| v.l())
+        .map_err(jni_err)?;
+
+    let mut best: Option<Position> = None;
+    for provider in PROVIDERS {
+        let name = env.new_string(provider).map_err(jni_err)?;
+        let location = env
+            .call_method(
+                &manager,
+                "getLastKnownLocation",
+                "(Ljava/lang/String;)Landroid/location/Location;",
+                &[JValue::Object(&name)],
+            )
+            .and_then(|v| v.l());
+        // Unknown providers and a missing permission throw
+        let location = match location {
+            Ok(location) if !location.is_null() => location,
+            _ => {
+                let _ = env.exception_clear();
+                continue;
+            }
+        };
+        let position = read_location(&mut env, &location)?;
+        if !matches!(&best, Some(b) if b.timestamp >= position.timestamp) {
+            best = Some(position);
+        }
+    }
+    best.ok_or_else(|| {
+        "No location is known yet. Allow location access for TiddlyDesktop and turn on location services.".to_string()
+    })
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows", target_os = "macos", target_os = "android")))]
+fn os_position(_app: &AppHandle) -> Result<Position, String> {
+    Err("Location services are not supported on this platform".to_string())
+}
+
+/// Whether a wiki may use the location, asking the user the first time
+fn permission(app: &AppHandle, wiki_path: &str) -> Result<bool, String> {
+    use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+
+    let _asking = ASKING.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(allowed) = load_wiki_configs(app)?.geolocation.get(wiki_path) {
+        return Ok(*allowed);
+    }
+    let allowed = app
+        .dialog()
+        .message(format!(
+            "\"{}\" wants to use your location. Allow it now and in the future?",
+            wiki_name(wiki_path)
+        ))
+        .kind(MessageDialogKind::Info)
+        .title("Location")
+        .buttons(MessageDialogButtons::OkCancelCustom("Allow".to_string(), "Deny".to_string()))
+        .blocking_show();
+    let mut configs = load_wiki_configs(app)?;
+    configs.geolocation.insert(wiki_path.to_string(), allowed);
+    save_wiki_configs(app, &configs)?;
+    Ok(allowed)
+}
+
+/// Current position from the OS location services, if the wiki of this
+/// process may use it
+#[cfg(not(target_os = "android"))]
+#[tauri::command]
+pub async fn get_current_position(app: AppHandle, state: tauri::State<'_, crate::WikiModeState>) -> Result<Position, String> {
+    let wiki_path = state.wiki_path.to_string_lossy().into_owned();
+    tokio::task::spawn_blocking(move || {
+        if !permission(&app, &wiki_path)? {
+            return Err("Location access was denied for this wiki".to_string());
+        }
+        os_position(&app)
+    })
+    .await
+    .map_err(|e| format!("Location request failed: {}", e))?
+}
+
+/// Current position for the wiki at `wiki_path`, opened in a wiki activity
+/// (Android, which has no app handle there: `data_dir` holds the wiki
+/// configs). None when the user hasn't been asked yet.
+#[cfg(target_os = "android")]
+pub fn android_position(data_dir: &Path, wiki_path: &str) -> Result<Option<Position>, String> {
+    let configs = tiddlydesktop_core::storage::DataStore::new(data_dir).load_wiki_configs()?;
+    match configs.geolocation.get(wiki_path) {
+        None => Ok(None),
+        Some(false) => Err("Location access was denied for this wiki".to_string()),
+        Some(true) => os_position().map(Some),
+    }
+}
+
+/// Record the answer to a wiki activity's permission dialog (Android)
+#[cfg(target_os = "android")]
+pub fn record_android_permission(data_dir: &Path, wiki_path: &str, allowed: bool) -> Result<(), String> {
+    let store = tiddlydesktop_core::storage::DataStore::new(data_dir);
+    let mut configs = store.load_wiki_configs()?;
+    configs.geolocation.insert(wiki_path.to_string(), allowed);
+    store.save_wiki_configs(&configs)
+}
+
+/// Recorded location access of all wikis, keyed by wiki path
+#[tauri::command]
+pub fn get_geolocation_permissions(app: AppHandle) -> Result<HashMap<String, bool>, String> {
+    Ok(load_wiki_configs(&app)?.geolocation)
+}
+
+/// Allow or deny a wiki's location access (None = ask again)
+#[tauri::command]
+pub fn set_geolocation_permission(app: AppHandle, wiki_path: String, allowed: Option<bool>) -> Result<(), String> {
+    let mut configs = load_wiki_configs(&app)?;
+    match allowed {
+        Some(allowed) => {
+            configs.geolocation.insert(wiki_path, allowed);
+        }
+        None => {
+            configs.geolocation.remove(&wiki_path);
+        }
+    }
+    save_wiki_configs(&app, &configs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known() {
+        assert_eq!(known(12.5, 0.0), Some(12.5));
+        assert_eq!(known(-1.0, 0.0), None);
+        assert_eq!(known(f64::NAN, 0.0), None);
+        assert_eq!(known(-f64::MAX, -1.0e9), None);
+        assert_eq!(known(-30.0, -1.0e9), Some(-30.0));
+    }
+
+    #[test]
+    fn test_wiki_name() {
+        assert_eq!(wiki_name("/home/me/travel.html"), "travel.html");
+        assert_eq!(wiki_name("/home/me/journal/"), "journal");
+    }
+}
@@ -0,0 +1,340 @@
+//! Batch operations from the command line, without any windows (desktop)
+//!
+//! `--headless <command> ...` runs one command with the same Node.js and
+//! TiddlyWiki the app uses, prints the result and exits, so wiki maintenance
+//! can be scripted (cron, CI):
+//! - `render <wiki> --filter <filter> [--template <title>] [--type <mime>]
+//!   [--output <dir>]`: render tiddlers to files, like TiddlyWiki's `--render`
+//! - `backup <wiki> [--output <dir>]`: copy a single-file wiki (or render a
+//!   folder wiki) into its backup folder, named like the save-time backups
+//! - `convert <source> <dest> [--dry-run]`: convert between single-file and
+//!   folder wikis, with the pre-flight checks and verification of `convert_wiki`;
+//!   the report is printed as JSON
+//!
+//! Exit status: 0 on success, 1 when the command failed, 2 for usage errors.
+
+use std::path::{Path, PathBuf};
+use std::process::Command as ProcessCommand;
+
+#[cfg(target_os = "windows")]
+use std::os::windows::process::CommandExt;
+
+use chrono::Local;
+use tiddlydesktop_core::backup::{backup_dir_for_wiki, BACKUP_TIMESTAMP_FORMAT};
+
+use crate::utils;
+use crate::wiki_storage::load_recent_files_from_disk;
+
+pub const USAGE: &str = "Usage:
+  tiddlydesktop-rs --headless render <wiki> --filter <filter> [--template <title>] [--type <mime>] [--output <dir>]
+  tiddlydesktop-rs --headless backup <wiki> [--output <dir>]
+  tiddlydesktop-rs --headless convert <source> <dest> [--dry-run]";
+
+/// Template `--render` uses unless given
+const DEFAULT_TEMPLATE: &str = "$:/core/templates/static.tiddler.html";
+
+/// Output type `--render` uses unless given
+const DEFAULT_TYPE: &str = "text/plain";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Render {
+        wiki: PathBuf,
+        filter: String,
+        template: String,
+        content_type: String,
+        output: PathBuf,
+    },
+    Backup {
+        wiki: PathBuf,
+        /// Instead of the wiki's backup folder
+        output: Option<PathBuf>,
+    },
+    Convert {
+        source: PathBuf,
+        dest: PathBuf,
+        dry_run: bool,
+    },
+    /// The wiki list as JSON (`--list-wikis` without a running instance, see `cli`)
+    ListWikis,
+    /// Back up every wiki of the list (`--backup all`, see `cli`)
+    BackupAll,
+}
+
+/// The command after `--headless`; None when it isn't given
+pub fn parse_args(args: &[String]) -> Option<Result<Command, String>> {
+    let start = args.iter().position(|arg| arg == "--headless")?;
+    Some(parse_command(&args[start + 1..]))
+}
+
+fn parse_command(args: &[String]) -> Result<Command, String> {
+    let (name, rest) = args.split_first().ok_or("No command given")?;
+    let mut positional: Vec<&String> = Vec::new();
+    let mut filter = None;
+    let mut template = None;
+    let mut content_type = None;
+    let mut output = None;
+    let mut dry_run = false;
+
+    let mut i = 0;
+    while i < rest.len() {
+        let arg = rest[i].as_str();
+        let value = || rest.get(i + 1).cloned().ok_or_else(|| format!("{} needs a value", arg));
+        match arg {
+            "--filter" => filter = Some(value()?),
+            "--template" => template = Some(value()?),
+            "--type" => content_type = Some(value()?),
+            "--output" => output = Some(PathBuf::from(value()?)),
+            "--dry-run" => {
+                dry_run = true;
+                i += 1;
+                continue;
+            }
+            _ if arg.starts_with("--") => return Err(format!("Unknown option: {}", arg)),
+            _ => {
+                positional.push(&rest[i]);
+                i += 1;
+                continue;
+            }
+        }
+        i += 2;
+    }
+
+    let expect_paths = |count: usize| -> Result<Vec<PathBuf>, String> {
+        if positional.len() != count {
+            return Err(format!("{} takes {} path(s), got {}", name, count, positional.len()));
+        }
+        Ok(positional.iter().map(PathBuf::from).collect())
+    };
+    match name.as_str() {
+        "render" => {
+            let wiki = expect_paths(1)?.remove(0);
+            Ok(Command::Render {
+                wiki,
+                filter: filter.ok_or("render needs --filter")?,
+                template: template.unwrap_or_else(|| DEFAULT_TEMPLATE.to_string()),
+                content_type: content_type.unwrap_or_else(|| DEFAULT_TYPE.to_string()),
+                output: output.unwrap_or_else(|| PathBuf::from(".")),
+            })
+        }
+        "backup" => Ok(Command::Backup { wiki: expect_paths(1)?.remove(0), output }),
+        "convert" => {
+            let mut paths = expect_paths(2)?;
+            let dest = paths.pop().unwrap_or_default();
+            Ok(Command::Convert { source: paths.remove(0), dest, dry_run })
+        }
+        _ => Err(format!("Unknown command: {}", name)),
+    }
+}
+
+/// Absolute form of a path given on the command line (it may not exist yet)
+pub(crate) fn absolute(path: &Path) -> PathBuf {
+    std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Run TiddlyWiki's `--render` on a wiki
+fn render(
+    app: &tauri::AppHandle,
+    wiki: &Path,
+    filter: &str,
+    template: &str,
+    content_type: &str,
+    output: &Path,
+) -> Result<String, String> {
+    let node_path = crate::get_node_path(app)?;
+    let tw_path = crate::get_tiddlywiki_path(app)?;
+    std::fs::create_dir_all(output).map_err(|e| format!("Failed to create {}: {}", output.display(), e))?;
+
+    let mut cmd = ProcessCommand::new(&node_path);
+    cmd.arg(&tw_path);
+    // A single-file wiki is loaded into an empty wiki folder
+    let build_dir = if wiki.is_dir() {
+        cmd.arg(wiki);
+        None
+    } else {
+        let dir = crate::scratch_dir::create_build_dir(app, "render")?;
+        cmd.arg(&dir).arg("--load").arg(wiki);
+        Some(dir)
+    };
+    cmd.arg("--output")
+        .arg(output)
+        .arg("--render")
+        .arg(filter)
+        .arg("[encodeuricomponent[]addsuffix[.html]]")
+        .arg(content_type)
+        .arg(template);
+    #[cfg(target_os = "windows")]
+    cmd.creation_flags(crate::CREATE_NO_WINDOW);
+
+    let result = cmd.output().map_err(|e| format!("Failed to run TiddlyWiki: {}", e));
+    if let Some(dir) = build_dir {
+        let _ = std::fs::remove_dir_all(dir);
+    }
+    let output_status = result?;
+    if !output_status.status.success() {
+        return Err(format!(
+            "Rendering failed:\n{}\n{}",
+            String::from_utf8_lossy(&output_status.stdout),
+            String::from_utf8_lossy(&output_status.stderr)
+        ));
+    }
+    Ok(format!("Rendered {} into {}", filter, output.display()))
+}
+
+/// Back up a wiki into its backup folder (or `output`). A folder wiki is
+/// rendered to a single file.
+pub(crate) fn backup(app: &tauri::AppHandle, wiki: &Path, output: Option<&Path>) -> Result<String, String> {
+    if !wiki.exists() {
+        return Err(format!("Wiki not found: {}", wiki.display()));
+    }
+    let wiki_str = wiki.to_string_lossy();
+    let custom_backup_dir = load_recent_files_from_disk(app)
+        .into_iter()
+        .find(|entry| utils::paths_equal(&entry.path, &wiki_str))
+        .and_then(|entry| entry.backup_dir);
+    let dir = match output {
+        Some(dir) => dir.to_path_buf(),
+        None => backup_dir_for_wiki(wiki, custom_backup_dir.as_deref()).ok_or("No backup folder for this wiki")?,
+    };
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+
+    let stem = wiki.file_stem().and_then(|s| s.to_str()).unwrap_or("wiki");
+    let dest = dir.join(format!("{}.{}.html", stem, Local::now().format(BACKUP_TIMESTAMP_FORMAT)));
+    if wiki.is_dir() {
+        let filename = dest.file_name().and_then(|n| n.to_str()).unwrap_or("wiki.html").to_string();
+        let temp_output = crate::scratch_dir::create_build_dir(app, "backup")?;
+        let result = crate::wiki_conversion::render_folder(app, wiki, &temp_output, &filename, true)
+            .and_then(|built| {
+                std::fs::copy(&built, &dest).map_err(|e| format!("Failed to write {}: {}", dest.display(), e))
+            });
+        let _ = std::fs::remove_dir_all(&temp_output);
+        result?;
+    } else {
+        // Copy next to the target first so a half-written copy never looks like a backup
+        let partial = dest.with_extension("html.partial");
+        std::fs::copy(wiki, &partial).and_then(|_| std::fs::rename(&partial, &dest)).map_err(|e| {
+            let _ = std::fs::remove_file(&partial);
+            format!("Failed to back up {}: {}", wiki.display(), e)
+        })?;
+    }
+    Ok(dest.to_string_lossy().into_owned())
+}
+
+/// Back up each of `wikis` with `backup_one`, printing where each backup
+/// went. Fails when any of them failed.
+pub(crate) fn backup_each(wikis: &[String], mut backup_one: impl FnMut(&str) -> Result<String, String>) -> Result<String, String> {
+    let mut failed = 0;
+    for wiki in wikis {
+        match backup_one(wiki) {
+            Ok(dest) => println!("{}", dest),
+            Err(e) => {
+                eprintln!("{}: {}", wiki, e);
+                failed += 1;
+            }
+        }
+    }
+    if failed > 0 {
+        return Err(format!("{} of {} backups failed", failed, wikis.len()));
+    }
+    Ok(format!("{} wikis backed up", wikis.len()))
+}
+
+/// The wiki list, in the shape of the control API's `GET /wikis`
+fn list_wikis(app: &tauri::AppHandle) -> Result<String, String> {
+    let wikis: Vec<serde_json::Value> = load_recent_files_from_disk(app)
+        .into_iter()
+        .map(|entry| {
+            serde_json::json!({
+                "path": entry.path,
+                "name": entry.filename,
+                "isFolder": entry.is_folder,
+                "open": false,
+            })
+        })
+        .collect();
+    serde_json::to_string_pretty(&wikis).map_err(|e| e.to_string())
+}
+
+/// Run a command. Returns what to print, or the error.
+fn execute(app: &tauri::AppHandle, command: &Command) -> Result<String, String> {
+    match command {
+        Command::Render { wiki, filter, template, content_type, output } => {
+            render(app, &absolute(wiki), filter, template, content_type, &absolute(output))
+        }
+        Command::Backup { wiki, output } => backup(app, &absolute(wiki), output.as_deref().map(absolute).as_deref()),
+        Command::Convert { source, dest, dry_run } => {
+            let source = absolute(source);
+            if !source.exists() {
+                return Err(format!("Wiki not found: {}", source.display()));
+            }
+            let to_folder = !source.is_dir();
+            let report = crate::wiki_conversion::convert(app, &source, &absolute(dest), to_folder, *dry_run)?;
+            let json = serde_json::to_string_pretty(&report).map_err(|e| e.to_string())?;
+            if !*dry_run && !report.verification.as_ref().is_some_and(|v| v.passed) {
+                return Err(json);
+            }
+            Ok(json)
+        }
+        Command::ListWikis => list_wikis(app),
+        Command::BackupAll => {
+            let wikis: Vec<String> = load_recent_files_from_disk(app).into_iter().map(|entry| entry.path).collect();
+            backup_each(&wikis, |wiki| backup(app, Path::new(wiki), None))
+        }
+    }
+}
+
+/// Run a command and return the exit status
+pub fn run(app: &tauri::AppHandle, command: &Command) -> i32 {
+    match execute(app, command) {
+        Ok(message) => {
+            println!("{}", message);
+            0
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_render() {
+        assert_eq!(parse_args(&args(&["app", "--wiki", "a.html"])), None);
+        assert_eq!(
+            parse_args(&args(&["app", "--headless", "render", "w.html", "--filter", "[tag[Done]]", "--output", "out"])),
+            Some(Ok(Command::Render {
+                wiki: PathBuf::from("w.html"),
+                filter: "[tag[Done]]".to_string(),
+                template: DEFAULT_TEMPLATE.to_string(),
+                content_type: DEFAULT_TYPE.to_string(),
+                output: PathBuf::from("out"),
+            }))
+        );
+        assert!(parse_args(&args(&["app", "--headless", "render", "w.html"])).unwrap().is_err());
+        assert!(parse_args(&args(&["app", "--headless", "render", "w.html", "--filter"])).unwrap().is_err());
+    }
+
+    #[test]
+    fn test_parse_backup_and_convert() {
+        assert_eq!(
+            parse_args(&args(&["app", "--headless", "backup", "w.html"])),
+            Some(Ok(Command::Backup { wiki: PathBuf::from("w.html"), output: None }))
+        );
+        assert_eq!(
+            parse_args(&args(&["app", "--headless", "convert", "--dry-run", "w.html", "w"])),
+            Some(Ok(Command::Convert { source: PathBuf::from("w.html"), dest: PathBuf::from("w"), dry_run: true }))
+        );
+        assert!(parse_args(&args(&["app", "--headless", "convert", "w.html"])).unwrap().is_err());
+        assert!(parse_args(&args(&["app", "--headless", "backup", "w.html", "--force"])).unwrap().is_err());
+        assert!(parse_args(&args(&["app", "--headless", "upgrade", "w.html"])).unwrap().is_err());
+        assert!(parse_args(&args(&["app", "--headless"])).unwrap().is_err());
+    }
+}
@@ -0,0 +1,188 @@
+//! System idle time (time since the last keyboard or mouse input)
+//!
+//! - Linux: X11 screensaver extension; on Wayland, Mutter's IdleMonitor
+//!   (GNOME) or the freedesktop ScreenSaver interface (KDE and others)
+//! - Windows: GetLastInputInfo
+//! - macOS: CoreGraphics' combined session event source (the HID idle time)
+//! - Android: not available
+//!
+//! `get_idle_time` returns the idle seconds. Every process also watches the
+//! idle time and emits `system-idle` (`{ idle, idleSeconds }`) to its windows
+//! when the user has been away for `IDLE_AFTER` and again when they are back.
+//! Wiki windows use it to save on idle (`init_script/idle.js`).
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use tauri::{AppHandle, Emitter};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Idle time after which the user counts as away
+const IDLE_AFTER: Duration = Duration::from_secs(5 * 60);
+
+static WATCHER_STARTED: OnceLock<()> = OnceLock::new();
+static IDLE: AtomicBool = AtomicBool::new(false);
+
+#[cfg(target_os = "linux")]
+fn idle_ms() -> Option<u64> {
+    if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        wayland_idle_ms()
+    } else {
+        x11_idle_ms()
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn x11_idle_ms() -> Option<u64> {
+    use x11rb::connection::Connection;
+    use x11rb::protocol::screensaver::ConnectionExt;
+    use x11rb::rust_connection::RustConnection;
+
+    static CONNECTION: OnceLock<Option<(RustConnection, u32)>> = OnceLock::new();
+    let (conn, root) = CONNECTION
+        .get_or_init(|| {
+            let (conn, screen) = x11rb::connect(None).ok()?;
+            let root = conn.setup().roots.get(screen)?.root;
+            Some((conn, root))
+        })
+        .as_ref()?;
+    let info = conn.screensaver_query_info(*root).ok()?.reply().ok()?;
+    Some(u64::from(info.ms_since_user_input))
+}
+
+#[cfg(target_os = "linux")]
+fn wayland_idle_ms() -> Option<u64> {
+    use zbus::blocking::Connection;
+
+    static SESSION: OnceLock<Option<Connection>> = OnceLock::new();
+    let conn = SESSION.get_or_init(|| Connection::session().ok()).as_ref()?;
+    // GNOME (milliseconds)
+    let mutter = conn.call_method(
+        Some("org.gnome.Mutter.IdleMonitor"),
+        "/org/gnome/Mutter/IdleMonitor/Core",
+        Some("org.gnome.Mutter.IdleMonitor"),
+        "GetIdletime",
+        &(),
+    );
+    if let Some(ms) = mutter.ok().and_then(|reply| reply.body().deserialize::<u64>().ok()) {
+        return Some(ms);
+    }
+    // KDE and others (seconds)
+    let reply = conn
+        .call_method(
+            Some("org.freedesktop.ScreenSaver"),
+            "/org/freedesktop/ScreenSaver",
+            Some("org.freedesktop.ScreenSaver"),
+            "GetSessionIdleTime",
+            &(),
+        )
+        .ok()?;
+    let seconds: u32 = reply.body().deserialize().ok()?;
+    Some(u64::from(seconds) * 1000)
+}
+
+#[cfg(target_os = "windows")]
+fn idle_ms() -> Option<u64> {
+    use windows::Win32::System::SystemInformation::GetTickCount;
+    use windows::Win32::UI::Input::KeyboardAndMouse::{GetLastInputInfo, LASTINPUTINFO};
+
+    let mut info = LASTINPUTINFO {
+        cbSize: std::mem::size_of::<LASTINPUTINFO>() as u32,
+        dwTime: 0,
+    };
+    unsafe {
+        if !GetLastInputInfo(&mut info).as_bool() {
+            return None;
+        }
+        // Both tick counts wrap after 49.7 days
+        Some(u64::from(GetTickCount().wrapping_sub(info.dwTime)))
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn idle_ms() -> Option<u64> {
+    #[link(name = "CoreGraphics", kind = "framework")]
+    extern "C" {
+        fn CGEventSourceSecondsSinceLastEventType(state: i32, event_type: u32) -> f64;
+    }
+    /// kCGEventSourceStateCombinedSessionState
+    const COMBINED_SESSION_STATE: i32 = 0;
+    /// kCGAnyInputEventType
+    const ANY_INPUT_EVENT: u32 = !0;
+
+    let seconds = unsafe { CGEventSourceSecondsSinceLastEventType(COMBINED_SESSION_STATE, ANY_INPUT_EVENT) };
+    (seconds.is_finite() && seconds >= 0.0).then(|| (seconds * 1000.0) as u64)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows", target_os = "macos")))]
+fn idle_ms() -> Option<u64> {
+    None
+}
+
+/// Seconds since the last keyboard or mouse input (None if unknown)
+pub fn idle_seconds() -> Option<u64> {
+    idle_ms().map(|ms| ms / 1000)
+}
+
+/// Whether the user is away (updated by the watcher)
+#[allow(dead_code)]
+pub fn is_idle() -> bool {
+    IDLE.load(Ordering::Relaxed)
+}
+
+/// The new idle state if the idle time crosses `IDLE_AFTER`
+fn transition(was_idle: bool, idle_ms: u64) -> Option<bool> {
+    let idle = idle_ms >= IDLE_AFTER.as_millis() as u64;
+    (idle != was_idle).then_some(idle)
+}
+
+/// Watch the idle time and emit `system-idle` when the user goes away or
+/// comes back (once per process)
+pub fn start(app: &AppHandle) {
+    if WATCHER_STARTED.set(()).is_err() {
+        return;
+    }
+    let app = app.clone();
+    std::thread::spawn(move || {
+        if idle_ms().is_none() {
+            eprintln!("[TiddlyDesktop] System idle time is not available");
+            return;
+        }
+        loop {
+            std::thread::sleep(POLL_INTERVAL);
+            let Some(ms) = idle_ms() else {
+                continue;
+            };
+            if let Some(idle) = transition(IDLE.load(Ordering::Relaxed), ms) {
+                IDLE.store(idle, Ordering::Relaxed);
+                let _ = app.emit(
+                    "system-idle",
+                    serde_json::json!({ "idle": idle, "idleSeconds": ms / 1000 }),
+                );
+            }
+        }
+    });
+}
+
+/// Seconds since the last keyboard or mouse input (null if the platform
+/// doesn't tell)
+#[tauri::command]
+pub fn get_idle_time() -> Option<u64> {
+    idle_seconds()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transition() {
+        let away = IDLE_AFTER.as_millis() as u64;
+        assert_eq!(transition(false, 1000), None);
+        assert_eq!(transition(false, away), Some(true));
+        assert_eq!(transition(true, away + 10_000), None);
+        assert_eq!(transition(true, 500), Some(false));
+    }
+}
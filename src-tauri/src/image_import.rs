@@ -0,0 +1,474 @@
+//! Image processing on attachment import
+//!
+//! With the image options of a wiki's `ExternalAttachmentsConfig`, imported
+//! photos are:
+//! - converted: HEIC/HEIF to JPEG, WebP to JPEG (PNG if it has transparency)
+//! - downscaled to a maximum width and height
+//! - stripped of their GPS location
+//!
+//! Re-encoded images lose all their metadata. JPEGs that only need the
+//! location removed are rewritten without re-encoding, so they keep their
+//! quality. HEIC/HEIF and WebP files are left alone unless conversion is on,
+//! and HEIC decoding needs a build with the `heic` feature (libheif).
+
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+use base64::Engine;
+use image::codecs::jpeg::JpegEncoder;
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader};
+use serde::Serialize;
+
+use crate::types::ExternalAttachmentsConfig;
+
+const JPEG_QUALITY: u8 = 85;
+
+/// Image formats the pipeline reads
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Kind {
+    Heic,
+    Webp,
+    Jpeg,
+    Png,
+}
+
+impl Kind {
+    fn from_filename(filename: &str) -> Option<Self> {
+        let extension = Path::new(filename).extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "heic" | "heif" => Some(Self::Heic),
+            "webp" => Some(Self::Webp),
+            "jpg" | "jpeg" => Some(Self::Jpeg),
+            "png" => Some(Self::Png),
+            _ => None,
+        }
+    }
+
+    fn format(self) -> Option<ImageFormat> {
+        match self {
+            Self::Heic => None,
+            Self::Webp => Some(ImageFormat::WebP),
+            Self::Jpeg => Some(ImageFormat::Jpeg),
+            Self::Png => Some(ImageFormat::Png),
+        }
+    }
+}
+
+/// Image formats the pipeline writes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Output {
+    Jpeg,
+    Png,
+}
+
+impl Output {
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Jpeg => "jpg",
+            Self::Png => "png",
+        }
+    }
+
+    fn mime_type(self) -> &'static str {
+        match self {
+            Self::Jpeg => "image/jpeg",
+            Self::Png => "image/png",
+        }
+    }
+}
+
+/// The image options of `ExternalAttachmentsConfig`
+#[derive(Clone, Copy, Debug, Default)]
+struct Options {
+    convert: bool,
+    max_dimension: u32,
+    strip_location: bool,
+}
+
+impl Options {
+    fn from_config(config: &ExternalAttachmentsConfig) -> Self {
+        Self {
+            convert: config.convert_images,
+            max_dimension: config.max_image_dimension,
+            strip_location: config.strip_image_location,
+        }
+    }
+
+    fn is_active(&self) -> bool {
+        self.convert || self.max_dimension > 0 || self.strip_location
+    }
+}
+
+#[cfg(feature = "heic")]
+fn decode_heic(bytes: &[u8]) -> Result<DynamicImage, String> {
+    use libheif_rs::{ColorSpace, HeifContext, LibHeif, RgbChroma};
+
+    let lib_heif = LibHeif::new();
+    let context = HeifContext::read_from_bytes(bytes).map_err(|e| format!("Failed to read HEIC image: {}", e))?;
+    let handle = context.primary_image_handle().map_err(|e| format!("Failed to read HEIC image: {}", e))?;
+    // libheif applies the rotation and mirroring of the image
+    let image = lib_heif
+        .decode(&handle, ColorSpace::Rgb(RgbChroma::Rgb), None)
+        .map_err(|e| format!("Failed to decode HEIC image: {}", e))?;
+    let plane = image.planes().interleaved.ok_or("HEIC image has no RGB data")?;
+    let row_len = plane.width as usize * 3;
+    let mut rgb = Vec::with_capacity(row_len * plane.height as usize);
+    for row in plane.data.chunks(plane.stride).take(plane.height as usize) {
+        rgb.extend_from_slice(&row[..row_len]);
+    }
+    image::RgbImage::from_raw(plane.width, plane.height, rgb)
+        .map(DynamicImage::ImageRgb8)
+        .ok_or_else(|| "Failed to decode HEIC image".to_string())
+}
+
+#[cfg(not(feature = "heic"))]
+fn decode_heic(_bytes: &[u8]) -> Result<DynamicImage, String> {
+    Err("HEIC images can't be converted: this build has no libheif support".to_string())
+}
+
+/// Decode an image, upright (JPEGs are often rotated by their EXIF orientation)
+fn decode(bytes: &[u8], kind: Kind) -> Result<DynamicImage, String> {
+    let Some(format) = kind.format() else {
+        return decode_heic(bytes);
+    };
+    let mut decoder = ImageReader::with_format(Cursor::new(bytes), format)
+        .into_decoder()
+        .map_err(|e| format!("Failed to read image: {}", e))?;
+    let orientation = decoder.orientation().map_err(|e| format!("Failed to read image: {}", e))?;
+    let mut image = DynamicImage::from_decoder(decoder).map_err(|e| format!("Failed to decode image: {}", e))?;
+    image.apply_orientation(orientation);
+    Ok(image)
+}
+
+fn encode(image: &DynamicImage, output: Output) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    match output {
+        Output::Jpeg => image
+            .to_rgb8()
+            .write_with_encoder(JpegEncoder::new_with_quality(&mut bytes, JPEG_QUALITY)),
+        Output::Png => image.write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png),
+    }
+    .map_err(|e| format!("Failed to encode image: {}", e))?;
+    Ok(bytes)
+}
+
+/// Run the pipeline on an image. None if the image is fine as it is.
+fn process(bytes: &[u8], kind: Kind, options: Options) -> Result<Option<(Vec<u8>, Output)>, String> {
+    let convert = matches!(kind, Kind::Heic | Kind::Webp);
+    if convert && !options.convert {
+        return Ok(None);
+    }
+
+    let too_large = |width: u32, height: u32| options.max_dimension > 0 && width.max(height) > options.max_dimension;
+    let needs_resize = match kind.format() {
+        Some(format) if !convert => {
+            let (width, height) = ImageReader::with_format(Cursor::new(bytes), format)
+                .into_dimensions()
+                .map_err(|e| format!("Failed to read image: {}", e))?;
+            too_large(width, height)
+        }
+        _ => false,
+    };
+
+    if convert || needs_resize {
+        let mut image = decode(bytes, kind)?;
+        if too_large(image.width(), image.height()) {
+            image = image.resize(options.max_dimension, options.max_dimension, image::imageops::FilterType::Lanczos3);
+        }
+        let output = match kind {
+            Kind::Png => Output::Png,
+            Kind::Jpeg => Output::Jpeg,
+            _ if image.color().has_alpha() => Output::Png,
+            _ => Output::Jpeg,
+        };
+        return Ok(Some((encode(&image, output)?, output)));
+    }
+
+    if kind == Kind::Jpeg && options.strip_location {
+        return Ok(strip_jpeg_location(bytes).map(|stripped| (stripped, Output::Jpeg)));
+    }
+    Ok(None)
+}
+
+/// Big- or little-endian reads and writes in a TIFF structure (EXIF data)
+struct Tiff<'a> {
+    data: &'a mut [u8],
+    big_endian: bool,
+}
+
+impl Tiff<'_> {
+    fn u16_at(&self, pos: usize) -> Option<u16> {
+        let b: [u8; 2] = self.data.get(pos..pos + 2)?.try_into().ok()?;
+        Some(if self.big_endian { u16::from_be_bytes(b) } else { u16::from_le_bytes(b) })
+    }
+
+    fn u32_at(&self, pos: usize) -> Option<u32> {
+        let b: [u8; 4] = self.data.get(pos..pos + 4)?.try_into().ok()?;
+        Some(if self.big_endian { u32::from_be_bytes(b) } else { u32::from_le_bytes(b) })
+    }
+
+    fn zero(&mut self, pos: usize, len: usize) {
+        if let Some(range) = self.data.get_mut(pos..pos.saturating_add(len)) {
+            range.fill(0);
+        }
+    }
+}
+
+/// Size of one value of a TIFF field type (0 for unknown types)
+fn tiff_type_size(field_type: u16) -> usize {
+    match field_type {
+        1 | 2 | 6 | 7 => 1,
+        3 | 8 => 2,
+        4 | 9 | 11 => 4,
+        5 | 10 | 12 => 8,
+        _ => 0,
+    }
+}
+
+const GPS_INFO_TAG: u16 = 0x8825;
+
+/// Empty the GPS directory of EXIF data (a TIFF structure), values included.
+/// Returns whether there was one.
+fn strip_exif_gps(data: &mut [u8]) -> bool {
+    let big_endian = match data.get(0..2) {
+        Some(b"MM") => true,
+        Some(b"II") => false,
+        _ => return false,
+    };
+    let mut tiff = Tiff { data, big_endian };
+    let Some(ifd0) = tiff.u32_at(4).map(|o| o as usize) else {
+        return false;
+    };
+    let Some(count) = tiff.u16_at(ifd0) else {
+        return false;
+    };
+    let gps_ifd = (0..count as usize)
+        .map(|i| ifd0 + 2 + i * 12)
+        .find(|&entry| tiff.u16_at(entry) == Some(GPS_INFO_TAG))
+        .and_then(|entry| tiff.u32_at(entry + 8))
+        .map(|o| o as usize);
+    let Some(gps_ifd) = gps_ifd else {
+        return false;
+    };
+    let Some(gps_count) = tiff.u16_at(gps_ifd) else {
+        return false;
+    };
+    for i in 0..gps_count as usize {
+        let entry = gps_ifd + 2 + i * 12;
+        let (Some(field_type), Some(values)) = (tiff.u16_at(entry + 2), tiff.u32_at(entry + 4)) else {
+            break;
+        };
+        // Values that don't fit in the entry are stored elsewhere
+        let size = tiff_type_size(field_type).saturating_mul(values as usize);
+        if size > 4 {
+            if let Some(offset) = tiff.u32_at(entry + 8) {
+                tiff.zero(offset as usize, size);
+            }
+        }
+        tiff.zero(entry, 12);
+    }
+    // An empty directory: zero entries
+    tiff.zero(gps_ifd, 2);
+    true
+}
+
+const EXIF_HEADER: &[u8] = b"Exif\0\0";
+const XMP_HEADER: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+
+/// Copy of a JPEG without GPS location: the GPS directory of the EXIF data is
+/// emptied and XMP metadata with GPS fields is dropped. None if there was no
+/// location (or the file isn't a JPEG we can read).
+fn strip_jpeg_location(jpeg: &[u8]) -> Option<Vec<u8>> {
+    if !jpeg.starts_with(&[0xFF, 0xD8]) {
+        return None;
+    }
+    let mut out = jpeg[..2].to_vec();
+    let mut pos = 2;
+    let mut changed = false;
+    while pos + 4 <= jpeg.len() {
+        if jpeg[pos] != 0xFF {
+            return None;
+        }
+        let marker = jpeg[pos + 1];
+        // Image data follows the start of scan: copy the rest as it is
+        if marker == 0xDA || marker == 0xD9 {
+            break;
+        }
+        let len = u16::from_be_bytes([jpeg[pos + 2], jpeg[pos + 3]]) as usize;
+        let end = pos + 2 + len;
+        if len < 2 || end > jpeg.len() {
+            return None;
+        }
+        let segment = &jpeg[pos..end];
+        let payload = &segment[4..];
+        if marker == 0xE1 && payload.starts_with(EXIF_HEADER) {
+            let mut segment = segment.to_vec();
+            changed |= strip_exif_gps(&mut segment[4 + EXIF_HEADER.len()..]);
+            out.extend_from_slice(&segment);
+        } else if marker == 0xE1
+            && payload.starts_with(XMP_HEADER)
+            && payload.windows(7).any(|w| w == b"exif:GP")
+        {
+            changed = true;
+        } else {
+            out.extend_from_slice(segment);
+        }
+        pos = end;
+    }
+    out.extend_from_slice(&jpeg[pos..]);
+    changed.then_some(out)
+}
+
+/// `name.ext` with the extension of the output format
+fn output_filename(filename: &str, output: Output) -> String {
+    let stem = Path::new(filename)
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| "image".to_string());
+    format!("{}.{}", stem, output.extension())
+}
+
+/// Directory external attachments of a wiki are saved to (like pasted files)
+pub fn attachments_dir(wiki_path: &str) -> PathBuf {
+    let wiki_path = Path::new(wiki_path);
+    let wiki_dir = if wiki_path.is_dir() {
+        wiki_path
+    } else {
+        wiki_path.parent().unwrap_or(wiki_path)
+    };
+    wiki_dir.join("files")
+}
+
+/// An imported image after processing
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProcessedImage {
+    pub filename: String,
+    pub mime_type: String,
+    /// Saved file (external attachments)
+    pub path: Option<String>,
+    /// Base64 content (embedded images)
+    pub text: Option<String>,
+}
+
+/// Apply a wiki's image options to an imported image file. The result is
+/// saved to the wiki's `files` folder, or returned as base64 with `embed`.
+/// None if the image is imported as it is.
+#[tauri::command]
+pub async fn process_imported_image(
+    app: tauri::AppHandle,
+    wiki_path: String,
+    path: String,
+    embed: bool,
+) -> Result<Option<ProcessedImage>, String> {
+    let Some(kind) = Kind::from_filename(&path) else {
+        return Ok(None);
+    };
+    let config = crate::wiki_storage::load_wiki_configs(&app)?
+        .external_attachments
+        .get(&wiki_path)
+        .cloned()
+        .unwrap_or_default();
+    let options = Options::from_config(&config);
+    if !options.is_active() {
+        return Ok(None);
+    }
+
+    let source = crate::drag_drop::sanitize::validate_user_file_path(&path)?;
+
+    tokio::task::spawn_blocking(move || -> Result<Option<ProcessedImage>, String> {
+        let bytes = std::fs::read(&source).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        let Some((bytes, output)) = process(&bytes, kind, options)? else {
+            return Ok(None);
+        };
+        let filename = output_filename(&path, output);
+        let mime_type = output.mime_type().to_string();
+        if embed {
+            let text = base64::engine::general_purpose::STANDARD.encode(&bytes);
+            return Ok(Some(ProcessedImage { filename, mime_type, path: None, text: Some(text) }));
+        }
+        let dir = attachments_dir(&wiki_path);
+        std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        let target = crate::downloads::unique_path(&dir, &filename);
+        std::fs::write(&target, &bytes).map_err(|e| format!("Failed to save {}: {}", target.display(), e))?;
+        let filename = target
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or(filename);
+        Ok(Some(ProcessedImage {
+            filename,
+            mime_type,
+            path: Some(target.to_string_lossy().into_owned()),
+            text: None,
+        }))
+    })
+    .await
+    .map_err(|e| format!("Image processing failed: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Little-endian EXIF data with one GPS entry (a RATIONAL[3] latitude)
+    fn exif_with_gps() -> Vec<u8> {
+        let mut tiff = b"II\x2a\x00\x08\x00\x00\x00".to_vec();
+        // IFD0: one entry, the GPS directory pointer (offset 26)
+        tiff.extend_from_slice(&[1, 0, 0x25, 0x88, 4, 0, 1, 0, 0, 0, 26, 0, 0, 0, 0, 0, 0, 0]);
+        // GPS directory: GPSLatitude, 3 rationals at offset 44
+        tiff.extend_from_slice(&[1, 0, 2, 0, 5, 0, 3, 0, 0, 0, 44, 0, 0, 0, 0, 0, 0, 0]);
+        tiff.extend_from_slice(&[48, 0, 0, 0, 1, 0, 0, 0, 12, 0, 0, 0, 1, 0, 0, 0, 30, 0, 0, 0, 1, 0, 0, 0]);
+        tiff
+    }
+
+    fn jpeg_with(segment_payload: &[u8]) -> Vec<u8> {
+        let mut jpeg = vec![0xFF, 0xD8, 0xFF, 0xE1];
+        jpeg.extend_from_slice(&((segment_payload.len() + 2) as u16).to_be_bytes());
+        jpeg.extend_from_slice(segment_payload);
+        jpeg.extend_from_slice(&[0xFF, 0xDA, 0x00, 0x02, 0x12, 0x34, 0xFF, 0xD9]);
+        jpeg
+    }
+
+    #[test]
+    fn test_strip_exif_gps() {
+        let mut tiff = exif_with_gps();
+        assert!(strip_exif_gps(&mut tiff));
+        assert!(tiff[26..].iter().all(|&b| b == 0));
+        // The pointer in IFD0 stays valid
+        assert_eq!(&tiff[10..12], &[0x25, 0x88]);
+        assert!(!strip_exif_gps(&mut b"II\x2a\x00\x08\x00\x00\x00\x00\x00".to_vec()));
+    }
+
+    #[test]
+    fn test_strip_jpeg_location() {
+        let mut payload = EXIF_HEADER.to_vec();
+        payload.extend_from_slice(&exif_with_gps());
+        let jpeg = jpeg_with(&payload);
+        let stripped = strip_jpeg_location(&jpeg).unwrap();
+        assert_eq!(stripped.len(), jpeg.len());
+        assert!(stripped.ends_with(&[0xFF, 0xDA, 0x00, 0x02, 0x12, 0x34, 0xFF, 0xD9]));
+
+        let mut xmp = XMP_HEADER.to_vec();
+        xmp.extend_from_slice(b"<x:xmpmeta exif:GPSLatitude=\"48,12N\"/>");
+        assert_eq!(
+            strip_jpeg_location(&jpeg_with(&xmp)).unwrap(),
+            vec![0xFF, 0xD8, 0xFF, 0xDA, 0x00, 0x02, 0x12, 0x34, 0xFF, 0xD9]
+        );
+        assert_eq!(strip_jpeg_location(&jpeg_with(b"Exif\0\0II\x2a\x00\x08\x00\x00\x00\x00\x00")), None);
+    }
+
+    #[test]
+    fn test_process() {
+        let options = Options { max_dimension: 50, ..Default::default() };
+        let image = DynamicImage::new_rgb8(200, 100);
+        let png = encode(&image, Output::Png).unwrap();
+        let (resized, output) = process(&png, Kind::Png, options).unwrap().unwrap();
+        assert_eq!(output, Output::Png);
+        assert_eq!(image::load_from_memory(&resized).unwrap().width(), 50);
+        assert!(process(&png, Kind::Png, Options { max_dimension: 500, ..Default::default() }).unwrap().is_none());
+        // WebP is only touched when conversion is on
+        assert!(process(&png, Kind::Webp, options).unwrap().is_none());
+        assert_eq!(output_filename("IMG_0001.HEIC", Output::Jpeg), "IMG_0001.jpg");
+    }
+}
@@ -0,0 +1,355 @@
+//! Incremental saving of big single-file wikis
+//!
+//! TiddlyWiki always saves the whole file, so every change to a 100 MB wiki
+//! rewrites 100 MB. With `AppSettings::incremental_save` on, saving a big wiki
+//! only appends the tiddlers that changed since the file was written, as one
+//! more tiddler store after the existing ones (TiddlyWiki loads the stores in
+//! order, later tiddlers replacing earlier ones with the same title), and
+//! rewrites the rest of the file after it: the boot code, a few hundred KB.
+//!
+//! The file is written in full again when appending can't express the change
+//! (deleted tiddlers, anything changed outside the tiddler stores), and once
+//! the appended stores get too many or too big, which leaves a clean file.
+//!
+//! The patch is written to `<wiki>.patch` before the wiki is changed in place,
+//! so a save interrupted by a crash is finished (or dropped, if the patch
+//! itself is incomplete) the next time the wiki is loaded or saved.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::hash::{Hash, Hasher};
+use std::io::{Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+use tiddlydesktop_core::tiddler_store::StoreReader;
+
+const STORE_START: &str = r#"<script class="tiddlywiki-tiddler-store" type="application/json">"#;
+const STORE_END: &str = "</script>";
+
+/// Smaller wikis are always written in full
+const MIN_SIZE: u64 = 5 * 1024 * 1024;
+
+/// Appended stores after which the file is written in full again
+const MAX_APPENDED_STORES: usize = 50;
+
+/// How much bigger than written in full (in percent) appended stores may make
+/// the file, with the earlier versions of changed tiddlers they keep
+const MAX_GROWTH_PERCENT: usize = 10;
+
+/// First line of a patch file: `tiddlydesktop-patch <offset> <length>`
+const PATCH_HEADER: &str = "tiddlydesktop-patch";
+
+/// What saving new content over a wiki file takes
+#[derive(Debug, PartialEq)]
+enum Plan {
+    /// The file already loads the same wiki
+    Unchanged,
+    /// Replace everything from `offset` with `bytes`
+    Patch { offset: usize, bytes: Vec<u8> },
+    /// Write the whole file
+    Full,
+}
+
+fn find(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    haystack
+        .get(from..)?
+        .windows(needle.len())
+        .position(|window| window == needle)
+        .map(|i| from + i)
+}
+
+/// The JSON tiddler stores of a wiki file, from `<script` to `</script>`
+/// (TiddlyWiki escapes `<` in them, so they can't contain `</script>`)
+fn stores(content: &[u8]) -> Vec<Range<usize>> {
+    let mut stores = Vec::new();
+    let mut pos = 0;
+    while let Some(start) = find(content, STORE_START.as_bytes(), pos) {
+        let Some(end) = find(content, STORE_END.as_bytes(), start + STORE_START.len()) else {
+            break;
+        };
+        pos = end + STORE_END.len();
+        stores.push(start..pos);
+    }
+    stores
+}
+
+/// The file without its tiddler stores (and the whitespace between them)
+fn skeleton<'a>(content: &'a [u8], stores: &[Range<usize>]) -> Vec<&'a [u8]> {
+    let mut parts = Vec::with_capacity(stores.len() + 1);
+    let mut pos = 0;
+    for store in stores.iter().chain(std::iter::once(&(content.len()..content.len()))) {
+        parts.push(&content[pos..store.start]);
+        pos = store.end;
+    }
+    parts.retain(|part| !part.iter().all(u8::is_ascii_whitespace));
+    parts
+}
+
+fn digest(tiddler: &Value) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    tiddler.to_string().hash(&mut hasher);
+    hasher.finish()
+}
+
+/// A tiddler store with `tiddlers`, written the way TiddlyWiki does
+fn store(tiddlers: &[Value]) -> String {
+    let tiddlers: Vec<String> = tiddlers
+        .iter()
+        .map(|tiddler| tiddler.to_string().replace('<', "\\u003C"))
+        .collect();
+    format!("{}[\n{}\n]{}", STORE_START, tiddlers.join(",\n"), STORE_END)
+}
+
+/// How to save `new` (a whole wiki, as TiddlyWiki saves it) over the file `old`
+fn plan(old: &[u8], new: &[u8]) -> Plan {
+    let old_stores = stores(old);
+    let new_stores = stores(new);
+    let Some(last) = old_stores.last() else {
+        return Plan::Full;
+    };
+    if new_stores.is_empty() || old_stores.len().saturating_sub(new_stores.len()) >= MAX_APPENDED_STORES {
+        return Plan::Full;
+    }
+    if skeleton(old, &old_stores) != skeleton(new, &new_stores) {
+        return Plan::Full;
+    }
+
+    // The tiddlers the file loads now, later ones replacing earlier ones
+    let mut saved: HashMap<String, u64> = HashMap::new();
+    for tiddler in StoreReader::new(old) {
+        let Ok(tiddler) = tiddler else {
+            return Plan::Full;
+        };
+        if let Some(title) = tiddler.get("title").and_then(Value::as_str) {
+            saved.insert(title.to_string(), digest(&tiddler));
+        }
+    }
+    let mut changed = Vec::new();
+    for tiddler in StoreReader::new(new) {
+        let Ok(tiddler) = tiddler else {
+            return Plan::Full;
+        };
+        let Some(title) = tiddler.get("title").and_then(Value::as_str) else {
+            continue;
+        };
+        if saved.remove(title) != Some(digest(&tiddler)) {
+            changed.push(tiddler);
+        }
+    }
+    // Appending can't delete tiddlers
+    if !saved.is_empty() {
+        return Plan::Full;
+    }
+    if changed.is_empty() {
+        return Plan::Unchanged;
+    }
+
+    let store = store(&changed);
+    let size = old.len() + 1 + store.len();
+    if size.saturating_sub(new.len()) > new.len() / 100 * MAX_GROWTH_PERCENT {
+        return Plan::Full;
+    }
+    let offset = last.end;
+    let mut bytes = Vec::with_capacity(size - offset);
+    bytes.push(b'\n');
+    bytes.extend_from_slice(store.as_bytes());
+    bytes.extend_from_slice(&old[offset..]);
+    Plan::Patch { offset, bytes }
+}
+
+fn patch_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".patch");
+    PathBuf::from(name)
+}
+
+/// Replace everything in the file from `offset` with `bytes`
+fn write_at(path: &Path, offset: usize, bytes: &[u8]) -> std::io::Result<()> {
+    let mut file = OpenOptions::new().write(true).open(path)?;
+    file.seek(SeekFrom::Start(offset as u64))?;
+    file.write_all(bytes)?;
+    file.set_len((offset + bytes.len()) as u64)?;
+    file.sync_all()
+}
+
+/// Patch the wiki file in place, with the patch on disk first
+fn apply(path: &Path, offset: usize, bytes: &[u8]) -> std::io::Result<()> {
+    let patch_path = patch_path(path);
+    let mut patch = File::create(&patch_path)?;
+    patch.write_all(format!("{} {} {}\n", PATCH_HEADER, offset, bytes.len()).as_bytes())?;
+    patch.write_all(bytes)?;
+    patch.sync_all()?;
+    drop(patch);
+    write_at(path, offset, bytes)?;
+    std::fs::remove_file(&patch_path)
+}
+
+/// The offset and bytes of a patch file, unless it's incomplete
+fn parse_patch(patch: &[u8]) -> Option<(usize, &[u8])> {
+    let newline = patch.iter().position(|&b| b == b'\n')?;
+    let header = std::str::from_utf8(&patch[..newline]).ok()?;
+    let mut parts = header.split(' ');
+    if parts.next()? != PATCH_HEADER {
+        return None;
+    }
+    let offset: usize = parts.next()?.parse().ok()?;
+    let len: usize = parts.next()?.parse().ok()?;
+    let bytes = &patch[newline + 1..];
+    (bytes.len() == len).then_some((offset, bytes))
+}
+
+/// Finish an incremental save of the wiki file at `path` that was interrupted
+/// (call before reading or writing the file)
+pub fn recover(path: &Path) {
+    let patch_path = patch_path(path);
+    let Ok(patch) = std::fs::read(&patch_path) else {
+        return;
+    };
+    let file_size = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    match parse_patch(&patch) {
+        Some((offset, bytes)) if offset as u64 <= file_size => {
+            if let Err(e) = write_at(path, offset, bytes) {
+                eprintln!("[TiddlyDesktop] Failed to finish the interrupted save of {}: {}", path.display(), e);
+                return;
+            }
+            eprintln!("[TiddlyDesktop] Finished the interrupted save of {}", path.display());
+        }
+        _ => eprintln!("[TiddlyDesktop] Dropped the incomplete patch of {}", path.display()),
+    }
+    let _ = std::fs::remove_file(&patch_path);
+}
+
+fn enabled(app: &tauri::AppHandle) -> bool {
+    crate::wiki_storage::load_app_settings(app).map(|s| s.incremental_save).unwrap_or(false)
+}
+
+/// Whether saving the wiki file at `path` may be incremental: it's on, and the file big enough
+pub fn wants(app: &tauri::AppHandle, path: &Path) -> bool {
+    let big = std::fs::metadata(path).is_ok_and(|m| m.is_file() && m.len() >= MIN_SIZE);
+    big && enabled(app)
+}
+
+/// Save `content` to the wiki file at `path` by patching in the changed
+/// tiddlers, if incremental saving is on and the change allows it. Returns
+/// whether it was saved; if not, the file still has to be written in full.
+pub fn save(app: &tauri::AppHandle, path: &Path, content: &[u8]) -> bool {
+    if !wants(app, path) {
+        return false;
+    }
+    let Ok(old) = std::fs::read(path) else {
+        return false;
+    };
+    match plan(&old, content) {
+        Plan::Full => false,
+        Plan::Unchanged => true,
+        Plan::Patch { offset, bytes } => match apply(path, offset, &bytes) {
+            Ok(()) => {
+                eprintln!(
+                    "[TiddlyDesktop] Saved {} incrementally ({} of {} bytes written)",
+                    path.display(),
+                    bytes.len(),
+                    offset + bytes.len()
+                );
+                true
+            }
+            Err(e) => {
+                // Written in full instead, so the patch mustn't be applied later
+                eprintln!("[TiddlyDesktop] Incremental save of {} failed: {}", path.display(), e);
+                let _ = std::fs::remove_file(patch_path(path));
+                false
+            }
+        },
+    }
+}
+
+/// Whether big single-file wikis are saved incrementally
+#[tauri::command]
+pub fn get_incremental_save(app: tauri::AppHandle) -> bool {
+    enabled(&app)
+}
+
+/// Save big single-file wikis incrementally (or always in full)
+#[tauri::command]
+pub fn set_incremental_save(app: tauri::AppHandle, enabled: bool) -> Result<bool, String> {
+    let mut settings = crate::wiki_storage::load_app_settings(&app)?;
+    settings.incremental_save = enabled;
+    crate::wiki_storage::save_app_settings(&app, &settings)?;
+    Ok(enabled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn wiki(title: &str, tiddlers: &[Value]) -> String {
+        format!(
+            "<html><head><title>{}</title></head><body>\n{}\n<div id=\"storeArea\" style=\"display:none;\"></div>\n<script>boot</script></body></html>",
+            title,
+            store(tiddlers)
+        )
+    }
+
+    fn patched(old: &str, plan: Plan) -> String {
+        match plan {
+            Plan::Patch { offset, bytes } => format!("{}{}", &old[..offset], String::from_utf8(bytes).unwrap()),
+            other => panic!("expected a patch, got {:?}", other),
+        }
+    }
+
+    fn loaded(content: &str) -> HashMap<String, Value> {
+        StoreReader::new(content.as_bytes())
+            .map(|tiddler| tiddler.unwrap())
+            .map(|tiddler| (tiddler["title"].as_str().unwrap().to_string(), tiddler))
+            .collect()
+    }
+
+    #[test]
+    fn test_plan() {
+        let padding = json!({"title": "Padding", "text": "x".repeat(10_000)});
+        let a = json!({"title": "A", "text": "one"});
+        let b = json!({"title": "B", "text": "<b>two</b>"});
+        let old = wiki("Wiki", &[padding.clone(), a.clone(), b.clone()]);
+
+        let b2 = json!({"title": "B", "text": "<b>changed</b>"});
+        let c = json!({"title": "C", "text": "new"});
+        let new = wiki("Wiki", &[padding.clone(), a.clone(), b2.clone(), c.clone()]);
+        let once = patched(&old, plan(old.as_bytes(), new.as_bytes()));
+        assert_eq!(stores(once.as_bytes()).len(), 2);
+        assert!(once.ends_with("<script>boot</script></body></html>"));
+        let tiddlers = loaded(&once);
+        assert_eq!(tiddlers["B"], b2);
+        assert_eq!(tiddlers["C"], c);
+        assert_eq!(tiddlers["A"], a);
+
+        // Saving again appends to the patched file, unless nothing changed
+        assert_eq!(plan(once.as_bytes(), new.as_bytes()), Plan::Unchanged);
+        let a2 = json!({"title": "A", "text": "three"});
+        let newer = wiki("Wiki", &[padding.clone(), a2.clone(), b2.clone(), c.clone()]);
+        let twice = patched(&once, plan(once.as_bytes(), newer.as_bytes()));
+        assert_eq!(stores(twice.as_bytes()).len(), 3);
+        assert_eq!(loaded(&twice)["A"], a2);
+
+        // Deletions and changes outside the stores need the whole file
+        let deleted = wiki("Wiki", &[padding.clone(), a.clone()]);
+        assert_eq!(plan(old.as_bytes(), deleted.as_bytes()), Plan::Full);
+        let retitled = wiki("Renamed", &[padding.clone(), a.clone(), b2.clone()]);
+        assert_eq!(plan(old.as_bytes(), retitled.as_bytes()), Plan::Full);
+
+        // So does growing too much through earlier versions of changed tiddlers
+        let padding2 = json!({"title": "Padding", "text": "y".repeat(10_000)});
+        let rewritten = wiki("Wiki", &[padding2, a, b]);
+        assert_eq!(plan(old.as_bytes(), rewritten.as_bytes()), Plan::Full);
+    }
+
+    #[test]
+    fn test_parse_patch() {
+        assert_eq!(parse_patch(b"tiddlydesktop-patch 12 3\nabc"), Some((12, &b"abc"[..])));
+        assert_eq!(parse_patch(b"tiddlydesktop-patch 12 3\nab"), None);
+        assert_eq!(parse_patch(b"tiddlydesktop-patch 12"), None);
+        assert_eq!(parse_patch(b"something else 12 3\nabc"), None);
+    }
+}
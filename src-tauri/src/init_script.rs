@@ -11,13 +11,38 @@
 //!
 //! The JavaScript is organized into semantic modules:
 //! - main.js: Entry point and namespace setup
+//! - js_errors.js: Reporting uncaught errors and console.error calls to the error log
 //! - core.js: Initialization guard, modal UI, confirm override
+//! - accelerators.js: Per-wiki configurable keyboard shortcuts
+//! - accessibility.js: OS high-contrast / reduced-motion propagation
+//! - app_lock.js: Activity reports for the app lock's inactivity timer
+//! - throttle.js: Pausing animations of minimized/hidden wiki windows
+//! - audio.js: Per-window volume of audio and video
+//! - media_session.js: OS media session and hardware media keys for played media
+//! - geolocation.js: Position from the OS location services (navigator.geolocation, geotagging)
+//! - serial.js: Serial port access (list, open, read, write; per-wiki permission)
+//! - qr_scan.js: Scanning QR codes and barcodes with the webcam, QR code images
+//! - idle.js: System idle state (SystemIdle temp tiddler, autosave on idle)
+//! - time_tracking.js: Recording the time spent in the wiki (opt-in per wiki)
+//! - focus_timer.js: Focus timer badge, start/stop messages
+//! - quick_capture.js: Tray quick actions queued for the wiki (new tiddler, journal, paste)
+//! - wiki_commands.js: Wiki-defined commands for the tray menu ($:/tags/TiddlyDesktopRS/MenuCommand)
+//! - automation.js: Tiddler requests of the local automation API (see `automation`)
+//! - mqtt.js: MQTT broker connection from config tiddlers, messages as temp tiddlers
+//! - allowed_commands.js: Running the user's allowed commands by name, output as temp tiddlers
+//! - environment.js: Platform, versions and optional features as a data tiddler
+//! - memory_limit.js: Save-and-restart prompt when the wiki exceeds its memory limit
 //! - window.js: Window close handler with unsaved changes check
 //! - filesystem.js: httpRequest override, path resolution, media interceptor
 //! - drag_drop.js: External attachments, file drops, content drags, paste, import hooks
 //! - session_auth.js: Session authentication URL management
 //! - internal_drag.js: Internal TiddlyWiki drag-and-drop polyfill
 //! - sync.js: Window handlers, cross-window tiddler synchronization
+//! - sync_badge.js: Sync status badge in the native window title
+//! - shared_clipboard.js: Sending the selection to / pasting text from connected sync devices
+//! - attachment_migration.js: Externalizing embedded attachments and embedding small external ones
+//! - custom_snippets.js: The user's custom CSS/JS snippets for the wiki (see `custom_snippets`)
+//! - plugin_dev.js: Reloading folder wikis when the plugin in development changes (see `plugin_dev`)
 
 /// Media controls CSS stylesheet (included inline because WebKitGTK doesn't load
 /// CSS from custom URI schemes like tdlib:// via <link> tags)
@@ -31,8 +56,48 @@ const COMBINED_INIT_SCRIPT: &str = concat!(
     "window.__tdInitErr=function(n,e){var m='[TD init] '+n+' error: '+(e&&e.message||e);if(window.__TAURI__&&window.__TAURI__.core&&window.__TAURI__.core.invoke){window.__TAURI__.core.invoke('js_log',{message:m}).catch(function(){})}console.error(m)};\n",
     "try{\n", include_str!("init_script/main.js"),
     "\n}catch(_e){window.__tdInitErr('main.js',_e)}\n",
+    "try{\n", include_str!("init_script/js_errors.js"),
+    "\n}catch(_e){window.__tdInitErr('js_errors.js',_e)}\n",
     "try{\n", include_str!("init_script/core.js"),
     "\n}catch(_e){window.__tdInitErr('core.js',_e)}\n",
+    "try{\n", include_str!("init_script/accelerators.js"),
+    "\n}catch(_e){window.__tdInitErr('accelerators.js',_e)}\n",
+    "try{\n", include_str!("init_script/accessibility.js"),
+    "\n}catch(_e){window.__tdInitErr('accessibility.js',_e)}\n",
+    "try{\n", include_str!("init_script/app_lock.js"),
+    "\n}catch(_e){window.__tdInitErr('app_lock.js',_e)}\n",
+    "try{\n", include_str!("init_script/throttle.js"),
+    "\n}catch(_e){window.__tdInitErr('throttle.js',_e)}\n",
+    "try{\n", include_str!("init_script/audio.js"),
+    "\n}catch(_e){window.__tdInitErr('audio.js',_e)}\n",
+    "try{\n", include_str!("init_script/media_session.js"),
+    "\n}catch(_e){window.__tdInitErr('media_session.js',_e)}\n",
+    "try{\n", include_str!("init_script/geolocation.js"),
+    "\n}catch(_e){window.__tdInitErr('geolocation.js',_e)}\n",
+    "try{\n", include_str!("init_script/serial.js"),
+    "\n}catch(_e){window.__tdInitErr('serial.js',_e)}\n",
+    "try{\n", include_str!("init_script/qr_scan.js"),
+    "\n}catch(_e){window.__tdInitErr('qr_scan.js',_e)}\n",
+    "try{\n", include_str!("init_script/idle.js"),
+    "\n}catch(_e){window.__tdInitErr('idle.js',_e)}\n",
+    "try{\n", include_str!("init_script/time_tracking.js"),
+    "\n}catch(_e){window.__tdInitErr('time_tracking.js',_e)}\n",
+    "try{\n", include_str!("init_script/focus_timer.js"),
+    "\n}catch(_e){window.__tdInitErr('focus_timer.js',_e)}\n",
+    "try{\n", include_str!("init_script/quick_capture.js"),
+    "\n}catch(_e){window.__tdInitErr('quick_capture.js',_e)}\n",
+    "try{\n", include_str!("init_script/wiki_commands.js"),
+    "\n}catch(_e){window.__tdInitErr('wiki_commands.js',_e)}\n",
+    "try{\n", include_str!("init_script/automation.js"),
+    "\n}catch(_e){window.__tdInitErr('automation.js',_e)}\n",
+    "try{\n", include_str!("init_script/mqtt.js"),
+    "\n}catch(_e){window.__tdInitErr('mqtt.js',_e)}\n",
+    "try{\n", include_str!("init_script/allowed_commands.js"),
+    "\n}catch(_e){window.__tdInitErr('allowed_commands.js',_e)}\n",
+    "try{\n", include_str!("init_script/environment.js"),
+    "\n}catch(_e){window.__tdInitErr('environment.js',_e)}\n",
+    "try{\n", include_str!("init_script/memory_limit.js"),
+    "\n}catch(_e){window.__tdInitErr('memory_limit.js',_e)}\n",
     "try{\n", include_str!("init_script/window.js"),
     "\n}catch(_e){window.__tdInitErr('window.js',_e)}\n",
     "try{\n", include_str!("init_script/filesystem.js"),
@@ -57,6 +122,16 @@ const COMBINED_INIT_SCRIPT: &str = concat!(
     "\n}catch(_e){window.__tdInitErr('conflict_ui.js',_e)}\n",
     "try{\n", include_str!("init_script/peer_status.js"),
     "\n}catch(_e){window.__tdInitErr('peer_status.js',_e)}\n",
+    "try{\n", include_str!("init_script/sync_badge.js"),
+    "\n}catch(_e){window.__tdInitErr('sync_badge.js',_e)}\n",
+    "try{\n", include_str!("init_script/shared_clipboard.js"),
+    "\n}catch(_e){window.__tdInitErr('shared_clipboard.js',_e)}\n",
+    "try{\n", include_str!("init_script/attachment_migration.js"),
+    "\n}catch(_e){window.__tdInitErr('attachment_migration.js',_e)}\n",
+    "try{\n", include_str!("init_script/custom_snippets.js"),
+    "\n}catch(_e){window.__tdInitErr('custom_snippets.js',_e)}\n",
+    "try{\n", include_str!("init_script/plugin_dev.js"),
+    "\n}catch(_e){window.__tdInitErr('plugin_dev.js',_e)}\n",
 );
 
 /// Full JavaScript initialization script for wiki windows - sets all necessary variables early
@@ -114,6 +189,8 @@ pub fn get_wiki_init_script_with_language(wiki_path: &str, window_label: &str, i
     if !is_main_wiki {
         let css_json = serde_json::to_string(MEDIA_CONTROLS_CSS).unwrap_or_else(|_| "\"\"".to_string());
         script.push_str(&format!("window.__MEDIA_CONTROLS_CSS__ = {};\n", css_json));
+        // The user's CSS/JS for this wiki, injected by custom_snippets.js
+        script.push_str(&crate::custom_snippets::init_script(wiki_path));
     }
     script.push_str(get_dialog_init_script());
     script
@@ -0,0 +1,160 @@
+// TiddlyDesktop Initialization Script - Accelerators Module
+// Provides: per-wiki configurable keyboard shortcuts (find bar, zoom reset, reopen closed wiki, share selection, quick switcher), input debugging
+
+(function(TD) {
+    'use strict';
+
+    // Built-in defaults, used until the wiki's map has been loaded
+    // (mirrors AcceleratorMap::defaults() in types.rs)
+    var DEFAULT_BINDINGS = {
+        'find': ['CmdOrCtrl+F'],
+        'find-next': ['F3', 'CmdOrCtrl+G'],
+        'find-previous': ['Shift+F3', 'CmdOrCtrl+Shift+G'],
+        'find-close': ['Escape'],
+        'zoom-reset': ['CmdOrCtrl+0'],
+        'reopen-closed-wiki': ['CmdOrCtrl+Shift+T'],
+        'share-selection': ['CmdOrCtrl+Alt+C'],
+        'quick-switcher': ['CmdOrCtrl+K']
+    };
+
+    var bindings = DEFAULT_BINDINGS;
+    var parsed = {};
+
+    var isMac = navigator.platform && navigator.platform.indexOf('Mac') !== -1;
+
+    function parseAccelerator(str) {
+        var acc = { cmdOrCtrl: false, ctrl: false, meta: false, alt: false, shift: false, key: '' };
+        str.split('+').forEach(function(part) {
+            var p = part.trim().toLowerCase();
+            if (p === 'cmdorctrl' || p === 'commandorcontrol') acc.cmdOrCtrl = true;
+            else if (p === 'ctrl' || p === 'control') acc.ctrl = true;
+            else if (p === 'cmd' || p === 'command' || p === 'meta' || p === 'super') acc.meta = true;
+            else if (p === 'alt' || p === 'option') acc.alt = true;
+            else if (p === 'shift') acc.shift = true;
+            else acc.key = p;
+        });
+        return acc;
+    }
+
+    function keyMatches(accKey, e) {
+        var key = (e.key || '').toLowerCase();
+        if (key === accKey) return true;
+        // Fall back to the physical key for digits and letters so that
+        // Shift/Alt-modified characters and non-Latin layouts still match
+        if (accKey.length === 1) {
+            var code = e.code || '';
+            if (code === 'Digit' + accKey.toUpperCase() || code === 'Key' + accKey.toUpperCase()) return true;
+        }
+        return false;
+    }
+
+    function eventMatches(acc, e) {
+        if (!keyMatches(acc.key, e)) return false;
+        if (acc.cmdOrCtrl && !acc.ctrl && !acc.meta) {
+            // Accept either modifier so Ctrl+F keeps working on macOS as before
+            if (!(e.ctrlKey || e.metaKey)) return false;
+        } else {
+            if (!!e.ctrlKey !== (acc.ctrl || (acc.cmdOrCtrl && !isMac))) return false;
+            if (!!e.metaKey !== (acc.meta || (acc.cmdOrCtrl && isMac))) return false;
+        }
+        if (!!e.altKey !== acc.alt) return false;
+        if (!!e.shiftKey !== acc.shift) return false;
+        return true;
+    }
+
+    function applyMap(map) {
+        if (!map || !map.bindings) return;
+        bindings = map.bindings;
+        parsed = {};
+    }
+
+    // Check whether a keyboard event triggers the given action
+    function matchesAccelerator(action, e) {
+        // Keys pressed while an IME composition is active belong to the IME
+        if (e.isComposing || e.keyCode === 229) return false;
+        if (!parsed[action]) {
+            parsed[action] = (bindings[action] || []).map(parseAccelerator);
+        }
+        for (var i = 0; i < parsed[action].length; i++) {
+            if (eventMatches(parsed[action][i], e)) return true;
+        }
+        return false;
+    }
+
+    // Human-readable list of the accelerators bound to an action
+    function describeAccelerator(action) {
+        return (bindings[action] || []).map(function(a) {
+            return a.replace('CmdOrCtrl', isMac ? 'Cmd' : 'Ctrl');
+        }).join(', ');
+    }
+
+    // Config key for this window (same convention as window state persistence)
+    function configKey() {
+        return window.__IS_MAIN_WIKI__ ? '__LANDING_PAGE__' : (window.__WIKI_PATH__ || '');
+    }
+
+    function loadAccelerators() {
+        if (!window.__TAURI__ || !window.__TAURI__.core) {
+            setTimeout(loadAccelerators, 100);
+            return;
+        }
+        window.__TAURI__.core.invoke('get_accelerator_map', { wikiPath: configKey() })
+            .then(applyMap)
+            .catch(function(err) {
+                console.log('[TiddlyDesktop] Using default accelerators:', err);
+            });
+    }
+
+    loadAccelerators();
+
+    // Changes made in this process arrive as an event; changes made from the
+    // landing page (another process) are picked up when the window regains focus
+    if (window.__TAURI__ && window.__TAURI__.event) {
+        window.__TAURI__.event.listen('accelerators-changed', function(event) {
+            var payload = event.payload || {};
+            if (payload.wikiPath === configKey()) {
+                applyMap(payload.map);
+            }
+        });
+    }
+    window.addEventListener('focus', loadAccelerators);
+
+    // Input debugging: log key and composition events as the webview sees them,
+    // together with what the platform input layer reports (get_input_debug_info)
+    var inputDebugHandler = null;
+    var INPUT_DEBUG_EVENTS = ['keydown', 'keyup', 'compositionstart', 'compositionupdate', 'compositionend', 'beforeinput'];
+
+    function inputDebug(enable) {
+        var invoke = window.__TAURI__ && window.__TAURI__.core && window.__TAURI__.core.invoke;
+        if (!invoke) return;
+        if (inputDebugHandler) {
+            INPUT_DEBUG_EVENTS.forEach(function(type) {
+                document.removeEventListener(type, inputDebugHandler, true);
+            });
+            inputDebugHandler = null;
+        }
+        if (enable === false) return;
+
+        invoke('get_input_debug_info').then(function(info) {
+            invoke('js_log', { message: '[InputDebug] platform: ' + JSON.stringify(info) }).catch(function() {});
+        }).catch(function() {});
+
+        inputDebugHandler = function(e) {
+            var msg = '[InputDebug] ' + e.type +
+                ' key=' + JSON.stringify(e.key) + ' code=' + e.code + ' keyCode=' + e.keyCode +
+                ' composing=' + !!e.isComposing +
+                ' mods=' + (e.ctrlKey ? 'C' : '') + (e.metaKey ? 'M' : '') + (e.altKey ? 'A' : '') + (e.shiftKey ? 'S' : '') +
+                (e.data !== undefined ? ' data=' + JSON.stringify(e.data) : '') +
+                (e.inputType ? ' inputType=' + e.inputType : '');
+            invoke('js_log', { message: msg }).catch(function() {});
+        };
+        INPUT_DEBUG_EVENTS.forEach(function(type) {
+            document.addEventListener(type, inputDebugHandler, true);
+        });
+    }
+
+    // Export to TD namespace
+    TD.matchesAccelerator = matchesAccelerator;
+    TD.describeAccelerator = describeAccelerator;
+    TD.inputDebug = inputDebug;
+})(window.TiddlyDesktop = window.TiddlyDesktop || {});
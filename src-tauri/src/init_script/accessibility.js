@@ -0,0 +1,92 @@
+// TiddlyDesktop Initialization Script - Accessibility Module
+// Provides: OS high-contrast / reduced-motion propagation into wiki windows
+
+(function(TD) {
+    'use strict';
+
+    var STYLE_ID = 'td-accessibility-styles';
+    var HIGH_CONTRAST_CLASS = 'td-high-contrast';
+    var REDUCED_MOTION_CLASS = 'td-reduced-motion';
+
+    // Reduced motion: neutralise CSS animations/transitions (TiddlyWiki's story river
+    // animations are CSS transitions) and smooth scrolling
+    var REDUCED_MOTION_CSS =
+        'html.' + REDUCED_MOTION_CLASS + ' *, html.' + REDUCED_MOTION_CLASS + ' *::before, html.' + REDUCED_MOTION_CLASS + ' *::after {' +
+        'animation-duration:0.01ms !important;animation-iteration-count:1 !important;' +
+        'transition-duration:0.01ms !important;scroll-behavior:auto !important;}';
+
+    // High contrast: make keyboard focus clearly visible using system colours
+    var HIGH_CONTRAST_CSS =
+        'html.' + HIGH_CONTRAST_CLASS + ' :focus-visible {outline:3px solid Highlight !important;outline-offset:2px !important;}';
+
+    var current = { highContrast: false, reducedMotion: false };
+
+    function applyPreferences(prefs) {
+        if (!prefs) return;
+        var changed = prefs.highContrast !== current.highContrast || prefs.reducedMotion !== current.reducedMotion;
+        current = { highContrast: !!prefs.highContrast, reducedMotion: !!prefs.reducedMotion };
+        window.__TD_ACCESSIBILITY__ = current;
+
+        var root = document.documentElement;
+        if (root) {
+            root.classList.toggle(HIGH_CONTRAST_CLASS, current.highContrast);
+            root.classList.toggle(REDUCED_MOTION_CLASS, current.reducedMotion);
+        }
+
+        if (document.head && !document.getElementById(STYLE_ID)) {
+            var style = document.createElement('style');
+            style.id = STYLE_ID;
+            style.textContent = REDUCED_MOTION_CSS + '\n' + HIGH_CONTRAST_CSS;
+            document.head.appendChild(style);
+        }
+
+        // Expose to wikitext as temp tiddlers (never saved)
+        if (typeof $tw !== 'undefined' && $tw.wiki) {
+            $tw.wiki.addTiddler({ title: '$:/temp/TiddlyDesktop/HighContrast', text: current.highContrast ? 'yes' : 'no' });
+            $tw.wiki.addTiddler({ title: '$:/temp/TiddlyDesktop/ReducedMotion', text: current.reducedMotion ? 'yes' : 'no' });
+        }
+
+        if (changed) {
+            try {
+                window.dispatchEvent(new CustomEvent('tiddlydesktop-accessibility-changed', { detail: current }));
+            } catch (e) {}
+        }
+    }
+
+    function loadPreferences() {
+        if (!window.__TAURI__ || !window.__TAURI__.core) {
+            setTimeout(loadPreferences, 100);
+            return;
+        }
+        window.__TAURI__.core.invoke('get_accessibility_preferences')
+            .then(applyPreferences)
+            .catch(function(err) {
+                console.log('[TiddlyDesktop] Accessibility preferences unavailable:', err);
+            });
+    }
+
+    if (document.readyState === 'loading') {
+        document.addEventListener('DOMContentLoaded', loadPreferences);
+    } else {
+        loadPreferences();
+    }
+
+    // $tw may not exist yet on first load - re-apply once TiddlyWiki has booted
+    (function waitForTw() {
+        if (typeof $tw !== 'undefined' && $tw.wiki && $tw.rootWidget) {
+            applyPreferences(current);
+        } else {
+            setTimeout(waitForTw, 200);
+        }
+    })();
+
+    if (window.__TAURI__ && window.__TAURI__.event) {
+        window.__TAURI__.event.listen('accessibility-preferences-changed', function(event) {
+            applyPreferences(event.payload);
+        });
+    }
+    window.addEventListener('focus', loadPreferences);
+
+    // Export to TD namespace
+    TD.getAccessibilityPreferences = function() { return current; };
+})(window.TiddlyDesktop = window.TiddlyDesktop || {});
@@ -0,0 +1,113 @@
+// Allowed commands - runs the local programs the user allowed on the landing
+// page, by name (allowed_commands.rs):
+// - tm-tiddlydesktop-rs-run-command (param: command name; other parameters
+//   fill in the command's {placeholders}) writes the output to
+//   $:/temp/TiddlyDesktopRS/CommandOutput/<name> (status field "running",
+//   "finished" or "failed", exit-code field)
+// - TiddlyDesktop.runCommand(name, params, onLine) returns a promise of
+//   { code, output } once the command exits; onLine(stream, line) gets the
+//   output as it arrives
+// - TiddlyDesktop.stopCommand(name) stops the last run of a command
+(function(TD) {
+    'use strict';
+
+    // Only run in wiki windows of the desktop app
+    if (!window.__WIKI_PATH__ || window.__WINDOW_LABEL__ === 'main') return;
+    if (typeof window.TiddlyDesktopSync !== 'undefined') return; // Android
+
+    var OUTPUT_PREFIX = '$:/temp/TiddlyDesktopRS/CommandOutput/';
+
+    // runId -> { lines, onLine, resolve }
+    var runs = {};
+    // Output and finish events that arrived before the run id was known
+    var early = {};
+    // Command name -> last run id
+    var lastRun = {};
+    var listening = null;
+
+    function invoke(cmd, args) {
+        return window.__TAURI__.core.invoke(cmd, args);
+    }
+
+    function handleLine(payload) {
+        var run = runs[payload.runId];
+        if (!run) {
+            (early[payload.runId] = early[payload.runId] || []).push({ line: payload });
+            return;
+        }
+        run.lines.push(payload.line);
+        if (run.onLine) run.onLine(payload.stream, payload.line);
+    }
+
+    function handleFinished(payload) {
+        var run = runs[payload.runId];
+        if (!run) {
+            (early[payload.runId] = early[payload.runId] || []).push({ finished: payload });
+            return;
+        }
+        delete runs[payload.runId];
+        run.resolve({ code: payload.code, output: run.lines.join('\n') });
+    }
+
+    function listen() {
+        if (!listening) {
+            listening = Promise.all([
+                window.__TAURI__.event.listen('allowed-command-output', function(event) { handleLine(event.payload); }),
+                window.__TAURI__.event.listen('allowed-command-finished', function(event) { handleFinished(event.payload); })
+            ]);
+        }
+        return listening;
+    }
+
+    TD.runCommand = function(name, params, onLine) {
+        return listen().then(function() {
+            return invoke('run_allowed_command', { name: name, params: params || null });
+        }).then(function(runId) {
+            lastRun[name] = runId;
+            return new Promise(function(resolve) {
+                runs[runId] = { lines: [], onLine: onLine, resolve: resolve };
+                (early[runId] || []).forEach(function(item) {
+                    if (item.line) handleLine(item.line);
+                    else handleFinished(item.finished);
+                });
+                delete early[runId];
+            });
+        });
+    };
+
+    TD.stopCommand = function(name) {
+        if (lastRun[name] === undefined) return Promise.resolve();
+        return invoke('stop_allowed_command', { runId: lastRun[name] });
+    };
+
+    function runToTiddler(name, params) {
+        var title = OUTPUT_PREFIX + name;
+        var lines = [];
+        function write(fields) {
+            $tw.wiki.addTiddler(new $tw.Tiddler({ title: title, text: lines.join('\n') }, fields));
+        }
+        write({ status: 'running', 'exit-code': '' });
+        TD.runCommand(name, params, function(stream, line) {
+            lines.push(line);
+            write({ status: 'running' });
+        }).then(function(result) {
+            write({ status: result.code === 0 ? 'finished' : 'failed', 'exit-code': result.code === null ? '' : String(result.code) });
+        }).catch(function(err) {
+            lines.push(String(err));
+            write({ status: 'failed', 'exit-code': '' });
+        });
+    }
+
+    function setup() {
+        if (typeof $tw === 'undefined' || !$tw.wiki || !$tw.rootWidget || !window.__TAURI__) {
+            setTimeout(setup, 100);
+            return;
+        }
+        $tw.rootWidget.addEventListener('tm-tiddlydesktop-rs-run-command', function(event) {
+            if (event.param) runToTiddler(event.param, event.paramObject || null);
+            return false;
+        });
+    }
+
+    setup();
+})(window.TiddlyDesktop = window.TiddlyDesktop || {});
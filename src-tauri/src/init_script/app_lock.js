@@ -0,0 +1,22 @@
+// TiddlyDesktop Initialization Script - App Lock Module
+// Provides: activity reports for the app lock's inactivity timer
+(function() {
+    'use strict';
+
+    // Reported at most this often (Rust throttles disk writes as well)
+    var REPORT_INTERVAL = 15000;
+    var lastReport = 0;
+
+    function reportActivity() {
+        var now = Date.now();
+        if (now - lastReport < REPORT_INTERVAL) return;
+        lastReport = now;
+        if (window.__TAURI__ && window.__TAURI__.core && window.__TAURI__.core.invoke) {
+            window.__TAURI__.core.invoke('app_lock_activity').catch(function() {});
+        }
+    }
+
+    ['keydown', 'mousedown', 'mousemove', 'wheel', 'touchstart'].forEach(function(type) {
+        window.addEventListener(type, reportActivity, { capture: true, passive: true });
+    });
+})();
@@ -0,0 +1,202 @@
+// Attachment migration - move embedded (base64) attachments of the wiki to
+// external files, or embed small external files again. Started from the
+// External Attachments settings tab:
+//   tm-tiddlydesktop-rs-externalize-attachments  (param "dry-run" only reports)
+//   tm-tiddlydesktop-rs-inline-attachments       (param "dry-run" only reports)
+// Files are written and read by attachment_migration.rs; the report is written
+// to $:/temp/TiddlyDesktopRS/AttachmentMigration.
+(function(TD) {
+    'use strict';
+
+    // Only run in wiki windows of the desktop app
+    if (!window.__WIKI_PATH__ || window.__WINDOW_LABEL__ === 'main') return;
+    if (typeof window.TiddlyDesktopSync !== 'undefined') return; // Android
+
+    var CONFIG_PREFIX = '$:/config/TiddlyDesktopRS/ExternalAttachments/';
+    var CONFIG_EXTERNALIZE_MIN_SIZE = CONFIG_PREFIX + 'ExternalizeMinSize';
+    var CONFIG_INLINE_MAX_SIZE = CONFIG_PREFIX + 'InlineMaxSize';
+    var REPORT_TITLE = '$:/temp/TiddlyDesktopRS/AttachmentMigration';
+
+    var wikiPath = window.__WIKI_PATH__;
+    var isFolderWiki = !!window.__TD_FOLDER_WIKI__;
+    var running = false;
+
+    function invoke(cmd, args) {
+        return window.__TAURI__.core.invoke(cmd, args);
+    }
+
+    // Threshold in bytes from a setting in KB
+    function sizeSetting(title, defaultKb) {
+        var kb = parseFloat($tw.wiki.getTiddlerText(title, String(defaultKb)));
+        return (isNaN(kb) || kb < 0 ? defaultKb : kb) * 1024;
+    }
+
+    function formatSize(bytes) {
+        if (bytes >= 1024 * 1024) return (bytes / (1024 * 1024)).toFixed(1) + ' MB';
+        if (bytes >= 1024) return Math.round(bytes / 1024) + ' KB';
+        return bytes + ' B';
+    }
+
+    function contentTypeInfo(type) {
+        return $tw.config.contentTypeInfo[type] || null;
+    }
+
+    function isBase64Type(type) {
+        var info = contentTypeInfo(type);
+        return !!info && info.encoding === 'base64';
+    }
+
+    // Decoded size of base64 text
+    function decodedSize(text) {
+        var clean = text.replace(/\s/g, '');
+        var padding = (clean.match(/=*$/) || [''])[0].length;
+        return Math.max(0, Math.floor(clean.length * 3 / 4) - padding);
+    }
+
+    function isRemoteUri(uri) {
+        return /^(https?|data|blob|tdasset|file):/i.test(uri);
+    }
+
+    // Tiddlers (not system, not shadow) with embedded base64 content of at least minSize
+    function findEmbedded(minSize) {
+        var found = [];
+        $tw.wiki.each(function(tiddler, title) {
+            var fields = tiddler.fields;
+            if ($tw.wiki.isSystemTiddler(title) || fields._canonical_uri || !fields.text || !isBase64Type(fields.type)) return;
+            var size = decodedSize(fields.text);
+            if (size >= minSize) found.push({ title: title, size: size });
+        });
+        return found;
+    }
+
+    // Tiddlers with a local _canonical_uri whose file is at most maxSize
+    function findExternal(maxSize) {
+        var candidates = [];
+        $tw.wiki.each(function(tiddler, title) {
+            var fields = tiddler.fields;
+            if (!fields._canonical_uri || isRemoteUri(fields._canonical_uri) || !isBase64Type(fields.type)) return;
+            candidates.push({ title: title, uri: fields._canonical_uri });
+        });
+        if (candidates.length === 0) return Promise.resolve([]);
+        return invoke('get_attachment_sizes', {
+            wikiPath: wikiPath,
+            uris: candidates.map(function(c) { return c.uri; })
+        }).then(function(sizes) {
+            return candidates.filter(function(c) {
+                c.size = sizes[c.uri];
+                return typeof c.size === 'number' && c.size <= maxSize;
+            });
+        });
+    }
+
+    function canonicalUriFor(path) {
+        var useAbsDesc = $tw.wiki.getTiddlerText(CONFIG_PREFIX + 'UseAbsoluteForDescendents', 'no') === 'yes';
+        var useAbsNonDesc = $tw.wiki.getTiddlerText(CONFIG_PREFIX + 'UseAbsoluteForNonDescendents', 'no') === 'yes';
+        return TD.makePathRelative(path, isFolderWiki ? wikiPath + '/index.html' : wikiPath, {
+            useAbsoluteForDescendents: useAbsDesc,
+            useAbsoluteForNonDescendents: useAbsNonDesc
+        });
+    }
+
+    function externalize(item) {
+        var tiddler = $tw.wiki.getTiddler(item.title);
+        var info = contentTypeInfo(tiddler.fields.type);
+        return invoke('save_embedded_attachment', {
+            wikiPath: wikiPath,
+            title: item.title,
+            extension: (info && info.extension) || '',
+            dataBase64: tiddler.fields.text
+        }).then(function(path) {
+            item.uri = canonicalUriFor(path);
+            $tw.wiki.addTiddler(new $tw.Tiddler(tiddler, {
+                text: undefined,
+                _canonical_uri: item.uri
+            }, $tw.wiki.getModificationFields()));
+        });
+    }
+
+    function inline(item) {
+        return invoke('read_external_attachment', { wikiPath: wikiPath, uri: item.uri }).then(function(base64) {
+            var tiddler = $tw.wiki.getTiddler(item.title);
+            $tw.wiki.addTiddler(new $tw.Tiddler(tiddler, {
+                text: base64,
+                _canonical_uri: undefined
+            }, $tw.wiki.getModificationFields()));
+        });
+    }
+
+    // Apply an action to the items one after the other, recording failures
+    function processAll(items, action) {
+        return items.reduce(function(chain, item) {
+            return chain.then(function() {
+                return action(item).catch(function(err) {
+                    item.error = String(err);
+                });
+            });
+        }, Promise.resolve()).then(function() {
+            return items;
+        });
+    }
+
+    function writeReport(heading, items, dryRun, note) {
+        var total = 0;
+        var failed = 0;
+        var lines = ['!!! ' + heading, ''];
+        items.forEach(function(item) {
+            if (item.error) failed++;
+            else total += item.size;
+        });
+        if (items.length === 0) {
+            lines.push('No tiddlers match the size threshold.');
+        } else {
+            lines.push((dryRun ? 'Would move ' : 'Moved ') + (items.length - failed) + ' tiddler(s), ' + formatSize(total) +
+                (failed ? ' (' + failed + ' failed)' : '') + '.', '');
+            if (note) lines.push(note, '');
+            lines.push('|!Tiddler |!Size |!File |');
+            items.forEach(function(item) {
+                var status = item.error ? 'Failed: ' + item.error : (item.uri || '');
+                lines.push('|<$link to="""' + item.title + '"""/> |' + formatSize(item.size) + ' |<code>' +
+                    $tw.utils.htmlEncode(status) + '</code> |');
+            });
+        }
+        $tw.wiki.addTiddler(new $tw.Tiddler({ title: REPORT_TITLE, text: lines.join('\n') }));
+    }
+
+    function run(heading, find, action, dryRun, note) {
+        if (running) return;
+        running = true;
+        $tw.wiki.addTiddler(new $tw.Tiddler({ title: REPORT_TITLE, text: '//Working…//' }));
+        Promise.resolve().then(find).then(function(items) {
+            return dryRun ? items : processAll(items, action);
+        }).then(function(items) {
+            writeReport(heading, items, dryRun, note);
+        }).catch(function(err) {
+            $tw.wiki.addTiddler(new $tw.Tiddler({ title: REPORT_TITLE, text: '@@color:red;' + String(err) + '@@' }));
+        }).then(function() {
+            running = false;
+        });
+    }
+
+    function setup() {
+        if (typeof $tw === 'undefined' || !$tw.wiki || !$tw.rootWidget || !window.__TAURI__ || !TD.makePathRelative) {
+            setTimeout(setup, 100);
+            return;
+        }
+        $tw.rootWidget.addEventListener('tm-tiddlydesktop-rs-externalize-attachments', function(event) {
+            var dryRun = event.param === 'dry-run';
+            run(dryRun ? 'Externalize (dry run)' : 'Externalize', function() {
+                return findEmbedded(sizeSetting(CONFIG_EXTERNALIZE_MIN_SIZE, 16));
+            }, externalize, dryRun, 'Files are saved to the <code>files</code> folder next to the wiki.');
+            return false;
+        });
+        $tw.rootWidget.addEventListener('tm-tiddlydesktop-rs-inline-attachments', function(event) {
+            var dryRun = event.param === 'dry-run';
+            run(dryRun ? 'Embed (dry run)' : 'Embed', function() {
+                return findExternal(sizeSetting(CONFIG_INLINE_MAX_SIZE, 32));
+            }, inline, dryRun, 'The external files are not deleted.');
+            return false;
+        });
+    }
+
+    setup();
+})(window.TiddlyDesktop = window.TiddlyDesktop || {});
@@ -0,0 +1,26 @@
+// TiddlyDesktop Initialization Script - Audio Module
+// Provides: window.__tdSetVolume(volume) - called from Rust (set_window_volume) to
+// set the volume (0 to 1) of the window's audio and video. Webviews have no
+// volume setting of their own, so it is applied to every media element,
+// including ones that start playing later. Muting is done by the webview.
+(function() {
+    'use strict';
+
+    var volume = 1;
+
+    function applyVolume(el) {
+        if (el && (el.tagName === 'AUDIO' || el.tagName === 'VIDEO')) {
+            el.volume = volume;
+        }
+    }
+
+    window.__tdSetVolume = function(value) {
+        volume = value;
+        Array.prototype.forEach.call(document.querySelectorAll('audio, video'), applyVolume);
+    };
+
+    // Media events don't bubble, so listen in the capture phase
+    document.addEventListener('play', function(e) {
+        if (volume !== 1) applyVolume(e.target);
+    }, true);
+})();
@@ -0,0 +1,119 @@
+// Automation API - carries out the tiddler requests of the local HTTP API
+// (automation.rs) in the wiki's main window: listing tiddlers by filter,
+// reading, writing and deleting tiddlers, and saving and closing the wiki.
+// Every request is answered with automation_respond.
+(function(TD) {
+    'use strict';
+
+    // Only run in wiki windows of the desktop app
+    if (!window.__WIKI_PATH__ || window.__WINDOW_LABEL__ === 'main') return;
+    if (typeof window.TiddlyDesktopSync !== 'undefined') return; // Android
+    // Single tiddler windows share the wiki with its main window
+    if (window.__SINGLE_TIDDLER_TITLE__) return;
+
+    // How long closing waits for the save to finish (the API waits a bit longer)
+    var SAVE_TIMEOUT = 60000;
+    var SAVE_POLL_INTERVAL = 500;
+
+    function isDirty() {
+        if (typeof $tw.wiki.isDirty === 'function') return $tw.wiki.isDirty();
+        if ($tw.saverHandler && typeof $tw.saverHandler.isDirty === 'function') return $tw.saverHandler.isDirty();
+        if ($tw.saverHandler && typeof $tw.saverHandler.numChanges === 'function') return $tw.saverHandler.numChanges() > 0;
+        if ($tw.syncer && typeof $tw.syncer.isDirty === 'function') return $tw.syncer.isDirty();
+        return false;
+    }
+
+    function respond(requestId, result, error) {
+        return window.__TAURI__.core.invoke('automation_respond', {
+            requestId: requestId,
+            result: result === undefined ? null : result,
+            error: error || null
+        }).catch(function(err) {
+            console.error('[TiddlyDesktop] Failed to answer automation request:', err);
+        });
+    }
+
+    function requireTitle(params) {
+        if (!params || typeof params.title !== 'string' || !params.title) throw new Error('Missing tiddler title');
+        return params.title;
+    }
+
+    // Save like the window's close button would, then close without asking
+    function saveAndClose(requestId) {
+        // Folder wikis are saved by the syncer on its own; single-file wikis need a save
+        if (isDirty() && !$tw.syncer && $tw.rootWidget) {
+            $tw.rootWidget.dispatchEvent({ type: 'tm-save-wiki' });
+        }
+        var started = Date.now();
+        (function waitForSave() {
+            if (isDirty() && Date.now() - started < SAVE_TIMEOUT) {
+                setTimeout(waitForSave, SAVE_POLL_INTERVAL);
+                return;
+            }
+            if (isDirty()) {
+                // Never throw away unsaved changes
+                respond(requestId, null, 'The wiki could not be saved, so it was not closed');
+                return;
+            }
+            respond(requestId, { closed: true }).then(function() {
+                window.__TAURI__.core.invoke('close_window');
+            });
+        })();
+    }
+
+    function handle(request) {
+        var params = request.params || {};
+        switch (request.action) {
+            case 'filter':
+                // Variables like <query> of the MCP server's search filter
+                var widget = params.variables && $tw.rootWidget.makeFakeWidgetWithVariables
+                    ? $tw.rootWidget.makeFakeWidgetWithVariables(params.variables)
+                    : undefined;
+                return $tw.wiki.filterTiddlers(params.filter || '', widget);
+            case 'get':
+                var tiddler = $tw.wiki.getTiddler(requireTitle(params));
+                return tiddler ? tiddler.getFieldStrings() : null;
+            case 'put':
+                var title = requireTitle(params);
+                $tw.wiki.addTiddler(new $tw.Tiddler(
+                    $tw.wiki.getCreationFields(),
+                    params.fields || {},
+                    { title: title },
+                    $tw.wiki.getModificationFields()
+                ));
+                return { title: title };
+            case 'delete':
+                var existing = requireTitle(params);
+                if (!$tw.wiki.tiddlerExists(existing)) throw new Error('Tiddler not found: ' + existing);
+                $tw.wiki.deleteTiddler(existing);
+                return { title: existing };
+            default:
+                throw new Error('Unknown action: ' + request.action);
+        }
+    }
+
+    function setup() {
+        if (typeof $tw === 'undefined' || !$tw.wiki || !$tw.rootWidget || !window.__TAURI__) {
+            setTimeout(setup, 100);
+            return;
+        }
+        window.__TAURI__.event.listen('automation-request', function(event) {
+            var request = event.payload || {};
+            if (typeof request.requestId !== 'number') return;
+            if (request.action === 'close') {
+                saveAndClose(request.requestId);
+                return;
+            }
+            var result;
+            try {
+                result = handle(request);
+            } catch (e) {
+                respond(request.requestId, null, (e && e.message) || String(e));
+                return;
+            }
+            respond(request.requestId, result);
+        });
+    }
+
+    setup();
+})(window.TiddlyDesktop = window.TiddlyDesktop || {});
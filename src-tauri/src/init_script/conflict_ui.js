@@ -426,6 +426,28 @@
     // --- Resolution ---
 
     function resolveConflict(conflictTitle, action) {
+        try {
+            var conflict = $tw.wiki.getTiddler(conflictTitle);
+            if (!conflict) return;
+
+            // Conflicts kept in the main process are resolved there: it saves
+            // and syncs the version to keep and deletes the conflict tiddler
+            var conflictId = conflict.fields['conflict-id'];
+            var td = window.TiddlyDesktop;
+            if (conflictId && td && td.resolveSyncConflict) {
+                td.resolveSyncConflict(conflictId, action).catch(function(e) {
+                    console.error('[TiddlyDesktop] resolveSyncConflict error:', e);
+                    resolveConflictLocally(conflictTitle, action);
+                });
+                return;
+            }
+        } catch (e) {
+            console.error('[TiddlyDesktop] resolveConflict error:', e);
+        }
+        resolveConflictLocally(conflictTitle, action);
+    }
+
+    function resolveConflictLocally(conflictTitle, action) {
         try {
             var conflict = $tw.wiki.getTiddler(conflictTitle);
             if (!conflict) return;
@@ -440,14 +462,16 @@
                         title: originalTitle,
                         'conflict-original-title': undefined,
                         'conflict-timestamp': undefined,
-                        'conflict-source': undefined
+                        'conflict-source': undefined,
+                        'conflict-id': undefined,
+                        'conflict-device': undefined
                     }));
                 }
             }
             // For 'remote': the remote version is already in place, just delete the conflict
             $tw.wiki.deleteTiddler(conflictTitle);
         } catch (e) {
-            console.error('[TiddlyDesktop] resolveConflict error:', e);
+            console.error('[TiddlyDesktop] resolveConflictLocally error:', e);
         }
     }
 
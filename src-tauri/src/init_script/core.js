@@ -291,10 +291,16 @@
         initPaletteSync();
     }
 
-    // Ctrl/Cmd+0 to reset zoom to 100%
+    // Ctrl/Cmd+0 (or the rebound "zoom-reset" accelerator) to reset zoom to 100%
     // (Ctrl/Cmd+Plus/Minus and Ctrl+mousewheel are handled by Tauri's built-in zoom_hotkeys_enabled)
+    function isZoomReset(e) {
+        if (typeof TD.matchesAccelerator === 'function') {
+            return TD.matchesAccelerator('zoom-reset', e);
+        }
+        return (e.ctrlKey || e.metaKey) && !e.shiftKey && !e.altKey && (e.key === '0' || e.code === 'Digit0');
+    }
     document.addEventListener('keydown', function(e) {
-        if ((e.ctrlKey || e.metaKey) && !e.shiftKey && !e.altKey && (e.key === '0' || e.code === 'Digit0')) {
+        if (isZoomReset(e)) {
             e.preventDefault();
             e.stopPropagation();
             if (window.__TAURI__ && window.__TAURI__.core) {
@@ -303,6 +309,34 @@
         }
     }, true);
 
+    // Ctrl/Cmd+Shift+T (or the rebound "reopen-closed-wiki" accelerator) reopens the last closed wiki.
+    // The landing page asks directly; wiki windows ask the main process over IPC
+    document.addEventListener('keydown', function(e) {
+        if (typeof TD.matchesAccelerator !== 'function' || !TD.matchesAccelerator('reopen-closed-wiki', e)) return;
+        e.preventDefault();
+        e.stopPropagation();
+        if (window.__TAURI__ && window.__TAURI__.core) {
+            var command = window.__IS_MAIN_WIKI__ ? 'reopen_last_closed_wiki' : 'ipc_reopen_closed_wiki';
+            window.__TAURI__.core.invoke(command).catch(function(err) {
+                console.error('[TiddlyDesktop] Failed to reopen closed wiki:', err);
+            });
+        }
+    }, true);
+
+    // Ctrl/Cmd+K (or the rebound "quick-switcher" accelerator) shows the quick switcher,
+    // which belongs to the main process like the reopen shortcut above
+    document.addEventListener('keydown', function(e) {
+        if (typeof TD.matchesAccelerator !== 'function' || !TD.matchesAccelerator('quick-switcher', e)) return;
+        e.preventDefault();
+        e.stopPropagation();
+        if (window.__TAURI__ && window.__TAURI__.core) {
+            var command = window.__IS_MAIN_WIKI__ ? 'show_quick_switcher' : 'ipc_show_quick_switcher';
+            window.__TAURI__.core.invoke(command).catch(function(err) {
+                console.error('[TiddlyDesktop] Failed to show quick switcher:', err);
+            });
+        }
+    }, true);
+
     // Export to TD namespace
     TD.showConfirmModal = showConfirmModal;
     TD.getColour = getColour;
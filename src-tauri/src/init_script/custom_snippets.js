@@ -0,0 +1,43 @@
+// Custom snippets - the CSS and JS the user attached to this wiki on the
+// landing page (custom_snippets.rs), passed in as window.__TD_CUSTOM_SNIPPETS__.
+// CSS is added as <style> elements as soon as the document has a head; JS runs
+// once the wiki has loaded, each snippet on its own so a failing one doesn't
+// stop the others.
+(function() {
+    'use strict';
+    if (window.__IS_MAIN_WIKI__) return;
+    var snippets = window.__TD_CUSTOM_SNIPPETS__;
+    if (!snippets || !snippets.length) return;
+
+    function addStyles() {
+        snippets.forEach(function(snippet) {
+            if (snippet.kind !== 'css') return;
+            var style = document.createElement('style');
+            style.setAttribute('data-td-snippet', snippet.name);
+            style.textContent = snippet.code;
+            (document.head || document.documentElement).appendChild(style);
+        });
+    }
+
+    function runScripts() {
+        snippets.forEach(function(snippet) {
+            if (snippet.kind !== 'js') return;
+            try {
+                new Function(snippet.code)();
+            } catch (e) {
+                window.__tdInitErr('custom snippet "' + snippet.name + '"', e);
+            }
+        });
+    }
+
+    if (document.readyState === 'loading') {
+        document.addEventListener('DOMContentLoaded', addStyles);
+    } else {
+        addStyles();
+    }
+    if (document.readyState === 'complete') {
+        runScripts();
+    } else {
+        window.addEventListener('load', runScripts);
+    }
+})();
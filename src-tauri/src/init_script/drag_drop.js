@@ -1287,7 +1287,12 @@
                 }
             }
 
-            fileInfos.forEach(function(info) {
+            // Images and videos are converted first when the wiki's settings ask for it
+            processImportedFiles(fileInfos, !externalEnabled).then(function(infos) {
+                infos.forEach(importFile);
+            });
+
+            function importFile(info) {
                 var isNative = isTwNativeFile(info.filename);
 
                 if (isNative) {
@@ -1304,6 +1309,10 @@
                         console.error("[TiddlyDesktop] Failed to read TW-native file:", info.filename, err);
                         done();
                     });
+                } else if (info.text !== undefined) {
+                    // Image processed for embedding
+                    allTiddlers.push({ title: info.filename, type: info.mimeType, text: info.text });
+                    done();
                 } else if (externalEnabled && info.path) {
                     // Non-native file with external attachments enabled: create _canonical_uri tiddler
                     // makePathRelative treats the last component of rootpath as a filename.
@@ -1353,7 +1362,7 @@
                         done();
                     });
                 }
-            });
+            }
         }
 
         function processGtkFileDrop() {
@@ -1906,13 +1915,21 @@
         // Keyboard and Focus Handlers
         // ========================================
 
+        // Ctrl/Cmd+F unless the wiki's accelerator map rebinds or disables "find"
+        function isFindAccelerator(event) {
+            if (typeof TD.matchesAccelerator === "function") {
+                return TD.matchesAccelerator("find", event);
+            }
+            return (event.key === "f" || event.key === "F") && (event.ctrlKey || event.metaKey);
+        }
+
         document.addEventListener("keydown", function(event) {
             if (event.key === "Escape") {
                 if (isDragging) cancelExternalDrag("escape pressed");
                 else if (contentDragActive) cancelContentDrag("escape pressed");
             }
 
-            if ((event.key === "f" || event.key === "F") && (event.ctrlKey || event.metaKey)) {
+            if (isFindAccelerator(event)) {
                 if (window.__IS_MAIN_WIKI__) {
                     event.preventDefault();
                     event.stopPropagation();
@@ -1921,7 +1938,7 @@
         }, true);
 
         document.addEventListener("keydown", function(event) {
-            if ((event.key === "f" || event.key === "F") && (event.ctrlKey || event.metaKey)) {
+            if (isFindAccelerator(event)) {
                 if (window.__IS_MAIN_WIKI__) return;
                 if (event.defaultPrevented) return;
 
@@ -2264,8 +2281,127 @@
         var CONFIG_ENABLE = CONFIG_PREFIX + "Enable";
         var CONFIG_ABS_DESC = CONFIG_PREFIX + "UseAbsoluteForDescendents";
         var CONFIG_ABS_NONDESC = CONFIG_PREFIX + "UseAbsoluteForNonDescendents";
+        var CONFIG_CONVERT_IMAGES = CONFIG_PREFIX + "ConvertImages";
+        var CONFIG_MAX_IMAGE_DIMENSION = CONFIG_PREFIX + "MaxImageDimension";
+        var CONFIG_STRIP_IMAGE_LOCATION = CONFIG_PREFIX + "StripImageLocation";
+        var CONFIG_TRANSCODE_VIDEOS = CONFIG_PREFIX + "TranscodeVideos";
         var CONFIG_SETTINGS_TAB = CONFIG_PREFIX + "settings";
 
+        // Image settings: HEIC/HEIF and WebP conversion, downscaling and removal of
+        // the GPS location, applied by process_imported_image (image_import.rs)
+        var IMAGE_EXTS = /\.(heic|heif|webp|jpe?g|png)$/i;
+
+        function getMaxImageDimension() {
+            return parseInt($tw.wiki.getTiddlerText(CONFIG_MAX_IMAGE_DIMENSION, "0"), 10) || 0;
+        }
+
+        function isImageProcessingEnabled() {
+            return $tw.wiki.getTiddlerText(CONFIG_CONVERT_IMAGES, "no") === "yes" ||
+                $tw.wiki.getTiddlerText(CONFIG_STRIP_IMAGE_LOCATION, "no") === "yes" ||
+                getMaxImageDimension() > 0;
+        }
+
+        // Resolves to the processed image ({filename, mimeType, path} saved to the
+        // wiki's files folder, or {filename, mimeType, text} for embedding), or to
+        // null if the file is imported as it is
+        function processImportedImage(path, filename, embed) {
+            if (!path || !IMAGE_EXTS.test(filename) || !isImageProcessingEnabled()) {
+                return Promise.resolve(null);
+            }
+            return invoke("process_imported_image", { wikiPath: wikiPath, path: path, embed: embed }).catch(function(err) {
+                console.error("[TiddlyDesktop] Failed to process image, importing it as is:", filename, err);
+                return null;
+            });
+        }
+
+        // Video setting: videos stored as external attachments are transcoded to
+        // H.264/AAC MP4 by transcode_video (video_import.rs), with a progress bar
+        var VIDEO_EXTS = /\.(mp4|m4v|mov|mkv|webm|avi|wmv|flv|3gp|mpe?g|mts|m2ts|ts|ogv)$/i;
+        var transcodeCount = 0;
+
+        function showTranscodeProgress(id, filename) {
+            var bar = document.createElement("div");
+            bar.className = "td-transcode-progress";
+            bar.style.cssText = "position:fixed;right:16px;bottom:16px;z-index:9999;" +
+                "background:#e7eefc;color:#1f3a7a;border:1px solid #5778d8;border-radius:6px;" +
+                "padding:8px 12px;font-size:13px;font-family:system-ui,sans-serif;" +
+                "display:flex;align-items:center;gap:8px;box-shadow:0 2px 4px rgba(0,0,0,0.1);";
+            var label = document.createElement("span");
+            label.textContent = "🎞 Converting " + filename + " for playback…";
+            var percent = document.createElement("span");
+            percent.style.cssText = "min-width:3em;text-align:right;font-variant-numeric:tabular-nums;";
+            var cancel = document.createElement("button");
+            cancel.textContent = "Cancel";
+            cancel.title = "Import the video without converting it";
+            cancel.style.cssText = "padding:2px 10px;border:1px solid #5778d8;border-radius:4px;" +
+                "background:transparent;color:#1f3a7a;cursor:pointer;font-size:12px;";
+            cancel.onclick = function() {
+                cancel.disabled = true;
+                invoke("cancel_video_transcode", { id: id }).catch(function() {});
+            };
+            bar.appendChild(label);
+            bar.appendChild(percent);
+            bar.appendChild(cancel);
+            document.body.appendChild(bar);
+            return {
+                update: function(value) { percent.textContent = value + "%"; },
+                remove: function() { if (bar.parentNode) bar.parentNode.removeChild(bar); }
+            };
+        }
+
+        // Resolves to the transcoded video ({filename, mimeType, path}), or to null
+        // if the video is imported as it is (already playable, cancelled or failed)
+        function transcodeImportedVideo(path, filename) {
+            if ($tw.wiki.getTiddlerText(CONFIG_TRANSCODE_VIDEOS, "no") !== "yes") {
+                return Promise.resolve(null);
+            }
+            var id = windowLabel + "-" + (++transcodeCount);
+            var progress = null;
+            // Only show the progress bar once ffmpeg is actually transcoding
+            var unlistenPromise = listen("video-transcode-progress", function(event) {
+                if (!event.payload || event.payload.id !== id) return;
+                if (!progress) progress = showTranscodeProgress(id, filename);
+                progress.update(event.payload.percent);
+            });
+            function cleanup() {
+                if (progress) progress.remove();
+                unlistenPromise.then(function(unlisten) { unlisten(); }).catch(function() {});
+            }
+            return invoke("transcode_video", { wikiPath: wikiPath, path: path, id: id }).then(function(video) {
+                cleanup();
+                return video;
+            }).catch(function(err) {
+                cleanup();
+                console.error("[TiddlyDesktop] Failed to convert video, importing it as is:", filename, err);
+                return null;
+            });
+        }
+
+        // Images are processed for embedding and external attachments, videos only
+        // for external attachments
+        function processImportedFile(path, filename, embed) {
+            if (path && !embed && VIDEO_EXTS.test(filename)) {
+                return transcodeImportedVideo(path, filename);
+            }
+            return processImportedImage(path, filename, embed);
+        }
+
+        // Process the images and videos among fileInfos ([{path, filename, mimeType}, ...]).
+        // Embedded images get their base64 content in info.text.
+        function processImportedFiles(fileInfos, embed) {
+            return Promise.all(fileInfos.map(function(info) {
+                return processImportedFile(info.path, info.filename, embed).then(function(processed) {
+                    if (!processed) return info;
+                    return {
+                        path: processed.path || info.path,
+                        filename: processed.filename,
+                        mimeType: processed.mimeType,
+                        text: processed.text === null || processed.text === undefined ? undefined : processed.text
+                    };
+                });
+            }));
+        }
+
         // Use shared plugin tiddlers from session_auth.js (or initialize if not present)
         TD.pluginTiddlers = TD.pluginTiddlers || {};
 
@@ -2368,20 +2504,23 @@
                         // makePathRelative treats the last component of rootpath as a filename.
                         // For folder wikis, append a dummy filename so the folder is the base.
                         var rootForRelative = isFolderWiki ? wikiPath + '/index.html' : wikiPath;
-                        var canonicalUri = makePathRelative(originalPath, rootForRelative, {
-                            useAbsoluteForDescendents: useAbsDesc,
-                            useAbsoluteForNonDescendents: useAbsNonDesc
-                        });
 
                         delete window.__pendingExternalFiles[filename];
 
-                        console.log("[TiddlyDesktop] Creating external attachment for '" + filename + "' -> " + canonicalUri);
+                        processImportedFile(originalPath, filename, false).then(function(processed) {
+                            var canonicalUri = makePathRelative(processed ? processed.path : originalPath, rootForRelative, {
+                                useAbsoluteForDescendents: useAbsDesc,
+                                useAbsoluteForNonDescendents: useAbsNonDesc
+                            });
 
-                        info.callback([{
-                            title: filename,
-                            type: info.type,
-                            "_canonical_uri": canonicalUri
-                        }]);
+                            console.log("[TiddlyDesktop] Creating external attachment for '" + filename + "' -> " + canonicalUri);
+
+                            info.callback([{
+                                title: processed ? processed.filename : filename,
+                                type: processed ? processed.mimeType : info.type,
+                                "_canonical_uri": canonicalUri
+                            }]);
+                        });
 
                         return true;
                     }
@@ -2394,31 +2533,38 @@
                 // Using read_file_as_binary bypasses the sandbox limitation.
                 if (originalPath && !externalEnabled) {
                     invoke("js_log", { message: "th-importing-file: embedding '" + filename + "' via Tauri IPC (external attachments disabled)" });
-                    invoke("read_file_as_binary", { path: originalPath }).then(function(bytes) {
-                        var uint8 = new Uint8Array(bytes);
-                        var isBinary;
-                        if (type.indexOf('text/') === 0) {
-                            isBinary = false;
-                        } else {
-                            var cti = $tw.config.contentTypeInfo && $tw.config.contentTypeInfo[type];
-                            isBinary = cti ? cti.encoding === "base64" : true;
+                    processImportedImage(originalPath, filename, true).then(function(image) {
+                        if (image) {
+                            delete window.__pendingExternalFiles[filename];
+                            info.callback([{ title: image.filename, type: image.mimeType, text: image.text }]);
+                            return;
                         }
-                        var tiddler = { title: filename, type: type };
-                        if (isBinary) {
-                            var binary = '';
-                            for (var i = 0; i < uint8.length; i++) {
-                                binary += String.fromCharCode(uint8[i]);
+                        invoke("read_file_as_binary", { path: originalPath }).then(function(bytes) {
+                            var uint8 = new Uint8Array(bytes);
+                            var isBinary;
+                            if (type.indexOf('text/') === 0) {
+                                isBinary = false;
+                            } else {
+                                var cti = $tw.config.contentTypeInfo && $tw.config.contentTypeInfo[type];
+                                isBinary = cti ? cti.encoding === "base64" : true;
                             }
-                            tiddler.text = btoa(binary);
-                        } else {
-                            tiddler.text = new TextDecoder('utf-8').decode(uint8);
-                        }
-                        delete window.__pendingExternalFiles[filename];
-                        info.callback([tiddler]);
-                    }).catch(function(err) {
-                        console.error("[TiddlyDesktop] Failed to embed file:", filename, err);
-                        delete window.__pendingExternalFiles[filename];
-                        info.callback(null);
+                            var tiddler = { title: filename, type: type };
+                            if (isBinary) {
+                                var binary = '';
+                                for (var i = 0; i < uint8.length; i++) {
+                                    binary += String.fromCharCode(uint8[i]);
+                                }
+                                tiddler.text = btoa(binary);
+                            } else {
+                                tiddler.text = new TextDecoder('utf-8').decode(uint8);
+                            }
+                            delete window.__pendingExternalFiles[filename];
+                            info.callback([tiddler]);
+                        }).catch(function(err) {
+                            console.error("[TiddlyDesktop] Failed to embed file:", filename, err);
+                            delete window.__pendingExternalFiles[filename];
+                            info.callback(null);
+                        });
                     });
                     return true;
                 }
@@ -2475,7 +2621,11 @@
             var config = {
                 enabled: $tw.wiki.getTiddlerText(CONFIG_ENABLE, "yes") === "yes",
                 use_absolute_for_descendents: $tw.wiki.getTiddlerText(CONFIG_ABS_DESC, "no") === "yes",
-                use_absolute_for_non_descendents: $tw.wiki.getTiddlerText(CONFIG_ABS_NONDESC, "no") === "yes"
+                use_absolute_for_non_descendents: $tw.wiki.getTiddlerText(CONFIG_ABS_NONDESC, "no") === "yes",
+                convert_images: $tw.wiki.getTiddlerText(CONFIG_CONVERT_IMAGES, "no") === "yes",
+                max_image_dimension: getMaxImageDimension(),
+                strip_image_location: $tw.wiki.getTiddlerText(CONFIG_STRIP_IMAGE_LOCATION, "no") === "yes",
+                transcode_videos: $tw.wiki.getTiddlerText(CONFIG_TRANSCODE_VIDEOS, "no") === "yes"
             };
 
             invoke("set_external_attachments_config", { wikiPath: wikiPath, config: config })
@@ -2524,6 +2674,22 @@
                 title: CONFIG_ABS_NONDESC,
                 text: config.use_absolute_for_non_descendents ? "yes" : "no"
             });
+            addPluginTiddler({
+                title: CONFIG_CONVERT_IMAGES,
+                text: config.convert_images ? "yes" : "no"
+            });
+            addPluginTiddler({
+                title: CONFIG_MAX_IMAGE_DIMENSION,
+                text: String(config.max_image_dimension || 0)
+            });
+            addPluginTiddler({
+                title: CONFIG_STRIP_IMAGE_LOCATION,
+                text: config.strip_image_location ? "yes" : "no"
+            });
+            addPluginTiddler({
+                title: CONFIG_TRANSCODE_VIDEOS,
+                text: config.transcode_videos ? "yes" : "no"
+            });
 
             addPluginTiddler({
                 title: CONFIG_SETTINGS_TAB,
@@ -2533,7 +2699,21 @@
                       "This keeps your wiki file smaller and allows the files to be edited externally.\n\n" +
                       "<$checkbox tiddler=\"" + CONFIG_ENABLE + "\" field=\"text\" checked=\"yes\" unchecked=\"no\" default=\"yes\"> Enable external attachments</$checkbox>\n\n" +
                       "<$checkbox tiddler=\"" + CONFIG_ABS_DESC + "\" field=\"text\" checked=\"yes\" unchecked=\"no\" default=\"no\"> Use absolute paths for files inside wiki folder</$checkbox>\n\n" +
-                      "<$checkbox tiddler=\"" + CONFIG_ABS_NONDESC + "\" field=\"text\" checked=\"yes\" unchecked=\"no\" default=\"no\"> Use absolute paths for files outside wiki folder</$checkbox>"
+                      "<$checkbox tiddler=\"" + CONFIG_ABS_NONDESC + "\" field=\"text\" checked=\"yes\" unchecked=\"no\" default=\"no\"> Use absolute paths for files outside wiki folder</$checkbox>\n\n" +
+                      "!! Images\n\n" +
+                      "Processed images are saved to the <code>files</code> folder next to the wiki (or embedded); the original files are not changed.\n\n" +
+                      "<$checkbox tiddler=\"" + CONFIG_CONVERT_IMAGES + "\" field=\"text\" checked=\"yes\" unchecked=\"no\" default=\"no\"> Convert HEIC and WebP images to JPEG (PNG with transparency)</$checkbox>\n\n" +
+                      "<$checkbox tiddler=\"" + CONFIG_STRIP_IMAGE_LOCATION + "\" field=\"text\" checked=\"yes\" unchecked=\"no\" default=\"no\"> Remove the GPS location from photos</$checkbox>\n\n" +
+                      "Downscale images larger than <$edit-text tiddler=\"" + CONFIG_MAX_IMAGE_DIMENSION + "\" tag=\"input\" type=\"number\" default=\"0\" size=\"6\"/> pixels (0 = keep size)\n\n" +
+                      "!! Videos\n\n" +
+                      "<$checkbox tiddler=\"" + CONFIG_TRANSCODE_VIDEOS + "\" field=\"text\" checked=\"yes\" unchecked=\"no\" default=\"no\"> Convert videos to MP4 (H.264/AAC) so they play on every platform</$checkbox>\n\n" +
+                      "Needs [[ffmpeg|https://ffmpeg.org]]. Converted videos are saved to the <code>files</code> folder next to the wiki; videos that already play everywhere are not converted.\n\n" +
+                      "!! Migrate existing attachments\n\n" +
+                      "Move embedded images, audio and other binary tiddlers of at least <$edit-text tiddler=\"" + CONFIG_PREFIX + "ExternalizeMinSize\" tag=\"input\" type=\"number\" default=\"16\" size=\"6\"/> KB to the <code>files</code> folder:\n\n" +
+                      "<$button message=\"tm-tiddlydesktop-rs-externalize-attachments\" param=\"dry-run\">Dry run</$button> <$button message=\"tm-tiddlydesktop-rs-externalize-attachments\">Externalize</$button>\n\n" +
+                      "Embed external files of at most <$edit-text tiddler=\"" + CONFIG_PREFIX + "InlineMaxSize\" tag=\"input\" type=\"number\" default=\"32\" size=\"6\"/> KB into the wiki:\n\n" +
+                      "<$button message=\"tm-tiddlydesktop-rs-inline-attachments\" param=\"dry-run\">Dry run</$button> <$button message=\"tm-tiddlydesktop-rs-inline-attachments\">Embed</$button>\n\n" +
+                      "<$transclude tiddler=\"$:/temp/TiddlyDesktopRS/AttachmentMigration\" mode=\"block\"/>"
             });
 
             // Register plugin (dirty state guard is inside registerPlugin)
@@ -2546,7 +2726,9 @@
             saveConfigToTauri();
 
             $tw.wiki.addEventListener("change", function(changes) {
-                if (changes[CONFIG_ENABLE] || changes[CONFIG_ABS_DESC] || changes[CONFIG_ABS_NONDESC]) {
+                if (changes[CONFIG_ENABLE] || changes[CONFIG_ABS_DESC] || changes[CONFIG_ABS_NONDESC] ||
+                        changes[CONFIG_CONVERT_IMAGES] || changes[CONFIG_MAX_IMAGE_DIMENSION] || changes[CONFIG_STRIP_IMAGE_LOCATION] ||
+                        changes[CONFIG_TRANSCODE_VIDEOS]) {
                     saveConfigToTauri();
                 }
             });
@@ -2574,7 +2756,8 @@
             })
             .catch(function(err) {
                 console.error("[TiddlyDesktop] Failed to load config, using defaults:", err);
-                injectConfigTiddlers({ enabled: true, use_absolute_for_descendents: false, use_absolute_for_non_descendents: false });
+                injectConfigTiddlers({ enabled: true, use_absolute_for_descendents: false, use_absolute_for_non_descendents: false,
+                    convert_images: false, max_image_dimension: 0, strip_image_location: false, transcode_videos: false });
             });
 
         installImportHook();
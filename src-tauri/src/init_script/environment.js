@@ -0,0 +1,36 @@
+// Environment - what this wiki runs on (get_environment in environment.rs),
+// for plugins that adapt to TiddlyDesktop-RS:
+// - $:/temp/TiddlyDesktopRS/Environment: JSON data tiddler, e.g.
+//   [[$:/temp/TiddlyDesktopRS/Environment]jsonget[features],[ffmpeg]]
+// - TiddlyDesktop.getEnvironment() returns a promise of the same object
+(function(TD) {
+    'use strict';
+
+    // Only run in wiki windows of the desktop app
+    if (!window.__WIKI_PATH__ || window.__WINDOW_LABEL__ === 'main') return;
+    if (typeof window.TiddlyDesktopSync !== 'undefined') return; // Android
+
+    var ENVIRONMENT_TIDDLER = '$:/temp/TiddlyDesktopRS/Environment';
+
+    TD.getEnvironment = function() {
+        return window.__TAURI__.core.invoke('get_environment');
+    };
+
+    function setup() {
+        if (typeof $tw === 'undefined' || !$tw.wiki || !window.__TAURI__) {
+            setTimeout(setup, 100);
+            return;
+        }
+        TD.getEnvironment().then(function(environment) {
+            $tw.wiki.addTiddler(new $tw.Tiddler({
+                title: ENVIRONMENT_TIDDLER,
+                type: 'application/json',
+                text: JSON.stringify(environment, null, 2)
+            }));
+        }).catch(function(err) {
+            console.error('[TiddlyDesktop] Failed to get the environment:', err);
+        });
+    }
+
+    setup();
+})(window.TiddlyDesktop = window.TiddlyDesktop || {});
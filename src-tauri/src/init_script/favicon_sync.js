@@ -16,10 +16,11 @@
 
         // Send to Rust to update the wiki list entry and window icon
         if (window.__TAURI__ && window.__TAURI__.core) {
-            // Update window icon (titlebar/taskbar)
+            // Update window icon (titlebar/taskbar), unless the wiki has a custom icon
             window.__TAURI__.core.invoke('set_window_icon', {
                 label: window.__WINDOW_LABEL__,
-                faviconDataUri: dataUri
+                faviconDataUri: dataUri,
+                path: wikiPath
             }).catch(function(err) {
                 console.error('TiddlyDesktop: Failed to set window icon:', err);
             });
@@ -78,6 +79,15 @@
             return;
         }
 
+        // Apply a custom icon assigned to a wiki that has no favicon
+        if (!$tw.wiki.getTiddler('$:/favicon.ico') && window.__TAURI__ && window.__TAURI__.core) {
+            window.__TAURI__.core.invoke('set_window_icon', {
+                label: window.__WINDOW_LABEL__,
+                faviconDataUri: null,
+                path: wikiPath
+            }).catch(function() {});
+        }
+
         // Initial extraction
         extractAndUpdateFavicon();
 
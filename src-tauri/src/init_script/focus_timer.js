@@ -0,0 +1,162 @@
+// Focus timer - the app-wide pomodoro timer of focus_timer.rs:
+// - a small badge with the phase and the time left (hidden when
+//   $:/config/TiddlyDesktopRS/FocusTimerBadge is "hide"; click to stop)
+// - $:/temp/TiddlyDesktopRS/FocusTimer: text is the phase ("focus",
+//   "shortBreak", "longBreak" or "stopped"), completed-sessions field
+// - tm-tiddlydesktop-rs-start-focus-timer (params: focus, break, long-break,
+//   sessions; all optional) and tm-tiddlydesktop-rs-stop-focus-timer
+// - TiddlyDesktop.startFocusTimer(options), stopFocusTimer(),
+//   getFocusTimerStatus()
+(function(TD) {
+    'use strict';
+
+    // Only run in wiki windows of the desktop app
+    if (!window.__WIKI_PATH__ || window.__WINDOW_LABEL__ === 'main') return;
+    if (typeof window.TiddlyDesktopSync !== 'undefined') return; // Android
+
+    var STATE_TIDDLER = '$:/temp/TiddlyDesktopRS/FocusTimer';
+    var BADGE_CONFIG = '$:/config/TiddlyDesktopRS/FocusTimerBadge';
+    var STYLE_ID = 'td-focus-timer-styles';
+    var PHASE_LABELS = { focus: 'Focus', shortBreak: 'Break', longBreak: 'Long break' };
+
+    var status = null;
+    // Date.now() when the current phase ends
+    var phaseEnd = 0;
+    var badge = null;
+    var ticker = null;
+
+    function invoke(cmd, args) {
+        return window.__TAURI__.core.invoke(cmd, args);
+    }
+
+    function toNumber(value) {
+        var number = parseInt(value, 10);
+        return isNaN(number) ? null : number;
+    }
+
+    TD.startFocusTimer = function(options) {
+        options = options || {};
+        return invoke('start_focus_timer', {
+            focusMinutes: toNumber(options.focusMinutes),
+            shortBreakMinutes: toNumber(options.shortBreakMinutes),
+            longBreakMinutes: toNumber(options.longBreakMinutes),
+            sessionsBeforeLongBreak: toNumber(options.sessionsBeforeLongBreak)
+        }).then(function(newStatus) {
+            applyStatus(newStatus);
+            return newStatus;
+        });
+    };
+
+    TD.stopFocusTimer = function() {
+        return invoke('stop_focus_timer').then(function() {
+            applyStatus({ running: false, phase: null, remainingSeconds: 0, completedSessions: 0 });
+        });
+    };
+
+    TD.getFocusTimerStatus = function() {
+        return invoke('get_focus_timer_status');
+    };
+
+    function addStyles() {
+        if (document.getElementById(STYLE_ID)) return;
+        var style = document.createElement('style');
+        style.id = STYLE_ID;
+        style.textContent =
+            '.td-focus-timer{position:fixed;right:12px;bottom:12px;z-index:9999;padding:3px 8px;border-radius:10px;' +
+            'background:rgba(0,0,0,.55);color:#fff;font:12px sans-serif;cursor:pointer;opacity:.75;user-select:none;}' +
+            '.td-focus-timer:hover{opacity:1;}' +
+            '.td-focus-timer-break{background:rgba(40,120,60,.7);}' +
+            '@media print{.td-focus-timer{display:none;}}';
+        document.head.appendChild(style);
+    }
+
+    function formatTime(seconds) {
+        var minutes = Math.floor(seconds / 60);
+        var rest = seconds % 60;
+        return minutes + ':' + (rest < 10 ? '0' : '') + rest;
+    }
+
+    function renderBadge() {
+        var show = status && status.running && $tw.wiki.getTiddlerText(BADGE_CONFIG, 'show') !== 'hide';
+        if (!show) {
+            if (badge) {
+                badge.remove();
+                badge = null;
+            }
+            return;
+        }
+        if (!badge) {
+            addStyles();
+            badge = document.createElement('div');
+            badge.className = 'td-focus-timer';
+            badge.title = 'Focus timer (click to stop)';
+            badge.addEventListener('click', function() {
+                TD.showConfirmModal('Stop the focus timer?', function(confirmed) {
+                    if (confirmed) TD.stopFocusTimer();
+                });
+            });
+            document.body.appendChild(badge);
+        }
+        var remaining = Math.max(0, Math.ceil((phaseEnd - Date.now()) / 1000));
+        badge.classList.toggle('td-focus-timer-break', status.phase !== 'focus');
+        badge.textContent = (PHASE_LABELS[status.phase] || '') + ' ' + formatTime(remaining);
+    }
+
+    function applyStatus(newStatus) {
+        status = newStatus;
+        phaseEnd = Date.now() + (status.remainingSeconds || 0) * 1000;
+        var phase = status.running ? status.phase : 'stopped';
+        var tiddler = $tw.wiki.getTiddler(STATE_TIDDLER);
+        if (!tiddler || tiddler.fields.text !== phase ||
+            tiddler.fields['completed-sessions'] !== String(status.completedSessions || 0)) {
+            $tw.wiki.addTiddler(new $tw.Tiddler({
+                title: STATE_TIDDLER,
+                text: phase,
+                'completed-sessions': String(status.completedSessions || 0)
+            }));
+        }
+        if (status.running && !ticker) {
+            ticker = setInterval(renderBadge, 1000);
+        } else if (!status.running && ticker) {
+            clearInterval(ticker);
+            ticker = null;
+        }
+        renderBadge();
+    }
+
+    function setup() {
+        if (typeof $tw === 'undefined' || !$tw.wiki || !$tw.rootWidget || !window.__TAURI__ || !document.body) {
+            setTimeout(setup, 100);
+            return;
+        }
+        window.__TAURI__.event.listen('focus-timer-changed', function(event) {
+            if (event.payload) applyStatus(event.payload);
+        });
+        TD.getFocusTimerStatus().then(applyStatus).catch(function(err) {
+            console.error('[TiddlyDesktop] Failed to get the focus timer status:', err);
+        });
+        $tw.wiki.addEventListener('change', function(changes) {
+            if (changes[BADGE_CONFIG]) renderBadge();
+        });
+        $tw.rootWidget.addEventListener('tm-tiddlydesktop-rs-start-focus-timer', function(event) {
+            var params = event.paramObject || {};
+            TD.startFocusTimer({
+                focusMinutes: params.focus,
+                shortBreakMinutes: params['break'],
+                longBreakMinutes: params['long-break'],
+                sessionsBeforeLongBreak: params.sessions
+            }).catch(function(err) {
+                console.error('[TiddlyDesktop] Failed to start the focus timer:', err);
+            });
+            return false;
+        });
+        $tw.rootWidget.addEventListener('tm-tiddlydesktop-rs-stop-focus-timer', function() {
+            TD.stopFocusTimer().catch(function(err) {
+                console.error('[TiddlyDesktop] Failed to stop the focus timer:', err);
+            });
+            return false;
+        });
+    }
+
+    setup();
+})(window.TiddlyDesktop = window.TiddlyDesktop || {});
@@ -0,0 +1,95 @@
+// Geolocation - position from the OS location services (geolocation.rs)
+// instead of the webview's own geolocation, which is often missing on Linux.
+// - navigator.geolocation.getCurrentPosition uses get_current_position
+// - TiddlyDesktop.getCurrentPosition() returns a promise of the position
+// - tm-tiddlydesktop-rs-geotag (param: tiddler title, default the current
+//   tiddler) sets the lat, long and alt fields used by the geospatial plugin
+(function(TD) {
+    'use strict';
+
+    // Only run in wiki windows of the desktop app
+    if (!window.__WIKI_PATH__ || window.__WINDOW_LABEL__ === 'main') return;
+    if (typeof window.TiddlyDesktopSync !== 'undefined') return; // Android
+
+    // GeolocationPositionError codes
+    var PERMISSION_DENIED = 1;
+    var POSITION_UNAVAILABLE = 2;
+    var TIMEOUT = 3;
+
+    var NOTIFICATION_TITLE = '$:/temp/TiddlyDesktopRS/GeotagFailed';
+
+    TD.getCurrentPosition = function() {
+        return window.__TAURI__.core.invoke('get_current_position');
+    };
+
+    function positionError(message) {
+        var code = /denied|turned off/i.test(message) ? PERMISSION_DENIED
+            : /timed out/i.test(message) ? TIMEOUT : POSITION_UNAVAILABLE;
+        return {
+            code: code,
+            message: message,
+            PERMISSION_DENIED: PERMISSION_DENIED,
+            POSITION_UNAVAILABLE: POSITION_UNAVAILABLE,
+            TIMEOUT: TIMEOUT
+        };
+    }
+
+    if (navigator.geolocation) {
+        try {
+            Object.defineProperty(navigator.geolocation, 'getCurrentPosition', {
+                configurable: true,
+                value: function(success, failure) {
+                    TD.getCurrentPosition().then(function(position) {
+                        success({
+                            coords: {
+                                latitude: position.latitude,
+                                longitude: position.longitude,
+                                accuracy: position.accuracy === null ? 0 : position.accuracy,
+                                altitude: position.altitude,
+                                altitudeAccuracy: null,
+                                heading: null,
+                                speed: null
+                            },
+                            timestamp: position.timestamp
+                        });
+                    }, function(err) {
+                        if (failure) failure(positionError(String(err)));
+                    });
+                }
+            });
+        } catch (e) {
+            console.warn('[TiddlyDesktop] Could not replace navigator.geolocation:', e);
+        }
+    }
+
+    function geotag(title) {
+        TD.getCurrentPosition().then(function(position) {
+            var tiddler = $tw.wiki.getTiddler(title);
+            var fields = {
+                title: title,
+                lat: String(position.latitude),
+                long: String(position.longitude),
+                alt: position.altitude === null ? undefined : String(position.altitude)
+            };
+            $tw.wiki.addTiddler(new $tw.Tiddler(tiddler, fields, $tw.wiki.getModificationFields()));
+        }).catch(function(err) {
+            $tw.wiki.addTiddler(new $tw.Tiddler({ title: NOTIFICATION_TITLE, text: 'Geotagging failed: ' + String(err) }));
+            $tw.notifier.display(NOTIFICATION_TITLE);
+            console.error('[TiddlyDesktop] Geotagging failed:', err);
+        });
+    }
+
+    function setup() {
+        if (typeof $tw === 'undefined' || !$tw.wiki || !$tw.rootWidget || !window.__TAURI__) {
+            setTimeout(setup, 100);
+            return;
+        }
+        $tw.rootWidget.addEventListener('tm-tiddlydesktop-rs-geotag', function(event) {
+            var title = event.param || event.tiddlerTitle;
+            if (title) geotag(title);
+            return false;
+        });
+    }
+
+    setup();
+})(window.TiddlyDesktop = window.TiddlyDesktop || {});
@@ -0,0 +1,46 @@
+// Idle - system idle time from idle.rs (no keyboard or mouse input):
+// - $:/temp/TiddlyDesktopRS/SystemIdle is "yes" while the user is away
+// - single-file wikis save when the user goes away, if
+//   $:/config/TiddlyDesktopRS/AutosaveOnIdle is "yes"
+// - TiddlyDesktop.getIdleTime() returns a promise of the idle seconds
+(function(TD) {
+    'use strict';
+
+    // Only run in wiki windows of the desktop app
+    if (!window.__WIKI_PATH__ || window.__WINDOW_LABEL__ === 'main') return;
+    if (typeof window.TiddlyDesktopSync !== 'undefined') return; // Android
+
+    var IDLE_TIDDLER = '$:/temp/TiddlyDesktopRS/SystemIdle';
+    var AUTOSAVE_CONFIG = '$:/config/TiddlyDesktopRS/AutosaveOnIdle';
+
+    TD.getIdleTime = function() {
+        return window.__TAURI__.core.invoke('get_idle_time');
+    };
+
+    function isDirty() {
+        if ($tw.saverHandler && typeof $tw.saverHandler.isDirty === 'function') return $tw.saverHandler.isDirty();
+        if ($tw.saverHandler && typeof $tw.saverHandler.numChanges === 'function') return $tw.saverHandler.numChanges() > 0;
+        return false;
+    }
+
+    function onIdleChanged(idle) {
+        $tw.wiki.addTiddler(new $tw.Tiddler({ title: IDLE_TIDDLER, text: idle ? 'yes' : 'no' }));
+        // Folder wikis are saved by the syncer on its own
+        if (idle && !$tw.syncer && $tw.wiki.getTiddlerText(AUTOSAVE_CONFIG, 'no') === 'yes' && isDirty()) {
+            $tw.rootWidget.dispatchEvent({ type: 'tm-save-wiki' });
+        }
+    }
+
+    function setup() {
+        if (typeof $tw === 'undefined' || !$tw.wiki || !$tw.rootWidget || !window.__TAURI__) {
+            setTimeout(setup, 100);
+            return;
+        }
+        $tw.wiki.addTiddler(new $tw.Tiddler({ title: IDLE_TIDDLER, text: 'no' }));
+        window.__TAURI__.event.listen('system-idle', function(event) {
+            onIdleChanged(!!(event.payload && event.payload.idle));
+        });
+    }
+
+    setup();
+})(window.TiddlyDesktop = window.TiddlyDesktop || {});
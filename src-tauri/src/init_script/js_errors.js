@@ -0,0 +1,73 @@
+// TiddlyDesktop Initialization Script - JS Errors Module
+// Provides: reporting uncaught errors, unhandled promise rejections and
+// console.error calls to the shell's error log (see js_errors.rs)
+(function() {
+    'use strict';
+
+    if (window.__tdJsErrorsInstalled) return;
+    window.__tdJsErrorsInstalled = true;
+
+    // Set while reporting, so a failing report can't report itself
+    var reporting = false;
+
+    function describe(value) {
+        if (value instanceof Error) return value.name + ': ' + value.message;
+        if (typeof value === 'string') return value;
+        try {
+            return JSON.stringify(value);
+        } catch (e) {
+            return String(value);
+        }
+    }
+
+    function report(details) {
+        if (reporting || !(window.__TAURI__ && window.__TAURI__.core && window.__TAURI__.core.invoke)) return;
+        reporting = true;
+        try {
+            window.__TAURI__.core.invoke('report_js_error', {
+                kind: details.kind,
+                message: details.message || 'Unknown error',
+                source: details.source || null,
+                line: details.line || null,
+                column: details.column || null,
+                stack: details.stack || null
+            }).catch(function() {});
+        } catch (e) {
+            // Nothing sensible left to do
+        }
+        reporting = false;
+    }
+
+    window.addEventListener('error', function(event) {
+        // Resource load errors (images, scripts) don't bubble to window
+        report({
+            kind: 'error',
+            message: event.message || describe(event.error),
+            source: event.filename,
+            line: event.lineno,
+            column: event.colno,
+            stack: event.error && event.error.stack
+        });
+    });
+
+    window.addEventListener('unhandledrejection', function(event) {
+        var reason = event.reason;
+        report({
+            kind: 'rejection',
+            message: describe(reason),
+            stack: reason && reason.stack
+        });
+    });
+
+    var originalError = console.error;
+    console.error = function() {
+        var args = Array.prototype.slice.call(arguments);
+        var error = args.filter(function(arg) { return arg instanceof Error; })[0];
+        report({
+            kind: 'console',
+            message: args.map(describe).join(' '),
+            stack: error && error.stack
+        });
+        return originalError.apply(console, arguments);
+    };
+})();
@@ -197,6 +197,56 @@
   // Module-scoped so both initLanSync and setupSyncHandlers can access it.
   var _preActivationOverflow = [];
 
+  // Peers of this wiki with their device ids, for sending to the shared
+  // clipboard (shared_clipboard.js). Device ids stay out of the peer tiddlers.
+  var syncPeers = [];
+  (window.TiddlyDesktop = window.TiddlyDesktop || {}).getSyncPeers = function() {
+    return syncPeers.slice();
+  };
+
+  // Resolve a sync conflict of this wiki (conflict_ui.js): 'local', 'remote',
+  // 'merged' (with the merged text) or 'dismiss'. The main process saves the
+  // version to keep and syncs it, then the conflict tiddler is deleted.
+  window.TiddlyDesktop.resolveSyncConflict = function(conflictId, resolution, text) {
+    if (isAndroid || !_collabWikiId) {
+      return Promise.reject('Sync conflicts can only be resolved here on desktop');
+    }
+    return window.__TAURI__.core.invoke('lan_sync_resolve_conflict', {
+      wikiId: _collabWikiId, conflictId: conflictId, resolution: resolution,
+      text: text === undefined ? null : text
+    });
+  };
+
+  // Deletions recorded for this wiki (newest first), and acting on them:
+  // restoring a tiddler a peer deleted, or applying / rejecting a deletion
+  // held for confirmation. Both sync to the peers like local changes.
+  window.TiddlyDesktop.getSyncDeletions = function() {
+    return activeSyncState ? activeSyncState.listDeletions() : [];
+  };
+  window.TiddlyDesktop.restoreSyncDeletion = function(title) {
+    return !!activeSyncState && activeSyncState.restoreDeletion(title);
+  };
+  window.TiddlyDesktop.confirmSyncDeletion = function(title, apply) {
+    return !!activeSyncState && activeSyncState.confirmDeletion(title, !!apply);
+  };
+
+  // Sync now: sends held local changes, applies held ones from peers and
+  // catches up (wikis syncing every N minutes or manually)
+  window.TiddlyDesktop.syncNow = function() {
+    if (!activeSyncState) return false;
+    activeSyncState.syncNow();
+    return true;
+  };
+
+  // Text shared from another device — shown by shared_clipboard.js
+  function dispatchSharedClipboard(data) {
+    try {
+      window.dispatchEvent(new CustomEvent('td-clipboard-received', {
+        detail: { from: data.from || '', text: data.text || '' }
+      }));
+    } catch(_e) {}
+  }
+
   // Logging helper at module scope — rsLog inside initLanSync is only available
   // there; setupSyncHandlers needs its own access.
   function _log(msg) {
@@ -311,6 +361,10 @@
                   deactivateSync();
                   continue;
                 }
+                if (data.type === 'clipboard-received') {
+                  dispatchSharedClipboard(data);
+                  continue;
+                }
                 // Buffer ALL non-activation messages so they aren't lost.
                 // Messages can arrive before the sync-activate in the same
                 // batch (or in earlier polls if sync was already pending).
@@ -422,6 +476,9 @@
     function deactivateSync() {
       if (!activeSyncState) return;
 
+      try { window.dispatchEvent(new Event('td-sync-deactivated')); } catch(_e) {}
+      syncPeers = [];
+
       rsLog('[LAN Sync] Deactivating sync for: ' + activeSyncState.syncId);
 
       // Remove the TiddlyWiki change listener
@@ -440,10 +497,13 @@
       }
       activeSyncState.inboundQueue = null;
 
-      // Clear outbound batch timer
+      // Clear outbound batch timers
       if (activeSyncState.outboundTimer) {
         clearTimeout(activeSyncState.outboundTimer);
       }
+      if (activeSyncState.highTimer) {
+        clearTimeout(activeSyncState.highTimer);
+      }
 
       // Stop Android polling
       if (activeSyncState.pollTimerId) {
@@ -496,6 +556,30 @@
     }
   }
 
+  // Sync schedule of a wiki: null syncs in real time, otherwise
+  // {mode: 'interval', minutes: N} or {mode: 'manual'}. Android syncs in real time.
+  function getSyncSchedule(path, callback) {
+    if (isAndroid) {
+      callback(null);
+    } else {
+      window.__TAURI__.core.invoke('get_wiki_sync_schedule', { path: path })
+        .then(function(schedule) { callback(schedule || null); })
+        .catch(function() { callback(null); });
+    }
+  }
+
+  // Sync priority of a wiki's tiddlers: null, or {high: filter, bulk: filter,
+  // bulk_minutes: N}. Android syncs all tiddlers alike.
+  function getSyncPriority(path, callback) {
+    if (isAndroid) {
+      callback(null);
+    } else {
+      window.__TAURI__.core.invoke('get_wiki_sync_priority', { path: path })
+        .then(function(priority) { callback(priority || null); })
+        .catch(function() { callback(null); });
+    }
+  }
+
   function notifyWikiOpened(wikiId) {
     if (isAndroid) {
       window.TiddlyDesktopSync.wikiOpened(wikiId);
@@ -524,6 +608,22 @@
     }
   }
 
+  // Open conflicts are kept with both versions in the main process until
+  // resolved (lan_sync_resolve_conflict). Not available on Android yet.
+  function sendConflictLocalVersion(wikiId, conflictId, tiddlerJson) {
+    if (isAndroid || !conflictId) return;
+    window.__TAURI__.core.invoke('lan_sync_conflict_local_version', {
+      wikiId: wikiId, conflictId: conflictId, tiddlerJson: tiddlerJson
+    }).catch(function(e) { console.error('[LAN Sync] conflict_local_version error:', e); });
+  }
+
+  function dismissConflict(wikiId, conflictId) {
+    if (isAndroid || !conflictId) return;
+    window.__TAURI__.core.invoke('lan_sync_resolve_conflict', {
+      wikiId: wikiId, conflictId: conflictId, resolution: 'dismiss', text: null
+    }).catch(function(e) { console.error('[LAN Sync] dismiss conflict error:', e); });
+  }
+
   function sendFullSyncBatch(wikiId, toDeviceId, tiddlers, isLastBatch) {
     if (isAndroid) {
       window.TiddlyDesktopSync.sendFullSyncBatch(
@@ -574,6 +674,18 @@
     }
   }
 
+  // Retention window and confirmation of deletions from peers (Android keeps
+  // the defaults; its tombstones are pruned when they are loaded)
+  function getDeletionPolicy(wikiId, callback) {
+    if (isAndroid) {
+      callback(null);
+    } else {
+      window.__TAURI__.core.invoke('lan_sync_get_deletion_policy', { wikiId: wikiId })
+        .then(function(policy) { callback(policy); })
+        .catch(function() { callback(null); });
+    }
+  }
+
   function saveTombstones(wikiId, tombstonesJson) {
     if (isAndroid) {
       window.TiddlyDesktopSync.saveTombstones(wikiId, tombstonesJson);
@@ -705,10 +817,30 @@
     // Deletion tombstones: title → {modified: TW date string, time: epoch ms}
     // When a tiddler is deleted locally, a tombstone is recorded so fingerprint
     // broadcasts inform peers to delete it too — even if they were offline.
+    // Deletions from peers also record the peer (from_device_id) and a copy of
+    // the tiddler (tiddler_json) for restoring; held ones are marked pending.
     var deletionTombstones = {};
     var tombstonesLoaded = false; // Set true after loadTombstones callback
-    var TOMBSTONE_MAX_AGE_MS = 30 * 24 * 60 * 60 * 1000; // 30 days
+    var tombstoneMaxAgeMs = 30 * 24 * 60 * 60 * 1000; // 30 days unless configured
+    // Hold deletions from peers until they are confirmed (per-wiki setting)
+    var confirmRemoteDeletions = false;
+    // Larger tiddlers aren't copied into their tombstone
+    var TOMBSTONE_MAX_COPY_LENGTH = 1024 * 1024;
 
+    var wikiPath = window.__WIKI_PATH__ || '';
+
+    // Sync schedule (see getSyncSchedule). Unless syncing in real time, local
+    // changes wait in pendingOutbound and changes from peers in the inbound
+    // queue until the next sync, which stays live for SYNC_WINDOW_MS so the
+    // catch-up with the peers can finish. Opening the wiki counts as a sync.
+    var syncSchedule = null;
+    var SYNC_WINDOW_MS = 60 * 1000;
+    var syncLiveUntil = Date.now() + SYNC_WINDOW_MS;
+    var syncIntervalId = null;
+
+    function isLive() {
+      return !syncSchedule || Date.now() < syncLiveUntil;
+    }
 
     // State object to return for cleanup
     var state = {
@@ -719,7 +851,8 @@
       saveTimer: null,
       batchTimer: null,
       inboundQueue: [],
-      outboundTimer: null
+      outboundTimer: null,
+      highTimer: null
     };
 
     // Compare incoming tiddler fields with local — returns true if content differs.
@@ -781,8 +914,9 @@
       var tombKeys = Object.keys(deletionTombstones);
       var tombstonesChanged = false;
       for (var k = 0; k < tombKeys.length; k++) {
-        if (deletionTombstones[tombKeys[k]].cleared) {
-          // Tombstone was cleared (tiddler re-created) — don't include in fingerprints
+        if (deletionTombstones[tombKeys[k]].cleared || deletionTombstones[tombKeys[k]].pending) {
+          // Tombstone was cleared (tiddler re-created) or the deletion is
+          // waiting for confirmation — don't include in fingerprints
           continue;
         }
         if (!seen[tombKeys[k]]) {
@@ -803,15 +937,331 @@
       return fps;
     }
 
+    // ── Deletions from peers ────────────────────────────────────────
+
+    // Delete a tiddler a peer deleted, or hold the deletion when it needs
+    // confirmation, keeping a copy for restoring. Returns true if the
+    // tiddler was deleted.
+    function applyRemoteDeletion(title, modified, fromDeviceId) {
+      var tiddler = $tw.wiki.getTiddler(title);
+      var tomb = { modified: modified, time: Date.now() };
+      if (fromDeviceId) tomb.from_device_id = fromDeviceId;
+      if (tiddler) {
+        var copy = serializeTiddlerFields(tiddler.fields);
+        if (copy.length <= TOMBSTONE_MAX_COPY_LENGTH) tomb.tiddler_json = copy;
+      }
+      if (tiddler && confirmRemoteDeletions) {
+        tomb.pending = true;
+        deletionTombstones[title] = tomb;
+        _log('[LAN Sync] Holding deletion for confirmation: ' + title);
+        try {
+          window.dispatchEvent(new CustomEvent('td-sync-deletion-pending', {
+            detail: { title: title, fromDeviceId: fromDeviceId || null }
+          }));
+        } catch(_e) {}
+        return false;
+      }
+      deletionTombstones[title] = tomb;
+      if (!tiddler) return false;
+      suppressOutbound.add(title);
+      $tw.wiki.deleteTiddler(title);
+      return true;
+    }
+
+    function listDeletions() {
+      return Object.keys(deletionTombstones).map(function(title) {
+        var tomb = deletionTombstones[title];
+        return {
+          title: title,
+          deletedAt: tomb.time || 0,
+          fromDeviceId: tomb.from_device_id || null,
+          pending: !!tomb.pending,
+          cleared: !!tomb.cleared,
+          restorable: !!tomb.tiddler_json && !tomb.pending && !tomb.cleared
+        };
+      }).sort(function(a, b) { return b.deletedAt - a.deletedAt; });
+    }
+
+    // Re-create a tiddler a peer deleted, modified now so it wins over the
+    // peers' tombstones. The change listener syncs it like a local edit.
+    function restoreDeletion(title) {
+      var tomb = deletionTombstones[title];
+      if (!tomb || !tomb.tiddler_json || tomb.pending || tomb.cleared) return false;
+      var fields;
+      try {
+        fields = JSON.parse(tomb.tiddler_json);
+      } catch (e) {
+        return false;
+      }
+      delete deletionTombstones[title];
+      saveTombstones(wikiId, JSON.stringify(deletionTombstones));
+      fields.title = title;
+      fields.modified = new Date();
+      $tw.wiki.addTiddler(new $tw.Tiddler(fields));
+      scheduleSave();
+      _log('[LAN Sync] Restored deleted tiddler: ' + title);
+      return true;
+    }
+
+    // Apply a held deletion, or reject it: keep the tiddler and send it back
+    // to the peers
+    function confirmDeletion(title, apply) {
+      var tomb = deletionTombstones[title];
+      if (!tomb || !tomb.pending) return false;
+      tomb.pending = false;
+      if (apply) {
+        tomb.time = Date.now();
+        if ($tw.wiki.tiddlerExists(title)) {
+          suppressOutbound.add(title);
+          $tw.wiki.deleteTiddler(title);
+          scheduleSave();
+        }
+      } else {
+        tomb.cleared = true;
+        var tiddler = $tw.wiki.getTiddler(title);
+        if (tiddler && syncMode !== 'receive-only') {
+          pendingOutbound[title] = { deleted: false, tiddlerJson: serializeTiddlerFields(tiddler.fields) };
+          if (!state.outboundTimer) state.outboundTimer = setTimeout(flushOutbound, 50);
+        }
+      }
+      saveTombstones(wikiId, JSON.stringify(deletionTombstones));
+      _log('[LAN Sync] ' + (apply ? 'Applied' : 'Rejected') + ' held deletion: ' + title);
+      return true;
+    }
+
+    state.listDeletions = listDeletions;
+    state.restoreDeletion = restoreDeletion;
+    state.confirmDeletion = confirmDeletion;
+
+    // ── Sync schedule ───────────────────────────────────────────────
+
+    function syncNow() {
+      syncLiveUntil = Date.now() + SYNC_WINDOW_MS;
+      _log('[LAN Sync] Syncing now');
+      flushBulk();
+      if (!state.outboundTimer && Object.keys(pendingOutbound).length > 0) {
+        state.outboundTimer = setTimeout(flushOutbound, 0);
+      }
+      if (!state.batchTimer && state.inboundQueue.length > 0) {
+        state.batchTimer = setTimeout(applyInboundBatch, 0);
+      }
+      // Peers send what changed while we weren't listening
+      notifyWikiOpened(wikiId);
+      if (!tombstonesLoaded) return; // onTombstonesLoaded broadcasts them
+      broadcastFingerprints(wikiId, collectFingerprints()).catch(function(e) {
+        _log('[LAN Sync] Broadcast fingerprints error: ' + e);
+      });
+    }
+
+    function applySyncSchedule(schedule) {
+      var wasRealtime = !syncSchedule;
+      syncSchedule = schedule || null;
+      if (syncIntervalId) {
+        clearInterval(syncIntervalId);
+        syncIntervalId = null;
+      }
+      if (syncSchedule && syncSchedule.mode === 'interval' && syncSchedule.minutes > 0) {
+        syncIntervalId = setInterval(syncNow, syncSchedule.minutes * 60 * 1000);
+      }
+      _log('[LAN Sync] Schedule: ' + (syncSchedule ? syncSchedule.mode : 'realtime'));
+      // Back to real time: catch up on what was held
+      if (!syncSchedule && !wasRealtime) syncNow();
+    }
+
+    state.syncNow = syncNow;
+    state.unlistenFns.push(function() {
+      if (syncIntervalId) clearInterval(syncIntervalId);
+    });
+    getSyncSchedule(wikiPath, applySyncSchedule);
+
+    // ── Sync priority ───────────────────────────────────────────────
+    // Local changes to tiddlers matching the high filter are sent right away,
+    // and changes from peers to them applied on arrival, even between
+    // scheduled syncs. Changes to tiddlers matching the bulk filter (images,
+    // big data tiddlers) wait in pendingBulk and go out together every
+    // bulk_minutes, or with the next sync (see getSyncPriority).
+    var highFilter = null;
+    var bulkFilter = null;
+    var BULK_DEFAULT_MINUTES = 10;
+    var bulkIntervalId = null;
+
+    function matchesFilter(filterFn, title) {
+      if (!filterFn) return false;
+      return filterFn.call($tw.wiki, $tw.wiki.makeTiddlerIterator([title])).indexOf(title) !== -1;
+    }
+
+    function priorityOf(title) {
+      if (matchesFilter(highFilter, title)) return 'high';
+      if (matchesFilter(bulkFilter, title)) return 'bulk';
+      return 'normal';
+    }
+
+    function applySyncPriority(priority) {
+      highFilter = priority && priority.high ? $tw.wiki.compileFilter(priority.high) : null;
+      bulkFilter = priority && priority.bulk ? $tw.wiki.compileFilter(priority.bulk) : null;
+      if (bulkIntervalId) {
+        clearInterval(bulkIntervalId);
+        bulkIntervalId = null;
+      }
+      if (bulkFilter) {
+        var minutes = priority.bulk_minutes > 0 ? priority.bulk_minutes : BULK_DEFAULT_MINUTES;
+        bulkIntervalId = setInterval(flushBulk, minutes * 60 * 1000);
+      } else {
+        // No bulk tiddlers anymore: send what was held for the next batch
+        flushBulk();
+      }
+      _log('[LAN Sync] Priority: high=' + (priority && priority.high || '-') +
+           ' bulk=' + (priority && priority.bulk || '-'));
+    }
+
+    state.unlistenFns.push(function() {
+      if (bulkIntervalId) clearInterval(bulkIntervalId);
+    });
+    getSyncPriority(wikiPath, applySyncPriority);
+
+    // ── Sync session snapshots ──────────────────────────────────────
+    // Before the first changes of a sync session (peers' changes arriving
+    // after SESSION_GAP_MS without any) are applied, the syncable tiddlers are
+    // recorded (lan_sync/snapshots.rs), so rollback_last_sync can bring the
+    // wiki back to that state. Not on Android.
+    var SESSION_GAP_MS = 10 * 60 * 1000;
+    var lastSyncActivity = 0;
+
+    function beginSyncSession() {
+      var now = Date.now();
+      var newSession = now - lastSyncActivity > SESSION_GAP_MS;
+      lastSyncActivity = now;
+      if (!newSession || isAndroid) return;
+      // Tiddlers are immutable: keeping them is keeping the state before the
+      // changes are applied
+      var tiddlers = {};
+      var fingerprints = {};
+      var titles = getSyncableTitles();
+      for (var i = 0; i < titles.length; i++) {
+        var tiddler = $tw.wiki.getTiddler(titles[i]);
+        if (!tiddler) continue;
+        tiddlers[titles[i]] = tiddler;
+        fingerprints[titles[i]] = tiddler.fields.modified ? $tw.utils.stringifyDate(tiddler.fields.modified) : '';
+      }
+      window.__TAURI__.core.invoke('lan_sync_snapshot_needed', { wikiId: wikiId, fingerprints: fingerprints })
+        .then(function(needed) {
+          var json = {};
+          for (var j = 0; j < needed.length; j++) {
+            if (tiddlers[needed[j]]) json[needed[j]] = serializeTiddlerFields(tiddlers[needed[j]].fields);
+          }
+          return window.__TAURI__.core.invoke('lan_sync_save_snapshot', {
+            wikiId: wikiId, fingerprints: fingerprints, tiddlers: json
+          });
+        })
+        .then(function() { _log('[LAN Sync] Snapshot of ' + titles.length + ' tiddlers before sync'); })
+        .catch(function(e) { console.error('[LAN Sync] Failed to save snapshot:', e); });
+    }
+
+    // Bring the wiki back to the latest snapshot. The restored tiddlers are
+    // modified now so they win over the peers' versions; the change listener
+    // syncs them (and the deletions) like local edits.
+    function rollbackLastSync() {
+      return window.__TAURI__.core.invoke('lan_sync_load_snapshot', { wikiId: wikiId }).then(function(snapshot) {
+        if (!snapshot) return;
+        var restored = 0, deleted = 0;
+        var titles = getSyncableTitles();
+        for (var i = 0; i < titles.length; i++) {
+          if (!Object.prototype.hasOwnProperty.call(snapshot.tiddlers, titles[i])) {
+            $tw.wiki.deleteTiddler(titles[i]);
+            deleted++;
+          }
+        }
+        var keys = Object.keys(snapshot.tiddlers);
+        for (var k = 0; k < keys.length; k++) {
+          var title = keys[k];
+          var json = snapshot.tiddlers[title];
+          var current = $tw.wiki.getTiddler(title);
+          if (current && serializeTiddlerFields(current.fields) === json) continue;
+          var fields;
+          try {
+            fields = JSON.parse(json);
+          } catch (e) {
+            continue;
+          }
+          fields.title = title;
+          fields.modified = new Date();
+          $tw.wiki.addTiddler(new $tw.Tiddler(fields));
+          restored++;
+        }
+        if (restored || deleted) scheduleSave();
+        _log('[LAN Sync] Rolled back the last sync: ' + restored + ' restored, ' + deleted + ' deleted');
+      }).catch(function(e) {
+        console.error('[LAN Sync] Rollback failed:', e);
+      });
+    }
+
+    // The rollback is requested from the main process (rollback_last_sync),
+    // which leaves a marker so it happens once, also if the wiki was closed
+    function takePendingRollback(callback) {
+      if (isAndroid) return callback(false);
+      window.__TAURI__.core.invoke('lan_sync_take_pending_rollback', { wikiId: wikiId })
+        .then(function(pending) { callback(pending); })
+        .catch(function() { callback(false); });
+    }
+
+    function handleRollbackRequest() {
+      takePendingRollback(function(pending) {
+        if (pending) rollbackLastSync();
+      });
+    }
+
     // ── Outbound: detect local changes (batched with 50ms window) ──────
 
     // Pending outbound changes: title → {deleted: bool, tiddlerJson: string|null}
     var pendingOutbound = {};
+    // Changes to high-priority tiddlers (sent right away) and to bulk
+    // tiddlers (sent with the next batch), see applySyncPriority
+    var pendingHigh = {};
+    var pendingBulk = {};
+
+    function queueOutbound(title, entry) {
+      delete pendingOutbound[title];
+      delete pendingHigh[title];
+      delete pendingBulk[title];
+      var priority = priorityOf(title);
+      if (priority === 'high') {
+        pendingHigh[title] = entry;
+      } else if (priority === 'bulk') {
+        pendingBulk[title] = entry;
+      } else {
+        pendingOutbound[title] = entry;
+      }
+    }
 
     function flushOutbound() {
       state.outboundTimer = null;
       var batch = pendingOutbound;
       pendingOutbound = {};
+      sendOutbound(batch);
+    }
+
+    function flushHigh() {
+      state.highTimer = null;
+      var batch = pendingHigh;
+      pendingHigh = {};
+      sendOutbound(batch);
+    }
+
+    // Queue the held bulk changes with the others (sent now if live)
+    function flushBulk() {
+      var titles = Object.keys(pendingBulk);
+      if (titles.length === 0) return;
+      for (var i = 0; i < titles.length; i++) {
+        pendingOutbound[titles[i]] = pendingBulk[titles[i]];
+      }
+      pendingBulk = {};
+      _log('[LAN Sync] Sending ' + titles.length + ' bulk change(s)');
+      if (!state.outboundTimer && isLive()) {
+        state.outboundTimer = setTimeout(flushOutbound, 0);
+      }
+    }
+
+    function sendOutbound(batch) {
       var titles = Object.keys(batch);
       for (var i = 0; i < titles.length; i++) {
         var title = titles[i];
@@ -839,23 +1289,29 @@
           var delMod = $tw.utils.stringifyDate(new Date());
           deletionTombstones[title] = { modified: delMod, time: Date.now() };
           saveTombstones(wikiId, JSON.stringify(deletionTombstones));
-          pendingOutbound[title] = { deleted: true, tiddlerJson: null };
+          queueOutbound(title, { deleted: true, tiddlerJson: null });
         } else {
           // If this tiddler had a deletion tombstone, mark it cleared
           // so the tombstone won't re-delete it on the next fingerprint sync
           if (deletionTombstones[title] && !deletionTombstones[title].cleared) {
             deletionTombstones[title].cleared = true;
+            deletionTombstones[title].pending = false;
             saveTombstones(wikiId, JSON.stringify(deletionTombstones));
             _log('[LAN Sync] Cleared tombstone for re-created tiddler: ' + title);
           }
           var tiddler = $tw.wiki.getTiddler(title);
           if (tiddler) {
-            pendingOutbound[title] = { deleted: false, tiddlerJson: serializeTiddlerFields(tiddler.fields) };
+            queueOutbound(title, { deleted: false, tiddlerJson: serializeTiddlerFields(tiddler.fields) });
           }
         }
       });
 
-      if (!state.outboundTimer && Object.keys(pendingOutbound).length > 0) {
+      // High-priority changes don't wait for the schedule
+      if (!state.highTimer && Object.keys(pendingHigh).length > 0) {
+        state.highTimer = setTimeout(flushHigh, 0);
+      }
+      // Held until the next sync unless syncing in real time
+      if (!state.outboundTimer && Object.keys(pendingOutbound).length > 0 && isLive()) {
         state.outboundTimer = setTimeout(flushOutbound, 50);
       }
     };
@@ -906,6 +1362,24 @@
         return;
       }
 
+      // Restoring or confirming a deletion (from the main process)
+      if (data.type === 'restore-deletion') {
+        restoreDeletion(data.title);
+        return;
+      }
+      if (data.type === 'confirm-deletion') {
+        confirmDeletion(data.title, !!data.apply);
+        return;
+      }
+      if (data.type === 'sync-now') {
+        syncNow();
+        return;
+      }
+      if (data.type === 'rollback-sync') {
+        handleRollbackRequest();
+        return;
+      }
+
       // Wiki config changed (tiddlywiki.info updated via LAN sync)
       if (data.type === 'wiki-info-changed') {
         _log('[LAN Sync] Wiki config changed from another device');
@@ -926,6 +1400,7 @@
       // Peer status updates — update shadow tiddlers for peer badge UI
       if (data.type === 'peer-update') {
         if (data.peers) {
+          syncPeers = data.peers;
           // Convert array to object keyed by index — strip device_id (sensitive)
           var peersObj = {};
           for (var pi = 0; pi < data.peers.length; pi++) {
@@ -952,8 +1427,21 @@
         return;
       }
 
+      // High-priority tiddlers are applied between scheduled syncs too
+      if (!isLive() && (data.type === 'apply-change' || data.type === 'apply-deletion') &&
+          priorityOf(data.title) === 'high') {
+        var held = state.inboundQueue;
+        var heldTimer = state.batchTimer;
+        state.inboundQueue = [data];
+        applyInboundBatch();
+        state.inboundQueue = held;
+        state.batchTimer = heldTimer;
+        return;
+      }
+
       state.inboundQueue.push(data);
-      if (!state.batchTimer) {
+      // Held until the next sync unless syncing in real time
+      if (!state.batchTimer && isLive()) {
         state.batchTimer = setTimeout(applyInboundBatch, 50);
       }
     }
@@ -964,6 +1452,9 @@
       state.inboundQueue = [];
       if (batch.length === 0) return;
 
+      // Shown as "syncing" in the window title (sync_badge.js)
+      try { window.dispatchEvent(new Event('td-sync-activity')); } catch(_e) {}
+
       // Send-only wikis don't apply incoming changes
       if (syncMode === 'send-only') {
         _log('[LAN Sync] Discarding ' + batch.length + ' inbound changes (send-only mode)');
@@ -971,6 +1462,9 @@
       }
 
       _log('[LAN Sync] Applying batch of ' + batch.length + ' inbound changes');
+      if (batch.some(function(d) { return d.type === 'apply-change' || d.type === 'apply-deletion'; })) {
+        beginSyncSession();
+      }
 
       var needSave = false;
       var pluginsChanged = false;
@@ -979,6 +1473,46 @@
       // 'apply-change' arrives. If the change is content-identical or a plugin,
       // the conflict is discarded (no conflict tiddler created).
       var pendingConflicts = {};
+      // title → the 'conflict' event (conflict ID, peer and remote version)
+      var conflictEvents = {};
+      // Save the local version as a conflict tiddler and tell the main process
+      // and the wiki (a 'td-sync-conflict' window event with both versions)
+      function saveConflictTiddler(origTitle, localTiddler) {
+        var info = conflictEvents[origTitle] || {};
+        var conflictTitle = '$:/TiddlyDesktopRS/Conflicts/' + origTitle;
+        conflictTitles.add(conflictTitle);
+        var conflictFields = Object.assign({}, localTiddler.fields, {
+          title: conflictTitle,
+          'conflict-original-title': origTitle,
+          'conflict-timestamp': new Date().toISOString(),
+          'conflict-source': 'local'
+        });
+        if (info.conflict_id) conflictFields['conflict-id'] = info.conflict_id;
+        if (info.from_device_id) conflictFields['conflict-device'] = info.from_device_id;
+        $tw.wiki.addTiddler(new $tw.Tiddler(conflictFields));
+        setTimeout(function() { conflictTitles.delete(conflictTitle); }, 500);
+        var localJson = serializeTiddlerFields(localTiddler.fields);
+        sendConflictLocalVersion(wikiId, info.conflict_id, localJson);
+        try {
+          window.dispatchEvent(new CustomEvent('td-sync-conflict', { detail: {
+            conflictId: info.conflict_id || null,
+            title: origTitle,
+            conflictTitle: conflictTitle,
+            fromDeviceId: info.from_device_id || null,
+            localTiddlerJson: localJson,
+            remoteTiddlerJson: info.remote_tiddler_json || null,
+            remoteDeleted: !!info.remote_deleted
+          }}));
+        } catch(_e) {}
+      }
+      // The conflict turned out not to be one (identical content or a plugin)
+      function discardConflict(origTitle) {
+        delete pendingConflicts[origTitle];
+        if (conflictEvents[origTitle]) {
+          dismissConflict(wikiId, conflictEvents[origTitle].conflict_id);
+          delete conflictEvents[origTitle];
+        }
+      }
       for (var i = 0; i < batch.length; i++) {
         var data = batch[i];
 
@@ -988,7 +1522,7 @@
             // Plugin tiddlers: only accept if incoming version is newer
             if (fields['plugin-type'] && fields.version) {
               // Never create conflicts for plugin tiddlers — version comparison only
-              delete pendingConflicts[fields.title];
+              discardConflict(fields.title);
               var localPlugin = $tw.wiki.getTiddler(fields.title);
               if (localPlugin && localPlugin.fields.version &&
                   compareVersions(fields.version, localPlugin.fields.version) <= 0) {
@@ -1005,18 +1539,9 @@
             if (tiddlerDiffers(fields)) {
               // Content actually differs — create the pending conflict if one exists
               if (pendingConflicts[fields.title]) {
-                var localSnap = pendingConflicts[fields.title];
-                var conflictTitle = '$:/TiddlyDesktopRS/Conflicts/' + fields.title;
-                conflictTitles.add(conflictTitle);
-                var conflictFields = Object.assign({}, localSnap.fields, {
-                  title: conflictTitle,
-                  'conflict-original-title': fields.title,
-                  'conflict-timestamp': new Date().toISOString(),
-                  'conflict-source': 'local'
-                });
-                $tw.wiki.addTiddler(new $tw.Tiddler(conflictFields));
-                setTimeout(function() { conflictTitles.delete(conflictTitle); }, 500);
+                saveConflictTiddler(fields.title, pendingConflicts[fields.title]);
                 delete pendingConflicts[fields.title];
+                delete conflictEvents[fields.title];
               }
               // Parse date strings to Date objects so TiddlyWiki stores them correctly
               if (fields.created) fields.created = $tw.utils.parseDate(fields.created);
@@ -1032,7 +1557,7 @@
               // Content identical (only metadata differs) — discard any pending conflict
               if (pendingConflicts[fields.title]) {
                 _log('[LAN Sync] Skipped conflict for metadata-only diff: ' + fields.title);
-                delete pendingConflicts[fields.title];
+                discardConflict(fields.title);
               }
               _log('[LAN Sync] Skipped identical tiddler: ' + fields.title);
               // Track so we include it in fingerprints (peer will stop re-sending)
@@ -1045,16 +1570,14 @@
 
         } else if (data.type === 'apply-deletion') {
           try {
-            if ($tw.wiki.tiddlerExists(data.title)) {
-              suppressOutbound.add(data.title);
-              $tw.wiki.deleteTiddler(data.title);
-              needSave = true;
-            }
-            // Record tombstone so this deletion propagates to other peers
+            // Delete (or hold) and record a tombstone so this deletion
+            // propagates to other peers
             var delMod = $tw.utils.stringifyDate(new Date());
-            if (!deletionTombstones[data.title] ||
+            if ($tw.wiki.tiddlerExists(data.title) || !deletionTombstones[data.title] ||
                 deletionTombstones[data.title].modified < delMod) {
-              deletionTombstones[data.title] = { modified: delMod, time: Date.now() };
+              if (applyRemoteDeletion(data.title, delMod, data.from_device_id)) {
+                needSave = true;
+              }
               saveTombstones(wikiId, JSON.stringify(deletionTombstones));
             }
           } catch (e) {
@@ -1064,8 +1587,10 @@
         } else if (data.type === 'conflict') {
           var title = data.title;
           // Skip conflicts for plugin tiddlers entirely
+          conflictEvents[title] = data;
           if (title.indexOf('$:/plugins/') === 0) {
             _log('[LAN Sync] Skipped conflict for plugin: ' + title);
+            discardConflict(title);
             continue;
           }
           // Defer conflict creation — will be resolved when the subsequent
@@ -1073,6 +1598,19 @@
           var localTiddler = $tw.wiki.getTiddler(title);
           if (localTiddler) {
             pendingConflicts[title] = localTiddler;
+          } else {
+            discardConflict(title);
+          }
+
+        } else if (data.type === 'conflict-resolved') {
+          // Resolved in the main process (maybe from another window)
+          var resolvedTitle = '$:/TiddlyDesktopRS/Conflicts/' + data.title;
+          var resolvedTiddler = $tw.wiki.getTiddler(resolvedTitle);
+          if (resolvedTiddler && resolvedTiddler.fields['conflict-id'] === data.conflict_id) {
+            conflictTitles.add(resolvedTitle);
+            $tw.wiki.deleteTiddler(resolvedTitle);
+            setTimeout(function() { conflictTitles.delete(resolvedTitle); }, 500);
+            needSave = true;
           }
         }
       }
@@ -1080,18 +1618,7 @@
       // (shouldn't normally happen, but handle gracefully)
       var pcKeys = Object.keys(pendingConflicts);
       for (var pc = 0; pc < pcKeys.length; pc++) {
-        var pcTitle = pcKeys[pc];
-        var pcLocal = pendingConflicts[pcTitle];
-        var pcConflictTitle = '$:/TiddlyDesktopRS/Conflicts/' + pcTitle;
-        conflictTitles.add(pcConflictTitle);
-        var pcConflictFields = Object.assign({}, pcLocal.fields, {
-          title: pcConflictTitle,
-          'conflict-original-title': pcTitle,
-          'conflict-timestamp': new Date().toISOString(),
-          'conflict-source': 'local'
-        });
-        $tw.wiki.addTiddler(new $tw.Tiddler(pcConflictFields));
-        setTimeout(function() { conflictTitles.delete(pcConflictTitle); }, 500);
+        saveConflictTiddler(pcKeys[pc], pendingConflicts[pcKeys[pc]]);
       }
       // If plugin tiddlers were updated, re-extract shadow tiddlers
       if (pluginsChanged) {
@@ -1134,11 +1661,17 @@
         setTimeout(function() { handleCompareFingerprints(fromDeviceId, peerFingerprints); }, 100);
         return;
       }
+      // Between scheduled syncs; the next one compares again
+      if (!isLive()) {
+        _log('[LAN Sync] Ignoring compare-fingerprints until the next sync');
+        return;
+      }
       // Guard against undefined/null fingerprints (e.g. truncated IPC message)
       if (!peerFingerprints || !peerFingerprints.length) {
         _log('[LAN Sync] compare-fingerprints: no fingerprints received');
         return;
       }
+      try { window.dispatchEvent(new Event('td-sync-activity')); } catch(_e) {}
       // Separate peer's fingerprints into normal tiddlers and tombstones
       var peerMap = {};       // title → modified
       var peerVersions = {};  // title → version (only for plugin tiddlers)
@@ -1160,6 +1693,8 @@
       var needSave = false;
       if (syncMode === 'send-only') {
         _log('[LAN Sync] Skipping tombstone application (send-only mode)');
+      } else if (tombKeys.length > 0) {
+        beginSyncSession();
       }
       for (var t = 0; t < tombKeys.length && syncMode !== 'send-only'; t++) {
         var tombTitle = tombKeys[t];
@@ -1177,11 +1712,16 @@
         if (localTiddler) {
           var localMod = localTiddler.fields.modified ? fieldToString(localTiddler.fields.modified) : '';
           if (!localMod || localMod <= tombModified) {
-            // Peer deleted it after our version — apply deletion
-            suppressOutbound.add(tombTitle);
-            $tw.wiki.deleteTiddler(tombTitle);
-            needSave = true;
-            _log('[LAN Sync] Applied tombstone deletion: ' + tombTitle);
+            // Peer deleted it after our version — apply deletion (unless
+            // it is already held for confirmation)
+            if (localTomb && localTomb.pending && localTomb.modified >= tombModified) {
+              continue;
+            }
+            if (applyRemoteDeletion(tombTitle, tombModified, fromDeviceId)) {
+              needSave = true;
+              _log('[LAN Sync] Applied tombstone deletion: ' + tombTitle);
+            }
+            continue;
           }
           // else: our version is newer than the deletion — keep it, will send below
         }
@@ -1211,8 +1751,13 @@
         var tiddler = $tw.wiki.getTiddler(title);
         if (!tiddler) continue;
 
+        // Bulk changes go out with the next batch
+        if (pendingBulk[title]) continue;
+
         if (!(title in peerMap)) {
-          // Peer doesn't have this tiddler — send it
+          // Peer doesn't have this tiddler — send it (unless the peer's
+          // deletion of it is waiting for confirmation here)
+          if (deletionTombstones[title] && deletionTombstones[title].pending) continue;
           toSend.push(title);
         } else if (tiddler.fields['plugin-type']) {
           // Plugin tiddler: compare by version exclusively (not modified timestamp)
@@ -1400,6 +1945,7 @@
                   case 'apply-change':
                   case 'apply-deletion':
                   case 'conflict':
+                  case 'conflict-resolved':
                     queueInboundChange(data);
                     break;
                   case 'dump-tiddlers':
@@ -1434,9 +1980,37 @@
                       _log('[LAN Sync] Mode changed to: ' + (syncMode || 'bidirectional'));
                     }
                     break;
+                  case 'deletion-policy-changed':
+                    if (data.wiki_path === wikiPath) {
+                      confirmRemoteDeletions = !!data.confirm_remote_deletions;
+                    }
+                    break;
+                  case 'restore-deletion':
+                    restoreDeletion(data.title);
+                    break;
+                  case 'confirm-deletion':
+                    confirmDeletion(data.title, !!data.apply);
+                    break;
+                  case 'sync-now':
+                    syncNow();
+                    break;
+                  case 'rollback-sync':
+                    handleRollbackRequest();
+                    break;
+                  case 'sync-schedule-changed':
+                    if (data.wiki_path === wikiPath) {
+                      applySyncSchedule(data.sync_schedule);
+                    }
+                    break;
+                  case 'sync-priority-changed':
+                    if (data.wiki_path === wikiPath) {
+                      applySyncPriority(data.sync_priority);
+                    }
+                    break;
                   case 'peer-update':
                     // Update shadow tiddlers for peer badge (pushed from main process)
                     if (data.peers) {
+                      syncPeers = data.peers;
                       var PEERS_TIDDLER = '$:/temp/tiddlydesktop/connected-peers';
                       var COUNT_TIDDLER = '$:/temp/tiddlydesktop/peer-count';
                       var peersObj2 = {};
@@ -1467,6 +2041,9 @@
                       }
                     }
                     break;
+                  case 'clipboard-received':
+                    dispatchSharedClipboard(data);
+                    break;
                 }
               } catch (e) {
                 _log('[LAN Sync] IPC poll parse error: ' + e);
@@ -1491,13 +2068,20 @@
     }
 
     // ── Load persisted tombstones + initial fingerprint broadcast ──────
-    loadTombstones(wikiId, function(stored) {
+    getDeletionPolicy(wikiId, function(policy) {
+      if (policy) {
+        tombstoneMaxAgeMs = policy.retention_days * 24 * 60 * 60 * 1000;
+        confirmRemoteDeletions = !!policy.confirm_remote_deletions;
+      }
+      loadTombstones(wikiId, onTombstonesLoaded);
+    });
+    function onTombstonesLoaded(stored) {
       try {
         var parsed = JSON.parse(stored);
         var now = Date.now();
         var keys = Object.keys(parsed);
         for (var i = 0; i < keys.length; i++) {
-          if (parsed[keys[i]].time && now - parsed[keys[i]].time > TOMBSTONE_MAX_AGE_MS) {
+          if (parsed[keys[i]].time && now - parsed[keys[i]].time > tombstoneMaxAgeMs) {
             continue; // expired
           }
           deletionTombstones[keys[i]] = parsed[keys[i]];
@@ -1506,19 +2090,26 @@
       } catch (e) {}
       tombstonesLoaded = true;
 
-      // Broadcast fingerprints (includes tombstones) for catch-up
-      var fps = collectFingerprints();
-      _log('[LAN Sync] Broadcasting ' + fps.length + ' fingerprints for catch-up');
-      broadcastFingerprints(wikiId, fps).catch(function(e) {
-        _log('[LAN Sync] Broadcast fingerprints error: ' + e);
+      // A rollback requested while the wiki was closed goes first, so the
+      // catch-up doesn't bring back what it undoes
+      takePendingRollback(function(pending) {
+        (pending ? rollbackLastSync() : Promise.resolve()).then(function() {
+          // Broadcast fingerprints (includes tombstones) for catch-up
+          var fps = collectFingerprints();
+          _log('[LAN Sync] Broadcasting ' + fps.length + ' fingerprints for catch-up');
+          broadcastFingerprints(wikiId, fps).catch(function(e) {
+            _log('[LAN Sync] Broadcast fingerprints error: ' + e);
+          });
+        });
       });
-    });
+    }
 
     // ── Periodic re-sync (5s safety net) ──────────────────────────────
     // Periodically re-broadcast fingerprints so peers detect diffs and
     // re-send missed changes.  Converges quickly then becomes a no-op
-    // once both sides have the same tiddler set.
+    // once both sides have the same tiddler set.  Paused between scheduled syncs.
     var resyncIntervalId = setInterval(function() {
+      if (!isLive()) return;
       try {
         var fps = collectFingerprints();
         broadcastFingerprints(wikiId, fps).catch(function(e) {
@@ -1536,7 +2127,7 @@
       var changed = false;
       var keys = Object.keys(deletionTombstones);
       for (var i = 0; i < keys.length; i++) {
-        if (deletionTombstones[keys[i]].time && now - deletionTombstones[keys[i]].time > TOMBSTONE_MAX_AGE_MS) {
+        if (deletionTombstones[keys[i]].time && now - deletionTombstones[keys[i]].time > tombstoneMaxAgeMs) {
           delete deletionTombstones[keys[i]];
           changed = true;
         }
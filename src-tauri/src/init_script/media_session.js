@@ -0,0 +1,120 @@
+// Media session - report audio and video played in the wiki to the OS media
+// session (media_session.rs: MPRIS, SMTC, Now Playing) and apply hardware
+// media keys and the OS media controls (media-session-action events) to the
+// element that played last.
+(function(TD) {
+    'use strict';
+
+    // Only run in wiki windows of the desktop app
+    if (!window.__WIKI_PATH__ || window.__WINDOW_LABEL__ === 'main') return;
+    if (typeof window.TiddlyDesktopSync !== 'undefined') return; // Android
+
+    // "Previous" restarts the current item after this many seconds
+    var RESTART_THRESHOLD = 3;
+
+    var current = null;
+
+    function invoke(cmd, args) {
+        return window.__TAURI__.core.invoke(cmd, args);
+    }
+
+    function isMedia(el) {
+        return !!el && (el.tagName === 'AUDIO' || el.tagName === 'VIDEO');
+    }
+
+    function allMedia() {
+        return Array.prototype.slice.call(document.querySelectorAll('audio, video'));
+    }
+
+    // The element's title, the tiddler it is shown in, or its file name
+    function mediaTitle(el) {
+        if (el.title) return el.title;
+        var frame = el.closest('[data-tiddler-title]');
+        if (frame) return frame.getAttribute('data-tiddler-title');
+        var name = (el.currentSrc || el.src || '').split(/[?#]/)[0].split('/').pop();
+        try {
+            name = decodeURIComponent(name);
+        } catch (e) {}
+        return name || document.title;
+    }
+
+    function finite(value) {
+        return isFinite(value) ? value : null;
+    }
+
+    function report(playback) {
+        if (!current || !window.__TAURI__ || !window.__TAURI__.core) return;
+        invoke('media_session_update', {
+            state: {
+                playback: playback,
+                title: mediaTitle(current),
+                album: document.title,
+                duration: finite(current.duration),
+                position: finite(current.currentTime)
+            }
+        }).catch(function() {});
+    }
+
+    function play(el) {
+        var result = el.play();
+        if (result && result.catch) result.catch(function() {});
+    }
+
+    function step(delta) {
+        var media = allMedia();
+        var next = media[media.indexOf(current) + delta];
+        if (!next) return;
+        current.pause();
+        play(next);
+    }
+
+    function handleAction(action) {
+        if (!current || !document.contains(current)) {
+            current = allMedia()[0] || null;
+            if (!current) return;
+        }
+        switch (action.action) {
+            case 'play': play(current); break;
+            case 'pause': current.pause(); break;
+            case 'toggle': if (current.paused) play(current); else current.pause(); break;
+            case 'stop':
+                current.pause();
+                current.currentTime = 0;
+                report('stopped');
+                break;
+            case 'next': step(1); break;
+            case 'previous':
+                if (current.currentTime > RESTART_THRESHOLD) current.currentTime = 0;
+                else step(-1);
+                break;
+            case 'seekBy': current.currentTime = Math.max(0, current.currentTime + action.offset); break;
+            case 'seekTo': current.currentTime = action.position; break;
+            case 'volume': current.volume = action.volume; break;
+        }
+    }
+
+    // Media events don't bubble, so listen in the capture phase
+    document.addEventListener('play', function(e) {
+        if (!isMedia(e.target)) return;
+        current = e.target;
+        report('playing');
+    }, true);
+    document.addEventListener('pause', function(e) {
+        if (e.target === current) report(current.ended ? 'stopped' : 'paused');
+    }, true);
+    document.addEventListener('seeked', function(e) {
+        if (e.target === current) report(current.paused ? 'paused' : 'playing');
+    }, true);
+
+    function setup() {
+        if (!window.__TAURI__ || !window.__TAURI__.event) {
+            setTimeout(setup, 100);
+            return;
+        }
+        window.__TAURI__.event.listen('media-session-action', function(event) {
+            handleAction(event.payload || {});
+        });
+    }
+
+    setup();
+})(window.TiddlyDesktop = window.TiddlyDesktop || {});
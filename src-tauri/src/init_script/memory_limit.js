@@ -0,0 +1,62 @@
+// TiddlyDesktop Initialization Script - Memory Limit Module
+// Provides: window.__tdMemoryLimit(info) - called from Rust when this wiki's process
+// uses more memory than the configured limit; saves the wiki and restarts it
+(function(TD) {
+    'use strict';
+
+    // How long to wait for the save to finish before giving up
+    var SAVE_TIMEOUT = 60000;
+    var SAVE_POLL_INTERVAL = 500;
+
+    var restarting = false;
+
+    function isDirty() {
+        if (typeof $tw === 'undefined' || !$tw.wiki) return false;
+        if (typeof $tw.wiki.isDirty === 'function') return $tw.wiki.isDirty();
+        if ($tw.saverHandler && typeof $tw.saverHandler.isDirty === 'function') return $tw.saverHandler.isDirty();
+        if ($tw.saverHandler && typeof $tw.saverHandler.numChanges === 'function') return $tw.saverHandler.numChanges() > 0;
+        if ($tw.syncer && typeof $tw.syncer.isDirty === 'function') return $tw.syncer.isDirty();
+        return false;
+    }
+
+    function saveThenRestart() {
+        if (restarting) return;
+        restarting = true;
+        var invoke = window.__TAURI__.core.invoke;
+
+        // Folder wikis are saved by the syncer on its own; single-file wikis need a save
+        if (isDirty() && !$tw.syncer && $tw.rootWidget) {
+            $tw.rootWidget.dispatchEvent({ type: 'tm-save-wiki' });
+        }
+
+        var started = Date.now();
+        (function waitForSave() {
+            if (isDirty() && Date.now() - started < SAVE_TIMEOUT) {
+                setTimeout(waitForSave, SAVE_POLL_INTERVAL);
+                return;
+            }
+            if (isDirty()) {
+                // Never throw away unsaved changes
+                restarting = false;
+                TD.showConfirmModal('The wiki could not be saved, so it was not restarted.', function() {});
+                return;
+            }
+            invoke('ipc_restart_wiki').catch(function(err) {
+                restarting = false;
+                console.error('[TiddlyDesktop] Restart failed:', err);
+            });
+        })();
+    }
+
+    window.__tdMemoryLimit = function(info) {
+        if (window.__SINGLE_TIDDLER_TITLE__ || restarting) return;
+        if (info.autoRestart) {
+            saveThenRestart();
+            return;
+        }
+        TD.showConfirmModal('This wiki is using ' + info.usedMb + ' MB of memory (limit: ' + info.limitMb +
+            ' MB). Save and restart it now to free memory?', function(confirmed) {
+            if (confirmed) saveThenRestart();
+        });
+    };
+})(window.TiddlyDesktop = window.TiddlyDesktop || {});
@@ -0,0 +1,118 @@
+// MQTT - connects this wiki to a broker (mqtt.rs) when
+// $:/config/TiddlyDesktopRS/MQTT/Broker is set (mqtt://host:port or mqtts://...):
+// - $:/config/TiddlyDesktopRS/MQTT/Topics: topics to subscribe to (title list,
+//   wildcards allowed, e.g. home/+/temperature [[home/living room/#]])
+// - $:/config/TiddlyDesktopRS/MQTT/Username and .../Password (optional; they
+//   are saved in the wiki like any tiddler)
+// - incoming messages go to $:/temp/TiddlyDesktopRS/MQTT/<topic> (text is the
+//   payload, fields topic, retained, received) and the
+//   th-tiddlydesktop-rs-mqtt-message hook
+// - $:/temp/TiddlyDesktopRS/MQTTStatus: "connected", "disconnected" or
+//   "error" (error field)
+// - tm-tiddlydesktop-rs-mqtt-publish (params: topic, payload, retain "yes",
+//   qos 0-2) and TiddlyDesktop.mqttPublish(topic, payload, options)
+(function(TD) {
+    'use strict';
+
+    // Only run in wiki windows of the desktop app
+    if (!window.__WIKI_PATH__ || window.__WINDOW_LABEL__ === 'main') return;
+    if (typeof window.TiddlyDesktopSync !== 'undefined') return; // Android
+    // Single tiddler windows share the wiki with its main window
+    if (window.__SINGLE_TIDDLER_TITLE__) return;
+
+    var CONFIG_PREFIX = '$:/config/TiddlyDesktopRS/MQTT/';
+    var MESSAGE_PREFIX = '$:/temp/TiddlyDesktopRS/MQTT/';
+    var STATUS_TIDDLER = '$:/temp/TiddlyDesktopRS/MQTTStatus';
+
+    var lastConfig = null;
+    var pending = null;
+
+    function invoke(cmd, args) {
+        return window.__TAURI__.core.invoke(cmd, args);
+    }
+
+    TD.mqttPublish = function(topic, payload, options) {
+        options = options || {};
+        return invoke('mqtt_publish', {
+            topic: topic,
+            payload: payload == null ? '' : String(payload),
+            retain: !!options.retain,
+            qos: options.qos == null ? null : parseInt(options.qos, 10) || 0
+        });
+    };
+
+    function setStatus(text, error) {
+        $tw.wiki.addTiddler(new $tw.Tiddler({ title: STATUS_TIDDLER, text: text, error: error || '' }));
+    }
+
+    function config() {
+        return {
+            broker: $tw.wiki.getTiddlerText(CONFIG_PREFIX + 'Broker', '').trim(),
+            username: $tw.wiki.getTiddlerText(CONFIG_PREFIX + 'Username', '').trim() || null,
+            password: $tw.wiki.getTiddlerText(CONFIG_PREFIX + 'Password', '') || null,
+            topics: $tw.utils.parseStringArray($tw.wiki.getTiddlerText(CONFIG_PREFIX + 'Topics', '')) || []
+        };
+    }
+
+    function applyConfig() {
+        pending = null;
+        var current = config();
+        var json = JSON.stringify(current);
+        if (json === lastConfig) return;
+        lastConfig = json;
+        if (!current.broker) {
+            invoke('mqtt_disconnect').then(function() { setStatus('disconnected'); });
+            return;
+        }
+        invoke('mqtt_connect', current).catch(function(err) {
+            console.error('[TiddlyDesktop] MQTT connection failed:', err);
+            setStatus('error', String(err));
+        });
+    }
+
+    function receive(message) {
+        $tw.wiki.addTiddler(new $tw.Tiddler({
+            title: MESSAGE_PREFIX + message.topic,
+            text: message.payload,
+            topic: message.topic,
+            retained: message.retain ? 'yes' : 'no',
+            received: $tw.utils.stringifyDate(new Date())
+        }));
+        $tw.hooks.invokeHook('th-tiddlydesktop-rs-mqtt-message', message);
+    }
+
+    function setup() {
+        if (typeof $tw === 'undefined' || !$tw.wiki || !$tw.rootWidget || !window.__TAURI__) {
+            setTimeout(setup, 100);
+            return;
+        }
+        window.__TAURI__.event.listen('mqtt-message', function(event) {
+            if (event.payload) receive(event.payload);
+        });
+        window.__TAURI__.event.listen('mqtt-status', function(event) {
+            var status = event.payload || {};
+            setStatus(status.connected ? 'connected' : 'error', status.error);
+        });
+        $tw.wiki.addEventListener('change', function(changes) {
+            var relevant = Object.keys(changes).some(function(title) {
+                return title.indexOf(CONFIG_PREFIX) === 0;
+            });
+            if (relevant && !pending) pending = setTimeout(applyConfig, 1000);
+        });
+        $tw.rootWidget.addEventListener('tm-tiddlydesktop-rs-mqtt-publish', function(event) {
+            var params = event.paramObject || {};
+            if (!params.topic) return false;
+            TD.mqttPublish(params.topic, params.payload, {
+                retain: params.retain === 'yes',
+                qos: params.qos
+            }).catch(function(err) {
+                console.error('[TiddlyDesktop] MQTT publish failed:', err);
+            });
+            return false;
+        });
+        if (config().broker) applyConfig();
+        else lastConfig = JSON.stringify(config());
+    }
+
+    setup();
+})(window.TiddlyDesktop = window.TiddlyDesktop || {});
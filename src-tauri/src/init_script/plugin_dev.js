@@ -0,0 +1,102 @@
+// TiddlyDesktop Initialization Script - Plugin Development Module
+// Provides: reloading folder wikis opened for plugin development when their
+// plugin changes, and an overlay saying why when the plugin fails to load
+// (see plugin_dev.rs)
+(function() {
+    'use strict';
+
+    // Only folder wiki windows load a plugin from its source folder
+    if (!window.__WIKI_PATH__ || !window.__TD_FOLDER_WIKI__) return;
+    if (window.__SINGLE_TIDDLER_TITLE__) return;
+
+    // How long reloading waits for the syncer to save changes to the wiki
+    var SAVE_TIMEOUT = 10000;
+    var SAVE_POLL_INTERVAL = 250;
+
+    var overlay = null;
+
+    function isDirty() {
+        if (typeof $tw === 'undefined' || !$tw.syncer) return false;
+        return typeof $tw.syncer.isDirty === 'function' && $tw.syncer.isDirty();
+    }
+
+    function showError(dir, message) {
+        if (!overlay) {
+            overlay = document.createElement('div');
+            overlay.id = 'td-plugin-dev-error';
+            overlay.style.cssText = 'position:fixed;top:0;left:0;right:0;bottom:0;z-index:10000;' +
+                'background:rgba(0,0,0,0.6);display:flex;align-items:center;justify-content:center;' +
+                'font-family:system-ui,sans-serif;';
+            var box = document.createElement('div');
+            box.style.cssText = 'max-width:80%;max-height:80%;overflow:auto;background:#fff;color:#333;' +
+                'border-top:4px solid #d32f2f;border-radius:6px;padding:16px 20px;' +
+                'box-shadow:0 4px 16px rgba(0,0,0,0.3);';
+            var heading = document.createElement('div');
+            heading.style.cssText = 'font-size:16px;font-weight:600;color:#d32f2f;margin-bottom:8px;';
+            heading.textContent = 'The plugin failed to load';
+            var folder = document.createElement('div');
+            folder.style.cssText = 'font-size:12px;color:#666;margin-bottom:12px;word-break:break-all;';
+            var details = document.createElement('pre');
+            details.style.cssText = 'font-size:13px;white-space:pre-wrap;margin:0 0 12px 0;';
+            var hint = document.createElement('div');
+            hint.style.cssText = 'font-size:13px;color:#666;';
+            hint.textContent = 'The wiki reloads once the plugin is fixed.';
+            var dismissBtn = document.createElement('button');
+            dismissBtn.textContent = 'Dismiss';
+            dismissBtn.style.cssText = 'margin-top:12px;padding:4px 12px;border:1px solid #ccc;' +
+                'border-radius:4px;background:#f5f5f5;color:#333;cursor:pointer;font-size:13px;';
+            dismissBtn.onclick = hideError;
+            box.appendChild(heading);
+            box.appendChild(folder);
+            box.appendChild(details);
+            box.appendChild(hint);
+            box.appendChild(dismissBtn);
+            overlay.appendChild(box);
+            overlay.__folder = folder;
+            overlay.__details = details;
+        }
+        overlay.__folder.textContent = dir || '';
+        overlay.__details.textContent = message;
+        if (!overlay.parentNode) document.body.appendChild(overlay);
+    }
+
+    function hideError() {
+        if (overlay && overlay.parentNode) overlay.parentNode.removeChild(overlay);
+    }
+
+    // Let the syncer save pending changes first, so reloading loses nothing
+    function reload() {
+        var started = Date.now();
+        (function waitForSave() {
+            if (isDirty() && Date.now() - started < SAVE_TIMEOUT) {
+                setTimeout(waitForSave, SAVE_POLL_INTERVAL);
+                return;
+            }
+            window.location.reload();
+        })();
+    }
+
+    function setup() {
+        if (!document.body || !window.__TAURI__ || !window.__TAURI__.core) {
+            setTimeout(setup, 100);
+            return;
+        }
+        window.__TAURI__.core.invoke('get_plugin_dev_status').then(function(status) {
+            if (!status) return;
+            if (status.error) showError(status.dir, status.error);
+            window.__TAURI__.event.listen('plugin-dev-changed', function(event) {
+                var error = event.payload && event.payload.error;
+                if (error) {
+                    showError(status.dir, error);
+                } else {
+                    hideError();
+                    reload();
+                }
+            });
+        }).catch(function() {
+            // Not a wiki folder process
+        });
+    }
+
+    setup();
+})();
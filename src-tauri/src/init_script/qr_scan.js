@@ -0,0 +1,229 @@
+// QR scan - scan QR codes and barcodes with the webcam; the frames are
+// decoded by qr_code.rs (decode_qr_frame):
+//   TiddlyDesktop.scanQrCode()        promise of the text (null if cancelled)
+//   tm-tiddlydesktop-rs-scan-qr-code  writes the text to a tiddler field
+//                                     (params: tiddler, field)
+// and draw QR codes (generate_qr_png):
+//   TiddlyDesktop.qrCodeDataUri(text)     promise of a PNG data URI
+//   tm-tiddlydesktop-rs-generate-qr-code  saves the code as an image tiddler
+//                                         (params: text, tiddler)
+// and the URLs other devices open this wiki with, when its server listens
+// beyond this computer (server_discovery.rs):
+//   TiddlyDesktop.serverUrls()            promise of the URLs (empty if none)
+//   tm-tiddlydesktop-rs-server-qr-code    saves a QR code of the first one as
+//                                         an image tiddler (params: tiddler)
+(function(TD) {
+    'use strict';
+
+    // Only run in wiki windows of the desktop app
+    if (!window.__WIKI_PATH__ || window.__WINDOW_LABEL__ === 'main') return;
+    if (typeof window.TiddlyDesktopSync !== 'undefined') return; // Android
+
+    var SCAN_INTERVAL = 250;
+    // Frames are scaled down to this size (and qr_code.rs MAX_SIDE)
+    var MAX_FRAME_SIDE = 800;
+    var DEFAULT_TIDDLER = '$:/temp/TiddlyDesktopRS/ScannedCode';
+    var DEFAULT_QR_TIDDLER = '$:/temp/TiddlyDesktopRS/QrCode';
+    var DEFAULT_SERVER_QR_TIDDLER = '$:/temp/TiddlyDesktopRS/ServerQrCode';
+    var STYLE_ID = 'td-qr-scan-styles';
+
+    var pending = null;
+
+    function invoke(cmd, args) {
+        return window.__TAURI__.core.invoke(cmd, args);
+    }
+
+    function addStyles() {
+        if (document.getElementById(STYLE_ID)) return;
+        var style = document.createElement('style');
+        style.id = STYLE_ID;
+        style.textContent =
+            '.td-qr-scan{position:fixed;inset:0;z-index:100000;background:rgba(0,0,0,.85);display:flex;' +
+            'flex-direction:column;align-items:center;justify-content:center;gap:12px;color:#fff;font:14px sans-serif;}' +
+            '.td-qr-scan video{max-width:90vw;max-height:70vh;border-radius:6px;background:#000;}' +
+            '.td-qr-scan button{background:#555;color:#fff;border:none;border-radius:3px;padding:6px 14px;font-size:14px;cursor:pointer;}';
+        document.head.appendChild(style);
+    }
+
+    function toBase64(bytes) {
+        var binary = '';
+        for (var i = 0; i < bytes.length; i += 0x8000) {
+            binary += String.fromCharCode.apply(null, bytes.subarray(i, i + 0x8000));
+        }
+        return btoa(binary);
+    }
+
+    // Grayscale copy of the current video frame (one byte per pixel)
+    function grabFrame(video, canvas) {
+        var scale = Math.min(1, MAX_FRAME_SIDE / Math.max(video.videoWidth, video.videoHeight));
+        var width = Math.round(video.videoWidth * scale);
+        var height = Math.round(video.videoHeight * scale);
+        if (!width || !height) return null;
+        canvas.width = width;
+        canvas.height = height;
+        var context = canvas.getContext('2d', { willReadFrequently: true });
+        context.drawImage(video, 0, 0, width, height);
+        var rgba = context.getImageData(0, 0, width, height).data;
+        var luma = new Uint8Array(width * height);
+        for (var i = 0, j = 0; j < luma.length; i += 4, j++) {
+            luma[j] = (rgba[i] * 77 + rgba[i + 1] * 150 + rgba[i + 2] * 29) >> 8;
+        }
+        return { width: width, height: height, luma: luma };
+    }
+
+    function scan() {
+        if (!navigator.mediaDevices || !navigator.mediaDevices.getUserMedia) {
+            return Promise.reject(new Error('The camera is not available in this window'));
+        }
+        addStyles();
+        var overlay = document.createElement('div');
+        overlay.className = 'td-qr-scan';
+        var video = document.createElement('video');
+        video.muted = true;
+        video.setAttribute('playsinline', '');
+        var hint = document.createElement('div');
+        hint.textContent = 'Hold a QR code or barcode in front of the camera';
+        var cancel = document.createElement('button');
+        cancel.textContent = 'Cancel';
+        overlay.appendChild(video);
+        overlay.appendChild(hint);
+        overlay.appendChild(cancel);
+        document.body.appendChild(overlay);
+        var canvas = document.createElement('canvas');
+
+        return new Promise(function(resolve, reject) {
+            var stream = null;
+            var timer = null;
+            var busy = false;
+            var done = false;
+
+            function finish(text, err) {
+                if (done) return;
+                done = true;
+                clearInterval(timer);
+                if (stream) stream.getTracks().forEach(function(track) { track.stop(); });
+                document.removeEventListener('keydown', onKeyDown, true);
+                overlay.remove();
+                if (err) reject(err);
+                else resolve(text);
+            }
+
+            function onKeyDown(event) {
+                if (event.key !== 'Escape') return;
+                event.preventDefault();
+                event.stopPropagation();
+                finish(null);
+            }
+
+            function tick() {
+                if (busy || done || video.readyState < 2) return;
+                var frame = grabFrame(video, canvas);
+                if (!frame) return;
+                busy = true;
+                invoke('decode_qr_frame', {
+                    width: frame.width,
+                    height: frame.height,
+                    lumaBase64: toBase64(frame.luma)
+                }).then(function(text) {
+                    busy = false;
+                    if (typeof text === 'string') finish(text);
+                }, function(err) {
+                    busy = false;
+                    finish(null, err);
+                });
+            }
+
+            cancel.addEventListener('click', function() { finish(null); });
+            document.addEventListener('keydown', onKeyDown, true);
+            navigator.mediaDevices.getUserMedia({ video: { facingMode: 'environment' }, audio: false }).then(function(s) {
+                stream = s;
+                if (done) {
+                    stream.getTracks().forEach(function(track) { track.stop(); });
+                    return;
+                }
+                video.srcObject = stream;
+                var playing = video.play();
+                if (playing && playing.catch) playing.catch(function() {});
+                timer = setInterval(tick, SCAN_INTERVAL);
+            }, function(err) {
+                finish(null, err);
+            });
+        });
+    }
+
+    TD.scanQrCode = function() {
+        if (!pending) {
+            pending = scan();
+            pending.then(function() { pending = null; }, function() { pending = null; });
+        }
+        return pending;
+    };
+
+    // Base64 PNG of the QR code for text
+    function qrCodeBase64(text) {
+        return invoke('generate_qr_png', { text: String(text) }).then(function(png) {
+            return toBase64(new Uint8Array(png));
+        });
+    }
+
+    TD.qrCodeDataUri = function(text) {
+        return qrCodeBase64(text).then(function(base64) {
+            return 'data:image/png;base64,' + base64;
+        });
+    };
+
+    TD.serverUrls = function() {
+        return invoke('get_server_urls', { wikiPath: window.__WIKI_PATH__ });
+    };
+
+    function setup() {
+        if (typeof $tw === 'undefined' || !$tw.wiki || !$tw.rootWidget || !window.__TAURI__) {
+            setTimeout(setup, 100);
+            return;
+        }
+        $tw.rootWidget.addEventListener('tm-tiddlydesktop-rs-scan-qr-code', function(event) {
+            var params = event.paramObject || {};
+            var title = params.tiddler || DEFAULT_TIDDLER;
+            var field = params.field || 'text';
+            TD.scanQrCode().then(function(text) {
+                if (text === null) return;
+                var fields = { title: title };
+                fields[field] = text;
+                $tw.wiki.addTiddler(new $tw.Tiddler($tw.wiki.getTiddler(title), fields, $tw.wiki.getModificationFields()));
+            }).catch(function(err) {
+                console.error('[TiddlyDesktop] QR scan failed:', err);
+                alert('QR scan failed: ' + ((err && err.message) || err));
+            });
+            return false;
+        });
+        $tw.rootWidget.addEventListener('tm-tiddlydesktop-rs-generate-qr-code', function(event) {
+            var params = event.paramObject || {};
+            var text = params.text || event.param;
+            if (!text) return false;
+            var title = params.tiddler || DEFAULT_QR_TIDDLER;
+            qrCodeBase64(text).then(function(base64) {
+                $tw.wiki.addTiddler(new $tw.Tiddler({ title: title, type: 'image/png', text: base64, 'qr-text': text }));
+            }).catch(function(err) {
+                console.error('[TiddlyDesktop] QR code generation failed:', err);
+            });
+            return false;
+        });
+        $tw.rootWidget.addEventListener('tm-tiddlydesktop-rs-server-qr-code', function(event) {
+            var params = event.paramObject || {};
+            var title = params.tiddler || DEFAULT_SERVER_QR_TIDDLER;
+            Promise.all([
+                TD.serverUrls(),
+                invoke('get_server_qr_png', { wikiPath: window.__WIKI_PATH__ })
+            ]).then(function(results) {
+                var base64 = toBase64(new Uint8Array(results[1]));
+                $tw.wiki.addTiddler(new $tw.Tiddler({ title: title, type: 'image/png', text: base64, 'qr-text': results[0][0] }));
+            }).catch(function(err) {
+                console.error('[TiddlyDesktop] Server QR code failed:', err);
+                alert('No QR code: ' + ((err && err.message) || err));
+            });
+            return false;
+        });
+    }
+
+    setup();
+})(window.TiddlyDesktop = window.TiddlyDesktop || {});
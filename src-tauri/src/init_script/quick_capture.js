@@ -0,0 +1,91 @@
+// Quick capture - applies the tray quick actions queued for this wiki by
+// quick_actions.rs (take_quick_captures), at startup and when the main
+// process announces new ones (quick-captures-pending):
+// - new-tiddler: a new tiddler in the editor
+// - new-journal: today's journal ($:/config/NewJournal/*) in the editor
+// - paste-clipboard: the clipboard saved as a new tiddler
+// - open-tiddler: navigate to a tiddler (tiddlydesktop:// deep links)
+(function(TD) {
+    'use strict';
+
+    // Only run in wiki windows of the desktop app
+    if (!window.__WIKI_PATH__ || window.__WINDOW_LABEL__ === 'main') return;
+    if (typeof window.TiddlyDesktopSync !== 'undefined') return; // Android
+    // Single tiddler windows share the wiki with its main window
+    if (window.__SINGLE_TIDDLER_TITLE__) return;
+
+    function story() {
+        return new $tw.Story({ wiki: $tw.wiki });
+    }
+
+    // Open a tiddler in the editor (a new one when it doesn't exist)
+    function editTiddler(title, fields) {
+        var draftTitle = $tw.wiki.findDraft(title);
+        if (!draftTitle) {
+            draftTitle = $tw.wiki.generateDraftTitle(title);
+            var existing = $tw.wiki.getTiddler(title);
+            $tw.wiki.addTiddler(new $tw.Tiddler(
+                existing ? existing.fields : fields,
+                { title: draftTitle, 'draft.title': title, 'draft.of': title },
+                $tw.wiki.getModificationFields()
+            ));
+        }
+        story().addToStory(draftTitle);
+        story().addToHistory(draftTitle);
+    }
+
+    function applyCapture(capture) {
+        var now = new Date();
+        switch (capture.kind) {
+            case 'new-tiddler':
+                editTiddler($tw.wiki.generateNewTitle($tw.language.getString('DefaultNewTiddlerTitle')), {});
+                break;
+            case 'new-journal':
+                var titleTemplate = $tw.wiki.getTiddlerText('$:/config/NewJournal/Title', 'DDth MMM YYYY');
+                editTiddler($tw.utils.formatDateString(now, titleTemplate), {
+                    tags: $tw.wiki.getTiddlerText('$:/config/NewJournal/Tags', ''),
+                    text: $tw.wiki.getTiddlerText('$:/config/NewJournal/Text', '')
+                });
+                break;
+            case 'paste-clipboard':
+                var title = $tw.wiki.generateNewTitle('Clipboard ' + $tw.utils.formatDateString(now, 'YYYY-0MM-0DD 0hh:0mm'));
+                $tw.wiki.addTiddler(new $tw.Tiddler(
+                    $tw.wiki.getCreationFields(),
+                    { title: title, type: capture.contentType || undefined, text: capture.text || '' },
+                    $tw.wiki.getModificationFields()
+                ));
+                story().navigateTiddler(title);
+                break;
+            case 'open-tiddler':
+                if (capture.text) {
+                    story().navigateTiddler(capture.text);
+                }
+                break;
+        }
+    }
+
+    function importCaptures() {
+        window.__TAURI__.core.invoke('take_quick_captures', { wikiPath: window.__WIKI_PATH__ }).then(function(captures) {
+            (captures || []).forEach(function(capture) {
+                try {
+                    applyCapture(capture);
+                } catch (e) {
+                    console.error('[TiddlyDesktop] Quick capture failed:', e);
+                }
+            });
+        }).catch(function(err) {
+            console.error('[TiddlyDesktop] Failed to load quick captures:', err);
+        });
+    }
+
+    function setup() {
+        if (typeof $tw === 'undefined' || !$tw.wiki || !$tw.rootWidget || !$tw.Story || !window.__TAURI__) {
+            setTimeout(setup, 100);
+            return;
+        }
+        window.__TAURI__.event.listen('quick-captures-pending', importCaptures);
+        importCaptures();
+    }
+
+    setup();
+})(window.TiddlyDesktop = window.TiddlyDesktop || {});
@@ -0,0 +1,54 @@
+// Serial ports - access to instruments and boards (serial.rs). The first use
+// asks the user whether this wiki may use serial ports.
+// - TiddlyDesktop.listSerialPorts() returns a promise of the ports
+//   ({ name, kind, manufacturer, product, serialNumber, vendorId, productId })
+// - TiddlyDesktop.openSerial(port, options) returns a promise of a port id
+//   (options: baudRate (9600), dataBits, parity "none"/"odd"/"even", stopBits)
+// - TiddlyDesktop.readSerial(id, options) returns a promise of the text that
+//   arrived (options: maxBytes, timeoutMs, binary for base64)
+// - TiddlyDesktop.writeSerial(id, data, options) (options: binary for base64)
+// - TiddlyDesktop.closeSerial(id)
+(function(TD) {
+    'use strict';
+
+    // Only run in wiki windows of the desktop app
+    if (!window.__WIKI_PATH__ || window.__WINDOW_LABEL__ === 'main') return;
+    if (typeof window.TiddlyDesktopSync !== 'undefined') return; // Android
+
+    function invoke(cmd, args) {
+        return window.__TAURI__.core.invoke(cmd, args);
+    }
+
+    TD.listSerialPorts = function() {
+        return invoke('list_serial_ports');
+    };
+
+    TD.openSerial = function(port, options) {
+        options = options || {};
+        return invoke('open_serial', {
+            port: port,
+            baudRate: options.baudRate || 9600,
+            dataBits: options.dataBits || null,
+            parity: options.parity || null,
+            stopBits: options.stopBits || null
+        });
+    };
+
+    TD.readSerial = function(id, options) {
+        options = options || {};
+        return invoke('read_serial', {
+            id: id,
+            maxBytes: options.maxBytes || null,
+            timeoutMs: options.timeoutMs == null ? null : options.timeoutMs,
+            binary: !!options.binary
+        });
+    };
+
+    TD.writeSerial = function(id, data, options) {
+        return invoke('write_serial', { id: id, data: String(data), binary: !!(options && options.binary) });
+    };
+
+    TD.closeSerial = function(id) {
+        return invoke('close_serial', { id: id });
+    };
+})(window.TiddlyDesktop = window.TiddlyDesktop || {});
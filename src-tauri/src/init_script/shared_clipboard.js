@@ -0,0 +1,186 @@
+// Shared clipboard - send the selection to a connected sync device and paste
+// text shared from other devices (opt-in, see lan_sync_set_shared_clipboard).
+//   Ctrl/Cmd+Alt+C (the "share-selection" accelerator) sends the selection to
+//   a peer of this wiki (with several peers, a banner asks which one).
+//   Text received from a peer is shown in a banner in the focused wiki window
+//   with "Paste as tiddler" and "Copy".
+(function(TD) {
+    'use strict';
+
+    // Only run in wiki windows of the desktop app
+    if (!window.__WIKI_PATH__ || window.__WINDOW_LABEL__ === 'main') return;
+    if (typeof window.TiddlyDesktopSync !== 'undefined') return; // Android
+
+    var PREVIEW_LENGTH = 80;
+    var NOTICE_MS = 3000;
+
+    var enabled = false;
+    var banner = null;
+    var noticeTimer = null;
+    var pending = null;
+
+    function invoke(cmd, args) {
+        return window.__TAURI__.core.invoke(cmd, args);
+    }
+
+    function loadEnabled() {
+        if (!window.__TAURI__ || !window.__TAURI__.core) {
+            setTimeout(loadEnabled, 100);
+            return;
+        }
+        invoke('lan_sync_get_shared_clipboard').then(function(value) {
+            enabled = !!value;
+        }).catch(function() {});
+    }
+
+    // --- Banner (same look as the sync conflict banner) ---
+
+    function buttonStyle(primary) {
+        return 'padding:4px 12px;border:1px solid #5778d8;border-radius:4px;cursor:pointer;' +
+            'font-size:13px;font-weight:500;' +
+            (primary ? 'background:#5778d8;color:#fff;' : 'background:transparent;color:#1f3a7a;');
+    }
+
+    function hideBanner() {
+        if (noticeTimer) {
+            clearTimeout(noticeTimer);
+            noticeTimer = null;
+        }
+        if (banner) banner.style.display = 'none';
+    }
+
+    // Show a message with optional buttons ({label, primary, action})
+    function showBanner(message, buttons, autoHide) {
+        if (!banner) {
+            banner = document.createElement('div');
+            banner.id = 'td-shared-clipboard-banner';
+            banner.style.cssText = 'position:fixed;top:0;left:0;right:0;z-index:9999;' +
+                'background:#e7eefc;color:#1f3a7a;border-bottom:2px solid #5778d8;' +
+                'padding:8px 16px;font-size:14px;font-family:system-ui,sans-serif;' +
+                'display:none;align-items:center;gap:8px;box-shadow:0 2px 4px rgba(0,0,0,0.1);';
+            document.body.appendChild(banner);
+        }
+        if (noticeTimer) {
+            clearTimeout(noticeTimer);
+            noticeTimer = null;
+        }
+        banner.textContent = '';
+
+        var textSpan = document.createElement('span');
+        textSpan.style.cssText = 'flex:1;overflow:hidden;text-overflow:ellipsis;white-space:nowrap;';
+        textSpan.textContent = message;
+        banner.appendChild(textSpan);
+
+        (buttons || []).forEach(function(b) {
+            var btn = document.createElement('button');
+            btn.textContent = b.label;
+            btn.style.cssText = buttonStyle(b.primary);
+            btn.onclick = function() {
+                hideBanner();
+                b.action();
+            };
+            banner.appendChild(btn);
+        });
+
+        var dismissBtn = document.createElement('button');
+        dismissBtn.textContent = '×';
+        dismissBtn.style.cssText = 'padding:2px 8px;border:none;background:transparent;' +
+            'color:#1f3a7a;cursor:pointer;font-size:18px;line-height:1;';
+        dismissBtn.onclick = hideBanner;
+        banner.appendChild(dismissBtn);
+
+        banner.style.display = 'flex';
+        if (autoHide) noticeTimer = setTimeout(hideBanner, NOTICE_MS);
+    }
+
+    // --- Sending ---
+
+    function peerName(peer) {
+        return peer.user_name ? peer.user_name + ' (' + peer.device_name + ')' : peer.device_name;
+    }
+
+    function selectedText() {
+        var el = document.activeElement;
+        if (el && (el.tagName === 'TEXTAREA' || el.tagName === 'INPUT') &&
+                typeof el.selectionStart === 'number' && el.selectionEnd > el.selectionStart) {
+            return el.value.substring(el.selectionStart, el.selectionEnd);
+        }
+        var sel = window.getSelection ? window.getSelection() : null;
+        return sel ? sel.toString() : '';
+    }
+
+    function sendTo(peer, text) {
+        invoke('lan_sync_share_clipboard', { deviceId: peer.device_id, text: text }).then(function() {
+            showBanner('📋 Sent to ' + peerName(peer), [], true);
+        }).catch(function(err) {
+            showBanner('📋 Could not send to ' + peerName(peer) + ': ' + err, [], true);
+        });
+    }
+
+    function shareSelection(text) {
+        var peers = typeof TD.getSyncPeers === 'function' ? TD.getSyncPeers() : [];
+        if (peers.length === 0) {
+            showBanner('📋 No connected device to send the selection to', [], true);
+        } else if (peers.length === 1) {
+            sendTo(peers[0], text);
+        } else {
+            showBanner('📋 Send selection to:', peers.map(function(peer) {
+                return { label: peerName(peer), action: function() { sendTo(peer, text); } };
+            }), false);
+        }
+    }
+
+    document.addEventListener('keydown', function(e) {
+        if (!enabled || typeof TD.matchesAccelerator !== 'function' || !TD.matchesAccelerator('share-selection', e)) return;
+        var text = selectedText();
+        if (!text) return;
+        e.preventDefault();
+        e.stopPropagation();
+        shareSelection(text);
+    }, true);
+
+    // --- Receiving ---
+
+    function pasteAsTiddler(from, text) {
+        if (typeof $tw === 'undefined' || !$tw.wiki) return;
+        var title = $tw.wiki.generateNewTitle('Clipboard from ' + from);
+        $tw.wiki.addTiddler(new $tw.Tiddler(
+            $tw.wiki.getCreationFields(),
+            { title: title, text: text },
+            $tw.wiki.getModificationFields()
+        ));
+        new $tw.Story({ wiki: $tw.wiki }).navigateTiddler(title);
+    }
+
+    function showReceived(data) {
+        var preview = data.text.replace(/\s+/g, ' ').trim();
+        if (preview.length > PREVIEW_LENGTH) preview = preview.substring(0, PREVIEW_LENGTH) + '…';
+        showBanner('📋 ' + data.from + ': ' + preview, [
+            { label: 'Paste as tiddler', primary: true, action: function() { pasteAsTiddler(data.from, data.text); } },
+            { label: 'Copy', action: function() { invoke('set_clipboard_content', { text: data.text }).catch(function() {}); } }
+        ], false);
+    }
+
+    // Every open wiki receives the text; it is shown in the window the user is in
+    window.addEventListener('td-clipboard-received', function(e) {
+        var data = e.detail || {};
+        if (!data.text) return;
+        if (document.hasFocus()) {
+            pending = null;
+            showReceived(data);
+        } else {
+            pending = data;
+        }
+    });
+
+    window.addEventListener('focus', function() {
+        loadEnabled();
+        if (pending) {
+            var data = pending;
+            pending = null;
+            showReceived(data);
+        }
+    });
+
+    loadEnabled();
+})(window.TiddlyDesktop = window.TiddlyDesktop || {});
@@ -253,6 +253,7 @@
             // TW5's built-in handler creates a <a download> element and clicks it,
             // which doesn't show a file chooser in Tauri webviews. We replace it
             // entirely with a direct invoke to Rust's download_file command.
+            // A "delimiter" param (";", "|" or "tab") overrides the wiki's CSV delimiter.
             (function() {
                 if ($tw.rootWidget.eventListeners) {
                     $tw.rootWidget.eventListeners['tm-download-file'] = [];
@@ -279,7 +280,9 @@
                     invoke('download_file', {
                         filename: filename,
                         content: text,
-                        contentType: paramObject.type || 'text/plain'
+                        contentType: paramObject.type || 'text/plain',
+                        wikiPath: window.__WIKI_PATH__ || null,
+                        csvDelimiter: paramObject.delimiter || null
                     }).then(function(path) {
                         console.log('[TiddlyDesktop] File saved to: ' + path);
                         if (typeof $tw !== 'undefined' && $tw.notifier) {
@@ -408,7 +411,8 @@
                         invoke('download_file', {
                             filename: filename,
                             content: reader.result,
-                            contentType: blob.type || 'text/plain'
+                            contentType: blob.type || 'text/plain',
+                            wikiPath: window.__WIKI_PATH__ || null
                         }).then(function(savedPath) {
                             console.log('[TiddlyDesktop] File saved to:', savedPath);
                             if (typeof $tw !== 'undefined' && $tw.notifier) {
@@ -439,7 +443,8 @@
                         invoke('download_file', {
                             filename: filename,
                             content: content,
-                            contentType: contentType
+                            contentType: contentType,
+                            wikiPath: window.__WIKI_PATH__ || null
                         }).catch(function(err) {
                             if (err !== 'Save cancelled') {
                                 console.error('[TiddlyDesktop] Failed to save file:', err);
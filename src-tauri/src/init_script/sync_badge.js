@@ -0,0 +1,93 @@
+// Sync status badge - shows the LAN/relay sync status of the wiki after the
+// native window title (see sync_badge.rs):
+//   conflict: conflict tiddlers are waiting to be resolved
+//   syncing:  changes were received or compared in the last few seconds
+//   synced:   peers are connected
+//   offline:  sync is enabled but no peer is connected
+// Driven by the events lan_sync.js dispatches and the peer count it keeps.
+(function() {
+    'use strict';
+
+    // Only run in wiki windows of the desktop app
+    if (!window.__WIKI_PATH__ || window.__WINDOW_LABEL__ === 'main') return;
+    if (typeof window.TiddlyDesktopSync !== 'undefined') return; // Android
+
+    var CONFLICT_PREFIX = '$:/TiddlyDesktopRS/Conflicts/';
+    var COUNT_TIDDLER = '$:/temp/tiddlydesktop/peer-count';
+    var SYNCING_MS = 3000;
+
+    var active = false;
+    var syncingUntil = 0;
+    var syncingTimer = null;
+    var lastStatus = null;
+
+    function hasConflicts() {
+        var found = false;
+        $tw.wiki.each(function(tiddler, title) {
+            if (!found && title.indexOf(CONFLICT_PREFIX) === 0) found = true;
+        });
+        return found;
+    }
+
+    function currentStatus() {
+        if (!active) return null;
+        if (hasConflicts()) return 'conflict';
+        if (Date.now() < syncingUntil) return 'syncing';
+        var peers = parseInt($tw.wiki.getTiddlerText(COUNT_TIDDLER, '0'), 10) || 0;
+        return peers > 0 ? 'synced' : 'offline';
+    }
+
+    function update() {
+        if (typeof $tw === 'undefined' || !$tw.wiki) return;
+        var status = currentStatus();
+        if (status === lastStatus) return;
+        lastStatus = status;
+        if (window.__TAURI__ && window.__TAURI__.core) {
+            window.__TAURI__.core.invoke('set_window_sync_status', {
+                label: window.__WINDOW_LABEL__,
+                status: status
+            }).catch(function(e) {
+                console.error('TiddlyDesktop: Failed to set sync status:', e);
+            });
+        }
+    }
+
+    window.addEventListener('collab-sync-activated', function() {
+        active = true;
+        update();
+    });
+
+    window.addEventListener('td-sync-deactivated', function() {
+        active = false;
+        update();
+    });
+
+    window.addEventListener('td-sync-activity', function() {
+        active = true;
+        syncingUntil = Date.now() + SYNCING_MS;
+        update();
+        if (syncingTimer) clearTimeout(syncingTimer);
+        syncingTimer = setTimeout(function() {
+            syncingTimer = null;
+            update();
+        }, SYNCING_MS + 50);
+    });
+
+    function watchWiki() {
+        if (typeof $tw === 'undefined' || !$tw.wiki || !$tw.wiki.addEventListener) {
+            setTimeout(watchWiki, 200);
+            return;
+        }
+        $tw.wiki.addEventListener('change', function(changes) {
+            for (var title in changes) {
+                if (title === COUNT_TIDDLER || title.indexOf(CONFLICT_PREFIX) === 0) {
+                    update();
+                    return;
+                }
+            }
+        });
+        update();
+    }
+
+    watchWiki();
+})();
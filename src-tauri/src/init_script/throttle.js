@@ -0,0 +1,48 @@
+// TiddlyDesktop Initialization Script - Throttle Module
+// Provides: window.__tdThrottle(on) - called from Rust when a wiki window has been
+// minimized/hidden for a while, and again when it is shown
+(function() {
+    'use strict';
+
+    var throttled = false;
+    var originalRaf = window.requestAnimationFrame;
+    var pendingFrames = [];
+
+    function setHidden(hidden) {
+        if (hidden) {
+            // Page Visibility API: well-behaved content stops animating by itself
+            Object.defineProperty(document, 'hidden', { configurable: true, get: function() { return true; } });
+            Object.defineProperty(document, 'visibilityState', { configurable: true, get: function() { return 'hidden'; } });
+        } else {
+            // Fall back to the real getters on Document.prototype
+            delete document.hidden;
+            delete document.visibilityState;
+        }
+        document.dispatchEvent(new Event('visibilitychange'));
+    }
+
+    window.__tdThrottle = function(on) {
+        if (on === throttled) return;
+        throttled = on;
+        if (on) {
+            var style = document.createElement('style');
+            style.id = 'td-throttle-style';
+            style.textContent = '*, *::before, *::after { animation-play-state: paused !important; }';
+            (document.head || document.documentElement).appendChild(style);
+            // Hold animation frames until the window is shown again
+            window.requestAnimationFrame = function(callback) {
+                pendingFrames.push(callback);
+                return 0;
+            };
+            setHidden(true);
+        } else {
+            var existing = document.getElementById('td-throttle-style');
+            if (existing) existing.remove();
+            window.requestAnimationFrame = originalRaf;
+            var frames = pendingFrames;
+            pendingFrames = [];
+            frames.forEach(function(callback) { originalRaf.call(window, callback); });
+            setHidden(false);
+        }
+    };
+})();
@@ -0,0 +1,97 @@
+// Time tracking - records the time this window is focused (record_time in
+// time_tracking.rs) when $:/config/TiddlyDesktopRS/TimeTracking is "yes".
+// With $:/config/TiddlyDesktopRS/TimeTracking/Tiddlers also "yes" the time
+// goes to the tiddler last navigated to. Time while the user is away
+// (system-idle from idle.rs) is left out.
+// - TiddlyDesktop.getTimeReport(range) returns a promise of the report
+//   (range: { from, to } in milliseconds since the epoch, both optional)
+(function(TD) {
+    'use strict';
+
+    // Only run in wiki windows of the desktop app
+    if (!window.__WIKI_PATH__ || window.__WINDOW_LABEL__ === 'main') return;
+    if (typeof window.TiddlyDesktopSync !== 'undefined') return; // Android
+
+    var CONFIG = '$:/config/TiddlyDesktopRS/TimeTracking';
+    var TIDDLERS_CONFIG = '$:/config/TiddlyDesktopRS/TimeTracking/Tiddlers';
+    // Running stretches are written every minute, so a crash loses little
+    var FLUSH_INTERVAL = 60000;
+    var MIN_ENTRY = 1000;
+
+    var focused = document.hasFocus();
+    var idle = false;
+    // { start, tiddler } while time is being counted
+    var current = null;
+
+    function invoke(cmd, args) {
+        return window.__TAURI__.core.invoke(cmd, args);
+    }
+
+    TD.getTimeReport = function(range) {
+        return invoke('get_time_report', { range: range || null });
+    };
+
+    function enabled() {
+        return $tw.wiki.getTiddlerText(CONFIG, 'no') === 'yes';
+    }
+
+    function currentTiddler() {
+        if ($tw.wiki.getTiddlerText(TIDDLERS_CONFIG, 'no') !== 'yes') return null;
+        var history = $tw.wiki.getTiddler('$:/HistoryList');
+        var title = history && history.fields['current-tiddler'];
+        if (!title) title = $tw.wiki.getTiddlerList('$:/StoryList')[0];
+        return title || null;
+    }
+
+    function stop(end) {
+        if (!current) return;
+        var entry = current;
+        current = null;
+        if (end - entry.start < MIN_ENTRY) return;
+        invoke('record_time', {
+            wikiPath: window.__WIKI_PATH__,
+            tiddler: entry.tiddler,
+            start: entry.start,
+            end: end
+        }).catch(function(err) {
+            console.error('[TiddlyDesktop] Failed to record time:', err);
+        });
+    }
+
+    function update() {
+        var active = focused && !idle && enabled();
+        var tiddler = active ? currentTiddler() : null;
+        if (current && (!active || current.tiddler !== tiddler)) stop(Date.now());
+        if (active && !current) current = { start: Date.now(), tiddler: tiddler };
+    }
+
+    function setup() {
+        if (typeof $tw === 'undefined' || !$tw.wiki || !$tw.rootWidget || !window.__TAURI__) {
+            setTimeout(setup, 100);
+            return;
+        }
+        window.addEventListener('focus', function() { focused = true; update(); });
+        window.addEventListener('blur', function() { focused = false; update(); });
+        window.addEventListener('beforeunload', function() { stop(Date.now()); });
+        window.__TAURI__.event.listen('system-idle', function(event) {
+            var payload = event.payload || {};
+            idle = !!payload.idle;
+            // The user left before the idle threshold was reached
+            if (idle && current) stop(Math.max(current.start, Date.now() - (payload.idleSeconds || 0) * 1000));
+            update();
+        });
+        $tw.wiki.addEventListener('change', function(changes) {
+            if (changes[CONFIG] || changes[TIDDLERS_CONFIG] || changes['$:/HistoryList'] || changes['$:/StoryList']) {
+                update();
+            }
+        });
+        setInterval(function() {
+            if (!current) return;
+            stop(Date.now());
+            update();
+        }, FLUSH_INTERVAL);
+        update();
+    }
+
+    setup();
+})(window.TiddlyDesktop = window.TiddlyDesktop || {});
@@ -0,0 +1,79 @@
+// Wiki commands - tiddlers tagged $:/tags/TiddlyDesktopRS/MenuCommand show up
+// in the tray menu under Wiki Commands (wiki_commands.rs). The caption field
+// is the menu label (the title when empty) and the text holds action widgets
+// that run in this window when the command is clicked, e.g.
+//   <$action-navigate $to="Inbox"/>
+// The list is sent again whenever command tiddlers change.
+(function(TD) {
+    'use strict';
+
+    // Only run in wiki windows of the desktop app
+    if (!window.__WIKI_PATH__ || window.__WINDOW_LABEL__ === 'main') return;
+    if (typeof window.TiddlyDesktopSync !== 'undefined') return; // Android
+    // Single tiddler windows share the wiki with its main window
+    if (window.__SINGLE_TIDDLER_TITLE__) return;
+
+    var TAG = '$:/tags/TiddlyDesktopRS/MenuCommand';
+    // The main process starts out without commands for this wiki
+    var lastSent = '[]';
+    var pending = null;
+
+    function commandTitles() {
+        return $tw.wiki.getTiddlersWithTag(TAG);
+    }
+
+    function sendCommands() {
+        pending = null;
+        var commands = commandTitles().map(function(title) {
+            var tiddler = $tw.wiki.getTiddler(title);
+            return { id: title, caption: (tiddler && tiddler.fields.caption) || title };
+        });
+        var json = JSON.stringify(commands);
+        if (json === lastSent) return;
+        lastSent = json;
+        window.__TAURI__.core.invoke('set_wiki_menu_commands', { commands: commands }).catch(function(err) {
+            console.error('[TiddlyDesktop] Failed to send wiki commands:', err);
+        });
+    }
+
+    // Messages sent by the actions should reach the story's navigator
+    function findNavigator(widget) {
+        if (widget.parseTreeNode && widget.parseTreeNode.type === 'navigator') return widget;
+        var children = widget.children || [];
+        for (var i = 0; i < children.length; i++) {
+            var found = findNavigator(children[i]);
+            if (found) return found;
+        }
+        return null;
+    }
+
+    function runCommand(title) {
+        var tiddler = $tw.wiki.getTiddler(title);
+        if (!tiddler || !tiddler.hasTag(TAG)) {
+            console.warn('[TiddlyDesktop] Unknown wiki command:', title);
+            return;
+        }
+        var widget = findNavigator($tw.rootWidget) || $tw.rootWidget;
+        widget.invokeActionString(tiddler.fields.text || '', widget, null, { currentTiddler: title });
+    }
+
+    function setup() {
+        if (typeof $tw === 'undefined' || !$tw.wiki || !$tw.rootWidget || !window.__TAURI__) {
+            setTimeout(setup, 100);
+            return;
+        }
+        window.__TAURI__.event.listen('wiki-menu-command', function(event) {
+            if (typeof event.payload === 'string') runCommand(event.payload);
+        });
+        $tw.wiki.addEventListener('change', function(changes) {
+            var relevant = Object.keys(changes).some(function(title) {
+                var tiddler = $tw.wiki.getTiddler(title);
+                return (tiddler && tiddler.hasTag(TAG)) || (lastSent.indexOf(JSON.stringify(title)) !== -1);
+            });
+            if (relevant && !pending) pending = setTimeout(sendCommands, 500);
+        });
+        sendCommands();
+    }
+
+    setup();
+})(window.TiddlyDesktop = window.TiddlyDesktop || {});
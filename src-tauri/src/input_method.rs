@@ -0,0 +1,199 @@
+//! Input method (IME) configuration for webview windows
+//!
+//! CJK input methods and compose/dead keys pass through the platform IME layer
+//! before they reach the webview. This module:
+//! - Applies the user's IME settings to each window (GTK IM module on Linux,
+//!   IMM context association on Windows)
+//! - Reports what the platform layer sees, to debug misbehaving input
+
+use std::collections::HashMap;
+
+use tauri::Manager;
+
+use crate::types::AppSettings;
+
+/// Environment variables that influence input method selection
+const IME_ENV_VARS: &[&str] = &[
+    "GTK_IM_MODULE",
+    "QT_IM_MODULE",
+    "XMODIFIERS",
+    "SDL_IM_MODULE",
+    "GLFW_IM_MODULE",
+    "XKB_DEFAULT_LAYOUT",
+    "LANG",
+    "LC_ALL",
+    "LC_CTYPE",
+];
+
+/// Current IME settings as shown in the settings UI
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct ImeSettings {
+    pub ime_module: Option<String>,
+    pub ime_disabled: bool,
+}
+
+/// Snapshot of the platform input layer, returned by `get_input_debug_info`
+#[derive(Clone, Debug, Default, serde::Serialize)]
+pub struct InputDebugInfo {
+    pub platform: String,
+    /// "wayland", "x11" or "unknown" (Linux only)
+    pub display_server: Option<String>,
+    pub locale: Option<String>,
+    /// IME-related environment variables that are set
+    pub env: HashMap<String, String>,
+    /// Configured IME module override (Linux)
+    pub configured_module: Option<String>,
+    /// IM module GTK is actually using (Linux)
+    pub gtk_im_module: Option<String>,
+    /// Whether the IME is detached from wiki windows (Windows)
+    pub ime_disabled: bool,
+    /// Active keyboard layout identifier (Windows)
+    pub keyboard_layout: Option<String>,
+    /// Whether the window has an IME context, and whether it is open (Windows)
+    pub ime_context_present: Option<bool>,
+    pub ime_open: Option<bool>,
+}
+
+/// Apply IME settings to a webview window
+pub fn configure_window(window: &tauri::WebviewWindow) {
+    let settings = crate::wiki_storage::load_app_settings(window.app_handle()).unwrap_or_default();
+    apply_settings(window, &settings);
+}
+
+#[cfg(target_os = "linux")]
+fn apply_settings(_window: &tauri::WebviewWindow, settings: &AppSettings) {
+    use gtk::glib::object::ObjectExt;
+
+    // WebKitGTK creates its input method context from the GTK IM module setting,
+    // so overriding it here affects the find bar and wiki content alike.
+    if let Some(module) = settings.ime_module.as_deref().filter(|m| !m.is_empty()) {
+        if let Some(gtk_settings) = gtk::Settings::default() {
+            gtk_settings.set_property("gtk-im-module", module);
+            eprintln!("[IME] Using GTK IM module '{}'", module);
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn apply_settings(window: &tauri::WebviewWindow, settings: &AppSettings) {
+    let disabled = settings.ime_disabled;
+    let _ = window.with_webview(move |webview| unsafe {
+        use windows::Win32::Foundation::HWND;
+        use windows::Win32::Globalization::HIMC;
+        use windows::Win32::UI::Input::Ime::{ImmAssociateContextEx, IACE_CHILDREN, IACE_DEFAULT};
+
+        let controller = webview.controller();
+        let mut hwnd = HWND::default();
+        let _ = controller.ParentWindow(&mut hwnd);
+
+        // IACE_DEFAULT restores the default IME context for the window and the
+        // WebView2 child windows; a NULL context with IACE_CHILDREN detaches it.
+        let flags = if disabled { IACE_CHILDREN } else { IACE_CHILDREN | IACE_DEFAULT };
+        let ok = ImmAssociateContextEx(hwnd, HIMC::default(), flags);
+        if !ok.as_bool() {
+            eprintln!("[IME] ImmAssociateContextEx failed for HWND {:?}", hwnd);
+        }
+    });
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+fn apply_settings(_window: &tauri::WebviewWindow, _settings: &AppSettings) {
+    // macOS/Android: the system text input client is used as-is
+}
+
+/// Get the current IME settings
+#[tauri::command]
+pub fn get_ime_settings(app: tauri::AppHandle) -> Result<ImeSettings, String> {
+    let settings = crate::wiki_storage::load_app_settings(&app)?;
+    Ok(ImeSettings {
+        ime_module: settings.ime_module,
+        ime_disabled: settings.ime_disabled,
+    })
+}
+
+/// Save IME settings and apply them to this process's windows.
+/// Wiki windows in other processes pick them up when they are next opened.
+#[tauri::command]
+pub fn set_ime_settings(app: tauri::AppHandle, ime_module: Option<String>, ime_disabled: bool) -> Result<(), String> {
+    let mut settings = crate::wiki_storage::load_app_settings(&app)?;
+    settings.ime_module = ime_module.filter(|m| !m.trim().is_empty()).map(|m| m.trim().to_string());
+    settings.ime_disabled = ime_disabled;
+    crate::wiki_storage::save_app_settings(&app, &settings)?;
+
+    for window in app.webview_windows().values() {
+        apply_settings(window, &settings);
+    }
+    Ok(())
+}
+
+/// Report what the platform input layer sees for this window
+#[tauri::command]
+pub async fn get_input_debug_info(window: tauri::WebviewWindow) -> Result<InputDebugInfo, String> {
+    let settings = crate::wiki_storage::load_app_settings(window.app_handle()).unwrap_or_default();
+
+    let mut info = InputDebugInfo {
+        platform: std::env::consts::OS.to_string(),
+        locale: sys_locale::get_locale(),
+        configured_module: settings.ime_module.clone(),
+        ime_disabled: settings.ime_disabled,
+        ..Default::default()
+    };
+    for var in IME_ENV_VARS {
+        if let Ok(value) = std::env::var(var) {
+            info.env.insert(var.to_string(), value);
+        }
+    }
+
+    collect_platform_info(&window, &mut info).await;
+    Ok(info)
+}
+
+#[cfg(target_os = "linux")]
+async fn collect_platform_info(window: &tauri::WebviewWindow, info: &mut InputDebugInfo) {
+    info.display_server = Some(match crate::drag_drop::native_dnd::get_display_server() {
+        crate::drag_drop::native_dnd::DisplayServer::Wayland => "wayland",
+        crate::drag_drop::native_dnd::DisplayServer::X11 => "x11",
+        crate::drag_drop::native_dnd::DisplayServer::Unknown => "unknown",
+    }.to_string());
+
+    // GTK objects must be touched on the main thread
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    let _ = window.run_on_main_thread(move || {
+        use gtk::glib::object::ObjectExt;
+        let module = gtk::Settings::default()
+            .and_then(|s| s.property::<Option<String>>("gtk-im-module"));
+        let _ = tx.send(module);
+    });
+    info.gtk_im_module = rx.await.ok().flatten();
+}
+
+#[cfg(target_os = "windows")]
+async fn collect_platform_info(window: &tauri::WebviewWindow, info: &mut InputDebugInfo) {
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    let _ = window.with_webview(move |webview| unsafe {
+        use windows::Win32::Foundation::HWND;
+        use windows::Win32::UI::Input::Ime::{ImmGetContext, ImmGetOpenStatus, ImmReleaseContext};
+        use windows::Win32::UI::Input::KeyboardAndMouse::GetKeyboardLayout;
+
+        let controller = webview.controller();
+        let mut hwnd = HWND::default();
+        let _ = controller.ParentWindow(&mut hwnd);
+
+        let layout = format!("{:08X}", GetKeyboardLayout(0).0 as usize);
+        let himc = ImmGetContext(hwnd);
+        let present = !himc.is_invalid();
+        let open = present && ImmGetOpenStatus(himc).as_bool();
+        if present {
+            let _ = ImmReleaseContext(hwnd, himc);
+        }
+        let _ = tx.send((layout, present, open));
+    });
+    if let Ok((layout, present, open)) = rx.await {
+        info.keyboard_layout = Some(layout);
+        info.ime_context_present = Some(present);
+        info.ime_open = Some(open);
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+async fn collect_platform_info(_window: &tauri::WebviewWindow, _info: &mut InputDebugInfo) {}
@@ -0,0 +1,80 @@
+//! Detection of a second app instance
+//!
+//! Two instances (e.g. a portable and an installed one) would fight over the
+//! IPC port, the LAN sync ports and possibly the same data directory. When the
+//! IPC port is already taken at startup, the new instance hands the wikis from
+//! its command line to the running instance and exits.
+//!
+//! If the running instance can't be reached (it belongs to another user, or
+//! its data directory is unknown to us), this instance starts in secondary
+//! mode instead: no IPC hub, no LAN sync, no adoption of wiki processes, and a
+//! warning explaining why.
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::ipc;
+
+/// Bundle identifier from tauri.conf.json (names the system data directory)
+const APP_IDENTIFIER: &str = "com.burningtreec.tiddlydesktop-rs";
+
+static SECONDARY: AtomicBool = AtomicBool::new(false);
+
+/// Whether this instance runs next to another one (see module docs)
+pub fn is_secondary() -> bool {
+    SECONDARY.load(Ordering::Relaxed)
+}
+
+/// Data directory of the installed (not portable) app
+pub fn system_data_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|d| d.join(APP_IDENTIFIER))
+}
+
+/// Data directories a running instance may have published its IPC session in:
+/// ours when portable, and the installed app's
+fn candidate_data_dirs() -> Vec<PathBuf> {
+    let mut candidates: Vec<PathBuf> = Vec::new();
+    candidates.extend(crate::portable_data_dir());
+    candidates.extend(system_data_dir());
+    candidates.dedup();
+    candidates
+}
+
+/// Called when the IPC port is taken: forward our command-line wikis to the
+/// running instance. Returns true if it took them (this instance should exit);
+/// otherwise switches this instance to secondary mode.
+pub fn hand_off_or_run_secondary() -> bool {
+    let paths = crate::file_open::command_line_wikis();
+    for dir in candidate_data_dirs() {
+        let Some(token) = crate::process_registry::read_session_token(&dir) else {
+            continue;
+        };
+        match ipc::hand_off(paths.clone(), token) {
+            Ok(true) => {
+                eprintln!("[TiddlyDesktop] Handed {} wiki(s) to the running instance", paths.len());
+                return true;
+            }
+            Ok(false) => {}
+            Err(e) => eprintln!("[TiddlyDesktop] Hand-off to running instance failed: {}", e),
+        }
+    }
+    eprintln!("[TiddlyDesktop] Another instance holds the IPC port, running in secondary mode");
+    SECONDARY.store(true, Ordering::Relaxed);
+    false
+}
+
+/// Tell the user why LAN sync and cross-window features are off
+pub fn show_secondary_warning(app: &tauri::AppHandle) {
+    use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+    app.dialog()
+        .message(
+            "Another TiddlyDesktop instance is already running and could not take over.\n\n\
+             This instance runs in secondary mode: LAN sync is off and its wiki windows \
+             are not coordinated with each other. Close the other instance and restart \
+             to use all features.",
+        )
+        .kind(MessageDialogKind::Warning)
+        .title("TiddlyDesktop")
+        .buttons(MessageDialogButtons::Ok)
+        .show(|_| {});
+}
@@ -97,6 +97,14 @@ pub enum IpcMessage {
     OpenWiki {
         path: String,
     },
+    /// A second app instance handing its command-line wikis and deep links to
+    /// the running one (empty `paths`: just show the landing page). Not
+    /// preceded by Register.
+    HandOff {
+        paths: Vec<String>,
+        /// Authentication token of the running instance
+        auth_token: String,
+    },
     /// Request to open a tiddler in a new window
     OpenTiddlerWindow {
         wiki_path: String,
@@ -143,6 +151,83 @@ pub enum IpcMessage {
         wiki_path: String,
         favicon: Option<String>,
     },
+    /// Main process → wiki process: the wiki exceeds the soft memory limit
+    MemoryLimitExceeded {
+        wiki_path: String,
+        used_mb: u64,
+        limit_mb: u64,
+        /// Save and restart without asking
+        auto_restart: bool,
+    },
+    /// Main process → wiki process: the window icon of the wiki changed
+    /// (custom icon, or the favicon when the custom icon was removed)
+    WikiIcon {
+        wiki_path: String,
+        icon: Option<String>,
+    },
+    /// Main process → wiki process: mute or unmute the wiki's windows (tray)
+    SetMuted {
+        wiki_path: String,
+        muted: bool,
+    },
+    /// Main process → wiki process: tray quick action captures are waiting
+    /// for the wiki (quick_actions.rs)
+    ImportCaptures {
+        wiki_path: String,
+    },
+    /// Main process → wiki process: run a wiki-defined menu command clicked in
+    /// the tray (wiki_commands.rs)
+    RunWikiMenuCommand {
+        wiki_path: String,
+        command_id: String,
+    },
+    /// Main process → wiki process: open the developer tools of the wiki's
+    /// windows (devtools.rs)
+    OpenDevtools {
+        wiki_path: String,
+    },
+    /// Wiki process → main process: reopen this wiki once the process has exited
+    RestartWiki {
+        wiki_path: String,
+    },
+    /// Wiki process → main process: reopen the most recently closed wiki
+    ReopenClosedWiki,
+    /// Wiki process → main process: show the quick switcher
+    ShowQuickSwitcher,
+    /// Wiki process → main process: the wiki's menu commands for the tray
+    WikiMenuCommands {
+        wiki_path: String,
+        commands: Vec<crate::wiki_commands::MenuCommand>,
+    },
+    /// Wiki process → main process: a window of the wiki logged JavaScript
+    /// errors (js_errors.rs)
+    JsErrorsReported {
+        wiki_path: String,
+    },
+    /// Main process → wiki process: carry out an automation API request in
+    /// the wiki's window (automation.rs)
+    AutomationRequest {
+        wiki_path: String,
+        request_id: u64,
+        action: String,
+        params_json: String,
+    },
+    /// Wiki process → main process: the answer to an `AutomationRequest`
+    AutomationResponse {
+        request_id: u64,
+        result_json: Option<String>,
+        error: Option<String>,
+    },
+    /// A local tool (the MCP server, `mcp`) asking the window of an open wiki
+    /// to carry out an automation action. Carries its own token like HandOff;
+    /// answered with `AutomationResponse`, or a failed `Ack` when the wiki
+    /// isn't open.
+    WikiQuery {
+        wiki_path: String,
+        action: String,
+        params_json: String,
+        auth_token: String,
+    },
     /// Ping/keepalive
     Ping,
     Pong,
@@ -164,6 +249,19 @@ pub enum IpcMessage {
         wiki_id: String,
         title: String,
     },
+    /// Wiki process → main process: the wiki's own version of a conflicting tiddler
+    LanSyncConflictLocal {
+        wiki_id: String,
+        conflict_id: String,
+        tiddler_json: String,
+    },
+    /// Wiki process → main process: resolve a sync conflict
+    LanSyncResolveConflict {
+        wiki_id: String,
+        conflict_id: String,
+        resolution: String,
+        text: Option<String>,
+    },
     /// Wiki process → main process: batch of tiddlers for full sync dump
     LanSyncFullSyncBatch {
         wiki_id: String,
@@ -223,6 +321,11 @@ pub enum IpcMessage {
     LanSyncAnnounceUsername {
         user_name: String,
     },
+    /// Wiki process → main process: send text to the shared clipboard of a device
+    LanSyncShareClipboard {
+        device_id: String,
+        text: String,
+    },
 }
 
 /// A connected wiki process
@@ -246,8 +349,14 @@ pub struct IpcServer {
     open_tiddler_callback: Arc<Mutex<Option<Box<dyn Fn(String, String, Option<String>) + Send + 'static>>>>,
     /// Callback for updating wiki favicon
     update_favicon_callback: Arc<Mutex<Option<Box<dyn Fn(String, Option<String>) + Send + 'static>>>>,
-    /// Callback for when a new wiki client registers (after authentication)
-    register_callback: Arc<Mutex<Option<Box<dyn Fn(String) + Send + 'static>>>>,
+    /// Callback for when a new wiki client registers (after authentication):
+    /// wiki path, pid, is_tiddler_window
+    register_callback: Arc<Mutex<Option<Box<dyn Fn(String, u32, bool) + Send + 'static>>>>,
+    /// Callback for wikis handed off by a second app instance
+    hand_off_callback: Arc<Mutex<Option<Box<dyn Fn(Vec<String>) + Send + 'static>>>>,
+    /// Callback for wiki processes asking to be restarted
+    restart_wiki_callback: Arc<Mutex<Option<Box<dyn Fn(String) + Send + 'static>>>>,
+    reopen_closed_callback: Arc<Mutex<Option<Box<dyn Fn() + Send + 'static>>>>,
     /// Authentication token for validating clients
     auth_token: String,
 }
@@ -263,6 +372,9 @@ impl IpcServer {
             open_tiddler_callback: Arc::new(Mutex::new(None)),
             update_favicon_callback: Arc::new(Mutex::new(None)),
             register_callback: Arc::new(Mutex::new(None)),
+            hand_off_callback: Arc::new(Mutex::new(None)),
+            restart_wiki_callback: Arc::new(Mutex::new(None)),
+            reopen_closed_callback: Arc::new(Mutex::new(None)),
             auth_token: token,
         }
     }
@@ -356,14 +468,42 @@ impl IpcServer {
     /// Set callback for when a new wiki client registers (after authentication)
     pub fn on_client_registered<F>(&self, callback: F)
     where
-        F: Fn(String) + Send + 'static,
+        F: Fn(String, u32, bool) + Send + 'static,
     {
         *self.register_callback.lock().unwrap() = Some(Box::new(callback));
     }
 
-    /// Start the IPC server (blocks, run in separate thread)
-    pub fn start(&self) -> std::io::Result<()> {
-        let listener = TcpListener::bind(format!("127.0.0.1:{}", IPC_PORT))?;
+    /// Set callback for wikis handed off by a second app instance
+    pub fn on_hand_off<F>(&self, callback: F)
+    where
+        F: Fn(Vec<String>) + Send + 'static,
+    {
+        *self.hand_off_callback.lock().unwrap() = Some(Box::new(callback));
+    }
+
+    /// Set callback for wiki processes asking to be restarted
+    pub fn on_restart_wiki<F>(&self, callback: F)
+    where
+        F: Fn(String) + Send + 'static,
+    {
+        *self.restart_wiki_callback.lock().unwrap() = Some(Box::new(callback));
+    }
+
+    /// Set callback for wiki processes asking to reopen the last closed wiki
+    pub fn on_reopen_closed_wiki<F>(&self, callback: F)
+    where
+        F: Fn() + Send + 'static,
+    {
+        *self.reopen_closed_callback.lock().unwrap() = Some(Box::new(callback));
+    }
+
+    /// Bind the IPC port. Fails with `AddrInUse` while another instance is running.
+    pub fn bind() -> std::io::Result<TcpListener> {
+        TcpListener::bind(format!("127.0.0.1:{}", IPC_PORT))
+    }
+
+    /// Start the IPC server on a listener from `bind` (blocks, run in separate thread)
+    pub fn start(&self, listener: TcpListener) -> std::io::Result<()> {
         eprintln!("[IPC] Server listening on port {}", IPC_PORT);
 
         for stream in listener.incoming() {
@@ -395,6 +535,9 @@ impl IpcServer {
                     let open_tiddler_cb = self.open_tiddler_callback.clone();
                     let update_favicon_cb = self.update_favicon_callback.clone();
                     let register_cb = self.register_callback.clone();
+                    let hand_off_cb = self.hand_off_callback.clone();
+                    let restart_wiki_cb = self.restart_wiki_callback.clone();
+                    let reopen_closed_cb = self.reopen_closed_callback.clone();
                     let auth_token = self.auth_token.clone();
 
                     thread::spawn(move || {
@@ -406,6 +549,9 @@ impl IpcServer {
                             open_tiddler_cb,
                             update_favicon_cb,
                             register_cb,
+                            hand_off_cb,
+                            restart_wiki_cb,
+                            reopen_closed_cb,
                             auth_token,
                         );
                         // Always decrement connection counter when done
@@ -439,6 +585,134 @@ impl IpcServer {
         }
         Ok(())
     }
+
+    /// Tell the processes of a wiki that it exceeds the soft memory limit
+    pub fn send_memory_limit_exceeded(&self, wiki_path: &str, used_mb: u64, limit_mb: u64, auto_restart: bool) -> std::io::Result<()> {
+        let msg = IpcMessage::MemoryLimitExceeded {
+            wiki_path: wiki_path.to_string(),
+            used_mb,
+            limit_mb,
+            auto_restart,
+        };
+        let json = serde_json::to_string(&msg)?;
+
+        let groups = self.wiki_groups.lock().unwrap();
+        if let Some(clients) = groups.get(wiki_path) {
+            for client in clients.iter().filter(|c| !c.is_tiddler_window) {
+                let mut s = client.write_stream.lock().unwrap();
+                let _ = writeln!(s, "{}", json);
+            }
+        }
+        Ok(())
+    }
+
+    /// Tell the processes of a wiki to mute or unmute their windows
+    pub fn send_muted(&self, wiki_path: &str, muted: bool) -> std::io::Result<()> {
+        let msg = IpcMessage::SetMuted {
+            wiki_path: wiki_path.to_string(),
+            muted,
+        };
+        let json = serde_json::to_string(&msg)?;
+
+        let groups = self.wiki_groups.lock().unwrap();
+        if let Some(clients) = groups.get(wiki_path) {
+            for client in clients {
+                let mut s = client.write_stream.lock().unwrap();
+                let _ = writeln!(s, "{}", json);
+            }
+        }
+        Ok(())
+    }
+
+    /// Tell the windows of a wiki to run one of its menu commands
+    pub fn send_run_wiki_command(&self, wiki_path: &str, command_id: &str) -> std::io::Result<()> {
+        let msg = IpcMessage::RunWikiMenuCommand {
+            wiki_path: wiki_path.to_string(),
+            command_id: command_id.to_string(),
+        };
+        let json = serde_json::to_string(&msg)?;
+
+        let groups = self.wiki_groups.lock().unwrap();
+        if let Some(clients) = groups.get(wiki_path) {
+            for client in clients.iter().filter(|c| !c.is_tiddler_window) {
+                let mut s = client.write_stream.lock().unwrap();
+                let _ = writeln!(s, "{}", json);
+            }
+        }
+        Ok(())
+    }
+
+    /// Ask the window of a wiki to carry out an automation API request
+    pub fn send_automation_request(&self, wiki_path: &str, request_id: u64, action: &str, params_json: &str) -> std::io::Result<()> {
+        let msg = IpcMessage::AutomationRequest {
+            wiki_path: wiki_path.to_string(),
+            request_id,
+            action: action.to_string(),
+            params_json: params_json.to_string(),
+        };
+        let json = serde_json::to_string(&msg)?;
+
+        let groups = self.wiki_groups.lock().unwrap();
+        if let Some(clients) = groups.get(wiki_path) {
+            for client in clients.iter().filter(|c| !c.is_tiddler_window) {
+                let mut s = client.write_stream.lock().unwrap();
+                let _ = writeln!(s, "{}", json);
+            }
+        }
+        Ok(())
+    }
+
+    /// Tell the processes of a wiki to open their developer tools
+    pub fn send_open_devtools(&self, wiki_path: &str) -> std::io::Result<()> {
+        let msg = IpcMessage::OpenDevtools {
+            wiki_path: wiki_path.to_string(),
+        };
+        let json = serde_json::to_string(&msg)?;
+
+        let groups = self.wiki_groups.lock().unwrap();
+        if let Some(clients) = groups.get(wiki_path) {
+            for client in clients {
+                let mut s = client.write_stream.lock().unwrap();
+                let _ = writeln!(s, "{}", json);
+            }
+        }
+        Ok(())
+    }
+
+    /// Tell the processes of a wiki to take their pending quick captures
+    pub fn send_import_captures(&self, wiki_path: &str) -> std::io::Result<()> {
+        let msg = IpcMessage::ImportCaptures {
+            wiki_path: wiki_path.to_string(),
+        };
+        let json = serde_json::to_string(&msg)?;
+
+        let groups = self.wiki_groups.lock().unwrap();
+        if let Some(clients) = groups.get(wiki_path) {
+            for client in clients.iter().filter(|c| !c.is_tiddler_window) {
+                let mut s = client.write_stream.lock().unwrap();
+                let _ = writeln!(s, "{}", json);
+            }
+        }
+        Ok(())
+    }
+
+    /// Tell the processes of a wiki to change their window icon
+    pub fn send_wiki_icon(&self, wiki_path: &str, icon: Option<String>) -> std::io::Result<()> {
+        let msg = IpcMessage::WikiIcon {
+            wiki_path: wiki_path.to_string(),
+            icon,
+        };
+        let json = serde_json::to_string(&msg)?;
+
+        let groups = self.wiki_groups.lock().unwrap();
+        if let Some(clients) = groups.get(wiki_path) {
+            for client in clients {
+                let mut s = client.write_stream.lock().unwrap();
+                let _ = writeln!(s, "{}", json);
+            }
+        }
+        Ok(())
+    }
 }
 
 fn handle_client(
@@ -448,7 +722,10 @@ fn handle_client(
     open_wiki_cb: Arc<Mutex<Option<Box<dyn Fn(String) + Send + 'static>>>>,
     open_tiddler_cb: Arc<Mutex<Option<Box<dyn Fn(String, String, Option<String>) + Send + 'static>>>>,
     update_favicon_cb: Arc<Mutex<Option<Box<dyn Fn(String, Option<String>) + Send + 'static>>>>,
-    register_cb: Arc<Mutex<Option<Box<dyn Fn(String) + Send + 'static>>>>,
+    register_cb: Arc<Mutex<Option<Box<dyn Fn(String, u32, bool) + Send + 'static>>>>,
+    hand_off_cb: Arc<Mutex<Option<Box<dyn Fn(Vec<String>) + Send + 'static>>>>,
+    restart_wiki_cb: Arc<Mutex<Option<Box<dyn Fn(String) + Send + 'static>>>>,
+    reopen_closed_cb: Arc<Mutex<Option<Box<dyn Fn() + Send + 'static>>>>,
     expected_auth_token: String,
 ) -> std::io::Result<()> {
     let peer_addr = stream.peer_addr()?;
@@ -542,7 +819,7 @@ fn handle_client(
 
                                 // Notify callback that a new client registered
                                 if let Some(ref cb) = *register_cb.lock().unwrap() {
-                                    cb(wiki_path.clone());
+                                    cb(wiki_path.clone(), *pid, *is_tiddler_window);
                                 }
                             }
 
@@ -580,6 +857,26 @@ fn handle_client(
                                 let _ = writeln!(ws, "{}", serde_json::to_string(&ack)?);
                             }
 
+                            IpcMessage::HandOff { paths, auth_token } => {
+                                // Carries its own token; the connection ends after the reply
+                                let success = auth_token == &expected_auth_token;
+                                if success {
+                                    eprintln!("[IPC] Hand-off from second instance: {} wiki(s)", paths.len());
+                                    if let Some(ref cb) = *hand_off_cb.lock().unwrap() {
+                                        cb(paths.clone());
+                                    }
+                                } else {
+                                    eprintln!("[IPC] Security: Invalid auth token in hand-off, rejecting");
+                                }
+                                let ack = IpcMessage::Ack {
+                                    success,
+                                    message: (!success).then(|| "Invalid authentication token".to_string()),
+                                };
+                                let mut ws = write_stream.lock().unwrap();
+                                let _ = writeln!(ws, "{}", serde_json::to_string(&ack)?);
+                                break;
+                            }
+
                             IpcMessage::OpenTiddlerWindow { wiki_path, tiddler_title, startup_tiddler } => {
                                 if !client_authenticated {
                                     eprintln!("[IPC] Security: Unauthenticated OpenTiddlerWindow attempt, ignoring");
@@ -688,6 +985,110 @@ fn handle_client(
                                 let _ = writeln!(ws, "{}", serde_json::to_string(&ack)?);
                             }
 
+                            IpcMessage::RestartWiki { wiki_path } => {
+                                if !client_authenticated {
+                                    eprintln!("[IPC] Security: Unauthenticated RestartWiki attempt, ignoring");
+                                    continue;
+                                }
+                                eprintln!("[IPC] RestartWiki request: wiki={}", wiki_path);
+                                if let Some(ref cb) = *restart_wiki_cb.lock().unwrap() {
+                                    cb(wiki_path.clone());
+                                }
+                                let ack = IpcMessage::Ack { success: true, message: None };
+                                let mut ws = write_stream.lock().unwrap();
+                                let _ = writeln!(ws, "{}", serde_json::to_string(&ack)?);
+                            }
+
+                            IpcMessage::ReopenClosedWiki => {
+                                if !client_authenticated {
+                                    eprintln!("[IPC] Security: Unauthenticated ReopenClosedWiki attempt, ignoring");
+                                    continue;
+                                }
+                                if let Some(ref cb) = *reopen_closed_cb.lock().unwrap() {
+                                    cb();
+                                }
+                                let ack = IpcMessage::Ack { success: true, message: None };
+                                let mut ws = write_stream.lock().unwrap();
+                                let _ = writeln!(ws, "{}", serde_json::to_string(&ack)?);
+                            }
+
+                            IpcMessage::ShowQuickSwitcher => {
+                                if !client_authenticated {
+                                    eprintln!("[IPC] Security: Unauthenticated ShowQuickSwitcher attempt, ignoring");
+                                    continue;
+                                }
+                                #[cfg(not(target_os = "android"))]
+                                if let Some(app) = crate::GLOBAL_APP_HANDLE.get() {
+                                    let handle = app.clone();
+                                    let _ = app.run_on_main_thread(move || crate::quick_switcher::show(&handle));
+                                }
+                                let ack = IpcMessage::Ack { success: true, message: None };
+                                let mut ws = write_stream.lock().unwrap();
+                                let _ = writeln!(ws, "{}", serde_json::to_string(&ack)?);
+                            }
+
+                            IpcMessage::WikiMenuCommands { wiki_path, commands } => {
+                                if !client_authenticated {
+                                    eprintln!("[IPC] Security: Unauthenticated WikiMenuCommands attempt, ignoring");
+                                    continue;
+                                }
+                                #[cfg(not(target_os = "android"))]
+                                if let Some(app) = crate::GLOBAL_APP_HANDLE.get() {
+                                    crate::wiki_commands::set_commands(app, wiki_path, commands.clone());
+                                }
+                                let ack = IpcMessage::Ack { success: true, message: None };
+                                let mut ws = write_stream.lock().unwrap();
+                                let _ = writeln!(ws, "{}", serde_json::to_string(&ack)?);
+                            }
+
+                            IpcMessage::AutomationResponse { request_id, result_json, error } => {
+                                if !client_authenticated {
+                                    eprintln!("[IPC] Security: Unauthenticated AutomationResponse attempt, ignoring");
+                                    continue;
+                                }
+                                #[cfg(not(target_os = "android"))]
+                                crate::automation::deliver_response(*request_id, result_json.clone(), error.clone());
+                                let ack = IpcMessage::Ack { success: true, message: None };
+                                let mut ws = write_stream.lock().unwrap();
+                                let _ = writeln!(ws, "{}", serde_json::to_string(&ack)?);
+                            }
+
+                            IpcMessage::WikiQuery { wiki_path, action, params_json, auth_token } => {
+                                // Carries its own token; the connection ends after the reply
+                                let reply = if auth_token != &expected_auth_token {
+                                    eprintln!("[IPC] Security: Invalid auth token in wiki query, rejecting");
+                                    IpcMessage::Ack { success: false, message: Some("Invalid authentication token".to_string()) }
+                                } else {
+                                    #[cfg(not(target_os = "android"))]
+                                    let result = crate::GLOBAL_APP_HANDLE
+                                        .get()
+                                        .and_then(|app| crate::automation::query_open_wiki(app, wiki_path, action, params_json));
+                                    #[cfg(target_os = "android")]
+                                    let result: Option<Result<String, String>> = None;
+                                    match result {
+                                        Some(Ok(result_json)) => IpcMessage::AutomationResponse { request_id: 0, result_json: Some(result_json), error: None },
+                                        Some(Err(error)) => IpcMessage::AutomationResponse { request_id: 0, result_json: None, error: Some(error) },
+                                        None => IpcMessage::Ack { success: false, message: Some("Wiki is not open".to_string()) },
+                                    }
+                                };
+                                let mut ws = write_stream.lock().unwrap();
+                                let _ = writeln!(ws, "{}", serde_json::to_string(&reply)?);
+                                break;
+                            }
+
+                            IpcMessage::JsErrorsReported { wiki_path } => {
+                                if !client_authenticated {
+                                    eprintln!("[IPC] Security: Unauthenticated JsErrorsReported attempt, ignoring");
+                                    continue;
+                                }
+                                if let Some(app) = crate::GLOBAL_APP_HANDLE.get() {
+                                    crate::js_errors::notify_changed(app, wiki_path);
+                                }
+                                let ack = IpcMessage::Ack { success: true, message: None };
+                                let mut ws = write_stream.lock().unwrap();
+                                let _ = writeln!(ws, "{}", serde_json::to_string(&ack)?);
+                            }
+
                             IpcMessage::Ping => {
                                 let pong = IpcMessage::Pong;
                                 let mut ws = write_stream.lock().unwrap();
@@ -737,6 +1138,34 @@ fn handle_client(
                                 }
                             }
 
+                            IpcMessage::LanSyncConflictLocal { wiki_id, conflict_id, tiddler_json } => {
+                                if !client_authenticated {
+                                    continue;
+                                }
+                                #[cfg(not(target_os = "android"))]
+                                {
+                                    if let Some(mgr) = crate::lan_sync::get_sync_manager() {
+                                        if let Err(e) = mgr.set_conflict_local_version(wiki_id, conflict_id, tiddler_json.clone()) {
+                                            eprintln!("[IPC] LanSyncConflictLocal: {}", e);
+                                        }
+                                    }
+                                }
+                            }
+
+                            IpcMessage::LanSyncResolveConflict { wiki_id, conflict_id, resolution, text } => {
+                                if !client_authenticated {
+                                    continue;
+                                }
+                                #[cfg(not(target_os = "android"))]
+                                {
+                                    if let Some(mgr) = crate::lan_sync::get_sync_manager() {
+                                        if let Err(e) = mgr.resolve_conflict(wiki_id, conflict_id, resolution, text.as_deref()) {
+                                            eprintln!("[IPC] LanSyncResolveConflict: {}", e);
+                                        }
+                                    }
+                                }
+                            }
+
                             IpcMessage::LanSyncFullSyncBatch { wiki_id, to_device_id, tiddlers_json, is_last_batch } => {
                                 if !client_authenticated {
                                     continue;
@@ -913,6 +1342,25 @@ fn handle_client(
                                 }
                             }
 
+                            IpcMessage::LanSyncShareClipboard { ref device_id, ref text } => {
+                                if !client_authenticated {
+                                    continue;
+                                }
+                                #[cfg(not(target_os = "android"))]
+                                {
+                                    if let Some(mgr) = crate::lan_sync::get_sync_manager() {
+                                        let mgr = mgr.clone();
+                                        let device_id = device_id.clone();
+                                        let text = text.clone();
+                                        tauri::async_runtime::spawn(async move {
+                                            if let Err(e) = mgr.share_clipboard(&device_id, text).await {
+                                                eprintln!("[IPC] Failed to share clipboard with {}: {}", device_id, e);
+                                            }
+                                        });
+                                    }
+                                }
+                            }
+
                             _ => {}
                         }
                     }
@@ -1070,6 +1518,45 @@ impl IpcClient {
         self.send(&msg)
     }
 
+    /// Ask the main process to reopen this wiki after this process exits
+    pub fn request_restart(&mut self) -> std::io::Result<()> {
+        let msg = IpcMessage::RestartWiki {
+            wiki_path: self.wiki_path.clone(),
+        };
+        self.send(&msg)
+    }
+
+    /// Ask the main process to reopen the most recently closed wiki
+    pub fn request_reopen_closed_wiki(&mut self) -> std::io::Result<()> {
+        self.send(&IpcMessage::ReopenClosedWiki)
+    }
+
+    /// Ask the main process to show the quick switcher
+    pub fn request_quick_switcher(&mut self) -> std::io::Result<()> {
+        self.send(&IpcMessage::ShowQuickSwitcher)
+    }
+
+    /// Send the wiki's menu commands to the main process (tray menu)
+    pub fn send_wiki_menu_commands(&mut self, commands: Vec<crate::wiki_commands::MenuCommand>) -> std::io::Result<()> {
+        let msg = IpcMessage::WikiMenuCommands {
+            wiki_path: self.wiki_path.clone(),
+            commands,
+        };
+        self.send(&msg)
+    }
+
+    /// Answer an automation API request of the main process
+    pub fn send_automation_response(&mut self, request_id: u64, result_json: Option<String>, error: Option<String>) -> std::io::Result<()> {
+        self.send(&IpcMessage::AutomationResponse { request_id, result_json, error })
+    }
+
+    /// Tell the main process that a window of the wiki logged JavaScript errors
+    pub fn send_js_errors_reported(&mut self, wiki_path: &str) -> std::io::Result<()> {
+        self.send(&IpcMessage::JsErrorsReported {
+            wiki_path: wiki_path.to_string(),
+        })
+    }
+
     // ── LAN Sync helpers ─────────────────────────────────────────────
 
     /// Notify main process that a sync-enabled wiki window opened
@@ -1096,6 +1583,25 @@ impl IpcClient {
         })
     }
 
+    /// Send the wiki's own version of a conflicting tiddler
+    pub fn send_lan_sync_conflict_local(&mut self, wiki_id: &str, conflict_id: &str, tiddler_json: &str) -> std::io::Result<()> {
+        self.send(&IpcMessage::LanSyncConflictLocal {
+            wiki_id: wiki_id.to_string(),
+            conflict_id: conflict_id.to_string(),
+            tiddler_json: tiddler_json.to_string(),
+        })
+    }
+
+    /// Ask the main process to resolve a sync conflict
+    pub fn send_lan_sync_resolve_conflict(&mut self, wiki_id: &str, conflict_id: &str, resolution: &str, text: Option<&str>) -> std::io::Result<()> {
+        self.send(&IpcMessage::LanSyncResolveConflict {
+            wiki_id: wiki_id.to_string(),
+            conflict_id: conflict_id.to_string(),
+            resolution: resolution.to_string(),
+            text: text.map(str::to_string),
+        })
+    }
+
     /// Send a batch of tiddlers for full sync dump
     pub fn send_lan_sync_full_batch(&mut self, wiki_id: &str, to_device_id: &str, tiddlers_json: &str, is_last_batch: bool) -> std::io::Result<()> {
         self.send(&IpcMessage::LanSyncFullSyncBatch {
@@ -1174,6 +1680,14 @@ impl IpcClient {
             user_name: user_name.to_string(),
         })
     }
+
+    /// Ask the main process to send text to the shared clipboard of a device
+    pub fn send_lan_sync_share_clipboard(&mut self, device_id: &str, text: &str) -> std::io::Result<()> {
+        self.send(&IpcMessage::LanSyncShareClipboard {
+            device_id: device_id.to_string(),
+            text: text.to_string(),
+        })
+    }
 }
 
 impl Drop for IpcClient {
@@ -1199,6 +1713,45 @@ pub fn try_connect(wiki_path: &str, is_tiddler_window: bool, tiddler_title: Opti
     }
 }
 
+/// Hand wikis to the running instance (called by a second app instance).
+/// Returns whether the running instance accepted `auth_token`.
+pub fn hand_off(paths: Vec<String>, auth_token: String) -> std::io::Result<bool> {
+    let mut stream = TcpStream::connect(format!("127.0.0.1:{}", IPC_PORT))?;
+    stream.set_read_timeout(Some(std::time::Duration::from_secs(5)))?;
+    let msg = IpcMessage::HandOff { paths, auth_token };
+    writeln!(stream, "{}", serde_json::to_string(&msg)?)?;
+
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line)?;
+    Ok(matches!(
+        serde_json::from_str::<IpcMessage>(line.trim()),
+        Ok(IpcMessage::Ack { success: true, .. })
+    ))
+}
+
+/// Have the window of an open wiki carry out an automation action (called by
+/// local tools like the MCP server, with the token of the running instance).
+/// None when the wiki isn't open or the instance didn't accept the token.
+pub fn query_wiki(wiki_path: &str, action: &str, params_json: &str, auth_token: String) -> std::io::Result<Option<Result<String, String>>> {
+    let mut stream = TcpStream::connect(format!("127.0.0.1:{}", IPC_PORT))?;
+    stream.set_read_timeout(Some(std::time::Duration::from_secs(30)))?;
+    let msg = IpcMessage::WikiQuery {
+        wiki_path: wiki_path.to_string(),
+        action: action.to_string(),
+        params_json: params_json.to_string(),
+        auth_token,
+    };
+    writeln!(stream, "{}", serde_json::to_string(&msg)?)?;
+
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line)?;
+    Ok(match serde_json::from_str::<IpcMessage>(line.trim()) {
+        Ok(IpcMessage::AutomationResponse { error: Some(error), .. }) => Some(Err(error)),
+        Ok(IpcMessage::AutomationResponse { result_json, .. }) => Some(Ok(result_json.unwrap_or_else(|| "null".to_string()))),
+        _ => None,
+    })
+}
+
 /// Run a listener loop on a stream (blocking, for use in a separate thread)
 /// This allows wiki processes to receive messages from the IPC server
 pub fn run_listener<F>(stream: TcpStream, mut callback: F)
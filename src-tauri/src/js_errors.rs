@@ -0,0 +1,263 @@
+//! JavaScript errors reported by wiki windows
+//!
+//! `init_script/js_errors.js` reports uncaught errors, unhandled promise
+//! rejections and `console.error` calls (`report_js_error`). Every process
+//! appends them to `logs/js-errors.jsonl` in the data directory, with the wiki
+//! path and window label; the log moves to `js-errors.1.jsonl` when it grows
+//! too big. Wiki processes tell the main process over IPC
+//! (`JsErrorsReported`), which has the landing page show an indicator next to
+//! the wiki until its errors are cleared.
+
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tauri::{Emitter, Manager};
+
+const LOG_FILE: &str = "js-errors.jsonl";
+const ROTATED_LOG_FILE: &str = "js-errors.1.jsonl";
+
+/// Size at which the log is rotated
+const MAX_LOG_BYTES: u64 = 1024 * 1024;
+
+/// Reports logged per window; a wiki stuck in an error loop stops there
+const MAX_REPORTS_PER_WINDOW: u32 = 100;
+
+const MAX_MESSAGE_CHARS: usize = 2000;
+const MAX_STACK_CHARS: usize = 8000;
+
+/// Window label → (reports logged, last message) in this process
+static REPORTS: LazyLock<Mutex<HashMap<String, (u32, String)>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Serializes rotating and clearing the log within this process
+static LOG_LOCK: Mutex<()> = Mutex::new(());
+
+/// A reported error
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JsError {
+    /// Milliseconds since the Unix epoch
+    pub time: u64,
+    /// None for windows of the main process (landing page)
+    pub wiki: Option<String>,
+    pub window: String,
+    /// "error", "rejection" or "console"
+    pub kind: String,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub column: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stack: Option<String>,
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn log_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(crate::get_data_dir(app)?.join("logs"))
+}
+
+fn truncate(text: String, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text,
+    }
+}
+
+/// Whether a report from `window` gets logged: not past the limit and not a
+/// repeat of the window's previous one
+fn should_log(reports: &mut HashMap<String, (u32, String)>, window: &str, message: &str) -> bool {
+    let (count, last) = reports.entry(window.to_string()).or_default();
+    if *count >= MAX_REPORTS_PER_WINDOW || last == message {
+        return false;
+    }
+    *count += 1;
+    *last = message.to_string();
+    true
+}
+
+/// Errors in the log files, oldest first (a line cut short by a crash is skipped)
+fn read_log(dir: &Path) -> Vec<JsError> {
+    [ROTATED_LOG_FILE, LOG_FILE]
+        .iter()
+        .filter_map(|name| std::fs::File::open(dir.join(name)).ok())
+        .flat_map(|file| std::io::BufReader::new(file).lines().map_while(Result::ok))
+        .filter_map(|line| serde_json::from_str::<JsError>(&line).ok())
+        .collect()
+}
+
+/// Number of errors per wiki
+fn count_per_wiki(errors: &[JsError]) -> HashMap<String, usize> {
+    let mut counts = HashMap::new();
+    for wiki in errors.iter().filter_map(|e| e.wiki.as_ref()) {
+        *counts.entry(wiki.clone()).or_default() += 1;
+    }
+    counts
+}
+
+/// The newest `limit` errors of a wiki, newest first
+fn recent(errors: Vec<JsError>, wiki: &str, limit: usize) -> Vec<JsError> {
+    let mut errors: Vec<JsError> = errors.into_iter().filter(|e| e.wiki.as_deref() == Some(wiki)).collect();
+    errors.reverse();
+    errors.truncate(limit);
+    errors
+}
+
+fn append(app: &tauri::AppHandle, error: &JsError) -> Result<(), String> {
+    let dir = log_dir(app)?;
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create log folder: {}", e))?;
+    let path = dir.join(LOG_FILE);
+    let _guard = LOG_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    if std::fs::metadata(&path).is_ok_and(|m| m.len() > MAX_LOG_BYTES) {
+        let _ = std::fs::rename(&path, dir.join(ROTATED_LOG_FILE));
+    }
+    let mut line = serde_json::to_string(error).map_err(|e| e.to_string())?;
+    line.push('\n');
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|e| format!("Failed to open JS error log: {}", e))?;
+    file.write_all(line.as_bytes())
+        .map_err(|e| format!("Failed to write JS error log: {}", e))
+}
+
+/// Let the landing page update the wiki's indicator (main process; IPC
+/// `JsErrorsReported` from wiki processes)
+pub fn notify_changed(app: &tauri::AppHandle, wiki_path: &str) {
+    let _ = app.emit("wiki-js-errors-changed", wiki_path);
+}
+
+/// Log an error reported by a window's init script
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub fn report_js_error(
+    app: tauri::AppHandle,
+    window: tauri::WebviewWindow,
+    kind: String,
+    message: String,
+    source: Option<String>,
+    line: Option<u32>,
+    column: Option<u32>,
+    stack: Option<String>,
+) -> Result<(), String> {
+    let message = truncate(message, MAX_MESSAGE_CHARS);
+    if !should_log(&mut REPORTS.lock().unwrap(), window.label(), &message) {
+        return Ok(());
+    }
+    let wiki_state = app.try_state::<crate::WikiModeState>();
+    let error = JsError {
+        time: now_ms(),
+        wiki: wiki_state.as_ref().map(|state| state.wiki_path.to_string_lossy().to_string()),
+        window: window.label().to_string(),
+        kind: match kind.as_str() {
+            "rejection" | "console" => kind,
+            _ => "error".to_string(),
+        },
+        message,
+        source: source.filter(|s| !s.is_empty()),
+        line,
+        column,
+        stack: stack.filter(|s| !s.is_empty()).map(|s| truncate(s, MAX_STACK_CHARS)),
+    };
+    append(&app, &error)?;
+
+    match (wiki_state, &error.wiki) {
+        (Some(state), Some(wiki)) => {
+            if let Some(client) = state.ipc_client.lock().unwrap().as_mut() {
+                let _ = client.send_js_errors_reported(wiki);
+            }
+        }
+        _ => eprintln!("[TiddlyDesktop] JS {} in {}: {}", error.kind, error.window, error.message),
+    }
+    Ok(())
+}
+
+/// Number of logged errors per wiki path
+#[tauri::command]
+pub fn get_js_error_counts(app: tauri::AppHandle) -> Result<HashMap<String, usize>, String> {
+    Ok(count_per_wiki(&read_log(&log_dir(&app)?)))
+}
+
+/// The newest logged errors of a wiki (default 50), newest first
+#[tauri::command]
+pub fn get_js_errors(app: tauri::AppHandle, path: String, limit: Option<usize>) -> Result<Vec<JsError>, String> {
+    Ok(recent(read_log(&log_dir(&app)?), &path, limit.unwrap_or(50)))
+}
+
+/// Remove a wiki's errors from the log
+#[tauri::command]
+pub fn clear_js_errors(app: tauri::AppHandle, path: String) -> Result<(), String> {
+    let dir = log_dir(&app)?;
+    let _guard = LOG_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut kept = String::new();
+    for error in read_log(&dir).into_iter().filter(|e| e.wiki.as_deref() != Some(path.as_str())) {
+        kept.push_str(&serde_json::to_string(&error).map_err(|e| e.to_string())?);
+        kept.push('\n');
+    }
+    let _ = std::fs::remove_file(dir.join(ROTATED_LOG_FILE));
+    if kept.is_empty() {
+        let _ = std::fs::remove_file(dir.join(LOG_FILE));
+        return Ok(());
+    }
+    std::fs::write(dir.join(LOG_FILE), kept).map_err(|e| format!("Failed to write JS error log: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(wiki: Option<&str>, message: &str) -> JsError {
+        JsError {
+            time: 0,
+            wiki: wiki.map(str::to_string),
+            window: "main".to_string(),
+            kind: "error".to_string(),
+            message: message.to_string(),
+            source: None,
+            line: None,
+            column: None,
+            stack: None,
+        }
+    }
+
+    #[test]
+    fn test_should_log_skips_repeats_and_stops_at_limit() {
+        let mut reports = HashMap::new();
+        assert!(should_log(&mut reports, "main", "a"));
+        assert!(!should_log(&mut reports, "main", "a"));
+        assert!(should_log(&mut reports, "other", "a"));
+        assert!(should_log(&mut reports, "main", "b"));
+        for i in 2..MAX_REPORTS_PER_WINDOW {
+            assert!(should_log(&mut reports, "main", &i.to_string()));
+        }
+        assert!(!should_log(&mut reports, "main", "new"));
+    }
+
+    #[test]
+    fn test_counts_and_recent_errors() {
+        let errors = vec![
+            error(Some("/a.html"), "1"),
+            error(None, "landing page"),
+            error(Some("/b.html"), "2"),
+            error(Some("/a.html"), "3"),
+            error(Some("/a.html"), "4"),
+        ];
+        let counts = count_per_wiki(&errors);
+        assert_eq!(counts.len(), 2);
+        assert_eq!(counts["/a.html"], 3);
+        assert_eq!(counts["/b.html"], 1);
+        let messages: Vec<String> = recent(errors, "/a.html", 2).into_iter().map(|e| e.message).collect();
+        assert_eq!(messages, ["4", "3"]);
+    }
+}
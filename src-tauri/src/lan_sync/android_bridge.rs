@@ -592,6 +592,7 @@ fn run_bridge_server(
                         port: None,
                         connected_peers: vec![],
                         relay_connected: false,
+                        wiki_transports: vec![],
                     }
                 };
                 let resp = serde_json::to_string(&status).unwrap_or_else(|_| "{}".to_string());
@@ -89,6 +89,7 @@ pub enum SyncToWiki {
     ApplyTiddlerDeletion {
         wiki_id: String,
         title: String,
+        from_device_id: String,
         /// Vector clock to merge after confirmed IPC delivery (None = already merged)
         vector_clock: Option<VectorClock>,
     },
@@ -96,7 +97,9 @@ pub enum SyncToWiki {
     SaveConflict {
         wiki_id: String,
         title: String,
-        conflict_tiddler_json: String,
+        from_device_id: String,
+        /// The peer's version (None = deleted remotely); the JS side has the local one
+        remote_tiddler_json: Option<String>,
     },
 }
 
@@ -191,7 +194,8 @@ impl SyncBridge {
                         let _ = self.sync_to_wiki_tx.send(SyncToWiki::SaveConflict {
                             wiki_id: wiki_id.clone(),
                             title: title.clone(),
-                            conflict_tiddler_json: String::new(), // JS side has the local version
+                            from_device_id: from_device_id.to_string(),
+                            remote_tiddler_json: Some(tiddler_json.clone()),
                         });
 
                         // Last-write-wins: apply the remote change
@@ -227,6 +231,7 @@ impl SyncBridge {
                         let _ = self.sync_to_wiki_tx.send(SyncToWiki::ApplyTiddlerDeletion {
                             wiki_id,
                             title,
+                            from_device_id: from_device_id.to_string(),
                             vector_clock: Some(vector_clock),
                         });
                     }
@@ -240,12 +245,14 @@ impl SyncBridge {
                         let _ = self.sync_to_wiki_tx.send(SyncToWiki::SaveConflict {
                             wiki_id: wiki_id.clone(),
                             title: title.clone(),
-                            conflict_tiddler_json: String::new(), // JS side has the local version
+                            from_device_id: from_device_id.to_string(),
+                            remote_tiddler_json: None,
                         });
                         // Defer clock merge until confirmed IPC delivery
                         let _ = self.sync_to_wiki_tx.send(SyncToWiki::ApplyTiddlerDeletion {
                             wiki_id,
                             title,
+                            from_device_id: from_device_id.to_string(),
                             vector_clock: Some(vector_clock),
                         });
                     }
@@ -303,7 +310,8 @@ impl SyncBridge {
                             let _ = self.sync_to_wiki_tx.send(SyncToWiki::SaveConflict {
                                 wiki_id: wiki_id.clone(),
                                 title: tiddler.title.clone(),
-                                conflict_tiddler_json: String::new(),
+                                from_device_id: from_device_id.to_string(),
+                                remote_tiddler_json: Some(tiddler.tiddler_json.clone()),
                             });
                             // Defer clock merge until confirmed IPC delivery
                             if self.sync_to_wiki_tx.send(SyncToWiki::ApplyTiddlerChange {
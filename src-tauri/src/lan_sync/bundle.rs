@@ -0,0 +1,386 @@
+//! `.twsync` bundles: a synced wiki with its sync state, for another device.
+//!
+//! A bundle is a zip file with the wiki under `wiki/` (a single-file wiki with
+//! its `attachments` folder) and `twsync.json`: the wiki's sync id and sync
+//! mode, the fingerprints it last synced with, its deletion tombstones and its
+//! relay room. Importing it registers the wiki under the same sync id with that
+//! state, so the devices only exchange what changed since the export instead of
+//! transferring the whole wiki — a copy on a USB stick bootstraps a large wiki.
+//!
+//! The room password is only included in bundles encrypted with a password
+//! (AES-256, like wiki archives); otherwise the importing device has to be in
+//! the room already.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tauri::Emitter;
+use zip::write::{FileOptions, ZipWriter};
+use zip::{AesMode, CompressionMethod, ZipArchive};
+
+use super::protocol::TiddlerFingerprint;
+use super::tombstones::{self, Tombstone};
+use super::{get_sync_manager, SyncManager};
+use crate::types::WikiEntry;
+use crate::utils;
+use crate::wiki_archive::{add_file, collect_files, entry_name};
+
+const MANIFEST: &str = "twsync.json";
+const WIKI_DIR: &str = "wiki";
+const FORMAT_VERSION: u32 = 1;
+
+/// Largest manifest read from a bundle
+const MAX_MANIFEST_SIZE: u64 = 256 * 1024 * 1024;
+
+/// The relay room of a bundled wiki
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BundleRoom {
+    pub name: String,
+    pub room_code: String,
+    /// Only in encrypted bundles
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+}
+
+/// `twsync.json`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Manifest {
+    version: u32,
+    wiki_id: String,
+    /// File or folder name of the wiki
+    wiki_name: String,
+    is_folder: bool,
+    #[serde(default)]
+    sync_mode: Option<String>,
+    #[serde(default)]
+    room: Option<BundleRoom>,
+    /// What the wiki last synced with
+    #[serde(default)]
+    fingerprints: Vec<TiddlerFingerprint>,
+    #[serde(default)]
+    tombstones: BTreeMap<String, Tombstone>,
+}
+
+/// A written bundle
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportedBundle {
+    pub path: String,
+    pub files: usize,
+    pub encrypted: bool,
+    /// The room's password is in the bundle
+    pub includes_room: bool,
+}
+
+/// Where an entry of the bundle goes below the import directory (None: not part of the wiki)
+fn wiki_entry_path(name: &Path) -> Option<PathBuf> {
+    let relative = name.strip_prefix(WIKI_DIR).ok()?;
+    (!relative.as_os_str().is_empty()).then(|| relative.to_path_buf())
+}
+
+fn write_bundle(
+    wiki: &Path,
+    manifest: &Manifest,
+    target: &Path,
+    password: Option<&str>,
+) -> Result<usize, String> {
+    let (base, prefix, files) = if manifest.is_folder {
+        let mut files = Vec::new();
+        collect_files(wiki, &mut files);
+        (wiki.to_path_buf(), format!("{}/{}", WIKI_DIR, manifest.wiki_name), files)
+    } else {
+        let base = wiki.parent().unwrap_or(wiki).to_path_buf();
+        let mut files = vec![wiki.to_path_buf()];
+        collect_files(&base.join("attachments"), &mut files);
+        (base, WIKI_DIR.to_string(), files)
+    };
+
+    let mut options: FileOptions<()> = FileOptions::default()
+        .compression_method(CompressionMethod::Deflated)
+        .large_file(true);
+    if let Some(password) = password {
+        options = options.with_aes_encryption(AesMode::Aes256, password);
+    }
+
+    let partial = target.with_extension("twsync.part");
+    let file = File::create(&partial).map_err(|e| format!("Failed to create {}: {}", partial.display(), e))?;
+    let mut zip = ZipWriter::new(file);
+    let json = serde_json::to_vec(manifest).map_err(|e| e.to_string())?;
+    let result = zip
+        .start_file(MANIFEST, options)
+        .map_err(|e| e.to_string())
+        .and_then(|_| zip.write_all(&json).map_err(|e| e.to_string()))
+        .map_err(|e| format!("Failed to add {}: {}", MANIFEST, e))
+        .and_then(|_| {
+            files.iter().try_for_each(|path| match entry_name(&base, &prefix, path) {
+                Some(name) => add_file(&mut zip, &name, path, options),
+                None => Ok(()),
+            })
+        });
+    let result = result.and_then(|_| {
+        let mut file = zip.finish().map_err(|e| format!("Failed to write bundle: {}", e))?;
+        file.flush().map_err(|e| format!("Failed to write bundle: {}", e))
+    });
+    if let Err(e) = result {
+        let _ = std::fs::remove_file(&partial);
+        return Err(e);
+    }
+    std::fs::rename(&partial, target).map_err(|e| format!("Failed to save {}: {}", target.display(), e))?;
+    Ok(files.len())
+}
+
+/// The fingerprints a wiki last synced with, from the sync manager's cache
+fn cached_fingerprints(mgr: Option<&SyncManager>, wiki_id: &str) -> Vec<TiddlerFingerprint> {
+    mgr.and_then(|mgr| mgr.get_accurate_cached_fingerprints(wiki_id)).unwrap_or_default()
+}
+
+/// Package a sync-enabled wiki with its sync state as a `.twsync` bundle,
+/// AES-256 encrypted (with its room password) if a password is given
+#[tauri::command]
+pub async fn lan_sync_export_bundle(
+    app: tauri::AppHandle,
+    wiki_path: String,
+    target: String,
+    password: Option<String>,
+) -> Result<ExportedBundle, String> {
+    let password = password.filter(|p| !p.is_empty());
+    let entry = crate::wiki_storage::load_recent_files_from_disk(&app)
+        .into_iter()
+        .find(|entry| utils::paths_equal(&entry.path, &wiki_path))
+        .ok_or("Wiki not in the wiki list")?;
+    let wiki_id = match (entry.sync_enabled, entry.sync_id.clone()) {
+        (true, Some(id)) => id,
+        _ => return Err("Sync is not enabled for this wiki".to_string()),
+    };
+    let wiki = PathBuf::from(&entry.path);
+    let wiki_name = wiki
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .ok_or("Invalid wiki path")?;
+
+    let mgr = get_sync_manager();
+    let mut room = None;
+    if let (Some(room_code), Some(relay)) = (&entry.relay_room, mgr.as_ref().and_then(|m| m.relay_manager.as_ref())) {
+        room = relay.get_room_credentials(room_code).await.map(|(name, room_code, room_password)| BundleRoom {
+            name,
+            room_code,
+            password: password.as_ref().map(|_| room_password),
+        });
+    }
+    let data_dir = crate::get_data_dir(&app)?;
+    let manifest = Manifest {
+        version: FORMAT_VERSION,
+        fingerprints: cached_fingerprints(mgr.as_deref(), &wiki_id),
+        tombstones: tombstones::load(&data_dir, &wiki_id),
+        wiki_id,
+        wiki_name,
+        is_folder: entry.is_folder,
+        sync_mode: entry.sync_mode.clone(),
+        room,
+    };
+
+    let encrypted = password.is_some();
+    let includes_room = manifest.room.as_ref().is_some_and(|r| r.password.is_some());
+    let target_path = PathBuf::from(&target);
+    let files = tokio::task::spawn_blocking(move || write_bundle(&wiki, &manifest, &target_path, password.as_deref()))
+        .await
+        .map_err(|e| format!("Export failed: {}", e))??;
+    Ok(ExportedBundle { path: target, files, encrypted, includes_room })
+}
+
+/// Read the manifest and extract the wiki into `target_dir`. Returns the
+/// manifest and the path of the extracted wiki.
+fn read_bundle(
+    bundle: &Path,
+    target_dir: &Path,
+    password: Option<&str>,
+    synced_as: impl Fn(&str) -> Option<String>,
+) -> Result<(Manifest, PathBuf), String> {
+    let file = File::open(bundle).map_err(|e| format!("Failed to open {}: {}", bundle.display(), e))?;
+    let mut archive = ZipArchive::new(file).map_err(|e| format!("Not a .twsync bundle: {}", e))?;
+
+    let index = archive.index_for_name(MANIFEST).ok_or("Not a .twsync bundle: twsync.json is missing")?;
+    let encrypted = archive.by_index_raw(index).map(|f| f.encrypted()).unwrap_or(false);
+    if encrypted && password.is_none() {
+        return Err("Password required: the bundle is encrypted".to_string());
+    }
+    let manifest: Manifest = {
+        let entry = match password {
+            Some(password) => archive.by_index_decrypt(index, password.as_bytes()),
+            None => archive.by_index(index),
+        };
+        let mut json = String::new();
+        entry
+            .map_err(|e| format!("Failed to read the bundle (wrong password?): {}", e))?
+            .take(MAX_MANIFEST_SIZE)
+            .read_to_string(&mut json)
+            .map_err(|e| format!("Failed to read the bundle (wrong password?): {}", e))?;
+        serde_json::from_str(&json).map_err(|e| format!("Invalid twsync.json: {}", e))?
+    };
+    if manifest.version > FORMAT_VERSION {
+        return Err("The bundle was made by a newer version of TiddlyDesktop".to_string());
+    }
+    if manifest.wiki_name.is_empty() || Path::new(&manifest.wiki_name).components().count() != 1 {
+        return Err(format!("Invalid wiki name in the bundle: {}", manifest.wiki_name));
+    }
+
+    if let Some(other) = synced_as(&manifest.wiki_id) {
+        return Err(format!("This wiki is already synced on this device: {}", other));
+    }
+    let wiki_path = target_dir.join(&manifest.wiki_name);
+    if wiki_path.exists() {
+        return Err(format!("{} already exists", wiki_path.display()));
+    }
+    for i in 0..archive.len() {
+        let entry = match password {
+            Some(password) => archive.by_index_decrypt(i, password.as_bytes()),
+            None => archive.by_index(i),
+        };
+        let mut entry = entry.map_err(|e| format!("Failed to read the bundle: {}", e))?;
+        let Some(relative) = entry.enclosed_name().as_deref().and_then(wiki_entry_path) else {
+            continue;
+        };
+        if entry.is_dir() {
+            continue;
+        }
+        let dest = target_dir.join(&relative);
+        // Attachments of a single-file wiki may already be there
+        if dest.exists() {
+            continue;
+        }
+        if let Some(parent) = dest.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        let mut out = File::create(&dest).map_err(|e| format!("Failed to create {}: {}", dest.display(), e))?;
+        std::io::copy(&mut entry, &mut out).map_err(|e| format!("Failed to extract {}: {}", dest.display(), e))?;
+    }
+    if !wiki_path.exists() {
+        return Err("The bundle doesn't contain the wiki".to_string());
+    }
+    Ok((manifest, wiki_path))
+}
+
+/// Seed the fingerprint cache of an imported wiki, so peers only send what
+/// changed since the export
+fn seed_fingerprints(data_dir: &Path, wiki_id: &str, fingerprints: Vec<TiddlerFingerprint>) {
+    if fingerprints.is_empty() {
+        return;
+    }
+    match get_sync_manager() {
+        Some(mgr) => mgr.update_fingerprint_cache(wiki_id, fingerprints),
+        None => {
+            let mut cache = SyncManager::load_fingerprint_cache(data_dir);
+            cache.insert(wiki_id.to_string(), fingerprints);
+            SyncManager::save_fingerprint_cache_to_disk(data_dir, &cache);
+        }
+    }
+}
+
+/// Import a `.twsync` bundle: extract the wiki into `target_dir`, add it to the
+/// wiki list under the bundled sync id and restore its sync state. The bundle's
+/// room is added if this device isn't in it yet (encrypted bundles only).
+#[tauri::command]
+pub async fn lan_sync_import_bundle(
+    app: tauri::AppHandle,
+    bundle_path: String,
+    target_dir: String,
+    password: Option<String>,
+) -> Result<WikiEntry, String> {
+    let password = password.filter(|p| !p.is_empty());
+    let entries = crate::wiki_storage::load_recent_files_from_disk(&app);
+    let synced_as = move |wiki_id: &str| {
+        entries
+            .iter()
+            .find(|entry| entry.sync_id.as_deref() == Some(wiki_id))
+            .map(|entry| entry.path.clone())
+    };
+    let (manifest, wiki_path) = tokio::task::spawn_blocking(move || {
+        read_bundle(Path::new(&bundle_path), Path::new(&target_dir), password.as_deref(), synced_as)
+    })
+    .await
+    .map_err(|e| format!("Import failed: {}", e))??;
+    let wiki_path = wiki_path.to_string_lossy().into_owned();
+    let Manifest { wiki_id, wiki_name, is_folder, sync_mode, room, fingerprints, tombstones: deletions, .. } = manifest;
+
+    let data_dir = crate::get_data_dir(&app)?;
+    seed_fingerprints(&data_dir, &wiki_id, fingerprints);
+    let tombstones_path = tombstones::file_path(&data_dir, &wiki_id);
+    if !deletions.is_empty() && !tombstones_path.exists() {
+        let json = serde_json::to_string(&deletions).map_err(|e| e.to_string())?;
+        if let Some(dir) = tombstones_path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        std::fs::write(&tombstones_path, json).map_err(|e| e.to_string())?;
+    }
+
+    let mut relay_room = None;
+    if let Some(room) = room {
+        let relay = get_sync_manager().and_then(|mgr| mgr.relay_manager.clone());
+        let known = match &relay {
+            Some(relay) => relay.get_room_credentials(&room.room_code).await.is_some(),
+            None => false,
+        };
+        if known {
+            relay_room = Some(room.room_code);
+        } else if let Some(password) = room.password {
+            super::relay_sync_add_room(room.name, room.room_code.clone(), password, false).await?;
+            relay_room = Some(room.room_code);
+        } else {
+            eprintln!("[LAN Sync] Bundle room {} is not set up on this device", room.room_code);
+        }
+    }
+
+    let entry = WikiEntry {
+        path: wiki_path,
+        filename: wiki_name,
+        display_path: None,
+        favicon: None,
+        is_folder,
+        backups_enabled: !is_folder,
+        backup_dir: None,
+        backup_count: None,
+        group: None,
+        sync_enabled: true,
+        sync_id: Some(wiki_id),
+        sync_peers: vec![],
+        relay_room,
+        sync_mode,
+        custom_icon: None,
+        accent_color: None,
+        emoji: None,
+        scheduled_backup: None,
+        confirm_remote_deletions: false,
+        sync_schedule: None,
+        hotkey: None,
+        sync_priority: None,
+        missing: false,
+    };
+    crate::wiki_storage::add_to_recent_files(&app, entry.clone())?;
+    let _ = app.emit("wiki-list-changed", &entry);
+    Ok(entry)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wiki_entry_path() {
+        assert_eq!(wiki_entry_path(Path::new("wiki/notes.html")), Some(PathBuf::from("notes.html")));
+        assert_eq!(
+            wiki_entry_path(Path::new("wiki/notes/tiddlers/a.tid")),
+            Some(PathBuf::from("notes/tiddlers/a.tid"))
+        );
+        assert_eq!(wiki_entry_path(Path::new("wiki")), None);
+        assert_eq!(wiki_entry_path(Path::new("twsync.json")), None);
+    }
+
+    #[test]
+    fn test_room_password_only_when_given() {
+        let room = BundleRoom { name: "Home".to_string(), room_code: "abc".to_string(), password: None };
+        assert_eq!(serde_json::to_string(&room).unwrap(), r#"{"name":"Home","room_code":"abc"}"#);
+    }
+}
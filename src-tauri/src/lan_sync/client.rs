@@ -28,13 +28,22 @@ pub async fn connect_to_room_peer(
     peers: Arc<RwLock<HashMap<String, PeerConnection>>>,
     event_tx: mpsc::UnboundedSender<ServerEvent>,
 ) -> Result<(), String> {
-    let url = format!("ws://{}:{}", addr, port);
+    if crate::relay_sync::directory::is_revoked(room_code, peer_device_id) {
+        return Err(format!("Device {} is revoked in room {}", peer_device_id, room_code));
+    }
+    let url = super::interfaces::ws_url(addr, port);
     eprintln!(
         "[LAN Sync] Connecting to room peer {} at {} (room {})",
         peer_device_id, url, room_code
     );
 
-    let (ws_stream, _) = tokio_tungstenite::connect_async_with_config(&url, Some(super::server::ws_config()), false)
+    // Connect to the socket address: a link-local IPv6 peer needs its zone,
+    // which the URL can't carry
+    let socket_addr = super::interfaces::socket_addr(addr, port)?;
+    let stream = tokio::net::TcpStream::connect(socket_addr)
+        .await
+        .map_err(|e| format!("WebSocket connect failed: {}", e))?;
+    let (ws_stream, _) = tokio_tungstenite::client_async_with_config(url.as_str(), stream, Some(super::server::ws_config()))
         .await
         .map_err(|e| format!("WebSocket connect failed: {}", e))?;
 
@@ -95,6 +104,10 @@ pub async fn connect_to_room_peer(
             }
 
             eprintln!("[LAN Sync] SPAKE2 auth: server verified — {} ({})", device_name, device_id);
+            if crate::relay_sync::directory::is_revoked(room_code, &device_id) {
+                return Err(format!("Device {} is revoked in room {}", device_id, room_code));
+            }
+            crate::relay_sync::directory::device_seen(room_code, &device_id, &device_name);
             (device_id, device_name, shared_secret)
         }
         RoomAuthMessage::RoomAuthReject { message } => {
@@ -227,6 +240,7 @@ pub async fn connect_to_room_peer(
             match msg_result {
                 Ok(Message::Binary(data)) => match decrypt_message(&cipher, &data) {
                     Ok(sync_msg) => {
+                        crate::transfer_stats::message_received(&inbound_peer_id, &sync_msg, data.len());
                         let _ = event_tx.send(ServerEvent::SyncMessageReceived {
                             from_device_id: inbound_peer_id.clone(),
                             message: sync_msg,
@@ -0,0 +1,267 @@
+//! Open sync conflicts, with both versions of the tiddler.
+//!
+//! When a tiddler was changed here and on a peer at the same time, the vector
+//! clocks conflict. The remote version still wins (last write), and the wiki
+//! keeps its own version as a `$:/TiddlyDesktopRS/Conflicts/` tiddler. This
+//! store also keeps both versions in the main process (`sync_conflicts.json`),
+//! so a conflict can be resolved later, from the wiki or elsewhere: keep the
+//! local version, keep the remote one, or save a merged text.
+//!
+//! The remote version is recorded when the conflict is detected. The local one
+//! arrives once the wiki has taken its snapshot (`lan_sync_conflict_local_version`).
+//! Every update is emitted as a `sync-conflict` event with the whole
+//! `SyncConflict`, and a resolution as `sync-conflict-resolved`.
+
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+const CONFLICTS_FILE: &str = "sync_conflicts.json";
+
+/// A tiddler changed both here and on a peer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncConflict {
+    pub id: String,
+    pub wiki_id: String,
+    pub title: String,
+    pub from_device_id: String,
+    /// Epoch ms
+    pub detected_at: u64,
+    /// This device's version (None until the wiki reported it)
+    pub local_tiddler_json: Option<String>,
+    /// The peer's version (None: the peer deleted the tiddler)
+    pub remote_tiddler_json: Option<String>,
+}
+
+/// How a conflict is settled
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Resolution {
+    /// Keep this device's version (it is synced to the peers again)
+    Local,
+    /// Keep the peer's version (already in the wiki)
+    Remote,
+    /// Save a merged text
+    Merged,
+    /// The wiki settled it itself (the content was identical, or a plugin
+    /// version decided it)
+    Dismiss,
+}
+
+impl Resolution {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "local" => Ok(Self::Local),
+            "remote" => Ok(Self::Remote),
+            "merged" => Ok(Self::Merged),
+            "dismiss" => Ok(Self::Dismiss),
+            _ => Err(format!("Unknown conflict resolution: {}", name)),
+        }
+    }
+}
+
+/// What resolving a conflict writes to the wiki
+#[derive(Debug, PartialEq)]
+pub enum Outcome {
+    /// Nothing: the wiki already has the version to keep
+    Keep,
+    /// Save this tiddler (JSON) as a local change
+    Save(String),
+}
+
+/// TiddlyWiki date of `now_ms` (UTC, `YYYYMMDDHHMMSSmmm`)
+fn tw_date(now_ms: u64) -> String {
+    chrono::DateTime::<chrono::Utc>::from_timestamp_millis(now_ms as i64)
+        .unwrap_or_default()
+        .format("%Y%m%d%H%M%S%3f")
+        .to_string()
+}
+
+/// The result of resolving a conflict. A merged text goes into the remote
+/// version (or the local one when the peer deleted the tiddler), modified now.
+pub fn outcome(
+    conflict: &SyncConflict,
+    resolution: Resolution,
+    merged_text: Option<&str>,
+    now_ms: u64,
+) -> Result<Outcome, String> {
+    match resolution {
+        Resolution::Remote | Resolution::Dismiss => Ok(Outcome::Keep),
+        Resolution::Local => conflict
+            .local_tiddler_json
+            .clone()
+            .map(Outcome::Save)
+            .ok_or_else(|| "The wiki hasn't reported its version of this tiddler yet".to_string()),
+        Resolution::Merged => {
+            let text = merged_text.ok_or("No merged text given")?;
+            let base = conflict
+                .remote_tiddler_json
+                .as_deref()
+                .or(conflict.local_tiddler_json.as_deref())
+                .ok_or("Neither version of this tiddler is known")?;
+            let mut fields: serde_json::Map<String, serde_json::Value> =
+                serde_json::from_str(base).map_err(|e| format!("Invalid tiddler JSON: {}", e))?;
+            fields.insert("title".to_string(), conflict.title.clone().into());
+            fields.insert("text".to_string(), text.into());
+            fields.insert("modified".to_string(), tw_date(now_ms).into());
+            serde_json::to_string(&fields).map(Outcome::Save).map_err(|e| e.to_string())
+        }
+    }
+}
+
+/// The open conflicts of all wikis, persisted in the data directory
+pub struct ConflictStore {
+    path: PathBuf,
+    conflicts: Mutex<Vec<SyncConflict>>,
+}
+
+impl ConflictStore {
+    pub fn new(data_dir: &Path) -> Self {
+        let path = data_dir.join(CONFLICTS_FILE);
+        let conflicts = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+        Self { path, conflicts: Mutex::new(conflicts) }
+    }
+
+    fn save(&self, conflicts: &[SyncConflict]) {
+        let result = serde_json::to_string(conflicts)
+            .map_err(|e| e.to_string())
+            .and_then(|json| std::fs::write(&self.path, json).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            eprintln!("[LAN Sync] Failed to save sync conflicts: {}", e);
+        }
+    }
+
+    /// Record a conflict detected in a remote change. A tiddler has at most
+    /// one open conflict: a further one updates the remote side and keeps
+    /// the local version the wiki reported.
+    pub fn record(
+        &self,
+        wiki_id: &str,
+        title: &str,
+        from_device_id: &str,
+        remote_tiddler_json: Option<String>,
+        now_ms: u64,
+    ) -> SyncConflict {
+        let mut conflicts = self.conflicts.lock().unwrap();
+        let index = match conflicts.iter().position(|c| c.wiki_id == wiki_id && c.title == title) {
+            Some(index) => {
+                let conflict = &mut conflicts[index];
+                conflict.from_device_id = from_device_id.to_string();
+                conflict.remote_tiddler_json = remote_tiddler_json;
+                conflict.detected_at = now_ms;
+                index
+            }
+            None => {
+                conflicts.push(SyncConflict {
+                    id: format!("{:016x}", rand::random::<u64>()),
+                    wiki_id: wiki_id.to_string(),
+                    title: title.to_string(),
+                    from_device_id: from_device_id.to_string(),
+                    detected_at: now_ms,
+                    local_tiddler_json: None,
+                    remote_tiddler_json,
+                });
+                conflicts.len() - 1
+            }
+        };
+        let conflict = conflicts[index].clone();
+        self.save(&conflicts);
+        conflict
+    }
+
+    /// Store the wiki's own version of a conflicting tiddler
+    pub fn set_local(&self, id: &str, tiddler_json: String) -> Option<SyncConflict> {
+        let mut conflicts = self.conflicts.lock().unwrap();
+        let conflict = conflicts.iter_mut().find(|c| c.id == id)?;
+        conflict.local_tiddler_json = Some(tiddler_json);
+        let updated = conflict.clone();
+        self.save(&conflicts);
+        Some(updated)
+    }
+
+    pub fn get(&self, id: &str) -> Option<SyncConflict> {
+        self.conflicts.lock().unwrap().iter().find(|c| c.id == id).cloned()
+    }
+
+    /// Open conflicts, of one wiki or all, oldest first
+    pub fn list(&self, wiki_id: Option<&str>) -> Vec<SyncConflict> {
+        self.conflicts
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|c| wiki_id.is_none_or(|id| c.wiki_id == id))
+            .cloned()
+            .collect()
+    }
+
+    pub fn remove(&self, id: &str) {
+        let mut conflicts = self.conflicts.lock().unwrap();
+        let before = conflicts.len();
+        conflicts.retain(|c| c.id != id);
+        if conflicts.len() != before {
+            self.save(&conflicts);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn conflict(local: Option<&str>, remote: Option<&str>) -> SyncConflict {
+        SyncConflict {
+            id: "c1".to_string(),
+            wiki_id: "w1".to_string(),
+            title: "Notes".to_string(),
+            from_device_id: "d1".to_string(),
+            detected_at: 0,
+            local_tiddler_json: local.map(str::to_string),
+            remote_tiddler_json: remote.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_keep_and_local() {
+        let c = conflict(Some(r#"{"title":"Notes","text":"mine"}"#), Some(r#"{"title":"Notes","text":"theirs"}"#));
+        assert_eq!(outcome(&c, Resolution::Remote, None, 0), Ok(Outcome::Keep));
+        assert_eq!(
+            outcome(&c, Resolution::Local, None, 0),
+            Ok(Outcome::Save(r#"{"title":"Notes","text":"mine"}"#.to_string()))
+        );
+        // The local version must be known
+        assert!(outcome(&conflict(None, Some("{}")), Resolution::Local, None, 0).is_err());
+    }
+
+    #[test]
+    fn test_merged() {
+        let c = conflict(
+            Some(r#"{"title":"Notes","text":"mine","tags":"a"}"#),
+            Some(r#"{"title":"Notes","text":"theirs","tags":"b"}"#),
+        );
+        let Ok(Outcome::Save(json)) = outcome(&c, Resolution::Merged, Some("both"), 1_767_225_600_000) else {
+            panic!("expected a tiddler to save");
+        };
+        let fields: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(fields["text"], "both");
+        // Other fields come from the remote version
+        assert_eq!(fields["tags"], "b");
+        assert_eq!(fields["modified"], "20260101000000000");
+        assert!(outcome(&c, Resolution::Merged, None, 0).is_err());
+
+        // Deleted remotely: merge into the local version
+        let deleted = conflict(Some(r#"{"title":"Notes","text":"mine","tags":"a"}"#), None);
+        let Ok(Outcome::Save(json)) = outcome(&deleted, Resolution::Merged, Some("both"), 0) else {
+            panic!("expected a tiddler to save");
+        };
+        assert!(json.contains(r#""tags":"a""#));
+    }
+
+    #[test]
+    fn test_resolution_names() {
+        assert_eq!(Resolution::parse("merged"), Ok(Resolution::Merged));
+        assert!(Resolution::parse("theirs").is_err());
+    }
+}
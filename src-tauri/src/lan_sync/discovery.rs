@@ -4,17 +4,21 @@
 //! from other TiddlyDesktop instances on the same LAN. Much simpler and more
 //! reliable than mDNS — works without MulticastLock on Android and without
 //! Avahi on Linux.
+//!
+//! Beacons go to the broadcast address of each selected interface and, on
+//! the same port, to an IPv6 link-local multicast group (see `interfaces`).
 
-use hmac::{Hmac, Mac};
-use sha2::Sha256;
-use socket2::{Domain, Protocol, Socket, Type};
+use socket2::{Domain, Protocol, SockRef, Socket, Type};
 use std::collections::{HashMap, HashSet};
-use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6, UdpSocket};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
 
+use super::interfaces;
+pub use super::protocol::hash_room_code;
+
 /// UDP port for discovery beacons
 const DISCOVERY_PORT: u16 = 45699;
 
@@ -24,6 +28,12 @@ const BEACON_INTERVAL: Duration = Duration::from_secs(2);
 /// How long before a peer is considered lost (no beacon received)
 const PEER_TIMEOUT: Duration = Duration::from_secs(10);
 
+/// IPv6 link-local multicast group for beacons
+const MULTICAST_V6: Ipv6Addr = Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 0x7464, 0x7273);
+
+/// How often the interfaces are read again (VPNs and NICs come and go)
+const INTERFACE_REFRESH: Duration = Duration::from_secs(10);
+
 /// Events from the discovery system
 #[derive(Debug, Clone)]
 pub enum DiscoveryEvent {
@@ -61,19 +71,82 @@ struct Beacon {
     room_hashes: Vec<String>,
 }
 
-/// Hash a room code for use in discovery beacons.
-/// Uses HMAC-SHA256 with a fixed label, truncated to first 8 bytes (16 hex chars).
-/// This prevents passive LAN observers from learning room codes while still
-/// allowing peers to match rooms by hashing their own codes and comparing.
-pub fn hash_room_code(room_code: &str) -> String {
-    let mut mac = Hmac::<Sha256>::new_from_slice(b"tiddlydesktop-discovery-room-hash")
-        .expect("HMAC can take key of any size");
-    mac.update(room_code.as_bytes());
-    let result = mac.finalize().into_bytes();
-    result[..8]
-        .iter()
-        .map(|b| format!("{:02x}", b))
-        .collect()
+/// Where beacons go: the selected interfaces' broadcast addresses and the
+/// interfaces the IPv6 socket joined the multicast group on
+#[derive(Default)]
+struct BeaconTargets {
+    ipv4: Vec<SocketAddr>,
+    ipv6_indexes: Vec<u32>,
+    refreshed: Option<Instant>,
+}
+
+impl BeaconTargets {
+    /// Read the interfaces again unless that was done recently
+    fn refresh(&mut self, socket_v6: Option<&UdpSocket>) {
+        if self.refreshed.is_some_and(|at| at.elapsed() < INTERFACE_REFRESH) {
+            return;
+        }
+        self.refreshed = Some(Instant::now());
+
+        let selection = interfaces::selection();
+        let selected = interfaces::selected(&selection);
+        let mut ipv4 = interfaces::ipv4_broadcast_targets(&selected);
+        // Nothing to go by: the limited broadcast leaves through the default route
+        if ipv4.is_empty() && selection.is_empty() {
+            ipv4.push(Ipv4Addr::BROADCAST);
+        }
+        self.ipv4 = ipv4
+            .into_iter()
+            .map(|ip| SocketAddrV4::new(ip, DISCOVERY_PORT).into())
+            .collect();
+
+        let indexes = interfaces::ipv6_indexes(&selected);
+        if let Some(socket) = socket_v6 {
+            let socket = SockRef::from(socket);
+            for index in indexes.iter().filter(|i| !self.ipv6_indexes.contains(i)) {
+                if let Err(e) = socket.join_multicast_v6(&MULTICAST_V6, *index) {
+                    eprintln!("[LAN Sync] Failed to join IPv6 discovery group on interface {}: {}", index, e);
+                }
+            }
+            for index in self.ipv6_indexes.iter().filter(|i| !indexes.contains(i)) {
+                let _ = socket.leave_multicast_v6(&MULTICAST_V6, *index);
+            }
+        }
+        self.ipv6_indexes = indexes;
+    }
+
+    fn send(&self, socket: &UdpSocket, socket_v6: Option<&UdpSocket>, data: &[u8]) {
+        for target in &self.ipv4 {
+            let _ = socket.send_to(data, target);
+        }
+        if let Some(socket_v6) = socket_v6 {
+            for index in &self.ipv6_indexes {
+                if SockRef::from(socket_v6).set_multicast_if_v6(*index).is_ok() {
+                    let _ = socket_v6.send_to(data, SocketAddrV6::new(MULTICAST_V6, DISCOVERY_PORT, 0, *index));
+                }
+            }
+        }
+    }
+}
+
+/// IPv6 beacon socket (non-blocking; drained after each IPv4 receive)
+fn ipv6_socket() -> Result<UdpSocket, String> {
+    let socket = Socket::new(Domain::IPV6, Type::DGRAM, Some(Protocol::UDP))
+        .map_err(|e| format!("Failed to create IPv6 discovery socket: {}", e))?;
+    socket
+        .set_only_v6(true)
+        .map_err(|e| format!("Failed to set IPV6_V6ONLY: {}", e))?;
+    socket
+        .set_reuse_address(true)
+        .map_err(|e| format!("Failed to set SO_REUSEADDR: {}", e))?;
+    socket
+        .set_nonblocking(true)
+        .map_err(|e| format!("Failed to make the IPv6 discovery socket non-blocking: {}", e))?;
+    let bind_addr = SocketAddrV6::new(Ipv6Addr::UNSPECIFIED, DISCOVERY_PORT, 0, 0);
+    socket
+        .bind(&bind_addr.into())
+        .map_err(|e| format!("Failed to bind IPv6 discovery socket to port {}: {}", DISCOVERY_PORT, e))?;
+    Ok(socket.into())
 }
 
 /// The UDP broadcast discovery manager
@@ -124,7 +197,16 @@ impl DiscoveryManager {
             .bind(&bind_addr.into())
             .map_err(|e| format!("Failed to bind discovery socket to port {}: {}", DISCOVERY_PORT, e))?;
 
-        let socket: std::net::UdpSocket = socket.into();
+        let socket: UdpSocket = socket.into();
+
+        // IPv6 is optional: IPv4 discovery goes on without it
+        let socket_v6 = match ipv6_socket() {
+            Ok(socket) => Some(socket),
+            Err(e) => {
+                eprintln!("[LAN Sync] IPv6 discovery unavailable: {}", e);
+                None
+            }
+        };
 
         let our_id = device_id.to_string();
         let our_name = device_name.to_string();
@@ -143,8 +225,8 @@ impl DiscoveryManager {
 
         // Spawn discovery thread
         std::thread::spawn(move || {
-            let broadcast_addr: SocketAddr =
-                SocketAddrV4::new(Ipv4Addr::BROADCAST, DISCOVERY_PORT).into();
+            let mut targets = BeaconTargets::default();
+            targets.refresh(socket_v6.as_ref());
             let mut peers: HashMap<String, Instant> = HashMap::new();
             // Track when we last emitted PeerDiscovered per peer, for throttled re-emission
             let mut last_emitted: HashMap<String, Instant> = HashMap::new();
@@ -173,7 +255,7 @@ impl DiscoveryManager {
             // (UDP is unreliable — a single beacon could be lost)
             for i in 0..3 {
                 let beacon_data = make_beacon_data(1);
-                let _ = socket.send_to(&beacon_data, broadcast_addr);
+                targets.send(&socket, socket_v6.as_ref(), &beacon_data);
                 if i < 2 {
                     std::thread::sleep(Duration::from_millis(200));
                 }
@@ -185,7 +267,7 @@ impl DiscoveryManager {
                     // Send goodbye beacon so peers remove us immediately
                     let goodbye_data = make_beacon_data(0);
                     for _ in 0..3 {
-                        let _ = socket.send_to(&goodbye_data, broadcast_addr);
+                        targets.send(&socket, socket_v6.as_ref(), &goodbye_data);
                     }
                     break;
                 }
@@ -195,7 +277,7 @@ impl DiscoveryManager {
                 if force_beacon_clone.swap(false, Ordering::Relaxed) {
                     let beacon_data = make_beacon_data(1);
                     for i in 0..3u8 {
-                        let _ = socket.send_to(&beacon_data, broadcast_addr);
+                        targets.send(&socket, socket_v6.as_ref(), &beacon_data);
                         if i < 2 {
                             std::thread::sleep(Duration::from_millis(150));
                         }
@@ -206,8 +288,9 @@ impl DiscoveryManager {
 
                 // Send beacon periodically (re-serialize each time to pick up room changes)
                 if last_broadcast.elapsed() >= BEACON_INTERVAL {
+                    targets.refresh(socket_v6.as_ref());
                     let beacon_data = make_beacon_data(1);
-                    let _ = socket.send_to(&beacon_data, broadcast_addr);
+                    targets.send(&socket, socket_v6.as_ref(), &beacon_data);
                     last_broadcast = Instant::now();
 
                     // Check for timed-out peers (skip peers with active WebSocket connections)
@@ -231,8 +314,13 @@ impl DiscoveryManager {
                     }
                 }
 
-                // Receive beacons from other devices (drain all buffered packets)
-                match socket.recv_from(&mut buf) {
+                // Receive beacons from other devices (drain all buffered packets),
+                // IPv6 ones once the IPv4 socket has nothing
+                let received = socket.recv_from(&mut buf).or_else(|e| match &socket_v6 {
+                    Some(socket_v6) if is_timeout(&e) => socket_v6.recv_from(&mut buf),
+                    _ => Err(e),
+                });
+                match received {
                     Ok((len, _src_addr)) => {
                         if let Ok(beacon) = serde_json::from_slice::<Beacon>(&buf[..len]) {
                             // Ignore our own beacons
@@ -304,10 +392,7 @@ impl DiscoveryManager {
 
                             if should_emit {
                                 last_emitted.insert(beacon.id.clone(), Instant::now());
-                                let addr = match _src_addr {
-                                    SocketAddr::V4(v4) => v4.ip().to_string(),
-                                    SocketAddr::V6(v6) => v6.ip().to_string(),
-                                };
+                                let addr = interfaces::peer_addr(_src_addr);
                                 if is_new {
                                     eprintln!(
                                         "[LAN Sync] Discovered peer: {} ({}) at {}:{} room_hashes={}",
@@ -325,8 +410,7 @@ impl DiscoveryManager {
                             }
                         }
                     }
-                    Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock
-                        || e.kind() == std::io::ErrorKind::TimedOut => {
+                    Err(ref e) if is_timeout(e) => {
                         // No data available — read timeout handles the wait efficiently
                     }
                     Err(_) => {
@@ -354,6 +438,11 @@ impl DiscoveryManager {
     }
 }
 
+/// A receive that found nothing (read timeout or nothing on a non-blocking socket)
+fn is_timeout(e: &std::io::Error) -> bool {
+    e.kind() == std::io::ErrorKind::WouldBlock || e.kind() == std::io::ErrorKind::TimedOut
+}
+
 impl Drop for DiscoveryManager {
     fn drop(&mut self) {
         self.shutdown();
@@ -0,0 +1,205 @@
+//! Network interfaces for LAN sync.
+//!
+//! Discovery beacons go out on every selected interface (`lan_sync_interfaces`
+//! in the app settings; all of them when none are selected): as a directed
+//! IPv4 broadcast, so they reach each subnet of a machine with several NICs or
+//! VPNs rather than only the default route's, and to an IPv6 link-local
+//! multicast group, so peers on IPv6-only networks find each other too.
+//!
+//! Peer addresses are kept as text. IPv6 link-local ones carry their zone
+//! (`fe80::1%3`), which URLs can't, so connections go to a socket address
+//! built by `socket_addr` and the URL (`ws_url`) only names the host.
+
+use std::collections::BTreeMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV6};
+
+use if_addrs::{IfAddr, Ifv4Addr, Interface};
+
+/// Names of the interfaces LAN sync is limited to (empty = all)
+pub fn selection() -> Vec<String> {
+    crate::GLOBAL_APP_HANDLE
+        .get()
+        .and_then(|app| crate::wiki_storage::load_app_settings(app).ok())
+        .map(|settings| settings.lan_sync_interfaces)
+        .unwrap_or_default()
+}
+
+/// Non-loopback interface addresses, limited to `selection` unless it is empty
+pub fn selected(selection: &[String]) -> Vec<Interface> {
+    if_addrs::get_if_addrs()
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|interface| !interface.is_loopback())
+        .filter(|interface| selection.is_empty() || selection.contains(&interface.name))
+        .collect()
+}
+
+/// Broadcast address of an IPv4 interface address (none for point-to-point
+/// links such as many VPN tunnels)
+fn broadcast_address(addr: &Ifv4Addr) -> Option<Ipv4Addr> {
+    if let Some(broadcast) = addr.broadcast {
+        return Some(broadcast);
+    }
+    let mask = u32::from(addr.netmask);
+    if mask == u32::MAX {
+        return None;
+    }
+    Some(Ipv4Addr::from(u32::from(addr.ip) | !mask))
+}
+
+/// Directed broadcast addresses of the interfaces, without duplicates
+pub fn ipv4_broadcast_targets(interfaces: &[Interface]) -> Vec<Ipv4Addr> {
+    let mut targets: Vec<Ipv4Addr> = Vec::new();
+    for interface in interfaces {
+        if let IfAddr::V4(v4) = &interface.addr {
+            if let Some(broadcast) = broadcast_address(v4) {
+                if !targets.contains(&broadcast) {
+                    targets.push(broadcast);
+                }
+            }
+        }
+    }
+    targets
+}
+
+/// Indexes of the interfaces with an IPv6 address, for multicast
+pub fn ipv6_indexes(interfaces: &[Interface]) -> Vec<u32> {
+    let mut indexes: Vec<u32> = Vec::new();
+    for interface in interfaces {
+        if let (IfAddr::V6(_), Some(index)) = (&interface.addr, interface.index) {
+            if !indexes.contains(&index) {
+                indexes.push(index);
+            }
+        }
+    }
+    indexes
+}
+
+fn is_ipv6_link_local(ip: &Ipv6Addr) -> bool {
+    ip.segments()[0] & 0xffc0 == 0xfe80
+}
+
+/// A peer's address as text: IPv4-mapped addresses as IPv4, link-local IPv6
+/// addresses with their zone
+pub fn peer_addr(src: SocketAddr) -> String {
+    match src {
+        SocketAddr::V4(v4) => v4.ip().to_string(),
+        SocketAddr::V6(v6) => match v6.ip().to_ipv4_mapped() {
+            Some(v4) => v4.to_string(),
+            None if is_ipv6_link_local(v6.ip()) && v6.scope_id() != 0 => {
+                format!("{}%{}", v6.ip(), v6.scope_id())
+            }
+            None => v6.ip().to_string(),
+        },
+    }
+}
+
+/// Socket address of a peer (`addr` as from `peer_addr`)
+pub fn socket_addr(addr: &str, port: u16) -> Result<SocketAddr, String> {
+    let (ip, zone) = match addr.split_once('%') {
+        Some((ip, zone)) => (ip, Some(zone)),
+        None => (addr, None),
+    };
+    let ip: IpAddr = ip.parse().map_err(|_| format!("Invalid peer address: {}", addr))?;
+    match (ip, zone) {
+        (IpAddr::V6(v6), Some(zone)) => {
+            let scope_id = zone.parse().map_err(|_| format!("Invalid zone in peer address: {}", addr))?;
+            Ok(SocketAddrV6::new(v6, port, 0, scope_id).into())
+        }
+        (ip, _) => Ok(SocketAddr::new(ip, port)),
+    }
+}
+
+/// WebSocket URL of a peer's sync server (the zone of a link-local address
+/// isn't part of it)
+pub fn ws_url(addr: &str, port: u16) -> String {
+    let host = addr.split('%').next().unwrap_or(addr);
+    if host.contains(':') {
+        format!("ws://[{}]:{}", host, port)
+    } else {
+        format!("ws://{}:{}", host, port)
+    }
+}
+
+/// A network interface for the settings
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct InterfaceInfo {
+    pub name: String,
+    pub addresses: Vec<String>,
+    /// LAN sync uses it (all interfaces are used when none are selected)
+    pub selected: bool,
+}
+
+/// The network interfaces LAN sync can use
+#[tauri::command]
+pub fn lan_sync_get_interfaces() -> Vec<InterfaceInfo> {
+    let selection = selection();
+    let mut by_name: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for interface in selected(&[]) {
+        by_name.entry(interface.name.clone()).or_default().push(interface.ip().to_string());
+    }
+    by_name
+        .into_iter()
+        .map(|(name, addresses)| InterfaceInfo {
+            selected: selection.is_empty() || selection.contains(&name),
+            name,
+            addresses,
+        })
+        .collect()
+}
+
+/// Limit LAN sync to some network interfaces (empty = all). Discovery picks
+/// the change up within a few seconds.
+#[tauri::command]
+pub fn lan_sync_set_interfaces(app: tauri::AppHandle, names: Vec<String>) -> Result<Vec<InterfaceInfo>, String> {
+    let mut settings = crate::wiki_storage::load_app_settings(&app)?;
+    settings.lan_sync_interfaces = names;
+    crate::wiki_storage::save_app_settings(&app, &settings)?;
+    Ok(lan_sync_get_interfaces())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v4(ip: [u8; 4], netmask: [u8; 4], broadcast: Option<[u8; 4]>) -> Ifv4Addr {
+        Ifv4Addr {
+            ip: ip.into(),
+            netmask: netmask.into(),
+            broadcast: broadcast.map(Ipv4Addr::from),
+        }
+    }
+
+    #[test]
+    fn test_broadcast_address() {
+        let lan = v4([192, 168, 1, 20], [255, 255, 255, 0], None);
+        assert_eq!(broadcast_address(&lan), Some(Ipv4Addr::new(192, 168, 1, 255)));
+        let reported = v4([10, 8, 0, 2], [255, 255, 0, 0], Some([10, 8, 255, 255]));
+        assert_eq!(broadcast_address(&reported), Some(Ipv4Addr::new(10, 8, 255, 255)));
+        let tunnel = v4([100, 64, 0, 7], [255, 255, 255, 255], None);
+        assert_eq!(broadcast_address(&tunnel), None);
+    }
+
+    #[test]
+    fn test_peer_addr() {
+        assert_eq!(peer_addr("192.168.1.20:45699".parse().unwrap()), "192.168.1.20");
+        assert_eq!(peer_addr("[::ffff:192.168.1.20]:45699".parse().unwrap()), "192.168.1.20");
+        assert_eq!(peer_addr("[2001:db8::1]:45699".parse().unwrap()), "2001:db8::1");
+        let link_local = SocketAddrV6::new("fe80::1".parse().unwrap(), 45699, 0, 3);
+        assert_eq!(peer_addr(link_local.into()), "fe80::1%3");
+    }
+
+    #[test]
+    fn test_socket_addr_and_url() {
+        assert_eq!(socket_addr("192.168.1.20", 45700).unwrap(), "192.168.1.20:45700".parse().unwrap());
+        assert_eq!(socket_addr("2001:db8::1", 45700).unwrap(), "[2001:db8::1]:45700".parse().unwrap());
+        let link_local = socket_addr("fe80::1%3", 45700).unwrap();
+        assert_eq!(link_local, SocketAddrV6::new("fe80::1".parse().unwrap(), 45700, 0, 3).into());
+        assert!(socket_addr("fe80::1%eth0", 45700).is_err());
+        assert!(socket_addr("not an address", 45700).is_err());
+
+        assert_eq!(ws_url("192.168.1.20", 45700), "ws://192.168.1.20:45700");
+        assert_eq!(ws_url("2001:db8::1", 45700), "ws://[2001:db8::1]:45700");
+        assert_eq!(ws_url("fe80::1%3", 45700), "ws://[fe80::1]:45700");
+    }
+}
@@ -3,28 +3,39 @@
 //! This module provides:
 //! - Encrypted WebSocket connections between devices (ChaCha20-Poly1305)
 //! - Room-based authentication (shared room code + password)
-//! - UDP broadcast discovery of peers on the LAN
-//! - Vector clock-based conflict resolution
+//! - UDP broadcast (and IPv6 multicast) discovery of peers on the LAN
+//! - Vector clock-based conflict detection, with open conflicts kept for resolution
+//! - Deletion tombstones with a retention window, restorable remote deletions
+//! - Snapshots before sync sessions, to roll back the last one
+//! - Relay rooms that use the relay only while their devices aren't on the LAN
 //! - Chunked attachment file transfer
+//! - `.twsync` bundles that carry a wiki with its sync state to another device
 //!
 //! Architecture:
 //! - One sync server per device in the main Tauri process
 //! - All wikis multiplexed over one WebSocket per peer
 //! - Desktop: bridges to wiki windows via IPC
 //! - Android: bridges to :wiki process via HTTP
+//! - Message types, encryption and conflict detection come from
+//!   `tiddlydesktop_core::sync` (re-exported here)
 
 
 pub mod attachments;
+pub mod bundle;
 #[cfg(target_os = "android")]
 pub mod android_bridge;
 pub mod bridge;
 pub mod client;
-pub mod conflict;
+pub mod conflict_store;
 pub mod discovery;
+pub mod interfaces;
 pub mod pairing;
-pub mod protocol;
+pub mod preview;
 pub mod server;
-pub mod wiki_info;
+pub mod snapshots;
+pub mod tombstones;
+pub mod transport;
+pub use tiddlydesktop_core::sync::{conflict, protocol, wiki_info};
 
 use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, OnceLock};
@@ -33,12 +44,13 @@ use tokio::sync::{mpsc, RwLock};
 use self::attachments::AttachmentManager;
 use self::bridge::{SyncBridge, SyncToWiki, WikiToSync};
 use self::conflict::ConflictManager;
+use self::conflict_store::{ConflictStore, Outcome, Resolution, SyncConflict};
 use self::discovery::{DiscoveryEvent, DiscoveryManager};
 use self::pairing::PairingManager;
 use self::protocol::SyncMessage;
 use self::server::{PeerConnection, ServerEvent, SyncServer};
 
-use crate::relay_sync::RelaySyncManager;
+use crate::relay_sync::{directory, RelaySyncManager};
 use crate::GLOBAL_APP_HANDLE;
 use tauri::{Emitter, Manager};
 
@@ -63,6 +75,18 @@ pub fn queue_lan_sync_ipc(payload_json: String) {
     queue.lock().unwrap().push(payload_json);
 }
 
+/// Largest text accepted for the shared clipboard (bytes)
+const MAX_CLIPBOARD_SHARE_SIZE: usize = 256 * 1024;
+
+/// Whether the user opted in to the shared clipboard (`AppSettings::shared_clipboard`)
+fn shared_clipboard_enabled() -> bool {
+    GLOBAL_APP_HANDLE
+        .get()
+        .and_then(|app| crate::wiki_storage::load_app_settings(app).ok())
+        .map(|settings| settings.shared_clipboard)
+        .unwrap_or(false)
+}
+
 /// Per-wiki sync configuration stored in wiki configs
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, Default)]
 pub struct WikiSyncConfig {
@@ -81,6 +105,9 @@ pub struct SyncStatus {
     pub port: Option<u16>,
     pub connected_peers: Vec<PeerInfo>,
     pub relay_connected: bool,
+    /// What carries the sync of each wiki assigned to a relay room
+    #[serde(default)]
+    pub wiki_transports: Vec<transport::WikiTransport>,
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -108,6 +135,8 @@ pub struct SyncManager {
     data_dir: std::path::PathBuf,
     pairing_manager: Arc<PairingManager>,
     conflict_manager: Arc<ConflictManager>,
+    /// Open conflicts with both versions, until resolved
+    conflict_store: Arc<ConflictStore>,
     attachment_manager: Arc<AttachmentManager>,
     bridge: Arc<SyncBridge>,
     /// Fast flag checked by the event loop to skip messages after stop()
@@ -255,6 +284,8 @@ impl SyncManager {
             data_dir,
         ));
 
+        let conflict_store = Arc::new(ConflictStore::new(data_dir));
+
         let attachment_manager = Arc::new(AttachmentManager::new());
 
         let (bridge, wiki_rx) = SyncBridge::new();
@@ -283,6 +314,7 @@ impl SyncManager {
             data_dir: data_dir.to_path_buf(),
             pairing_manager,
             conflict_manager,
+            conflict_store,
             attachment_manager,
             bridge,
             running: std::sync::atomic::AtomicBool::new(false),
@@ -394,6 +426,41 @@ impl SyncManager {
         Some(fps)
     }
 
+    /// Answer a peer's sync preview with our fingerprints of a wiki, if it is
+    /// shared with that peer (the cached ones, else read from the wiki file)
+    async fn answer_preview_request(&self, device_id: &str, wiki_id: &str) {
+        let Some(app) = GLOBAL_APP_HANDLE.get() else { return };
+        let shared = crate::wiki_storage::get_sync_enabled_wikis(app).iter().any(|(id, _, _)| id == wiki_id)
+            && self.is_peer_allowed_for_wiki(app, wiki_id, device_id).await;
+        let fingerprints = if !shared {
+            None
+        } else if let Some(fps) = self.get_accurate_cached_fingerprints(wiki_id) {
+            Some(fps)
+        } else {
+            let path = crate::wiki_storage::get_wiki_path_by_sync_id(app, wiki_id);
+            let fps = tokio::task::spawn_blocking(move || path.map(|p| preview::file_fingerprints(std::path::Path::new(&p))))
+                .await
+                .ok()
+                .flatten();
+            match fps {
+                Some(Ok(fps)) => Some(fps),
+                Some(Err(e)) => {
+                    eprintln!("[LAN Sync] Preview: failed to read wiki {}: {}", wiki_id, e);
+                    None
+                }
+                None => None,
+            }
+        };
+        let reply = SyncMessage::PreviewFingerprints {
+            wiki_id: wiki_id.to_string(),
+            available: fingerprints.is_some(),
+            fingerprints: fingerprints.unwrap_or_default(),
+        };
+        if let Err(e) = self.send_to_peer_any(device_id, &reply).await {
+            eprintln!("[LAN Sync] Failed to send preview fingerprints to {}: {}", device_id, e);
+        }
+    }
+
     /// Deduplication: check if we recently sent fingerprints to this peer for this wiki.
     /// Returns true if we should skip (sent within last 3s). Records the send if not skipped.
     fn dedup_fp_send(&self, wiki_id: &str, device_id: &str) -> bool {
@@ -498,6 +565,8 @@ impl SyncManager {
                     mgr.run_event_loop(erx, wrx).await;
                 }
             });
+            // Relay fallback of rooms synced over the LAN
+            transport::start();
         }
 
         // Start Android bridge early so relay-only sync can deliver changes to wiki windows
@@ -578,6 +647,24 @@ impl SyncManager {
         }
     }
 
+    /// Disconnect the relay rooms while the connection is metered (`metered`);
+    /// they stay activated, so LAN sync goes on
+    pub async fn suspend_relay(&self) {
+        if let Some(relay) = &self.relay_manager {
+            eprintln!("[Relay] Metered connection — disconnecting relay rooms");
+            relay.stop_all().await;
+        }
+    }
+
+    /// Reconnect the auto-connect relay rooms once the connection is unmetered
+    pub async fn resume_relay(&self) {
+        if let Some(relay) = &self.relay_manager {
+            if let Err(e) = relay.start_all().await {
+                eprintln!("[Relay] Failed to reconnect rooms: {}", e);
+            }
+        }
+    }
+
     /// Start the sync server and mDNS discovery
     pub async fn start(&self) -> Result<(), String> {
         // Guard against multiple starts
@@ -1057,6 +1144,72 @@ impl SyncManager {
         }
     }
 
+    /// Send text to the shared clipboard of a connected device (LAN or relay)
+    pub async fn share_clipboard(&self, device_id: &str, text: String) -> Result<(), String> {
+        if !shared_clipboard_enabled() {
+            return Err("Shared clipboard is disabled".to_string());
+        }
+        if text.is_empty() {
+            return Err("Nothing to share".to_string());
+        }
+        if text.len() > MAX_CLIPBOARD_SHARE_SIZE {
+            return Err(format!(
+                "Text too large to share ({} KB, max {} KB)",
+                text.len() / 1024,
+                MAX_CLIPBOARD_SHARE_SIZE / 1024
+            ));
+        }
+        let msg = SyncMessage::ClipboardShare {
+            device_name: self.pairing_manager.device_name().to_string(),
+            text,
+        };
+        self.send_to_peer_any(device_id, &msg).await
+    }
+
+    /// Connected devices (LAN + relay) that text can be shared with
+    pub async fn clipboard_peers(&self) -> Vec<PeerInfo> {
+        let names = self.peer_user_names.lock().map(|n| n.clone()).unwrap_or_default();
+        self.connected_peers_all()
+            .await
+            .into_iter()
+            .map(|(device_id, device_name)| PeerInfo {
+                user_name: names.get(&device_id).cloned(),
+                device_id,
+                device_name,
+            })
+            .collect()
+    }
+
+    /// Show text shared by a peer: as a notification in the landing page and
+    /// (desktop) as a paste banner in the wiki windows
+    fn handle_clipboard_share(from_device_id: &str, device_name: &str, text: &str) {
+        if !shared_clipboard_enabled() {
+            eprintln!("[LAN Sync] Ignoring shared clipboard from {} (disabled)", from_device_id);
+            return;
+        }
+        eprintln!(
+            "[LAN Sync] Received shared clipboard from {} ({} bytes)",
+            from_device_id,
+            text.len()
+        );
+        if let Some(app) = GLOBAL_APP_HANDLE.get() {
+            let _ = app.emit(
+                "lan-sync-clipboard-received",
+                serde_json::json!({ "from": device_name, "text": text }),
+            );
+        }
+        // No wiki_id: delivered to every wiki process, the focused window shows it
+        #[cfg(not(target_os = "android"))]
+        if let Some(server) = crate::GLOBAL_IPC_SERVER.get() {
+            let payload = serde_json::json!({
+                "type": "clipboard-received",
+                "from": device_name,
+                "text": text,
+            }).to_string();
+            server.send_lan_sync_to_all("", &payload);
+        }
+    }
+
     /// Stop the sync server and discovery
     pub async fn stop(&self) {
         // Mark as not running immediately so the event loop stops processing messages
@@ -1665,6 +1818,7 @@ impl SyncManager {
 
     /// Get current sync status
     pub async fn get_status(&self) -> SyncStatus {
+        let wiki_transports = transport::wiki_transports(self).await;
         let server = self.server.read().await;
         let port = server.as_ref().map(|s| s.port());
         let connected = if let Some(s) = server.as_ref() {
@@ -1693,6 +1847,7 @@ impl SyncManager {
                 })
                 .collect(),
             relay_connected,
+            wiki_transports,
         }
     }
 
@@ -2213,6 +2368,132 @@ impl SyncManager {
         });
     }
 
+    // ── Sync conflicts ───────────────────────────────────────────────
+
+    /// Open sync conflicts, of one wiki or all
+    pub fn get_conflicts(&self, wiki_id: Option<&str>) -> Vec<SyncConflict> {
+        self.conflict_store.list(wiki_id)
+    }
+
+    /// The wiki reports its own version of a conflicting tiddler
+    pub fn set_conflict_local_version(&self, wiki_id: &str, conflict_id: &str, tiddler_json: String) -> Result<(), String> {
+        self.open_conflict(wiki_id, conflict_id)?;
+        if let Some(conflict) = self.conflict_store.set_local(conflict_id, tiddler_json) {
+            if let Some(app) = GLOBAL_APP_HANDLE.get() {
+                let _ = app.emit("sync-conflict", &conflict);
+            }
+        }
+        Ok(())
+    }
+
+    fn open_conflict(&self, wiki_id: &str, conflict_id: &str) -> Result<SyncConflict, String> {
+        self.conflict_store
+            .get(conflict_id)
+            .filter(|c| c.wiki_id == wiki_id)
+            .ok_or_else(|| format!("No open sync conflict {}", conflict_id))
+    }
+
+    /// Resolve a conflict: "local" saves this device's version again, "merged"
+    /// saves `merged_text`, and both sync to the peers as a new local change.
+    /// "remote" keeps the peer's version the wiki already has, "dismiss" just
+    /// closes the conflict. The wiki drops its conflict tiddler in any case.
+    pub fn resolve_conflict(
+        &self,
+        wiki_id: &str,
+        conflict_id: &str,
+        resolution: &str,
+        merged_text: Option<&str>,
+    ) -> Result<(), String> {
+        let conflict = self.open_conflict(wiki_id, conflict_id)?;
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        let outcome = conflict_store::outcome(&conflict, Resolution::parse(resolution)?, merged_text, now)?;
+        if let Outcome::Save(tiddler_json) = outcome {
+            Self::emit_to_wiki(wiki_id, "lan-sync-apply-change", serde_json::json!({
+                "type": "apply-change",
+                "wiki_id": wiki_id,
+                "title": conflict.title,
+                "tiddler_json": tiddler_json,
+            }));
+            self.notify_tiddler_changed(wiki_id, &conflict.title, &tiddler_json);
+        }
+        Self::emit_to_wiki(wiki_id, "lan-sync-conflict-resolved", serde_json::json!({
+            "type": "conflict-resolved",
+            "wiki_id": wiki_id,
+            "title": conflict.title,
+            "conflict_id": conflict_id,
+        }));
+        self.conflict_store.remove(conflict_id);
+        if let Some(app) = GLOBAL_APP_HANDLE.get() {
+            let _ = app.emit("sync-conflict-resolved", serde_json::json!({
+                "id": conflict_id,
+                "wiki_id": wiki_id,
+                "title": conflict.title,
+                "resolution": resolution,
+            }));
+        }
+        Ok(())
+    }
+
+    /// Have the open wiki window sync now: send the changes it held back,
+    /// apply the ones that arrived and catch up with its peers (for wikis on
+    /// an interval or manual sync schedule; realtime ones just catch up)
+    pub fn sync_now(&self, wiki_id: &str) -> Result<(), String> {
+        let sent = Self::emit_to_wiki(wiki_id, "lan-sync-sync-now", serde_json::json!({
+            "type": "sync-now",
+            "wiki_id": wiki_id,
+        }));
+        if sent == 0 {
+            return Err("Open the wiki to sync it".to_string());
+        }
+        Ok(())
+    }
+
+    // ── Deletions ────────────────────────────────────────────────────
+
+    /// Have the open wiki window act on one of its recorded deletions:
+    /// "restore-deletion" re-creates the tiddler from the copy kept with the
+    /// tombstone, "confirm-deletion" applies (`apply`) or rejects a pending one.
+    /// Both sync to the peers like local changes.
+    fn send_deletion_action(&self, wiki_id: &str, title: &str, action: &str, apply: bool) -> Result<(), String> {
+        let stored = tombstones::load(&self.data_dir, wiki_id);
+        let record = tombstones::records(wiki_id, &stored)
+            .into_iter()
+            .find(|r| r.title == title)
+            .ok_or_else(|| format!("No recorded deletion of {}", title))?;
+        match action {
+            "restore-deletion" if !record.restorable => {
+                return Err(format!("{} can't be restored", title));
+            }
+            "confirm-deletion" if !record.pending => {
+                return Err(format!("The deletion of {} isn't waiting for confirmation", title));
+            }
+            _ => {}
+        }
+        let sent = Self::emit_to_wiki(wiki_id, "lan-sync-deletion-action", serde_json::json!({
+            "type": action,
+            "wiki_id": wiki_id,
+            "title": title,
+            "apply": apply,
+        }));
+        if sent == 0 {
+            return Err("Open the wiki to change its deletions".to_string());
+        }
+        Ok(())
+    }
+
+    /// Bring back a tiddler a peer deleted
+    pub fn restore_deletion(&self, wiki_id: &str, title: &str) -> Result<(), String> {
+        self.send_deletion_action(wiki_id, title, "restore-deletion", false)
+    }
+
+    /// Apply or reject a deletion waiting for confirmation
+    pub fn confirm_deletion(&self, wiki_id: &str, title: &str, apply: bool) -> Result<(), String> {
+        self.send_deletion_action(wiki_id, title, "confirm-deletion", apply)
+    }
+
     // ── Collaborative editing methods ────────────────────────────────
 
     /// Notify peers that we started editing a tiddler
@@ -2700,7 +2981,7 @@ impl SyncManager {
                 _ = maintenance_interval.tick() => {
                     self.check_fingerprint_timeouts().await;
                     self.conflict_manager.flush_dirty_states();
-                    self.conflict_manager.prune_tombstones();
+                    self.conflict_manager.prune_tombstones(tombstones::retention_days());
                 }
                 else => break,
             }
@@ -2822,7 +3103,7 @@ impl SyncManager {
                 _ = maintenance_interval.tick() => {
                     self.check_fingerprint_timeouts().await;
                     self.conflict_manager.flush_dirty_states();
-                    self.conflict_manager.prune_tombstones();
+                    self.conflict_manager.prune_tombstones(tombstones::retention_days());
                 }
                 _ = attachment_scan_interval.tick() => {
                     self.scan_android_attachments().await;
@@ -2863,6 +3144,7 @@ impl SyncManager {
     fn spawn_emit_task(&self) -> tokio::task::JoinHandle<()> {
         let sync_to_wiki_rx = self.bridge.sync_to_wiki_rx.clone();
         let conflict_manager = self.conflict_manager.clone();
+        let conflict_store = self.conflict_store.clone();
 
         tokio::spawn(async move {
             let mut rx = sync_to_wiki_rx.lock().await;
@@ -2892,11 +3174,12 @@ impl SyncManager {
                             }
                         }
                     }
-                    SyncToWiki::ApplyTiddlerDeletion { wiki_id, title, vector_clock } => {
+                    SyncToWiki::ApplyTiddlerDeletion { wiki_id, title, from_device_id, vector_clock } => {
                         let payload = serde_json::json!({
                             "type": "apply-deletion",
                             "wiki_id": wiki_id,
                             "title": title,
+                            "from_device_id": from_device_id,
                         });
                         let sent = Self::emit_to_wiki(&wiki_id, "lan-sync-apply-deletion", payload);
                         // Accept vector clock only after confirmed delivery
@@ -2914,13 +3197,29 @@ impl SyncManager {
                             }
                         }
                     }
-                    SyncToWiki::SaveConflict { wiki_id, title, .. } => {
+                    SyncToWiki::SaveConflict { wiki_id, title, from_device_id, remote_tiddler_json } => {
+                        let now = std::time::SystemTime::now()
+                            .duration_since(std::time::UNIX_EPOCH)
+                            .unwrap_or_default()
+                            .as_millis() as u64;
+                        let conflict = conflict_store.record(&wiki_id, &title, &from_device_id, remote_tiddler_json, now);
                         let payload = serde_json::json!({
                             "type": "conflict",
                             "wiki_id": wiki_id,
                             "title": title,
+                            "conflict_id": conflict.id,
+                            "from_device_id": conflict.from_device_id,
+                            "remote_tiddler_json": conflict.remote_tiddler_json,
+                            "remote_deleted": conflict.remote_tiddler_json.is_none(),
                         });
                         Self::emit_to_wiki(&wiki_id, "lan-sync-conflict", payload);
+                        if let Some(app) = crate::get_global_app_handle() {
+                            let _ = app.emit("sync-conflict", &conflict);
+                            crate::webhooks::dispatch(&app, crate::webhooks::WebhookEvent::SyncConflict, serde_json::json!({
+                                "wikiId": wiki_id,
+                                "title": title,
+                            }));
+                        }
                     }
                 }
             }
@@ -3108,6 +3407,35 @@ impl SyncManager {
         }
     }
 
+    /// Stop sharing a room with a LAN peer (it left the room or was revoked
+    /// in it); the peer is disconnected when no other room is left
+    async fn remove_lan_peer_from_room(&self, device_id: &str, room_code: &str) {
+        if let Some(ref server) = *self.server.read().await {
+            let fully_disconnected = server.remove_room_from_peer(device_id, room_code).await;
+            if fully_disconnected {
+                if let Ok(mut set) = self.connected_peer_ids.write() {
+                    set.remove(device_id);
+                }
+                self.remote_wikis.write().await.remove(device_id);
+                if let Some(app) = GLOBAL_APP_HANDLE.get() {
+                    let _ = app.emit("lan-sync-peer-disconnected", serde_json::json!({
+                        "device_id": device_id,
+                    }));
+                    let _ = app.emit("lan-sync-peers-updated", serde_json::json!({}));
+                    let available = self.get_available_remote_wikis().await;
+                    let _ = app.emit("lan-sync-remote-wikis-updated", &available);
+                }
+            } else {
+                // Peer still has other rooms — just refresh status
+                if let Some(app) = GLOBAL_APP_HANDLE.get() {
+                    let _ = app.emit("lan-sync-peers-updated", serde_json::json!({}));
+                }
+            }
+            #[cfg(not(target_os = "android"))]
+            self.push_peer_updates_to_ipc().await;
+        }
+    }
+
     /// Handle a server event
     async fn handle_server_event(&self, event: ServerEvent) {
         match event {
@@ -3204,6 +3532,16 @@ impl SyncManager {
                 }
             }
             ServerEvent::PeerDisconnected { device_id } => {
+                // Still connected over the other transport (e.g. a fallback
+                // room left the relay because the peer is back on the LAN)
+                let still_connected = match *self.server.read().await {
+                    Some(ref server) => server.connected_peers().await.iter().any(|(id, _, _)| *id == device_id),
+                    None => false,
+                };
+                if still_connected {
+                    eprintln!("[LAN Sync] Peer {} left one transport, still connected over the other", device_id);
+                    return;
+                }
                 eprintln!("[LAN Sync] Peer disconnected: {}", device_id);
                 // Remove from connected set
                 if let Ok(mut set) = self.connected_peer_ids.write() {
@@ -3328,6 +3666,8 @@ impl SyncManager {
                     SyncMessage::AttachmentChunk { filename, chunk_index, .. } => format!("AttachmentChunk({} #{})", filename, chunk_index),
                     SyncMessage::AttachmentDeleted { filename, .. } => format!("AttachmentDeleted({})", filename),
                     SyncMessage::RequestFingerprints { ref wiki_id } => format!("RequestFingerprints({})", wiki_id),
+                    SyncMessage::RequestPreviewFingerprints { ref wiki_id } => format!("RequestPreviewFingerprints({})", wiki_id),
+                    SyncMessage::PreviewFingerprints { fingerprints, .. } => format!("PreviewFingerprints({})", fingerprints.len()),
                     SyncMessage::WikiInfoChanged { ref wiki_id, ref content_hash, .. } => format!("WikiInfoChanged({}, hash={})", wiki_id, &content_hash[..8.min(content_hash.len())]),
                     SyncMessage::WikiInfoRequest { ref wiki_id } => format!("WikiInfoRequest({})", wiki_id),
                     SyncMessage::PluginManifest { ref plugin_name, .. } => format!("PluginManifest({})", plugin_name),
@@ -3350,30 +3690,7 @@ impl SyncManager {
                         "[LAN Sync] Peer {} leaving room {}",
                         from_device_id, room_code
                     );
-                    if let Some(ref server) = *self.server.read().await {
-                        let fully_disconnected = server.remove_room_from_peer(&from_device_id, room_code).await;
-                        if fully_disconnected {
-                            if let Ok(mut set) = self.connected_peer_ids.write() {
-                                set.remove(&from_device_id);
-                            }
-                            self.remote_wikis.write().await.remove(&from_device_id);
-                            if let Some(app) = GLOBAL_APP_HANDLE.get() {
-                                let _ = app.emit("lan-sync-peer-disconnected", serde_json::json!({
-                                    "device_id": &from_device_id,
-                                }));
-                                let _ = app.emit("lan-sync-peers-updated", serde_json::json!({}));
-                                let available = self.get_available_remote_wikis().await;
-                                let _ = app.emit("lan-sync-remote-wikis-updated", &available);
-                            }
-                        } else {
-                            // Peer still has other rooms — just refresh status
-                            if let Some(app) = GLOBAL_APP_HANDLE.get() {
-                                let _ = app.emit("lan-sync-peers-updated", serde_json::json!({}));
-                            }
-                        }
-                        #[cfg(not(target_os = "android"))]
-                        self.push_peer_updates_to_ipc().await;
-                    }
+                    self.remove_lan_peer_from_room(&from_device_id, room_code).await;
                     return;
                 }
 
@@ -3831,6 +4148,14 @@ impl SyncManager {
                             }),
                         );
                     }
+                    SyncMessage::RequestPreviewFingerprints { ref wiki_id } => {
+                        // A peer previews syncing its copy of a wiki with ours
+                        // (preview.rs): answer with our fingerprints only
+                        self.answer_preview_request(&from_device_id, wiki_id).await;
+                    }
+                    SyncMessage::PreviewFingerprints { ref wiki_id, ref fingerprints, available } => {
+                        preview::reply_received(wiki_id, &from_device_id, available.then(|| fingerprints.clone()));
+                    }
                     SyncMessage::UserNameAnnounce { ref user_name } => {
                         eprintln!(
                             "[LAN Sync] Peer {} announced username: {}",
@@ -3852,6 +4177,9 @@ impl SyncManager {
                         #[cfg(not(target_os = "android"))]
                         self.push_peer_updates_to_ipc().await;
                     }
+                    SyncMessage::ClipboardShare { ref device_name, ref text } => {
+                        Self::handle_clipboard_share(&from_device_id, device_name, text);
+                    }
                     SyncMessage::RequestWikiFile { ref wiki_id, ref have_files } => {
                         self.handle_request_wiki_file(&from_device_id, wiki_id, have_files).await;
                     }
@@ -5379,6 +5707,15 @@ impl SyncManager {
                                 sync_peers: vec![],
                                 relay_room,
                                 sync_mode: None,
+                                custom_icon: None,
+                                accent_color: None,
+                                emoji: None,
+                                scheduled_backup: None,
+                                confirm_remote_deletions: false,
+                                sync_schedule: None,
+                                hotkey: None,
+                                sync_priority: None,
+                                missing: false,
                             };
                             if let Err(e) = crate::wiki_storage::add_to_recent_files(app, entry) {
                                 eprintln!("[LAN Sync] Failed to early-register wiki: {}", e);
@@ -5614,6 +5951,15 @@ impl SyncManager {
                 sync_peers: vec![],
                 relay_room,
                 sync_mode: None,
+                custom_icon: None,
+                accent_color: None,
+                emoji: None,
+                scheduled_backup: None,
+                confirm_remote_deletions: false,
+                sync_schedule: None,
+                hotkey: None,
+                sync_priority: None,
+                missing: false,
             };
             if let Err(e) = crate::wiki_storage::add_to_recent_files(app, entry) {
                 eprintln!("[LAN Sync] Failed to add received wiki to recent files: {}", e);
@@ -6909,6 +7255,10 @@ pub fn set_ipc_client_for_sync(client: Arc<std::sync::Mutex<Option<crate::ipc::I
 
 #[tauri::command]
 pub async fn lan_sync_start(_app: tauri::AppHandle) -> Result<(), String> {
+    #[cfg(not(target_os = "android"))]
+    if crate::instance::is_secondary() {
+        return Err("LAN sync is off while another TiddlyDesktop instance is running".to_string());
+    }
     let mgr = get_sync_manager().ok_or("Sync not initialized")?;
     mgr.start().await?;
 
@@ -6985,6 +7335,52 @@ pub async fn lan_sync_announce_username(user_name: String) -> Result<(), String>
     Ok(())
 }
 
+/// Send text (e.g. the selection in a wiki) to the shared clipboard of a connected device
+#[tauri::command]
+pub async fn lan_sync_share_clipboard(app: tauri::AppHandle, device_id: String, text: String) -> Result<(), String> {
+    if !crate::wiki_storage::load_app_settings(&app)?.shared_clipboard {
+        return Err("Shared clipboard is disabled".to_string());
+    }
+    // Main process: send directly
+    if let Some(mgr) = get_sync_manager() {
+        return mgr.share_clipboard(&device_id, text).await;
+    }
+    // Wiki process: the main process sends it
+    #[cfg(not(target_os = "android"))]
+    {
+        if let Some(ipc) = IPC_CLIENT_FOR_SYNC.get() {
+            let mut guard = ipc.lock().unwrap();
+            if let Some(ref mut client) = *guard {
+                return client.send_lan_sync_share_clipboard(&device_id, &text)
+                    .map_err(|e| format!("IPC send failed: {}", e));
+            }
+        }
+    }
+    Err("Sync not initialized".to_string())
+}
+
+/// Connected devices the shared clipboard can send to
+#[tauri::command]
+pub async fn lan_sync_get_clipboard_peers() -> Result<Vec<PeerInfo>, String> {
+    let mgr = get_sync_manager().ok_or("Sync not initialized")?;
+    Ok(mgr.clipboard_peers().await)
+}
+
+/// Whether the shared clipboard is enabled
+#[tauri::command]
+pub fn lan_sync_get_shared_clipboard(app: tauri::AppHandle) -> bool {
+    crate::wiki_storage::load_app_settings(&app).map(|s| s.shared_clipboard).unwrap_or(false)
+}
+
+/// Enable or disable sending and receiving text through the shared clipboard
+#[tauri::command]
+pub fn lan_sync_set_shared_clipboard(app: tauri::AppHandle, enabled: bool) -> Result<bool, String> {
+    let mut settings = crate::wiki_storage::load_app_settings(&app)?;
+    settings.shared_clipboard = enabled;
+    crate::wiki_storage::save_app_settings(&app, &settings)?;
+    Ok(enabled)
+}
+
 #[tauri::command]
 pub async fn lan_sync_get_status() -> Result<SyncStatus, String> {
     let mgr = get_sync_manager().ok_or("Sync not initialized")?;
@@ -7050,6 +7446,98 @@ pub fn lan_sync_tiddler_deleted(wiki_id: String, title: String) -> Result<(), St
     Ok(())
 }
 
+/// Open sync conflicts with both versions, of one wiki or all (main process;
+/// wiki windows get each conflict with the `conflict` sync event)
+#[tauri::command]
+pub fn lan_sync_get_conflicts(wiki_id: Option<String>) -> Result<Vec<SyncConflict>, String> {
+    let mgr = get_sync_manager().ok_or("Sync is not running")?;
+    Ok(mgr.get_conflicts(wiki_id.as_deref()))
+}
+
+/// Called by JS with its own version of a conflicting tiddler, once it saved
+/// the conflict tiddler
+#[tauri::command]
+pub fn lan_sync_conflict_local_version(
+    wiki_id: String,
+    conflict_id: String,
+    tiddler_json: String,
+) -> Result<(), String> {
+    if let Some(mgr) = get_sync_manager() {
+        return mgr.set_conflict_local_version(&wiki_id, &conflict_id, tiddler_json);
+    }
+    #[cfg(not(target_os = "android"))]
+    {
+        if let Some(ipc) = IPC_CLIENT_FOR_SYNC.get() {
+            let mut guard = ipc.lock().unwrap();
+            if let Some(ref mut client) = *guard {
+                return client
+                    .send_lan_sync_conflict_local(&wiki_id, &conflict_id, &tiddler_json)
+                    .map_err(|e| e.to_string());
+            }
+        }
+    }
+    Err("Sync is not running".to_string())
+}
+
+/// Resolve a sync conflict with "local", "remote", "merged" (with `text`) or
+/// "dismiss"
+#[tauri::command]
+pub fn lan_sync_resolve_conflict(
+    wiki_id: String,
+    conflict_id: String,
+    resolution: String,
+    text: Option<String>,
+) -> Result<(), String> {
+    if let Some(mgr) = get_sync_manager() {
+        return mgr.resolve_conflict(&wiki_id, &conflict_id, &resolution, text.as_deref());
+    }
+    #[cfg(not(target_os = "android"))]
+    {
+        // Checked here too, as the main process can't answer over IPC
+        Resolution::parse(&resolution)?;
+        if resolution == "merged" && text.is_none() {
+            return Err("No merged text given".to_string());
+        }
+        if let Some(ipc) = IPC_CLIENT_FOR_SYNC.get() {
+            let mut guard = ipc.lock().unwrap();
+            if let Some(ref mut client) = *guard {
+                return client
+                    .send_lan_sync_resolve_conflict(&wiki_id, &conflict_id, &resolution, text.as_deref())
+                    .map_err(|e| e.to_string());
+            }
+        }
+    }
+    Err("Sync is not running".to_string())
+}
+
+/// Sync a wiki with its peers now (main process; the wiki must be open)
+#[tauri::command]
+pub fn sync_now(app: tauri::AppHandle, path: String) -> Result<(), String> {
+    let mgr = get_sync_manager().ok_or("Sync is not running")?;
+    let sync_id = crate::wiki_storage::load_recent_files_from_disk(&app)
+        .into_iter()
+        .find(|entry| crate::utils::paths_equal(&entry.path, &path) && entry.sync_enabled)
+        .and_then(|entry| entry.sync_id)
+        .ok_or("Sync is not enabled for this wiki")?;
+    mgr.sync_now(&sync_id)
+}
+
+/// Restore a tiddler a peer deleted, from the copy kept with its tombstone
+/// (main process; the wiki must be open)
+#[tauri::command]
+pub fn lan_sync_restore_deletion(wiki_id: String, title: String) -> Result<(), String> {
+    let mgr = get_sync_manager().ok_or("Sync is not running")?;
+    mgr.restore_deletion(&wiki_id, &title)
+}
+
+/// Apply (`apply`) or reject a deletion from a peer held for confirmation
+/// (main process; the wiki must be open)
+#[tauri::command]
+pub fn lan_sync_confirm_deletion(wiki_id: String, title: String, apply: bool) -> Result<(), String> {
+    let mgr = get_sync_manager().ok_or("Sync is not running")?;
+    mgr.confirm_deletion(&wiki_id, &title, apply)
+}
+
 /// Called by JS when a sync-enabled wiki window opens. Triggers catch-up sync
 /// with all connected peers that have this wiki, so changes made while the
 /// wiki was closed (or while the app was restarted) are applied.
@@ -7261,7 +7749,8 @@ pub fn lan_sync_poll_ipc() -> Vec<String> {
     Vec::new()
 }
 
-/// Load persisted deletion tombstones for a wiki (by sync_id).
+/// Load persisted deletion tombstones for a wiki (by sync_id), without the
+/// ones past the retention window.
 /// Returns JSON string (empty object `{}` if none stored).
 #[tauri::command]
 pub async fn lan_sync_load_tombstones(
@@ -7269,13 +7758,17 @@ pub async fn lan_sync_load_tombstones(
     wiki_id: String,
 ) -> Result<String, String> {
     let data_dir = crate::get_data_dir(&app)?;
-    let tombstone_dir = data_dir.join("lan_sync_tombstones");
-    let safe_name = wiki_id.replace(|c: char| !c.is_alphanumeric() && c != '-' && c != '_', "_");
-    let file_path = tombstone_dir.join(format!("{}.json", safe_name));
-    match tokio::fs::read_to_string(&file_path).await {
-        Ok(content) => Ok(content),
-        Err(_) => Ok("{}".to_string()),
-    }
+    let mut stored = tombstones::load(&data_dir, &wiki_id);
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64;
+    let pruned = tombstones::prune(&mut stored, now, tombstones::retention_days());
+    let json = serde_json::to_string(&stored).map_err(|e| e.to_string())?;
+    if pruned {
+        tokio::fs::write(tombstones::file_path(&data_dir, &wiki_id), &json).await.map_err(|e| e.to_string())?;
+    }
+    Ok(json)
 }
 
 /// Save deletion tombstones for a wiki (by sync_id).
@@ -7286,10 +7779,10 @@ pub async fn lan_sync_save_tombstones(
     tombstones_json: String,
 ) -> Result<(), String> {
     let data_dir = crate::get_data_dir(&app)?;
-    let tombstone_dir = data_dir.join("lan_sync_tombstones");
-    tokio::fs::create_dir_all(&tombstone_dir).await.map_err(|e| e.to_string())?;
-    let safe_name = wiki_id.replace(|c: char| !c.is_alphanumeric() && c != '-' && c != '_', "_");
-    let file_path = tombstone_dir.join(format!("{}.json", safe_name));
+    let file_path = tombstones::file_path(&data_dir, &wiki_id);
+    if let Some(dir) = file_path.parent() {
+        tokio::fs::create_dir_all(dir).await.map_err(|e| e.to_string())?;
+    }
     tokio::fs::write(&file_path, tombstones_json).await.map_err(|e| e.to_string())?;
     Ok(())
 }
@@ -7579,7 +8072,11 @@ pub async fn relay_sync_connect_room(room_code: String) -> Result<(), String> {
     if let Some(relay) = &mgr.relay_manager {
         // Try relay connection — log but don't fail if relay is unavailable
         // (LAN-only sync should still work)
-        if let Err(e) = relay.connect_room(&room_code).await {
+        // A fallback room connects to the relay once its devices can't be
+        // found on the LAN (`transport`)
+        if relay.is_relay_fallback(&room_code).await {
+            eprintln!("[LAN Sync] Room {} uses the relay only as a fallback", room_code);
+        } else if let Err(e) = relay.connect_room(&room_code).await {
             eprintln!("[LAN Sync] Relay connect failed (LAN still works): {}", e);
         } else {
             eprintln!("[LAN Sync] relay.connect_room spawned task for {}", room_code);
@@ -7719,6 +8216,29 @@ pub async fn relay_sync_set_room_auto_connect(
     }
 }
 
+/// Use the relay for a room only while none of its devices is reachable on
+/// the LAN (or always, like before)
+#[tauri::command]
+pub async fn relay_sync_set_room_relay_fallback(
+    room_code: String,
+    enabled: bool,
+) -> Result<(), String> {
+    let mgr = get_sync_manager().ok_or("Sync not initialized")?;
+    let relay = mgr.relay_manager.as_ref().ok_or("Relay sync not available")?;
+    relay.set_room_relay_fallback(&room_code, enabled).await;
+    // Turned off: a connected room goes back to using the relay right away
+    // (turned on, the next check leaves the relay if the LAN has the room)
+    if !enabled {
+        let connected = relay.get_rooms().await.into_iter().any(|r| r.room_code == room_code && r.connected);
+        if connected {
+            if let Err(e) = relay.connect_room(&room_code).await {
+                eprintln!("[LAN Sync] Relay connect failed (LAN still works): {}", e);
+            }
+        }
+    }
+    Ok(())
+}
+
 #[tauri::command]
 pub async fn relay_sync_set_room_password(
     room_code: String,
@@ -7748,6 +8268,132 @@ pub async fn relay_sync_set_room_name(
     }
 }
 
+/// A device seen in a relay room
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct RoomDirectoryMember {
+    pub device_id: String,
+    pub device_name: String,
+    /// Connected now, over the relay or the LAN
+    pub online: bool,
+    pub revoked: bool,
+    /// Epoch ms
+    pub first_seen: u64,
+    pub last_seen: u64,
+    pub last_sync: Option<u64>,
+}
+
+/// A relay room with the devices seen in it
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct RoomDirectoryEntry {
+    pub name: String,
+    pub room_code: String,
+    pub connected: bool,
+    /// Online devices first, then the most recently seen
+    pub members: Vec<RoomDirectoryMember>,
+}
+
+/// The rooms with every device seen in them, when it was last seen and when
+/// it last synced
+#[tauri::command]
+pub async fn relay_sync_get_room_directory() -> Result<Vec<RoomDirectoryEntry>, String> {
+    let mgr = get_sync_manager().ok_or("Sync not initialized")?;
+    let relay = mgr.relay_manager.as_ref().ok_or("Relay sync not available")?;
+    let local_device_id = mgr.pairing_manager.device_id().to_string();
+    let mut entries = Vec::new();
+    for room in relay.get_rooms().await {
+        let mut online: Vec<(String, String)> = room
+            .connected_peers
+            .iter()
+            .map(|p| (p.device_id.clone(), p.device_name.clone()))
+            .collect();
+        if let Some(ref server) = *mgr.server.read().await {
+            // LAN peers: their real names, and their last sync from the
+            // transfer counters (relay syncs are recorded as they arrive)
+            for (id, name) in server.lan_peers_for_room(&room.room_code).await {
+                if let Some(at) = crate::transfer_stats::peer_last_sync(&id) {
+                    directory::device_synced(&room.room_code, &id, at);
+                }
+                online.push((id, name));
+            }
+        }
+        online.retain(|(id, _)| *id != local_device_id);
+        for (id, name) in &online {
+            directory::device_seen(&room.room_code, id, name);
+        }
+
+        let record = directory::room(&room.room_code);
+        let mut members: Vec<RoomDirectoryMember> = record
+            .devices
+            .iter()
+            .map(|(id, device)| RoomDirectoryMember {
+                device_id: id.clone(),
+                device_name: device.name.clone(),
+                online: online.iter().any(|(online_id, _)| online_id == id),
+                revoked: record.revoked.contains(id),
+                first_seen: device.first_seen,
+                last_seen: device.last_seen,
+                last_sync: device.last_sync,
+            })
+            .collect();
+        members.sort_by(|a, b| b.online.cmp(&a.online).then(b.last_seen.cmp(&a.last_seen)));
+        entries.push(RoomDirectoryEntry {
+            name: room.name,
+            room_code: room.room_code,
+            connected: room.connected,
+            members,
+        });
+    }
+    Ok(entries)
+}
+
+/// Revoke a device in a room: disconnect it and ignore it from now on. Since
+/// it still knows the room password, `rotate_password` replaces the password
+/// (returned, to share with the remaining devices) so it can't rejoin.
+#[tauri::command]
+pub async fn relay_sync_revoke_device(
+    room_code: String,
+    device_id: String,
+    rotate_password: bool,
+) -> Result<Option<String>, String> {
+    let mgr = get_sync_manager().ok_or("Sync not initialized")?;
+    let relay = mgr.relay_manager.as_ref().ok_or("Relay sync not available")?;
+    if relay.get_room_credentials(&room_code).await.is_none() {
+        return Err(format!("Room '{}' not found", room_code));
+    }
+    eprintln!("[LAN Sync] Revoking device {} in room {}", device_id, room_code);
+    directory::set_revoked(&room_code, &device_id, true);
+    relay.drop_member(&room_code, &device_id).await;
+    mgr.remove_lan_peer_from_room(&device_id, &room_code).await;
+    if let Some(app) = GLOBAL_APP_HANDLE.get() {
+        let _ = app.emit("lan-sync-peers-updated", serde_json::json!({}));
+    }
+    if !rotate_password {
+        return Ok(None);
+    }
+
+    let was_connected = relay.get_connected_room_codes().await.contains(&room_code);
+    let password = crate::relay_sync::generate_room_password();
+    relay.set_room_password(&room_code, password.clone()).await?;
+    mgr.update_room_keys().await;
+    if was_connected {
+        if let Err(e) = relay.connect_room(&room_code).await {
+            eprintln!("[LAN Sync] Relay reconnect after password change failed: {}", e);
+        }
+    }
+    Ok(Some(password))
+}
+
+/// Take a revocation back (with a rotated password the device also needs
+/// the new one)
+#[tauri::command]
+pub async fn relay_sync_unrevoke_device(room_code: String, device_id: String) -> Result<(), String> {
+    directory::set_revoked(&room_code, &device_id, false);
+    if let Some(app) = GLOBAL_APP_HANDLE.get() {
+        let _ = app.emit("lan-sync-peers-updated", serde_json::json!({}));
+    }
+    Ok(())
+}
+
 #[tauri::command]
 pub async fn relay_sync_get_room_credentials(
     room_code: String,
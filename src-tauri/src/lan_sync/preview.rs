@@ -0,0 +1,308 @@
+//! Sync preview — what the first sync between two copies of a wiki would do.
+//!
+//! Before a local wiki is linked to a peer's wiki (`lan_sync_link_wiki`),
+//! `preview_sync` compares the fingerprints (title + modified) of both copies
+//! the way the wiki's fingerprint sync does, without syncing anything:
+//! - tiddlers only here are pushed, tiddlers only on the peer pulled
+//! - tiddlers on both sides with different modified times are conflicts:
+//!   the newer version replaces the other (plugins compare by version)
+//! - deletion tombstones of either side delete older copies on the other
+//!
+//! Our fingerprints come from the fingerprint cache when the wiki already
+//! syncs, else from the wiki file. The peer answers a
+//! `RequestPreviewFingerprints` with its fingerprints (`PreviewFingerprints`).
+
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+
+use serde::Serialize;
+use tokio::sync::oneshot;
+
+use super::conflict::ConflictManager;
+use super::protocol::{SyncMessage, TiddlerFingerprint};
+use tiddlydesktop_core::{tiddler_store, utils, wiki_folder};
+
+/// How long to wait for the peer's fingerprints
+const REPLY_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// A tiddler both copies have, in different versions
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PreviewConflict {
+    pub title: String,
+    pub local_modified: String,
+    pub remote_modified: String,
+    /// The version the first sync keeps ("local" or "remote")
+    pub keeps: &'static str,
+}
+
+/// What the first sync of a wiki with a peer would do
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct SyncPreview {
+    /// Tiddlers only here, sent to the peer
+    pub push: Vec<String>,
+    /// Tiddlers only on the peer, added here
+    pub pull: Vec<String>,
+    pub conflicts: Vec<PreviewConflict>,
+    /// Tiddlers here the peer deleted later
+    pub delete_local: Vec<String>,
+    /// Tiddlers on the peer deleted here later
+    pub delete_remote: Vec<String>,
+    /// Tiddlers that are the same on both sides
+    pub unchanged: usize,
+    pub local_count: usize,
+    pub remote_count: usize,
+}
+
+/// Compare plugin versions like the wiki does (dotted numbers)
+fn compare_versions(a: &str, b: &str) -> Ordering {
+    let parts = |v: &str| -> Vec<u64> {
+        v.split('.')
+            .map(|p| {
+                let digits: String = p.chars().take_while(|c| c.is_ascii_digit()).collect();
+                digits.parse().unwrap_or(0)
+            })
+            .collect()
+    };
+    match (a.is_empty(), b.is_empty()) {
+        (true, true) => return Ordering::Equal,
+        (true, false) => return Ordering::Less,
+        (false, true) => return Ordering::Greater,
+        _ => {}
+    }
+    let (pa, pb) = (parts(a), parts(b));
+    for i in 0..pa.len().max(pb.len()) {
+        let ordering = pa.get(i).unwrap_or(&0).cmp(pb.get(i).unwrap_or(&0));
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    Ordering::Equal
+}
+
+/// Which copy is newer: by version for plugins, else by modified time
+fn newer(local: &TiddlerFingerprint, remote: &TiddlerFingerprint) -> Ordering {
+    match (&local.version, &remote.version) {
+        (Some(a), Some(b)) => compare_versions(a, b),
+        _ => local.modified.cmp(&remote.modified),
+    }
+}
+
+/// Compare the fingerprints of both copies
+pub fn compare(local: &[TiddlerFingerprint], remote: &[TiddlerFingerprint]) -> SyncPreview {
+    fn split(fps: &[TiddlerFingerprint]) -> (BTreeMap<&str, &TiddlerFingerprint>, BTreeMap<&str, &str>) {
+        let mut tiddlers = BTreeMap::new();
+        let mut tombstones = BTreeMap::new();
+        for fp in fps.iter().filter(|fp| ConflictManager::should_sync_tiddler(&fp.title)) {
+            if fp.deleted == Some(true) {
+                tombstones.insert(fp.title.as_str(), fp.modified.as_str());
+            } else {
+                tiddlers.insert(fp.title.as_str(), fp);
+            }
+        }
+        (tiddlers, tombstones)
+    }
+    let (local_tiddlers, local_tombstones) = split(local);
+    let (remote_tiddlers, remote_tombstones) = split(remote);
+
+    let mut preview = SyncPreview {
+        local_count: local_tiddlers.len(),
+        remote_count: remote_tiddlers.len(),
+        ..Default::default()
+    };
+    for (title, fp) in &local_tiddlers {
+        let Some(remote_fp) = remote_tiddlers.get(title) else {
+            match remote_tombstones.get(title) {
+                // Deleted on the peer after our last change
+                Some(deleted) if fp.modified.as_str() <= *deleted => preview.delete_local.push(title.to_string()),
+                _ => preview.push.push(title.to_string()),
+            }
+            continue;
+        };
+        let keeps = match newer(fp, remote_fp) {
+            Ordering::Equal => {
+                preview.unchanged += 1;
+                continue;
+            }
+            Ordering::Greater => "local",
+            Ordering::Less => "remote",
+        };
+        preview.conflicts.push(PreviewConflict {
+            title: title.to_string(),
+            local_modified: fp.modified.clone(),
+            remote_modified: remote_fp.modified.clone(),
+            keeps,
+        });
+    }
+    for (title, fp) in &remote_tiddlers {
+        if local_tiddlers.contains_key(title) {
+            continue;
+        }
+        match local_tombstones.get(title) {
+            Some(deleted) if fp.modified.as_str() <= *deleted => preview.delete_remote.push(title.to_string()),
+            _ => preview.pull.push(title.to_string()),
+        }
+    }
+    preview
+}
+
+fn fingerprint(tiddler: &BTreeMap<String, String>) -> Option<TiddlerFingerprint> {
+    Some(TiddlerFingerprint {
+        title: tiddler.get("title")?.clone(),
+        modified: tiddler.get("modified").cloned().unwrap_or_default(),
+        deleted: None,
+        version: tiddler.get("plugin-type").and(tiddler.get("version")).cloned(),
+    })
+}
+
+/// Fingerprints of the tiddlers in a wiki file or folder
+pub fn file_fingerprints(path: &Path) -> Result<Vec<TiddlerFingerprint>, String> {
+    let mut by_title: BTreeMap<String, TiddlerFingerprint> = BTreeMap::new();
+    if path.is_dir() {
+        for file in wiki_folder::load_tiddlers_from_path(&path.join("tiddlers")) {
+            for fp in file.tiddlers.iter().filter_map(fingerprint) {
+                by_title.insert(fp.title.clone(), fp);
+            }
+        }
+    } else {
+        // Later tiddlers override earlier ones, like in TiddlyWiki
+        for tiddler in tiddler_store::open(path)? {
+            let Ok(serde_json::Value::Object(fields)) = tiddler else { continue };
+            let fields: BTreeMap<String, String> = fields
+                .into_iter()
+                .filter_map(|(k, v)| v.as_str().map(|v| (k, v.to_string())))
+                .collect();
+            if let Some(fp) = fingerprint(&fields) {
+                by_title.insert(fp.title.clone(), fp);
+            }
+        }
+    }
+    Ok(by_title.into_values().collect())
+}
+
+type Reply = Option<Vec<TiddlerFingerprint>>;
+
+/// Preview requests waiting for a peer: (wiki_id, device_id) → reply channel
+static PENDING: LazyLock<Mutex<HashMap<(String, String), oneshot::Sender<Reply>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// A peer answered a preview request (None: it doesn't share the wiki with us)
+pub(super) fn reply_received(wiki_id: &str, device_id: &str, fingerprints: Reply) {
+    let sender = PENDING.lock().unwrap().remove(&(wiki_id.to_string(), device_id.to_string()));
+    match sender {
+        Some(sender) => {
+            let _ = sender.send(fingerprints);
+        }
+        None => eprintln!("[LAN Sync] Ignoring unrequested preview fingerprints from {}", device_id),
+    }
+}
+
+/// Compare a local wiki with a peer's copy of the wiki `sync_id` before
+/// linking them. Nothing is synced.
+#[tauri::command]
+pub async fn preview_sync(
+    app: tauri::AppHandle,
+    path: String,
+    sync_id: String,
+    device_id: String,
+) -> Result<SyncPreview, String> {
+    let mgr = super::get_sync_manager().ok_or("Sync is not running")?;
+
+    // A wiki that already syncs has more accurate fingerprints (with tombstones)
+    let already_syncs = crate::wiki_storage::load_recent_files_from_disk(&app).iter().any(|e| {
+        e.sync_enabled && e.sync_id.as_deref() == Some(sync_id.as_str()) && utils::paths_equal(&e.path, &path)
+    });
+    let local = match already_syncs.then(|| mgr.get_accurate_cached_fingerprints(&sync_id)).flatten() {
+        Some(fps) => fps,
+        None => {
+            let wiki_path = std::path::PathBuf::from(&path);
+            tokio::task::spawn_blocking(move || file_fingerprints(&wiki_path))
+                .await
+                .map_err(|e| e.to_string())??
+        }
+    };
+
+    let key = (sync_id.clone(), device_id.clone());
+    let (tx, rx) = oneshot::channel();
+    PENDING.lock().unwrap().insert(key.clone(), tx);
+    let request = SyncMessage::RequestPreviewFingerprints { wiki_id: sync_id };
+    if let Err(e) = mgr.send_to_peer_any(&device_id, &request).await {
+        PENDING.lock().unwrap().remove(&key);
+        return Err(e);
+    }
+    let reply = tokio::time::timeout(REPLY_TIMEOUT, rx).await;
+    PENDING.lock().unwrap().remove(&key);
+    let remote = match reply {
+        Ok(Ok(Some(fps))) => fps,
+        Ok(Ok(None)) => return Err("The peer doesn't share this wiki with this device".to_string()),
+        // Older versions don't know preview requests
+        _ => return Err("The peer didn't answer (it may need an update)".to_string()),
+    };
+    Ok(compare(&local, &remote))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fp(title: &str, modified: &str) -> TiddlerFingerprint {
+        TiddlerFingerprint { title: title.to_string(), modified: modified.to_string(), deleted: None, version: None }
+    }
+
+    fn tombstone(title: &str, modified: &str) -> TiddlerFingerprint {
+        TiddlerFingerprint { deleted: Some(true), ..fp(title, modified) }
+    }
+
+    #[test]
+    fn test_compare() {
+        let local = vec![
+            fp("Only here", "20260101000000000"),
+            fp("Same", "20260102000000000"),
+            fp("Edited here", "20260301000000000"),
+            fp("Edited there", "20260101000000000"),
+            fp("Deleted there", "20260101000000000"),
+            tombstone("Deleted here", "20260201000000000"),
+            fp("$:/StoryList", "20260101000000000"),
+        ];
+        let remote = vec![
+            fp("Only there", "20260101000000000"),
+            fp("Same", "20260102000000000"),
+            fp("Edited here", "20260201000000000"),
+            fp("Edited there", "20260401000000000"),
+            tombstone("Deleted there", "20260201000000000"),
+            fp("Deleted here", "20260101000000000"),
+        ];
+        let preview = compare(&local, &remote);
+        assert_eq!(preview.push, ["Only here"]);
+        assert_eq!(preview.pull, ["Only there"]);
+        assert_eq!(preview.delete_local, ["Deleted there"]);
+        assert_eq!(preview.delete_remote, ["Deleted here"]);
+        assert_eq!(preview.unchanged, 1);
+        let keeps: Vec<_> = preview.conflicts.iter().map(|c| (c.title.as_str(), c.keeps)).collect();
+        assert_eq!(keeps, [("Edited here", "local"), ("Edited there", "remote")]);
+        // $:/StoryList isn't synced
+        assert_eq!((preview.local_count, preview.remote_count), (5, 5));
+    }
+
+    #[test]
+    fn test_tiddler_newer_than_tombstone_is_kept() {
+        let local = vec![fp("Recreated", "20260301000000000")];
+        let remote = vec![tombstone("Recreated", "20260201000000000")];
+        assert_eq!(compare(&local, &remote).push, ["Recreated"]);
+    }
+
+    #[test]
+    fn test_plugins_compare_by_version() {
+        let plugin = |modified: &str, version: &str| TiddlerFingerprint {
+            version: Some(version.to_string()),
+            ..fp("$:/plugins/me/thing", modified)
+        };
+        // Newer modified time, older version: the peer's version wins
+        let preview = compare(&[plugin("20260301000000000", "1.2.0")], &[plugin("20260101000000000", "1.10.0")]);
+        assert_eq!(preview.conflicts[0].keeps, "remote");
+        let preview = compare(&[plugin("20260301000000000", "1.2")], &[plugin("20260101000000000", "1.2.0")]);
+        assert_eq!(preview.unchanged, 1);
+    }
+}
@@ -1,7 +1,7 @@
 //! WebSocket server for LAN sync.
 //!
-//! Listens on a port in the 45700-45710 range and accepts connections from
-//! paired devices. Each connection goes through pairing (if not already paired)
+//! Listens on a port in the 45700-45710 range, on IPv6 and IPv4 where the
+//! system allows both, and accepts connections from paired devices. Each connection goes through pairing (if not already paired)
 //! then enters encrypted sync mode.
 
 use std::collections::HashMap;
@@ -35,6 +35,13 @@ pub fn ws_config() -> WebSocketConfig {
     }
 }
 
+/// Listen on all IPv6 and IPv4 addresses, or on IPv4 only where IPv6 is off
+fn bind_dual_stack(port: u16) -> std::io::Result<TcpListener> {
+    let listener = crate::server_address::bind_dual_stack(port)?;
+    listener.set_nonblocking(true)?;
+    TcpListener::from_std(listener)
+}
+
 /// Get current epoch milliseconds for pong tracking
 pub fn epoch_ms() -> u64 {
     std::time::SystemTime::now()
@@ -121,7 +128,7 @@ impl SyncServer {
     ) -> Result<Self, String> {
         let mut port = LAN_SYNC_PORT_START;
         let listener = loop {
-            match TcpListener::bind(format!("0.0.0.0:{}", port)).await {
+            match bind_dual_stack(port) {
                 Ok(listener) => break listener,
                 Err(_) if port < LAN_SYNC_PORT_END => {
                     port += 1;
@@ -213,6 +220,7 @@ impl SyncServer {
             let mut peers = self.peers.write().await;
             if let Some(peer) = peers.get_mut(device_id) {
                 let encrypted = encrypt_message(&mut peer.cipher, msg)?;
+                crate::transfer_stats::message_sent(device_id, msg, encrypted.len());
                 let channel = if is_bulk { peer.bulk_tx.clone() } else { peer.tx.clone() };
                 drop(peers);
                 Some(channel.send(encrypted).await.map_err(|_| "Peer channel closed".to_string()))
@@ -252,6 +260,7 @@ impl SyncServer {
                 if let Some(peer) = peers.get_mut(device_id.as_str()) {
                     match encrypt_message(&mut peer.cipher, msg) {
                         Ok(encrypted) => {
+                            crate::transfer_stats::message_sent(device_id, msg, encrypted.len());
                             let channel = if is_bulk { peer.bulk_tx.clone() } else { peer.tx.clone() };
                             sends.push((device_id.clone(), channel, encrypted));
                         }
@@ -308,6 +317,7 @@ impl SyncServer {
                 .filter_map(|(device_id, peer)| {
                     match encrypt_message(&mut peer.cipher, msg) {
                         Ok(encrypted) => {
+                            crate::transfer_stats::message_sent(device_id, msg, encrypted.len());
                             let channel = if is_bulk { peer.bulk_tx.clone() } else { peer.tx.clone() };
                             Some((device_id.clone(), channel, encrypted))
                         }
@@ -413,6 +423,9 @@ impl SyncServer {
         let mut peers = self.peers.write().await;
         if let Some(peer) = peers.get_mut(device_id) {
             for code in new_codes {
+                if crate::relay_sync::directory::is_revoked(code, device_id) {
+                    continue;
+                }
                 if !peer.auth_room_codes.contains(code) {
                     peer.auth_room_codes.push(code.clone());
                 }
@@ -571,6 +584,16 @@ async fn handle_connection(
                 }
             };
 
+            if crate::relay_sync::directory::is_revoked(&matched_room_code, &device_id) {
+                let reject = RoomAuthMessage::RoomAuthReject {
+                    message: "Device revoked".to_string(),
+                };
+                let _ = ws_sender.send(Message::Text(
+                    serde_json::to_string(&reject).unwrap().into(),
+                )).await;
+                return Err(format!("Device {} is revoked in room {}", device_id, matched_room_code));
+            }
+
             // Decode client's SPAKE2 message A
             let msg_a_bytes = STANDARD.decode(&spake_msg)
                 .map_err(|e| format!("Invalid base64 in spake_msg: {}", e))?;
@@ -636,6 +659,7 @@ async fn handle_connection(
                 "[LAN Sync] SPAKE2 auth completed: {} ({}) for room {}",
                 device_name, device_id, matched_room_code
             );
+            crate::relay_sync::directory::device_seen(&matched_room_code, &device_id, &device_name);
 
             (device_id, device_name, matched_room_code, shared_secret)
         }
@@ -765,6 +789,7 @@ async fn handle_connection(
                 Ok(Message::Binary(data)) => {
                     match decrypt_message(&cipher, &data) {
                         Ok(sync_msg) => {
+                            crate::transfer_stats::message_received(&inbound_peer_id, &sync_msg, data.len());
                             let _ = event_tx.send(ServerEvent::SyncMessageReceived {
                                 from_device_id: inbound_peer_id.clone(),
                                 message: sync_msg,
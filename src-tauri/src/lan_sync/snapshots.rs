@@ -0,0 +1,240 @@
+//! Snapshots of synced wikis before sync sessions, and rolling back the last one.
+//!
+//! When changes from peers start arriving after a quiet spell (a sync session),
+//! the wiki's sync script records the syncable tiddlers before applying them.
+//! Snapshots are kept in `lan_sync_snapshots/<sync id>/`: every tiddler version
+//! once in `objects/` (named by the SHA-256 of its JSON), and `index.json` with
+//! the last `MAX_SNAPSHOTS` snapshots as title → (modified, object). A tiddler
+//! that didn't change since the previous snapshot isn't sent or stored again.
+//!
+//! `rollback_last_sync` brings the wiki back to its latest snapshot: tiddlers
+//! added since are deleted, changed and deleted ones restored. It is done by
+//! the wiki window (the rollback syncs to the peers like local changes), right
+//! away if the wiki is open, otherwise the next time it opens.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+const SNAPSHOTS_DIR: &str = "lan_sync_snapshots";
+
+/// Snapshots kept per wiki
+const MAX_SNAPSHOTS: usize = 5;
+
+/// Marker of a rollback waiting for the wiki window
+const ROLLBACK_MARKER: &str = "rollback-pending";
+
+/// A tiddler in a snapshot
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct SnapshotTiddler {
+    modified: String,
+    /// SHA-256 of the tiddler JSON (its file in `objects/`)
+    object: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Snapshot {
+    /// Epoch ms when it was taken
+    time: u64,
+    tiddlers: BTreeMap<String, SnapshotTiddler>,
+}
+
+/// The latest snapshot of a wiki, for its sync script
+#[derive(Debug, Serialize)]
+pub struct LoadedSnapshot {
+    pub time: u64,
+    /// Title → tiddler JSON
+    pub tiddlers: HashMap<String, String>,
+}
+
+fn wiki_dir(data_dir: &Path, wiki_id: &str) -> PathBuf {
+    let safe_name = wiki_id.replace(|c: char| !c.is_alphanumeric() && c != '-' && c != '_', "_");
+    data_dir.join(SNAPSHOTS_DIR).join(safe_name)
+}
+
+fn load_index(dir: &Path) -> Vec<Snapshot> {
+    std::fs::read_to_string(dir.join("index.json"))
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+fn hash(json: &str) -> String {
+    Sha256::digest(json.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// The titles whose JSON the snapshot needs: those not in `previous` with the
+/// same modified date (and those without one, which can change unnoticed)
+fn needed_titles(previous: Option<&Snapshot>, fingerprints: &BTreeMap<String, String>) -> Vec<String> {
+    fingerprints
+        .iter()
+        .filter(|(title, modified)| {
+            modified.is_empty()
+                || previous
+                    .and_then(|snapshot| snapshot.tiddlers.get(*title))
+                    .is_none_or(|known| known.modified != **modified)
+        })
+        .map(|(title, _)| title.clone())
+        .collect()
+}
+
+/// Objects no snapshot refers to anymore
+fn unreferenced(snapshots: &[Snapshot], objects: impl Iterator<Item = String>) -> Vec<String> {
+    let used: HashSet<&String> = snapshots.iter().flat_map(|s| s.tiddlers.values().map(|t| &t.object)).collect();
+    objects.filter(|object| !used.contains(object)).collect()
+}
+
+fn now_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// The titles a new snapshot needs the JSON of, given the wiki's syncable
+/// tiddlers as title → modified (wiki window, when a sync session starts)
+#[tauri::command]
+pub fn lan_sync_snapshot_needed(
+    app: tauri::AppHandle,
+    wiki_id: String,
+    fingerprints: BTreeMap<String, String>,
+) -> Result<Vec<String>, String> {
+    let dir = wiki_dir(&crate::get_data_dir(&app)?, &wiki_id);
+    Ok(needed_titles(load_index(&dir).last(), &fingerprints))
+}
+
+/// Record a snapshot: all syncable tiddlers as title → modified, with the JSON
+/// of those `lan_sync_snapshot_needed` asked for
+#[tauri::command]
+pub async fn lan_sync_save_snapshot(
+    app: tauri::AppHandle,
+    wiki_id: String,
+    fingerprints: BTreeMap<String, String>,
+    tiddlers: HashMap<String, String>,
+) -> Result<(), String> {
+    let dir = wiki_dir(&crate::get_data_dir(&app)?, &wiki_id);
+    tokio::task::spawn_blocking(move || {
+        let objects_dir = dir.join("objects");
+        std::fs::create_dir_all(&objects_dir).map_err(|e| format!("Failed to create snapshot folder: {}", e))?;
+        let mut index = load_index(&dir);
+        let mut snapshot = Snapshot { time: now_ms(), tiddlers: BTreeMap::new() };
+        for (title, modified) in fingerprints {
+            let object = match tiddlers.get(&title) {
+                Some(json) => {
+                    let object = hash(json);
+                    let path = objects_dir.join(&object);
+                    if !path.exists() {
+                        std::fs::write(&path, json).map_err(|e| format!("Failed to save snapshot: {}", e))?;
+                    }
+                    object
+                }
+                None => match index.last().and_then(|previous| previous.tiddlers.get(&title)) {
+                    Some(known) => known.object.clone(),
+                    None => continue,
+                },
+            };
+            snapshot.tiddlers.insert(title, SnapshotTiddler { modified, object });
+        }
+        index.push(snapshot);
+        if index.len() > MAX_SNAPSHOTS {
+            index.drain(..index.len() - MAX_SNAPSHOTS);
+        }
+        let json = serde_json::to_string(&index).map_err(|e| e.to_string())?;
+        std::fs::write(dir.join("index.json"), json).map_err(|e| format!("Failed to save snapshot: {}", e))?;
+
+        let stored = std::fs::read_dir(&objects_dir)
+            .map(|entries| entries.flatten().filter_map(|e| e.file_name().to_str().map(str::to_string)).collect::<Vec<_>>())
+            .unwrap_or_default();
+        for object in unreferenced(&index, stored.into_iter()) {
+            let _ = std::fs::remove_file(objects_dir.join(object));
+        }
+        Ok(())
+    })
+    .await
+    .map_err(|e| format!("Failed to spawn blocking task: {}", e))?
+}
+
+/// The latest snapshot of a wiki (None: no sync session was recorded yet)
+#[tauri::command]
+pub fn lan_sync_load_snapshot(app: tauri::AppHandle, wiki_id: String) -> Result<Option<LoadedSnapshot>, String> {
+    let dir = wiki_dir(&crate::get_data_dir(&app)?, &wiki_id);
+    let Some(snapshot) = load_index(&dir).pop() else {
+        return Ok(None);
+    };
+    let mut tiddlers = HashMap::new();
+    for (title, tiddler) in snapshot.tiddlers {
+        let json = std::fs::read_to_string(dir.join("objects").join(&tiddler.object))
+            .map_err(|e| format!("The snapshot is incomplete ({}): {}", title, e))?;
+        tiddlers.insert(title, json);
+    }
+    Ok(Some(LoadedSnapshot { time: snapshot.time, tiddlers }))
+}
+
+/// Whether a rollback is waiting for the wiki window, which then does it
+/// (the marker is removed, so it happens once)
+#[tauri::command]
+pub fn lan_sync_take_pending_rollback(app: tauri::AppHandle, wiki_id: String) -> Result<bool, String> {
+    let marker = wiki_dir(&crate::get_data_dir(&app)?, &wiki_id).join(ROLLBACK_MARKER);
+    Ok(std::fs::remove_file(marker).is_ok())
+}
+
+/// Undo the last sync session of a wiki: bring it back to the snapshot taken
+/// before it (main process). Returns whether the wiki was open and rolled back
+/// now; otherwise that happens the next time it opens.
+#[tauri::command]
+pub fn rollback_last_sync(app: tauri::AppHandle, path: String) -> Result<bool, String> {
+    let sync_id = crate::wiki_storage::load_recent_files_from_disk(&app)
+        .into_iter()
+        .find(|entry| crate::utils::paths_equal(&entry.path, &path) && entry.sync_enabled)
+        .and_then(|entry| entry.sync_id)
+        .ok_or("Sync is not enabled for this wiki")?;
+    let dir = wiki_dir(&crate::get_data_dir(&app)?, &sync_id);
+    if load_index(&dir).is_empty() {
+        return Err("No sync session of this wiki was recorded yet".to_string());
+    }
+    std::fs::write(dir.join(ROLLBACK_MARKER), "").map_err(|e| format!("Failed to request the rollback: {}", e))?;
+
+    let sent = super::SyncManager::emit_to_wiki(
+        &sync_id,
+        "lan-sync-rollback",
+        serde_json::json!({ "type": "rollback-sync", "wiki_id": sync_id }),
+    );
+    Ok(sent > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(tiddlers: &[(&str, &str, &str)]) -> Snapshot {
+        Snapshot {
+            time: 0,
+            tiddlers: tiddlers
+                .iter()
+                .map(|(title, modified, object)| {
+                    (title.to_string(), SnapshotTiddler { modified: modified.to_string(), object: object.to_string() })
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_needed_titles() {
+        let previous = snapshot(&[("A", "20260101", "a1"), ("B", "20260101", "b1"), ("C", "", "c1")]);
+        let fingerprints: BTreeMap<String, String> = [("A", "20260101"), ("B", "20260202"), ("C", ""), ("D", "20260101")]
+            .iter()
+            .map(|(t, m)| (t.to_string(), m.to_string()))
+            .collect();
+        assert_eq!(needed_titles(Some(&previous), &fingerprints), ["B", "C", "D"]);
+        assert_eq!(needed_titles(None, &fingerprints).len(), 4);
+    }
+
+    #[test]
+    fn test_unreferenced() {
+        let snapshots = [snapshot(&[("A", "1", "a1")]), snapshot(&[("A", "2", "a2"), ("B", "1", "b1")])];
+        let stored = ["a1", "a2", "b0", "b1"].iter().map(|s| s.to_string());
+        assert_eq!(unreferenced(&snapshots, stored), ["b0"]);
+    }
+}
@@ -0,0 +1,217 @@
+//! Deletion tombstones, and the deletions sync applied.
+//!
+//! A sync-enabled wiki keeps a tombstone for every deleted tiddler in
+//! `lan_sync_tombstones/<sync id>.json`, written by its sync script (title →
+//! `Tombstone`). Fingerprint exchanges carry them, so peers that were offline
+//! learn about the deletion too. Tombstones are kept for the retention window
+//! (`lan_sync_tombstone_days` in the app settings, 30 days by default); after
+//! that a peer that still has the tiddler brings it back.
+//!
+//! A deletion from a peer keeps a copy of the tiddler, so it can be restored.
+//! With `confirm_remote_deletions` set on the wiki, deletions from peers are
+//! held as pending until they are confirmed or rejected. Restoring and
+//! confirming go through the open wiki window, which owns its tombstones.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+const TOMBSTONES_DIR: &str = "lan_sync_tombstones";
+
+/// Retention window unless configured otherwise
+pub const DEFAULT_RETENTION_DAYS: u32 = 30;
+
+const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+/// A deleted tiddler, as the wiki's sync script records it
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Tombstone {
+    /// TiddlyWiki date of the deletion (compared with peers' tiddlers)
+    pub modified: String,
+    /// Epoch ms when it was recorded (0 in old records: never pruned)
+    #[serde(default)]
+    pub time: u64,
+    /// The tiddler exists again: re-created, restored or a pending deletion
+    /// was rejected
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub cleared: bool,
+    /// A deletion from a peer waiting for confirmation (the tiddler is still there)
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub pending: bool,
+    /// The peer the deletion came from (None: deleted here)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from_device_id: Option<String>,
+    /// The tiddler before a deletion from a peer
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tiddler_json: Option<String>,
+}
+
+/// A deletion for the settings and `lan_sync_recent_deletions`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DeletionRecord {
+    pub wiki_id: String,
+    pub title: String,
+    /// Epoch ms
+    pub deleted_at: u64,
+    pub from_device_id: Option<String>,
+    pub pending: bool,
+    /// The tiddler exists again
+    pub cleared: bool,
+    /// A copy of the tiddler was kept
+    pub restorable: bool,
+}
+
+/// Retention window in days, from the app settings
+pub fn retention_days() -> u32 {
+    crate::GLOBAL_APP_HANDLE
+        .get()
+        .and_then(|app| crate::wiki_storage::load_app_settings(app).ok())
+        .map(|settings| settings.lan_sync_tombstone_days)
+        .filter(|&days| days > 0)
+        .unwrap_or(DEFAULT_RETENTION_DAYS)
+}
+
+/// Tombstone file of a wiki
+pub fn file_path(data_dir: &Path, wiki_id: &str) -> PathBuf {
+    let safe_name = wiki_id.replace(|c: char| !c.is_alphanumeric() && c != '-' && c != '_', "_");
+    data_dir.join(TOMBSTONES_DIR).join(format!("{}.json", safe_name))
+}
+
+/// The tombstones of a wiki (none if the file is missing or unreadable)
+pub fn load(data_dir: &Path, wiki_id: &str) -> BTreeMap<String, Tombstone> {
+    std::fs::read_to_string(file_path(data_dir, wiki_id))
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+/// Drop tombstones older than the retention window. Returns whether any were dropped.
+pub fn prune(tombstones: &mut BTreeMap<String, Tombstone>, now_ms: u64, retention_days: u32) -> bool {
+    let max_age = retention_days as u64 * DAY_MS;
+    let before = tombstones.len();
+    tombstones.retain(|_, t| t.time == 0 || now_ms.saturating_sub(t.time) <= max_age);
+    tombstones.len() != before
+}
+
+/// The deletions of a wiki, newest first
+pub fn records(wiki_id: &str, tombstones: &BTreeMap<String, Tombstone>) -> Vec<DeletionRecord> {
+    let mut records: Vec<DeletionRecord> = tombstones
+        .iter()
+        .map(|(title, t)| DeletionRecord {
+            wiki_id: wiki_id.to_string(),
+            title: title.clone(),
+            deleted_at: t.time,
+            from_device_id: t.from_device_id.clone(),
+            pending: t.pending,
+            cleared: t.cleared,
+            restorable: t.tiddler_json.is_some() && !t.cleared && !t.pending,
+        })
+        .collect();
+    records.sort_by(|a, b| b.deleted_at.cmp(&a.deleted_at).then_with(|| a.title.cmp(&b.title)));
+    records
+}
+
+/// Recorded deletions, of one wiki or all, newest first
+#[tauri::command]
+pub fn lan_sync_recent_deletions(app: tauri::AppHandle, wiki_id: Option<String>) -> Result<Vec<DeletionRecord>, String> {
+    let data_dir = crate::get_data_dir(&app)?;
+    let wiki_ids: Vec<String> = match wiki_id {
+        Some(id) => vec![id],
+        None => std::fs::read_dir(data_dir.join(TOMBSTONES_DIR))
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .filter_map(|entry| {
+                        let path = entry.path();
+                        if path.extension()? != "json" {
+                            return None;
+                        }
+                        path.file_stem()?.to_str().map(str::to_string)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default(),
+    };
+    let mut all: Vec<DeletionRecord> =
+        wiki_ids.iter().flat_map(|id| records(id, &load(&data_dir, id))).collect();
+    all.sort_by(|a, b| b.deleted_at.cmp(&a.deleted_at));
+    Ok(all)
+}
+
+/// How a wiki's sync script handles deletions
+#[derive(Debug, Clone, Serialize)]
+pub struct DeletionPolicy {
+    pub retention_days: u32,
+    pub confirm_remote_deletions: bool,
+}
+
+/// The deletion policy of a wiki (by sync_id)
+#[tauri::command]
+pub fn lan_sync_get_deletion_policy(app: tauri::AppHandle, wiki_id: String) -> DeletionPolicy {
+    let confirm_remote_deletions = crate::wiki_storage::load_recent_files_from_disk(&app)
+        .iter()
+        .any(|entry| entry.sync_id.as_deref() == Some(wiki_id.as_str()) && entry.confirm_remote_deletions);
+    DeletionPolicy { retention_days: retention_days(), confirm_remote_deletions }
+}
+
+/// Set how many days deletion tombstones are kept (0 = the default)
+#[tauri::command]
+pub fn lan_sync_set_tombstone_days(app: tauri::AppHandle, days: u32) -> Result<(), String> {
+    let mut settings = crate::wiki_storage::load_app_settings(&app)?;
+    settings.lan_sync_tombstone_days = days;
+    crate::wiki_storage::save_app_settings(&app, &settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tombstone(time: u64) -> Tombstone {
+        Tombstone { modified: "20260101000000000".to_string(), time, ..Default::default() }
+    }
+
+    #[test]
+    fn test_prune() {
+        let now = 100 * DAY_MS;
+        let mut tombstones = BTreeMap::from([
+            ("Old".to_string(), tombstone(now - 31 * DAY_MS)),
+            ("Recent".to_string(), tombstone(now - 29 * DAY_MS)),
+            ("No time".to_string(), tombstone(0)),
+        ]);
+        assert!(prune(&mut tombstones, now, 30));
+        assert_eq!(tombstones.keys().collect::<Vec<_>>(), ["No time", "Recent"]);
+        assert!(!prune(&mut tombstones, now, 30));
+        assert!(prune(&mut tombstones, now, 7));
+        assert_eq!(tombstones.keys().collect::<Vec<_>>(), ["No time"]);
+    }
+
+    #[test]
+    fn test_records() {
+        // The format the sync script writes
+        let tombstones: BTreeMap<String, Tombstone> = serde_json::from_str(
+            r#"{
+                "Mine": {"modified": "20260101000000000", "time": 1000},
+                "Theirs": {"modified": "20260102000000000", "time": 3000, "from_device_id": "d1",
+                           "tiddler_json": "{\"title\":\"Theirs\"}"},
+                "Held": {"modified": "20260103000000000", "time": 2000, "pending": true, "from_device_id": "d1",
+                         "tiddler_json": "{\"title\":\"Held\"}"}
+            }"#,
+        )
+        .unwrap();
+        let records = records("w1", &tombstones);
+        assert_eq!(records.iter().map(|r| r.title.as_str()).collect::<Vec<_>>(), ["Theirs", "Held", "Mine"]);
+        assert!(records[0].restorable);
+        // Pending deletions haven't removed anything yet
+        assert!(records[1].pending && !records[1].restorable);
+        assert!(!records[2].restorable);
+
+        // Defaults stay out of the file
+        let json = serde_json::to_string(&tombstones["Mine"]).unwrap();
+        assert_eq!(json, r#"{"modified":"20260101000000000","time":1000}"#);
+    }
+}
@@ -0,0 +1,200 @@
+//! Which transport carries a room's sync, and the relay fallback.
+//!
+//! A relay room can use the relay only as a fallback (`relay_fallback`): its
+//! devices sync over the LAN, and the relay connection is opened while none of
+//! them has been reachable there for `LAN_GRACE` (client isolation on hotel
+//! Wi-Fi, another network), then closed again once one is back on the LAN.
+//! The main process checks the fallback rooms every `CHECK_INTERVAL`; like the
+//! auto-connect rooms, they wait on metered connections.
+//!
+//! The transport of every synced wiki is part of the sync status
+//! (`SyncStatus::wiki_transports`).
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use super::SyncManager;
+
+/// How often the fallback rooms are checked
+const CHECK_INTERVAL: Duration = Duration::from_secs(15);
+
+/// How long a room's devices must be missing from the LAN before the relay is used
+const LAN_GRACE: Duration = Duration::from_secs(45);
+
+/// What carries a wiki's sync right now
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Transport {
+    /// No peer on the LAN and no relay connection
+    None,
+    Lan,
+    Relay,
+    LanAndRelay,
+}
+
+impl Transport {
+    pub fn of(lan_peers: bool, relay_connected: bool) -> Self {
+        match (lan_peers, relay_connected) {
+            (false, false) => Self::None,
+            (true, false) => Self::Lan,
+            (false, true) => Self::Relay,
+            (true, true) => Self::LanAndRelay,
+        }
+    }
+}
+
+/// The transport of a synced wiki, for the sync status
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WikiTransport {
+    pub wiki_id: String,
+    pub room_code: String,
+    pub transport: Transport,
+    /// The relay is only used while the room's devices aren't on the LAN
+    pub relay_fallback: bool,
+}
+
+/// What to do with the relay connection of a fallback room
+#[derive(Debug, PartialEq)]
+enum Action {
+    Connect,
+    Disconnect,
+    Keep,
+}
+
+fn next_action(lan_peers: bool, relay_connected: bool, lan_missing_for: Duration) -> Action {
+    if lan_peers && relay_connected {
+        Action::Disconnect
+    } else if !lan_peers && !relay_connected && lan_missing_for >= LAN_GRACE {
+        Action::Connect
+    } else {
+        Action::Keep
+    }
+}
+
+/// Since when each fallback room has had no device on the LAN
+static LAN_MISSING_SINCE: Mutex<Option<HashMap<String, Instant>>> = Mutex::new(None);
+
+/// Connect or disconnect the relay for the fallback rooms
+async fn check(mgr: &SyncManager) {
+    let Some(relay) = &mgr.relay_manager else {
+        return;
+    };
+    let rooms = relay.active_fallback_rooms().await;
+    let relay_rooms = relay.get_connected_room_codes().await;
+    let mut lan_rooms = Vec::new();
+    if let Some(ref server) = *mgr.server.read().await {
+        for room_code in &rooms {
+            if !server.lan_peers_for_room(room_code).await.is_empty() {
+                lan_rooms.push(room_code.clone());
+            }
+        }
+    }
+
+    let now = Instant::now();
+    let actions: Vec<(String, Action)> = {
+        let mut guard = LAN_MISSING_SINCE.lock().unwrap();
+        let missing_since = guard.get_or_insert_with(HashMap::new);
+        missing_since.retain(|room_code, _| rooms.contains(room_code));
+        rooms
+            .iter()
+            .map(|room_code| {
+                let lan_peers = lan_rooms.contains(room_code);
+                let missing_for = if lan_peers {
+                    missing_since.remove(room_code);
+                    Duration::ZERO
+                } else {
+                    now.duration_since(*missing_since.entry(room_code.clone()).or_insert(now))
+                };
+                (room_code.clone(), next_action(lan_peers, relay_rooms.contains(room_code), missing_for))
+            })
+            .collect()
+    };
+
+    for (room_code, action) in actions {
+        match action {
+            Action::Disconnect => {
+                eprintln!("[LAN Sync] Room {} is reachable on the LAN again — leaving the relay", room_code);
+                relay.disconnect_room(&room_code).await;
+            }
+            Action::Connect => {
+                let Some(app) = crate::GLOBAL_APP_HANDLE.get() else {
+                    continue;
+                };
+                if !relay.is_authenticated().await
+                    || crate::metered::defers(app, crate::metered::Feature::RelaySync).await
+                {
+                    continue;
+                }
+                eprintln!("[LAN Sync] No device of room {} found on the LAN — falling back to the relay", room_code);
+                if let Err(e) = relay.connect_room(&room_code).await {
+                    eprintln!("[LAN Sync] Relay fallback for room {} failed: {}", room_code, e);
+                }
+            }
+            Action::Keep => {}
+        }
+    }
+}
+
+/// Check the fallback rooms periodically (main process, once)
+pub fn start() {
+    tauri::async_runtime::spawn(async {
+        loop {
+            tokio::time::sleep(CHECK_INTERVAL).await;
+            if let Some(mgr) = super::get_sync_manager() {
+                check(&mgr).await;
+            }
+        }
+    });
+}
+
+/// The transport of each sync-enabled wiki assigned to a relay room
+pub async fn wiki_transports(mgr: &SyncManager) -> Vec<WikiTransport> {
+    let (Some(app), Some(relay)) = (crate::GLOBAL_APP_HANDLE.get(), &mgr.relay_manager) else {
+        return Vec::new();
+    };
+    let relay_rooms = relay.get_connected_room_codes().await;
+    let server = mgr.server.read().await;
+    let mut transports = Vec::new();
+    for entry in crate::wiki_storage::load_recent_files_from_disk(app) {
+        let (true, Some(wiki_id), Some(room_code)) = (entry.sync_enabled, entry.sync_id, entry.relay_room) else {
+            continue;
+        };
+        let lan_peers = match server.as_ref() {
+            Some(server) => !server.lan_peers_for_room(&room_code).await.is_empty(),
+            None => false,
+        };
+        transports.push(WikiTransport {
+            wiki_id,
+            transport: Transport::of(lan_peers, relay_rooms.contains(&room_code)),
+            relay_fallback: relay.is_relay_fallback(&room_code).await,
+            room_code,
+        });
+    }
+    transports
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_action() {
+        // Back on the LAN: leave the relay
+        assert_eq!(next_action(true, true, Duration::ZERO), Action::Disconnect);
+        assert_eq!(next_action(true, false, Duration::ZERO), Action::Keep);
+        // Missing from the LAN: wait for the grace period first
+        assert_eq!(next_action(false, false, Duration::from_secs(10)), Action::Keep);
+        assert_eq!(next_action(false, false, LAN_GRACE), Action::Connect);
+        assert_eq!(next_action(false, true, LAN_GRACE * 2), Action::Keep);
+    }
+
+    #[test]
+    fn test_transport_names() {
+        assert_eq!(serde_json::to_string(&Transport::of(true, true)).unwrap(), r#""lan-and-relay""#);
+        assert_eq!(Transport::of(false, true), Transport::Relay);
+        assert_eq!(Transport::of(false, false), Transport::None);
+    }
+}
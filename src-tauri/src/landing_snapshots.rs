@@ -0,0 +1,121 @@
+//! Snapshots of the landing page
+//!
+//! `tiddlydesktop.html` holds the wiki list and most app configuration, so the
+//! last versions of it are kept in `tiddlydesktop.backups/` next to it (the
+//! same layout as wiki backups). A snapshot is taken before each migration to
+//! a new version, and hourly while the app runs if the landing page changed
+//! since the last one. `restore_app_wiki` puts a snapshot back.
+
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use chrono::Local;
+use tauri::Manager;
+use tiddlydesktop_core::backup;
+use tiddlydesktop_core::tiddler_store;
+
+/// Number of snapshots kept
+const SNAPSHOT_COUNT: usize = 10;
+
+/// How often the landing page is checked for changes
+const SNAPSHOT_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+static STARTED: OnceLock<()> = OnceLock::new();
+
+/// A landing page snapshot as shown in the landing page
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct AppWikiSnapshot {
+    pub path: String,
+    /// Local time the snapshot was taken (`YYYY-MM-DD HH:MM:SS`)
+    pub timestamp: String,
+}
+
+/// Copy the landing page into its snapshot folder and drop the oldest
+/// snapshots beyond `SNAPSHOT_COUNT`
+pub fn snapshot(wiki_path: &Path) -> Result<PathBuf, String> {
+    let dir = backup::backup_dir_for_wiki(wiki_path, None).ok_or("No parent directory")?;
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create snapshot dir: {}", e))?;
+
+    let stem = wiki_path.file_stem().and_then(|s| s.to_str()).unwrap_or("tiddlydesktop");
+    let path = dir.join(format!("{}.{}.html", stem, Local::now().format("%Y%m%d-%H%M%S")));
+    std::fs::copy(wiki_path, &path).map_err(|e| format!("Failed to snapshot landing page: {}", e))?;
+
+    let snapshots = backup::list_wiki_backups(wiki_path, None);
+    for old in snapshots.iter().take(snapshots.len().saturating_sub(SNAPSHOT_COUNT)) {
+        let _ = std::fs::remove_file(&old.path);
+    }
+    Ok(path)
+}
+
+/// Whether the landing page changed since its newest snapshot
+fn changed_since_snapshot(wiki_path: &Path) -> bool {
+    let Some(newest) = backup::list_wiki_backups(wiki_path, None).pop() else {
+        return true;
+    };
+    std::fs::metadata(wiki_path)
+        .and_then(|m| m.modified())
+        .map(|modified| chrono::DateTime::<Local>::from(modified).naive_local() > newest.timestamp)
+        .unwrap_or(false)
+}
+
+/// Start taking periodic snapshots (once per process)
+pub fn start(wiki_path: PathBuf) {
+    if STARTED.set(()).is_err() {
+        return;
+    }
+    std::thread::spawn(move || loop {
+        if wiki_path.exists() && changed_since_snapshot(&wiki_path) {
+            if let Err(e) = snapshot(&wiki_path) {
+                eprintln!("[TiddlyDesktop] {}", e);
+            }
+        }
+        std::thread::sleep(SNAPSHOT_INTERVAL);
+    });
+}
+
+/// List the landing page snapshots, newest first
+#[tauri::command]
+pub fn list_app_wiki_snapshots(state: tauri::State<'_, crate::AppState>) -> Vec<AppWikiSnapshot> {
+    backup::list_wiki_backups(&state.main_wiki_path, None)
+        .into_iter()
+        .rev()
+        .map(|b| AppWikiSnapshot {
+            path: b.path.to_string_lossy().to_string(),
+            timestamp: b.timestamp.format("%Y-%m-%d %H:%M:%S").to_string(),
+        })
+        .collect()
+}
+
+/// Replace the landing page with one of its snapshots and reload it.
+/// The current landing page is snapshotted first, so this can be undone.
+#[tauri::command]
+pub fn restore_app_wiki(app: tauri::AppHandle, snapshot_path: String) -> Result<(), String> {
+    let wiki_path = app.state::<crate::AppState>().main_wiki_path.clone();
+    // Only snapshots of the landing page can be restored
+    let source = backup::list_wiki_backups(&wiki_path, None)
+        .into_iter()
+        .map(|b| b.path)
+        .find(|p| p.to_string_lossy() == snapshot_path)
+        .ok_or("Not a landing page snapshot")?;
+    if tiddler_store::find_tiddler(&source, "$:/TiddlyDesktop/WikiList").ok().flatten().is_none() {
+        return Err("The snapshot has no wiki list".to_string());
+    }
+
+    // Copied aside first: snapshotting the current version may prune `source`
+    let mut temp_name = wiki_path.file_name().unwrap_or_default().to_os_string();
+    temp_name.push(".restore-tmp");
+    let temp_path = wiki_path.with_file_name(temp_name);
+    std::fs::copy(&source, &temp_path).map_err(|e| format!("Failed to restore snapshot: {}", e))?;
+    if let Err(e) = snapshot(&wiki_path) {
+        let _ = std::fs::remove_file(&temp_path);
+        return Err(e);
+    }
+    std::fs::rename(&temp_path, &wiki_path).map_err(|e| format!("Failed to restore snapshot: {}", e))?;
+    eprintln!("[TiddlyDesktop] Restored landing page from {:?}", source);
+
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.eval("window.location.reload();");
+    }
+    Ok(())
+}
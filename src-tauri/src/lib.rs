@@ -25,27 +25,46 @@ pub fn get_data_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
     Ok(dir)
 }
 
-/// Resolve the data directory, checking for portable mode.
-/// Portable mode: `portable` or `portable.txt` marker next to the executable,
-/// or `tiddlydesktop.html` already exists next to the executable.
-fn resolve_data_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+/// Directory that portable-relative wiki paths are stored against: the executable's
+/// directory when running in portable mode, None otherwise.
+pub fn get_portable_dir(app: &tauri::AppHandle) -> Option<PathBuf> {
+    #[cfg(target_os = "android")]
+    {
+        let _ = app;
+        None
+    }
     #[cfg(not(target_os = "android"))]
     {
-        if let Ok(exe_path) = std::env::current_exe() {
-            if let Some(exe_dir) = exe_path.parent() {
-                if exe_dir.join("portable").exists()
-                    || exe_dir.join("portable.txt").exists()
-                {
-                    eprintln!("[TiddlyDesktop] Data directory: {} (portable mode, marker file)", exe_dir.display());
-                    return Ok(exe_dir.to_path_buf());
-                }
-                if exe_dir.join("tiddlydesktop.html").exists() {
-                    eprintln!("[TiddlyDesktop] Data directory: {} (portable mode, tiddlydesktop.html exists)", exe_dir.display());
-                    return Ok(exe_dir.to_path_buf());
-                }
-            }
+        let data_dir = get_data_dir(app).ok()?;
+        let exe_dir = std::env::current_exe().ok()?.parent()?.to_path_buf();
+        if data_dir == exe_dir {
+            Some(exe_dir)
+        } else {
+            None
         }
     }
+}
+
+/// The executable's directory when running in portable mode:
+/// `portable` or `portable.txt` marker next to the executable,
+/// or `tiddlydesktop.html` already exists next to the executable.
+#[cfg(not(target_os = "android"))]
+fn portable_data_dir() -> Option<PathBuf> {
+    let exe_path = std::env::current_exe().ok()?;
+    let exe_dir = exe_path.parent()?;
+    let is_portable = exe_dir.join("portable").exists()
+        || exe_dir.join("portable.txt").exists()
+        || exe_dir.join("tiddlydesktop.html").exists();
+    is_portable.then(|| exe_dir.to_path_buf())
+}
+
+/// Resolve the data directory, checking for portable mode (see `portable_data_dir`).
+fn resolve_data_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    #[cfg(not(target_os = "android"))]
+    if let Some(dir) = portable_data_dir() {
+        eprintln!("[TiddlyDesktop] Data directory: {} (portable mode)", dir.display());
+        return Ok(dir);
+    }
     let dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
     eprintln!("[TiddlyDesktop] Data directory: {} (system mode)", dir.display());
     Ok(dir)
@@ -169,25 +188,42 @@ fn x11_activate_window_impl(gtk_window: &gtk::ApplicationWindow, gdk_window: &gt
     }
 }
 
-/// Linux: Enable smooth (kinetic) scrolling on a WebKitGTK webview.
-/// WebKitGTK defaults to non-smooth scrolling; this enables momentum/inertial scrolling.
+/// Linux: Find the WebKitWebView widget inside a window's widget tree
 #[cfg(target_os = "linux")]
-fn enable_smooth_scrolling(window: &tauri::WebviewWindow) {
+fn linux_find_webkit_widget(widget: &impl gtk::glib::object::IsA<gtk::Widget>) -> Option<gtk::Widget> {
     use gtk::prelude::*;
-    fn find_webkit(widget: &impl IsA<gtk::Widget>) -> Option<gtk::Widget> {
-        let w = widget.upcast_ref::<gtk::Widget>();
-        if w.type_().name().contains("WebKit") {
-            return Some(w.clone());
-        }
-        if let Some(c) = w.downcast_ref::<gtk::Container>() {
-            for child in c.children() {
-                if let Some(found) = find_webkit(&child) {
-                    return Some(found);
-                }
+    let w = widget.upcast_ref::<gtk::Widget>();
+    if w.type_().name().contains("WebKit") {
+        return Some(w.clone());
+    }
+    if let Some(c) = w.downcast_ref::<gtk::Container>() {
+        for child in c.children() {
+            if let Some(found) = linux_find_webkit_widget(&child) {
+                return Some(found);
             }
         }
-        None
     }
+    None
+}
+
+/// Linux: Give a native widget an accessible name and role for AT-SPI screen readers.
+/// Also sets the tooltip so sighted keyboard users get the same label.
+#[cfg(target_os = "linux")]
+fn linux_set_accessible(widget: &impl gtk::glib::object::IsA<gtk::Widget>, name: &str, role: atk::Role) {
+    use atk::prelude::AtkObjectExt;
+    use gtk::prelude::WidgetExt;
+    widget.set_tooltip_text(Some(name));
+    if let Some(accessible) = widget.accessible() {
+        accessible.set_name(name);
+        accessible.set_role(role);
+    }
+}
+
+/// Linux: Enable smooth (kinetic) scrolling on a WebKitGTK webview.
+/// WebKitGTK defaults to non-smooth scrolling; this enables momentum/inertial scrolling.
+#[cfg(target_os = "linux")]
+fn enable_smooth_scrolling(window: &tauri::WebviewWindow) {
+    use gtk::prelude::*;
 
     extern "C" {
         fn webkit_web_view_get_settings(
@@ -200,7 +236,7 @@ fn enable_smooth_scrolling(window: &tauri::WebviewWindow) {
     }
 
     if let Ok(gtk_window) = window.gtk_window() {
-        if let Some(webview_widget) = find_webkit(&gtk_window) {
+        if let Some(webview_widget) = linux_find_webkit_widget(&gtk_window) {
             unsafe {
                 use gtk::glib::object::ObjectExt;
                 let wv_ptr = webview_widget.as_ptr() as *mut gtk::glib::gobject_ffi::GObject;
@@ -257,6 +293,10 @@ fn setup_header_bar(window: &tauri::WebviewWindow) {
         title_label.set_valign(gtk::Align::Center);
         title_label.set_hexpand(true);
         title_label.style_context().add_class("title");
+        if let Some(accessible) = title_label.accessible() {
+            use atk::prelude::AtkObjectExt;
+            accessible.set_role(atk::Role::Heading);
+        }
         overlay.add(&title_label); // Base widget
 
         // Favicon icon overlaid on the left - initially hidden, shown when set via set_window_icon
@@ -267,6 +307,11 @@ fn setup_header_bar(window: &tauri::WebviewWindow) {
         icon_image.set_widget_name("headerbar-favicon");
         icon_image.set_visible(false); // Hidden until favicon is set
         icon_image.set_no_show_all(true); // Don't show with show_all()
+        if let Some(accessible) = icon_image.accessible() {
+            use atk::prelude::AtkObjectExt;
+            accessible.set_name("Wiki icon");
+            accessible.set_role(atk::Role::Icon);
+        }
         overlay.add_overlay(&icon_image);
 
         // Button box for window controls (minimize and close) overlaid on the right
@@ -279,6 +324,9 @@ fn setup_header_bar(window: &tauri::WebviewWindow) {
         let minimize_button = gtk::Button::from_icon_name(Some("window-minimize-symbolic"), gtk::IconSize::Menu);
         minimize_button.style_context().add_class("titlebutton");
         minimize_button.style_context().add_class("minimize");
+        minimize_button.set_can_focus(true);
+        minimize_button.set_focus_on_click(false); // Mouse clicks shouldn't pull focus out of the wiki
+        linux_set_accessible(&minimize_button, "Minimize", atk::Role::PushButton);
         let win_weak_minimize = glib::object::ObjectExt::downgrade(&gtk_window);
         minimize_button.connect_clicked(move |_| {
             if let Some(win) = win_weak_minimize.upgrade() {
@@ -291,6 +339,9 @@ fn setup_header_bar(window: &tauri::WebviewWindow) {
         let close_button = gtk::Button::from_icon_name(Some("window-close-symbolic"), gtk::IconSize::Menu);
         close_button.style_context().add_class("titlebutton");
         close_button.style_context().add_class("close");
+        close_button.set_can_focus(true);
+        close_button.set_focus_on_click(false);
+        linux_set_accessible(&close_button, "Close", atk::Role::PushButton);
         let win_weak_close = glib::object::ObjectExt::downgrade(&gtk_window);
         close_button.connect_clicked(move |_| {
             if let Some(win) = win_weak_close.upgrade() {
@@ -300,9 +351,52 @@ fn setup_header_bar(window: &tauri::WebviewWindow) {
         button_box.pack_start(&close_button, false, false, 0);
 
         overlay.add_overlay(&button_box);
+        if let Some(accessible) = button_box.accessible() {
+            use atk::prelude::AtkObjectExt;
+            accessible.set_name("Window controls");
+            accessible.set_role(atk::Role::ToolBar);
+        }
 
         event_box.add(&overlay);
 
+        // Keyboard reachability: the webview keeps focus for Tab navigation, so
+        // Ctrl+F6 moves focus into the headerbar controls and back (Escape also returns).
+        // Left/Right move between the buttons once they have focus.
+        let minimize_weak = glib::object::ObjectExt::downgrade(&minimize_button);
+        let close_weak = glib::object::ObjectExt::downgrade(&close_button);
+        gtk_window.connect_key_press_event(move |win, event| {
+            let (Some(minimize), Some(close)) = (minimize_weak.upgrade(), close_weak.upgrade()) else {
+                return glib::Propagation::Proceed;
+            };
+            let in_headerbar = minimize.has_focus() || close.has_focus();
+            let keyval = event.keyval();
+            let ctrl = event.state().contains(gdk::ModifierType::CONTROL_MASK);
+
+            if keyval == gdk::keys::constants::F6 && ctrl {
+                if in_headerbar {
+                    if let Some(webview) = linux_find_webkit_widget(win) {
+                        webview.grab_focus();
+                    }
+                } else {
+                    minimize.grab_focus();
+                }
+                return glib::Propagation::Stop;
+            }
+            if in_headerbar {
+                if keyval == gdk::keys::constants::Escape {
+                    if let Some(webview) = linux_find_webkit_widget(win) {
+                        webview.grab_focus();
+                    }
+                    return glib::Propagation::Stop;
+                }
+                if keyval == gdk::keys::constants::Left || keyval == gdk::keys::constants::Right {
+                    if minimize.has_focus() { close.grab_focus(); } else { minimize.grab_focus(); }
+                    return glib::Propagation::Stop;
+                }
+            }
+            glib::Propagation::Proceed
+        });
+
         // Enable events on the event box for dragging
         event_box.add_events(
             gdk::EventMask::BUTTON_PRESS_MASK
@@ -678,16 +772,178 @@ mod ipc;
 
 /// JavaScript initialization scripts for wiki windows
 mod init_script;
+/// Custom CSS/JS snippets the user attached to wikis (injected by the init script)
+mod custom_snippets;
 
 /// Core data types
-mod types;
+use tiddlydesktop_core::types;
 pub use types::{WikiEntry, ExternalAttachmentsConfig, AuthUrlEntry, SessionAuthConfig, WikiConfigs, EditionInfo, PluginInfo, FolderStatus};
 
 /// Clipboard operations
 mod clipboard;
 
+/// Input method (IME) configuration and input debugging
+mod input_method;
+/// OS accessibility preferences (high contrast, reduced motion)
+mod accessibility;
+/// App-wide PIN/password lock
+mod app_lock;
+/// Throttling of wiki windows that stay minimized or hidden
+mod throttle;
+/// Soft memory limit for wiki processes
+mod memory_limit;
+/// Node-free TiddlyWeb server for folder wikis
+mod folder_server;
+/// WebDAV access to folder wikis' tiddler files (served by `folder_server`)
+mod webdav;
+/// Where the built-in servers listen (this computer, all networks or one interface)
+mod server_address;
+/// mDNS advertisement of the built-in servers and QR codes of their URLs
+mod server_discovery;
+/// Landing page migration in the background, with a boot check before swapping
+mod main_wiki_migration;
+/// Rendering a tiddler to a PNG in a hidden window (share cards, thumbnails)
+mod tiddler_image;
+/// Screenshot comparison of tiddlers against stored baselines (theme regressions)
+mod visual_regression;
+/// Developer tools and remote debugging ports of wiki windows
+mod devtools;
+/// Snapshots of the landing page (wiki list and app configuration)
+mod landing_snapshots;
+/// Adding many wikis to the wiki list at once
+mod batch_register;
+/// Recently closed wikis and reopening them (Ctrl/Cmd+Shift+T)
+mod recently_closed;
+/// Reopening the wikis that were open when the app last quit or crashed
+mod session_restore;
+/// Sync status badge in the native title bar of wiki windows
+mod sync_badge;
+/// Removable drive / network mount detection for wiki entries
+mod removable_media;
+/// Path identity (symlink/hardlink-aware path comparison)
+use tiddlydesktop_core::path_identity;
+/// Scratch directory for Node.js builds and conversions
+mod scratch_dir;
+/// Wiki format conversion with pre-flight report and verification
+#[cfg(not(target_os = "android"))]
+mod wiki_conversion;
+/// Scheduled single-file snapshots of folder wikis
+mod folder_snapshot;
+/// Save dialog filters, remembered directories and auto-download directories for downloads
+mod downloads;
+/// Conversion, downscaling and location stripping of imported images
+#[cfg(not(target_os = "android"))]
+mod image_import;
+/// Transcoding of imported videos to H.264/AAC MP4 (ffmpeg)
+#[cfg(not(target_os = "android"))]
+mod video_import;
+/// Hash manifest of external attachments (modified, corrupted and missing files)
+mod attachment_manifest;
+/// Per-window audio mute and volume (tray mute toggle per wiki)
+#[cfg(not(target_os = "android"))]
+mod audio;
+/// OS media session (media keys) for audio and video played in wiki windows
+#[cfg(not(target_os = "android"))]
+mod media_session;
+/// Moving embedded attachments to the files folder and inlining small external ones
+#[cfg(not(target_os = "android"))]
+mod attachment_migration;
+/// Zip archives of a wiki with its attachments (optionally AES-encrypted)
+mod wiki_archive;
+/// Current position from the OS location services (per-wiki permission)
+mod geolocation;
+/// QR code and barcode decoding (webcam frames, Android camera photos)
+mod qr_code;
+/// Serial port access for wiki windows (per-wiki permission)
+mod serial;
+/// System idle time (presence, autosave on idle)
+mod idle;
+/// Time spent per wiki and tiddler (time reports)
+mod time_tracking;
+/// JavaScript errors reported by wiki windows (log, landing page indicator)
+mod js_errors;
+/// App-wide focus (pomodoro) timer with phase notifications
+mod focus_timer;
+/// Tray quick actions with global hotkeys (new tiddler, journal, paste to a wiki)
+mod quick_actions;
+/// Global hotkeys that open or focus a wiki
+mod wiki_hotkeys;
+/// Wiki-defined commands in the tray menu (tiddlers tagged $:/tags/TiddlyDesktopRS/MenuCommand)
+mod wiki_commands;
+/// Event webhooks (wiki saved, sync conflict, backup completed) with HMAC signatures
+mod webhooks;
+/// Environment info for feature detection in wikis (get_environment)
+mod environment;
+/// Local programs the user allowed wikis to run by name (output streamed as events)
+mod allowed_commands;
+/// MQTT client for wiki windows (topic subscriptions, mqtt_publish)
+mod mqtt;
+/// First-run setup wizard (storage mode, importing wikis, backups, sync, starter wiki)
+mod first_run;
+/// Importing the wiki list, favicons, window states and backup settings of classic TiddlyDesktop
+mod classic_import;
+/// Export and import of the whole app configuration as a zip archive
+mod app_config;
+/// Quick switcher window for jumping to any wiki (Ctrl/Cmd+K)
+mod quick_switcher;
+/// External browser mode: wikis served to the system browser instead of a window
+mod browser_mode;
+/// Headless server mode (`--serve-all`): folder wiki servers, sync and a control API
+mod serve_all;
+/// Local HTTP API for launchers and scripts (wikis, tiddlers, backups)
+mod automation;
+/// Opening a wiki with its plugins turned off (TiddlyWiki's safe mode)
+mod safe_mode;
+/// MCP server giving local AI assistants access to shared wikis (`--mcp`)
+mod mcp;
+/// Compatibility report of a wiki's plugins before plugins are installed
+mod plugin_compat;
+/// Full-text search across the wikis of the wiki list (background index)
+mod search_index;
+/// Folder wikis loading a plugin from its source folder, reloaded when it changes
+mod plugin_dev;
+/// `tiddlywiki.files` manifests referencing external media folders from folder wikis
+mod media_manifest;
+/// Saving big single-file wikis by appending the changed tiddlers
+mod incremental_save;
+/// Saving big wikis in chunks instead of as one IPC argument
+mod chunked_save;
+/// Favicons of single-file wikis, cached by path and modification time
+mod favicon_cache;
+/// Per-wiki environment variables of folder wiki processes, optionally from the OS keychain
+mod wiki_env;
+/// Installing the headless server mode as a systemd / launchd / scheduled task service
+#[cfg(not(target_os = "android"))]
+mod service;
+/// `--headless` batch commands (render, backup, convert) without windows
+#[cfg(not(target_os = "android"))]
+mod headless;
+/// `--list-wikis`, `--backup` and `--sync-now` for scripts, through the running instance if there is one
+#[cfg(not(target_os = "android"))]
+mod cli;
+/// Watched folders: automatic discovery of wikis in chosen directories
+mod watched_folders;
+/// Wiki process registry: reconnecting wiki processes to a restarted main process
+#[cfg(not(target_os = "android"))]
+mod process_registry;
+/// Second app instance detection and hand-off to the running instance
+#[cfg(not(target_os = "android"))]
+mod instance;
+/// Opening wikis passed by the OS (file associations, "Open With")
+#[cfg(not(target_os = "android"))]
+mod file_open;
+/// `tiddlydesktop://open?path=...&tiddler=...` links
+#[cfg(not(target_os = "android"))]
+mod deep_link;
+/// Sync tool conflict copies (Syncthing, Dropbox) and guided merge
+mod conflict_copies;
+/// Desktop shortcuts that open a single wiki
+mod desktop_shortcut;
+/// Shell extensions: commands, tray items and protocol handlers from native libraries
+mod extensions;
+
 /// Utility functions
-mod utils;
+use tiddlydesktop_core::utils;
 
 #[cfg(not(target_os = "android"))]
 mod media_server;
@@ -703,8 +959,25 @@ const LINUX_USER_AGENT: &str = "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.
 mod wiki_storage;
 
 /// TiddlyWiki HTML manipulation
-#[cfg_attr(target_os = "android", allow(dead_code))]
-mod tiddlywiki_html;
+use tiddlydesktop_core::tiddlywiki_html;
+use tiddlydesktop_core::tiddler_store;
+
+/// Wiki backups before each save
+use tiddlydesktop_core::backup::create_backup;
+
+/// Tiddler history reconstructed from wiki backups
+mod backup_history;
+/// Periodic backups of single-file wikis, independent of saves
+mod scheduled_backups;
+
+/// Structured text diffs (tiddler history, conflict and backup comparison)
+mod text_diff;
+
+/// Filter evaluation against closed wikis
+mod wiki_filter;
+
+/// Import and export of wikis stored in SQLite
+mod sqlite_wiki;
 
 /// Cross-platform file system abstraction (desktop: std::fs, Android: SAF)
 mod fs_abstraction;
@@ -724,6 +997,12 @@ mod lan_sync;
 #[allow(dead_code)]
 mod relay_sync;
 
+/// Transfer statistics for sync peers, synced wikis and the media server
+mod transfer_stats;
+
+/// Metered connection awareness: deferring relay sync, plugin library fetches and update checks
+mod metered;
+
 /// Helper trait to conditionally add platform-specific plugins to the Tauri builder.
 /// On Android, this adds the Android FS plugin for SAF support.
 trait BuilderExt<R: tauri::Runtime> {
@@ -1040,79 +1319,28 @@ pub fn needs_resource_extraction(app: &tauri::App) -> bool {
     }
 }
 
-/// Ensure main wiki file exists, extracting from resources if needed
-/// Also handles migration when bundled version is newer than existing
+/// Ensure main wiki file exists, extracting from resources if needed.
+/// Migration to a newer bundled version runs in the background once the app
+/// is set up (see `main_wiki_migration`).
 #[cfg(not(target_os = "android"))]
 fn ensure_main_wiki_exists(app: &tauri::App) -> Result<PathBuf, String> {
     let wiki_dir = determine_storage_mode(app)?;
     std::fs::create_dir_all(&wiki_dir).map_err(|e| format!("Failed to create wiki dir: {}", e))?;
 
     let main_wiki_path = wiki_dir.join("tiddlydesktop.html");
-    let bundled_path = get_bundled_index_path(app)?;
 
     if !main_wiki_path.exists() {
         // First run: copy from bundled resources
+        let bundled_path = get_bundled_index_path(app)?;
         std::fs::copy(&bundled_path, &main_wiki_path)
             .map_err(|e| format!("Failed to copy wiki: {}", e))?;
         eprintln!("[TiddlyDesktop] Created main wiki from {:?}", bundled_path);
-    } else {
-        // Check if we need to migrate to a newer version
-        let existing_html = std::fs::read_to_string(&main_wiki_path)
-            .map_err(|e| format!("Failed to read existing wiki: {}", e))?;
-        let bundled_html = std::fs::read_to_string(&bundled_path)
-            .map_err(|e| format!("Failed to read bundled wiki: {}", e))?;
-
-        let existing_version = tiddlywiki_html::extract_tiddler_from_html(&existing_html, "$:/TiddlyDesktop/AppVersion")
-            .and_then(|v| v.trim().parse::<u32>().ok())
-            .unwrap_or(0);
-        let bundled_version = tiddlywiki_html::extract_tiddler_from_html(&bundled_html, "$:/TiddlyDesktop/AppVersion")
-            .and_then(|v| v.trim().parse::<u32>().ok())
-            .unwrap_or(0);
-
-        if bundled_version > existing_version {
-            eprintln!("[TiddlyDesktop] Migrating landing page from version {} to {}", existing_version, bundled_version);
-
-            // Create backup before migration
-            let backup_path = wiki_dir.join("tiddlydesktop.html.migration-backup");
-            std::fs::copy(&main_wiki_path, &backup_path)
-                .map_err(|e| format!("Failed to create migration backup: {}", e))?;
-            eprintln!("[TiddlyDesktop] Created backup: {:?}", backup_path);
-
-            // Merge tiddler stores: preserve user data, update system/plugin tiddlers
-            match tiddlywiki_html::build_merged_html(&existing_html, &bundled_html) {
-                Ok(merged_html) => {
-                    // Validate: if old had WikiList, make sure merged does too
-                    let had_wiki_list = tiddlywiki_html::extract_tiddler_from_html(
-                        &existing_html, "$:/TiddlyDesktop/WikiList").is_some();
-                    let has_wiki_list = tiddlywiki_html::extract_tiddler_from_html(
-                        &merged_html, "$:/TiddlyDesktop/WikiList").is_some();
-
-                    if had_wiki_list && !has_wiki_list {
-                        eprintln!("[TiddlyDesktop] ERROR: WikiList lost during merge, restoring backup");
-                        std::fs::copy(&backup_path, &main_wiki_path)
-                            .map_err(|e| format!("Failed to restore backup: {}", e))?;
-                        return Ok(main_wiki_path);
-                    }
-
-                    // Atomic write: temp file + rename
-                    let temp_path = wiki_dir.join("tiddlydesktop.html.migration-tmp");
-                    std::fs::write(&temp_path, &merged_html)
-                        .map_err(|e| format!("Failed to write migrated wiki: {}", e))?;
-                    std::fs::rename(&temp_path, &main_wiki_path)
-                        .map_err(|e| format!("Failed to finalize migrated wiki: {}", e))?;
-
-                    eprintln!("[TiddlyDesktop] Migration complete (version {} -> {})",
-                        existing_version, bundled_version);
-                }
-                Err(e) => {
-                    eprintln!("[TiddlyDesktop] ERROR: Migration merge failed: {}. Restoring backup.", e);
-                    std::fs::copy(&backup_path, &main_wiki_path)
-                        .map_err(|e| format!("Failed to restore backup: {}", e))?;
-                }
-            }
-        }
+        first_run::mark_pending(&wiki_dir);
     }
 
+    // A migration interrupted by quitting or a crash leaves its temp file behind
+    let _ = std::fs::remove_file(wiki_dir.join("tiddlydesktop.html.migration-tmp"));
+
     Ok(main_wiki_path)
 }
 
@@ -1133,11 +1361,8 @@ fn ensure_main_wiki_exists(app: &tauri::App) -> Result<PathBuf, String> {
             .map_err(|e| format!("Failed to write wiki: {}", e))?;
         eprintln!("[TiddlyDesktop] Created main wiki from bundled assets");
     } else {
-        // Check if we need to migrate to a newer version
-        let existing_html = std::fs::read_to_string(&main_wiki_path)
-            .map_err(|e| format!("Failed to read existing wiki: {}", e))?;
-
-        let existing_version = tiddlywiki_html::extract_tiddler_from_html(&existing_html, "$:/TiddlyDesktop/AppVersion")
+        // Check if we need to migrate to a newer version (streaming the existing wiki)
+        let existing_version = tiddler_store::tiddler_text(&main_wiki_path, "$:/TiddlyDesktop/AppVersion")
             .and_then(|v| v.trim().parse::<u32>().ok())
             .unwrap_or(0);
         let bundled_version = tiddlywiki_html::extract_tiddler_from_html(&bundled_html, "$:/TiddlyDesktop/AppVersion")
@@ -1148,17 +1373,20 @@ fn ensure_main_wiki_exists(app: &tauri::App) -> Result<PathBuf, String> {
             eprintln!("[TiddlyDesktop] Migrating landing page from version {} to {}", existing_version, bundled_version);
 
             // Create backup before migration
+            if let Err(e) = landing_snapshots::snapshot(&main_wiki_path) {
+                eprintln!("[TiddlyDesktop] {}", e);
+            }
             let backup_path = wiki_dir.join("tiddlydesktop.html.migration-backup");
             std::fs::copy(&main_wiki_path, &backup_path)
                 .map_err(|e| format!("Failed to create migration backup: {}", e))?;
             eprintln!("[TiddlyDesktop] Created backup: {:?}", backup_path);
 
             // Merge tiddler stores: preserve user data, update system/plugin tiddlers
-            match tiddlywiki_html::build_merged_html(&existing_html, &bundled_html) {
+            match tiddlywiki_html::build_merged_html_from_file(&backup_path, &bundled_html) {
                 Ok(merged_html) => {
                     // Validate: if old had WikiList, make sure merged does too
-                    let had_wiki_list = tiddlywiki_html::extract_tiddler_from_html(
-                        &existing_html, "$:/TiddlyDesktop/WikiList").is_some();
+                    let had_wiki_list = tiddler_store::find_tiddler(
+                        &backup_path, "$:/TiddlyDesktop/WikiList").ok().flatten().is_some();
                     let has_wiki_list = tiddlywiki_html::extract_tiddler_from_html(
                         &merged_html, "$:/TiddlyDesktop/WikiList").is_some();
 
@@ -1191,74 +1419,6 @@ fn ensure_main_wiki_exists(app: &tauri::App) -> Result<PathBuf, String> {
     Ok(main_wiki_path)
 }
 
-/// Create a backup of the wiki file before saving
-/// If custom_backup_dir is Some, backups go there; otherwise to .backups folder next to wiki
-/// backup_count: None = default 20, Some(0) = unlimited, Some(n) = keep n backups
-async fn create_backup(path: &PathBuf, custom_backup_dir: Option<&str>, backup_count: Option<u32>) -> Result<(), String> {
-    if !path.exists() {
-        return Ok(()); // No backup needed for new files
-    }
-
-    let parent = path.parent().ok_or("No parent directory")?;
-    let filename = path.file_stem().and_then(|s| s.to_str()).unwrap_or("wiki");
-
-    // Determine backup directory
-    let backup_dir = if let Some(custom_dir) = custom_backup_dir {
-        PathBuf::from(custom_dir)
-    } else {
-        // Default: .backups folder next to the wiki
-        parent.join(format!("{}.backups", filename))
-    };
-
-    tokio::fs::create_dir_all(&backup_dir)
-        .await
-        .map_err(|e| format!("Failed to create backup dir: {}", e))?;
-
-    // Create timestamped backup filename
-    let timestamp = Local::now().format("%Y%m%d-%H%M%S");
-    let backup_name = format!("{}.{}.html", filename, timestamp);
-    let backup_path = backup_dir.join(backup_name);
-
-    // Copy current file to backup
-    tokio::fs::copy(path, &backup_path)
-        .await
-        .map_err(|e| format!("Failed to create backup: {}", e))?;
-
-    // Clean up old backups (default: keep last 20, 0 = unlimited)
-    let keep = backup_count.unwrap_or(20);
-    if keep > 0 {
-        cleanup_old_backups(&backup_dir, keep as usize).await;
-    }
-
-    Ok(())
-}
-
-/// Remove old backups, keeping only the most recent ones
-async fn cleanup_old_backups(backup_dir: &PathBuf, keep: usize) {
-    if keep == 0 {
-        return; // 0 means unlimited, don't delete anything
-    }
-
-    if let Ok(mut entries) = tokio::fs::read_dir(backup_dir).await {
-        let mut backups: Vec<PathBuf> = Vec::new();
-        while let Ok(Some(entry)) = entries.next_entry().await {
-            let path = entry.path();
-            if path.extension().map(|e| e == "html").unwrap_or(false) {
-                backups.push(path);
-            }
-        }
-
-        // Sort by name (which includes timestamp) descending
-        backups.sort();
-        backups.reverse();
-
-        // Remove old backups
-        for old_backup in backups.into_iter().skip(keep) {
-            let _ = tokio::fs::remove_file(old_backup).await;
-        }
-    }
-}
-
 /// Load wiki content from disk
 #[tauri::command]
 async fn load_wiki(_app: tauri::AppHandle, path: String) -> Result<String, String> {
@@ -1270,12 +1430,55 @@ async fn load_wiki(_app: tauri::AppHandle, path: String) -> Result<String, Strin
 
     // Desktop: Validate and read from filesystem
     let validated_path = drag_drop::sanitize::validate_wiki_path(&path)?;
+    incremental_save::recover(&validated_path);
 
     tokio::fs::read_to_string(&validated_path)
         .await
         .map_err(|e| format!("Failed to read wiki: {}", e))
 }
 
+/// Checks and backups before a wiki file is overwritten with `new_size` bytes
+/// of new content (`save_wiki`, `chunked_save`)
+async fn prepare_wiki_write(app: &tauri::AppHandle, path: &str, validated_path: &std::path::Path, new_size: u64) -> Result<(), String> {
+    // Safety guard: refuse to overwrite an existing file with content that is
+    // drastically smaller (< 30% of original). This catches the scenario where
+    // a WebView renderer crash causes TiddlyWiki to autosave a nearly-empty page,
+    // destroying the user's settings and wiki list.
+    if validated_path.exists() {
+        if let Ok(existing_size) = tokio::fs::metadata(validated_path).await.map(|m| m.len()) {
+            if existing_size > 50_000 && new_size < existing_size * 30 / 100 {
+                eprintln!(
+                    "[TiddlyDesktop] SAVE BLOCKED: new content ({} bytes) is <30% of existing file ({} bytes). \
+                     This likely indicates a corrupt save after a renderer crash.",
+                    new_size, existing_size
+                );
+                return Err(format!(
+                    "Save blocked: content ({} bytes) is too small compared to existing file ({} bytes)",
+                    new_size, existing_size
+                ));
+            }
+        }
+    }
+
+    // Check if backups are enabled for this wiki
+    let state = app.state::<AppState>();
+    if should_create_backup(app, &state, path) {
+        let backup_dir = get_wiki_backup_dir(app, path);
+        let backup_count = wiki_storage::get_wiki_backup_count(app, path);
+        match create_backup(validated_path, backup_dir.as_deref(), backup_count).await {
+            Ok(()) => {
+                webhooks::dispatch(app, webhooks::WebhookEvent::BackupCompleted, serde_json::json!({ "wikiPath": path }));
+            }
+            Err(e) => {
+                // Log but don't block the save — backup failure should not prevent saving
+                eprintln!("[TiddlyDesktop] Backup failed (non-fatal): {}", e);
+            }
+        }
+    }
+
+    Ok(())
+}
+
 /// Save wiki content to disk with backup
 #[tauri::command]
 async fn save_wiki(app: tauri::AppHandle, path: String, content: String) -> Result<(), String> {
@@ -1301,6 +1504,7 @@ async fn save_wiki(app: tauri::AppHandle, path: String, content: String) -> Resu
                 if let Err(e) = android::saf::create_backup(&path, &backup_dir_uri, &filename_stem) {
                     eprintln!("[TiddlyDesktop] Failed to create Android backup: {}", e);
                 } else {
+                    webhooks::dispatch(&app, webhooks::WebhookEvent::BackupCompleted, serde_json::json!({ "wikiPath": path }));
                     // Clean up old backups
                     let backup_count = wiki_storage::get_wiki_backup_count(&app, &path).unwrap_or(20);
                     let _ = android::saf::cleanup_old_backups(&backup_dir_uri, &filename_stem, backup_count as usize);
@@ -1310,70 +1514,62 @@ async fn save_wiki(app: tauri::AppHandle, path: String, content: String) -> Resu
             }
         }
 
-        return android::saf::write_document_string(&path, &content);
+        android::saf::write_document_string(&path, &content)?;
+        webhooks::dispatch(&app, webhooks::WebhookEvent::WikiSaved, serde_json::json!({ "wikiPath": path }));
+        return Ok(());
     }
 
     // Desktop/Android filesystem: Validate and write
     let validated_path = drag_drop::sanitize::validate_wiki_path_for_write(&path)?;
+    incremental_save::recover(&validated_path);
 
-    // Safety guard: refuse to overwrite an existing file with content that is
-    // drastically smaller (< 30% of original). This catches the scenario where
-    // a WebView renderer crash causes TiddlyWiki to autosave a nearly-empty page,
-    // destroying the user's settings and wiki list.
-    if validated_path.exists() {
-        if let Ok(existing_size) = tokio::fs::metadata(&validated_path).await.map(|m| m.len()) {
-            let new_size = content.len() as u64;
-            if existing_size > 50_000 && new_size < existing_size * 30 / 100 {
-                eprintln!(
-                    "[TiddlyDesktop] SAVE BLOCKED: new content ({} bytes) is <30% of existing file ({} bytes). \
-                     This likely indicates a corrupt save after a renderer crash.",
-                    new_size, existing_size
-                );
-                return Err(format!(
-                    "Save blocked: content ({} bytes) is too small compared to existing file ({} bytes)",
-                    new_size, existing_size
-                ));
-            }
-        }
-    }
-
-    // Check if backups are enabled for this wiki
-    let state = app.state::<AppState>();
-    if should_create_backup(&app, &state, &path) {
-        let backup_dir = get_wiki_backup_dir(&app, &path);
-        let backup_count = wiki_storage::get_wiki_backup_count(&app, &path);
-        match create_backup(&validated_path, backup_dir.as_deref(), backup_count).await {
-            Ok(()) => {},
-            Err(e) => {
-                // Log but don't block the save — backup failure should not prevent saving
-                eprintln!("[TiddlyDesktop] Backup failed (non-fatal): {}", e);
-            }
-        }
-    }
+    prepare_wiki_write(&app, &path, &validated_path, content.len() as u64).await?;
 
-    // Write to a temp file first, then rename for atomic operation
-    let temp_path = validated_path.with_extension("tmp");
+    // Big wikis only get the changed tiddlers appended, if that's enabled.
+    // The content moves to the blocking task and back, so it isn't copied.
+    let (incremental, content) = if incremental_save::wants(&app, &validated_path) {
+        let app = app.clone();
+        let path = validated_path.clone();
+        tokio::task::spawn_blocking(move || (incremental_save::save(&app, &path, content.as_bytes()), content))
+            .await
+            .map_err(|e| format!("Failed to save file: {}", e))?
+    } else {
+        (false, content)
+    };
 
-    tokio::fs::write(&temp_path, &content)
-        .await
-        .map_err(|e| format!("Failed to write temp file: {}", e))?;
+    if !incremental {
+        // Write to a temp file first, then rename for atomic operation
+        let temp_path = validated_path.with_extension("tmp");
 
-    // Try rename first, fall back to direct write if it fails (Windows file locking)
-    if let Err(_) = tokio::fs::rename(&temp_path, &validated_path).await {
-        let _ = tokio::fs::remove_file(&temp_path).await;
-        tokio::fs::write(&validated_path, &content)
+        tokio::fs::write(&temp_path, &content)
             .await
-            .map_err(|e| format!("Failed to save file: {}", e))?;
+            .map_err(|e| format!("Failed to write temp file: {}", e))?;
+
+        // Try rename first, fall back to direct write if it fails (Windows file locking)
+        if let Err(_) = tokio::fs::rename(&temp_path, &validated_path).await {
+            let _ = tokio::fs::remove_file(&temp_path).await;
+            tokio::fs::write(&validated_path, &content)
+                .await
+                .map_err(|e| format!("Failed to save file: {}", e))?;
+        }
     }
 
+    favicon_cache::refresh_after_save(&app, &validated_path);
+    webhooks::dispatch(&app, webhooks::WebhookEvent::WikiSaved, serde_json::json!({ "wikiPath": path }));
     Ok(())
 }
 
-/// Set window title
-/// On Linux, navigates the HeaderBar widget tree to find and update the title label
+/// Set window title (with the sync status badge of the window, see `sync_badge`)
 #[tauri::command]
 async fn set_window_title(app: tauri::AppHandle, label: String, title: String) -> Result<(), String> {
-    if let Some(window) = app.get_webview_window(&label) {
+    let title = sync_badge::badged_title(&label, &title);
+    apply_window_title(&app, &label, &title)
+}
+
+/// Set the native title of a window
+/// On Linux, navigates the HeaderBar widget tree to find and update the title label
+fn apply_window_title(app: &tauri::AppHandle, label: &str, title: &str) -> Result<(), String> {
+    if let Some(window) = app.get_webview_window(label) {
         #[cfg(target_os = "linux")]
         {
             use gtk::prelude::{BinExt, GtkWindowExt, HeaderBarExt, LabelExt};
@@ -1390,7 +1586,7 @@ async fn set_window_title(app: tauri::AppHandle, label: String, title: String) -
                                     if let Some(overlay) = overlay.downcast_ref::<gtk::Overlay>() {
                                         if let Some(label) = overlay.child() {
                                             if let Some(title_label) = label.downcast_ref::<gtk::Label>() {
-                                                title_label.set_text(&title);
+                                                title_label.set_text(title);
                                                 updated_header = true;
                                             }
                                         }
@@ -1403,19 +1599,19 @@ async fn set_window_title(app: tauri::AppHandle, label: String, title: String) -
             }
             // Fallback: set WM title (used on X11 with server-side decorations)
             if !updated_header {
-                let _ = window.set_title(&title);
+                let _ = window.set_title(title);
             }
         }
 
         #[cfg(not(any(target_os = "linux", target_os = "android")))]
         {
-            window.set_title(&title).map_err(|e| e.to_string())?;
+            window.set_title(title).map_err(|e| e.to_string())?;
         }
 
         #[cfg(target_os = "android")]
         {
             // Android doesn't support setting window titles
-            let _ = (window, &title);
+            let _ = (window, title);
         }
     }
     Ok(())
@@ -1643,14 +1839,17 @@ fn set_window_icon_internal(
 }
 
 /// Set window icon from favicon data URI
-/// Call with None to reset to default app icon
+/// Call with None to reset to default app icon.
+/// With the wiki `path`, a custom icon assigned to the wiki takes precedence.
 #[tauri::command]
 fn set_window_icon(
     app: tauri::AppHandle,
     label: String,
     favicon_data_uri: Option<String>,
+    path: Option<String>,
 ) -> Result<(), String> {
-    set_window_icon_internal(&app, &label, favicon_data_uri.as_deref())
+    let custom_icon = path.and_then(|p| wiki_storage::get_wiki_custom_icon(&app, &p));
+    set_window_icon_internal(&app, &label, custom_icon.as_deref().or(favicon_data_uri.as_deref()))
 }
 
 /// Get current window label
@@ -1662,26 +1861,13 @@ fn get_window_label(window: tauri::Window) -> String {
 /// Check if backups should be created for a wiki path
 /// Checks both if it's the main wiki (always no backup) and the user's backups_enabled setting
 fn should_create_backup(app: &tauri::AppHandle, state: &AppState, path: &str) -> bool {
-    // Don't backup the main TiddlyDesktop wiki
-    // Use canonicalized paths for robust comparison (handles symlinks, relative paths, etc.)
-    let path_buf = PathBuf::from(path);
+    // Don't backup the main TiddlyDesktop wiki (also when reached via a symlink or hardlink)
     let main_wiki = &state.main_wiki_path;
     // Empty main_wiki_path means we're in a wiki child process — skip the main wiki check
-    if main_wiki.as_os_str().len() > 0 {
-        if let (Ok(canonical_path), Ok(canonical_main)) = (
-            dunce::canonicalize(&path_buf),
-            dunce::canonicalize(main_wiki)
-        ) {
-            if canonical_path == canonical_main {
-                return false;
-            }
-        } else {
-            // Fallback to string comparison if canonicalization fails
-            let main_wiki_str = main_wiki.to_string_lossy();
-            if utils::paths_equal(path, &main_wiki_str) {
-                return false;
-            }
-        }
+    if main_wiki.as_os_str().len() > 0
+        && path_identity::PathIdentity::new(path).same_as(&path_identity::PathIdentity::new(main_wiki))
+    {
+        return false;
     }
     // Check if backups are enabled for this wiki in the recent files list
     let entries = wiki_storage::load_recent_files_from_disk(app);
@@ -1830,7 +2016,8 @@ fn print_page(window: tauri::WebviewWindow) -> Result<(), String> {
     }
 }
 
-/// Show a save file dialog and write content to the selected file (used by tm-download-file)
+/// Show a save file dialog and write content to the selected file (used by tm-download-file).
+/// Wikis with an auto-download directory save there without asking (see `downloads`).
 #[tauri::command]
 #[allow(unused_variables)]
 async fn download_file(
@@ -1839,37 +2026,42 @@ async fn download_file(
     filename: String,
     content: String,
     content_type: Option<String>,
+    wiki_path: Option<String>,
+    csv_delimiter: Option<String>,
 ) -> Result<String, String> {
     use tauri_plugin_dialog::DialogExt;
 
     eprintln!("[download_file] Called: filename={}, content_type={:?}, content_len={}", filename, content_type, content.len());
 
-    // Determine file filter based on content type or filename extension
-    let extension = std::path::Path::new(&filename)
-        .extension()
-        .and_then(|e| e.to_str())
-        .unwrap_or("");
-
-    let filter_name = match content_type.as_deref() {
-        Some("application/json") => "JSON files",
-        Some("text/html") => "HTML files",
-        Some("text/plain") => "Text files",
-        Some("text/csv") => "CSV files",
-        _ => match extension {
-            "json" => "JSON files",
-            "html" | "htm" => "HTML files",
-            "txt" => "Text files",
-            "csv" => "CSV files",
-            "tid" => "Tiddler files",
-            _ => "All files",
-        },
-    };
+    let config = wiki_path
+        .as_deref()
+        .map(|p| downloads::load_config(&app, p))
+        .unwrap_or_default();
 
-    let extensions: &[&str] = match extension {
-        "" => &["*"],
-        ext => &[ext],
+    // CSV exports with another delimiter than the comma (from the message, else the wiki's setting)
+    let delimiter = csv_delimiter
+        .as_deref()
+        .or(config.csv_delimiter.as_deref())
+        .and_then(downloads::parse_delimiter);
+    let content = match delimiter {
+        Some(d) if downloads::is_csv(&filename, content_type.as_deref()) => downloads::redelimit_csv(&content, d),
+        _ => content,
     };
 
+    // Saved without a dialog in the wiki's auto-download directory
+    #[cfg(not(target_os = "android"))]
+    if let Some(ref dir) = config.auto_dir {
+        let path = downloads::unique_path(std::path::Path::new(dir), &filename);
+        tokio::fs::write(&path, &content)
+            .await
+            .map_err(|e| format!("Failed to write file: {}", e))?;
+        eprintln!("[download_file] Saved to auto-download directory: {}", path.display());
+        return Ok(path.to_string_lossy().into_owned());
+    }
+
+    let (filter_name, extensions) = downloads::file_filter(&filename, content_type.as_deref());
+    let extensions: Vec<&str> = extensions.iter().map(String::as_str).collect();
+
     // Show save dialog (async variant to avoid blocking the tokio runtime,
     // which can deadlock on Linux/WebKitGTK where dialogs need the GTK main loop).
     // Parent window is set so the dialog appears correctly on desktop platforms.
@@ -1877,10 +2069,16 @@ async fn download_file(
     let mut builder = app.dialog()
         .file()
         .set_file_name(&filename)
-        .add_filter(filter_name, extensions);
+        .add_filter(filter_name, &extensions);
     #[cfg(not(target_os = "android"))]
     {
         builder = builder.set_parent(&window);
+        // Start where the wiki's last download went
+        if let Some(ref dir) = config.last_dir {
+            if std::path::Path::new(dir).is_dir() {
+                builder = builder.set_directory(dir);
+            }
+        }
     }
     builder.save_file(move |file_path| {
         let _ = tx.send(file_path);
@@ -1898,17 +2096,29 @@ async fn download_file(
                 .await
                 .map_err(|e| format!("Failed to write file: {}", e))?;
             eprintln!("[download_file] File written successfully: {}", path_str);
+            #[cfg(not(target_os = "android"))]
+            if let Some(ref wiki_path) = wiki_path {
+                downloads::remember_dir(&app, wiki_path, &path_str);
+            }
             Ok(path_str)
         }
         None => Err("Save cancelled".to_string()),
     }
 }
 
+/// Plugin library fetches deferred on a metered connection
+#[cfg(not(target_os = "android"))]
+const METERED_LIBRARY_ERROR: &str =
+    "The connection is metered. Plugin library downloads can be allowed on metered connections in the TiddlyDesktop settings.";
+
 /// Fetch a URL via Rust (bypasses CORS restrictions for wikifile:// pages).
 /// Simple GET-only version used by plugin library loading.
 #[cfg(not(target_os = "android"))]
 #[tauri::command]
-async fn fetch_url(url: String) -> Result<String, String> {
+async fn fetch_url(app: tauri::AppHandle, url: String) -> Result<String, String> {
+    if metered::defers(&app, metered::Feature::PluginLibrary).await {
+        return Err(METERED_LIBRARY_ERROR.to_string());
+    }
     let resp = reqwest::get(&url)
         .await
         .map_err(|e| format!("Fetch failed: {}", e))?;
@@ -1927,7 +2137,10 @@ async fn fetch_url(url: String) -> Result<String, String> {
 /// The title must be double-URI-encoded (the library stores files that way).
 #[cfg(not(target_os = "android"))]
 #[tauri::command]
-async fn fetch_library_plugin(url: String, title: String) -> Result<String, String> {
+async fn fetch_library_plugin(app: tauri::AppHandle, url: String, title: String) -> Result<String, String> {
+    if metered::defers(&app, metered::Feature::PluginLibrary).await {
+        return Err(METERED_LIBRARY_ERROR.to_string());
+    }
     // Construct the plugin JSON URL from the library URL
     // Library URL: https://tiddlywiki.com/library/v5.3.8/index.html
     // Plugin URL:  https://tiddlywiki.com/library/v5.3.8/recipes/library/tiddlers/{double-encoded}.json
@@ -2345,21 +2558,47 @@ const FIND_BAR_JS: &str = r#"
         return fallback;
     }
 
-    // Get palette colors
-    var pageBackground = getColour('page-background', '#f0f0f0');
-    var background = getColour('background', '#ffffff');
-    var foreground = getColour('foreground', '#333333');
-    var tabBorder = getColour('tab-border', '#cccccc');
-    var tabBackground = getColour('tab-background', '#eeeeee');
-    var mutedForeground = getColour('muted-foreground', '#666666');
-    var primary = getColour('primary', '#5778d8');
+    // Check a keydown event against the wiki's accelerator map (falls back to built-in keys)
+    function matches(action, e, fallback) {
+        if (window.TiddlyDesktop && typeof window.TiddlyDesktop.matchesAccelerator === 'function') {
+            return window.TiddlyDesktop.matchesAccelerator(action, e);
+        }
+        return fallback(e);
+    }
+
+    function describe(action, fallback) {
+        if (window.TiddlyDesktop && typeof window.TiddlyDesktop.describeAccelerator === 'function') {
+            return window.TiddlyDesktop.describeAccelerator(action) || fallback;
+        }
+        return fallback;
+    }
+
+    // OS accessibility preferences (set by the init script's accessibility module)
+    var a11y = window.__TD_ACCESSIBILITY__ || {};
+    var highContrast = !!a11y.highContrast;
+    var scrollBehavior = a11y.reducedMotion ? 'auto' : 'smooth';
+
+    // Get palette colors - high contrast mode uses CSS system colours instead,
+    // so the bar follows the OS contrast theme rather than the wiki palette
+    var pageBackground = highContrast ? 'Canvas' : getColour('page-background', '#f0f0f0');
+    var background = highContrast ? 'Field' : getColour('background', '#ffffff');
+    var foreground = highContrast ? 'FieldText' : getColour('foreground', '#333333');
+    var tabBorder = highContrast ? 'CanvasText' : getColour('tab-border', '#cccccc');
+    var tabBackground = highContrast ? 'ButtonFace' : getColour('tab-background', '#eeeeee');
+    var mutedForeground = highContrast ? 'CanvasText' : getColour('muted-foreground', '#666666');
+    var primary = highContrast ? 'Highlight' : getColour('primary', '#5778d8');
 
     // Add highlight styles if not present
     if (!document.getElementById('td-find-styles')) {
         var style = document.createElement('style');
         style.id = 'td-find-styles';
-        style.textContent = '.' + HIGHLIGHT_CLASS + '{background:#ffeb3b;color:#000;border-radius:2px;}' +
-            '.' + CURRENT_CLASS + '{background:#ff9800;color:#000;box-shadow:0 0 0 2px #ff9800;}';
+        style.textContent = highContrast
+            ? '.' + HIGHLIGHT_CLASS + '{background:Mark;color:MarkText;outline:1px solid MarkText;}' +
+              '.' + CURRENT_CLASS + '{background:Highlight;color:HighlightText;outline:2px solid HighlightText;}' +
+              '#td-find-bar button{background:ButtonFace !important;color:ButtonText !important;border:1px solid ButtonText !important;}' +
+              '#td-find-bar :focus-visible{outline:2px solid Highlight !important;}'
+            : '.' + HIGHLIGHT_CLASS + '{background:#ffeb3b;color:#000;border-radius:2px;}' +
+              '.' + CURRENT_CLASS + '{background:#ff9800;color:#000;box-shadow:0 0 0 2px #ff9800;}';
         document.head.appendChild(style);
     }
 
@@ -2383,7 +2622,7 @@ const FIND_BAR_JS: &str = r#"
     var input = document.createElement('input');
     input.type = 'text';
     input.placeholder = 'Find in page...';
-    input.style.cssText = 'flex:1;max-width:300px;padding:6px 10px;border:1px solid ' + tabBorder + ';border-radius:4px;font-size:14px;outline:none;background:' + background + ';color:' + foreground + ';';
+    input.style.cssText = 'flex:1;max-width:300px;padding:6px 10px;border:1px solid ' + tabBorder + ';border-radius:4px;font-size:14px;' + (highContrast ? '' : 'outline:none;') + 'background:' + background + ';color:' + foreground + ';';
 
     var info = document.createElement('span');
     info.style.cssText = 'color:' + mutedForeground + ';min-width:100px;text-align:center;';
@@ -2391,17 +2630,17 @@ const FIND_BAR_JS: &str = r#"
 
     var prevBtn = document.createElement('button');
     prevBtn.textContent = '▲';
-    prevBtn.title = 'Previous (Shift+F3, Shift+Enter, Ctrl/Cmd+Shift+G)';
+    prevBtn.title = 'Previous (' + describe('find-previous', 'Shift+F3, Ctrl/Cmd+Shift+G') + ', Shift+Enter)';
     prevBtn.style.cssText = 'padding:4px 10px;border:1px solid ' + tabBorder + ';border-radius:4px;background:' + background + ';color:' + foreground + ';cursor:pointer;font-size:12px;';
 
     var nextBtn = document.createElement('button');
     nextBtn.textContent = '▼';
-    nextBtn.title = 'Next (F3, Enter, Ctrl/Cmd+G)';
+    nextBtn.title = 'Next (' + describe('find-next', 'F3, Ctrl/Cmd+G') + ', Enter)';
     nextBtn.style.cssText = 'padding:4px 10px;border:1px solid ' + tabBorder + ';border-radius:4px;background:' + background + ';color:' + foreground + ';cursor:pointer;font-size:12px;';
 
     var closeBtn = document.createElement('button');
     closeBtn.textContent = '✕';
-    closeBtn.title = 'Close (Escape)';
+    closeBtn.title = 'Close (' + describe('find-close', 'Escape') + ')';
     closeBtn.style.cssText = 'padding:4px 10px;border:none;background:transparent;cursor:pointer;font-size:16px;color:' + mutedForeground + ';';
 
     bar.appendChild(input);
@@ -2507,7 +2746,7 @@ const FIND_BAR_JS: &str = r#"
         highlights.forEach(function(span, i) {
             if (i === currentIndex) {
                 span.classList.add(CURRENT_CLASS);
-                span.scrollIntoView({ behavior: 'smooth', block: 'center' });
+                span.scrollIntoView({ behavior: scrollBehavior, block: 'center' });
             } else {
                 span.classList.remove(CURRENT_CLASS);
             }
@@ -2536,21 +2775,32 @@ const FIND_BAR_JS: &str = r#"
         document.removeEventListener('keydown', globalKeyHandler, true);
     }
 
+    function isFindNext(e) {
+        return matches('find-next', e, function(e) {
+            return !e.shiftKey && (e.key === 'F3' || ((e.key === 'g' || e.key === 'G') && (e.ctrlKey || e.metaKey)));
+        });
+    }
+
+    function isFindPrevious(e) {
+        return matches('find-previous', e, function(e) {
+            return e.shiftKey && (e.key === 'F3' || ((e.key === 'g' || e.key === 'G') && (e.ctrlKey || e.metaKey)));
+        });
+    }
+
+    function isFindClose(e) {
+        return matches('find-close', e, function(e) { return e.key === 'Escape'; });
+    }
+
     function globalKeyHandler(e) {
         if (bar.style.display === 'none') return;
+        if (e.isComposing || e.keyCode === 229) return;
 
-        if (e.key === 'F3') {
+        if (isFindNext(e) || isFindPrevious(e)) {
             e.preventDefault();
             e.stopPropagation();
-            goToMatch(e.shiftKey ? -1 : 1);
+            goToMatch(isFindPrevious(e) ? -1 : 1);
             input.focus();
-        } else if ((e.key === 'g' || e.key === 'G') && (e.ctrlKey || e.metaKey)) {
-            // Ctrl+G / Cmd+G - Find next, Ctrl+Shift+G / Cmd+Shift+G - Find previous
-            e.preventDefault();
-            e.stopPropagation();
-            goToMatch(e.shiftKey ? -1 : 1);
-            input.focus();
-        } else if (e.key === 'Escape') {
+        } else if (isFindClose(e)) {
             e.preventDefault();
             e.stopPropagation();
             closeBar();
@@ -2559,20 +2809,37 @@ const FIND_BAR_JS: &str = r#"
 
     document.addEventListener('keydown', globalKeyHandler, true);
 
-    input.addEventListener('input', function() {
+    // IME composition (CJK input, dead keys): don't search on intermediate
+    // input - highlighting rewrites the DOM and would break the composition
+    var composing = false;
+
+    input.addEventListener('compositionstart', function() {
+        composing = true;
+    });
+
+    input.addEventListener('compositionend', function() {
+        composing = false;
+        if (searchTimeout) clearTimeout(searchTimeout);
+        searchTimeout = setTimeout(doSearch, 200);
+    });
+
+    input.addEventListener('input', function(e) {
+        if (composing || e.isComposing) return;
         if (searchTimeout) clearTimeout(searchTimeout);
         searchTimeout = setTimeout(doSearch, 200);
     });
 
     input.addEventListener('keydown', function(e) {
-        if (e.key === 'Enter' || e.key === 'F3') {
+        // Enter/Escape confirm or cancel the composition, not the find bar
+        if (composing || e.isComposing || e.keyCode === 229) return;
+        if (e.key === 'Enter' || isFindNext(e) || isFindPrevious(e)) {
             e.preventDefault();
             if (searchTimeout) {
                 clearTimeout(searchTimeout);
                 doSearch();
             }
-            goToMatch(e.shiftKey ? -1 : 1);
-        } else if (e.key === 'Escape') {
+            goToMatch((e.key === 'Enter' ? e.shiftKey : isFindPrevious(e)) ? -1 : 1);
+        } else if (isFindClose(e)) {
             e.preventDefault();
             closeBar();
         }
@@ -3164,11 +3431,48 @@ fn wait_for_server_ready(port: u16, process: &mut Child, timeout: std::time::Dur
     }
 }
 
+/// Start the Node.js TiddlyWiki server of a wiki folder and wait until it's ready
+#[cfg(not(target_os = "android"))]
+fn start_folder_node_server(node_path: &std::path::Path, tw_path: &std::path::Path, folder_path: &std::path::Path, port: u16, host: std::net::IpAddr) -> Result<Child, String> {
+    let mut cmd = Command::new(node_path);
+    cmd.arg(tw_path);
+    // A plugin in development, straight from its source folder (plugin_dev.rs)
+    if let Some(dir) = plugin_dev::loadable_dir() {
+        let mut arg = std::ffi::OsString::from("++");
+        arg.push(dir);
+        cmd.arg(arg);
+    }
+    cmd.arg(folder_path)
+        .arg("--listen")
+        .arg(format!("port={}", port))
+        .arg(format!("host={}", host));
+
+    #[cfg(target_os = "windows")]
+    cmd.creation_flags(CREATE_NO_WINDOW);
+
+    let mut server_process = cmd.spawn()
+        .map_err(|e| format!("Failed to start TiddlyWiki server: {}", e))?;
+
+    // Windows: Assign to job object so it gets killed when parent exits
+    #[cfg(target_os = "windows")]
+    drag_drop::windows_job::assign_process_to_job(server_process.id());
+
+    // Wait for server to be ready
+    if let Err(e) = wait_for_server_ready(port, &mut server_process, std::time::Duration::from_secs(15)) {
+        let _ = server_process.kill();
+        return Err(format!("Server failed to start: {}", e));
+    }
+    Ok(server_process)
+}
+
 /// Open a wiki folder in a separate process with its own server
 /// Returns WikiEntry so frontend can update its wiki list
 #[cfg(not(target_os = "android"))]
 #[tauri::command]
 async fn open_wiki_folder(app: tauri::AppHandle, path: String, _tiddler_title: Option<String>) -> Result<WikiEntry, String> {
+    // Report an unplugged drive instead of a missing folder
+    removable_media::ensure_volume_present(&path)?;
+
     // Security: Validate path is a user-accessible directory
     let path_buf = drag_drop::sanitize::validate_user_directory_path(&path)?;
     let state = app.state::<AppState>();
@@ -3213,6 +3517,15 @@ async fn open_wiki_folder(app: tauri::AppHandle, path: String, _tiddler_title: O
                 sync_peers: vec![],
         relay_room: None,
         sync_mode: None,
+        custom_icon: None,
+        accent_color: None,
+        emoji: None,
+        scheduled_backup: None,
+        confirm_remote_deletions: false,
+        sync_schedule: None,
+        hotkey: None,
+        sync_priority: None,
+        missing: false,
             });
         }
     }
@@ -3220,6 +3533,38 @@ async fn open_wiki_folder(app: tauri::AppHandle, path: String, _tiddler_title: O
     // Extract favicon from the wiki folder
     let favicon = tiddlywiki_html::extract_favicon_from_folder(&path_buf).await;
 
+    // Served to the system browser instead of a wiki window
+    if browser_mode::is_enabled(&app, &path) {
+        browser_mode::open(&app, &path, true)?;
+        let entry = WikiEntry {
+            path: path.clone(),
+            filename: folder_name,
+            display_path: Some(path),
+            favicon,
+            is_folder: true,
+            backups_enabled: false,
+            backup_dir: None,
+            backup_count: None,
+            group: None,
+            sync_enabled: false,
+            sync_id: None,
+            sync_peers: vec![],
+            relay_room: None,
+            sync_mode: None,
+            custom_icon: None,
+            accent_color: None,
+            emoji: None,
+            scheduled_backup: None,
+            confirm_remote_deletions: false,
+            sync_schedule: None,
+            hotkey: None,
+            sync_priority: None,
+            missing: false,
+        };
+        let _ = wiki_storage::add_to_recent_files(&app, entry.clone());
+        return Ok(entry);
+    }
+
     // Allocate a port for this server
     let port = allocate_port(&state);
 
@@ -3234,6 +3579,13 @@ async fn open_wiki_folder(app: tauri::AppHandle, path: String, _tiddler_title: O
     let mut cmd = Command::new(&exe_path);
     cmd.arg("--wiki-folder").arg(&path)
        .arg("--port").arg(port.to_string());
+    let settings = wiki_storage::load_app_settings(&app).unwrap_or_default();
+    if settings.native_folder_server {
+        cmd.arg("--native-server");
+    }
+    if settings.folder_webdav {
+        cmd.arg("--webdav");
+    }
 
     // Pass IPC auth token to child process via environment variable
     if let Some(token) = ipc::get_auth_token() {
@@ -3249,6 +3601,22 @@ async fn open_wiki_folder(app: tauri::AppHandle, path: String, _tiddler_title: O
         }
     }
 
+    // Developer tools and remote debugging port, if turned on for the wiki
+    #[cfg(not(target_os = "android"))]
+    devtools::configure_process(&mut cmd, &path);
+
+    // Safe mode, if the wiki is being opened in it
+    #[cfg(not(target_os = "android"))]
+    safe_mode::configure_process(&mut cmd, &path);
+
+    // The plugin folder, if the wiki is being opened for plugin development
+    #[cfg(not(target_os = "android"))]
+    plugin_dev::configure_process(&mut cmd, &path);
+
+    // Environment variables set for the wiki (read by its server plugins)
+    #[cfg(not(target_os = "android"))]
+    wiki_env::configure_process(&app, &mut cmd, &path);
+
     // Set TIDDLYWIKI_PLUGIN_PATH so Node.js can find user-installed plugins from {app_data}/plugins/.
     // Bundled plugins live in tiddlywiki/plugins/ and are found automatically by TiddlyWiki.
     if let Ok(data_dir) = get_data_dir(&app) {
@@ -3304,6 +3672,7 @@ async fn open_wiki_folder(app: tauri::AppHandle, path: String, _tiddler_title: O
         pid,
         path: path.clone(),
     });
+    session_restore::update(&app);
 
     // Spawn a thread to wait for the process to exit and clean up
     let app_handle = app.clone();
@@ -3315,14 +3684,17 @@ async fn open_wiki_folder(app: tauri::AppHandle, path: String, _tiddler_title: O
         // Clean up tracking
         let state = app_handle.state::<AppState>();
         state.wiki_processes.lock().unwrap().remove(&path_clone);
+        session_restore::update(&app_handle);
 
         // Notify landing page that a wiki was closed
+        recently_closed::record(&app_handle, &path_clone);
         let _ = app_handle.emit("wiki-process-closed", &path_clone);
 
         // Exit app if no more wikis and no windows
         let wiki_count = state.wiki_processes.lock().unwrap().len();
         let has_windows = app_handle.webview_windows().len() > 0;
-        if wiki_count == 0 && !has_windows {
+        // (a wiki being restarted over its memory limit is about to reopen)
+        if wiki_count == 0 && !has_windows && !browser_mode::has_sessions() && !serve_all::is_active() && !memory_limit::restart_pending() {
             eprintln!("[TiddlyDesktop] No more wikis or windows, exiting");
             app_handle.exit(0);
         }
@@ -3344,6 +3716,15 @@ async fn open_wiki_folder(app: tauri::AppHandle, path: String, _tiddler_title: O
         sync_peers: vec![],
         relay_room: None,
         sync_mode: None,
+        custom_icon: None,
+        accent_color: None,
+        emoji: None,
+        scheduled_backup: None,
+        confirm_remote_deletions: false,
+        sync_schedule: None,
+        hotkey: None,
+        sync_priority: None,
+        missing: false,
     };
 
     // Add to recent files list
@@ -3458,6 +3839,15 @@ fn open_wiki_folder_blocking(app: tauri::AppHandle, path: String) -> Result<Wiki
         sync_peers: vec![],
         relay_room: None,
         sync_mode: None,
+        custom_icon: None,
+        accent_color: None,
+        emoji: None,
+        scheduled_backup: None,
+        confirm_remote_deletions: false,
+        sync_schedule: None,
+        hotkey: None,
+        sync_priority: None,
+        missing: false,
         is_folder: true,
     };
 
@@ -3992,9 +4382,7 @@ async fn get_wiki_installed_plugins(app: tauri::AppHandle, path: String, is_fold
         Ok(installed)
     } else {
         // For single-file wikis: use Node.js to extract plugin list
-        let temp_dir = std::env::temp_dir().join(format!("tiddlydesktop-plugins-{}", std::process::id()));
-        std::fs::create_dir_all(&temp_dir)
-            .map_err(|e| format!("Failed to create temp directory: {}", e))?;
+        let temp_dir = scratch_dir::create_build_dir(&app, "plugins")?;
         // Create empty tiddlers dir
         let _ = std::fs::create_dir_all(temp_dir.join("tiddlers"));
         // Write minimal tiddlywiki.info
@@ -4140,9 +4528,7 @@ async fn install_plugins_to_wiki(app: tauri::AppHandle, path: String, is_folder:
     } else {
         // For single-file wikis: rebuild with new plugin set via Node.js
         // Create temp directory for the rebuild
-        let temp_dir = std::env::temp_dir().join(format!("tiddlydesktop-rebuild-{}", std::process::id()));
-        std::fs::create_dir_all(&temp_dir)
-            .map_err(|e| format!("Failed to create temp directory: {}", e))?;
+        let temp_dir = scratch_dir::create_build_dir(&app, "rebuild")?;
         let _ = std::fs::create_dir_all(temp_dir.join("tiddlers"));
 
         // Write tiddlywiki.info with the desired plugins
@@ -4621,6 +5007,15 @@ async fn init_wiki_folder(app: tauri::AppHandle, path: String, edition: String,
         sync_peers: vec![],
         relay_room: None,
         sync_mode: None,
+        custom_icon: None,
+        accent_color: None,
+        emoji: None,
+        scheduled_backup: None,
+        confirm_remote_deletions: false,
+        sync_schedule: None,
+        hotkey: None,
+        sync_priority: None,
+        missing: false,
         is_folder: true,
     };
 
@@ -4767,9 +5162,7 @@ async fn create_wiki_file(app: tauri::AppHandle, path: String, edition: String,
     let tw_dir = tw_path.parent().ok_or("Failed to get TiddlyWiki directory")?;
 
     // Create a temporary directory for the build
-    let temp_dir = std::env::temp_dir().join(format!("tiddlydesktop-build-{}", std::process::id()));
-    std::fs::create_dir_all(&temp_dir)
-        .map_err(|e| format!("Failed to create temp directory: {}", e))?;
+    let temp_dir = scratch_dir::create_build_dir(&app, "build")?;
 
     println!("  Temp dir: {:?}", temp_dir);
 
@@ -5029,10 +5422,11 @@ fn add_server_plugins_to_tiddlywiki_info(info_path: &std::path::Path) -> Result<
     Ok(())
 }
 
-/// Convert a wiki between single-file and folder formats
+/// Convert a wiki between single-file and folder formats.
+/// With `dry_run`, only the pre-flight report is returned and nothing is written.
 #[cfg(not(target_os = "android"))]
 #[tauri::command]
-async fn convert_wiki(app: tauri::AppHandle, source_path: String, dest_path: String, to_folder: bool) -> Result<(), String> {
+async fn convert_wiki(app: tauri::AppHandle, source_path: String, dest_path: String, to_folder: bool, dry_run: Option<bool>) -> Result<wiki_conversion::ConversionReport, String> {
     // Security: Validate source path
     if drag_drop::sanitize::validate_file_path(&source_path).is_none() {
         return Err("Invalid source path".to_string());
@@ -5061,135 +5455,10 @@ async fn convert_wiki(app: tauri::AppHandle, source_path: String, dest_path: Str
         return Err("Source wiki does not exist".to_string());
     }
 
-    let node_path = get_node_path(&app)?;
-    let tw_path = get_tiddlywiki_path(&app)?;
-
-    if to_folder {
-        // Convert single-file to folder: tiddlywiki --load <file> --savewikifolder <folder>
-        println!("Converting single-file wiki to folder:");
-        println!("  Source: {:?}", source);
-        println!("  Destination: {:?}", dest);
-
-        // Create destination folder
-        std::fs::create_dir_all(&dest)
-            .map_err(|e| format!("Failed to create destination folder: {}", e))?;
-
-        let mut cmd = Command::new(&node_path);
-        cmd.arg(&tw_path)
-            .arg("--load")
-            .arg(&source)
-            // Strip TiddlyDesktop-injected tiddlers that shouldn't be in standalone wikis
-            .arg("--deletetiddlers")
-            .arg("[prefix[$:/plugins/tiddlywiki/tiddlydesktop-rs]]")
-            .arg("--deletetiddlers")
-            .arg("[prefix[$:/plugins/tiddlydesktop-rs]]")
-            .arg("--deletetiddlers")
-            .arg("[prefix[$:/temp/tiddlydesktop]]")
-            .arg("--savewikifolder")
-            .arg(&dest);
-        #[cfg(target_os = "windows")]
-        cmd.creation_flags(CREATE_NO_WINDOW);
-
-        let output = cmd.output()
-            .map_err(|e| format!("Failed to run conversion: {}", e))?;
-
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            let stdout = String::from_utf8_lossy(&output.stdout);
-            return Err(format!("Conversion failed:\n{}\n{}", stdout, stderr));
-        }
-
-        // Verify tiddlywiki.info was created
-        let info_path = dest.join("tiddlywiki.info");
-        if !info_path.exists() {
-            return Err("Conversion failed - tiddlywiki.info not created".to_string());
-        }
-
-        // Add tiddlyweb and filesystem plugins to tiddlywiki.info for proper folder wiki operation
-        if let Err(e) = add_server_plugins_to_tiddlywiki_info(&info_path) {
-            println!("Warning: Failed to add server plugins to tiddlywiki.info: {}", e);
-        }
-
-        println!("Successfully converted to folder wiki: {:?}", dest);
-    } else {
-        // Convert folder to single-file: tiddlywiki <folder> --render '$:/core/save/all' 'output.html' 'text/plain'
-        println!("Converting folder wiki to single-file:");
-        println!("  Source: {:?}", source);
-        println!("  Destination: {:?}", dest);
-
-        // Ensure destination has .html extension
-        let dest = if dest.extension().map(|e| e == "html" || e == "htm").unwrap_or(false) {
-            dest
-        } else {
-            dest.with_extension("html")
-        };
-
-        let output_filename = dest.file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("wiki.html");
-
-        // Create a temp output directory
-        let temp_output = std::env::temp_dir().join(format!("tiddlydesktop-convert-{}", std::process::id()));
-        std::fs::create_dir_all(&temp_output)
-            .map_err(|e| format!("Failed to create temp directory: {}", e))?;
-
-        let mut cmd = Command::new(&node_path);
-        cmd.arg(&tw_path)
-            .arg(&source)
-            // Remove server-only plugins that don't work in single-file wikis
-            .arg("--deletetiddlers")
-            .arg("$:/plugins/tiddlywiki/tiddlyweb")
-            .arg("--deletetiddlers")
-            .arg("$:/plugins/tiddlywiki/filesystem")
-            // Strip TiddlyDesktop-injected tiddlers that shouldn't be in standalone wikis
-            .arg("--deletetiddlers")
-            .arg("[prefix[$:/plugins/tiddlywiki/tiddlydesktop-rs]]")
-            .arg("--deletetiddlers")
-            .arg("[prefix[$:/plugins/tiddlydesktop-rs]]")
-            .arg("--deletetiddlers")
-            .arg("[prefix[$:/temp/tiddlydesktop]]")
-            .arg("--output")
-            .arg(&temp_output)
-            .arg("--render")
-            .arg("$:/core/save/all")
-            .arg(output_filename)
-            .arg("text/plain");
-        #[cfg(target_os = "windows")]
-        cmd.creation_flags(CREATE_NO_WINDOW);
-
-        let output = cmd.output()
-            .map_err(|e| format!("Failed to run conversion: {}", e))?;
-
-        if !output.status.success() {
-            let _ = std::fs::remove_dir_all(&temp_output);
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            let stdout = String::from_utf8_lossy(&output.stdout);
-            return Err(format!("Conversion failed:\n{}\n{}", stdout, stderr));
-        }
-
-        // Move the output file to the destination
-        let built_file = temp_output.join(output_filename);
-        if !built_file.exists() {
-            let _ = std::fs::remove_dir_all(&temp_output);
-            return Err("Conversion succeeded but output file not found".to_string());
-        }
-
-        // Ensure parent directory exists
-        if let Some(parent) = dest.parent() {
-            std::fs::create_dir_all(parent)
-                .map_err(|e| format!("Failed to create output directory: {}", e))?;
-        }
-
-        std::fs::copy(&built_file, &dest)
-            .map_err(|e| format!("Failed to copy wiki to destination: {}", e))?;
-
-        // Clean up
-        let _ = std::fs::remove_dir_all(&temp_output);
-
-        println!("Successfully converted to single-file wiki: {:?}", dest);
-    }
-
-    Ok(())
+    let dry_run = dry_run.unwrap_or(false);
+    tokio::task::spawn_blocking(move || wiki_conversion::convert(&app, &source, &dest, to_folder, dry_run))
+        .await
+        .map_err(|e| format!("Failed to spawn blocking task: {}", e))?
 }
 
 /// Android implementation of convert_wiki using Node.js via SAF
@@ -5793,6 +6062,9 @@ async fn open_wiki_window(
     _backup_count: Option<u32>,
     _tiddler_title: Option<String>,
 ) -> Result<WikiEntry, String> {
+    // Report an unplugged drive instead of a missing file
+    removable_media::ensure_volume_present(&path)?;
+
     // Security: Validate path is a user-accessible wiki file
     let path_buf = drag_drop::sanitize::validate_user_file_path(&path)?;
 
@@ -5836,19 +6108,60 @@ async fn open_wiki_window(
                 sync_peers: vec![],
         relay_room: None,
         sync_mode: None,
+        custom_icon: None,
+        accent_color: None,
+        emoji: None,
+        scheduled_backup: None,
+        confirm_remote_deletions: false,
+        sync_schedule: None,
+        hotkey: None,
+        sync_priority: None,
+        missing: false,
             });
         }
     }
 
     // Extract favicon - first try <head> link, then fall back to $:/favicon.ico tiddler
     let favicon = {
-        if let Ok(content) = tokio::fs::read_to_string(&path_buf).await {
-            tiddlywiki_html::extract_favicon(&content)
-        } else {
-            None
-        }
+        let path_buf = path_buf.clone();
+        let app = app.clone();
+        tokio::task::spawn_blocking(move || favicon_cache::favicon_for_file(&app, &path_buf))
+            .await
+            .unwrap_or(None)
     };
 
+    // Served to the system browser instead of a wiki window
+    if browser_mode::is_enabled(&app, &path) {
+        browser_mode::open(&app, &path, false)?;
+        let entry = WikiEntry {
+            path: path.clone(),
+            filename,
+            display_path: Some(path),
+            favicon,
+            is_folder: false,
+            backups_enabled: true,
+            backup_dir: None,
+            backup_count: None,
+            group: None,
+            sync_enabled: false,
+            sync_id: None,
+            sync_peers: vec![],
+            relay_room: None,
+            sync_mode: None,
+            custom_icon: None,
+            accent_color: None,
+            emoji: None,
+            scheduled_backup: None,
+            confirm_remote_deletions: false,
+            sync_schedule: None,
+            hotkey: None,
+            sync_priority: None,
+            missing: false,
+        };
+        let _ = wiki_storage::add_to_recent_files(&app, entry.clone());
+        return Ok(entry);
+    }
+
     // Get the path to our own executable
     let exe_path = std::env::current_exe()
         .map_err(|e| format!("Failed to get executable path: {}", e))?;
@@ -5873,6 +6186,14 @@ async fn open_wiki_window(
         }
     }
 
+    // Developer tools and remote debugging port, if turned on for the wiki
+    #[cfg(not(target_os = "android"))]
+    devtools::configure_process(&mut cmd, &path);
+
+    // Safe mode, if the wiki is being opened in it
+    #[cfg(not(target_os = "android"))]
+    safe_mode::configure_process(&mut cmd, &path);
+
     // Platform-specific process configuration
     #[cfg(target_os = "windows")]
     {
@@ -5897,6 +6218,7 @@ async fn open_wiki_window(
         pid,
         path: path.clone(),
     });
+    session_restore::update(&app);
 
     // Spawn a thread to wait for the process to exit and clean up
     let app_handle = app.clone();
@@ -5915,15 +6237,18 @@ async fn open_wiki_window(
         // Clean up tracking
         let state = app_handle.state::<AppState>();
         state.wiki_processes.lock().unwrap().remove(&path_clone);
+        session_restore::update(&app_handle);
         eprintln!("[TiddlyDesktop] Removed wiki process from tracking: {}", path_clone);
 
         // Notify landing page that a wiki was closed
+        recently_closed::record(&app_handle, &path_clone);
         let _ = app_handle.emit("wiki-process-closed", &path_clone);
 
         // Exit app if no more wikis and no windows
         let wiki_count = state.wiki_processes.lock().unwrap().len();
         let has_windows = app_handle.webview_windows().len() > 0;
-        if wiki_count == 0 && !has_windows {
+        // (a wiki being restarted over its memory limit is about to reopen)
+        if wiki_count == 0 && !has_windows && !browser_mode::has_sessions() && !serve_all::is_active() && !memory_limit::restart_pending() {
             eprintln!("[TiddlyDesktop] No more wikis or windows, exiting");
             app_handle.exit(0);
         }
@@ -5958,6 +6283,15 @@ async fn open_wiki_window(
         sync_peers: vec![],
         relay_room: None,
         sync_mode: None,
+        custom_icon: None,
+        accent_color: None,
+        emoji: None,
+        scheduled_backup: None,
+        confirm_remote_deletions: false,
+        sync_schedule: None,
+        hotkey: None,
+        sync_priority: None,
+        missing: false,
     };
 
     // Add to recent files list
@@ -6019,10 +6353,7 @@ fn open_wiki_window_blocking(
             Err(_) => None,
         }
     } else {
-        match std::fs::read_to_string(&path) {
-            Ok(content) => tiddlywiki_html::extract_favicon(&content),
-            Err(_) => None,
-        }
+        tiddlywiki_html::extract_favicon_from_file(std::path::Path::new(&path))
     };
 
     eprintln!("[TiddlyDesktop] Opening single-file wiki: {}", filename);
@@ -6075,6 +6406,15 @@ fn open_wiki_window_blocking(
         sync_peers: vec![],
         relay_room: None,
         sync_mode: None,
+        custom_icon: None,
+        accent_color: None,
+        emoji: None,
+        scheduled_backup: None,
+        confirm_remote_deletions: false,
+        sync_schedule: None,
+        hotkey: None,
+        sync_priority: None,
+        missing: false,
     };
 
     // Add to recent files
@@ -6200,7 +6540,7 @@ async fn open_tiddler_window(
         .initialization_script(&init_script::get_wiki_init_script(&wiki_path, &label, false))
         .on_document_title_changed(|ww, title| { let _ = ww.set_title(&title); })
         .zoom_hotkeys_enabled(true)
-        .devtools(cfg!(debug_assertions)); // Only enable in debug builds
+        .devtools(devtools::enabled()); // Debug builds, or remote debugging on (devtools.rs)
 
     #[cfg(target_os = "linux")]
     let mut builder = builder.user_agent(LINUX_USER_AGENT);
@@ -6215,7 +6555,7 @@ async fn open_tiddler_window(
     #[cfg(target_os = "android")]
     let mut builder = WebviewWindowBuilder::new(&app, &label, WebviewUrl::External(wiki_url.parse().unwrap()))
         .initialization_script(&init_script::get_wiki_init_script(&wiki_path, &label, false))
-        .devtools(cfg!(debug_assertions)); // Only enable in debug builds
+        .devtools(devtools::enabled()); // Debug builds, or remote debugging on (devtools.rs)
 
     // Apply isolated session if available (shares with parent wiki)
     if let Some(dir) = session_dir {
@@ -6254,6 +6594,9 @@ async fn open_tiddler_window(
         linux_finalize_window_state(&window, &None);
     }
 
+    // Apply IME configuration (GTK IM module / Windows IMM context)
+    input_method::configure_window(&window);
+
     // Handle window close
     let app_handle = app.clone();
     let label_clone = label.clone();
@@ -6292,6 +6635,10 @@ fn spawn_wiki_process_sync(wiki_path: &str) -> Result<u32, String> {
         }
     }
 
+    // Developer tools and remote debugging port, if turned on for the wiki
+    #[cfg(not(target_os = "android"))]
+    devtools::configure_process(&mut cmd, wiki_path);
+
     // Platform-specific process configuration
     #[cfg(target_os = "windows")]
     {
@@ -6477,6 +6824,30 @@ fn ipc_send_sync_state(
     Ok(())
 }
 
+/// IPC command: Have the main process reopen this wiki, then exit (memory limit)
+#[cfg(not(target_os = "android"))]
+#[tauri::command]
+fn ipc_restart_wiki(app: tauri::AppHandle, state: tauri::State<WikiModeState>) -> Result<(), String> {
+    {
+        let mut client_guard = state.ipc_client.lock().unwrap();
+        let client = client_guard.as_mut().ok_or("Not connected to the main process")?;
+        client.request_restart()
+            .map_err(|e| format!("IPC error: {}", e))?;
+    }
+    app.exit(0);
+    Ok(())
+}
+
+/// IPC command: Have the main process reopen the most recently closed wiki
+#[cfg(not(target_os = "android"))]
+#[tauri::command]
+fn ipc_reopen_closed_wiki(state: tauri::State<WikiModeState>) -> Result<(), String> {
+    let mut client_guard = state.ipc_client.lock().unwrap();
+    let client = client_guard.as_mut().ok_or("Not connected to the main process")?;
+    client.request_reopen_closed_wiki()
+        .map_err(|e| format!("IPC error: {}", e))
+}
+
 /// Response for update check
 #[derive(serde::Serialize)]
 struct UpdateCheckResult {
@@ -6504,8 +6875,12 @@ fn is_wiki_open(#[allow(unused_variables)] app: tauri::AppHandle, path: String)
 /// Check for application updates
 /// On Android: Checks Google Play Store
 /// On Desktop: Checks GitHub releases
+/// Deferred on a metered connection unless allowed (see `metered`)
 #[tauri::command]
-async fn check_for_updates() -> Result<UpdateCheckResult, String> {
+async fn check_for_updates(app: tauri::AppHandle) -> Result<UpdateCheckResult, String> {
+    if metered::defers(&app, metered::Feature::Updates).await {
+        return Err("Deferred: the connection is metered".to_string());
+    }
     #[cfg(target_os = "android")]
     {
         check_for_updates_android().await
@@ -7263,6 +7638,9 @@ fn wiki_protocol_handler(app: &tauri::AppHandle, request: Request<Vec<u8>>) -> R
         };
 
         let content = String::from_utf8_lossy(request.body()).to_string();
+        if !is_saf_uri {
+            incremental_save::recover(&wiki_path);
+        }
 
         // Check if backups should be created for this wiki
         let wiki_path_str = wiki_path.to_string_lossy();
@@ -7291,6 +7669,7 @@ fn wiki_protocol_handler(app: &tauri::AppHandle, request: Request<Vec<u8>>) -> R
                     if let Err(e) = android::saf::create_backup(&decoded, &backup_dir_uri, &filename_stem) {
                         eprintln!("[TiddlyDesktop] Failed to create Android backup: {}", e);
                     } else {
+                        webhooks::dispatch(app, webhooks::WebhookEvent::BackupCompleted, serde_json::json!({ "wikiPath": wiki_path_str }));
                         // Clean up old backups
                         let backup_count = wiki_storage::get_wiki_backup_count(app, wiki_path_str.as_ref()).unwrap_or(20);
                         let _ = android::saf::cleanup_old_backups(&backup_dir_uri, &filename_stem, backup_count as usize);
@@ -7358,7 +7737,12 @@ fn wiki_protocol_handler(app: &tauri::AppHandle, request: Request<Vec<u8>>) -> R
                         let timestamp = Local::now().format("%Y%m%d-%H%M%S");
                         let backup_name = format!("{}.{}.html", filename, timestamp);
                         let backup_path = backup_dir.join(backup_name);
-                        let _ = std::fs::copy(&wiki_path, &backup_path);
+                        if std::fs::copy(&wiki_path, &backup_path).is_ok() {
+                            webhooks::dispatch(app, webhooks::WebhookEvent::BackupCompleted, serde_json::json!({
+                                "wikiPath": wiki_path_str,
+                                "backupPath": backup_path,
+                            }));
+                        }
 
                         // Clean up old backups (synchronous version)
                         let backup_count = wiki_storage::get_wiki_backup_count(app, wiki_path_str.as_ref()).unwrap_or(20);
@@ -7403,9 +7787,22 @@ fn wiki_protocol_handler(app: &tauri::AppHandle, request: Request<Vec<u8>>) -> R
             }
         }
 
-        // Write wiki file (uses fs_abstraction for atomic writes and Android SAF support)
-        match fs_abstraction::write_wiki_file(&wiki_path, &content) {
+        // Write wiki file (uses fs_abstraction for atomic writes and Android SAF support),
+        // or only the changed tiddlers of big wikis if incremental saving is on
+        let saved = if !is_saf_uri && incremental_save::save(app, &wiki_path, content.as_bytes()) {
+            Ok(())
+        } else {
+            fs_abstraction::write_wiki_file(&wiki_path, &content)
+        };
+        match saved {
             Ok(_) => {
+                // The landing page saves itself too, which is no user event
+                if !utils::paths_equal(&wiki_path_str, &state.main_wiki_path.to_string_lossy()) {
+                    webhooks::dispatch(app, webhooks::WebhookEvent::WikiSaved, serde_json::json!({ "wikiPath": wiki_path_str }));
+                    if !is_saf_uri {
+                        favicon_cache::refresh_after_save(app, &wiki_path);
+                    }
+                }
                 return Response::builder()
                     .status(200)
                     .header("Access-Control-Allow-Origin", "*")
@@ -7578,6 +7975,8 @@ fn wiki_protocol_handler(app: &tauri::AppHandle, request: Request<Vec<u8>>) -> R
         })
         .unwrap_or_default();
 
+    incremental_save::recover(&file_path);
+
     // Validate that this is a TiddlyWiki file before loading
     if let Err(e) = validate_tiddlywiki_file(&file_path) {
         eprintln!("[TiddlyDesktop] Refusing to load non-TiddlyWiki file: {} - {}", file_path.display(), e);
@@ -7689,6 +8088,9 @@ window.__SAVE_URL__ = "{save_url}";
 
     var SAVE_URL = "{save_url_inner}";
 
+    // Wikis bigger than this (in characters) are saved in chunks of this size
+    var SAVE_CHUNK_SIZE = 8 * 1024 * 1024;
+
     // Check if this is an encrypted wiki
     function isEncryptedWiki() {{
         return !!document.getElementById('encryptedStoreArea');
@@ -7758,12 +8160,42 @@ window.__SAVE_URL__ = "{save_url}";
                         }}
                     }}
 
+                    // Big wikis are sent in chunks, so the content isn't copied
+                    // into one huge IPC message (see chunked_save.rs)
+                    function saveViaIpc() {{
+                        var invoke = window.__TAURI__.core.invoke;
+                        if(text.length <= SAVE_CHUNK_SIZE) {{
+                            return invoke('save_wiki', {{ path: wikiPath, content: text }});
+                        }}
+                        return invoke('save_wiki_begin', {{ path: wikiPath }}).then(function(id) {{
+                            var offset = 0;
+                            function next() {{
+                                if(offset >= text.length) {{
+                                    return invoke('save_wiki_commit', {{ id: id }});
+                                }}
+                                var end = Math.min(offset + SAVE_CHUNK_SIZE, text.length);
+                                // Don't split a surrogate pair between chunks
+                                var last = text.charCodeAt(end - 1);
+                                if(end < text.length && last >= 0xD800 && last <= 0xDBFF) {{
+                                    end--;
+                                }}
+                                var chunk = text.substring(offset, end);
+                                offset = end;
+                                return invoke('save_wiki_chunk', {{ id: id, chunk: chunk }}).then(next);
+                            }}
+                            return next().catch(function(err) {{
+                                invoke('save_wiki_abort', {{ id: id }}).catch(function() {{}});
+                                throw err;
+                            }});
+                        }}, function() {{
+                            // Documents (Android) can't be saved in chunks
+                            return invoke('save_wiki', {{ path: wikiPath, content: text }});
+                        }});
+                    }}
+
                     // Try Tauri IPC first (works reliably on all platforms)
                     if(window.__TAURI__ && window.__TAURI__.core && window.__TAURI__.core.invoke) {{
-                        var savePromise = window.__TAURI__.core.invoke('save_wiki', {{
-                            path: wikiPath,
-                            content: text
-                        }}).then(function() {{
+                        var savePromise = saveViaIpc().then(function() {{
                             window.__TD_SAVE_PROMISE__ = null;
                             callback(null);
                             chainCloudSavers();
@@ -8050,6 +8482,9 @@ fn reveal_or_create_main_window(app_handle: &tauri::AppHandle) {
                 linux_finalize_window_state(&main_window, &saved_state);
             }
 
+            // Apply IME configuration (GTK IM module / Windows IMM context)
+            input_method::configure_window(&main_window);
+
             // Restore maximized state (Windows/macOS only - Linux handled in linux_finalize_window_state)
             #[cfg(not(target_os = "linux"))]
             if saved_state.as_ref().map(|s| s.maximized).unwrap_or(false) {
@@ -8061,27 +8496,252 @@ fn reveal_or_create_main_window(app_handle: &tauri::AppHandle) {
     }
 }
 
+/// Id of the system tray icon
+#[cfg(not(target_os = "android"))]
+const TRAY_ID: &str = "main";
+
+/// Number of recent wikis in the tray menu
+#[cfg(not(target_os = "android"))]
+const TRAY_RECENT_WIKIS: usize = 10;
+
+/// Tray menu icon of a wiki: its custom icon or favicon (PNG only) scaled
+/// down, or a dot in its accent color
+#[cfg(not(target_os = "android"))]
+fn tray_wiki_icon(entry: &WikiEntry) -> Option<Image<'static>> {
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    const SIZE: u32 = 16;
+
+    let png = entry.custom_icon.as_deref().or(entry.favicon.as_deref())
+        .and_then(|uri| uri.strip_prefix("data:image/png;base64,"))
+        .and_then(|b64| STANDARD.decode(b64).ok())
+        .and_then(|bytes| image::load_from_memory_with_format(&bytes, image::ImageFormat::Png).ok());
+    let rgba = match (png, entry.accent_color.as_deref()) {
+        (Some(img), _) => img.resize_exact(SIZE, SIZE, image::imageops::FilterType::Lanczos3).into_rgba8(),
+        (None, Some(color)) => {
+            let hex = u32::from_str_radix(color.trim_start_matches('#'), 16).ok()?;
+            let [_, r, g, b] = hex.to_be_bytes();
+            let center = (SIZE as f32 - 1.0) / 2.0;
+            image::RgbaImage::from_fn(SIZE, SIZE, |x, y| {
+                let (dx, dy) = (x as f32 - center, y as f32 - center);
+                let inside = dx * dx + dy * dy <= (SIZE as f32 / 2.0 - 2.0).powi(2);
+                image::Rgba([r, g, b, if inside { 255 } else { 0 }])
+            })
+        }
+        (None, None) => return None,
+    };
+    Some(Image::new_owned(rgba.into_raw(), SIZE, SIZE))
+}
+
+#[cfg(not(target_os = "android"))]
+fn build_tray_menu(app: &tauri::AppHandle) -> tauri::Result<tauri::menu::Menu<tauri::Wry>> {
+    use tauri::menu::{CheckMenuItemBuilder, IconMenuItemBuilder, SubmenuBuilder};
+
+    // '&' marks the mnemonic so every item is reachable from the keyboard
+    // (rendered as an underlined access key on Windows/Linux, stripped on macOS)
+    let show_window = MenuItemBuilder::with_id("show_window", "&Show TiddlyDesktop").build(app)?;
+    let reopen_closed = MenuItemBuilder::with_id("reopen_closed_wiki", "&Reopen Closed Wiki").build(app)?;
+    let switcher = MenuItemBuilder::with_id("quick_switcher", "Quic&k Switcher…").build(app)?;
+    let focus_timer_label = if focus_timer::is_running(app) { "Stop &Focus Timer" } else { "Start &Focus Timer" };
+    let focus_timer = MenuItemBuilder::with_id("focus_timer", focus_timer_label).build(app)?;
+    let quit = MenuItemBuilder::with_id("quit", "&Quit").build(app)?;
+
+    // Recent wikis with their custom icon/favicon. The emoji is repeated in
+    // the label for trays that don't show menu icons (AppIndicator).
+    let mut recent = SubmenuBuilder::new(app, "Recent &Wikis");
+    let entries = wiki_storage::load_recent_files_from_disk(app);
+    for entry in entries.iter().take(TRAY_RECENT_WIKIS) {
+        let name = entry.filename.replace('&', "&&");
+        let label = match entry.emoji.as_deref() {
+            Some(emoji) => format!("{} {}", emoji, name),
+            None => name,
+        };
+        let id = format!("open_wiki:{}", entry.path);
+        recent = match tray_wiki_icon(entry) {
+            Some(icon) => recent.item(&IconMenuItemBuilder::with_id(id, label).icon(icon).build(app)?),
+            None => recent.item(&MenuItemBuilder::with_id(id, label).build(app)?),
+        };
+    }
+    let recent = recent.enabled(!entries.is_empty()).build()?;
+
+    // Mute toggle for every open wiki
+    let mut mute = SubmenuBuilder::new(app, "&Mute Audio");
+    let mut open_wikis: Vec<String> = app
+        .state::<AppState>()
+        .wiki_processes
+        .lock()
+        .unwrap()
+        .keys()
+        .cloned()
+        .collect();
+    open_wikis.sort();
+    for path in &open_wikis {
+        let name = entries
+            .iter()
+            .find(|e| utils::paths_equal(&e.path, path))
+            .map(|e| e.filename.clone())
+            .unwrap_or_else(|| path.clone())
+            .replace('&', "&&");
+        mute = mute.item(
+            &CheckMenuItemBuilder::with_id(format!("mute_wiki:{}", path), name)
+                .checked(audio::is_wiki_muted(path))
+                .build(app)?,
+        );
+    }
+    let mute = mute.enabled(!open_wikis.is_empty()).build()?;
+
+    // Quick actions (new tiddler, journal, paste clipboard into a wiki)
+    let action_items = quick_actions::tray_items(app);
+    let mut actions = SubmenuBuilder::new(app, "&Quick Actions");
+    for (id, label) in &action_items {
+        actions = actions.item(&MenuItemBuilder::with_id(id, label).build(app)?);
+    }
+    let actions = actions.enabled(!action_items.is_empty()).build()?;
+
+    // Commands defined by the open wikis, one submenu per wiki
+    let command_wikis = wiki_commands::tray_items();
+    let mut commands = SubmenuBuilder::new(app, "Wiki &Commands");
+    for (path, items) in &command_wikis {
+        let name = entries
+            .iter()
+            .find(|e| utils::paths_equal(&e.path, path))
+            .map(|e| e.filename.clone())
+            .unwrap_or_else(|| path.clone())
+            .replace('&', "&&");
+        let mut submenu = SubmenuBuilder::new(app, name);
+        for (id, label) in items {
+            submenu = submenu.item(&MenuItemBuilder::with_id(id, label).build(app)?);
+        }
+        commands = commands.item(&submenu.build()?);
+    }
+    let commands = commands.enabled(!command_wikis.is_empty()).build()?;
+
+    // Wikis served to the system browser, to open again or stop
+    let served = browser_mode::served_wikis();
+    let mut browser = SubmenuBuilder::new(app, "In &Browser");
+    for path in &served {
+        let name = entries
+            .iter()
+            .find(|e| utils::paths_equal(&e.path, path))
+            .map(|e| e.filename.clone())
+            .unwrap_or_else(|| path.clone())
+            .replace('&', "&&");
+        let mut submenu = SubmenuBuilder::new(app, name)
+            .item(&MenuItemBuilder::with_id(format!("browser_open:{}", path), "&Open in Browser").build(app)?);
+        // Served beyond this computer (server_address.rs)
+        if !server_discovery::urls(path).is_empty() {
+            submenu = submenu.item(&MenuItemBuilder::with_id(format!("browser_qr:{}", path), "Show &QR Code").build(app)?);
+        }
+        let submenu = submenu
+            .item(&MenuItemBuilder::with_id(format!("browser_stop:{}", path), "&Stop Serving").build(app)?)
+            .build()?;
+        browser = browser.item(&submenu);
+    }
+    let browser = browser.enabled(!served.is_empty()).build()?;
+
+    let mut menu = MenuBuilder::new(app)
+        .item(&show_window)
+        .item(&recent)
+        .item(&reopen_closed)
+        .item(&switcher)
+        .item(&actions)
+        .item(&commands)
+        .item(&browser)
+        .item(&mute)
+        .item(&focus_timer);
+    // Items of enabled shell extensions (changes apply after a restart)
+    let extension_items = extensions::tray_items(app);
+    if !extension_items.is_empty() {
+        menu = menu.separator();
+        for (id, label) in extension_items {
+            menu = menu.item(&MenuItemBuilder::with_id(id, label).build(app)?);
+        }
+    }
+    menu.separator().item(&quit).build()
+}
+
+/// Rebuild the tray menu after the wiki list or a wiki's appearance changed
+#[cfg(not(target_os = "android"))]
+pub(crate) fn refresh_tray_menu(app: &tauri::AppHandle) {
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        return;
+    };
+    match build_tray_menu(app) {
+        Ok(menu) => {
+            let _ = tray.set_menu(Some(menu));
+        }
+        Err(e) => eprintln!("[TiddlyDesktop] Failed to rebuild tray menu: {}", e),
+    }
+}
+
+/// Open a wiki from the tray menu
+#[cfg(not(target_os = "android"))]
+fn open_wiki_from_tray(app: &tauri::AppHandle, path: String) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let result = if std::path::Path::new(&path).is_dir() {
+            open_wiki_folder(app.clone(), path, None).await
+        } else {
+            open_wiki_window(app.clone(), path, None, None, None).await
+        };
+        match result {
+            Ok(entry) => {
+                let _ = app.emit("wiki-list-changed", &entry);
+            }
+            Err(e) => eprintln!("[TiddlyDesktop] Failed to open wiki from tray: {}", e),
+        }
+    });
+}
+
 // System tray is only available on desktop platforms
 #[cfg(not(target_os = "android"))]
 fn setup_system_tray(app: &tauri::App) -> Result<(), Box<dyn std::error::Error>> {
-    let show_window = MenuItemBuilder::with_id("show_window", "Show TiddlyDesktop").build(app)?;
-    let quit = MenuItemBuilder::with_id("quit", "Quit").build(app)?;
+    use tauri::Listener;
 
-    let menu = MenuBuilder::new(app)
-        .item(&show_window)
-        .separator()
-        .item(&quit)
-        .build()?;
+    let menu = build_tray_menu(app.handle())?;
+
+    // Closed wikis leave the tray's mute and wiki command menus (opened ones join it when they register over IPC)
+    let handle = app.handle().clone();
+    app.listen("wiki-process-closed", move |event| {
+        if let Ok(path) = serde_json::from_str::<String>(event.payload()) {
+            audio::wiki_closed(&handle, &path);
+            wiki_commands::wiki_closed(&handle, &path);
+        }
+    });
 
-    let _tray = TrayIconBuilder::new()
+    let _tray = TrayIconBuilder::with_id(TRAY_ID)
         .icon(Image::from_bytes(include_bytes!("../icons/32x32.png"))?)
         .menu(&menu)
+        // The tooltip doubles as the tray icon's accessible name (UIA on Windows, AT-SPI on Linux)
         .tooltip("TiddlyDesktopRS")
         .on_menu_event(|app, event| {
             match event.id().as_ref() {
                 "show_window" => {
                     reveal_or_create_main_window(app);
                 }
+                "reopen_closed_wiki" => {
+                    recently_closed::reopen_in_background(app);
+                }
+                "quick_switcher" => {
+                    quick_switcher::show(app);
+                }
+                "focus_timer" => {
+                    focus_timer::toggle(app);
+                }
+                id if id.starts_with("open_wiki:") => {
+                    open_wiki_from_tray(app, id["open_wiki:".len()..].to_string());
+                }
+                id if id.starts_with("mute_wiki:") => {
+                    audio::toggle_wiki_muted(app, &id["mute_wiki:".len()..]);
+                }
+                id if id.starts_with("quick_action:") => {
+                    quick_actions::handle_tray_event(app, id);
+                }
+                id if id.starts_with("wiki_command:") => {
+                    wiki_commands::handle_tray_event(id);
+                }
+                id if id.starts_with("browser_open:") || id.starts_with("browser_qr:") || id.starts_with("browser_stop:") => {
+                    browser_mode::handle_tray_event(app, id);
+                }
                 "quit" => {
                     // Close all open windows (wiki windows + landing page) before exiting
                     let windows = app.webview_windows();
@@ -8093,11 +8753,17 @@ fn setup_system_tray(app: &tauri::App) -> Result<(), Box<dyn std::error::Error>>
                             let _ = window.destroy();
                         }
                     }
-                    // Clear wiki processes so ExitRequested handler allows exit
+                    // Remember the open wikis for the next launch, then clear wiki
+                    // processes and browser servers so ExitRequested handler allows exit
+                    session_restore::mark_quit(app);
                     let state = app.state::<AppState>();
                     state.wiki_processes.lock().unwrap().clear();
+                    browser_mode::stop_all();
                     app.exit(0);
                 }
+                id if id.starts_with("ext:") => {
+                    extensions::handle_tray_event(app, id);
+                }
                 _ => {}
             }
         })
@@ -8125,6 +8791,10 @@ struct WikiModeArgs {
 struct WikiFolderModeArgs {
     folder_path: PathBuf,
     port: u16,
+    /// Serve the folder with `folder_server` instead of Node.js
+    native_server: bool,
+    /// Also serve the tiddler files over WebDAV (`folder_server` only)
+    webdav: bool,
 }
 
 /// Parse command-line arguments for special modes
@@ -8143,6 +8813,8 @@ fn parse_special_mode_args() -> Option<SpecialModeArgs> {
     let mut tiddler_title: Option<String> = None;
     let mut startup_tiddler: Option<String> = None;
     let mut port: Option<u16> = None;
+    let mut native_server = false;
+    let mut webdav = false;
 
     let mut i = 1;
     while i < args.len() {
@@ -8167,6 +8839,14 @@ fn parse_special_mode_args() -> Option<SpecialModeArgs> {
                 port = args[i + 1].parse().ok();
                 i += 2;
             }
+            "--native-server" => {
+                native_server = true;
+                i += 1;
+            }
+            "--webdav" => {
+                webdav = true;
+                i += 1;
+            }
             _ => {
                 i += 1;
             }
@@ -8175,9 +8855,18 @@ fn parse_special_mode_args() -> Option<SpecialModeArgs> {
 
     // Wiki folder mode takes precedence
     if let Some(folder_path) = wiki_folder_path {
+        // Desktop shortcuts launch folder wikis without a port from the main process
+        let port = port.unwrap_or_else(|| {
+            std::net::TcpListener::bind(("127.0.0.1", 0))
+                .and_then(|l| l.local_addr())
+                .map(|a| a.port())
+                .unwrap_or(8080)
+        });
         return Some(SpecialModeArgs::WikiFolder(WikiFolderModeArgs {
             folder_path,
-            port: port.unwrap_or(8080),
+            port,
+            native_server,
+            webdav,
         }));
     }
 
@@ -8234,6 +8923,8 @@ fn run_wiki_mode(args: WikiModeArgs) {
     } else {
         eprintln!("[TiddlyDesktop] Warning: Could not connect to IPC server (main process not running?)");
     }
+    // Without the main process, this process shows the app lock window itself
+    let ipc_connected = ipc_client.lock().unwrap().is_some();
 
     // Linux: Configure WebKitGTK hardware acceleration (same as main mode)
     #[cfg(target_os = "linux")]
@@ -8296,6 +8987,13 @@ fn run_wiki_mode(args: WikiModeArgs) {
     let builder = builder.setup(move |app| {
             // Resolve data directory (portable mode check) for wiki process
             let _ = resolve_data_dir(app.handle()).map(|dir| DATA_DIR.set(dir));
+            app_lock::start(app.handle(), !ipc_connected, false);
+            idle::start(app.handle());
+            focus_timer::start(app.handle(), !ipc_connected);
+            throttle::start(app.handle());
+            if !is_tiddler_window_for_state {
+                process_registry::record_process(app.handle(), &wiki_path.to_string_lossy(), false, None);
+            }
 
             // Initialize PDFium for native PDF rendering
             init_pdfium_from_resources(&app.handle());
@@ -8314,7 +9012,7 @@ fn run_wiki_mode(args: WikiModeArgs) {
 
             // Start localhost HTTP media server (Linux: GStreamer needs HTTP URLs;
             // also used for folder wikis on all platforms)
-            match media_server::MediaServer::start() {
+            match media_server::MediaServer::start(&server_address::Binding::current(app.handle())) {
                 Ok(server) => {
                     app.manage(MediaServerState { server });
                 }
@@ -8356,7 +9054,7 @@ fn run_wiki_mode(args: WikiModeArgs) {
                 format!("wikifile://localhost/{}?tiddler={}&template={}",
                     path_key_clone, encoded_tiddler, encoded_template)
             } else {
-                format!("wikifile://localhost/{}", path_key_clone)
+                safe_mode::wiki_url(format!("wikifile://localhost/{}", path_key_clone))
             };
 
             // Create the wiki window
@@ -8397,7 +9095,7 @@ fn run_wiki_mode(args: WikiModeArgs) {
                 .initialization_script(&init_script::get_wiki_init_script(&wiki_path_clone.to_string_lossy(), &label, false))
                 .on_document_title_changed(|ww, title| { let _ = ww.set_title(&title); })
                 .zoom_hotkeys_enabled(true)
-                .devtools(cfg!(debug_assertions)); // Only enable in debug builds
+                .devtools(devtools::enabled()); // Debug builds, or remote debugging on (devtools.rs)
 
             // Isolate session data (cookies, localStorage) per wiki
             if let Some(session_dir) = get_wiki_session_dir(app.handle(), &wiki_path_clone.to_string_lossy()) {
@@ -8435,6 +9133,9 @@ fn run_wiki_mode(args: WikiModeArgs) {
                 linux_finalize_window_state(&window, &saved_state);
             }
 
+            // Apply IME configuration (GTK IM module / Windows IMM context)
+            input_method::configure_window(&window);
+
             // Restore maximized state (Windows/macOS only - Linux handled in linux_finalize_window_state)
             #[cfg(not(target_os = "linux"))]
             if saved_state.as_ref().map(|s| s.maximized).unwrap_or(false) {
@@ -8443,102 +9144,149 @@ fn run_wiki_mode(args: WikiModeArgs) {
 
             eprintln!("[TiddlyDesktop] Wiki window created: {}", label);
 
-            // Start IPC listener thread to receive messages from other wiki windows
-            let client_guard = ipc_client_for_state.lock().unwrap();
-            if let Some(ref client) = *client_guard {
-                if let Some(listener_stream) = client.get_listener_stream() {
-                    eprintln!("[TiddlyDesktop] IPC listener stream cloned successfully");
-                    let app_handle = app.handle().clone();
-                    std::thread::spawn(move || {
-                        ipc::run_listener(listener_stream, |msg| {
-                            match msg {
-                                ipc::IpcMessage::TiddlerChanged { tiddler_title, tiddler_json, .. } => {
-                                    eprintln!("[IPC Listener] Tiddler changed: {}", tiddler_title);
-                                    // Emit event to JavaScript to update the tiddler
-                                    let _ = app_handle.emit("ipc-tiddler-changed", serde_json::json!({
-                                        "title": tiddler_title,
-                                        "tiddler": tiddler_json
-                                    }));
-                                }
-                                ipc::IpcMessage::TiddlerDeleted { tiddler_title, .. } => {
-                                    eprintln!("[IPC Listener] Tiddler deleted: {}", tiddler_title);
-                                    // Emit event to JavaScript to delete the tiddler
-                                    let _ = app_handle.emit("ipc-tiddler-deleted", serde_json::json!({
-                                        "title": tiddler_title
-                                    }));
-                                }
-                                ipc::IpcMessage::SyncState { tiddlers_json, .. } => {
-                                    eprintln!("[IPC Listener] Received sync state");
-                                    // Emit event to JavaScript to sync all tiddlers
-                                    let _ = app_handle.emit("ipc-sync-state", serde_json::json!({
-                                        "tiddlers": tiddlers_json
-                                    }));
-                                }
-                                ipc::IpcMessage::RequestSync { requester_pid, .. } => {
-                                    eprintln!("[IPC Listener] Sync request from pid {}", requester_pid);
-                                    // Emit event to JavaScript to send current state
-                                    let _ = app_handle.emit("ipc-sync-request", serde_json::json!({
-                                        "requester_pid": requester_pid
-                                    }));
+            // Start IPC listener thread to receive messages from other wiki windows.
+            // If the main process goes away (or wasn't running), keep trying to
+            // reconnect so a restarted main process can take this wiki over again.
+            if ipc_client_for_state.lock().unwrap().is_none() {
+                eprintln!("[TiddlyDesktop] WARNING: IPC client not connected — waiting for the main process");
+            }
+            let app_handle = app.handle().clone();
+            let ipc_client_for_listener = ipc_client_for_state.clone();
+            let listener_wiki_path = wiki_path_clone.to_string_lossy().to_string();
+            let listener_tiddler_title = tiddler_title_for_state.clone();
+            std::thread::spawn(move || {
+                let mut handle_message = |msg: ipc::IpcMessage| {
+                    match msg {
+                        ipc::IpcMessage::TiddlerChanged { tiddler_title, tiddler_json, .. } => {
+                            eprintln!("[IPC Listener] Tiddler changed: {}", tiddler_title);
+                            // Emit event to JavaScript to update the tiddler
+                            let _ = app_handle.emit("ipc-tiddler-changed", serde_json::json!({
+                                "title": tiddler_title,
+                                "tiddler": tiddler_json
+                            }));
+                        }
+                        ipc::IpcMessage::TiddlerDeleted { tiddler_title, .. } => {
+                            eprintln!("[IPC Listener] Tiddler deleted: {}", tiddler_title);
+                            // Emit event to JavaScript to delete the tiddler
+                            let _ = app_handle.emit("ipc-tiddler-deleted", serde_json::json!({
+                                "title": tiddler_title
+                            }));
+                        }
+                        ipc::IpcMessage::SyncState { tiddlers_json, .. } => {
+                            eprintln!("[IPC Listener] Received sync state");
+                            // Emit event to JavaScript to sync all tiddlers
+                            let _ = app_handle.emit("ipc-sync-state", serde_json::json!({
+                                "tiddlers": tiddlers_json
+                            }));
+                        }
+                        ipc::IpcMessage::RequestSync { requester_pid, .. } => {
+                            eprintln!("[IPC Listener] Sync request from pid {}", requester_pid);
+                            // Emit event to JavaScript to send current state
+                            let _ = app_handle.emit("ipc-sync-request", serde_json::json!({
+                                "requester_pid": requester_pid
+                            }));
+                        }
+                        ipc::IpcMessage::Ack { success, message } => {
+                            if !success {
+                                if let Some(msg) = message {
+                                    eprintln!("[IPC Listener] Server error: {}", msg);
                                 }
-                                ipc::IpcMessage::Ack { success, message } => {
-                                    if !success {
-                                        if let Some(msg) = message {
-                                            eprintln!("[IPC Listener] Server error: {}", msg);
-                                        }
+                            }
+                        }
+                        ipc::IpcMessage::MemoryLimitExceeded { used_mb, limit_mb, auto_restart, .. } => {
+                            memory_limit::notify_window(&app_handle, used_mb, limit_mb, auto_restart);
+                        }
+                        ipc::IpcMessage::SetMuted { muted, .. } => {
+                            audio::set_all_muted(&app_handle, muted);
+                        }
+                        ipc::IpcMessage::ImportCaptures { .. } => {
+                            let _ = app_handle.emit("quick-captures-pending", ());
+                        }
+                        ipc::IpcMessage::RunWikiMenuCommand { command_id, .. } => {
+                            let _ = app_handle.emit("wiki-menu-command", command_id);
+                        }
+                        ipc::IpcMessage::AutomationRequest { request_id, action, params_json, .. } => {
+                            let params = serde_json::from_str::<serde_json::Value>(&params_json).unwrap_or_default();
+                            let _ = app_handle.emit("automation-request", serde_json::json!({
+                                "requestId": request_id,
+                                "action": action,
+                                "params": params,
+                            }));
+                        }
+                        ipc::IpcMessage::OpenDevtools { .. } => {
+                            let handle = app_handle.clone();
+                            let _ = app_handle.run_on_main_thread(move || devtools::open_all(&handle));
+                        }
+                        ipc::IpcMessage::WikiIcon { icon, .. } => {
+                            let handle = app_handle.clone();
+                            let _ = app_handle.run_on_main_thread(move || {
+                                for label in handle.webview_windows().into_keys() {
+                                    if let Err(e) = set_window_icon_internal(&handle, &label, icon.as_deref()) {
+                                        eprintln!("[IPC Listener] Failed to set window icon: {}", e);
                                     }
                                 }
-                                ipc::IpcMessage::FocusWiki { .. } => {
-                                    eprintln!("[IPC Listener] Focus window request received");
-                                    // Focus this window - must run on main thread for GTK
-                                    let handle = app_handle.clone();
-                                    let _ = app_handle.run_on_main_thread(move || {
-                                        // Get any window in this process (wiki processes have one window)
-                                        let windows = handle.webview_windows();
-                                        if let Some((label, window)) = windows.into_iter().next() {
-                                            eprintln!("[IPC Listener] Found window '{}', attempting to focus", label);
-                                            let _ = window.unminimize();
-                                            let _ = window.show();
-                                            #[cfg(target_os = "linux")]
-                                            {
-                                                if let Ok(gtk_window) = window.gtk_window() {
-                                                    linux_activate_window(&gtk_window);
-                                                }
-                                            }
-                                            #[cfg(not(target_os = "linux"))]
-                                            {
-                                                let _ = window.set_focus();
-                                            }
-                                        } else {
-                                            eprintln!("[IPC Listener] No windows found in process!");
-                                        }
-                                    });
-                                }
-                                // LAN Sync: main process → wiki process
-                                ipc::IpcMessage::LanSyncApplyChange { wiki_id, payload_json } => {
-                                    // Queue the message for JS to poll via lan_sync_poll_ipc.
-                                    // Neither Tauri emit() nor WebView eval() reliably deliver
-                                    // messages from IPC listener threads to JS on Linux/WebKitGTK.
-                                    if let Ok(payload) = serde_json::from_str::<serde_json::Value>(&payload_json) {
-                                        let event_type = payload["type"].as_str().unwrap_or("");
-                                        if !event_type.is_empty() {
-                                            eprintln!("[IPC Listener] LAN Sync {}: wiki_id={}", event_type, wiki_id);
-                                            lan_sync::queue_lan_sync_ipc(payload_json);
+                            });
+                        }
+                        ipc::IpcMessage::FocusWiki { .. } => {
+                            eprintln!("[IPC Listener] Focus window request received");
+                            // Focus this window - must run on main thread for GTK
+                            let handle = app_handle.clone();
+                            let _ = app_handle.run_on_main_thread(move || {
+                                // Get any window in this process (wiki processes have one window)
+                                let windows = handle.webview_windows();
+                                if let Some((label, window)) = windows.into_iter().next() {
+                                    eprintln!("[IPC Listener] Found window '{}', attempting to focus", label);
+                                    let _ = window.unminimize();
+                                    let _ = window.show();
+                                    #[cfg(target_os = "linux")]
+                                    {
+                                        if let Ok(gtk_window) = window.gtk_window() {
+                                            linux_activate_window(&gtk_window);
                                         }
                                     }
+                                    #[cfg(not(target_os = "linux"))]
+                                    {
+                                        let _ = window.set_focus();
+                                    }
+                                } else {
+                                    eprintln!("[IPC Listener] No windows found in process!");
+                                }
+                            });
+                        }
+                        // LAN Sync: main process → wiki process
+                        ipc::IpcMessage::LanSyncApplyChange { wiki_id, payload_json } => {
+                            // Queue the message for JS to poll via lan_sync_poll_ipc.
+                            // Neither Tauri emit() nor WebView eval() reliably deliver
+                            // messages from IPC listener threads to JS on Linux/WebKitGTK.
+                            if let Ok(payload) = serde_json::from_str::<serde_json::Value>(&payload_json) {
+                                let event_type = payload["type"].as_str().unwrap_or("");
+                                if !event_type.is_empty() {
+                                    eprintln!("[IPC Listener] LAN Sync {}: wiki_id={}", event_type, wiki_id);
+                                    lan_sync::queue_lan_sync_ipc(payload_json);
                                 }
-                                _ => {}
                             }
-                        });
-                    });
-                    eprintln!("[TiddlyDesktop] IPC listener thread started");
-                } else {
-                    eprintln!("[TiddlyDesktop] WARNING: Failed to clone IPC stream for listener — LAN sync IPC will not work!");
+                        }
+                        _ => {}
+                    }
+                };
+                loop {
+                    let listener_stream = ipc_client_for_listener.lock().unwrap()
+                        .as_ref()
+                        .and_then(|client| client.get_listener_stream());
+                    if let Some(listener_stream) = listener_stream {
+                        ipc::run_listener(listener_stream, &mut handle_message);
+                    }
+                    let client = process_registry::reconnect(
+                        &app_handle,
+                        &listener_wiki_path,
+                        is_tiddler_window_for_state,
+                        listener_tiddler_title.clone(),
+                    );
+                    *ipc_client_for_listener.lock().unwrap() = Some(client);
+                    lan_sync::set_ipc_client_for_sync(ipc_client_for_listener.clone());
                 }
-            } else {
-                eprintln!("[TiddlyDesktop] WARNING: IPC client not connected — LAN sync IPC will not work!");
-            }
-            drop(client_guard);
+            });
+            eprintln!("[TiddlyDesktop] IPC listener thread started");
 
             Ok(())
         })
@@ -8552,6 +9300,12 @@ fn run_wiki_mode(args: WikiModeArgs) {
             std::thread::spawn(move || {
                 responder.respond(tdasset_protocol_handler(request));
             });
+        })
+        .register_asynchronous_uri_scheme_protocol("tdext", |ctx, request, responder| {
+            let app = ctx.app_handle().clone();
+            std::thread::spawn(move || {
+                responder.respond(extensions::protocol_handler(&app, request));
+            });
         });
         // tdlib:// protocol is desktop-only (Android serves libraries via WikiHttpServer /_td/)
         #[cfg(not(target_os = "android"))]
@@ -8561,11 +9315,17 @@ fn run_wiki_mode(args: WikiModeArgs) {
         builder.plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
+        .plugin(tauri_plugin_notification::init())
         .invoke_handler(tauri::generate_handler![
             // Core wiki commands needed for operation
             load_wiki,
             save_wiki,
+            chunked_save::save_wiki_begin,
+            chunked_save::save_wiki_chunk,
+            chunked_save::save_wiki_commit,
+            chunked_save::save_wiki_abort,
             set_window_title,
+            sync_badge::set_window_sync_status,
             set_window_icon,
             set_headerbar_colors,
             get_window_label,
@@ -8580,9 +9340,50 @@ fn run_wiki_mode(args: WikiModeArgs) {
             pick_files_for_import,
             wiki_storage::get_external_attachments_config,
             wiki_storage::set_external_attachments_config,
+            image_import::process_imported_image,
+            video_import::transcode_video,
+            video_import::cancel_video_transcode,
+            attachment_migration::save_embedded_attachment,
+            attachment_migration::get_attachment_sizes,
+            attachment_migration::read_external_attachment,
+            audio::set_window_muted,
+            audio::set_window_volume,
+            media_session::media_session_update,
+            geolocation::get_current_position,
+            qr_code::decode_qr_frame,
+            qr_code::generate_qr_png,
+            server_discovery::get_server_urls,
+            server_discovery::get_server_qr_png,
+            idle::get_idle_time,
+            time_tracking::record_time,
+            time_tracking::get_time_report,
+            focus_timer::start_focus_timer,
+            focus_timer::stop_focus_timer,
+            focus_timer::get_focus_timer_status,
+            quick_actions::take_quick_captures,
+            wiki_commands::set_wiki_menu_commands,
+            mqtt::mqtt_connect,
+            mqtt::mqtt_publish,
+            mqtt::mqtt_disconnect,
+            serial::list_serial_ports,
+            serial::open_serial,
+            serial::read_serial,
+            serial::write_serial,
+            serial::close_serial,
+            allowed_commands::run_allowed_command,
+            allowed_commands::stop_allowed_command,
+            environment::get_environment,
             wiki_storage::js_log,
+            wiki_storage::get_accelerator_map,
+            wiki_storage::set_accelerator,
+            wiki_storage::reset_accelerators,
+            input_method::get_input_debug_info,
+            accessibility::get_accessibility_preferences,
+            app_lock::app_lock_activity,
             clipboard::get_clipboard_content,
             clipboard::set_clipboard_content,
+            backup_history::get_tiddler_history,
+            text_diff::diff_texts,
 
             // Drag-drop commands
             start_native_drag,
@@ -8619,9 +9420,14 @@ fn run_wiki_mode(args: WikiModeArgs) {
             ipc_request_sync,
             ipc_send_sync_state,
             ipc_update_favicon,
+            ipc_reopen_closed_wiki,
+            quick_switcher::ipc_show_quick_switcher,
+            ipc_restart_wiki,
+            extensions::extension_invoke,
             show_find_in_page,
             extract_video_poster,
             register_media_url,
+            transfer_stats::get_transfer_stats,
             // PDF rendering commands
             pdf_open,
             pdf_open_file,
@@ -8635,14 +9441,30 @@ fn run_wiki_mode(args: WikiModeArgs) {
             wiki_storage::get_wiki_sync_id,
             lan_sync::lan_sync_wiki_opened,
             lan_sync::lan_sync_announce_username,
+            lan_sync::lan_sync_share_clipboard,
+            lan_sync::lan_sync_get_shared_clipboard,
             lan_sync::lan_sync_tiddler_changed,
             lan_sync::lan_sync_tiddler_deleted,
+            lan_sync::lan_sync_get_conflicts,
+            lan_sync::lan_sync_conflict_local_version,
+            lan_sync::lan_sync_resolve_conflict,
+            lan_sync::tombstones::lan_sync_get_deletion_policy,
             lan_sync::lan_sync_send_full_sync,
             lan_sync::lan_sync_send_fingerprints,
             lan_sync::lan_sync_broadcast_fingerprints,
             lan_sync::lan_sync_broadcast_manifest,
             lan_sync::lan_sync_poll_ipc,
             lan_sync::lan_sync_load_tombstones,
+            lan_sync::snapshots::lan_sync_snapshot_needed,
+            lan_sync::snapshots::lan_sync_save_snapshot,
+            lan_sync::snapshots::lan_sync_load_snapshot,
+            lan_sync::snapshots::lan_sync_take_pending_rollback,
+            automation::automation_respond,
+            js_errors::report_js_error,
+            devtools::open_devtools,
+            tiddler_image::render_tiddler_image,
+            tiddler_image::report_tiddler_image,
+            visual_regression::visual_regression_check,
             lan_sync::lan_sync_save_tombstones,
             lan_sync::lan_sync_collab_editing_started,
             lan_sync::lan_sync_collab_editing_stopped,
@@ -8660,8 +9482,12 @@ fn run_wiki_mode(args: WikiModeArgs) {
             lan_sync::relay_sync_connect_room,
             lan_sync::relay_sync_disconnect_room,
             lan_sync::relay_sync_set_room_auto_connect,
+            lan_sync::relay_sync_set_room_relay_fallback,
             lan_sync::relay_sync_set_room_password,
             lan_sync::relay_sync_set_room_name,
+            lan_sync::relay_sync_get_room_directory,
+            lan_sync::relay_sync_revoke_device,
+            lan_sync::relay_sync_unrevoke_device,
             lan_sync::relay_sync_get_room_credentials,
             lan_sync::relay_sync_set_url,
             lan_sync::relay_sync_generate_credentials,
@@ -8686,17 +9512,42 @@ fn run_wiki_mode(args: WikiModeArgs) {
             wiki_storage::set_wiki_relay_room,
             // Per-wiki sync mode (bidirectional / send-only / receive-only)
             wiki_storage::set_wiki_sync_mode,
+            wiki_storage::set_wiki_confirm_remote_deletions,
+            wiki_storage::get_wiki_sync_schedule,
+            wiki_storage::set_wiki_sync_schedule,
+            wiki_storage::get_wiki_sync_priority,
+            wiki_storage::set_wiki_sync_priority,
             wiki_storage::get_wiki_sync_mode,
             get_wiki_installed_plugins,
             install_plugins_to_wiki
         ])
         .build(tauri::generate_context!())
         .expect("error while building wiki-mode application")
-        .run(|_app, _event| {
-            // Wiki mode doesn't need special event handling
+        .run(|app, event| {
+            if let tauri::RunEvent::Exit = event {
+                process_registry::forget_process(app);
+            }
         });
 }
 
+/// Run a `--headless` command in an app without windows, then exit with its status.
+/// The app is only needed for the resource and data directories.
+#[cfg(not(target_os = "android"))]
+fn run_headless_mode(command: headless::Command) {
+    tauri::Builder::default()
+        .setup(move |app| {
+            let handle = app.handle().clone();
+            std::thread::spawn(move || {
+                let code = headless::run(&handle, &command);
+                handle.exit(code);
+            });
+            Ok(())
+        })
+        .build(tauri::generate_context!())
+        .expect("error while building headless application")
+        .run(|_app, _event| {});
+}
+
 /// Run in wiki-folder mode - a Node.js TiddlyWiki server in its own process
 /// This is called when the app is started with --wiki-folder <path> --port <port>
 #[cfg(not(target_os = "android"))]
@@ -8731,15 +9582,15 @@ fn run_wiki_folder_mode(args: WikiFolderModeArgs) {
     let folder_path_str = folder_path.to_string_lossy().to_string();
 
     // We need to find Node.js and TiddlyWiki paths
-    // In folder mode, we'll use the same logic as the main process
-    let node_path_result = find_node_executable();
-    let node_path = match node_path_result {
-        Some(p) => p,
-        None => {
-            eprintln!("[TiddlyDesktop] Error: Node.js not found");
-            return;
-        }
-    };
+    // In folder mode, we'll use the same logic as the main process.
+    // Without Node.js, the built-in server takes over.
+    // SQLite wikis can only be served by the built-in server.
+    let node_path = find_node_executable();
+    let is_sqlite_wiki = tiddlydesktop_core::sqlite_wiki::is_sqlite_wiki(&folder_path);
+    if node_path.is_none() && !args.native_server && !is_sqlite_wiki {
+        eprintln!("[TiddlyDesktop] Node.js not found, using the built-in folder server");
+    }
+    let native_server = args.native_server || is_sqlite_wiki || node_path.is_none();
 
     // Find TiddlyWiki - it should be in the resources directory
     let exe_dir = std::env::current_exe()
@@ -8780,7 +9631,7 @@ fn run_wiki_folder_mode(args: WikiFolderModeArgs) {
     };
 
     eprintln!("[TiddlyDesktop] Starting wiki folder server:");
-    eprintln!("  Node.js: {:?}", node_path);
+    eprintln!("  Node.js: {}", if native_server { "not used (built-in server)".to_string() } else { format!("{:?}", node_path) });
     eprintln!("  TiddlyWiki: {:?}", tw_path);
     eprintln!("  Wiki folder: {:?}", folder_path);
     eprintln!("  Port: {}", port);
@@ -8788,41 +9639,49 @@ fn run_wiki_folder_mode(args: WikiFolderModeArgs) {
     // Ensure required plugins and autosave are enabled
     ensure_wiki_folder_config(&folder_path);
 
-    // Start the Node.js server
-    let mut cmd = Command::new(&node_path);
-    cmd.arg(&tw_path)
-        .arg(&folder_path)
-        .arg("--listen")
-        .arg(format!("port={}", port))
-        .arg("host=127.0.0.1");
-
-    #[cfg(target_os = "windows")]
-    cmd.creation_flags(CREATE_NO_WINDOW);
-
-    let mut server_process = match cmd.spawn() {
-        Ok(child) => child,
-        Err(e) => {
-            eprintln!("[TiddlyDesktop] Error: Failed to start TiddlyWiki server: {}", e);
-            return;
+    // Where the server listens besides 127.0.0.1 (server_address.rs)
+    let mut binding = server_address::Binding::from_data_dir();
+    let mut _announcement = None;
+
+    let server_process = match (&node_path, native_server) {
+        (Some(node_path), false) => {
+            // Node.js listens on one address: all of them, or 127.0.0.1
+            if binding.single_address().is_none() {
+                eprintln!("[TiddlyDesktop] The Node.js server can't listen on a single interface, using 127.0.0.1");
+                binding = server_address::Binding::local();
+            }
+            let host = binding.single_address().unwrap_or(std::net::Ipv4Addr::LOCALHOST.into());
+            match start_folder_node_server(node_path, &tw_path, &folder_path, port, host) {
+                Ok(server_process) => {
+                    _announcement = Some(server_discovery::announce(&binding, &folder_path_str, port, "/", false));
+                    Some(server_process)
+                }
+                Err(e) => {
+                    eprintln!("[TiddlyDesktop] Error: {}", e);
+                    return;
+                }
+            }
+        }
+        _ => {
+            // The built-in server runs on threads of this process
+            let tw_dir = tw_path.parent().map(|p| p.to_path_buf()).unwrap_or_default();
+            if let Err(e) = folder_server::start(&folder_path, &tw_dir, port, args.webdav, plugin_dev::plugin_dir(), &binding) {
+                eprintln!("[TiddlyDesktop] Error: Built-in folder server failed to start: {}", e);
+                return;
+            }
+            None
         }
     };
 
-    // Windows: Assign to job object so it gets killed when parent exits
-    #[cfg(target_os = "windows")]
-    drag_drop::windows_job::assign_process_to_job(server_process.id());
-
-    // Wait for server to be ready
-    if let Err(e) = wait_for_server_ready(port, &mut server_process, std::time::Duration::from_secs(15)) {
-        eprintln!("[TiddlyDesktop] Error: Server failed to start: {}", e);
-        let _ = server_process.kill();
-        return;
-    }
-
+    // Windows load it from 127.0.0.1, other devices use the LAN URLs
     let server_url = format!("http://127.0.0.1:{}", port);
     eprintln!("[TiddlyDesktop] Wiki folder server ready at {}", server_url);
+    for url in binding.lan_urls(port) {
+        eprintln!("[TiddlyDesktop]   and at {}", url);
+    }
 
     // Store server process in a mutex for cleanup
-    let server_process = Arc::new(Mutex::new(Some(server_process)));
+    let server_process = Arc::new(Mutex::new(server_process));
     let server_process_for_exit = server_process.clone();
 
     // Connect to IPC server in main process
@@ -8836,6 +9695,8 @@ fn run_wiki_folder_mode(args: WikiFolderModeArgs) {
         // Register the IPC client for LAN sync commands to route through
         lan_sync::set_ipc_client_for_sync(ipc_client_for_setup.clone());
     }
+    // Without the main process, this process shows the app lock window itself
+    let ipc_connected = ipc_client.lock().unwrap().is_some();
 
     let folder_path_for_state = folder_path.clone();
     let folder_path_for_wiki_state = folder_path.clone();
@@ -8853,6 +9714,21 @@ fn run_wiki_folder_mode(args: WikiFolderModeArgs) {
     let label = format!("folder-{}-{:x}", folder_name.replace(|c: char| !c.is_alphanumeric(), "-"), path_hash & 0xFFFF);
     let label_for_state = label.clone();
 
+    // Snapshot schedule runs its own TiddlyWiki instance against the folder (needs Node.js)
+    let snapshot_paths = node_path.clone()
+        .filter(|_| !is_sqlite_wiki)
+        .map(|node_path| (node_path, tw_path.clone(), folder_path.clone()));
+    let snapshot_paths_for_exit = Arc::new(Mutex::new(snapshot_paths.clone()));
+
+    // Plugin development restarts the Node.js server to load the changed plugin
+    let server_process_for_plugin_dev = server_process.clone();
+    let node_server_for_plugin_dev = node_path.clone()
+        .filter(|_| !native_server)
+        .map(|node_path| {
+            let host = binding.single_address().unwrap_or(std::net::Ipv4Addr::LOCALHOST.into());
+            (node_path, tw_path.clone(), folder_path.clone(), host)
+        });
+
     // Build the Tauri app for this wiki folder
     tauri::Builder::default()
         .with_platform_plugins()
@@ -8860,6 +9736,11 @@ fn run_wiki_folder_mode(args: WikiFolderModeArgs) {
         .setup(move |app| {
             // Resolve data directory (portable mode check) for wiki-folder process
             let _ = resolve_data_dir(app.handle()).map(|dir| DATA_DIR.set(dir));
+            app_lock::start(app.handle(), !ipc_connected, false);
+            idle::start(app.handle());
+            focus_timer::start(app.handle(), !ipc_connected);
+            throttle::start(app.handle());
+            process_registry::record_process(app.handle(), &folder_path_for_state.to_string_lossy(), true, Some(port));
 
             // Initialize PDFium for native PDF rendering
             init_pdfium_from_resources(&app.handle());
@@ -8884,9 +9765,14 @@ fn run_wiki_folder_mode(args: WikiFolderModeArgs) {
                 (w, h)
             };
 
+            // Scheduled single-file snapshots (if configured for this wiki)
+            if let Some((node_path, tw_path, folder)) = snapshot_paths {
+                folder_snapshot::start_schedule(app.handle(), node_path, tw_path, folder);
+            }
+
             // Start localhost HTTP media server for file serving in folder wikis
             // (must be before window builder so embed proxy port is available for init script)
-            match media_server::MediaServer::start() {
+            match media_server::MediaServer::start(&server_address::Binding::current(app.handle())) {
                 Ok(server) => {
                     app.manage(MediaServerState { server });
                 }
@@ -8898,7 +9784,7 @@ fn run_wiki_folder_mode(args: WikiFolderModeArgs) {
             let mut builder = WebviewWindowBuilder::new(
                 app,
                 &label_for_state,
-                WebviewUrl::External(server_url.parse().unwrap())
+                WebviewUrl::External(safe_mode::wiki_url(server_url.clone()).parse().unwrap())
             )
             .title(&folder_name_for_state)
             .inner_size(win_width, win_height)
@@ -8911,7 +9797,7 @@ fn run_wiki_folder_mode(args: WikiFolderModeArgs) {
             ))
             .on_document_title_changed(|ww, title| { let _ = ww.set_title(&title); })
             .zoom_hotkeys_enabled(true)
-            .devtools(cfg!(debug_assertions)); // Only enable in debug builds
+            .devtools(devtools::enabled()); // Debug builds, or remote debugging on (devtools.rs)
 
             // Isolate session data (cookies, localStorage) per wiki
             if let Some(session_dir) = get_wiki_session_dir(app.handle(), &folder_path_for_state.to_string_lossy()) {
@@ -8942,12 +9828,30 @@ fn run_wiki_folder_mode(args: WikiFolderModeArgs) {
                 linux_finalize_window_state(&window, &saved_state);
             }
 
+            // Apply IME configuration (GTK IM module / Windows IMM context)
+            input_method::configure_window(&window);
+
             // Restore maximized state (Windows/macOS only - Linux handled in linux_finalize_window_state)
             #[cfg(not(target_os = "linux"))]
             if saved_state.as_ref().map(|s| s.maximized).unwrap_or(false) {
                 let _ = window.maximize();
             }
 
+            // Plugin development: reload with the plugin whenever it changes (plugin_dev.rs)
+            plugin_dev::start(app.handle(), move || {
+                // The built-in server packs the plugin again on every page load
+                let Some((node_path, tw_path, folder, host)) = &node_server_for_plugin_dev else {
+                    return Ok(());
+                };
+                let mut server_process = server_process_for_plugin_dev.lock().unwrap();
+                if let Some(mut process) = server_process.take() {
+                    let _ = process.kill();
+                    let _ = process.wait();
+                }
+                *server_process = Some(start_folder_node_server(node_path, tw_path, folder, port, *host)?);
+                Ok(())
+            });
+
             // Minimal app state for this process
 
 
@@ -8971,67 +9875,112 @@ fn run_wiki_folder_mode(args: WikiFolderModeArgs) {
                 ipc_client: ipc_client_for_wiki_state.clone(),
             });
 
-            // Start IPC listener for focus requests and LAN sync messages
-            let client_guard = ipc_client_for_state.lock().unwrap();
-            if let Some(ref client) = *client_guard {
-                if let Some(listener_stream) = client.get_listener_stream() {
-                    let app_handle = app.handle().clone();
-                    std::thread::spawn(move || {
-                        ipc::run_listener(listener_stream, |msg| {
-                            match msg {
-                                ipc::IpcMessage::FocusWiki { .. } => {
-                                    eprintln!("[IPC Listener] Focus window request received");
-                                    // Focus this window - must run on main thread for GTK
-                                    let handle = app_handle.clone();
-                                    let _ = app_handle.run_on_main_thread(move || {
-                                        // Get any window in this process (wiki processes have one window)
-                                        let windows = handle.webview_windows();
-                                        if let Some((label, window)) = windows.into_iter().next() {
-                                            eprintln!("[IPC Listener] Found window '{}', attempting to focus", label);
-                                            let _ = window.unminimize();
-                                            let _ = window.show();
-                                            #[cfg(target_os = "linux")]
-                                            {
-                                                if let Ok(gtk_window) = window.gtk_window() {
-                                                    linux_activate_window(&gtk_window);
-                                                }
-                                            }
-                                            #[cfg(not(target_os = "linux"))]
-                                            {
-                                                let _ = window.set_focus();
-                                            }
-                                        } else {
-                                            eprintln!("[IPC Listener] No windows found in process!");
-                                        }
-                                    });
+            // Start IPC listener for focus requests and LAN sync messages,
+            // reconnecting whenever the main process goes away
+            let app_handle = app.handle().clone();
+            let ipc_client_for_listener = ipc_client_for_state.clone();
+            let listener_folder_path = folder_path_for_state.to_string_lossy().to_string();
+            std::thread::spawn(move || {
+                let mut handle_message = |msg: ipc::IpcMessage| {
+                    match msg {
+                        ipc::IpcMessage::MemoryLimitExceeded { used_mb, limit_mb, auto_restart, .. } => {
+                            memory_limit::notify_window(&app_handle, used_mb, limit_mb, auto_restart);
+                        }
+                        ipc::IpcMessage::SetMuted { muted, .. } => {
+                            audio::set_all_muted(&app_handle, muted);
+                        }
+                        ipc::IpcMessage::ImportCaptures { .. } => {
+                            let _ = app_handle.emit("quick-captures-pending", ());
+                        }
+                        ipc::IpcMessage::RunWikiMenuCommand { command_id, .. } => {
+                            let _ = app_handle.emit("wiki-menu-command", command_id);
+                        }
+                        ipc::IpcMessage::AutomationRequest { request_id, action, params_json, .. } => {
+                            let params = serde_json::from_str::<serde_json::Value>(&params_json).unwrap_or_default();
+                            let _ = app_handle.emit("automation-request", serde_json::json!({
+                                "requestId": request_id,
+                                "action": action,
+                                "params": params,
+                            }));
+                        }
+                        ipc::IpcMessage::OpenDevtools { .. } => {
+                            let handle = app_handle.clone();
+                            let _ = app_handle.run_on_main_thread(move || devtools::open_all(&handle));
+                        }
+                        ipc::IpcMessage::WikiIcon { icon, .. } => {
+                            let handle = app_handle.clone();
+                            let _ = app_handle.run_on_main_thread(move || {
+                                for label in handle.webview_windows().into_keys() {
+                                    if let Err(e) = set_window_icon_internal(&handle, &label, icon.as_deref()) {
+                                        eprintln!("[IPC Listener] Failed to set window icon: {}", e);
+                                    }
                                 }
-                                // LAN Sync: main process → folder wiki process
-                                ipc::IpcMessage::LanSyncApplyChange { wiki_id, payload_json } => {
-                                    if let Ok(payload) = serde_json::from_str::<serde_json::Value>(&payload_json) {
-                                        let event_type = payload["type"].as_str().unwrap_or("");
-                                        if !event_type.is_empty() {
-                                            eprintln!("[IPC Listener] LAN Sync {}: wiki_id={}", event_type, wiki_id);
-                                            lan_sync::queue_lan_sync_ipc(payload_json);
+                            });
+                        }
+                        ipc::IpcMessage::FocusWiki { .. } => {
+                            eprintln!("[IPC Listener] Focus window request received");
+                            // Focus this window - must run on main thread for GTK
+                            let handle = app_handle.clone();
+                            let _ = app_handle.run_on_main_thread(move || {
+                                // Get any window in this process (wiki processes have one window)
+                                let windows = handle.webview_windows();
+                                if let Some((label, window)) = windows.into_iter().next() {
+                                    eprintln!("[IPC Listener] Found window '{}', attempting to focus", label);
+                                    let _ = window.unminimize();
+                                    let _ = window.show();
+                                    #[cfg(target_os = "linux")]
+                                    {
+                                        if let Ok(gtk_window) = window.gtk_window() {
+                                            linux_activate_window(&gtk_window);
                                         }
                                     }
+                                    #[cfg(not(target_os = "linux"))]
+                                    {
+                                        let _ = window.set_focus();
+                                    }
+                                } else {
+                                    eprintln!("[IPC Listener] No windows found in process!");
+                                }
+                            });
+                        }
+                        // LAN Sync: main process → folder wiki process
+                        ipc::IpcMessage::LanSyncApplyChange { wiki_id, payload_json } => {
+                            if let Ok(payload) = serde_json::from_str::<serde_json::Value>(&payload_json) {
+                                let event_type = payload["type"].as_str().unwrap_or("");
+                                if !event_type.is_empty() {
+                                    eprintln!("[IPC Listener] LAN Sync {}: wiki_id={}", event_type, wiki_id);
+                                    lan_sync::queue_lan_sync_ipc(payload_json);
                                 }
-                                _ => {}
                             }
-                        });
-                    });
+                        }
+                        _ => {}
+                    }
+                };
+                loop {
+                    let listener_stream = ipc_client_for_listener.lock().unwrap()
+                        .as_ref()
+                        .and_then(|client| client.get_listener_stream());
+                    if let Some(listener_stream) = listener_stream {
+                        ipc::run_listener(listener_stream, &mut handle_message);
+                    }
+                    let client = process_registry::reconnect(&app_handle, &listener_folder_path, false, None);
+                    *ipc_client_for_listener.lock().unwrap() = Some(client);
+                    lan_sync::set_ipc_client_for_sync(ipc_client_for_listener.clone());
                 }
-            }
-            drop(client_guard);
+            });
 
             Ok(())
         })
-        .on_window_event(move |_window, event| {
+        .on_window_event(move |window, event| {
             if let tauri::WindowEvent::Destroyed = event {
                 // Kill the Node.js server when window closes
                 if let Some(mut process) = server_process_for_exit.lock().unwrap().take() {
                     eprintln!("[TiddlyDesktop] Killing wiki folder server");
                     let _ = process.kill();
                 }
+                if let Some((node_path, tw_path, folder)) = snapshot_paths_for_exit.lock().unwrap().take() {
+                    folder_snapshot::snapshot_on_shutdown(window.app_handle(), &node_path, &tw_path, &folder);
+                }
             }
         })
         .register_uri_scheme_protocol("tdlib", |ctx, request| {
@@ -9042,13 +9991,25 @@ fn run_wiki_folder_mode(args: WikiFolderModeArgs) {
                 responder.respond(tdasset_protocol_handler(request));
             });
         })
+        .register_asynchronous_uri_scheme_protocol("tdext", |ctx, request, responder| {
+            let app = ctx.app_handle().clone();
+            std::thread::spawn(move || {
+                responder.respond(extensions::protocol_handler(&app, request));
+            });
+        })
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
+        .plugin(tauri_plugin_notification::init())
         .invoke_handler(tauri::generate_handler![
             load_wiki,
             save_wiki,
+            chunked_save::save_wiki_begin,
+            chunked_save::save_wiki_chunk,
+            chunked_save::save_wiki_commit,
+            chunked_save::save_wiki_abort,
             set_window_title,
+            sync_badge::set_window_sync_status,
             set_window_icon,
             set_headerbar_colors,
             get_window_label,
@@ -9073,8 +10034,48 @@ fn run_wiki_folder_mode(args: WikiFolderModeArgs) {
             pick_files_for_import,
             wiki_storage::get_external_attachments_config,
             wiki_storage::set_external_attachments_config,
+            image_import::process_imported_image,
+            video_import::transcode_video,
+            video_import::cancel_video_transcode,
+            attachment_migration::save_embedded_attachment,
+            attachment_migration::get_attachment_sizes,
+            attachment_migration::read_external_attachment,
+            audio::set_window_muted,
+            audio::set_window_volume,
+            media_session::media_session_update,
+            geolocation::get_current_position,
+            qr_code::decode_qr_frame,
+            qr_code::generate_qr_png,
+            server_discovery::get_server_urls,
+            server_discovery::get_server_qr_png,
+            idle::get_idle_time,
+            time_tracking::record_time,
+            time_tracking::get_time_report,
+            focus_timer::start_focus_timer,
+            focus_timer::stop_focus_timer,
+            focus_timer::get_focus_timer_status,
+            quick_actions::take_quick_captures,
+            wiki_commands::set_wiki_menu_commands,
+            mqtt::mqtt_connect,
+            mqtt::mqtt_publish,
+            mqtt::mqtt_disconnect,
+            serial::list_serial_ports,
+            serial::open_serial,
+            serial::read_serial,
+            serial::write_serial,
+            serial::close_serial,
+            allowed_commands::run_allowed_command,
+            allowed_commands::stop_allowed_command,
+            environment::get_environment,
+            plugin_dev::get_plugin_dev_status,
             wiki_storage::save_window_state,
             wiki_storage::js_log,
+            wiki_storage::get_accelerator_map,
+            wiki_storage::set_accelerator,
+            wiki_storage::reset_accelerators,
+            input_method::get_input_debug_info,
+            accessibility::get_accessibility_preferences,
+            app_lock::app_lock_activity,
             clipboard::get_clipboard_content,
             clipboard::set_clipboard_content,
             show_find_in_page,
@@ -9102,20 +10103,38 @@ fn run_wiki_folder_mode(args: WikiFolderModeArgs) {
             set_over_droppable,
             set_internal_drag_type,
             register_media_url,
+            transfer_stats::get_transfer_stats,
             // IPC commands for favicon sync
             ipc_update_favicon,
+            ipc_reopen_closed_wiki,
+            quick_switcher::ipc_show_quick_switcher,
+            ipc_restart_wiki,
+            extensions::extension_invoke,
             // LAN sync commands (fall back to IPC when sync manager not in this process)
             wiki_storage::get_wiki_sync_id,
             lan_sync::lan_sync_wiki_opened,
             lan_sync::lan_sync_announce_username,
+            lan_sync::lan_sync_share_clipboard,
+            lan_sync::lan_sync_get_shared_clipboard,
             lan_sync::lan_sync_tiddler_changed,
             lan_sync::lan_sync_tiddler_deleted,
+            lan_sync::lan_sync_get_conflicts,
+            lan_sync::lan_sync_conflict_local_version,
+            lan_sync::lan_sync_resolve_conflict,
+            lan_sync::tombstones::lan_sync_get_deletion_policy,
             lan_sync::lan_sync_send_full_sync,
             lan_sync::lan_sync_send_fingerprints,
             lan_sync::lan_sync_broadcast_fingerprints,
             lan_sync::lan_sync_broadcast_manifest,
             lan_sync::lan_sync_poll_ipc,
             lan_sync::lan_sync_load_tombstones,
+            lan_sync::snapshots::lan_sync_snapshot_needed,
+            lan_sync::snapshots::lan_sync_save_snapshot,
+            lan_sync::snapshots::lan_sync_load_snapshot,
+            lan_sync::snapshots::lan_sync_take_pending_rollback,
+            automation::automation_respond,
+            js_errors::report_js_error,
+            devtools::open_devtools,
             lan_sync::lan_sync_save_tombstones,
             lan_sync::lan_sync_collab_editing_started,
             lan_sync::lan_sync_collab_editing_stopped,
@@ -9133,8 +10152,12 @@ fn run_wiki_folder_mode(args: WikiFolderModeArgs) {
             lan_sync::relay_sync_connect_room,
             lan_sync::relay_sync_disconnect_room,
             lan_sync::relay_sync_set_room_auto_connect,
+            lan_sync::relay_sync_set_room_relay_fallback,
             lan_sync::relay_sync_set_room_password,
             lan_sync::relay_sync_set_room_name,
+            lan_sync::relay_sync_get_room_directory,
+            lan_sync::relay_sync_revoke_device,
+            lan_sync::relay_sync_unrevoke_device,
             lan_sync::relay_sync_get_room_credentials,
             lan_sync::relay_sync_set_url,
             lan_sync::relay_sync_generate_credentials,
@@ -9159,13 +10182,22 @@ fn run_wiki_folder_mode(args: WikiFolderModeArgs) {
             wiki_storage::set_wiki_relay_room,
             // Per-wiki sync mode (bidirectional / send-only / receive-only)
             wiki_storage::set_wiki_sync_mode,
+            wiki_storage::set_wiki_confirm_remote_deletions,
+            wiki_storage::get_wiki_sync_schedule,
+            wiki_storage::set_wiki_sync_schedule,
+            wiki_storage::get_wiki_sync_priority,
+            wiki_storage::set_wiki_sync_priority,
             wiki_storage::get_wiki_sync_mode,
             get_wiki_installed_plugins,
             install_plugins_to_wiki
         ])
         .build(tauri::generate_context!())
         .expect("error while building wiki-folder-mode application")
-        .run(|_app, _event| {});
+        .run(|app, event| {
+            if let tauri::RunEvent::Exit = event {
+                process_registry::forget_process(app);
+            }
+        });
 }
 
 /// Windows: Check if Microsoft Edge version 131+ is installed
@@ -9316,6 +10348,48 @@ pub fn run() {
     #[cfg(target_os = "linux")]
     std::env::set_var("GTK_OVERLAY_SCROLLING", "0");
 
+    // --install-service / --uninstall-service: set up the headless server and exit
+    #[cfg(not(target_os = "android"))]
+    if let Some(code) = service::run_cli() {
+        std::process::exit(code);
+    }
+
+    // --mcp: serve shared wikis to AI assistants on stdio and exit when done
+    #[cfg(not(target_os = "android"))]
+    if let Some(code) = mcp::run_cli() {
+        std::process::exit(code);
+    }
+
+    // --headless <command>: run a batch command without windows and exit
+    #[cfg(not(target_os = "android"))]
+    if let Some(command) = headless::parse_args(&std::env::args().collect::<Vec<_>>()) {
+        match command {
+            Ok(command) => run_headless_mode(command),
+            Err(e) => {
+                eprintln!("{}\n{}", e, headless::USAGE);
+                std::process::exit(2);
+            }
+        }
+        return;
+    }
+
+    // --list-wikis, --backup, --sync-now: through the running instance's control API,
+    // or on their own like --headless commands
+    #[cfg(not(target_os = "android"))]
+    if let Some(command) = cli::parse_args(&std::env::args().collect::<Vec<_>>()) {
+        match command {
+            Ok(command) => match cli::run(command) {
+                cli::Outcome::Exit(code) => std::process::exit(code),
+                cli::Outcome::Standalone(command) => run_headless_mode(command),
+            },
+            Err(e) => {
+                eprintln!("{}\n{}", e, cli::USAGE);
+                std::process::exit(2);
+            }
+        }
+        return;
+    }
+
     // Windows: Check WebView2 version at startup
     #[cfg(target_os = "windows")]
     check_webview2_version();
@@ -9343,7 +10417,22 @@ pub fn run() {
     // Main process: Start the IPC server for wiki process coordination
     // IPC server is desktop-only (Android uses single-process architecture)
     #[cfg(not(target_os = "android"))]
-    {
+    let ipc_listener = match ipc::IpcServer::bind() {
+        Ok(listener) => Some(listener),
+        Err(e) if e.kind() == std::io::ErrorKind::AddrInUse => {
+            // Another instance is running: let it open our wikis, or run alongside it
+            if instance::hand_off_or_run_secondary() {
+                return;
+            }
+            None
+        }
+        Err(e) => {
+            eprintln!("[TiddlyDesktop] IPC server error: {}", e);
+            None
+        }
+    };
+    #[cfg(not(target_os = "android"))]
+    if let Some(ipc_listener) = ipc_listener {
         let server = Arc::new(ipc::IpcServer::new());
         let _ = GLOBAL_IPC_SERVER.set(server.clone());
 
@@ -9380,9 +10469,15 @@ pub fn run() {
             // When a new wiki client registers, send sync-activate if sync is enabled
             // This ensures wiki processes in separate OS processes receive activation
             // (app.emit() only reaches webviews in the same process)
-            server.on_client_registered(|wiki_path| {
+            server.on_client_registered(|wiki_path, pid, is_tiddler_window| {
                 eprintln!("[IPC] on_client_registered: wiki_path={:?}", wiki_path);
                 if let Some(app) = GLOBAL_APP_HANDLE.get() {
+                    // Wikis that outlived a previous main process (or were opened
+                    // from a shortcut) are tracked from now on
+                    if !is_tiddler_window {
+                        process_registry::adopt(app, pid, &wiki_path);
+                    }
+                    audio::wiki_registered(app, &wiki_path);
                     let entries = wiki_storage::load_recent_files_from_disk(app);
                     let mut found = false;
                     for entry in &entries {
@@ -9422,7 +10517,34 @@ pub fn run() {
                 }
             });
 
-            if let Err(e) = server.start() {
+            // Wikis from the command line of a second instance
+            server.on_hand_off(|paths| {
+                if let Some(app_handle) = GLOBAL_APP_HANDLE.get() {
+                    let app_handle = app_handle.clone();
+                    if paths.is_empty() {
+                        let handle = app_handle.clone();
+                        let _ = app_handle.run_on_main_thread(move || reveal_or_create_main_window(&handle));
+                        return;
+                    }
+                    file_open::open_wikis(&app_handle, paths);
+                }
+            });
+
+            // A wiki process over its memory limit is about to exit and wants to be reopened
+            server.on_restart_wiki(|path| {
+                if let Some(app_handle) = GLOBAL_APP_HANDLE.get() {
+                    memory_limit::restart_wiki(app_handle, path);
+                }
+            });
+
+            // Ctrl/Cmd+Shift+T in a wiki window
+            server.on_reopen_closed_wiki(|| {
+                if let Some(app_handle) = GLOBAL_APP_HANDLE.get() {
+                    recently_closed::reopen_in_background(app_handle);
+                }
+            });
+
+            if let Err(e) = server.start(ipc_listener) {
                 eprintln!("[TiddlyDesktop] IPC server error: {}", e);
             }
         });
@@ -9520,6 +10642,28 @@ pub fn run() {
 
             println!("Main wiki path: {:?}", main_wiki_path);
 
+            // Portable mode: convert absolute wiki paths from older versions to relative ones
+            wiki_storage::migrate_portable_paths(app.handle());
+
+            // Keep the last versions of the landing page
+            #[cfg(not(target_os = "android"))]
+            let snapshot_landing_page = !instance::is_secondary();
+            #[cfg(target_os = "android")]
+            let snapshot_landing_page = true;
+            if snapshot_landing_page {
+                landing_snapshots::start(main_wiki_path.clone());
+            }
+
+            // Remove build directories left behind by crashed or killed builds
+            scratch_dir::cleanup_stale(app.handle());
+            watched_folders::start(app.handle());
+            #[cfg(not(target_os = "android"))]
+            app_lock::start(app.handle(), true, true);
+            #[cfg(not(target_os = "android"))]
+            idle::start(app.handle());
+            #[cfg(not(target_os = "android"))]
+            focus_timer::start(app.handle(), true);
+
             // Initialize app state
 
 
@@ -9534,10 +10678,24 @@ pub fn run() {
                 saf_wiki_mappings: Mutex::new(HashMap::new()),
             });
 
+            // Take over wiki processes that outlived a previous main process
+            #[cfg(not(target_os = "android"))]
+            if instance::is_secondary() {
+                instance::show_secondary_warning(app.handle());
+            } else {
+                process_registry::write_session(app.handle());
+                session_restore::load_last(app.handle());
+                process_registry::adopt_running(app.handle());
+                session_restore::start(app.handle());
+                memory_limit::start(app.handle());
+                scheduled_backups::start(app.handle());
+                search_index::start(app.handle());
+            }
+
             // Start localhost HTTP media server (Linux: GStreamer needs HTTP URLs;
             // also used for folder wikis on all platforms)
             #[cfg(not(target_os = "android"))]
-            match media_server::MediaServer::start() {
+            match media_server::MediaServer::start(&server_address::Binding::current(app.handle())) {
                 Ok(server) => {
                     app.manage(MediaServerState { server });
                 }
@@ -9561,12 +10719,26 @@ pub fn run() {
                 // Start background event loop and auto-connect relay rooms
                 // (independent of LAN sync — relay rooms can run without LAN sync)
                 // Passes app handle so configs can be persisted before sync starts.
+                // A secondary instance leaves sync to the running one.
+                #[cfg(not(target_os = "android"))]
+                let start_sync = !instance::is_secondary();
+                #[cfg(target_os = "android")]
+                let start_sync = true;
                 let app_for_sync = app.handle().clone();
-                tauri::async_runtime::spawn(async move {
-                    if let Some(mgr) = lan_sync::get_sync_manager() {
-                        mgr.start_background(Some(app_for_sync)).await;
-                    }
-                });
+                if start_sync {
+                    metered::start(app.handle());
+                    tauri::async_runtime::spawn(async move {
+                        if let Some(mgr) = lan_sync::get_sync_manager() {
+                            mgr.start_background(Some(app_for_sync)).await;
+                            // A headless node anchors the LAN mesh, with or without relay rooms
+                            if serve_all::is_active() {
+                                if let Err(e) = mgr.start().await {
+                                    eprintln!("[TiddlyDesktop] Failed to start LAN sync: {}", e);
+                                }
+                            }
+                        }
+                    });
+                }
 
             }
 
@@ -9584,134 +10756,148 @@ pub fn run() {
             // Use wikifile:// protocol to load main wiki
             let wiki_url = format!("wikifile://localhost/{}", path_key);
 
-            // Load saved window state for landing page
-            let saved_state = wiki_storage::get_window_state(&app.handle(), "__LANDING_PAGE__");
-            let (win_width, win_height) = {
-                let (w, h) = saved_state.as_ref()
-                    .map(|s| (s.width as f64, s.height as f64))
-                    .unwrap_or((800.0, 600.0));
-
-                // On Linux, clamp size to prevent GNOME's auto-maximize (only if not maximized)
-                #[cfg(target_os = "linux")]
-                let (w, h) = if !saved_state.as_ref().map(|s| s.maximized).unwrap_or(false) {
-                    linux_clamp_window_size(w, h)
-                } else {
-                    (w, h)
-                };
+            // Headless server mode: no landing page, tray or global hotkeys
+            let headless = serve_all::is_active();
 
-                (w, h)
+            // Desktop: a newer bundled landing page is merged in the background
+            // behind a progress splash; the main window opens once that's done
+            #[cfg(not(target_os = "android"))]
+            let migrating = !headless && match get_bundled_index_path(app).ok()
+                .and_then(|bundled| main_wiki_migration::pending(&main_wiki_path, &bundled))
+            {
+                Some(migration) => {
+                    if let Err(e) = main_wiki_migration::show_splash(app.handle()) {
+                        eprintln!("[TiddlyDesktop] {}", e);
+                    }
+                    main_wiki_migration::start(app.handle(), migration, reveal_or_create_main_window);
+                    true
+                }
+                None => false,
             };
-            eprintln!("[TiddlyDesktop] Landing page saved state: {:?}", saved_state);
-            eprintln!("[TiddlyDesktop] Using size: {}x{}", win_width, win_height);
-
-            // Get effective language (user preference or system-detected)
-            let language = wiki_storage::get_effective_language(&app.handle());
-            eprintln!("[TiddlyDesktop] UI language: {}", language);
+            #[cfg(target_os = "android")]
+            let migrating = false;
+
+            if !migrating && !headless {
+                // Load saved window state for landing page
+                let saved_state = wiki_storage::get_window_state(&app.handle(), "__LANDING_PAGE__");
+                let (win_width, win_height) = {
+                    let (w, h) = saved_state.as_ref()
+                        .map(|s| (s.width as f64, s.height as f64))
+                        .unwrap_or((800.0, 600.0));
+
+                    // On Linux, clamp size to prevent GNOME's auto-maximize (only if not maximized)
+                    #[cfg(target_os = "linux")]
+                    let (w, h) = if !saved_state.as_ref().map(|s| s.maximized).unwrap_or(false) {
+                        linux_clamp_window_size(w, h)
+                    } else {
+                        (w, h)
+                    };
 
-            // Create the main window programmatically with initialization script
-            // Use full init script with is_main_wiki=true so setupExternalAttachments knows to skip
-            #[cfg(not(target_os = "android"))]
-            let icon = Image::from_bytes(include_bytes!("../icons/icon.png"))?;
-            #[allow(unused_mut)]
-            #[cfg(not(target_os = "android"))]
-            let mut builder = WebviewWindowBuilder::new(app, "main", WebviewUrl::External(wiki_url.parse().unwrap()))
-                .title("TiddlyDesktopRS")
-                .inner_size(win_width, win_height)
-                .icon(icon)?
-                .window_classname("tiddlydesktop-rs")
-                .initialization_script(&init_script::get_wiki_init_script_with_language(&main_wiki_path.to_string_lossy(), "main", true, Some(&language)))
-                .zoom_hotkeys_enabled(true)
-                .devtools(cfg!(debug_assertions)); // Only enable in debug builds
+                    (w, h)
+                };
+                eprintln!("[TiddlyDesktop] Landing page saved state: {:?}", saved_state);
+                eprintln!("[TiddlyDesktop] Using size: {}x{}", win_width, win_height);
+
+                // Get effective language (user preference or system-detected)
+                let language = wiki_storage::get_effective_language(&app.handle());
+                eprintln!("[TiddlyDesktop] UI language: {}", language);
+
+                // Create the main window programmatically with initialization script
+                // Use full init script with is_main_wiki=true so setupExternalAttachments knows to skip
+                #[cfg(not(target_os = "android"))]
+                let icon = Image::from_bytes(include_bytes!("../icons/icon.png"))?;
+                #[allow(unused_mut)]
+                #[cfg(not(target_os = "android"))]
+                let mut builder = WebviewWindowBuilder::new(app, "main", WebviewUrl::External(wiki_url.parse().unwrap()))
+                    .title("TiddlyDesktopRS")
+                    .inner_size(win_width, win_height)
+                    .icon(icon)?
+                    .window_classname("tiddlydesktop-rs")
+                    .initialization_script(&init_script::get_wiki_init_script_with_language(&main_wiki_path.to_string_lossy(), "main", true, Some(&language)))
+                    .zoom_hotkeys_enabled(true)
+                    .devtools(devtools::enabled()); // Debug builds, or remote debugging on (devtools.rs)
 
-            #[cfg(target_os = "linux")]
-            let mut builder = builder.user_agent(LINUX_USER_AGENT);
+                #[cfg(target_os = "linux")]
+                let mut builder = builder.user_agent(LINUX_USER_AGENT);
+
+                // Android: Extract resources synchronously if needed (first run)
+                // This takes ~1.5 seconds with ZIP extraction, so we do it before window creation
+                #[cfg(target_os = "android")]
+                if needs_resource_extraction(app) {
+                    eprintln!("[TiddlyDesktop] First run detected, extracting resources...");
+                    if let Err(e) = extract_tiddlywiki_resources(app) {
+                        eprintln!("[TiddlyDesktop] Resource extraction failed: {}", e);
+                    }
+                }
 
-            // Android: Extract resources synchronously if needed (first run)
-            // This takes ~1.5 seconds with ZIP extraction, so we do it before window creation
-            #[cfg(target_os = "android")]
-            if needs_resource_extraction(app) {
-                eprintln!("[TiddlyDesktop] First run detected, extracting resources...");
-                if let Err(e) = extract_tiddlywiki_resources(app) {
-                    eprintln!("[TiddlyDesktop] Resource extraction failed: {}", e);
+                // Android: Verify Node.js binary is ready (extracted via ZIP in extract_tiddlywiki_resources)
+                #[cfg(target_os = "android")]
+                if let Err(e) = android::node_bridge::ensure_node_binary(app) {
+                    eprintln!("[TiddlyDesktop] Node.js binary check failed: {}", e);
+                    // Non-fatal - wiki viewing still works, just not creation/serving
                 }
-            }
 
-            // Android: Verify Node.js binary is ready (extracted via ZIP in extract_tiddlywiki_resources)
-            #[cfg(target_os = "android")]
-            if let Err(e) = android::node_bridge::ensure_node_binary(app) {
-                eprintln!("[TiddlyDesktop] Node.js binary check failed: {}", e);
-                // Non-fatal - wiki viewing still works, just not creation/serving
-            }
+                // Android: Clean up any stale wiki mirror directories from previous sessions
+                #[cfg(target_os = "android")]
+                android::node_bridge::cleanup_stale_wiki_mirrors();
+
+                // Android: Create window with wiki URL directly (resources are already extracted)
+                // Note: Individual wikis open in separate WikiActivity instances (not Tauri-based)
+                #[cfg(target_os = "android")]
+                let builder = WebviewWindowBuilder::new(app, "main", WebviewUrl::External(wiki_url.parse().unwrap()))
+                    .initialization_script(&init_script::get_wiki_init_script_with_language(&main_wiki_path.to_string_lossy(), "main", true, Some(&language)));
+
+                // Apply saved position if available, with monitor validation on Windows/macOS
+                // (Android windows are fullscreen - no position needed)
+                #[cfg(not(target_os = "android"))]
+                if let Some(ref state) = saved_state {
+                    let (x, y) = validate_window_position(app.handle(), state);
+                    builder = builder.position(x, y);
+                }
 
-            // Android: Clean up any stale wiki mirror directories from previous sessions
-            #[cfg(target_os = "android")]
-            android::node_bridge::cleanup_stale_wiki_mirrors();
+                // Tauri's drag/drop handler: On Windows, our WRY patch intercepts drops,
+                // extracts file paths, emits tauri://drag-* events, then forwards to WebView2.
+                // On Linux, vanilla WebKitGTK handles drops natively.
 
-            // Android: Create window with wiki URL directly (resources are already extracted)
-            // Note: Individual wikis open in separate WikiActivity instances (not Tauri-based)
-            #[cfg(target_os = "android")]
-            let builder = WebviewWindowBuilder::new(app, "main", WebviewUrl::External(wiki_url.parse().unwrap()))
-                .initialization_script(&init_script::get_wiki_init_script_with_language(&main_wiki_path.to_string_lossy(), "main", true, Some(&language)));
+                let main_window = builder.build()?;
 
-            // Apply saved position if available, with monitor validation on Windows/macOS
-            // (Android windows are fullscreen - no position needed)
-            #[cfg(not(target_os = "android"))]
-            if let Some(ref state) = saved_state {
-                let (x, y) = validate_window_position(app.handle(), state);
-                builder = builder.position(x, y);
-            }
+                // Note: Drag handlers are set up via the drag_drop plugin's on_webview_ready hook
 
-            // Tauri's drag/drop handler: On Windows, our WRY patch intercepts drops,
-            // extracts file paths, emits tauri://drag-* events, then forwards to WebView2.
-            // On Linux, vanilla WebKitGTK handles drops natively.
+                // Linux: Set up HeaderBar, enable smooth scrolling, finalize window state
+                #[cfg(target_os = "linux")]
+                {
+                    setup_header_bar(&main_window);
+                    enable_smooth_scrolling(&main_window);
+                    linux_finalize_window_state(&main_window, &saved_state);
+                }
 
-            let main_window = builder.build()?;
+                // Apply IME configuration (GTK IM module / Windows IMM context)
+                input_method::configure_window(&main_window);
 
-            // Note: Drag handlers are set up via the drag_drop plugin's on_webview_ready hook
+                // Restore maximized state (Windows/macOS only - Linux handled in linux_finalize_window_state)
+                // (Android windows are always fullscreen)
+                #[cfg(not(any(target_os = "linux", target_os = "android")))]
+                if saved_state.as_ref().map(|s| s.maximized).unwrap_or(false) {
+                    let _ = main_window.maximize();
+                }
 
-            // Linux: Set up HeaderBar, enable smooth scrolling, finalize window state
-            #[cfg(target_os = "linux")]
-            {
-                setup_header_bar(&main_window);
-                enable_smooth_scrolling(&main_window);
-                linux_finalize_window_state(&main_window, &saved_state);
+                // Android: window is already created with wiki URL (resources extracted synchronously above)
+                #[cfg(target_os = "android")]
+                let _ = &main_window;
             }
 
-            // Restore maximized state (Windows/macOS only - Linux handled in linux_finalize_window_state)
-            // (Android windows are always fullscreen)
-            #[cfg(not(any(target_os = "linux", target_os = "android")))]
-            if saved_state.as_ref().map(|s| s.maximized).unwrap_or(false) {
-                let _ = main_window.maximize();
+            #[cfg(not(target_os = "android"))]
+            if headless {
+                serve_all::start(app.handle());
+            } else {
+                setup_system_tray(app)?;
+                quick_actions::register_hotkeys(app.handle());
+                automation::start(app.handle());
             }
 
-            // Android: window is already created with wiki URL (resources extracted synchronously above)
-            #[cfg(target_os = "android")]
-            let _ = &main_window;
-
+            // Wikis and deep links passed as command-line arguments (file associations, "Open With")
             #[cfg(not(target_os = "android"))]
-            setup_system_tray(app)?;
-
-            // Handle files passed as command-line arguments
-            let args: Vec<String> = std::env::args().skip(1).collect();
-            for arg in args {
-                let path = PathBuf::from(&arg);
-                // Only open files that exist and have .html or .htm extension
-                if path.exists() && path.is_file() {
-                    if let Some(ext) = path.extension() {
-                        let ext_lower = ext.to_string_lossy().to_lowercase();
-                        if ext_lower == "html" || ext_lower == "htm" {
-                            let app_handle = app.handle().clone();
-                            let path_str = arg.clone();
-                            tauri::async_runtime::spawn(async move {
-                                if let Ok(entry) = open_wiki_window(app_handle.clone(), path_str, None, None, None).await {
-                                    // Emit event to refresh wiki list in main window
-                                    let _ = app_handle.emit("wiki-list-changed", entry);
-                                }
-                            });
-                        }
-                    }
-                }
-            }
+            file_open::open_wikis(app.handle(), file_open::command_line_wikis());
 
             Ok(())
         })
@@ -9722,18 +10908,41 @@ pub fn run() {
             std::thread::spawn(move || {
                 responder.respond(tdasset_protocol_handler(request));
             });
+        })
+        .register_asynchronous_uri_scheme_protocol("tdext", |ctx, request, responder| {
+            let app = ctx.app_handle().clone();
+            std::thread::spawn(move || {
+                responder.respond(extensions::protocol_handler(&app, request));
+            });
         });
         // tdlib:// protocol is desktop-only (Android serves libraries via WikiHttpServer /_td/)
         #[cfg(not(target_os = "android"))]
         let builder = builder.register_uri_scheme_protocol("tdlib", |ctx, request| {
             tdlib_protocol_handler(ctx.app_handle(), request)
         });
+        // Native notifications (focus timer) and global hotkeys are desktop-only
+        #[cfg(not(target_os = "android"))]
+        let builder = builder.plugin(tauri_plugin_notification::init()).plugin(
+            tauri_plugin_global_shortcut::Builder::new()
+                .with_handler(|app, shortcut, event| {
+                    if event.state() == tauri_plugin_global_shortcut::ShortcutState::Pressed
+                        && !wiki_hotkeys::handle_shortcut(app, shortcut)
+                    {
+                        quick_actions::handle_shortcut(app, shortcut);
+                    }
+                })
+                .build(),
+        );
         builder.plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
         .invoke_handler(tauri::generate_handler![
             load_wiki,
             save_wiki,
+            chunked_save::save_wiki_begin,
+            chunked_save::save_wiki_chunk,
+            chunked_save::save_wiki_commit,
+            chunked_save::save_wiki_abort,
             open_wiki_window,
             open_wiki_folder,
             open_tiddler_window,
@@ -9761,11 +10970,67 @@ pub fn run() {
             wiki_storage::get_recent_files,
             wiki_storage::remove_recent_file,
             wiki_storage::reconcile_recent_files,
+            wiki_storage::relocate_wiki,
+            scratch_dir::get_scratch_dir,
+            scratch_dir::set_scratch_dir,
+            removable_media::get_wiki_storage_status,
+            downloads::get_download_configs,
+            downloads::set_download_config,
+            attachment_manifest::verify_attachments,
+            attachment_manifest::accept_attachment_changes,
+            wiki_archive::export_wiki_archive,
+            qr_code::generate_qr_png,
+            server_discovery::get_server_urls,
+            server_discovery::get_server_qr_png,
+            idle::get_idle_time,
+            time_tracking::get_time_report,
+            focus_timer::start_focus_timer,
+            focus_timer::stop_focus_timer,
+            focus_timer::get_focus_timer_status,
+            quick_actions::get_quick_actions,
+            quick_actions::set_quick_actions,
+            wiki_hotkeys::set_wiki_hotkey,
+            wiki_hotkeys::clear_wiki_hotkey,
+            webhooks::get_webhooks,
+            webhooks::set_webhooks,
+            allowed_commands::get_allowed_commands,
+            allowed_commands::set_allowed_commands,
+            environment::get_environment,
+            first_run::get_first_run_state,
+            first_run::use_portable_mode,
+            classic_import::import_classic_wikis,
+            app_config::export_app_config,
+            app_config::import_app_config,
+            first_run::finish_first_run,
+            quick_switcher::show_quick_switcher,
+            quick_switcher::quick_switcher_search,
+            quick_switcher::quick_switcher_open,
+            browser_mode::get_external_browser_wikis,
+            browser_mode::set_external_browser,
+            geolocation::get_geolocation_permissions,
+            geolocation::set_geolocation_permission,
+            serial::get_serial_permissions,
+            serial::set_serial_permission,
+            mqtt::get_mqtt_permissions,
+            mqtt::set_mqtt_permission,
+            folder_snapshot::get_folder_snapshot_configs,
+            folder_snapshot::set_folder_snapshot_config,
+            folder_snapshot::snapshot_folder_wiki_now,
+            watched_folders::get_watched_folders,
+            watched_folders::get_watched_folders_summary,
+            watched_folders::add_watched_folder,
+            watched_folders::remove_watched_folder,
+            conflict_copies::get_conflict_copies,
+            conflict_copies::compare_conflict_copy,
+            conflict_copies::merge_conflict_copy,
+            desktop_shortcut::create_desktop_shortcut,
             wiki_storage::save_full_wiki_list,
             wiki_storage::set_wiki_backups,
             wiki_storage::set_wiki_backup_dir,
             wiki_storage::set_wiki_backup_count,
+            scheduled_backups::set_wiki_scheduled_backup,
             wiki_storage::update_wiki_favicon,
+            wiki_storage::set_wiki_appearance,
             wiki_storage::get_wiki_backup_dir_setting,
             wiki_storage::set_wiki_sync,
             wiki_storage::get_wiki_sync_id,
@@ -9794,6 +11059,8 @@ pub fn run() {
             wiki_storage::set_custom_edition_path,
             wiki_storage::get_share_templates,
             wiki_storage::save_share_templates_config,
+            input_method::get_ime_settings,
+            input_method::set_ime_settings,
             open_auth_window,
             clear_wiki_session,
 
@@ -9803,8 +11070,54 @@ pub fn run() {
             set_zoom_level,
             download_file,
             wiki_storage::js_log,
+            wiki_storage::get_accelerator_map,
+            wiki_storage::set_accelerator,
+            wiki_storage::reset_accelerators,
+            input_method::get_input_debug_info,
+            accessibility::get_accessibility_preferences,
+            app_lock::app_lock_activity,
+            app_lock::unlock_app,
+            app_lock::get_app_lock_settings,
+            app_lock::set_app_lock_secret,
+            app_lock::set_app_lock_timeout,
+            app_lock::lock_app_now,
+            throttle::get_throttle_hidden_minutes,
+            throttle::set_throttle_hidden_minutes,
+            memory_limit::get_memory_limit_settings,
+            memory_limit::set_memory_limit,
+            folder_server::get_native_folder_server,
+            folder_server::set_native_folder_server,
+            folder_server::get_folder_webdav,
+            folder_server::set_folder_webdav,
+            server_address::get_server_listen,
+            server_address::set_server_listen,
+            extensions::list_extensions,
+            extensions::set_extension_enabled,
+            extensions::open_extensions_folder,
+            extensions::extension_invoke,
             clipboard::get_clipboard_content,
             clipboard::set_clipboard_content,
+            backup_history::get_tiddler_history,
+            backup_history::list_backups,
+            backup_history::diff_backup,
+            backup_history::restore_backup,
+            text_diff::diff_texts,
+            wiki_filter::filter_wiki,
+            sqlite_wiki::import_sqlite_wiki,
+            sqlite_wiki::export_sqlite_wiki,
+            main_wiki_migration::report_migration_boot,
+            landing_snapshots::list_app_wiki_snapshots,
+            landing_snapshots::restore_app_wiki,
+            batch_register::pick_and_register_wikis,
+            recently_closed::reopen_last_closed_wiki,
+            custom_snippets::list_custom_snippets,
+            custom_snippets::add_custom_snippet,
+            custom_snippets::remove_custom_snippet,
+            custom_snippets::set_custom_snippets_disabled,
+            session_restore::get_last_session,
+            session_restore::restore_last_session,
+            session_restore::dismiss_last_session,
+            session_restore::set_session_restore_mode,
             start_native_drag,
             prepare_native_drag,
             cleanup_native_drag,
@@ -9848,10 +11161,33 @@ pub fn run() {
             lan_sync::lan_sync_start,
             lan_sync::lan_sync_stop,
             lan_sync::lan_sync_get_status,
+            lan_sync::interfaces::lan_sync_get_interfaces,
+            lan_sync::interfaces::lan_sync_set_interfaces,
+            lan_sync::preview::preview_sync,
+            lan_sync::tombstones::lan_sync_recent_deletions,
+            lan_sync::tombstones::lan_sync_set_tombstone_days,
+            lan_sync::bundle::lan_sync_export_bundle,
+            lan_sync::bundle::lan_sync_import_bundle,
+            lan_sync::lan_sync_restore_deletion,
+            lan_sync::lan_sync_confirm_deletion,
+            lan_sync::sync_now,
+            lan_sync::snapshots::rollback_last_sync,
+            transfer_stats::get_transfer_stats,
+            transfer_stats::reset_transfer_stats,
+            metered::get_metered_status,
+            metered::set_metered_allowed,
             lan_sync::lan_sync_get_wiki_peers,
             lan_sync::lan_sync_announce_username,
+            lan_sync::lan_sync_share_clipboard,
+            lan_sync::lan_sync_get_shared_clipboard,
+            lan_sync::lan_sync_get_clipboard_peers,
+            lan_sync::lan_sync_set_shared_clipboard,
             lan_sync::lan_sync_tiddler_changed,
             lan_sync::lan_sync_tiddler_deleted,
+            lan_sync::lan_sync_get_conflicts,
+            lan_sync::lan_sync_conflict_local_version,
+            lan_sync::lan_sync_resolve_conflict,
+            lan_sync::tombstones::lan_sync_get_deletion_policy,
             lan_sync::lan_sync_wiki_opened,
             lan_sync::lan_sync_get_available_wikis,
             lan_sync::lan_sync_request_wiki,
@@ -9861,6 +11197,36 @@ pub fn run() {
             lan_sync::lan_sync_broadcast_manifest,
             lan_sync::lan_sync_poll_ipc,
             lan_sync::lan_sync_load_tombstones,
+            lan_sync::snapshots::lan_sync_snapshot_needed,
+            lan_sync::snapshots::lan_sync_save_snapshot,
+            lan_sync::snapshots::lan_sync_load_snapshot,
+            lan_sync::snapshots::lan_sync_take_pending_rollback,
+            automation::get_automation_api,
+            safe_mode::open_wiki_safe_mode,
+            mcp::get_mcp_access,
+            mcp::set_mcp_access,
+            wiki_env::get_wiki_env_vars,
+            wiki_env::set_wiki_env_var,
+            wiki_env::remove_wiki_env_var,
+            plugin_compat::check_plugin_compatibility,
+            search_index::search_all_wikis,
+            search_index::open_search_result,
+            plugin_dev::open_wiki_plugin_dev,
+            media_manifest::generate_media_manifest,
+            incremental_save::get_incremental_save,
+            incremental_save::set_incremental_save,
+            automation::set_automation_api,
+            js_errors::report_js_error,
+            js_errors::get_js_error_counts,
+            js_errors::get_js_errors,
+            js_errors::clear_js_errors,
+            devtools::open_devtools,
+            devtools::enable_remote_debugging,
+            devtools::disable_remote_debugging,
+            devtools::list_debug_targets,
+            tiddler_image::render_tiddler_image,
+            tiddler_image::report_tiddler_image,
+            visual_regression::visual_regression_check,
             lan_sync::lan_sync_save_tombstones,
             lan_sync::lan_sync_collab_editing_started,
             lan_sync::lan_sync_collab_editing_stopped,
@@ -9878,8 +11244,12 @@ pub fn run() {
             lan_sync::relay_sync_connect_room,
             lan_sync::relay_sync_disconnect_room,
             lan_sync::relay_sync_set_room_auto_connect,
+            lan_sync::relay_sync_set_room_relay_fallback,
             lan_sync::relay_sync_set_room_password,
             lan_sync::relay_sync_set_room_name,
+            lan_sync::relay_sync_get_room_directory,
+            lan_sync::relay_sync_revoke_device,
+            lan_sync::relay_sync_unrevoke_device,
             lan_sync::relay_sync_get_room_credentials,
             lan_sync::relay_sync_set_url,
             lan_sync::relay_sync_generate_credentials,
@@ -9904,6 +11274,11 @@ pub fn run() {
             wiki_storage::set_wiki_relay_room,
             // Per-wiki sync mode (bidirectional / send-only / receive-only)
             wiki_storage::set_wiki_sync_mode,
+            wiki_storage::set_wiki_confirm_remote_deletions,
+            wiki_storage::get_wiki_sync_schedule,
+            wiki_storage::set_wiki_sync_schedule,
+            wiki_storage::get_wiki_sync_priority,
+            wiki_storage::set_wiki_sync_priority,
             wiki_storage::get_wiki_sync_mode,
             get_wiki_installed_plugins,
             install_plugins_to_wiki
@@ -9919,28 +11294,15 @@ pub fn run() {
                     if wiki_count > 0 {
                         eprintln!("[TiddlyDesktop] Preventing exit - {} wiki(s) still open", wiki_count);
                         api.prevent_exit();
+                    } else if browser_mode::has_sessions() {
+                        eprintln!("[TiddlyDesktop] Preventing exit - wikis still served to the browser");
+                        api.prevent_exit();
                     }
                 }
-                // Handle files opened via macOS file associations
+                // Wikis opened via macOS file associations, "Open With" or the Dock icon
                 #[cfg(target_os = "macos")]
                 tauri::RunEvent::Opened { urls } => {
-                    for url in urls {
-                        if let Ok(path) = url.to_file_path() {
-                            if let Some(ext) = path.extension() {
-                                let ext_lower = ext.to_string_lossy().to_lowercase();
-                                if ext_lower == "html" || ext_lower == "htm" {
-                                    let app_handle = app.clone();
-                                    let path_str = path.to_string_lossy().to_string();
-                                    tauri::async_runtime::spawn(async move {
-                                        if let Ok(entry) = open_wiki_window(app_handle.clone(), path_str, None, None, None).await {
-                                            // Emit event to refresh wiki list in main window
-                                            let _ = app_handle.emit("wiki-list-changed", entry);
-                                        }
-                                    });
-                                }
-                            }
-                        }
-                    }
+                    file_open::open_wikis(app, file_open::wikis_from_urls(&urls));
                 }
                 _ => {}
             }
@@ -10239,6 +11601,72 @@ pub extern "system" fn Java_com_burningtreec_tiddlydesktop_1rs_WikiActivity_pdfC
     }
 }
 
+/// JNI: Decode a QR code or barcode in a photo taken with the camera app.
+/// Returns the text, an empty string if there is no code, or "ERROR:..." on failure.
+#[cfg(target_os = "android")]
+#[no_mangle]
+pub extern "system" fn Java_com_burningtreec_tiddlydesktop_1rs_WikiActivity_decodeQrImage<'a>(
+    mut env: jni::JNIEnv<'a>,
+    _class: jni::objects::JClass<'a>,
+    path: jni::objects::JString<'a>,
+) -> jni::objects::JString<'a> {
+    let path_str: String = match env.get_string(&path) {
+        Ok(s) => s.into(),
+        Err(e) => {
+            return env.new_string(format!("ERROR:Failed to get path: {}", e)).unwrap();
+        }
+    };
+    match qr_code::decode_image_file(&path_str) {
+        Ok(text) => env.new_string(text.unwrap_or_default()).unwrap(),
+        Err(e) => env.new_string(format!("ERROR:{}", e)).unwrap(),
+    }
+}
+
+/// JNI: Current position for the wiki a WikiActivity shows, if the wiki may use it.
+/// Returns the position as JSON, an empty string if the user hasn't been asked yet,
+/// or "ERROR:..." on failure.
+#[cfg(target_os = "android")]
+#[no_mangle]
+pub extern "system" fn Java_com_burningtreec_tiddlydesktop_1rs_WikiActivity_currentPosition<'a>(
+    mut env: jni::JNIEnv<'a>,
+    _class: jni::objects::JClass<'a>,
+    data_dir: jni::objects::JString<'a>,
+    wiki_path: jni::objects::JString<'a>,
+) -> jni::objects::JString<'a> {
+    let data_dir_str: Option<String> = env.get_string(&data_dir).ok().map(Into::into);
+    let wiki_path_str: Option<String> = env.get_string(&wiki_path).ok().map(Into::into);
+    let (Some(data_dir_str), Some(wiki_path_str)) = (data_dir_str, wiki_path_str) else {
+        return env.new_string("ERROR:Failed to get wiki path").unwrap();
+    };
+    let result = geolocation::android_position(std::path::Path::new(&data_dir_str), &wiki_path_str)
+        .and_then(|position| position.map(|p| serde_json::to_string(&p).map_err(|e| e.to_string())).transpose());
+    match result {
+        Ok(json) => env.new_string(json.unwrap_or_default()).unwrap(),
+        Err(e) => env.new_string(format!("ERROR:{}", e)).unwrap(),
+    }
+}
+
+/// JNI: Record whether the wiki a WikiActivity shows may use the location (the user's
+/// answer to its permission dialog).
+#[cfg(target_os = "android")]
+#[no_mangle]
+pub extern "system" fn Java_com_burningtreec_tiddlydesktop_1rs_WikiActivity_recordGeolocationPermission(
+    mut env: jni::JNIEnv,
+    _class: jni::objects::JClass,
+    data_dir: jni::objects::JString,
+    wiki_path: jni::objects::JString,
+    allowed: jni::sys::jboolean,
+) {
+    let data_dir_str: Option<String> = env.get_string(&data_dir).ok().map(Into::into);
+    let wiki_path_str: Option<String> = env.get_string(&wiki_path).ok().map(Into::into);
+    let (Some(data_dir_str), Some(wiki_path_str)) = (data_dir_str, wiki_path_str) else {
+        return;
+    };
+    if let Err(e) = geolocation::record_android_permission(std::path::Path::new(&data_dir_str), &wiki_path_str, allowed != 0) {
+        eprintln!("[TiddlyDesktop] Failed to record location permission: {}", e);
+    }
+}
+
 /// JNI: Called from MainActivity when the `tiddlydesktop://auth?state=...` deep link arrives.
 /// Notifies the pending relay_sync OAuth flow to retrieve the auth result from the relay server.
 #[cfg(target_os = "android")]
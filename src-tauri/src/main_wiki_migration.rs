@@ -0,0 +1,230 @@
+//! Landing page migration in the background (desktop)
+//!
+//! When the bundled landing page is newer than the user's `tiddlydesktop.html`,
+//! the user tiddlers are merged into the new version on a background thread
+//! while a small splash window shows the progress. The merged wiki is written
+//! to a temp file and booted in a hidden window first; it only replaces the
+//! landing page once it boots and reports the new version. Otherwise the
+//! landing page is left untouched.
+//!
+//! The previous version stays next to the landing page as
+//! `tiddlydesktop.html.migration-backup`, and among the landing page snapshots
+//! (see `landing_snapshots`).
+
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use tauri::{Emitter, Manager};
+use tiddlydesktop_core::{tiddler_store, tiddlywiki_html};
+
+use crate::utils;
+
+/// Label of the progress splash window
+pub const SPLASH_WINDOW_LABEL: &str = "migration";
+
+/// Label of the hidden window the migrated wiki is test-booted in
+const CHECK_WINDOW_LABEL: &str = "migration-check";
+
+/// How long the migrated wiki gets to boot
+const BOOT_TIMEOUT: Duration = Duration::from_secs(60);
+
+const VERSION_TIDDLER: &str = "$:/TiddlyDesktop/AppVersion";
+const WIKI_LIST_TIDDLER: &str = "$:/TiddlyDesktop/WikiList";
+
+/// Where the boot check of the running migration reports to
+static BOOT_REPORT: Mutex<Option<mpsc::Sender<Result<String, String>>>> = Mutex::new(None);
+
+/// A landing page that is older than the bundled one
+pub struct PendingMigration {
+    wiki_path: PathBuf,
+    bundled_path: PathBuf,
+    from_version: u32,
+    to_version: u32,
+}
+
+fn app_version(path: &Path) -> u32 {
+    tiddler_store::tiddler_text(path, VERSION_TIDDLER)
+        .and_then(|v| v.trim().parse::<u32>().ok())
+        .unwrap_or(0)
+}
+
+/// The migration the landing page at `wiki_path` needs, if any
+pub fn pending(wiki_path: &Path, bundled_path: &Path) -> Option<PendingMigration> {
+    let from_version = app_version(wiki_path);
+    let to_version = app_version(bundled_path);
+    (to_version > from_version).then(|| PendingMigration {
+        wiki_path: wiki_path.to_path_buf(),
+        bundled_path: bundled_path.to_path_buf(),
+        from_version,
+        to_version,
+    })
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+fn progress(app: &tauri::AppHandle, stage: &str, percent: u32) {
+    let _ = app.emit_to(
+        SPLASH_WINDOW_LABEL,
+        "main-wiki-migration-progress",
+        serde_json::json!({ "stage": stage, "percent": percent }),
+    );
+}
+
+/// Show the progress splash window
+pub fn show_splash(app: &tauri::AppHandle) -> Result<(), String> {
+    tauri::WebviewWindowBuilder::new(app, SPLASH_WINDOW_LABEL, tauri::WebviewUrl::App("migration.html".into()))
+        .title("TiddlyDesktop")
+        .inner_size(420.0, 200.0)
+        .resizable(false)
+        .center()
+        .focused(true)
+        .build()
+        .map(|_| ())
+        .map_err(|e| format!("Failed to open migration window: {}", e))
+}
+
+/// Migrate on a background thread, then call `done` on the main thread (with
+/// the landing page migrated or not) and close the splash window
+pub fn start(app: &tauri::AppHandle, migration: PendingMigration, done: fn(&tauri::AppHandle)) {
+    let app = app.clone();
+    std::thread::spawn(move || {
+        eprintln!("[TiddlyDesktop] Migrating landing page from version {} to {}",
+            migration.from_version, migration.to_version);
+        match migrate(&app, &migration) {
+            Ok(()) => {
+                eprintln!("[TiddlyDesktop] Migration complete (version {} -> {})",
+                    migration.from_version, migration.to_version);
+                progress(&app, "done", 100);
+            }
+            Err(e) => {
+                eprintln!("[TiddlyDesktop] ERROR: Landing page migration failed, keeping version {}: {}",
+                    migration.from_version, e);
+                progress(&app, "failed", 100);
+            }
+        }
+        let handle = app.clone();
+        let _ = app.run_on_main_thread(move || {
+            done(&handle);
+            if let Some(window) = handle.get_webview_window(SPLASH_WINDOW_LABEL) {
+                let _ = window.destroy();
+            }
+        });
+    });
+}
+
+fn migrate(app: &tauri::AppHandle, migration: &PendingMigration) -> Result<(), String> {
+    let backup_path = with_suffix(&migration.wiki_path, ".migration-backup");
+    let temp_path = with_suffix(&migration.wiki_path, ".migration-tmp");
+
+    progress(app, "backup", 10);
+    crate::landing_snapshots::snapshot(&migration.wiki_path)?;
+    std::fs::copy(&migration.wiki_path, &backup_path)
+        .map_err(|e| format!("Failed to create migration backup: {}", e))?;
+    eprintln!("[TiddlyDesktop] Created backup: {:?}", backup_path);
+
+    // Merge tiddler stores: preserve user data, update system/plugin tiddlers
+    progress(app, "merge", 30);
+    let bundled_html = std::fs::read_to_string(&migration.bundled_path)
+        .map_err(|e| format!("Failed to read bundled wiki: {}", e))?;
+    let merged_html = tiddlywiki_html::build_merged_html_from_file(&backup_path, &bundled_html)?;
+    drop(bundled_html);
+
+    let had_wiki_list = tiddler_store::find_tiddler(&backup_path, WIKI_LIST_TIDDLER).ok().flatten().is_some();
+    if had_wiki_list && tiddlywiki_html::extract_tiddler_from_html(&merged_html, WIKI_LIST_TIDDLER).is_none() {
+        return Err("WikiList lost during merge".to_string());
+    }
+
+    progress(app, "write", 50);
+    std::fs::write(&temp_path, &merged_html)
+        .map_err(|e| format!("Failed to write migrated wiki: {}", e))?;
+    drop(merged_html);
+
+    progress(app, "verify", 70);
+    let booted = boot_check(app, &temp_path);
+    if let Err(e) = booted.and_then(|version| match version.trim().parse::<u32>() {
+        Ok(v) if v == migration.to_version => Ok(()),
+        _ => Err(format!("migrated wiki reports version {:?}", version)),
+    }) {
+        let _ = std::fs::remove_file(&temp_path);
+        return Err(e);
+    }
+
+    progress(app, "swap", 90);
+    std::fs::rename(&temp_path, &migration.wiki_path)
+        .map_err(|e| format!("Failed to finalize migrated wiki: {}", e))
+}
+
+/// Boot `wiki_path` in a hidden window and return the app version it reports
+fn boot_check(app: &tauri::AppHandle, wiki_path: &Path) -> Result<String, String> {
+    let (tx, rx) = mpsc::channel();
+    *BOOT_REPORT.lock().unwrap() = Some(tx);
+
+    // Not labelled "main", so the landing page startup code stays idle
+    let path_key = utils::base64_url_encode(&wiki_path.to_string_lossy());
+    let state = app.state::<crate::AppState>();
+    state.wiki_paths.lock().unwrap().insert(path_key.clone(), wiki_path.to_path_buf());
+
+    let url = format!("wikifile://localhost/{}", path_key);
+    let built = tauri::WebviewWindowBuilder::new(app, CHECK_WINDOW_LABEL, tauri::WebviewUrl::External(url.parse().unwrap()))
+        .visible(false)
+        .initialization_script(BOOT_CHECK_SCRIPT)
+        .build();
+    let result = match built {
+        Ok(_) => rx.recv_timeout(BOOT_TIMEOUT)
+            .unwrap_or_else(|_| Err("migrated wiki did not finish booting".to_string())),
+        Err(e) => Err(format!("Failed to open boot check window: {}", e)),
+    };
+
+    *BOOT_REPORT.lock().unwrap() = None;
+    state.wiki_paths.lock().unwrap().remove(&path_key);
+    if let Some(window) = app.get_webview_window(CHECK_WINDOW_LABEL) {
+        let _ = window.destroy();
+    }
+    result
+}
+
+/// Reports the app version once the wiki has rendered, or the first error
+/// thrown before that
+const BOOT_CHECK_SCRIPT: &str = r#"
+(function() {
+    var reported = false;
+    function report(args) {
+        if (reported || !window.__TAURI__) return;
+        reported = true;
+        window.__TAURI__.core.invoke("report_migration_boot", args);
+    }
+    window.addEventListener("error", function(event) {
+        report({ version: null, error: String(event.message || "Script error") });
+    });
+    function poll() {
+        if (window.$tw && $tw.wiki && $tw.rootWidget) {
+            report({ version: $tw.wiki.getTiddlerText("$:/TiddlyDesktop/AppVersion", ""), error: null });
+        } else {
+            setTimeout(poll, 200);
+        }
+    }
+    poll();
+})();
+"#;
+
+/// Called by the boot check window once the migrated wiki booted or failed
+#[tauri::command]
+pub fn report_migration_boot(window: tauri::WebviewWindow, version: Option<String>, error: Option<String>) {
+    if window.label() != CHECK_WINDOW_LABEL {
+        return;
+    }
+    let report = match (version, error) {
+        (_, Some(error)) => Err(format!("migrated wiki failed to boot: {}", error)),
+        (Some(version), None) => Ok(version),
+        (None, None) => Err("migrated wiki reported no version".to_string()),
+    };
+    if let Some(tx) = BOOT_REPORT.lock().unwrap().as_ref() {
+        let _ = tx.send(report);
+    }
+}
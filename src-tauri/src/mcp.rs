@@ -0,0 +1,501 @@
+//! MCP server: wikis for local AI assistants (desktop)
+//!
+//! `--mcp` speaks the Model Context Protocol (JSON-RPC, one message per line)
+//! on stdin/stdout, so AI tools can start the app as an MCP server; with
+//! `--mcp-port <port>` it listens on `127.0.0.1:<port>` instead and serves
+//! every connection the same way. It runs without windows, next to the app or
+//! without it.
+//!
+//! Only wikis shared on the landing page (`mcp_access` in the wiki configs)
+//! are visible, read-only or read-write. The tools:
+//! - `list_wikis`: the shared wikis
+//! - `search_tiddlers`: titles matching a search text or a filter
+//! - `read_tiddler`: the fields of a tiddler
+//! - `write_tiddler`, `delete_tiddler`: changes to read-write wikis
+//!
+//! A wiki that's open in the running app is asked over IPC (`WikiQuery`), so
+//! assistants see unsaved changes and changes are saved like edits in the
+//! window. Closed wikis are read from disk (`tiddlydesktop_core::filter`) and
+//! can't be changed.
+
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use std::path::Path;
+
+use serde_json::{json, Value};
+use tiddlydesktop_core::filter::Wiki;
+use tiddlydesktop_core::storage::DataStore;
+use tiddlydesktop_core::types::McpAccess;
+
+use crate::{ipc, utils};
+
+/// Protocol versions we can speak; the newest is offered to other clients
+const PROTOCOL_VERSIONS: [&str; 3] = ["2025-06-18", "2025-03-26", "2024-11-05"];
+
+/// Titles `search_tiddlers` returns unless given a limit, and at most
+const DEFAULT_LIMIT: usize = 50;
+const MAX_LIMIT: usize = 1000;
+
+/// Tiddlers `search_tiddlers` lists without a search text or filter
+const DEFAULT_FILTER: &str = "[all[tiddlers]!is[system]sort[title]]";
+
+/// Tiddlers `search_tiddlers` looks through for a search text
+const SEARCH_FILTER: &str = "[!is[system]search<query>sort[title]]";
+
+// JSON-RPC error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// A wiki shared with AI assistants
+struct SharedWiki {
+    path: String,
+    name: String,
+    is_folder: bool,
+    access: McpAccess,
+}
+
+/// One MCP session (a client on stdio or a socket connection)
+struct Session {
+    store: DataStore,
+}
+
+/// The data directory the app uses (portable or installed)
+#[cfg(not(target_os = "android"))]
+fn data_store() -> Result<DataStore, String> {
+    let portable = crate::portable_data_dir();
+    let dir = portable
+        .clone()
+        .or_else(crate::instance::system_data_dir)
+        .ok_or("No data directory found")?;
+    Ok(DataStore::new(dir).with_portable_base(portable))
+}
+
+#[cfg(target_os = "android")]
+fn data_store() -> Result<DataStore, String> {
+    Err("The MCP server is not available on Android".to_string())
+}
+
+fn reply(id: Value, result: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "result": result })
+}
+
+fn error_reply(id: Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+/// The result of a tool call: text for the assistant, marked as an error or not
+fn tool_result(result: Result<Value, String>) -> Value {
+    match result {
+        Ok(value) => json!({
+            "content": [{ "type": "text", "text": serde_json::to_string_pretty(&value).unwrap_or_default() }],
+            "isError": false,
+        }),
+        Err(message) => json!({
+            "content": [{ "type": "text", "text": message }],
+            "isError": true,
+        }),
+    }
+}
+
+fn tools() -> Value {
+    let wiki = json!({ "type": "string", "description": "Path or file name of the wiki (see list_wikis)" });
+    let title = json!({ "type": "string", "description": "Title of the tiddler" });
+    json!([
+        {
+            "name": "list_wikis",
+            "description": "List the TiddlyWiki wikis shared with you, with their access (read-only or read-write)",
+            "inputSchema": { "type": "object", "properties": {} },
+        },
+        {
+            "name": "search_tiddlers",
+            "description": "Find tiddlers of a wiki: the titles of the non-system tiddlers containing all words of `query`, or those selected by a TiddlyWiki `filter`",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "wiki": wiki,
+                    "query": { "type": "string", "description": "Words to search for" },
+                    "filter": { "type": "string", "description": "TiddlyWiki filter expression (instead of query); <query> is the search text" },
+                    "limit": { "type": "integer", "description": "Most titles to return (default 50)" },
+                },
+                "required": ["wiki"],
+            },
+        },
+        {
+            "name": "read_tiddler",
+            "description": "Read all fields of a tiddler (text, tags, created, modified, ...)",
+            "inputSchema": {
+                "type": "object",
+                "properties": { "wiki": wiki, "title": title },
+                "required": ["wiki", "title"],
+            },
+        },
+        {
+            "name": "write_tiddler",
+            "description": "Create or replace a tiddler of a read-write wiki that is open in TiddlyDesktop",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "wiki": wiki,
+                    "title": title,
+                    "text": { "type": "string", "description": "Text of the tiddler" },
+                    "fields": { "type": "object", "description": "Other fields, e.g. tags (\"[[Two words]] one\") or type", "additionalProperties": { "type": "string" } },
+                },
+                "required": ["wiki", "title"],
+            },
+        },
+        {
+            "name": "delete_tiddler",
+            "description": "Delete a tiddler of a read-write wiki that is open in TiddlyDesktop",
+            "inputSchema": {
+                "type": "object",
+                "properties": { "wiki": wiki, "title": title },
+                "required": ["wiki", "title"],
+            },
+        },
+    ])
+}
+
+/// A string argument of a tool call (None when missing or empty)
+fn string_arg(args: &Value, name: &str) -> Option<String> {
+    args.get(name).and_then(Value::as_str).filter(|s| !s.is_empty()).map(str::to_string)
+}
+
+fn required_arg(args: &Value, name: &str) -> Result<String, String> {
+    string_arg(args, name).ok_or_else(|| format!("Missing argument: {}", name))
+}
+
+/// The wiki a `wiki` argument names: by path, or by file name when that's unique
+fn find_wiki<'a>(wikis: &'a [SharedWiki], name: &str) -> Result<&'a SharedWiki, String> {
+    if let Some(wiki) = wikis.iter().find(|w| utils::paths_equal(&w.path, name)) {
+        return Ok(wiki);
+    }
+    let mut by_name = wikis.iter().filter(|w| w.name == name);
+    match (by_name.next(), by_name.next()) {
+        (Some(wiki), None) => Ok(wiki),
+        (Some(_), Some(_)) => Err(format!("Several shared wikis are named {}; use the path", name)),
+        _ => Err(format!("No shared wiki: {} (see list_wikis)", name)),
+    }
+}
+
+/// The filter `search_tiddlers` runs
+fn search_filter(args: &Value) -> String {
+    string_arg(args, "filter").unwrap_or_else(|| {
+        if string_arg(args, "query").is_some() {
+            SEARCH_FILTER.to_string()
+        } else {
+            DEFAULT_FILTER.to_string()
+        }
+    })
+}
+
+impl Session {
+    fn shared_wikis(&self) -> Result<Vec<SharedWiki>, String> {
+        let access = self.store.load_wiki_configs()?.mcp_access;
+        Ok(self
+            .store
+            .load_recent_files()
+            .into_iter()
+            .filter_map(|entry| {
+                let access = *access.get(&entry.path)?;
+                Some(SharedWiki { path: entry.path, name: entry.filename, is_folder: entry.is_folder, access })
+            })
+            .collect())
+    }
+
+    /// Ask the wiki's window in the running app; None when the app isn't
+    /// running or the wiki isn't open
+    fn ask_open_wiki(&self, wiki: &SharedWiki, action: &str, params: Value) -> Option<Result<Value, String>> {
+        #[cfg(not(target_os = "android"))]
+        let token = crate::process_registry::read_session_token(self.store.data_dir())?;
+        #[cfg(target_os = "android")]
+        let token = String::new();
+        match ipc::query_wiki(&wiki.path, action, &params.to_string(), token) {
+            Ok(result) => result.map(|r| r.map(|json| serde_json::from_str(&json).unwrap_or(Value::Null))),
+            Err(e) => {
+                eprintln!("[MCP] Failed to reach the app: {}", e);
+                None
+            }
+        }
+    }
+
+    fn search(&self, wiki: &SharedWiki, args: &Value) -> Result<Value, String> {
+        let filter = search_filter(args);
+        let query = string_arg(args, "query").unwrap_or_default();
+        let limit = args
+            .get("limit")
+            .and_then(Value::as_u64)
+            .map_or(DEFAULT_LIMIT, |limit| (limit as usize).min(MAX_LIMIT));
+        let params = json!({ "filter": filter, "variables": { "query": query } });
+        let (titles, open) = match self.ask_open_wiki(wiki, "filter", params) {
+            Some(result) => (serde_json::from_value::<Vec<String>>(result?).map_err(|e| e.to_string())?, true),
+            None => {
+                let variables = HashMap::from([("query".to_string(), query)]);
+                (Wiki::load(Path::new(&wiki.path))?.filter_with_variables(&filter, &variables)?, false)
+            }
+        };
+        Ok(json!({
+            "total": titles.len(),
+            "titles": titles.into_iter().take(limit).collect::<Vec<_>>(),
+            "wikiOpen": open,
+        }))
+    }
+
+    fn read(&self, wiki: &SharedWiki, title: &str) -> Result<Value, String> {
+        let fields = match self.ask_open_wiki(wiki, "get", json!({ "title": title })) {
+            Some(result) => result?,
+            None => Wiki::load(Path::new(&wiki.path))?
+                .get_tiddler(title)
+                .map(|fields| json!(fields))
+                .unwrap_or(Value::Null),
+        };
+        if fields.is_null() {
+            return Err(format!("Tiddler not found: {}", title));
+        }
+        Ok(fields)
+    }
+
+    /// Change an open read-write wiki
+    fn change(&self, wiki: &SharedWiki, action: &str, params: Value) -> Result<Value, String> {
+        if wiki.access != McpAccess::ReadWrite {
+            return Err(format!("{} is shared read-only", wiki.name));
+        }
+        self.ask_open_wiki(wiki, action, params)
+            .unwrap_or_else(|| Err(format!("{} is not open in TiddlyDesktop; open it to change tiddlers", wiki.name)))
+    }
+
+    fn call_tool(&self, name: &str, args: &Value) -> Result<Value, String> {
+        let wikis = self.shared_wikis()?;
+        if name == "list_wikis" {
+            return Ok(wikis
+                .iter()
+                .map(|w| json!({ "path": w.path, "name": w.name, "isFolder": w.is_folder, "access": w.access }))
+                .collect());
+        }
+        let wiki = find_wiki(&wikis, &required_arg(args, "wiki")?)?;
+        match name {
+            "search_tiddlers" => self.search(wiki, args),
+            "read_tiddler" => self.read(wiki, &required_arg(args, "title")?),
+            "write_tiddler" => {
+                let title = required_arg(args, "title")?;
+                let mut fields = args.get("fields").cloned().unwrap_or_else(|| json!({}));
+                let fields_map = fields.as_object_mut().ok_or("fields must be an object")?;
+                if let Some(text) = args.get("text").and_then(Value::as_str) {
+                    fields_map.insert("text".to_string(), json!(text));
+                }
+                self.change(wiki, "put", json!({ "title": title, "fields": fields }))
+            }
+            "delete_tiddler" => self.change(wiki, "delete", json!({ "title": required_arg(args, "title")? })),
+            _ => Err(format!("Unknown tool: {}", name)),
+        }
+    }
+
+    /// The reply to a message; None for notifications
+    fn handle(&self, message: &Value) -> Option<Value> {
+        let Some(method) = message.get("method").and_then(Value::as_str) else {
+            // A response to us (we send no requests) or garbage
+            return message
+                .get("id")
+                .filter(|_| message.get("result").is_none() && message.get("error").is_none())
+                .map(|id| error_reply(id.clone(), INVALID_REQUEST, "Invalid request"));
+        };
+        // Notifications (initialized, cancelled, ...) need no reply
+        let id = message.get("id")?.clone();
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+        Some(match method {
+            "initialize" => {
+                let requested = params.get("protocolVersion").and_then(Value::as_str);
+                let version = requested
+                    .filter(|v| PROTOCOL_VERSIONS.contains(v))
+                    .unwrap_or(PROTOCOL_VERSIONS[0]);
+                reply(
+                    id,
+                    json!({
+                        "protocolVersion": version,
+                        "capabilities": { "tools": {} },
+                        "serverInfo": { "name": "tiddlydesktop-rs", "version": env!("CARGO_PKG_VERSION") },
+                        "instructions": "Tools for the TiddlyWiki wikis the user shared from TiddlyDesktop. Call list_wikis first.",
+                    }),
+                )
+            }
+            "ping" => reply(id, json!({})),
+            "tools/list" => reply(id, json!({ "tools": tools() })),
+            "tools/call" => match params.get("name").and_then(Value::as_str) {
+                Some(name) => {
+                    let args = params.get("arguments").cloned().unwrap_or_else(|| json!({}));
+                    reply(id, tool_result(self.call_tool(name, &args)))
+                }
+                None => error_reply(id, INVALID_PARAMS, "Missing tool name"),
+            },
+            _ => error_reply(id, METHOD_NOT_FOUND, &format!("Method not found: {}", method)),
+        })
+    }
+
+    /// Answer the messages of one client until it disconnects
+    fn serve(&self, reader: impl BufRead, mut writer: impl Write) -> std::io::Result<()> {
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let answer = match serde_json::from_str::<Value>(&line) {
+                Ok(message) => self.handle(&message),
+                Err(e) => Some(error_reply(Value::Null, PARSE_ERROR, &format!("Parse error: {}", e))),
+            };
+            if let Some(answer) = answer {
+                writeln!(writer, "{}", answer)?;
+                writer.flush()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn listen(port: u16) -> Result<(), String> {
+    let listener = std::net::TcpListener::bind(("127.0.0.1", port))
+        .map_err(|e| format!("Failed to listen on port {}: {}", port, e))?;
+    eprintln!("[MCP] Listening on 127.0.0.1:{}", port);
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else { continue };
+        let session = Session { store: data_store()? };
+        std::thread::spawn(move || {
+            let reader = match stream.try_clone() {
+                Ok(reader) => std::io::BufReader::new(reader),
+                Err(e) => return eprintln!("[MCP] {}", e),
+            };
+            if let Err(e) = session.serve(reader, stream) {
+                eprintln!("[MCP] Connection closed: {}", e);
+            }
+        });
+    }
+    Ok(())
+}
+
+/// `--mcp [--mcp-port <port>]`: run the MCP server until stdin closes (or
+/// forever on a port) and return the exit status; None without `--mcp`
+pub fn run_cli() -> Option<i32> {
+    let args: Vec<String> = std::env::args().collect();
+    if !args.iter().any(|arg| arg == "--mcp") {
+        return None;
+    }
+    let port = args
+        .iter()
+        .position(|arg| arg == "--mcp-port")
+        .and_then(|i| args.get(i + 1))
+        .map(|port| port.parse::<u16>().map_err(|_| format!("Invalid port: {}", port)));
+    let result = match port {
+        Some(Ok(port)) => listen(port),
+        Some(Err(e)) => Err(e),
+        None => data_store().and_then(|store| {
+            Session { store }
+                .serve(std::io::stdin().lock(), std::io::stdout().lock())
+                .map_err(|e| e.to_string())
+        }),
+    };
+    match result {
+        Ok(()) => Some(0),
+        Err(e) => {
+            eprintln!("[MCP] {}", e);
+            Some(1)
+        }
+    }
+}
+
+/// Access of AI assistants per wiki path (landing page)
+#[tauri::command]
+pub fn get_mcp_access(app: tauri::AppHandle) -> Result<HashMap<String, McpAccess>, String> {
+    Ok(crate::wiki_storage::load_wiki_configs(&app)?.mcp_access)
+}
+
+/// Share a wiki with AI assistants, or stop sharing it (None)
+#[tauri::command]
+pub fn set_mcp_access(app: tauri::AppHandle, wiki_path: String, access: Option<McpAccess>) -> Result<(), String> {
+    let mut configs = crate::wiki_storage::load_wiki_configs(&app)?;
+    match access {
+        Some(access) => configs.mcp_access.insert(wiki_path, access),
+        None => configs.mcp_access.remove(&wiki_path),
+    };
+    crate::wiki_storage::save_wiki_configs(&app, &configs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(name: &str) -> (Session, std::path::PathBuf) {
+        let dir = std::env::temp_dir().join(format!("td-mcp-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let wiki = dir.join("notes.html");
+        std::fs::write(
+            &wiki,
+            r#"<html><script class="tiddlywiki-tiddler-store" type="application/json">[
+                {"title":"Getting Started","text":"Welcome to the notes"},
+                {"title":"Shopping","text":"milk, bread","tags":"List"},
+                {"title":"$:/config/x","text":"notes"}
+            ]</script></html>"#,
+        )
+        .unwrap();
+        let store = DataStore::new(&dir);
+        let path = wiki.to_string_lossy().to_string();
+        let entry: crate::WikiEntry = serde_json::from_value(json!({ "path": path, "filename": "notes.html" })).unwrap();
+        let other: crate::WikiEntry = serde_json::from_value(json!({ "path": "/private.html", "filename": "private.html" })).unwrap();
+        store.save_recent_files(&[entry, other]).unwrap();
+        let mut configs = store.load_wiki_configs().unwrap();
+        configs.mcp_access.insert(path, McpAccess::ReadOnly);
+        store.save_wiki_configs(&configs).unwrap();
+        (Session { store }, dir)
+    }
+
+    fn call(session: &Session, name: &str, arguments: Value) -> Value {
+        let message = json!({ "jsonrpc": "2.0", "id": 7, "method": "tools/call", "params": { "name": name, "arguments": arguments } });
+        session.handle(&message).unwrap()["result"].clone()
+    }
+
+    fn text(result: &Value) -> Value {
+        serde_json::from_str(result["content"][0]["text"].as_str().unwrap()).unwrap_or(Value::Null)
+    }
+
+    #[test]
+    fn test_protocol_messages() {
+        let (session, dir) = session("protocol");
+        let init = session
+            .handle(&json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": { "protocolVersion": "2024-11-05" } }))
+            .unwrap();
+        assert_eq!(init["result"]["protocolVersion"], "2024-11-05");
+        assert!(session.handle(&json!({ "jsonrpc": "2.0", "method": "notifications/initialized" })).is_none());
+        let tools = session.handle(&json!({ "jsonrpc": "2.0", "id": 2, "method": "tools/list" })).unwrap();
+        assert_eq!(tools["result"]["tools"].as_array().unwrap().len(), 5);
+        let unknown = session.handle(&json!({ "jsonrpc": "2.0", "id": 3, "method": "resources/list" })).unwrap();
+        assert_eq!(unknown["error"]["code"], METHOD_NOT_FOUND);
+
+        let mut output = Vec::new();
+        session.serve("not json\n\n".as_bytes(), &mut output).unwrap();
+        let parsed: Value = serde_json::from_slice(&output).unwrap();
+        assert_eq!(parsed["error"]["code"], PARSE_ERROR);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_tools_on_closed_wiki() {
+        let (session, dir) = session("tools");
+        let wikis = text(&call(&session, "list_wikis", json!({})));
+        assert_eq!(wikis.as_array().unwrap().len(), 1);
+        assert_eq!(wikis[0]["access"], "read-only");
+
+        let found = text(&call(&session, "search_tiddlers", json!({ "wiki": "notes.html", "query": "notes" })));
+        assert_eq!(found["titles"], json!(["Getting Started"]));
+        let all = text(&call(&session, "search_tiddlers", json!({ "wiki": "notes.html", "filter": "[tag[List]]" })));
+        assert_eq!(all["titles"], json!(["Shopping"]));
+
+        let tiddler = text(&call(&session, "read_tiddler", json!({ "wiki": "notes.html", "title": "Shopping" })));
+        assert_eq!(tiddler["text"], "milk, bread");
+        assert_eq!(call(&session, "read_tiddler", json!({ "wiki": "notes.html", "title": "Nope" }))["isError"], true);
+
+        let private = call(&session, "read_tiddler", json!({ "wiki": "/private.html", "title": "x" }));
+        assert_eq!(private["isError"], true);
+        let write = call(&session, "write_tiddler", json!({ "wiki": "notes.html", "title": "New", "text": "x" }));
+        assert_eq!(write["content"][0]["text"], "notes.html is shared read-only");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
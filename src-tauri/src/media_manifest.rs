@@ -0,0 +1,249 @@
+//! `tiddlywiki.files` manifests for external media folders
+//!
+//! Referencing a big media library from a folder wiki without copying it into
+//! `tiddlers/`: `generate_media_manifest` scans a folder and its subfolders for
+//! images, audio, video and PDFs and writes a `tiddlywiki.files` into
+//! `tiddlers/<folder name>/` of the wiki. It lists one tiddler per file, with
+//! the type from the file's extension, the file as `_canonical_uri` and empty
+//! text, so the wiki only loads a file when it's shown.
+//!
+//! Running it again for the same folder updates the manifest: new files are
+//! added, missing ones are dropped, and what was changed by hand in the
+//! entries of the remaining files (titles, tags, other fields) is kept.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use tiddlydesktop_core::utils::get_mime_type;
+use tiddlydesktop_core::wiki_folder::title_to_filename;
+
+const MANIFEST: &str = "tiddlywiki.files";
+
+/// Manifest key with the media folder it was generated for (TiddlyWiki ignores it)
+const MEDIA_FOLDER_KEY: &str = "mediaFolder";
+
+/// Result of generating a manifest
+#[derive(Debug, Serialize)]
+pub struct MediaManifest {
+    /// The `tiddlywiki.files` written
+    pub manifest: String,
+    /// Media files listed in it
+    pub files: usize,
+    pub added: usize,
+    pub removed: usize,
+}
+
+/// Whether files of this type are worth a lazily loaded tiddler
+fn is_media_type(content_type: &str) -> bool {
+    content_type.starts_with("image/")
+        || content_type.starts_with("audio/")
+        || content_type.starts_with("video/")
+        || content_type == "application/pdf"
+}
+
+/// The media files below `dir`, relative to it and sorted (hidden files and folders skipped)
+fn scan(dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut stack = vec![dir.to_path_buf()];
+    while let Some(current) = stack.pop() {
+        let Ok(entries) = std::fs::read_dir(&current) else { continue };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+            if path.is_dir() {
+                stack.push(path);
+            } else if is_media_type(get_mime_type(&path)) {
+                if let Ok(relative) = path.strip_prefix(dir) {
+                    files.push(relative.to_path_buf());
+                }
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+/// The manifest listing `files` (relative to `media_dir`), with titles made of
+/// `title_prefix` and their relative path. Entries of `existing` for files
+/// still there are kept with their fields. Returns the manifest and how many
+/// entries were added and removed.
+fn build_manifest(media_dir: &Path, files: &[PathBuf], title_prefix: &str, existing: Option<&Value>) -> (Value, usize, usize) {
+    let mut previous: HashMap<String, Value> = existing
+        .and_then(|manifest| manifest.get("tiddlers"))
+        .and_then(|tiddlers| tiddlers.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| Some((entry.get("file")?.as_str()?.to_string(), entry.clone())))
+        .collect();
+
+    let mut added = 0;
+    let tiddlers: Vec<Value> = files
+        .iter()
+        .map(|relative| {
+            let path = media_dir.join(relative);
+            let file = path.to_string_lossy().to_string();
+            let mut entry = previous.remove(&file).unwrap_or_else(|| {
+                added += 1;
+                json!({ "file": file })
+            });
+            let fields = entry
+                .as_object_mut()
+                .map(|object| object.entry("fields").or_insert_with(|| Value::Object(Map::new())))
+                .and_then(Value::as_object_mut);
+            if let Some(fields) = fields {
+                fields.entry("title").or_insert_with(|| {
+                    let relative = relative.components()
+                        .map(|c| c.as_os_str().to_string_lossy())
+                        .collect::<Vec<_>>()
+                        .join("/");
+                    Value::String(format!("{}{}", title_prefix, relative))
+                });
+                fields.insert("type".to_string(), Value::String(get_mime_type(&path).to_string()));
+                fields.insert("_canonical_uri".to_string(), Value::String(file.clone()));
+                // The text comes from `_canonical_uri` when the tiddler is shown
+                fields.insert("text".to_string(), Value::String(String::new()));
+            }
+            entry
+        })
+        .collect();
+    let removed = previous.len();
+
+    let mut manifest = existing.and_then(Value::as_object).cloned().unwrap_or_default();
+    manifest.insert(MEDIA_FOLDER_KEY.to_string(), Value::String(media_dir.to_string_lossy().to_string()));
+    manifest.insert("tiddlers".to_string(), Value::Array(tiddlers));
+    (Value::Object(manifest), added, removed)
+}
+
+fn read_manifest(path: &Path) -> Option<Value> {
+    let content = std::fs::read_to_string(path).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// The folder below `tiddlers` with the manifest of `media_dir`: the one
+/// generated for it before, or a new one named after it
+fn manifest_dir(tiddlers_dir: &Path, media_dir: &Path) -> PathBuf {
+    let media_folder = media_dir.to_string_lossy();
+    let generated_before = std::fs::read_dir(tiddlers_dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .find(|dir| {
+            read_manifest(&dir.join(MANIFEST))
+                .is_some_and(|manifest| manifest.get(MEDIA_FOLDER_KEY).and_then(Value::as_str) == Some(&media_folder))
+        });
+    if let Some(dir) = generated_before {
+        return dir;
+    }
+    let name = media_dir
+        .file_name()
+        .map(|n| title_to_filename(&n.to_string_lossy()))
+        .unwrap_or_else(|| "media".to_string());
+    (1..)
+        .map(|n| if n == 1 { tiddlers_dir.join(&name) } else { tiddlers_dir.join(format!("{}-{}", name, n)) })
+        .find(|dir| !dir.exists())
+        .unwrap_or_else(|| tiddlers_dir.join(name))
+}
+
+/// Generate or update the manifest of `media_dir` in the wiki folder `wiki_dir`
+fn generate(wiki_dir: &Path, media_dir: &Path, title_prefix: Option<&str>) -> Result<MediaManifest, String> {
+    if !crate::utils::is_wiki_folder(wiki_dir) {
+        return Err(format!("{} is not a wiki folder", wiki_dir.display()));
+    }
+    if tiddlydesktop_core::sqlite_wiki::is_sqlite_wiki(wiki_dir) {
+        return Err("Wikis stored in a database can't load tiddlywiki.files manifests".to_string());
+    }
+    if !media_dir.is_dir() {
+        return Err(format!("{} is not a folder", media_dir.display()));
+    }
+    let tiddlers_dir = wiki_dir.join("tiddlers");
+    if media_dir.starts_with(&tiddlers_dir) {
+        return Err("The media folder is already part of the wiki's tiddlers".to_string());
+    }
+
+    let files = scan(media_dir);
+    let dir = manifest_dir(&tiddlers_dir, media_dir);
+    let path = dir.join(MANIFEST);
+    let existing = read_manifest(&path);
+    let default_prefix = media_dir
+        .file_name()
+        .map(|n| format!("{}/", n.to_string_lossy()))
+        .unwrap_or_default();
+    let title_prefix = title_prefix.unwrap_or(&default_prefix);
+    let (manifest, added, removed) = build_manifest(media_dir, &files, title_prefix, existing.as_ref());
+
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let content = serde_json::to_string_pretty(&manifest).map_err(|e| e.to_string())?;
+    std::fs::write(&path, content).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    eprintln!("[TiddlyDesktop] {} media files in {} (+{} -{})", files.len(), path.display(), added, removed);
+    Ok(MediaManifest {
+        manifest: path.to_string_lossy().to_string(),
+        files: files.len(),
+        added,
+        removed,
+    })
+}
+
+/// Generate (or update) a `tiddlywiki.files` manifest in the folder wiki at
+/// `wiki_path` for the media files in `media_dir` and its subfolders. Titles
+/// are `title_prefix` (default: the folder's name and a slash) + the path of
+/// the file in the folder. The wiki sees the changes when it's opened again.
+#[tauri::command]
+pub async fn generate_media_manifest(wiki_path: String, media_dir: String, title_prefix: Option<String>) -> Result<MediaManifest, String> {
+    tokio::task::spawn_blocking(move || {
+        generate(Path::new(&wiki_path), Path::new(&media_dir), title_prefix.as_deref().filter(|p| !p.is_empty()))
+    })
+    .await
+    .map_err(|e| format!("Generating the manifest failed: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_manifest() {
+        let media = Path::new("/media/Photos");
+        let files = vec![PathBuf::from("a.jpg"), PathBuf::from("2020").join("b.pdf")];
+        let (manifest, added, removed) = build_manifest(media, &files, "Photos/", None);
+        assert_eq!((added, removed), (2, 0));
+        let entry = &manifest["tiddlers"][1];
+        assert_eq!(entry["file"], media.join("2020").join("b.pdf").to_string_lossy().as_ref());
+        assert_eq!(entry["fields"]["title"], "Photos/2020/b.pdf");
+        assert_eq!(entry["fields"]["type"], "application/pdf");
+        assert_eq!(entry["fields"]["text"], "");
+
+        // Fields added by hand survive an update, entries of missing files don't
+        let mut edited = manifest.clone();
+        edited["tiddlers"][0]["fields"]["tags"] = json!("Holiday");
+        let files = vec![PathBuf::from("a.jpg"), PathBuf::from("c.png")];
+        let (updated, added, removed) = build_manifest(media, &files, "Photos/", Some(&edited));
+        assert_eq!((added, removed), (1, 1));
+        assert_eq!(updated["tiddlers"][0]["fields"]["tags"], "Holiday");
+        assert_eq!(updated["tiddlers"][1]["fields"]["title"], "Photos/c.png");
+        assert_eq!(updated["tiddlers"][1]["fields"]["type"], "image/png");
+        assert_eq!(updated[MEDIA_FOLDER_KEY], media.to_string_lossy().as_ref());
+    }
+
+    #[test]
+    fn test_scan() {
+        let dir = std::env::temp_dir().join(format!("td-media-manifest-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        std::fs::create_dir_all(dir.join(".thumbnails")).unwrap();
+        for file in ["a.JPG", "notes.txt", "sub/song.mp3", "sub/clip.mp4", ".thumbnails/a.png", ".hidden.png"] {
+            std::fs::write(dir.join(file), b"x").unwrap();
+        }
+        let files = scan(&dir);
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!(files, vec![
+            PathBuf::from("a.JPG"),
+            PathBuf::from("sub").join("clip.mp4"),
+            PathBuf::from("sub").join("song.mp3"),
+        ]);
+    }
+}
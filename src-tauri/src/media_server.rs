@@ -5,7 +5,9 @@
 //! and folder wiki mode on all platforms (HTTP-origin pages can't load tdasset://).
 //!
 //! Security model:
-//! - Bound to 127.0.0.1 only (no external access)
+//! - Bound to 127.0.0.1, and to the addresses of `server_listen` in the app
+//!   settings (see `server_address`); windows use the 127.0.0.1 URLs
+//! - Requests with a foreign `Host` header are refused (DNS rebinding)
 //! - Per-file token allowlist: only files explicitly registered by the wiki can be served
 //! - Path validation: same sanitize checks as tdasset:// protocol
 //! - Opaque tokens: URLs contain no filesystem path information
@@ -19,11 +21,12 @@
 use std::collections::HashMap;
 use std::fs::{self, File};
 use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
-use std::net::{TcpListener, TcpStream};
+use std::net::TcpStream;
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, SystemTime};
 
+use crate::server_address::{self, Binding};
 use crate::utils;
 
 /// Per-file token entry.
@@ -39,24 +42,32 @@ pub struct MediaServer {
 }
 
 impl MediaServer {
-    /// Start the media server on a random localhost port.
-    pub fn start() -> io::Result<Self> {
-        let listener = TcpListener::bind("127.0.0.1:0")?;
-        let port = listener.local_addr()?.port();
+    /// Start the media server on a random port, on the addresses of `binding`.
+    pub fn start(binding: &Binding) -> io::Result<Self> {
+        let listeners = binding.listen(0).map_err(io::Error::other)?;
+        let port = match listeners.first() {
+            Some(listener) => listener.local_addr()?.port(),
+            None => return Err(io::Error::other("No address to listen on")),
+        };
         let tokens: Arc<Mutex<HashMap<String, MediaEntry>>> =
             Arc::new(Mutex::new(HashMap::new()));
 
-        let tokens_clone = tokens.clone();
-        std::thread::spawn(move || {
-            for stream in listener.incoming().flatten() {
-                let tokens = tokens_clone.clone();
-                std::thread::spawn(move || {
-                    serve_connection(stream, &tokens);
-                });
-            }
-        });
+        for listener in listeners {
+            let tokens_clone = tokens.clone();
+            std::thread::spawn(move || {
+                for stream in listener.incoming().flatten() {
+                    let tokens = tokens_clone.clone();
+                    std::thread::spawn(move || {
+                        serve_connection(stream, &tokens);
+                    });
+                }
+            });
+        }
 
         eprintln!("[MediaServer] Started on 127.0.0.1:{}", port);
+        for url in binding.lan_urls(port) {
+            eprintln!("[MediaServer]   and on {}", url);
+        }
         Ok(Self { port, tokens })
     }
 
@@ -151,6 +162,7 @@ struct Request {
     range: Option<String>,
     if_none_match: Option<String>,
     if_range: Option<String>,
+    host: Option<String>,
     keep_alive: bool,
 }
 
@@ -181,6 +193,7 @@ fn read_request(reader: &mut BufReader<TcpStream>) -> io::Result<Option<Request>
     let mut range = None;
     let mut if_none_match = None;
     let mut if_range = None;
+    let mut host = None;
 
     // Read headers
     loop {
@@ -197,6 +210,8 @@ fn read_request(reader: &mut BufReader<TcpStream>) -> io::Result<Option<Request>
             if_none_match = Some(value);
         } else if let Some(value) = header_value(trimmed, "If-Range") {
             if_range = Some(value);
+        } else if let Some(value) = header_value(trimmed, "Host") {
+            host = Some(value);
         } else if let Some(value) = header_value(trimmed, "Connection") {
             let lower = value.to_lowercase();
             if lower.contains("close") {
@@ -214,6 +229,7 @@ fn read_request(reader: &mut BufReader<TcpStream>) -> io::Result<Option<Request>
         range,
         if_none_match,
         if_range,
+        host,
         keep_alive,
     }))
 }
@@ -244,6 +260,11 @@ fn serve_one_request(
     let keep_alive = req.keep_alive;
     let conn_value = if keep_alive { "keep-alive" } else { "close" };
 
+    if !server_address::allows_host_header(req.host.as_deref()) {
+        send_error(writer, &req.http_version, 403, "Forbidden", conn_value)?;
+        return Ok(false);
+    }
+
     // CORS preflight
     if req.method == "OPTIONS" {
         let resp = format!(
@@ -486,16 +507,24 @@ fn parse_range(range_str: &str, file_size: u64) -> Option<(u64, u64)> {
 fn send_file_data(file: &mut File, stream: &mut TcpStream, length: u64) -> io::Result<()> {
     let mut remaining = length;
     let mut buf = [0u8; 262144]; // 256KB buffer
-    while remaining > 0 {
+    let result = loop {
+        if remaining == 0 {
+            break Ok(());
+        }
         let to_read = (remaining as usize).min(buf.len());
-        let n = file.read(&mut buf[..to_read])?;
-        if n == 0 {
-            break;
+        let n = match file.read(&mut buf[..to_read]) {
+            Ok(0) => break Ok(()),
+            Ok(n) => n,
+            Err(e) => break Err(e),
+        };
+        if let Err(e) = stream.write_all(&buf[..n]) {
+            break Err(e);
         }
-        stream.write_all(&buf[..n])?;
         remaining -= n as u64;
-    }
-    Ok(())
+    };
+    // Also when the player dropped the connection (seeking)
+    crate::transfer_stats::media_served(length - remaining);
+    result
 }
 
 /// Send a simple HTTP error response.
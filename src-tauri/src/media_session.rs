@@ -0,0 +1,197 @@
+//! OS media session for audio and video played in wiki windows
+//!
+//! When a wiki window plays media, `init_script/media_session.js` reports it
+//! (`media_session_update`) and the wiki process shows it in the OS media
+//! session: MPRIS on Linux, the System Media Transport Controls on Windows and
+//! Now Playing on macOS. Hardware media keys and the OS media controls come
+//! back to the window that played last as `media-session-action` events.
+//!
+//! Every wiki process has its own session, created on the main thread the
+//! first time something plays.
+
+use std::cell::RefCell;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::Deserialize;
+use serde_json::json;
+use souvlaki::{
+    MediaControlEvent, MediaControls, MediaMetadata, MediaPlayback, MediaPosition, PlatformConfig, SeekDirection,
+};
+use tauri::{AppHandle, Emitter, Manager, WebviewWindow};
+
+/// Seconds skipped by the seek keys
+const SEEK_STEP: f64 = 10.0;
+
+thread_local! {
+    /// Session of this process (only used on the main thread)
+    static CONTROLS: RefCell<Option<MediaControls>> = const { RefCell::new(None) };
+}
+
+/// Window that played last (the target of media keys)
+static ACTIVE_WINDOW: Mutex<Option<String>> = Mutex::new(None);
+
+/// What a window plays
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaState {
+    /// "playing", "paused" or "stopped"
+    playback: String,
+    title: Option<String>,
+    artist: Option<String>,
+    album: Option<String>,
+    /// Seconds
+    duration: Option<f64>,
+    /// Seconds
+    position: Option<f64>,
+}
+
+fn seconds(value: Option<f64>) -> Option<Duration> {
+    value
+        .filter(|v| v.is_finite() && *v >= 0.0)
+        .map(Duration::from_secs_f64)
+}
+
+fn signed(direction: &SeekDirection, secs: f64) -> f64 {
+    match direction {
+        SeekDirection::Forward => secs,
+        SeekDirection::Backward => -secs,
+    }
+}
+
+/// Action for the page from a media key or the OS media controls
+fn page_action(event: &MediaControlEvent) -> Option<serde_json::Value> {
+    Some(match event {
+        MediaControlEvent::Play => json!({ "action": "play" }),
+        MediaControlEvent::Pause => json!({ "action": "pause" }),
+        MediaControlEvent::Toggle => json!({ "action": "toggle" }),
+        MediaControlEvent::Next => json!({ "action": "next" }),
+        MediaControlEvent::Previous => json!({ "action": "previous" }),
+        MediaControlEvent::Stop => json!({ "action": "stop" }),
+        MediaControlEvent::Seek(direction) => json!({ "action": "seekBy", "offset": signed(direction, SEEK_STEP) }),
+        MediaControlEvent::SeekBy(direction, by) => {
+            json!({ "action": "seekBy", "offset": signed(direction, by.as_secs_f64()) })
+        }
+        MediaControlEvent::SetPosition(MediaPosition(position)) => {
+            json!({ "action": "seekTo", "position": position.as_secs_f64() })
+        }
+        MediaControlEvent::SetVolume(volume) => json!({ "action": "volume", "volume": volume.clamp(0.0, 1.0) }),
+        _ => return None,
+    })
+}
+
+fn active_window(app: &AppHandle) -> Option<WebviewWindow> {
+    let label = ACTIVE_WINDOW.lock().unwrap().clone()?;
+    app.get_webview_window(&label)
+}
+
+fn handle_event(app: &AppHandle, event: MediaControlEvent) {
+    let Some(window) = active_window(app) else {
+        return;
+    };
+    if matches!(event, MediaControlEvent::Raise) {
+        let target = window.clone();
+        let _ = window.run_on_main_thread(move || {
+            let _ = target.unminimize();
+            let _ = target.show();
+            let _ = target.set_focus();
+        });
+    } else if let Some(action) = page_action(&event) {
+        let _ = window.emit_to(window.label(), "media-session-action", action);
+    }
+}
+
+fn create_controls(window: &WebviewWindow) -> Result<MediaControls, String> {
+    // MPRIS names must be unique on the session bus
+    let dbus_name = format!("tiddlydesktop_rs.wiki{}", std::process::id());
+    let display_name = window.title().unwrap_or_else(|_| "TiddlyDesktop".to_string());
+    #[cfg(target_os = "windows")]
+    let hwnd = Some(window.hwnd().map_err(|e| format!("Failed to get window handle: {}", e))?.0 as *mut std::ffi::c_void);
+    #[cfg(not(target_os = "windows"))]
+    let hwnd = None;
+
+    let mut controls = MediaControls::new(PlatformConfig {
+        dbus_name: &dbus_name,
+        display_name: &display_name,
+        hwnd,
+    })
+    .map_err(|e| format!("Failed to create media session: {:?}", e))?;
+    let app = window.app_handle().clone();
+    controls
+        .attach(move |event| handle_event(&app, event))
+        .map_err(|e| format!("Failed to attach media session: {:?}", e))?;
+    Ok(controls)
+}
+
+fn update(window: &WebviewWindow, state: &MediaState) -> Result<(), String> {
+    CONTROLS.with(|cell| {
+        let mut controls = cell.borrow_mut();
+        if controls.is_none() {
+            if state.playback == "stopped" {
+                return Ok(());
+            }
+            *controls = Some(create_controls(window)?);
+        }
+        let Some(controls) = controls.as_mut() else {
+            return Ok(());
+        };
+
+        let progress = seconds(state.position).map(MediaPosition);
+        let playback = match state.playback.as_str() {
+            "playing" => MediaPlayback::Playing { progress },
+            "paused" => MediaPlayback::Paused { progress },
+            _ => MediaPlayback::Stopped,
+        };
+        controls
+            .set_metadata(MediaMetadata {
+                title: state.title.as_deref(),
+                artist: state.artist.as_deref(),
+                album: state.album.as_deref(),
+                cover_url: None,
+                duration: seconds(state.duration),
+            })
+            .map_err(|e| format!("Failed to set media metadata: {:?}", e))?;
+        controls
+            .set_playback(playback)
+            .map_err(|e| format!("Failed to set media playback: {:?}", e))
+    })
+}
+
+/// Show what a window plays in the OS media session
+#[tauri::command]
+pub fn media_session_update(window: WebviewWindow, state: MediaState) {
+    *ACTIVE_WINDOW.lock().unwrap() = Some(window.label().to_string());
+    let target = window.clone();
+    let _ = window.run_on_main_thread(move || {
+        if let Err(e) = update(&target, &state) {
+            eprintln!("[TiddlyDesktop] Media session: {}", e);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_action() {
+        assert_eq!(page_action(&MediaControlEvent::Toggle), Some(json!({ "action": "toggle" })));
+        assert_eq!(
+            page_action(&MediaControlEvent::Seek(SeekDirection::Backward)),
+            Some(json!({ "action": "seekBy", "offset": -10.0 }))
+        );
+        assert_eq!(
+            page_action(&MediaControlEvent::SetPosition(MediaPosition(Duration::from_secs(90)))),
+            Some(json!({ "action": "seekTo", "position": 90.0 }))
+        );
+        assert_eq!(page_action(&MediaControlEvent::Quit), None);
+    }
+
+    #[test]
+    fn test_seconds() {
+        assert_eq!(seconds(Some(1.5)), Some(Duration::from_millis(1500)));
+        assert_eq!(seconds(Some(f64::INFINITY)), None);
+        assert_eq!(seconds(Some(-1.0)), None);
+        assert_eq!(seconds(None), None);
+    }
+}
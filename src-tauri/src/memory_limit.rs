@@ -0,0 +1,187 @@
+//! Soft memory limit for wiki processes
+//!
+//! The main process periodically adds up the memory of each wiki process and
+//! its children (the webview's web/renderer processes do most of the work).
+//! When a wiki exceeds the limit (app setting `memory_limit_mb`, 0 = off), the
+//! wiki process is told over IPC; `init_script/memory_limit.js` then asks the
+//! user to save and restart it, or does so right away with
+//! `memory_limit_auto_restart`. The wiki process asks the main process to
+//! reopen it (`RestartWiki`) and exits.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use tauri::{AppHandle, Manager};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How long a restart waits for the old process to exit
+const RESTART_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Wikis being restarted (the app must not quit while none is running)
+static RESTARTING: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MemoryLimitSettings {
+    pub limit_mb: u32,
+    pub auto_restart: bool,
+}
+
+/// Whether a wiki restart is in progress
+pub fn restart_pending() -> bool {
+    !RESTARTING.lock().unwrap().is_empty()
+}
+
+/// Memory of `root` and all its descendants, from (pid, parent pid, bytes) triples
+fn tree_memory(processes: &[(u32, Option<u32>, u64)], root: u32) -> u64 {
+    let mut children: HashMap<u32, Vec<u32>> = HashMap::new();
+    let mut memory: HashMap<u32, u64> = HashMap::new();
+    for &(pid, parent, bytes) in processes {
+        memory.insert(pid, bytes);
+        if let Some(parent) = parent {
+            children.entry(parent).or_default().push(pid);
+        }
+    }
+
+    let mut total = 0;
+    let mut seen = HashSet::new();
+    let mut stack = vec![root];
+    while let Some(pid) = stack.pop() {
+        if !seen.insert(pid) {
+            continue;
+        }
+        total += memory.get(&pid).copied().unwrap_or(0);
+        if let Some(kids) = children.get(&pid) {
+            stack.extend(kids);
+        }
+    }
+    total
+}
+
+fn process_snapshot(sys: &mut sysinfo::System) -> Vec<(u32, Option<u32>, u64)> {
+    use sysinfo::{ProcessRefreshKind, ProcessesToUpdate};
+    sys.refresh_processes_specifics(ProcessesToUpdate::All, true, ProcessRefreshKind::nothing().with_memory());
+    sys.processes()
+        .iter()
+        .map(|(pid, p)| (pid.as_u32(), p.parent().map(|pp| pp.as_u32()), p.memory()))
+        .collect()
+}
+
+/// Watch the memory of all wiki processes (main process)
+#[cfg(not(target_os = "android"))]
+pub fn start(app: &AppHandle) {
+    let app = app.clone();
+    std::thread::spawn(move || {
+        let mut sys = sysinfo::System::new();
+        // Processes already told, so the prompt isn't repeated every poll
+        let mut notified: HashSet<u32> = HashSet::new();
+        loop {
+            std::thread::sleep(POLL_INTERVAL);
+            let settings = crate::wiki_storage::load_app_settings(&app).unwrap_or_default();
+            if settings.memory_limit_mb == 0 {
+                notified.clear();
+                continue;
+            }
+            let wikis: Vec<(String, u32)> = {
+                let state = app.state::<crate::AppState>();
+                let processes = state.wiki_processes.lock().unwrap();
+                processes.values().map(|p| (p.path.clone(), p.pid)).collect()
+            };
+            notified.retain(|pid| wikis.iter().any(|(_, p)| p == pid));
+            if wikis.is_empty() {
+                continue;
+            }
+
+            let snapshot = process_snapshot(&mut sys);
+            let limit_mb = u64::from(settings.memory_limit_mb);
+            for (path, pid) in wikis {
+                let used_mb = tree_memory(&snapshot, pid) / (1024 * 1024);
+                if used_mb <= limit_mb || notified.contains(&pid) {
+                    continue;
+                }
+                eprintln!("[TiddlyDesktop] Wiki {} uses {} MB (limit {} MB)", path, used_mb, limit_mb);
+                notified.insert(pid);
+                if let Some(server) = crate::GLOBAL_IPC_SERVER.get() {
+                    let _ = server.send_memory_limit_exceeded(&path, used_mb, limit_mb, settings.memory_limit_auto_restart);
+                }
+            }
+        }
+    });
+}
+
+/// Show the memory limit prompt in this wiki process's window
+pub fn notify_window(app: &AppHandle, used_mb: u64, limit_mb: u64, auto_restart: bool) {
+    let script = format!(
+        "window.__tdMemoryLimit && window.__tdMemoryLimit({})",
+        serde_json::json!({ "usedMb": used_mb, "limitMb": limit_mb, "autoRestart": auto_restart })
+    );
+    let handle = app.clone();
+    let _ = app.run_on_main_thread(move || {
+        if let Some((_, window)) = handle.webview_windows().into_iter().next() {
+            let _ = window.eval(&script);
+        }
+    });
+}
+
+/// Reopen a wiki once its process has exited (main process, on `RestartWiki`)
+#[cfg(not(target_os = "android"))]
+pub fn restart_wiki(app: &AppHandle, wiki_path: String) {
+    RESTARTING.lock().unwrap().push(wiki_path.clone());
+    let app = app.clone();
+    std::thread::spawn(move || {
+        let started = std::time::Instant::now();
+        while crate::is_wiki_open(app.clone(), wiki_path.clone()) && started.elapsed() < RESTART_TIMEOUT {
+            std::thread::sleep(Duration::from_millis(250));
+        }
+        let result = if std::path::Path::new(&wiki_path).is_dir() {
+            tauri::async_runtime::block_on(crate::open_wiki_folder(app.clone(), wiki_path.clone(), None))
+        } else {
+            tauri::async_runtime::block_on(crate::open_wiki_window(app.clone(), wiki_path.clone(), None, None, None))
+        };
+        if let Err(e) = result {
+            eprintln!("[TiddlyDesktop] Failed to restart wiki {}: {}", wiki_path, e);
+        }
+        RESTARTING.lock().unwrap().retain(|p| p != &wiki_path);
+    });
+}
+
+/// Get the soft memory limit for wiki processes
+#[tauri::command]
+pub fn get_memory_limit_settings(app: AppHandle) -> MemoryLimitSettings {
+    let settings = crate::wiki_storage::load_app_settings(&app).unwrap_or_default();
+    MemoryLimitSettings {
+        limit_mb: settings.memory_limit_mb,
+        auto_restart: settings.memory_limit_auto_restart,
+    }
+}
+
+/// Set the soft memory limit for wiki processes (0 = off)
+#[tauri::command]
+pub fn set_memory_limit(app: AppHandle, limit_mb: u32, auto_restart: bool) -> Result<MemoryLimitSettings, String> {
+    let mut settings = crate::wiki_storage::load_app_settings(&app)?;
+    settings.memory_limit_mb = limit_mb;
+    settings.memory_limit_auto_restart = auto_restart;
+    crate::wiki_storage::save_app_settings(&app, &settings)?;
+    Ok(MemoryLimitSettings { limit_mb, auto_restart })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sums_process_tree() {
+        let processes = [
+            (1, None, 100),
+            (10, Some(1), 200),
+            (11, Some(10), 300),
+            (20, Some(1), 50),
+            (30, None, 1000),
+        ];
+        assert_eq!(tree_memory(&processes, 10), 500);
+        assert_eq!(tree_memory(&processes, 1), 650);
+        assert_eq!(tree_memory(&processes, 99), 0);
+    }
+}
@@ -0,0 +1,344 @@
+//! Metered connection awareness
+//!
+//! While the connection is metered or roaming, background traffic waits for
+//! an unmetered one: connecting relay rooms, fetching plugins from plugin
+//! libraries and checking for updates. Each can be allowed anyway
+//! (`metered_allow` in the app settings). LAN sync and rooms the user
+//! connects by hand are never deferred.
+//!
+//! The connection cost comes from
+//! - Linux: NetworkManager's `Metered` property over D-Bus
+//! - Windows: the cost hints of the internet connection profile
+//! - Android: ConnectivityManager (roaming from the network capabilities)
+//!
+//! Elsewhere, and when the OS can't tell, connections count as unmetered.
+//! The main process checks every minute and disconnects or reconnects the
+//! relay rooms when that changes.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+
+use crate::types::MeteredAllow;
+
+/// How long a reading of the connection cost is reused
+const CACHE_TTL: Duration = Duration::from_secs(30);
+
+/// How often the main process looks for changes
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Cost hints of the current connection
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct NetworkCost {
+    pub metered: bool,
+    pub roaming: bool,
+}
+
+/// Background traffic deferred on metered connections
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Feature {
+    RelaySync,
+    PluginLibrary,
+    Updates,
+}
+
+impl Feature {
+    /// Name used by the landing page
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "relay-sync" => Some(Self::RelaySync),
+            "plugin-library" => Some(Self::PluginLibrary),
+            "updates" => Some(Self::Updates),
+            _ => None,
+        }
+    }
+
+    fn allowed(self, allow: &MeteredAllow) -> bool {
+        match self {
+            Self::RelaySync => allow.relay_sync,
+            Self::PluginLibrary => allow.plugin_library,
+            Self::Updates => allow.updates,
+        }
+    }
+
+    fn set_allowed(self, allow: &mut MeteredAllow, allowed: bool) {
+        match self {
+            Self::RelaySync => allow.relay_sync = allowed,
+            Self::PluginLibrary => allow.plugin_library = allowed,
+            Self::Updates => allow.updates = allowed,
+        }
+    }
+}
+
+/// Last reading of the connection cost
+static CACHE: Mutex<Option<(Instant, NetworkCost)>> = Mutex::new(None);
+
+/// Connection cost and whether relay sync was deferred at the last check (main process)
+static LAST_CHECK: Mutex<Option<(NetworkCost, bool)>> = Mutex::new(None);
+
+/// NMMetered: YES (1) and GUESS_YES (3) are metered; UNKNOWN, NO and GUESS_NO aren't
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn nm_metered(value: u32) -> bool {
+    matches!(value, 1 | 3)
+}
+
+#[cfg(target_os = "linux")]
+fn os_network_cost() -> Result<NetworkCost, String> {
+    use zbus::blocking::{Connection, Proxy};
+
+    fn nm_err(e: impl std::fmt::Display) -> String {
+        format!("NetworkManager: {}", e)
+    }
+
+    let conn = Connection::system().map_err(nm_err)?;
+    let manager = Proxy::new(
+        &conn,
+        "org.freedesktop.NetworkManager",
+        "/org/freedesktop/NetworkManager",
+        "org.freedesktop.NetworkManager",
+    )
+    .map_err(nm_err)?;
+    let metered: u32 = manager.get_property("Metered").map_err(nm_err)?;
+    Ok(NetworkCost {
+        metered: nm_metered(metered),
+        roaming: false,
+    })
+}
+
+#[cfg(target_os = "windows")]
+fn os_network_cost() -> Result<NetworkCost, String> {
+    use windows::Networking::Connectivity::{NetworkCostType, NetworkInformation};
+
+    fn cost_err(e: windows::core::Error) -> String {
+        format!("Connection cost: {}", e)
+    }
+
+    // No internet connection profile: offline, nothing to defer
+    let Ok(profile) = NetworkInformation::GetInternetConnectionProfile() else {
+        return Ok(NetworkCost::default());
+    };
+    let cost = profile.GetConnectionCost().map_err(cost_err)?;
+    let cost_type = cost.NetworkCostType().map_err(cost_err)?;
+    let near_limit = cost.ApproachingDataLimit().unwrap_or(false) || cost.OverDataLimit().unwrap_or(false);
+    Ok(NetworkCost {
+        metered: cost_type == NetworkCostType::Fixed || cost_type == NetworkCostType::Variable || near_limit,
+        roaming: cost.Roaming().map_err(cost_err)?,
+    })
+}
+
+#[cfg(target_os = "android")]
+fn os_network_cost() -> Result<NetworkCost, String> {
+    use jni::objects::JValue;
+
+    /// NetworkCapabilities.NET_CAPABILITY_NOT_ROAMING
+    const NOT_ROAMING: i32 = 18;
+
+    fn jni_err(e: jni::errors::Error) -> String {
+        format!("Connectivity: {}", e)
+    }
+
+    let vm = crate::android::wiki_activity::get_java_vm()?;
+    let mut env = vm.attach_current_thread().map_err(jni_err)?;
+    let activity_thread = env.find_class("android/app/ActivityThread").map_err(jni_err)?;
+    let context = env
+        .call_static_method(&activity_thread, "currentApplication", "()Landroid/app/Application;", &[])
+        .and_then(|v| v.l())
+        .map_err(jni_err)?;
+    let service = env.new_string("connectivity").map_err(jni_err)?;
+    let manager = env
+        .call_method(&context, "getSystemService", "(Ljava/lang/String;)Ljava/lang/Object;", &[JValue::Object(&service)])
+        .and_then(|v| v.l())
+        .map_err(jni_err)?;
+    let metered = env
+        .call_method(&manager, "isActiveNetworkMetered", "()Z", &[])
+        .and_then(|v| v.z())
+        .map_err(jni_err)?;
+    let network = env
+        .call_method(&manager, "getActiveNetwork", "()Landroid/net/Network;", &[])
+        .and_then(|v| v.l())
+        .map_err(jni_err)?;
+    let roaming = if network.is_null() {
+        false
+    } else {
+        let capabilities = env
+            .call_method(
+                &manager,
+                "getNetworkCapabilities",
+                "(Landroid/net/Network;)Landroid/net/NetworkCapabilities;",
+                &[JValue::Object(&network)],
+            )
+            .and_then(|v| v.l())
+            .map_err(jni_err)?;
+        !capabilities.is_null()
+            && !env
+                .call_method(&capabilities, "hasCapability", "(I)Z", &[JValue::Int(NOT_ROAMING)])
+                .and_then(|v| v.z())
+                .map_err(jni_err)?
+    };
+    Ok(NetworkCost { metered, roaming })
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows", target_os = "android")))]
+fn os_network_cost() -> Result<NetworkCost, String> {
+    Ok(NetworkCost::default())
+}
+
+/// Cost hints of the current connection (blocking; read again after `CACHE_TTL`)
+pub fn network_cost() -> NetworkCost {
+    let mut cache = CACHE.lock().unwrap();
+    if let Some((read_at, cost)) = *cache {
+        if read_at.elapsed() < CACHE_TTL {
+            return cost;
+        }
+    }
+    let cost = os_network_cost().unwrap_or_else(|e| {
+        eprintln!("[Metered] {}", e);
+        NetworkCost::default()
+    });
+    *cache = Some((Instant::now(), cost));
+    cost
+}
+
+/// Whether a feature waits on a connection with these cost hints
+fn is_deferred(cost: NetworkCost, allow: &MeteredAllow, feature: Feature) -> bool {
+    (cost.metered || cost.roaming) && !feature.allowed(allow)
+}
+
+fn allowed_features(app: &AppHandle) -> MeteredAllow {
+    crate::wiki_storage::load_app_settings(app)
+        .map(|settings| settings.metered_allow)
+        .unwrap_or_default()
+}
+
+/// Whether the background traffic of a feature should wait for an unmetered connection
+pub async fn defers(app: &AppHandle, feature: Feature) -> bool {
+    let allow = allowed_features(app);
+    if feature.allowed(&allow) {
+        return false;
+    }
+    let cost = tokio::task::spawn_blocking(network_cost).await.unwrap_or_default();
+    is_deferred(cost, &allow, feature)
+}
+
+/// Connection cost and what may use a metered connection
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MeteredStatus {
+    metered: bool,
+    roaming: bool,
+    allow_relay_sync: bool,
+    allow_plugin_library: bool,
+    allow_updates: bool,
+}
+
+fn status(cost: NetworkCost, allow: &MeteredAllow) -> MeteredStatus {
+    MeteredStatus {
+        metered: cost.metered,
+        roaming: cost.roaming,
+        allow_relay_sync: allow.relay_sync,
+        allow_plugin_library: allow.plugin_library,
+        allow_updates: allow.updates,
+    }
+}
+
+/// Tell the landing page about connection changes and disconnect or reconnect
+/// the relay rooms when relay sync becomes deferred or no longer is
+fn check(app: &AppHandle) {
+    let cost = network_cost();
+    let allow = allowed_features(app);
+    let relay_deferred = is_deferred(cost, &allow, Feature::RelaySync);
+    // The first check only records the state; sync startup already honours it
+    let Some((last_cost, last_relay_deferred)) = LAST_CHECK.lock().unwrap().replace((cost, relay_deferred)) else {
+        return;
+    };
+
+    if cost != last_cost {
+        eprintln!("[Metered] Connection changed: metered={}, roaming={}", cost.metered, cost.roaming);
+        let _ = app.emit("metered-connection-changed", status(cost, &allow));
+    }
+    if relay_deferred != last_relay_deferred {
+        if let Some(mgr) = crate::lan_sync::get_sync_manager() {
+            tauri::async_runtime::spawn(async move {
+                if relay_deferred {
+                    mgr.suspend_relay().await;
+                } else {
+                    mgr.resume_relay().await;
+                }
+            });
+        }
+    }
+}
+
+/// Watch the connection cost (main process, with sync)
+pub fn start(app: &AppHandle) {
+    let app = app.clone();
+    std::thread::spawn(move || loop {
+        check(&app);
+        std::thread::sleep(CHECK_INTERVAL);
+    });
+}
+
+/// Cost of the current connection and what may use it when metered
+#[tauri::command]
+pub async fn get_metered_status(app: AppHandle) -> MeteredStatus {
+    let cost = tokio::task::spawn_blocking(network_cost).await.unwrap_or_default();
+    status(cost, &allowed_features(&app))
+}
+
+/// Let a feature ("relay-sync", "plugin-library" or "updates") use metered
+/// connections, or defer it again
+#[tauri::command]
+pub async fn set_metered_allowed(app: AppHandle, feature: String, allowed: bool) -> Result<MeteredStatus, String> {
+    let parsed = Feature::parse(&feature).ok_or_else(|| format!("Unknown feature: {}", feature))?;
+    let mut settings = crate::wiki_storage::load_app_settings(&app)?;
+    parsed.set_allowed(&mut settings.metered_allow, allowed);
+    crate::wiki_storage::save_app_settings(&app, &settings)?;
+
+    let for_check = app.clone();
+    let cost = tokio::task::spawn_blocking(move || {
+        check(&for_check);
+        network_cost()
+    })
+    .await
+    .map_err(|e| format!("Failed to check the connection: {}", e))?;
+    Ok(status(cost, &settings.metered_allow))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nm_metered() {
+        assert!(nm_metered(1));
+        assert!(nm_metered(3));
+        assert!(!nm_metered(0));
+        assert!(!nm_metered(2));
+        assert!(!nm_metered(4));
+    }
+
+    #[test]
+    fn test_is_deferred() {
+        let metered = NetworkCost { metered: true, roaming: false };
+        let roaming = NetworkCost { metered: false, roaming: true };
+        let allow = MeteredAllow { relay_sync: true, ..Default::default() };
+        assert!(!is_deferred(NetworkCost::default(), &MeteredAllow::default(), Feature::Updates));
+        assert!(is_deferred(metered, &MeteredAllow::default(), Feature::Updates));
+        assert!(is_deferred(roaming, &allow, Feature::PluginLibrary));
+        assert!(!is_deferred(roaming, &allow, Feature::RelaySync));
+    }
+
+    #[test]
+    fn test_feature_names() {
+        let mut allow = MeteredAllow::default();
+        for name in ["relay-sync", "plugin-library", "updates"] {
+            let feature = Feature::parse(name).unwrap();
+            feature.set_allowed(&mut allow, true);
+            assert!(feature.allowed(&allow));
+        }
+        assert_eq!(allow, MeteredAllow { relay_sync: true, plugin_library: true, updates: true });
+        assert_eq!(Feature::parse("lan-sync"), None);
+    }
+}
@@ -0,0 +1,347 @@
+//! MQTT client for wiki windows (home automation dashboards)
+//!
+//! Each wiki process can hold one connection to an MQTT broker, set up by
+//! `init_script/mqtt.js` from the wiki's config tiddlers. Messages on the
+//! subscribed topics are emitted to the window as `mqtt-message`
+//! (`{ topic, payload, retain }`), connection changes as `mqtt-status`
+//! (`{ connected, error }`), and `mqtt_publish` sends messages, e.g. from
+//! action widgets that toggle a device.
+//!
+//! Like serial ports, brokers need the user's permission: the first
+//! connection of a wiki asks, and the answer is recorded per wiki (`mqtt` in
+//! the wiki configs) and can be reset on the landing page. Publishing needs
+//! the recorded permission too, so a reset takes effect without reconnecting.
+//!
+//! Brokers are given as `mqtt://host[:port]` (1883) or `mqtts://host[:port]`
+//! (8883, TLS). The client is part of the optional `mqtt` feature (rumqttc);
+//! builds without it report that MQTT isn't available.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use serde::Serialize;
+use tauri::AppHandle;
+
+use crate::wiki_storage::{load_wiki_configs, save_wiki_configs};
+
+/// Only one permission dialog at a time
+static ASKING: Mutex<()> = Mutex::new(());
+
+#[derive(Clone, Debug, Serialize)]
+pub struct MqttMessage {
+    topic: String,
+    payload: String,
+    retain: bool,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct MqttStatus {
+    connected: bool,
+    error: Option<String>,
+}
+
+/// Host, port and whether to use TLS for a broker URL
+#[cfg_attr(not(feature = "mqtt"), allow(dead_code))]
+fn parse_broker(broker: &str) -> Result<(String, u16, bool), String> {
+    let broker = broker.trim();
+    let (rest, tls) = if let Some(rest) = broker.strip_prefix("mqtts://") {
+        (rest, true)
+    } else if let Some(rest) = broker.strip_prefix("mqtt://") {
+        (rest, false)
+    } else if broker.contains("://") {
+        return Err(format!("Unsupported MQTT broker URL \"{}\" (use mqtt:// or mqtts://)", broker));
+    } else {
+        (broker, false)
+    };
+    let authority = rest.trim_end_matches('/');
+    if authority.is_empty() || authority.contains('/') {
+        return Err(format!("Invalid MQTT broker \"{}\"", broker));
+    }
+    let default_port = if tls { 8883 } else { 1883 };
+    // "[::1]:1883" or "host:1883"
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) if !port.contains(']') => {
+            let port = port.parse::<u16>().map_err(|_| format!("Invalid MQTT broker port \"{}\"", port))?;
+            (host, port)
+        }
+        _ => (authority, default_port),
+    };
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    if host.is_empty() {
+        return Err(format!("Invalid MQTT broker \"{}\"", broker));
+    }
+    Ok((host.to_string(), port, tls))
+}
+
+/// Whether a wiki may connect to MQTT brokers, asking the user the first time
+fn permission(app: &AppHandle, wiki_path: &str, broker: &str) -> Result<bool, String> {
+    use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+
+    let _asking = ASKING.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(allowed) = load_wiki_configs(app)?.mqtt.get(wiki_path) {
+        return Ok(*allowed);
+    }
+    let allowed = app
+        .dialog()
+        .message(format!(
+            "\"{}\" wants to connect to the MQTT broker {} (home automation, sensors). \
+             Allow it to use MQTT brokers now and in the future?",
+            crate::geolocation::wiki_name(wiki_path),
+            broker.trim()
+        ))
+        .kind(MessageDialogKind::Warning)
+        .title("MQTT")
+        .buttons(MessageDialogButtons::OkCancelCustom("Allow".to_string(), "Deny".to_string()))
+        .blocking_show();
+    let mut configs = load_wiki_configs(app)?;
+    configs.mqtt.insert(wiki_path.to_string(), allowed);
+    save_wiki_configs(app, &configs)?;
+    Ok(allowed)
+}
+
+fn require_permission(app: &AppHandle, wiki_path: &str, broker: &str) -> Result<(), String> {
+    if permission(app, wiki_path, broker)? {
+        Ok(())
+    } else {
+        Err("MQTT access was denied for this wiki".to_string())
+    }
+}
+
+/// The wiki of this process, whose permission the commands check
+#[cfg(not(target_os = "android"))]
+fn this_wiki(state: &tauri::State<'_, crate::WikiModeState>) -> String {
+    state.wiki_path.to_string_lossy().into_owned()
+}
+
+/// The connection is only used while the wiki's permission stands (it can be
+/// reset on the landing page meanwhile)
+fn require_recorded_permission(app: &AppHandle, wiki_path: &str) -> Result<(), String> {
+    if load_wiki_configs(app)?.mqtt.get(wiki_path) == Some(&true) {
+        Ok(())
+    } else {
+        Err("MQTT access was denied for this wiki".to_string())
+    }
+}
+
+#[cfg(feature = "mqtt")]
+mod backend {
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    use rumqttc::{AsyncClient, Event, MqttOptions, Packet, QoS, Transport};
+    use tauri::Emitter;
+
+    use super::{parse_broker, MqttMessage, MqttStatus};
+
+    const KEEP_ALIVE: Duration = Duration::from_secs(30);
+    const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+    struct Connection {
+        client: AsyncClient,
+        task: tauri::async_runtime::JoinHandle<()>,
+    }
+
+    /// The connection of this wiki process
+    static CONNECTION: Mutex<Option<Connection>> = Mutex::new(None);
+
+    fn qos(level: u8) -> QoS {
+        match level {
+            0 => QoS::AtMostOnce,
+            1 => QoS::AtLeastOnce,
+            _ => QoS::ExactlyOnce,
+        }
+    }
+
+    pub async fn connect(
+        app: tauri::AppHandle,
+        broker: String,
+        username: Option<String>,
+        password: Option<String>,
+        topics: Vec<String>,
+    ) -> Result<(), String> {
+        let (host, port, tls) = parse_broker(&broker)?;
+        let client_id = format!("tiddlydesktop-{:08x}", rand::random::<u32>());
+        let mut options = MqttOptions::new(client_id, host, port);
+        options.set_keep_alive(KEEP_ALIVE);
+        if let Some(username) = username.filter(|u| !u.is_empty()) {
+            options.set_credentials(username, password.unwrap_or_default());
+        }
+        if tls {
+            options.set_transport(Transport::tls_with_default_config());
+        }
+        disconnect().await?;
+
+        let (client, mut eventloop) = AsyncClient::new(options, 64);
+        let subscriber = client.clone();
+        let task = tauri::async_runtime::spawn(async move {
+            loop {
+                match eventloop.poll().await {
+                    // (Re)subscribe on every connect: the broker forgets
+                    // subscriptions of clean sessions
+                    Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                        for topic in &topics {
+                            if let Err(e) = subscriber.subscribe(topic.as_str(), QoS::AtMostOnce).await {
+                                eprintln!("[MQTT] Failed to subscribe to {}: {}", topic, e);
+                            }
+                        }
+                        let _ = app.emit("mqtt-status", MqttStatus { connected: true, error: None });
+                    }
+                    Ok(Event::Incoming(Packet::Publish(publish))) => {
+                        let message = MqttMessage {
+                            topic: publish.topic,
+                            payload: String::from_utf8_lossy(&publish.payload).into_owned(),
+                            retain: publish.retain,
+                        };
+                        let _ = app.emit("mqtt-message", message);
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        eprintln!("[MQTT] Connection error: {}", e);
+                        let status = MqttStatus { connected: false, error: Some(e.to_string()) };
+                        let _ = app.emit("mqtt-status", status);
+                        // The event loop reconnects on the next poll
+                        tokio::time::sleep(RECONNECT_DELAY).await;
+                    }
+                }
+            }
+        });
+        *CONNECTION.lock().unwrap() = Some(Connection { client, task });
+        Ok(())
+    }
+
+    pub async fn publish(topic: String, payload: String, retain: bool, qos_level: u8) -> Result<(), String> {
+        let client = CONNECTION
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|c| c.client.clone())
+            .ok_or("Not connected to an MQTT broker")?;
+        client
+            .publish(topic, qos(qos_level), retain, payload)
+            .await
+            .map_err(|e| format!("Failed to publish MQTT message: {}", e))
+    }
+
+    pub async fn disconnect() -> Result<(), String> {
+        let connection = CONNECTION.lock().unwrap().take();
+        if let Some(connection) = connection {
+            let _ = connection.client.disconnect().await;
+            connection.task.abort();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "mqtt"))]
+mod backend {
+    const NOT_AVAILABLE: &str = "MQTT isn't available: this build has no MQTT support";
+
+    pub async fn connect(
+        _app: tauri::AppHandle,
+        _broker: String,
+        _username: Option<String>,
+        _password: Option<String>,
+        _topics: Vec<String>,
+    ) -> Result<(), String> {
+        Err(NOT_AVAILABLE.to_string())
+    }
+
+    pub async fn publish(_topic: String, _payload: String, _retain: bool, _qos_level: u8) -> Result<(), String> {
+        Err(NOT_AVAILABLE.to_string())
+    }
+
+    pub async fn disconnect() -> Result<(), String> {
+        Ok(())
+    }
+}
+
+/// Connect this wiki window to a broker and subscribe to `topics` (MQTT
+/// wildcards allowed), replacing the previous connection
+#[cfg(not(target_os = "android"))]
+#[tauri::command]
+pub async fn mqtt_connect(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, crate::WikiModeState>,
+    broker: String,
+    username: Option<String>,
+    password: Option<String>,
+    topics: Vec<String>,
+) -> Result<(), String> {
+    let topics = topics
+        .into_iter()
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+        .collect();
+    // Builds without MQTT have nothing to ask about
+    if cfg!(feature = "mqtt") {
+        let wiki_path = this_wiki(&state);
+        let (asker, asked_broker) = (app.clone(), broker.clone());
+        tokio::task::spawn_blocking(move || require_permission(&asker, &wiki_path, &asked_broker))
+            .await
+            .map_err(|e| format!("MQTT request failed: {}", e))??;
+    }
+    backend::connect(app, broker, username, password, topics).await
+}
+
+/// Publish a message (QoS 0-2, default 0)
+#[cfg(not(target_os = "android"))]
+#[tauri::command]
+pub async fn mqtt_publish(
+    app: AppHandle,
+    state: tauri::State<'_, crate::WikiModeState>,
+    topic: String,
+    payload: String,
+    retain: Option<bool>,
+    qos: Option<u8>,
+) -> Result<(), String> {
+    if topic.is_empty() || topic.contains(['+', '#']) {
+        return Err(format!("Invalid MQTT topic \"{}\"", topic));
+    }
+    if cfg!(feature = "mqtt") {
+        require_recorded_permission(&app, &this_wiki(&state))?;
+    }
+    backend::publish(topic, payload, retain.unwrap_or(false), qos.unwrap_or(0).min(2)).await
+}
+
+#[tauri::command]
+pub async fn mqtt_disconnect() -> Result<(), String> {
+    backend::disconnect().await
+}
+
+/// Recorded MQTT access of all wikis, keyed by wiki path
+#[tauri::command]
+pub fn get_mqtt_permissions(app: AppHandle) -> Result<HashMap<String, bool>, String> {
+    Ok(load_wiki_configs(&app)?.mqtt)
+}
+
+/// Allow or deny a wiki's MQTT access (None = ask again)
+#[tauri::command]
+pub fn set_mqtt_permission(app: AppHandle, wiki_path: String, allowed: Option<bool>) -> Result<(), String> {
+    let mut configs = load_wiki_configs(&app)?;
+    match allowed {
+        Some(allowed) => {
+            configs.mqtt.insert(wiki_path, allowed);
+        }
+        None => {
+            configs.mqtt.remove(&wiki_path);
+        }
+    }
+    save_wiki_configs(&app, &configs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_broker() {
+        assert_eq!(parse_broker("mqtt://ha.local"), Ok(("ha.local".to_string(), 1883, false)));
+        assert_eq!(parse_broker("mqtts://broker.example.com/"), Ok(("broker.example.com".to_string(), 8883, true)));
+        assert_eq!(parse_broker("192.168.1.5:1884"), Ok(("192.168.1.5".to_string(), 1884, false)));
+        assert_eq!(parse_broker("mqtt://[::1]:1883"), Ok(("::1".to_string(), 1883, false)));
+        assert_eq!(parse_broker("mqtt://[::1]"), Ok(("::1".to_string(), 1883, false)));
+        assert!(parse_broker("ws://ha.local").is_err());
+        assert!(parse_broker("mqtt://ha.local:port").is_err());
+        assert!(parse_broker("mqtt://").is_err());
+    }
+}
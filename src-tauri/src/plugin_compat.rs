@@ -0,0 +1,332 @@
+//! Plugin compatibility check before plugins are installed into a wiki
+//!
+//! Installing plugins into a single-file wiki rebuilds it with the bundled
+//! TiddlyWiki (see `install_plugins_to_wiki`), and a folder wiki always runs
+//! on it, so plugins made for another core version end up running on this
+//! one. `check_plugin_compatibility` looks at the plugins the wiki will have
+//! after the install and reports:
+//! - plugins whose `core-version` range excludes the bundled core; these can
+//!   keep the wiki from booting
+//! - JavaScript modules of a type that nothing loads any more: the type isn't
+//!   named anywhere in the bundled core or in the wiki's plugins, as with
+//!   module types that were removed from the core
+//!
+//! The landing page shows the report and lets the user cancel the install.
+
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::LazyLock;
+
+use regex::Regex;
+use serde::Serialize;
+use tiddlydesktop_core::filter::Tiddler;
+use tiddlydesktop_core::wiki_folder::{self, TiddlyWikiInstall};
+use tiddlydesktop_core::{filter, tiddler_store};
+
+/// Quoted names in JavaScript source that could be module types
+static STRING_LITERAL_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"["']([a-z][a-z0-9-]*)["']"#).expect("Invalid string literal regex"));
+
+/// Modules that are loaded with `require()` by title instead of by type
+const REQUIRED_MODULE_TYPES: &[&str] = &["library"];
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum IssueKind {
+    /// The plugin's `core-version` excludes the bundled core
+    CoreVersion,
+    /// A module of the plugin has a type nothing loads
+    ModuleType,
+}
+
+/// A plugin that may not work with the bundled core
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PluginIssue {
+    pub title: String,
+    pub version: Option<String>,
+    pub kind: IssueKind,
+    /// The `core-version` range (CoreVersion)
+    pub core_version: Option<String>,
+    /// Title and type of the module (ModuleType)
+    pub module: Option<String>,
+    pub module_type: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompatibilityReport {
+    /// Version of the bundled TiddlyWiki
+    pub core_version: String,
+    pub issues: Vec<PluginIssue>,
+}
+
+/// `5.3.0` or `5.3.0-prerelease` as a sortable key (releases after their prereleases)
+fn parse_version(version: &str) -> Option<(u64, u64, u64, bool)> {
+    let version = version.trim().trim_start_matches('v');
+    let (numbers, prerelease) = match version.split_once('-') {
+        Some((numbers, _)) => (numbers, true),
+        None => (version, false),
+    };
+    let mut parts = numbers.split('.');
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next().map_or(Some(0), |p| p.parse().ok())?;
+    let patch = parts.next().map_or(Some(0), |p| p.parse().ok())?;
+    if parts.next().is_some() {
+        return None;
+    }
+    Some((major, minor, patch, !prerelease))
+}
+
+fn comparator_matches(comparator: &str, version: (u64, u64, u64, bool)) -> Option<bool> {
+    let (op, bound) = ["<=", ">=", "<", ">", "=", "^", "~"]
+        .iter()
+        .find_map(|op| comparator.strip_prefix(op).map(|rest| (*op, rest)))
+        .unwrap_or(("=", comparator));
+    let bound = parse_version(bound)?;
+    Some(match op {
+        "<=" => version <= bound,
+        ">=" => version >= bound,
+        "<" => version < bound,
+        ">" => version > bound,
+        "^" => version >= bound && version.0 == bound.0,
+        "~" => version >= bound && (version.0, version.1) == (bound.0, bound.1),
+        _ => (version.0, version.1, version.2) == (bound.0, bound.1, bound.2),
+    })
+}
+
+/// Whether `version` is in a `core-version` range like `>=5.3.0`,
+/// `>=5.2.0 <5.4.0` or `^5.2.0 || >=5.3.4`; None when either can't be read
+pub fn satisfies(range: &str, version: &str) -> Option<bool> {
+    let version = parse_version(version)?;
+    let mut satisfied = false;
+    for alternative in range.split("||") {
+        let mut matches = true;
+        for comparator in alternative.split_whitespace() {
+            matches &= comparator_matches(comparator, version)?;
+        }
+        satisfied |= matches;
+    }
+    Some(satisfied)
+}
+
+/// The shadow tiddlers packed into a plugin tiddler
+fn plugin_tiddlers(plugin: &Tiddler) -> Vec<Tiddler> {
+    plugin
+        .get("text")
+        .and_then(|text| serde_json::from_str::<serde_json::Value>(text).ok())
+        .and_then(|json| {
+            json.get("tiddlers").and_then(|t| t.as_object()).map(|tiddlers| {
+                tiddlers
+                    .iter()
+                    .filter_map(|(title, fields)| {
+                        let mut tiddler = wiki_folder::tiddler_from_json(fields)?;
+                        tiddler.entry("title".to_string()).or_insert_with(|| title.clone());
+                        Some(tiddler)
+                    })
+                    .collect()
+            })
+        })
+        .unwrap_or_default()
+}
+
+fn is_module(tiddler: &Tiddler) -> bool {
+    tiddler.contains_key("module-type") && tiddler.get("type").map(String::as_str) == Some("application/javascript")
+}
+
+/// Add the names quoted in JavaScript source to `names`
+fn collect_string_literals(source: &str, names: &mut HashSet<String>) {
+    for capture in STRING_LITERAL_REGEX.captures_iter(source) {
+        names.insert(capture[1].to_string());
+    }
+}
+
+/// Check `plugins` against a core of `core_version`; `known_types` are the
+/// names quoted in the core's and the plugins' source
+pub fn check_plugins(core_version: &str, plugins: &[Tiddler], known_types: &HashSet<String>) -> Vec<PluginIssue> {
+    let mut issues = Vec::new();
+    for plugin in plugins {
+        let title = plugin.get("title").cloned().unwrap_or_default();
+        let issue = |kind| PluginIssue {
+            title: title.clone(),
+            version: plugin.get("version").cloned(),
+            kind,
+            core_version: None,
+            module: None,
+            module_type: None,
+        };
+        if let Some(range) = plugin.get("core-version").filter(|r| !r.trim().is_empty()) {
+            if satisfies(range, core_version) == Some(false) {
+                issues.push(PluginIssue { core_version: Some(range.clone()), ..issue(IssueKind::CoreVersion) });
+            }
+        }
+        for module in plugin_tiddlers(plugin).iter().filter(|t| is_module(t)) {
+            let module_type = &module["module-type"];
+            if !REQUIRED_MODULE_TYPES.contains(&module_type.as_str()) && !known_types.contains(module_type) {
+                issues.push(PluginIssue {
+                    module: module.get("title").cloned(),
+                    module_type: Some(module_type.clone()),
+                    ..issue(IssueKind::ModuleType)
+                });
+            }
+        }
+    }
+    issues
+}
+
+/// Plugin tiddlers stored in the wiki itself (single-file wikis, or the
+/// `tiddlers/` of wiki folders), apart from the core
+fn stored_plugins(path: &Path, is_folder: bool) -> Result<Vec<Tiddler>, String> {
+    let is_plugin = |t: &Tiddler| t.contains_key("plugin-type") && t.get("title").map(String::as_str) != Some("$:/core");
+    if is_folder {
+        let wiki = filter::Wiki::from_folder(path)?;
+        return Ok(wiki
+            .titles()
+            .iter()
+            .filter_map(|title| wiki.get_tiddler(title))
+            .filter(|t| is_plugin(t))
+            .cloned()
+            .collect());
+    }
+    let mut plugins = Vec::new();
+    for tiddler in tiddler_store::open(path)? {
+        match tiddler.map(|value| wiki_folder::tiddler_from_json(&value)) {
+            Ok(Some(tiddler)) if is_plugin(&tiddler) => plugins.push(tiddler),
+            Ok(_) => {}
+            Err(e) => eprintln!("[TiddlyDesktop] Warning: {}", e),
+        }
+    }
+    Ok(plugins)
+}
+
+/// The plugins of a wiki after installing the library plugins `selected`
+fn plugins_after_install(install: &TiddlyWikiInstall, path: &Path, is_folder: bool, selected: &[String]) -> Result<Vec<Tiddler>, String> {
+    let mut plugins = Vec::new();
+    for name in selected {
+        match install.plugin_folder(name) {
+            Some(dir) => plugins.push(wiki_folder::load_plugin_folder(&dir)?),
+            None => eprintln!("[TiddlyDesktop] Cannot find plugin {}", name),
+        }
+    }
+    let selected_titles: HashSet<String> = selected.iter().map(|name| format!("$:/plugins/{}", name)).collect();
+    plugins.extend(
+        stored_plugins(path, is_folder)?
+            .into_iter()
+            .filter(|p| p.get("title").is_some_and(|title| !selected_titles.contains(title))),
+    );
+    if is_folder {
+        // The wiki's own plugins/, themes/ and languages/ folders
+        for subdir in ["plugins", "themes", "languages"] {
+            if let Ok(entries) = std::fs::read_dir(path.join(subdir)) {
+                for dir in entries.flatten().map(|e| e.path()).filter(|p| p.join("plugin.info").is_file()) {
+                    match wiki_folder::load_plugin_folder(&dir) {
+                        Ok(plugin) => plugins.push(plugin),
+                        Err(e) => eprintln!("[TiddlyDesktop] {}", e),
+                    }
+                }
+            }
+        }
+    }
+    Ok(plugins)
+}
+
+#[cfg(not(target_os = "android"))]
+fn check(app: &tauri::AppHandle, path: &str, is_folder: bool, selected: &[String]) -> Result<CompatibilityReport, String> {
+    let tw_path = crate::get_tiddlywiki_path(app)?;
+    let tw_dir = tw_path.parent().ok_or("Failed to get TiddlyWiki directory")?;
+    // User-installed plugins are found like in the rebuild (TIDDLYWIKI_PLUGIN_PATH)
+    let mut install = TiddlyWikiInstall::new(tw_dir);
+    if let Ok(data_dir) = crate::get_data_dir(app) {
+        install.plugin_paths.insert(0, data_dir.join("plugins"));
+    }
+    let core_version = install.version();
+    let plugins = plugins_after_install(&install, Path::new(path), is_folder, selected)?;
+
+    let mut known_types = HashSet::new();
+    if let Ok(boot) = std::fs::read_to_string(tw_dir.join("boot").join("boot.js")) {
+        collect_string_literals(&boot, &mut known_types);
+    }
+    let core = wiki_folder::load_plugin_folder(&tw_dir.join("core"))?;
+    for plugin in std::iter::once(&core).chain(&plugins) {
+        for tiddler in plugin_tiddlers(plugin) {
+            if tiddler.get("type").map(String::as_str) == Some("application/javascript") {
+                collect_string_literals(tiddler.get("text").map_or("", String::as_str), &mut known_types);
+            }
+        }
+    }
+
+    let issues = check_plugins(&core_version, &plugins, &known_types);
+    Ok(CompatibilityReport { core_version, issues })
+}
+
+#[cfg(target_os = "android")]
+fn check(_app: &tauri::AppHandle, _path: &str, _is_folder: bool, _selected: &[String]) -> Result<CompatibilityReport, String> {
+    Err("The plugin compatibility check is not available on Android".to_string())
+}
+
+/// Check the plugins a wiki will have after installing `plugins` from the
+/// plugin library against the bundled TiddlyWiki
+#[tauri::command]
+pub async fn check_plugin_compatibility(
+    app: tauri::AppHandle,
+    path: String,
+    is_folder: bool,
+    plugins: Vec<String>,
+) -> Result<CompatibilityReport, String> {
+    check(&app, &path, is_folder, &plugins)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plugin(title: &str, core_version: Option<&str>, modules: &[(&str, &str)]) -> Tiddler {
+        let tiddlers: serde_json::Map<String, serde_json::Value> = modules
+            .iter()
+            .map(|(title, module_type)| {
+                (
+                    title.to_string(),
+                    serde_json::json!({ "type": "application/javascript", "module-type": module_type, "text": "" }),
+                )
+            })
+            .collect();
+        let mut fields = Tiddler::new();
+        fields.insert("title".to_string(), title.to_string());
+        fields.insert("plugin-type".to_string(), "plugin".to_string());
+        fields.insert("text".to_string(), serde_json::json!({ "tiddlers": tiddlers }).to_string());
+        if let Some(core_version) = core_version {
+            fields.insert("core-version".to_string(), core_version.to_string());
+        }
+        fields
+    }
+
+    #[test]
+    fn test_satisfies() {
+        assert_eq!(satisfies(">=5.3.0", "5.3.8"), Some(true));
+        assert_eq!(satisfies(">=5.4.0", "5.3.8"), Some(false));
+        assert_eq!(satisfies(">=5.4.0", "5.4.0-prerelease"), Some(false));
+        assert_eq!(satisfies(">=5.2.0 <5.3.0", "5.3.8"), Some(false));
+        assert_eq!(satisfies("<5.3.0 || >=5.3.5", "5.3.8"), Some(true));
+        assert_eq!(satisfies("^5.2.0", "5.3.8"), Some(true));
+        assert_eq!(satisfies("~5.2.0", "5.3.8"), Some(false));
+        assert_eq!(satisfies("5.3.8", "5.3.8"), Some(true));
+        assert_eq!(satisfies(">=5.x", "5.3.8"), None);
+    }
+
+    #[test]
+    fn test_check_plugins() {
+        let mut known_types = HashSet::new();
+        collect_string_literals(r#"$tw.modules.forEachModuleOfType("startup", fn); applyMethods('widget')"#, &mut known_types);
+        let plugins = vec![
+            plugin("$:/plugins/a/ok", Some(">=5.1.0"), &[("$:/plugins/a/ok/w.js", "widget"), ("$:/plugins/a/ok/lib.js", "library")]),
+            plugin("$:/plugins/a/new", Some(">=5.4.0"), &[("$:/plugins/a/new/s.js", "startup")]),
+            plugin("$:/plugins/a/old", None, &[("$:/plugins/a/old/r.js", "wikitextrule")]),
+        ];
+        let issues = check_plugins("5.3.8", &plugins, &known_types);
+        assert_eq!(issues.len(), 2);
+        assert_eq!((issues[0].title.as_str(), &issues[0].kind), ("$:/plugins/a/new", &IssueKind::CoreVersion));
+        assert_eq!(issues[0].core_version.as_deref(), Some(">=5.4.0"));
+        assert_eq!((issues[1].title.as_str(), &issues[1].kind), ("$:/plugins/a/old", &IssueKind::ModuleType));
+        assert_eq!(issues[1].module_type.as_deref(), Some("wikitextrule"));
+    }
+}
@@ -0,0 +1,200 @@
+//! Plugin development mode of folder wikis (desktop)
+//!
+//! For plugin authors: `open_wiki_plugin_dev` opens a folder wiki with a
+//! plugin loaded from its source folder, which can be anywhere. The wiki's
+//! process is started with `TIDDLYDESKTOP_PLUGIN_DEV` set to that folder and
+//! boots the wiki with the plugin (Node.js: `++<folder>`, the built-in server:
+//! packed again on every page load). It watches the folder, and once changes
+//! settle the plugin is packed again: if that works, the Node.js server is
+//! restarted and the window reloaded; if not, the window shows why over the
+//! wiki until the next change.
+//!
+//! The plugin is never written to the wiki, and only the next start of the
+//! wiki's process is affected; opening the wiki again loads it without.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{LazyLock, Mutex};
+
+use serde::Serialize;
+use tauri::Manager;
+#[cfg(not(target_os = "android"))]
+use tauri::Emitter;
+
+/// Set for wiki processes that load a plugin from its source folder
+const PLUGIN_DEV_ENV_VAR: &str = "TIDDLYDESKTOP_PLUGIN_DEV";
+
+/// How long the plugin folder has to stay unchanged before it's packed again
+#[cfg(not(target_os = "android"))]
+const SETTLE_DELAY: std::time::Duration = std::time::Duration::from_millis(400);
+
+/// Wiki path → plugin folder, for the wiki's next process (main process)
+static PENDING: LazyLock<Mutex<HashMap<String, PathBuf>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Why the plugin couldn't be packed the last time (wiki process)
+static ERROR: Mutex<Option<String>> = Mutex::new(None);
+
+/// Plugin development state of a wiki window
+#[derive(Debug, Serialize)]
+pub struct PluginDevStatus {
+    pub dir: String,
+    /// Why the plugin failed to load, if it did
+    pub error: Option<String>,
+}
+
+/// The plugin folder this wiki process loads, if it was started for plugin development
+pub fn plugin_dir() -> Option<PathBuf> {
+    std::env::var_os(PLUGIN_DEV_ENV_VAR).map(PathBuf::from)
+}
+
+/// Pack the plugin in `dir`, returning its title
+fn pack(dir: &Path) -> Result<String, String> {
+    let plugin = tiddlydesktop_core::wiki_folder::load_plugin_folder(dir)?;
+    plugin
+        .get("title")
+        .filter(|title| !title.is_empty())
+        .cloned()
+        .ok_or_else(|| format!("{} has no title", dir.join("plugin.info").display()))
+}
+
+/// The plugin folder if the plugin can be packed now, so a broken one doesn't
+/// stop the server from starting (remembers why it can't be for the window)
+pub fn loadable_dir() -> Option<PathBuf> {
+    let dir = plugin_dir()?;
+    match pack(&dir) {
+        Ok(_) => {
+            *ERROR.lock().unwrap() = None;
+            Some(dir)
+        }
+        Err(e) => {
+            eprintln!("[TiddlyDesktop] Plugin in development failed to load: {}", e);
+            *ERROR.lock().unwrap() = Some(e);
+            None
+        }
+    }
+}
+
+/// Start a wiki process about to be spawned with the plugin folder it was asked for
+pub fn configure_process(cmd: &mut Command, wiki_path: &str) {
+    if let Some(dir) = PENDING.lock().unwrap().remove(wiki_path) {
+        cmd.env(PLUGIN_DEV_ENV_VAR, dir);
+    }
+}
+
+/// Watch the plugin folder of this wiki process. After changes, `restart`
+/// makes the server serve the packed plugin, then the window reloads (or
+/// shows why the plugin couldn't be packed).
+#[cfg(not(target_os = "android"))]
+pub fn start(app: &tauri::AppHandle, restart: impl Fn() -> Result<(), String> + Send + 'static) {
+    use notify::{Config, EventKind, RecursiveMode, Watcher};
+
+    let Some(dir) = plugin_dir() else {
+        return;
+    };
+    let (tx, rx) = std::sync::mpsc::channel::<notify::Result<notify::Event>>();
+    let mut watcher = match notify::RecommendedWatcher::new(tx, Config::default()) {
+        Ok(w) => w,
+        Err(e) => {
+            eprintln!("[TiddlyDesktop] Failed to create plugin folder watcher: {}", e);
+            return;
+        }
+    };
+    if let Err(e) = watcher.watch(&dir, RecursiveMode::Recursive) {
+        eprintln!("[TiddlyDesktop] Failed to watch {}: {}", dir.display(), e);
+        return;
+    }
+    eprintln!("[TiddlyDesktop] Developing the plugin in {}", dir.display());
+
+    let app = app.clone();
+    std::thread::spawn(move || {
+        // Watches as long as the process runs
+        let _watcher = watcher;
+        while let Ok(event) = rx.recv() {
+            if !event.is_ok_and(|e| !matches!(e.kind, EventKind::Access(_))) {
+                continue;
+            }
+            // Editors write several files (and backups) in a row
+            while rx.recv_timeout(SETTLE_DELAY).is_ok() {}
+            let result = pack(&dir).and_then(|title| {
+                restart()?;
+                eprintln!("[TiddlyDesktop] Reloading with the changed plugin {}", title);
+                Ok(())
+            });
+            let error = result.err();
+            *ERROR.lock().unwrap() = error.clone();
+            let _ = app.emit("plugin-dev-changed", serde_json::json!({ "error": error }));
+        }
+    });
+}
+
+/// The plugin folder of this wiki process and whether its plugin loaded
+#[tauri::command]
+pub fn get_plugin_dev_status() -> Option<PluginDevStatus> {
+    plugin_dir().map(|dir| PluginDevStatus {
+        dir: dir.to_string_lossy().to_string(),
+        error: ERROR.lock().unwrap().clone(),
+    })
+}
+
+/// Open a folder wiki of the wiki list with the plugin in `plugin_dir`,
+/// reloading it whenever the plugin changes
+#[tauri::command]
+pub async fn open_wiki_plugin_dev(app: tauri::AppHandle, path: String, plugin_dir: String) -> Result<crate::WikiEntry, String> {
+    let open = app
+        .state::<crate::AppState>()
+        .wiki_processes
+        .lock()
+        .unwrap()
+        .keys()
+        .any(|open| crate::utils::paths_equal(open, &path));
+    if open {
+        return Err("Close the wiki first, then open it for plugin development".to_string());
+    }
+    if crate::browser_mode::is_enabled(&app, &path) {
+        return Err("Wikis served to the browser can't be opened for plugin development".to_string());
+    }
+    let plugin_dir = PathBuf::from(plugin_dir);
+    if !plugin_dir.join("plugin.info").is_file() {
+        return Err(format!("{} is not a plugin folder (missing plugin.info)", plugin_dir.display()));
+    }
+
+    PENDING.lock().unwrap().insert(path.clone(), plugin_dir);
+    #[cfg(not(target_os = "android"))]
+    let result = if Path::new(&path).is_dir() {
+        crate::open_wiki_folder(app.clone(), path.clone(), None).await
+    } else {
+        Err("Only folder wikis can load a plugin from its source folder".to_string())
+    };
+    #[cfg(target_os = "android")]
+    let result = Err("Plugin development is not available on Android".to_string());
+    // Don't leave it for a later, normal start if the process wasn't spawned
+    PENDING.lock().unwrap().remove(&path);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pack() {
+        let dir = std::env::temp_dir().join(format!("td-plugin-dev-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("plugin.info"), r#"{"title": "$:/plugins/me/demo", "description": "Demo"}"#).unwrap();
+        std::fs::write(dir.join("readme.tid"), "title: $:/plugins/me/demo/readme\n\nHello").unwrap();
+        let packed = pack(&dir);
+
+        std::fs::write(dir.join("plugin.info"), r#"{"title": "$:/plugins/me/demo","#).unwrap();
+        let broken = pack(&dir);
+
+        std::fs::write(dir.join("plugin.info"), r#"{"description": "Demo"}"#).unwrap();
+        let untitled = pack(&dir);
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert_eq!(packed, Ok("$:/plugins/me/demo".to_string()));
+        assert!(broken.unwrap_err().contains("plugin.info"));
+        assert!(untitled.unwrap_err().contains("has no title"));
+    }
+}
@@ -0,0 +1,278 @@
+//! Recovery of wiki processes after the main process died
+//!
+//! Wiki processes keep running when the landing page process crashes, but
+//! without the IPC hub they can't be focused, tracked or reached by LAN sync.
+//! To get them back without closing any wiki:
+//! - Each wiki process records itself in `<data_dir>/processes/<pid>.json`
+//! - The main process publishes its IPC auth token in `<data_dir>/ipc_session.json`
+//! - Wiki processes that lose the IPC connection keep retrying with the
+//!   published token until a new main process accepts them
+//! - A new main process adopts recorded wiki processes that are still alive
+//!   and watches them until they exit
+//!
+//! On Windows, wiki processes spawned by the main process belong to a
+//! kill-on-close job object and die with it, so only wikis started on their
+//! own (e.g. from a desktop shortcut) are adopted there.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::ipc;
+
+const PROCESSES_DIR: &str = "processes";
+const SESSION_FILE: &str = "ipc_session.json";
+
+/// How often a disconnected wiki process looks for a new main process
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How often the main process checks whether an adopted wiki process is still running
+const WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// A running wiki process, as recorded by itself
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+struct ProcessEntry {
+    pid: u32,
+    wiki_path: String,
+    is_folder: bool,
+    /// Node.js server port of folder wikis
+    #[serde(default)]
+    port: Option<u16>,
+}
+
+/// The running main process and its IPC auth token
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SessionInfo {
+    pid: u32,
+    auth_token: String,
+}
+
+fn processes_dir(app: &AppHandle) -> Option<PathBuf> {
+    crate::get_data_dir(app).ok().map(|d| d.join(PROCESSES_DIR))
+}
+
+fn entry_path(app: &AppHandle, pid: u32) -> Option<PathBuf> {
+    processes_dir(app).map(|d| d.join(format!("{}.json", pid)))
+}
+
+/// Write a file only the current user can read (it may contain the IPC auth token)
+fn write_private(path: &Path, contents: &str) -> std::io::Result<()> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    std::io::Write::write_all(&mut options.open(path)?, contents.as_bytes())
+}
+
+/// Record this wiki process so a restarted main process can find it
+pub fn record_process(app: &AppHandle, wiki_path: &str, is_folder: bool, port: Option<u16>) {
+    let pid = std::process::id();
+    let (Some(dir), Some(path)) = (processes_dir(app), entry_path(app, pid)) else {
+        return;
+    };
+    let entry = ProcessEntry {
+        pid,
+        wiki_path: wiki_path.to_string(),
+        is_folder,
+        port,
+    };
+    let result = std::fs::create_dir_all(&dir).and_then(|_| {
+        let json = serde_json::to_string(&entry).map_err(std::io::Error::other)?;
+        write_private(&path, &json)
+    });
+    if let Err(e) = result {
+        eprintln!("[TiddlyDesktop] Failed to record wiki process: {}", e);
+    }
+}
+
+/// Remove this wiki process from the registry (on exit)
+pub fn forget_process(app: &AppHandle) {
+    if let Some(path) = entry_path(app, std::process::id()) {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+/// Publish the main process's IPC auth token for wiki processes that outlived
+/// a previous main process
+pub fn write_session(app: &AppHandle) {
+    let Ok(data_dir) = crate::get_data_dir(app) else {
+        return;
+    };
+    let info = SessionInfo {
+        pid: std::process::id(),
+        auth_token: ipc::init_auth_token(),
+    };
+    let result = serde_json::to_string(&info)
+        .map_err(std::io::Error::other)
+        .and_then(|json| write_private(&data_dir.join(SESSION_FILE), &json));
+    if let Err(e) = result {
+        eprintln!("[TiddlyDesktop] Failed to write IPC session file: {}", e);
+    }
+}
+
+/// IPC auth token of the main process using `data_dir`, if it's still running
+pub fn read_session_token(data_dir: &Path) -> Option<String> {
+    let content = std::fs::read_to_string(data_dir.join(SESSION_FILE)).ok()?;
+    let info: SessionInfo = serde_json::from_str(&content).ok()?;
+    (info.pid != std::process::id() && is_process_alive(info.pid)).then_some(info.auth_token)
+}
+
+/// Block until a main process accepts this wiki process again.
+/// Prefers the token published by the running main process and falls back to
+/// the one this process was started with.
+pub fn reconnect(
+    app: &AppHandle,
+    wiki_path: &str,
+    is_tiddler_window: bool,
+    tiddler_title: Option<String>,
+) -> ipc::IpcClient {
+    loop {
+        std::thread::sleep(RECONNECT_INTERVAL);
+        let published = crate::get_data_dir(app).ok().and_then(|d| read_session_token(&d));
+        let Some(token) = published.or_else(ipc::get_auth_token) else {
+            continue;
+        };
+        let mut client = ipc::IpcClient::new(wiki_path.to_string(), is_tiddler_window, tiddler_title.clone(), token);
+        if client.connect().is_ok() {
+            eprintln!("[TiddlyDesktop] Reconnected to IPC server");
+            return client;
+        }
+    }
+}
+
+#[cfg(unix)]
+pub fn is_process_alive(pid: u32) -> bool {
+    let Ok(pid) = i32::try_from(pid) else {
+        return false;
+    };
+    // Signal 0 only checks whether the process exists; EPERM means it does
+    // but belongs to someone else
+    unsafe { libc::kill(pid, 0) == 0 }
+        || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+#[cfg(target_os = "windows")]
+pub fn is_process_alive(pid: u32) -> bool {
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn OpenProcess(dwDesiredAccess: u32, bInheritHandle: i32, dwProcessId: u32) -> *mut std::ffi::c_void;
+        fn GetExitCodeProcess(hProcess: *mut std::ffi::c_void, lpExitCode: *mut u32) -> i32;
+        fn CloseHandle(hObject: *mut std::ffi::c_void) -> i32;
+    }
+    const PROCESS_QUERY_LIMITED_INFORMATION: u32 = 0x1000;
+    const STILL_ACTIVE: u32 = 259;
+
+    unsafe {
+        let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+        if process.is_null() {
+            return false;
+        }
+        let mut exit_code = 0u32;
+        let ok = GetExitCodeProcess(process, &mut exit_code) != 0;
+        CloseHandle(process);
+        ok && exit_code == STILL_ACTIVE
+    }
+}
+
+/// Track a wiki process the main process didn't spawn itself (one that
+/// outlived a previous main process, or was started from a shortcut).
+/// Does nothing if the wiki is already tracked.
+pub fn adopt(app: &AppHandle, pid: u32, wiki_path: &str) {
+    let Some(state) = app.try_state::<crate::AppState>() else {
+        return;
+    };
+    {
+        let mut processes = state.wiki_processes.lock().unwrap();
+        if processes.contains_key(wiki_path) || processes.values().any(|p| p.pid == pid) {
+            return;
+        }
+        processes.insert(wiki_path.to_string(), crate::WikiProcess {
+            pid,
+            path: wiki_path.to_string(),
+        });
+    }
+    crate::session_restore::update(app);
+    eprintln!("[TiddlyDesktop] Adopted wiki process (PID {}): {}", pid, wiki_path);
+    let _ = app.emit("wiki-process-adopted", wiki_path);
+
+    // No child handle to wait on, so poll until the process is gone
+    let app_handle = app.clone();
+    let path = wiki_path.to_string();
+    std::thread::spawn(move || {
+        while is_process_alive(pid) {
+            std::thread::sleep(WATCH_INTERVAL);
+        }
+        eprintln!("[TiddlyDesktop] Adopted wiki process {} exited", pid);
+
+        let state = app_handle.state::<crate::AppState>();
+        {
+            let mut processes = state.wiki_processes.lock().unwrap();
+            // The wiki may have been reopened (as a new process) in the meantime
+            if processes.get(&path).map(|p| p.pid) != Some(pid) {
+                return;
+            }
+            processes.remove(&path);
+        }
+        crate::session_restore::update(&app_handle);
+        crate::recently_closed::record(&app_handle, &path);
+        let _ = app_handle.emit("wiki-process-closed", &path);
+
+        // Exit app if no more wikis and no windows
+        let wiki_count = state.wiki_processes.lock().unwrap().len();
+        if wiki_count == 0 && app_handle.webview_windows().is_empty() && !crate::browser_mode::has_sessions() && !crate::serve_all::is_active() && !crate::memory_limit::restart_pending() {
+            eprintln!("[TiddlyDesktop] No more wikis or windows, exiting");
+            app_handle.exit(0);
+        }
+    });
+}
+
+/// Adopt recorded wiki processes that are still running and drop stale records.
+/// Called once by the main process at startup.
+pub fn adopt_running(app: &AppHandle) {
+    let Some(dir) = processes_dir(app) else {
+        return;
+    };
+    let Ok(entries) = std::fs::read_dir(&dir) else {
+        return;
+    };
+    let state = app.state::<crate::AppState>();
+    for file in entries.flatten().map(|e| e.path()) {
+        let entry = std::fs::read_to_string(&file)
+            .ok()
+            .and_then(|content| serde_json::from_str::<ProcessEntry>(&content).ok());
+        let Some(entry) = entry.filter(|e| is_process_alive(e.pid)) else {
+            let _ = std::fs::remove_file(&file);
+            continue;
+        };
+        // Keep new folder servers off ports the adopted ones still use
+        if let Some(port) = entry.port {
+            let mut next_port = state.next_port.lock().unwrap();
+            if port >= *next_port {
+                *next_port = port + 1;
+            }
+        }
+        adopt(app, entry.pid, &entry.wiki_path);
+        eprintln!("[TiddlyDesktop] Found running {} wiki: {}", if entry.is_folder { "folder" } else { "file" }, entry.wiki_path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_own_process_is_alive() {
+        assert!(is_process_alive(std::process::id()));
+    }
+
+    #[test]
+    fn test_process_entry_without_port() {
+        let entry: ProcessEntry = serde_json::from_str(r#"{"pid":42,"wiki_path":"/w.html","is_folder":false}"#).unwrap();
+        assert_eq!(entry.pid, 42);
+        assert_eq!(entry.port, None);
+    }
+}
@@ -0,0 +1,134 @@
+//! QR code and barcode scanning, and QR code images
+//!
+//! Wikis scan codes with `TiddlyDesktop.scanQrCode()` or the
+//! `tm-tiddlydesktop-rs-scan-qr-code` message:
+//! - Desktop: `init_script/qr_scan.js` shows the webcam (getUserMedia) and
+//!   sends grayscale frames to `decode_qr_frame`
+//! - Android: WikiActivity takes a photo with the camera app (through its
+//!   `TiddlyDesktopQr` interface) and decodes it with `decode_image_file`
+//!   (JNI `decodeQrImage`)
+//!
+//! There is deliberately no `scan_qr_code` command: the camera is opened by
+//! the page (getUserMedia) or the wiki activity, which have the permission
+//! prompts and the preview, so Rust only decodes what they capture.
+//!
+//! Decoding uses rxing, which reads QR codes and the common 1D/2D barcodes.
+//!
+//! `generate_qr_png` draws a QR code as PNG (for pairing, sharing URLs and
+//! deep links), so the landing page and wikis don't need a JS QR library.
+
+use base64::Engine;
+use image::imageops::FilterType;
+
+/// Largest side of a decoded frame; photos are scaled down to it
+const MAX_SIDE: u32 = 1600;
+
+/// Light border around generated codes, in modules (the QR spec minimum)
+const QUIET_ZONE: u32 = 4;
+
+/// Pixels per module of generated codes
+const DEFAULT_MODULE_SIZE: u32 = 8;
+const MAX_MODULE_SIZE: u32 = 32;
+
+fn decode_luma(luma: Vec<u8>, width: u32, height: u32) -> Option<String> {
+    rxing::helpers::detect_in_luma(luma, width, height, None)
+        .ok()
+        .map(|result| result.getText().to_string())
+}
+
+fn check_frame(width: u32, height: u32, len: usize) -> Result<(), String> {
+    if width == 0 || height == 0 || width > MAX_SIDE || height > MAX_SIDE {
+        return Err(format!("Unsupported frame size {}x{}", width, height));
+    }
+    let expected = width as usize * height as usize;
+    if len != expected {
+        return Err(format!("Frame has {} bytes, expected {}", len, expected));
+    }
+    Ok(())
+}
+
+/// Black-on-white image of the QR code for `text`
+fn qr_image(text: &str, module_size: u32) -> Result<image::GrayImage, String> {
+    let code = qrcode::QrCode::new(text.as_bytes()).map_err(|e| format!("Failed to create QR code: {}", e))?;
+    let width = code.width() as u32;
+    let colors = code.to_colors();
+    let side = (width + 2 * QUIET_ZONE) * module_size;
+    Ok(image::GrayImage::from_fn(side, side, |x, y| {
+        let (column, row) = (x / module_size, y / module_size);
+        let modules = QUIET_ZONE..QUIET_ZONE + width;
+        let dark = modules.contains(&column)
+            && modules.contains(&row)
+            && colors[((row - QUIET_ZONE) * width + column - QUIET_ZONE) as usize] == qrcode::Color::Dark;
+        image::Luma([if dark { 0 } else { 255 }])
+    }))
+}
+
+/// Decode a code in a photo (None if there is none)
+#[cfg_attr(not(target_os = "android"), allow(dead_code))]
+pub fn decode_image_file(path: &str) -> Result<Option<String>, String> {
+    let image = image::open(path).map_err(|e| format!("Failed to read photo: {}", e))?;
+    let image = if image.width() > MAX_SIDE || image.height() > MAX_SIDE {
+        image.resize(MAX_SIDE, MAX_SIDE, FilterType::Triangle)
+    } else {
+        image
+    };
+    let luma = image.to_luma8();
+    let (width, height) = luma.dimensions();
+    Ok(decode_luma(luma.into_raw(), width, height))
+}
+
+/// Decode a QR code or barcode in a grayscale camera frame (one byte per
+/// pixel, row by row). None if the frame has no readable code.
+#[tauri::command]
+#[cfg_attr(target_os = "android", allow(dead_code))]
+pub fn decode_qr_frame(width: u32, height: u32, luma_base64: String) -> Result<Option<String>, String> {
+    let luma = base64::engine::general_purpose::STANDARD
+        .decode(luma_base64.as_bytes())
+        .map_err(|e| format!("Invalid frame data: {}", e))?;
+    check_frame(width, height, luma.len())?;
+    Ok(decode_luma(luma, width, height))
+}
+
+/// PNG of the QR code for `text`
+pub fn qr_png(text: &str, module_size: Option<u32>) -> Result<Vec<u8>, String> {
+    let module_size = module_size.unwrap_or(DEFAULT_MODULE_SIZE).clamp(1, MAX_MODULE_SIZE);
+    let image = qr_image(text, module_size)?;
+    let mut png = Vec::new();
+    image
+        .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+        .map_err(|e| format!("Failed to encode QR code: {}", e))?;
+    Ok(png)
+}
+
+/// PNG of the QR code for `text`, as raw bytes (an ArrayBuffer in JS)
+#[tauri::command]
+pub fn generate_qr_png(text: String, module_size: Option<u32>) -> Result<tauri::ipc::Response, String> {
+    qr_png(&text, module_size).map(tauri::ipc::Response::new)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_frame() {
+        assert!(check_frame(640, 480, 640 * 480).is_ok());
+        assert!(check_frame(640, 480, 640 * 480 - 1).is_err());
+        assert!(check_frame(0, 480, 0).is_err());
+        assert!(check_frame(4000, 3000, 4000 * 3000).is_err());
+    }
+
+    #[test]
+    fn test_qr_image_roundtrip() {
+        let text = "tiddlydesktop-rs test";
+        let image = qr_image(text, 4).unwrap();
+        assert_eq!(image.width(), image.height());
+        let (width, height) = image.dimensions();
+        assert_eq!(decode_luma(image.into_raw(), width, height).as_deref(), Some(text));
+    }
+
+    #[test]
+    fn test_blank_frame_has_no_code() {
+        assert_eq!(decode_luma(vec![255; 64 * 64], 64, 64), None);
+    }
+}
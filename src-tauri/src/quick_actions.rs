@@ -0,0 +1,348 @@
+//! Tray quick actions
+//!
+//! Actions in the tray menu, each optionally bound to a global hotkey, that
+//! add something to a wiki without going through its window first:
+//! - new tiddler: a new tiddler in the editor
+//! - new journal: today's journal tiddler in the editor
+//! - paste clipboard: the clipboard saved as a new tiddler
+//!
+//! Like the Android Quick Capture widget, an action writes a pending capture
+//! to `captures/` in the data dir and then opens or focuses the wiki. Wiki
+//! windows take their captures at startup and when the main process sends
+//! `ImportCaptures` (`init_script/quick_capture.js`). The actions themselves
+//! are stored in `quick_actions.json`. Their hotkeys are registered together
+//! with the hotkeys that open wikis (`wiki_hotkeys`).
+//!
+//! Deep links (`deep_link`) queue an `open-tiddler` capture the same way.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+/// Captures no wiki picked up within this time are dropped
+const CAPTURE_MAX_AGE_MS: u64 = 7 * 24 * 60 * 60 * 1000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum QuickActionKind {
+    NewTiddler,
+    NewJournal,
+    PasteClipboard,
+    /// Navigate to the tiddler named by the capture's text (deep links only,
+    /// not a tray action)
+    OpenTiddler,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuickAction {
+    kind: QuickActionKind,
+    wiki_path: String,
+    /// Global hotkey, e.g. "CommandOrControl+Shift+J"
+    #[serde(default)]
+    hotkey: Option<String>,
+}
+
+/// Pending capture for a wiki
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Capture {
+    wiki_path: String,
+    kind: QuickActionKind,
+    /// Tiddler type of pasted content ("" for wikitext)
+    #[serde(default)]
+    content_type: Option<String>,
+    #[serde(default)]
+    text: Option<String>,
+    /// Milliseconds since the Unix epoch
+    created: u64,
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn config_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(crate::get_data_dir(app)?.join("quick_actions.json"))
+}
+
+fn captures_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(crate::get_data_dir(app)?.join("captures"))
+}
+
+fn load_actions(app: &tauri::AppHandle) -> Vec<QuickAction> {
+    config_path(app)
+        .ok()
+        .and_then(|p| std::fs::read_to_string(p).ok())
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+/// Tiddler type and text for clipboard content: text, or else an image
+fn clipboard_tiddler(data: &HashMap<String, String>) -> Option<(String, String)> {
+    let text = ["text/plain", "text/uri-list", "text/html"].iter().find_map(|mime| {
+        let text = data.get(*mime).filter(|t| !t.trim().is_empty())?;
+        let content_type = if *mime == "text/html" { "text/html" } else { "" };
+        Some((content_type.to_string(), text.clone()))
+    });
+    text.or_else(|| {
+        data.iter().find_map(|(mime, content)| {
+            let (_, base64) = content.split_once(";base64,").filter(|_| mime.starts_with("image/"))?;
+            Some((mime.clone(), base64.to_string()))
+        })
+    })
+}
+
+/// Tray label of an action
+fn action_label(kind: QuickActionKind, wiki_name: &str) -> String {
+    match kind {
+        QuickActionKind::NewTiddler => format!("New Tiddler in {}", wiki_name),
+        QuickActionKind::NewJournal => format!("New Journal in {}", wiki_name),
+        QuickActionKind::PasteClipboard => format!("Paste Clipboard to {}", wiki_name),
+        QuickActionKind::OpenTiddler => format!("Open {}", wiki_name),
+    }
+}
+
+fn write_capture(app: &tauri::AppHandle, capture: &Capture) -> Result<(), String> {
+    let dir = captures_dir(app)?;
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create captures folder: {}", e))?;
+    let name = format!("capture_{}_{:08x}.json", capture.created, rand::random::<u32>());
+    let json = serde_json::to_string(capture).map_err(|e| e.to_string())?;
+    std::fs::write(dir.join(name), json).map_err(|e| format!("Failed to save capture: {}", e))
+}
+
+/// Queue navigating to `title` for a wiki and tell it if it's open (deep links)
+#[cfg(not(target_os = "android"))]
+pub fn queue_open_tiddler(app: &tauri::AppHandle, wiki_path: &str, title: String) -> Result<(), String> {
+    write_capture(
+        app,
+        &Capture {
+            wiki_path: wiki_path.to_string(),
+            kind: QuickActionKind::OpenTiddler,
+            content_type: None,
+            text: Some(title),
+            created: now_ms(),
+        },
+    )?;
+    if let Some(server) = crate::GLOBAL_IPC_SERVER.get() {
+        let _ = server.send_import_captures(wiki_path);
+    }
+    Ok(())
+}
+
+/// Tray menu items: (menu id, label)
+#[cfg(not(target_os = "android"))]
+pub fn tray_items(app: &tauri::AppHandle) -> Vec<(String, String)> {
+    let entries = crate::wiki_storage::load_recent_files_from_disk(app);
+    load_actions(app)
+        .iter()
+        .enumerate()
+        .map(|(index, action)| {
+            let name = entries
+                .iter()
+                .find(|e| crate::utils::paths_equal(&e.path, &action.wiki_path))
+                .map(|e| e.filename.clone())
+                .unwrap_or_else(|| action.wiki_path.clone());
+            (format!("quick_action:{}", index), action_label(action.kind, &name).replace('&', "&&"))
+        })
+        .collect()
+}
+
+/// Run an action: queue its capture, then open or focus the wiki
+#[cfg(not(target_os = "android"))]
+fn run_action(app: &tauri::AppHandle, action: QuickAction) {
+    let app = app.clone();
+    // The clipboard is read on the main thread (GTK)
+    let handle = app.clone();
+    let _ = handle.run_on_main_thread(move || {
+        let mut capture = Capture {
+            wiki_path: action.wiki_path.clone(),
+            kind: action.kind,
+            content_type: None,
+            text: None,
+            created: now_ms(),
+        };
+        if action.kind == QuickActionKind::PasteClipboard {
+            let content = crate::clipboard::get_clipboard_content()
+                .ok()
+                .and_then(|content| clipboard_tiddler(&content.data));
+            let Some((content_type, text)) = content else {
+                eprintln!("[TiddlyDesktop] Quick action: the clipboard is empty");
+                return;
+            };
+            capture.content_type = Some(content_type);
+            capture.text = Some(text);
+        }
+        if let Err(e) = write_capture(&app, &capture) {
+            eprintln!("[TiddlyDesktop] Quick action failed: {}", e);
+            return;
+        }
+        // An open wiki takes the capture right away; a closed one when it starts
+        if let Some(server) = crate::GLOBAL_IPC_SERVER.get() {
+            let _ = server.send_import_captures(&action.wiki_path);
+        }
+        crate::open_wiki_from_tray(&app, action.wiki_path);
+    });
+}
+
+/// Handle a click on a quick action in the tray menu
+#[cfg(not(target_os = "android"))]
+pub fn handle_tray_event(app: &tauri::AppHandle, id: &str) {
+    let index = id.strip_prefix("quick_action:").and_then(|i| i.parse::<usize>().ok());
+    if let Some(action) = index.and_then(|i| load_actions(app).into_iter().nth(i)) {
+        run_action(app, action);
+    }
+}
+
+/// The hotkeys of the actions
+pub fn hotkeys(app: &tauri::AppHandle) -> Vec<String> {
+    load_actions(app).into_iter().filter_map(|a| a.hotkey).collect()
+}
+
+/// Register the hotkeys of all actions and wikis (replacing the previous ones)
+#[cfg(not(target_os = "android"))]
+pub fn register_hotkeys(app: &tauri::AppHandle) {
+    use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut};
+
+    let shortcuts = app.global_shortcut();
+    let _ = shortcuts.unregister_all();
+    let wiki_hotkeys = crate::wiki_hotkeys::hotkeys(app).into_iter().map(|(hotkey, _)| hotkey);
+    for hotkey in hotkeys(app).into_iter().chain(wiki_hotkeys) {
+        let registered = hotkey
+            .parse::<Shortcut>()
+            .map_err(|e| e.to_string())
+            .and_then(|shortcut| shortcuts.register(shortcut).map_err(|e| e.to_string()));
+        if let Err(e) = registered {
+            eprintln!("[TiddlyDesktop] Failed to register hotkey {}: {}", hotkey, e);
+        }
+    }
+}
+
+/// Run the action bound to a pressed global hotkey
+#[cfg(not(target_os = "android"))]
+pub fn handle_shortcut(app: &tauri::AppHandle, shortcut: &tauri_plugin_global_shortcut::Shortcut) {
+    let action = load_actions(app).into_iter().find(|action| {
+        action
+            .hotkey
+            .as_deref()
+            .and_then(|h| h.parse::<tauri_plugin_global_shortcut::Shortcut>().ok())
+            .is_some_and(|s| s == *shortcut)
+    });
+    if let Some(action) = action {
+        run_action(app, action);
+    }
+}
+
+#[tauri::command]
+pub fn get_quick_actions(app: tauri::AppHandle) -> Vec<QuickAction> {
+    load_actions(&app)
+}
+
+/// Replace the quick actions, then update the hotkeys and the tray menu
+#[tauri::command]
+pub fn set_quick_actions(app: tauri::AppHandle, actions: Vec<QuickAction>) -> Result<(), String> {
+    let mut actions = actions;
+    for action in &mut actions {
+        if action.kind == QuickActionKind::OpenTiddler {
+            return Err("Opening a tiddler is not available as a quick action".to_string());
+        }
+        action.hotkey = action.hotkey.take().map(|h| h.trim().to_string()).filter(|h| !h.is_empty());
+        #[cfg(not(target_os = "android"))]
+        if let Some(hotkey) = &action.hotkey {
+            let shortcut = hotkey
+                .parse::<tauri_plugin_global_shortcut::Shortcut>()
+                .map_err(|e| format!("Invalid hotkey \"{}\": {}", hotkey, e))?;
+            if let Some((_, path)) = crate::wiki_hotkeys::hotkeys(&app)
+                .into_iter()
+                .find(|(h, _)| h.parse::<tauri_plugin_global_shortcut::Shortcut>().is_ok_and(|s| s == shortcut))
+            {
+                return Err(format!("Can't use {}: it already opens {}", hotkey, path));
+            }
+        }
+    }
+    let json = serde_json::to_string_pretty(&actions).map_err(|e| e.to_string())?;
+    std::fs::write(config_path(&app)?, json).map_err(|e| format!("Failed to save quick actions: {}", e))?;
+    #[cfg(not(target_os = "android"))]
+    {
+        register_hotkeys(&app);
+        crate::refresh_tray_menu(&app);
+    }
+    Ok(())
+}
+
+/// Take the pending captures for a wiki (called by its window), oldest first
+#[tauri::command]
+pub fn take_quick_captures(app: tauri::AppHandle, wiki_path: String) -> Result<Vec<Capture>, String> {
+    let Ok(entries) = std::fs::read_dir(captures_dir(&app)?) else {
+        return Ok(Vec::new());
+    };
+    let now = now_ms();
+    let mut captures = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let is_capture = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(|n| n.starts_with("capture_") && n.ends_with(".json"));
+        if !is_capture {
+            continue;
+        }
+        // Android captures use another format and live elsewhere, so anything
+        // unreadable here is left alone
+        let Some(capture) = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|s| serde_json::from_str::<Capture>(&s).ok())
+        else {
+            continue;
+        };
+        if now.saturating_sub(capture.created) > CAPTURE_MAX_AGE_MS {
+            let _ = std::fs::remove_file(&path);
+        } else if crate::utils::paths_equal(&capture.wiki_path, &wiki_path) {
+            // Removed first so a second window of the wiki can't take it too
+            if std::fs::remove_file(&path).is_ok() {
+                captures.push(capture);
+            }
+        }
+    }
+    captures.sort_by_key(|c| c.created);
+    Ok(captures)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_clipboard_tiddler() {
+        assert_eq!(
+            clipboard_tiddler(&data(&[("text/plain", "hello"), ("text/html", "<b>hello</b>")])),
+            Some((String::new(), "hello".to_string()))
+        );
+        assert_eq!(
+            clipboard_tiddler(&data(&[("image/png", "data:image/png;base64,iVBOR")])),
+            Some(("image/png".to_string(), "iVBOR".to_string()))
+        );
+        assert_eq!(
+            clipboard_tiddler(&data(&[("text/html", "<p>x</p>")])),
+            Some(("text/html".to_string(), "<p>x</p>".to_string()))
+        );
+        assert_eq!(clipboard_tiddler(&data(&[("text/plain", "  ")])), None);
+    }
+
+    #[test]
+    fn test_action_config_format() {
+        let action: QuickAction =
+            serde_json::from_str(r#"{"kind":"new-journal","wikiPath":"/w.html","hotkey":"Alt+J"}"#).unwrap();
+        assert_eq!(action.kind, QuickActionKind::NewJournal);
+        assert_eq!(action_label(action.kind, "w.html"), "New Journal in w.html");
+    }
+}
@@ -0,0 +1,200 @@
+//! Quick switcher: a small window for jumping to any wiki (desktop)
+//!
+//! The `quick-switcher` accelerator (Ctrl/Cmd+K) shows it from the landing
+//! page and from every wiki window; wiki windows ask the main process over IPC
+//! (`ShowQuickSwitcher`), which owns the window. It lists the open wikis and
+//! the wiki list, fuzzy-filtered as you type (`quick_switcher_search`), and
+//! choosing one focuses its window or starts its process.
+
+use serde::Serialize;
+use tauri::Manager;
+
+use crate::utils;
+
+/// Label of the quick switcher window
+pub const QUICK_SWITCHER_LABEL: &str = "quick-switcher";
+
+/// Results shown at most
+const MAX_RESULTS: usize = 50;
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SwitcherItem {
+    path: String,
+    name: String,
+    is_folder: bool,
+    is_open: bool,
+    emoji: Option<String>,
+}
+
+/// Score of `query` as a fuzzy match in `text` (its characters in order,
+/// ignoring case), or None when it doesn't match. Consecutive characters and
+/// characters at the start of words score higher; gaps cost a little.
+fn fuzzy_score(query: &str, text: &str) -> Option<i32> {
+    let query: Vec<char> = query.chars().filter(|c| !c.is_whitespace()).flat_map(char::to_lowercase).collect();
+    if query.is_empty() {
+        return Some(0);
+    }
+    let text: Vec<char> = text.chars().flat_map(char::to_lowercase).collect();
+    let mut score = 0;
+    let mut next = 0;
+    let mut last_match: Option<usize> = None;
+    for (i, c) in text.iter().enumerate() {
+        if next == query.len() {
+            break;
+        }
+        if *c != query[next] {
+            continue;
+        }
+        score += 1;
+        if i == 0 || !text[i - 1].is_alphanumeric() {
+            score += 8;
+        }
+        match last_match {
+            Some(last) if last + 1 == i => score += 5,
+            Some(last) => score -= ((i - last - 1) as i32).min(3),
+            None => {}
+        }
+        last_match = Some(i);
+        next += 1;
+    }
+    (next == query.len()).then_some(score)
+}
+
+/// Open wikis and the wiki list, open ones first
+fn all_items(app: &tauri::AppHandle) -> Vec<SwitcherItem> {
+    #[cfg(not(target_os = "android"))]
+    let open: Vec<String> = app.state::<crate::AppState>().wiki_processes.lock().unwrap().keys().cloned().collect();
+    #[cfg(target_os = "android")]
+    let open: Vec<String> = Vec::new();
+
+    let entries = crate::wiki_storage::load_recent_files_from_disk(app);
+    let mut items: Vec<SwitcherItem> = entries
+        .iter()
+        .map(|entry| SwitcherItem {
+            path: entry.path.clone(),
+            name: entry.filename.clone(),
+            is_folder: entry.is_folder,
+            is_open: open.iter().any(|path| utils::paths_equal(path, &entry.path)),
+            emoji: entry.emoji.clone(),
+        })
+        .collect();
+    // Open wikis that aren't in the list (e.g. started from a desktop shortcut)
+    for path in open.iter().filter(|path| !entries.iter().any(|e| utils::paths_equal(&e.path, path))) {
+        let name = std::path::Path::new(path)
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.clone());
+        items.push(SwitcherItem {
+            path: path.clone(),
+            name,
+            is_folder: std::path::Path::new(path).is_dir(),
+            is_open: true,
+            emoji: None,
+        });
+    }
+    // Stable, so the wiki list order is kept within each group
+    items.sort_by_key(|item| !item.is_open);
+    items
+}
+
+/// Show the quick switcher (or bring it back to the front)
+#[cfg(not(target_os = "android"))]
+pub fn show(app: &tauri::AppHandle) {
+    use tauri::Emitter;
+
+    if let Some(window) = app.get_webview_window(QUICK_SWITCHER_LABEL) {
+        let _ = window.emit_to(QUICK_SWITCHER_LABEL, "quick-switcher-shown", ());
+        let _ = window.show();
+        let _ = window.set_focus();
+        return;
+    }
+    let built = tauri::WebviewWindowBuilder::new(app, QUICK_SWITCHER_LABEL, tauri::WebviewUrl::App("quick-switcher.html".into()))
+        .title("Quick Switcher")
+        .inner_size(520.0, 380.0)
+        .resizable(false)
+        .decorations(false)
+        .skip_taskbar(true)
+        .center()
+        .always_on_top(true)
+        .focused(true)
+        .build();
+    if let Err(e) = built {
+        eprintln!("[TiddlyDesktop] Failed to open quick switcher: {}", e);
+    }
+}
+
+/// Show the quick switcher (landing page)
+#[tauri::command]
+pub fn show_quick_switcher(app: tauri::AppHandle) {
+    #[cfg(not(target_os = "android"))]
+    show(&app);
+    #[cfg(target_os = "android")]
+    let _ = app;
+}
+
+/// Have the main process show the quick switcher (wiki window)
+#[cfg(not(target_os = "android"))]
+#[tauri::command]
+pub fn ipc_show_quick_switcher(state: tauri::State<crate::WikiModeState>) -> Result<(), String> {
+    let mut client_guard = state.ipc_client.lock().unwrap();
+    let client = client_guard.as_mut().ok_or("Not connected to the main process")?;
+    client
+        .request_quick_switcher()
+        .map_err(|e| format!("IPC error: {}", e))
+}
+
+/// Wikis matching `query`, best matches first
+#[tauri::command]
+pub fn quick_switcher_search(app: tauri::AppHandle, query: String) -> Vec<SwitcherItem> {
+    let mut scored: Vec<(i32, SwitcherItem)> = all_items(&app)
+        .into_iter()
+        .filter_map(|item| {
+            // The name counts more than a match somewhere in the path
+            let score = fuzzy_score(&query, &item.name)
+                .map(|s| s * 2)
+                .or_else(|| fuzzy_score(&query, &item.path))?;
+            Some((score, item))
+        })
+        .collect();
+    // Stable, so equally good matches stay open-first in wiki list order
+    scored.sort_by(|a, b| b.0.cmp(&a.0));
+    scored.into_iter().take(MAX_RESULTS).map(|(_, item)| item).collect()
+}
+
+/// Focus or open the chosen wiki and hide the quick switcher
+#[tauri::command]
+pub fn quick_switcher_open(app: tauri::AppHandle, path: Option<String>) {
+    if let Some(window) = app.get_webview_window(QUICK_SWITCHER_LABEL) {
+        let _ = window.hide();
+    }
+    #[cfg(not(target_os = "android"))]
+    if let Some(path) = path {
+        crate::open_wiki_from_tray(&app, path);
+    }
+    #[cfg(target_os = "android")]
+    let _ = path;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuzzy_score_matches() {
+        assert_eq!(fuzzy_score("", "Anything"), Some(0));
+        assert!(fuzzy_score("rcp", "Recipes.html").is_some());
+        assert!(fuzzy_score("RECIPES", "recipes.html").is_some());
+        assert!(fuzzy_score("my notes", "MyNotes.html").is_some());
+        assert_eq!(fuzzy_score("xyz", "Recipes.html"), None);
+        assert_eq!(fuzzy_score("sr", "Recipes.html"), None);
+    }
+
+    #[test]
+    fn test_fuzzy_score_ranking() {
+        // Consecutive characters beat scattered ones
+        assert!(fuzzy_score("note", "notes.html") > fuzzy_score("note", "nxoxtxe.html"));
+        // Word starts beat matches inside words
+        assert!(fuzzy_score("pw", "project-wiki.html") > fuzzy_score("pw", "upward.html"));
+    }
+}
@@ -0,0 +1,97 @@
+//! Recently closed wikis
+//!
+//! The main process remembers the last wikis whose processes exited, with the
+//! window state they had when closing. `reopen_last_closed_wiki` reopens them
+//! newest first, in their old window position. It is bound to Ctrl/Cmd+Shift+T
+//! (the `reopen-closed-wiki` accelerator) in every wiki window and the landing
+//! page, and is also in the tray menu.
+
+use std::sync::Mutex;
+
+use tauri::{Emitter, Manager};
+
+use crate::types::{WikiEntry, WindowState};
+use crate::utils;
+
+/// Number of closed wikis remembered
+const MAX_CLOSED: usize = 20;
+
+/// A wiki whose window was closed
+struct ClosedWiki {
+    path: String,
+    is_folder: bool,
+    window_state: Option<WindowState>,
+}
+
+/// Closed wikis, most recently closed last
+static CLOSED: Mutex<Vec<ClosedWiki>> = Mutex::new(Vec::new());
+
+/// Remember a wiki whose process just exited
+pub fn record(app: &tauri::AppHandle, path: &str) {
+    // Wikis restarted over their memory limit are reopened right away
+    if crate::memory_limit::restart_pending() {
+        return;
+    }
+    let wiki = ClosedWiki {
+        path: path.to_string(),
+        is_folder: std::path::Path::new(path).is_dir(),
+        // Saved by the wiki process before it exited
+        window_state: crate::wiki_storage::get_window_state(app, path),
+    };
+    let mut closed = CLOSED.lock().unwrap();
+    closed.retain(|c| !utils::paths_equal(&c.path, path));
+    closed.push(wiki);
+    let excess = closed.len().saturating_sub(MAX_CLOSED);
+    closed.drain(..excess);
+}
+
+/// Reopen the most recently closed wiki that isn't open again.
+/// Returns None when there is nothing to reopen.
+#[tauri::command]
+pub async fn reopen_last_closed_wiki(app: tauri::AppHandle) -> Result<Option<WikiEntry>, String> {
+    let next = {
+        let state = app.state::<crate::AppState>();
+        let open = state.wiki_processes.lock().unwrap();
+        let mut closed = CLOSED.lock().unwrap();
+        // Wikis reopened some other way in the meantime are dropped
+        closed.retain(|c| !open.contains_key(&c.path));
+        closed.pop()
+    };
+    let Some(wiki) = next else {
+        return Ok(None);
+    };
+
+    // Put the window back where it was when the wiki was closed
+    if let Some(ws) = wiki.window_state {
+        let _ = crate::wiki_storage::save_window_state(
+            app.clone(),
+            wiki.path.clone(),
+            ws.width,
+            ws.height,
+            ws.x,
+            ws.y,
+            ws.monitor_name,
+            Some(ws.monitor_x),
+            Some(ws.monitor_y),
+            ws.maximized,
+        );
+    }
+
+    let entry = if wiki.is_folder {
+        crate::open_wiki_folder(app.clone(), wiki.path, None).await?
+    } else {
+        crate::open_wiki_window(app.clone(), wiki.path, None, None, None).await?
+    };
+    let _ = app.emit("wiki-list-changed", &entry);
+    Ok(Some(entry))
+}
+
+/// Reopen the most recently closed wiki in the background (tray menu, IPC)
+pub fn reopen_in_background(app: &tauri::AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = reopen_last_closed_wiki(app).await {
+            eprintln!("[TiddlyDesktop] Failed to reopen closed wiki: {}", e);
+        }
+    });
+}
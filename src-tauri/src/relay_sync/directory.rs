@@ -0,0 +1,282 @@
+//! Room directory — the devices seen in each room, and the revoked ones.
+//!
+//! The relay server only knows accounts, not devices, and a room's members
+//! are otherwise only visible while connected. This records every device that
+//! joined one of our rooms (over the relay or on the LAN) with when it was
+//! first and last seen and when it last synced, in `relay_room_directory.json`
+//! next to the relay config.
+//!
+//! Revoking a device makes this device ignore it in that room: its relay
+//! session inits are dropped and LAN connections to or from it for that room
+//! are refused. Anyone who knows the room password can still read the room's
+//! broadcasts, so to lock a device out for good the password has to change
+//! too (`relay_sync_revoke_device` can rotate it).
+//!
+//! Times are epoch milliseconds, like the transfer counters.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+use crate::lan_sync::protocol::SyncMessage;
+
+const DIRECTORY_FILE: &str = "relay_room_directory.json";
+
+/// Last-seen and last-sync times are written at most this often; new and
+/// revoked devices are written right away
+const SAVE_INTERVAL_MS: u64 = 60_000;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct KnownDevice {
+    pub name: String,
+    pub first_seen: u64,
+    pub last_seen: u64,
+    #[serde(default)]
+    pub last_sync: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RoomRecord {
+    #[serde(default)]
+    pub devices: BTreeMap<String, KnownDevice>,
+    #[serde(default)]
+    pub revoked: BTreeSet<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Directory {
+    #[serde(default)]
+    rooms: BTreeMap<String, RoomRecord>,
+}
+
+/// Whether a name is the placeholder used before a device's real name is
+/// known (the start of its device ID)
+fn is_placeholder_name(device_id: &str, name: &str) -> bool {
+    name.is_empty() || device_id.starts_with(name)
+}
+
+impl Directory {
+    /// A device was seen in a room. Returns true for a device not known before.
+    fn seen(&mut self, room_code: &str, device_id: &str, name: &str, now: u64) -> bool {
+        let room = self.rooms.entry(room_code.to_string()).or_default();
+        match room.devices.get_mut(device_id) {
+            Some(device) => {
+                device.last_seen = device.last_seen.max(now);
+                if !is_placeholder_name(device_id, name) {
+                    device.name = name.to_string();
+                }
+                false
+            }
+            None => {
+                let name = if name.is_empty() {
+                    device_id[..8.min(device_id.len())].to_string()
+                } else {
+                    name.to_string()
+                };
+                room.devices.insert(
+                    device_id.to_string(),
+                    KnownDevice { name, first_seen: now, last_seen: now, last_sync: None },
+                );
+                true
+            }
+        }
+    }
+
+    /// A device synced in a room at `at`
+    fn synced(&mut self, room_code: &str, device_id: &str, at: u64) {
+        if let Some(device) = self.rooms.get_mut(room_code).and_then(|r| r.devices.get_mut(device_id)) {
+            device.last_seen = device.last_seen.max(at);
+            device.last_sync = Some(device.last_sync.map_or(at, |last| last.max(at)));
+        }
+    }
+
+    fn is_revoked(&self, room_code: &str, device_id: &str) -> bool {
+        self.rooms.get(room_code).is_some_and(|r| r.revoked.contains(device_id))
+    }
+
+    /// Returns false if nothing changed
+    fn set_revoked(&mut self, room_code: &str, device_id: &str, revoked: bool) -> bool {
+        let room = self.rooms.entry(room_code.to_string()).or_default();
+        if revoked {
+            room.revoked.insert(device_id.to_string())
+        } else {
+            room.revoked.remove(device_id)
+        }
+    }
+}
+
+struct State {
+    path: Option<PathBuf>,
+    directory: Directory,
+    dirty: bool,
+    saved_at: u64,
+}
+
+static STATE: LazyLock<Mutex<State>> = LazyLock::new(|| {
+    Mutex::new(State { path: None, directory: Directory::default(), dirty: false, saved_at: 0 })
+});
+
+fn now_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+impl State {
+    fn save(&mut self, now: u64) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        match serde_json::to_string_pretty(&self.directory) {
+            Ok(json) => {
+                let tmp = path.with_extension("json.tmp");
+                if let Err(e) = std::fs::write(&tmp, json).and_then(|_| std::fs::rename(&tmp, path)) {
+                    eprintln!("[Relay] Failed to save room directory: {}", e);
+                    return;
+                }
+                self.dirty = false;
+                self.saved_at = now;
+            }
+            Err(e) => eprintln!("[Relay] Failed to serialize room directory: {}", e),
+        }
+    }
+
+    /// Save now, or once the save interval has passed
+    fn changed(&mut self, now: u64, urgent: bool) {
+        self.dirty = true;
+        if urgent || now.saturating_sub(self.saved_at) >= SAVE_INTERVAL_MS {
+            self.save(now);
+        }
+    }
+}
+
+/// Load the directory from the data directory (when the relay manager starts)
+pub fn load(data_dir: &Path) {
+    let path = data_dir.join(DIRECTORY_FILE);
+    let directory = match std::fs::read_to_string(&path) {
+        Ok(s) => serde_json::from_str(&s).unwrap_or_else(|e| {
+            eprintln!("[Relay] Ignoring unreadable room directory {}: {}", path.display(), e);
+            Directory::default()
+        }),
+        Err(_) => Directory::default(),
+    };
+    let mut state = STATE.lock().unwrap();
+    state.path = Some(path);
+    state.directory = directory;
+    state.dirty = false;
+}
+
+/// Write pending last-seen times (on shutdown)
+pub fn flush() {
+    let mut state = STATE.lock().unwrap();
+    if state.dirty {
+        state.save(now_ms());
+    }
+}
+
+/// A device joined a room or reconnected. `name` may be a placeholder; a
+/// real name replaces it once known.
+pub fn device_seen(room_code: &str, device_id: &str, name: &str) {
+    let now = now_ms();
+    let mut state = STATE.lock().unwrap();
+    let is_new = state.directory.seen(room_code, device_id, name, now);
+    state.changed(now, is_new);
+}
+
+/// A message arrived from a device in a room (keepalives only count as seen)
+pub fn message_received(room_code: &str, device_id: &str, msg: &SyncMessage) {
+    let now = now_ms();
+    let mut state = STATE.lock().unwrap();
+    if !matches!(msg, SyncMessage::Ping | SyncMessage::Pong) {
+        state.directory.synced(room_code, device_id, now);
+    } else if let Some(device) = state.directory.rooms.get_mut(room_code).and_then(|r| r.devices.get_mut(device_id)) {
+        device.last_seen = now;
+    }
+    state.changed(now, false);
+}
+
+/// A device synced in a room at `at` (from the transfer counters of LAN peers)
+pub fn device_synced(room_code: &str, device_id: &str, at: u64) {
+    let now = now_ms();
+    let mut state = STATE.lock().unwrap();
+    state.directory.synced(room_code, device_id, at);
+    state.changed(now, false);
+}
+
+/// Whether a device was revoked in a room
+pub fn is_revoked(room_code: &str, device_id: &str) -> bool {
+    STATE.lock().unwrap().directory.is_revoked(room_code, device_id)
+}
+
+/// Revoke a device in a room, or take a revocation back
+pub fn set_revoked(room_code: &str, device_id: &str, revoked: bool) {
+    let now = now_ms();
+    let mut state = STATE.lock().unwrap();
+    if state.directory.set_revoked(room_code, device_id, revoked) {
+        state.changed(now, true);
+    }
+}
+
+/// The known and revoked devices of a room
+pub fn room(room_code: &str) -> RoomRecord {
+    STATE.lock().unwrap().directory.rooms.get(room_code).cloned().unwrap_or_default()
+}
+
+/// Drop a room's records (the room was removed)
+pub fn forget_room(room_code: &str) {
+    let now = now_ms();
+    let mut state = STATE.lock().unwrap();
+    if state.directory.rooms.remove(room_code).is_some() {
+        state.changed(now, true);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEVICE: &str = "3f2a9c1e-5b7d-4e21-9a0c-7d1e2f3a4b5c";
+
+    #[test]
+    fn test_seen_keeps_real_names() {
+        let mut dir = Directory::default();
+        assert!(dir.seen("ROOM1234", DEVICE, "3f2a9c1e", 1_000));
+        assert_eq!(dir.rooms["ROOM1234"].devices[DEVICE].name, "3f2a9c1e");
+
+        assert!(!dir.seen("ROOM1234", DEVICE, "Laptop", 2_000));
+        // The placeholder doesn't overwrite a real name
+        assert!(!dir.seen("ROOM1234", DEVICE, "3f2a9c1e", 3_000));
+        let device = &dir.rooms["ROOM1234"].devices[DEVICE];
+        assert_eq!(device.name, "Laptop");
+        assert_eq!((device.first_seen, device.last_seen), (1_000, 3_000));
+    }
+
+    #[test]
+    fn test_synced() {
+        let mut dir = Directory::default();
+        // Unknown devices aren't added by a sync
+        dir.synced("ROOM1234", DEVICE, 1_000);
+        assert!(dir.rooms.is_empty());
+
+        dir.seen("ROOM1234", DEVICE, "Laptop", 1_000);
+        dir.synced("ROOM1234", DEVICE, 5_000);
+        // An older sync time (from the transfer counters) doesn't go back
+        dir.synced("ROOM1234", DEVICE, 4_000);
+        let device = &dir.rooms["ROOM1234"].devices[DEVICE];
+        assert_eq!((device.last_seen, device.last_sync), (5_000, Some(5_000)));
+    }
+
+    #[test]
+    fn test_revoked_per_room() {
+        let mut dir = Directory::default();
+        assert!(dir.set_revoked("ROOM1234", DEVICE, true));
+        assert!(!dir.set_revoked("ROOM1234", DEVICE, true));
+        assert!(dir.is_revoked("ROOM1234", DEVICE));
+        assert!(!dir.is_revoked("OTHER567", DEVICE));
+        assert!(dir.set_revoked("ROOM1234", DEVICE, false));
+        assert!(!dir.is_revoked("ROOM1234", DEVICE));
+    }
+}
@@ -11,6 +11,7 @@
 //! - `ServerEvent`s are emitted into the same channel as LAN sync
 
 pub mod connection;
+pub mod directory;
 pub mod github_auth;
 
 use crate::lan_sync::pairing::PairingManager;
@@ -284,6 +285,15 @@ fn decrypt_password(device_key: &[u8; 32], encrypted: &str) -> Option<String> {
     String::from_utf8(plaintext).ok()
 }
 
+/// Whether relay sync has been set up (signed in or rooms defined), without
+/// starting the manager
+pub fn is_configured(data_dir: &std::path::Path) -> bool {
+    std::fs::read_to_string(data_dir.join(RELAY_CONFIG_FILE))
+        .ok()
+        .and_then(|s| serde_json::from_str::<RelayConfig>(&s).ok())
+        .is_some_and(|config| !config.rooms.is_empty() || config.encrypted_auth_token.is_some())
+}
+
 /// Load config from backup file. Returns default config if backup doesn't exist or fails.
 fn load_config_from_backup(backup_path: &std::path::Path) -> RelayConfig {
     if !backup_path.exists() {
@@ -488,6 +498,10 @@ pub struct RoomDefinition {
     pub encrypted_password: Option<String>,
     #[serde(default)]
     pub auto_connect: bool,
+    /// Connect to the relay only while none of the room's devices is
+    /// reachable on the LAN (`lan_sync::transport`)
+    #[serde(default)]
+    pub relay_fallback: bool,
 }
 
 // ── Room connection state ───────────────────────────────────────────
@@ -514,7 +528,10 @@ pub struct RoomStatus {
     pub room_code: String,
     pub password: String,
     pub auto_connect: bool,
+    pub relay_fallback: bool,
     pub connected: bool,
+    /// Connected to the relay (`connected` also counts LAN-only rooms)
+    pub relay_connected: bool,
     pub connected_peers: Vec<RoomPeerInfo>,
 }
 
@@ -573,6 +590,7 @@ impl RelaySyncManager {
         event_tx: mpsc::UnboundedSender<ServerEvent>,
     ) -> Self {
         let device_key = load_or_create_device_key(data_dir);
+        directory::load(data_dir);
         let config_path = data_dir.join(RELAY_CONFIG_FILE);
         let backup_path = config_path.with_extension("json.bak");
         let mut config: RelayConfig = if config_path.exists() {
@@ -770,6 +788,7 @@ impl RelaySyncManager {
                 password,
                 encrypted_password: None, // will be set on save_config
                 auto_connect,
+                relay_fallback: false,
             });
         }
         self.save_config().await;
@@ -786,6 +805,7 @@ impl RelaySyncManager {
             config.rooms.retain(|r| r.room_code != room_code);
         }
         self.save_config().await;
+        directory::forget_room(room_code);
         Ok(())
     }
 
@@ -875,6 +895,15 @@ impl RelaySyncManager {
             }
         }
 
+        // Wait for an unmetered connection unless allowed (`metered` connects
+        // the rooms when the connection changes)
+        if let Some(app) = crate::GLOBAL_APP_HANDLE.get() {
+            if crate::metered::defers(app, crate::metered::Feature::RelaySync).await {
+                eprintln!("[Relay] Metered connection — deferring relay auto-connect (LAN sync still works)");
+                return Ok(());
+            }
+        }
+
         // Auth token required for relay — skip relay if not authenticated
         // (LAN sync still works via activate_room above)
         if config.auth_token.is_empty() {
@@ -906,8 +935,9 @@ impl RelaySyncManager {
             }
         }
 
+        // Fallback rooms connect once their devices can't be found on the LAN
         for room_def in &config.rooms {
-            if room_def.auto_connect {
+            if room_def.auto_connect && !room_def.relay_fallback {
                 if !self.rooms.read().await.contains_key(&room_def.room_code) {
                     self.spawn_room_task(config.relay_url.clone(), room_def.clone(), config.auth_token.clone(), provider.clone()).await;
                 }
@@ -922,6 +952,7 @@ impl RelaySyncManager {
         for code in room_codes {
             self.disconnect_room(&code).await;
         }
+        directory::flush();
         eprintln!("[Relay] All rooms disconnected");
     }
 
@@ -1205,6 +1236,10 @@ impl RelaySyncManager {
                             if from_device == my_device_id {
                                 continue;
                             }
+                            // Nor one from a device revoked in this room
+                            if directory::is_revoked(room_code, &from_device) {
+                                continue;
+                            }
 
                             match SessionCipher::new(group_key, nonce) {
                                 Ok(cipher) => {
@@ -1225,6 +1260,8 @@ impl RelaySyncManager {
                                                 room.old_decrypt_ciphers.insert(from_device.clone(), old_cipher.clone());
                                             }
                                             room.decrypt_ciphers.insert(from_device.clone(), cipher);
+                                            let known_name = room.member_names.get(&from_device).map(String::as_str);
+                                            directory::device_seen(room_code, &from_device, known_name.unwrap_or(""));
                                             // Store the device name from the device_id for now
                                             // (will be updated when we receive their device_name via a sync message)
                                             if is_new {
@@ -1329,6 +1366,8 @@ impl RelaySyncManager {
                                 if let Some(cipher) = room.decrypt_ciphers.get(&sender_id) {
                                     match decrypt_message(cipher, encrypted_payload) {
                                         Ok(message) => {
+                                            crate::transfer_stats::message_received(&sender_id, &message, encrypted_payload.len());
+                                            directory::message_received(room_code, &sender_id, &message);
                                             let _ = event_tx.send(
                                                 ServerEvent::SyncMessageReceived {
                                                     from_device_id: sender_id.clone(),
@@ -1341,6 +1380,8 @@ impl RelaySyncManager {
                                             if let Some(old_cipher) = room.old_decrypt_ciphers.get(&sender_id) {
                                                 match decrypt_message(old_cipher, encrypted_payload) {
                                                     Ok(message) => {
+                                                        crate::transfer_stats::message_received(&sender_id, &message, encrypted_payload.len());
+                                                        directory::message_received(room_code, &sender_id, &message);
                                                         eprintln!(
                                                             "[Relay] Room {}: decrypted with old cipher from {}",
                                                             room_code,
@@ -1496,6 +1537,8 @@ impl RelaySyncManager {
                                         if let Some(cipher) = room.decrypt_ciphers.get(&sender_id) {
                                             match decrypt_message(cipher, &encrypted_payload) {
                                                 Ok(message) => {
+                                                    crate::transfer_stats::message_received(&sender_id, &message, encrypted_payload.len());
+                                                    directory::message_received(room_code, &sender_id, &message);
                                                     let _ = event_tx.send(
                                                         ServerEvent::SyncMessageReceived {
                                                             from_device_id: sender_id.clone(),
@@ -1512,6 +1555,8 @@ impl RelaySyncManager {
                                                             &encrypted_payload,
                                                         ) {
                                                             Ok(message) => {
+                                                                crate::transfer_stats::message_received(&sender_id, &message, encrypted_payload.len());
+                                                                directory::message_received(room_code, &sender_id, &message);
                                                                 let _ = event_tx.send(
                                                                     ServerEvent::SyncMessageReceived {
                                                                         from_device_id: sender_id
@@ -1653,6 +1698,7 @@ impl RelaySyncManager {
             for (_room_code, room) in rooms.iter_mut() {
                 if room.decrypt_ciphers.contains_key(device_id) {
                     let encrypted = encrypt_message(&mut room.encrypt_cipher, msg)?;
+                    crate::transfer_stats::message_sent(device_id, msg, encrypted.len());
                     found = Some((room.sender.clone(), encrypted));
                     break;
                 }
@@ -1691,6 +1737,7 @@ impl RelaySyncManager {
                 if let Some(room) = rooms.get_mut(room_code) {
                     match encrypt_message(&mut room.encrypt_cipher, msg) {
                         Ok(encrypted) => {
+                            crate::transfer_stats::room_message_sent(room_code, msg, encrypted.len());
                             result.push((room.sender.clone(), encrypted, room_code.clone()));
                         }
                         Err(e) => {
@@ -1724,6 +1771,7 @@ impl RelaySyncManager {
             }
 
             let encrypted = encrypt_message(&mut room.encrypt_cipher, msg)?;
+            crate::transfer_stats::room_message_sent(room_code, msg, encrypted.len());
             (room.sender.clone(), encrypted)
         };
         // Lock released — safe to await
@@ -1774,11 +1822,13 @@ impl RelaySyncManager {
 
         if is_broadcast {
             // No exclusions — broadcast to whole room
+            crate::transfer_stats::room_message_sent(room_code, msg, encrypted.len());
             return send_maybe_chunked(&sender, &my_device_id, None, encrypted).await;
         }
 
         // Send targeted frames to each relay-only peer (reuse same encrypted bytes)
         for target_id in &relay_only_targets {
+            crate::transfer_stats::message_sent(target_id, msg, encrypted.len());
             if let Err(e) =
                 send_maybe_chunked(&sender, &my_device_id, Some(target_id), encrypted.clone())
                     .await
@@ -1816,6 +1866,40 @@ impl RelaySyncManager {
         Some((code.to_string(), password.to_string()))
     }
 
+    /// Use the relay for a room only while its devices aren't reachable on the LAN
+    pub async fn set_room_relay_fallback(&self, room_code: &str, relay_fallback: bool) {
+        {
+            let mut config = self.config.write().await;
+            if let Some(room) = config.rooms.iter_mut().find(|r| r.room_code == room_code) {
+                room.relay_fallback = relay_fallback;
+            }
+        }
+        self.save_config().await;
+    }
+
+    /// Whether a room uses the relay only as a fallback
+    pub async fn is_relay_fallback(&self, room_code: &str) -> bool {
+        self.config.read().await.rooms.iter().any(|r| r.room_code == room_code && r.relay_fallback)
+    }
+
+    /// Connected rooms (LAN or relay) that use the relay only as a fallback
+    pub async fn active_fallback_rooms(&self) -> Vec<String> {
+        let activated = self.manually_activated.read().await;
+        self.config
+            .read()
+            .await
+            .rooms
+            .iter()
+            .filter(|r| r.relay_fallback && activated.contains(&r.room_code))
+            .map(|r| r.room_code.clone())
+            .collect()
+    }
+
+    /// Whether there is an auth token to connect to the relay with
+    pub async fn is_authenticated(&self) -> bool {
+        !self.config.read().await.auth_token.is_empty()
+    }
+
     /// Update the relay URL
     pub async fn set_relay_url(&self, url: String) {
         self.config.write().await.relay_url = normalize_relay_url(&url);
@@ -1853,7 +1937,9 @@ impl RelaySyncManager {
                     room_code: def.room_code.clone(),
                     password: def.password.clone(),
                     auto_connect: def.auto_connect,
+                    relay_fallback: def.relay_fallback,
                     connected: connected_room.is_some() || is_activated,
+                    relay_connected: connected_room.is_some(),
                     connected_peers: connected_room
                         .map(|r| {
                             r.member_names
@@ -1910,6 +1996,26 @@ impl RelaySyncManager {
             .collect()
     }
 
+    /// Stop accepting a device in a room (it was revoked): drop its ciphers
+    /// so its messages are ignored until it sends a session init we accept
+    pub async fn drop_member(&self, room_code: &str, device_id: &str) {
+        let mut rooms = self.rooms.write().await;
+        let Some(room) = rooms.get_mut(room_code) else {
+            return;
+        };
+        room.decrypt_ciphers.remove(device_id);
+        room.old_decrypt_ciphers.remove(device_id);
+        if room.member_names.remove(device_id).is_none() {
+            return;
+        }
+        // Still a peer if it shares another room with us
+        if !rooms.values().any(|r| r.decrypt_ciphers.contains_key(device_id)) {
+            let _ = self.event_tx.send(ServerEvent::PeerDisconnected {
+                device_id: device_id.to_string(),
+            });
+        }
+    }
+
     // ── Authentication ─────────────────────────────────────────────
 
     /// Start OAuth login flow for a given provider.
@@ -0,0 +1,348 @@
+//! Removable drive and network mount awareness for wiki entries
+//!
+//! Wikis on USB sticks, SD cards or network shares disappear whenever the
+//! volume is unplugged or unmounted. This module:
+//! - Classifies where a wiki lives (local, removable, network)
+//! - Tells "drive not present" apart from "file deleted" when opening
+//! - Watches for mount changes and notifies the landing page
+//!   (`wiki-storage-changed`) so entries re-enable automatically
+
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use tauri::Emitter;
+
+/// How often the mount table is re-checked
+const POLL_INTERVAL: Duration = Duration::from_secs(3);
+
+static WATCHER_STARTED: OnceLock<()> = OnceLock::new();
+
+/// Kind of storage a wiki lives on
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageKind {
+    Local,
+    Removable,
+    Network,
+}
+
+/// Availability of a wiki in the recent files list
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct WikiStorageStatus {
+    pub path: String,
+    pub kind: StorageKind,
+    /// Drive letter or mount point the wiki lives on (removable/network only)
+    pub volume: Option<String>,
+    /// Whether the volume is currently mounted/plugged in
+    pub volume_present: bool,
+    /// Whether the wiki file/folder itself exists
+    pub available: bool,
+}
+
+/// Where a path lives, as far as the platform can tell
+struct VolumeInfo {
+    kind: StorageKind,
+    volume: Option<PathBuf>,
+    present: bool,
+}
+
+/// Check the storage status of a single wiki path
+pub fn storage_status(path: &str) -> WikiStorageStatus {
+    // Android SAF URIs are handled by the permission checks instead
+    if path.starts_with("content://") || path.starts_with('{') {
+        return WikiStorageStatus {
+            path: path.to_string(),
+            kind: StorageKind::Local,
+            volume: None,
+            volume_present: true,
+            available: true,
+        };
+    }
+
+    let info = volume_info(Path::new(path));
+    WikiStorageStatus {
+        path: path.to_string(),
+        kind: info.kind,
+        volume: info.volume.map(|v| v.to_string_lossy().into_owned()),
+        volume_present: info.present,
+        available: info.present && Path::new(path).exists(),
+    }
+}
+
+/// Fail with a "drive not present" error if the wiki's volume is unplugged.
+/// Called before opening so the user isn't told the file doesn't exist.
+pub fn ensure_volume_present(path: &str) -> Result<(), String> {
+    let status = storage_status(path);
+    if status.volume_present {
+        return Ok(());
+    }
+    Err(format!(
+        "Drive not present: {} is on {} which is not connected",
+        path,
+        status.volume.as_deref().unwrap_or("a removable drive")
+    ))
+}
+
+// ── Linux ───────────────────────────────────────────────────────────────
+
+#[cfg(target_os = "linux")]
+const NETWORK_FS_TYPES: &[&str] = &[
+    "nfs", "nfs4", "cifs", "smb3", "smbfs", "sshfs", "fuse.sshfs", "davfs", "fuse.davfs2",
+    "fuse.gvfsd-fuse", "fuse.rclone", "9p", "afs", "ceph", "glusterfs",
+];
+
+/// Directories under which desktop environments auto-mount removable drives
+#[cfg(target_os = "linux")]
+const REMOVABLE_MOUNT_ROOTS: &[&str] = &["/media", "/run/media", "/mnt"];
+
+#[cfg(target_os = "linux")]
+struct MountEntry {
+    device: String,
+    mount_point: PathBuf,
+    fs_type: String,
+}
+
+#[cfg(target_os = "linux")]
+fn read_mounts() -> Vec<MountEntry> {
+    // Mount points escape spaces etc. as octal (\040)
+    fn unescape(s: &str) -> String {
+        let mut out = String::with_capacity(s.len());
+        let mut chars = s.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c == '\\' {
+                let code: String = chars.by_ref().take(3).collect();
+                match u8::from_str_radix(&code, 8) {
+                    Ok(b) => out.push(b as char),
+                    Err(_) => {
+                        out.push('\\');
+                        out.push_str(&code);
+                    }
+                }
+            } else {
+                out.push(c);
+            }
+        }
+        out
+    }
+
+    std::fs::read_to_string("/proc/self/mounts")
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let device = fields.next()?;
+            let mount_point = fields.next()?;
+            let fs_type = fields.next()?;
+            Some(MountEntry {
+                device: device.to_string(),
+                mount_point: PathBuf::from(unescape(mount_point)),
+                fs_type: fs_type.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Check the kernel's removable flag for a block device (partition or whole disk)
+#[cfg(target_os = "linux")]
+fn is_removable_device(device: &str) -> bool {
+    let name = match device.strip_prefix("/dev/") {
+        Some(n) => n,
+        None => return false,
+    };
+    let sys_path = match std::fs::canonicalize(Path::new("/sys/class/block").join(name)) {
+        Ok(p) => p,
+        Err(_) => return false,
+    };
+    // Partitions live below their disk: .../block/sdb/sdb1
+    [sys_path.join("removable"), sys_path.join("../removable")]
+        .iter()
+        .any(|p| std::fs::read_to_string(p).map(|s| s.trim() == "1").unwrap_or(false))
+}
+
+#[cfg(target_os = "linux")]
+fn volume_info(path: &Path) -> VolumeInfo {
+    let mounts = read_mounts();
+    let mount = mounts
+        .iter()
+        .filter(|m| path.starts_with(&m.mount_point))
+        .max_by_key(|m| m.mount_point.as_os_str().len());
+
+    if let Some(m) = mount.filter(|m| m.mount_point != Path::new("/")) {
+        let kind = if NETWORK_FS_TYPES.contains(&m.fs_type.as_str()) {
+            StorageKind::Network
+        } else if is_removable_device(&m.device)
+            || REMOVABLE_MOUNT_ROOTS.iter().any(|root| m.mount_point.starts_with(root))
+        {
+            StorageKind::Removable
+        } else {
+            StorageKind::Local
+        };
+        let volume = (kind != StorageKind::Local).then(|| m.mount_point.clone());
+        return VolumeInfo { kind, volume, present: true };
+    }
+
+    // Not on a dedicated mount: if it's below an auto-mount root, the drive
+    // is most likely unplugged (e.g. /run/media/<user>/<label>/wiki.html)
+    for root in REMOVABLE_MOUNT_ROOTS {
+        if let Ok(rest) = path.strip_prefix(root) {
+            // /media/<label> or /media/<user>/<label>, /run/media/<user>/<label>
+            let depth = if *root == "/mnt" { 1 } else { 2 };
+            let parts: Vec<_> = rest.components().take(depth).collect();
+            if parts.len() == depth {
+                let volume = parts.iter().fold(PathBuf::from(root), |acc, c| acc.join(c));
+                let present = volume.exists() && mounts.iter().any(|m| m.mount_point == volume);
+                if !present && !path.exists() {
+                    return VolumeInfo { kind: StorageKind::Removable, volume: Some(volume), present: false };
+                }
+            }
+        }
+    }
+
+    VolumeInfo { kind: StorageKind::Local, volume: None, present: true }
+}
+
+#[cfg(target_os = "linux")]
+fn mount_signature() -> String {
+    std::fs::read_to_string("/proc/self/mounts").unwrap_or_default()
+}
+
+// ── Windows ─────────────────────────────────────────────────────────────
+
+#[cfg(target_os = "windows")]
+fn volume_info(path: &Path) -> VolumeInfo {
+    use std::path::{Component, Prefix};
+    use windows::core::PCWSTR;
+    use windows::Win32::Storage::FileSystem::GetDriveTypeW;
+
+    // GetDriveTypeW return values
+    const DRIVE_NO_ROOT_DIR: u32 = 1;
+    const DRIVE_REMOVABLE: u32 = 2;
+    const DRIVE_REMOTE: u32 = 4;
+    const DRIVE_CDROM: u32 = 5;
+
+    let prefix = match path.components().next() {
+        Some(Component::Prefix(p)) => p,
+        _ => return VolumeInfo { kind: StorageKind::Local, volume: None, present: true },
+    };
+
+    match prefix.kind() {
+        Prefix::UNC(..) | Prefix::VerbatimUNC(..) => {
+            let share = PathBuf::from(format!("{}\\", prefix.as_os_str().to_string_lossy()));
+            let present = share.exists();
+            VolumeInfo { kind: StorageKind::Network, volume: Some(share), present }
+        }
+        Prefix::Disk(letter) | Prefix::VerbatimDisk(letter) => {
+            let root = format!("{}:\\", letter as char);
+            let wide: Vec<u16> = root.encode_utf16().chain(std::iter::once(0)).collect();
+            let drive_type = unsafe { GetDriveTypeW(PCWSTR(wide.as_ptr())) };
+            let kind = match drive_type {
+                DRIVE_REMOVABLE | DRIVE_CDROM => StorageKind::Removable,
+                DRIVE_REMOTE => StorageKind::Network,
+                // A missing drive letter was almost always a USB stick or mapped share
+                DRIVE_NO_ROOT_DIR => StorageKind::Removable,
+                _ => StorageKind::Local,
+            };
+            let present = drive_type != DRIVE_NO_ROOT_DIR;
+            let volume = (kind != StorageKind::Local).then(|| PathBuf::from(root));
+            VolumeInfo { kind, volume, present }
+        }
+        _ => VolumeInfo { kind: StorageKind::Local, volume: None, present: true },
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn mount_signature() -> String {
+    use windows::Win32::Storage::FileSystem::GetLogicalDrives;
+    format!("{:032b}", unsafe { GetLogicalDrives() })
+}
+
+// ── macOS ───────────────────────────────────────────────────────────────
+
+#[cfg(target_os = "macos")]
+fn volume_info(path: &Path) -> VolumeInfo {
+    let name = match path.strip_prefix("/Volumes").ok().and_then(|rest| rest.components().next()) {
+        Some(c) => c.as_os_str().to_owned(),
+        None => return VolumeInfo { kind: StorageKind::Local, volume: None, present: true },
+    };
+    let volume = Path::new("/Volumes").join(name);
+
+    // `mount` lists e.g. "//user@server/share on /Volumes/share (smbfs, ...)"
+    let needle = format!(" on {} (", volume.display());
+    let is_network = std::process::Command::new("mount")
+        .output()
+        .map(|o| {
+            String::from_utf8_lossy(&o.stdout).lines().any(|line| {
+                line.contains(&needle)
+                    && ["smbfs", "afpfs", "nfs", "webdav", "ftp"].iter().any(|t| line.contains(&format!("({}", t)))
+            })
+        })
+        .unwrap_or(false);
+
+    let present = volume.exists();
+    let kind = if is_network { StorageKind::Network } else { StorageKind::Removable };
+    VolumeInfo { kind, volume: Some(volume), present }
+}
+
+#[cfg(target_os = "macos")]
+fn mount_signature() -> String {
+    let mut names: Vec<String> = std::fs::read_dir("/Volumes")
+        .map(|dir| dir.flatten().map(|e| e.file_name().to_string_lossy().into_owned()).collect())
+        .unwrap_or_default();
+    names.sort();
+    names.join("\n")
+}
+
+// ── Android ─────────────────────────────────────────────────────────────
+
+#[cfg(target_os = "android")]
+fn volume_info(_path: &Path) -> VolumeInfo {
+    VolumeInfo { kind: StorageKind::Local, volume: None, present: true }
+}
+
+#[cfg(target_os = "android")]
+fn mount_signature() -> String {
+    String::new()
+}
+
+/// Storage status of every wiki in the recent files list
+fn recent_files_status(app: &tauri::AppHandle) -> Vec<WikiStorageStatus> {
+    crate::wiki_storage::load_recent_files_from_disk(app)
+        .iter()
+        .map(|entry| storage_status(&entry.path))
+        .collect()
+}
+
+/// Start polling the mount table (once per process) and emit
+/// `wiki-storage-changed` when drives are plugged in or removed
+fn ensure_watcher(app: &tauri::AppHandle) {
+    if cfg!(target_os = "android") || WATCHER_STARTED.set(()).is_err() {
+        return;
+    }
+    let app = app.clone();
+    std::thread::spawn(move || {
+        let mut last = mount_signature();
+        loop {
+            std::thread::sleep(POLL_INTERVAL);
+            let current = mount_signature();
+            if current != last {
+                last = current;
+                eprintln!("[TiddlyDesktop] Mounted volumes changed, rechecking wiki availability");
+                let _ = app.emit("wiki-storage-changed", recent_files_status(&app));
+            }
+        }
+    });
+}
+
+/// Get the storage kind and availability of every wiki in the recent files list.
+/// Also starts watching for drives being plugged in or removed.
+#[tauri::command]
+pub async fn get_wiki_storage_status(app: tauri::AppHandle) -> Result<Vec<WikiStorageStatus>, String> {
+    let app_clone = app.clone();
+    // Stat calls on stale network mounts can block for a while
+    let statuses = tokio::task::spawn_blocking(move || recent_files_status(&app_clone))
+        .await
+        .map_err(|e| format!("Failed to spawn blocking task: {}", e))?;
+    ensure_watcher(&app);
+    Ok(statuses)
+}
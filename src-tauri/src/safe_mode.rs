@@ -0,0 +1,95 @@
+//! Opening a wiki in TiddlyWiki's safe mode (desktop)
+//!
+//! A plugin that breaks the wiki's startup makes the wiki unusable, and with
+//! it the plugin library that could remove it. `open_wiki_safe_mode` starts
+//! the wiki's process with `TIDDLYDESKTOP_SAFE_MODE`, and the process loads
+//! the wiki with the `#:safe` URL fragment: TiddlyWiki then only registers the
+//! core plugin and ignores the wiki's startup tiddlers. The other plugins stay
+//! in the wiki, so the broken one can be deleted and the wiki saved.
+//!
+//! Only the next start of the wiki's process is affected; opening the wiki
+//! again loads it normally.
+
+use std::collections::HashSet;
+#[cfg(not(target_os = "android"))]
+use std::path::Path;
+use std::process::Command;
+use std::sync::{LazyLock, Mutex};
+
+use tauri::Manager;
+
+/// Set for wiki processes that load their wiki in safe mode
+const SAFE_MODE_ENV_VAR: &str = "TIDDLYDESKTOP_SAFE_MODE";
+
+/// Wikis whose next process starts in safe mode (main process)
+static PENDING: LazyLock<Mutex<HashSet<String>>> = LazyLock::new(|| Mutex::new(HashSet::new()));
+
+/// Whether this wiki process loads its wiki in safe mode
+pub fn enabled() -> bool {
+    std::env::var_os(SAFE_MODE_ENV_VAR).is_some()
+}
+
+/// `url` with TiddlyWiki's safe mode fragment
+pub fn safe_mode_url(url: &str) -> String {
+    let base = url.split('#').next().unwrap_or(url);
+    format!("{}#:safe", base)
+}
+
+/// The URL a wiki window loads: in safe mode if this process was started so
+pub fn wiki_url(url: String) -> String {
+    if enabled() {
+        safe_mode_url(&url)
+    } else {
+        url
+    }
+}
+
+/// Start a wiki process about to be spawned in safe mode, if it was asked for
+pub fn configure_process(cmd: &mut Command, wiki_path: &str) {
+    if PENDING.lock().unwrap().remove(wiki_path) {
+        cmd.env(SAFE_MODE_ENV_VAR, "1");
+    }
+}
+
+/// Open a wiki of the wiki list with its plugins (except the core) turned off
+#[tauri::command]
+pub async fn open_wiki_safe_mode(app: tauri::AppHandle, path: String) -> Result<crate::WikiEntry, String> {
+    let open = app
+        .state::<crate::AppState>()
+        .wiki_processes
+        .lock()
+        .unwrap()
+        .keys()
+        .any(|open| crate::utils::paths_equal(open, &path));
+    if open {
+        return Err("Close the wiki first, then open it in safe mode".to_string());
+    }
+    if crate::browser_mode::is_enabled(&app, &path) {
+        return Err("Wikis served to the browser can't be opened in safe mode; add #:safe to the address".to_string());
+    }
+
+    PENDING.lock().unwrap().insert(path.clone());
+    #[cfg(not(target_os = "android"))]
+    let result = if Path::new(&path).is_dir() {
+        crate::open_wiki_folder(app.clone(), path.clone(), None).await
+    } else {
+        crate::open_wiki_window(app.clone(), path.clone(), None, None, None).await
+    };
+    #[cfg(target_os = "android")]
+    let result = Err("Safe mode is not available on Android".to_string());
+    // Don't leave it for a later, normal start if the process wasn't spawned
+    PENDING.lock().unwrap().remove(&path);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_safe_mode_url() {
+        assert_eq!(safe_mode_url("wikifile://localhost/abc"), "wikifile://localhost/abc#:safe");
+        assert_eq!(safe_mode_url("http://127.0.0.1:8080/"), "http://127.0.0.1:8080/#:safe");
+        assert_eq!(safe_mode_url("http://127.0.0.1:8080/#GettingStarted"), "http://127.0.0.1:8080/#:safe");
+    }
+}
@@ -0,0 +1,215 @@
+//! Scheduled backups of single-file wikis (desktop)
+//!
+//! Independent of the backups taken before each save: a wiki with a
+//! `scheduled_backup` interval in its wiki list entry is copied every N
+//! minutes, whether it is open or not, unless it hasn't changed since the last
+//! scheduled backup. The copies go to a `scheduled` folder inside the wiki's
+//! backup folder, named like the save-time backups, so the save-time pruning
+//! never touches them; they have their own retention (count and age).
+//!
+//! The main process checks the wiki list every minute. When a backup is due
+//! follows from the newest scheduled backup, so the schedule survives restarts.
+
+use std::path::{Path, PathBuf};
+
+use chrono::{Local, NaiveDateTime};
+use tiddlydesktop_core::backup::{backup_dir_for_wiki, list_wiki_backups, BackupFile, BACKUP_TIMESTAMP_FORMAT};
+
+use crate::types::{ScheduledBackupConfig, WikiEntry};
+use crate::utils;
+use crate::wiki_storage::{load_recent_files_from_disk, save_recent_files_to_disk};
+
+/// Folder inside the backup folder holding the scheduled backups
+const SCHEDULED_DIR: &str = "scheduled";
+
+/// Scheduled backups kept unless configured otherwise
+const DEFAULT_KEEP: u32 = 24;
+
+/// How often the wiki list is checked for due backups
+#[cfg(not(target_os = "android"))]
+const CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// Folder of a wiki's scheduled backups
+pub fn scheduled_dir(wiki_path: &Path, custom_backup_dir: Option<&str>) -> Option<PathBuf> {
+    Some(backup_dir_for_wiki(wiki_path, custom_backup_dir)?.join(SCHEDULED_DIR))
+}
+
+/// Whether a backup is due at `now`, given the newest scheduled backup
+fn is_due(config: &ScheduledBackupConfig, newest: Option<NaiveDateTime>, now: NaiveDateTime) -> bool {
+    if config.interval_minutes == 0 {
+        return false;
+    }
+    newest.is_none_or(|newest| now - newest >= chrono::Duration::minutes(config.interval_minutes as i64))
+}
+
+/// Backups to delete (`backups` oldest first): beyond the count to keep or
+/// older than the maximum age. The newest one is always kept, so an unchanged
+/// wiki still has a scheduled backup.
+fn to_prune(backups: &[BackupFile], config: &ScheduledBackupConfig, now: NaiveDateTime) -> Vec<PathBuf> {
+    let Some((_newest, older)) = backups.split_last() else {
+        return Vec::new();
+    };
+    let keep = config.keep.unwrap_or(DEFAULT_KEEP) as usize;
+    let over_count = if keep == 0 { 0 } else { backups.len().saturating_sub(keep) };
+    older
+        .iter()
+        .enumerate()
+        .filter(|(i, backup)| {
+            *i < over_count
+                || config
+                    .max_age_days
+                    .is_some_and(|days| now - backup.timestamp > chrono::Duration::days(days as i64))
+        })
+        .map(|(_, backup)| backup.path.clone())
+        .collect()
+}
+
+/// Whether two files have the same content
+fn same_content(a: &Path, b: &Path) -> bool {
+    match (std::fs::metadata(a), std::fs::metadata(b)) {
+        (Ok(ma), Ok(mb)) if ma.len() == mb.len() => {}
+        _ => return false,
+    }
+    matches!((std::fs::read(a), std::fs::read(b)), (Ok(ca), Ok(cb)) if ca == cb)
+}
+
+/// Take a scheduled backup of a wiki if it changed since the last one, then
+/// apply the retention rules. Returns the new backup, if one was taken.
+fn back_up(entry: &WikiEntry, config: &ScheduledBackupConfig) -> Result<Option<PathBuf>, String> {
+    let wiki_path = Path::new(&entry.path);
+    let dir = scheduled_dir(wiki_path, entry.backup_dir.as_deref()).ok_or("No backup folder for this wiki")?;
+    let dir_str = dir.to_string_lossy().into_owned();
+    let backups = list_wiki_backups(wiki_path, Some(&dir_str));
+
+    let mut taken = None;
+    if !backups.last().is_some_and(|newest| same_content(wiki_path, &newest.path)) {
+        std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        let stem = wiki_path.file_stem().and_then(|s| s.to_str()).unwrap_or("wiki");
+        let dest = dir.join(format!("{}.{}.html", stem, Local::now().format(BACKUP_TIMESTAMP_FORMAT)));
+        // Copy next to the target first so a half-written copy never looks like a backup
+        let partial = dest.with_extension("html.partial");
+        std::fs::copy(wiki_path, &partial)
+            .and_then(|_| std::fs::rename(&partial, &dest))
+            .map_err(|e| {
+                let _ = std::fs::remove_file(&partial);
+                format!("Failed to back up {}: {}", entry.path, e)
+            })?;
+        taken = Some(dest);
+    }
+
+    let backups = list_wiki_backups(wiki_path, Some(&dir_str));
+    for old in to_prune(&backups, config, Local::now().naive_local()) {
+        let _ = std::fs::remove_file(old);
+    }
+    Ok(taken)
+}
+
+/// Take the backups that are due
+#[cfg(not(target_os = "android"))]
+fn run_due(app: &tauri::AppHandle) {
+    let now = Local::now().naive_local();
+    for entry in load_recent_files_from_disk(app) {
+        let Some(config) = entry.scheduled_backup.clone() else {
+            continue;
+        };
+        if entry.is_folder || entry.path.starts_with("content://") || !Path::new(&entry.path).is_file() {
+            continue;
+        }
+        let wiki_path = Path::new(&entry.path);
+        let newest = scheduled_dir(wiki_path, entry.backup_dir.as_deref())
+            .map(|dir| list_wiki_backups(wiki_path, Some(&dir.to_string_lossy())))
+            .and_then(|backups| backups.last().map(|b| b.timestamp));
+        if !is_due(&config, newest, now) {
+            continue;
+        }
+        match back_up(&entry, &config) {
+            Ok(Some(dest)) => {
+                eprintln!("[ScheduledBackup] {} -> {}", entry.path, dest.display());
+                crate::webhooks::dispatch(
+                    app,
+                    crate::webhooks::WebhookEvent::BackupCompleted,
+                    serde_json::json!({ "wikiPath": entry.path, "scheduled": true }),
+                );
+            }
+            Ok(None) => {}
+            Err(e) => eprintln!("[ScheduledBackup] {}", e),
+        }
+    }
+}
+
+/// Check for due backups every minute (main process)
+#[cfg(not(target_os = "android"))]
+pub fn start(app: &tauri::AppHandle) {
+    let app = app.clone();
+    std::thread::spawn(move || loop {
+        std::thread::sleep(CHECK_INTERVAL);
+        run_due(&app);
+    });
+}
+
+/// Set (or with None or a zero interval, clear) the scheduled backups of a wiki
+#[tauri::command]
+pub fn set_wiki_scheduled_backup(
+    app: tauri::AppHandle,
+    path: String,
+    config: Option<ScheduledBackupConfig>,
+) -> Result<(), String> {
+    let mut entries = load_recent_files_from_disk(&app);
+    let entry = entries
+        .iter_mut()
+        .find(|entry| utils::paths_equal(&entry.path, &path))
+        .ok_or("Wiki not in the wiki list")?;
+    entry.scheduled_backup = config.filter(|c| c.interval_minutes > 0);
+    save_recent_files_to_disk(&app, &entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    fn config(interval_minutes: u32, keep: Option<u32>, max_age_days: Option<u32>) -> ScheduledBackupConfig {
+        ScheduledBackupConfig { interval_minutes, keep, max_age_days }
+    }
+
+    fn backups(times: &[&str]) -> Vec<BackupFile> {
+        times
+            .iter()
+            .map(|t| BackupFile { path: PathBuf::from(*t), timestamp: at(t) })
+            .collect()
+    }
+
+    #[test]
+    fn test_is_due() {
+        let hourly = config(60, None, None);
+        assert!(is_due(&hourly, None, at("2026-01-01 10:00")));
+        assert!(!is_due(&hourly, Some(at("2026-01-01 09:30")), at("2026-01-01 10:00")));
+        assert!(is_due(&hourly, Some(at("2026-01-01 09:00")), at("2026-01-01 10:00")));
+        assert!(!is_due(&config(0, None, None), None, at("2026-01-01 10:00")));
+    }
+
+    #[test]
+    fn test_prune_by_count() {
+        let list = backups(&["2026-01-01 08:00", "2026-01-01 09:00", "2026-01-01 10:00"]);
+        let now = at("2026-01-01 10:30");
+        assert_eq!(to_prune(&list, &config(60, Some(2), None), now), vec![PathBuf::from("2026-01-01 08:00")]);
+        assert!(to_prune(&list, &config(60, Some(0), None), now).is_empty());
+        assert!(to_prune(&list, &config(60, None, None), now).is_empty());
+    }
+
+    #[test]
+    fn test_prune_by_age_keeps_newest() {
+        let list = backups(&["2025-12-01 08:00", "2025-12-20 08:00", "2025-12-30 08:00"]);
+        let now = at("2026-01-01 10:00");
+        assert_eq!(
+            to_prune(&list, &config(60, Some(0), Some(7)), now),
+            vec![PathBuf::from("2025-12-01 08:00"), PathBuf::from("2025-12-20 08:00")]
+        );
+        // Even when the newest is too old
+        let old = backups(&["2025-11-01 08:00"]);
+        assert!(to_prune(&old, &config(60, None, Some(7)), now).is_empty());
+    }
+}
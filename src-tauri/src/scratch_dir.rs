@@ -0,0 +1,142 @@
+//! Scratch directory for wiki builds, conversions and plugin rebuilds
+//!
+//! Node.js builds need a temporary working directory. The system temp dir can
+//! be a small tmpfs or blocked by mandatory profiles, so builds go to a scratch
+//! directory under the data dir by default, or a user-configured location.
+//! Build directories left behind by crashed or killed builds are removed on startup.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Prefix of every build directory created in the scratch directory
+const BUILD_DIR_PREFIX: &str = "tiddlydesktop-";
+
+/// Build directories older than this are considered abandoned
+const STALE_AFTER: Duration = Duration::from_secs(12 * 60 * 60);
+
+/// Scratch directory as shown in the settings UI
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct ScratchDirInfo {
+    /// Effective scratch directory
+    pub path: String,
+    /// Whether a custom location is configured
+    pub custom: bool,
+}
+
+/// Default scratch directory (under the data dir)
+fn default_scratch_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(crate::get_data_dir(app)?.join("scratch"))
+}
+
+/// Get the effective scratch directory, creating it if needed
+pub fn scratch_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let custom = crate::wiki_storage::load_app_settings(app)
+        .ok()
+        .and_then(|s| s.scratch_dir)
+        .filter(|d| !d.is_empty());
+    let dir = match custom {
+        Some(d) => PathBuf::from(d),
+        None => default_scratch_dir(app)?,
+    };
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create scratch directory {}: {}", dir.display(), e))?;
+    Ok(dir)
+}
+
+/// Create a fresh build directory in the scratch directory,
+/// e.g. `tiddlydesktop-build-<pid>`
+pub fn create_build_dir(app: &tauri::AppHandle, kind: &str) -> Result<PathBuf, String> {
+    let dir = scratch_dir(app)?.join(format!("{}{}-{}", BUILD_DIR_PREFIX, kind, std::process::id()));
+    if dir.exists() {
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create temp directory: {}", e))?;
+    Ok(dir)
+}
+
+/// Remove abandoned build directories from `dir`. Returns how many were removed.
+fn remove_stale_build_dirs(dir: &Path) -> usize {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(e) => e,
+        Err(_) => return 0,
+    };
+    let mut removed = 0;
+    for entry in entries.flatten() {
+        if !entry.file_name().to_string_lossy().starts_with(BUILD_DIR_PREFIX) {
+            continue;
+        }
+        let is_stale = entry
+            .metadata()
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .map(|age| age > STALE_AFTER)
+            .unwrap_or(false);
+        if !is_stale {
+            continue;
+        }
+        let path = entry.path();
+        let result = if path.is_dir() {
+            std::fs::remove_dir_all(&path)
+        } else {
+            std::fs::remove_file(&path)
+        };
+        match result {
+            Ok(()) => removed += 1,
+            Err(e) => eprintln!("[TiddlyDesktop] Failed to remove stale build dir {}: {}", path.display(), e),
+        }
+    }
+    removed
+}
+
+/// Remove stale build directories from the scratch directory, and from the
+/// system temp dir where older versions created them. Runs in the background.
+pub fn cleanup_stale(app: &tauri::AppHandle) {
+    let app = app.clone();
+    std::thread::spawn(move || {
+        let mut removed = 0;
+        if let Ok(dir) = scratch_dir(&app) {
+            removed += remove_stale_build_dirs(&dir);
+        }
+        #[cfg(not(target_os = "android"))]
+        {
+            removed += remove_stale_build_dirs(&std::env::temp_dir());
+        }
+        if removed > 0 {
+            eprintln!("[TiddlyDesktop] Removed {} stale build directories", removed);
+        }
+    });
+}
+
+/// Get the effective scratch directory
+#[tauri::command]
+pub fn get_scratch_dir(app: tauri::AppHandle) -> Result<ScratchDirInfo, String> {
+    let custom = crate::wiki_storage::load_app_settings(&app)?
+        .scratch_dir
+        .is_some_and(|d| !d.is_empty());
+    Ok(ScratchDirInfo {
+        path: scratch_dir(&app)?.to_string_lossy().into_owned(),
+        custom,
+    })
+}
+
+/// Set a custom scratch directory (empty string restores the default).
+/// The directory must be writable.
+#[tauri::command]
+pub fn set_scratch_dir(app: tauri::AppHandle, path: String) -> Result<ScratchDirInfo, String> {
+    let path = path.trim().to_string();
+    if !path.is_empty() {
+        let dir = crate::drag_drop::sanitize::validate_user_directory_path(&path)?;
+        let probe = dir.join(format!(".{}probe-{}", BUILD_DIR_PREFIX, std::process::id()));
+        std::fs::write(&probe, b"")
+            .map_err(|e| format!("Scratch directory is not writable: {}", e))?;
+        let _ = std::fs::remove_file(&probe);
+    }
+
+    let mut settings = crate::wiki_storage::load_app_settings(&app)?;
+    settings.scratch_dir = if path.is_empty() { None } else { Some(path) };
+    crate::wiki_storage::save_app_settings(&app, &settings)?;
+    eprintln!("[TiddlyDesktop] Scratch directory set to: {:?}", settings.scratch_dir);
+    get_scratch_dir(app)
+}
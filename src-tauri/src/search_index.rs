@@ -0,0 +1,348 @@
+//! Full-text search across the wikis of the wiki list
+//!
+//! A background thread in the main process (desktop) keeps an in-memory
+//! inverted index of the titles and text of the non-system tiddlers of every
+//! wiki in the wiki list. Wikis are read from disk like `wiki_filter` does, so
+//! they don't need to be open. A wiki is indexed again when its file (or a
+//! file in its folder) changes and dropped when it leaves the wiki list;
+//! changes in open wikis are found once they are saved.
+//!
+//! `search_all_wikis` finds the tiddlers containing every word of a query
+//! (the last word also as the start of a word, for searching as you type),
+//! best matches first, each with a snippet around its first match.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::SystemTime;
+
+use serde::Serialize;
+use tiddlydesktop_core::filter::{Tiddler, Wiki};
+
+/// How often the wiki list is checked for changed wikis
+#[cfg(not(target_os = "android"))]
+const REFRESH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// Queries shorter than this find nothing
+const MIN_QUERY_CHARS: usize = 2;
+
+/// Results returned unless given a limit, and at most
+const DEFAULT_LIMIT: usize = 50;
+const MAX_LIMIT: usize = 500;
+
+/// Characters of text shown before the first match, and in all
+const SNIPPET_BEFORE: usize = 40;
+const SNIPPET_LENGTH: usize = 160;
+
+/// The indexed wikis, by path
+static INDEX: LazyLock<Mutex<HashMap<String, Arc<WikiIndex>>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Whether wikis are being indexed right now
+static INDEXING: AtomicBool = AtomicBool::new(false);
+
+struct Doc {
+    title: String,
+    text: String,
+}
+
+/// The searchable tiddlers of one wiki
+struct WikiIndex {
+    name: String,
+    /// Modification time of the wiki when it was indexed
+    stamp: Option<SystemTime>,
+    docs: Vec<Doc>,
+    /// Words (lowercase) and the docs containing them
+    terms: BTreeMap<String, Vec<u32>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchHit {
+    pub wiki_path: String,
+    pub wiki_name: String,
+    pub title: String,
+    pub snippet: String,
+    #[serde(skip)]
+    score: u32,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchResults {
+    pub hits: Vec<SearchHit>,
+    /// Still indexing: wikis may be missing or out of date
+    pub indexing: bool,
+}
+
+/// The words of `text`, lowercase, with their byte offsets
+fn words(text: &str) -> Vec<(usize, String)> {
+    let mut words = Vec::new();
+    let mut start = None;
+    for (i, c) in text.char_indices() {
+        match (c.is_alphanumeric(), start) {
+            (true, None) => start = Some(i),
+            (false, Some(s)) => {
+                words.push((s, text[s..i].to_lowercase()));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        words.push((s, text[s..].to_lowercase()));
+    }
+    words
+}
+
+/// Whether `word` matches query term `i` of `terms`: the last term also as
+/// the start of a word
+fn term_matches(terms: &[String], i: usize, word: &str) -> bool {
+    if i + 1 == terms.len() {
+        word.starts_with(terms[i].as_str())
+    } else {
+        word == terms[i]
+    }
+}
+
+/// Tiddlers worth searching: no system tiddlers, drafts or binary tiddlers
+fn is_searchable(tiddler: &Tiddler) -> bool {
+    let title = tiddler.get("title").map_or("", String::as_str);
+    let content_type = tiddler.get("type").map_or("", String::as_str);
+    !title.is_empty()
+        && !title.starts_with("$:/")
+        && !tiddler.contains_key("draft.of")
+        && (content_type.is_empty() || content_type.starts_with("text/") || content_type == "application/json")
+}
+
+impl WikiIndex {
+    fn build<'a>(name: String, stamp: Option<SystemTime>, tiddlers: impl IntoIterator<Item = &'a Tiddler>) -> Self {
+        let mut docs = Vec::new();
+        let mut terms: BTreeMap<String, Vec<u32>> = BTreeMap::new();
+        for tiddler in tiddlers.into_iter().filter(|t| is_searchable(t)) {
+            let doc = Doc {
+                title: tiddler["title"].clone(),
+                text: tiddler.get("text").cloned().unwrap_or_default(),
+            };
+            let id = docs.len() as u32;
+            let doc_terms: HashSet<String> = words(&doc.title).into_iter().chain(words(&doc.text)).map(|(_, w)| w).collect();
+            for term in doc_terms {
+                terms.entry(term).or_default().push(id);
+            }
+            docs.push(doc);
+        }
+        Self { name, stamp, docs, terms }
+    }
+
+    /// Docs containing every term (the last one as the start of a word)
+    fn matching_docs(&self, terms: &[String]) -> Vec<u32> {
+        let mut result: Option<HashSet<u32>> = None;
+        for (i, term) in terms.iter().enumerate() {
+            let docs: HashSet<u32> = if i + 1 == terms.len() {
+                self.terms
+                    .range(term.clone()..)
+                    .take_while(|(word, _)| word.starts_with(term.as_str()))
+                    .flat_map(|(_, docs)| docs.iter().copied())
+                    .collect()
+            } else {
+                self.terms.get(term).map(|docs| docs.iter().copied().collect()).unwrap_or_default()
+            };
+            result = Some(match result {
+                None => docs,
+                Some(result) => result.intersection(&docs).copied().collect(),
+            });
+        }
+        result.map(|docs| docs.into_iter().collect()).unwrap_or_default()
+    }
+
+    fn search(&self, path: &str, terms: &[String]) -> Vec<SearchHit> {
+        self.matching_docs(terms)
+            .into_iter()
+            .map(|id| {
+                let doc = &self.docs[id as usize];
+                let title_words = words(&doc.title);
+                let text_words = words(&doc.text);
+                let matches = |words: &[(usize, String)]| {
+                    words.iter().filter(|(_, w)| (0..terms.len()).any(|i| term_matches(terms, i, w))).count() as u32
+                };
+                let first_match = text_words
+                    .iter()
+                    .find(|(_, w)| (0..terms.len()).any(|i| term_matches(terms, i, w)))
+                    .map(|(offset, _)| *offset);
+                SearchHit {
+                    wiki_path: path.to_string(),
+                    wiki_name: self.name.clone(),
+                    title: doc.title.clone(),
+                    snippet: snippet(&doc.text, first_match.unwrap_or(0)),
+                    score: matches(&title_words) * 10 + matches(&text_words).min(10),
+                }
+            })
+            .collect()
+    }
+}
+
+/// A line of `text` around the byte offset `at`, with whitespace collapsed
+fn snippet(text: &str, at: usize) -> String {
+    let before: Vec<char> = text[..at].chars().rev().take(SNIPPET_BEFORE + 1).collect();
+    let start = at - before.iter().take(SNIPPET_BEFORE).map(|c| c.len_utf8()).sum::<usize>();
+    let mut snippet = String::new();
+    if before.len() > SNIPPET_BEFORE {
+        snippet.push('…');
+    }
+    let mut rest = text[start..].chars();
+    snippet.push_str(&rest.by_ref().take(SNIPPET_LENGTH).collect::<String>());
+    if rest.next().is_some() {
+        snippet.push('…');
+    }
+    snippet.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// When a wiki file, or the newest file in a wiki folder, was last changed
+fn modified(path: &Path) -> Option<SystemTime> {
+    if !path.is_dir() {
+        return std::fs::metadata(path).and_then(|m| m.modified()).ok();
+    }
+    let mut newest = None;
+    let mut stack = vec![path.to_path_buf()];
+    while let Some(dir) = stack.pop() {
+        for entry in std::fs::read_dir(&dir).into_iter().flatten().flatten() {
+            let Ok(metadata) = entry.metadata() else { continue };
+            if metadata.is_dir() {
+                stack.push(entry.path());
+            } else if let Ok(time) = metadata.modified() {
+                newest = newest.max(Some(time));
+            }
+        }
+    }
+    newest
+}
+
+/// Index the wikis of the wiki list that changed, and forget removed ones
+fn refresh(app: &tauri::AppHandle) {
+    let entries = crate::wiki_storage::load_recent_files_from_disk(app);
+    INDEX.lock().unwrap().retain(|path, _| entries.iter().any(|entry| entry.path == *path));
+    for entry in entries {
+        let stamp = modified(Path::new(&entry.path));
+        let indexed = INDEX.lock().unwrap().get(&entry.path).map(|index| index.stamp);
+        if stamp.is_none() {
+            // Missing, e.g. on an unplugged drive
+            INDEX.lock().unwrap().remove(&entry.path);
+            continue;
+        }
+        if indexed == Some(stamp) {
+            continue;
+        }
+        INDEXING.store(true, Ordering::Relaxed);
+        match Wiki::load(Path::new(&entry.path)) {
+            Ok(wiki) => {
+                let titles = wiki.titles();
+                let index = WikiIndex::build(entry.filename, stamp, titles.iter().filter_map(|t| wiki.get_tiddler(t)));
+                INDEX.lock().unwrap().insert(entry.path, Arc::new(index));
+            }
+            Err(e) => eprintln!("[SearchIndex] Failed to index {}: {}", entry.path, e),
+        }
+    }
+    INDEXING.store(false, Ordering::Relaxed);
+}
+
+/// Keep the index up to date in the background (main process)
+#[cfg(not(target_os = "android"))]
+pub fn start(app: &tauri::AppHandle) {
+    let app = app.clone();
+    INDEXING.store(true, Ordering::Relaxed);
+    std::thread::spawn(move || loop {
+        refresh(&app);
+        std::thread::sleep(REFRESH_INTERVAL);
+    });
+}
+
+fn search(indexes: &HashMap<String, Arc<WikiIndex>>, query: &str, limit: usize) -> Vec<SearchHit> {
+    let terms: Vec<String> = words(query).into_iter().map(|(_, w)| w).collect();
+    if terms.is_empty() || query.trim().chars().count() < MIN_QUERY_CHARS {
+        return Vec::new();
+    }
+    let mut hits: Vec<SearchHit> = indexes.iter().flat_map(|(path, index)| index.search(path, &terms)).collect();
+    hits.sort_by(|a, b| {
+        b.score
+            .cmp(&a.score)
+            .then_with(|| a.wiki_name.to_lowercase().cmp(&b.wiki_name.to_lowercase()))
+            .then_with(|| a.title.to_lowercase().cmp(&b.title.to_lowercase()))
+    });
+    hits.truncate(limit);
+    hits
+}
+
+/// Search the tiddlers of all wikis in the wiki list
+#[tauri::command]
+pub fn search_all_wikis(query: String, limit: Option<usize>) -> SearchResults {
+    let indexes = INDEX.lock().unwrap().clone();
+    SearchResults {
+        hits: search(&indexes, &query, limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT)),
+        indexing: INDEXING.load(Ordering::Relaxed),
+    }
+}
+
+/// Open the wiki of a search result and navigate to its tiddler
+#[tauri::command]
+pub fn open_search_result(app: tauri::AppHandle, wiki_path: String, title: String) -> Result<(), String> {
+    #[cfg(not(target_os = "android"))]
+    {
+        crate::quick_actions::queue_open_tiddler(&app, &wiki_path, title)?;
+        crate::file_open::open_wikis(&app, vec![wiki_path]);
+        Ok(())
+    }
+    #[cfg(target_os = "android")]
+    {
+        let _ = (app, wiki_path, title);
+        Err("Searching all wikis is not available on Android".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tiddler(title: &str, text: &str) -> Tiddler {
+        Tiddler::from([("title".to_string(), title.to_string()), ("text".to_string(), text.to_string())])
+    }
+
+    #[test]
+    fn test_words() {
+        assert_eq!(
+            words("Über-cool, 42x!"),
+            vec![(0, "über".to_string()), (6, "cool".to_string()), (12, "42x".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_search() {
+        let notes = [
+            tiddler("Gardening", "Tomatoes need sun and water."),
+            tiddler("Tomato soup", "A recipe for the sun-ripened tomatoes of the garden."),
+            tiddler("$:/config/Tomato", "tomato"),
+            tiddler("Shopping", "Bread, milk"),
+        ];
+        let index = WikiIndex::build("notes.html".to_string(), None, notes.iter());
+        let indexes = HashMap::from([("/wikis/notes.html".to_string(), Arc::new(index))]);
+
+        let hits = search(&indexes, "tomato", 10);
+        let titles: Vec<&str> = hits.iter().map(|h| h.title.as_str()).collect();
+        assert_eq!(titles, vec!["Tomato soup", "Gardening"]);
+        assert_eq!(hits[1].snippet, "Tomatoes need sun and water.");
+
+        let titles: Vec<String> = search(&indexes, "sun TOMATOES", 10).into_iter().map(|h| h.title).collect();
+        assert_eq!(titles, vec!["Gardening".to_string(), "Tomato soup".to_string()]);
+        assert!(search(&indexes, "sun bread", 10).is_empty());
+        assert!(search(&indexes, "t", 10).is_empty());
+    }
+
+    #[test]
+    fn test_snippet() {
+        let text = format!("{} match here", "word ".repeat(20));
+        let at = text.find("match").unwrap();
+        let around = snippet(&text, at);
+        assert!(around.starts_with('…'));
+        assert!(around.ends_with("match here"));
+        assert_eq!(snippet(&"x".repeat(200), 0).chars().count(), SNIPPET_LENGTH + 1);
+    }
+}
@@ -0,0 +1,327 @@
+//! Serial port access for wiki windows (lab instruments, microcontrollers)
+//!
+//! Wikis list ports, open them and read or write data through
+//! `init_script/serial.js`. Like the location, serial access needs the user's
+//! permission: the first request of a wiki asks, and the answer is recorded
+//! per wiki (`serial` in the wiki configs) and can be reset on the landing page.
+//!
+//! Open ports belong to the wiki process and are closed with it. Reads wait up
+//! to a timeout and return whatever arrived; data is text (UTF-8, invalid bytes
+//! replaced) or base64 for binary protocols.
+
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::Duration;
+
+use base64::Engine;
+use serde::Serialize;
+use serialport::{DataBits, FlowControl, Parity, SerialPort, SerialPortType, StopBits};
+use tauri::AppHandle;
+
+use crate::wiki_storage::{load_wiki_configs, save_wiki_configs};
+
+const MAX_READ_BYTES: usize = 64 * 1024;
+const MAX_READ_TIMEOUT_MS: u64 = 60_000;
+
+/// Only one permission dialog at a time
+static ASKING: Mutex<()> = Mutex::new(());
+
+static NEXT_ID: AtomicU32 = AtomicU32::new(1);
+
+/// Open ports of this process by id
+static PORTS: LazyLock<Mutex<HashMap<u32, Arc<OpenPort>>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Separate handles so a write doesn't wait for a pending read
+struct OpenPort {
+    reader: Mutex<Box<dyn SerialPort>>,
+    writer: Mutex<Box<dyn SerialPort>>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SerialPortEntry {
+    name: String,
+    /// "usb", "bluetooth", "pci" or "unknown"
+    kind: &'static str,
+    manufacturer: Option<String>,
+    product: Option<String>,
+    serial_number: Option<String>,
+    vendor_id: Option<u16>,
+    product_id: Option<u16>,
+}
+
+/// Line settings from the names used by the wiki side
+fn line_settings(
+    data_bits: Option<u8>,
+    parity: Option<&str>,
+    stop_bits: Option<u8>,
+) -> Result<(DataBits, Parity, StopBits), String> {
+    let data_bits = match data_bits.unwrap_or(8) {
+        5 => DataBits::Five,
+        6 => DataBits::Six,
+        7 => DataBits::Seven,
+        8 => DataBits::Eight,
+        other => return Err(format!("Unsupported data bits: {}", other)),
+    };
+    let parity = match parity.unwrap_or("none") {
+        "none" => Parity::None,
+        "odd" => Parity::Odd,
+        "even" => Parity::Even,
+        other => return Err(format!("Unsupported parity \"{}\" (none, odd or even)", other)),
+    };
+    let stop_bits = match stop_bits.unwrap_or(1) {
+        1 => StopBits::One,
+        2 => StopBits::Two,
+        other => return Err(format!("Unsupported stop bits: {}", other)),
+    };
+    Ok((data_bits, parity, stop_bits))
+}
+
+/// Whether a wiki may use serial ports, asking the user the first time
+fn permission(app: &AppHandle, wiki_path: &str) -> Result<bool, String> {
+    use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+
+    let _asking = ASKING.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(allowed) = load_wiki_configs(app)?.serial.get(wiki_path) {
+        return Ok(*allowed);
+    }
+    let allowed = app
+        .dialog()
+        .message(format!(
+            "\"{}\" wants to use the serial ports of this computer (instruments, boards, modems). \
+             Allow it now and in the future?",
+            crate::geolocation::wiki_name(wiki_path)
+        ))
+        .kind(MessageDialogKind::Warning)
+        .title("Serial Ports")
+        .buttons(MessageDialogButtons::OkCancelCustom("Allow".to_string(), "Deny".to_string()))
+        .blocking_show();
+    let mut configs = load_wiki_configs(app)?;
+    configs.serial.insert(wiki_path.to_string(), allowed);
+    save_wiki_configs(app, &configs)?;
+    Ok(allowed)
+}
+
+fn require_permission(app: &AppHandle, wiki_path: &str) -> Result<(), String> {
+    if permission(app, wiki_path)? {
+        Ok(())
+    } else {
+        Err("Serial port access was denied for this wiki".to_string())
+    }
+}
+
+/// The wiki of this process, whose permission the commands check
+#[cfg(not(target_os = "android"))]
+fn this_wiki(state: &tauri::State<'_, crate::WikiModeState>) -> String {
+    state.wiki_path.to_string_lossy().into_owned()
+}
+
+/// Open ports are only used while the wiki's permission stands (it can be
+/// reset on the landing page meanwhile)
+fn require_recorded_permission(app: &AppHandle, wiki_path: &str) -> Result<(), String> {
+    if load_wiki_configs(app)?.serial.get(wiki_path) == Some(&true) {
+        Ok(())
+    } else {
+        Err("Serial port access was denied for this wiki".to_string())
+    }
+}
+
+fn open_port(id: u32) -> Result<Arc<OpenPort>, String> {
+    PORTS
+        .lock()
+        .unwrap()
+        .get(&id)
+        .cloned()
+        .ok_or_else(|| format!("Serial port {} is not open", id))
+}
+
+/// Serial ports of this computer, if the wiki may use them
+#[cfg(not(target_os = "android"))]
+#[tauri::command]
+pub async fn list_serial_ports(app: AppHandle, state: tauri::State<'_, crate::WikiModeState>) -> Result<Vec<SerialPortEntry>, String> {
+    let wiki_path = this_wiki(&state);
+    tokio::task::spawn_blocking(move || {
+        require_permission(&app, &wiki_path)?;
+        let ports = serialport::available_ports().map_err(|e| format!("Failed to list serial ports: {}", e))?;
+        Ok(ports
+            .into_iter()
+            .map(|port| {
+                let mut entry = SerialPortEntry {
+                    name: port.port_name,
+                    kind: "unknown",
+                    manufacturer: None,
+                    product: None,
+                    serial_number: None,
+                    vendor_id: None,
+                    product_id: None,
+                };
+                match port.port_type {
+                    SerialPortType::UsbPort(usb) => {
+                        entry.kind = "usb";
+                        entry.manufacturer = usb.manufacturer;
+                        entry.product = usb.product;
+                        entry.serial_number = usb.serial_number;
+                        entry.vendor_id = Some(usb.vid);
+                        entry.product_id = Some(usb.pid);
+                    }
+                    SerialPortType::BluetoothPort => entry.kind = "bluetooth",
+                    SerialPortType::PciPort => entry.kind = "pci",
+                    SerialPortType::Unknown => {}
+                }
+                entry
+            })
+            .collect())
+    })
+    .await
+    .map_err(|e| format!("Serial port request failed: {}", e))?
+}
+
+/// Open a port (8N1 unless given otherwise), returning its id
+#[cfg(not(target_os = "android"))]
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn open_serial(
+    app: AppHandle,
+    state: tauri::State<'_, crate::WikiModeState>,
+    port: String,
+    baud_rate: u32,
+    data_bits: Option<u8>,
+    parity: Option<String>,
+    stop_bits: Option<u8>,
+) -> Result<u32, String> {
+    let (data_bits, parity, stop_bits) = line_settings(data_bits, parity.as_deref(), stop_bits)?;
+    let wiki_path = this_wiki(&state);
+    tokio::task::spawn_blocking(move || {
+        require_permission(&app, &wiki_path)?;
+        let reader = serialport::new(&port, baud_rate)
+            .data_bits(data_bits)
+            .parity(parity)
+            .stop_bits(stop_bits)
+            .flow_control(FlowControl::None)
+            .open()
+            .map_err(|e| format!("Failed to open {}: {}", port, e))?;
+        let writer = reader.try_clone().map_err(|e| format!("Failed to open {}: {}", port, e))?;
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let open = OpenPort { reader: Mutex::new(reader), writer: Mutex::new(writer) };
+        PORTS.lock().unwrap().insert(id, Arc::new(open));
+        Ok(id)
+    })
+    .await
+    .map_err(|e| format!("Serial port request failed: {}", e))?
+}
+
+/// Read what arrives within `timeout_ms` (default 1000): text, or base64 when
+/// `binary` is set. Empty when nothing arrived.
+#[cfg(not(target_os = "android"))]
+#[tauri::command]
+pub async fn read_serial(
+    app: AppHandle,
+    state: tauri::State<'_, crate::WikiModeState>,
+    id: u32,
+    max_bytes: Option<usize>,
+    timeout_ms: Option<u64>,
+    binary: Option<bool>,
+) -> Result<String, String> {
+    require_recorded_permission(&app, &this_wiki(&state))?;
+    let open = open_port(id)?;
+    let max_bytes = max_bytes.unwrap_or(4096).clamp(1, MAX_READ_BYTES);
+    let timeout = Duration::from_millis(timeout_ms.unwrap_or(1000).min(MAX_READ_TIMEOUT_MS));
+    let bytes = tokio::task::spawn_blocking(move || {
+        let mut reader = open.reader.lock().unwrap();
+        reader
+            .set_timeout(timeout)
+            .map_err(|e| format!("Failed to read serial port: {}", e))?;
+        let mut buffer = vec![0u8; max_bytes];
+        match reader.read(&mut buffer) {
+            Ok(read) => {
+                buffer.truncate(read);
+                Ok(buffer)
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::TimedOut => Ok(Vec::new()),
+            Err(e) => Err(format!("Failed to read serial port: {}", e)),
+        }
+    })
+    .await
+    .map_err(|e| format!("Serial port request failed: {}", e))??;
+    Ok(if binary.unwrap_or(false) {
+        base64::engine::general_purpose::STANDARD.encode(&bytes)
+    } else {
+        String::from_utf8_lossy(&bytes).into_owned()
+    })
+}
+
+/// Write text, or base64-encoded bytes when `binary` is set
+#[cfg(not(target_os = "android"))]
+#[tauri::command]
+pub async fn write_serial(
+    app: AppHandle,
+    state: tauri::State<'_, crate::WikiModeState>,
+    id: u32,
+    data: String,
+    binary: Option<bool>,
+) -> Result<(), String> {
+    require_recorded_permission(&app, &this_wiki(&state))?;
+    let open = open_port(id)?;
+    let bytes = if binary.unwrap_or(false) {
+        base64::engine::general_purpose::STANDARD
+            .decode(data.as_bytes())
+            .map_err(|e| format!("Invalid base64 data: {}", e))?
+    } else {
+        data.into_bytes()
+    };
+    tokio::task::spawn_blocking(move || {
+        let mut writer = open.writer.lock().unwrap();
+        writer
+            .write_all(&bytes)
+            .and_then(|_| writer.flush())
+            .map_err(|e| format!("Failed to write serial port: {}", e))
+    })
+    .await
+    .map_err(|e| format!("Serial port request failed: {}", e))?
+}
+
+#[tauri::command]
+pub fn close_serial(id: u32) -> Result<(), String> {
+    PORTS.lock().unwrap().remove(&id);
+    Ok(())
+}
+
+/// Recorded serial port access of all wikis, keyed by wiki path
+#[tauri::command]
+pub fn get_serial_permissions(app: AppHandle) -> Result<HashMap<String, bool>, String> {
+    Ok(load_wiki_configs(&app)?.serial)
+}
+
+/// Allow or deny a wiki's serial port access (None = ask again)
+#[tauri::command]
+pub fn set_serial_permission(app: AppHandle, wiki_path: String, allowed: Option<bool>) -> Result<(), String> {
+    let mut configs = load_wiki_configs(&app)?;
+    match allowed {
+        Some(allowed) => {
+            configs.serial.insert(wiki_path, allowed);
+        }
+        None => {
+            configs.serial.remove(&wiki_path);
+        }
+    }
+    save_wiki_configs(&app, &configs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_settings() {
+        assert_eq!(line_settings(None, None, None), Ok((DataBits::Eight, Parity::None, StopBits::One)));
+        assert_eq!(
+            line_settings(Some(7), Some("even"), Some(2)),
+            Ok((DataBits::Seven, Parity::Even, StopBits::Two))
+        );
+        assert!(line_settings(Some(9), None, None).is_err());
+        assert!(line_settings(None, Some("mark"), None).is_err());
+        assert!(line_settings(None, None, Some(3)).is_err());
+    }
+}
@@ -0,0 +1,400 @@
+//! Headless server mode for always-on machines (`--serve-all`)
+//!
+//! Started with `--serve-all`, the main process opens no windows and no tray.
+//! It serves every folder wiki of the wiki list with the built-in folder
+//! server, starts LAN sync (and the relay rooms, as always) so the machine can
+//! anchor the sync mesh, and answers a small control API on port
+//! `--control-port` (default 8079):
+//! - `GET /status`: the served wikis with the URLs they're reachable at and
+//!   the sync status
+//! - `POST /reload`: serve wikis added to the list since, stop removed ones
+//! - `POST /shutdown`: stop serving and exit
+//!
+//! The wikis and the control API listen where `server_listen` in the app
+//! settings says (see `server_address`): all networks or an interface make
+//! them reachable from other machines.
+//!
+//! Requests need `Authorization: Bearer <token>`, with the token from the
+//! `control-token` file in the data directory (created on the first start).
+//! The same server answers the automation API (see `automation`), which the
+//! windowed app can also run on its own.
+//! On Linux the webview toolkit still needs a display; run it under
+//! `xvfb-run` on machines without one.
+//!
+//! SIGTERM and Ctrl+C shut it down like `POST /shutdown`, so service managers
+//! can stop it cleanly (`--install-service` sets one up, see `service`).
+
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::sync::{Arc, LazyLock, Mutex};
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+use tiny_http::{Header, Method, Request, Response, StatusCode};
+
+use crate::server_address::{self, Binding, Servers};
+
+/// Control API port when `--control-port` isn't given (below the wiki ports)
+const DEFAULT_CONTROL_PORT: u16 = 8079;
+
+/// File in the data directory with the control API token
+pub(crate) const TOKEN_FILE: &str = "control-token";
+
+/// Started with `--serve-all`
+static ACTIVE: LazyLock<bool> = LazyLock::new(|| std::env::args().any(|arg| arg == "--serve-all"));
+
+struct Served {
+    /// Reachable from other devices first, then 127.0.0.1
+    urls: Vec<String>,
+    server: Servers,
+}
+
+/// The running control API, to stop it when the automation API is turned off
+static CONTROL_SERVER: Mutex<Option<Servers>> = Mutex::new(None);
+
+/// Folder wikis being served, by path
+static SERVED: LazyLock<Mutex<BTreeMap<String, Served>>> = LazyLock::new(|| Mutex::new(BTreeMap::new()));
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ServedWiki {
+    path: String,
+    name: String,
+    /// The first of `urls`
+    url: String,
+    urls: Vec<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Status {
+    version: String,
+    wikis: Vec<ServedWiki>,
+    lan_sync: Option<crate::lan_sync::SyncStatus>,
+}
+
+/// Whether the app runs as a headless server
+pub fn is_active() -> bool {
+    *ACTIVE
+}
+
+/// `--control-port <port>`, or the default
+pub(crate) fn control_port(args: &[String]) -> u16 {
+    args.iter()
+        .position(|arg| arg == "--control-port")
+        .and_then(|i| args.get(i + 1))
+        .and_then(|port| port.parse().ok())
+        .unwrap_or(DEFAULT_CONTROL_PORT)
+}
+
+/// Whether an `Authorization` header carries the token
+fn is_authorized(authorization: Option<&str>, token: &str) -> bool {
+    authorization
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|given| is_token(given, token))
+}
+
+/// Whether `given` is the token, compared in constant time
+pub(crate) fn is_token(given: &str, token: &str) -> bool {
+    given.len() == token.len()
+        && given.bytes().zip(token.bytes()).fold(0u8, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+/// The control API token, created on the first start (also the password other
+/// devices write to the servers with, see `server_address`)
+pub(crate) fn load_or_create_token(data_dir: &Path) -> Result<String, String> {
+    let path = data_dir.join(TOKEN_FILE);
+    if let Ok(token) = std::fs::read_to_string(&path) {
+        let token = token.trim().to_string();
+        if !token.is_empty() {
+            // Also files created before they were made readable by the user only
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))
+                    .map_err(|e| format!("Failed to protect {}: {}", path.display(), e))?;
+            }
+            return Ok(token);
+        }
+    }
+    // An empty file is replaced; the new one is readable by the user only
+    // from the start, so the token is never readable by others
+    let _ = std::fs::remove_file(&path);
+    let token = format!("{:032x}", rand::random::<u128>());
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options
+        .open(&path)
+        .and_then(|mut file| file.write_all(token.as_bytes()))
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    Ok(token)
+}
+
+/// Serve the wiki list and start the control API (called from setup; LAN
+/// sync is started with the other sync subsystems)
+pub fn start(app: &AppHandle) {
+    eprintln!("[ServeAll] Running headless");
+    reload(app);
+
+    let args: Vec<String> = std::env::args().collect();
+    if let Err(e) = start_control_api(app, control_port(&args)) {
+        eprintln!("[ServeAll] Control API not available: {}", e);
+    }
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        wait_for_stop_signal().await;
+        eprintln!("[ServeAll] Stop signal received");
+        shutdown(&app);
+    });
+}
+
+/// Wait for SIGTERM (service managers) or Ctrl+C
+async fn wait_for_stop_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = terminate.recv() => {}
+                    _ = tokio::signal::ctrl_c() => {}
+                }
+            }
+            Err(e) => {
+                eprintln!("[ServeAll] Failed to listen for SIGTERM: {}", e);
+                let _ = tokio::signal::ctrl_c().await;
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
+
+/// Stop serving and exit
+fn shutdown(app: &AppHandle) {
+    for wiki in std::mem::take(&mut *SERVED.lock().unwrap()).values() {
+        wiki.server.unblock();
+    }
+    app.exit(0);
+}
+
+/// Serve the folder wikis of the wiki list that aren't served yet and stop
+/// the ones no longer in it
+fn reload(app: &AppHandle) {
+    let entries = crate::wiki_storage::load_recent_files_from_disk(app);
+    let wanted: Vec<String> = entries.iter().filter(|e| e.is_folder).map(|e| e.path.clone()).collect();
+
+    let mut served = SERVED.lock().unwrap();
+    served.retain(|path, wiki| {
+        let keep = wanted.contains(path);
+        if !keep {
+            wiki.server.unblock();
+            eprintln!("[ServeAll] Stopped serving {}", path);
+        }
+        keep
+    });
+
+    let tw_dir = match crate::get_tiddlywiki_path(app) {
+        Ok(tw_path) => tw_path.parent().map(Path::to_path_buf).unwrap_or_default(),
+        Err(e) => {
+            eprintln!("[ServeAll] {}", e);
+            return;
+        }
+    };
+    let webdav = crate::wiki_storage::load_app_settings(app).map(|s| s.folder_webdav).unwrap_or(false);
+    let binding = Binding::current(app);
+    for path in wanted {
+        if served.contains_key(&path) {
+            continue;
+        }
+        if !crate::utils::is_wiki_folder(Path::new(&path)) {
+            eprintln!("[ServeAll] Skipping {} (not a wiki folder or not reachable)", path);
+            continue;
+        }
+        let port = crate::allocate_port(&app.state::<crate::AppState>());
+        match crate::folder_server::start(Path::new(&path), &tw_dir, port, webdav, None, &binding) {
+            Ok(server) => {
+                let mut urls = binding.lan_urls(port);
+                urls.push(server_address::local_url(port));
+                served.insert(path, Served { urls, server });
+            }
+            Err(e) => eprintln!("[ServeAll] Failed to serve {}: {}", path, e),
+        }
+    }
+}
+
+fn status(app: &AppHandle) -> Status {
+    let wikis = SERVED
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(path, wiki)| ServedWiki {
+            path: path.clone(),
+            name: Path::new(path)
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_else(|| path.clone()),
+            url: wiki.urls[0].clone(),
+            urls: wiki.urls.clone(),
+        })
+        .collect();
+    let lan_sync = match crate::lan_sync::get_sync_manager() {
+        Some(mgr) => Some(tauri::async_runtime::block_on(mgr.get_status())),
+        None => None,
+    };
+    Status {
+        version: app.package_info().version.to_string(),
+        wikis,
+        lan_sync,
+    }
+}
+
+/// Where the control API token is kept
+pub(crate) fn token_path(app: &AppHandle) -> Result<std::path::PathBuf, String> {
+    Ok(crate::get_data_dir(app)?.join(TOKEN_FILE))
+}
+
+pub(crate) fn start_control_api(app: &AppHandle, port: u16) -> Result<(), String> {
+    let data_dir = crate::get_data_dir(app)?;
+    let token = Arc::new(load_or_create_token(&data_dir)?);
+    let binding = Binding::current(app);
+    let servers = binding.http(port)?;
+    eprintln!(
+        "[ServeAll] Control API at {} (token in {})",
+        server_address::local_url(port),
+        data_dir.join(TOKEN_FILE).display()
+    );
+    for url in binding.lan_urls(port) {
+        eprintln!("[ServeAll] Control API also at {}", url);
+    }
+
+    let app = app.clone();
+    servers.serve(move |request| {
+        // Requests to wikis wait for their windows; don't hold up the others
+        let app = app.clone();
+        let token = token.clone();
+        std::thread::spawn(move || {
+            if let Err(e) = handle_request(&app, &token, request) {
+                eprintln!("[ServeAll] {}", e);
+            }
+        });
+    });
+    *CONTROL_SERVER.lock().unwrap() = Some(servers);
+    Ok(())
+}
+
+/// Stop the control API
+pub(crate) fn stop_control_api() {
+    if let Some(servers) = CONTROL_SERVER.lock().unwrap().take() {
+        servers.unblock();
+    }
+}
+
+/// Whether the control API is running
+pub(crate) fn control_api_running() -> bool {
+    CONTROL_SERVER.lock().unwrap().is_some()
+}
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name.as_bytes(), value.as_bytes()).unwrap()
+}
+
+fn respond<R: Read>(request: Request, response: Response<R>) -> Result<(), String> {
+    request.respond(response).map_err(|e| format!("Failed to send response: {}", e))
+}
+
+pub(crate) fn respond_status(request: Request, status: u16, message: &str) -> Result<(), String> {
+    respond(request, Response::from_string(message).with_status_code(StatusCode(status)))
+}
+
+pub(crate) fn respond_json<T: Serialize>(request: Request, value: &T) -> Result<(), String> {
+    let body = serde_json::to_string(value).map_err(|e| e.to_string())?;
+    respond(request, Response::from_string(body).with_header(header("Content-Type", "application/json")))
+}
+
+fn handle_request(app: &AppHandle, token: &str, request: Request) -> Result<(), String> {
+    // Web pages that rebind their own host name to this computer
+    if !server_address::allows_host(&request) {
+        return respond_status(request, 403, "Unknown host");
+    }
+    let authorization = request
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv("Authorization"))
+        .map(|h| h.value.as_str().to_string());
+    if !is_authorized(authorization.as_deref(), token) {
+        return respond_status(request, 401, "Unauthorized");
+    }
+
+    let method = request.method().clone();
+    let url = request.url().split('?').next().unwrap_or("").to_string();
+    match (method, url.as_str()) {
+        (Method::Get, "/status") if is_active() => respond_json(request, &status(app)),
+        (Method::Post, "/reload") if is_active() => {
+            reload(app);
+            respond_json(request, &status(app))
+        }
+        (Method::Post, "/shutdown") if is_active() => {
+            respond_status(request, 202, "Shutting down")?;
+            eprintln!("[ServeAll] Shutdown requested");
+            shutdown(app);
+            Ok(())
+        }
+        #[cfg(not(target_os = "android"))]
+        _ => crate::automation::handle_request(app, request),
+        #[cfg(target_os = "android")]
+        _ => respond_status(request, 404, "Not Found"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_control_port() {
+        let args = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(control_port(&args(&["app", "--serve-all"])), DEFAULT_CONTROL_PORT);
+        assert_eq!(control_port(&args(&["app", "--serve-all", "--control-port", "9000"])), 9000);
+        assert_eq!(control_port(&args(&["app", "--control-port", "nope"])), DEFAULT_CONTROL_PORT);
+        assert_eq!(control_port(&args(&["app", "--control-port"])), DEFAULT_CONTROL_PORT);
+    }
+
+    #[test]
+    fn test_is_authorized() {
+        assert!(is_authorized(Some("Bearer abc123"), "abc123"));
+        assert!(!is_authorized(Some("Bearer abc124"), "abc123"));
+        assert!(!is_authorized(Some("Bearer abc"), "abc123"));
+        assert!(!is_authorized(Some("abc123"), "abc123"));
+        assert!(!is_authorized(None, "abc123"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_token_file_readable_by_user_only() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = std::env::temp_dir().join(format!("td-control-token-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let mode = || std::fs::metadata(dir.join(TOKEN_FILE)).unwrap().permissions().mode() & 0o777;
+
+        let token = load_or_create_token(&dir).unwrap();
+        assert_eq!(token.len(), 32);
+        assert_eq!(mode(), 0o600);
+
+        // A file left readable by others keeps its token
+        std::fs::set_permissions(dir.join(TOKEN_FILE), std::fs::Permissions::from_mode(0o644)).unwrap();
+        assert_eq!(load_or_create_token(&dir).unwrap(), token);
+        assert_eq!(mode(), 0o600);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,427 @@
+//! Where the built-in servers listen
+//!
+//! The folder wiki servers (with their WebDAV share), the servers of wikis
+//! opened in the system browser, `--serve-all` and the media server follow
+//! `server_listen` in the app settings:
+//! - `local` (default): 127.0.0.1 only
+//! - `all`: every IPv6 and IPv4 address with one dual-stack socket (IPv4
+//!   only where IPv6 is off), for phones and tablets on the LAN or a VPN
+//! - `interface`: 127.0.0.1 and the addresses of one network interface, each
+//!   with its own socket on the same port
+//!
+//! Windows always load the servers from 127.0.0.1, the only origin the
+//! capabilities and the CSP allow. Other devices use `Binding::lan_urls`, one
+//! per address, IPv6 ones in brackets. Link-local IPv6 addresses are left out
+//! since URLs can't carry their zone.
+//!
+//! Servers answer only requests whose `Host` is an IP address, localhost or
+//! this computer's name (`allows_host`), so web pages can't reach them
+//! through DNS rebinding.
+//!
+//! Other devices can read the served wikis, but changing them (saving a wiki,
+//! writing over WebDAV) needs a password (`Binding::allows_write`): the token
+//! in the `control-token` file of the data directory (see `serve_all`), as
+//! the password of HTTP Basic authentication with any user name, or as a
+//! Bearer token. Requests from this computer need none.
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener};
+use std::path::Path;
+use std::sync::Arc;
+
+use base64::Engine;
+use socket2::{Domain, Protocol, Socket, Type};
+use tiddlydesktop_core::types::ServerListen;
+use tiny_http::{Header, Method, Request, Response, Server, StatusCode};
+
+/// The addresses a server listens on, resolved from the setting when it starts
+#[derive(Clone, Debug, PartialEq)]
+pub struct Binding {
+    /// Addresses to bind, each with its own socket (`::` is dual-stack)
+    addresses: Vec<IpAddr>,
+    /// Addresses other devices can reach it at
+    lan_addresses: Vec<IpAddr>,
+    /// What other devices write with, when they can reach it
+    password: Option<String>,
+}
+
+impl Binding {
+    /// 127.0.0.1 only
+    pub fn local() -> Self {
+        Self {
+            addresses: vec![IpAddr::V4(Ipv4Addr::LOCALHOST)],
+            lan_addresses: Vec::new(),
+            password: None,
+        }
+    }
+
+    /// The binding for `listen`, given the addresses of the network
+    /// interfaces of this computer (by interface name)
+    fn resolve(listen: &ServerListen, interfaces: &[(String, IpAddr)]) -> Self {
+        let reachable = |ip: &IpAddr| !ip.is_loopback() && !is_ipv6_link_local(ip);
+        match listen {
+            ServerListen::Local => Self::local(),
+            ServerListen::All => Self {
+                addresses: vec![IpAddr::V6(Ipv6Addr::UNSPECIFIED)],
+                lan_addresses: interfaces.iter().map(|(_, ip)| *ip).filter(reachable).collect(),
+                password: None,
+            },
+            ServerListen::Interface(name) => {
+                let lan_addresses: Vec<IpAddr> = interfaces
+                    .iter()
+                    .filter(|(interface, _)| interface == name)
+                    .map(|(_, ip)| *ip)
+                    .filter(reachable)
+                    .collect();
+                if lan_addresses.is_empty() {
+                    eprintln!("[Servers] Interface {} has no address, listening on 127.0.0.1 only", name);
+                    return Self::local();
+                }
+                let mut addresses = vec![IpAddr::V4(Ipv4Addr::LOCALHOST)];
+                addresses.extend(&lan_addresses);
+                Self { addresses, lan_addresses, password: None }
+            }
+        }
+    }
+
+    /// The binding for the current setting and network interfaces
+    pub fn current(app: &tauri::AppHandle) -> Self {
+        let listen = crate::wiki_storage::load_app_settings(app)
+            .map(|settings| settings.server_listen)
+            .unwrap_or_default();
+        Self::for_listen(&listen).with_password(crate::get_data_dir(app).ok().as_deref())
+    }
+
+    /// `current`, with the settings read straight from the data directory,
+    /// for folder wiki processes starting their server before the app
+    #[cfg(not(target_os = "android"))]
+    pub fn from_data_dir() -> Self {
+        let data_dir = crate::portable_data_dir().or_else(crate::instance::system_data_dir);
+        let listen = data_dir
+            .clone()
+            .and_then(|dir| tiddlydesktop_core::storage::DataStore::new(dir).load_app_settings().ok())
+            .map(|settings| settings.server_listen)
+            .unwrap_or_default();
+        Self::for_listen(&listen).with_password(data_dir.as_deref())
+    }
+
+    fn for_listen(listen: &ServerListen) -> Self {
+        let interfaces: Vec<(String, IpAddr)> = if_addrs::get_if_addrs()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|interface| (interface.name.clone(), interface.ip()))
+            .collect();
+        Self::resolve(listen, &interfaces)
+    }
+
+    /// With the password, when other devices can reach the server (without
+    /// one they can't write)
+    fn with_password(mut self, data_dir: Option<&Path>) -> Self {
+        if !self.lan_addresses.is_empty() {
+            self.password = match data_dir.map(crate::serve_all::load_or_create_token) {
+                Some(Ok(password)) => Some(password),
+                Some(Err(e)) => {
+                    eprintln!("[Servers] No password for other devices, they can't write: {}", e);
+                    None
+                }
+                None => None,
+            };
+        }
+        self
+    }
+
+    /// Whether a request may change what the server serves: reads and
+    /// requests from this computer may, others need the password
+    pub fn allows_write(&self, request: &Request) -> bool {
+        if is_read(request.method()) || request.remote_addr().is_some_and(|addr| addr.ip().to_canonical().is_loopback()) {
+            return true;
+        }
+        let authorization = request
+            .headers()
+            .iter()
+            .find(|h| h.field.equiv("Authorization"))
+            .map(|h| h.value.as_str().to_string());
+        self.password
+            .as_deref()
+            .is_some_and(|password| has_password(authorization.as_deref(), password))
+    }
+
+    /// The address of the one socket covering the binding, for servers that
+    /// listen on a single address (Node.js); None with an interface
+    pub fn single_address(&self) -> Option<IpAddr> {
+        match self.addresses[..] {
+            [ip] => Some(ip),
+            _ => None,
+        }
+    }
+
+    /// Addresses other devices can reach the server at
+    pub fn lan_addresses(&self) -> &[IpAddr] {
+        &self.lan_addresses
+    }
+
+    /// URLs other devices reach the server on `port` at
+    pub fn lan_urls(&self, port: u16) -> Vec<String> {
+        self.lan_addresses.iter().map(|ip| url(*ip, port)).collect()
+    }
+
+    /// Listen on `port` on every address; with port 0, on the same free port.
+    /// Only 127.0.0.1 (or `::`) has to work: an interface address that can't
+    /// be bound is left out.
+    pub fn listen(&self, port: u16) -> Result<Vec<TcpListener>, String> {
+        let mut port = port;
+        let mut listeners = Vec::new();
+        for (i, ip) in self.addresses.iter().enumerate() {
+            let bound = if ip.is_unspecified() {
+                bind_dual_stack(port)
+            } else {
+                TcpListener::bind((*ip, port))
+            };
+            match bound {
+                Ok(listener) => {
+                    if let Ok(addr) = listener.local_addr() {
+                        port = addr.port();
+                    }
+                    listeners.push(listener);
+                }
+                Err(e) if i > 0 => eprintln!("[Servers] Failed to listen on {}: {}", SocketAddr::new(*ip, port), e),
+                Err(e) => return Err(format!("Failed to listen on port {}: {}", port, e)),
+            }
+        }
+        Ok(listeners)
+    }
+
+    /// HTTP servers on `port`, one per address
+    pub fn http(&self, port: u16) -> Result<Servers, String> {
+        let servers = self
+            .listen(port)?
+            .into_iter()
+            .map(|listener| {
+                Server::from_listener(listener, None)
+                    .map(Arc::new)
+                    .map_err(|e| format!("Failed to listen on port {}: {}", port, e))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Servers(servers))
+    }
+}
+
+/// The HTTP servers of one port, on each address of a binding
+pub struct Servers(Vec<Arc<Server>>);
+
+impl Servers {
+    /// Answer the requests to all of them with `handle`, on a thread per address
+    pub fn serve<F: Fn(Request) + Send + Sync + 'static>(&self, handle: F) {
+        let handle = Arc::new(handle);
+        for server in &self.0 {
+            let server = server.clone();
+            let handle = handle.clone();
+            std::thread::spawn(move || {
+                for request in server.incoming_requests() {
+                    handle(request);
+                }
+            });
+        }
+    }
+
+    /// Stop serving
+    pub fn unblock(&self) {
+        for server in &self.0 {
+            server.unblock();
+        }
+    }
+}
+
+/// Answer a request `Binding::allows_write` refused, asking for the password
+pub fn refuse_write(request: Request) -> Result<(), String> {
+    let response = Response::from_string("Changes from other devices need the password")
+        .with_status_code(StatusCode(401))
+        .with_header(Header::from_bytes(&b"WWW-Authenticate"[..], &b"Basic realm=\"TiddlyDesktop\""[..]).unwrap());
+    request.respond(response).map_err(|e| format!("Failed to send response: {}", e))
+}
+
+/// Methods that change nothing (WebDAV's `PROPFIND` included)
+fn is_read(method: &Method) -> bool {
+    matches!(method, Method::Get | Method::Head | Method::Options) || method.as_str() == "PROPFIND"
+}
+
+/// Whether an `Authorization` header carries the password, as Basic
+/// credentials (any user name) or a Bearer token
+fn has_password(authorization: Option<&str>, password: &str) -> bool {
+    let Some(authorization) = authorization else {
+        return false;
+    };
+    if let Some(token) = authorization.strip_prefix("Bearer ") {
+        return crate::serve_all::is_token(token, password);
+    }
+    authorization
+        .strip_prefix("Basic ")
+        .and_then(|encoded| base64::engine::general_purpose::STANDARD.decode(encoded.trim()).ok())
+        .and_then(|decoded| String::from_utf8(decoded).ok())
+        .and_then(|credentials| credentials.split_once(':').map(|(_, given)| crate::serve_all::is_token(given, password)))
+        .unwrap_or(false)
+}
+
+/// URL of the server on `port` at 127.0.0.1, for windows and the browser of this computer
+pub fn local_url(port: u16) -> String {
+    url(IpAddr::V4(Ipv4Addr::LOCALHOST), port)
+}
+
+fn url(ip: IpAddr, port: u16) -> String {
+    match ip {
+        IpAddr::V4(v4) => format!("http://{}:{}/", v4, port),
+        IpAddr::V6(v6) => format!("http://[{}]:{}/", v6, port),
+    }
+}
+
+fn is_ipv6_link_local(ip: &IpAddr) -> bool {
+    matches!(ip, IpAddr::V6(v6) if v6.segments()[0] & 0xffc0 == 0xfe80)
+}
+
+/// Listen on all IPv6 and IPv4 addresses, or on IPv4 only where IPv6 is off
+pub fn bind_dual_stack(port: u16) -> std::io::Result<TcpListener> {
+    let dual_stack = || -> std::io::Result<TcpListener> {
+        let socket = Socket::new(Domain::IPV6, Type::STREAM, Some(Protocol::TCP))?;
+        socket.set_only_v6(false)?;
+        // Like tokio's own bind, so a restarted server gets its port back
+        #[cfg(unix)]
+        socket.set_reuse_address(true)?;
+        socket.bind(&SocketAddr::from((Ipv6Addr::UNSPECIFIED, port)).into())?;
+        socket.listen(1024)?;
+        Ok(socket.into())
+    };
+    match dual_stack() {
+        Ok(listener) => Ok(listener),
+        Err(_) => TcpListener::bind((Ipv4Addr::UNSPECIFIED, port)),
+    }
+}
+
+/// This computer's name as a DNS label (lowercase, for `<name>.local`)
+pub fn host_name() -> Option<String> {
+    let name = hostname::get().ok()?.to_string_lossy().to_lowercase();
+    let label = name.split('.').next().unwrap_or_default().to_string();
+    (!label.is_empty()).then_some(label)
+}
+
+/// Whether a request may be answered (see the module docs)
+pub fn allows_host(request: &Request) -> bool {
+    let host = request
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv("Host"))
+        .map(|h| h.value.as_str().to_string());
+    allows_host_header(host.as_deref())
+}
+
+/// `allows_host` for servers parsing their requests themselves
+pub fn allows_host_header(host: Option<&str>) -> bool {
+    is_allowed_host(host, host_name().as_deref())
+}
+
+fn is_allowed_host(host: Option<&str>, own_name: Option<&str>) -> bool {
+    // HTTP/1.0 clients may leave it out; browsers always send it
+    let Some(host) = host else {
+        return true;
+    };
+    // IPv6 addresses are in brackets
+    if let Some(bracketed) = host.strip_prefix('[') {
+        return bracketed.split(']').next().is_some_and(|ip| ip.parse::<Ipv6Addr>().is_ok());
+    }
+    let name = host.split(':').next().unwrap_or_default().trim_end_matches('.').to_lowercase();
+    if name.parse::<Ipv4Addr>().is_ok() || name == "localhost" {
+        return true;
+    }
+    own_name.is_some_and(|own| name == own || name == format!("{}.local", own))
+}
+
+/// Where the built-in servers listen (`server_listen` in the app settings)
+#[tauri::command]
+pub fn get_server_listen(app: tauri::AppHandle) -> ServerListen {
+    crate::wiki_storage::load_app_settings(&app)
+        .map(|settings| settings.server_listen)
+        .unwrap_or_default()
+}
+
+/// Listen on `listen` from now on (servers started before keep their addresses)
+#[tauri::command]
+pub fn set_server_listen(app: tauri::AppHandle, listen: ServerListen) -> Result<ServerListen, String> {
+    let mut settings = crate::wiki_storage::load_app_settings(&app)?;
+    settings.server_listen = listen.clone();
+    crate::wiki_storage::save_app_settings(&app, &settings)?;
+    Ok(listen)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn interfaces() -> Vec<(String, IpAddr)> {
+        vec![
+            ("lo".to_string(), "127.0.0.1".parse().unwrap()),
+            ("eth0".to_string(), "192.168.1.5".parse().unwrap()),
+            ("eth0".to_string(), "fe80::1".parse().unwrap()),
+            ("eth0".to_string(), "2001:db8::5".parse().unwrap()),
+            ("wg0".to_string(), "10.8.0.2".parse().unwrap()),
+        ]
+    }
+
+    #[test]
+    fn test_resolve() {
+        assert_eq!(Binding::resolve(&ServerListen::Local, &interfaces()), Binding::local());
+
+        let all = Binding::resolve(&ServerListen::All, &interfaces());
+        assert_eq!(all.addresses, vec![IpAddr::V6(Ipv6Addr::UNSPECIFIED)]);
+        assert_eq!(
+            all.lan_urls(8080),
+            vec!["http://192.168.1.5:8080/", "http://[2001:db8::5]:8080/", "http://10.8.0.2:8080/"]
+        );
+
+        let vpn = Binding::resolve(&ServerListen::Interface("wg0".to_string()), &interfaces());
+        assert_eq!(vpn.addresses, vec![IpAddr::V4(Ipv4Addr::LOCALHOST), "10.8.0.2".parse().unwrap()]);
+        assert_eq!(vpn.lan_urls(8080), vec!["http://10.8.0.2:8080/"]);
+
+        // Gone (a VPN that is down): this computer only
+        assert_eq!(Binding::resolve(&ServerListen::Interface("tun9".to_string()), &interfaces()), Binding::local());
+    }
+
+    #[test]
+    fn test_is_allowed_host() {
+        let own = Some("desk");
+        assert!(is_allowed_host(None, own));
+        assert!(is_allowed_host(Some("127.0.0.1:8080"), own));
+        assert!(is_allowed_host(Some("192.168.1.5:8080"), own));
+        assert!(is_allowed_host(Some("[::1]:8080"), own));
+        assert!(is_allowed_host(Some("[2001:db8::5]:8080"), own));
+        assert!(is_allowed_host(Some("localhost:8080"), own));
+        assert!(is_allowed_host(Some("Desk.local.:8080"), own));
+        assert!(is_allowed_host(Some("desk:8080"), own));
+        assert!(!is_allowed_host(Some("attacker.example:8080"), own));
+        assert!(!is_allowed_host(Some("desk.attacker.example"), own));
+        assert!(!is_allowed_host(Some("[not-an-ip]:8080"), own));
+        assert!(!is_allowed_host(Some("desk.local"), None));
+    }
+
+    #[test]
+    fn test_has_password() {
+        // "anyone:s3cret" and "anyone:wrong"
+        assert!(has_password(Some("Basic YW55b25lOnMzY3JldA=="), "s3cret"));
+        assert!(!has_password(Some("Basic YW55b25lOndyb25n"), "s3cret"));
+        assert!(has_password(Some("Bearer s3cret"), "s3cret"));
+        assert!(!has_password(Some("Bearer s3cre"), "s3cret"));
+        assert!(!has_password(Some("Basic not base64"), "s3cret"));
+        assert!(!has_password(Some("s3cret"), "s3cret"));
+        assert!(!has_password(None, "s3cret"));
+    }
+
+    #[test]
+    fn test_listen_same_port() {
+        let binding = Binding {
+            addresses: vec![IpAddr::V4(Ipv4Addr::LOCALHOST), IpAddr::V6(Ipv6Addr::LOCALHOST)],
+            lan_addresses: Vec::new(),
+            password: None,
+        };
+        // ::1 may be missing (IPv6 off); then 127.0.0.1 alone is fine
+        let listeners = binding.listen(0).unwrap();
+        let ports: Vec<u16> = listeners.iter().map(|l| l.local_addr().unwrap().port()).collect();
+        assert!(ports.iter().all(|port| *port == ports[0]));
+    }
+}
@@ -0,0 +1,221 @@
+//! Finding the built-in servers from other devices
+//!
+//! When the servers listen beyond this computer (see `server_address`), each
+//! folder wiki server and each wiki served to the browser is advertised over
+//! mDNS/DNS-SD, so phones and tablets on the LAN find it without typing
+//! addresses: as `_http._tcp`, and the WebDAV share of folder wikis as
+//! `_webdav._tcp`, with the path in the TXT record. Instance names are
+//! "<wiki> on <computer>", the same on every start, so clients that
+//! remember a service find it again. Wikis of the same name served by this
+//! process get "(2)", "(3)"... appended; clashes with other processes and
+//! devices are resolved by mDNS probing.
+//!
+//! `get_server_urls` lists the URLs other devices can use for a wiki served
+//! by this process, and `get_server_qr_png` draws the first one as a QR code
+//! for a phone's camera.
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{LazyLock, Mutex};
+
+use mdns_sd::{ServiceDaemon, ServiceInfo};
+
+use crate::server_address::{self, Binding};
+
+const HTTP_SERVICE: &str = "_http._tcp.local.";
+const WEBDAV_SERVICE: &str = "_webdav._tcp.local.";
+
+/// Longest DNS-SD instance name, in bytes
+const MAX_INSTANCE_NAME: usize = 63;
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// A wiki this process serves
+struct Served {
+    wiki_path: String,
+    /// Other devices open it with these
+    urls: Vec<String>,
+    /// Its instance name, when advertised
+    name: Option<String>,
+}
+
+/// The served wikis, by announcement
+static SERVED: LazyLock<Mutex<HashMap<u64, Served>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Started with the first advertised server
+static DAEMON: LazyLock<Option<ServiceDaemon>> = LazyLock::new(|| match ServiceDaemon::new() {
+    Ok(daemon) => Some(daemon),
+    Err(e) => {
+        eprintln!("[Discovery] mDNS not available: {}", e);
+        None
+    }
+});
+
+/// A served wiki, advertised until dropped
+pub struct Announcement {
+    id: u64,
+    /// Full names of its mDNS services
+    services: Vec<String>,
+}
+
+impl Drop for Announcement {
+    fn drop(&mut self) {
+        SERVED.lock().unwrap().remove(&self.id);
+        if let Some(daemon) = DAEMON.as_ref() {
+            for service in &self.services {
+                let _ = daemon.unregister(service);
+            }
+        }
+    }
+}
+
+/// "<wiki> on <computer>", cut to what DNS-SD allows
+fn instance_name(wiki_path: &str, host: &str) -> String {
+    let file_name = Path::new(wiki_path)
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| wiki_path.to_string());
+    let wiki = file_name
+        .strip_suffix(".html")
+        .or_else(|| file_name.strip_suffix(".htm"))
+        .unwrap_or(&file_name);
+    let mut name = format!("{} on {}", wiki, host);
+    while name.len() > MAX_INSTANCE_NAME {
+        name.pop();
+    }
+    name
+}
+
+/// `name`, or with " (2)", " (3)"... appended when it's taken (DNS names
+/// ignore case)
+fn unique_name(name: &str, taken: &[&str]) -> String {
+    let is_taken = |candidate: &str| taken.iter().any(|t| t.eq_ignore_ascii_case(candidate));
+    let mut candidate = name.to_string();
+    let mut index = 2;
+    while is_taken(&candidate) {
+        let suffix = format!(" ({})", index);
+        let mut base = name.to_string();
+        while base.len() + suffix.len() > MAX_INSTANCE_NAME {
+            base.pop();
+        }
+        candidate = base + &suffix;
+        index += 1;
+    }
+    candidate
+}
+
+/// Advertise a service, returning its full name
+fn advertise(service_type: &str, name: &str, addresses: &[IpAddr], port: u16, path: &str) -> Option<String> {
+    let daemon = DAEMON.as_ref()?;
+    let host = format!("{}.local.", server_address::host_name()?);
+    let addresses = addresses.iter().map(IpAddr::to_string).collect::<Vec<_>>().join(",");
+    let properties = [("path", path)];
+    let registered = ServiceInfo::new(service_type, name, &host, addresses.as_str(), port, &properties[..])
+        .and_then(|info| {
+            let fullname = info.get_fullname().to_string();
+            daemon.register(info).map(|_| fullname)
+        });
+    match registered {
+        Ok(fullname) => Some(fullname),
+        Err(e) => {
+            eprintln!("[Discovery] Failed to advertise {}: {}", name, e);
+            None
+        }
+    }
+}
+
+/// Record the server of `wiki_path` on `port` (the wiki at `path` on it) and
+/// advertise it, with its WebDAV share if `dav` is set, when other devices
+/// can reach it
+pub fn announce(binding: &Binding, wiki_path: &str, port: u16, path: &str, dav: bool) -> Announcement {
+    let urls: Vec<String> = binding
+        .lan_urls(port)
+        .iter()
+        .map(|url| format!("{}{}", url.trim_end_matches('/'), path))
+        .collect();
+    let host = server_address::host_name().filter(|_| !urls.is_empty());
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    // Taken with the entry, so two wikis of the same name can't both get it
+    let name = {
+        let mut served = SERVED.lock().unwrap();
+        let name = host.map(|host| {
+            let taken: Vec<&str> = served.values().filter_map(|s| s.name.as_deref()).collect();
+            unique_name(&instance_name(wiki_path, &host), &taken)
+        });
+        served.insert(id, Served { wiki_path: wiki_path.to_string(), urls, name: name.clone() });
+        name
+    };
+    let mut services = Vec::new();
+    if let Some(name) = name {
+        services.extend(advertise(HTTP_SERVICE, &name, binding.lan_addresses(), port, path));
+        if dav {
+            let dav_path = format!("{}/", crate::webdav::DAV_PREFIX);
+            services.extend(advertise(WEBDAV_SERVICE, &name, binding.lan_addresses(), port, &dav_path));
+        }
+        if !services.is_empty() {
+            eprintln!("[Discovery] Advertising \"{}\" on port {}", name, port);
+        }
+    }
+    Announcement { id, services }
+}
+
+/// LAN URLs of a wiki this process serves
+pub fn urls(wiki_path: &str) -> Vec<String> {
+    SERVED
+        .lock()
+        .unwrap()
+        .values()
+        .find(|served| crate::utils::paths_equal(&served.wiki_path, wiki_path))
+        .map(|served| served.urls.clone())
+        .unwrap_or_default()
+}
+
+/// The first LAN URL of a wiki this process serves
+pub fn first_url(wiki_path: &str) -> Result<String, String> {
+    urls(wiki_path).into_iter().next().ok_or_else(|| {
+        "The wiki isn't served to other devices (the built-in servers listen on this computer only)".to_string()
+    })
+}
+
+/// URLs other devices can open the wiki at `wiki_path` with, if this process
+/// serves it to them
+#[tauri::command]
+pub fn get_server_urls(wiki_path: String) -> Vec<String> {
+    urls(&wiki_path)
+}
+
+/// PNG of a QR code of the first URL other devices can open the wiki at
+/// `wiki_path` with
+#[tauri::command]
+pub fn get_server_qr_png(wiki_path: String, module_size: Option<u32>) -> Result<tauri::ipc::Response, String> {
+    crate::qr_code::qr_png(&first_url(&wiki_path)?, module_size).map(tauri::ipc::Response::new)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_instance_name() {
+        assert_eq!(instance_name("/home/me/Notes.html", "desk"), "Notes on desk");
+        assert_eq!(instance_name("/home/me/Projects", "desk"), "Projects on desk");
+        assert_eq!(instance_name("/home/me/Work.htm", "desk"), "Work on desk");
+        let long = instance_name(&format!("/w/{}", "ä".repeat(40)), "desk");
+        assert!(long.len() <= MAX_INSTANCE_NAME);
+        assert!(long.starts_with("ää"));
+    }
+
+    #[test]
+    fn test_unique_name() {
+        assert_eq!(unique_name("Notes on desk", &[]), "Notes on desk");
+        assert_eq!(unique_name("Notes on desk", &["Work on desk"]), "Notes on desk");
+        assert_eq!(unique_name("Notes on desk", &["notes on desk"]), "Notes on desk (2)");
+        assert_eq!(unique_name("Notes on desk", &["Notes on desk", "Notes on desk (2)"]), "Notes on desk (3)");
+        let long = "n".repeat(MAX_INSTANCE_NAME);
+        let second = unique_name(&long, &[long.as_str()]);
+        assert_eq!(second.len(), MAX_INSTANCE_NAME);
+        assert!(second.ends_with(" (2)"));
+    }
+}
@@ -0,0 +1,343 @@
+//! Running the headless server mode as a service (desktop)
+//!
+//! `--install-service` sets up the service manager of the platform to start
+//! `--serve-all` (see `serve_all`) and keep it running; `--uninstall-service`
+//! removes it again. A `--control-port <port>` given along is passed on.
+//! - Linux: a systemd user unit, logging to the journal
+//!   (`journalctl --user -u tiddlydesktop-rs`). It runs under `xvfb-run` when
+//!   that is installed, as user services have no display.
+//! - macOS: a launchd agent, logging to `~/Library/Logs/tiddlydesktop-rs/`
+//! - Windows: a scheduled task started at logon, through a small command
+//!   script that appends the output to `serve-all.log` next to it
+//!
+//! The service managers stop it with SIGTERM (Ctrl+C on Windows), which
+//! `serve_all` answers by stopping the servers and exiting.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Name of the systemd unit and the Windows task
+const SERVICE_NAME: &str = "tiddlydesktop-rs";
+
+/// Label of the launchd agent
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+const LAUNCHD_LABEL: &str = "com.burningtreec.tiddlydesktop-rs.serve-all";
+
+/// Seconds the service manager waits for a clean shutdown
+const STOP_TIMEOUT_SECS: u32 = 30;
+
+/// Arguments of the service: `--serve-all` and the control port, if given
+fn service_args(args: &[String]) -> Vec<String> {
+    let mut service_args = vec!["--serve-all".to_string()];
+    if let Some(i) = args.iter().position(|arg| arg == "--control-port") {
+        if let Some(port) = args.get(i + 1).filter(|port| port.parse::<u16>().is_ok()) {
+            service_args.push("--control-port".to_string());
+            service_args.push(port.clone());
+        }
+    }
+    service_args
+}
+
+/// A word of a systemd command line
+fn systemd_quote(word: &str) -> String {
+    if word.chars().any(|c| c.is_whitespace() || c == '"' || c == '\\' || c == '\'') {
+        format!("\"{}\"", word.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        word.to_string()
+    }
+}
+
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn systemd_unit(exe: &Path, args: &[String], xvfb_run: Option<&Path>) -> String {
+    let mut command: Vec<String> = Vec::new();
+    if let Some(xvfb_run) = xvfb_run {
+        command.push(systemd_quote(&xvfb_run.to_string_lossy()));
+        command.push("-a".to_string());
+    }
+    command.push(systemd_quote(&exe.to_string_lossy()));
+    command.extend(args.iter().map(|arg| systemd_quote(arg)));
+    format!(
+        "[Unit]\n\
+         Description=TiddlyDesktopRS headless server (folder wikis, sync)\n\
+         \n\
+         [Service]\n\
+         Type=simple\n\
+         ExecStart={}\n\
+         Restart=on-failure\n\
+         RestartSec=5\n\
+         KillSignal=SIGTERM\n\
+         TimeoutStopSec={}\n\
+         StandardOutput=journal\n\
+         StandardError=journal\n\
+         SyslogIdentifier={}\n\
+         \n\
+         [Install]\n\
+         WantedBy=default.target\n",
+        command.join(" "),
+        STOP_TIMEOUT_SECS,
+        SERVICE_NAME,
+    )
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+fn launchd_plist(exe: &Path, args: &[String], log: &Path) -> String {
+    let program: String = std::iter::once(exe.to_string_lossy().into_owned())
+        .chain(args.iter().cloned())
+        .map(|arg| format!("        <string>{}</string>\n", xml_escape(&arg)))
+        .collect();
+    let log = xml_escape(&log.to_string_lossy());
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n\
+         <plist version=\"1.0\">\n\
+         <dict>\n\
+         \x20   <key>Label</key>\n\
+         \x20   <string>{}</string>\n\
+         \x20   <key>ProgramArguments</key>\n\
+         \x20   <array>\n\
+         {}\
+         \x20   </array>\n\
+         \x20   <key>RunAtLoad</key>\n\
+         \x20   <true/>\n\
+         \x20   <key>KeepAlive</key>\n\
+         \x20   <dict>\n\
+         \x20       <key>SuccessfulExit</key>\n\
+         \x20       <false/>\n\
+         \x20   </dict>\n\
+         \x20   <key>ExitTimeOut</key>\n\
+         \x20   <integer>{}</integer>\n\
+         \x20   <key>StandardOutPath</key>\n\
+         \x20   <string>{}</string>\n\
+         \x20   <key>StandardErrorPath</key>\n\
+         \x20   <string>{}</string>\n\
+         </dict>\n\
+         </plist>\n",
+        LAUNCHD_LABEL, program, STOP_TIMEOUT_SECS, log, log,
+    )
+}
+
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+fn windows_script(exe: &Path, args: &[String], log: &Path) -> String {
+    format!(
+        "@echo off\r\n\"{}\" {} >> \"{}\" 2>&1\r\n",
+        exe.display(),
+        args.join(" "),
+        log.display(),
+    )
+}
+
+/// Run a command of the service manager, failing on a non-zero exit
+fn run(program: &str, args: &[&str]) -> Result<(), String> {
+    let status = Command::new(program)
+        .args(args)
+        .status()
+        .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("{} {} failed ({})", program, args.join(" "), status))
+    }
+}
+
+fn write_file(path: &Path, content: &str) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    std::fs::write(path, content).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// First `program` on the PATH
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn find_on_path(program: &str) -> Option<PathBuf> {
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .map(|dir| dir.join(program))
+        .find(|path| path.is_file())
+}
+
+#[cfg(target_os = "linux")]
+fn unit_path() -> Result<PathBuf, String> {
+    let config = dirs::config_dir().ok_or("No configuration directory")?;
+    Ok(config.join("systemd").join("user").join(format!("{}.service", SERVICE_NAME)))
+}
+
+#[cfg(target_os = "linux")]
+fn install(exe: &Path, args: &[String]) -> Result<String, String> {
+    let xvfb_run = find_on_path("xvfb-run");
+    let path = unit_path()?;
+    write_file(&path, &systemd_unit(exe, args, xvfb_run.as_deref()))?;
+    run("systemctl", &["--user", "daemon-reload"])?;
+    let unit = format!("{}.service", SERVICE_NAME);
+    run("systemctl", &["--user", "enable", "--now", &unit])?;
+    let mut message = format!(
+        "Installed {}\nLogs: journalctl --user -u {}\nTo keep it running while logged out: loginctl enable-linger",
+        path.display(),
+        SERVICE_NAME
+    );
+    if xvfb_run.is_none() {
+        message.push_str("\nxvfb-run wasn't found: without a display session the service can't start (install xvfb)");
+    }
+    Ok(message)
+}
+
+#[cfg(target_os = "linux")]
+fn uninstall() -> Result<String, String> {
+    let path = unit_path()?;
+    let unit = format!("{}.service", SERVICE_NAME);
+    if let Err(e) = run("systemctl", &["--user", "disable", "--now", &unit]) {
+        eprintln!("{}", e);
+    }
+    if path.exists() {
+        std::fs::remove_file(&path).map_err(|e| format!("Failed to remove {}: {}", path.display(), e))?;
+    }
+    run("systemctl", &["--user", "daemon-reload"])?;
+    Ok(format!("Removed {}", path.display()))
+}
+
+#[cfg(target_os = "macos")]
+fn plist_path() -> Result<PathBuf, String> {
+    let home = dirs::home_dir().ok_or("No home directory")?;
+    Ok(home.join("Library").join("LaunchAgents").join(format!("{}.plist", LAUNCHD_LABEL)))
+}
+
+#[cfg(target_os = "macos")]
+fn install(exe: &Path, args: &[String]) -> Result<String, String> {
+    let home = dirs::home_dir().ok_or("No home directory")?;
+    let log_dir = home.join("Library").join("Logs").join(SERVICE_NAME);
+    std::fs::create_dir_all(&log_dir).map_err(|e| format!("Failed to create {}: {}", log_dir.display(), e))?;
+    let log = log_dir.join("serve-all.log");
+    let path = plist_path()?;
+    // Replace an agent installed before
+    if path.exists() {
+        let _ = run("launchctl", &["unload", "-w", &path.to_string_lossy()]);
+    }
+    write_file(&path, &launchd_plist(exe, args, &log))?;
+    run("launchctl", &["load", "-w", &path.to_string_lossy()])?;
+    Ok(format!("Installed {}\nLogs: {}", path.display(), log.display()))
+}
+
+#[cfg(target_os = "macos")]
+fn uninstall() -> Result<String, String> {
+    let path = plist_path()?;
+    if path.exists() {
+        if let Err(e) = run("launchctl", &["unload", "-w", &path.to_string_lossy()]) {
+            eprintln!("{}", e);
+        }
+        std::fs::remove_file(&path).map_err(|e| format!("Failed to remove {}: {}", path.display(), e))?;
+    }
+    Ok(format!("Removed {}", path.display()))
+}
+
+#[cfg(target_os = "windows")]
+fn script_dir() -> Result<PathBuf, String> {
+    Ok(dirs::data_local_dir().ok_or("No local data directory")?.join(SERVICE_NAME))
+}
+
+#[cfg(target_os = "windows")]
+fn install(exe: &Path, args: &[String]) -> Result<String, String> {
+    let dir = script_dir()?;
+    let script = dir.join("serve-all.cmd");
+    let log = dir.join("serve-all.log");
+    write_file(&script, &windows_script(exe, args, &log))?;
+    let task_command = format!("\"{}\"", script.display());
+    run("schtasks", &["/Create", "/F", "/SC", "ONLOGON", "/RL", "LIMITED", "/TN", SERVICE_NAME, "/TR", &task_command])?;
+    run("schtasks", &["/Run", "/TN", SERVICE_NAME])?;
+    Ok(format!("Installed the scheduled task {}\nLogs: {}", SERVICE_NAME, log.display()))
+}
+
+#[cfg(target_os = "windows")]
+fn uninstall() -> Result<String, String> {
+    let _ = run("schtasks", &["/End", "/TN", SERVICE_NAME]);
+    run("schtasks", &["/Delete", "/F", "/TN", SERVICE_NAME])?;
+    let script = script_dir()?.join("serve-all.cmd");
+    let _ = std::fs::remove_file(&script);
+    Ok(format!("Removed the scheduled task {}", SERVICE_NAME))
+}
+
+/// Handle `--install-service` and `--uninstall-service`; returns the exit
+/// code when one of them was given
+pub fn run_cli() -> Option<i32> {
+    let args: Vec<String> = std::env::args().collect();
+    let result = if args.iter().any(|arg| arg == "--install-service") {
+        std::env::current_exe()
+            .map_err(|e| format!("Failed to get executable path: {}", e))
+            .and_then(|exe| install(&exe, &service_args(&args)))
+    } else if args.iter().any(|arg| arg == "--uninstall-service") {
+        uninstall()
+    } else {
+        return None;
+    };
+    match result {
+        Ok(message) => {
+            println!("{}", message);
+            Some(0)
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            Some(1)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_service_args() {
+        assert_eq!(service_args(&args(&["app", "--install-service"])), args(&["--serve-all"]));
+        assert_eq!(
+            service_args(&args(&["app", "--install-service", "--control-port", "9000"])),
+            args(&["--serve-all", "--control-port", "9000"])
+        );
+        assert_eq!(service_args(&args(&["app", "--install-service", "--control-port", "x"])), args(&["--serve-all"]));
+    }
+
+    #[test]
+    fn test_systemd_unit() {
+        let unit = systemd_unit(
+            Path::new("/opt/Tiddly Desktop/tiddlydesktop-rs"),
+            &args(&["--serve-all"]),
+            Some(Path::new("/usr/bin/xvfb-run")),
+        );
+        assert!(unit.contains("ExecStart=/usr/bin/xvfb-run -a \"/opt/Tiddly Desktop/tiddlydesktop-rs\" --serve-all\n"));
+        assert!(unit.contains("KillSignal=SIGTERM\n"));
+        assert!(unit.contains("WantedBy=default.target\n"));
+
+        let unit = systemd_unit(Path::new("/usr/bin/tiddlydesktop-rs"), &args(&["--serve-all"]), None);
+        assert!(unit.contains("ExecStart=/usr/bin/tiddlydesktop-rs --serve-all\n"));
+    }
+
+    #[test]
+    fn test_launchd_plist() {
+        let plist = launchd_plist(
+            Path::new("/Applications/TiddlyDesktop & Co.app/Contents/MacOS/tiddlydesktop-rs"),
+            &args(&["--serve-all"]),
+            Path::new("/Users/me/Library/Logs/tiddlydesktop-rs/serve-all.log"),
+        );
+        assert!(plist.contains("<string>/Applications/TiddlyDesktop &amp; Co.app/Contents/MacOS/tiddlydesktop-rs</string>"));
+        assert!(plist.contains("        <string>--serve-all</string>\n"));
+        assert!(plist.contains(&format!("<string>{}</string>", LAUNCHD_LABEL)));
+        assert!(plist.contains("<key>StandardErrorPath</key>"));
+    }
+
+    #[test]
+    fn test_windows_script() {
+        let script = windows_script(
+            Path::new(r"C:\Program Files\tiddlydesktop-rs\tiddlydesktop-rs.exe"),
+            &args(&["--serve-all"]),
+            Path::new(r"C:\Users\me\AppData\Local\tiddlydesktop-rs\serve-all.log"),
+        );
+        assert_eq!(
+            script,
+            "@echo off\r\n\"C:\\Program Files\\tiddlydesktop-rs\\tiddlydesktop-rs.exe\" --serve-all >> \"C:\\Users\\me\\AppData\\Local\\tiddlydesktop-rs\\serve-all.log\" 2>&1\r\n"
+        );
+    }
+}
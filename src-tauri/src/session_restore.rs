@@ -0,0 +1,228 @@
+//! Reopening the wikis that were open when the app last quit
+//!
+//! The main process keeps the set of running wiki processes in
+//! `<data_dir>/open_wikis.json`, rewritten whenever one starts or exits.
+//! Quitting from the tray marks the file as a clean shutdown and keeps the set
+//! (closing the wikis there isn't the user closing them). A file without that
+//! mark was left by a crash, power loss or logout, and lists what was open then.
+//!
+//! On the next launch, the wikis of that session that aren't running anymore
+//! (wikis that outlived the main process are adopted instead, see
+//! `process_registry`) are reopened, or offered on the landing page, depending
+//! on the app setting `restore_session`. Their windows come back where they
+//! were, from the saved window state.
+
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use tauri::{Emitter, Manager};
+
+use crate::types::SessionRestore;
+use crate::utils;
+
+const SESSION_FILE: &str = "open_wikis.json";
+
+/// A wiki that was open
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionWiki {
+    pub path: String,
+    pub is_folder: bool,
+}
+
+/// The wikis open in a session, as written to `open_wikis.json`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Session {
+    pub wikis: Vec<SessionWiki>,
+    /// Quit from the tray (false: the app went down while wikis were open)
+    #[serde(default)]
+    pub clean_exit: bool,
+}
+
+/// The previous session's wikis that can still be reopened
+static LAST_SESSION: Mutex<Option<Session>> = Mutex::new(None);
+
+/// Set when quitting, so the wikis closed on the way out stay in the session
+static QUITTING: AtomicBool = AtomicBool::new(false);
+
+fn read(app: &tauri::AppHandle) -> Option<Session> {
+    let data_dir = crate::get_data_dir(app).ok()?;
+    let content = std::fs::read_to_string(data_dir.join(SESSION_FILE)).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+fn write(app: &tauri::AppHandle, session: &Session) {
+    let result = crate::get_data_dir(app).and_then(|data_dir| {
+        let json = serde_json::to_string(session).map_err(|e| e.to_string())?;
+        std::fs::write(data_dir.join(SESSION_FILE), json).map_err(|e| e.to_string())
+    });
+    if let Err(e) = result {
+        eprintln!("[TiddlyDesktop] Failed to save open wikis: {}", e);
+    }
+}
+
+/// The running wikis
+fn current(app: &tauri::AppHandle) -> Vec<SessionWiki> {
+    let Some(state) = app.try_state::<crate::AppState>() else {
+        return Vec::new();
+    };
+    let mut wikis: Vec<SessionWiki> = state
+        .wiki_processes
+        .lock()
+        .unwrap()
+        .keys()
+        .map(|path| SessionWiki { path: path.clone(), is_folder: Path::new(path).is_dir() })
+        .collect();
+    wikis.sort_by(|a, b| a.path.cmp(&b.path));
+    wikis
+}
+
+/// The wikis of a session that still exist and aren't open
+fn reopenable(session: &Session, is_open: impl Fn(&str) -> bool, exists: impl Fn(&str) -> bool) -> Vec<SessionWiki> {
+    session
+        .wikis
+        .iter()
+        .filter(|wiki| !is_open(&wiki.path) && exists(&wiki.path))
+        .cloned()
+        .collect()
+}
+
+fn restore_mode(app: &tauri::AppHandle) -> SessionRestore {
+    crate::wiki_storage::load_app_settings(app)
+        .map(|settings| settings.restore_session)
+        .unwrap_or_default()
+}
+
+/// Read the previous session before anything changes it.
+/// Called once by the main process at startup, before adopting running wikis.
+pub fn load_last(app: &tauri::AppHandle) {
+    let Some(session) = read(app).filter(|s| !s.wikis.is_empty()) else {
+        return;
+    };
+    if !session.clean_exit {
+        eprintln!("[TiddlyDesktop] The last session ended with {} wiki(s) open", session.wikis.len());
+    }
+    *LAST_SESSION.lock().unwrap() = Some(session);
+}
+
+/// Reopen the previous session's wikis, or keep them for the landing page to
+/// offer, per `restore_session`. Called once after adopting running wikis.
+pub fn start(app: &tauri::AppHandle) {
+    update(app);
+    match restore_mode(app) {
+        SessionRestore::Always => restore_in_background(app),
+        SessionRestore::Never => *LAST_SESSION.lock().unwrap() = None,
+        SessionRestore::Ask => {}
+    }
+}
+
+/// Save the running wikis (a wiki process started or exited)
+pub fn update(app: &tauri::AppHandle) {
+    if QUITTING.load(Ordering::SeqCst) {
+        return;
+    }
+    write(app, &Session { wikis: current(app), clean_exit: false });
+}
+
+/// Save the running wikis as the session to restore (quitting from the tray)
+pub fn mark_quit(app: &tauri::AppHandle) {
+    if QUITTING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    write(app, &Session { wikis: current(app), clean_exit: true });
+}
+
+/// The previous session, for the landing page
+#[derive(Debug, Clone, Serialize)]
+pub struct LastSession {
+    /// The wikis that can be reopened
+    pub wikis: Vec<SessionWiki>,
+    /// The app didn't quit normally
+    pub crashed: bool,
+    pub mode: SessionRestore,
+}
+
+/// The previous session's wikis that can be reopened (none once restored or dismissed)
+#[tauri::command]
+pub fn get_last_session(app: tauri::AppHandle) -> LastSession {
+    let last = LAST_SESSION.lock().unwrap().clone().unwrap_or_default();
+    let state = app.state::<crate::AppState>();
+    let wikis = {
+        let open = state.wiki_processes.lock().unwrap();
+        reopenable(
+            &last,
+            |path| open.keys().any(|p| utils::paths_equal(p, path)),
+            |path| Path::new(path).exists(),
+        )
+    };
+    LastSession { wikis, crashed: !last.clean_exit && !last.wikis.is_empty(), mode: restore_mode(&app) }
+}
+
+/// Reopen the previous session's wikis. Returns how many were opened.
+#[tauri::command]
+pub async fn restore_last_session(app: tauri::AppHandle) -> Result<usize, String> {
+    let wikis = get_last_session(app.clone()).wikis;
+    *LAST_SESSION.lock().unwrap() = None;
+    let mut opened = 0;
+    for wiki in wikis {
+        let result = if wiki.is_folder {
+            crate::open_wiki_folder(app.clone(), wiki.path.clone(), None).await
+        } else {
+            crate::open_wiki_window(app.clone(), wiki.path.clone(), None, None, None).await
+        };
+        match result {
+            Ok(entry) => {
+                let _ = app.emit("wiki-list-changed", &entry);
+                opened += 1;
+            }
+            Err(e) => eprintln!("[TiddlyDesktop] Failed to reopen {}: {}", wiki.path, e),
+        }
+    }
+    Ok(opened)
+}
+
+fn restore_in_background(app: &tauri::AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        match restore_last_session(app).await {
+            Ok(0) => {}
+            Ok(count) => eprintln!("[TiddlyDesktop] Reopened {} wiki(s) of the last session", count),
+            Err(e) => eprintln!("[TiddlyDesktop] Failed to restore the last session: {}", e),
+        }
+    });
+}
+
+/// Don't offer the previous session's wikis again
+#[tauri::command]
+pub fn dismiss_last_session() {
+    *LAST_SESSION.lock().unwrap() = None;
+}
+
+/// Set whether the wikis open at quit are reopened on the next launch
+#[tauri::command]
+pub fn set_session_restore_mode(app: tauri::AppHandle, mode: SessionRestore) -> Result<(), String> {
+    let mut settings = crate::wiki_storage::load_app_settings(&app)?;
+    settings.restore_session = mode;
+    crate::wiki_storage::save_app_settings(&app, &settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wiki(path: &str) -> SessionWiki {
+        SessionWiki { path: path.to_string(), is_folder: false }
+    }
+
+    #[test]
+    fn test_reopenable() {
+        let session = Session { wikis: vec![wiki("/a.html"), wiki("/b.html"), wiki("/gone.html")], clean_exit: true };
+        let wikis = reopenable(&session, |path| path == "/a.html", |path| path != "/gone.html");
+        assert_eq!(wikis, [wiki("/b.html")]);
+
+        // Files from before the clean-exit mark count as crashed sessions
+        let session: Session = serde_json::from_str(r#"{"wikis":[{"path":"/a.html","is_folder":false}]}"#).unwrap();
+        assert!(!session.clean_exit);
+    }
+}
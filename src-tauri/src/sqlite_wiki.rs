@@ -0,0 +1,42 @@
+//! Import and export of SQLite wikis
+//!
+//! SQLite wikis are wiki folders whose tiddlers live in a database (see
+//! `tiddlydesktop_core::sqlite_wiki`). They open like other folder wikis and
+//! are always served by `folder_server`.
+
+use tiddlydesktop_core::sqlite_wiki;
+use tiddlydesktop_core::wiki_folder::TiddlyWikiInstall;
+
+/// Create a SQLite wiki in `folder_path` from the tiddlers of a single-file
+/// wiki. Returns the number of imported tiddlers.
+#[tauri::command]
+pub async fn import_sqlite_wiki(html_path: String, folder_path: String) -> Result<usize, String> {
+    let html_path = crate::drag_drop::sanitize::validate_wiki_path(&html_path)?;
+    let folder_path = crate::drag_drop::sanitize::validate_user_directory_path(&folder_path)?;
+
+    tokio::task::spawn_blocking(move || {
+        let html = std::fs::read_to_string(&html_path)
+            .map_err(|e| format!("Failed to read {}: {}", html_path.display(), e))?;
+        sqlite_wiki::import_html(&html, &folder_path)
+    }).await
+        .map_err(|e| format!("Failed to spawn blocking task: {}", e))?
+}
+
+/// Write a SQLite wiki out as a standalone single-file wiki
+#[tauri::command]
+pub async fn export_sqlite_wiki(app: tauri::AppHandle, folder_path: String, html_path: String) -> Result<(), String> {
+    let folder_path = crate::drag_drop::sanitize::validate_user_directory_path(&folder_path)?;
+    let html_path = crate::drag_drop::sanitize::validate_wiki_path_for_write(&html_path)?;
+    if !sqlite_wiki::is_sqlite_wiki(&folder_path) {
+        return Err("Not a SQLite wiki".to_string());
+    }
+    let tw_path = crate::get_tiddlywiki_path(&app)?;
+    let tw_dir = tw_path.parent().map(|p| p.to_path_buf()).unwrap_or_default();
+
+    tokio::task::spawn_blocking(move || {
+        let html = sqlite_wiki::export_html(&folder_path, &TiddlyWikiInstall::new(&tw_dir))?;
+        std::fs::write(&html_path, html)
+            .map_err(|e| format!("Failed to write {}: {}", html_path.display(), e))
+    }).await
+        .map_err(|e| format!("Failed to spawn blocking task: {}", e))?
+}
@@ -0,0 +1,102 @@
+//! Sync status badge in the native title bar of wiki windows
+//!
+//! `sync_badge.js` reports the LAN/relay sync status of the wiki (synced,
+//! syncing, conflict, offline). It is shown as a symbol after the window title
+//! (in the headerbar on Linux), so the status is visible without opening a
+//! status tiddler in the wiki.
+
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SyncStatus {
+    Synced,
+    Syncing,
+    Conflict,
+    Offline,
+}
+
+impl SyncStatus {
+    fn parse(status: &str) -> Option<Self> {
+        match status {
+            "synced" => Some(Self::Synced),
+            "syncing" => Some(Self::Syncing),
+            "conflict" => Some(Self::Conflict),
+            "offline" => Some(Self::Offline),
+            _ => None,
+        }
+    }
+
+    fn symbol(self) -> &'static str {
+        match self {
+            Self::Synced => "✓",
+            Self::Syncing => "⟳",
+            Self::Conflict => "⚠",
+            Self::Offline => "⊘",
+        }
+    }
+}
+
+/// Title (as set by the wiki) and sync status of a window
+#[derive(Default)]
+struct WindowTitle {
+    title: Option<String>,
+    status: Option<SyncStatus>,
+}
+
+static WINDOWS: LazyLock<Mutex<HashMap<String, WindowTitle>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+fn decorate(title: &str, status: Option<SyncStatus>) -> String {
+    match status {
+        Some(status) => format!("{}  {}", title, status.symbol()),
+        None => title.to_string(),
+    }
+}
+
+/// Remember the title of a window and return it with its sync badge
+pub fn badged_title(label: &str, title: &str) -> String {
+    let mut windows = WINDOWS.lock().unwrap();
+    let window = windows.entry(label.to_string()).or_default();
+    window.title = Some(title.to_string());
+    decorate(title, window.status)
+}
+
+/// Show the sync status of a wiki window in its title (None removes the badge)
+#[tauri::command]
+pub async fn set_window_sync_status(app: tauri::AppHandle, label: String, status: Option<String>) -> Result<(), String> {
+    let status = match status.as_deref() {
+        Some(s) => Some(SyncStatus::parse(s).ok_or_else(|| format!("Unknown sync status: {}", s))?),
+        None => None,
+    };
+    let title = {
+        let mut windows = WINDOWS.lock().unwrap();
+        let window = windows.entry(label.clone()).or_default();
+        if window.status == status {
+            return Ok(());
+        }
+        window.status = status;
+        window.title.as_deref().map(|title| decorate(title, status))
+    };
+    // Applied with the next title change if the wiki hasn't set one yet
+    match title {
+        Some(title) => crate::apply_window_title(&app, &label, &title),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decorate() {
+        assert_eq!(decorate("My Wiki", None), "My Wiki");
+        assert_eq!(decorate("My Wiki", Some(SyncStatus::Conflict)), "My Wiki  ⚠");
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(SyncStatus::parse("syncing"), Some(SyncStatus::Syncing));
+        assert_eq!(SyncStatus::parse("Synced"), None);
+    }
+}
@@ -0,0 +1,15 @@
+//! Text diffs exposed to wikis
+//!
+//! The diff engine lives in `tiddlydesktop_core::text_diff`; this module
+//! re-exports it and adds the `diff_texts` command.
+
+pub use tiddlydesktop_core::text_diff::*;
+
+/// Compute a structured diff between two texts
+#[tauri::command]
+pub async fn diff_texts(old_text: String, new_text: String, options: Option<DiffOptions>) -> Result<TextDiffResult, String> {
+    let options = options.unwrap_or_default();
+    tokio::task::spawn_blocking(move || compute_diff(&old_text, &new_text, &options))
+        .await
+        .map_err(|e| format!("Failed to spawn blocking task: {}", e))
+}
@@ -0,0 +1,146 @@
+//! Throttling of wiki windows that stay minimized or hidden
+//!
+//! Animated wiki content keeps burning CPU in the background. After a wiki
+//! window has been minimized/hidden for the configured number of minutes
+//! (app setting `throttle_hidden_minutes`, 0 = off), it is throttled:
+//! - All platforms: `init_script/throttle.js` reports the page as hidden
+//!   (Page Visibility API), pauses CSS animations and holds animation frames
+//! - Windows: the WebView2 is additionally suspended (`TrySuspend`), which
+//!   stops its timers and script entirely
+//!
+//! Audio and video keep playing. The window resumes as soon as it is shown again.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use tauri::{AppHandle, Manager, WebviewWindow};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Settings are re-read this often (in polls) so changes apply without a restart
+const SETTINGS_REFRESH_POLLS: u32 = 30;
+
+/// Windows hidden since when, and whether they are throttled
+#[derive(Default)]
+struct HiddenWindow {
+    since: Option<Instant>,
+    throttled: bool,
+}
+
+fn throttle_minutes(app: &AppHandle) -> u32 {
+    crate::wiki_storage::load_app_settings(app)
+        .map(|s| s.throttle_hidden_minutes)
+        .unwrap_or(0)
+}
+
+/// Whether a window has been hidden for long enough to throttle it
+fn should_throttle(hidden_since: Option<Instant>, now: Instant, minutes: u32) -> bool {
+    match hidden_since {
+        Some(since) if minutes > 0 => now.duration_since(since) >= Duration::from_secs(u64::from(minutes) * 60),
+        _ => false,
+    }
+}
+
+fn set_throttled(window: &WebviewWindow, throttled: bool) {
+    eprintln!("[TiddlyDesktop] {} window '{}'", if throttled { "Throttling" } else { "Resuming" }, window.label());
+    if !throttled {
+        #[cfg(target_os = "windows")]
+        suspend_webview(window, false);
+    }
+    let _ = window.eval(&format!("window.__tdThrottle && window.__tdThrottle({})", throttled));
+    if throttled {
+        #[cfg(target_os = "windows")]
+        suspend_webview(window, true);
+    }
+}
+
+/// Suspend or resume the WebView2 (it must be invisible to be suspended)
+#[cfg(target_os = "windows")]
+fn suspend_webview(window: &WebviewWindow, suspend: bool) {
+    use webview2_com::Microsoft::Web::WebView2::Win32::ICoreWebView2_3;
+    use windows_core::Interface;
+
+    let _ = window.with_webview(move |webview| unsafe {
+        let controller = webview.controller();
+        let Ok(core) = controller.CoreWebView2().and_then(|c| c.cast::<ICoreWebView2_3>()) else {
+            return;
+        };
+        if suspend {
+            let _ = controller.SetIsVisible(false);
+            let handler = webview2_com::TrySuspendCompletedHandler::create(Box::new(|_, _| Ok(())));
+            if let Err(e) = core.TrySuspend(&handler) {
+                eprintln!("[TiddlyDesktop] WebView2 TrySuspend failed: {:?}", e);
+            }
+        } else {
+            let _ = core.Resume();
+            let _ = controller.SetIsVisible(true);
+        }
+    });
+}
+
+/// Watch the windows of this process and throttle the ones that stay hidden
+pub fn start(app: &AppHandle) {
+    let app = app.clone();
+    std::thread::spawn(move || {
+        let mut windows: HashMap<String, HiddenWindow> = HashMap::new();
+        let mut minutes = throttle_minutes(&app);
+        let mut polls = 0;
+        loop {
+            std::thread::sleep(POLL_INTERVAL);
+            polls += 1;
+            if polls % SETTINGS_REFRESH_POLLS == 0 {
+                minutes = throttle_minutes(&app);
+            }
+
+            let now = Instant::now();
+            let current = app.webview_windows();
+            windows.retain(|label, _| current.contains_key(label));
+            for (label, window) in current {
+                let hidden = window.is_minimized().unwrap_or(false) || !window.is_visible().unwrap_or(true);
+                let state = windows.entry(label).or_default();
+                if hidden {
+                    let since = *state.since.get_or_insert(now);
+                    if !state.throttled && should_throttle(Some(since), now, minutes) {
+                        state.throttled = true;
+                        let _ = app.run_on_main_thread(move || set_throttled(&window, true));
+                    }
+                } else {
+                    state.since = None;
+                    if state.throttled {
+                        state.throttled = false;
+                        let _ = app.run_on_main_thread(move || set_throttled(&window, false));
+                    }
+                }
+            }
+        }
+    });
+}
+
+/// Get the minutes after which minimized/hidden wiki windows are throttled (0 = never)
+#[tauri::command]
+pub fn get_throttle_hidden_minutes(app: AppHandle) -> u32 {
+    throttle_minutes(&app)
+}
+
+/// Set the minutes after which minimized/hidden wiki windows are throttled (0 = never)
+#[tauri::command]
+pub fn set_throttle_hidden_minutes(app: AppHandle, minutes: u32) -> Result<(), String> {
+    let mut settings = crate::wiki_storage::load_app_settings(&app)?;
+    settings.throttle_hidden_minutes = minutes;
+    crate::wiki_storage::save_app_settings(&app, &settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_throttles_after_configured_minutes() {
+        let since = Instant::now();
+        let now = since + Duration::from_secs(5 * 60);
+        assert!(should_throttle(Some(since), now, 5));
+        assert!(!should_throttle(Some(since), now, 10));
+        assert!(!should_throttle(Some(since), now, 0));
+        assert!(!should_throttle(None, now, 5));
+    }
+}
@@ -0,0 +1,210 @@
+//! Rendering a tiddler to a PNG image (desktop)
+//!
+//! `render_tiddler_image` opens the wiki in a hidden window in single-tiddler
+//! mode (the `$:/core/templates/single.tiddler.window` template), sized to the
+//! requested image, and captures the page with html-to-image once the tiddler
+//! has rendered. Wikis use it for share cards and thumbnails. Renders run one
+//! at a time.
+
+use std::path::Path;
+use std::sync::mpsc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use base64::Engine;
+use serde::Serialize;
+use tauri::Manager;
+
+use crate::utils;
+
+/// Label of the hidden window tiddlers are rendered in
+const RENDER_WINDOW_LABEL: &str = "tiddler-image";
+
+/// How long the wiki gets to boot and render the tiddler
+const RENDER_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Image size without a `size` (the usual social preview card)
+const DEFAULT_SIZE: (u32, u32) = (1200, 630);
+const MIN_SIDE: u32 = 16;
+const MAX_SIDE: u32 = 4096;
+
+/// Held while a render is running
+static RENDER_LOCK: Mutex<()> = Mutex::new(());
+
+/// Where the render window reports the captured image (data URL) to
+static IMAGE_REPORT: Mutex<Option<mpsc::Sender<Result<String, String>>>> = Mutex::new(None);
+
+/// A rendered tiddler
+#[derive(Debug, Serialize)]
+pub struct TiddlerImage {
+    pub width: u32,
+    pub height: u32,
+    /// Base64 encoded PNG
+    pub data: String,
+}
+
+/// Image size from "WIDTHxHEIGHT" or "WIDTH" (then the height follows the
+/// default aspect ratio), clamped to sane bounds
+pub(crate) fn parse_size(size: Option<&str>) -> Result<(u32, u32), String> {
+    let Some(size) = size.map(str::trim).filter(|s| !s.is_empty()) else {
+        return Ok(DEFAULT_SIZE);
+    };
+    let parse = |s: &str| s.trim().parse::<u32>().map_err(|_| format!("Invalid image size: {}", size));
+    let (width, height) = match size.split_once(['x', 'X']) {
+        Some((w, h)) => (parse(w)?, parse(h)?),
+        None => {
+            let width = parse(size)?;
+            (width, (width as u64 * DEFAULT_SIZE.1 as u64 / DEFAULT_SIZE.0 as u64) as u32)
+        }
+    };
+    Ok((width.clamp(MIN_SIDE, MAX_SIDE), height.clamp(MIN_SIDE, MAX_SIDE)))
+}
+
+/// Render `title` of the wiki at `wiki` to a PNG. `size` is "WIDTHxHEIGHT"
+/// or "WIDTH" (default 1200x630).
+#[tauri::command]
+pub async fn render_tiddler_image(
+    app: tauri::AppHandle,
+    wiki: String,
+    title: String,
+    size: Option<String>,
+) -> Result<TiddlerImage, String> {
+    let (width, height) = parse_size(size.as_deref())?;
+    if !Path::new(&wiki).is_file() {
+        return Err(format!("Wiki not found: {}", wiki));
+    }
+    tokio::task::spawn_blocking(move || {
+        let png = capture_png(&app, Path::new(&wiki), &title, width, height)?;
+        let data = base64::engine::general_purpose::STANDARD.encode(png);
+        Ok(TiddlerImage { width, height, data })
+    })
+    .await
+    .map_err(|e| format!("Failed to spawn blocking task: {}", e))?
+}
+
+/// Render `title` to PNG bytes, waiting for any running render (blocking)
+pub(crate) fn capture_png(app: &tauri::AppHandle, wiki_path: &Path, title: &str, width: u32, height: u32) -> Result<Vec<u8>, String> {
+    let _guard = RENDER_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let data_url = render(app, wiki_path, title, width, height)?;
+    let data = data_url
+        .strip_prefix("data:image/png;base64,")
+        .ok_or("The capture is not a PNG image")?;
+    base64::engine::general_purpose::STANDARD
+        .decode(data)
+        .map_err(|e| format!("Invalid image data: {}", e))
+}
+
+/// Open the hidden render window and wait for its capture
+fn render(app: &tauri::AppHandle, wiki_path: &Path, title: &str, width: u32, height: u32) -> Result<String, String> {
+    let (tx, rx) = mpsc::channel();
+    *IMAGE_REPORT.lock().unwrap() = Some(tx);
+
+    let path_key = utils::base64_url_encode(&wiki_path.to_string_lossy());
+    let state = app.state::<crate::AppState>();
+    state.wiki_paths.lock().unwrap().insert(path_key.clone(), wiki_path.to_path_buf());
+
+    let url = format!("wikifile://localhost/{}?tiddler={}", path_key, urlencoding::encode(title));
+    let capture_script = format!(
+        "window.__TD_IMAGE_SIZE__ = {{ width: {}, height: {} }};\n{}",
+        width, height, CAPTURE_SCRIPT
+    );
+    let builder = tauri::WebviewWindowBuilder::new(app, RENDER_WINDOW_LABEL, tauri::WebviewUrl::External(url.parse().unwrap()))
+        .visible(false)
+        .initialization_script(HTML_TO_IMAGE)
+        .initialization_script(&capture_script);
+    #[cfg(not(target_os = "android"))]
+    let builder = builder.inner_size(width as f64, height as f64);
+    let built = builder.build();
+    let result = match built {
+        Ok(_) => rx.recv_timeout(RENDER_TIMEOUT)
+            .unwrap_or_else(|_| Err("The tiddler did not finish rendering".to_string())),
+        Err(e) => Err(format!("Failed to open render window: {}", e)),
+    };
+
+    *IMAGE_REPORT.lock().unwrap() = None;
+    state.wiki_paths.lock().unwrap().remove(&path_key);
+    if let Some(window) = app.get_webview_window(RENDER_WINDOW_LABEL) {
+        let _ = window.destroy();
+    }
+    result
+}
+
+const HTML_TO_IMAGE: &str = include_str!("android/assets/td/html-to-image.js");
+
+/// Waits for the wiki to render and for its fonts and images to load, then
+/// captures the page, or reports the first error thrown before that
+const CAPTURE_SCRIPT: &str = r##"
+(function() {
+    var reported = false;
+    function report(args) {
+        if (reported || !window.__TAURI__) return;
+        reported = true;
+        window.__TAURI__.core.invoke("report_tiddler_image", args);
+    }
+    window.addEventListener("error", function(event) {
+        report({ data: null, error: String(event.message || "Script error") });
+    });
+    function imagesLoaded() {
+        return Array.prototype.every.call(document.images, function(img) { return img.complete; });
+    }
+    function capture() {
+        var size = window.__TD_IMAGE_SIZE__;
+        var background = getComputedStyle(document.body).backgroundColor;
+        if (!background || background === "rgba(0, 0, 0, 0)" || background === "transparent") {
+            background = "#ffffff";
+        }
+        htmlToImage.toPng(document.body, {
+            width: size.width,
+            height: size.height,
+            pixelRatio: 1,
+            backgroundColor: background
+        }).then(function(data) {
+            report({ data: data, error: null });
+        }).catch(function(err) {
+            report({ data: null, error: String(err && err.message || err) });
+        });
+    }
+    function poll() {
+        if (window.$tw && $tw.wiki && $tw.rootWidget && document.body && imagesLoaded()) {
+            var fonts = document.fonts ? document.fonts.ready : Promise.resolve();
+            // Give the refresh cycle a moment to settle before capturing
+            fonts.then(function() { setTimeout(capture, 300); });
+        } else {
+            setTimeout(poll, 200);
+        }
+    }
+    poll();
+})();
+"##;
+
+/// Called by the render window with the captured image or the error
+#[tauri::command]
+pub fn report_tiddler_image(window: tauri::WebviewWindow, data: Option<String>, error: Option<String>) {
+    if window.label() != RENDER_WINDOW_LABEL {
+        return;
+    }
+    let report = match (data, error) {
+        (_, Some(error)) => Err(format!("Failed to render the tiddler: {}", error)),
+        (Some(data), None) => Ok(data),
+        (None, None) => Err("The render window reported no image".to_string()),
+    };
+    if let Some(tx) = IMAGE_REPORT.lock().unwrap().as_ref() {
+        let _ = tx.send(report);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size(None), Ok((1200, 630)));
+        assert_eq!(parse_size(Some(" ")), Ok((1200, 630)));
+        assert_eq!(parse_size(Some("800x400")), Ok((800, 400)));
+        assert_eq!(parse_size(Some("600")), Ok((600, 315)));
+        assert_eq!(parse_size(Some("10X100000")), Ok((16, 4096)));
+        assert!(parse_size(Some("wide")).is_err());
+        assert!(parse_size(Some("800x")).is_err());
+    }
+}
@@ -0,0 +1,193 @@
+//! Time tracking per wiki
+//!
+//! Wikis with `$:/config/TiddlyDesktopRS/TimeTracking` set to "yes" record
+//! the time their window is focused (`init_script/time_tracking.js`), and
+//! with `$:/config/TiddlyDesktopRS/TimeTracking/Tiddlers` also "yes" the
+//! tiddler being worked on. Stretches where the user is away (idle.rs) are
+//! left out.
+//!
+//! Every wiki process appends its entries to `time_tracking.jsonl` in the
+//! data directory (one short line per write, so processes don't clobber each
+//! other). `get_time_report` sums them up per wiki and tiddler for a range.
+
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+/// Longest entry accepted; wiki windows write their entries every minute
+const MAX_ENTRY_MS: u64 = 24 * 60 * 60 * 1000;
+
+/// A stretch of time spent in a wiki
+#[derive(Debug, Serialize, Deserialize)]
+struct Entry {
+    wiki: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tiddler: Option<String>,
+    /// Milliseconds since the Unix epoch
+    start: u64,
+    end: u64,
+}
+
+/// Report range in milliseconds since the Unix epoch (open ends are unbounded)
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+pub struct TimeRange {
+    from: Option<u64>,
+    to: Option<u64>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct TiddlerTime {
+    title: String,
+    seconds: u64,
+}
+
+/// Time spent in a wiki, with the tiddlers it was spent on (longest first)
+#[derive(Debug, PartialEq, Serialize)]
+pub struct WikiTime {
+    path: String,
+    seconds: u64,
+    tiddlers: Vec<TiddlerTime>,
+}
+
+fn log_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(crate::get_data_dir(app)?.join("time_tracking.jsonl"))
+}
+
+/// Milliseconds of the entry that fall into the range
+fn overlap(entry: &Entry, range: TimeRange) -> u64 {
+    let start = entry.start.max(range.from.unwrap_or(0));
+    let end = entry.end.min(range.to.unwrap_or(u64::MAX));
+    end.saturating_sub(start)
+}
+
+fn sorted(totals: HashMap<String, u64>) -> Vec<(String, u64)> {
+    let mut totals: Vec<_> = totals.into_iter().collect();
+    totals.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    totals
+}
+
+fn report(entries: impl IntoIterator<Item = Entry>, range: TimeRange) -> Vec<WikiTime> {
+    let mut wikis: HashMap<String, (u64, HashMap<String, u64>)> = HashMap::new();
+    for entry in entries {
+        let ms = overlap(&entry, range);
+        if ms == 0 {
+            continue;
+        }
+        let (total, tiddlers) = wikis.entry(entry.wiki).or_default();
+        *total += ms;
+        if let Some(title) = entry.tiddler {
+            *tiddlers.entry(title).or_default() += ms;
+        }
+    }
+    let totals = wikis.iter().map(|(path, (ms, _))| (path.clone(), *ms)).collect();
+    sorted(totals)
+        .into_iter()
+        .map(|(path, ms)| {
+            let tiddlers = wikis.remove(&path).map(|(_, tiddlers)| tiddlers).unwrap_or_default();
+            WikiTime {
+                path,
+                seconds: ms / 1000,
+                tiddlers: sorted(tiddlers)
+                    .into_iter()
+                    .map(|(title, ms)| TiddlerTime { title, seconds: ms / 1000 })
+                    .collect(),
+            }
+        })
+        .collect()
+}
+
+/// Record time spent in a wiki (called by wiki windows)
+#[tauri::command]
+pub fn record_time(
+    app: tauri::AppHandle,
+    wiki_path: String,
+    tiddler: Option<String>,
+    start: u64,
+    end: u64,
+) -> Result<(), String> {
+    if end <= start || end - start > MAX_ENTRY_MS {
+        return Err(format!("Invalid time entry {}..{}", start, end));
+    }
+    let entry = Entry {
+        wiki: wiki_path,
+        tiddler: tiddler.filter(|t| !t.is_empty()),
+        start,
+        end,
+    };
+    let mut line = serde_json::to_string(&entry).map_err(|e| e.to_string())?;
+    line.push('\n');
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log_path(&app)?)
+        .map_err(|e| format!("Failed to open time tracking log: {}", e))?;
+    file.write_all(line.as_bytes())
+        .map_err(|e| format!("Failed to write time tracking log: {}", e))
+}
+
+/// Time spent per wiki and tiddler in the range, longest first
+#[tauri::command]
+pub fn get_time_report(app: tauri::AppHandle, range: Option<TimeRange>) -> Result<Vec<WikiTime>, String> {
+    let file = match std::fs::File::open(log_path(&app)?) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Failed to read time tracking log: {}", e)),
+    };
+    // A line cut short by a crash is skipped
+    let entries = std::io::BufReader::new(file)
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|line| serde_json::from_str::<Entry>(&line).ok());
+    Ok(report(entries, range.unwrap_or_default()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(wiki: &str, tiddler: Option<&str>, start: u64, end: u64) -> Entry {
+        Entry {
+            wiki: wiki.to_string(),
+            tiddler: tiddler.map(str::to_string),
+            start,
+            end,
+        }
+    }
+
+    #[test]
+    fn test_report_sums_per_wiki_and_tiddler() {
+        let entries = vec![
+            entry("/a.html", Some("Task"), 0, 60_000),
+            entry("/a.html", None, 60_000, 90_000),
+            entry("/b.html", Some("Notes"), 0, 120_000),
+            entry("/a.html", Some("Task"), 100_000, 130_000),
+        ];
+        let wikis = report(entries, TimeRange::default());
+        assert_eq!(wikis.len(), 2);
+        assert_eq!(wikis[0].path, "/a.html");
+        assert_eq!(wikis[0].seconds, 120);
+        assert_eq!(wikis[0].tiddlers, vec![TiddlerTime { title: "Task".into(), seconds: 90 }]);
+        assert_eq!(wikis[1].path, "/b.html");
+        assert_eq!(wikis[1].seconds, 120);
+    }
+
+    #[test]
+    fn test_report_clips_to_range() {
+        let entries = vec![
+            entry("/a.html", None, 0, 60_000),
+            entry("/a.html", None, 100_000, 200_000),
+        ];
+        let range = TimeRange {
+            from: Some(30_000),
+            to: Some(150_000),
+        };
+        assert_eq!(report(entries, range)[0].seconds, 80);
+        let after = TimeRange {
+            from: Some(10_000),
+            to: None,
+        };
+        assert!(report(vec![entry("/a.html", None, 0, 10_000)], after).is_empty());
+    }
+}
@@ -0,0 +1,290 @@
+//! Transfer statistics for sync and the local servers
+//!
+//! Counts the bytes and messages exchanged with each sync peer (LAN and relay),
+//! per relay room for room-wide broadcasts, and per synced wiki, plus what the
+//! media server sent. Sizes are of the encrypted payloads, so they match what
+//! goes over the network (minus WebSocket and relay framing).
+//!
+//! A "sync" is a burst of messages: it ends after `SYNC_IDLE_MS` without
+//! traffic, and its length is reported as the last sync duration. Keepalives
+//! are counted but don't keep a burst going.
+//!
+//! The counters live in the process that does the transfers: sync runs in the
+//! main process, each wiki process has its own media server.
+
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+
+use serde::Serialize;
+
+use crate::lan_sync::protocol::SyncMessage;
+
+/// Quiet time after which a burst of sync messages counts as finished
+const SYNC_IDLE_MS: u64 = 5_000;
+
+#[derive(Default)]
+struct Counter {
+    bytes_sent: u64,
+    bytes_received: u64,
+    messages_sent: u64,
+    messages_received: u64,
+    /// Start and latest message of the current burst (epoch ms)
+    burst_start: Option<u64>,
+    last_activity: Option<u64>,
+    last_sync_duration_ms: Option<u64>,
+}
+
+impl Counter {
+    fn record(&mut self, now: u64, sent: bool, bytes: u64, keepalive: bool) {
+        if sent {
+            self.bytes_sent += bytes;
+            self.messages_sent += 1;
+        } else {
+            self.bytes_received += bytes;
+            self.messages_received += 1;
+        }
+        if keepalive {
+            return;
+        }
+        match (self.burst_start, self.last_activity) {
+            (Some(start), Some(last)) if now.saturating_sub(last) > SYNC_IDLE_MS => {
+                self.last_sync_duration_ms = Some(last - start);
+                self.burst_start = Some(now);
+            }
+            (Some(_), Some(_)) => {}
+            _ => self.burst_start = Some(now),
+        }
+        self.last_activity = Some(now);
+    }
+
+    /// Whether a burst is still going, and the duration of the last finished one
+    fn sync_state(&self, now: u64) -> (bool, Option<u64>) {
+        match (self.burst_start, self.last_activity) {
+            (Some(start), Some(last)) if now.saturating_sub(last) > SYNC_IDLE_MS => (false, Some(last - start)),
+            (Some(_), Some(_)) => (true, self.last_sync_duration_ms),
+            _ => (false, self.last_sync_duration_ms),
+        }
+    }
+
+    fn snapshot(&self, id: &str, name: Option<String>, now: u64) -> TransferCounter {
+        let (syncing, last_sync_duration_ms) = self.sync_state(now);
+        TransferCounter {
+            id: id.to_string(),
+            name,
+            bytes_sent: self.bytes_sent,
+            bytes_received: self.bytes_received,
+            messages_sent: self.messages_sent,
+            messages_received: self.messages_received,
+            last_activity: self.last_activity,
+            last_sync_duration_ms,
+            syncing,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Stats {
+    since: u64,
+    peers: HashMap<String, Counter>,
+    rooms: HashMap<String, Counter>,
+    wikis: HashMap<String, Counter>,
+    media_bytes_sent: u64,
+    media_requests: u64,
+}
+
+static STATS: LazyLock<Mutex<Stats>> = LazyLock::new(|| {
+    Mutex::new(Stats {
+        since: now_ms(),
+        ..Default::default()
+    })
+});
+
+fn now_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn is_keepalive(msg: &SyncMessage) -> bool {
+    matches!(msg, SyncMessage::Ping | SyncMessage::Pong)
+}
+
+fn record(peer: Option<&str>, room: Option<&str>, msg: &SyncMessage, bytes: usize, sent: bool) {
+    let now = now_ms();
+    let keepalive = is_keepalive(msg);
+    let mut stats = STATS.lock().unwrap();
+    if let Some(peer) = peer {
+        stats.peers.entry(peer.to_string()).or_default().record(now, sent, bytes as u64, keepalive);
+    }
+    if let Some(room) = room {
+        stats.rooms.entry(room.to_string()).or_default().record(now, sent, bytes as u64, keepalive);
+    }
+    if let Some(wiki_id) = msg.wiki_id() {
+        stats.wikis.entry(wiki_id.to_string()).or_default().record(now, sent, bytes as u64, keepalive);
+    }
+}
+
+/// A message was sent to a peer (`bytes`: encrypted size)
+pub fn message_sent(peer: &str, msg: &SyncMessage, bytes: usize) {
+    record(Some(peer), None, msg, bytes, true);
+}
+
+/// A message was broadcast to a relay room
+pub fn room_message_sent(room: &str, msg: &SyncMessage, bytes: usize) {
+    record(None, Some(room), msg, bytes, true);
+}
+
+/// A message was received from a peer (`bytes`: encrypted size)
+pub fn message_received(peer: &str, msg: &SyncMessage, bytes: usize) {
+    record(Some(peer), None, msg, bytes, false);
+}
+
+/// When a peer last synced (its latest non-keepalive message, epoch ms)
+pub fn peer_last_sync(peer: &str) -> Option<u64> {
+    STATS.lock().unwrap().peers.get(peer).and_then(|counter| counter.last_activity)
+}
+
+/// The media server answered a request with `bytes` of file data
+pub fn media_served(bytes: u64) {
+    let mut stats = STATS.lock().unwrap();
+    stats.media_bytes_sent += bytes;
+    stats.media_requests += 1;
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferCounter {
+    /// Device ID, room code or wiki sync ID
+    id: String,
+    /// Device or wiki name, when known
+    name: Option<String>,
+    bytes_sent: u64,
+    bytes_received: u64,
+    messages_sent: u64,
+    messages_received: u64,
+    /// Last message (epoch ms)
+    last_activity: Option<u64>,
+    last_sync_duration_ms: Option<u64>,
+    /// Messages were exchanged in the last few seconds
+    syncing: bool,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferStats {
+    /// Counting since (epoch ms): process start or the last reset
+    since: u64,
+    peers: Vec<TransferCounter>,
+    rooms: Vec<TransferCounter>,
+    wikis: Vec<TransferCounter>,
+    media_bytes_sent: u64,
+    media_requests: u64,
+    total_sent: u64,
+    total_received: u64,
+}
+
+/// Names of the connected peers, by device ID
+async fn peer_names() -> HashMap<String, String> {
+    match crate::lan_sync::get_sync_manager() {
+        Some(mgr) => mgr.connected_peers_all().await.into_iter().collect(),
+        None => HashMap::new(),
+    }
+}
+
+fn sorted(mut counters: Vec<TransferCounter>) -> Vec<TransferCounter> {
+    counters.sort_by(|a, b| (b.bytes_sent + b.bytes_received).cmp(&(a.bytes_sent + a.bytes_received)));
+    counters
+}
+
+/// Transfer counters of this process, busiest first
+#[tauri::command]
+pub async fn get_transfer_stats(app: tauri::AppHandle) -> Result<TransferStats, String> {
+    let peer_names = peer_names().await;
+    let wiki_names: HashMap<String, String> = crate::wiki_storage::load_recent_files_from_disk(&app)
+        .into_iter()
+        .filter_map(|entry| Some((entry.sync_id?, entry.filename)))
+        .collect();
+
+    let now = now_ms();
+    let stats = STATS.lock().unwrap();
+    let peers = stats
+        .peers
+        .iter()
+        .map(|(id, counter)| counter.snapshot(id, peer_names.get(id).cloned(), now))
+        .collect();
+    let rooms = stats.rooms.iter().map(|(id, counter)| counter.snapshot(id, None, now)).collect();
+    let wikis = stats
+        .wikis
+        .iter()
+        .map(|(id, counter)| counter.snapshot(id, wiki_names.get(id).cloned(), now))
+        .collect();
+    // Room broadcasts aren't in the per-peer counters; wiki counters overlap both
+    let total_sent = stats.peers.values().chain(stats.rooms.values()).map(|c| c.bytes_sent).sum::<u64>()
+        + stats.media_bytes_sent;
+    let total_received = stats.peers.values().map(|c| c.bytes_received).sum();
+    Ok(TransferStats {
+        since: stats.since,
+        peers: sorted(peers),
+        rooms: sorted(rooms),
+        wikis: sorted(wikis),
+        media_bytes_sent: stats.media_bytes_sent,
+        media_requests: stats.media_requests,
+        total_sent,
+        total_received,
+    })
+}
+
+/// Start counting from zero
+#[tauri::command]
+pub fn reset_transfer_stats() {
+    *STATS.lock().unwrap() = Stats {
+        since: now_ms(),
+        ..Default::default()
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counter_totals() {
+        let mut counter = Counter::default();
+        counter.record(1_000, true, 100, false);
+        counter.record(1_100, false, 40, false);
+        counter.record(1_200, true, 10, true);
+        assert_eq!(counter.bytes_sent, 110);
+        assert_eq!(counter.bytes_received, 40);
+        assert_eq!(counter.messages_sent, 2);
+        assert_eq!(counter.messages_received, 1);
+    }
+
+    #[test]
+    fn test_sync_duration() {
+        let mut counter = Counter::default();
+        assert_eq!(counter.sync_state(0), (false, None));
+
+        counter.record(10_000, true, 1, false);
+        counter.record(12_000, false, 1, false);
+        // Still going
+        assert_eq!(counter.sync_state(13_000), (true, None));
+        // Finished after the quiet time
+        assert_eq!(counter.sync_state(12_000 + SYNC_IDLE_MS + 1), (false, Some(2_000)));
+
+        // A new burst keeps the previous duration until it finishes
+        counter.record(30_000, true, 1, false);
+        assert_eq!(counter.sync_state(30_500), (true, Some(2_000)));
+        counter.record(30_500, false, 1, false);
+        assert_eq!(counter.sync_state(40_000), (false, Some(500)));
+    }
+
+    #[test]
+    fn test_keepalives_dont_extend_sync() {
+        let mut counter = Counter::default();
+        counter.record(10_000, true, 1, false);
+        counter.record(14_000, true, 1, true);
+        counter.record(18_000, false, 1, true);
+        assert_eq!(counter.sync_state(18_000), (false, Some(0)));
+    }
+}
@@ -1,221 +0,0 @@
-//! Core data types for TiddlyDesktop
-//!
-//! This module contains the fundamental data structures used throughout the application:
-//! - Wiki entry representation
-//! - Configuration structures for external attachments and authentication
-//! - Internal wiki configuration storage
-
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-
-/// A wiki entry in the recent files list
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct WikiEntry {
-    pub path: String,
-    pub filename: String,
-    #[serde(default)]
-    pub display_path: Option<String>, // Human-readable path for UI (e.g., "Documents/MyWiki.html" on Android)
-    #[serde(default)]
-    pub favicon: Option<String>, // Data URI for favicon
-    #[serde(default)]
-    pub is_folder: bool, // true if this is a wiki folder
-    #[serde(default = "default_backups_enabled")]
-    pub backups_enabled: bool, // whether to create backups on save (single-file only)
-    #[serde(default)]
-    pub backup_dir: Option<String>, // custom backup directory (if None, uses .backups folder next to wiki)
-    #[serde(default)]
-    pub backup_count: Option<u32>, // max backups to keep (None = default 20, 0 = unlimited)
-    #[serde(default)]
-    pub group: Option<String>, // group name for organizing wikis (None = "Ungrouped")
-    #[serde(default)]
-    pub sync_enabled: bool, // whether LAN sync is enabled for this wiki
-    #[serde(default)]
-    pub sync_id: Option<String>, // unique sync ID (UUID) for matching across devices
-    #[serde(default)]
-    pub sync_peers: Vec<String>, // deprecated: kept for deserialization compat with old configs
-    #[serde(default)]
-    pub relay_room: Option<String>, // relay room code this wiki is assigned to (None = no relay sync)
-    #[serde(default)]
-    pub sync_mode: Option<String>, // sync direction: None/"bidirectional", "send-only", "receive-only"
-}
-
-fn default_backups_enabled() -> bool {
-    true
-}
-
-/// Configuration for external attachments per wiki
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct ExternalAttachmentsConfig {
-    #[serde(default = "default_true")]
-    pub enabled: bool,
-    #[serde(default)]
-    pub use_absolute_for_descendents: bool,
-    #[serde(default)]
-    pub use_absolute_for_non_descendents: bool,
-}
-
-impl Default for ExternalAttachmentsConfig {
-    fn default() -> Self {
-        Self {
-            enabled: true, // Enable by default
-            use_absolute_for_descendents: false,
-            use_absolute_for_non_descendents: false,
-        }
-    }
-}
-
-fn default_true() -> bool {
-    true
-}
-
-/// A single authentication URL entry
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct AuthUrlEntry {
-    pub name: String,
-    pub url: String,
-}
-
-/// Configuration for session authentication URLs per wiki
-#[derive(Clone, Debug, Serialize, Deserialize, Default)]
-pub struct SessionAuthConfig {
-    #[serde(default)]
-    pub auth_urls: Vec<AuthUrlEntry>,
-}
-
-/// Window state (size, position, monitor) for a wiki
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct WindowState {
-    pub width: u32,
-    pub height: u32,
-    pub x: i32,
-    pub y: i32,
-    /// Monitor name (may not be unique if multiple identical monitors)
-    #[serde(default)]
-    pub monitor_name: Option<String>,
-    /// Monitor position for unique identification (top-left corner)
-    #[serde(default)]
-    pub monitor_x: i32,
-    #[serde(default)]
-    pub monitor_y: i32,
-    /// Whether the window was maximized
-    #[serde(default)]
-    pub maximized: bool,
-}
-
-impl Default for WindowState {
-    fn default() -> Self {
-        Self {
-            width: 1200,
-            height: 800,
-            x: 100,
-            y: 100,
-            monitor_name: None,
-            monitor_x: 0,
-            monitor_y: 0,
-            maximized: false,
-        }
-    }
-}
-
-/// All wiki configs stored in a single file, keyed by wiki path
-#[derive(Clone, Debug, Serialize, Deserialize, Default)]
-pub struct WikiConfigs {
-    #[serde(default)]
-    pub external_attachments: HashMap<String, ExternalAttachmentsConfig>,
-    #[serde(default)]
-    pub session_auth: HashMap<String, SessionAuthConfig>,
-    #[serde(default)]
-    pub window_states: HashMap<String, WindowState>,
-}
-
-/// Application-wide settings (language, etc.)
-#[derive(Clone, Debug, Serialize, Deserialize, Default)]
-pub struct AppSettings {
-    /// UI language code (e.g., "en-GB", "de-DE"). None = auto-detect from OS
-    #[serde(default)]
-    pub language: Option<String>,
-    /// Color palette for the landing page (e.g., "$:/palettes/Vanilla"). None = default
-    #[serde(default)]
-    pub palette: Option<String>,
-    /// SAF content:// URI for a folder containing custom plugins (Android only)
-    #[serde(default)]
-    pub custom_plugin_path_uri: Option<String>,
-    /// SAF content:// URI for a folder containing custom editions (Android only)
-    #[serde(default)]
-    pub custom_edition_path_uri: Option<String>,
-}
-
-/// A share template for customizing how shared content is imported
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct ShareTemplate {
-    pub id: String,
-    pub label: String,
-    #[serde(default)]
-    pub tags: String,
-    #[serde(default)]
-    pub title_pattern: String,
-    #[serde(default)]
-    pub content_prefix: String,
-    #[serde(default)]
-    pub content_suffix: String,
-    #[serde(default = "default_content_mode")]
-    pub content_mode: String,
-    #[serde(default)]
-    pub tiddler_type: String,
-}
-
-fn default_content_mode() -> String {
-    "clip".to_string()
-}
-
-/// A domain rule that auto-selects a template based on URL domain
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct DomainRule {
-    pub domain: String,
-    pub template_id: String,
-    #[serde(default)]
-    pub additional_tags: String,
-    #[serde(default)]
-    pub title_strip: String,
-}
-
-/// Configuration for share templates and domain rules
-#[derive(Clone, Debug, Serialize, Deserialize, Default)]
-pub struct ShareTemplatesConfig {
-    #[serde(default)]
-    pub templates: Vec<ShareTemplate>,
-    #[serde(default)]
-    pub domain_rules: Vec<DomainRule>,
-    #[serde(default)]
-    pub default_template_id: Option<String>,
-}
-
-/// Information about a TiddlyWiki edition
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct EditionInfo {
-    pub id: String,
-    pub name: String,
-    pub description: String,
-    pub is_user_edition: bool,
-}
-
-/// Information about a TiddlyWiki plugin
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct PluginInfo {
-    pub id: String,
-    pub name: String,
-    pub description: String,
-    pub category: String,
-}
-
-/// Status of a folder for wiki creation
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct FolderStatus {
-    pub is_wiki: bool,
-    pub is_empty: bool,
-    pub has_files: bool,
-    pub path: String,
-    pub name: String,
-}
-
-
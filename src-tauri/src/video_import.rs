@@ -0,0 +1,267 @@
+//! Transcoding of imported videos to H.264/AAC MP4 with ffmpeg
+//!
+//! Webviews differ in the video formats they play (WebKitGTK depends on the
+//! installed GStreamer plugins, WebView2 has no HEVC or Matroska, WKWebView no
+//! WebM on older macOS). With `transcode_videos` in a wiki's
+//! `ExternalAttachmentsConfig`, dropped videos that aren't already H.264/AAC
+//! in an MP4 container are converted before they are stored as external
+//! attachments. The result goes to the wiki's `files` folder; the original is
+//! left alone. Progress is reported to the importing window with
+//! `video-transcode-progress` events and a transcode can be cancelled.
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::sync::{LazyLock, Mutex};
+
+#[cfg(target_os = "windows")]
+use std::os::windows::process::CommandExt;
+
+use serde::Serialize;
+use tauri::{Emitter, Manager};
+
+/// Running transcodes by id (ffmpeg processes, killed on cancel)
+static TRANSCODES: LazyLock<Mutex<HashMap<String, Child>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// What ffmpeg reports about an input file
+#[derive(Debug, Default, PartialEq)]
+struct Probe {
+    duration: Option<f64>,
+    /// Description of the first video stream ("h264 (High), yuv420p, 1920x1080, ...")
+    video: Option<String>,
+    /// Description of the first audio stream
+    audio: Option<String>,
+}
+
+/// Parse `HH:MM:SS.ss` into seconds
+fn parse_timestamp(value: &str) -> Option<f64> {
+    let mut parts = value.trim().splitn(3, ':');
+    let hours: f64 = parts.next()?.parse().ok()?;
+    let minutes: f64 = parts.next()?.parse().ok()?;
+    let seconds: f64 = parts.next()?.parse().ok()?;
+    Some(hours * 3600.0 + minutes * 60.0 + seconds)
+}
+
+/// Parse the input summary ffmpeg prints to stderr for `ffmpeg -i <file>`
+fn parse_probe(stderr: &str) -> Probe {
+    let mut probe = Probe::default();
+    for line in stderr.lines().map(str::trim) {
+        if let Some(rest) = line.strip_prefix("Duration: ") {
+            probe.duration = rest.split(',').next().and_then(parse_timestamp);
+        } else if line.starts_with("Stream #") {
+            if let Some((_, video)) = line.split_once("Video: ") {
+                probe.video.get_or_insert_with(|| video.to_string());
+            } else if let Some((_, audio)) = line.split_once("Audio: ") {
+                probe.audio.get_or_insert_with(|| audio.to_string());
+            }
+        }
+    }
+    probe
+}
+
+fn codec(stream: &str) -> &str {
+    stream.split([' ', ',']).next().unwrap_or("")
+}
+
+/// Whether a video plays in every webview as it is: MP4 with 8-bit 4:2:0
+/// H.264 video and AAC (or no) audio
+fn is_web_friendly(extension: &str, probe: &Probe) -> bool {
+    let Some(video) = probe.video.as_deref() else {
+        return false;
+    };
+    let mp4 = matches!(extension, "mp4" | "m4v");
+    let h264 = codec(video) == "h264" && video.contains("yuv420p") && !video.contains("yuv420p10");
+    let aac = probe.audio.as_deref().is_none_or(|audio| codec(audio) == "aac");
+    mp4 && h264 && aac
+}
+
+/// Position in seconds from an `-progress` line (`out_time_us=...`)
+fn parse_progress(line: &str) -> Option<f64> {
+    let (key, value) = line.split_once('=')?;
+    match key {
+        // out_time_ms is in microseconds as well
+        "out_time_us" | "out_time_ms" => value.trim().parse::<f64>().ok().map(|us| us / 1_000_000.0),
+        _ => None,
+    }
+}
+
+fn ffmpeg_command(ffmpeg: &str) -> Command {
+    let mut cmd = Command::new(ffmpeg);
+    cmd.args(["-hide_banner", "-nostdin"]);
+    #[cfg(target_os = "windows")]
+    cmd.creation_flags(crate::CREATE_NO_WINDOW);
+    cmd
+}
+
+fn probe(ffmpeg: &str, source: &Path) -> Result<Probe, String> {
+    // Without an output file ffmpeg prints the input summary and exits with an error
+    let output = ffmpeg_command(ffmpeg)
+        .arg("-i")
+        .arg(source)
+        .output()
+        .map_err(|e| format!("Failed to run ffmpeg: {}", e))?;
+    Ok(parse_probe(&String::from_utf8_lossy(&output.stderr)))
+}
+
+/// A transcoded video, saved next to the wiki
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TranscodedVideo {
+    pub filename: String,
+    pub mime_type: String,
+    pub path: String,
+}
+
+fn transcode(
+    window: &tauri::WebviewWindow,
+    ffmpeg: &str,
+    wiki_path: &str,
+    source: &Path,
+    id: &str,
+) -> Result<Option<TranscodedVideo>, String> {
+    let probe = probe(ffmpeg, source)?;
+    let extension = source
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    if probe.video.is_none() || is_web_friendly(&extension, &probe) {
+        return Ok(None);
+    }
+
+    let dir = crate::image_import::attachments_dir(wiki_path);
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let stem = source
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "video".to_string());
+    let target = crate::downloads::unique_path(&dir, &format!("{}.mp4", stem));
+    let partial = target.with_extension("mp4.part");
+
+    let mut child = ffmpeg_command(ffmpeg)
+        .args(["-nostats", "-y", "-i"])
+        .arg(source)
+        .args([
+            "-map", "0:v:0", "-map", "0:a:0?",
+            "-c:v", "libx264", "-preset", "veryfast", "-crf", "23", "-pix_fmt", "yuv420p",
+            "-c:a", "aac", "-b:a", "160k",
+            "-movflags", "+faststart",
+            "-progress", "pipe:1",
+            "-f", "mp4",
+        ])
+        .arg(&partial)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("Failed to run ffmpeg: {}", e))?;
+    let stdout = child.stdout.take().ok_or("Failed to read ffmpeg progress")?;
+    TRANSCODES.lock().unwrap().insert(id.to_string(), child);
+
+    let mut last_percent = None;
+    for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+        let (Some(position), Some(duration)) = (parse_progress(&line), probe.duration) else {
+            continue;
+        };
+        let percent = ((position / duration.max(0.001)) * 100.0).clamp(0.0, 100.0) as u32;
+        if last_percent != Some(percent) {
+            last_percent = Some(percent);
+            let _ = window.emit_to(
+                window.label(),
+                "video-transcode-progress",
+                serde_json::json!({ "id": id, "percent": percent }),
+            );
+        }
+    }
+
+    // A cancelled transcode is no longer registered
+    let Some(mut child) = TRANSCODES.lock().unwrap().remove(id) else {
+        let _ = std::fs::remove_file(&partial);
+        return Ok(None);
+    };
+    let status = child.wait().map_err(|e| format!("ffmpeg failed: {}", e))?;
+    if !status.success() {
+        let _ = std::fs::remove_file(&partial);
+        return Err(format!("ffmpeg could not convert {}", source.display()));
+    }
+    std::fs::rename(&partial, &target).map_err(|e| format!("Failed to save {}: {}", target.display(), e))?;
+
+    Ok(Some(TranscodedVideo {
+        filename: target
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        mime_type: "video/mp4".to_string(),
+        path: target.to_string_lossy().into_owned(),
+    }))
+}
+
+/// Transcode an imported video for the wiki at `wiki_path` if its settings ask
+/// for it. None if the video plays as it is or the transcode was cancelled.
+#[tauri::command]
+pub async fn transcode_video(
+    window: tauri::WebviewWindow,
+    wiki_path: String,
+    path: String,
+    id: String,
+) -> Result<Option<TranscodedVideo>, String> {
+    let config = crate::wiki_storage::load_wiki_configs(window.app_handle())?
+        .external_attachments
+        .get(&wiki_path)
+        .cloned()
+        .unwrap_or_default();
+    if !config.transcode_videos {
+        return Ok(None);
+    }
+    let source = crate::drag_drop::sanitize::validate_user_file_path(&path)?;
+    let ffmpeg = crate::find_ffmpeg().ok_or("ffmpeg was not found; install it to convert videos")?;
+
+    tokio::task::spawn_blocking(move || transcode(&window, &ffmpeg, &wiki_path, &source, &id))
+        .await
+        .map_err(|e| format!("Transcode failed: {}", e))?
+}
+
+/// Stop a running transcode; the video is imported as it is
+#[tauri::command]
+pub fn cancel_video_transcode(id: String) {
+    if let Some(mut child) = TRANSCODES.lock().unwrap().remove(&id) {
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IPHONE: &str = "Input #0, mov,mp4,m4a,3gp,3g2,mj2, from 'IMG_0001.MOV':
+  Duration: 00:01:02.50, start: 0.000000, bitrate: 8000 kb/s
+  Stream #0:0[0x1](und): Video: hevc (Main) (hvc1 / 0x31637668), yuv420p(tv, bt709), 1920x1080, 7800 kb/s, 29.97 fps
+  Stream #0:1[0x2](und): Audio: aac (LC) (mp4a / 0x6134706D), 44100 Hz, stereo, fltp, 160 kb/s";
+
+    #[test]
+    fn test_parse_probe() {
+        let probe = parse_probe(IPHONE);
+        assert_eq!(probe.duration, Some(62.5));
+        assert_eq!(codec(probe.video.as_deref().unwrap()), "hevc");
+        assert_eq!(codec(probe.audio.as_deref().unwrap()), "aac");
+        assert_eq!(parse_probe("not a video"), Probe::default());
+    }
+
+    #[test]
+    fn test_is_web_friendly() {
+        let mut probe = parse_probe(IPHONE);
+        assert!(!is_web_friendly("mov", &probe));
+        probe.video = Some("h264 (High) (avc1 / 0x31637661), yuv420p(progressive), 1280x720".to_string());
+        assert!(is_web_friendly("mp4", &probe));
+        assert!(!is_web_friendly("mkv", &probe));
+        probe.video = Some("h264 (High 10), yuv420p10le(progressive), 1280x720".to_string());
+        assert!(!is_web_friendly("mp4", &probe));
+    }
+
+    #[test]
+    fn test_parse_progress() {
+        assert_eq!(parse_progress("out_time_us=1500000"), Some(1.5));
+        assert_eq!(parse_progress("progress=continue"), None);
+        assert_eq!(parse_timestamp("01:00:01.5"), Some(3601.5));
+    }
+}
@@ -0,0 +1,244 @@
+//! Screenshot-based visual regression checks of a wiki (desktop)
+//!
+//! `visual_regression_check` renders tiddlers of a wiki at several viewport
+//! sizes (see `tiddler_image`) and compares them pixel by pixel with baselines
+//! from an earlier run, e.g. to catch a theme breaking after a core upgrade.
+//!
+//! Baselines are kept in `visual_baselines/<wiki hash>/<WIDTHxHEIGHT>/`, one
+//! PNG per tiddler. The first capture of a tiddler becomes its baseline. When
+//! a capture differs, it is saved next to the baseline as `.current.png`,
+//! with a `.diff.png` showing the changed pixels in red; the baseline is only
+//! replaced when the check runs with `update_baselines`.
+
+use std::path::{Path, PathBuf};
+
+use image::{ImageFormat, Rgba, RgbaImage};
+use serde::Serialize;
+
+const BASELINES_DIR: &str = "visual_baselines";
+
+/// Viewports without `viewports`: desktop, tablet, phone
+const DEFAULT_VIEWPORTS: [&str; 3] = ["1280x800", "768x1024", "375x667"];
+
+/// Share of differing pixels a capture may have and still match (0.1%)
+const DEFAULT_THRESHOLD: f64 = 0.001;
+
+/// Channel difference below which pixels count as equal (antialiasing noise)
+const CHANNEL_TOLERANCE: u8 = 16;
+
+/// The outcome for one tiddler at one viewport
+#[derive(Debug, Serialize)]
+pub struct VisualResult {
+    pub title: String,
+    pub viewport: String,
+    /// "new" (baseline saved), "same", "changed", "updated" or "error"
+    pub status: &'static str,
+    /// Share of differing pixels (1.0 when the size changed)
+    pub diff_ratio: Option<f64>,
+    pub baseline: String,
+    pub diff_image: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VisualReport {
+    pub baseline_dir: String,
+    /// Captures that differ from their baseline or failed
+    pub failures: usize,
+    pub results: Vec<VisualResult>,
+}
+
+/// File name of a tiddler's captures: readable part of the title plus a hash
+/// (titles can differ only in characters that aren't kept)
+fn capture_name(title: &str) -> String {
+    let readable: String = title
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .take(40)
+        .collect();
+    format!("{}-{:x}", readable, md5::compute(title.as_bytes()))
+}
+
+/// Number of pixels that differ by more than `CHANNEL_TOLERANCE` in a channel,
+/// and an image of the baseline (faded) with those pixels in red
+fn pixel_diff(baseline: &RgbaImage, current: &RgbaImage) -> (u64, RgbaImage) {
+    let mut differing = 0;
+    let diff = RgbaImage::from_fn(baseline.width(), baseline.height(), |x, y| {
+        let a = baseline.get_pixel(x, y);
+        let b = current.get_pixel(x, y);
+        if a.0.iter().zip(b.0.iter()).any(|(a, b)| a.abs_diff(*b) > CHANNEL_TOLERANCE) {
+            differing += 1;
+            Rgba([255, 0, 0, 255])
+        } else {
+            let [r, g, bl, _] = a.0;
+            let fade = |c: u8| 255 - (255 - c) / 4;
+            Rgba([fade(r), fade(g), fade(bl), 255])
+        }
+    });
+    (differing, diff)
+}
+
+/// Share of differing pixels of `current` against `baseline`, with the diff
+/// image (None when the sizes differ)
+fn compare(baseline: &RgbaImage, current: &RgbaImage) -> (f64, Option<RgbaImage>) {
+    if baseline.dimensions() != current.dimensions() {
+        return (1.0, None);
+    }
+    let (differing, diff) = pixel_diff(baseline, current);
+    let total = (baseline.width() as u64 * baseline.height() as u64).max(1);
+    (differing as f64 / total as f64, Some(diff))
+}
+
+fn load_png(path: &Path) -> Result<RgbaImage, String> {
+    image::open(path)
+        .map(|img| img.into_rgba8())
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))
+}
+
+fn write_file(path: &Path, bytes: &[u8]) -> Result<(), String> {
+    std::fs::write(path, bytes).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Capture `title` at `viewport` and check it against its baseline
+fn check_tiddler(
+    app: &tauri::AppHandle,
+    wiki_path: &Path,
+    dir: &Path,
+    title: &str,
+    viewport: &str,
+    threshold: f64,
+    update_baselines: bool,
+) -> Result<VisualResult, String> {
+    let (width, height) = crate::tiddler_image::parse_size(Some(viewport))?;
+    let dir = dir.join(format!("{}x{}", width, height));
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create baseline folder: {}", e))?;
+    let name = capture_name(title);
+    let baseline_path = dir.join(format!("{}.png", name));
+    let current_path = dir.join(format!("{}.current.png", name));
+    let diff_path = dir.join(format!("{}.diff.png", name));
+    let mut result = VisualResult {
+        title: title.to_string(),
+        viewport: format!("{}x{}", width, height),
+        status: "same",
+        diff_ratio: None,
+        baseline: baseline_path.to_string_lossy().to_string(),
+        diff_image: None,
+        error: None,
+    };
+
+    let png = crate::tiddler_image::capture_png(app, wiki_path, title, width, height)?;
+    let _ = std::fs::remove_file(&current_path);
+    let _ = std::fs::remove_file(&diff_path);
+    if !baseline_path.exists() || update_baselines {
+        result.status = if baseline_path.exists() { "updated" } else { "new" };
+        write_file(&baseline_path, &png)?;
+        return Ok(result);
+    }
+
+    let current = image::load_from_memory_with_format(&png, ImageFormat::Png)
+        .map_err(|e| format!("Failed to decode the capture: {}", e))?
+        .into_rgba8();
+    let (ratio, diff) = compare(&load_png(&baseline_path)?, &current);
+    result.diff_ratio = Some(ratio);
+    if ratio > threshold {
+        result.status = "changed";
+        write_file(&current_path, &png)?;
+        if let Some(diff) = diff {
+            diff.save_with_format(&diff_path, ImageFormat::Png)
+                .map_err(|e| format!("Failed to write {}: {}", diff_path.display(), e))?;
+            result.diff_image = Some(diff_path.to_string_lossy().to_string());
+        }
+    }
+    Ok(result)
+}
+
+/// Folder of the baselines of a wiki
+fn baseline_dir(app: &tauri::AppHandle, wiki: &str) -> Result<PathBuf, String> {
+    Ok(crate::get_data_dir(app)?
+        .join(BASELINES_DIR)
+        .join(format!("{:x}", md5::compute(wiki.as_bytes()))))
+}
+
+/// Render `titles` of the wiki at `wiki` at each of `viewports` ("WIDTHxHEIGHT",
+/// default desktop, tablet and phone sizes) and compare them with their
+/// baselines. A capture matches when at most `threshold` (0..1, default 0.001)
+/// of its pixels differ. With `update_baselines`, the captures replace the
+/// baselines instead.
+#[tauri::command]
+pub async fn visual_regression_check(
+    app: tauri::AppHandle,
+    wiki: String,
+    titles: Vec<String>,
+    viewports: Option<Vec<String>>,
+    threshold: Option<f64>,
+    update_baselines: Option<bool>,
+) -> Result<VisualReport, String> {
+    if !Path::new(&wiki).is_file() {
+        return Err(format!("Wiki not found: {}", wiki));
+    }
+    if titles.is_empty() {
+        return Err("No tiddlers to check".to_string());
+    }
+    let viewports = viewports
+        .filter(|v| !v.is_empty())
+        .unwrap_or_else(|| DEFAULT_VIEWPORTS.iter().map(|v| v.to_string()).collect());
+    let threshold = threshold.unwrap_or(DEFAULT_THRESHOLD).clamp(0.0, 1.0);
+    let update_baselines = update_baselines.unwrap_or(false);
+    let dir = baseline_dir(&app, &wiki)?;
+
+    tokio::task::spawn_blocking(move || {
+        let wiki_path = Path::new(&wiki);
+        let mut results = Vec::new();
+        for viewport in &viewports {
+            for title in &titles {
+                let result = check_tiddler(&app, wiki_path, &dir, title, viewport, threshold, update_baselines)
+                    .unwrap_or_else(|error| VisualResult {
+                        title: title.clone(),
+                        viewport: viewport.clone(),
+                        status: "error",
+                        diff_ratio: None,
+                        baseline: String::new(),
+                        diff_image: None,
+                        error: Some(error),
+                    });
+                results.push(result);
+            }
+        }
+        let failures = results.iter().filter(|r| matches!(r.status, "changed" | "error")).count();
+        Ok(VisualReport { baseline_dir: dir.to_string_lossy().to_string(), failures, results })
+    })
+    .await
+    .map_err(|e| format!("Failed to spawn blocking task: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compare() {
+        let baseline = RgbaImage::from_pixel(10, 10, Rgba([200, 200, 200, 255]));
+        let mut current = baseline.clone();
+        // Within the tolerance
+        current.put_pixel(0, 0, Rgba([210, 195, 200, 255]));
+        assert_eq!(compare(&baseline, &current).0, 0.0);
+
+        current.put_pixel(1, 0, Rgba([0, 0, 0, 255]));
+        current.put_pixel(2, 0, Rgba([200, 200, 200, 0]));
+        let (ratio, diff) = compare(&baseline, &current);
+        assert_eq!(ratio, 0.02);
+        let diff = diff.unwrap();
+        assert_eq!(diff.get_pixel(1, 0), &Rgba([255, 0, 0, 255]));
+        assert_ne!(diff.get_pixel(3, 0), &Rgba([255, 0, 0, 255]));
+
+        let resized = RgbaImage::from_pixel(10, 12, Rgba([200, 200, 200, 255]));
+        assert_eq!(compare(&baseline, &resized).0, 1.0);
+        assert!(compare(&baseline, &resized).1.is_none());
+    }
+
+    #[test]
+    fn test_capture_name() {
+        assert_ne!(capture_name("A/B"), capture_name("A:B"));
+        assert!(capture_name("$:/theme/My Theme").starts_with("___theme_My_Theme-"));
+    }
+}
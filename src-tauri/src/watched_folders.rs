@@ -0,0 +1,391 @@
+//! Watched folders: directories scanned for TiddlyWiki files and wiki folders
+//!
+//! Wikis found in a watched folder (e.g. a Syncthing or Dropbox directory) are
+//! added to the wiki list automatically, grouped under the watched folder's name.
+//! Folders are rescanned at startup and whenever their contents change.
+//! Each wiki is only added the first time it is discovered, so removing it
+//! from the list sticks. Removing a watched folder keeps the wikis that were
+//! already discovered.
+//!
+//! Wikis of the list below a watched folder whose file is gone (moved or
+//! deleted on another machine) are marked missing, until it's back.
+
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use tauri::Emitter;
+
+use crate::types::WikiEntry;
+use crate::utils;
+
+/// How deep below a watched folder wikis are looked for
+const MAX_DEPTH: usize = 3;
+
+/// How much of an HTML file is read to recognise TiddlyWiki
+const SNIFF_BYTES: u64 = 64 * 1024;
+
+/// Wait for a burst of file changes (e.g. a sync run) to settle before rescanning
+#[cfg(not(target_os = "android"))]
+const RESCAN_DELAY: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// Active file watcher, replaced whenever the watched folders change
+#[cfg(not(target_os = "android"))]
+static WATCHER: std::sync::Mutex<Option<notify::RecommendedWatcher>> = std::sync::Mutex::new(None);
+
+/// What the last scan that changed the wiki list did (for the landing page,
+/// which may not listen yet when the scan at startup finishes)
+static LAST_SUMMARY: std::sync::Mutex<Option<ScanSummary>> = std::sync::Mutex::new(None);
+
+/// Wikis a scan of the watched folders added to the list, found missing, or found again
+#[derive(Clone, Debug, Serialize)]
+pub struct ScanSummary {
+    pub added: Vec<String>,
+    pub missing: Vec<String>,
+    pub reappeared: Vec<String>,
+}
+
+/// Whether an HTML file is a TiddlyWiki (checks the head for TiddlyWiki's markers)
+fn is_tiddlywiki_file(path: &Path) -> bool {
+    let is_html = path
+        .extension()
+        .map(|e| e.eq_ignore_ascii_case("html") || e.eq_ignore_ascii_case("htm"))
+        .unwrap_or(false);
+    if !is_html {
+        return false;
+    }
+    let mut head = Vec::new();
+    let read = std::fs::File::open(path).and_then(|f| f.take(SNIFF_BYTES).read_to_end(&mut head));
+    if read.is_err() {
+        return false;
+    }
+    let head = String::from_utf8_lossy(&head);
+    head.contains(r#"content="TiddlyWiki""#) || head.contains("tiddlywiki-tiddler-store") || head.contains(r#"id="storeArea""#)
+}
+
+/// Find wiki files and wiki folders below `dir`. Hidden entries (sync tool
+/// metadata, `.backups`) are skipped, and wiki folders are not descended into.
+pub(crate) fn scan_folder(dir: &Path) -> Vec<(PathBuf, bool)> {
+    fn visit(dir: &Path, depth: usize, found: &mut Vec<(PathBuf, bool)>) {
+        let entries = match std::fs::read_dir(dir) {
+            Ok(e) => e,
+            Err(_) => return,
+        };
+        let mut entries: Vec<_> = entries.flatten().collect();
+        entries.sort_by_key(|e| e.file_name());
+        for entry in entries {
+            if entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+            let path = entry.path();
+            if path.is_dir() {
+                if utils::is_wiki_folder(&path) {
+                    found.push((path, true));
+                } else if depth < MAX_DEPTH {
+                    visit(&path, depth + 1, found);
+                }
+            } else if is_tiddlywiki_file(&path) {
+                found.push((path, false));
+            }
+        }
+    }
+
+    let mut found = Vec::new();
+    visit(dir, 1, &mut found);
+    found
+}
+
+/// Group name for wikis discovered in a watched folder
+fn group_name(dir: &Path) -> String {
+    dir.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| dir.to_string_lossy().into_owned())
+}
+
+#[cfg(not(target_os = "android"))]
+fn load_watched_folders(app: &tauri::AppHandle) -> Vec<String> {
+    crate::wiki_storage::load_app_settings(app)
+        .map(|s| s.watched_folders)
+        .unwrap_or_default()
+}
+
+/// Mark the wikis of the list below one of `folders` whose file is gone as
+/// missing, and unmark the ones that are back. Returns their paths.
+fn reconcile(entries: &mut [WikiEntry], folders: &[PathBuf]) -> (Vec<String>, Vec<String>) {
+    let mut missing = Vec::new();
+    let mut reappeared = Vec::new();
+    for entry in entries.iter_mut() {
+        let path = Path::new(&entry.path);
+        if !folders.iter().any(|folder| path.starts_with(folder)) {
+            continue;
+        }
+        let exists = path.exists();
+        if exists == entry.missing {
+            entry.missing = !exists;
+            if exists {
+                reappeared.push(entry.path.clone());
+            } else {
+                missing.push(entry.path.clone());
+            }
+        }
+    }
+    (missing, reappeared)
+}
+
+/// Scan all watched folders, add newly discovered wikis to the wiki list and
+/// mark the ones whose files vanished. Emits `watched-folders-changed` with
+/// the added entries when the list changed, and `watched-folders-reconciled`
+/// with a summary when anything was added, went missing or came back.
+pub fn scan_watched_folders(app: &tauri::AppHandle) {
+    let mut settings = match crate::wiki_storage::load_app_settings(app) {
+        Ok(s) => s,
+        Err(_) => return,
+    };
+    let mut entries = crate::wiki_storage::load_recent_files_from_disk(app);
+    let mut added = Vec::new();
+    let mut newly_seen = Vec::new();
+
+    // Folders that aren't there (an unmounted drive, a sync client not set up
+    // yet) are left alone, rather than marking all of their wikis missing
+    let folders: Vec<PathBuf> = settings
+        .watched_folders
+        .iter()
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_dir())
+        .collect();
+
+    for dir in &folders {
+        let group = group_name(dir);
+        for (path, is_folder) in scan_folder(dir) {
+            let path = path.to_string_lossy().into_owned();
+            if settings.discovered_wikis.iter().chain(newly_seen.iter()).any(|p| utils::paths_equal(p, &path)) {
+                continue;
+            }
+            newly_seen.push(path.clone());
+            if entries.iter().any(|e| utils::paths_equal(&e.path, &path)) {
+                continue;
+            }
+            let filename = Path::new(&path)
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_else(|| path.clone());
+            added.push(WikiEntry {
+                path: path.clone(),
+                filename,
+                display_path: Some(path),
+                favicon: None,
+                is_folder,
+                backups_enabled: !is_folder,
+                backup_dir: None,
+                backup_count: None,
+                group: Some(group.clone()),
+                sync_enabled: false,
+                sync_id: None,
+                sync_peers: vec![],
+                relay_room: None,
+                sync_mode: None,
+                custom_icon: None,
+                accent_color: None,
+                emoji: None,
+                scheduled_backup: None,
+                confirm_remote_deletions: false,
+                sync_schedule: None,
+                hotkey: None,
+                sync_priority: None,
+                missing: false,
+            });
+        }
+    }
+
+    if !newly_seen.is_empty() {
+        settings.discovered_wikis.extend(newly_seen);
+        if let Err(e) = crate::wiki_storage::save_app_settings(app, &settings) {
+            eprintln!("[TiddlyDesktop] Failed to save discovered wikis: {}", e);
+        }
+    }
+
+    let (missing, reappeared) = reconcile(&mut entries, &folders);
+    if added.is_empty() && missing.is_empty() && reappeared.is_empty() {
+        return;
+    }
+    // Discovered wikis go to the end so they don't push recently opened ones out
+    entries.extend(added.iter().cloned());
+    if let Err(e) = crate::wiki_storage::save_recent_files_to_disk(app, &entries) {
+        eprintln!("[TiddlyDesktop] Failed to update wikis from watched folders: {}", e);
+        return;
+    }
+    eprintln!(
+        "[TiddlyDesktop] Watched folders: {} wikis added, {} missing, {} back",
+        added.len(),
+        missing.len(),
+        reappeared.len()
+    );
+    let summary = ScanSummary {
+        added: added.iter().map(|entry| entry.path.clone()).collect(),
+        missing,
+        reappeared,
+    };
+    *LAST_SUMMARY.lock().unwrap() = Some(summary.clone());
+    let _ = app.emit("watched-folders-changed", &added);
+    let _ = app.emit("watched-folders-reconciled", &summary);
+}
+
+/// (Re)start watching the configured folders. Changes are debounced and
+/// trigger a rescan.
+#[cfg(not(target_os = "android"))]
+fn restart_watcher(app: &tauri::AppHandle) {
+    use notify::event::ModifyKind;
+    use notify::{Config, EventKind, RecursiveMode, Watcher};
+
+    let mut guard = WATCHER.lock().unwrap();
+    *guard = None;
+
+    let folders = load_watched_folders(app);
+    if folders.is_empty() {
+        return;
+    }
+
+    let (tx, rx) = std::sync::mpsc::channel::<notify::Result<notify::Event>>();
+    let mut watcher = match notify::RecommendedWatcher::new(tx, Config::default()) {
+        Ok(w) => w,
+        Err(e) => {
+            eprintln!("[TiddlyDesktop] Failed to create watched folder watcher: {}", e);
+            return;
+        }
+    };
+    for folder in &folders {
+        if let Err(e) = watcher.watch(Path::new(folder), RecursiveMode::Recursive) {
+            eprintln!("[TiddlyDesktop] Failed to watch {}: {}", folder, e);
+        }
+    }
+    *guard = Some(watcher);
+
+    // The thread ends when the watcher (and with it the sender) is dropped
+    let app = app.clone();
+    std::thread::spawn(move || {
+        while let Ok(event) = rx.recv() {
+            // New, removed or renamed files (sync tools write to a temp name first)
+            let relevant = event.is_ok_and(|e| {
+                matches!(e.kind, EventKind::Create(_) | EventKind::Remove(_) | EventKind::Modify(ModifyKind::Name(_)))
+            });
+            if !relevant {
+                continue;
+            }
+            // Swallow the rest of the burst, then rescan once
+            while rx.recv_timeout(RESCAN_DELAY).is_ok() {}
+            scan_watched_folders(&app);
+        }
+    });
+}
+
+/// Scan watched folders and start watching them (called once at startup, in the background)
+pub fn start(app: &tauri::AppHandle) {
+    let app = app.clone();
+    std::thread::spawn(move || {
+        scan_watched_folders(&app);
+        #[cfg(not(target_os = "android"))]
+        restart_watcher(&app);
+    });
+}
+
+/// Get the watched folders
+#[tauri::command]
+pub fn get_watched_folders(app: tauri::AppHandle) -> Result<Vec<String>, String> {
+    Ok(crate::wiki_storage::load_app_settings(&app)?.watched_folders)
+}
+
+/// What the last scan of the watched folders changed in the wiki list, if anything
+#[tauri::command]
+pub fn get_watched_folders_summary() -> Option<ScanSummary> {
+    LAST_SUMMARY.lock().unwrap().clone()
+}
+
+/// Add a watched folder and scan it right away. Returns the watched folders.
+#[tauri::command]
+pub async fn add_watched_folder(app: tauri::AppHandle, path: String) -> Result<Vec<String>, String> {
+    let dir = crate::drag_drop::sanitize::validate_user_directory_path(&path)?;
+    let dir = dir.to_string_lossy().into_owned();
+
+    let mut settings = crate::wiki_storage::load_app_settings(&app)?;
+    if !settings.watched_folders.iter().any(|f| utils::paths_equal(f, &dir)) {
+        settings.watched_folders.push(dir);
+        crate::wiki_storage::save_app_settings(&app, &settings)?;
+    }
+
+    let app_clone = app.clone();
+    tokio::task::spawn_blocking(move || {
+        scan_watched_folders(&app_clone);
+        #[cfg(not(target_os = "android"))]
+        restart_watcher(&app_clone);
+    })
+    .await
+    .map_err(|e| format!("Failed to spawn blocking task: {}", e))?;
+    Ok(settings.watched_folders)
+}
+
+/// Stop watching a folder. Wikis already discovered in it stay in the list.
+#[tauri::command]
+pub fn remove_watched_folder(app: tauri::AppHandle, path: String) -> Result<Vec<String>, String> {
+    let mut settings = crate::wiki_storage::load_app_settings(&app)?;
+    settings.watched_folders.retain(|f| !utils::paths_equal(f, &path));
+    // Forget what was discovered there, so watching it again re-adds removed wikis
+    let folder = PathBuf::from(&path);
+    settings.discovered_wikis.retain(|p| !Path::new(p).starts_with(&folder));
+    crate::wiki_storage::save_app_settings(&app, &settings)?;
+    #[cfg(not(target_os = "android"))]
+    restart_watcher(&app);
+    Ok(settings.watched_folders)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_finds_wiki_files_and_folders() {
+        let dir = std::env::temp_dir().join(format!("td-watched-folder-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("nested").join("Notes").join("tiddlers")).unwrap();
+        std::fs::create_dir_all(dir.join(".stfolder")).unwrap();
+        std::fs::write(dir.join("wiki.html"), r#"<html><head><meta name="application-name" content="TiddlyWiki" /></head></html>"#).unwrap();
+        std::fs::write(dir.join("page.html"), "<html><body>Not a wiki</body></html>").unwrap();
+        std::fs::write(dir.join(".stfolder").join("hidden.html"), r#"<meta content="TiddlyWiki">"#).unwrap();
+        std::fs::write(dir.join("nested").join("Notes").join("tiddlywiki.info"), "{}").unwrap();
+        // Files inside a wiki folder are not separate wikis
+        std::fs::write(dir.join("nested").join("Notes").join("tiddlers").join("inner.html"), r#"content="TiddlyWiki""#).unwrap();
+
+        let found = scan_folder(&dir);
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!(found, vec![
+            (dir.join("nested").join("Notes"), true),
+            (dir.join("wiki.html"), false),
+        ]);
+    }
+
+    #[test]
+    fn test_marks_vanished_wikis_missing() {
+        let dir = std::env::temp_dir().join(format!("td-watched-reconcile-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("here.html"), "").unwrap();
+        let entry = |path: PathBuf| -> WikiEntry {
+            serde_json::from_value(serde_json::json!({ "path": path, "filename": "wiki.html" })).unwrap()
+        };
+        let elsewhere = std::env::temp_dir().join("td-not-watched").join("gone.html");
+        let mut entries = vec![entry(dir.join("here.html")), entry(dir.join("gone.html")), entry(elsewhere)];
+
+        let watched = std::slice::from_ref(&dir);
+        let first = reconcile(&mut entries, watched);
+        let again = reconcile(&mut entries, watched);
+        std::fs::write(dir.join("gone.html"), "").unwrap();
+        let back = reconcile(&mut entries, watched);
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let gone = dir.join("gone.html").to_string_lossy().into_owned();
+        assert_eq!(first, (vec![gone.clone()], vec![]));
+        assert_eq!(again, (vec![], vec![]));
+        assert_eq!(back, (vec![], vec![gone]));
+        // Only wikis below a watched folder are checked
+        assert!(!entries[2].missing);
+    }
+}
@@ -0,0 +1,582 @@
+//! WebDAV access to the tiddler files of folder wikis
+//!
+//! When enabled in the settings, the built-in folder server (`folder_server`)
+//! also serves the wiki's `tiddlers/` folder under `/dav/` on the wiki's port,
+//! so other TiddlyWiki clients and editors can mount it or edit tiddler files
+//! directly. It speaks WebDAV class 1 and 2:
+//! - `PROPFIND` (depth 0 and 1), `GET`/`HEAD`, `PUT`, `DELETE`, `MKCOL`,
+//!   `COPY` and `MOVE`
+//! - ETags from size and modification time; `If-Match` and `If-None-Match`
+//!   guard `PUT` and `DELETE` against overwriting changes made meanwhile
+//! - `LOCK`/`UNLOCK` with exclusive write locks kept in memory: writing a
+//!   locked file needs its lock token in the `If` header
+//!
+//! Changes are picked up by the wiki like edits by any other program.
+
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+
+use tiny_http::{Header, Request, Response, StatusCode};
+
+/// Where the tiddler files are served
+pub const DAV_PREFIX: &str = "/dav";
+
+/// Lock timeout when the client doesn't ask for one, and the longest granted
+const DEFAULT_LOCK_TIMEOUT: Duration = Duration::from_secs(600);
+const MAX_LOCK_TIMEOUT: Duration = Duration::from_secs(3600);
+
+/// Largest request body read (tiddler files with images or PDFs are the big ones)
+pub const MAX_BODY_BYTES: u64 = 64 * 1024 * 1024;
+
+struct Lock {
+    token: String,
+    expires: Instant,
+}
+
+/// A directory served over WebDAV, with its locks
+pub struct DavShare {
+    root: PathBuf,
+    locks: Mutex<HashMap<PathBuf, Lock>>,
+}
+
+/// The body of a request (uploaded file contents for `PUT`), read before
+/// the request is handled so a slow upload holds up nothing else; None when
+/// it's larger than `MAX_BODY_BYTES`
+pub fn read_body(request: &mut Request) -> Result<Option<Vec<u8>>, String> {
+    let mut body = Vec::new();
+    request
+        .as_reader()
+        .take(MAX_BODY_BYTES + 1)
+        .read_to_end(&mut body)
+        .map_err(|e| format!("Failed to read request body: {}", e))?;
+    Ok((body.len() as u64 <= MAX_BODY_BYTES).then_some(body))
+}
+
+/// Whether a request path belongs to the WebDAV share
+pub fn is_dav_path(path: &str) -> bool {
+    path == DAV_PREFIX || path.starts_with(&format!("{}/", DAV_PREFIX))
+}
+
+/// The file a request path refers to, or None for paths outside the share
+/// (`..`, absolute or drive components)
+fn resolve(root: &Path, url_path: &str) -> Option<PathBuf> {
+    let relative = url_path.strip_prefix(DAV_PREFIX)?;
+    if !relative.is_empty() && !relative.starts_with('/') {
+        return None;
+    }
+    let mut path = root.to_path_buf();
+    for segment in relative.split('/').filter(|s| !s.is_empty()) {
+        let segment = urlencoding::decode(segment).ok()?;
+        if segment == "." || segment == ".." || segment.contains(['/', '\\', ':']) {
+            return None;
+        }
+        path.push(segment.as_ref());
+    }
+    Some(path)
+}
+
+/// URL path of a file of the share (directories end with `/`)
+fn href(root: &Path, path: &Path, is_dir: bool) -> String {
+    let mut href = DAV_PREFIX.to_string();
+    if let Ok(relative) = path.strip_prefix(root) {
+        for segment in relative.iter() {
+            href.push('/');
+            href.push_str(&urlencoding::encode(&segment.to_string_lossy()));
+        }
+    }
+    if is_dir {
+        href.push('/');
+    }
+    href
+}
+
+/// ETag of a file as last written
+fn etag(len: u64, modified: SystemTime) -> String {
+    let nanos = modified
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    format!("\"{:x}-{:x}\"", len, nanos)
+}
+
+fn file_etag(path: &Path) -> Option<String> {
+    let metadata = std::fs::metadata(path).ok()?;
+    metadata.is_file().then_some(())?;
+    Some(etag(metadata.len(), metadata.modified().ok()?))
+}
+
+/// HTTP date (RFC 1123) for `Last-Modified` and `getlastmodified`
+fn http_date(time: SystemTime) -> String {
+    chrono::DateTime::<chrono::Utc>::from(time).format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
+
+/// Requested lock timeout from a `Timeout` header (`Second-N` or `Infinite`)
+fn lock_timeout(header: Option<&str>) -> Duration {
+    let requested = header
+        .and_then(|value| value.split(',').next())
+        .map(str::trim)
+        .and_then(|value| match value {
+            "Infinite" => Some(MAX_LOCK_TIMEOUT),
+            _ => value.strip_prefix("Second-")?.parse().ok().map(Duration::from_secs),
+        });
+    requested.unwrap_or(DEFAULT_LOCK_TIMEOUT).min(MAX_LOCK_TIMEOUT)
+}
+
+/// Path of a `Destination` header (absolute URL or path)
+fn destination_path(destination: &str) -> &str {
+    match destination.split_once("://") {
+        Some((_, rest)) => rest.find('/').map(|i| &rest[i..]).unwrap_or("/"),
+        None => destination,
+    }
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name.as_bytes(), value.as_bytes()).unwrap()
+}
+
+fn header_value(request: &Request, name: &'static str) -> Option<String> {
+    request
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv(name))
+        .map(|h| h.value.as_str().to_string())
+}
+
+fn respond<R: Read>(request: Request, response: Response<R>) -> Result<(), String> {
+    request.respond(response).map_err(|e| format!("Failed to send response: {}", e))
+}
+
+fn respond_status(request: Request, status: u16) -> Result<(), String> {
+    respond(request, Response::empty(StatusCode(status)))
+}
+
+fn respond_xml(request: Request, status: u16, xml: String) -> Result<(), String> {
+    respond(request, Response::from_string(xml)
+        .with_status_code(StatusCode(status))
+        .with_header(header("Content-Type", "application/xml; charset=utf-8")))
+}
+
+/// Copy a file or folder; symbolic links are left out, so nothing outside
+/// the share is copied into it
+fn copy_recursive(from: &Path, to: &Path) -> std::io::Result<()> {
+    let file_type = std::fs::symlink_metadata(from)?.file_type();
+    if file_type.is_symlink() {
+        Ok(())
+    } else if file_type.is_dir() {
+        std::fs::create_dir_all(to)?;
+        for entry in std::fs::read_dir(from)? {
+            let entry = entry?;
+            copy_recursive(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        Ok(())
+    } else {
+        std::fs::copy(from, to).map(|_| ())
+    }
+}
+
+fn remove_any(path: &Path) -> std::io::Result<()> {
+    if path.is_dir() {
+        std::fs::remove_dir_all(path)
+    } else {
+        std::fs::remove_file(path)
+    }
+}
+
+impl DavShare {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root, locks: Mutex::new(HashMap::new()) }
+    }
+
+    /// Whether the request may write `path`: unlocked, or its lock token is
+    /// in the `If` header
+    fn may_write(&self, request: &Request, path: &Path) -> bool {
+        let mut locks = self.locks.lock().unwrap();
+        locks.retain(|_, lock| lock.expires > Instant::now());
+        match locks.get(path) {
+            Some(lock) => header_value(request, "If").is_some_and(|value| value.contains(&lock.token)),
+            None => true,
+        }
+    }
+
+    fn forget_locks(&self, path: &Path) {
+        self.locks.lock().unwrap().retain(|locked, _| !locked.starts_with(path));
+    }
+
+    /// Answer a WebDAV request with its body (from `read_body`); returns
+    /// whether files were changed
+    pub fn handle(&self, request: Request, body: Vec<u8>) -> Result<bool, String> {
+        let url = request.url().split('?').next().unwrap_or("").to_string();
+        let Some(path) = resolve(&self.root, &url) else {
+            respond_status(request, 403)?;
+            return Ok(false);
+        };
+        let method = request.method().clone();
+        match method.as_str() {
+            "OPTIONS" => {
+                respond(request, Response::empty(StatusCode(200))
+                    .with_header(header("DAV", "1, 2"))
+                    .with_header(header("MS-Author-Via", "DAV"))
+                    .with_header(header("Allow", "OPTIONS, GET, HEAD, PUT, DELETE, PROPFIND, MKCOL, COPY, MOVE, LOCK, UNLOCK")))?;
+                Ok(false)
+            }
+            "PROPFIND" => self.propfind(request, &path).map(|_| false),
+            "GET" | "HEAD" => self.get(request, &path).map(|_| false),
+            "PUT" => self.put(request, &path, &body),
+            "DELETE" => self.delete(request, &path),
+            "MKCOL" => self.mkcol(request, &path),
+            "COPY" => self.copy_or_move(request, &path, false),
+            "MOVE" => self.copy_or_move(request, &path, true),
+            "LOCK" => self.lock(request, &path),
+            "UNLOCK" => self.unlock(request, &path).map(|_| false),
+            _ => {
+                respond_status(request, 405)?;
+                Ok(false)
+            }
+        }
+    }
+
+    fn propfind(&self, request: Request, path: &Path) -> Result<(), String> {
+        let Ok(metadata) = std::fs::metadata(path) else {
+            return respond_status(request, 404);
+        };
+        let mut entries = vec![(path.to_path_buf(), metadata.clone())];
+        // Depth infinity is answered like depth 1
+        if metadata.is_dir() && header_value(&request, "Depth").as_deref() != Some("0") {
+            if let Ok(read_dir) = std::fs::read_dir(path) {
+                let mut children: Vec<(PathBuf, std::fs::Metadata)> = read_dir
+                    .flatten()
+                    .filter_map(|entry| Some((entry.path(), entry.metadata().ok()?)))
+                    .collect();
+                children.sort_by(|a, b| a.0.cmp(&b.0));
+                entries.extend(children);
+            }
+        }
+
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<D:multistatus xmlns:D=\"DAV:\">\n");
+        for (entry_path, metadata) in &entries {
+            let is_dir = metadata.is_dir();
+            let name = entry_path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+            xml.push_str("<D:response><D:href>");
+            xml.push_str(&xml_escape(&href(&self.root, entry_path, is_dir)));
+            xml.push_str("</D:href><D:propstat><D:prop>");
+            xml.push_str(&format!("<D:displayname>{}</D:displayname>", xml_escape(&name)));
+            if let Ok(modified) = metadata.modified() {
+                xml.push_str(&format!("<D:getlastmodified>{}</D:getlastmodified>", http_date(modified)));
+                if !is_dir {
+                    xml.push_str(&format!("<D:getetag>{}</D:getetag>", xml_escape(&etag(metadata.len(), modified))));
+                }
+            }
+            if is_dir {
+                xml.push_str("<D:resourcetype><D:collection/></D:resourcetype>");
+            } else {
+                xml.push_str("<D:resourcetype/>");
+                xml.push_str(&format!("<D:getcontentlength>{}</D:getcontentlength>", metadata.len()));
+                xml.push_str(&format!("<D:getcontenttype>{}</D:getcontenttype>", crate::utils::get_mime_type(entry_path)));
+            }
+            xml.push_str("<D:supportedlock><D:lockentry><D:lockscope><D:exclusive/></D:lockscope><D:locktype><D:write/></D:locktype></D:lockentry></D:supportedlock>");
+            xml.push_str("</D:prop><D:status>HTTP/1.1 200 OK</D:status></D:propstat></D:response>\n");
+        }
+        xml.push_str("</D:multistatus>\n");
+        respond_xml(request, 207, xml)
+    }
+
+    fn get(&self, request: Request, path: &Path) -> Result<(), String> {
+        if path.is_dir() {
+            return respond_status(request, 405);
+        }
+        let Ok(file) = std::fs::File::open(path) else {
+            return respond_status(request, 404);
+        };
+        let mut response = Response::from_file(file)
+            .with_header(header("Content-Type", crate::utils::get_mime_type(path)));
+        if let Ok(metadata) = std::fs::metadata(path) {
+            if let Ok(modified) = metadata.modified() {
+                response = response
+                    .with_header(header("ETag", &etag(metadata.len(), modified)))
+                    .with_header(header("Last-Modified", &http_date(modified)));
+            }
+        }
+        respond(request, response)
+    }
+
+    fn put(&self, request: Request, path: &Path, body: &[u8]) -> Result<bool, String> {
+        if path.is_dir() {
+            respond_status(request, 405)?;
+            return Ok(false);
+        }
+        if !path.parent().is_some_and(Path::is_dir) {
+            respond_status(request, 409)?;
+            return Ok(false);
+        }
+        if !self.may_write(&request, path) {
+            respond_status(request, 423)?;
+            return Ok(false);
+        }
+        let current = file_etag(path);
+        let if_match = header_value(&request, "If-Match");
+        let if_none_match = header_value(&request, "If-None-Match");
+        let precondition_failed = match (&if_match, &if_none_match) {
+            (Some(expected), _) if expected.trim() != "*" => current.as_deref() != Some(expected.trim()),
+            (Some(_), _) => current.is_none(),
+            (_, Some(none)) if none.trim() == "*" => current.is_some(),
+            _ => false,
+        };
+        if precondition_failed {
+            respond_status(request, 412)?;
+            return Ok(false);
+        }
+
+        // Write next to the file and rename, so readers never see half a file
+        let temp = path.with_file_name(format!(
+            ".{}.davtmp",
+            path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default()
+        ));
+        let written = std::fs::write(&temp, body).and_then(|_| std::fs::rename(&temp, path));
+        if let Err(e) = written {
+            let _ = std::fs::remove_file(&temp);
+            respond_status(request, 500)?;
+            return Err(format!("Failed to write {}: {}", path.display(), e));
+        }
+        let mut response = Response::empty(StatusCode(if current.is_some() { 204 } else { 201 }));
+        if let Some(etag) = file_etag(path) {
+            response = response.with_header(header("ETag", &etag));
+        }
+        respond(request, response)?;
+        Ok(true)
+    }
+
+    fn delete(&self, request: Request, path: &Path) -> Result<bool, String> {
+        if path == self.root {
+            respond_status(request, 403)?;
+            return Ok(false);
+        }
+        if !path.exists() {
+            respond_status(request, 404)?;
+            return Ok(false);
+        }
+        if !self.may_write(&request, path) {
+            respond_status(request, 423)?;
+            return Ok(false);
+        }
+        if let Some(expected) = header_value(&request, "If-Match") {
+            if expected.trim() != "*" && file_etag(path).as_deref() != Some(expected.trim()) {
+                respond_status(request, 412)?;
+                return Ok(false);
+            }
+        }
+        match remove_any(path) {
+            Ok(()) => {
+                self.forget_locks(path);
+                respond_status(request, 204)?;
+                Ok(true)
+            }
+            Err(e) => {
+                respond_status(request, 500)?;
+                Err(format!("Failed to delete {}: {}", path.display(), e))
+            }
+        }
+    }
+
+    fn mkcol(&self, request: Request, path: &Path) -> Result<bool, String> {
+        if path.exists() {
+            respond_status(request, 405)?;
+            return Ok(false);
+        }
+        if !path.parent().is_some_and(Path::is_dir) {
+            respond_status(request, 409)?;
+            return Ok(false);
+        }
+        match std::fs::create_dir(path) {
+            Ok(()) => {
+                respond_status(request, 201)?;
+                Ok(true)
+            }
+            Err(e) => {
+                respond_status(request, 500)?;
+                Err(format!("Failed to create {}: {}", path.display(), e))
+            }
+        }
+    }
+
+    fn copy_or_move(&self, request: Request, path: &Path, is_move: bool) -> Result<bool, String> {
+        let Some(destination) = header_value(&request, "Destination")
+            .and_then(|value| resolve(&self.root, destination_path(&value)))
+        else {
+            respond_status(request, 400)?;
+            return Ok(false);
+        };
+        if !path.exists() {
+            respond_status(request, 404)?;
+            return Ok(false);
+        }
+        if destination == path || destination.starts_with(path) {
+            respond_status(request, 403)?;
+            return Ok(false);
+        }
+        if !destination.parent().is_some_and(Path::is_dir) {
+            respond_status(request, 409)?;
+            return Ok(false);
+        }
+        if (is_move && !self.may_write(&request, path)) || !self.may_write(&request, &destination) {
+            respond_status(request, 423)?;
+            return Ok(false);
+        }
+        let existed = destination.exists();
+        if existed {
+            if header_value(&request, "Overwrite").as_deref() == Some("F") {
+                respond_status(request, 412)?;
+                return Ok(false);
+            }
+            if let Err(e) = remove_any(&destination) {
+                respond_status(request, 500)?;
+                return Err(format!("Failed to replace {}: {}", destination.display(), e));
+            }
+        }
+        let result = if is_move {
+            std::fs::rename(path, &destination)
+        } else {
+            copy_recursive(path, &destination)
+        };
+        if let Err(e) = result {
+            respond_status(request, 500)?;
+            return Err(format!("Failed to copy {} to {}: {}", path.display(), destination.display(), e));
+        }
+        if is_move {
+            self.forget_locks(path);
+        }
+        respond_status(request, if existed { 204 } else { 201 })?;
+        Ok(true)
+    }
+
+    fn lock(&self, request: Request, path: &Path) -> Result<bool, String> {
+        let timeout = lock_timeout(header_value(&request, "Timeout").as_deref());
+        let if_header = header_value(&request, "If");
+        let mut locks = self.locks.lock().unwrap();
+        locks.retain(|_, lock| lock.expires > Instant::now());
+
+        let token = match locks.get_mut(path) {
+            // Refresh of a lock the client holds
+            Some(lock) if if_header.as_deref().is_some_and(|value| value.contains(&lock.token)) => {
+                lock.expires = Instant::now() + timeout;
+                lock.token.clone()
+            }
+            Some(_) => {
+                drop(locks);
+                respond_status(request, 423)?;
+                return Ok(false);
+            }
+            None => {
+                let token = format!("opaquelocktoken:{:032x}", rand::random::<u128>());
+                locks.insert(path.to_path_buf(), Lock { token: token.clone(), expires: Instant::now() + timeout });
+                token
+            }
+        };
+        drop(locks);
+
+        // Locking a missing file creates it empty, so editors can save to it
+        let created = !path.exists();
+        if created {
+            if !path.parent().is_some_and(Path::is_dir) {
+                self.forget_locks(path);
+                respond_status(request, 409)?;
+                return Ok(false);
+            }
+            if let Err(e) = std::fs::write(path, b"") {
+                self.forget_locks(path);
+                respond_status(request, 500)?;
+                return Err(format!("Failed to create {}: {}", path.display(), e));
+            }
+        }
+
+        let xml = format!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<D:prop xmlns:D=\"DAV:\"><D:lockdiscovery><D:activelock>\
+             <D:locktype><D:write/></D:locktype><D:lockscope><D:exclusive/></D:lockscope><D:depth>0</D:depth>\
+             <D:timeout>Second-{}</D:timeout><D:locktoken><D:href>{}</D:href></D:locktoken>\
+             <D:lockroot><D:href>{}</D:href></D:lockroot></D:activelock></D:lockdiscovery></D:prop>\n",
+            timeout.as_secs(),
+            token,
+            xml_escape(&href(&self.root, path, path.is_dir())),
+        );
+        respond(request, Response::from_string(xml)
+            .with_status_code(StatusCode(if created { 201 } else { 200 }))
+            .with_header(header("Content-Type", "application/xml; charset=utf-8"))
+            .with_header(header("Lock-Token", &format!("<{}>", token))))?;
+        Ok(created)
+    }
+
+    fn unlock(&self, request: Request, path: &Path) -> Result<(), String> {
+        let token = header_value(&request, "Lock-Token")
+            .map(|value| value.trim().trim_start_matches('<').trim_end_matches('>').to_string());
+        let mut locks = self.locks.lock().unwrap();
+        let held = locks.get(path).is_some_and(|lock| Some(&lock.token) == token.as_ref());
+        if held {
+            locks.remove(path);
+        }
+        drop(locks);
+        respond_status(request, if held { 204 } else { 409 })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve() {
+        let root = Path::new("/wiki/tiddlers");
+        assert_eq!(resolve(root, "/dav"), Some(root.to_path_buf()));
+        assert_eq!(resolve(root, "/dav/"), Some(root.to_path_buf()));
+        assert_eq!(resolve(root, "/dav/My%20Tiddler.tid"), Some(root.join("My Tiddler.tid")));
+        assert_eq!(resolve(root, "/dav/sub/a.tid"), Some(root.join("sub").join("a.tid")));
+        assert_eq!(resolve(root, "/dav/../secret"), None);
+        assert_eq!(resolve(root, "/dav/%2e%2e/secret"), None);
+        assert_eq!(resolve(root, "/dav/a%2Fb"), None);
+        assert_eq!(resolve(root, "/davx/a.tid"), None);
+        assert_eq!(resolve(root, "/files/a.tid"), None);
+    }
+
+    #[test]
+    fn test_href() {
+        let root = Path::new("/wiki/tiddlers");
+        assert_eq!(href(root, root, true), "/dav/");
+        assert_eq!(href(root, &root.join("My Tiddler.tid"), false), "/dav/My%20Tiddler.tid");
+        assert_eq!(href(root, &root.join("sub"), true), "/dav/sub/");
+    }
+
+    #[test]
+    fn test_lock_timeout() {
+        assert_eq!(lock_timeout(None), DEFAULT_LOCK_TIMEOUT);
+        assert_eq!(lock_timeout(Some("Second-120")), Duration::from_secs(120));
+        assert_eq!(lock_timeout(Some("Second-99999")), MAX_LOCK_TIMEOUT);
+        assert_eq!(lock_timeout(Some("Infinite, Second-4100000000")), MAX_LOCK_TIMEOUT);
+        assert_eq!(lock_timeout(Some("bogus")), DEFAULT_LOCK_TIMEOUT);
+    }
+
+    #[test]
+    fn test_destination_path() {
+        assert_eq!(destination_path("http://127.0.0.1:8080/dav/b.tid"), "/dav/b.tid");
+        assert_eq!(destination_path("/dav/b.tid"), "/dav/b.tid");
+        assert_eq!(destination_path("http://host"), "/");
+    }
+
+    #[test]
+    fn test_http_date() {
+        let time = SystemTime::UNIX_EPOCH + Duration::from_secs(784111777);
+        assert_eq!(http_date(time), "Sun, 06 Nov 1994 08:49:37 GMT");
+    }
+
+    #[test]
+    fn test_is_dav_path() {
+        assert!(is_dav_path("/dav"));
+        assert!(is_dav_path("/dav/a.tid"));
+        assert!(!is_dav_path("/davx"));
+        assert!(!is_dav_path("/"));
+    }
+}
@@ -0,0 +1,198 @@
+//! Event webhooks
+//!
+//! POSTs a JSON payload to user-configured URLs when something happens, so
+//! Home Assistant, CI jobs and the like can react without polling:
+//! - `wiki-saved`: a single-file wiki was saved (`{ wikiPath }`)
+//! - `backup-completed`: a backup was made before a save (`{ wikiPath, backupPath? }`)
+//! - `sync-conflict`: LAN/relay sync found concurrent edits of a tiddler
+//!   (`{ wikiId, title }`)
+//!
+//! The body is `{ event, timestamp, data }` (timestamp in milliseconds since
+//! the epoch). Requests carry an `X-TiddlyDesktop-Event` header and, when the
+//! hook has a secret, `X-TiddlyDesktop-Signature: sha256=<hex>` with the
+//! HMAC-SHA256 of the body. Failed deliveries are retried with backoff.
+//!
+//! The hooks are stored in `webhooks.json` in the data dir, which every process
+//! reads when an event fires (saves happen in the wiki processes).
+
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+/// Attempts per delivery (the first one and the retries)
+const MAX_ATTEMPTS: u32 = 4;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum WebhookEvent {
+    WikiSaved,
+    BackupCompleted,
+    SyncConflict,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Webhook {
+    url: String,
+    events: Vec<WebhookEvent>,
+    /// Key for the HMAC signature header
+    #[serde(default)]
+    secret: Option<String>,
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn config_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(crate::get_data_dir(app)?.join("webhooks.json"))
+}
+
+fn load_webhooks(app: &tauri::AppHandle) -> Vec<Webhook> {
+    config_path(app)
+        .ok()
+        .and_then(|p| std::fs::read_to_string(p).ok())
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn event_name(event: WebhookEvent) -> String {
+    serde_json::to_value(event)
+        .ok()
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+fn payload(event: WebhookEvent, data: &serde_json::Value, timestamp: u64) -> String {
+    serde_json::json!({
+        "event": event,
+        "timestamp": timestamp,
+        "data": data,
+    })
+    .to_string()
+}
+
+/// Value of the signature header: "sha256=" and the hex HMAC-SHA256 of the body
+fn signature(secret: &str, body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(body.as_bytes());
+    let hex: String = mac.finalize().into_bytes().iter().map(|b| format!("{:02x}", b)).collect();
+    format!("sha256={}", hex)
+}
+
+/// Delay before retry `attempt` (1-based): 2, 4, 8... seconds
+fn retry_delay(attempt: u32) -> Duration {
+    Duration::from_secs(1 << attempt.min(6))
+}
+
+fn validate_url(url: &str) -> Result<(), String> {
+    let parsed = reqwest::Url::parse(url).map_err(|e| format!("Invalid webhook URL \"{}\": {}", url, e))?;
+    match parsed.scheme() {
+        "http" | "https" => Ok(()),
+        scheme => Err(format!("Webhook URLs must use http or https, not {}", scheme)),
+    }
+}
+
+async fn deliver(client: &reqwest::Client, hook: &Webhook, event: WebhookEvent, body: &str) {
+    for attempt in 1..=MAX_ATTEMPTS {
+        let mut request = client
+            .post(&hook.url)
+            .header("Content-Type", "application/json")
+            .header("X-TiddlyDesktop-Event", event_name(event))
+            .body(body.to_string());
+        if let Some(secret) = hook.secret.as_deref().filter(|s| !s.is_empty()) {
+            request = request.header("X-TiddlyDesktop-Signature", signature(secret, body));
+        }
+        let error = match request.send().await {
+            Ok(response) if response.status().is_success() => return,
+            Ok(response) => format!("HTTP {}", response.status()),
+            Err(e) => e.to_string(),
+        };
+        if attempt == MAX_ATTEMPTS {
+            eprintln!("[Webhooks] Giving up on {} after {} attempts: {}", hook.url, attempt, error);
+        } else {
+            eprintln!("[Webhooks] Delivery to {} failed ({}), retrying", hook.url, error);
+            tokio::time::sleep(retry_delay(attempt)).await;
+        }
+    }
+}
+
+/// Send an event to the hooks that subscribed to it (in the background)
+pub fn dispatch(app: &tauri::AppHandle, event: WebhookEvent, data: serde_json::Value) {
+    let hooks: Vec<Webhook> = load_webhooks(app)
+        .into_iter()
+        .filter(|hook| hook.events.contains(&event))
+        .collect();
+    if hooks.is_empty() {
+        return;
+    }
+    let body = payload(event, &data, now_ms());
+    tauri::async_runtime::spawn(async move {
+        let client = match reqwest::Client::builder().timeout(REQUEST_TIMEOUT).build() {
+            Ok(client) => client,
+            Err(e) => {
+                eprintln!("[Webhooks] HTTP client error: {}", e);
+                return;
+            }
+        };
+        let deliveries = hooks.iter().map(|hook| deliver(&client, hook, event, &body));
+        futures_util::future::join_all(deliveries).await;
+    });
+}
+
+#[tauri::command]
+pub fn get_webhooks(app: tauri::AppHandle) -> Vec<Webhook> {
+    load_webhooks(&app)
+}
+
+/// Replace the webhooks
+#[tauri::command]
+pub fn set_webhooks(app: tauri::AppHandle, webhooks: Vec<Webhook>) -> Result<(), String> {
+    let mut webhooks = webhooks;
+    for hook in &mut webhooks {
+        hook.url = hook.url.trim().to_string();
+        validate_url(&hook.url)?;
+        hook.secret = hook.secret.take().filter(|s| !s.is_empty());
+    }
+    let json = serde_json::to_string_pretty(&webhooks).map_err(|e| e.to_string())?;
+    std::fs::write(config_path(&app)?, json).map_err(|e| format!("Failed to save webhooks: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signature() {
+        assert_eq!(
+            signature("key", "The quick brown fox jumps over the lazy dog"),
+            "sha256=f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8"
+        );
+    }
+
+    #[test]
+    fn test_payload() {
+        let body = payload(WebhookEvent::WikiSaved, &serde_json::json!({ "wikiPath": "/w.html" }), 42);
+        let value: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(value["event"], "wiki-saved");
+        assert_eq!(value["timestamp"], 42);
+        assert_eq!(value["data"]["wikiPath"], "/w.html");
+        assert_eq!(event_name(WebhookEvent::SyncConflict), "sync-conflict");
+    }
+
+    #[test]
+    fn test_validate_url() {
+        assert!(validate_url("https://ha.local:8123/api/webhook/tiddly").is_ok());
+        assert!(validate_url("file:///etc/passwd").is_err());
+        assert!(validate_url("not a url").is_err());
+        assert_eq!(retry_delay(1), Duration::from_secs(2));
+        assert_eq!(retry_delay(3), Duration::from_secs(8));
+    }
+}
@@ -0,0 +1,194 @@
+//! Zip archives of a wiki with its external attachments
+//!
+//! The archive holds the wiki file (or every file of a folder wiki) and the
+//! local files its `_canonical_uri` tiddlers point to. Attachments inside the
+//! wiki's directory keep their relative path, so the links still work when the
+//! archive is extracted; attachments elsewhere go to `attachments/`.
+//!
+//! With a password every entry is AES-256 encrypted (WinZip AE-2, which 7-Zip,
+//! WinZip and Keka can open). File names stay readable, as in any zip file.
+
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use zip::write::{FileOptions, ZipWriter};
+use zip::{AesMode, CompressionMethod};
+
+use crate::attachment_manifest::{canonical_uris, resolve_uri};
+
+/// Directories of a folder wiki that aren't part of the wiki
+const SKIPPED_DIRS: &[&str] = &[".git", "node_modules", "output"];
+
+/// A written archive
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportedArchive {
+    pub path: String,
+    pub files: usize,
+    pub encrypted: bool,
+    /// Attachments that were not found
+    pub missing: Vec<String>,
+}
+
+/// Archive entry name of `path` relative to `base`, below `prefix`
+pub(crate) fn entry_name(base: &Path, prefix: &str, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(base).ok()?;
+    let mut parts: Vec<String> = relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    if parts.is_empty() || parts.iter().any(|p| p == "..") {
+        return None;
+    }
+    if !prefix.is_empty() {
+        parts.insert(0, prefix.to_string());
+    }
+    Some(parts.join("/"))
+}
+
+/// Entry name for an attachment outside the wiki's directory
+fn outside_entry_name(path: &Path, used: &HashSet<String>) -> String {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "attachment".to_string());
+    (0..)
+        .map(|n| {
+            if n == 0 {
+                format!("attachments/{}", name)
+            } else {
+                format!("attachments/{}-{}", n, name)
+            }
+        })
+        .find(|entry| !used.contains(entry))
+        .unwrap_or_default()
+}
+
+/// Files of a folder wiki
+pub(crate) fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for path in entries.flatten().map(|e| e.path()) {
+        if path.is_dir() {
+            let skipped = path
+                .file_name()
+                .is_some_and(|n| SKIPPED_DIRS.iter().any(|s| n == *s));
+            if !skipped {
+                collect_files(&path, files);
+            }
+        } else if path.is_file() {
+            files.push(path);
+        }
+    }
+}
+
+pub(crate) fn add_file(zip: &mut ZipWriter<File>, name: &str, source: &Path, options: FileOptions<()>) -> Result<(), String> {
+    zip.start_file(name, options)
+        .map_err(|e| format!("Failed to add {}: {}", name, e))?;
+    let mut file = File::open(source).map_err(|e| format!("Failed to read {}: {}", source.display(), e))?;
+    io::copy(&mut file, zip).map_err(|e| format!("Failed to add {}: {}", source.display(), e))?;
+    Ok(())
+}
+
+/// Write the archive of the wiki at `wiki_path` to `target`
+pub fn export(wiki_path: &str, target: &Path, password: Option<&str>) -> Result<ExportedArchive, String> {
+    let wiki = Path::new(wiki_path);
+    let (base, prefix, mut files) = if wiki.is_dir() {
+        let mut files = Vec::new();
+        collect_files(wiki, &mut files);
+        let name = wiki
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "wiki".to_string());
+        (wiki.to_path_buf(), name, files)
+    } else if wiki.is_file() {
+        (wiki.parent().unwrap_or(wiki).to_path_buf(), String::new(), vec![wiki.to_path_buf()])
+    } else {
+        return Err(format!("{} was not found", wiki_path));
+    };
+
+    let mut missing = Vec::new();
+    for uri in canonical_uris(wiki)? {
+        let path = resolve_uri(wiki, &uri);
+        if !path.is_file() {
+            missing.push(uri);
+        } else if !files.contains(&path) {
+            files.push(path);
+        }
+    }
+
+    let mut options: FileOptions<()> = FileOptions::default()
+        .compression_method(CompressionMethod::Deflated)
+        .large_file(true);
+    if let Some(password) = password {
+        options = options.with_aes_encryption(AesMode::Aes256, password);
+    }
+
+    let partial = target.with_extension("zip.part");
+    let file = File::create(&partial).map_err(|e| format!("Failed to create {}: {}", partial.display(), e))?;
+    let mut zip = ZipWriter::new(file);
+    let mut used = HashSet::new();
+    let result = files.iter().try_for_each(|path| {
+        let name = entry_name(&base, &prefix, path).unwrap_or_else(|| outside_entry_name(path, &used));
+        used.insert(name.clone());
+        add_file(&mut zip, &name, path, options)
+    });
+    let result = result.and_then(|_| {
+        let mut file = zip.finish().map_err(|e| format!("Failed to write archive: {}", e))?;
+        file.flush().map_err(|e| format!("Failed to write archive: {}", e))
+    });
+    if let Err(e) = result {
+        let _ = std::fs::remove_file(&partial);
+        return Err(e);
+    }
+    std::fs::rename(&partial, target).map_err(|e| format!("Failed to save {}: {}", target.display(), e))?;
+
+    Ok(ExportedArchive {
+        path: target.to_string_lossy().into_owned(),
+        files: files.len(),
+        encrypted: password.is_some(),
+        missing,
+    })
+}
+
+/// Export a wiki with its attachments as a zip archive, AES-256 encrypted if a
+/// password is given
+#[tauri::command]
+pub async fn export_wiki_archive(
+    wiki_path: String,
+    target: String,
+    password: Option<String>,
+) -> Result<ExportedArchive, String> {
+    let password = password.filter(|p| !p.is_empty());
+    tokio::task::spawn_blocking(move || export(&wiki_path, Path::new(&target), password.as_deref()))
+        .await
+        .map_err(|e| format!("Export failed: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entry_name() {
+        let base = Path::new("/home/me/wikis");
+        assert_eq!(entry_name(base, "", Path::new("/home/me/wikis/notes.html")).as_deref(), Some("notes.html"));
+        assert_eq!(
+            entry_name(base, "notes", Path::new("/home/me/wikis/files/a.png")).as_deref(),
+            Some("notes/files/a.png")
+        );
+        assert_eq!(entry_name(base, "", Path::new("/media/usb/a.pdf")), None);
+    }
+
+    #[test]
+    fn test_outside_entry_name() {
+        let mut used = HashSet::new();
+        assert_eq!(outside_entry_name(Path::new("/media/usb/a.pdf"), &used), "attachments/a.pdf");
+        used.insert("attachments/a.pdf".to_string());
+        assert_eq!(outside_entry_name(Path::new("/tmp/a.pdf"), &used), "attachments/1-a.pdf");
+    }
+}
@@ -0,0 +1,175 @@
+//! Wiki-defined commands in the tray menu
+//!
+//! A wiki declares commands as tiddlers tagged
+//! `$:/tags/TiddlyDesktopRS/MenuCommand`: the `caption` field is the menu label
+//! and the text holds action widgets, like keyboard shortcuts in TiddlyWiki.
+//! `init_script/wiki_commands.js` reports them (`set_wiki_menu_commands`)
+//! whenever they change, and the wiki process forwards the list to the main
+//! process over IPC (`WikiMenuCommands`). The tray shows a submenu per open
+//! wiki; clicking a command sends `RunWikiMenuCommand` back to the wiki process
+//! and its window runs the actions (`wiki-menu-command`).
+
+use std::collections::BTreeMap;
+use std::sync::{LazyLock, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+const MAX_COMMANDS: usize = 50;
+const MAX_CAPTION_CHARS: usize = 80;
+
+/// Commands of the open wikis, by wiki path (main process)
+static COMMANDS: LazyLock<Mutex<BTreeMap<String, Vec<MenuCommand>>>> =
+    LazyLock::new(|| Mutex::new(BTreeMap::new()));
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MenuCommand {
+    /// Title of the command tiddler
+    pub id: String,
+    pub caption: String,
+}
+
+/// Drop commands without an id and duplicates, tidy up the captions and keep
+/// at most `MAX_COMMANDS`
+fn sanitize(commands: Vec<MenuCommand>) -> Vec<MenuCommand> {
+    let mut result: Vec<MenuCommand> = Vec::new();
+    for command in commands {
+        if command.id.is_empty() || result.iter().any(|c| c.id == command.id) {
+            continue;
+        }
+        let caption = command.caption.split_whitespace().collect::<Vec<_>>().join(" ");
+        let caption = if caption.is_empty() { command.id.clone() } else { caption };
+        let caption = match caption.char_indices().nth(MAX_CAPTION_CHARS) {
+            Some((end, _)) => format!("{}…", &caption[..end]),
+            None => caption,
+        };
+        result.push(MenuCommand { id: command.id, caption });
+        if result.len() == MAX_COMMANDS {
+            break;
+        }
+    }
+    result
+}
+
+/// Wiki and command index of a tray menu id ("wiki_command:<wiki>:<command>")
+fn parse_tray_id(id: &str) -> Option<(usize, usize)> {
+    let (wiki, command) = id.strip_prefix("wiki_command:")?.split_once(':')?;
+    Some((wiki.parse().ok()?, command.parse().ok()?))
+}
+
+/// Store the commands a wiki reported (main process, from IPC)
+#[cfg(not(target_os = "android"))]
+pub fn set_commands(app: &tauri::AppHandle, wiki_path: &str, commands: Vec<MenuCommand>) {
+    let commands = sanitize(commands);
+    let changed = {
+        let mut all = COMMANDS.lock().unwrap();
+        if commands.is_empty() {
+            all.remove(wiki_path).is_some()
+        } else {
+            all.insert(wiki_path.to_string(), commands.clone()).as_ref() != Some(&commands)
+        }
+    };
+    if changed {
+        crate::refresh_tray_menu(app);
+    }
+}
+
+/// Forget the commands of a wiki whose process exited
+#[cfg(not(target_os = "android"))]
+pub fn wiki_closed(app: &tauri::AppHandle, wiki_path: &str) {
+    if COMMANDS.lock().unwrap().remove(wiki_path).is_some() {
+        crate::refresh_tray_menu(app);
+    }
+}
+
+/// Tray submenus: (wiki path, [(menu id, label)]) for every wiki with commands
+#[cfg(not(target_os = "android"))]
+pub fn tray_items() -> Vec<(String, Vec<(String, String)>)> {
+    COMMANDS
+        .lock()
+        .unwrap()
+        .iter()
+        .enumerate()
+        .map(|(wiki_index, (path, commands))| {
+            let items = commands
+                .iter()
+                .enumerate()
+                .map(|(index, command)| {
+                    (format!("wiki_command:{}:{}", wiki_index, index), command.caption.replace('&', "&&"))
+                })
+                .collect();
+            (path.clone(), items)
+        })
+        .collect()
+}
+
+/// Handle a click on a wiki command in the tray menu: focus the wiki's window
+/// and run the command there
+#[cfg(not(target_os = "android"))]
+pub fn handle_tray_event(id: &str) {
+    let Some((wiki_index, index)) = parse_tray_id(id) else {
+        return;
+    };
+    let target = COMMANDS
+        .lock()
+        .unwrap()
+        .iter()
+        .nth(wiki_index)
+        .and_then(|(path, commands)| Some((path.clone(), commands.get(index)?.id.clone())));
+    let (Some((wiki_path, command_id)), Some(server)) = (target, crate::GLOBAL_IPC_SERVER.get()) else {
+        return;
+    };
+    let _ = server.send_focus_window(&wiki_path);
+    if let Err(e) = server.send_run_wiki_command(&wiki_path, &command_id) {
+        eprintln!("[TiddlyDesktop] Failed to run wiki command: {}", e);
+    }
+}
+
+/// Report the menu commands of this wiki to the main process (wiki window)
+#[cfg(not(target_os = "android"))]
+#[tauri::command]
+pub fn set_wiki_menu_commands(
+    state: tauri::State<crate::WikiModeState>,
+    commands: Vec<MenuCommand>,
+) -> Result<(), String> {
+    let mut client = state.ipc_client.lock().unwrap();
+    match client.as_mut() {
+        Some(client) => client
+            .send_wiki_menu_commands(sanitize(commands))
+            .map_err(|e| format!("Failed to send wiki commands: {}", e)),
+        // Without the main process there is no tray
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command(id: &str, caption: &str) -> MenuCommand {
+        MenuCommand { id: id.to_string(), caption: caption.to_string() }
+    }
+
+    #[test]
+    fn test_sanitize() {
+        let commands = sanitize(vec![
+            command("A", "  Run\n  backup "),
+            command("", "No id"),
+            command("A", "Duplicate"),
+            command("B", ""),
+        ]);
+        assert_eq!(commands, vec![command("A", "Run backup"), command("B", "B")]);
+
+        let long = "x".repeat(MAX_CAPTION_CHARS + 10);
+        assert_eq!(sanitize(vec![command("C", &long)])[0].caption.chars().count(), MAX_CAPTION_CHARS + 1);
+
+        let many = (0..MAX_COMMANDS + 5).map(|i| command(&i.to_string(), "")).collect();
+        assert_eq!(sanitize(many).len(), MAX_COMMANDS);
+    }
+
+    #[test]
+    fn test_parse_tray_id() {
+        assert_eq!(parse_tray_id("wiki_command:2:10"), Some((2, 10)));
+        assert_eq!(parse_tray_id("wiki_command:2"), None);
+        assert_eq!(parse_tray_id("quick_action:1"), None);
+    }
+}
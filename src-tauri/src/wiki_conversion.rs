@@ -0,0 +1,519 @@
+//! Wiki format conversion (single-file ⇄ folder) with pre-flight checks and verification
+//!
+//! A conversion runs in three steps:
+//! - Pre-flight: count the source's tiddlers, list plugins that won't survive
+//!   the new format and summarize external attachments. A dry run stops here.
+//! - Conversion: Node.js `--savewikifolder` or `--render $:/core/save/all`.
+//! - Verification: read the result back, check every source tiddler made it
+//!   across and that TiddlyWiki can boot it. A single-file result is only
+//!   written to its destination after it has been verified.
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+#[cfg(target_os = "windows")]
+use std::os::windows::process::CommandExt;
+
+use serde_json::Value;
+
+/// Tiddlers injected by TiddlyDesktop, stripped from converted wikis
+const STRIPPED_FILTERS: &[&str] = &[
+    "[prefix[$:/plugins/tiddlywiki/tiddlydesktop-rs]]",
+    "[prefix[$:/plugins/tiddlydesktop-rs]]",
+    "[prefix[$:/temp/tiddlydesktop]]",
+];
+
+/// Title prefixes matching `STRIPPED_FILTERS`
+const STRIPPED_PREFIXES: &[&str] = &[
+    "$:/plugins/tiddlywiki/tiddlydesktop-rs",
+    "$:/plugins/tiddlydesktop-rs",
+    "$:/temp/tiddlydesktop",
+];
+
+/// Server-only plugins removed when converting a folder wiki to a single file
+const SERVER_PLUGINS: &[&str] = &["$:/plugins/tiddlywiki/tiddlyweb", "$:/plugins/tiddlywiki/filesystem"];
+
+/// Tiddlers that are legitimately regenerated or skipped when saving,
+/// so they are not expected to match between source and result
+const TRANSIENT_PREFIXES: &[&str] = &["$:/temp/", "$:/state/", "$:/boot/", "$:/library/", "$:/HistoryList", "$:/StoryList"];
+
+/// Maximum number of missing titles listed in a verification result
+const MAX_MISSING_TITLES: usize = 50;
+
+/// A plugin that will be removed or stop working after conversion
+#[derive(Clone, Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PluginIssue {
+    pub title: String,
+    pub reason: String,
+}
+
+/// External attachments (`_canonical_uri` tiddlers) and embedded binaries
+#[derive(Clone, Debug, Default, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AttachmentSummary {
+    /// Tiddlers whose content lives in an external file
+    pub external: usize,
+    /// External attachments referenced by a relative path
+    pub relative: usize,
+    /// Binary tiddlers embedded in the wiki (images, PDFs, ...)
+    pub embedded_binary: usize,
+    /// Whether external attachments are enabled for the source wiki
+    /// (the setting is carried over to the converted wiki)
+    pub external_attachments_enabled: bool,
+}
+
+/// Result of reading the converted wiki back
+#[derive(Clone, Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConversionVerification {
+    /// Tiddlers expected in the converted wiki
+    pub expected_tiddlers: usize,
+    /// Tiddlers found in the converted wiki
+    pub converted_tiddlers: usize,
+    /// Expected tiddlers missing from the converted wiki (first few)
+    pub missing_titles: Vec<String>,
+    /// Whether TiddlyWiki booted the converted wiki
+    pub opened: bool,
+    /// Counts match and the wiki opened: the original can be considered superseded
+    pub passed: bool,
+}
+
+/// Pre-flight (and, after conversion, verification) report
+#[derive(Clone, Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConversionReport {
+    pub source: String,
+    pub destination: String,
+    pub to_folder: bool,
+    pub dry_run: bool,
+    /// Non-shadow tiddlers in the source wiki
+    pub tiddler_count: usize,
+    /// System tiddlers among them
+    pub system_tiddler_count: usize,
+    /// Plugins in the source wiki
+    pub plugins: Vec<String>,
+    /// Plugins that won't survive the conversion
+    pub plugin_issues: Vec<PluginIssue>,
+    pub attachments: AttachmentSummary,
+    pub warnings: Vec<String>,
+    /// Set once the conversion has run
+    pub verification: Option<ConversionVerification>,
+}
+
+/// Run TiddlyWiki on a folder wiki and render it to a single HTML file in `out_dir`
+pub fn render_folder(app: &tauri::AppHandle, folder: &Path, out_dir: &Path, filename: &str, strip_server_plugins: bool) -> Result<PathBuf, String> {
+    let node_path = crate::get_node_path(app)?;
+    let tw_path = crate::get_tiddlywiki_path(app)?;
+    render_folder_with(&node_path, &tw_path, folder, out_dir, filename, strip_server_plugins)
+}
+
+/// `render_folder` with explicit Node.js and TiddlyWiki paths
+/// (wiki folder processes locate them without an app-wide lookup)
+pub fn render_folder_with(node_path: &Path, tw_path: &Path, folder: &Path, out_dir: &Path, filename: &str, strip_server_plugins: bool) -> Result<PathBuf, String> {
+    let mut cmd = Command::new(node_path);
+    cmd.arg(tw_path).arg(folder);
+    if strip_server_plugins {
+        for plugin in SERVER_PLUGINS {
+            cmd.arg("--deletetiddlers").arg(plugin);
+        }
+    }
+    for filter in STRIPPED_FILTERS {
+        cmd.arg("--deletetiddlers").arg(filter);
+    }
+    cmd.arg("--output")
+        .arg(out_dir)
+        .arg("--render")
+        .arg("$:/core/save/all")
+        .arg(filename)
+        .arg("text/plain");
+    #[cfg(target_os = "windows")]
+    cmd.creation_flags(crate::CREATE_NO_WINDOW);
+
+    let output = cmd.output()
+        .map_err(|e| format!("Failed to run conversion: {}", e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        return Err(format!("Conversion failed:\n{}\n{}", stdout, stderr));
+    }
+
+    let built_file = out_dir.join(filename);
+    if !built_file.exists() {
+        return Err("Conversion succeeded but output file not found".to_string());
+    }
+    Ok(built_file)
+}
+
+/// Save a single-file wiki as a folder wiki
+fn save_as_folder(app: &tauri::AppHandle, source: &Path, dest: &Path) -> Result<(), String> {
+    std::fs::create_dir_all(dest)
+        .map_err(|e| format!("Failed to create destination folder: {}", e))?;
+
+    let mut cmd = Command::new(crate::get_node_path(app)?);
+    cmd.arg(crate::get_tiddlywiki_path(app)?).arg("--load").arg(source);
+    for filter in STRIPPED_FILTERS {
+        cmd.arg("--deletetiddlers").arg(filter);
+    }
+    cmd.arg("--savewikifolder").arg(dest);
+    #[cfg(target_os = "windows")]
+    cmd.creation_flags(crate::CREATE_NO_WINDOW);
+
+    let output = cmd.output()
+        .map_err(|e| format!("Failed to run conversion: {}", e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        return Err(format!("Conversion failed:\n{}\n{}", stdout, stderr));
+    }
+
+    let info_path = dest.join("tiddlywiki.info");
+    if !info_path.exists() {
+        return Err("Conversion failed - tiddlywiki.info not created".to_string());
+    }
+
+    // Add tiddlyweb and filesystem plugins to tiddlywiki.info for proper folder wiki operation
+    if let Err(e) = crate::add_server_plugins_to_tiddlywiki_info(&info_path) {
+        println!("Warning: Failed to add server plugins to tiddlywiki.info: {}", e);
+    }
+    Ok(())
+}
+
+/// Read the tiddler store of a single-file wiki
+fn read_html_tiddlers(path: &Path) -> Result<Vec<Value>, String> {
+    let html = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    Ok(crate::tiddlywiki_html::extract_all_tiddlers_from_html(&html))
+}
+
+/// Read the tiddlers of a folder wiki by rendering it in the scratch directory
+fn read_folder_tiddlers(app: &tauri::AppHandle, folder: &Path) -> Result<Vec<Value>, String> {
+    let temp = crate::scratch_dir::create_build_dir(app, "convert-check")?;
+    let result = render_folder(app, folder, &temp, "check.html", false)
+        .and_then(|built| read_html_tiddlers(&built));
+    let _ = std::fs::remove_dir_all(&temp);
+    result
+}
+
+fn title_of(tiddler: &Value) -> Option<&str> {
+    tiddler.get("title").and_then(|t| t.as_str())
+}
+
+fn field_str<'a>(tiddler: &'a Value, field: &str) -> Option<&'a str> {
+    tiddler.get(field).and_then(|v| v.as_str())
+}
+
+/// Whether a tiddler is expected to be carried across by the conversion
+fn is_expected(title: &str, to_folder: bool) -> bool {
+    if STRIPPED_PREFIXES.iter().chain(TRANSIENT_PREFIXES).any(|p| title.starts_with(p)) {
+        return false;
+    }
+    to_folder || !SERVER_PLUGINS.contains(&title)
+}
+
+/// Module types used by a plugin's shadow tiddlers
+fn plugin_module_types(plugin: &Value) -> BTreeSet<String> {
+    field_str(plugin, "text")
+        .and_then(|text| serde_json::from_str::<Value>(text).ok())
+        .and_then(|content| content.get("tiddlers").and_then(|t| t.as_object()).cloned())
+        .map(|shadows| {
+            shadows
+                .values()
+                .filter_map(|s| field_str(s, "module-type").map(|m| m.to_string()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Why a plugin won't survive the conversion, if it won't
+fn plugin_issue(plugin: &Value, title: &str, to_folder: bool) -> Option<String> {
+    if STRIPPED_PREFIXES.iter().any(|p| title.starts_with(p)) {
+        return Some("Injected by TiddlyDesktop; it is not saved with the converted wiki".to_string());
+    }
+    if !to_folder && SERVER_PLUGINS.contains(&title) {
+        return Some("Only used by folder wikis; removed from the single-file wiki".to_string());
+    }
+    let module_types = plugin_module_types(plugin);
+    if !to_folder {
+        let server_only: Vec<&str> = ["command", "route", "server"]
+            .into_iter()
+            .filter(|m| module_types.contains(*m))
+            .collect();
+        if !server_only.is_empty() {
+            return Some(format!("Contains Node.js-only modules ({}) that don't run in a single-file wiki", server_only.join(", ")));
+        }
+    } else if module_types.contains("saver") {
+        return Some("Contains savers, which folder wikis don't use (they save through the Node.js server)".to_string());
+    }
+    None
+}
+
+/// Whether a `_canonical_uri` is relative to the wiki's location
+fn is_relative_uri(uri: &str) -> bool {
+    !(uri.contains("://")
+        || uri.starts_with("data:")
+        || uri.starts_with('/')
+        || uri.starts_with('\\')
+        || Path::new(uri).is_absolute())
+}
+
+fn is_binary_type(content_type: &str) -> bool {
+    (content_type.starts_with("image/") && content_type != "image/svg+xml")
+        || content_type.starts_with("audio/")
+        || content_type.starts_with("video/")
+        || content_type == "application/pdf"
+        || content_type == "application/zip"
+}
+
+/// Build the pre-flight report from the source wiki's tiddlers
+fn analyze(app: &tauri::AppHandle, source: &Path, dest: &Path, to_folder: bool, tiddlers: &[Value]) -> ConversionReport {
+    let mut report = ConversionReport {
+        source: source.to_string_lossy().into_owned(),
+        destination: dest.to_string_lossy().into_owned(),
+        to_folder,
+        dry_run: true,
+        tiddler_count: 0,
+        system_tiddler_count: 0,
+        plugins: Vec::new(),
+        plugin_issues: Vec::new(),
+        attachments: AttachmentSummary::default(),
+        warnings: Vec::new(),
+        verification: None,
+    };
+
+    for tiddler in tiddlers {
+        let title = match title_of(tiddler) {
+            Some(t) => t,
+            None => continue,
+        };
+        report.tiddler_count += 1;
+        if title.starts_with("$:/") {
+            report.system_tiddler_count += 1;
+        }
+        if field_str(tiddler, "plugin-type").is_some() {
+            report.plugins.push(title.to_string());
+            if let Some(reason) = plugin_issue(tiddler, title, to_folder) {
+                report.plugin_issues.push(PluginIssue { title: title.to_string(), reason });
+            }
+        }
+        if let Some(uri) = field_str(tiddler, "_canonical_uri") {
+            report.attachments.external += 1;
+            if is_relative_uri(uri) {
+                report.attachments.relative += 1;
+            }
+        } else if field_str(tiddler, "type").is_some_and(is_binary_type)
+            && field_str(tiddler, "text").is_some_and(|t| !t.is_empty())
+        {
+            report.attachments.embedded_binary += 1;
+        }
+    }
+
+    let source_key = source.to_string_lossy();
+    report.attachments.external_attachments_enabled = crate::wiki_storage::load_wiki_configs(app)
+        .ok()
+        .and_then(|c| c.external_attachments.get(source_key.as_ref()).cloned())
+        .unwrap_or_default()
+        .enabled;
+
+    if report.tiddler_count == 0 {
+        report.warnings.push("No tiddlers were found in the source wiki. Wikis saved by TiddlyWiki versions before 5.2 can't be analyzed".to_string());
+    }
+    if report.attachments.relative > 0 {
+        // Relative URIs resolve against the HTML file's folder, or the folder wiki's files/ directory
+        let old_base = if to_folder { source.parent().map(Path::to_path_buf) } else { Some(source.join("files")) };
+        let new_base = if to_folder { Some(dest.join("files")) } else { dest.parent().map(Path::to_path_buf) };
+        let same_base = match (old_base, new_base) {
+            (Some(a), Some(b)) => crate::path_identity::same_path(&a.to_string_lossy(), &b.to_string_lossy()),
+            _ => false,
+        };
+        if !same_base {
+            report.warnings.push(format!(
+                "{} attachments use relative paths and will break unless their files are moved next to the converted wiki",
+                report.attachments.relative
+            ));
+        }
+    }
+    if to_folder && dest.join("tiddlywiki.info").exists() {
+        report.warnings.push("The destination folder already contains a wiki and will be overwritten".to_string());
+    } else if !to_folder && dest.exists() {
+        report.warnings.push("The destination file already exists and will be overwritten".to_string());
+    }
+    report
+}
+
+/// Compare the converted wiki against the source and build the verification result
+fn verify(source_tiddlers: &[Value], converted_tiddlers: &[Value], to_folder: bool, opened: bool) -> ConversionVerification {
+    let expected: BTreeSet<&str> = source_tiddlers
+        .iter()
+        .filter_map(title_of)
+        .filter(|t| is_expected(t, to_folder))
+        .collect();
+    let converted: BTreeSet<&str> = converted_tiddlers.iter().filter_map(title_of).collect();
+    let missing: Vec<String> = expected.difference(&converted).map(|t| t.to_string()).collect();
+
+    ConversionVerification {
+        expected_tiddlers: expected.len(),
+        converted_tiddlers: converted.iter().filter(|t| is_expected(t, to_folder)).count(),
+        passed: opened && missing.is_empty(),
+        missing_titles: missing.into_iter().take(MAX_MISSING_TITLES).collect(),
+        opened,
+    }
+}
+
+/// Whether a rendered single-file wiki looks bootable: it has the boot kernel and the core plugin
+fn looks_bootable(html_path: &Path, tiddlers: &[Value]) -> bool {
+    let has_kernel = std::fs::read_to_string(html_path)
+        .map(|html| html.contains("$:/boot/boot.js"))
+        .unwrap_or(false);
+    has_kernel && tiddlers.iter().filter_map(title_of).any(|t| t == "$:/core")
+}
+
+/// Carry the source wiki's external attachments setting over to the converted wiki
+fn carry_over_settings(app: &tauri::AppHandle, source: &Path, dest: &Path) {
+    let mut configs = match crate::wiki_storage::load_wiki_configs(app) {
+        Ok(c) => c,
+        Err(_) => return,
+    };
+    if let Some(config) = configs.external_attachments.get(source.to_string_lossy().as_ref()).cloned() {
+        configs.external_attachments.insert(dest.to_string_lossy().into_owned(), config);
+        if let Err(e) = crate::wiki_storage::save_wiki_configs(app, &configs) {
+            eprintln!("[TiddlyDesktop] Failed to carry over wiki settings after conversion: {}", e);
+        }
+    }
+}
+
+/// Convert a wiki, or with `dry_run` only return the pre-flight report.
+/// Paths must already be validated by the caller.
+pub fn convert(app: &tauri::AppHandle, source: &Path, dest: &Path, to_folder: bool, dry_run: bool) -> Result<ConversionReport, String> {
+    // Ensure a single-file destination has an .html extension
+    let dest = if to_folder || dest.extension().map(|e| e == "html" || e == "htm").unwrap_or(false) {
+        dest.to_path_buf()
+    } else {
+        dest.with_extension("html")
+    };
+
+    let source_tiddlers = if to_folder {
+        read_html_tiddlers(source)?
+    } else {
+        read_folder_tiddlers(app, source)?
+    };
+    let mut report = analyze(app, source, &dest, to_folder, &source_tiddlers);
+    if dry_run {
+        return Ok(report);
+    }
+    report.dry_run = false;
+
+    println!("Converting {} wiki:", if to_folder { "single-file wiki to folder" } else { "folder wiki to single-file" });
+    println!("  Source: {:?}", source);
+    println!("  Destination: {:?}", dest);
+
+    if to_folder {
+        save_as_folder(app, source, &dest)?;
+        // Opening smoke test: TiddlyWiki must be able to boot the new folder
+        let (converted, opened) = match read_folder_tiddlers(app, &dest) {
+            Ok(tiddlers) => (tiddlers, true),
+            Err(e) => {
+                report.warnings.push(format!("The converted folder wiki failed to load: {}", e));
+                (Vec::new(), false)
+            }
+        };
+        report.verification = Some(verify(&source_tiddlers, &converted, to_folder, opened));
+    } else {
+        let output_filename = dest.file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("wiki.html");
+        let temp_output = crate::scratch_dir::create_build_dir(app, "convert")?;
+        let built_file = match render_folder(app, source, &temp_output, output_filename, true) {
+            Ok(f) => f,
+            Err(e) => {
+                let _ = std::fs::remove_dir_all(&temp_output);
+                return Err(e);
+            }
+        };
+        let converted = read_html_tiddlers(&built_file).unwrap_or_default();
+        let verification = verify(&source_tiddlers, &converted, to_folder, looks_bootable(&built_file, &converted));
+
+        // Only a verified wiki replaces whatever is at the destination
+        if verification.passed {
+            if let Some(parent) = dest.parent() {
+                std::fs::create_dir_all(parent)
+                    .map_err(|e| format!("Failed to create output directory: {}", e))?;
+            }
+            if let Err(e) = std::fs::copy(&built_file, &dest) {
+                let _ = std::fs::remove_dir_all(&temp_output);
+                return Err(format!("Failed to copy wiki to destination: {}", e));
+            }
+        } else {
+            report.warnings.push("The converted wiki failed verification and was not written".to_string());
+        }
+        let _ = std::fs::remove_dir_all(&temp_output);
+        report.verification = Some(verification);
+    }
+
+    let passed = report.verification.as_ref().is_some_and(|v| v.passed);
+    if passed {
+        carry_over_settings(app, source, &dest);
+        println!("Successfully converted wiki: {:?}", dest);
+    } else {
+        eprintln!("[TiddlyDesktop] Conversion of {:?} failed verification", source);
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_verification_ignores_stripped_and_transient_tiddlers() {
+        let source = vec![
+            json!({"title": "HelloThere"}),
+            json!({"title": "$:/StoryList"}),
+            json!({"title": "$:/plugins/tiddlywiki/tiddlydesktop-rs"}),
+            json!({"title": "$:/plugins/tiddlywiki/tiddlyweb"}),
+        ];
+        let converted = vec![json!({"title": "HelloThere"}), json!({"title": "$:/core"})];
+
+        let to_file = verify(&source, &converted, false, true);
+        assert!(to_file.passed);
+        assert_eq!(to_file.expected_tiddlers, 1);
+
+        // Folder wikis keep tiddlyweb
+        let to_folder = verify(&source, &converted, true, true);
+        assert!(!to_folder.passed);
+        assert_eq!(to_folder.missing_titles, vec!["$:/plugins/tiddlywiki/tiddlyweb".to_string()]);
+    }
+
+    #[test]
+    fn test_verification_fails_when_wiki_does_not_open() {
+        let source = vec![json!({"title": "HelloThere"})];
+        assert!(!verify(&source, &source, true, false).passed);
+    }
+
+    #[test]
+    fn test_plugin_issues_depend_on_direction() {
+        let plugin = json!({
+            "title": "$:/plugins/example/server",
+            "plugin-type": "plugin",
+            "text": json!({"tiddlers": {
+                "$:/plugins/example/server/route.js": {"module-type": "route"},
+                "$:/plugins/example/server/saver.js": {"module-type": "saver"}
+            }}).to_string()
+        });
+        let title = "$:/plugins/example/server";
+        assert!(plugin_issue(&plugin, title, false).unwrap().contains("route"));
+        assert!(plugin_issue(&plugin, title, true).unwrap().contains("savers"));
+        assert!(plugin_issue(&json!({"text": "{}"}), "$:/plugins/tiddlywiki/tiddlyweb", true).is_none());
+    }
+
+    #[test]
+    fn test_relative_canonical_uris() {
+        assert!(is_relative_uri("images/photo.jpg"));
+        assert!(is_relative_uri("../files/doc.pdf"));
+        assert!(!is_relative_uri("https://example.com/a.png"));
+        assert!(!is_relative_uri("/home/user/a.png"));
+        assert!(!is_relative_uri("data:image/png;base64,AAAA"));
+    }
+}
@@ -0,0 +1,171 @@
+//! Per-wiki environment variables (desktop)
+//!
+//! Server plugins of folder wikis may read API keys or feature flags from the
+//! environment. The variables set for a wiki on the landing page (`env_vars`
+//! in the wiki configs) are passed to the wiki's process, and with it to the
+//! Node.js server it starts. Values can be kept in the OS keychain (macOS
+//! Keychain, Windows Credential Manager, Secret Service on Linux) instead of
+//! the wiki configs: the configs then only hold the keychain account, and the
+//! value is read when the wiki is opened.
+
+use std::collections::HashMap;
+use std::process::Command;
+
+use tiddlydesktop_core::types::WikiEnvVar;
+
+use crate::wiki_storage::{load_wiki_configs, save_wiki_configs};
+
+/// Service of the keychain entries holding values
+#[cfg(not(target_os = "android"))]
+const KEYCHAIN_SERVICE: &str = "tiddlydesktop-rs";
+
+/// Prefixes of the variables TiddlyDesktop and TiddlyWiki set themselves
+const RESERVED_PREFIXES: &[&str] = &["TIDDLYDESKTOP_", "TIDDLYWIKI_"];
+
+/// Whether `name` can be set: letters, digits and underscores, not starting
+/// with a digit, and none of the reserved ones
+fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    let starts_well = chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_');
+    starts_well
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !RESERVED_PREFIXES.iter().any(|prefix| name.to_ascii_uppercase().starts_with(prefix))
+}
+
+#[cfg(not(target_os = "android"))]
+fn keychain_entry(account: &str) -> Result<keyring::Entry, String> {
+    keyring::Entry::new(KEYCHAIN_SERVICE, account).map_err(|e| format!("Keychain unavailable: {}", e))
+}
+
+#[cfg(not(target_os = "android"))]
+fn keychain_get(account: &str) -> Result<String, String> {
+    keychain_entry(account)?
+        .get_password()
+        .map_err(|e| format!("Failed to read from the keychain: {}", e))
+}
+
+#[cfg(not(target_os = "android"))]
+fn keychain_set(account: &str, value: &str) -> Result<(), String> {
+    keychain_entry(account)?
+        .set_password(value)
+        .map_err(|e| format!("Failed to store in the keychain: {}", e))
+}
+
+#[cfg(not(target_os = "android"))]
+fn keychain_delete(account: &str) {
+    if let Ok(entry) = keychain_entry(account) {
+        let _ = entry.delete_credential();
+    }
+}
+
+#[cfg(target_os = "android")]
+fn keychain_get(_account: &str) -> Result<String, String> {
+    Err("The keychain is not available on Android".to_string())
+}
+
+#[cfg(target_os = "android")]
+fn keychain_set(_account: &str, _value: &str) -> Result<(), String> {
+    Err("The keychain is not available on Android".to_string())
+}
+
+#[cfg(target_os = "android")]
+fn keychain_delete(_account: &str) {}
+
+/// Delete the keychain entries of variables that are gone with their wiki
+pub fn forget(vars: &[WikiEnvVar]) {
+    for account in vars.iter().filter_map(|var| var.keychain.as_deref()) {
+        keychain_delete(account);
+    }
+}
+
+/// Set the environment variables of the wiki at `wiki_path` for its process
+/// about to be spawned (the ones whose keychain entry can't be read are left out)
+pub fn configure_process(app: &tauri::AppHandle, cmd: &mut Command, wiki_path: &str) {
+    let Ok(mut configs) = load_wiki_configs(app) else {
+        return;
+    };
+    let Some(vars) = configs.env_vars.remove(wiki_path) else {
+        return;
+    };
+    for var in vars {
+        let value = match &var.keychain {
+            Some(account) => match keychain_get(account) {
+                Ok(value) => value,
+                Err(e) => {
+                    eprintln!("[TiddlyDesktop] {} not set for {}: {}", var.name, wiki_path, e);
+                    continue;
+                }
+            },
+            None => var.value,
+        };
+        cmd.env(&var.name, value);
+    }
+}
+
+/// The environment variables of all wikis, keyed by wiki path (without the
+/// values kept in the keychain)
+#[tauri::command]
+pub fn get_wiki_env_vars(app: tauri::AppHandle) -> Result<HashMap<String, Vec<WikiEnvVar>>, String> {
+    Ok(load_wiki_configs(&app)?.env_vars)
+}
+
+/// Set an environment variable for the wiki at `wiki_path`, with its value
+/// in the wiki configs or, with `keychain`, in the OS keychain. Applies the
+/// next time the wiki is opened.
+#[tauri::command]
+pub fn set_wiki_env_var(app: tauri::AppHandle, wiki_path: String, name: String, value: String, keychain: bool) -> Result<(), String> {
+    let name = name.trim().to_string();
+    if !is_valid_name(&name) {
+        return Err(format!("\"{}\" can't be used as the name of an environment variable", name));
+    }
+    let mut configs = load_wiki_configs(&app)?;
+    let vars = configs.env_vars.entry(wiki_path).or_default();
+    let previous = vars.iter().position(|var| var.name == name).map(|i| vars.remove(i));
+    let previous_account = previous.and_then(|var| var.keychain);
+    let var = if keychain {
+        let account = previous_account.unwrap_or_else(|| format!("wiki-env-{:016x}", rand::random::<u64>()));
+        keychain_set(&account, &value)?;
+        WikiEnvVar { name, value: String::new(), keychain: Some(account) }
+    } else {
+        if let Some(account) = previous_account {
+            keychain_delete(&account);
+        }
+        WikiEnvVar { name, value, keychain: None }
+    };
+    vars.push(var);
+    save_wiki_configs(&app, &configs)
+}
+
+/// Remove an environment variable of the wiki at `wiki_path` (and its keychain entry)
+#[tauri::command]
+pub fn remove_wiki_env_var(app: tauri::AppHandle, wiki_path: String, name: String) -> Result<(), String> {
+    let mut configs = load_wiki_configs(&app)?;
+    let Some(vars) = configs.env_vars.get_mut(&wiki_path) else {
+        return Ok(());
+    };
+    let (removed, kept): (Vec<_>, Vec<_>) = vars.drain(..).partition(|var| var.name == name);
+    forget(&removed);
+    if kept.is_empty() {
+        configs.env_vars.remove(&wiki_path);
+    } else {
+        *vars = kept;
+    }
+    save_wiki_configs(&app, &configs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_valid_name() {
+        assert!(is_valid_name("OPENAI_API_KEY"));
+        assert!(is_valid_name("_flag2"));
+        assert!(!is_valid_name(""));
+        assert!(!is_valid_name("2FA"));
+        assert!(!is_valid_name("MY-KEY"));
+        assert!(!is_valid_name("KEY=VALUE"));
+        assert!(!is_valid_name("TIDDLYWIKI_PLUGIN_PATH"));
+        assert!(!is_valid_name("tiddlydesktop_safe_mode"));
+    }
+}
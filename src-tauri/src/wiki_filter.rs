@@ -0,0 +1,18 @@
+//! Filter evaluation against wikis that aren't open
+//!
+//! The evaluator lives in `tiddlydesktop_core::filter`; this module exposes it
+//! to the landing page.
+
+use tiddlydesktop_core::filter::Wiki;
+
+/// Evaluate a TiddlyWiki filter against a wiki file or folder on disk.
+/// Fails for filters using operators that need a running TiddlyWiki.
+#[tauri::command]
+pub async fn filter_wiki(wiki_path: String, filter: String) -> Result<Vec<String>, String> {
+    let validated_path = crate::drag_drop::sanitize::validate_wiki_path(&wiki_path)?;
+
+    tokio::task::spawn_blocking(move || {
+        Wiki::load(&validated_path)?.filter(&filter)
+    }).await
+        .map_err(|e| format!("Failed to spawn blocking task: {}", e))?
+}
@@ -0,0 +1,96 @@
+//! Global hotkeys that open wikis (desktop)
+//!
+//! A wiki in the wiki list can be bound to a global hotkey (`hotkey` in its
+//! entry) that opens it, or focuses its window, from anywhere in the OS. They
+//! are registered together with the hotkeys of the tray quick actions
+//! (`quick_actions::register_hotkeys`), so a hotkey belongs to one wiki or
+//! one action.
+
+#[cfg(not(target_os = "android"))]
+use tauri_plugin_global_shortcut::Shortcut;
+
+use crate::utils;
+use crate::wiki_storage::{load_recent_files_from_disk, save_recent_files_to_disk};
+
+#[cfg(not(target_os = "android"))]
+fn parse(hotkey: &str) -> Result<Shortcut, String> {
+    hotkey.parse::<Shortcut>().map_err(|e| format!("Invalid hotkey \"{}\": {}", hotkey, e))
+}
+
+/// The wiki hotkeys: (hotkey, wiki path)
+pub fn hotkeys(app: &tauri::AppHandle) -> Vec<(String, String)> {
+    load_recent_files_from_disk(app)
+        .into_iter()
+        .filter_map(|entry| Some((entry.hotkey?, entry.path)))
+        .collect()
+}
+
+/// Open the wiki bound to a pressed global hotkey. Returns false if none is.
+#[cfg(not(target_os = "android"))]
+pub fn handle_shortcut(app: &tauri::AppHandle, shortcut: &Shortcut) -> bool {
+    let path = hotkeys(app)
+        .into_iter()
+        .find(|(hotkey, _)| hotkey.parse::<Shortcut>().is_ok_and(|s| s == *shortcut))
+        .map(|(_, path)| path);
+    match path {
+        Some(path) => {
+            crate::open_wiki_from_tray(app, path);
+            true
+        }
+        None => false,
+    }
+}
+
+/// What a hotkey is already used for, other than opening the wiki at `path`
+#[cfg(not(target_os = "android"))]
+fn hotkey_in_use(app: &tauri::AppHandle, shortcut: &Shortcut, path: &str) -> Option<String> {
+    let same = |hotkey: &str| hotkey.parse::<Shortcut>().is_ok_and(|s| s == *shortcut);
+    if let Some((_, other)) = hotkeys(app)
+        .into_iter()
+        .find(|(hotkey, other)| same(hotkey) && !utils::paths_equal(path, other))
+    {
+        return Some(format!("it already opens {}", other));
+    }
+    if crate::quick_actions::hotkeys(app).iter().any(|hotkey| same(hotkey)) {
+        return Some("a quick action already uses it".to_string());
+    }
+    None
+}
+
+fn update_hotkey(app: &tauri::AppHandle, path: &str, hotkey: Option<String>) -> Result<(), String> {
+    let mut entries = load_recent_files_from_disk(app);
+    let entry = entries
+        .iter_mut()
+        .find(|entry| utils::paths_equal(&entry.path, path))
+        .ok_or("Wiki not in the wiki list")?;
+    entry.hotkey = hotkey;
+    save_recent_files_to_disk(app, &entries)?;
+    #[cfg(not(target_os = "android"))]
+    crate::quick_actions::register_hotkeys(app);
+    Ok(())
+}
+
+/// Bind a global hotkey (e.g. "CommandOrControl+Alt+1") to a wiki
+#[tauri::command]
+pub fn set_wiki_hotkey(app: tauri::AppHandle, path: String, hotkey: String) -> Result<(), String> {
+    let hotkey = hotkey.trim().to_string();
+    if hotkey.is_empty() {
+        return update_hotkey(&app, &path, None);
+    }
+    #[cfg(not(target_os = "android"))]
+    {
+        let shortcut = parse(&hotkey)?;
+        if let Some(reason) = hotkey_in_use(&app, &shortcut, &path) {
+            return Err(format!("Can't use {}: {}", hotkey, reason));
+        }
+        update_hotkey(&app, &path, Some(hotkey))
+    }
+    #[cfg(target_os = "android")]
+    Err("Global hotkeys are only available on desktop".to_string())
+}
+
+/// Remove the global hotkey of a wiki
+#[tauri::command]
+pub fn clear_wiki_hotkey(app: tauri::AppHandle, path: String) -> Result<(), String> {
+    update_hotkey(&app, &path, None)
+}
@@ -2,146 +2,69 @@
 //!
 //! This module handles persistent storage for TiddlyDesktop:
 //! - Recent wikis list (wiki_list.json)
-//! - Wiki-specific configurations (external attachments, session auth)
+//! - Wiki-specific configurations (external attachments, session auth, accelerators)
+//! - Portable mode: wiki paths stored relative to the executable
 
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
 use tauri::{Emitter, Manager};
-use crate::types::{WikiEntry, WikiConfigs, ExternalAttachmentsConfig, SessionAuthConfig, AppSettings, ShareTemplatesConfig};
+use tiddlydesktop_core::storage::DataStore;
+use crate::types::{WikiEntry, WikiConfigs, ExternalAttachmentsConfig, SessionAuthConfig, AcceleratorMap, AppSettings, ShareTemplatesConfig, SyncPriority, SyncSchedule};
 use crate::utils;
 
-/// Atomic write with backup: keeps a .bak copy of the previous file, writes to
-/// a .tmp file first, then renames over the target. Prevents data loss if the
-/// process is killed mid-write (std::fs::write truncates first → empty file).
-fn atomic_write_with_backup(path: &Path, content: &str) -> Result<(), String> {
-    let backup_path = path.with_extension("json.bak");
-    if path.exists() {
-        let _ = std::fs::copy(path, &backup_path);
-    }
-    let tmp_path = path.with_extension("json.tmp");
-    std::fs::write(&tmp_path, content)
-        .map_err(|e| format!("Failed to write temp file {}: {}", tmp_path.display(), e))?;
-    std::fs::rename(&tmp_path, path).map_err(|e| {
-        let _ = std::fs::remove_file(&tmp_path);
-        format!("Failed to rename {} -> {}: {}", tmp_path.display(), path.display(), e)
-    })
-}
-
-/// Load a JSON config from a .bak backup file. Returns default on failure.
-fn load_json_from_backup<T: serde::de::DeserializeOwned + Default>(backup_path: &Path) -> Result<T, String> {
-    if !backup_path.exists() {
-        eprintln!("[WikiStorage] No backup at {} — using defaults", backup_path.display());
-        return Ok(T::default());
-    }
-    match std::fs::read_to_string(backup_path) {
-        Ok(s) if s.trim().is_empty() => {
-            eprintln!("[WikiStorage] Backup is also empty — using defaults");
-            Ok(T::default())
-        }
-        Ok(s) => match serde_json::from_str(&s) {
-            Ok(c) => {
-                eprintln!("[WikiStorage] Recovered from backup at {}", backup_path.display());
-                Ok(c)
-            }
-            Err(e) => {
-                eprintln!("[WikiStorage] Backup also corrupt: {} — using defaults", e);
-                Ok(T::default())
-            }
-        },
-        Err(e) => {
-            eprintln!("[WikiStorage] Failed to read backup: {} — using defaults", e);
-            Ok(T::default())
-        }
-    }
+/// The config files of this app's data directory (see `tiddlydesktop_core::storage`)
+pub fn data_store(app: &tauri::AppHandle) -> Result<DataStore, String> {
+    let data_dir = crate::get_data_dir(app)?;
+    Ok(DataStore::new(data_dir).with_portable_base(crate::get_portable_dir(app)))
 }
 
 /// Get the path to the recent files JSON
 pub fn get_recent_files_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
-    let data_dir = crate::get_data_dir(app)?;
-    Ok(data_dir.join("recent_wikis.json"))
+    Ok(data_store(app)?.recent_files_path())
 }
 
 /// Get the path to the wiki configs JSON
 pub fn get_wiki_configs_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
-    let data_dir = crate::get_data_dir(app)?;
-    Ok(data_dir.join("wiki_configs.json"))
+    Ok(data_store(app)?.wiki_configs_path())
 }
 
 /// Get the path to the app settings JSON
 pub fn get_app_settings_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
-    let data_dir = crate::get_data_dir(app)?;
-    Ok(data_dir.join("app_settings.json"))
+    Ok(data_store(app)?.app_settings_path())
 }
 
 /// Load app settings from disk
 pub fn load_app_settings(app: &tauri::AppHandle) -> Result<AppSettings, String> {
-    let path = get_app_settings_path(app)?;
-    if path.exists() {
-        let content = std::fs::read_to_string(&path)
-            .map_err(|e| format!("Failed to read app settings: {}", e))?;
-        serde_json::from_str(&content)
-            .map_err(|e| format!("Failed to parse app settings: {}", e))
-    } else {
-        Ok(AppSettings::default())
-    }
+    data_store(app)?.load_app_settings()
 }
 
 /// Save app settings to disk (atomic write with backup)
 pub fn save_app_settings(app: &tauri::AppHandle, settings: &AppSettings) -> Result<(), String> {
-    let path = get_app_settings_path(app)?;
-
-    // Ensure parent directory exists
-    if let Some(parent) = path.parent() {
-        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create config directory: {}", e))?;
-    }
-
-    let content = serde_json::to_string_pretty(settings)
-        .map_err(|e| format!("Failed to serialize app settings: {}", e))?;
-    atomic_write_with_backup(&path, &content)
-        .map_err(|e| format!("Failed to write app settings: {}", e))
+    data_store(app)?.save_app_settings(settings)
 }
 
 /// Get the path to the share templates JSON
 pub fn get_share_templates_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
-    let data_dir = crate::get_data_dir(app)?;
-    Ok(data_dir.join("share_templates.json"))
+    Ok(data_store(app)?.share_templates_path())
 }
 
 /// Load share templates config from disk
 pub fn load_share_templates(app: &tauri::AppHandle) -> Result<ShareTemplatesConfig, String> {
-    let path = get_share_templates_path(app)?;
+    let store = data_store(app)?;
+    let path = store.share_templates_path();
     eprintln!("[ShareTemplates] Loading from: {} (exists={})", path.display(), path.exists());
-    if path.exists() {
-        let content = std::fs::read_to_string(&path)
-            .map_err(|e| format!("Failed to read share templates: {}", e))?;
-        if content.trim().is_empty() {
-            eprintln!("[ShareTemplates] File is empty");
-            return Ok(ShareTemplatesConfig::default());
-        }
-        let config: ShareTemplatesConfig = serde_json::from_str(&content)
-            .map_err(|e| format!("Failed to parse share templates: {}", e))?;
-        eprintln!("[ShareTemplates] Loaded {} templates, {} domain rules", config.templates.len(), config.domain_rules.len());
-        Ok(config)
-    } else {
-        Ok(ShareTemplatesConfig::default())
-    }
+    let config = store.load_share_templates()?;
+    eprintln!("[ShareTemplates] Loaded {} templates, {} domain rules", config.templates.len(), config.domain_rules.len());
+    Ok(config)
 }
 
 /// Save share templates config to disk (atomic write with backup)
 pub fn save_share_templates(app: &tauri::AppHandle, config: &ShareTemplatesConfig) -> Result<(), String> {
-    let path = get_share_templates_path(app)?;
-    eprintln!("[ShareTemplates] Saving to: {} ({} templates, {} domain rules)", path.display(), config.templates.len(), config.domain_rules.len());
+    let store = data_store(app)?;
+    eprintln!("[ShareTemplates] Saving to: {} ({} templates, {} domain rules)", store.share_templates_path().display(), config.templates.len(), config.domain_rules.len());
     for rule in &config.domain_rules {
         eprintln!("[ShareTemplates]   rule: domain='{}' template_id='{}'", rule.domain, rule.template_id);
     }
-
-    if let Some(parent) = path.parent() {
-        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create config directory: {}", e))?;
-    }
-
-    let content = serde_json::to_string_pretty(config)
-        .map_err(|e| format!("Failed to serialize share templates: {}", e))?;
-    atomic_write_with_backup(&path, &content)
-        .map_err(|e| format!("Failed to write share templates: {}", e))
+    store.save_share_templates(config)
 }
 
 /// Detect system locale and return a language code
@@ -165,90 +88,32 @@ pub fn get_effective_language(app: &tauri::AppHandle) -> String {
     effective
 }
 
-/// Load all wiki configs from disk (with backup recovery on corruption)
+/// Load all wiki configs (portable-relative keys resolved to absolute paths)
 pub fn load_wiki_configs(app: &tauri::AppHandle) -> Result<WikiConfigs, String> {
-    let path = get_wiki_configs_path(app)?;
-    if !path.exists() {
-        return Ok(WikiConfigs::default());
-    }
-    match std::fs::read_to_string(&path) {
-        Ok(content) if content.trim().is_empty() => {
-            eprintln!("[WikiStorage] WARNING: wiki_configs.json is empty — trying backup");
-            load_json_from_backup::<WikiConfigs>(&path.with_extension("json.bak"))
-        }
-        Ok(content) => match serde_json::from_str(&content) {
-            Ok(c) => Ok(c),
-            Err(e) => {
-                eprintln!("[WikiStorage] WARNING: Failed to parse wiki_configs.json: {} — trying backup", e);
-                load_json_from_backup::<WikiConfigs>(&path.with_extension("json.bak"))
-            }
-        },
-        Err(e) => {
-            eprintln!("[WikiStorage] WARNING: Failed to read wiki_configs.json: {} — trying backup", e);
-            load_json_from_backup::<WikiConfigs>(&path.with_extension("json.bak"))
-        }
-    }
+    data_store(app)?.load_wiki_configs()
 }
 
 /// Save all wiki configs to disk (atomic write with backup)
 pub fn save_wiki_configs(app: &tauri::AppHandle, configs: &WikiConfigs) -> Result<(), String> {
-    let path = get_wiki_configs_path(app)?;
-
-    // Ensure parent directory exists
-    if let Some(parent) = path.parent() {
-        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create config directory: {}", e))?;
-    }
-
-    let content = serde_json::to_string_pretty(configs)
-        .map_err(|e| format!("Failed to serialize wiki configs: {}", e))?;
-    atomic_write_with_backup(&path, &content)
-        .map_err(|e| format!("Failed to write wiki configs: {}", e))
+    data_store(app)?.save_wiki_configs(configs)
 }
 
 /// Load recent files from disk (with backup recovery on corruption)
 pub fn load_recent_files_from_disk(app: &tauri::AppHandle) -> Vec<WikiEntry> {
-    let path = match get_recent_files_path(app) {
-        Ok(p) => p,
-        Err(_) => return Vec::new(),
-    };
-
-    if !path.exists() {
-        return Vec::new();
-    }
-
-    match std::fs::read_to_string(&path) {
-        Ok(content) if content.trim().is_empty() => {
-            eprintln!("[WikiStorage] WARNING: recent_wikis.json is empty — trying backup");
-            load_json_from_backup::<Vec<WikiEntry>>(&path.with_extension("json.bak"))
-                .unwrap_or_default()
-        }
-        Ok(content) => match serde_json::from_str(&content) {
-            Ok(entries) => entries,
-            Err(e) => {
-                eprintln!("[WikiStorage] WARNING: Failed to parse recent_wikis.json: {} — trying backup", e);
-                load_json_from_backup::<Vec<WikiEntry>>(&path.with_extension("json.bak"))
-                    .unwrap_or_default()
-            }
-        },
-        Err(e) => {
-            eprintln!("[WikiStorage] WARNING: Failed to read recent_wikis.json: {} — trying backup", e);
-            load_json_from_backup::<Vec<WikiEntry>>(&path.with_extension("json.bak"))
-                .unwrap_or_default()
-        }
-    }
+    data_store(app).map(|store| store.load_recent_files()).unwrap_or_default()
 }
 
 /// Save recent files to disk (atomic write with backup)
 pub fn save_recent_files_to_disk(app: &tauri::AppHandle, entries: &[WikiEntry]) -> Result<(), String> {
-    let path = get_recent_files_path(app)?;
+    data_store(app)?.save_recent_files(entries)
+}
 
-    // Ensure parent directory exists
-    if let Some(parent) = path.parent() {
-        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+/// Portable mode: rewrite absolute wiki paths stored by earlier versions into
+/// portable-relative form. Called once at startup; no-op outside portable mode.
+pub fn migrate_portable_paths(app: &tauri::AppHandle) {
+    if let Ok(store) = data_store(app) {
+        store.migrate_portable_paths();
     }
-
-    let json = serde_json::to_string_pretty(entries).map_err(|e| e.to_string())?;
-    atomic_write_with_backup(&path, &json)
 }
 
 /// Add or update a wiki in the recent files list
@@ -270,6 +135,10 @@ pub fn add_to_recent_files(app: &tauri::AppHandle, mut entry: WikiEntry) -> Resu
         if entry.relay_room.is_none() && existing.relay_room.is_some() {
             entry.relay_room = existing.relay_room.clone();
         }
+        // Preserve the custom icon, accent color and emoji
+        entry.custom_icon = existing.custom_icon.clone();
+        entry.accent_color = existing.accent_color.clone();
+        entry.emoji = existing.emoji.clone();
     }
 
     // Remove existing entry with same path (if any)
@@ -372,11 +241,24 @@ pub fn remove_recent_file(app: tauri::AppHandle, path: String) -> Result<(), Str
         changed |= configs.external_attachments.remove(&path).is_some();
         changed |= configs.session_auth.remove(&path).is_some();
         changed |= configs.window_states.remove(&path).is_some();
+        changed |= configs.accelerators.remove(&path).is_some();
+        changed |= configs.folder_snapshots.remove(&path).is_some();
+        changed |= configs.downloads.remove(&path).is_some();
+        changed |= configs.geolocation.remove(&path).is_some();
+        changed |= configs.serial.remove(&path).is_some();
+        changed |= configs.mqtt.remove(&path).is_some();
+        changed |= configs.external_browser.remove(&path).is_some();
+        if let Some(vars) = configs.env_vars.remove(&path) {
+            crate::wiki_env::forget(&vars);
+            changed = true;
+        }
         if changed {
             let _ = save_wiki_configs(&app, &configs);
         }
     }
 
+    crate::attachment_manifest::remove_manifest(&app, &path);
+
     // Clean up sync data if the wiki had a sync_id
     if let Some(ref entry) = removed_entry {
         if let Some(ref sync_id) = entry.sync_id {
@@ -405,6 +287,68 @@ pub fn remove_recent_file(app: tauri::AppHandle, path: String) -> Result<(), Str
     Ok(())
 }
 
+/// Point an existing wiki entry at a new location, keeping its settings
+/// (e.g. a removable drive that now mounts under a different letter or path)
+#[tauri::command]
+pub fn relocate_wiki(app: tauri::AppHandle, old_path: String, new_path: String) -> Result<WikiEntry, String> {
+    let mut entries = load_recent_files_from_disk(&app);
+    let entry = entries
+        .iter_mut()
+        .find(|e| utils::paths_equal(&e.path, &old_path))
+        .ok_or_else(|| format!("Wiki not found in list: {}", old_path))?;
+
+    let new_path_buf = PathBuf::from(&new_path);
+    if entry.is_folder && !utils::is_wiki_folder(&new_path_buf) {
+        return Err("Not a valid wiki folder (missing tiddlywiki.info)".to_string());
+    }
+    if !entry.is_folder && !new_path_buf.is_file() {
+        return Err(format!("File does not exist: {}", new_path));
+    }
+
+    entry.path = new_path.clone();
+    if let Some(name) = new_path_buf.file_name().and_then(|n| n.to_str()) {
+        entry.filename = name.to_string();
+    }
+    if entry.display_path.is_some() {
+        entry.display_path = Some(new_path.clone());
+    }
+    let updated = entry.clone();
+    save_recent_files_to_disk(&app, &entries)?;
+
+    // Move per-wiki configs over to the new path
+    if let Ok(mut configs) = load_wiki_configs(&app) {
+        fn rekey<V>(map: &mut std::collections::HashMap<String, V>, old: &str, new: &str) -> bool {
+            match map.remove(old) {
+                Some(v) => {
+                    map.insert(new.to_string(), v);
+                    true
+                }
+                None => false,
+            }
+        }
+        let mut changed = false;
+        changed |= rekey(&mut configs.external_attachments, &old_path, &new_path);
+        changed |= rekey(&mut configs.session_auth, &old_path, &new_path);
+        changed |= rekey(&mut configs.window_states, &old_path, &new_path);
+        changed |= rekey(&mut configs.accelerators, &old_path, &new_path);
+        changed |= rekey(&mut configs.folder_snapshots, &old_path, &new_path);
+        changed |= rekey(&mut configs.downloads, &old_path, &new_path);
+        changed |= rekey(&mut configs.geolocation, &old_path, &new_path);
+        changed |= rekey(&mut configs.serial, &old_path, &new_path);
+        changed |= rekey(&mut configs.mqtt, &old_path, &new_path);
+        changed |= rekey(&mut configs.external_browser, &old_path, &new_path);
+        changed |= rekey(&mut configs.env_vars, &old_path, &new_path);
+        if changed {
+            let _ = save_wiki_configs(&app, &configs);
+        }
+    }
+
+    crate::attachment_manifest::move_manifest(&app, &old_path, &new_path);
+
+    eprintln!("[WikiStorage] Relocated wiki: {} -> {}", old_path, new_path);
+    Ok(updated)
+}
+
 /// Reconcile the Rust JSON config with the authoritative WikiList from the frontend.
 /// Removes any entries from the Rust JSON that are NOT in the provided list of paths.
 /// This prevents stale entries from being broadcast to sync peers.
@@ -452,6 +396,17 @@ pub fn reconcile_recent_files(app: tauri::AppHandle, paths: Vec<String>) -> Resu
             changed |= configs.external_attachments.remove(&entry.path).is_some();
             changed |= configs.session_auth.remove(&entry.path).is_some();
             changed |= configs.window_states.remove(&entry.path).is_some();
+            changed |= configs.accelerators.remove(&entry.path).is_some();
+            changed |= configs.folder_snapshots.remove(&entry.path).is_some();
+            changed |= configs.downloads.remove(&entry.path).is_some();
+            changed |= configs.geolocation.remove(&entry.path).is_some();
+            changed |= configs.serial.remove(&entry.path).is_some();
+            changed |= configs.mqtt.remove(&entry.path).is_some();
+            changed |= configs.external_browser.remove(&entry.path).is_some();
+            if let Some(vars) = configs.env_vars.remove(&entry.path) {
+                crate::wiki_env::forget(&vars);
+                changed = true;
+            }
         }
         if changed {
             let _ = save_wiki_configs(&app, &configs);
@@ -461,6 +416,7 @@ pub fn reconcile_recent_files(app: tauri::AppHandle, paths: Vec<String>) -> Resu
     // Clean up sync data for removed entries
     let data_dir = crate::get_data_dir(&app).unwrap_or_default();
     for entry in &removed {
+        crate::attachment_manifest::remove_manifest(&app, &entry.path);
         if let Some(ref sync_id) = entry.sync_id {
             let state_path = data_dir.join("sync_state").join(format!("{}.json", sync_id));
             let _ = std::fs::remove_file(state_path);
@@ -497,7 +453,18 @@ pub fn save_full_wiki_list(app: tauri::AppHandle, entries: Vec<WikiEntry>) -> Re
             return Ok(());
         }
     }
-    save_recent_files_to_disk(&app, &entries)
+    #[cfg(not(target_os = "android"))]
+    let hotkeys_before = crate::wiki_hotkeys::hotkeys(&app);
+    save_recent_files_to_disk(&app, &entries)?;
+    #[cfg(not(target_os = "android"))]
+    {
+        crate::refresh_tray_menu(&app);
+        // Hotkeys of removed wikis go away
+        if crate::wiki_hotkeys::hotkeys(&app) != hotkeys_before {
+            crate::quick_actions::register_hotkeys(&app);
+        }
+    }
+    Ok(())
 }
 
 /// Set backups enabled/disabled for a wiki
@@ -655,6 +622,74 @@ pub fn update_wiki_favicon(app: tauri::AppHandle, path: String, favicon: Option<
         "favicon": favicon
     }));
 
+    // The tray menu shows favicons too
+    #[cfg(not(target_os = "android"))]
+    crate::refresh_tray_menu(&app);
+
+    Ok(())
+}
+
+/// Get the custom icon assigned to a wiki
+pub fn get_wiki_custom_icon(app: &tauri::AppHandle, path: &str) -> Option<String> {
+    load_recent_files_from_disk(app)
+        .into_iter()
+        .find(|e| utils::paths_equal(&e.path, path))
+        .and_then(|e| e.custom_icon)
+}
+
+/// Maximum size for custom icon data URIs (256KB, the landing page stores 64x64 PNGs)
+const MAX_CUSTOM_ICON_SIZE: usize = 256 * 1024;
+
+fn is_hex_color(color: &str) -> bool {
+    color.len() == 7 && color.starts_with('#') && color[1..].chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Set the custom icon, accent color and emoji of a wiki (None clears them).
+/// Open windows of the wiki switch to the new icon right away.
+#[tauri::command]
+pub fn set_wiki_appearance(
+    app: tauri::AppHandle,
+    path: String,
+    custom_icon: Option<String>,
+    accent_color: Option<String>,
+    emoji: Option<String>,
+) -> Result<(), String> {
+    if let Some(ref icon) = custom_icon {
+        if icon.len() > MAX_CUSTOM_ICON_SIZE || !icon.starts_with("data:image/png;base64,") {
+            return Err("Custom icon must be a PNG data URI of at most 256KB".to_string());
+        }
+    }
+    // Used in style attributes of the landing page
+    if let Some(ref color) = accent_color {
+        if !is_hex_color(color) {
+            return Err(format!("Invalid accent color: {}", color));
+        }
+    }
+    if emoji.as_ref().is_some_and(|e| e.chars().count() > 16) {
+        return Err("Emoji too long".to_string());
+    }
+
+    let mut entries = load_recent_files_from_disk(&app);
+    let entry = entries
+        .iter_mut()
+        .find(|e| utils::paths_equal(&e.path, &path))
+        .ok_or("Wiki not in the wiki list")?;
+    entry.custom_icon = custom_icon;
+    entry.accent_color = accent_color;
+    entry.emoji = emoji;
+    let icon = entry.custom_icon.clone().or_else(|| entry.favicon.clone());
+    let wiki_path = entry.path.clone();
+    save_recent_files_to_disk(&app, &entries)?;
+
+    #[cfg(not(target_os = "android"))]
+    {
+        if let Some(server) = crate::GLOBAL_IPC_SERVER.get() {
+            let _ = server.send_wiki_icon(&wiki_path, icon);
+        }
+        crate::refresh_tray_menu(&app);
+    }
+    #[cfg(target_os = "android")]
+    let _ = (wiki_path, icon);
     Ok(())
 }
 
@@ -930,6 +965,102 @@ pub fn set_wiki_sync_mode(app: tauri::AppHandle, path: String, mode: Option<Stri
     Ok(())
 }
 
+/// Get the sync schedule of a wiki (None = real time)
+#[tauri::command]
+pub fn get_wiki_sync_schedule(app: tauri::AppHandle, path: String) -> Option<SyncSchedule> {
+    load_recent_files_from_disk(&app)
+        .into_iter()
+        .find(|entry| utils::paths_equal(&entry.path, &path) && entry.sync_enabled)
+        .and_then(|entry| entry.sync_schedule)
+}
+
+/// Set the sync schedule of a wiki: an interval, manual, or None (or a zero
+/// interval) for real time
+#[tauri::command]
+pub fn set_wiki_sync_schedule(app: tauri::AppHandle, path: String, schedule: Option<SyncSchedule>) -> Result<(), String> {
+    let schedule = schedule.filter(|s| *s != SyncSchedule::Interval { minutes: 0 });
+    let mut entries = load_recent_files_from_disk(&app);
+    let entry = entries
+        .iter_mut()
+        .find(|entry| utils::paths_equal(&entry.path, &path))
+        .ok_or("Wiki not in the wiki list")?;
+    entry.sync_schedule = schedule.clone();
+    save_recent_files_to_disk(&app, &entries)?;
+
+    // The open wiki window picks the change up without reactivating sync
+    #[cfg(not(target_os = "android"))]
+    if let Some(server) = crate::GLOBAL_IPC_SERVER.get() {
+        let payload = serde_json::json!({
+            "type": "sync-schedule-changed",
+            "wiki_path": path,
+            "sync_schedule": schedule,
+        }).to_string();
+        server.send_lan_sync_to_all("*", &payload);
+    }
+    Ok(())
+}
+
+/// Get the sync priority filters of a wiki (None = all tiddlers alike)
+#[tauri::command]
+pub fn get_wiki_sync_priority(app: tauri::AppHandle, path: String) -> Option<SyncPriority> {
+    load_recent_files_from_disk(&app)
+        .into_iter()
+        .find(|entry| utils::paths_equal(&entry.path, &path) && entry.sync_enabled)
+        .and_then(|entry| entry.sync_priority)
+}
+
+/// Set the filters of tiddlers a wiki syncs right away (high) and in batches
+/// every `bulk_minutes` (bulk). Two empty filters clear them.
+#[tauri::command]
+pub fn set_wiki_sync_priority(app: tauri::AppHandle, path: String, priority: Option<SyncPriority>) -> Result<(), String> {
+    let priority = priority
+        .map(|p| SyncPriority { high: p.high.trim().to_string(), bulk: p.bulk.trim().to_string(), ..p })
+        .filter(|p| !p.high.is_empty() || !p.bulk.is_empty());
+    let mut entries = load_recent_files_from_disk(&app);
+    let entry = entries
+        .iter_mut()
+        .find(|entry| utils::paths_equal(&entry.path, &path))
+        .ok_or("Wiki not in the wiki list")?;
+    entry.sync_priority = priority.clone();
+    save_recent_files_to_disk(&app, &entries)?;
+
+    // The open wiki window picks the change up without reactivating sync
+    #[cfg(not(target_os = "android"))]
+    if let Some(server) = crate::GLOBAL_IPC_SERVER.get() {
+        let payload = serde_json::json!({
+            "type": "sync-priority-changed",
+            "wiki_path": path,
+            "sync_priority": priority,
+        }).to_string();
+        server.send_lan_sync_to_all("*", &payload);
+    }
+    Ok(())
+}
+
+/// Hold deletions from peers until they are confirmed (or apply them right away)
+#[tauri::command]
+pub fn set_wiki_confirm_remote_deletions(app: tauri::AppHandle, path: String, confirm: bool) -> Result<(), String> {
+    let mut entries = load_recent_files_from_disk(&app);
+    let entry = entries
+        .iter_mut()
+        .find(|entry| utils::paths_equal(&entry.path, &path))
+        .ok_or("Wiki not in the wiki list")?;
+    entry.confirm_remote_deletions = confirm;
+    save_recent_files_to_disk(&app, &entries)?;
+
+    // The open wiki window picks the change up without reactivating sync
+    #[cfg(not(target_os = "android"))]
+    if let Some(server) = crate::GLOBAL_IPC_SERVER.get() {
+        let payload = serde_json::json!({
+            "type": "deletion-policy-changed",
+            "wiki_path": path,
+            "confirm_remote_deletions": confirm,
+        }).to_string();
+        server.send_lan_sync_to_all("*", &payload);
+    }
+    Ok(())
+}
+
 /// Set the relay room for a wiki (None to unassign)
 #[tauri::command]
 pub fn set_wiki_relay_room(app: tauri::AppHandle, path: String, room_code: Option<String>) -> Result<(), String> {
@@ -1146,6 +1277,114 @@ pub fn set_session_auth_config(app: tauri::AppHandle, wiki_path: String, config:
     save_wiki_configs(&app, &configs)
 }
 
+/// Normalize an accelerator string like "ctrl+shift+g" to "Ctrl+Shift+G".
+/// Modifiers are put in a fixed order so equivalent bindings compare equal.
+fn normalize_accelerator(accelerator: &str) -> Result<String, String> {
+    let mut cmd_or_ctrl = false;
+    let mut ctrl = false;
+    let mut meta = false;
+    let mut alt = false;
+    let mut shift = false;
+    let mut key: Option<String> = None;
+
+    for part in accelerator.split('+').map(|p| p.trim()) {
+        match part.to_lowercase().as_str() {
+            "" => return Err(format!("Invalid accelerator: '{}'", accelerator)),
+            "cmdorctrl" | "commandorcontrol" => cmd_or_ctrl = true,
+            "ctrl" | "control" => ctrl = true,
+            "cmd" | "command" | "meta" | "super" => meta = true,
+            "alt" | "option" => alt = true,
+            "shift" => shift = true,
+            _ => {
+                if key.is_some() {
+                    return Err(format!("Accelerator '{}' has more than one key", accelerator));
+                }
+                // Single characters are stored upper-case, named keys (F3, Escape) as given
+                key = Some(if part.chars().count() == 1 { part.to_uppercase() } else { part.to_string() });
+            }
+        }
+    }
+
+    let key = key.ok_or_else(|| format!("Accelerator '{}' has no key", accelerator))?;
+    let mut parts: Vec<&str> = Vec::new();
+    if cmd_or_ctrl { parts.push("CmdOrCtrl"); }
+    if ctrl { parts.push("Ctrl"); }
+    if meta { parts.push("Cmd"); }
+    if alt { parts.push("Alt"); }
+    if shift { parts.push("Shift"); }
+    parts.push(&key);
+    Ok(parts.join("+"))
+}
+
+/// Notify this process's windows that a wiki's accelerators changed.
+/// Windows in other wiki processes pick up the change when they regain focus.
+fn emit_accelerators_changed(app: &tauri::AppHandle, wiki_path: &str, map: &AcceleratorMap) {
+    let _ = app.emit("accelerators-changed", serde_json::json!({
+        "wikiPath": wiki_path,
+        "map": map,
+    }));
+}
+
+/// Get the effective accelerator map for a wiki (defaults plus overrides)
+#[tauri::command]
+pub fn get_accelerator_map(app: tauri::AppHandle, wiki_path: String) -> Result<AcceleratorMap, String> {
+    let configs = load_wiki_configs(&app)?;
+    Ok(configs.accelerators.get(&wiki_path).cloned().unwrap_or_default().with_defaults())
+}
+
+/// Rebind a single action for a wiki. An empty list disables the action.
+/// Returns the new effective map.
+#[tauri::command]
+pub fn set_accelerator(app: tauri::AppHandle, wiki_path: String, action: String, accelerators: Vec<String>) -> Result<AcceleratorMap, String> {
+    if !AcceleratorMap::ACTIONS.contains(&action.as_str()) {
+        return Err(format!("Unknown accelerator action: '{}'", action));
+    }
+    let normalized = accelerators.iter()
+        .map(|a| normalize_accelerator(a))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut configs = load_wiki_configs(&app)?;
+    let overrides = configs.accelerators.entry(wiki_path.clone()).or_default();
+    if AcceleratorMap::defaults().bindings.get(&action) == Some(&normalized) {
+        overrides.bindings.remove(&action);
+    } else {
+        overrides.bindings.insert(action, normalized);
+    }
+    let effective = overrides.with_defaults();
+    if overrides.bindings.is_empty() {
+        configs.accelerators.remove(&wiki_path);
+    }
+    save_wiki_configs(&app, &configs)?;
+
+    emit_accelerators_changed(&app, &wiki_path, &effective);
+    Ok(effective)
+}
+
+/// Reset one action (or all actions if None) of a wiki to the default shortcuts.
+/// Returns the new effective map.
+#[tauri::command]
+pub fn reset_accelerators(app: tauri::AppHandle, wiki_path: String, action: Option<String>) -> Result<AcceleratorMap, String> {
+    let mut configs = load_wiki_configs(&app)?;
+    match action {
+        Some(action) => {
+            if let Some(overrides) = configs.accelerators.get_mut(&wiki_path) {
+                overrides.bindings.remove(&action);
+                if overrides.bindings.is_empty() {
+                    configs.accelerators.remove(&wiki_path);
+                }
+            }
+        }
+        None => {
+            configs.accelerators.remove(&wiki_path);
+        }
+    }
+    let effective = configs.accelerators.get(&wiki_path).cloned().unwrap_or_default().with_defaults();
+    save_wiki_configs(&app, &configs)?;
+
+    emit_accelerators_changed(&app, &wiki_path, &effective);
+    Ok(effective)
+}
+
 /// Get current UI language (user preference or auto-detected)
 #[tauri::command]
 pub fn get_language(app: tauri::AppHandle) -> String {